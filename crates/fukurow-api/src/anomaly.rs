@@ -0,0 +1,88 @@
+//! Event-rate anomaly detection on ingestion
+//!
+//! テナント・イベント種別ごとにウィンドウ内の取り込み件数を数え、ウィンドウが閉じるたびに
+//! その件数をアンサンブル異常検知器に渡す。ウィンドウは次のイベントが届いた時点で閉じ、
+//! イベントの無かったウィンドウは数えない
+
+use chrono::{DateTime, Utc};
+use fukurow_core::model::CyberEvent;
+use fukurow_domain_cyber::anomaly_detection::SecurityAnomalyDetector;
+use fukurow_store::TenantId;
+use fukurow_streaming::{AnomalyWindow, StreamingEvent};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default window event rates are counted over
+pub const DEFAULT_ANOMALY_WINDOW_SECS: u64 = 60;
+
+/// Ingested events of one type in the current window, and the detector fed with closed windows
+#[derive(Debug)]
+struct EventRate {
+    detector: SecurityAnomalyDetector,
+    start: DateTime<Utc>,
+    count: u64,
+}
+
+/// Per-tenant, per-event-type ingestion rates checked for anomalies
+#[derive(Debug, Clone)]
+pub struct EventRateMonitor {
+    window: chrono::Duration,
+    rates: Arc<Mutex<HashMap<(TenantId, &'static str), EventRate>>>,
+}
+
+impl EventRateMonitor {
+    pub fn new(window: Duration) -> Self {
+        let window = chrono::Duration::from_std(window.max(Duration::from_secs(1))).unwrap_or(chrono::Duration::MAX);
+        Self { window, rates: Arc::default() }
+    }
+
+    /// Count one event ingested at `at`; returns an `AnomalyDetected` event when this closes an anomalous window
+    ///
+    /// 返すイベントの `metric` はイベント種別、`window` は閉じたウィンドウの範囲
+    pub fn record(&self, tenant: &TenantId, event: &CyberEvent, at: DateTime<Utc>) -> Option<StreamingEvent> {
+        let event_type = event_type(event);
+        let mut rates = self.rates.lock().unwrap_or_else(|e| e.into_inner());
+        let rate = rates.entry((tenant.clone(), event_type)).or_insert_with(|| EventRate {
+            detector: SecurityAnomalyDetector::new(),
+            start: at,
+            count: 0,
+        });
+
+        let end = rate.start + self.window;
+        let mut anomaly = None;
+        if at >= end {
+            let result = rate.detector.analyze_event_ensemble(event_type, rate.count);
+            if let Some(result) = result.filter(|result| result.is_anomaly) {
+                let mut detected = result.to_streaming_event();
+                if let StreamingEvent::AnomalyDetected { window, .. } = &mut detected {
+                    *window = Some(AnomalyWindow { start: rate.start, end });
+                }
+                anomaly = Some(detected);
+            }
+            rate.start = at;
+            rate.count = 0;
+        }
+        rate.count += 1;
+        anomaly
+    }
+}
+
+impl Default for EventRateMonitor {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_ANOMALY_WINDOW_SECS))
+    }
+}
+
+fn event_type(event: &CyberEvent) -> &'static str {
+    match event {
+        CyberEvent::NetworkConnection { .. } => "NetworkConnection",
+        CyberEvent::ProcessExecution { .. } => "ProcessExecution",
+        CyberEvent::FileAccess { .. } => "FileAccess",
+        CyberEvent::UserLogin { .. } => "UserLogin",
+        CyberEvent::DnsQuery { .. } => "DnsQuery",
+        CyberEvent::HttpRequest { .. } => "HttpRequest",
+        CyberEvent::RegistryModification { .. } => "RegistryModification",
+        CyberEvent::EmailReceived { .. } => "EmailReceived",
+    }
+}
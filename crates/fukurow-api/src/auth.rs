@@ -0,0 +1,388 @@
+//! Authentication and role-based authorization
+//!
+//! API キー (`X-API-Key`) または JWT bearer トークンで呼び出し元を認証し、
+//! ルートごとに必要なロールを検査する。認証済みの [`Principal`] はリクエストの
+//! 拡張に格納され、ハンドラーは [`AuthenticatedPrincipal`] で取り出して
+//! 監査エントリの actor と、操作対象のテナントの決定に使う
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::models::ApiResponse;
+use fukurow_store::TenantId;
+use fukurow_observability::tracing::attributes;
+
+/// Header carrying an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Header selecting the tenant
+///
+/// 認証が有効な場合、テナントは資格情報で決まり、このヘッダは一致の確認にのみ使う
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+/// Permission level, ordered from least to most privileged
+///
+/// 上位のロールは下位のロールの権限をすべて含む
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Queries, statistics and streams
+    #[default]
+    ReadOnly,
+    /// Event submission and reasoning
+    Ingest,
+    /// Audit log, reset and rule management
+    Admin,
+}
+
+impl Role {
+    pub fn permits(self, required: Role) -> bool {
+        self >= required
+    }
+}
+
+/// Authenticated caller
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    pub id: String,
+    pub role: Role,
+    /// Tenant whose graphs the caller may access
+    #[serde(default)]
+    pub tenant: TenantId,
+}
+
+impl Principal {
+    /// Principal of the default tenant
+    pub fn new(id: impl Into<String>, role: Role) -> Self {
+        Self { id: id.into(), role, tenant: TenantId::default() }
+    }
+
+    pub fn with_tenant(mut self, tenant: TenantId) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    /// Caller used when authentication is disabled
+    pub fn anonymous() -> Self {
+        Self::new("anonymous", Role::Admin)
+    }
+}
+
+/// HS256 JWT verification settings
+#[derive(Clone)]
+pub struct JwtConfig {
+    secret: String,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+}
+
+impl JwtConfig {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self { secret: secret.into(), issuer: None, audience: None }
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    fn validation(&self) -> Validation {
+        let mut validation = Validation::new(Algorithm::HS256);
+        // set_issuer / set_audience だけでは欠落したクレームを拒否しない
+        let mut required = vec!["exp"];
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            required.push("iss");
+        }
+        match &self.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                required.push("aud");
+            }
+            None => validation.validate_aud = false,
+        }
+        validation.set_required_spec_claims(&required);
+        validation
+    }
+}
+
+// シークレットをログに出さない
+impl std::fmt::Debug for JwtConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtConfig")
+            .field("secret", &"<redacted>")
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .finish()
+    }
+}
+
+/// JWT claims understood by the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    /// Missing roles default to read-only
+    #[serde(default)]
+    pub role: Role,
+    /// Missing tenants default to the default tenant
+    #[serde(default)]
+    pub tenant: Option<TenantId>,
+    pub exp: u64,
+}
+
+/// Authentication settings
+///
+/// API キーも JWT も設定されていなければ認証は無効で、すべての呼び出しは
+/// 管理者権限の匿名ユーザーとして扱われる
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    /// API key -> principal
+    pub api_keys: HashMap<String, Principal>,
+    pub jwt: Option<JwtConfig>,
+}
+
+impl AuthConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_api_key(mut self, key: impl Into<String>, principal: Principal) -> Self {
+        self.api_keys.insert(key.into(), principal);
+        self
+    }
+
+    pub fn with_jwt(mut self, jwt: JwtConfig) -> Self {
+        self.jwt = Some(jwt);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwt.is_some()
+    }
+
+    /// Identify the caller and its tenant from request headers
+    ///
+    /// 認証が無効なら `X-Tenant-ID` でテナントを選べる。有効な場合に資格情報と
+    /// 異なるテナントを指定すると拒否する
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        let requested = headers.get(TENANT_HEADER)
+            .map(|value| value.to_str().ok().and_then(|value| value.parse::<TenantId>().ok()).ok_or(AuthError::InvalidTenant))
+            .transpose()?;
+
+        if !self.is_enabled() {
+            return Ok(Principal::anonymous().with_tenant(requested.unwrap_or_default()));
+        }
+
+        let principal = self.authenticate_credentials(headers)?;
+        match requested {
+            Some(tenant) if tenant != principal.tenant => Err(AuthError::TenantMismatch { requested: tenant, actual: principal.tenant }),
+            _ => Ok(principal),
+        }
+    }
+
+    fn authenticate_credentials(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        if let Some(key) = headers.get(API_KEY_HEADER) {
+            let key = key.to_str().map_err(|_| AuthError::InvalidApiKey)?;
+            return self.api_keys.get(key).cloned().ok_or(AuthError::InvalidApiKey);
+        }
+
+        let token = headers.get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AuthError::MissingCredentials)?;
+        let jwt = self.jwt.as_ref()
+            .ok_or_else(|| AuthError::InvalidToken("bearer tokens are not accepted".to_string()))?;
+        let data = jsonwebtoken::decode::<Claims>(token.trim(), &DecodingKey::from_secret(jwt.secret.as_bytes()), &jwt.validation())
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        Ok(Principal::new(data.claims.sub, data.claims.role).with_tenant(data.claims.tenant.unwrap_or_default()))
+    }
+}
+
+/// Authentication / authorization failures
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing credentials")]
+    MissingCredentials,
+    #[error("Invalid API key")]
+    InvalidApiKey,
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+    #[error("Role {actual:?} may not access a route requiring {required:?}")]
+    Forbidden { required: Role, actual: Role },
+    #[error("Invalid tenant id")]
+    InvalidTenant,
+    #[error("Credentials for tenant {actual} may not access tenant {requested}")]
+    TenantMismatch { requested: TenantId, actual: TenantId },
+    #[error("Invalid webhook secret")]
+    InvalidWebhookSecret,
+}
+
+impl AuthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::Forbidden { .. } | AuthError::TenantMismatch { .. } => StatusCode::FORBIDDEN,
+            AuthError::InvalidTenant => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = Json(ApiResponse::<String>::error(self.to_string()));
+        if status == StatusCode::UNAUTHORIZED {
+            (status, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response()
+        } else {
+            (status, body).into_response()
+        }
+    }
+}
+
+/// Middleware state: the auth settings and the role a route group requires
+#[derive(Debug, Clone)]
+pub struct RouteGuard {
+    pub config: Arc<AuthConfig>,
+    pub required: Role,
+}
+
+impl RouteGuard {
+    pub fn new(config: Arc<AuthConfig>, required: Role) -> Self {
+        Self { config, required }
+    }
+}
+
+/// Authenticate the request and check the route's role before running the handler
+pub async fn authorize(State(guard): State<RouteGuard>, mut request: Request, next: Next) -> Result<Response, AuthError> {
+    let principal = guard.config.authenticate(request.headers())?;
+    if !principal.role.permits(guard.required) {
+        return Err(AuthError::Forbidden { required: guard.required, actual: principal.role });
+    }
+    let span = tracing::Span::current();
+    span.record(attributes::USER_ID, principal.id.as_str());
+    span.record(attributes::TENANT_ID, principal.tenant.as_str());
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+/// Extractor for the principal set by [`authorize`]
+#[derive(Debug, Clone)]
+pub struct AuthenticatedPrincipal(pub Principal);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthenticatedPrincipal {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Principal>().cloned()
+            .map(AuthenticatedPrincipal)
+            .ok_or(AuthError::MissingCredentials)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn token(secret: &str, claims: &Claims) -> String {
+        encode(&Header::default(), claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token)).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_role_ordering() {
+        assert!(Role::Admin.permits(Role::Ingest));
+        assert!(Role::Ingest.permits(Role::ReadOnly));
+        assert!(!Role::ReadOnly.permits(Role::Ingest));
+    }
+
+    #[test]
+    fn test_disabled_auth_is_anonymous_admin() {
+        assert_eq!(AuthConfig::default().authenticate(&HeaderMap::new()), Ok(Principal::anonymous()));
+    }
+
+    #[test]
+    fn test_api_key_authentication() {
+        let config = AuthConfig::new().with_api_key("k1", Principal::new("sensor-1", Role::Ingest));
+        let mut headers = HeaderMap::new();
+        assert_eq!(config.authenticate(&headers), Err(AuthError::MissingCredentials));
+
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("k1"));
+        assert_eq!(config.authenticate(&headers), Ok(Principal::new("sensor-1", Role::Ingest)));
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("wrong"));
+        assert_eq!(config.authenticate(&headers), Err(AuthError::InvalidApiKey));
+    }
+
+    #[test]
+    fn test_jwt_authentication() {
+        let config = AuthConfig::new().with_jwt(JwtConfig::new("secret").with_issuer("fukurow"));
+        let exp = chrono::Utc::now().timestamp() as u64 + 3600;
+        let claims = Claims { sub: "alice".to_string(), role: Role::Admin, tenant: None, exp };
+
+        let valid = encode(&Header::default(), &serde_json::json!({ "sub": "alice", "role": "admin", "exp": exp, "iss": "fukurow" }), &EncodingKey::from_secret(b"secret")).unwrap();
+        assert_eq!(config.authenticate(&bearer(&valid)), Ok(Principal::new("alice", Role::Admin)));
+
+        // 発行者が無い・署名鍵が違うトークンは拒否する
+        assert!(matches!(config.authenticate(&bearer(&token("secret", &claims))), Err(AuthError::InvalidToken(_))));
+        assert!(matches!(config.authenticate(&bearer(&token("other", &claims))), Err(AuthError::InvalidToken(_))));
+    }
+
+    #[test]
+    fn test_jwt_requires_configured_audience() {
+        let config = AuthConfig::new().with_jwt(JwtConfig::new("secret").with_audience("fukurow-api"));
+        let exp = chrono::Utc::now().timestamp() as u64 + 3600;
+
+        let valid = encode(&Header::default(), &serde_json::json!({ "sub": "alice", "exp": exp, "aud": "fukurow-api" }), &EncodingKey::from_secret(b"secret")).unwrap();
+        assert_eq!(config.authenticate(&bearer(&valid)).unwrap().id, "alice");
+
+        let missing = encode(&Header::default(), &serde_json::json!({ "sub": "alice", "exp": exp }), &EncodingKey::from_secret(b"secret")).unwrap();
+        assert!(matches!(config.authenticate(&bearer(&missing)), Err(AuthError::InvalidToken(_))));
+    }
+
+    #[test]
+    fn test_tenant_comes_from_credentials() {
+        let acme = TenantId::new("acme").unwrap();
+        let config = AuthConfig::new()
+            .with_api_key("k1", Principal::new("sensor-1", Role::Ingest).with_tenant(acme.clone()))
+            .with_jwt(JwtConfig::new("secret"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("k1"));
+        assert_eq!(config.authenticate(&headers).unwrap().tenant, acme);
+        headers.insert(TENANT_HEADER, HeaderValue::from_static("acme"));
+        assert_eq!(config.authenticate(&headers).unwrap().tenant, acme);
+        headers.insert(TENANT_HEADER, HeaderValue::from_static("globex"));
+        assert!(matches!(config.authenticate(&headers), Err(AuthError::TenantMismatch { .. })));
+
+        let exp = chrono::Utc::now().timestamp() as u64 + 3600;
+        let claims = Claims { sub: "bob".to_string(), role: Role::ReadOnly, tenant: Some(TenantId::new("globex").unwrap()), exp };
+        assert_eq!(config.authenticate(&bearer(&token("secret", &claims))).unwrap().tenant.as_str(), "globex");
+
+        // 認証無効時はヘッダでテナントを選ぶ
+        let mut headers = HeaderMap::new();
+        headers.insert(TENANT_HEADER, HeaderValue::from_static("globex"));
+        assert_eq!(AuthConfig::default().authenticate(&headers).unwrap().tenant.as_str(), "globex");
+        headers.insert(TENANT_HEADER, HeaderValue::from_static("../etc"));
+        assert_eq!(AuthConfig::default().authenticate(&headers), Err(AuthError::InvalidTenant));
+    }
+}
@@ -0,0 +1,265 @@
+//! Bulk event ingestion (`POST /events/batch`)
+//!
+//! リクエストボディ (NDJSON または JSON 配列) をチャンク単位で読み進め、
+//! 全体をバッファせずにイベント単位へ分割・検証する
+
+use crate::models::SubmitEventRequest;
+use fukurow_core::model::CyberEvent;
+use fukurow_core::validation::{describe_issues, EventValidator, ValidationOutcome};
+
+/// Maximum events accepted in one request
+pub const MAX_BATCH_EVENTS: usize = 100_000;
+
+/// Maximum encoded size of a single event
+pub const MAX_ITEM_BYTES: usize = 1024 * 1024;
+
+/// Body framing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchFormat {
+    /// One JSON document per line
+    Ndjson,
+    /// A single JSON array of events
+    JsonArray,
+}
+
+impl BatchFormat {
+    /// Format from a `Content-Type` header or `format` parameter
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        match mime.as_str() {
+            "application/x-ndjson" | "application/ndjson" | "application/jsonl" | "ndjson" | "jsonl" => Some(BatchFormat::Ndjson),
+            "application/json" | "json" => Some(BatchFormat::JsonArray),
+            _ => None,
+        }
+    }
+}
+
+/// Body framing errors
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum BatchDecodeError {
+    #[error("Event exceeds {limit} bytes")]
+    ItemTooLarge { limit: usize },
+
+    #[error("Batch exceeds {limit} events")]
+    TooManyItems { limit: usize },
+
+    #[error("Malformed body: {0}")]
+    Malformed(String),
+}
+
+/// Incremental splitter for NDJSON / JSON array bodies
+#[derive(Debug)]
+pub struct BatchDecoder {
+    format: Option<BatchFormat>,
+    item: Vec<u8>,
+    emitted: usize,
+    // JSON 配列用の字句状態
+    array_opened: bool,
+    array_closed: bool,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl BatchDecoder {
+    /// `format: None` detects the framing from the first non-whitespace byte
+    pub fn new(format: Option<BatchFormat>) -> Self {
+        Self {
+            format,
+            item: Vec::new(),
+            emitted: 0,
+            array_opened: false,
+            array_closed: false,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    /// Feed a body chunk; returns the raw events completed by it
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, BatchDecodeError> {
+        let mut items = Vec::new();
+        for &byte in chunk {
+            let format = match self.format {
+                Some(format) => format,
+                None if byte.is_ascii_whitespace() => continue,
+                None => {
+                    let detected = if byte == b'[' { BatchFormat::JsonArray } else { BatchFormat::Ndjson };
+                    self.format = Some(detected);
+                    detected
+                }
+            };
+            match format {
+                BatchFormat::Ndjson => self.push_ndjson(byte, &mut items)?,
+                BatchFormat::JsonArray => self.push_array(byte, &mut items)?,
+            }
+        }
+        Ok(items)
+    }
+
+    /// Signal end of body; returns a trailing event if any
+    pub fn finish(mut self) -> Result<Vec<Vec<u8>>, BatchDecodeError> {
+        match self.format {
+            Some(BatchFormat::Ndjson) => {
+                let mut items = Vec::new();
+                self.emit(&mut items)?;
+                Ok(items)
+            }
+            Some(BatchFormat::JsonArray) if !self.array_closed => {
+                Err(BatchDecodeError::Malformed("unterminated JSON array".to_string()))
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn emit(&mut self, items: &mut Vec<Vec<u8>>) -> Result<(), BatchDecodeError> {
+        let item = std::mem::take(&mut self.item);
+        if item.iter().all(|b| b.is_ascii_whitespace()) {
+            return Ok(());
+        }
+        if self.emitted >= MAX_BATCH_EVENTS {
+            return Err(BatchDecodeError::TooManyItems { limit: MAX_BATCH_EVENTS });
+        }
+        self.emitted += 1;
+        items.push(item);
+        Ok(())
+    }
+
+    fn append(&mut self, byte: u8) -> Result<(), BatchDecodeError> {
+        if self.item.len() >= MAX_ITEM_BYTES {
+            return Err(BatchDecodeError::ItemTooLarge { limit: MAX_ITEM_BYTES });
+        }
+        self.item.push(byte);
+        Ok(())
+    }
+
+    fn push_ndjson(&mut self, byte: u8, items: &mut Vec<Vec<u8>>) -> Result<(), BatchDecodeError> {
+        if byte == b'\n' {
+            self.emit(items)
+        } else {
+            self.append(byte)
+        }
+    }
+
+    fn push_array(&mut self, byte: u8, items: &mut Vec<Vec<u8>>) -> Result<(), BatchDecodeError> {
+        if self.array_closed {
+            return if byte.is_ascii_whitespace() {
+                Ok(())
+            } else {
+                Err(BatchDecodeError::Malformed("data after closing bracket".to_string()))
+            };
+        }
+        if !self.array_opened {
+            return if byte == b'[' {
+                self.array_opened = true;
+                Ok(())
+            } else {
+                Err(BatchDecodeError::Malformed("expected '['".to_string()))
+            };
+        }
+
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+            }
+            return self.append(byte);
+        }
+
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                self.append(byte)
+            }
+            b'{' | b'[' => {
+                self.depth += 1;
+                self.append(byte)
+            }
+            b'}' | b']' if self.depth > 0 => {
+                self.depth -= 1;
+                self.append(byte)
+            }
+            // トップレベルの区切り
+            b',' if self.depth == 0 => self.emit(items),
+            b']' => {
+                self.array_closed = true;
+                self.emit(items)
+            }
+            b'}' => Err(BatchDecodeError::Malformed("unbalanced '}'".to_string())),
+            _ => self.append(byte),
+        }
+    }
+}
+
+/// Parse one raw item: either a bare event or `{"event": ..., "source": ..., "event_id": ...}`
+pub fn parse_batch_item(raw: &[u8]) -> Result<SubmitEventRequest, String> {
+    let value: serde_json::Value = serde_json::from_slice(raw).map_err(|e| format!("Invalid JSON: {}", e))?;
+    if value.get("event").is_some() {
+        serde_json::from_value(value).map_err(|e| format!("Invalid event: {}", e))
+    } else {
+        let event: CyberEvent = serde_json::from_value(value).map_err(|e| format!("Invalid event: {}", e))?;
+        Ok(SubmitEventRequest { event, source: None, event_id: None })
+    }
+}
+
+/// Semantic checks beyond deserialization, with the default (strict) validator
+///
+/// サーバーでは設定された [`EventValidator`] のモードに従う (`AppState::ingest_event` を参照)
+pub fn validate_event(event: &CyberEvent) -> Result<(), String> {
+    match EventValidator::default().validate(event) {
+        ValidationOutcome::Accept { .. } => Ok(()),
+        ValidationOutcome::Reject(issues) | ValidationOutcome::Quarantine(issues) => Err(describe_issues(&issues)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(format: Option<BatchFormat>, body: &str, chunk_size: usize) -> Result<Vec<String>, BatchDecodeError> {
+        let mut decoder = BatchDecoder::new(format);
+        let mut items = Vec::new();
+        for chunk in body.as_bytes().chunks(chunk_size) {
+            items.extend(decoder.push(chunk)?);
+        }
+        items.extend(decoder.finish()?);
+        Ok(items.into_iter().map(|item| String::from_utf8(item).unwrap().trim().to_string()).collect())
+    }
+
+    #[test]
+    fn test_decoder_splits_across_chunks() {
+        let array = r#"[ {"a": "x,]\"}"}, {"b": [1, {"c": 2}]} ]"#;
+        for chunk_size in [1, 3, 7, array.len()] {
+            let items = decode_all(None, array, chunk_size).unwrap();
+            assert_eq!(items, vec![r#"{"a": "x,]\"}"}"#, r#"{"b": [1, {"c": 2}]}"#]);
+        }
+
+        let ndjson = "{\"a\":1}\n\n{\"b\":2}\r\n{\"c\":3}";
+        let items = decode_all(Some(BatchFormat::Ndjson), ndjson, 4).unwrap();
+        assert_eq!(items, vec!["{\"a\":1}", "{\"b\":2}", "{\"c\":3}"]);
+    }
+
+    #[test]
+    fn test_decoder_rejects_malformed_bodies() {
+        assert!(matches!(decode_all(None, r#"[{"a": 1}"#, 4), Err(BatchDecodeError::Malformed(_))));
+        assert!(matches!(decode_all(Some(BatchFormat::JsonArray), r#"{"a": 1}"#, 4), Err(BatchDecodeError::Malformed(_))));
+        assert_eq!(BatchFormat::from_content_type("application/x-ndjson; charset=utf-8"), Some(BatchFormat::Ndjson));
+    }
+
+    #[test]
+    fn test_parse_and_validate_items() {
+        let item = parse_batch_item(br#"{"event": {"type": "UserLogin", "data": {"user": "alice", "source_ip": "10.0.0.1", "success": true, "timestamp": 1700000000}}, "source": "edr-7", "event_id": "evt-1"}"#).unwrap();
+        assert_eq!(item.source.as_deref(), Some("edr-7"));
+        assert_eq!(item.event_id.as_deref(), Some("evt-1"));
+        assert!(validate_event(&item.event).is_ok());
+
+        let item = parse_batch_item(br#"{"type": "NetworkConnection", "data": {"source_ip": "10.0.0.1", "dest_ip": "not-an-ip", "port": 443, "protocol": "tcp", "timestamp": 1700000000}}"#).unwrap();
+        assert!(item.source.is_none() && item.event_id.is_none());
+        assert!(validate_event(&item.event).unwrap_err().contains("dest_ip"));
+
+        assert!(parse_batch_item(br#"{"type": "Unknown"}"#).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! HTTP caching for read endpoints
+//!
+//! 読み取り系のレスポンスに、ストアのリビジョン ([`RdfStore::revision`]) から求めた ETag と
+//! `Last-Modified` を付ける。`If-None-Match` (なければ `If-Modified-Since`) が現在のリビジョンと
+//! 一致すれば本文を計算せずに 304 を返すので、ダッシュボードのポーリングで変化のないデータを
+//! 再送しない。ETag はテナントとサーバープロセスごとに異なり、再起動後の古い ETag には一致しない
+
+use std::sync::OnceLock;
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use fukurow_store::{store::RdfStore, TenantId};
+
+use crate::pagination;
+
+/// `Cache-Control` of cacheable responses by default: clients may keep them but must revalidate
+pub const DEFAULT_CACHE_CONTROL: &str = "private, no-cache";
+
+/// Caching of read endpoints (`GET /graph/query`, `GET /stats`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpCacheConfig {
+    /// Send validators and answer conditional requests with 304
+    pub enabled: bool,
+    /// `Cache-Control` sent with cacheable responses
+    pub cache_control: String,
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, cache_control: DEFAULT_CACHE_CONTROL.to_string() }
+    }
+}
+
+impl HttpCacheConfig {
+    /// No validators, every request gets the full response
+    pub fn disabled() -> Self {
+        Self { enabled: false, ..Self::default() }
+    }
+
+    /// e.g. `public, max-age=5` to let dashboards skip revalidation for a few seconds
+    pub fn with_cache_control(mut self, cache_control: impl Into<String>) -> Self {
+        self.cache_control = cache_control.into();
+        self
+    }
+
+    /// 304 response when `validators` match the request's conditional headers
+    pub fn not_modified(&self, headers: &HeaderMap, validators: &Validators) -> Option<Response> {
+        (self.enabled && validators.matches(headers))
+            .then(|| self.with_validators(StatusCode::NOT_MODIFIED, validators))
+    }
+
+    /// `response` with the validators and `Cache-Control`
+    pub fn with_validators(&self, response: impl IntoResponse, validators: &Validators) -> Response {
+        let mut response = response.into_response();
+        if !self.enabled {
+            return response;
+        }
+        let headers = response.headers_mut();
+        if let Ok(etag) = HeaderValue::from_str(&validators.etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Some(last_modified) = validators.last_modified.and_then(|time| HeaderValue::from_str(&http_date(time)).ok()) {
+            headers.insert(header::LAST_MODIFIED, last_modified);
+        }
+        if let Ok(cache_control) = HeaderValue::from_str(&self.cache_control) {
+            headers.insert(header::CACHE_CONTROL, cache_control);
+        }
+        response
+    }
+}
+
+/// Identifies this server process in entity tags
+fn boot_id() -> &'static str {
+    static BOOT_ID: OnceLock<String> = OnceLock::new();
+    BOOT_ID.get_or_init(|| uuid::Uuid::new_v4().simple().to_string())
+}
+
+/// Validators of a response computed from one store revision
+///
+/// 応答の `timestamp` やアップタイムはリビジョンが同じでも変わるため、ETag は弱い (`W/"…"`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+impl Validators {
+    /// Validators of a response computed from `store` for `tenant`
+    ///
+    /// `variant` にはリクエストごとに変わる表現の要素 (クエリパラメーターなど) を渡す
+    pub fn for_store(store: &RdfStore, tenant: &TenantId, variant: &[Option<&str>]) -> Self {
+        let revision = store.revision().to_string();
+        let mut parts = vec![Some(boot_id()), Some(tenant.as_str()), Some(revision.as_str())];
+        parts.extend_from_slice(variant);
+        Self {
+            etag: format!("W/\"{:016x}\"", pagination::fingerprint(&parts)),
+            last_modified: store.modified_at().and_then(|millis| DateTime::from_timestamp_millis(millis as i64)),
+        }
+    }
+
+    /// Whether the client's copy is current
+    ///
+    /// `If-None-Match` は弱い比較で判定し、これがあるときは `If-Modified-Since` を見ない (RFC 9110 13.2.2)
+    pub fn matches(&self, headers: &HeaderMap) -> bool {
+        if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+            let ours = opaque_tag(&self.etag);
+            return if_none_match.to_str()
+                .is_ok_and(|tags| tags.split(',').map(str::trim).any(|tag| tag == "*" || opaque_tag(tag) == ours));
+        }
+        match (self.last_modified, headers.get(header::IF_MODIFIED_SINCE).and_then(|value| value.to_str().ok())) {
+            (Some(modified), Some(since)) => DateTime::parse_from_rfc2822(since).is_ok_and(|since| modified.timestamp() <= since.timestamp()),
+            _ => false,
+        }
+    }
+}
+
+/// Entity tag without its weakness indicator
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+/// IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`)
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+
+    fn conditional(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_validators_follow_store_revision() {
+        let mut store = RdfStore::new();
+        store.insert(Triple {
+            subject: "http://example.org/h1".to_string(),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: "http://example.org/c2".to_string(),
+        }, GraphId::Default, Provenance::Sensor { source: "edr".to_string(), confidence: None });
+        let tenant = TenantId::default();
+        let validators = Validators::for_store(&store, &tenant, &[Some("h1")]);
+        let config = HttpCacheConfig::default();
+
+        let response = config.not_modified(&conditional(header::IF_NONE_MATCH, &format!("\"other\", {}", validators.etag)), &validators).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], DEFAULT_CACHE_CONTROL);
+        assert!(response.headers().contains_key(header::LAST_MODIFIED));
+        assert!(validators.matches(&conditional(header::IF_NONE_MATCH, validators.etag.trim_start_matches("W/"))));
+        assert_ne!(Validators::for_store(&store, &tenant, &[Some("h2")]), validators);
+        assert!(HttpCacheConfig::disabled().not_modified(&conditional(header::IF_NONE_MATCH, "*"), &validators).is_none());
+
+        let modified = http_date(validators.last_modified.unwrap());
+        assert!(validators.matches(&conditional(header::IF_MODIFIED_SINCE, &modified)));
+        assert!(!validators.matches(&conditional(header::IF_MODIFIED_SINCE, "Sun, 06 Nov 1994 08:49:37 GMT")));
+
+        store.clear_all();
+        assert!(!Validators::for_store(&store, &tenant, &[Some("h1")]).matches(&conditional(header::IF_NONE_MATCH, &validators.etag)));
+    }
+}
@@ -0,0 +1,265 @@
+//! Graceful shutdown and drain mode
+//!
+//! SIGTERM または `POST /admin/drain` でドレインを開始すると、以降のリクエストには 503 と
+//! `x-fukurow-draining` ヘッダーを返す。実行中の推論ジョブを期限まで待ってから
+//! ストリーミングの停止シグナルを発火し (コンシューマはオフセットをコミット、プロセッサは
+//! バッファをフラッシュ)、HTTP サーバーの停止後に全テナントのストアをスナップショットに保存する
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use fukurow_store::{SnapshotInfo, TenantId};
+use fukurow_streaming::ShutdownSignal;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::handlers::AppState;
+use crate::models::ApiResponse;
+
+/// Response header set on requests rejected while draining
+pub const DRAIN_HEADER: &str = "x-fukurow-draining";
+
+/// Default time running jobs and streaming tasks get to finish
+pub const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+
+/// Path still served while draining, so operators can follow the drain
+pub const DRAIN_PATH: &str = "/admin/drain";
+
+/// Drain status (`GET /admin/drain`)
+#[derive(Debug, Clone, Serialize)]
+pub struct DrainStatus {
+    pub draining: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    /// Jobs are abandoned (not awaited) after this point
+    pub deadline: Option<DateTime<Utc>>,
+    /// Queued and running reasoning jobs
+    pub pending_jobs: usize,
+    pub streaming_tasks: usize,
+}
+
+/// Store persisted on shutdown
+#[derive(Debug, Clone, Serialize)]
+pub struct PersistedStore {
+    pub tenant: TenantId,
+    pub snapshot: SnapshotInfo,
+}
+
+/// What the shutdown sequence did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DrainReport {
+    /// Jobs still unfinished at the deadline
+    pub abandoned_jobs: usize,
+    /// Streaming tasks that finished after the stop signal
+    pub streaming_stopped: usize,
+    /// Streaming tasks aborted at the deadline
+    pub streaming_aborted: usize,
+    pub persisted: Vec<PersistedStore>,
+    /// Tenants whose store could not be persisted
+    pub errors: Vec<String>,
+}
+
+struct DrainInner {
+    /// Fired when draining starts
+    requested: ShutdownSignal,
+    /// Fired once jobs are done; shared with consumers and processors
+    streaming: ShutdownSignal,
+    started_at: Mutex<Option<DateTime<Utc>>>,
+    timeout: Duration,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+/// Coordinates drain mode and the shutdown sequence
+#[derive(Clone)]
+pub struct DrainController {
+    inner: Arc<DrainInner>,
+}
+
+impl Default for DrainController {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_DRAIN_TIMEOUT_SECS))
+    }
+}
+
+impl DrainController {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(DrainInner {
+                requested: ShutdownSignal::new(),
+                streaming: ShutdownSignal::new(),
+                started_at: Mutex::new(None),
+                timeout,
+                tasks: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Signal for streaming consumers (`run_until`) and processors (`with_shutdown`)
+    pub fn streaming_signal(&self) -> ShutdownSignal {
+        self.inner.streaming.clone()
+    }
+
+    /// Await `task` during shutdown; it should end once the streaming signal fires
+    pub fn track(&self, task: JoinHandle<()>) {
+        self.inner.tasks.lock().unwrap().push(task);
+    }
+
+    /// Enter drain mode; returns `false` when already draining
+    pub fn begin(&self) -> bool {
+        let mut started_at = self.inner.started_at.lock().unwrap();
+        if !self.inner.requested.trigger() {
+            return false;
+        }
+        *started_at = Some(Utc::now());
+        info!("Draining: rejecting new requests, waiting up to {:?} for running work", self.inner.timeout);
+        true
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.inner.requested.is_triggered()
+    }
+
+    /// Complete once draining has started
+    pub async fn requested(&self) {
+        self.inner.requested.triggered().await
+    }
+
+    pub fn status(&self, state: &AppState) -> DrainStatus {
+        let started_at = *self.inner.started_at.lock().unwrap();
+        let timeout = chrono::Duration::from_std(self.inner.timeout).unwrap_or_else(|_| chrono::Duration::zero());
+        DrainStatus {
+            draining: self.is_draining(),
+            started_at,
+            deadline: started_at.map(|started| started + timeout),
+            pending_jobs: state.jobs.pending(),
+            streaming_tasks: self.inner.tasks.lock().unwrap().len(),
+        }
+    }
+
+    /// Let running jobs finish, then stop streaming (both bounded by the drain timeout)
+    ///
+    /// 期限はジョブとストリーミングで共有する。期限切れのストリーミングタスクは中断する
+    pub async fn settle(&self, state: &AppState) -> DrainReport {
+        self.begin();
+        let deadline = tokio::time::Instant::now() + self.inner.timeout;
+        let mut report = DrainReport::default();
+
+        if !state.jobs.wait_idle(self.inner.timeout).await {
+            report.abandoned_jobs = state.jobs.pending();
+            warn!("Drain deadline reached with {} reasoning jobs unfinished", report.abandoned_jobs);
+        }
+
+        self.inner.streaming.trigger();
+        let tasks = std::mem::take(&mut *self.inner.tasks.lock().unwrap());
+        for mut task in tasks {
+            match tokio::time::timeout_at(deadline, &mut task).await {
+                Ok(_) => report.streaming_stopped += 1,
+                Err(_) => {
+                    task.abort();
+                    report.streaming_aborted += 1;
+                }
+            }
+        }
+        if report.streaming_aborted > 0 {
+            warn!("Aborted {} streaming tasks that did not stop before the drain deadline", report.streaming_aborted);
+        }
+        report
+    }
+
+    /// Snapshot every tenant's store into the persistence directory
+    pub async fn persist(&self, state: &AppState, report: &mut DrainReport) {
+        for tenant in state.tenants.tenants() {
+            let Some(engine) = state.tenants.get(&tenant) else { continue };
+            let snapshot = {
+                let store = engine.get_graph_store().await;
+                let graph_store = store.read().await;
+                graph_store.snapshot()
+            };
+            let persistence = if tenant.is_default() {
+                Arc::clone(&state.persistence)
+            } else {
+                Arc::new(state.persistence.for_tenant(&tenant))
+            };
+            let result = tokio::task::spawn_blocking(move || persistence.export_snapshot(&snapshot))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|exported| exported.map_err(|e| e.to_string()));
+            match result {
+                Ok(snapshot) => report.persisted.push(PersistedStore { tenant, snapshot }),
+                Err(e) => {
+                    warn!("Failed to persist store of tenant {} on shutdown: {}", tenant, e);
+                    report.errors.push(format!("{}: {}", tenant, e));
+                }
+            }
+        }
+    }
+}
+
+/// Reject requests with 503 while draining (except [`DRAIN_PATH`])
+pub async fn reject_while_draining(State(drain): State<DrainController>, request: Request, next: Next) -> Response {
+    if !drain.is_draining() || request.uri().path() == DRAIN_PATH {
+        return next.run(request).await;
+    }
+    let body = Json(ApiResponse::<String>::error("Server is draining; retry on another instance".to_string()));
+    (StatusCode::SERVICE_UNAVAILABLE, [(DRAIN_HEADER, "true"), (header::CONNECTION.as_str(), "close")], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ReasonerServer;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn server() -> ReasonerServer {
+        ReasonerServer::new(Arc::new(fukurow_observability::DefaultHealthMonitor::new()))
+    }
+
+    #[tokio::test]
+    async fn test_draining_rejects_requests_with_header() {
+        let server = server();
+        let drain = server.app_state().drain;
+        let app = server.create_app();
+
+        let health = || axum::http::Request::builder().uri("/health").body(Body::empty()).unwrap();
+        assert_eq!(app.clone().oneshot(health()).await.unwrap().status(), StatusCode::OK);
+
+        assert!(drain.begin());
+        assert!(!drain.begin());
+        let response = app.clone().oneshot(health()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[DRAIN_HEADER], "true");
+
+        let status = axum::http::Request::builder().uri(DRAIN_PATH).body(Body::empty()).unwrap();
+        assert_ne!(app.oneshot(status).await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_settle_stops_streaming_and_persist_snapshots_tenants() {
+        let dir = std::env::temp_dir().join(format!("fukurow-drain-{}", std::process::id()));
+        let mut state = server().app_state();
+        state.persistence = Arc::new(fukurow_store::PersistenceManager::new(&dir));
+        state.tenants.engine(&TenantId::default());
+        let drain = state.drain.clone();
+
+        let signal = drain.streaming_signal();
+        drain.track(tokio::spawn(async move { signal.triggered().await }));
+        let mut report = drain.settle(&state).await;
+        assert!(drain.is_draining());
+        assert_eq!((report.abandoned_jobs, report.streaming_stopped, report.streaming_aborted), (0, 1, 0));
+
+        drain.persist(&state, &mut report).await;
+        assert!(report.errors.is_empty());
+        assert_eq!(report.persisted.len(), 1);
+        assert!(report.persisted[0].snapshot.path.starts_with(&dir));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
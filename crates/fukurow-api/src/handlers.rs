@@ -1,33 +1,153 @@
 //! API request handlers
 
 use axum::{
-    extract::{Extension, Json},
-    http::StatusCode,
-    response::Json as JsonResponse,
+    body::Body,
+    extract::{Extension, Json, Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json as JsonResponse, Response},
+    response::sse::{Event, KeepAlive, Sse},
 };
+use futures::stream::Stream;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::time::Instant;
 
+use crate::anomaly::EventRateMonitor;
+use crate::auth::{AuthConfig, AuthenticatedPrincipal, Principal};
+use crate::batch;
+use crate::caching::{HttpCacheConfig, Validators};
+use crate::jobs::{JobError, JobManager, ReasoningJob};
 use crate::models::*;
-use fukurow_observability::{HealthMonitor, HealthStatus, HealthCheck, SystemMetrics};
-use fukurow_engine::ReasonerEngine;
+use crate::pagination;
+use crate::push::{PushFilter, PushHub};
+use crate::views::{ViewManager, ViewStatus};
+use crate::drain::{DrainController, DrainStatus};
+use crate::webhook::WebhookConfig;
+use fukurow_observability::{HealthMonitor, HealthStatus, HealthCheck};
+use fukurow_observability::tracing::{attributes, spans};
+use fukurow_core::model::CyberEvent;
+use fukurow_core::validation::{describe_issues, EventValidator, ValidationOutcome};
+use fukurow_engine::{EventReceipt, ReasonerEngine, TenantEngines};
 use fukurow_domain_cyber::threat_intelligence::ThreatProcessor;
+use fukurow_streaming::StreamingEvent;
+use fukurow_sparql::SparqlParser;
+use fukurow_store::{PersistenceManager, SnapshotInfo, TenantId};
+use tokio::sync::broadcast;
 
 #[cfg(feature = "streaming")]
 use fukurow_streaming::processor::EventSender;
 
+/// JSON response of a handler, or the status and error message it failed with
+type HandlerResult<T> = Result<JsonResponse<ApiResponse<T>>, (StatusCode, JsonResponse<ApiResponse<String>>)>;
+
 /// Shared application state
+///
+/// ストア・推論・保存クエリ・プッシュ配信はテナントごとに分離される。
+/// 脅威インテリジェンスは全テナントで共有する
 #[derive(Clone)]
 pub struct AppState {
-    pub reasoner: Arc<ReasonerEngine>,
+    pub tenants: Arc<TenantEngines>,
     pub threat_processor: Arc<RwLock<ThreatProcessor>>,
     pub monitoring: Arc<dyn HealthMonitor>,
     pub start_time: Instant,
+    pub push_hub: PushHub,
+    /// Tenant -> query name -> stored query
+    pub stored_queries: Arc<RwLock<HashMap<TenantId, HashMap<String, StoredQuery>>>>,
+    pub auth: Arc<AuthConfig>,
+    pub persistence: Arc<PersistenceManager>,
+    pub webhooks: Arc<WebhookConfig>,
+    /// Background reasoning jobs of every tenant
+    pub jobs: JobManager,
+    /// Reasoning capacity of each tenant (synchronous reasoning and jobs)
+    pub scheduler: Arc<fukurow_engine::TenantScheduler>,
+    /// Checks applied to events before ingestion
+    pub validator: Arc<EventValidator>,
+    /// Limits of every SPARQL query (requests may tighten them)
+    pub query_limits: fukurow_sparql::QueryLimits,
+    /// Prefixes understood and produced by auto-completion
+    pub prefixes: Arc<fukurow_core::prefix::PrefixMap>,
+    /// Validators and `Cache-Control` of read endpoints
+    pub http_cache: Arc<HttpCacheConfig>,
+    /// Materialized CONSTRUCT views of every tenant
+    pub views: ViewManager,
+    /// Drain mode and the shutdown sequence
+    pub drain: DrainController,
+    /// Ingestion rates checked for anomalies
+    pub event_rates: EventRateMonitor,
     #[cfg(feature = "streaming")]
     pub event_sender: Option<EventSender>,
 }
 
+impl AppState {
+    /// Reasoner engine (and store) of the caller's tenant, created on first use by a writing request
+    ///
+    /// 認証が有効ならテナントは資格情報で決まるため作ってよい。認証が無効なら `X-Tenant-ID` は
+    /// 誰でも指定できるので、既定のテナントと `tenant_isolation` に設定したテナントだけを作る
+    pub fn reasoner_for(&self, principal: &Principal) -> Result<Arc<ReasonerEngine>, ApiError> {
+        if let Some(engine) = self.tenants.get(&principal.tenant) {
+            return Ok(engine);
+        }
+        let configured = principal.tenant.is_default()
+            || self.scheduler.config().tenants.contains_key(principal.tenant.as_str());
+        if !self.auth.is_enabled() && !configured {
+            return Err(ApiError::UnknownTenant(principal.tenant.clone()));
+        }
+        Ok(self.tenants.engine(&principal.tenant))
+    }
+
+    /// Existing engine of the caller's tenant, for read-only requests (never creates one)
+    pub fn existing_reasoner(&self, principal: &Principal) -> Option<Arc<ReasonerEngine>> {
+        self.tenants.get(&principal.tenant)
+    }
+
+    /// [`AppState::existing_reasoner`], or 404 for a tenant nothing was written to
+    fn reader_for(&self, principal: &Principal) -> Result<Arc<ReasonerEngine>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+        self.existing_reasoner(principal).ok_or_else(|| unknown_tenant_response(&principal.tenant))
+    }
+
+    /// [`AppState::reasoner_for`] with the error as a handler response
+    fn writer_for(&self, principal: &Principal) -> Result<Arc<ReasonerEngine>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+        self.reasoner_for(principal).map_err(|_| unknown_tenant_response(&principal.tenant))
+    }
+
+    /// Validate `event`, then ingest it or store it in the quarantine graph per the validation mode
+    ///
+    /// 検証で拒否したイベントは `ApiError::InvalidRequest` になる
+    pub async fn ingest_event(&self, principal: &Principal, event: &CyberEvent, source: &str, event_id: Option<&str>) -> Result<EventReceipt, ApiError> {
+        let reasoner = self.reasoner_for(principal)?;
+        match self.validator.validate(event) {
+            ValidationOutcome::Accept { warnings } => {
+                if !warnings.is_empty() {
+                    tracing::warn!("Ingesting event from {} with warnings: {}", source, describe_issues(&warnings));
+                }
+                Ok(reasoner.submit_event(event.clone(), source, event_id, Some(&principal.id)).await?)
+            }
+            ValidationOutcome::Reject(issues) => Err(ApiError::InvalidRequest(describe_issues(&issues))),
+            ValidationOutcome::Quarantine(issues) => {
+                Ok(reasoner.quarantine_event(event.clone(), source, &issues, Some(&principal.id)).await?)
+            }
+        }
+    }
+    /// Count an ingested event toward its tenant's event rate and publish the anomaly of a window it closes
+    ///
+    /// 異常はテナントのプッシュ購読者と (有効なら) ストリーミングプロセッサに送る
+    pub fn record_ingested(&self, principal: &Principal, event: &CyberEvent) {
+        let Some(anomaly) = self.event_rates.record(&principal.tenant, event, chrono::Utc::now()) else { return };
+        #[cfg(feature = "streaming")]
+        if let Some(ref sender) = self.event_sender {
+            let _ = sender.send(anomaly.clone());
+        }
+        self.push_hub.publish_to(&principal.tenant, anomaly);
+    }
+}
+
+fn unknown_tenant_response(tenant: &TenantId) -> (StatusCode, JsonResponse<ApiResponse<String>>) {
+    (StatusCode::NOT_FOUND, JsonResponse(ApiResponse::error(ApiError::UnknownTenant(tenant.clone()).to_string())))
+}
+
 /// Health check handler
 pub async fn health_check(Extension(state): Extension<Arc<AppState>>) -> JsonResponse<ApiResponse<HealthResponse>> {
     let uptime = state.start_time.elapsed();
@@ -44,18 +164,27 @@ pub async fn health_check(Extension(state): Extension<Arc<AppState>>) -> JsonRes
 /// Submit cyber event handler
 pub async fn submit_event(
     Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
     Json(request): Json<SubmitEventRequest>,
-) -> Result<JsonResponse<ApiResponse<String>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
-    match state.reasoner.add_event(request.event.clone()).await {
-        Ok(_) => {
-            // Send security event if streaming is enabled
-            #[cfg(feature = "streaming")]
-            if let Some(ref sender) = state.event_sender {
-                let _ = sender.send_security_event(request.event, "api".to_string());
+) -> HandlerResult<fukurow_engine::EventReceipt> {
+    let source = request.source.clone().unwrap_or_else(|| "api".to_string());
+    match state.ingest_event(&principal, &request.event, &source, request.event_id.as_deref()).await {
+        Ok(receipt) => {
+            if !receipt.duplicate && !receipt.quarantined {
+                state.record_ingested(&principal, &request.event);
+                // Send security event if streaming is enabled (duplicates were already streamed)
+                #[cfg(feature = "streaming")]
+                if let Some(ref sender) = state.event_sender {
+                    let _ = sender.send_correlated_security_event(request.event, source, Some(receipt.correlation_id.clone()));
+                }
             }
 
-            let response = ApiResponse::success("Event submitted successfully".to_string());
-            Ok(JsonResponse(response))
+            Ok(JsonResponse(ApiResponse::success(receipt)))
+        }
+        Err(ApiError::UnknownTenant(tenant)) => Err(unknown_tenant_response(&tenant)),
+        Err(ApiError::InvalidRequest(message)) => {
+            let error_response = ApiResponse::error(format!("Invalid event: {}", message));
+            Err((StatusCode::BAD_REQUEST, JsonResponse(error_response)))
         }
         Err(e) => {
             let error_response = ApiResponse::error(format!("Failed to submit event: {}", e));
@@ -64,30 +193,260 @@ pub async fn submit_event(
     }
 }
 
+/// Quarantined events handler (`GET /events/quarantine`)
+pub async fn list_quarantined_events(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+) -> JsonResponse<ApiResponse<QuarantineResponse>> {
+    let events = match state.existing_reasoner(&principal) {
+        Some(reasoner) => reasoner.quarantined_events().await,
+        None => Vec::new(),
+    };
+    let count = events.len();
+    JsonResponse(ApiResponse::success(QuarantineResponse { events, count }))
+}
+
+/// Bulk event submission handler (NDJSON or JSON array, read incrementally)
+pub async fn submit_event_batch(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<BatchIngestParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> HandlerResult<BatchIngestResponse> {
+    let start = Instant::now();
+    let default_source = params.source.clone().unwrap_or_else(|| "api".to_string());
+    let format = params.format.as_deref()
+        .or_else(|| headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()))
+        .and_then(batch::BatchFormat::from_content_type);
+
+    let reasoner = state.writer_for(&principal)?;
+    let ingestor = reasoner.start_batch_ingestion(fukurow_engine::IngestConfig::default());
+    let mut decoder = Some(batch::BatchDecoder::new(format));
+    let mut results = Vec::new();
+    let mut tickets = Vec::new();
+    let mut received = 0;
+    let mut framing_error = None;
+
+    let mut stream = body.into_data_stream();
+    while let Some(current) = decoder.as_mut() {
+        let decoded = match stream.next().await {
+            Some(Ok(chunk)) => current.push(&chunk),
+            Some(Err(e)) => {
+                let error_response = ApiResponse::error(format!("Failed to read request body: {}", e));
+                return Err((StatusCode::BAD_REQUEST, JsonResponse(error_response)));
+            }
+            // ボディ終端: 末尾の要素を取り出してループを抜ける
+            None => decoder.take().map(|d| d.finish()).unwrap_or_else(|| Ok(Vec::new())),
+        };
+        let raw_items = match decoded {
+            Ok(items) => items,
+            Err(e) => {
+                framing_error = Some(e.to_string());
+                break;
+            }
+        };
+
+        for raw in raw_items {
+            let index = received;
+            received += 1;
+            let SubmitEventRequest { event, source, event_id } = match batch::parse_batch_item(&raw) {
+                Ok(item) => item,
+                Err(error) => {
+                    results.push(BatchItemResult { index, status: BatchItemStatus::Rejected, error: Some(error), triples: 0, correlation_id: None });
+                    continue;
+                }
+            };
+
+            let source = source.unwrap_or_else(|| default_source.clone());
+            match state.validator.validate(&event) {
+                ValidationOutcome::Accept { warnings } => {
+                    if !warnings.is_empty() {
+                        tracing::warn!("Ingesting batch item {} with warnings: {}", index, describe_issues(&warnings));
+                    }
+                }
+                ValidationOutcome::Reject(issues) => {
+                    results.push(BatchItemResult { index, status: BatchItemStatus::Rejected, error: Some(describe_issues(&issues)), triples: 0, correlation_id: None });
+                    continue;
+                }
+                ValidationOutcome::Quarantine(issues) => {
+                    let result = match reasoner.quarantine_event(event, &source, &issues, Some(&principal.id)).await {
+                        Ok(receipt) => BatchItemResult { index, status: BatchItemStatus::Quarantined, error: Some(describe_issues(&issues)), triples: 0, correlation_id: Some(receipt.correlation_id) },
+                        Err(e) => BatchItemResult { index, status: BatchItemStatus::Rejected, error: Some(e.to_string()), triples: 0, correlation_id: None },
+                    };
+                    results.push(result);
+                    continue;
+                }
+            }
+            // 単発投入と同じ重複排除の窓を通し、相関 ID を付けて書き込む
+            match ingestor.submit_event(&event, &source, event_id.as_deref()).await {
+                Ok(ticket) => tickets.push((index, ticket, event, source)),
+                Err(e) => {
+                    results.push(BatchItemResult { index, status: BatchItemStatus::Rejected, error: Some(e.to_string()), triples: 0, correlation_id: None });
+                }
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "streaming"), allow(unused_variables))]
+    for (index, ticket, event, source) in tickets {
+        results.push(match ticket.wait().await {
+            Ok(receipt) if receipt.duplicate => {
+                BatchItemResult { index, status: BatchItemStatus::Duplicate, error: None, triples: 0, correlation_id: receipt.correlation_id }
+            }
+            Ok(receipt) => {
+                state.record_ingested(&principal, &event);
+                // 重複は既に配信済みのため、書き込んだイベントだけを配信する
+                #[cfg(feature = "streaming")]
+                if let Some(ref sender) = state.event_sender {
+                    let _ = sender.send_correlated_security_event(event, source, receipt.correlation_id.clone());
+                }
+                BatchItemResult { index, status: BatchItemStatus::Accepted, error: None, triples: receipt.triples, correlation_id: receipt.correlation_id }
+            }
+            Err(e) => BatchItemResult { index, status: BatchItemStatus::Rejected, error: Some(e.to_string()), triples: 0, correlation_id: None },
+        });
+    }
+    results.sort_by_key(|result| result.index);
+
+    let count = |status: BatchItemStatus| results.iter().filter(|r| r.status == status).count();
+    let accepted = count(BatchItemStatus::Accepted);
+    let quarantined = count(BatchItemStatus::Quarantined);
+    let duplicates = count(BatchItemStatus::Duplicate);
+    Ok(JsonResponse(ApiResponse::success(BatchIngestResponse {
+        received,
+        accepted,
+        rejected: received - accepted - quarantined - duplicates,
+        quarantined,
+        duplicates,
+        triples: results.iter().map(|r| r.triples).sum(),
+        results,
+        error: framing_error,
+        execution_time_ms: start.elapsed().as_millis() as u64,
+    })))
+}
+
+/// Sensor webhook handler (`POST /ingest/:route`)
+///
+/// ルートの形式で本文をパースし、マッピングした CyberEvent をテナントのエンジンに投入する。
+/// 認証はルートの共有シークレット、またはシークレットの無いルートでは通常の資格情報で行う
+pub async fn ingest_webhook(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: String,
+) -> HandlerResult<WebhookIngestResponse> {
+    let start = Instant::now();
+    let route = match state.webhooks.route(&name) {
+        Some(route) => route,
+        None => {
+            let error_response = ApiResponse::error(format!("Unknown webhook route: {}", name));
+            return Err((StatusCode::NOT_FOUND, JsonResponse(error_response)));
+        }
+    };
+    let principal = route.authenticate(&name, &headers, &state.auth)
+        .map_err(|e| (e.status(), JsonResponse(ApiResponse::error(e.to_string()))))?;
+    let decoded = route.decode(&body).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, JsonResponse(ApiResponse::error(e.to_string())))
+    })?;
+
+    let received = decoded.len();
+    let mut errors = Vec::new();
+    let mut correlation_ids = Vec::new();
+    let mut duplicates = 0;
+    let mut quarantined = 0;
+    for (index, item) in decoded.into_iter().enumerate() {
+        let item = match item {
+            Ok(item) => item,
+            Err(e) => {
+                errors.push(WebhookItemError { index, error: e.to_string() });
+                continue;
+            }
+        };
+
+        match state.ingest_event(&principal, &item.event, &route.source, item.event_id.as_deref()).await {
+            Ok(receipt) => {
+                if !receipt.duplicate && !receipt.quarantined {
+                    state.record_ingested(&principal, &item.event);
+                    #[cfg(feature = "streaming")]
+                    if let Some(ref sender) = state.event_sender {
+                        let _ = sender.send_correlated_security_event(item.event, route.source.clone(), Some(receipt.correlation_id.clone()));
+                    }
+                }
+                if receipt.duplicate {
+                    duplicates += 1;
+                }
+                if receipt.quarantined {
+                    quarantined += 1;
+                }
+                correlation_ids.push(receipt.correlation_id);
+            }
+            Err(ApiError::InvalidRequest(error)) => errors.push(WebhookItemError { index, error }),
+            Err(e) => errors.push(WebhookItemError { index, error: e.to_string() }),
+        }
+    }
+
+    Ok(JsonResponse(ApiResponse::success(WebhookIngestResponse {
+        route: name,
+        received,
+        accepted: received - errors.len(),
+        duplicates,
+        rejected: errors.len(),
+        errors,
+        correlation_ids,
+        quarantined,
+        execution_time_ms: start.elapsed().as_millis() as u64,
+    })))
+}
+
 /// Execute reasoning handler
 pub async fn execute_reasoning(
     Extension(state): Extension<Arc<AppState>>,
-    Json(_request): Json<ReasoningRequest>,
-) -> Result<JsonResponse<ApiResponse<ReasoningResponse>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Json(request): Json<ReasoningRequest>,
+) -> HandlerResult<ReasoningResponse> {
     let start = Instant::now();
+    let _permit = match state.scheduler.acquire(principal.tenant.as_str()).await {
+        Ok(permit) => permit,
+        Err(e) => return Err((StatusCode::SERVICE_UNAVAILABLE, JsonResponse(ApiResponse::error(e.to_string())))),
+    };
+    let reasoner = state.writer_for(&principal)?;
+    let outcome = match request.profile {
+        Some(profile) => reasoner.reason_with_profile(profile).await,
+        None => reasoner.reason_correlated().await,
+    };
 
-    match state.reasoner.reason().await {
-        Ok(actions) => {
+    match outcome {
+        Ok(correlated) => {
             let execution_time = start.elapsed();
+            let correlation_ids: Vec<Vec<String>> = correlated.iter().map(|c| c.correlation_ids.clone()).collect();
+            let actions: Vec<_> = correlated.into_iter().map(|c| c.action).collect();
+            let mut all_ids: Vec<String> = correlation_ids.iter().flatten().cloned().collect();
+            all_ids.sort();
+            all_ids.dedup();
 
             let response = ReasoningResponse {
                 actions: actions.clone(),
                 execution_time_ms: execution_time.as_millis() as u64,
                 event_count: 0, // TODO: Get actual event count from reasoner
+                correlation_ids,
             };
 
+            state.push_hub.publish_to(&principal.tenant, StreamingEvent::ReasoningResult {
+                actions: actions.clone(),
+                execution_time_ms: execution_time.as_millis() as u64,
+                event_count: 0,
+                timestamp: chrono::Utc::now(),
+                correlation_ids: all_ids.clone(),
+            });
+
             // Send reasoning result event if streaming is enabled
             #[cfg(feature = "streaming")]
             if let Some(ref sender) = state.event_sender {
-                let _ = sender.send_reasoning_result(
+                let _ = sender.send_correlated_reasoning_result(
                     actions,
                     execution_time.as_millis() as u64,
                     0, // TODO: Get actual event count
+                    all_ids,
                 );
             }
 
@@ -100,31 +459,758 @@ pub async fn execute_reasoning(
     }
 }
 
+/// Start reasoning as a background job
+///
+/// `202 Accepted` でジョブを返し、結果は `GET /jobs/:id` で取得する
+pub async fn submit_reasoning_job(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Json(request): Json<AsyncReasoningRequest>,
+) -> Result<(StatusCode, JsonResponse<ApiResponse<ReasoningJob>>), (StatusCode, JsonResponse<ApiResponse<String>>)> {
+    let push_hub = state.push_hub.clone();
+    let tenant = principal.tenant.clone();
+    #[cfg(feature = "streaming")]
+    let sender = state.event_sender.clone();
+    let publish_result = request.publish_result;
+
+    let on_complete = move |job: &ReasoningJob| {
+        if !publish_result {
+            return;
+        }
+        let mut all_ids: Vec<String> = job.correlation_ids.iter().flatten().cloned().collect();
+        all_ids.sort();
+        all_ids.dedup();
+        let execution_time_ms = job.execution_time_ms.unwrap_or_default();
+
+        push_hub.publish_to(&tenant, StreamingEvent::ReasoningResult {
+            actions: job.actions.clone(),
+            execution_time_ms,
+            event_count: 0,
+            timestamp: chrono::Utc::now(),
+            correlation_ids: all_ids.clone(),
+        });
+
+        #[cfg(feature = "streaming")]
+        if let Some(sender) = sender {
+            let _ = sender.send_correlated_reasoning_result(job.actions.clone(), execution_time_ms, 0, all_ids);
+        }
+    };
+
+    let reasoner = state.writer_for(&principal)?;
+    match state.jobs.submit(principal.tenant.clone(), reasoner, request.profile, on_complete) {
+        Ok(job) => Ok((StatusCode::ACCEPTED, JsonResponse(ApiResponse::success(job)))),
+        Err(e @ JobError::QueueFull { .. }) => Err((StatusCode::SERVICE_UNAVAILABLE, JsonResponse(ApiResponse::error(e.to_string())))),
+    }
+}
+
+/// Status, progress and (partial) results of a reasoning job
+pub async fn get_reasoning_job(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(id): Path<String>,
+) -> HandlerResult<ReasoningJob> {
+    state.jobs.get(&principal.tenant, &id)
+        .map(|job| JsonResponse(ApiResponse::success(job)))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, JsonResponse(ApiResponse::error(format!("Job not found: {}", id)))))
+}
+
+/// Subscribe to reasoning results and anomalies as Server-Sent Events
+pub async fn stream_events(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(query): Query<EventStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+    let filter = PushFilter::from_query(query.types.as_deref(), query.min_severity.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, JsonResponse(ApiResponse::error(e))))?;
+    let receiver = state.push_hub.subscribe_to(&principal.tenant);
+
+    let stream = futures::stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if filter.matches(&event) => {
+                    let sse_event = Event::default()
+                        .event(event.event_type())
+                        .json_data(&event)
+                        .unwrap_or_else(|e| Event::default().comment(format!("serialization error: {}", e)));
+                    return Some((Ok(sse_event), (receiver, filter)));
+                }
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event stream subscriber lagged, skipped {} events", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 /// Query graph handler
 pub async fn query_graph(
     Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
     Json(request): Json<GraphQueryRequest>,
-) -> Result<JsonResponse<ApiResponse<GraphQueryResponse>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
-    let store = state.reasoner.get_graph_store().await;
-    let graph_store = store.read().await;
+) -> HandlerResult<GraphQueryResponse> {
+    // クエリはレプリカで実行し、イベント投入や推論の書き込みを待たせない
+    let graph_store = state.reader_for(&principal)?.query_view();
+    let response = run_graph_query(&graph_store, &request)?;
+    Ok(JsonResponse(ApiResponse::success(response)))
+}
+
+/// Graph query handler for `GET /graph/query?subject=…` (paginated, cacheable)
+///
+/// 応答にはストアのリビジョンから求めた ETag と Last-Modified を付け、
+/// `If-None-Match` / `If-Modified-Since` が一致すればクエリを実行せずに 304 を返す
+pub async fn query_graph_get(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(request): Query<GraphQueryRequest>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+    let graph_store = state.reader_for(&principal)?.query_view();
+    let limit = request.limit.map(|limit| limit.to_string());
+    let validators = Validators::for_store(&graph_store, &principal.tenant, &[
+        request.subject.as_deref(),
+        request.predicate.as_deref(),
+        request.object.as_deref(),
+        request.graph_name.as_deref(),
+        limit.as_deref(),
+        request.cursor.as_deref(),
+    ]);
+    if let Some(not_modified) = state.http_cache.not_modified(&headers, &validators) {
+        return Ok(not_modified);
+    }
+
+    let response = run_graph_query(&graph_store, &request)?;
+    Ok(state.http_cache.with_validators(JsonResponse(ApiResponse::success(response)), &validators))
+}
 
-    let triples = graph_store.find_triples(
+/// Pattern match and paginate a graph query against `graph_store`
+fn run_graph_query(graph_store: &fukurow_store::RdfStore, request: &GraphQueryRequest) -> Result<GraphQueryResponse, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+    let fingerprint = pagination::fingerprint(&[
         request.subject.as_deref(),
         request.predicate.as_deref(),
         request.object.as_deref(),
-    );
+        request.graph_name.as_deref(),
+    ]);
+    let after = pagination::triple_keyset(request.cursor.as_deref(), fingerprint)
+        .map_err(|e| (StatusCode::BAD_REQUEST, JsonResponse(ApiResponse::error(e.to_string()))))?;
+
+    // キーセットをストアの走査に渡し、1 ページ分 (+1 件) だけを取り出す
+    let span = store_query_span("pattern");
+    let triples = span.in_scope(|| graph_store.find_triples_page(
+        request.subject.as_deref(),
+        request.predicate.as_deref(),
+        request.object.as_deref(),
+        after.as_ref().map(|(s, p, o)| (s.as_str(), p.as_str(), o.as_str())),
+        pagination::page_size(request.limit) + 1,
+    ));
+    span.record(attributes::RESULT_COUNT, triples.len());
+
+    let (page, next_cursor) = pagination::triple_page(triples, request.limit, fingerprint);
+
+    Ok(GraphQueryResponse {
+        count: page.len(),
+        triples: page,
+        next_cursor,
+    })
+}
+
+/// `store.query` span around a store read
+fn store_query_span(kind: &'static str) -> tracing::Span {
+    tracing::info_span!(
+        spans::STORE_QUERY,
+        query.kind = kind,
+        result.count = tracing::field::Empty,
+        error.type = tracing::field::Empty
+    )
+}
+
+/// SPARQL query handler (paginated)
+///
+/// `explain=true` ではクエリを実行せず、最適化後の計画と推定行数を返す
+pub async fn query_sparql(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<SparqlQueryParams>,
+    Json(request): Json<SparqlQueryRequest>,
+) -> HandlerResult<SparqlQueryResponse> {
+    let graph_store = state.reader_for(&principal)?.query_view();
+
+    if params.explain {
+        fukurow_sparql::parser::DefaultSparqlParser.parse(&request.query).map_err(|e| {
+            (StatusCode::BAD_REQUEST, JsonResponse(ApiResponse::error(format!("Invalid query: {}", e))))
+        })?;
+        let plan = fukurow_sparql::explain_query(&request.query, &graph_store).map_err(|e| {
+            (StatusCode::INTERNAL_SERVER_ERROR, JsonResponse(ApiResponse::error(format!("Explain failed: {}", e))))
+        })?;
+        let result = SparqlResultPage::Explain { plan };
+        return Ok(JsonResponse(ApiResponse::success(SparqlQueryResponse { result, count: 0, next_cursor: None, truncated: None })));
+    }
+
+    let span = store_query_span("sparql");
+    let response = span.in_scope(|| run_sparql(&request, &graph_store, state.query_limits)).map_err(|failure| {
+        span.record(attributes::ERROR_TYPE, failure.error_type);
+        (failure.status, JsonResponse(ApiResponse::error(failure.message)))
+    })?;
+    span.record(attributes::RESULT_COUNT, response.count);
+
+    Ok(JsonResponse(ApiResponse::success(response)))
+}
+
+/// Batch SPARQL handler: every query reads the same store snapshot
+///
+/// スナップショットは読み取り専用のため、各クエリはブロッキングプールで並列に実行する。
+/// 失敗したクエリはその結果だけがエラーになり、他のクエリの結果は返す
+pub async fn query_sparql_batch(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Json(request): Json<SparqlBatchRequest>,
+) -> HandlerResult<SparqlBatchResponse> {
+    if request.queries.is_empty() || request.queries.len() > MAX_SPARQL_BATCH {
+        let message = format!("A batch holds 1 to {} queries, got {}", MAX_SPARQL_BATCH, request.queries.len());
+        return Err((StatusCode::BAD_REQUEST, JsonResponse(ApiResponse::error(message))));
+    }
+
+    let graph_store = state.reader_for(&principal)?.query_view();
+    let tasks: Vec<_> = request.queries.into_iter().enumerate()
+        .map(|(index, query)| {
+            let store = Arc::clone(&graph_store);
+            let limits = state.query_limits;
+            let span = store_query_span("sparql_batch");
+            let id = query.id.unwrap_or_else(|| index.to_string());
+            let task = tokio::task::spawn_blocking(move || {
+                let _entered = span.enter();
+                let outcome = run_sparql(&query.request, &store, limits);
+                match &outcome {
+                    Ok(response) => span.record(attributes::RESULT_COUNT, response.count),
+                    Err(failure) => span.record(attributes::ERROR_TYPE, failure.error_type),
+                };
+                outcome
+            });
+            (id, task)
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for (id, task) in tasks {
+        let outcome = task.await.unwrap_or_else(|e| Err(SparqlFailure {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error_type: "query_failed",
+            message: format!("Query failed: {}", e),
+        }));
+        results.push(match outcome {
+            Ok(response) => SparqlBatchResult { id, status: StatusCode::OK.as_u16(), response: Some(response), error: None },
+            Err(failure) => SparqlBatchResult { id, status: failure.status.as_u16(), response: None, error: Some(failure.message) },
+        });
+    }
+    let failed = results.iter().filter(|result| result.error.is_some()).count();
+
+    Ok(JsonResponse(ApiResponse::success(SparqlBatchResponse {
+        succeeded: results.len() - failed,
+        failed,
+        results,
+    })))
+}
+
+/// Most queries accepted by `POST /sparql/batch`
+pub const MAX_SPARQL_BATCH: usize = 50;
+
+/// Why a SPARQL request failed
+struct SparqlFailure {
+    status: StatusCode,
+    /// `error.type` recorded on the query span
+    error_type: &'static str,
+    message: String,
+}
+
+/// Run one SPARQL request against `store` within the server's limits and paginate its results
+fn run_sparql(request: &SparqlQueryRequest, store: &fukurow_store::RdfStore, server_limits: fukurow_sparql::QueryLimits) -> Result<SparqlQueryResponse, SparqlFailure> {
+    let parsed = fukurow_sparql::parser::DefaultSparqlParser.parse(&request.query).map_err(|e| SparqlFailure {
+        status: StatusCode::BAD_REQUEST,
+        error_type: "invalid_query",
+        message: format!("Invalid query: {}", e),
+    })?;
+
+    let limits = match &request.limits {
+        Some(requested) => requested.apply(server_limits),
+        None => server_limits,
+    };
+    let fingerprint = pagination::fingerprint(&[Some(request.query.as_str())]);
+    let bad_cursor = |e: pagination::PaginationError| SparqlFailure {
+        status: StatusCode::BAD_REQUEST,
+        error_type: "invalid_cursor",
+        message: e.to_string(),
+    };
+    let offset = match parsed.query_type {
+        fukurow_sparql::parser::QueryType::Select => pagination::row_offset(request.cursor.as_deref(), fingerprint).map_err(bad_cursor)?,
+        _ => 0,
+    };
+
+    // SELECT の並べ替えと切り出しは評価器に任せ、1 ページ分 (+1 件) だけを受け取る
+    let page_rows = pagination::page_size(request.limit) + 1;
+    let limited = fukurow_sparql::execute_query_page(&request.query, store, limits, offset, page_rows).map_err(|e| {
+        let (status, error_type) = match e {
+            fukurow_sparql::SparqlError::LimitExceeded(_) => (StatusCode::UNPROCESSABLE_ENTITY, "limit_exceeded"),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "query_failed"),
+        };
+        SparqlFailure { status, error_type, message: format!("Query failed: {}", e) }
+    })?;
+    let (result, truncated) = (limited.result, limited.truncated);
+
+    let (result, count, next_cursor) = match result {
+        fukurow_sparql::QueryResult::Select { variables, bindings } => {
+            let rows: Vec<_> = bindings.iter().map(fukurow_sparql::diff::bindings_to_row).collect();
+            let (rows, next) = pagination::row_page(rows, offset, request.limit, fingerprint);
+            let count = rows.len();
+            let variables = variables.into_iter().map(|v| v.0).collect();
+            (SparqlResultPage::Select { variables, rows }, count, next)
+        }
+        fukurow_sparql::QueryResult::Construct { triples } | fukurow_sparql::QueryResult::Describe { triples } => {
+            let (triples, next) = pagination::paginate_triples(triples, request.limit, request.cursor.as_deref(), fingerprint)
+                .map_err(bad_cursor)?;
+            let count = triples.len();
+            (SparqlResultPage::Graph { triples }, count, next)
+        }
+        fukurow_sparql::QueryResult::Ask { result } => (SparqlResultPage::Ask { boolean: result }, 1, None),
+    };
+
+    Ok(SparqlQueryResponse { result, count, next_cursor, truncated })
+}
+
+/// Query audit log handler
+pub async fn query_audit(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<AuditQueryParams>,
+) -> HandlerResult<AuditQueryResponse> {
+    let query = fukurow_store::AuditQuery {
+        from: params.from,
+        to: params.to,
+        actor: params.actor,
+        operation: params.operation,
+        graph: params.graph,
+        limit: params.limit,
+    };
+
+    let store = state.reader_for(&principal)?.get_graph_store().await;
+    let graph_store = store.read().await;
 
-    let count = triples.len();
-    let response = GraphQueryResponse {
-        triples: triples.into_iter().map(|stored| stored.triple.clone()).collect(),
+    match graph_store.query_audit(&query) {
+        Ok(entries) => {
+            let count = entries.len();
+            Ok(JsonResponse(ApiResponse::success(AuditQueryResponse { entries, count })))
+        }
+        Err(e) => {
+            let error_response = ApiResponse::error(format!("Failed to query audit log: {}", e));
+            Err((StatusCode::INTERNAL_SERVER_ERROR, JsonResponse(error_response)))
+        }
+    }
+}
+
+/// Export a store snapshot handler
+///
+/// 読み取りロックはスナップショットを取る間だけ保持し、書き出しはブロッキングスレッドで行う。
+/// 既定テナント以外はスナップショットディレクトリ配下のテナント名のディレクトリに書き出す
+pub async fn export_snapshot(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<SnapshotParams>,
+) -> HandlerResult<SnapshotInfo> {
+    let snapshot = {
+        let store = state.reader_for(&principal)?.get_graph_store().await;
+        let graph_store = store.read().await;
+        graph_store.snapshot()
+    };
+
+    let persistence = if principal.tenant.is_default() {
+        Arc::clone(&state.persistence)
+    } else {
+        Arc::new(state.persistence.for_tenant(&principal.tenant))
+    };
+    let format = params.format.unwrap_or(persistence.format());
+    let result = tokio::task::spawn_blocking(move || persistence.export_snapshot_as(&snapshot, format))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|exported| exported.map_err(|e| e.to_string()));
+
+    match result {
+        Ok(info) => Ok(JsonResponse(ApiResponse::success(info))),
+        Err(e) => {
+            let error_response = ApiResponse::error(format!("Failed to export snapshot: {}", e));
+            Err((StatusCode::INTERNAL_SERVER_ERROR, JsonResponse(error_response)))
+        }
+    }
+}
+
+/// Store (or replace) a named SPARQL query handler
+pub async fn save_query(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+    Json(request): Json<StoreQueryRequest>,
+) -> HandlerResult<StoredQuery> {
+    // 登録時に構文だけ検証しておく
+    if let Err(e) = fukurow_sparql::parser::DefaultSparqlParser.parse(&request.query) {
+        let error_response = ApiResponse::error(format!("Invalid query: {}", e));
+        return Err((StatusCode::BAD_REQUEST, JsonResponse(error_response)));
+    }
+
+    let stored = StoredQuery {
+        name: name.clone(),
+        query: request.query,
+        description: request.description,
+        created_at: chrono::Utc::now().timestamp_millis().max(0) as u64,
+    };
+    state.stored_queries.write().await.entry(principal.tenant).or_default().insert(name, stored.clone());
+
+    Ok(JsonResponse(ApiResponse::success(stored)))
+}
+
+/// List stored queries handler
+pub async fn list_queries(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+) -> JsonResponse<ApiResponse<Vec<StoredQuery>>> {
+    let mut queries: Vec<StoredQuery> = state.stored_queries.read().await
+        .get(&principal.tenant)
+        .map(|queries| queries.values().cloned().collect())
+        .unwrap_or_default();
+    queries.sort_by(|a, b| a.name.cmp(&b.name));
+    JsonResponse(ApiResponse::success(queries))
+}
+
+/// Diff a stored query between a historical snapshot and now handler
+pub async fn diff_stored_query(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+    Query(params): Query<QueryDiffParams>,
+) -> HandlerResult<QueryDiffResponse> {
+    let query = match state.stored_queries.read().await.get(&principal.tenant).and_then(|queries| queries.get(&name)) {
+        Some(stored) => stored.query.clone(),
+        None => {
+            let error_response = ApiResponse::error(format!("Stored query not found: {}", name));
+            return Err((StatusCode::NOT_FOUND, JsonResponse(error_response)));
+        }
+    };
+
+    let until = chrono::Utc::now().timestamp_millis().max(0) as u64;
+    let since = params.since
+        .unwrap_or_else(|| until.saturating_sub(params.since_secs.unwrap_or(86_400) * 1000));
+
+    let store = state.reader_for(&principal)?.get_graph_store().await;
+    let graph_store = store.read().await;
+
+    match fukurow_sparql::diff_since(&query, &graph_store, since) {
+        Ok(diff) => Ok(JsonResponse(ApiResponse::success(QueryDiffResponse {
+            name,
+            since,
+            until,
+            has_changes: diff.has_changes(),
+            diff,
+        }))),
+        Err(e) => {
+            // 監査ログの保持期間より前の since は要求側の問題として扱う
+            let status = match e {
+                fukurow_sparql::SparqlError::HistoryError(_) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let error_response = ApiResponse::error(format!("Failed to diff query: {}", e));
+            Err((status, JsonResponse(error_response)))
+        }
+    }
+}
+
+/// Build a view writing to named graph `graph` (400 on an invalid query or graph)
+fn materialized_view(name: &str, query: &str, graph: &str) -> Result<fukurow_sparql::MaterializedView, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+    if graph.trim().is_empty() {
+        let error_response = ApiResponse::error("Graph IRI must not be empty".to_string());
+        return Err((StatusCode::BAD_REQUEST, JsonResponse(error_response)));
+    }
+    let graph = fukurow_store::GraphId::Named(graph.trim().to_string());
+    fukurow_sparql::MaterializedView::new(name, query, graph).map_err(|e| {
+        (StatusCode::BAD_REQUEST, JsonResponse(ApiResponse::error(format!("Invalid view: {}", e))))
+    })
+}
+
+fn materialization_failed(e: fukurow_sparql::SparqlError) -> (StatusCode, JsonResponse<ApiResponse<String>>) {
+    (StatusCode::INTERNAL_SERVER_ERROR, JsonResponse(ApiResponse::error(format!("Materialization failed: {}", e))))
+}
+
+fn view_not_found(name: &str) -> (StatusCode, JsonResponse<ApiResponse<String>>) {
+    (StatusCode::NOT_FOUND, JsonResponse(ApiResponse::error(format!("View not found: {}", name))))
+}
+
+/// Execute a CONSTRUCT query once and write its result to a named graph handler
+pub async fn materialize_sparql(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Json(request): Json<MaterializeRequest>,
+) -> HandlerResult<fukurow_sparql::MaterializationReport> {
+    let view = materialized_view("construct", &request.query, &request.graph)?;
+    let store = state.writer_for(&principal)?.get_graph_store().await;
+    let mut graph_store = store.write().await;
+    let report = view.refresh(&mut graph_store).map_err(materialization_failed)?;
+    Ok(JsonResponse(ApiResponse::success(report)))
+}
+
+/// Define (or replace) a materialized view handler
+pub async fn define_view(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+    Json(request): Json<ViewRequest>,
+) -> HandlerResult<ViewStatus> {
+    let mut view = materialized_view(&name, &request.query, &request.graph)?;
+    if let Some(secs) = request.refresh_interval_secs {
+        view = view.with_refresh_interval(secs);
+    }
+    let engine = state.writer_for(&principal)?;
+    let status = state.views.define(principal.tenant, engine, view).await.map_err(materialization_failed)?;
+    Ok(JsonResponse(ApiResponse::success(status)))
+}
+
+/// List materialized views handler
+pub async fn list_views(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+) -> JsonResponse<ApiResponse<Vec<ViewStatus>>> {
+    JsonResponse(ApiResponse::success(state.views.list(&principal.tenant)))
+}
+
+/// Refresh a materialized view now handler
+pub async fn refresh_view(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+) -> HandlerResult<ViewStatus> {
+    let engine = state.writer_for(&principal)?;
+    match state.views.refresh(&principal.tenant, &name, &engine).await {
+        Some(Ok(status)) => Ok(JsonResponse(ApiResponse::success(status))),
+        Some(Err(e)) => Err(materialization_failed(e)),
+        None => Err(view_not_found(&name)),
+    }
+}
+
+/// Remove a materialized view and its graph handler
+pub async fn delete_view(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+) -> HandlerResult<ViewStatus> {
+    let engine = state.reader_for(&principal)?;
+    match state.views.remove(&principal.tenant, &name, &engine).await {
+        Some(status) => Ok(JsonResponse(ApiResponse::success(status))),
+        None => Err(view_not_found(&name)),
+    }
+}
+
+/// List sensor health handler
+pub async fn list_sensors(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<SensorHealthParams>,
+) -> JsonResponse<ApiResponse<SensorHealthResponse>> {
+    let stale_after_secs = params.stale_after_secs.unwrap_or(15 * 60);
+    let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+
+    let mut sensors = match state.existing_reasoner(&principal) {
+        Some(reasoner) => reasoner.get_graph_store().await.read().await.sensor_registry().health(now, stale_after_secs * 1000),
+        None => Vec::new(),
+    };
+    let stale_count = sensors.iter().filter(|s| s.status == fukurow_store::SensorStatus::Stale).count();
+    if params.stale_only {
+        sensors.retain(|s| s.status == fukurow_store::SensorStatus::Stale);
+    }
+
+    let count = sensors.len();
+    JsonResponse(ApiResponse::success(SensorHealthResponse {
+        sensors,
         count,
+        stale_count,
+        stale_after_secs,
+    }))
+}
+
+/// Store consistency handler, with the axioms responsible for an inconsistency
+///
+/// 公理を絞り込むため検査を何度も繰り返す。ブロッキングスレッドで実行する
+pub async fn check_consistency(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<ConsistencyParams>,
+) -> HandlerResult<fukurow_engine::OwlConsistencyReport> {
+    let engine = state.reader_for(&principal)?;
+    let profile = params.profile.unwrap_or(fukurow_engine::ReasoningProfile::OwlLite);
+    tokio::task::spawn_blocking(move || engine.check_consistency(profile))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|report| report.map_err(|e| e.to_string()))
+        .map(|report| JsonResponse(ApiResponse::success(report)))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, JsonResponse(ApiResponse::error(e))))
+}
+
+/// Ontology vocabulary lookup handler (auto-completion metadata)
+pub async fn ontology_terms(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<OntologyTermsParams>,
+) -> HandlerResult<OntologyTermsResponse> {
+    let kind = match params.kind.as_deref().map(str::parse::<fukurow_store::TermKind>).transpose() {
+        Ok(kind) => kind,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, JsonResponse(ApiResponse::error(e)))),
     };
 
-    Ok(JsonResponse(ApiResponse::success(response)))
+    let store = state.reader_for(&principal)?.get_graph_store().await;
+    let graph_store = store.read().await;
+
+    let mut terms = fukurow_store::search_terms(graph_store.ontology_terms(), params.q.as_deref(), kind);
+    let total = terms.len();
+    terms.truncate(params.limit.unwrap_or(50));
+
+    Ok(JsonResponse(ApiResponse::success(OntologyTermsResponse {
+        count: terms.len(),
+        terms,
+        total,
+    })))
+}
+
+/// Ontology-aware auto-completion handler for rule and query authoring
+///
+/// クエリ用レプリカを読むため、推論や取り込みの最中でも待たずに返る
+pub async fn complete_ontology_terms(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Query(params): Query<CompletionParams>,
+) -> HandlerResult<CompletionResponse> {
+    let kind = match params.kind.as_deref().map(str::parse::<fukurow_store::TermKind>).transpose() {
+        Ok(kind) => kind,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, JsonResponse(ApiResponse::error(e)))),
+    };
+
+    let graph_store = state.reader_for(&principal)?.query_view();
+    let mut completions = fukurow_store::complete_terms(graph_store.ontology_terms(), &params.q, &state.prefixes, kind);
+    let total = completions.len();
+    completions.truncate(params.limit.unwrap_or(20));
+
+    Ok(JsonResponse(ApiResponse::success(CompletionResponse {
+        count: completions.len(),
+        completions,
+        total,
+    })))
+}
+
+/// Ontology version upload handler
+///
+/// 版を登録するだけで、有効化は `/ontologies/activate` で行う
+pub async fn upload_ontology(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Json(request): Json<UploadOntologyRequest>,
+) -> HandlerResult<fukurow_engine::OntologyVersion> {
+    let source = format!("ontology:{}@{}", request.iri, request.version);
+    let triples = match fukurow_store::read_dataset(request.content.as_bytes(), request.format, &source) {
+        Ok(stored) => stored.into_iter().map(|stored| stored.triple.into()).collect(),
+        Err(e) => {
+            let error_response = ApiResponse::error(format!("Invalid ontology: {}", e));
+            return Err((StatusCode::BAD_REQUEST, JsonResponse(error_response)));
+        }
+    };
+
+    state.writer_for(&principal)?
+        .register_ontology(&request.iri, &request.version, triples)
+        .map(|version| JsonResponse(ApiResponse::success(version)))
+        .map_err(ontology_error_response)
+}
+
+/// Registered ontology versions handler
+pub async fn list_ontologies(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+) -> JsonResponse<ApiResponse<OntologyVersionsResponse>> {
+    let versions = state.existing_reasoner(&principal).map(|reasoner| reasoner.ontology_versions()).unwrap_or_default();
+    JsonResponse(ApiResponse::success(OntologyVersionsResponse { count: versions.len(), versions }))
+}
+
+/// Ontology consistency check handler
+pub async fn validate_ontology(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Json(request): Json<OntologyVersionRequest>,
+) -> HandlerResult<fukurow_engine::OntologyValidation> {
+    state.reader_for(&principal)?
+        .validate_ontology(&request.iri, &request.version).await
+        .map(|validation| JsonResponse(ApiResponse::success(validation)))
+        .map_err(ontology_error_response)
+}
+
+/// Ontology activation handler
+///
+/// 不整合な版は 409 で拒否し、有効な版とストアはそのまま残る
+pub async fn activate_ontology(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Json(request): Json<OntologyVersionRequest>,
+) -> HandlerResult<fukurow_engine::OntologySwap> {
+    state.writer_for(&principal)?
+        .activate_ontology(&request.iri, &request.version).await
+        .map(|swap| JsonResponse(ApiResponse::success(swap)))
+        .map_err(ontology_error_response)
+}
+
+fn ontology_error_response(err: fukurow_engine::ReasonerError) -> (StatusCode, JsonResponse<ApiResponse<String>>) {
+    use fukurow_engine::OntologyError;
+
+    let status = match &err {
+        fukurow_engine::ReasonerError::OntologyError(e) => match e {
+            OntologyError::UnknownVersion { .. } => StatusCode::NOT_FOUND,
+            OntologyError::AlreadyRegistered { .. } | OntologyError::AlreadyActive { .. } | OntologyError::Inconsistent { .. } => StatusCode::CONFLICT,
+            OntologyError::Empty { .. } => StatusCode::BAD_REQUEST,
+        },
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, JsonResponse(ApiResponse::error(err.to_string())))
+}
+
+/// MITRE ATT&CK coverage handler
+///
+/// 有効なルールが宣言した技術 ID をタクソノミーと突き合わせる。
+/// ストアに ATT&CK バンドルが読み込まれていなければ同梱のリリースを使う
+pub async fn attack_coverage(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+) -> HandlerResult<fukurow_domain_cyber::AttackCoverage> {
+    let reasoner = state.reader_for(&principal)?;
+    let mappings = reasoner.rule_registry().attack_technique_mappings();
+
+    let store = reasoner.get_graph_store().await;
+    let mut taxonomy = fukurow_domain_cyber::AttackTaxonomy::from_store(&*store.read().await);
+    if taxonomy.techniques().next().is_none() {
+        taxonomy = fukurow_domain_cyber::AttackTaxonomy::bundled();
+    }
+
+    Ok(JsonResponse(ApiResponse::success(taxonomy.coverage(&mappings))))
 }
 
 /// Get statistics handler
-pub async fn get_stats(Extension(state): Extension<Arc<AppState>>) -> JsonResponse<ApiResponse<StatsResponse>> {
+///
+/// 呼び出し元テナントのストアのリビジョンを ETag にする (リビジョンが同じ間は 304 を返す)
+pub async fn get_stats(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    headers: HeaderMap,
+) -> Response {
+    let reasoner = match state.reader_for(&principal) {
+        Ok(reasoner) => reasoner,
+        Err(not_found) => return not_found.into_response(),
+    };
+    let validators = Validators::for_store(&reasoner.query_view(), &principal.tenant, &[Some("stats")]);
+    if let Some(not_modified) = state.http_cache.not_modified(&headers, &validators) {
+        return not_modified;
+    }
     let uptime = state.start_time.elapsed();
 
     // TODO: Get actual statistics from reasoner
@@ -135,29 +1221,122 @@ pub async fn get_stats(Extension(state): Extension<Arc<AppState>>) -> JsonRespon
         memory_usage_mb: None, // TODO: Implement memory usage tracking
     };
 
-    JsonResponse(ApiResponse::success(response))
+    state.http_cache.with_validators(JsonResponse(ApiResponse::success(response)), &validators)
 }
 
 /// Reset reasoner state handler
 pub async fn reset_reasoner(
     Extension(_state): Extension<Arc<AppState>>,
-) -> Result<JsonResponse<ApiResponse<String>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+) -> HandlerResult<String> {
     // TODO: Implement reset functionality - requires mutable access to reasoner
     let error_response = ApiResponse::error("Reset functionality not yet implemented".to_string());
     Err((StatusCode::NOT_IMPLEMENTED, JsonResponse(error_response)))
 }
 
+/// Drain status handler
+pub async fn drain_status(Extension(state): Extension<Arc<AppState>>) -> JsonResponse<ApiResponse<DrainStatus>> {
+    JsonResponse(ApiResponse::success(state.drain.status(&state)))
+}
+
+/// Start draining: reject new requests, then shut down once running work has settled
+pub async fn start_drain(Extension(state): Extension<Arc<AppState>>) -> (StatusCode, JsonResponse<ApiResponse<DrainStatus>>) {
+    if state.drain.begin() {
+        tracing::info!("Drain requested through the API");
+    }
+    (StatusCode::ACCEPTED, JsonResponse(ApiResponse::success(state.drain.status(&state))))
+}
+
 /// Add custom rule handler
 pub async fn add_rule(
     Extension(_state): Extension<Arc<AppState>>,
     Json(_request): Json<AddRuleRequest>,
-) -> Result<JsonResponse<ApiResponse<String>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+) -> HandlerResult<String> {
     // Note: This would require mutable access to reasoner, which needs design consideration
     // For now, return not implemented
     let error_response = ApiResponse::error("Adding custom rules not yet implemented".to_string());
     Err((StatusCode::NOT_IMPLEMENTED, JsonResponse(error_response)))
 }
 
+/// Rule list handler
+pub async fn list_rules(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+) -> JsonResponse<ApiResponse<RulesResponse>> {
+    let rules = state.existing_reasoner(&principal).map(|reasoner| reasoner.rule_registry().rules()).unwrap_or_default();
+    let count = rules.len();
+    JsonResponse(ApiResponse::success(RulesResponse { rules, count }))
+}
+
+/// Single rule handler
+pub async fn get_rule(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+) -> HandlerResult<fukurow_rules::RuleInfo> {
+    state.reader_for(&principal)?.rule_registry().rule(&name)
+        .map(|info| JsonResponse(ApiResponse::success(info)))
+        .ok_or_else(|| rule_error_response(fukurow_rules::RuleError::UnknownRule { name }))
+}
+
+/// Rule enable handler
+pub async fn enable_rule(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+) -> HandlerResult<fukurow_rules::RuleInfo> {
+    set_rule_enabled(&state, &principal, &name, true)
+}
+
+/// Rule disable handler
+///
+/// 無効にしたルールは推論・ATT&CK カバレッジの対象外になる (再起動までの設定)
+pub async fn disable_rule(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+) -> HandlerResult<fukurow_rules::RuleInfo> {
+    set_rule_enabled(&state, &principal, &name, false)
+}
+
+fn set_rule_enabled(
+    state: &AppState,
+    principal: &Principal,
+    name: &str,
+    enabled: bool,
+) -> HandlerResult<fukurow_rules::RuleInfo> {
+    let info = state.writer_for(principal)?.rule_registry()
+        .set_enabled(name, enabled)
+        .map_err(rule_error_response)?;
+    tracing::info!(rule = %name, enabled, principal = %principal.id, "Rule toggled");
+    Ok(JsonResponse(ApiResponse::success(info)))
+}
+
+/// Rule dry-run handler
+///
+/// ストアにも統計にも書き込まず、ルールが返す結果だけを返す。無効なルールも評価できる
+pub async fn test_rule(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+    Path(name): Path<String>,
+    Json(request): Json<RuleTestRequest>,
+) -> HandlerResult<fukurow_rules::RuleDryRun> {
+    let reasoner = state.reader_for(&principal)?;
+    if reasoner.rule_registry().rule(&name).is_none() {
+        return Err(rule_error_response(fukurow_rules::RuleError::UnknownRule { name }));
+    }
+    reasoner.dry_run_rule(&name, request.event.as_ref()).await
+        .map(|dry_run| JsonResponse(ApiResponse::success(dry_run)))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, JsonResponse(ApiResponse::error(e.to_string()))))
+}
+
+fn rule_error_response(err: fukurow_rules::RuleError) -> (StatusCode, JsonResponse<ApiResponse<String>>) {
+    let status = match &err {
+        fukurow_rules::RuleError::UnknownRule { .. } => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, JsonResponse(ApiResponse::error(err.to_string())))
+}
+
 /// Get threat intelligence info handler
 pub async fn get_threat_intel(
     Extension(state): Extension<Arc<AppState>>,
@@ -178,7 +1357,7 @@ pub async fn get_threat_intel(
 /// Export threat indicators handler
 pub async fn export_threat_indicators(
     Extension(state): Extension<Arc<AppState>>,
-) -> Result<JsonResponse<ApiResponse<String>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+) -> HandlerResult<String> {
     let threat_processor = state.threat_processor.read().await;
 
     match threat_processor.export_indicators() {
@@ -196,7 +1375,7 @@ pub async fn export_threat_indicators(
 pub async fn import_threat_indicators(
     Extension(state): Extension<Arc<AppState>>,
     Json(json_data): Json<String>,
-) -> Result<JsonResponse<ApiResponse<String>>, (StatusCode, JsonResponse<ApiResponse<String>>)> {
+) -> HandlerResult<String> {
     let mut threat_processor = state.threat_processor.write().await;
 
     match threat_processor.import_indicators(&json_data) {
@@ -223,8 +1402,15 @@ pub async fn monitoring_health_detailed(Extension(state): Extension<Arc<AppState
     JsonResponse(checks)
 }
 
-/// Monitoring: system metrics
-pub async fn monitoring_metrics(Extension(state): Extension<Arc<AppState>>) -> JsonResponse<SystemMetrics> {
-    let metrics = state.monitoring.get_metrics().await;
-    JsonResponse(metrics)
+/// Monitoring: system metrics and store access statistics
+pub async fn monitoring_metrics(
+    Extension(state): Extension<Arc<AppState>>,
+    AuthenticatedPrincipal(principal): AuthenticatedPrincipal,
+) -> JsonResponse<MetricsResponse> {
+    let system = state.monitoring.get_metrics().await;
+    let store_access = match state.existing_reasoner(&principal) {
+        Some(reasoner) => reasoner.get_graph_store().await.read().await.access_statistics(),
+        None => Default::default(),
+    };
+    JsonResponse(MetricsResponse { system, store_access })
 }
@@ -0,0 +1,355 @@
+//! Asynchronous reasoning jobs
+//!
+//! 大きなストアの推論は HTTP のタイムアウトを超えることがあるため、`POST /reason/async` で
+//! ジョブとして受け付け、`GET /jobs/:id` で状態・進捗・途中結果を返す。
+//! 同時に実行するジョブ数はセマフォで制限し、未完了のジョブが上限に達したら受け付けない
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use fukurow_core::model::SecurityAction;
+use fukurow_engine::{ReasonerEngine, ReasoningProfile, ReasoningStage, StageProgress, TenantScheduler};
+use fukurow_store::TenantId;
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+/// Default number of jobs reasoning at the same time
+pub const DEFAULT_MAX_CONCURRENT_JOBS: usize = 2;
+
+/// Default number of queued and running jobs accepted before submissions are rejected
+pub const DEFAULT_MAX_PENDING_JOBS: usize = 64;
+
+/// Default number of finished jobs kept for polling
+pub const DEFAULT_RETAINED_JOBS: usize = 256;
+
+/// How often `wait_idle` checks for unfinished jobs
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Lifecycle state of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for a free worker
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed)
+    }
+}
+
+/// Stage progress of a running job
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobProgress {
+    pub completed_stages: usize,
+    pub total_stages: usize,
+    /// Last finished stage
+    pub last_stage: Option<ReasoningStage>,
+    pub inferred_triples: usize,
+}
+
+/// Reasoning job as returned by `GET /jobs/:id`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReasoningJob {
+    pub id: String,
+    #[serde(skip)]
+    pub tenant: TenantId,
+    pub status: JobStatus,
+    pub profile: Option<ReasoningProfile>,
+    pub submitted_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub progress: JobProgress,
+    /// Actions proposed so far while running; every action once completed
+    pub actions: Vec<SecurityAction>,
+    /// Correlation IDs index-aligned with `actions` (completed jobs only)
+    pub correlation_ids: Vec<Vec<String>>,
+    pub execution_time_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Job submission errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum JobError {
+    #[error("Too many pending reasoning jobs (limit {limit})")]
+    QueueFull { limit: usize },
+}
+
+#[derive(Default)]
+struct JobTable {
+    jobs: HashMap<String, ReasoningJob>,
+    /// Submission order, for evicting the oldest finished jobs
+    order: VecDeque<String>,
+}
+
+/// Runs reasoning jobs on a bounded number of tokio tasks
+#[derive(Clone)]
+pub struct JobManager {
+    table: Arc<Mutex<JobTable>>,
+    workers: Arc<Semaphore>,
+    /// Per-tenant capacity shared with synchronous reasoning
+    scheduler: Option<Arc<TenantScheduler>>,
+    max_pending: usize,
+    retained: usize,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_JOBS)
+    }
+}
+
+impl JobManager {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            table: Arc::default(),
+            workers: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            scheduler: None,
+            max_pending: DEFAULT_MAX_PENDING_JOBS,
+            retained: DEFAULT_RETAINED_JOBS,
+        }
+    }
+
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.max(1);
+        self
+    }
+
+    pub fn with_retained(mut self, retained: usize) -> Self {
+        self.retained = retained;
+        self
+    }
+
+    /// Run each job inside its tenant's pool of `scheduler`
+    pub fn with_scheduler(mut self, scheduler: Arc<TenantScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Queue reasoning on `engine` and return the queued job
+    ///
+    /// `on_complete` は成功したジョブの最終状態で一度だけ呼ばれる (結果の配信に使う)
+    pub fn submit<F>(&self, tenant: TenantId, engine: Arc<ReasonerEngine>, profile: Option<ReasoningProfile>, on_complete: F) -> Result<ReasoningJob, JobError>
+    where
+        F: FnOnce(&ReasoningJob) + Send + 'static,
+    {
+        let job = ReasoningJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            tenant,
+            status: JobStatus::Queued,
+            profile,
+            submitted_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            progress: JobProgress::default(),
+            actions: Vec::new(),
+            correlation_ids: Vec::new(),
+            execution_time_ms: None,
+            error: None,
+        };
+        {
+            let mut table = self.table.lock().unwrap();
+            let pending = table.jobs.values().filter(|job| !job.status.is_finished()).count();
+            if pending >= self.max_pending {
+                return Err(JobError::QueueFull { limit: self.max_pending });
+            }
+            table.jobs.insert(job.id.clone(), job.clone());
+            table.order.push_back(job.id.clone());
+            self.evict_finished(&mut table);
+        }
+
+        let manager = self.clone();
+        let id = job.id.clone();
+        let job_tenant = job.tenant.clone();
+        tokio::spawn(async move {
+            // テナント枠を先に取り、1 テナントのバーストがワーカーを占有しないようにする
+            let _tenant_permit = match &manager.scheduler {
+                Some(scheduler) => match scheduler.acquire(job_tenant.as_str()).await {
+                    Ok(permit) => Some(permit),
+                    Err(e) => {
+                        manager.update(&id, |job| {
+                            job.status = JobStatus::Failed;
+                            job.finished_at = Some(Utc::now());
+                            job.error = Some(e.to_string());
+                        });
+                        return;
+                    }
+                },
+                None => None,
+            };
+            let Ok(_permit) = manager.workers.clone().acquire_owned().await else { return };
+            manager.update(&id, |job| {
+                job.status = JobStatus::Running;
+                job.started_at = Some(Utc::now());
+            });
+
+            let start = Instant::now();
+            let observer = |progress: &StageProgress| manager.update(&id, |job| {
+                job.progress = JobProgress {
+                    completed_stages: progress.completed_stages,
+                    total_stages: progress.total_stages,
+                    last_stage: Some(progress.stage),
+                    inferred_triples: progress.inferred_triples,
+                };
+                job.actions = progress.actions.clone();
+            });
+            let outcome = engine.reason_with_progress(profile, &observer).await;
+            let execution_time_ms = start.elapsed().as_millis() as u64;
+
+            manager.update(&id, |job| {
+                job.finished_at = Some(Utc::now());
+                job.execution_time_ms = Some(execution_time_ms);
+                match outcome {
+                    Ok(correlated) => {
+                        job.status = JobStatus::Completed;
+                        job.correlation_ids = correlated.iter().map(|c| c.correlation_ids.clone()).collect();
+                        job.actions = correlated.into_iter().map(|c| c.action).collect();
+                    }
+                    Err(e) => {
+                        job.status = JobStatus::Failed;
+                        job.error = Some(e.to_string());
+                    }
+                }
+            });
+
+            let finished = manager.table.lock().unwrap().jobs.get(&id).cloned();
+            if let Some(job) = finished.filter(|job| job.status == JobStatus::Completed) {
+                on_complete(&job);
+            }
+        });
+
+        Ok(job)
+    }
+
+    /// Job `id` if it belongs to `tenant`
+    pub fn get(&self, tenant: &TenantId, id: &str) -> Option<ReasoningJob> {
+        self.table.lock().unwrap().jobs.get(id).filter(|job| &job.tenant == tenant).cloned()
+    }
+
+    /// Queued and running jobs of every tenant
+    pub fn pending(&self) -> usize {
+        self.table.lock().unwrap().jobs.values().filter(|job| !job.status.is_finished()).count()
+    }
+
+    /// Wait until every queued and running job has finished, up to `timeout`
+    ///
+    /// 期限内に終わった場合は `true`。期限切れでもジョブは中断しない
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.pending() > 0 {
+            if tokio::time::Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+        }
+        true
+    }
+
+    fn update(&self, id: &str, apply: impl FnOnce(&mut ReasoningJob)) {
+        if let Some(job) = self.table.lock().unwrap().jobs.get_mut(id) {
+            apply(job);
+        }
+    }
+
+    /// Drop the oldest finished jobs beyond the retention limit
+    fn evict_finished(&self, table: &mut JobTable) {
+        let mut finished = table.jobs.values().filter(|job| job.status.is_finished()).count();
+        let JobTable { jobs, order } = table;
+        order.retain(|id| {
+            if finished <= self.retained || !jobs.get(id).is_some_and(|job| job.status.is_finished()) {
+                return true;
+            }
+            jobs.remove(id);
+            finished -= 1;
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn wait_until_finished(manager: &JobManager, tenant: &TenantId, id: &str) -> ReasoningJob {
+        for _ in 0..200 {
+            let job = manager.get(tenant, id).unwrap();
+            if job.status.is_finished() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {} did not finish", id);
+    }
+
+    #[tokio::test]
+    async fn test_job_runs_in_background_and_reports_progress() {
+        let manager = JobManager::new(1);
+        let tenant = TenantId::default();
+        let engine = Arc::new(ReasonerEngine::new());
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        let job = manager.submit(tenant.clone(), engine, Some(ReasoningProfile::Rdfs), move |job| {
+            let _ = sender.send(job.id.clone());
+        }).unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+
+        let finished = wait_until_finished(&manager, &tenant, &job.id).await;
+        assert_eq!(finished.status, JobStatus::Completed);
+        assert_eq!(finished.progress.completed_stages, finished.progress.total_stages);
+        assert!(finished.started_at.is_some() && finished.execution_time_ms.is_some());
+        assert_eq!(receiver.await.unwrap(), job.id);
+
+        // 他テナントからは見えない
+        assert!(manager.get(&TenantId::new("other").unwrap(), &job.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pending_limit_and_retention() {
+        let manager = JobManager::new(1).with_max_pending(1).with_retained(1);
+        let tenant = TenantId::default();
+        let engine = Arc::new(ReasonerEngine::new());
+
+        let first = manager.submit(tenant.clone(), Arc::clone(&engine), None, |_| {}).unwrap();
+        assert_eq!(
+            manager.submit(tenant.clone(), Arc::clone(&engine), None, |_| {}).unwrap_err(),
+            JobError::QueueFull { limit: 1 },
+        );
+        wait_until_finished(&manager, &tenant, &first.id).await;
+
+        let second = manager.submit(tenant.clone(), Arc::clone(&engine), None, |_| {}).unwrap();
+        wait_until_finished(&manager, &tenant, &second.id).await;
+        let third = manager.submit(tenant.clone(), engine, None, |_| {}).unwrap();
+        wait_until_finished(&manager, &tenant, &third.id).await;
+        assert!(manager.get(&tenant, &first.id).is_none());
+        assert!(manager.get(&tenant, &third.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_respect_tenant_quota() {
+        use fukurow_engine::{TenantIsolationConfig, TenantPoolConfig};
+
+        let config = TenantIsolationConfig::default()
+            .with_tenant("busy", TenantPoolConfig { max_concurrency: 1, max_queue: 0 });
+        let scheduler = Arc::new(TenantScheduler::new(config));
+        let manager = JobManager::new(2).with_scheduler(Arc::clone(&scheduler));
+        let busy = TenantId::new("busy").unwrap();
+        let engine = Arc::new(ReasonerEngine::new());
+
+        // 同期推論が枠を使っている間はキューに入れず失敗させる
+        let held = scheduler.acquire("busy").await.unwrap();
+        let job = manager.submit(busy.clone(), Arc::clone(&engine), None, |_| {}).unwrap();
+        let failed = wait_until_finished(&manager, &busy, &job.id).await;
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert!(failed.error.unwrap().contains("Queue quota exceeded"));
+        drop(held);
+
+        let job = manager.submit(busy.clone(), engine, None, |_| {}).unwrap();
+        assert_eq!(wait_until_finished(&manager, &busy, &job.id).await.status, JobStatus::Completed);
+    }
+}
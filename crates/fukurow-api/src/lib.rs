@@ -9,11 +9,31 @@ pub mod handlers;
 pub mod models;
 pub mod server;
 pub mod siem_integration;
+pub mod push;
+pub mod pagination;
+pub mod batch;
+pub mod auth;
+pub mod webhook;
+pub mod jobs;
+pub mod request_trace;
+pub mod views;
+pub mod drain;
+pub mod caching;
+pub mod anomaly;
 pub use routes::*;
 pub use handlers::*;
 pub use models::*;
 pub use server::*;
 pub use siem_integration::*;
+pub use push::*;
+pub use auth::*;
+pub use webhook::*;
+pub use jobs::*;
+pub use views::*;
+pub use drain::*;
+pub use caching::*;
+pub use anomaly::*;
+pub use request_trace::{current_request_id, trace_request};
 
 #[cfg(test)]
 mod tests {
@@ -56,7 +76,7 @@ mod tests {
                 timestamp: 1640995200,
             };
 
-            let request = SubmitEventRequest { event: event.clone() };
+            let request = SubmitEventRequest { event: event.clone(), source: None, event_id: None };
 
             match request.event {
                 CyberEvent::NetworkConnection { source_ip, .. } => {
@@ -70,13 +90,19 @@ mod tests {
         fn test_reasoning_request() {
             let request = ReasoningRequest {
                 include_details: Some(true),
+                profile: None,
             };
             assert_eq!(request.include_details, Some(true));
 
             let request2 = ReasoningRequest {
                 include_details: None,
+                profile: None,
             };
             assert_eq!(request2.include_details, None);
+
+            let request3: ReasoningRequest = serde_json::from_str(r#"{"profile": "owl-lite"}"#).unwrap();
+            assert_eq!(request3.profile, Some(fukurow_engine::ReasoningProfile::OwlLite));
+            assert!(serde_json::from_str::<ReasoningRequest>(r#"{"profile": "owl-full"}"#).is_err());
         }
 
         #[test]
@@ -93,6 +119,7 @@ mod tests {
                 actions: actions.clone(),
                 execution_time_ms: 150,
                 event_count: 5,
+                correlation_ids: vec![vec![]],
             };
 
             assert_eq!(response.actions.len(), 1);
@@ -107,6 +134,8 @@ mod tests {
                 predicate: Some("predicate1".to_string()),
                 object: None,
                 graph_name: Some("default".to_string()),
+                limit: None,
+                cursor: None,
             };
 
             assert_eq!(request.subject, Some("subject1".to_string()));
@@ -128,12 +157,97 @@ mod tests {
             let response = GraphQueryResponse {
                 triples: triples.clone(),
                 count: 1,
+                next_cursor: None,
             };
 
             assert_eq!(response.triples.len(), 1);
             assert_eq!(response.count, 1);
         }
 
+        #[test]
+        fn test_sparql_query_response_serialization() {
+            let request: SparqlQueryRequest = serde_json::from_str(r#"{"query": "SELECT ?s WHERE { ?s ?p ?o }", "limit": 10}"#).unwrap();
+            assert_eq!(request.limit, Some(10));
+            assert!(request.cursor.is_none());
+
+            let row: fukurow_sparql::diff::ResultRow = [("s".to_string(), "http://example.org/a".to_string())].into_iter().collect();
+            let response = SparqlQueryResponse {
+                result: SparqlResultPage::Select { variables: vec!["s".to_string()], rows: vec![row] },
+                count: 1,
+                next_cursor: Some("abc".to_string()),
+                truncated: None,
+            };
+
+            let json = serde_json::to_value(&response).unwrap();
+            assert_eq!(json["kind"], "select");
+            assert_eq!(json["rows"][0]["s"], "http://example.org/a");
+            assert_eq!(json["next_cursor"], "abc");
+            assert!(json.get("truncated").is_none());
+        }
+
+        #[test]
+        fn test_sparql_request_limits_tighten_server_limits() {
+            let request: SparqlQueryRequest = serde_json::from_str(
+                r#"{"query": "SELECT ?s WHERE { ?s ?p ?o }", "limits": {"timeout_ms": 60000, "max_results": 50, "partial": true}}"#
+            ).unwrap();
+            let server = ServerConfig::default().query_limits;
+            let limits = request.limits.unwrap().apply(server);
+            assert_eq!(limits.max_execution_time, server.max_execution_time);
+            assert_eq!(limits.max_intermediate_bindings, Some(DEFAULT_MAX_INTERMEDIATE_BINDINGS));
+            assert_eq!(limits.max_results, Some(50));
+            assert!(limits.partial_results);
+        }
+
+        #[test]
+        fn test_sparql_batch_serialization() {
+            let request: SparqlBatchRequest = serde_json::from_str(
+                r#"{"queries": [{"id": "hosts", "query": "SELECT ?s WHERE { ?s ?p ?o }", "limit": 5}, {"query": "ASK { ?s ?p ?o }"}]}"#
+            ).unwrap();
+            assert_eq!(request.queries.len(), 2);
+            assert_eq!(request.queries[0].id.as_deref(), Some("hosts"));
+            assert_eq!(request.queries[0].request.limit, Some(5));
+            assert!(request.queries[1].id.is_none());
+
+            let response = SparqlBatchResponse {
+                results: vec![
+                    SparqlBatchResult {
+                        id: "hosts".to_string(),
+                        status: 200,
+                        response: Some(SparqlQueryResponse { result: SparqlResultPage::Ask { boolean: true }, count: 1, next_cursor: None, truncated: None }),
+                        error: None,
+                    },
+                    SparqlBatchResult { id: "1".to_string(), status: 400, response: None, error: Some("Invalid query".to_string()) },
+                ],
+                succeeded: 1,
+                failed: 1,
+            };
+            let json = serde_json::to_value(&response).unwrap();
+            assert_eq!(json["results"][0]["kind"], "ask");
+            assert_eq!(json["results"][0]["status"], 200);
+            assert!(json["results"][0].get("error").is_none());
+            assert_eq!(json["results"][1]["error"], "Invalid query");
+            assert!(json["results"][1].get("kind").is_none());
+        }
+
+        #[test]
+        fn test_query_diff_response_serialization() {
+            let response = QueryDiffResponse {
+                name: "admins".to_string(),
+                since: 1_000,
+                until: 2_000,
+                has_changes: true,
+                diff: fukurow_sparql::QueryDiff::Ask { before: false, after: true },
+            };
+
+            let json = serde_json::to_value(&response).unwrap();
+            assert_eq!(json["diff"]["kind"], "ask");
+            assert_eq!(json["has_changes"], true);
+
+            let params: QueryDiffParams = serde_json::from_str(r#"{"since_secs": 3600}"#).unwrap();
+            assert_eq!(params.since_secs, Some(3600));
+            assert!(params.since.is_none());
+        }
+
         #[test]
         fn test_health_response() {
             let response = HealthResponse {
@@ -219,6 +333,19 @@ mod tests {
                 host: "127.0.0.1".to_string(),
                 port: 8080,
                 max_connections: 50,
+                auth: AuthConfig::default(),
+                snapshot_dir: std::path::PathBuf::from("snapshots"),
+                webhooks: WebhookConfig::default(),
+                max_concurrent_jobs: 2,
+                validation: fukurow_core::validation::EventValidator::default(),
+                drain_timeout_secs: 10,
+                schedules: Vec::new(),
+                query_limits: fukurow_sparql::QueryLimits::default(),
+                prefixes: fukurow_core::prefix::PrefixMap::default(),
+                http_cache: crate::caching::HttpCacheConfig::default(),
+                tenant_isolation: fukurow_engine::TenantIsolationConfig::default(),
+                anomaly_window_secs: crate::anomaly::DEFAULT_ANOMALY_WINDOW_SECS,
+                audit_dir: None,
             };
 
             assert_eq!(config.host, "127.0.0.1");
@@ -232,6 +359,19 @@ mod tests {
                 host: "127.0.0.1".to_string(),
                 port: 8080,
                 max_connections: 50,
+                auth: AuthConfig::default(),
+                snapshot_dir: std::path::PathBuf::from("snapshots"),
+                webhooks: WebhookConfig::default(),
+                max_concurrent_jobs: 2,
+                validation: fukurow_core::validation::EventValidator::default(),
+                drain_timeout_secs: 10,
+                schedules: Vec::new(),
+                query_limits: fukurow_sparql::QueryLimits::default(),
+                prefixes: fukurow_core::prefix::PrefixMap::default(),
+                http_cache: crate::caching::HttpCacheConfig::default(),
+                tenant_isolation: fukurow_engine::TenantIsolationConfig::default(),
+                anomaly_window_secs: crate::anomaly::DEFAULT_ANOMALY_WINDOW_SECS,
+                audit_dir: None,
             };
 
             let monitoring = std::sync::Arc::new(fukurow_observability::DefaultHealthMonitor::new());
@@ -280,6 +420,92 @@ mod tests {
 
             assert_eq!(addr.to_string(), "0.0.0.0:3000");
         }
+
+        #[tokio::test]
+        async fn test_unauthenticated_callers_only_create_configured_tenants() {
+            use axum::body::Body;
+            use axum::http::{Request, StatusCode};
+            use tower::ServiceExt;
+
+            let config = ServerConfig {
+                tenant_isolation: fukurow_engine::TenantIsolationConfig::default()
+                    .with_tenant("acme", fukurow_engine::TenantPoolConfig::default()),
+                ..ServerConfig::default()
+            };
+            let server = ReasonerServer::with_config(config, std::sync::Arc::new(fukurow_observability::DefaultHealthMonitor::new()));
+            let request = |tenant: &str, method: &str, uri: &str, body: &str| Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(TENANT_HEADER, tenant)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let event = r#"{"event": {"type": "UserLogin", "data": {"user": "alice", "source_ip": "10.0.0.1", "success": true, "timestamp": 1700000000}}}"#;
+            let status = |request: Request<Body>| async { server.create_app().oneshot(request).await.unwrap().status() };
+
+            // 設定にないテナントは読み取りでも書き込みでも作らない
+            assert_eq!(status(request("globex", "GET", "/events/quarantine", "")).await, StatusCode::OK);
+            assert_eq!(status(request("globex", "GET", "/attack/coverage", "")).await, StatusCode::NOT_FOUND);
+            assert_eq!(status(request("globex", "POST", "/events", event)).await, StatusCode::NOT_FOUND);
+            assert!(server.tenants().get(&fukurow_store::TenantId::new("globex").unwrap()).is_none());
+
+            // 設定済みのテナントは最初の書き込みで作る
+            let acme = fukurow_store::TenantId::new("acme").unwrap();
+            assert_eq!(status(request("acme", "GET", "/attack/coverage", "")).await, StatusCode::NOT_FOUND);
+            assert_eq!(status(request("acme", "POST", "/events", event)).await, StatusCode::OK);
+            assert!(server.tenants().get(&acme).is_some());
+            assert_eq!(status(request("acme", "GET", "/attack/coverage", "")).await, StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn test_audit_log_survives_a_restart() {
+            use axum::body::Body;
+            use axum::http::{Request, StatusCode};
+            use tower::ServiceExt;
+
+            let audit_dir = std::env::temp_dir().join(format!("fukurow-api-audit-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&audit_dir);
+            let config = ServerConfig { audit_dir: Some(audit_dir.clone()), ..ServerConfig::default() };
+            let monitoring = || std::sync::Arc::new(fukurow_observability::DefaultHealthMonitor::new());
+            let audit_request = || Request::builder().uri("/audit?operation=insert").body(Body::empty()).unwrap();
+
+            let audited = |server: ReasonerServer| async move {
+                let response = server.create_app().oneshot(audit_request()).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()["data"]["entries"].clone()
+            };
+
+            let server = ReasonerServer::with_config(config.clone(), monitoring());
+            let event = r#"{"event": {"type": "UserLogin", "data": {"user": "alice", "source_ip": "10.0.0.1", "success": true, "timestamp": 1700000000}}}"#;
+            let request = Request::builder()
+                .method("POST")
+                .uri("/events")
+                .header("content-type", "application/json")
+                .body(Body::from(event))
+                .unwrap();
+            assert_eq!(server.create_app().oneshot(request).await.unwrap().status(), StatusCode::OK);
+            let before = audited(server).await;
+            assert!(!before.as_array().unwrap().is_empty());
+            assert!(audit_dir.join("default.db").exists());
+
+            // 再起動後も GET /audit は前のプロセスの書き込みを返す
+            let after = audited(ReasonerServer::with_config(config, monitoring())).await;
+            assert_eq!(after, before);
+            let _ = std::fs::remove_dir_all(&audit_dir);
+        }
+
+        #[tokio::test]
+        async fn test_tenant_engines_register_the_builtin_cyber_rules() {
+            use axum::body::Body;
+            use axum::http::{Request, StatusCode};
+            use tower::ServiceExt;
+
+            let monitoring = std::sync::Arc::new(fukurow_observability::DefaultHealthMonitor::new());
+            let app = ReasonerServer::with_config(ServerConfig::default(), monitoring).create_app();
+            let request = Request::builder().uri("/rules/stale_sensor_detection").body(Body::empty()).unwrap();
+            assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+        }
     }
 
     #[cfg(test)]
@@ -353,6 +579,7 @@ mod tests {
                 actions: actions.clone(),
                 execution_time_ms: 150,
                 event_count: 5,
+                correlation_ids: vec![vec![]],
             };
 
             assert_eq!(response.actions.len(), 1);
@@ -373,6 +600,7 @@ mod tests {
             let response = GraphQueryResponse {
                 triples: triples.clone(),
                 count: 1,
+                next_cursor: None,
             };
 
             assert_eq!(response.triples.len(), 1);
@@ -399,4 +627,215 @@ mod tests {
             assert_eq!(response.statistics.get("phishing"), Some(&5));
         }
     }
+
+    #[cfg(feature = "streaming")]
+    mod streaming_tests {
+        use super::*;
+        use axum::body::Body;
+        use fukurow_streaming::{EventStreamProcessor, ShutdownSignal, StreamError, StreamProcessor, StreamingConfig, StreamingEvent};
+        use std::sync::{Arc, Mutex};
+        use tower::ServiceExt;
+
+        #[derive(Clone, Default)]
+        struct Collector {
+            events: Arc<Mutex<Vec<StreamingEvent>>>,
+        }
+
+        #[async_trait::async_trait]
+        impl StreamProcessor for Collector {
+            async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+                self.events.lock().unwrap().push(event);
+                Ok(())
+            }
+
+            async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+                self.events.lock().unwrap().extend(events);
+                Ok(())
+            }
+
+            fn name(&self) -> &'static str {
+                "collector"
+            }
+
+            async fn health_check(&self) -> Result<(), StreamError> {
+                Ok(())
+            }
+        }
+
+        #[tokio::test]
+        async fn test_batch_ingest_forwards_events_to_stream() {
+            let collector = Collector::default();
+            let shutdown = ShutdownSignal::new();
+            let processor = EventStreamProcessor::new(collector.clone(), StreamingConfig::default())
+                .with_shutdown(shutdown.clone());
+            let mut server = ReasonerServer::new(Arc::new(fukurow_observability::DefaultHealthMonitor::new()));
+            server.set_event_sender(processor.event_sender());
+            let task = processor.start_processing().await.unwrap();
+
+            // 同じ event_id の 2 件目は重複として書き込まず、配信もしない
+            let item = r#"{"event": {"type": "UserLogin", "data": {"user": "alice", "source_ip": "10.0.0.1", "success": true, "timestamp": 1700000000}}, "source": "edr-7", "event_id": "evt-1"}"#;
+            let body = format!("[{},{}]", item, item);
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri("/events/batch")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            let response = server.create_app().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(report["data"]["accepted"], 1);
+            assert_eq!(report["data"]["duplicates"], 1);
+            assert_eq!(report["data"]["results"][1]["correlation_id"], "evt-1");
+
+            let engine = server.app_state().tenants.engine(&fukurow_store::TenantId::default());
+            assert_eq!(engine.pending_event_count(), 1);
+            let store = engine.get_graph_store().await;
+            assert_eq!(store.read().await.find_triples(None, Some("http://example.org/correlationId"), Some("evt-1")).len(), 1);
+
+            // シャットダウン後、バッファ済みのイベントがすべて処理される
+            shutdown.trigger();
+            task.await.unwrap();
+            let events = collector.events.lock().unwrap();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].event_type(), "security_event");
+        }
+    }
+
+    #[cfg(test)]
+    mod push_tests {
+        use super::*;
+        use fukurow_streaming::StreamingEvent;
+
+        fn anomaly(score: f64) -> StreamingEvent {
+            StreamingEvent::AnomalyDetected {
+                score,
+                threshold: 1.0,
+                metric: "login_attempts".to_string(),
+                timestamp: chrono::Utc::now(),
+                strategy: None,
+                contributions: Vec::new(),
+                entity: None,
+                window: None,
+            }
+        }
+
+        #[test]
+        fn test_push_filter_default_matches_reasoning_and_anomaly() {
+            let filter = PushFilter::default();
+            let reasoning = StreamingEvent::ReasoningResult {
+                actions: vec![],
+                execution_time_ms: 1,
+                event_count: 0,
+                timestamp: chrono::Utc::now(),
+                correlation_ids: Vec::new(),
+            };
+            let metrics = StreamingEvent::SystemMetrics {
+                cpu_usage: 1.0,
+                memory_usage: 1.0,
+                active_connections: 1,
+                timestamp: chrono::Utc::now(),
+            };
+
+            assert!(filter.matches(&reasoning));
+            assert!(filter.matches(&anomaly(0.5)));
+            assert!(!filter.matches(&metrics));
+        }
+
+        #[test]
+        fn test_push_filter_by_type_and_severity() {
+            let filter = PushFilter::from_query(Some("anomaly_detected"), Some("high")).unwrap();
+            assert!(!filter.matches(&anomaly(1.2)));
+            assert!(filter.matches(&anomaly(2.0)));
+
+            let alert = StreamingEvent::ReasoningResult {
+                actions: vec![SecurityAction::Alert {
+                    severity: "critical".to_string(),
+                    message: "test".to_string(),
+                    details: serde_json::json!({}),
+                }],
+                execution_time_ms: 1,
+                event_count: 1,
+                timestamp: chrono::Utc::now(),
+                correlation_ids: Vec::new(),
+            };
+            assert!(!filter.matches(&alert));
+            assert_eq!(PushSeverity::of_event(&alert), PushSeverity::Critical);
+
+            assert!(PushFilter::from_query(Some("system_metrics"), None).is_err());
+            assert!(PushFilter::from_query(None, Some("extreme")).is_err());
+        }
+
+        #[tokio::test]
+        async fn test_push_hub_delivers_to_subscribers() {
+            let hub = PushHub::new(16);
+            assert_eq!(hub.publish(anomaly(2.0)), 0);
+
+            let mut receiver = hub.subscribe();
+            assert_eq!(hub.subscriber_count(), 1);
+            assert_eq!(hub.publish(anomaly(2.0)), 1);
+
+            let event = receiver.recv().await.unwrap();
+            assert_eq!(event.event_type(), "anomaly_detected");
+        }
+
+        fn login(user: &str) -> CyberEvent {
+            CyberEvent::UserLogin { user: user.to_string(), source_ip: "10.0.0.1".to_string(), success: false, timestamp: 1_700_000_000 }
+        }
+
+        /// Ingest `count` events in each of the windows `start` ..; returns the start of the next window
+        fn fill_windows(monitor: &EventRateMonitor, tenant: &fukurow_store::TenantId, start: chrono::DateTime<chrono::Utc>, counts: &[usize]) -> chrono::DateTime<chrono::Utc> {
+            let mut window = start;
+            for &count in counts {
+                for i in 0..count {
+                    monitor.record(tenant, &login("alice"), window + chrono::Duration::milliseconds(i as i64));
+                }
+                window += chrono::Duration::seconds(60);
+            }
+            window
+        }
+
+        #[test]
+        fn test_event_rate_monitor_flags_a_burst_when_its_window_closes() {
+            let monitor = EventRateMonitor::new(std::time::Duration::from_secs(60));
+            let tenant = fukurow_store::TenantId::default();
+            let start = chrono::Utc::now() - chrono::Duration::hours(1);
+            let spike = fill_windows(&monitor, &tenant, start, &[5, 6, 5, 7, 5, 6, 5, 7, 5, 6, 5]);
+            assert!(monitor.record(&tenant, &login("bob"), spike).is_none());
+
+            // 突発的に増えたウィンドウは、次のウィンドウの最初のイベントで閉じて異常になる
+            let next = fill_windows(&monitor, &tenant, spike, &[499]);
+            let anomaly = monitor.record(&tenant, &login("alice"), next).unwrap();
+            match anomaly {
+                StreamingEvent::AnomalyDetected { metric, window, strategy, contributions, .. } => {
+                    assert_eq!(metric, "UserLogin");
+                    assert_eq!(window.unwrap().start, spike);
+                    assert_eq!(window.unwrap().end, next);
+                    assert!(strategy.is_some());
+                    assert!(contributions.iter().any(|c| c.is_anomaly));
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+
+            // 他のテナントの件数とは混ざらない
+            let other = fukurow_store::TenantId::new("acme").unwrap();
+            assert!(monitor.record(&other, &login("alice"), next).is_none());
+        }
+
+        #[tokio::test]
+        async fn test_ingested_anomalies_are_pushed_to_the_tenant() {
+            let server = ReasonerServer::new(std::sync::Arc::new(fukurow_observability::DefaultHealthMonitor::new()));
+            let state = server.app_state();
+            let tenant = fukurow_store::TenantId::default();
+            let start = chrono::Utc::now() - chrono::Duration::hours(1);
+            fill_windows(&state.event_rates, &tenant, start, &[5, 6, 5, 7, 5, 6, 5, 7, 5, 6, 5, 500]);
+
+            let mut receiver = server.push_hub().subscribe();
+            state.record_ingested(&Principal::anonymous(), &login("alice"));
+            let event = receiver.try_recv().unwrap();
+            assert_eq!(event.event_type(), "anomaly_detected");
+            assert!(PushSeverity::of_event(&event) >= PushSeverity::Medium);
+        }
+    }
 }
@@ -1,7 +1,10 @@
 //! API data models
+//!
+//! リクエスト・レスポンスの型はサーバーと `fukurow-client` で共有する
 
 use fukurow_core::model::{CyberEvent, SecurityAction};
-use fukurow_engine::ReasonerError;
+use fukurow_engine::{ReasonerError, ReasoningProfile};
+use fukurow_store::TenantId;
 use serde::{Deserialize, Serialize};
 
 /// API response wrapper
@@ -37,40 +40,461 @@ impl<T> ApiResponse<T> {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubmitEventRequest {
     pub event: CyberEvent,
+    /// Reporting sensor/agent identifier (tracked in the sensor registry)
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Sensor-assigned event ID; the same ID from another transport is dropped as a duplicate
+    #[serde(default)]
+    pub event_id: Option<String>,
+}
+
+/// Bulk ingestion parameters (`POST /events/batch`)
+#[derive(Debug, Default, Deserialize)]
+pub struct BatchIngestParams {
+    /// Default sensor identifier for items without their own `source`
+    pub source: Option<String>,
+    /// Body framing (`ndjson` or `json`); defaults to Content-Type / auto-detection
+    pub format: Option<String>,
+}
+
+/// Outcome of a single batch item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Accepted,
+    Rejected,
+    /// Failed validation and was stored in the quarantine graph
+    Quarantined,
+    /// Seen within the deduplication window and not ingested again
+    Duplicate,
+}
+
+/// Per-item result in a bulk ingestion report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    /// Zero-based position in the request body
+    pub index: usize,
+    pub status: BatchItemStatus,
+    pub error: Option<String>,
+    /// Triples inserted for this item
+    pub triples: usize,
+    /// Correlation ID of the event (the original one for duplicates)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// Bulk ingestion report
+#[derive(Debug, Serialize)]
+pub struct BatchIngestResponse {
+    pub received: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub quarantined: usize,
+    pub duplicates: usize,
+    pub triples: usize,
+    pub results: Vec<BatchItemResult>,
+    /// Framing error that stopped reading the body (items before it were processed)
+    pub error: Option<String>,
+    pub execution_time_ms: u64,
+}
+
+/// Message rejected by a webhook route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookItemError {
+    /// Zero-based position of the message in the request body
+    pub index: usize,
+    pub error: String,
+}
+
+/// Webhook ingestion report
+#[derive(Debug, Serialize)]
+pub struct WebhookIngestResponse {
+    pub route: String,
+    pub received: usize,
+    pub accepted: usize,
+    /// Accepted messages that were already seen within the deduplication window
+    pub duplicates: usize,
+    pub rejected: usize,
+    pub errors: Vec<WebhookItemError>,
+    /// Correlation IDs of the accepted messages, in body order
+    pub correlation_ids: Vec<String>,
+    /// Accepted messages stored in the quarantine graph instead of being ingested
+    pub quarantined: usize,
+    pub execution_time_ms: u64,
+}
+
+/// Events held in the quarantine graph
+#[derive(Debug, Serialize)]
+pub struct QuarantineResponse {
+    pub events: Vec<fukurow_engine::QuarantinedEvent>,
+    pub count: usize,
 }
 
 /// Reasoning request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ReasoningRequest {
     pub include_details: Option<bool>,
+    /// Reasoners to run: `none`, `rdfs`, `owl-lite` or `owl-dl` (engine defaults when omitted)
+    #[serde(default)]
+    pub profile: Option<ReasoningProfile>,
+}
+
+/// Asynchronous reasoning request (`POST /reason/async`)
+#[derive(Debug, Deserialize)]
+pub struct AsyncReasoningRequest {
+    /// Reasoners to run: `none`, `rdfs`, `owl-lite` or `owl-dl` (engine defaults when omitted)
+    #[serde(default)]
+    pub profile: Option<ReasoningProfile>,
+    /// Publish the result to `/events/stream` and the streaming producer when the job completes
+    #[serde(default = "default_publish_result")]
+    pub publish_result: bool,
+}
+
+fn default_publish_result() -> bool {
+    true
 }
 
 /// Reasoning response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ReasoningResponse {
     pub actions: Vec<SecurityAction>,
     pub execution_time_ms: u64,
     pub event_count: usize,
+    /// Correlation IDs of the originating events, index-aligned with `actions`
+    #[serde(default)]
+    pub correlation_ids: Vec<Vec<String>>,
 }
 
 /// Graph query request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GraphQueryRequest {
     pub subject: Option<String>,
     pub predicate: Option<String>,
     pub object: Option<String>,
     pub graph_name: Option<String>,
+    /// Maximum triples per page (default 1000, max 10000)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Continuation token from a previous response's `next_cursor`
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// Event push subscription query (`GET /events/stream`)
+#[derive(Debug, Default, Deserialize)]
+pub struct EventStreamQuery {
+    /// Comma separated event types (`reasoning_result`, `anomaly_detected`)
+    pub types: Option<String>,
+    /// Minimum severity (`low`, `medium`, `high`, `critical`)
+    pub min_severity: Option<String>,
 }
 
 /// Graph query response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GraphQueryResponse {
     pub triples: Vec<fukurow_core::model::Triple>,
+    /// Number of triples in this page
     pub count: usize,
+    /// Token for the next page (`None` on the last page)
+    pub next_cursor: Option<String>,
 }
 
-/// Health check response
+/// Audit log query parameters (`GET /audit`)
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQueryParams {
+    /// Inclusive lower bound (Unix timestamp in milliseconds)
+    pub from: Option<u64>,
+    /// Inclusive upper bound (Unix timestamp in milliseconds)
+    pub to: Option<u64>,
+    pub actor: Option<String>,
+    /// Operation kind (`insert`, `delete`, `clear`, `inference`, `query`)
+    pub operation: Option<String>,
+    /// Graph in display form (`default`, `named:events`, ...)
+    pub graph: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Audit log query response
 #[derive(Debug, Serialize)]
+pub struct AuditQueryResponse {
+    pub entries: Vec<fukurow_store::AuditEntry>,
+    pub count: usize,
+}
+
+/// Snapshot export parameters (`POST /snapshot`)
+#[derive(Debug, Default, Deserialize)]
+pub struct SnapshotParams {
+    /// `nquads` (default) or `json`
+    pub format: Option<fukurow_store::SnapshotFormat>,
+}
+
+/// Sensor health query parameters (`GET /sensors`)
+#[derive(Debug, Default, Deserialize)]
+pub struct SensorHealthParams {
+    /// Silence threshold in seconds (default: 900)
+    pub stale_after_secs: Option<u64>,
+    /// Only return stale sensors
+    #[serde(default)]
+    pub stale_only: bool,
+}
+
+/// Sensor health response
+#[derive(Debug, Serialize)]
+pub struct SensorHealthResponse {
+    pub sensors: Vec<fukurow_store::SensorHealth>,
+    pub count: usize,
+    pub stale_count: usize,
+    pub stale_after_secs: u64,
+}
+
+/// Ontology term lookup parameters (`GET /ontology/terms`)
+#[derive(Debug, Default, Deserialize)]
+pub struct OntologyTermsParams {
+    /// Search text matched against local names, labels and IRIs
+    pub q: Option<String>,
+    /// Term kind (`class`, `object_property`, `datatype_property`, `annotation_property`, `property`)
+    pub kind: Option<String>,
+    /// Maximum number of terms (default: 50)
+    pub limit: Option<usize>,
+}
+
+/// Ontology term lookup response
+#[derive(Debug, Serialize)]
+pub struct OntologyTermsResponse {
+    pub terms: Vec<fukurow_store::OntologyTerm>,
+    pub count: usize,
+    /// Matching terms before `limit` was applied
+    pub total: usize,
+}
+
+/// Auto-completion parameters (`GET /ontology/complete`)
+#[derive(Debug, Default, Deserialize)]
+pub struct CompletionParams {
+    /// Typed text: a local name or label prefix, `prefix:partial` or a partial IRI
+    pub q: String,
+    /// Term kind (`class`, `object_property`, `datatype_property`, `annotation_property`, `property`)
+    pub kind: Option<String>,
+    /// Maximum number of candidates (default: 20)
+    pub limit: Option<usize>,
+}
+
+/// Auto-completion response
+#[derive(Debug, Serialize)]
+pub struct CompletionResponse {
+    pub completions: Vec<fukurow_store::TermCompletion>,
+    pub count: usize,
+    /// Matching terms before `limit` was applied
+    pub total: usize,
+}
+
+/// Consistency check parameters (`GET /ontology/consistency`)
+#[derive(Debug, Default, Deserialize)]
+pub struct ConsistencyParams {
+    /// `owl-dl` checks with the tableau; any other profile (default) with OWL Lite
+    pub profile: Option<ReasoningProfile>,
+}
+
+/// Ontology version upload request
+#[derive(Debug, Deserialize)]
+pub struct UploadOntologyRequest {
+    /// Ontology IRI (the named graph it is stored in)
+    pub iri: String,
+    pub version: String,
+    /// `nquads` (also accepts N-Triples) or `trig` (also accepts Turtle); default `nquads`
+    #[serde(default)]
+    pub format: fukurow_store::DatasetFormat,
+    pub content: String,
+}
+
+/// Ontology version selector for validation and activation
+#[derive(Debug, Deserialize)]
+pub struct OntologyVersionRequest {
+    pub iri: String,
+    pub version: String,
+}
+
+/// Registered ontology versions
+#[derive(Debug, Serialize)]
+pub struct OntologyVersionsResponse {
+    pub versions: Vec<fukurow_engine::OntologyVersion>,
+    pub count: usize,
+}
+
+/// Stored SPARQL query used for change monitoring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredQuery {
+    pub name: String,
+    pub query: String,
+    pub description: Option<String>,
+    /// Unix timestamp in milliseconds
+    pub created_at: u64,
+}
+
+/// Stored query registration request (`PUT /queries/:name`)
+#[derive(Debug, Deserialize)]
+pub struct StoreQueryRequest {
+    pub query: String,
+    pub description: Option<String>,
+}
+
+/// Query diff parameters (`GET /queries/:name/diff`)
+#[derive(Debug, Default, Deserialize)]
+pub struct QueryDiffParams {
+    /// Historical point in time (Unix timestamp in milliseconds)
+    pub since: Option<u64>,
+    /// Relative alternative to `since` (default: 86400 = one day ago)
+    pub since_secs: Option<u64>,
+}
+
+/// Query diff response
+#[derive(Debug, Serialize)]
+pub struct QueryDiffResponse {
+    pub name: String,
+    pub since: u64,
+    pub until: u64,
+    pub has_changes: bool,
+    pub diff: fukurow_sparql::QueryDiff,
+}
+
+/// CONSTRUCT materialization request (`POST /sparql/materialize`)
+#[derive(Debug, Deserialize)]
+pub struct MaterializeRequest {
+    /// CONSTRUCT query whose result is written
+    pub query: String,
+    /// Named graph IRI receiving the result (replaced on every run)
+    pub graph: String,
+}
+
+/// Materialized view registration request (`PUT /views/:name`)
+#[derive(Debug, Deserialize)]
+pub struct ViewRequest {
+    pub query: String,
+    pub graph: String,
+    /// Refresh period; the view is only refreshed on demand when omitted
+    pub refresh_interval_secs: Option<u64>,
+}
+
+/// SPARQL query request (`POST /sparql/query`)
+#[derive(Debug, Deserialize)]
+pub struct SparqlQueryRequest {
+    pub query: String,
+    /// Maximum rows/triples per page (default 1000, max 10000)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Continuation token from a previous response's `next_cursor`
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Limits for this query (the server's limits apply when omitted)
+    #[serde(default)]
+    pub limits: Option<QueryLimitsRequest>,
+}
+
+/// Per-request SPARQL limits; they can tighten but not loosen the server's limits
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QueryLimitsRequest {
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub max_intermediate_bindings: Option<usize>,
+    #[serde(default)]
+    pub max_results: Option<usize>,
+    /// Return the rows found before a limit was hit instead of an error
+    #[serde(default)]
+    pub partial: bool,
+}
+
+impl QueryLimitsRequest {
+    /// `server` tightened by this request
+    pub fn apply(&self, server: fukurow_sparql::QueryLimits) -> fukurow_sparql::QueryLimits {
+        server.tightened_by(fukurow_sparql::QueryLimits {
+            max_execution_time: self.timeout_ms.map(std::time::Duration::from_millis),
+            max_intermediate_bindings: self.max_intermediate_bindings,
+            max_results: self.max_results,
+            partial_results: self.partial,
+        })
+    }
+}
+
+/// Batch of SPARQL queries (`POST /sparql/batch`)
+#[derive(Debug, Deserialize)]
+pub struct SparqlBatchRequest {
+    pub queries: Vec<SparqlBatchQuery>,
+}
+
+/// One query of a batch
+#[derive(Debug, Deserialize)]
+pub struct SparqlBatchQuery {
+    /// Key for matching the result (defaults to the query's position)
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub request: SparqlQueryRequest,
+}
+
+/// Results of a batch, in request order
+#[derive(Debug, Serialize)]
+pub struct SparqlBatchResponse {
+    pub results: Vec<SparqlBatchResult>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// Result of one query of a batch
+#[derive(Debug, Serialize)]
+pub struct SparqlBatchResult {
+    pub id: String,
+    /// Status the query would have got from `POST /sparql/query`
+    pub status: u16,
+    #[serde(flatten)]
+    pub response: Option<SparqlQueryResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// SPARQL query parameters (`POST /sparql/query?explain=true`)
+#[derive(Debug, Default, Deserialize)]
+pub struct SparqlQueryParams {
+    /// Return the optimized plan with estimated cardinalities instead of running the query
+    #[serde(default)]
+    pub explain: bool,
+}
+
+/// One page of SPARQL results
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SparqlResultPage {
+    Select {
+        variables: Vec<String>,
+        rows: Vec<fukurow_sparql::diff::ResultRow>,
+    },
+    Graph {
+        triples: Vec<fukurow_core::model::Triple>,
+    },
+    Ask {
+        boolean: bool,
+    },
+    /// Plan of the query (`explain=true`); nothing is executed
+    Explain {
+        plan: fukurow_sparql::QueryExplanation,
+    },
+}
+
+/// SPARQL query response
+#[derive(Debug, Serialize)]
+pub struct SparqlQueryResponse {
+    #[serde(flatten)]
+    pub result: SparqlResultPage,
+    /// Number of rows/triples in this page
+    pub count: usize,
+    /// Token for the next page (`None` on the last page)
+    pub next_cursor: Option<String>,
+    /// Limit that cut the results short (only with `partial` limits)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<fukurow_sparql::LimitKind>,
+}
+
+/// Health check response
+#[derive(Debug, Serialize, Deserialize)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
@@ -78,7 +502,7 @@ pub struct HealthResponse {
 }
 
 /// Statistics response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct StatsResponse {
     pub total_events: usize,
     pub total_actions: usize,
@@ -86,6 +510,15 @@ pub struct StatsResponse {
     pub memory_usage_mb: Option<f64>,
 }
 
+/// System metrics with the caller's store access profile (`GET /monitoring/metrics`)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricsResponse {
+    #[serde(flatten)]
+    pub system: fukurow_observability::SystemMetrics,
+    /// Per-graph reads/writes and hot subjects/predicates of the tenant's store
+    pub store_access: fukurow_store::AccessStatistics,
+}
+
 /// Rule management request
 #[derive(Debug, Deserialize)]
 pub struct AddRuleRequest {
@@ -95,10 +528,19 @@ pub struct AddRuleRequest {
 /// Rules list response
 #[derive(Debug, Serialize)]
 pub struct RulesResponse {
-    pub rules: Vec<fukurow_core::model::InferenceRule>,
+    pub rules: Vec<fukurow_rules::RuleInfo>,
     pub count: usize,
 }
 
+/// Rule dry-run request
+///
+/// `event` を指定すると、現在のストアのコピーにそのイベントを加えてルールを評価する
+#[derive(Debug, Default, Deserialize)]
+pub struct RuleTestRequest {
+    #[serde(default)]
+    pub event: Option<fukurow_core::model::CyberEvent>,
+}
+
 /// Threat intelligence response
 #[derive(Debug, Serialize)]
 pub struct ThreatIntelResponse {
@@ -125,6 +567,10 @@ pub enum ApiError {
 
     #[error("Internal server error: {0}")]
     InternalError(String),
+
+    /// The tenant has no engine and the caller may not create one
+    #[error("Unknown tenant: {0}")]
+    UnknownTenant(TenantId),
 }
 
 impl From<ReasonerError> for ApiError {
@@ -134,6 +580,9 @@ impl From<ReasonerError> for ApiError {
             ReasonerError::RuleError(_) => ApiError::ReasoningError(err.to_string()),
             ReasonerError::ReasoningError(_) => ApiError::ReasoningError(err.to_string()),
             ReasonerError::StoreError(_) => ApiError::InternalError(err.to_string()),
+            ReasonerError::CapacityError(_) => ApiError::ReasoningError(err.to_string()),
+            ReasonerError::OntologyError(_) => ApiError::InvalidRequest(err.to_string()),
+            ReasonerError::StageError(_) => ApiError::InternalError(err.to_string()),
         }
     }
 }
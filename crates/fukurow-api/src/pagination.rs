@@ -0,0 +1,222 @@
+//! Cursor-based pagination for query endpoints
+//!
+//! 継続トークンは不透明な base64 文字列で、クエリのフィンガープリントと
+//! ページ位置（トリプルはキーセット、SPARQL 結果は行オフセット）を含む。
+//! 別のクエリのトークンを流用した場合は拒否する
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use fukurow_core::model::Triple;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Page size used when the request does not specify `limit`
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// Upper bound for `limit`
+pub const MAX_PAGE_SIZE: usize = 10_000;
+
+/// Pagination errors
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum PaginationError {
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("Cursor was issued for a different query")]
+    CursorMismatch,
+}
+
+/// Position encoded in a continuation token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorPosition {
+    /// Last (subject, predicate, object) returned
+    After(String, String, String),
+    /// Number of rows already returned
+    Offset(usize),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CursorPayload {
+    fingerprint: u64,
+    position: CursorPosition,
+}
+
+/// Clamp the requested page size
+pub fn page_size(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Stable fingerprint of the query parameters (FNV-1a, independent of process/hash seed)
+pub fn fingerprint(parts: &[Option<&str>]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        let bytes = match part {
+            Some(value) => value.as_bytes(),
+            None => b"\x01",
+        };
+        for byte in bytes.iter().chain(std::iter::once(&0u8)) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Encode a continuation token
+pub fn encode_cursor(fingerprint: u64, position: CursorPosition) -> String {
+    let payload = CursorPayload { fingerprint, position };
+    // CursorPayload のシリアライズは失敗しない
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&payload).unwrap_or_default())
+}
+
+/// Decode a continuation token issued for the query with `fingerprint`
+pub fn decode_cursor(token: &str, fingerprint: u64) -> Result<CursorPosition, PaginationError> {
+    let bytes = URL_SAFE_NO_PAD.decode(token)
+        .map_err(|e| PaginationError::InvalidCursor(e.to_string()))?;
+    let payload: CursorPayload = serde_json::from_slice(&bytes)
+        .map_err(|e| PaginationError::InvalidCursor(e.to_string()))?;
+    if payload.fingerprint != fingerprint {
+        return Err(PaginationError::CursorMismatch);
+    }
+    Ok(payload.position)
+}
+
+fn triple_key(triple: &Triple) -> (&str, &str, &str) {
+    (&triple.subject, &triple.predicate, &triple.object)
+}
+
+/// Last (subject, predicate, object) of the previous page (`None` on the first page)
+pub fn triple_keyset(cursor: Option<&str>, fingerprint: u64) -> Result<Option<(String, String, String)>, PaginationError> {
+    match cursor.map(|token| decode_cursor(token, fingerprint)).transpose()? {
+        None => Ok(None),
+        Some(CursorPosition::After(s, p, o)) => Ok(Some((s, p, o))),
+        Some(CursorPosition::Offset(_)) => Err(PaginationError::InvalidCursor("expected a triple cursor".to_string())),
+    }
+}
+
+/// Build a page from the distinct triples following the keyset, in (subject, predicate, object) order
+///
+/// `ordered` には `page_size(limit) + 1` 件まで渡す。超過した 1 件で次のページの有無を判定する
+pub fn triple_page(mut ordered: Vec<Triple>, limit: Option<usize>, fingerprint: u64) -> (Vec<Triple>, Option<String>) {
+    let size = page_size(limit);
+    let has_more = ordered.len() > size;
+    ordered.truncate(size);
+    let next = ordered.last().filter(|_| has_more).map(|last| encode_cursor(
+        fingerprint,
+        CursorPosition::After(last.subject.clone(), last.predicate.clone(), last.object.clone()),
+    ));
+    (ordered, next)
+}
+
+/// Keyset pagination over already materialized triples (CONSTRUCT / DESCRIBE results)
+///
+/// 全件は整列せず、前回の最終キーより後ろで小さい順に 1 ページ分 (+1 件) だけを選び出す。
+/// ページ間で挿入・削除があっても重複や取りこぼしは起きない
+pub fn paginate_triples(
+    triples: Vec<Triple>,
+    limit: Option<usize>,
+    cursor: Option<&str>,
+    fingerprint: u64,
+) -> Result<(Vec<Triple>, Option<String>), PaginationError> {
+    let after = triple_keyset(cursor, fingerprint)?;
+    let after = after.as_ref().map(|(s, p, o)| (s.as_str(), p.as_str(), o.as_str()));
+    let wanted = page_size(limit) + 1;
+
+    let mut selected: BTreeMap<(&str, &str, &str), &Triple> = BTreeMap::new();
+    for triple in &triples {
+        let key = triple_key(triple);
+        if after.is_some_and(|after| key <= after) || selected.contains_key(&key) {
+            continue;
+        }
+        if selected.len() == wanted {
+            match selected.last_key_value() {
+                Some((largest, _)) if key < *largest => {
+                    selected.pop_last();
+                }
+                _ => continue,
+            }
+        }
+        selected.insert(key, triple);
+    }
+
+    let ordered = selected.into_values().cloned().collect();
+    Ok(triple_page(ordered, limit, fingerprint))
+}
+
+/// Number of rows returned by the previous pages (0 on the first page)
+pub fn row_offset(cursor: Option<&str>, fingerprint: u64) -> Result<usize, PaginationError> {
+    match cursor.map(|token| decode_cursor(token, fingerprint)).transpose()? {
+        None => Ok(0),
+        Some(CursorPosition::Offset(offset)) => Ok(offset),
+        Some(CursorPosition::After(..)) => Err(PaginationError::InvalidCursor("expected a row cursor".to_string())),
+    }
+}
+
+/// Build a page from the rows starting at `offset`
+///
+/// 切り出しは評価器で済ませておき、`rows` には `page_size(limit) + 1` 件まで渡す
+pub fn row_page<T>(mut rows: Vec<T>, offset: usize, limit: Option<usize>, fingerprint: u64) -> (Vec<T>, Option<String>) {
+    let size = page_size(limit);
+    let has_more = rows.len() > size;
+    rows.truncate(size);
+    let next = has_more.then(|| encode_cursor(fingerprint, CursorPosition::Offset(offset + size)));
+    (rows, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triple(s: &str, p: &str, o: &str) -> Triple {
+        Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() }
+    }
+
+    #[test]
+    fn test_triple_pages_cover_all_results() {
+        let fp = fingerprint(&[Some("s"), None, None]);
+        let triples: Vec<Triple> = (0..5).rev().map(|i| triple("s", "p", &format!("o{}", i))).collect();
+
+        let (first, cursor) = paginate_triples(triples.clone(), Some(2), None, fp).unwrap();
+        assert_eq!(first, vec![triple("s", "p", "o0"), triple("s", "p", "o1")]);
+
+        // 2 ページ目の取得前に先頭側へ挿入されても結果はずれない
+        let mut grown = triples.clone();
+        grown.push(triple("a", "p", "o"));
+        let (second, cursor) = paginate_triples(grown.clone(), Some(2), cursor.as_deref(), fp).unwrap();
+        assert_eq!(second, vec![triple("s", "p", "o2"), triple("s", "p", "o3")]);
+
+        let (third, cursor) = paginate_triples(grown, Some(2), cursor.as_deref(), fp).unwrap();
+        assert_eq!(third, vec![triple("s", "p", "o4")]);
+        assert!(cursor.is_none());
+    }
+
+    #[test]
+    fn test_row_pages_and_cursor_validation() {
+        let fp = fingerprint(&[Some("SELECT ?s WHERE { ?s ?p ?o }")]);
+        let rows: Vec<usize> = (0..3).collect();
+        assert_eq!(row_offset(None, fp).unwrap(), 0);
+        let (page, cursor) = row_page(rows[..3].to_vec(), 0, Some(2), fp);
+        assert_eq!(page, vec![0, 1]);
+
+        let cursor = cursor.unwrap();
+        let offset = row_offset(Some(&cursor), fp).unwrap();
+        assert_eq!(offset, 2);
+        let (page, next) = row_page(rows[offset..].to_vec(), offset, Some(2), fp);
+        assert_eq!(page, vec![2]);
+        assert!(next.is_none());
+
+        let other = fingerprint(&[Some("ASK { ?s ?p ?o }")]);
+        assert_eq!(row_offset(Some(&cursor), other).unwrap_err(), PaginationError::CursorMismatch);
+        assert!(matches!(row_offset(Some("not a cursor"), fp), Err(PaginationError::InvalidCursor(_))));
+        assert!(matches!(triple_keyset(Some(&cursor), fp), Err(PaginationError::InvalidCursor(_))));
+    }
+
+    #[test]
+    fn test_page_size_is_clamped() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(0)), 1);
+        assert_eq!(page_size(Some(MAX_PAGE_SIZE + 1)), MAX_PAGE_SIZE);
+        assert_ne!(fingerprint(&[Some("a"), None]), fingerprint(&[None, Some("a")]));
+    }
+}
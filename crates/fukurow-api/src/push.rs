@@ -0,0 +1,174 @@
+//! Real-time push of reasoning and anomaly events (Server-Sent Events)
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use fukurow_core::model::SecurityAction;
+use fukurow_store::TenantId;
+use fukurow_streaming::StreamingEvent;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Default number of buffered events per subscriber before it starts lagging
+pub const DEFAULT_PUSH_CAPACITY: usize = 1024;
+
+/// Severity used for per-connection filtering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl FromStr for PushSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "low" | "info" => Ok(PushSeverity::Low),
+            "medium" | "warning" => Ok(PushSeverity::Medium),
+            "high" | "error" => Ok(PushSeverity::High),
+            "critical" => Ok(PushSeverity::Critical),
+            other => Err(format!("Unknown severity: {}", other)),
+        }
+    }
+}
+
+impl PushSeverity {
+    /// Severity of a single security action
+    pub fn of_action(action: &SecurityAction) -> Self {
+        match action {
+            SecurityAction::Alert { severity, .. } => severity.parse().unwrap_or(PushSeverity::Medium),
+            _ => PushSeverity::High,
+        }
+    }
+
+    /// Severity of a streaming event
+    pub fn of_event(event: &StreamingEvent) -> Self {
+        match event {
+            StreamingEvent::ReasoningResult { actions, .. } => actions
+                .iter()
+                .map(Self::of_action)
+                .max()
+                .unwrap_or(PushSeverity::Low),
+            StreamingEvent::AnomalyDetected { score, threshold, .. } => {
+                let ratio = if *threshold > 0.0 { score / threshold } else { f64::INFINITY };
+                if ratio >= 3.0 {
+                    PushSeverity::Critical
+                } else if ratio >= 1.5 {
+                    PushSeverity::High
+                } else if ratio >= 1.0 {
+                    PushSeverity::Medium
+                } else {
+                    PushSeverity::Low
+                }
+            }
+            _ => PushSeverity::Low,
+        }
+    }
+}
+
+/// Per-connection subscription filter
+#[derive(Debug, Clone)]
+pub struct PushFilter {
+    pub event_types: HashSet<String>,
+    pub min_severity: PushSeverity,
+}
+
+impl Default for PushFilter {
+    fn default() -> Self {
+        Self {
+            event_types: ["reasoning_result", "anomaly_detected"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            min_severity: PushSeverity::Low,
+        }
+    }
+}
+
+impl PushFilter {
+    /// Build a filter from query parameters (`types=a,b&min_severity=high`)
+    pub fn from_query(types: Option<&str>, min_severity: Option<&str>) -> Result<Self, String> {
+        let mut filter = Self::default();
+
+        if let Some(types) = types {
+            let requested: HashSet<String> = types
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect();
+            if let Some(unknown) = requested.iter().find(|t| !filter.event_types.contains(*t)) {
+                return Err(format!("Unsupported event type: {}", unknown));
+            }
+            if !requested.is_empty() {
+                filter.event_types = requested;
+            }
+        }
+
+        if let Some(severity) = min_severity {
+            filter.min_severity = severity.parse()?;
+        }
+
+        Ok(filter)
+    }
+
+    /// Whether the event should be delivered to this connection
+    pub fn matches(&self, event: &StreamingEvent) -> bool {
+        self.event_types.contains(event.event_type())
+            && PushSeverity::of_event(event) >= self.min_severity
+    }
+}
+
+/// Fan-out hub that delivers events to connected subscribers
+///
+/// 購読者はテナントごとのチャネルに分かれ、他テナントのイベントは届かない。
+/// テナントを指定しない `publish` / `subscribe` は既定テナントを使う
+#[derive(Debug, Clone)]
+pub struct PushHub {
+    capacity: usize,
+    senders: Arc<RwLock<HashMap<TenantId, broadcast::Sender<StreamingEvent>>>>,
+}
+
+impl PushHub {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), senders: Arc::default() }
+    }
+
+    /// Publish an event to the default tenant; returns the number of subscribers it was delivered to
+    pub fn publish(&self, event: StreamingEvent) -> usize {
+        self.publish_to(&TenantId::default(), event)
+    }
+
+    /// Publish an event to the subscribers of `tenant`
+    pub fn publish_to(&self, tenant: &TenantId, event: StreamingEvent) -> usize {
+        let senders = self.senders.read().unwrap_or_else(|e| e.into_inner());
+        senders.get(tenant).and_then(|sender| sender.send(event).ok()).unwrap_or(0)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamingEvent> {
+        self.subscribe_to(&TenantId::default())
+    }
+
+    pub fn subscribe_to(&self, tenant: &TenantId) -> broadcast::Receiver<StreamingEvent> {
+        let mut senders = self.senders.write().unwrap_or_else(|e| e.into_inner());
+        senders.entry(tenant.clone())
+            .or_insert_with(|| broadcast::channel(self.capacity).0)
+            .subscribe()
+    }
+
+    /// Subscribers across all tenants
+    pub fn subscriber_count(&self) -> usize {
+        let senders = self.senders.read().unwrap_or_else(|e| e.into_inner());
+        senders.values().map(|sender| sender.receiver_count()).sum()
+    }
+}
+
+impl Default for PushHub {
+    fn default() -> Self {
+        Self::new(DEFAULT_PUSH_CAPACITY)
+    }
+}
@@ -0,0 +1,106 @@
+//! Per-request tracing
+//!
+//! 各リクエストを `api.request` スパンで包み、リクエスト ID (`x-request-id`、無ければ採番) を
+//! スパン・レスポンスヘッダー・下流への送信 (SIEM など) に引き継ぐ。
+//! `traceparent` ヘッダーがあれば呼び出し元のトレースの子として記録する
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use fukurow_observability::telemetry;
+use fukurow_observability::tracing::{attributes, headers, spans};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::Instrument;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request ID of the API request handled by the current task
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Middleware wrapping every request in an `api.request` span
+pub async fn trace_request(request: Request, next: Next) -> Response {
+    let request_id = request.headers().get(headers::REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let endpoint = request.extensions().get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let span = tracing::info_span!(
+        spans::API_REQUEST,
+        request.id = %request_id,
+        method = %request.method(),
+        endpoint = %endpoint,
+        user.id = tracing::field::Empty,
+        tenant.id = tracing::field::Empty,
+        status.code = tracing::field::Empty,
+        duration.ms = tracing::field::Empty
+    );
+    let trace_context: HashMap<String, String> = [headers::TRACEPARENT, headers::TRACESTATE].into_iter()
+        .filter_map(|name| {
+            let value = request.headers().get(name)?.to_str().ok()?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect();
+    telemetry::set_remote_parent(&span, &trace_context);
+
+    let started = Instant::now();
+    let mut response = REQUEST_ID.scope(request_id.clone(), next.run(request))
+        .instrument(span.clone())
+        .await;
+    span.record(attributes::STATUS_CODE, response.status().as_u16());
+    span.record(attributes::DURATION_MS, started.elapsed().as_millis() as u64);
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(headers::REQUEST_ID, value);
+    }
+    response
+}
+
+/// Accept caller-supplied IDs only if they are short and header/log safe
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/id", get(|| async { current_request_id().unwrap_or_default() }))
+            .layer(middleware::from_fn(trace_request))
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_propagated_or_generated() {
+        let response = app()
+            .oneshot(Request::builder().uri("/id").header(headers::REQUEST_ID, "req-42").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[headers::REQUEST_ID], "req-42");
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], b"req-42");
+
+        // 不正な ID は採番し直す
+        let response = app()
+            .oneshot(Request::builder().uri("/id").header(headers::REQUEST_ID, "bad id").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let generated = response.headers()[headers::REQUEST_ID].to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&generated).is_ok());
+        assert_eq!(current_request_id(), None);
+    }
+}
@@ -1,46 +1,129 @@
 //! API route definitions
 
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
     extract::Extension,
+    middleware,
 };
 use tower_http::cors::CorsLayer;
 use std::sync::Arc;
+use crate::auth::{authorize, Role, RouteGuard};
+use crate::drain::reject_while_draining;
 use crate::handlers::*;
+use crate::request_trace::trace_request;
 /// Create the main API router
+///
+/// ヘルスチェック以外のルートは必要なロールごとにまとめ、認証ミドルウェアを掛ける
 pub fn create_router(state: Arc<AppState>) -> Router {
-    Router::new()
-        // Health and status routes
+    let guard = |required: Role| {
+        middleware::from_fn_with_state(RouteGuard::new(Arc::clone(&state.auth), required), authorize)
+    };
+
+    // Unauthenticated liveness probes
+    let public = Router::new()
         .route("/health", get(health_check))
-        .route("/stats", get(get_stats))
+        .route("/monitoring/health", get(monitoring_health))
 
-        // Event management routes
-        .route("/events", post(submit_event))
+        // Sensor webhooks authenticate per route (shared secret or API credentials)
+        .route("/ingest/:route", post(ingest_webhook));
 
-        // Reasoning routes
-        .route("/reason", post(execute_reasoning))
-        .route("/reason/reset", post(reset_reasoner))
+    let read_only = Router::new()
+        .route("/stats", get(get_stats))
+        .route("/events/stream", get(stream_events))
+        .route("/events/quarantine", get(list_quarantined_events))
 
         // Graph query routes
-        .route("/graph/query", post(query_graph))
+        .route("/graph/query", get(query_graph_get).post(query_graph))
+        .route("/sparql/query", post(query_sparql))
+        .route("/sparql/batch", post(query_sparql_batch))
+
+        // Stored query / change monitoring routes
+        .route("/queries", get(list_queries))
+        .route("/queries/:name/diff", get(diff_stored_query))
+
+        // Materialized view routes
+        .route("/views", get(list_views))
+
+        // Sensor heartbeat routes
+        .route("/sensors", get(list_sensors))
+
+        // Background reasoning job status
+        .route("/jobs/:id", get(get_reasoning_job))
 
-        // Rule management routes (future)
-        .route("/rules", post(add_rule))
+        // Ontology metadata routes
+        .route("/ontology/terms", get(ontology_terms))
+        .route("/ontology/complete", get(complete_ontology_terms))
+        .route("/ontology/consistency", get(check_consistency))
+
+        // Detection coverage routes
+        .route("/attack/coverage", get(attack_coverage))
 
         // Threat intelligence routes
         .route("/threat-intel", get(get_threat_intel))
         .route("/threat-intel/export", get(export_threat_indicators))
-        .route("/threat-intel/import", post(import_threat_indicators))
 
         // Monitoring routes (bound to AppState)
-        .route("/monitoring/health", get(monitoring_health))
         .route("/monitoring/health/detailed", get(monitoring_health_detailed))
         .route("/monitoring/metrics", get(monitoring_metrics))
+        .route_layer(guard(Role::ReadOnly));
+
+    let ingest = Router::new()
+        // Event management routes
+        .route("/events", post(submit_event))
+        .route("/events/batch", post(submit_event_batch))
+
+        // Reasoning routes
+        .route("/reason", post(execute_reasoning))
+        .route("/reason/async", post(submit_reasoning_job))
+
+        .route("/queries/:name", put(save_query))
+
+        // CONSTRUCT materialization routes
+        .route("/sparql/materialize", post(materialize_sparql))
+        .route("/views/:name", put(define_view))
+        .route("/views/:name/refresh", post(refresh_view))
+        .route("/threat-intel/import", post(import_threat_indicators))
+        .route_layer(guard(Role::Ingest));
+
+    let admin = Router::new()
+        .route("/reason/reset", post(reset_reasoner))
+
+        // Drain mode (graceful shutdown) routes
+        .route("/admin/drain", get(drain_status).post(start_drain))
+
+        // Audit log routes
+        .route("/audit", get(query_audit))
+
+        // Store snapshot routes
+        .route("/snapshot", post(export_snapshot))
+
+        // Materialized view removal (drops the view graph)
+        .route("/views/:name", delete(delete_view))
+
+        // Ontology version routes
+        .route("/ontologies", get(list_ontologies).post(upload_ontology))
+        .route("/ontologies/validate", post(validate_ontology))
+        .route("/ontologies/activate", post(activate_ontology))
+
+        // Rule management routes
+        .route("/rules", get(list_rules).post(add_rule))
+        .route("/rules/:name", get(get_rule))
+        .route("/rules/:name/enable", post(enable_rule))
+        .route("/rules/:name/disable", post(disable_rule))
+        .route("/rules/:name/test", post(test_rule))
+        .route_layer(guard(Role::Admin));
+
+    public
+        .merge(read_only)
+        .merge(ingest)
+        .merge(admin)
 
         // Apply middleware
+        .layer(middleware::from_fn_with_state(state.drain.clone(), reject_while_draining))
         .layer(CorsLayer::permissive())
         .layer(Extension(state))
+        .layer(middleware::from_fn(trace_request))
 }
 
 /// API documentation routes (OpenAPI/Swagger)
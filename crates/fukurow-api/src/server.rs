@@ -2,25 +2,70 @@
 
 use axum::Router;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
 use tracing::{info, error};
 
-use crate::{routes::create_router, handlers::AppState};
+use crate::{routes::create_router, handlers::AppState, push::PushHub, auth::AuthConfig, webhook::WebhookConfig};
+use crate::jobs::{JobManager, DEFAULT_MAX_CONCURRENT_JOBS};
+use crate::views::ViewManager;
+use crate::drain::{DrainController, DEFAULT_DRAIN_TIMEOUT_SECS};
+use crate::caching::HttpCacheConfig;
+use crate::anomaly::{EventRateMonitor, DEFAULT_ANOMALY_WINDOW_SECS};
 use fukurow_observability::HealthMonitor;
-use fukurow_engine::ReasonerEngine;
-use fukurow_domain_cyber::threat_intelligence::ThreatProcessor;
+use fukurow_core::prefix::PrefixMap;
+use fukurow_core::validation::EventValidator;
+use fukurow_engine::{ReasonerEngine, ReasoningSchedule, ReasoningScheduler, SchedulerTask, TenantEngines, TenantIsolationConfig, TenantScheduler};
+use fukurow_store::{AuditSinkError, BootstrapConfig, Bootstrapper, PersistenceManager, SqliteAuditSink, TenantId};
+use fukurow_store::store::RdfStore;
+use fukurow_domain_cyber::{cyber_rules, threat_intelligence::ThreatProcessor};
+use fukurow_sparql::QueryLimits;
 
 #[cfg(feature = "streaming")]
 use fukurow_streaming::processor::EventSender;
 
+/// Default time a SPARQL query may run
+pub const DEFAULT_QUERY_TIMEOUT_SECS: u64 = 30;
+
+/// Default bound on the solutions a SPARQL query may produce while joining
+pub const DEFAULT_MAX_INTERMEDIATE_BINDINGS: usize = 1_000_000;
+
 /// Server configuration
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub max_connections: usize,
+    /// API key / JWT authentication (disabled when empty)
+    pub auth: AuthConfig,
+    /// Directory written by `POST /snapshot`
+    pub snapshot_dir: PathBuf,
+    /// Sensor webhook routes served under `/ingest/:route`
+    pub webhooks: WebhookConfig,
+    /// Reasoning jobs (`POST /reason/async`) run at the same time
+    pub max_concurrent_jobs: usize,
+    /// Event validation at ingestion (strict, lenient or quarantine)
+    pub validation: EventValidator,
+    /// Time running jobs and streaming tasks get to finish on shutdown
+    pub drain_timeout_secs: u64,
+    /// Reasoning passes run on a cron schedule while serving
+    pub schedules: Vec<ReasoningSchedule>,
+    /// Limits of SPARQL queries; requests may tighten them
+    pub query_limits: QueryLimits,
+    /// Prefixes recognised in auto-completion input and used for its CURIEs
+    pub prefixes: PrefixMap,
+    /// ETag / Last-Modified validators and `Cache-Control` of read endpoints
+    pub http_cache: HttpCacheConfig,
+    /// Per-tenant pools shared by `POST /reason` and reasoning jobs
+    pub tenant_isolation: TenantIsolationConfig,
+    /// Window ingestion rates are counted over before checking them for anomalies
+    pub anomaly_window_secs: u64,
+    /// Directory of the per-tenant SQLite audit logs (`<tenant>.db`) served by `GET /audit`
+    ///
+    /// 未設定なら監査ログはメモリ上にだけ残り、再起動で失われる
+    pub audit_dir: Option<PathBuf>,
 }
 
 impl Default for ServerConfig {
@@ -29,6 +74,21 @@ impl Default for ServerConfig {
             host: "0.0.0.0".to_string(),
             port: 3000,
             max_connections: 100,
+            auth: AuthConfig::default(),
+            snapshot_dir: PathBuf::from("snapshots"),
+            webhooks: WebhookConfig::default(),
+            max_concurrent_jobs: DEFAULT_MAX_CONCURRENT_JOBS,
+            validation: EventValidator::default(),
+            drain_timeout_secs: DEFAULT_DRAIN_TIMEOUT_SECS,
+            schedules: Vec::new(),
+            query_limits: QueryLimits::default()
+                .with_max_execution_time(Duration::from_secs(DEFAULT_QUERY_TIMEOUT_SECS))
+                .with_max_intermediate_bindings(DEFAULT_MAX_INTERMEDIATE_BINDINGS),
+            prefixes: PrefixMap::default(),
+            http_cache: HttpCacheConfig::default(),
+            tenant_isolation: TenantIsolationConfig::default(),
+            anomaly_window_secs: DEFAULT_ANOMALY_WINDOW_SECS,
+            audit_dir: None,
         }
     }
 }
@@ -37,6 +97,7 @@ impl Default for ServerConfig {
 pub struct ReasonerServer {
     config: ServerConfig,
     app_state: AppState,
+    bootstrap: BootstrapConfig,
     #[cfg(feature = "streaming")]
     event_sender: Option<EventSender>,
 }
@@ -49,17 +110,37 @@ impl ReasonerServer {
 
     /// Create new server with custom configuration
     pub fn with_config(config: ServerConfig, monitoring: std::sync::Arc<dyn HealthMonitor>) -> Self {
-        let reasoner = ReasonerEngine::new();
         let threat_processor = ThreatProcessor::new();
-
-        // Initialize reasoner with default cyber security rules
-        // TODO: Implement rule initialization for new fukurow architecture
+        let scheduler = Arc::new(TenantScheduler::new(config.tenant_isolation.clone()));
+
+        // 既定のテナントは常に存在する (ブートストラップは bootstrap_store で行う)
+        // 各テナントのエンジンには組み込みのサイバーセキュリティルールを登録する
+        let tenants = TenantEngines::new();
+        tenants.set_rules(cyber_rules);
+        if let Some(audit_dir) = config.audit_dir.clone() {
+            tenants.set_initializer(move |tenant, store| attach_audit_log(&audit_dir, tenant, store));
+        }
+        tenants.engine(&TenantId::default());
 
         let app_state = AppState {
-            reasoner: std::sync::Arc::new(reasoner),
+            tenants: Arc::new(tenants),
             threat_processor: std::sync::Arc::new(tokio::sync::RwLock::new(threat_processor)),
             monitoring,
             start_time: Instant::now(),
+            push_hub: PushHub::default(),
+            stored_queries: Default::default(),
+            auth: Arc::new(config.auth.clone()),
+            persistence: Arc::new(PersistenceManager::new(config.snapshot_dir.clone())),
+            webhooks: Arc::new(config.webhooks.clone()),
+            jobs: JobManager::new(config.max_concurrent_jobs).with_scheduler(Arc::clone(&scheduler)),
+            scheduler,
+            validator: Arc::new(config.validation.clone()),
+            query_limits: config.query_limits,
+            prefixes: Arc::new(config.prefixes.clone()),
+            http_cache: Arc::new(config.http_cache.clone()),
+            views: ViewManager::default(),
+            drain: DrainController::new(Duration::from_secs(config.drain_timeout_secs)),
+            event_rates: EventRateMonitor::new(Duration::from_secs(config.anomaly_window_secs)),
             #[cfg(feature = "streaming")]
             event_sender: None,
         };
@@ -67,6 +148,7 @@ impl ReasonerServer {
         Self {
             config,
             app_state,
+            bootstrap: BootstrapConfig::default(),
             #[cfg(feature = "streaming")]
             event_sender: None,
         }
@@ -79,6 +161,56 @@ impl ReasonerServer {
         self.app_state.event_sender = Some(sender);
     }
 
+    /// Configure which bundled ontologies are loaded before serving
+    pub fn with_bootstrap(mut self, bootstrap: BootstrapConfig) -> Self {
+        self.bootstrap = bootstrap;
+        self
+    }
+
+    /// Load the bundled ontologies and shapes into every tenant's store
+    ///
+    /// 既に同じバージョンが入っていれば何もしないため、起動のたびに呼んでよい。
+    /// 起動後に初めて参照されたテナントのストアにも同じバンドルを読み込む
+    pub async fn bootstrap_store(&self) -> anyhow::Result<()> {
+        let bootstrap = self.bootstrap.clone();
+        let audit_dir = self.config.audit_dir.clone();
+        self.app_state.tenants.set_initializer(move |tenant, store| {
+            if let Some(audit_dir) = &audit_dir {
+                attach_audit_log(audit_dir, tenant, store);
+            }
+            match Bootstrapper::new(bootstrap.clone()).run(store) {
+                Ok(report) => info!("Bootstrapped store of tenant {} ({} bundles)", tenant, report.bundles.len()),
+                Err(e) => error!("Failed to bootstrap store of tenant {}: {}", tenant, e),
+            }
+        });
+
+        self.app_state.tenants.engine(&TenantId::default());
+        for tenant in self.app_state.tenants.tenants() {
+            let Some(engine) = self.app_state.tenants.get(&tenant) else { continue };
+            let store = engine.get_graph_store().await;
+            let report = Bootstrapper::new(self.bootstrap.clone()).run(&mut *store.write().await)?;
+            for (name, outcome) in &report.bundles {
+                info!("Bootstrap bundle {} for tenant {}: {:?}", name, tenant, outcome);
+            }
+        }
+        Ok(())
+    }
+
+    /// Per-tenant reasoner engines
+    pub fn tenants(&self) -> Arc<TenantEngines> {
+        Arc::clone(&self.app_state.tenants)
+    }
+
+    /// Hub for pushing events to `/events/stream` subscribers
+    pub fn push_hub(&self) -> PushHub {
+        self.app_state.push_hub.clone()
+    }
+
+    /// Shared state of the handlers, for serving other protocols from the same engines
+    pub fn app_state(&self) -> AppState {
+        self.app_state.clone()
+    }
+
     /// Get the server address
     pub fn address(&self) -> SocketAddr {
         format!("{}:{}", self.config.host, self.config.port)
@@ -91,53 +223,148 @@ impl ReasonerServer {
         create_router(Arc::new(self.app_state.clone()))
     }
 
-    /// Start the server
-    pub async fn serve(self) -> anyhow::Result<()> {
-        let addr = self.address();
-        let app = self.create_app();
-
-        info!("Starting Reasoner API server on {}", addr);
+    /// Drain controller, for streaming tasks to stop with the server
+    pub fn drain(&self) -> DrainController {
+        self.app_state.drain.clone()
+    }
 
-        let listener = TcpListener::bind(addr).await?;
-        info!("Server listening on {}", addr);
+    /// Start the configured reasoning schedules, one scheduler per tenant
+    ///
+    /// 結果はテナントの `/events/stream` 購読者 (とストリーミング送信先) に届く。
+    /// 同じテナントのスケジュールは 1 つのスケジューラを共有するため、パスは重ならない
+    pub fn start_schedules(&self) -> Vec<SchedulerTask> {
+        let mut by_tenant: Vec<(TenantId, Vec<ReasoningSchedule>)> = Vec::new();
+        for schedule in &self.config.schedules {
+            match by_tenant.iter_mut().find(|(tenant, _)| *tenant == schedule.tenant) {
+                Some((_, schedules)) => schedules.push(schedule.clone()),
+                None => by_tenant.push((schedule.tenant.clone(), vec![schedule.clone()])),
+            }
+        }
+        by_tenant.into_iter()
+            .map(|(tenant, schedules)| {
+                info!("Starting {} reasoning schedules for tenant {}", schedules.len(), tenant);
+                let engine = self.app_state.tenants.engine(&tenant);
+                let push_hub = self.app_state.push_hub.clone();
+                #[cfg(feature = "streaming")]
+                let sender = self.app_state.event_sender.clone();
+                let scheduler = ReasoningScheduler::new(engine, move |event| {
+                    #[cfg(feature = "streaming")]
+                    if let Some(sender) = &sender {
+                        let _ = sender.send(event.clone());
+                    }
+                    push_hub.publish_to(&tenant, event);
+                });
+                Arc::new(scheduler).spawn(schedules)
+            })
+            .collect()
+    }
 
-        axum::serve(listener, app).await.map_err(|e| {
-            error!("Server error: {}", e);
-            e.into()
-        })
+    /// Start the server, draining on SIGTERM / Ctrl+C
+    pub async fn serve(self) -> anyhow::Result<()> {
+        self.run_with_shutdown(shutdown_signal()).await
     }
 
     /// Run the server with graceful shutdown
+    ///
+    /// `shutdown_signal` か `POST /admin/drain` でドレインを始め、推論ジョブとストリーミングが
+    /// 落ち着くまでは 503 を返しながら待ち受けを続ける。その後 HTTP サーバーを止め、
+    /// 処理中だったリクエストの完了後にストアを保存する
     pub async fn run_with_shutdown(self, shutdown_signal: impl std::future::Future<Output = ()> + Send + 'static) -> anyhow::Result<()> {
+        self.bootstrap_store().await?;
         let addr = self.address();
         let app = self.create_app();
+        let state = self.app_state.clone();
+        let drain = state.drain.clone();
+        let schedules = self.start_schedules();
 
         info!("Starting Reasoner API server on {} with graceful shutdown", addr);
 
         let listener = TcpListener::bind(addr).await?;
         info!("Server listening on {}", addr);
 
+        let (settled_tx, settled_rx) = tokio::sync::oneshot::channel();
+        let settle = {
+            let state = state.clone();
+            async move {
+                tokio::select! {
+                    _ = shutdown_signal => {}
+                    _ = state.drain.requested() => {}
+                }
+                // ドレイン中は新しい定期パスを始めない
+                drop(schedules);
+                let _ = settled_tx.send(state.drain.settle(&state).await);
+            }
+        };
+
         axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_signal)
+            .with_graceful_shutdown(settle)
             .await
             .map_err(|e| {
                 error!("Server error: {}", e);
-                e.into()
-            })
+                anyhow::Error::from(e)
+            })?;
+
+        let mut report = settled_rx.await.unwrap_or_default();
+        drain.persist(&state, &mut report).await;
+        info!(
+            "Shutdown complete: {} jobs abandoned, {} streaming tasks stopped ({} aborted), {} stores persisted",
+            report.abandoned_jobs, report.streaming_stopped, report.streaming_aborted, report.persisted.len()
+        );
+        if !report.errors.is_empty() {
+            anyhow::bail!("Failed to persist stores on shutdown: {}", report.errors.join("; "));
+        }
+        Ok(())
     }
 }
 
 // Default cannot be implemented without a default monitor
 
-/// Create a server with custom reasoner engine
+/// Persist the audit trail of `tenant`'s store to `<audit_dir>/<tenant>.db`
+///
+/// 開けなければログに残し、監査はメモリ上だけで続ける
+fn attach_audit_log(audit_dir: &Path, tenant: &TenantId, store: &mut RdfStore) {
+    let path = audit_dir.join(format!("{}.db", tenant));
+    let sink = std::fs::create_dir_all(audit_dir)
+        .map_err(AuditSinkError::from)
+        .and_then(|_| SqliteAuditSink::open(&path));
+    match sink {
+        Ok(sink) => store.set_audit_sink(Box::new(sink)),
+        Err(e) => error!("Failed to open audit log {} of tenant {}: {}", path.display(), tenant, e),
+    }
+}
+
+/// Create a server with custom reasoner engine (used for the default tenant)
+///
+/// `audit_dir` と組み込みルールは後から作られるテナントにだけ適用する。渡したエンジンのルールと監査の保存先は呼び出し側で設定する
 pub fn create_server_with_reasoner(reasoner: ReasonerEngine, config: ServerConfig, monitoring: std::sync::Arc<dyn HealthMonitor>) -> ReasonerServer {
     let threat_processor = ThreatProcessor::new();
+    let scheduler = Arc::new(TenantScheduler::new(config.tenant_isolation.clone()));
+    let tenants = TenantEngines::new();
+    tenants.set_rules(cyber_rules);
+    if let Some(audit_dir) = config.audit_dir.clone() {
+        tenants.set_initializer(move |tenant, store| attach_audit_log(&audit_dir, tenant, store));
+    }
+    tenants.insert(TenantId::default(), reasoner);
 
         let app_state = AppState {
-            reasoner: std::sync::Arc::new(reasoner),
+            tenants: Arc::new(tenants),
             threat_processor: std::sync::Arc::new(tokio::sync::RwLock::new(threat_processor)),
             monitoring,
             start_time: Instant::now(),
+            push_hub: PushHub::default(),
+            stored_queries: Default::default(),
+            auth: Arc::new(config.auth.clone()),
+            persistence: Arc::new(PersistenceManager::new(config.snapshot_dir.clone())),
+            webhooks: Arc::new(config.webhooks.clone()),
+            jobs: JobManager::new(config.max_concurrent_jobs).with_scheduler(Arc::clone(&scheduler)),
+            scheduler,
+            validator: Arc::new(config.validation.clone()),
+            query_limits: config.query_limits,
+            prefixes: Arc::new(config.prefixes.clone()),
+            http_cache: Arc::new(config.http_cache.clone()),
+            views: ViewManager::default(),
+            drain: DrainController::new(Duration::from_secs(config.drain_timeout_secs)),
+            event_rates: EventRateMonitor::new(Duration::from_secs(config.anomaly_window_secs)),
             #[cfg(feature = "streaming")]
             event_sender: None,
         };
@@ -145,6 +372,7 @@ pub fn create_server_with_reasoner(reasoner: ReasonerEngine, config: ServerConfi
     ReasonerServer {
         config,
         app_state,
+        bootstrap: BootstrapConfig::default(),
         #[cfg(feature = "streaming")]
         event_sender: None,
     }
@@ -6,7 +6,13 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use reqwest::Client;
-use tracing::{info, warn, error};
+use tracing::{info, warn, error, Instrument};
+use fukurow_observability::telemetry;
+use fukurow_observability::tracing::{attributes, headers, spans};
+use crate::request_trace::current_request_id;
+
+/// Metadata key of the API request ID that produced an event
+pub const REQUEST_ID_METADATA_KEY: &str = "request_id";
 
 /// SIEMシステムタイプ
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,12 +71,25 @@ impl SiemIntegrationManager {
             return Ok(());
         }
 
+        let request_id = current_request_id();
+        let event = match &request_id {
+            Some(id) => event.with_request_id(id),
+            None => event,
+        };
         let mut errors = Vec::new();
 
         for (name, config) in &self.configs {
-            match self.send_to_siem(name, config, &event).await {
+            let span = tracing::info_span!(
+                spans::SIEM_SEND,
+                siem.name = %name,
+                request.id = request_id.as_deref(),
+                status.code = tracing::field::Empty,
+                error.type = tracing::field::Empty
+            );
+            match self.send_to_siem(name, config, &event).instrument(span.clone()).await {
                 Ok(_) => info!("Successfully sent event to SIEM: {}", name),
                 Err(e) => {
+                    span.record(attributes::ERROR_TYPE, tracing::field::display(&e));
                     warn!("Failed to send event to SIEM {}: {}", name, e);
                     errors.push((name.clone(), e));
                 }
@@ -116,6 +135,14 @@ impl SiemIntegrationManager {
             request = request.basic_auth(username, Some(password));
         }
 
+        // API リクエストと SIEM 側のログを突き合わせられるよう、リクエスト ID とトレースを渡す
+        if let Some(request_id) = event.metadata.get(REQUEST_ID_METADATA_KEY).and_then(|id| id.as_str()) {
+            request = request.header(headers::REQUEST_ID, request_id);
+        }
+        for (name, value) in telemetry::trace_headers(&tracing::Span::current()) {
+            request = request.header(name, value);
+        }
+
         // カスタムヘッダー
         for (key, value) in &config.custom_headers {
             request = request.header(key, value);
@@ -126,6 +153,7 @@ impl SiemIntegrationManager {
             .send()
             .await
             .map_err(|e| SiemError::NetworkError(e.to_string()))?;
+        tracing::Span::current().record(attributes::STATUS_CODE, response.status().as_u16());
 
         if !response.status().is_success() {
             let status = response.status();
@@ -231,6 +259,12 @@ impl SiemEvent {
     pub fn from_security_action(action: &fukurow_core::model::SecurityAction, host: String) -> Self {
         match action {
             fukurow_core::model::SecurityAction::Alert { severity, message, details } => {
+                // ATT&CK の技術 ID は SIEM 側で検索・集計できるようメタデータにも載せる
+                let mut metadata = HashMap::new();
+                let techniques = action.attack_techniques();
+                if !techniques.is_empty() {
+                    metadata.insert(fukurow_core::model::ATTACK_DETAILS_KEY.to_string(), serde_json::json!(techniques));
+                }
                 Self {
                     timestamp: chrono::Utc::now().to_rfc3339(),
                     level: severity.clone(),
@@ -240,7 +274,7 @@ impl SiemEvent {
                     event_type: "security_alert".to_string(),
                     severity: severity.clone(),
                     details: details.clone(),
-                    metadata: HashMap::new(),
+                    metadata,
                 }
             }
             fukurow_core::model::SecurityAction::IsolateHost { host_ip, reason } => {
@@ -316,6 +350,13 @@ impl SiemEvent {
         }
     }
 
+    /// Record the API request that produced this event (kept if already set)
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        self.metadata.entry(REQUEST_ID_METADATA_KEY.to_string())
+            .or_insert_with(|| serde_json::json!(request_id));
+        self
+    }
+
     /// 異常検知結果からSIEMイベントを作成
     pub fn from_anomaly_result(result: &::fukurow_domain_cyber::anomaly_detection::AnomalyResult, host: String) -> Self {
         Self {
@@ -439,6 +480,29 @@ mod tests {
         assert_eq!(event.host, "testhost");
         assert_eq!(event.source, "fukurow-reasoner");
         assert_eq!(event.event_type, "security_alert");
+        assert!(event.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_siem_event_carries_attack_techniques() {
+        let action = SecurityAction::Alert {
+            severity: "high".to_string(),
+            message: "Brute force".to_string(),
+            details: serde_json::json!({}),
+        }.with_attack_techniques(&["T1110".to_string()]);
+
+        let event = SiemEvent::from_security_action(&action, "testhost".to_string());
+        assert_eq!(event.metadata["mitre_attack"], serde_json::json!(["T1110"]));
+        assert_eq!(event.details["mitre_attack"]["techniques"], serde_json::json!(["T1110"]));
+    }
+
+    #[test]
+    fn test_siem_event_carries_request_id() {
+        let action = SecurityAction::IsolateHost { host_ip: "10.0.0.5".to_string(), reason: "C2".to_string() };
+        let event = SiemEvent::from_security_action(&action, "testhost".to_string())
+            .with_request_id("req-1")
+            .with_request_id("req-2");
+        assert_eq!(event.metadata[REQUEST_ID_METADATA_KEY], "req-1");
     }
 
     #[test]
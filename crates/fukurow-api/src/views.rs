@@ -0,0 +1,191 @@
+//! Materialized views
+//!
+//! `PUT /views/:name` で CONSTRUCT クエリを登録すると、結果をすぐに名前付きグラフへ書き込み、
+//! `refresh_interval_secs` を指定した場合は Tokio タスクで定期的に再計算する。
+//! 再計算の失敗はビューの状態 (`last_error`) に残し、グラフは直前の結果のまま保つ
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use fukurow_engine::ReasonerEngine;
+use fukurow_sparql::{MaterializationReport, MaterializedView, SparqlError};
+use fukurow_store::TenantId;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+/// View definition with the outcome of its latest refresh (`GET /views`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewStatus {
+    #[serde(flatten)]
+    pub view: MaterializedView,
+    pub last_refresh: Option<MaterializationReport>,
+    /// Error of the latest refresh (cleared by the next success)
+    pub last_error: Option<String>,
+}
+
+struct ViewEntry {
+    status: ViewStatus,
+    /// Scheduled refresh, aborted when the view is replaced or removed
+    task: Option<JoinHandle<()>>,
+}
+
+impl Drop for ViewEntry {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+type ViewTable = Mutex<HashMap<(TenantId, String), ViewEntry>>;
+
+/// Materialized views of every tenant
+#[derive(Clone, Default)]
+pub struct ViewManager {
+    views: Arc<ViewTable>,
+}
+
+impl ViewManager {
+    /// Materialize `view` on `engine` and keep it (replacing a view of the same name)
+    ///
+    /// 最初の計算が失敗したビューは登録しない
+    pub async fn define(&self, tenant: TenantId, engine: Arc<ReasonerEngine>, view: MaterializedView) -> Result<ViewStatus, SparqlError> {
+        let report = Self::materialize(&engine, &view).await?;
+        let status = ViewStatus { view: view.clone(), last_refresh: Some(report), last_error: None };
+        let task = view.refresh_interval_secs.map(|secs| {
+            Self::spawn_refresh(Arc::downgrade(&self.views), tenant.clone(), engine, view.clone(), Duration::from_secs(secs))
+        });
+        let entry = ViewEntry { status: status.clone(), task };
+        self.views.lock().unwrap().insert((tenant, view.name), entry);
+        Ok(status)
+    }
+
+    /// Recompute view `name` now (`None` when it does not exist)
+    pub async fn refresh(&self, tenant: &TenantId, name: &str, engine: &ReasonerEngine) -> Option<Result<ViewStatus, SparqlError>> {
+        let view = self.get(tenant, name)?.view;
+        match Self::materialize(engine, &view).await {
+            Ok(report) => {
+                let fallback = ViewStatus { view, last_refresh: Some(report.clone()), last_error: None };
+                Some(Ok(Self::record(&self.views, tenant, name, Ok(report)).unwrap_or(fallback)))
+            }
+            Err(e) => {
+                Self::record(&self.views, tenant, name, Err(e.to_string()));
+                Some(Err(e))
+            }
+        }
+    }
+
+    pub fn get(&self, tenant: &TenantId, name: &str) -> Option<ViewStatus> {
+        self.views.lock().unwrap().get(&(tenant.clone(), name.to_string())).map(|entry| entry.status.clone())
+    }
+
+    /// Views of `tenant`, sorted by name
+    pub fn list(&self, tenant: &TenantId) -> Vec<ViewStatus> {
+        let mut views: Vec<ViewStatus> = self.views.lock().unwrap().iter()
+            .filter(|((owner, _), _)| owner == tenant)
+            .map(|(_, entry)| entry.status.clone())
+            .collect();
+        views.sort_by(|a, b| a.view.name.cmp(&b.view.name));
+        views
+    }
+
+    /// Stop refreshing view `name` and drop its graph
+    pub async fn remove(&self, tenant: &TenantId, name: &str, engine: &ReasonerEngine) -> Option<ViewStatus> {
+        let entry = self.views.lock().unwrap().remove(&(tenant.clone(), name.to_string()))?;
+        let store = engine.get_graph_store().await;
+        store.write().await.clear_graph(&entry.status.view.graph);
+        Some(entry.status.clone())
+    }
+
+    async fn materialize(engine: &ReasonerEngine, view: &MaterializedView) -> Result<MaterializationReport, SparqlError> {
+        let store = engine.get_graph_store().await;
+        let mut store = store.write().await;
+        view.refresh(&mut store)
+    }
+
+    /// Record a refresh outcome on the view, if it still exists
+    fn record(views: &ViewTable, tenant: &TenantId, name: &str, outcome: Result<MaterializationReport, String>) -> Option<ViewStatus> {
+        let mut views = views.lock().unwrap();
+        let entry = views.get_mut(&(tenant.clone(), name.to_string()))?;
+        match outcome {
+            Ok(report) => {
+                entry.status.last_refresh = Some(report);
+                entry.status.last_error = None;
+            }
+            Err(e) => entry.status.last_error = Some(e),
+        }
+        Some(entry.status.clone())
+    }
+
+    /// Refresh every `interval`; the task ends once the manager is dropped
+    fn spawn_refresh(views: Weak<ViewTable>, tenant: TenantId, engine: Arc<ReasonerEngine>, view: MaterializedView, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval.max(Duration::from_secs(1)));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // 最初の tick は即座に返る。登録時に計算済みのため読み捨てる
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let outcome = Self::materialize(&engine, &view).await;
+                let Some(views) = views.upgrade() else { break };
+                if let Err(e) = &outcome {
+                    tracing::warn!("Refreshing materialized view {} failed: {}", view.name, e);
+                }
+                Self::record(&views, &tenant, &view.name, outcome.map_err(|e| e.to_string()));
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+
+    const QUERY: &str = "PREFIX ex: <http://example.org/>\nCONSTRUCT {\n?host ex:talksTo ?dest .\n}\nWHERE {\n?conn ex:source ?host .\n?conn ex:dest ?dest .\n}";
+
+    async fn connect(engine: &ReasonerEngine, conn: &str, source: &str, dest: &str) {
+        let store = engine.get_graph_store().await;
+        let mut store = store.write().await;
+        let sensor = Provenance::Sensor { source: "fw".to_string(), confidence: None };
+        for (predicate, object) in [("source", source), ("dest", dest)] {
+            let triple = Triple {
+                subject: format!("http://example.org/{}", conn),
+                predicate: format!("http://example.org/{}", predicate),
+                object: format!("http://example.org/{}", object),
+            };
+            store.insert(triple, GraphId::Default, sensor.clone());
+        }
+    }
+
+    async fn graph_size(engine: &ReasonerEngine, graph: &GraphId) -> usize {
+        engine.get_graph_store().await.read().await.get_graph(graph).len()
+    }
+
+    #[tokio::test]
+    async fn test_views_refresh_and_drop_their_graph() {
+        let manager = ViewManager::default();
+        let tenant = TenantId::default();
+        let engine = Arc::new(ReasonerEngine::new());
+        let graph = GraphId::Named("http://example.org/views/talks-to".to_string());
+        connect(&engine, "c1", "h1", "h2").await;
+
+        let view = MaterializedView::new("talks_to", QUERY, graph.clone()).unwrap();
+        let status = manager.define(tenant.clone(), Arc::clone(&engine), view).await.unwrap();
+        assert_eq!(status.last_refresh.map(|report| report.triples), Some(1));
+        assert_eq!(graph_size(&engine, &graph).await, 1);
+
+        connect(&engine, "c2", "h3", "h2").await;
+        let status = manager.refresh(&tenant, "talks_to", &engine).await.unwrap().unwrap();
+        assert_eq!(status.last_refresh.map(|report| (report.triples, report.replaced)), Some((2, 1)));
+        assert_eq!(graph_size(&engine, &graph).await, 2);
+        assert!(manager.list(&TenantId::new("other").unwrap()).is_empty());
+
+        assert!(manager.refresh(&tenant, "missing", &engine).await.is_none());
+        assert!(manager.remove(&tenant, "talks_to", &engine).await.is_some());
+        assert_eq!(graph_size(&engine, &graph).await, 0);
+        assert!(manager.list(&tenant).is_empty());
+    }
+}
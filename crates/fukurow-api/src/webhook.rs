@@ -0,0 +1,922 @@
+//! Webhook receivers for sensor formats
+//!
+//! 多くのセンサーは syslog / CEF / ECS JSON を URL に POST することしかできないため、
+//! `POST /ingest/:route` でそれらを受け取り CyberEvent に変換する。
+//! - パース: 各形式をフラットなフィールド (`src`, `source.ip` など) のレコードにする
+//! - マッピング: [`MappingRule`] の並びで最初に変換できたルールを採用する。ルールは設定で追加できる
+//! - 認証: ルートごとの共有シークレット (`X-Webhook-Secret` または `Authorization: Bearer`)
+
+use chrono::{Datelike, NaiveDateTime, TimeZone, Utc};
+use axum::http::{header, HeaderMap};
+use fukurow_core::model::CyberEvent;
+use fukurow_store::TenantId;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::auth::{AuthConfig, AuthError, Principal, Role};
+
+/// Header carrying a route's shared secret
+pub const WEBHOOK_SECRET_HEADER: &str = "x-webhook-secret";
+
+/// Record fields carrying the sensor's own event ID (used for deduplication)
+pub const EVENT_ID_FIELDS: &[&str] = &["event.id", "externalId"];
+
+/// Flat field record parsed from one sensor message
+pub type WebhookRecord = BTreeMap<String, String>;
+
+/// Event mapped from one sensor message
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    pub event: CyberEvent,
+    /// Sensor-assigned event ID, if the message carried one
+    pub event_id: Option<String>,
+}
+
+/// Sensor payload format accepted by a webhook route
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// ArcSight Common Event Format, one message per line (a syslog prefix is allowed)
+    Cef,
+    /// Elastic Common Schema JSON: an object, an array or NDJSON
+    Ecs,
+    /// RFC 5424 / RFC 3164 syslog, one message per line
+    Syslog,
+}
+
+/// Webhook payload errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid CEF message: {0}")]
+    InvalidCef(String),
+    #[error("Invalid ECS document: {0}")]
+    InvalidEcs(String),
+    #[error("Invalid syslog message: {0}")]
+    InvalidSyslog(String),
+    #[error("Invalid message pattern {pattern}: {error}")]
+    InvalidPattern { pattern: String, error: String },
+    #[error("No mapping rule matched the message")]
+    Unmapped,
+}
+
+/// Split a request body into records
+pub fn parse_records(format: WebhookFormat, body: &str, patterns: &[Regex]) -> Vec<Result<WebhookRecord, WebhookError>> {
+    match format {
+        WebhookFormat::Cef => non_empty_lines(body).map(parse_cef).collect(),
+        WebhookFormat::Syslog => non_empty_lines(body).map(|line| parse_syslog(line, patterns)).collect(),
+        WebhookFormat::Ecs => parse_ecs(body),
+    }
+}
+
+fn non_empty_lines(body: &str) -> impl Iterator<Item = &str> {
+    body.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+/// Parse `CEF:Version|Vendor|Product|Version|SignatureID|Name|Severity|Extension`
+///
+/// ヘッダは `cef.*`、拡張は `src=10.0.0.1` のようにキー名のまま格納する。
+/// `cs1Label=user cs1=alice` のようなカスタムラベルはラベル名でも引けるようにする
+pub fn parse_cef(message: &str) -> Result<WebhookRecord, WebhookError> {
+    let start = message.find("CEF:").ok_or_else(|| WebhookError::InvalidCef("missing CEF: header".to_string()))?;
+    let mut record = WebhookRecord::new();
+    let prefix = message[..start].trim();
+    if !prefix.is_empty() {
+        record.insert("syslog.prefix".to_string(), prefix.to_string());
+    }
+
+    let mut header = Vec::new();
+    let mut current = String::new();
+    let mut chars = message[start + 4..].chars();
+    while header.len() < 7 {
+        match chars.next() {
+            Some('\\') => match chars.next() {
+                Some(c @ ('|' | '\\')) => current.push(c),
+                Some(c) => {
+                    current.push('\\');
+                    current.push(c);
+                }
+                None => current.push('\\'),
+            },
+            Some('|') => header.push(std::mem::take(&mut current)),
+            Some(c) => current.push(c),
+            None => break,
+        }
+    }
+    if header.len() < 7 {
+        return Err(WebhookError::InvalidCef(format!("expected 7 header fields, found {}", header.len() + 1)));
+    }
+
+    let names = ["cef.version", "cef.device_vendor", "cef.device_product", "cef.device_version", "cef.signature_id", "cef.name", "cef.severity"];
+    for (name, value) in names.iter().zip(header) {
+        record.insert(name.to_string(), value.trim().to_string());
+    }
+    let extension: String = chars.collect();
+    record.extend(parse_cef_extension(&extension));
+
+    // カスタムラベル (csN / cnN / flexStringN) をラベル名でも参照できるようにする
+    let labelled: Vec<(String, String)> = record.iter()
+        .filter_map(|(key, label)| key.strip_suffix("Label").map(|base| (base, label)))
+        .filter_map(|(base, label)| record.get(base).map(|value| (label.clone(), value.clone())))
+        .collect();
+    for (label, value) in labelled {
+        record.entry(label).or_insert(value);
+    }
+    Ok(record)
+}
+
+/// Parse CEF extension `key=value` pairs; values may contain spaces and `\=` escapes
+fn parse_cef_extension(extension: &str) -> Vec<(String, String)> {
+    let bytes = extension.as_bytes();
+    // エスケープされていない '=' の位置
+    let mut separators = Vec::new();
+    let mut escaped = false;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if escaped {
+            escaped = false;
+        } else if byte == b'\\' {
+            escaped = true;
+        } else if byte == b'=' {
+            separators.push(i);
+        }
+    }
+
+    // 各 '=' の直前の空白区切りトークンがキー。値は次のキーの手前まで
+    let keys: Vec<(usize, usize)> = separators.iter()
+        .map(|&eq| (extension[..eq].rfind(' ').map(|space| space + 1).unwrap_or(0), eq))
+        .collect();
+    let mut pairs = Vec::new();
+    for (index, &(key_start, eq)) in keys.iter().enumerate() {
+        let key = &extension[key_start..eq];
+        if key.is_empty() {
+            continue;
+        }
+        let value_end = keys.get(index + 1).map(|&(next_start, _)| next_start).unwrap_or(extension.len());
+        let value = extension[eq + 1..value_end.max(eq + 1)].trim();
+        pairs.push((key.to_string(), unescape_cef_value(value)));
+    }
+    pairs
+}
+
+fn unescape_cef_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Parse an RFC 5424 or RFC 3164 syslog line
+///
+/// メッセージ本体に CEF があれば CEF として展開する。そうでなければ `patterns` の名前付き
+/// キャプチャと `key=value` の組をフィールドにする
+pub fn parse_syslog(line: &str, patterns: &[Regex]) -> Result<WebhookRecord, WebhookError> {
+    let mut record = WebhookRecord::new();
+    let mut rest = line.trim();
+
+    if let Some(after) = rest.strip_prefix('<') {
+        let end = after.find('>').ok_or_else(|| WebhookError::InvalidSyslog("unterminated PRI".to_string()))?;
+        let pri: u8 = after[..end].parse().map_err(|_| WebhookError::InvalidSyslog(format!("invalid PRI: {}", &after[..end])))?;
+        record.insert("syslog.facility".to_string(), (pri / 8).to_string());
+        record.insert("syslog.severity".to_string(), (pri % 8).to_string());
+        rest = &after[end + 1..];
+    }
+
+    let message = if let Some(after) = rest.strip_prefix("1 ") {
+        // RFC 5424: TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG
+        let mut parts = after.splitn(6, ' ');
+        for name in ["timestamp", "hostname", "appname", "procid", "msgid"] {
+            match parts.next() {
+                Some("-") => {}
+                Some(value) => {
+                    record.insert(name.to_string(), value.to_string());
+                }
+                None => return Err(WebhookError::InvalidSyslog("truncated RFC 5424 header".to_string())),
+            }
+        }
+        let remainder = parts.next().unwrap_or("");
+        let message = parse_structured_data(remainder, &mut record);
+        message.trim_start_matches('\u{feff}').trim()
+    } else {
+        parse_rfc3164_header(rest, &mut record)
+    };
+
+    record.insert("message".to_string(), message.to_string());
+    if message.contains("CEF:") {
+        record.extend(parse_cef(message)?);
+        return Ok(record);
+    }
+    for pattern in patterns {
+        if let Some(captures) = pattern.captures(message) {
+            for name in pattern.capture_names().flatten() {
+                if let Some(value) = captures.name(name) {
+                    record.insert(name.to_string(), value.as_str().to_string());
+                }
+            }
+        }
+    }
+    for (key, value) in key_value_pairs(message) {
+        record.entry(key).or_insert(value);
+    }
+    Ok(record)
+}
+
+/// `MMM dd HH:MM:SS HOSTNAME TAG[PID]: MSG`; returns the message
+fn parse_rfc3164_header<'a>(rest: &'a str, record: &mut WebhookRecord) -> &'a str {
+    let mut rest = rest;
+    // タイムスタンプは固定長 15 文字 ("Oct 16 09:12:44")
+    if rest.len() > 15 && rest.is_char_boundary(15) && parse_timestamp(&rest[..15]).is_some() {
+        record.insert("timestamp".to_string(), rest[..15].to_string());
+        rest = rest[15..].trim_start();
+        if let Some((hostname, after)) = rest.split_once(' ') {
+            if !hostname.ends_with(':') {
+                record.insert("hostname".to_string(), hostname.to_string());
+                rest = after;
+            }
+        }
+    }
+    if let Some((tag, message)) = rest.split_once(": ") {
+        if !tag.is_empty() && !tag.contains(' ') {
+            match tag.split_once('[') {
+                Some((app, pid)) => {
+                    record.insert("appname".to_string(), app.to_string());
+                    record.insert("procid".to_string(), pid.trim_end_matches(']').to_string());
+                }
+                None => {
+                    record.insert("appname".to_string(), tag.to_string());
+                }
+            }
+            return message.trim();
+        }
+    }
+    rest.trim()
+}
+
+/// Consume RFC 5424 structured data (`-` or `[id k="v" ...]...`), recording its parameters
+fn parse_structured_data<'a>(remainder: &'a str, record: &mut WebhookRecord) -> &'a str {
+    if let Some(message) = remainder.strip_prefix('-') {
+        return message;
+    }
+    let mut rest = remainder;
+    while rest.starts_with('[') {
+        let mut end = None;
+        let mut escaped = false;
+        let mut in_value = false;
+        for (i, c) in rest.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_value = !in_value,
+                ']' if !in_value => {
+                    end = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let end = match end {
+            Some(end) => end,
+            None => break,
+        };
+        let element = &rest[1..end];
+        let params = element.split_once(' ').map(|(_, params)| params).unwrap_or("");
+        for (key, value) in key_value_pairs(params) {
+            record.insert(key, value);
+        }
+        rest = &rest[end + 1..];
+    }
+    rest
+}
+
+/// `key=value` / `key="quoted value"` pairs in free text
+fn key_value_pairs(text: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut rest = text;
+    while let Some(eq) = rest.find('=') {
+        let key_start = rest[..eq].rfind([' ', '\t']).map(|i| i + 1).unwrap_or(0);
+        let key = &rest[key_start..eq];
+        let after = &rest[eq + 1..];
+        let (value, consumed) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(close) => (&quoted[..close], close + 2),
+                None => (quoted, after.len()),
+            },
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        let valid_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if valid_key && !value.is_empty() {
+            pairs.push((key.to_string(), value.to_string()));
+        }
+        rest = &after[consumed.min(after.len())..];
+    }
+    pairs
+}
+
+/// Parse ECS JSON (object, array of objects or NDJSON) into dotted-key records
+pub fn parse_ecs(body: &str) -> Vec<Result<WebhookRecord, WebhookError>> {
+    let trimmed = body.trim();
+    let documents: Vec<Result<Value, WebhookError>> = match serde_json::from_str::<Value>(trimmed) {
+        Ok(Value::Array(items)) => items.into_iter().map(Ok).collect(),
+        Ok(value) => vec![Ok(value)],
+        Err(_) => non_empty_lines(trimmed)
+            .map(|line| serde_json::from_str(line).map_err(|e| WebhookError::InvalidEcs(e.to_string())))
+            .collect(),
+    };
+    documents.into_iter()
+        .map(|document| {
+            let document = document?;
+            if !document.is_object() {
+                return Err(WebhookError::InvalidEcs("expected a JSON object".to_string()));
+            }
+            let mut record = WebhookRecord::new();
+            flatten_json("", &document, &mut record);
+            Ok(record)
+        })
+        .collect()
+}
+
+/// Flatten nested objects into dotted keys; arrays of scalars become comma-separated values
+fn flatten_json(prefix: &str, value: &Value, record: &mut WebhookRecord) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_json(&path, value, record);
+            }
+        }
+        Value::Array(items) => {
+            let scalars: Vec<String> = items.iter().filter_map(scalar_text).collect();
+            if !scalars.is_empty() {
+                record.insert(prefix.to_string(), scalars.join(","));
+            }
+        }
+        Value::Null => {}
+        scalar => {
+            if let Some(text) = scalar_text(scalar) {
+                record.insert(prefix.to_string(), text);
+            }
+        }
+    }
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse a sensor timestamp into Unix seconds
+///
+/// 数値 (秒またはミリ秒)、RFC 3339、CEF の `MMM dd yyyy HH:mm:ss`、
+/// 年の無い RFC 3164 の `MMM dd HH:mm:ss` (今年とみなす) を受け付ける
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(number) = value.parse::<i64>() {
+        // 1e11 秒は西暦 5000 年を超えるので、それ以上はミリ秒とみなす
+        return Some(if number.abs() >= 100_000_000_000 { number / 1000 } else { number });
+    }
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(time.timestamp());
+    }
+    let collapsed = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    for format in ["%b %d %Y %H:%M:%S%.f", "%b %d %Y %H:%M:%S"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(&collapsed, format) {
+            return Some(Utc.from_utc_datetime(&time).timestamp());
+        }
+    }
+    let with_year = format!("{} {}", Utc::now().year(), collapsed);
+    NaiveDateTime::parse_from_str(&with_year, "%Y %b %d %H:%M:%S")
+        .ok()
+        .map(|time| Utc.from_utc_datetime(&time).timestamp())
+}
+
+/// Record -> CyberEvent rule
+///
+/// `fields` は CyberEvent のフィールド名から、値を探すレコードのフィールド名の候補 (先頭優先) への対応。
+/// 必須フィールドが埋まらない、または `when` の条件に合わないルールは適用されない。
+/// `timestamp` が得られなければ受信時刻を使う
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MappingRule {
+    /// CyberEvent variant, e.g. `NetworkConnection`
+    pub event_type: String,
+    /// Record field -> accepted values (case-insensitive, `*` wildcards); all must match
+    #[serde(default)]
+    pub when: BTreeMap<String, Vec<String>>,
+    /// Event field -> candidate record fields
+    #[serde(default)]
+    pub fields: BTreeMap<String, Vec<String>>,
+    /// Event field -> value used when no candidate is present
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
+}
+
+impl MappingRule {
+    pub fn new(event_type: impl Into<String>) -> Self {
+        Self { event_type: event_type.into(), when: BTreeMap::new(), fields: BTreeMap::new(), defaults: BTreeMap::new() }
+    }
+
+    pub fn when(mut self, field: impl Into<String>, values: &[&str]) -> Self {
+        self.when.insert(field.into(), values.iter().map(|value| value.to_string()).collect());
+        self
+    }
+
+    pub fn field(mut self, target: impl Into<String>, sources: &[&str]) -> Self {
+        self.fields.insert(target.into(), sources.iter().map(|source| source.to_string()).collect());
+        self
+    }
+
+    pub fn default_value(mut self, target: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defaults.insert(target.into(), value.into());
+        self
+    }
+
+    /// Build the event, or `None` when the rule does not apply to the record
+    pub fn apply(&self, record: &WebhookRecord) -> Option<CyberEvent> {
+        let applies = self.when.iter().all(|(field, patterns)| {
+            record.get(field).map(|value| patterns.iter().any(|pattern| wildcard_match(pattern, value))).unwrap_or(false)
+        });
+        if !applies {
+            return None;
+        }
+
+        let mut data = Map::new();
+        for (target, sources) in &self.fields {
+            let found = sources.iter()
+                .filter_map(|source| record.get(source))
+                .find(|value| !value.trim().is_empty())
+                .or_else(|| self.defaults.get(target));
+            if let Some(value) = found {
+                data.insert(target.clone(), coerce_field(target, value)?);
+            }
+        }
+        for (target, value) in &self.defaults {
+            if !data.contains_key(target) {
+                data.insert(target.clone(), coerce_field(target, value)?);
+            }
+        }
+        data.entry("timestamp").or_insert_with(|| Value::from(Utc::now().timestamp()));
+
+        let mut event = Map::new();
+        event.insert("type".to_string(), Value::String(self.event_type.clone()));
+        event.insert("data".to_string(), Value::Object(data));
+        serde_json::from_value(Value::Object(event)).ok()
+    }
+}
+
+/// Convert a record value into the JSON type of a CyberEvent field
+fn coerce_field(target: &str, value: &str) -> Option<Value> {
+    let value = value.trim();
+    match target {
+        "port" | "process_id" | "parent_process_id" | "status_code" => value.parse::<u64>().ok().map(Value::from),
+        "timestamp" => parse_timestamp(value).map(Value::from),
+        "success" => parse_outcome(value).map(Value::Bool),
+        "resolved_ips" | "urls" | "attachments" => Some(Value::Array(
+            value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(|item| Value::String(item.to_string())).collect(),
+        )),
+        "protocol" | "method" => Some(Value::String(value.to_ascii_lowercase())),
+        _ => Some(Value::String(value.to_string())),
+    }
+}
+
+fn parse_outcome(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "success" | "succeeded" | "accepted" | "allow" | "allowed" | "pass" => Some(true),
+        "false" | "0" | "failure" | "failed" | "fail" | "denied" | "deny" | "blocked" | "reject" | "rejected" => Some(false),
+        _ => None,
+    }
+}
+
+/// Case-insensitive match with `*` wildcards
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let value = value.to_ascii_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !value.starts_with(first) || !value[first.len()..].ends_with(last) || value.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Ordered mapping rules and syslog message patterns of a route
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventMapping {
+    /// Regexes with named captures applied to syslog messages
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Tried in order; the first rule producing an event wins
+    #[serde(default)]
+    pub rules: Vec<MappingRule>,
+}
+
+impl EventMapping {
+    /// Built-in mapping for a format
+    pub fn for_format(format: WebhookFormat) -> Self {
+        match format {
+            WebhookFormat::Cef => Self { patterns: Vec::new(), rules: cef_rules() },
+            WebhookFormat::Ecs => Self { patterns: Vec::new(), rules: ecs_rules() },
+            WebhookFormat::Syslog => {
+                // syslog 経由の CEF もそのまま変換できるように CEF のルールも含める
+                let mut rules = syslog_rules();
+                rules.extend(cef_rules());
+                Self { patterns: default_syslog_patterns(), rules }
+            }
+        }
+    }
+
+    /// Add a rule tried before the existing ones
+    pub fn with_rule(mut self, rule: MappingRule) -> Self {
+        self.rules.insert(0, rule);
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    pub fn compile_patterns(&self) -> Result<Vec<Regex>, WebhookError> {
+        self.patterns.iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| WebhookError::InvalidPattern { pattern: pattern.clone(), error: e.to_string() }))
+            .collect()
+    }
+
+    pub fn map(&self, record: &WebhookRecord) -> Result<CyberEvent, WebhookError> {
+        self.rules.iter().find_map(|rule| rule.apply(record)).ok_or(WebhookError::Unmapped)
+    }
+}
+
+fn cef_rules() -> Vec<MappingRule> {
+    vec![
+        MappingRule::new("UserLogin")
+            .when("cef.name", &["*login*", "*logon*", "*authentication*", "*sign-in*"])
+            .field("user", &["suser", "duser"])
+            .field("source_ip", &["src", "shost"])
+            .field("success", &["outcome", "act"])
+            .field("timestamp", &["rt", "end", "start"]),
+        MappingRule::new("HttpRequest")
+            .field("method", &["requestMethod"])
+            .field("url", &["request"])
+            .field("host", &["dhost", "dst"])
+            .field("user_agent", &["requestClientApplication"])
+            .field("source_ip", &["src"])
+            .field("status_code", &["cn1"])
+            .field("timestamp", &["rt", "end", "start"])
+            .default_value("user_agent", ""),
+        MappingRule::new("FileAccess")
+            .field("file_path", &["filePath", "fname"])
+            .field("access_type", &["act", "cef.name"])
+            .field("user", &["suser", "duser"])
+            .field("process_id", &["spid", "dpid"])
+            .field("timestamp", &["rt", "end", "start"])
+            .default_value("process_id", "0"),
+        MappingRule::new("ProcessExecution")
+            .field("process_id", &["dpid", "spid"])
+            .field("command_line", &["dproc", "sproc"])
+            .field("user", &["duser", "suser"])
+            .field("timestamp", &["rt", "end", "start"]),
+        MappingRule::new("NetworkConnection")
+            .field("source_ip", &["src"])
+            .field("dest_ip", &["dst"])
+            .field("port", &["dpt"])
+            .field("protocol", &["proto", "app"])
+            .field("timestamp", &["rt", "end", "start"])
+            .default_value("protocol", "tcp"),
+    ]
+}
+
+fn ecs_rules() -> Vec<MappingRule> {
+    const TIMESTAMP: &[&str] = &["@timestamp", "event.created", "event.ingested"];
+    vec![
+        MappingRule::new("UserLogin")
+            .when("event.category", &["authentication", "*authentication*"])
+            .field("user", &["user.name", "user.id"])
+            .field("source_ip", &["source.ip", "client.ip"])
+            .field("success", &["event.outcome"])
+            .field("timestamp", TIMESTAMP),
+        MappingRule::new("DnsQuery")
+            .field("query_name", &["dns.question.name"])
+            .field("query_type", &["dns.question.type"])
+            .field("source_ip", &["source.ip", "client.ip"])
+            .field("resolved_ips", &["dns.resolved_ip"])
+            .field("timestamp", TIMESTAMP)
+            .default_value("query_type", "A")
+            .default_value("resolved_ips", ""),
+        MappingRule::new("HttpRequest")
+            .field("method", &["http.request.method"])
+            .field("url", &["url.full", "url.original"])
+            .field("host", &["url.domain", "destination.domain", "host.name"])
+            .field("user_agent", &["user_agent.original"])
+            .field("source_ip", &["source.ip", "client.ip"])
+            .field("status_code", &["http.response.status_code"])
+            .field("timestamp", TIMESTAMP)
+            .default_value("user_agent", ""),
+        MappingRule::new("RegistryModification")
+            .field("key_path", &["registry.path", "registry.key"])
+            .field("value_name", &["registry.value"])
+            .field("value_data", &["registry.data.strings"])
+            .field("operation", &["event.action", "event.type"])
+            .field("process_id", &["process.pid"])
+            .field("user", &["user.name"])
+            .field("timestamp", TIMESTAMP)
+            .default_value("process_id", "0"),
+        MappingRule::new("FileAccess")
+            .field("file_path", &["file.path"])
+            .field("access_type", &["event.action", "event.type"])
+            .field("user", &["user.name"])
+            .field("process_id", &["process.pid"])
+            .field("timestamp", TIMESTAMP)
+            .default_value("process_id", "0"),
+        MappingRule::new("ProcessExecution")
+            .field("process_id", &["process.pid"])
+            .field("parent_process_id", &["process.parent.pid"])
+            .field("command_line", &["process.command_line", "process.executable", "process.name"])
+            .field("user", &["user.name"])
+            .field("timestamp", TIMESTAMP),
+        MappingRule::new("NetworkConnection")
+            .field("source_ip", &["source.ip", "client.ip"])
+            .field("dest_ip", &["destination.ip", "server.ip"])
+            .field("port", &["destination.port", "server.port"])
+            .field("protocol", &["network.transport", "network.protocol"])
+            .field("timestamp", TIMESTAMP)
+            .default_value("protocol", "tcp"),
+    ]
+}
+
+fn syslog_rules() -> Vec<MappingRule> {
+    vec![
+        MappingRule::new("UserLogin")
+            .field("user", &["user", "suser"])
+            .field("source_ip", &["src", "rhost"])
+            .field("success", &["outcome"])
+            .field("timestamp", &["timestamp"]),
+        MappingRule::new("NetworkConnection")
+            .field("source_ip", &["src", "SRC"])
+            .field("dest_ip", &["dst", "DST"])
+            .field("port", &["dpt", "DPT", "dport"])
+            .field("protocol", &["proto", "PROTO"])
+            .field("timestamp", &["timestamp"])
+            .default_value("protocol", "tcp"),
+    ]
+}
+
+fn default_syslog_patterns() -> Vec<String> {
+    vec![
+        // sshd: "Accepted password for alice from 10.0.0.5 port 52311 ssh2"
+        r"(?P<outcome>Accepted|Failed) \S+ for (?:invalid user )?(?P<user>\S+) from (?P<src>\S+)".to_string(),
+    ]
+}
+
+/// One webhook endpoint (`POST /ingest/:route`)
+#[derive(Clone)]
+pub struct WebhookRoute {
+    pub format: WebhookFormat,
+    /// Shared secret; without one the request must carry regular API credentials
+    secret: Option<String>,
+    /// Tenant receiving the events of secret-authenticated requests
+    pub tenant: TenantId,
+    /// Source label recorded with the events
+    pub source: String,
+    pub mapping: EventMapping,
+}
+
+impl WebhookRoute {
+    pub fn new(format: WebhookFormat) -> Self {
+        let source = match format {
+            WebhookFormat::Cef => "webhook:cef",
+            WebhookFormat::Ecs => "webhook:ecs",
+            WebhookFormat::Syslog => "webhook:syslog",
+        };
+        Self {
+            format,
+            secret: None,
+            tenant: TenantId::default(),
+            source: source.to_string(),
+            mapping: EventMapping::for_format(format),
+        }
+    }
+
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: TenantId) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    pub fn with_mapping(mut self, mapping: EventMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    pub fn has_secret(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    /// Check a presented secret in constant time
+    pub fn verify_secret(&self, presented: &str) -> bool {
+        match &self.secret {
+            Some(secret) => constant_time_eq(secret.as_bytes(), presented.as_bytes()),
+            None => false,
+        }
+    }
+
+    /// Identify the caller of `POST /ingest/<name>`
+    ///
+    /// シークレット付きのルートはシークレットだけで認証し、ルートのテナントに Ingest 権限で書き込む。
+    /// シークレットが無いルートは通常の API キー / JWT 認証を要求する
+    pub fn authenticate(&self, name: &str, headers: &HeaderMap, auth: &AuthConfig) -> Result<Principal, AuthError> {
+        if self.secret.is_none() {
+            let principal = auth.authenticate(headers)?;
+            if !principal.role.permits(Role::Ingest) {
+                return Err(AuthError::Forbidden { required: Role::Ingest, actual: principal.role });
+            }
+            return Ok(principal);
+        }
+
+        let presented = headers.get(WEBHOOK_SECRET_HEADER)
+            .or_else(|| headers.get(header::AUTHORIZATION))
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.strip_prefix("Bearer ").unwrap_or(value).trim())
+            .ok_or(AuthError::MissingCredentials)?;
+        if !self.verify_secret(presented) {
+            return Err(AuthError::InvalidWebhookSecret);
+        }
+        Ok(Principal::new(format!("webhook:{}", name), Role::Ingest).with_tenant(self.tenant.clone()))
+    }
+
+    /// Parse and map a request body; one result per message
+    pub fn decode(&self, body: &str) -> Result<Vec<Result<WebhookEvent, WebhookError>>, WebhookError> {
+        let patterns = self.mapping.compile_patterns()?;
+        Ok(parse_records(self.format, body, &patterns)
+            .into_iter()
+            .map(|record| {
+                let record = record?;
+                let event = self.mapping.map(&record)?;
+                let event_id = EVENT_ID_FIELDS.iter().find_map(|field| record.get(*field).cloned());
+                Ok(WebhookEvent { event, event_id })
+            })
+            .collect())
+    }
+}
+
+// シークレットをログに出さない
+impl std::fmt::Debug for WebhookRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookRoute")
+            .field("format", &self.format)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("tenant", &self.tenant)
+            .field("source", &self.source)
+            .field("mapping", &self.mapping)
+            .finish()
+    }
+}
+
+fn constant_time_eq(expected: &[u8], presented: &[u8]) -> bool {
+    let mut diff = expected.len() ^ presented.len();
+    for (i, byte) in expected.iter().enumerate() {
+        diff |= usize::from(byte ^ presented.get(i).copied().unwrap_or(0));
+    }
+    diff == 0
+}
+
+/// Webhook routes by name
+#[derive(Debug, Clone, Default)]
+pub struct WebhookConfig {
+    pub routes: HashMap<String, WebhookRoute>,
+}
+
+impl WebhookConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_route(mut self, name: impl Into<String>, route: WebhookRoute) -> Self {
+        self.routes.insert(name.into(), route);
+        self
+    }
+
+    pub fn route(&self, name: &str) -> Option<&WebhookRoute> {
+        self.routes.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_events(route: &WebhookRoute, body: &str) -> Vec<Result<CyberEvent, WebhookError>> {
+        route.decode(body).unwrap().into_iter().map(|decoded| decoded.map(|decoded| decoded.event)).collect()
+    }
+
+    #[test]
+    fn test_parse_cef_with_escapes_and_labels() {
+        let message = r"<134>Oct 16 09:12:44 fw01 CEF:0|Acme|Fire\|wall|1.0|100|Connection allowed|3|src=10.0.0.5 dst=198.51.100.7 dpt=443 proto=TCP msg=path a\=b c cs1Label=zone cs1=dmz";
+        let record = parse_cef(message).unwrap();
+        assert_eq!(record["cef.device_product"], "Fire|wall");
+        assert_eq!(record["msg"], "path a=b c");
+        assert_eq!(record["zone"], "dmz");
+
+        let event = EventMapping::for_format(WebhookFormat::Cef).map(&record).unwrap();
+        match event {
+            CyberEvent::NetworkConnection { source_ip, dest_ip, port, protocol, .. } => {
+                assert_eq!((source_ip.as_str(), dest_ip.as_str(), port, protocol.as_str()), ("10.0.0.5", "198.51.100.7", 443, "tcp"));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(parse_cef("CEF:0|Acme|only|three").is_err());
+    }
+
+    #[test]
+    fn test_syslog_patterns_and_structured_data() {
+        let route = WebhookRoute::new(WebhookFormat::Syslog);
+        let body = "<38>Oct 16 09:12:44 bastion sshd[811]: Failed password for invalid user admin from 203.0.113.9 port 52311 ssh2\n\
+                    <165>1 2026-10-16T09:12:44Z gw kernel - - [meta seq=\"7\"] SRC=10.0.0.8 DST=10.0.0.9 DPT=22 PROTO=TCP";
+        let events = decode_events(&route, body);
+        match &events[0] {
+            Ok(CyberEvent::UserLogin { user, source_ip, success, .. }) => {
+                assert_eq!((user.as_str(), source_ip.as_str(), *success), ("admin", "203.0.113.9", false));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match &events[1] {
+            Ok(CyberEvent::NetworkConnection { port, timestamp, .. }) => {
+                assert_eq!(*port, 22);
+                assert_eq!(*timestamp, parse_timestamp("2026-10-16T09:12:44Z").unwrap());
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let record = parse_syslog("<165>1 2026-10-16T09:12:44Z gw app - - [meta seq=\"7\"] hello", &[]).unwrap();
+        assert_eq!(record["seq"], "7");
+        assert_eq!(record["message"], "hello");
+    }
+
+    #[test]
+    fn test_ecs_mapping_and_custom_rules() {
+        let body = r#"{"@timestamp": "2026-10-16T09:12:44Z", "event": {"category": ["authentication"], "outcome": "success"}, "user": {"name": "alice"}, "source": {"ip": "10.0.0.5"}}
+{"dns": {"question": {"name": "evil.example", "type": "AAAA"}, "resolved_ip": ["2001:db8::1"]}, "source": {"ip": "10.0.0.6"}}
+{"sensor": {"kind": "usb"}, "device": {"owner": "bob"}}"#;
+        let route = WebhookRoute::new(WebhookFormat::Ecs);
+        let events = decode_events(&route, body);
+        assert!(matches!(&events[0], Ok(CyberEvent::UserLogin { success: true, .. })));
+        match &events[1] {
+            Ok(CyberEvent::DnsQuery { query_type, resolved_ips, .. }) => {
+                assert_eq!(query_type, "AAAA");
+                assert_eq!(resolved_ips, &vec!["2001:db8::1".to_string()]);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(matches!(events[2], Err(WebhookError::Unmapped)));
+
+        // 設定で追加したルールは組み込みのルールより先に試す
+        let mapping = EventMapping::for_format(WebhookFormat::Ecs).with_rule(
+            MappingRule::new("FileAccess")
+                .when("sensor.kind", &["USB"])
+                .field("user", &["device.owner"])
+                .default_value("file_path", "usb://removable")
+                .default_value("access_type", "mount")
+                .default_value("process_id", "0"),
+        );
+        let route = route.with_secret("s3cret").with_mapping(mapping);
+        assert!(matches!(&decode_events(&route, body)[2], Ok(CyberEvent::FileAccess { .. })));
+        assert!(route.verify_secret("s3cret"));
+        assert!(!route.verify_secret("s3cre"));
+        assert!(!format!("{:?}", route).contains("s3cret"));
+    }
+}
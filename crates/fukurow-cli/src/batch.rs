@@ -0,0 +1,279 @@
+//! Batch reasoning pipeline
+//!
+//! `pipeline run` サブコマンドの実装。サーバーを起動せずに、エクスポートしたイベント (NDJSON)・
+//! ルール・オントロジーを読み込んで推論を 1 回実行し、アクション・推論トリプル・所要時間を書き出す
+
+use fukurow_core::model::{CorrelatedAction, CyberEvent};
+use fukurow_engine::{ProcessingOptions, ReasonerEngine, ReasoningProfile};
+use fukurow_rules::{DslRule, Rule, SparqlRule};
+use fukurow_store::provenance::GraphId;
+use fukurow_store::store::RdfStore;
+use fukurow_store::{write_dataset, DatasetFormat};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Sensor name recorded on events read by the pipeline
+pub const BATCH_SOURCE: &str = "pipeline";
+/// Files written to the output directory
+pub const ACTIONS_FILE: &str = "actions.json";
+pub const INFERRED_FILE: &str = "inferred.nq";
+pub const REPORT_FILE: &str = "report.json";
+
+/// Inputs and outputs of one batch run
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Events, one JSON `CyberEvent` per line
+    pub input: PathBuf,
+    /// Rule files or directories (`*.json` DSL policies, `*.rq` / `*.sparql` SPARQL rules)
+    pub rules: Vec<PathBuf>,
+    /// Ontologies loaded before the events (Turtle / TriG, or N-Triples / N-Quads)
+    pub ontologies: Vec<PathBuf>,
+    /// Output directory, or the actions file (`*.json`) whose directory receives the other files
+    pub output: PathBuf,
+    /// Reasoning profile (engine defaults when unset)
+    pub profile: Option<ReasoningProfile>,
+    /// Reasoning workers (one per available core when unset)
+    pub workers: Option<usize>,
+}
+
+/// Paths written by a batch run
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchOutputs {
+    pub actions: PathBuf,
+    pub inferred: PathBuf,
+    pub report: PathBuf,
+}
+
+impl BatchOutputs {
+    /// `actions.json`, `inferred.nq` and `report.json` in `output`, or next to `output` when it names a JSON file
+    pub fn for_output(output: &Path) -> Self {
+        let (dir, actions) = if output.extension().is_some_and(|ext| ext == "json") {
+            (output.parent().map(Path::to_path_buf).unwrap_or_default(), output.to_path_buf())
+        } else {
+            (output.to_path_buf(), output.join(ACTIONS_FILE))
+        };
+        Self { actions, inferred: dir.join(INFERRED_FILE), report: dir.join(REPORT_FILE) }
+    }
+}
+
+/// Time spent in each phase (milliseconds)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchTimings {
+    pub load_ontologies_ms: u64,
+    pub load_rules_ms: u64,
+    pub load_events_ms: u64,
+    pub reasoning_ms: u64,
+    pub write_ms: u64,
+    pub total_ms: u64,
+}
+
+/// Summary written to `report.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub events: usize,
+    pub rules: usize,
+    pub ontology_triples: usize,
+    pub actions: usize,
+    pub inferred_triples: usize,
+    pub profile: Option<String>,
+    pub outputs: BatchOutputs,
+    pub timings: BatchTimings,
+}
+
+/// Run the pipeline and write its outputs
+pub async fn run_batch(config: &BatchConfig) -> Result<BatchReport> {
+    let started = Instant::now();
+    let mut timings = BatchTimings::default();
+    let elapsed = |since: Instant| since.elapsed().as_millis() as u64;
+
+    let phase = Instant::now();
+    let mut store = RdfStore::new();
+    let mut ontology_triples = 0;
+    for path in &config.ontologies {
+        ontology_triples += load_ontology(&mut store, path)?;
+    }
+    timings.load_ontologies_ms = elapsed(phase);
+
+    let phase = Instant::now();
+    let rules = load_rules(&config.rules)?;
+    let rule_count = rules.len();
+    let options = ProcessingOptions { max_workers: config.workers, ..ProcessingOptions::default() };
+    let engine = rules.into_iter().fold(
+        ReasonerEngine::with_store(store, options),
+        |engine, rule| engine.with_rule(rule),
+    );
+    timings.load_rules_ms = elapsed(phase);
+
+    let phase = Instant::now();
+    let events = read_events(&config.input)?;
+    let event_count = events.len();
+    for event in events {
+        engine.add_event_from(event, BATCH_SOURCE).await?;
+    }
+    timings.load_events_ms = elapsed(phase);
+
+    let phase = Instant::now();
+    let actions: Vec<CorrelatedAction> = match config.profile {
+        Some(profile) => engine.reason_with_profile(profile).await?,
+        None => engine.reason_correlated().await?,
+    };
+    timings.reasoning_ms = elapsed(phase);
+
+    let phase = Instant::now();
+    let outputs = BatchOutputs::for_output(&config.output);
+    for path in [&outputs.actions, &outputs.inferred, &outputs.report] {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+    }
+    std::fs::write(&outputs.actions, serde_json::to_string_pretty(&actions)?)
+        .with_context(|| format!("Failed to write {}", outputs.actions.display()))?;
+
+    let view = engine.fresh_query_view().await;
+    let inferred = view.all_triples().iter()
+        .filter(|(graph_id, _)| matches!(graph_id, GraphId::Inferred(_)))
+        .flat_map(|(_, triples)| triples);
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(&outputs.inferred)
+        .with_context(|| format!("Failed to write {}", outputs.inferred.display()))?);
+    let inferred_triples = write_dataset(inferred, DatasetFormat::NQuads, &mut writer)?;
+    std::io::Write::flush(&mut writer)?;
+    timings.write_ms = elapsed(phase);
+    timings.total_ms = elapsed(started);
+
+    let report = BatchReport {
+        events: event_count,
+        rules: rule_count,
+        ontology_triples,
+        actions: actions.len(),
+        inferred_triples,
+        profile: config.profile.map(|profile| profile.as_str().to_string()),
+        outputs,
+        timings,
+    };
+    std::fs::write(&report.outputs.report, serde_json::to_string_pretty(&report)?)
+        .with_context(|| format!("Failed to write {}", report.outputs.report.display()))?;
+    Ok(report)
+}
+
+/// Read NDJSON events, skipping blank lines
+pub fn read_events(path: &Path) -> Result<Vec<CyberEvent>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    content.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| format!("Invalid event at {}:{}", path.display(), index + 1))
+        })
+        .collect()
+}
+
+/// Load an ontology file into `store`; returns the number of triples
+///
+/// N-Triples / N-Quads 以外は Turtle (TriG のサブセット) として読む
+pub fn load_ontology(store: &mut RdfStore, path: &Path) -> Result<usize> {
+    let format = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("nt" | "nq" | "nquads") => DatasetFormat::NQuads,
+        _ => DatasetFormat::TriG,
+    };
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    store.import_dataset(file, format, &path.display().to_string())
+        .with_context(|| format!("Invalid ontology {}", path.display()))
+}
+
+/// Rules from files and directories (directories are read in file name order, not recursively)
+///
+/// `*.json` は DSL ポリシー、`*.rq` / `*.sparql` はファイル名をルール名とする SPARQL ルール。
+/// ディレクトリ内のその他のファイルは無視する
+pub fn load_rules(paths: &[PathBuf]) -> Result<Vec<Box<dyn Rule>>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<_>>()?;
+            entries.sort();
+            files.extend(entries.into_iter().filter(|entry| entry.is_file() && rule_kind(entry).is_some()));
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    files.iter()
+        .map(|file| {
+            let text = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+            let rule: Box<dyn Rule> = match rule_kind(file) {
+                Some(RuleKind::Policy) => Box::new(DslRule::new().with_json_policy(&text)
+                    .with_context(|| format!("Invalid policy {}", file.display()))?),
+                Some(RuleKind::Sparql) => {
+                    let name = file.file_stem().and_then(|stem| stem.to_str()).unwrap_or("sparql_rule");
+                    Box::new(SparqlRule::new(name, &text).with_context(|| format!("Invalid SPARQL rule {}", file.display()))?)
+                }
+                None => anyhow::bail!("Unsupported rule file {} (expected .json, .rq or .sparql)", file.display()),
+            };
+            Ok(rule)
+        })
+        .collect()
+}
+
+enum RuleKind {
+    Policy,
+    Sparql,
+}
+
+fn rule_kind(path: &Path) -> Option<RuleKind> {
+    match path.extension()?.to_str()? {
+        "json" => Some(RuleKind::Policy),
+        "rq" | "sparql" => Some(RuleKind::Sparql),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_paths() {
+        let outputs = BatchOutputs::for_output(Path::new("out/actions.json"));
+        assert_eq!(outputs.actions, PathBuf::from("out/actions.json"));
+        assert_eq!(outputs.inferred, PathBuf::from("out/inferred.nq"));
+        let outputs = BatchOutputs::for_output(Path::new("nightly"));
+        assert_eq!(outputs.actions, PathBuf::from("nightly/actions.json"));
+        assert_eq!(outputs.report, PathBuf::from("nightly/report.json"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_run_writes_actions_inferences_and_report() {
+        let dir = std::env::temp_dir().join(format!("fukurow-batch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("rules")).unwrap();
+        let event = r#"{"type": "NetworkConnection", "data": {"source_ip": "192.168.1.10", "dest_ip": "10.0.0.50", "port": 443, "protocol": "tcp", "timestamp": 1700000000}}"#;
+        std::fs::write(dir.join("events.ndjson"), format!("{}\n\n{}\n", event, event)).unwrap();
+        std::fs::write(dir.join("sec.ttl"), "@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+            <http://example.org/Beacon> rdfs:subClassOf <http://example.org/Threat> .\n").unwrap();
+        std::fs::write(dir.join("rules/connections.rq"), "CONSTRUCT { ?e <http://example.org/seen> \"yes\" } WHERE { ?e ?p \"10.0.0.50\" }").unwrap();
+        std::fs::write(dir.join("rules/README.md"), "ignored").unwrap();
+
+        let report = run_batch(&BatchConfig {
+            input: dir.join("events.ndjson"),
+            rules: vec![dir.join("rules")],
+            ontologies: vec![dir.join("sec.ttl")],
+            output: dir.join("out"),
+            profile: Some(ReasoningProfile::Rdfs),
+            workers: Some(2),
+        }).await.unwrap();
+
+        assert_eq!((report.events, report.rules, report.ontology_triples), (2, 1, 1));
+        assert!(report.inferred_triples > 0);
+        let actions: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("out/actions.json")).unwrap()).unwrap();
+        assert_eq!(actions.as_array().map(Vec::len), Some(report.actions));
+        assert!(std::fs::read_to_string(dir.join("out/inferred.nq")).unwrap().contains("<http://example.org/seen>"));
+        let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("out/report.json")).unwrap()).unwrap();
+        assert_eq!(written["events"], 2);
+        assert!(written["timings"]["total_ms"].is_u64());
+
+        assert!(read_events(&dir.join("sec.ttl")).unwrap_err().to_string().contains("sec.ttl:1"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,9 +1,14 @@
 //! CLI command definitions and handlers
 
 use clap::{Parser, Subcommand};
-use fukurow_engine::ReasonerEngine;
+use fukurow_engine::{ReasonerEngine, ReasoningProfile};
 use fukurow_core::model::CyberEvent;
+use fukurow_core::prefix::PrefixMap;
 use fukurow_domain_cyber::threat_intelligence::{ThreatProcessor, IndicatorType};
+use fukurow_store::SqliteBackend;
+use crate::batch::{run_batch, BatchConfig};
+use crate::packs::{install_pack, list_packs, verify_pack, DEFAULT_PACK_DIR};
+use crate::sparql::{render_query_result, result_count, ResultFormat};
 use std::path::PathBuf;
 use anyhow::Result;
 
@@ -34,15 +39,15 @@ pub enum Commands {
     /// Analyze a single event
     Analyze {
         /// Event data as JSON file
-        #[arg(short, long)]
+        #[arg(short, long, required_unless_present = "json")]
         file: Option<PathBuf>,
 
         /// Event data as JSON string
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "file")]
         json: Option<String>,
 
         /// Output format
-        #[arg(short, long, default_value = "text")]
+        #[arg(long, default_value = "text")]
         format: OutputFormat,
     },
 
@@ -61,19 +66,97 @@ pub enum Commands {
         format: OutputFormat,
     },
 
-    /// Query the knowledge graph
+    /// Run a SPARQL query against a persisted store or the session's graph
     Query {
-        /// Subject filter
-        #[arg(short, long)]
-        subject: Option<String>,
+        /// SPARQL query text
+        query: Option<String>,
 
-        /// Predicate filter
-        #[arg(short, long)]
-        predicate: Option<String>,
+        /// Read the SPARQL query from a file
+        #[arg(short = 'q', long = "query-file", conflicts_with = "query")]
+        query_file: Option<PathBuf>,
+
+        /// SQLite database holding the store (default: the graph built by this session's commands)
+        #[arg(long)]
+        store: Option<PathBuf>,
+
+        /// Result format
+        #[arg(short, long, default_value = "table")]
+        format: ResultFormat,
+
+        /// Print the optimized plan with estimated cardinalities instead of running the query
+        #[arg(long)]
+        explain: bool,
+
+        /// Declare a prefix for the query and the output (repeatable), e.g. `ex=http://example.org/`
+        #[arg(long = "prefix", value_name = "PREFIX=IRI")]
+        prefixes: Vec<String>,
 
-        /// Object filter
+        /// Print full IRIs instead of `prefix:local` in table and CSV output
+        #[arg(long)]
+        full_iris: bool,
+    },
+
+    /// Write the result of a CONSTRUCT query into a named graph of the store
+    Materialize {
+        /// SPARQL CONSTRUCT query text
+        query: Option<String>,
+
+        /// Read the query from a file
+        #[arg(short = 'q', long = "query-file", conflicts_with = "query")]
+        query_file: Option<PathBuf>,
+
+        /// SQLite database holding the store
+        #[arg(long, default_value = "fukurow.db")]
+        store: PathBuf,
+
+        /// Named graph IRI receiving the result (replaced on every run)
         #[arg(short, long)]
-        object: Option<String>,
+        graph: String,
+
+        /// Keep running and refresh the graph every N seconds
+        #[arg(long, value_name = "SECS")]
+        every: Option<u64>,
+
+        /// Declare a prefix for the query (repeatable), e.g. `ex=http://example.org/`
+        #[arg(long = "prefix", value_name = "PREFIX=IRI")]
+        prefixes: Vec<String>,
+    },
+
+    /// Check a persisted store for OWL consistency and explain an inconsistency
+    Consistency {
+        /// SQLite database holding the store
+        #[arg(long, default_value = "fukurow.db")]
+        store: PathBuf,
+
+        /// Reasoning profile (owl-dl uses the tableau, anything else OWL Lite)
+        #[arg(long, default_value = "owl-lite")]
+        profile: String,
+
+        /// Output format
+        #[arg(short, long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Suggest ontology classes and properties for a typed prefix, CURIE or partial IRI
+    Complete {
+        /// Typed text, e.g. `Ho`, `sec:Ho` or `https://w3id.org/security#Ho`
+        input: String,
+
+        /// SQLite database holding the store
+        #[arg(long, default_value = "fukurow.db")]
+        store: PathBuf,
+
+        /// Only suggest terms of this kind (class, object_property, datatype_property, annotation_property, property)
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// Maximum number of suggestions
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+
+        /// Declare a prefix for the input and the output (repeatable), e.g. `ex=http://example.org/`
+        #[arg(long = "prefix", value_name = "PREFIX=IRI")]
+        prefixes: Vec<String>,
 
         /// Output format
         #[arg(short, long, default_value = "text")]
@@ -86,6 +169,18 @@ pub enum Commands {
         command: ThreatCommands,
     },
 
+    /// File-based batch reasoning
+    Pipeline {
+        #[command(subcommand)]
+        command: PipelineCommands,
+    },
+
+    /// Detection pack management
+    Pack {
+        #[command(subcommand)]
+        command: PackCommands,
+    },
+
     /// Show system information
     Info,
 }
@@ -122,6 +217,68 @@ pub enum ThreatCommands {
     },
 }
 
+/// Batch pipeline subcommands
+#[derive(Subcommand)]
+pub enum PipelineCommands {
+    /// Load events, rules and ontologies, reason once and write the results (no server)
+    Run {
+        /// Events as NDJSON (one JSON event per line)
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Rule file or directory of `.json` policies and `.rq` / `.sparql` rules (repeatable)
+        #[arg(short, long)]
+        rules: Vec<PathBuf>,
+
+        /// Ontology file (Turtle, TriG, N-Triples or N-Quads; repeatable)
+        #[arg(long)]
+        ontology: Vec<PathBuf>,
+
+        /// Output directory, or the actions file whose directory receives `inferred.nq` and `report.json`
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Reasoning profile (none, rdfs, owl-lite or owl-dl)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Rules evaluated in parallel (defaults to the number of CPU cores)
+        #[arg(long)]
+        workers: Option<usize>,
+    },
+}
+
+/// Detection pack subcommands
+#[derive(Subcommand)]
+pub enum PackCommands {
+    /// Verify a pack and install it into the pack directory
+    Install {
+        /// Pack directory (containing `pack.json`)
+        path: PathBuf,
+
+        /// Directory packs are installed into
+        #[arg(long, default_value = DEFAULT_PACK_DIR)]
+        dir: PathBuf,
+
+        /// Replace an installed pack of the same or a newer version
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Load a pack and run its tests
+    Verify {
+        /// Pack directory (containing `pack.json`)
+        path: PathBuf,
+    },
+
+    /// List installed packs
+    List {
+        /// Directory packs are installed into
+        #[arg(long, default_value = DEFAULT_PACK_DIR)]
+        dir: PathBuf,
+    },
+}
+
 /// Output format options
 #[derive(Clone, Debug, PartialEq, clap::ValueEnum)]
 pub enum OutputFormat {
@@ -160,10 +317,26 @@ impl CommandExecutor {
             Commands::Serve { host, port } => self.execute_serve(host, port).await,
             Commands::Analyze { file, json, format } => self.execute_analyze(file, json, format).await,
             Commands::Process { input, output, format } => self.execute_process(input, output, format).await,
-            Commands::Query { subject, predicate, object, format } => {
-                self.execute_query(subject, predicate, object, format).await
+            Commands::Query { query, query_file, store, format, explain, prefixes, full_iris } => {
+                let query = read_query(query, query_file)?;
+                self.execute_query(query, store, format, explain, parse_prefixes(&prefixes)?, full_iris).await
+            }
+            Commands::Materialize { query, query_file, store, graph, every, prefixes } => {
+                let query = read_query(query, query_file)?;
+                let query = format!("{}{}", parse_prefixes(&prefixes)?.sparql_prologue(), query);
+                self.execute_materialize(query, store, graph, every).await
+            }
+            Commands::Consistency { store, profile, format } => {
+                let profile = profile.parse().map_err(anyhow::Error::msg)?;
+                self.execute_consistency(store, profile, format)
+            }
+            Commands::Complete { input, store, kind, limit, prefixes, format } => {
+                let kind = kind.as_deref().map(str::parse).transpose().map_err(anyhow::Error::msg)?;
+                self.execute_complete(input, store, kind, limit, parse_prefixes(&prefixes)?, format)
             }
             Commands::Threat { command } => self.execute_threat_command(command).await,
+            Commands::Pipeline { command } => self.execute_pipeline_command(command).await,
+            Commands::Pack { command } => self.execute_pack_command(command).await,
             Commands::Info => self.execute_info(),
         }
     }
@@ -171,8 +344,9 @@ impl CommandExecutor {
     async fn execute_serve(&self, host: String, port: u16) -> Result<CommandResult> {
         use fukurow_api::{ReasonerServer, ServerConfig};
 
-        let config = ServerConfig { host: host.clone(), port, max_connections: 100 };
-        let server = ReasonerServer::with_config(config);
+        let config = ServerConfig { host: host.clone(), port, max_connections: 100, ..Default::default() };
+        let monitoring = std::sync::Arc::new(fukurow_observability::DefaultHealthMonitor::new());
+        let server = ReasonerServer::with_config(config, monitoring);
 
         println!("Starting server on {}:{}", host, port);
         println!("Press Ctrl+C to stop");
@@ -267,41 +441,143 @@ impl CommandExecutor {
         })
     }
 
+    /// `--store` が無ければ、このセッションの `analyze` / `process` で取り込んだグラフに問い合わせる
     async fn execute_query(
         &self,
-        subject: Option<String>,
-        predicate: Option<String>,
-        object: Option<String>,
-        format: OutputFormat,
+        query: String,
+        store_path: Option<PathBuf>,
+        format: ResultFormat,
+        explain: bool,
+        prefixes: PrefixMap,
+        full_iris: bool,
     ) -> Result<CommandResult> {
-        let store = self.reasoner.get_graph_store().await;
-        let graph_store = store.read().await;
+        // `--prefix` の宣言はクエリ内の PREFIX より前に置く (クエリ側が優先)
+        let query = format!("{}{}", prefixes.sparql_prologue(), query);
 
-        let triples = graph_store.find_triples(
-            subject.as_deref(),
-            predicate.as_deref(),
-            object.as_deref(),
-        );
+        let Some(store_path) = store_path else {
+            let store = self.reasoner.get_graph_store().await;
+            let store = store.read().await;
+            return query_store(&query, &store, &format, explain, &prefixes, full_iris);
+        };
+        // 存在しないパスを開くと空の DB が作られてしまうため先に確認する
+        if !store_path.exists() {
+            return Err(anyhow::anyhow!("Store not found: {}", store_path.display()));
+        }
+        let store = SqliteBackend::open(&store_path)?.load_store()?;
+        query_store(&query, &store, &format, explain, &prefixes, full_iris)
+    }
 
-        let count = triples.len();
-        let result = match format {
+    /// `--every` を指定した場合は Ctrl+C まで再計算を繰り返す。毎回 DB から読み直すため、
+    /// 他のプロセスが書き込んだトリプルも次の再計算に反映される
+    async fn execute_materialize(
+        &self,
+        query: String,
+        store_path: PathBuf,
+        graph: String,
+        every: Option<u64>,
+    ) -> Result<CommandResult> {
+        if !store_path.exists() {
+            return Err(anyhow::anyhow!("Store not found: {}", store_path.display()));
+        }
+        let mut view = fukurow_sparql::MaterializedView::new("cli", query, fukurow_store::GraphId::Named(graph))?;
+        if let Some(secs) = every {
+            view = view.with_refresh_interval(secs);
+        }
+        let backend = SqliteBackend::open(&store_path)?;
+
+        loop {
+            let mut store = backend.load_store()?;
+            let report = view.refresh(&mut store)?;
+            backend.save_store(&store)?;
+            println!("Materialized {} triple(s) into {:?} (replaced {})", report.triples, report.graph, report.replaced);
+
+            let Some(secs) = view.refresh_interval_secs else {
+                return Ok(CommandResult {
+                    success: true,
+                    message: format!("{} triple(s) materialized", report.triples),
+                    data: Some(serde_json::to_value(&report)?),
+                });
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_secs(secs)) => {}
+                _ = tokio::signal::ctrl_c() => {
+                    return Ok(CommandResult {
+                        success: true,
+                        message: "Materialization stopped".to_string(),
+                        data: Some(serde_json::to_value(&report)?),
+                    });
+                }
+            }
+        }
+    }
+
+    /// 不整合なら、それだけで不整合になる極小の公理集合を表示して失敗を返す
+    fn execute_consistency(&self, store_path: PathBuf, profile: ReasoningProfile, format: OutputFormat) -> Result<CommandResult> {
+        if !store_path.exists() {
+            return Err(anyhow::anyhow!("Store not found: {}", store_path.display()));
+        }
+        let store = SqliteBackend::open(&store_path)?.load_store()?;
+        let report = fukurow_engine::owl_consistency_report(&store, profile)?;
+
+        match format {
+            OutputFormat::Text if report.consistent => {
+                println!("Consistent ({} axioms, {})", report.axioms, profile.as_str());
+            }
             OutputFormat::Text => {
-                let mut output = format!("Found {} triples:\n", count);
-                for triple in &triples {
-                    output.push_str(&format!("  {} {} {}\n", triple.triple.subject, triple.triple.predicate, triple.triple.object));
+                println!("Inconsistent ({} axioms, {}); justification of {} axiom(s):", report.axioms, profile.as_str(), report.justification.len());
+                for axiom in &report.justification {
+                    println!("  {:?}", axiom);
                 }
-                output
             }
-            OutputFormat::Json => serde_json::to_string(&triples)?,
-            OutputFormat::JsonPretty => serde_json::to_string_pretty(&triples)?,
-        };
+            OutputFormat::Json => println!("{}", serde_json::to_string(&report)?),
+            OutputFormat::JsonPretty => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
 
-        println!("{}", result);
+        Ok(CommandResult {
+            success: report.consistent,
+            message: if report.consistent { "Consistent".to_string() } else { format!("Inconsistent: {} axiom(s) in the justification", report.justification.len()) },
+            data: Some(serde_json::to_value(&report)?),
+        })
+    }
+
+    fn execute_complete(
+        &self,
+        input: String,
+        store_path: PathBuf,
+        kind: Option<fukurow_store::TermKind>,
+        limit: usize,
+        prefixes: PrefixMap,
+        format: OutputFormat,
+    ) -> Result<CommandResult> {
+        if !store_path.exists() {
+            return Err(anyhow::anyhow!("Store not found: {}", store_path.display()));
+        }
+        let store = SqliteBackend::open(&store_path)?.load_store()?;
+        let mut known = PrefixMap::default();
+        known.extend(&prefixes);
+        let mut completions = fukurow_store::complete_terms(store.ontology_terms(), &input, &known, kind);
+        completions.truncate(limit);
+
+        match format {
+            OutputFormat::Text => {
+                for completion in &completions {
+                    println!(
+                        "{:<40} {:<20} {:>8}  {}",
+                        completion.curie.as_deref().unwrap_or(&completion.iri),
+                        format!("{:?}", completion.kind),
+                        completion.usage_count,
+                        completion.label.as_deref().unwrap_or(""),
+                    );
+                }
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string(&completions)?),
+            OutputFormat::JsonPretty => println!("{}", serde_json::to_string_pretty(&completions)?),
+        }
 
         Ok(CommandResult {
             success: true,
-            message: format!("Found {} triples", count),
-            data: Some(serde_json::json!({ "triples": triples })),
+            message: format!("{} suggestion(s) for {}", completions.len(), input),
+            data: Some(serde_json::to_value(&completions)?),
         })
     }
 
@@ -377,6 +653,77 @@ impl CommandExecutor {
         }
     }
 
+    async fn execute_pipeline_command(&self, command: PipelineCommands) -> Result<CommandResult> {
+        match command {
+            PipelineCommands::Run { input, rules, ontology, output, profile, workers } => {
+                let profile = profile.map(|profile| profile.parse()).transpose().map_err(anyhow::Error::msg)?;
+                let report = run_batch(&BatchConfig { input, rules, ontologies: ontology, output, profile, workers }).await?;
+
+                println!(
+                    "Processed {} events with {} rules: {} actions, {} inferred triples in {} ms",
+                    report.events, report.rules, report.actions, report.inferred_triples, report.timings.total_ms
+                );
+                println!("Report written to {}", report.outputs.report.display());
+
+                Ok(CommandResult {
+                    success: true,
+                    message: format!("Processed {} events", report.events),
+                    data: Some(serde_json::to_value(&report)?),
+                })
+            }
+        }
+    }
+
+    async fn execute_pack_command(&self, command: PackCommands) -> Result<CommandResult> {
+        match command {
+            PackCommands::Install { path, dir, force } => {
+                let pack = install_pack(&path, &dir, force).await?;
+                println!("Installed {} {} to {}", pack.manifest.name, pack.manifest.version, pack.path.display());
+
+                Ok(CommandResult {
+                    success: true,
+                    message: format!("Installed pack {}", pack.manifest.name),
+                    data: Some(serde_json::to_value(&pack)?),
+                })
+            }
+            PackCommands::Verify { path } => {
+                let verification = verify_pack(&path).await?;
+                println!(
+                    "{} {}: {} rules, {} tests",
+                    verification.manifest.name, verification.manifest.version, verification.rules.len(), verification.tests.len()
+                );
+                for test in &verification.tests {
+                    let status = if test.passed { "PASS" } else { "FAIL" };
+                    println!("  {} {}", status, test.name);
+                    for failure in &test.failures {
+                        println!("       {}", failure);
+                    }
+                }
+
+                Ok(CommandResult {
+                    success: verification.passed(),
+                    message: format!("{} of {} pack tests passed", verification.tests.len() - verification.failed_tests().count(), verification.tests.len()),
+                    data: Some(serde_json::to_value(&verification)?),
+                })
+            }
+            PackCommands::List { dir } => {
+                let packs = list_packs(&dir)?;
+                if packs.is_empty() {
+                    println!("No packs installed in {}", dir.display());
+                }
+                for pack in &packs {
+                    println!("{} {}  {}", pack.manifest.name, pack.manifest.version, pack.manifest.description);
+                }
+
+                Ok(CommandResult {
+                    success: true,
+                    message: format!("{} packs installed", packs.len()),
+                    data: Some(serde_json::to_value(&packs)?),
+                })
+            }
+        }
+    }
+
     fn execute_info(&self) -> Result<CommandResult> {
         let info = serde_json::json!({
             "name": env!("CARGO_PKG_NAME"),
@@ -404,3 +751,60 @@ impl Default for CommandExecutor {
         Self::new()
     }
 }
+
+/// Run (or with `explain`, plan) `query` over `store` and print the result
+fn query_store(
+    query: &str,
+    store: &fukurow_store::RdfStore,
+    format: &ResultFormat,
+    explain: bool,
+    prefixes: &PrefixMap,
+    full_iris: bool,
+) -> Result<CommandResult> {
+    if explain {
+        let explanation = fukurow_sparql::explain_query(query, store)?;
+        match format {
+            ResultFormat::Json => println!("{}", serde_json::to_string_pretty(&explanation)?),
+            _ => print!("{}", explanation),
+        }
+        return Ok(CommandResult {
+            success: true,
+            message: format!("estimated {} result(s)", explanation.plan.estimated_rows),
+            data: Some(serde_json::to_value(&explanation)?),
+        });
+    }
+
+    let result = fukurow_sparql::execute_query(query, store)?;
+    let count = result_count(&result);
+    let mut display = PrefixMap::default();
+    display.extend(prefixes);
+    println!("{}", render_query_result(&result, format, (!full_iris).then_some(&display)));
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("{} result(s)", count),
+        data: Some(serde_json::json!({ "count": count })),
+    })
+}
+
+/// Query text from the argument or `--query-file`
+fn read_query(query: Option<String>, query_file: Option<PathBuf>) -> Result<String> {
+    if let Some(file_path) = query_file {
+        Ok(std::fs::read_to_string(file_path)?)
+    } else if let Some(query) = query {
+        Ok(query)
+    } else {
+        Err(anyhow::anyhow!("Either a query or --query-file must be specified"))
+    }
+}
+
+/// `--prefix ex=http://example.org/` declarations
+pub fn parse_prefixes(declarations: &[String]) -> Result<PrefixMap> {
+    let mut prefixes = PrefixMap::empty();
+    for declaration in declarations {
+        let (prefix, namespace) = declaration.split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid prefix declaration: {} (expected PREFIX=IRI)", declaration))?;
+        prefixes.insert(prefix.trim(), namespace.trim());
+    }
+    Ok(prefixes)
+}
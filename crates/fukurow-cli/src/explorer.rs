@@ -0,0 +1,291 @@
+//! Graph explorer for the interactive mode
+//!
+//! 保存済みのストアを開き、ノードから出る辺・入る辺をたどりながら各トリプルの出所を確認する。
+//! IRI と接頭辞は Tab で補完でき、入力履歴はセッションをまたいでファイルに保存される
+
+use anyhow::Result;
+use fukurow_core::prefix::{PrefixError, PrefixMap};
+use fukurow_core::term::RdfTerm;
+use fukurow_store::provenance::Provenance;
+use fukurow_store::store::{RdfStore, StoredTriple};
+use fukurow_store::SqliteBackend;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// History file name in the home directory
+pub const HISTORY_FILE: &str = ".fukurow_history";
+
+/// Environment variable overriding the history file location
+pub const HISTORY_ENV: &str = "FUKUROW_HISTORY";
+
+/// Commands offered for completion at the start of a line
+pub const EXPLORER_COMMANDS: &[&str] = &[
+    "open", "out", "in", "node", "prefix", "history",
+    "serve", "analyze", "process", "query", "threat", "info", "help", "clear", "quit",
+];
+
+/// Security vocabulary prefix declared in addition to the core defaults
+const SECURITY_PREFIX: (&str, &str) = ("sec", "https://w3id.org/security#");
+
+/// Direction of the edges listed for a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeDirection {
+    Outgoing,
+    Incoming,
+}
+
+/// Store opened for exploration, with the prefixes used to shorten IRIs
+pub struct GraphExplorer {
+    store: RdfStore,
+    prefixes: PrefixMap,
+}
+
+impl GraphExplorer {
+    pub fn new(store: RdfStore) -> Self {
+        let prefixes = PrefixMap::default().with_prefix(SECURITY_PREFIX.0, SECURITY_PREFIX.1);
+        Self { store, prefixes }
+    }
+
+    /// Load a store persisted in SQLite
+    pub fn open(path: &Path) -> Result<Self> {
+        // 存在しないパスを開くと空の DB が作られてしまうため先に確認する
+        if !path.exists() {
+            return Err(anyhow::anyhow!("Store not found: {}", path.display()));
+        }
+        Ok(Self::new(SqliteBackend::open(path)?.load_store()?))
+    }
+
+    pub fn store(&self) -> &RdfStore {
+        &self.store
+    }
+
+    pub fn prefixes(&self) -> &PrefixMap {
+        &self.prefixes
+    }
+
+    pub fn add_prefix(&mut self, prefix: &str, namespace: &str) {
+        self.prefixes.insert(prefix, namespace);
+    }
+
+    /// Stored form of a node typed by the user (`<iri>`, `prefix:local`, `_:label` or a full IRI)
+    pub fn resolve(&self, token: &str) -> Result<String> {
+        if let Some(iri) = token.strip_prefix('<').and_then(|rest| rest.strip_suffix('>')) {
+            return Ok(RdfTerm::iri(iri).encode());
+        }
+        if token.starts_with("_:") || token.contains("://") || token.starts_with("urn:") {
+            return Ok(token.to_string());
+        }
+        match self.prefixes.expand(token) {
+            Ok(iri) => Ok(iri),
+            Err(PrefixError::NotACurie(_)) => Err(anyhow::anyhow!("Not a node: {} (use <iri> or prefix:local)", token)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Shorten an IRI with the longest matching prefix; other terms are shown as stored
+    pub fn compact(&self, value: &str) -> String {
+        let iri = match RdfTerm::parse(value) {
+            RdfTerm::Iri(iri) => iri,
+            _ => return value.to_string(),
+        };
+        self.prefixes.display(&iri)
+    }
+
+    /// Triples leaving (`Outgoing`) or entering (`Incoming`) a node, ordered by predicate
+    pub fn edges(&self, node: &str, direction: EdgeDirection) -> Vec<&StoredTriple> {
+        let mut edges = match direction {
+            EdgeDirection::Outgoing => self.store.find_triples(Some(node), None, None),
+            EdgeDirection::Incoming => self.store.find_triples(None, None, Some(node)),
+        };
+        edges.sort_by(|a, b| {
+            (&a.triple.predicate, &a.triple.subject, &a.triple.object, a.asserted_at)
+                .cmp(&(&b.triple.predicate, &b.triple.subject, &b.triple.object, b.asserted_at))
+        });
+        edges
+    }
+
+    /// Edges of a node with the provenance of each triple on the line below it
+    pub fn render_edges(&self, node: &str, direction: EdgeDirection) -> String {
+        let edges = self.edges(node, direction);
+        let label = match direction {
+            EdgeDirection::Outgoing => "outgoing",
+            EdgeDirection::Incoming => "incoming",
+        };
+        let mut lines = vec![format!("{} ({} {} edge(s))", self.compact(node), edges.len(), label)];
+        for stored in edges {
+            let predicate = self.compact(&stored.triple.predicate);
+            lines.push(match direction {
+                EdgeDirection::Outgoing => format!("  --{}--> {}", predicate, self.compact(&stored.triple.object)),
+                EdgeDirection::Incoming => format!("  <--{}-- {}", predicate, self.compact(&stored.triple.subject)),
+            });
+            lines.push(format!(
+                "      graph {} | asserted {} | {}",
+                stored.graph_id,
+                format_timestamp(stored.asserted_at),
+                describe_provenance(&stored.provenance),
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Completion candidates: every IRI in the store (shortened when possible) and the declared prefixes
+    pub fn completion_terms(&self) -> Vec<String> {
+        let mut terms: BTreeSet<String> = self.prefixes.iter().map(|(prefix, _)| format!("{}:", prefix)).collect();
+        for stored in self.store.all_triples().values().flatten() {
+            let triple = &stored.triple;
+            for value in [&triple.subject, &triple.predicate, &triple.object] {
+                if RdfTerm::parse(value).is_iri() {
+                    terms.insert(self.compact(value));
+                }
+            }
+        }
+        terms.into_iter().collect()
+    }
+}
+
+/// One-line summary of where a triple came from
+pub fn describe_provenance(provenance: &Provenance) -> String {
+    match provenance {
+        Provenance::Sensor { source, confidence: Some(confidence) } => {
+            format!("sensor {} (confidence {:.2})", source, confidence)
+        }
+        Provenance::Sensor { source, confidence: None } => format!("sensor {}", source),
+        Provenance::Inferred { rule, reasoning_level, evidence, confidence } => {
+            let mut description = format!("inferred by {} [{}] from {} triple(s)", rule, reasoning_level, evidence.len());
+            if let Some(confidence) = confidence {
+                description.push_str(&format!(" (confidence {:.2})", confidence));
+            }
+            description
+        }
+        Provenance::Imported { source_uri, imported_at } => {
+            format!("imported from {} at {}", source_uri, format_timestamp(*imported_at))
+        }
+    }
+}
+
+/// Unix milliseconds as RFC 3339 (UTC)
+fn format_timestamp(millis: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis as i64)
+        .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .unwrap_or_else(|| millis.to_string())
+}
+
+/// Completions for the word before `pos`: commands for the first word, nodes and prefixes after it
+///
+/// 返り値は置き換える単語の開始位置と候補
+pub fn complete_word(line: &str, pos: usize, commands: &[&str], terms: &[String]) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before.rfind(char::is_whitespace).map(|index| index + 1).unwrap_or(0);
+    let word = &before[start..];
+
+    let candidates = if before[..start].trim().is_empty() {
+        commands.iter().filter(|command| command.starts_with(word)).map(|command| command.to_string()).collect()
+    } else {
+        terms.iter().filter(|term| term.starts_with(word)).cloned().collect()
+    };
+    (start, candidates)
+}
+
+/// Default location of the session history
+pub fn history_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(HISTORY_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+/// Line editor helper completing commands, prefixes and the IRIs of the open store
+#[derive(Default)]
+pub struct ExplorerHelper {
+    terms: Vec<String>,
+}
+
+impl ExplorerHelper {
+    /// Replace the completion terms (called when a store is opened or a prefix declared)
+    pub fn set_terms(&mut self, terms: Vec<String>) {
+        self.terms = terms;
+    }
+}
+
+impl Completer for ExplorerHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = complete_word(line, pos, EXPLORER_COMMANDS, &self.terms);
+        let pairs = candidates.into_iter()
+            .map(|candidate| Pair { display: candidate.clone(), replacement: candidate })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ExplorerHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ExplorerHelper {}
+
+impl Validator for ExplorerHelper {}
+
+impl Helper for ExplorerHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::GraphId;
+
+    fn explorer() -> GraphExplorer {
+        let mut store = RdfStore::new();
+        let edge = |from: &str, to: &str| Triple {
+            subject: format!("http://example.org/{}", from),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: format!("http://example.org/{}", to),
+        };
+        store.insert_at(edge("h1", "h2"), GraphId::Sensor("edr-1".to_string()),
+            Provenance::Sensor { source: "edr-1".to_string(), confidence: Some(0.9) }, 1_700_000_000_000);
+        store.insert_at(edge("h3", "h2"), GraphId::Inferred("transitive".to_string()), Provenance::Inferred {
+            rule: "transitive".to_string(),
+            reasoning_level: "owl-lite".to_string(),
+            evidence: vec!["t1".to_string(), "t2".to_string()],
+            confidence: None,
+        }, 1_700_000_000_000);
+
+        let mut explorer = GraphExplorer::new(store);
+        explorer.add_prefix("ex:", "<http://example.org/>");
+        explorer
+    }
+
+    #[test]
+    fn test_follow_edges_with_provenance() {
+        let explorer = explorer();
+        let h2 = explorer.resolve("ex:h2").unwrap();
+        assert_eq!(h2, "http://example.org/h2");
+        assert_eq!(explorer.resolve("<http://example.org/h2>").unwrap(), h2);
+        assert!(explorer.resolve("nope:h2").is_err());
+
+        assert_eq!(explorer.edges(&h2, EdgeDirection::Outgoing).len(), 0);
+        let incoming = explorer.render_edges(&h2, EdgeDirection::Incoming);
+        assert_eq!(incoming.lines().next(), Some("ex:h2 (2 incoming edge(s))"));
+        assert!(incoming.contains("<--ex:connectsTo-- ex:h1"));
+        assert!(incoming.contains("graph sensor:edr-1 | asserted 2023-11-14T22:13:20Z | sensor edr-1 (confidence 0.90)"));
+        assert!(incoming.contains("inferred by transitive [owl-lite] from 2 triple(s)"));
+    }
+
+    #[test]
+    fn test_complete_commands_and_nodes() {
+        let terms = explorer().completion_terms();
+        assert!(terms.contains(&"ex:connectsTo".to_string()));
+        assert!(terms.contains(&"rdf:".to_string()));
+
+        assert_eq!(complete_word("ou", 2, EXPLORER_COMMANDS, &terms), (0, vec!["out".to_string()]));
+        let (start, candidates) = complete_word("out ex:h", 8, EXPLORER_COMMANDS, &terms);
+        assert_eq!(start, 4);
+        assert_eq!(candidates, vec!["ex:h1", "ex:h2", "ex:h3"]);
+    }
+}
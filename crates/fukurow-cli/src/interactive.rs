@@ -1,19 +1,26 @@
 //! Interactive CLI mode
 
 use crate::commands::{CommandExecutor, Cli};
+use crate::explorer::{history_path, EdgeDirection, ExplorerHelper, GraphExplorer};
 use clap::Parser;
-use std::io::{self, Write};
+use rustyline::error::ReadlineError;
+use rustyline::history::FileHistory;
+use rustyline::Editor;
+use std::path::Path;
 use anyhow::Result;
 
 /// Interactive CLI session
 pub struct InteractiveSession {
     executor: CommandExecutor,
+    /// Store opened with `open`
+    explorer: Option<GraphExplorer>,
 }
 
 impl InteractiveSession {
     pub fn new() -> Self {
         Self {
             executor: CommandExecutor::new(),
+            explorer: None,
         }
     }
 
@@ -23,17 +30,27 @@ impl InteractiveSession {
         println!("Type 'help' for available commands, 'quit' to exit");
         println!("{}", "=".repeat(50));
 
-        loop {
-            print!("reasoner> ");
-            io::stdout().flush()?;
+        let mut editor: Editor<ExplorerHelper, FileHistory> = Editor::new()?;
+        editor.set_helper(Some(ExplorerHelper::default()));
+        let history = history_path();
+        if let Some(path) = &history {
+            // 初回起動時は履歴ファイルがないので読み込み失敗は無視する
+            let _ = editor.load_history(path);
+        }
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
+        loop {
+            let input = match editor.readline("reasoner> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            };
             let input = input.trim();
 
             if input.is_empty() {
                 continue;
             }
+            editor.add_history_entry(input)?;
 
             match input {
                 "quit" | "exit" | "q" => {
@@ -47,20 +64,38 @@ impl InteractiveSession {
                     // Clear screen (Unix-like systems)
                     print!("\x1B[2J\x1B[1;1H");
                 }
+                "history" => {
+                    for (index, entry) in editor.history().iter().enumerate() {
+                        println!("{:>5}  {}", index + 1, entry);
+                    }
+                }
                 _ => {
                     if let Err(e) = self.execute_command(input).await {
                         eprintln!("Error: {}", e);
                     }
+                    // 開いたストアや宣言した接頭辞を補完候補に反映する
+                    if let (Some(helper), Some(explorer)) = (editor.helper_mut(), &self.explorer) {
+                        helper.set_terms(explorer.completion_terms());
+                    }
                 }
             }
         }
 
+        if let Some(path) = &history {
+            if let Err(e) = editor.save_history(path) {
+                eprintln!("Could not save history to {}: {}", path.display(), e);
+            }
+        }
+
         Ok(())
     }
 
     async fn execute_command(&mut self, input: &str) -> Result<()> {
         // Parse the input as CLI arguments
         let args = shell_words::split(input)?;
+        if self.execute_explorer_command(&args)? {
+            return Ok(());
+        }
         let cli = match Cli::try_parse_from(args) {
             Ok(cli) => cli,
             Err(e) => {
@@ -78,19 +113,65 @@ impl InteractiveSession {
         Ok(())
     }
 
+    /// Graph explorer commands; returns false when `args` is not one of them
+    fn execute_explorer_command(&mut self, args: &[String]) -> Result<bool> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["open", path] => {
+                let explorer = GraphExplorer::open(Path::new(path))?;
+                println!("Opened {} ({} triples)", path, explorer.store().statistics().total_triples);
+                self.explorer = Some(explorer);
+            }
+            ["prefix"] => {
+                let explorer = self.explorer()?;
+                for (prefix, namespace) in explorer.prefixes().iter() {
+                    println!("{}: <{}>", prefix, namespace);
+                }
+            }
+            ["prefix", prefix, namespace] => self.explorer_mut()?.add_prefix(prefix, namespace),
+            [command @ ("out" | "in" | "node"), node] => {
+                let explorer = self.explorer()?;
+                let node = explorer.resolve(node)?;
+                if *command != "in" {
+                    println!("{}", explorer.render_edges(&node, EdgeDirection::Outgoing));
+                }
+                if *command != "out" {
+                    println!("{}", explorer.render_edges(&node, EdgeDirection::Incoming));
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn explorer(&self) -> Result<&GraphExplorer> {
+        self.explorer.as_ref().ok_or_else(|| anyhow::anyhow!("No store open; use 'open <db>' first"))
+    }
+
+    fn explorer_mut(&mut self) -> Result<&mut GraphExplorer> {
+        self.explorer.as_mut().ok_or_else(|| anyhow::anyhow!("No store open; use 'open <db>' first"))
+    }
+
     fn show_help(&self) {
         println!("Available commands:");
         println!("  serve [options]     Start API server");
         println!("  analyze [options]   Analyze single event");
         println!("  process [options]   Process events from file");
-        println!("  query [options]     Query knowledge graph");
+        println!("  query <sparql>      Run a SPARQL query against a stored graph");
         println!("  threat [subcommand] Threat intelligence operations");
         println!("  info                Show system information");
+        println!("  open <db>           Open a stored graph for exploration");
+        println!("  out <node>          Show outgoing edges of a node with provenance");
+        println!("  in <node>           Show incoming edges of a node with provenance");
+        println!("  node <node>         Show both directions");
+        println!("  prefix [<p> <iri>]  List or declare prefixes used for display and completion");
+        println!("  history             Show command history");
         println!("  help                Show this help");
         println!("  clear               Clear screen");
         println!("  quit                Exit interactive mode");
         println!();
         println!("Use '<command> --help' for detailed help on each command");
+        println!("Press Tab to complete commands, prefixes and IRIs of the open store");
     }
 }
 
@@ -105,3 +186,43 @@ pub async fn start_interactive() -> Result<()> {
     let mut session = InteractiveSession::new();
     session.run().await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+    use fukurow_store::store::RdfStore;
+    use fukurow_store::SqliteBackend;
+
+    fn args(input: &str) -> Vec<String> {
+        shell_words::split(input).unwrap()
+    }
+
+    #[test]
+    fn test_explorer_commands_need_an_open_store() {
+        let dir = std::env::temp_dir().join(format!("fukurow-cli-interactive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("graph.db");
+        let mut store = RdfStore::new();
+        store.insert(Triple {
+            subject: "http://example.org/h1".to_string(),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: "http://example.org/h2".to_string(),
+        }, GraphId::Default, Provenance::Sensor { source: "edr-1".to_string(), confidence: None });
+        SqliteBackend::open(&path).unwrap().save_store(&store).unwrap();
+
+        let mut session = InteractiveSession::new();
+        assert!(session.execute_explorer_command(&args("out ex:h1")).is_err());
+        assert!(session.execute_explorer_command(&args("open missing.db")).is_err());
+
+        assert!(session.execute_explorer_command(&args(&format!("open '{}'", path.display()))).unwrap());
+        assert!(session.execute_explorer_command(&args("prefix ex http://example.org/")).unwrap());
+        assert!(session.execute_explorer_command(&args("node ex:h1")).unwrap());
+        assert_eq!(session.explorer().unwrap().resolve("ex:h2").unwrap(), "http://example.org/h2");
+
+        // 探索コマンド以外は通常の CLI コマンドとして扱う
+        assert!(!session.execute_explorer_command(&args("query 'ASK { ?s ?p ?o }'")).unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
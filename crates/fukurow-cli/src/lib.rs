@@ -3,8 +3,16 @@
 //! JSON-LD Reasoner のコマンドラインインターフェース
 //! サイバーセキュリティイベントの推論をコマンドラインから実行
 
+pub mod batch;
 pub mod commands;
+pub mod explorer;
 pub mod interactive;
+pub mod packs;
+pub mod sparql;
 
+pub use batch::*;
 pub use commands::*;
+pub use explorer::*;
 pub use interactive::*;
+pub use packs::*;
+pub use sparql::*;
@@ -2,7 +2,6 @@
 
 use clap::Parser;
 use fukurow_cli::{commands::{Cli, CommandExecutor}, interactive::start_interactive};
-use tracing_subscriber;
 use anyhow::Result;
 
 #[tokio::main]
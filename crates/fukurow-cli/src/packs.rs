@@ -0,0 +1,165 @@
+//! Detection pack management
+//!
+//! `pack install/verify/list` サブコマンドの実装。パックはインストール先ディレクトリの
+//! `<パック名>/` にコピーし、インストール前に読み込みとテストの成功を確認する
+
+use fukurow_rules::{compare_versions, DetectionPack, PackManifest, PackTestOutcome, PACK_MANIFEST};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// Default directory packs are installed into
+pub const DEFAULT_PACK_DIR: &str = "packs";
+
+/// Result of verifying a pack
+#[derive(Debug, Clone, Serialize)]
+pub struct PackVerification {
+    pub manifest: PackManifest,
+    /// Namespaced names of the pack's rules
+    pub rules: Vec<String>,
+    pub tests: Vec<PackTestOutcome>,
+}
+
+impl PackVerification {
+    pub fn passed(&self) -> bool {
+        self.tests.iter().all(|test| test.passed)
+    }
+
+    pub fn failed_tests(&self) -> impl Iterator<Item = &PackTestOutcome> {
+        self.tests.iter().filter(|test| !test.passed)
+    }
+}
+
+/// An installed pack
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledPack {
+    pub manifest: PackManifest,
+    pub path: PathBuf,
+}
+
+/// Load `path` as a pack and run its tests
+pub async fn verify_pack(path: &Path) -> Result<PackVerification> {
+    let pack = DetectionPack::load(path).with_context(|| format!("Failed to load pack {}", path.display()))?;
+    let tests = pack.run_tests().await?;
+    Ok(PackVerification { manifest: pack.manifest().clone(), rules: pack.rule_names(), tests })
+}
+
+/// Verify the pack at `source` and copy it into `dir`
+///
+/// テストが失敗したパックはインストールしない。同じか新しいバージョンが
+/// インストール済みの場合は `force` が必要
+pub async fn install_pack(source: &Path, dir: &Path, force: bool) -> Result<InstalledPack> {
+    let verification = verify_pack(source).await?;
+    if let Some(failed) = verification.failed_tests().next() {
+        bail!("Pack test {:?} failed: {}", failed.name, failed.failures.join("; "));
+    }
+
+    let manifest = verification.manifest;
+    let target = dir.join(&manifest.name);
+    if target.exists() {
+        if !force {
+            if let Ok(installed) = DetectionPack::load(&target) {
+                let installed = &installed.manifest().version;
+                if compare_versions(installed, &manifest.version) != Some(Ordering::Less) {
+                    bail!(
+                        "Pack {} {} is already installed (installing {}); use --force to replace it",
+                        manifest.name, installed, manifest.version
+                    );
+                }
+            }
+        }
+        std::fs::remove_dir_all(&target).with_context(|| format!("Failed to remove {}", target.display()))?;
+    }
+    copy_dir(source, &target)?;
+    Ok(InstalledPack { manifest, path: target })
+}
+
+/// Packs installed in `dir`, sorted by name (a missing directory has none)
+///
+/// マニフェストのないサブディレクトリは無視し、壊れたパックはエラーにする
+pub fn list_packs(dir: &Path) -> Result<Vec<InstalledPack>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut packs = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.join(PACK_MANIFEST).is_file() {
+            let pack = DetectionPack::load(&path).with_context(|| format!("Failed to load pack {}", path.display()))?;
+            packs.push(InstalledPack { manifest: pack.manifest().clone(), path });
+        }
+    }
+    packs.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+    Ok(packs)
+}
+
+fn copy_dir(source: &Path, target: &Path) -> Result<()> {
+    std::fs::create_dir_all(target).with_context(|| format!("Failed to create {}", target.display()))?;
+    for entry in std::fs::read_dir(source).with_context(|| format!("Failed to read {}", source.display()))? {
+        let entry = entry?;
+        let destination = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &destination)?;
+        } else {
+            std::fs::copy(entry.path(), &destination)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_pack(dir: &Path, version: &str, expected_object: &str) {
+        std::fs::create_dir_all(dir.join("tests")).unwrap();
+        std::fs::write(dir.join(PACK_MANIFEST), serde_json::json!({
+            "name": "beacons",
+            "version": version,
+            "rules": ["beacon.rq"],
+            "tests": ["tests/beacon.json"]
+        }).to_string()).unwrap();
+        std::fs::write(dir.join("beacon.rq"), "PREFIX ex: <http://example.org/>\n\
+            CONSTRUCT { ?h a ex:Beaconing . } WHERE { ?h ex:connectsTo ?c2 .\n?c2 a ex:KnownC2 . }").unwrap();
+        let triple = |s: &str, p: &str, o: &str| serde_json::json!({ "subject": s, "predicate": p, "object": o });
+        let rdf_type = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+        std::fs::write(dir.join("tests/beacon.json"), serde_json::json!({
+            "name": "host talking to a C2 server",
+            "triples": [
+                triple("http://example.org/h1", "http://example.org/connectsTo", "http://example.org/c2"),
+                triple("http://example.org/c2", rdf_type, "http://example.org/KnownC2")
+            ],
+            "expect_triples": [triple("http://example.org/h1", rdf_type, expected_object)]
+        }).to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_install_verify_and_list_packs() {
+        let root = std::env::temp_dir().join(format!("fukurow-cli-packs-{}", std::process::id()));
+        let (source, installed) = (root.join("source"), root.join("installed"));
+        write_pack(&source, "0.1.0", "http://example.org/Beaconing");
+
+        let verification = verify_pack(&source).await.unwrap();
+        assert!(verification.passed());
+        assert_eq!(verification.rules, vec!["beacons/beacon"]);
+
+        let pack = install_pack(&source, &installed, false).await.unwrap();
+        assert_eq!(pack.path, installed.join("beacons"));
+        assert!(install_pack(&source, &installed, false).await.is_err());
+        assert!(install_pack(&source, &installed, true).await.is_ok());
+
+        let listed = list_packs(&installed).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].manifest.name.as_str(), listed[0].manifest.version.as_str()), ("beacons", "0.1.0"));
+
+        // テストが失敗するパックはインストールしない
+        write_pack(&source, "0.2.0", "http://example.org/Exfiltration");
+        assert!(!verify_pack(&source).await.unwrap().passed());
+        assert!(install_pack(&source, &installed, false).await.is_err());
+        assert_eq!(list_packs(&installed).unwrap()[0].manifest.version, "0.1.0");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,189 @@
+//! SPARQL result rendering
+//!
+//! `query` サブコマンドの結果を表・JSON (SPARQL 1.1 Query Results JSON)・CSV で出力する。
+//! 表と CSV では接頭辞で IRI を短縮できる (JSON は常に完全な IRI)
+
+use fukurow_core::model::Triple;
+use fukurow_core::prefix::PrefixMap;
+use fukurow_core::term::RdfTerm;
+use fukurow_sparql::diff::format_term;
+use fukurow_sparql::parser::{Bindings, Term, Variable};
+use fukurow_sparql::QueryResult;
+
+/// Result format of the `query` subcommand
+#[derive(Clone, Debug, PartialEq, clap::ValueEnum)]
+pub enum ResultFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Render a query result in the requested format, shortening IRIs with `prefixes` when given
+pub fn render_query_result(result: &QueryResult, format: &ResultFormat, prefixes: Option<&PrefixMap>) -> String {
+    let compact = |iri: &str| prefixes.and_then(|prefixes| prefixes.compact(iri)).unwrap_or_else(|| iri.to_string());
+    match result {
+        QueryResult::Select { variables, bindings } => {
+            let columns = result_columns(variables, bindings);
+            match format {
+                ResultFormat::Json => select_to_json(&columns, bindings).to_string(),
+                _ => {
+                    let header: Vec<String> = columns.iter().map(|v| v.0.clone()).collect();
+                    let rows: Vec<Vec<String>> = bindings.iter()
+                        .map(|binding| columns.iter().map(|var| match binding.get(var) {
+                            Some(Term::Iri(iri)) => compact(&iri.0),
+                            Some(term) => format_term(term),
+                            None => String::new(),
+                        }).collect())
+                        .collect();
+                    render_rows(&header, &rows, format)
+                }
+            }
+        }
+        QueryResult::Ask { result } => match format {
+            ResultFormat::Json => serde_json::json!({ "head": {}, "boolean": result }).to_string(),
+            _ => render_rows(&["boolean".to_string()], &[vec![result.to_string()]], format),
+        },
+        QueryResult::Construct { triples } | QueryResult::Describe { triples } => match format {
+            ResultFormat::Json => serde_json::to_string(triples).unwrap_or_default(),
+            _ => {
+                let header = ["subject", "predicate", "object"].map(str::to_string);
+                let rows: Vec<Vec<String>> = triples.iter()
+                    .map(|Triple { subject, predicate, object }| [subject, predicate, object].into_iter()
+                        .map(|value| match RdfTerm::parse(value) {
+                            RdfTerm::Iri(iri) => compact(&iri),
+                            _ => value.to_string(),
+                        })
+                        .collect())
+                    .collect();
+                render_rows(&header, &rows, format)
+            }
+        },
+    }
+}
+
+/// Number of result rows (solutions or triples)
+pub fn result_count(result: &QueryResult) -> usize {
+    match result {
+        QueryResult::Select { bindings, .. } => bindings.len(),
+        QueryResult::Construct { triples } | QueryResult::Describe { triples } => triples.len(),
+        QueryResult::Ask { .. } => 1,
+    }
+}
+
+/// Projected variables, or every bound variable (sorted) for `SELECT *`
+fn result_columns(variables: &[Variable], bindings: &[Bindings]) -> Vec<Variable> {
+    if !variables.is_empty() {
+        return variables.to_vec();
+    }
+    let mut columns: Vec<Variable> = bindings.iter().flat_map(|b| b.keys().cloned()).collect();
+    columns.sort();
+    columns.dedup();
+    columns
+}
+
+fn select_to_json(columns: &[Variable], bindings: &[Bindings]) -> serde_json::Value {
+    let rows: Vec<serde_json::Value> = bindings.iter().map(|binding| {
+        let mut row = serde_json::Map::new();
+        for var in columns {
+            if let Some(term) = binding.get(var) {
+                row.insert(var.0.clone(), term_to_json(term));
+            }
+        }
+        serde_json::Value::Object(row)
+    }).collect();
+
+    serde_json::json!({
+        "head": { "vars": columns.iter().map(|v| v.0.clone()).collect::<Vec<_>>() },
+        "results": { "bindings": rows },
+    })
+}
+
+fn term_to_json(term: &Term) -> serde_json::Value {
+    match term {
+        Term::Iri(iri) => serde_json::json!({ "type": "uri", "value": iri.0 }),
+        Term::BlankNode(label) => serde_json::json!({ "type": "bnode", "value": label }),
+        Term::Literal(literal) => {
+            let mut value = serde_json::json!({ "type": "literal", "value": literal.value });
+            if let Some(language) = &literal.language {
+                value["xml:lang"] = serde_json::json!(language);
+            } else if let Some(datatype) = &literal.datatype {
+                value["datatype"] = serde_json::json!(datatype.0);
+            }
+            value
+        }
+        other => serde_json::json!({ "type": "literal", "value": format_term(other) }),
+    }
+}
+
+fn render_rows(header: &[String], rows: &[Vec<String>], format: &ResultFormat) -> String {
+    match format {
+        ResultFormat::Csv => {
+            let mut output = String::new();
+            for line in std::iter::once(header).chain(rows.iter().map(Vec::as_slice)) {
+                let cells: Vec<String> = line.iter().map(|cell| csv_escape(cell)).collect();
+                output.push_str(&cells.join(","));
+                output.push_str("\r\n");
+            }
+            output
+        }
+        _ => render_table(header, rows),
+    }
+}
+
+fn render_table(header: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_line = |cells: &[String]| -> String {
+        let padded: Vec<String> = cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = *width))
+            .collect();
+        format!("| {} |", padded.join(" | "))
+    };
+    let separator = format!("+{}+", widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>().join("+"));
+
+    let mut lines = vec![separator.clone(), format_line(header), separator.clone()];
+    lines.extend(rows.iter().map(|row| format_line(row)));
+    lines.push(separator);
+    lines.push(format!("{} row(s)", rows.len()));
+    lines.join("\n")
+}
+
+/// RFC 4180: カンマ・引用符・改行を含むセルだけを引用符で囲む
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_sparql::parser::Iri;
+
+    #[test]
+    fn test_table_output_compacts_iris() {
+        let host = Variable("host".to_string());
+        let mut binding = Bindings::new();
+        binding.insert(host.clone(), Term::Iri(Iri("http://example.org/h1".to_string())));
+        let result = QueryResult::Select { variables: vec![host], bindings: vec![binding] };
+
+        let prefixes = PrefixMap::default().with_prefix("ex", "http://example.org/");
+        assert!(render_query_result(&result, &ResultFormat::Table, Some(&prefixes)).contains("| ex:h1 |"));
+        assert!(render_query_result(&result, &ResultFormat::Table, None).contains("| http://example.org/h1 |"));
+        assert!(render_query_result(&result, &ResultFormat::Json, Some(&prefixes)).contains("http://example.org/h1"));
+
+        let triples = QueryResult::Construct { triples: vec![Triple {
+            subject: "http://example.org/h1".to_string(),
+            predicate: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+            object: "10.0.0.5".to_string(),
+        }] };
+        assert_eq!(render_query_result(&triples, &ResultFormat::Csv, Some(&prefixes)).lines().nth(1), Some("ex:h1,rdf:type,10.0.0.5"));
+    }
+}
@@ -1,8 +1,8 @@
 //! Tests for the cli crate
 
-use reasoner_cli::commands::{Cli, Commands, CommandResult, CommandExecutor, OutputFormat};
+use fukurow_cli::commands::{Cli, Commands, CommandResult, CommandExecutor, OutputFormat, PackCommands, PipelineCommands};
+use fukurow_cli::sparql::ResultFormat;
 use clap::Parser;
-use reasoner_graph::model::CyberEvent;
 use std::path::PathBuf;
 
 #[test]
@@ -79,22 +79,35 @@ fn test_cli_parsing_process() {
 #[test]
 fn test_cli_parsing_query() {
     let args = vec![
-        "reasoner-cli", "query",
-        "--subject", "https://example.com/user/1",
-        "--predicate", "https://example.com/ns/name",
-        "--format", "text"
+        "reasoner-cli", "query", "SELECT ?s WHERE { ?s ?p ?o }",
+        "--store", "events.db",
+        "--format", "json",
+        "--prefix", "ex=https://example.com/ns/",
+        "--explain",
     ];
     let cli = Cli::try_parse_from(args).unwrap();
 
     match cli.command {
-        Commands::Query { subject, predicate, object, format } => {
-            assert_eq!(subject, Some("https://example.com/user/1".to_string()));
-            assert_eq!(predicate, Some("https://example.com/ns/name".to_string()));
-            assert_eq!(object, None);
-            assert_eq!(format, OutputFormat::Text);
+        Commands::Query { query, query_file, store, format, explain, prefixes, full_iris } => {
+            assert_eq!(query.as_deref(), Some("SELECT ?s WHERE { ?s ?p ?o }"));
+            assert_eq!(query_file, None);
+            assert_eq!(store, Some(PathBuf::from("events.db")));
+            assert_eq!(format, ResultFormat::Json);
+            assert!(explain);
+            assert_eq!(prefixes, vec!["ex=https://example.com/ns/".to_string()]);
+            assert!(!full_iris);
         }
         _ => panic!("Expected Query command"),
     }
+
+    // クエリ本文とファイル指定は排他
+    assert!(Cli::try_parse_from(["reasoner-cli", "query", "ASK {}", "--query-file", "q.rq"]).is_err());
+
+    // --store が無ければセッションのグラフに問い合わせる
+    match Cli::try_parse_from(["reasoner-cli", "query", "ASK {}"]).unwrap().command {
+        Commands::Query { store, .. } => assert_eq!(store, None),
+        _ => panic!("Expected Query command"),
+    }
 }
 
 #[test]
@@ -105,7 +118,7 @@ fn test_cli_parsing_threat_stats() {
     match cli.command {
         Commands::Threat { command } => {
             match command {
-                reasoner_cli::commands::ThreatCommands::Stats => {} // Expected
+                fukurow_cli::commands::ThreatCommands::Stats => {} // Expected
                 _ => panic!("Expected Stats subcommand"),
             }
         }
@@ -121,7 +134,7 @@ fn test_cli_parsing_threat_check() {
     match cli.command {
         Commands::Threat { command } => {
             match command {
-                reasoner_cli::commands::ThreatCommands::Check { value, r#type } => {
+                fukurow_cli::commands::ThreatCommands::Check { value, r#type } => {
                     assert_eq!(value, "192.168.1.100");
                     assert_eq!(r#type, "ip");
                 }
@@ -175,9 +188,8 @@ fn test_command_result_creation() {
 
 #[tokio::test]
 async fn test_command_executor_creation() {
-    let executor = CommandExecutor::new();
     // Should create without panicking
-    assert!(true); // If we reach here, creation succeeded
+    let _executor = CommandExecutor::new();
 }
 
 #[tokio::test]
@@ -218,11 +230,13 @@ async fn test_command_executor_analyze_valid_json() {
 
     let event_json = r#"{
         "type": "NetworkConnection",
-        "source_ip": "192.168.1.10",
-        "dest_ip": "10.0.0.50",
-        "port": 443,
-        "protocol": "tcp",
-        "timestamp": 1640995200
+        "data": {
+            "source_ip": "192.168.1.10",
+            "dest_ip": "10.0.0.50",
+            "port": 443,
+            "protocol": "tcp",
+            "timestamp": 1640995200
+        }
     }"#;
 
     let command = Commands::Analyze {
@@ -242,18 +256,148 @@ async fn test_command_executor_analyze_valid_json() {
 async fn test_command_executor_query_empty_graph() {
     let mut executor = CommandExecutor::new();
 
-    let command = Commands::Query {
-        subject: None,
-        predicate: None,
-        object: None,
-        format: OutputFormat::Text,
+    let result = executor.execute(session_query_command("SELECT ?s WHERE { ?s ?p ?o }")).await.unwrap();
+    assert!(result.success);
+    assert_eq!(result.message, "0 result(s)");
+    assert_eq!(result.data, Some(serde_json::json!({ "count": 0 })));
+}
+
+#[tokio::test]
+async fn test_command_executor_query_missing_store() {
+    let mut executor = CommandExecutor::new();
+    let dir = tempfile::tempdir().unwrap();
+    let store = dir.path().join("missing.db");
+
+    let result = executor.execute(query_command("SELECT ?s WHERE { ?s ?p ?o }", store.clone(), false)).await;
+    assert!(result.is_err());
+    // 存在しないストアを空の DB として作らない
+    assert!(!store.exists());
+}
+
+#[tokio::test]
+async fn test_command_executor_query_persisted_store() {
+    let mut executor = CommandExecutor::new();
+    let dir = tempfile::tempdir().unwrap();
+    let store = persisted_store(&dir);
+
+    let result = executor.execute(query_command("SELECT ?s WHERE { ?s ?p ?o }", store.clone(), false)).await.unwrap();
+    assert!(result.success);
+    assert_eq!(result.message, "2 result(s)");
+    assert_eq!(result.data, Some(serde_json::json!({ "count": 2 })));
+}
+
+#[tokio::test]
+async fn test_command_executor_query_explain() {
+    let mut executor = CommandExecutor::new();
+    let dir = tempfile::tempdir().unwrap();
+    let store = persisted_store(&dir);
+
+    // --explain はクエリを実行せず、ストアの統計に基づく計画を返す
+    let explained = executor.execute(query_command("SELECT ?s WHERE { ?s ?p ?o }", store, true)).await.unwrap();
+    assert!(explained.success);
+    assert!(explained.message.starts_with("estimated"));
+    let data = explained.data.unwrap();
+    assert_eq!(data["query_type"], "SELECT");
+    assert_eq!(data["triple_count"], 2);
+    assert!(data.get("plan").is_some());
+}
+
+#[tokio::test]
+async fn test_command_executor_materialize() {
+    let mut executor = CommandExecutor::new();
+    let dir = tempfile::tempdir().unwrap();
+    let store = persisted_store(&dir);
+    let graph = "https://example.com/graph/names";
+    let materialize = |store: PathBuf| Commands::Materialize {
+        query: Some("CONSTRUCT {\n  ?s <https://example.com/ns/label> ?o .\n}\nWHERE {\n  ?s <https://example.com/ns/name> ?o .\n}\n".to_string()),
+        query_file: None,
+        store,
+        graph: graph.to_string(),
+        every: None,
+        prefixes: Vec::new(),
     };
 
-    let result = executor.execute(command).await.unwrap();
+    let result = executor.execute(materialize(store.clone())).await.unwrap();
+    assert!(result.success);
+    assert_eq!(result.message, "1 triple(s) materialized");
+
+    // 再実行はグラフを置き換えるだけで重複させない
+    let result = executor.execute(materialize(store.clone())).await.unwrap();
+    assert_eq!(result.data.unwrap()["replaced"], 1);
+    let saved = fukurow_store::SqliteBackend::open(&store).unwrap().load_store().unwrap();
+    let named = saved.all_triples().get(&fukurow_store::provenance::GraphId::Named(graph.to_string())).unwrap();
+    assert_eq!(named.len(), 1);
 
+    assert!(executor.execute(materialize(dir.path().join("missing.db"))).await.is_err());
+}
+
+#[tokio::test]
+async fn test_command_executor_consistency() {
+    let mut executor = CommandExecutor::new();
+    let dir = tempfile::tempdir().unwrap();
+    let owner = "http://example.org/owner";
+    let mut triples = vec![
+        (owner, "http://www.w3.org/1999/02/22-rdf-syntax-ns#type", "http://www.w3.org/2002/07/owl#FunctionalProperty"),
+        (owner, "http://www.w3.org/1999/02/22-rdf-syntax-ns#type", "http://www.w3.org/2002/07/owl#ObjectProperty"),
+        ("http://example.org/host1", owner, "http://example.org/alice"),
+    ];
+    let consistency = |store: PathBuf, profile: &str| Commands::Consistency {
+        store,
+        profile: profile.to_string(),
+        format: OutputFormat::Json,
+    };
+
+    let store = save_triples(&dir, "consistent.db", &triples);
+    let result = executor.execute(consistency(store, "owl-lite")).await.unwrap();
     assert!(result.success);
-    assert!(result.message.contains("Found 0 triples"));
-    assert!(result.data.is_some());
+    assert_eq!(result.message, "Consistent");
+
+    // 関数的プロパティに 2 つ目の値が付くと、その 3 公理が正当化として返る
+    triples.push(("http://example.org/host1", owner, "http://example.org/bob"));
+    let store = save_triples(&dir, "inconsistent.db", &triples);
+    let result = executor.execute(consistency(store.clone(), "owl-lite")).await.unwrap();
+    assert!(!result.success);
+    assert_eq!(result.message, "Inconsistent: 3 axiom(s) in the justification");
+    assert_eq!(result.data.unwrap()["justification"].as_array().map(Vec::len), Some(3));
+
+    assert!(executor.execute(consistency(store, "owl-full")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_command_executor_complete() {
+    let mut executor = CommandExecutor::new();
+    let dir = tempfile::tempdir().unwrap();
+    let rdf_type = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+    let store = save_triples(&dir, "ontology.db", &[
+        ("http://example.org/Host", rdf_type, "http://www.w3.org/2002/07/owl#Class"),
+        ("http://example.org/HostGroup", rdf_type, "http://www.w3.org/2002/07/owl#Class"),
+        ("http://example.org/hostname", rdf_type, "http://www.w3.org/2002/07/owl#DatatypeProperty"),
+        ("http://example.org/User", rdf_type, "http://www.w3.org/2002/07/owl#Class"),
+    ]);
+    let complete = |input: &str, kind: Option<&str>, limit: usize| Commands::Complete {
+        input: input.to_string(),
+        store: store.clone(),
+        kind: kind.map(str::to_string),
+        limit,
+        prefixes: vec!["ex=http://example.org/".to_string()],
+        format: OutputFormat::Json,
+    };
+
+    // 接頭辞の一致は大文字小文字を区別しない
+    let result = executor.execute(complete("ex:Ho", None, 20)).await.unwrap();
+    assert!(result.success);
+    assert_eq!(result.message, "3 suggestion(s) for ex:Ho");
+    assert_eq!(executor.execute(complete("ex:Ho", None, 1)).await.unwrap().message, "1 suggestion(s) for ex:Ho");
+
+    let classes = executor.execute(complete("ex:Ho", Some("class"), 20)).await.unwrap();
+    let mut curies: Vec<_> = classes.data.unwrap().as_array().unwrap().iter()
+        .map(|completion| completion["curie"].as_str().unwrap().to_string())
+        .collect();
+    curies.sort();
+    assert_eq!(curies, vec!["ex:Host", "ex:HostGroup"]);
+    let properties = executor.execute(complete("ex:ho", Some("datatype_property"), 20)).await.unwrap();
+    assert_eq!(properties.data.unwrap()[0]["iri"], "http://example.org/hostname");
+    assert!(executor.execute(complete("ex:Ho", Some("widget"), 20)).await.is_err());
 }
 
 #[tokio::test]
@@ -261,7 +405,7 @@ async fn test_command_executor_threat_stats() {
     let mut executor = CommandExecutor::new();
 
     let command = Commands::Threat {
-        command: reasoner_cli::commands::ThreatCommands::Stats,
+        command: fukurow_cli::commands::ThreatCommands::Stats,
     };
 
     let result = executor.execute(command).await.unwrap();
@@ -284,7 +428,7 @@ async fn test_command_executor_threat_check_malicious() {
     let mut executor = CommandExecutor::new();
 
     let command = Commands::Threat {
-        command: reasoner_cli::commands::ThreatCommands::Check {
+        command: fukurow_cli::commands::ThreatCommands::Check {
             value: "192.168.1.100".to_string(),
             r#type: "ip".to_string(),
         },
@@ -310,7 +454,7 @@ async fn test_command_executor_threat_check_safe() {
     let mut executor = CommandExecutor::new();
 
     let command = Commands::Threat {
-        command: reasoner_cli::commands::ThreatCommands::Check {
+        command: fukurow_cli::commands::ThreatCommands::Check {
             value: "8.8.8.8".to_string(),
             r#type: "ip".to_string(),
         },
@@ -334,7 +478,7 @@ async fn test_command_executor_threat_check_invalid_type() {
     let mut executor = CommandExecutor::new();
 
     let command = Commands::Threat {
-        command: reasoner_cli::commands::ThreatCommands::Check {
+        command: fukurow_cli::commands::ThreatCommands::Check {
             value: "192.168.1.100".to_string(),
             r#type: "invalid_type".to_string(),
         },
@@ -348,17 +492,15 @@ async fn test_command_executor_threat_check_invalid_type() {
 async fn test_command_executor_serve_command_structure() {
     let mut executor = CommandExecutor::new();
 
+    // Hold the port so the server cannot bind it and fails instead of serving forever
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
     let command = Commands::Serve {
         host: "127.0.0.1".to_string(),
-        port: 8080,
+        port: taken.local_addr().unwrap().port(),
     };
 
-    // Note: This will attempt to start a server, but we expect it to fail gracefully
-    // in a test environment (since we're not actually running an async runtime for server)
     let result = executor.execute(command).await;
-
-    // The result depends on the server implementation, but it should not panic
-    assert!(result.is_ok() || result.is_err()); // Just ensure it doesn't crash
+    assert!(result.is_err());
 }
 
 #[tokio::test]
@@ -386,11 +528,13 @@ async fn test_command_executor_process_valid_data() {
     let events_data = r#"[
         {
             "type": "NetworkConnection",
-            "source_ip": "192.168.1.10",
-            "dest_ip": "10.0.0.50",
-            "port": 443,
-            "protocol": "tcp",
-            "timestamp": 1640995200
+            "data": {
+                "source_ip": "192.168.1.10",
+                "dest_ip": "10.0.0.50",
+                "port": 443,
+                "protocol": "tcp",
+                "timestamp": 1640995200
+            }
         }
     ]"#;
 
@@ -413,6 +557,74 @@ async fn test_command_executor_process_valid_data() {
     drop(input_file);
 }
 
+#[tokio::test]
+async fn test_command_executor_pipeline_run() {
+    let args = vec![
+        "reasoner-cli", "pipeline", "run",
+        "--input", "events.ndjson",
+        "--rules", "rules", "--rules", "extra.rq",
+        "--ontology", "sec.ttl",
+        "--output", "out",
+        "--profile", "rdfs",
+    ];
+    match Cli::try_parse_from(args).unwrap().command {
+        Commands::Pipeline { command: PipelineCommands::Run { rules, ontology, profile, workers, .. } } => {
+            assert_eq!(rules, vec![PathBuf::from("rules"), PathBuf::from("extra.rq")]);
+            assert_eq!(ontology, vec![PathBuf::from("sec.ttl")]);
+            assert_eq!(profile.as_deref(), Some("rdfs"));
+            assert_eq!(workers, None);
+        }
+        _ => panic!("Expected pipeline run"),
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("events.ndjson");
+    std::fs::write(&input, r#"{"type": "UserLogin", "data": {"user": "alice", "source_ip": "10.0.0.1", "success": true, "timestamp": 1700000000}}"#).unwrap();
+    let run = |profile: Option<&str>| Commands::Pipeline {
+        command: PipelineCommands::Run {
+            input: input.clone(),
+            rules: Vec::new(),
+            ontology: Vec::new(),
+            output: dir.path().join("out"),
+            profile: profile.map(str::to_string),
+            workers: Some(1),
+        },
+    };
+
+    let mut executor = CommandExecutor::new();
+    let result = executor.execute(run(None)).await.unwrap();
+    assert!(result.success);
+    assert_eq!(result.message, "Processed 1 events");
+    assert!(dir.path().join("out/report.json").exists());
+    assert!(executor.execute(run(Some("owl-full"))).await.is_err());
+}
+
+#[tokio::test]
+async fn test_command_executor_pack_commands() {
+    let args = vec!["reasoner-cli", "pack", "install", "packs/beacons", "--dir", "installed", "--force"];
+    match Cli::try_parse_from(args).unwrap().command {
+        Commands::Pack { command: PackCommands::Install { path, dir, force } } => {
+            assert_eq!(path, PathBuf::from("packs/beacons"));
+            assert_eq!(dir, PathBuf::from("installed"));
+            assert!(force);
+        }
+        _ => panic!("Expected pack install"),
+    }
+
+    let mut executor = CommandExecutor::new();
+    let dir = tempfile::tempdir().unwrap();
+    let listed = executor.execute(Commands::Pack { command: PackCommands::List { dir: dir.path().to_path_buf() } }).await.unwrap();
+    assert!(listed.success);
+    assert_eq!(listed.message, "0 packs installed");
+
+    // マニフェストのないディレクトリは検証もインストールもできない
+    let missing = dir.path().join("missing");
+    assert!(executor.execute(Commands::Pack { command: PackCommands::Verify { path: missing.clone() } }).await.is_err());
+    assert!(executor.execute(Commands::Pack {
+        command: PackCommands::Install { path: missing, dir: dir.path().join("installed"), force: false },
+    }).await.is_err());
+}
+
 #[test]
 fn test_interactive_mode_parsing() {
     // Test that shell-words parsing works for interactive commands
@@ -457,11 +669,13 @@ async fn test_command_executor_integration() {
         file: None,
         json: Some(r#"{
             "type": "NetworkConnection",
-            "source_ip": "192.168.1.10",
-            "dest_ip": "10.0.0.50",
-            "port": 443,
-            "protocol": "tcp",
-            "timestamp": 1640995200
+            "data": {
+                "source_ip": "192.168.1.10",
+                "dest_ip": "10.0.0.50",
+                "port": 443,
+                "protocol": "tcp",
+                "timestamp": 1640995200
+            }
         }"#.to_string()),
         format: OutputFormat::Json,
     }).await;
@@ -469,17 +683,75 @@ async fn test_command_executor_integration() {
     assert!(analyze_result.is_ok());
     assert!(analyze_result.unwrap().success);
 
-    // Query the graph - should now have triples
-    let query_result = executor.execute(Commands::Query {
-        subject: None,
-        predicate: None,
-        object: None,
-        format: OutputFormat::Json,
-    }).await;
+    // Query the graph - should now have the event's triples
+    let query_result = executor.execute(session_query_command(
+        "SELECT ?ip WHERE { ?event <http://example.org/sourceIP> ?ip }",
+    )).await.unwrap();
+    assert!(query_result.success);
+    assert_eq!(query_result.message, "1 result(s)");
+
+    // Query a persisted store with the same executor
+    let dir = tempfile::tempdir().unwrap();
+    let store = persisted_store(&dir);
+    let query_result = executor.execute(query_command(
+        "SELECT ?o WHERE { <https://example.com/user/1> <https://example.com/ns/name> ?o }",
+        store,
+        false,
+    )).await;
 
     assert!(query_result.is_ok());
     let query_command_result = query_result.unwrap();
     assert!(query_command_result.success);
-    assert!(query_command_result.message.contains("Found"));
-    assert!(query_command_result.data.is_some());
+    assert_eq!(query_command_result.message, "1 result(s)");
+}
+
+fn query_command(query: &str, store: PathBuf, explain: bool) -> Commands {
+    Commands::Query {
+        query: Some(query.to_string()),
+        query_file: None,
+        store: Some(store),
+        format: ResultFormat::Json,
+        explain,
+        prefixes: Vec::new(),
+        full_iris: false,
+    }
+}
+
+/// Query of the executor's own graph (no `--store`)
+fn session_query_command(query: &str) -> Commands {
+    Commands::Query {
+        query: Some(query.to_string()),
+        query_file: None,
+        store: None,
+        format: ResultFormat::Json,
+        explain: false,
+        prefixes: Vec::new(),
+        full_iris: false,
+    }
+}
+
+/// Save a two-triple store into `dir` and return the database path
+fn persisted_store(dir: &tempfile::TempDir) -> PathBuf {
+    save_triples(dir, "store.db", &[
+        ("https://example.com/user/1", "https://example.com/ns/name", "\"Alice\""),
+        ("https://example.com/user/1", "https://example.com/ns/role", "https://example.com/ns/Admin"),
+    ])
+}
+
+/// Save `triples` into a new store `name` in `dir` and return the database path
+fn save_triples(dir: &tempfile::TempDir, name: &str, triples: &[(&str, &str, &str)]) -> PathBuf {
+    let mut store = fukurow_store::RdfStore::new();
+    for (subject, predicate, object) in triples {
+        store.insert(fukurow_core::model::Triple {
+            subject: subject.to_string(),
+            predicate: predicate.to_string(),
+            object: object.to_string(),
+        }, fukurow_store::provenance::GraphId::Default, fukurow_store::provenance::Provenance::Sensor {
+            source: "test".to_string(),
+            confidence: None,
+        });
+    }
+    let path = dir.path().join(name);
+    fukurow_store::SqliteBackend::open(&path).unwrap().save_store(&store).unwrap();
+    path
 }
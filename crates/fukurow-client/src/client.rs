@@ -0,0 +1,308 @@
+//! REST client
+
+use crate::error::{ClientError, ClientResult};
+use crate::retry::RetryPolicy;
+use fukurow_api::auth::{API_KEY_HEADER, TENANT_HEADER};
+use fukurow_api::models::{
+    ApiResponse, GraphQueryRequest, GraphQueryResponse, HealthResponse, ReasoningRequest,
+    ReasoningResponse, StatsResponse, SubmitEventRequest,
+};
+use fukurow_core::model::{CyberEvent, Triple};
+use fukurow_engine::{EventReceipt, ReasoningProfile};
+use reqwest::{Method, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+enum Credentials {
+    None,
+    ApiKey(String),
+    Bearer(String),
+}
+
+/// Typed client for the Fukurow REST API
+#[derive(Debug, Clone)]
+pub struct FukurowClient {
+    base_url: Url,
+    http: reqwest::Client,
+    credentials: Credentials,
+    tenant: Option<String>,
+    timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl FukurowClient {
+    /// Client for the API served at `base_url` (e.g. `http://localhost:3000`)
+    pub fn new(base_url: &str) -> ClientResult<Self> {
+        let mut url = Url::parse(base_url)
+            .map_err(|e| ClientError::ConfigError(format!("invalid base URL {}: {}", base_url, e)))?;
+        if url.cannot_be_a_base() {
+            return Err(ClientError::ConfigError(format!("invalid base URL: {}", base_url)));
+        }
+        // 相対パスを結合したときにパスの末尾が失われないようにする
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(Self {
+            base_url: url,
+            http: reqwest::Client::new(),
+            credentials: Credentials::None,
+            tenant: None,
+            timeout: Duration::from_secs(30),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Authenticate with an API key (`X-API-Key`)
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.credentials = Credentials::ApiKey(api_key.to_string());
+        self
+    }
+
+    /// Authenticate with a JWT bearer token
+    pub fn with_bearer_token(mut self, token: &str) -> Self {
+        self.credentials = Credentials::Bearer(token.to_string());
+        self
+    }
+
+    /// Tenant sent as `X-Tenant-Id`
+    pub fn with_tenant(mut self, tenant: &str) -> Self {
+        self.tenant = Some(tenant.to_string());
+        self
+    }
+
+    /// Timeout of each attempt (default 30 seconds)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Use a preconfigured HTTP client (proxies, TLS roots, ...)
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// `GET /health`
+    pub async fn health(&self) -> ClientResult<HealthResponse> {
+        self.send(Method::GET, "health", None::<&()>).await
+    }
+
+    /// `GET /stats`
+    pub async fn stats(&self) -> ClientResult<StatsResponse> {
+        self.send(Method::GET, "stats", None::<&()>).await
+    }
+
+    /// `POST /events`
+    pub async fn submit_event(&self, event: CyberEvent) -> ClientResult<EventReceipt> {
+        self.submit_event_request(&SubmitEventRequest { event, source: None, event_id: None }).await
+    }
+
+    /// `POST /events` with the reporting sensor and its event ID
+    pub async fn submit_event_request(&self, request: &SubmitEventRequest) -> ClientResult<EventReceipt> {
+        self.send(Method::POST, "events", Some(request)).await
+    }
+
+    /// `POST /reason` with the server's default profile
+    pub async fn reason(&self) -> ClientResult<ReasoningResponse> {
+        self.reason_with(&ReasoningRequest::default()).await
+    }
+
+    /// `POST /reason` with a reasoning profile
+    pub async fn reason_with_profile(&self, profile: ReasoningProfile) -> ClientResult<ReasoningResponse> {
+        self.reason_with(&ReasoningRequest { include_details: None, profile: Some(profile) }).await
+    }
+
+    pub async fn reason_with(&self, request: &ReasoningRequest) -> ClientResult<ReasoningResponse> {
+        self.send(Method::POST, "reason", Some(request)).await
+    }
+
+    /// `POST /graph/query` (one page)
+    pub async fn query_graph(&self, request: &GraphQueryRequest) -> ClientResult<GraphQueryResponse> {
+        self.send(Method::POST, "graph/query", Some(request)).await
+    }
+
+    /// Every page of a graph query, following `next_cursor`
+    pub async fn query_graph_all(&self, request: &GraphQueryRequest) -> ClientResult<Vec<Triple>> {
+        let mut request = request.clone();
+        let mut triples = Vec::new();
+        loop {
+            let page = self.query_graph(&request).await?;
+            triples.extend(page.triples);
+            match page.next_cursor {
+                Some(cursor) => request.cursor = Some(cursor),
+                None => return Ok(triples),
+            }
+        }
+    }
+
+    /// Request to `path` (relative to the base URL) with credentials and tenant set
+    pub(crate) fn request(&self, method: Method, path: &str) -> ClientResult<reqwest::RequestBuilder> {
+        let url = self.base_url.join(path.trim_start_matches('/'))
+            .map_err(|e| ClientError::ConfigError(format!("invalid path {}: {}", path, e)))?;
+        let mut builder = self.http.request(method, url);
+        builder = match &self.credentials {
+            Credentials::None => builder,
+            Credentials::ApiKey(key) => builder.header(API_KEY_HEADER, key),
+            Credentials::Bearer(token) => builder.bearer_auth(token),
+        };
+        if let Some(tenant) = &self.tenant {
+            builder = builder.header(TENANT_HEADER, tenant);
+        }
+        Ok(builder)
+    }
+
+    /// Send a request, retrying per the policy, and unwrap the `ApiResponse` envelope
+    async fn send<B, T>(&self, method: Method, path: &str, body: Option<&B>) -> ClientResult<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let mut retry = 0;
+        loop {
+            let mut builder = self.request(method.clone(), path)?.timeout(self.timeout);
+            if let Some(body) = body {
+                builder = builder.json(body);
+            }
+            let outcome = builder.send().await;
+            let retryable = match &outcome {
+                Ok(response) => RetryPolicy::is_retryable_status(response.status().as_u16()),
+                Err(e) => RetryPolicy::is_retryable_error(e),
+            };
+            if retryable && retry < self.retry.max_retries {
+                retry += 1;
+                tokio::time::sleep(self.retry.backoff(retry)).await;
+                continue;
+            }
+            return decode(outcome?).await;
+        }
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> ClientResult<T> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(api_error(response).await);
+    }
+    let envelope: ApiResponse<T> = response.json().await
+        .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+    match envelope.data {
+        Some(data) if envelope.success => Ok(data),
+        _ => Err(ClientError::Api {
+            status: status.as_u16(),
+            message: envelope.error.unwrap_or_else(|| "response has no data".to_string()),
+        }),
+    }
+}
+
+/// Error carried by an unsuccessful response (the raw body when it is not an `ApiResponse`)
+pub(crate) async fn api_error(response: reqwest::Response) -> ClientError {
+    let status = response.status().as_u16();
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(e) => return ClientError::Http(e),
+    };
+    let message = serde_json::from_str::<ApiResponse<serde_json::Value>>(&body)
+        .ok()
+        .and_then(|envelope| envelope.error)
+        .unwrap_or_else(|| body.trim().to_string());
+    ClientError::Api { status, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 1 接続に 1 つずつ `responses` を返す HTTP サーバー。受け取ったリクエストを記録する
+    async fn serve(responses: Vec<(u16, String)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, payload)) = text.split_once("\r\n\r\n") {
+                        let length = head.lines()
+                            .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if payload.len() >= length {
+                            break;
+                        }
+                    }
+                }
+                recorded.lock().unwrap().push(String::from_utf8_lossy(&request).to_string());
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status, body.len(), body,
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (address, requests)
+    }
+
+    #[tokio::test]
+    async fn test_retries_unavailable_server_with_credentials() {
+        let health = serde_json::to_string(&ApiResponse::success(HealthResponse {
+            status: "healthy".to_string(),
+            version: "0.2.0".to_string(),
+            uptime_seconds: 5,
+        })).unwrap();
+        let (address, requests) = serve(vec![(503, "busy".to_string()), (200, health)]).await;
+
+        let client = FukurowClient::new(&address).unwrap()
+            .with_api_key("secret")
+            .with_tenant("acme")
+            .with_retry(RetryPolicy::default().with_initial_backoff(Duration::from_millis(1)));
+        let response = client.health().await.unwrap();
+        assert_eq!(response.status, "healthy");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("GET /health HTTP/1.1"));
+        assert!(requests[1].contains("x-api-key: secret"));
+        assert!(requests[1].contains("x-tenant-id: acme"));
+    }
+
+    #[tokio::test]
+    async fn test_api_errors_are_not_retried() {
+        let error = serde_json::to_string(&ApiResponse::<String>::error("Invalid cursor".to_string())).unwrap();
+        let (address, requests) = serve(vec![(400, error)]).await;
+
+        let client = FukurowClient::new(&format!("{}/api", address)).unwrap().with_bearer_token("token");
+        let request = GraphQueryRequest { subject: Some("http://example.org/h1".to_string()), ..Default::default() };
+        let err = client.query_graph(&request).await.unwrap_err();
+        assert!(matches!(&err, ClientError::Api { status: 400, message } if message == "Invalid cursor"));
+        assert_eq!(err.status(), Some(400));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST /api/graph/query HTTP/1.1"));
+        assert!(requests[0].contains("authorization: Bearer token"));
+        assert!(requests[0].contains("\"subject\":\"http://example.org/h1\""));
+    }
+}
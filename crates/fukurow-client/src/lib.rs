@@ -0,0 +1,27 @@
+//! # Fukurow Client
+//!
+//! fukurow-api の REST API を型付きの async メソッドで呼び出すクライアント。
+//! リクエスト・レスポンスの型はサーバー ([`fukurow_api::models`]) と共有する
+//!
+//! - 認証は API キー (`X-API-Key`) または JWT bearer トークン、テナントは `X-Tenant-Id` で指定する
+//! - 接続エラー・タイムアウト・429 / 502 / 503 / 504 は指数バックオフで再試行する ([`RetryPolicy`])
+//! - `streaming` フィーチャーで `/events/stream` (Server-Sent Events) を購読できる
+
+pub mod client;
+pub mod error;
+pub mod retry;
+#[cfg(feature = "streaming")]
+pub mod subscription;
+
+pub use client::FukurowClient;
+pub use error::{ClientError, ClientResult};
+pub use retry::RetryPolicy;
+#[cfg(feature = "streaming")]
+pub use subscription::{EventSubscription, SubscriptionFilter};
+
+pub use fukurow_api::models::{
+    GraphQueryRequest, GraphQueryResponse, HealthResponse, ReasoningRequest, ReasoningResponse,
+    StatsResponse, SubmitEventRequest,
+};
+pub use fukurow_core::model::{CyberEvent, SecurityAction, Triple};
+pub use fukurow_engine::{EventReceipt, ReasoningProfile};
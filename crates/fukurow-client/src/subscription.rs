@@ -0,0 +1,152 @@
+//! Event subscription (`GET /events/stream`)
+//!
+//! 推論結果と異常検知を Server-Sent Events で受け取る。
+//! 切断されるとストリームは終わるので、再接続は呼び出し側で行う
+
+use crate::client::{api_error, FukurowClient};
+use crate::error::{ClientError, ClientResult};
+use fukurow_streaming::StreamingEvent;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use reqwest::header::ACCEPT;
+use reqwest::Method;
+use std::collections::VecDeque;
+
+/// Which pushed events to receive (server defaults when empty)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionFilter {
+    /// Event types (`reasoning_result`, `anomaly_detected`)
+    pub types: Vec<String>,
+    /// Minimum severity (`low`, `medium`, `high`, `critical`)
+    pub min_severity: Option<String>,
+}
+
+impl SubscriptionFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_type(mut self, event_type: &str) -> Self {
+        self.types.push(event_type.to_string());
+        self
+    }
+
+    pub fn with_min_severity(mut self, severity: &str) -> Self {
+        self.min_severity = Some(severity.to_string());
+        self
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        if !self.types.is_empty() {
+            query.push(("types", self.types.join(",")));
+        }
+        if let Some(severity) = &self.min_severity {
+            query.push(("min_severity", severity.clone()));
+        }
+        query
+    }
+}
+
+impl FukurowClient {
+    /// Subscribe to pushed events
+    ///
+    /// 接続の確立は 1 回だけ試みる (再試行ポリシーは適用しない)
+    pub async fn subscribe(&self, filter: &SubscriptionFilter) -> ClientResult<EventSubscription> {
+        let response = self.request(Method::GET, "events/stream")?
+            .query(&filter.query())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        Ok(EventSubscription {
+            body: response.bytes_stream().map(|chunk| chunk.map(|bytes| bytes.to_vec())).boxed(),
+            decoder: SseDecoder::default(),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+/// Open event stream
+pub struct EventSubscription {
+    body: BoxStream<'static, reqwest::Result<Vec<u8>>>,
+    decoder: SseDecoder,
+    pending: VecDeque<String>,
+}
+
+impl EventSubscription {
+    /// Next event (`None` once the server closes the stream)
+    pub async fn next_event(&mut self) -> Option<ClientResult<StreamingEvent>> {
+        loop {
+            if let Some(payload) = self.pending.pop_front() {
+                return Some(serde_json::from_str(&payload)
+                    .map_err(|e| ClientError::InvalidResponse(format!("invalid event: {}", e))));
+            }
+            match self.body.next().await? {
+                Ok(chunk) => self.pending.extend(self.decoder.push(&chunk)),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = ClientResult<StreamingEvent>> {
+        futures::stream::unfold(self, |mut subscription| async move {
+            subscription.next_event().await.map(|event| (event, subscription))
+        })
+    }
+}
+
+impl std::fmt::Debug for EventSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSubscription")
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+/// Splits an SSE byte stream into the `data` of each event
+#[derive(Debug, Default)]
+struct SseDecoder {
+    line: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut payloads = Vec::new();
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).trim_end_matches('\r').to_string();
+            self.line.clear();
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    payloads.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data.push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+            // `event:` とキープアライブのコメント行は読み飛ばす (種別はペイロードに含まれる)
+        }
+        payloads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_decoder_handles_split_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b": keep-alive\n\nevent: anomaly_detected\ndata: {\"a\":").is_empty());
+        assert_eq!(decoder.push(b"1}\r\n\r\ndata: x\ndata: y\n\n"), vec!["{\"a\":1}".to_string(), "x\ny".to_string()]);
+
+        let filter = SubscriptionFilter::new().with_type("reasoning_result").with_type("anomaly_detected").with_min_severity("high");
+        assert_eq!(filter.query(), vec![("types", "reasoning_result,anomaly_detected".to_string()), ("min_severity", "high".to_string())]);
+    }
+}
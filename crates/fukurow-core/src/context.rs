@@ -0,0 +1,390 @@
+//! JSON-LD context processing
+//!
+//! `@context` を解釈して項 (term) と IRI を相互に変換する。JSON-LD 1.1 のうち次を扱う:
+//! - 項の定義 (文字列、または `@id` / `@type` / `@language` / `@container` を持つオブジェクト)
+//! - `@vocab`・`@base`・`@language` と接頭辞付き名 (`ex:name`)
+//! - 配列による複数コンテキストの合成と `null` によるリセット
+//!
+//! リモートのコンテキストは取得しない。[`JsonLdContext::with_document`] で事前に登録した
+//! IRI だけを参照できる。
+
+use crate::prefix::PrefixMap;
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Definition of one context term
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermDefinition {
+    /// Expanded IRI of the term
+    pub iri: String,
+    /// Value coercion: `@id`, `@vocab` or a datatype IRI
+    pub type_mapping: Option<String>,
+    /// Default language of string values
+    pub language: Option<String>,
+    /// `@container` (`@list`, `@set`, ...); lists are imported as plain multi-valued properties
+    pub container: Option<String>,
+}
+
+impl TermDefinition {
+    pub fn new(iri: impl Into<String>) -> Self {
+        Self { iri: iri.into(), type_mapping: None, language: None, container: None }
+    }
+
+    pub fn with_type(mut self, type_mapping: impl Into<String>) -> Self {
+        self.type_mapping = Some(type_mapping.into());
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Whether values are node references (`@type: @id` or `@vocab`)
+    pub fn is_reference(&self) -> bool {
+        matches!(self.type_mapping.as_deref(), Some("@id") | Some("@vocab"))
+    }
+
+    /// Datatype coercion, if any
+    pub fn datatype(&self) -> Option<&str> {
+        self.type_mapping.as_deref().filter(|t| !t.starts_with('@'))
+    }
+
+    /// JSON-LD 1.1 と同じく、IRI が区切り文字で終わる項だけを接頭辞として使う
+    fn is_prefix(&self) -> bool {
+        self.iri.ends_with(['/', '#', ':', '?', '[', ']', '@'])
+    }
+
+    fn is_plain(&self) -> bool {
+        self.type_mapping.is_none() && self.language.is_none() && self.container.is_none()
+    }
+}
+
+/// Active JSON-LD context
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonLdContext {
+    terms: BTreeMap<String, TermDefinition>,
+    vocab: Option<String>,
+    base: Option<String>,
+    language: Option<String>,
+    /// Preloaded remote contexts by IRI
+    documents: BTreeMap<String, Value>,
+}
+
+impl JsonLdContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process a `@context` value
+    pub fn parse(context: &Value) -> Result<Self> {
+        Self::default().process(context)
+    }
+
+    /// Register a remote context so `"@context": "<iri>"` can be resolved offline
+    ///
+    /// `document` は `@context` を持つ文書でもコンテキスト本体でもよい
+    pub fn with_document(mut self, iri: impl Into<String>, document: Value) -> Self {
+        self.documents.insert(iri.into(), document);
+        self
+    }
+
+    pub fn with_vocab(mut self, vocab: impl Into<String>) -> Self {
+        self.vocab = Some(vocab.into());
+        self
+    }
+
+    pub fn with_base(mut self, base: impl Into<String>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Define (or redefine) a term
+    pub fn with_term(mut self, term: impl Into<String>, definition: TermDefinition) -> Self {
+        self.terms.insert(term.into(), definition);
+        self
+    }
+
+    pub fn vocab(&self) -> Option<&str> {
+        self.vocab.as_deref()
+    }
+
+    pub fn base(&self) -> Option<&str> {
+        self.base.as_deref()
+    }
+
+    /// Default language of string values
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn term(&self, term: &str) -> Option<&TermDefinition> {
+        self.terms.get(term)
+    }
+
+    pub fn terms(&self) -> impl Iterator<Item = (&str, &TermDefinition)> {
+        self.terms.iter().map(|(term, definition)| (term.as_str(), definition))
+    }
+
+    /// Terms usable as prefixes
+    pub fn prefixes(&self) -> PrefixMap {
+        let mut prefixes = PrefixMap::empty();
+        for (term, definition) in &self.terms {
+            if definition.is_prefix() && !term.contains(':') {
+                prefixes.insert(term, &definition.iri);
+            }
+        }
+        prefixes
+    }
+
+    /// Apply a local context on top of this one and return the new active context
+    pub fn process(&self, context: &Value) -> Result<Self> {
+        let mut active = self.clone();
+        let entries = match context {
+            Value::Array(entries) => entries.iter().collect(),
+            entry => vec![entry],
+        };
+        for entry in entries {
+            match entry {
+                Value::Null => {
+                    active = Self { documents: std::mem::take(&mut active.documents), ..Self::default() };
+                }
+                Value::String(iri) => {
+                    let document = active.documents.get(iri)
+                        .ok_or_else(|| anyhow!("Remote context {} is not preloaded", iri))?;
+                    let local = document.get("@context").unwrap_or(document).clone();
+                    // 自分自身を参照するリモートコンテキストで無限に再帰しないよう、処理中は外しておく
+                    let mut nested = active.clone();
+                    nested.documents.remove(iri);
+                    let processed = nested.process(&local)?;
+                    active = Self { documents: std::mem::take(&mut active.documents), ..processed };
+                }
+                Value::Object(definitions) => active.define(definitions)?,
+                other => return Err(anyhow!("Invalid @context entry: {}", other)),
+            }
+        }
+        Ok(active)
+    }
+
+    fn define(&mut self, definitions: &Map<String, Value>) -> Result<()> {
+        if let Some(base) = definitions.get("@base") {
+            self.base = optional_string(base, "@base")?;
+        }
+        if let Some(vocab) = definitions.get("@vocab") {
+            self.vocab = optional_string(vocab, "@vocab")?.map(|vocab| self.expand_iri(&vocab, true));
+        }
+        if let Some(language) = definitions.get("@language") {
+            self.language = optional_string(language, "@language")?.map(|l| l.to_ascii_lowercase());
+        }
+
+        let mut defined = Vec::new();
+        for (term, value) in definitions {
+            if term.starts_with('@') {
+                continue;
+            }
+            let definition = match value {
+                Value::Null => {
+                    self.terms.remove(term);
+                    continue;
+                }
+                Value::String(iri) => TermDefinition::new(iri.as_str()),
+                Value::Object(entries) => TermDefinition {
+                    iri: match entries.get("@id") {
+                        Some(Value::String(iri)) => iri.clone(),
+                        None => term.clone(),
+                        Some(other) => return Err(anyhow!("Invalid @id for term {}: {}", term, other)),
+                    },
+                    type_mapping: entries.get("@type").map(|t| optional_string(t, "@type")).transpose()?.flatten(),
+                    language: entries.get("@language").map(|l| optional_string(l, "@language")).transpose()?.flatten()
+                        .map(|l| l.to_ascii_lowercase()),
+                    container: entries.get("@container").map(|c| optional_string(c, "@container")).transpose()?.flatten(),
+                },
+                other => return Err(anyhow!("Invalid definition for term {}: {}", term, other)),
+            };
+            self.terms.insert(term.clone(), definition);
+            defined.push(term.clone());
+        }
+
+        // 定義中の IRI は同じコンテキストの項や接頭辞を参照してよいので、全て登録してから
+        // 変化がなくなるまで展開する
+        for _ in 0..=defined.len() {
+            let mut changed = false;
+            for term in &defined {
+                let definition = &self.terms[term];
+                let iri = self.expand_term_iri(term, &definition.iri);
+                let type_mapping = definition.type_mapping.as_deref().map(|t| match t {
+                    "@id" | "@vocab" => t.to_string(),
+                    datatype => self.expand_iri(datatype, true),
+                });
+                let definition = self.terms.get_mut(term).expect("defined above");
+                if definition.iri != iri || definition.type_mapping != type_mapping {
+                    definition.iri = iri;
+                    definition.type_mapping = type_mapping;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand the IRI of `term`'s own definition without looking the term itself up
+    fn expand_term_iri(&self, term: &str, iri: &str) -> String {
+        if iri == term {
+            let mut without = self.clone();
+            without.terms.remove(term);
+            without.expand_iri(iri, true)
+        } else {
+            self.expand_iri(iri, true)
+        }
+    }
+
+    /// Expand a term, prefixed name, or relative IRI
+    ///
+    /// `vocab` が真なら (プロパティ名・`@type`・データ型) 項と `@vocab` を使い、
+    /// 偽なら (`@id` の値) 接頭辞と `@base` だけを使う。展開できない値はそのまま返す
+    pub fn expand_iri(&self, value: &str, vocab: bool) -> String {
+        if value.starts_with('@') {
+            return value.to_string();
+        }
+        if vocab {
+            if let Some(definition) = self.terms.get(value) {
+                return definition.iri.clone();
+            }
+        }
+        if let Some((prefix, suffix)) = value.split_once(':') {
+            if prefix == "_" || suffix.starts_with("//") {
+                return value.to_string();
+            }
+            return match self.terms.get(prefix).filter(|definition| definition.is_prefix()) {
+                Some(definition) => format!("{}{}", definition.iri, suffix),
+                // urn: や mailto: などの絶対 IRI
+                None => value.to_string(),
+            };
+        }
+        if vocab {
+            if let Some(vocab) = &self.vocab {
+                return format!("{}{}", vocab, value);
+            }
+        }
+        match &self.base {
+            Some(base) if !value.is_empty() => resolve_relative(base, value),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Compact an IRI with the terms, `@vocab` and prefixes of this context
+    pub fn compact_iri(&self, iri: &str, vocab: bool) -> String {
+        if vocab {
+            let term = self.terms.iter()
+                .filter(|(_, definition)| definition.iri == iri)
+                .map(|(term, _)| term)
+                .min_by_key(|term| term.len());
+            if let Some(term) = term {
+                return term.clone();
+            }
+        }
+        self.compact_without_terms(iri, vocab)
+    }
+
+    /// Property key for `iri` and the definition that applies to its values
+    ///
+    /// 同じ IRI の項が複数あれば、値を素の文字列で書ける型指定の項を優先する。
+    /// `accepts` は項の型指定がその値に合うかを判定する
+    pub fn compact_property(&self, iri: &str, accepts: impl Fn(&TermDefinition) -> bool) -> (String, Option<&TermDefinition>) {
+        let candidates: Vec<(&String, &TermDefinition)> = self.terms.iter()
+            .filter(|(_, definition)| definition.iri == iri)
+            .collect();
+        let chosen = candidates.iter()
+            .filter(|(_, definition)| accepts(definition))
+            .chain(candidates.iter().filter(|(_, definition)| definition.is_plain()))
+            .min_by_key(|(term, definition)| (!accepts(definition), term.len()));
+        match chosen {
+            Some((term, definition)) => ((*term).clone(), Some(*definition)),
+            None => (self.compact_without_terms(iri, true), None),
+        }
+    }
+
+    fn compact_without_terms(&self, iri: &str, vocab: bool) -> String {
+        if vocab {
+            if let Some(suffix) = self.vocab.as_deref().and_then(|vocab| iri.strip_prefix(vocab)) {
+                // 項と衝突する・接頭辞付き名に見える場合は @vocab で短縮しない
+                if !suffix.is_empty() && !suffix.contains(':') && !self.terms.contains_key(suffix) {
+                    return suffix.to_string();
+                }
+            }
+        }
+        let prefix = self.terms.iter()
+            .filter(|(term, definition)| {
+                definition.is_prefix() && !term.contains(':') && iri.len() > definition.iri.len() && iri.starts_with(&definition.iri)
+            })
+            .max_by_key(|(_, definition)| definition.iri.len());
+        match prefix {
+            Some((term, definition)) if !iri[definition.iri.len()..].starts_with("//") => {
+                format!("{}:{}", term, &iri[definition.iri.len()..])
+            }
+            _ => iri.to_string(),
+        }
+    }
+
+    /// The context as a `@context` value
+    pub fn to_value(&self) -> Value {
+        let mut context = Map::new();
+        if let Some(base) = &self.base {
+            context.insert("@base".to_string(), Value::String(base.clone()));
+        }
+        if let Some(vocab) = &self.vocab {
+            context.insert("@vocab".to_string(), Value::String(vocab.clone()));
+        }
+        if let Some(language) = &self.language {
+            context.insert("@language".to_string(), Value::String(language.clone()));
+        }
+        for (term, definition) in &self.terms {
+            let value = if definition.is_plain() {
+                Value::String(definition.iri.clone())
+            } else {
+                let mut entries = Map::new();
+                entries.insert("@id".to_string(), Value::String(definition.iri.clone()));
+                if let Some(type_mapping) = &definition.type_mapping {
+                    entries.insert("@type".to_string(), Value::String(type_mapping.clone()));
+                }
+                if let Some(language) = &definition.language {
+                    entries.insert("@language".to_string(), Value::String(language.clone()));
+                }
+                if let Some(container) = &definition.container {
+                    entries.insert("@container".to_string(), Value::String(container.clone()));
+                }
+                Value::Object(entries)
+            };
+            context.insert(term.clone(), value);
+        }
+        Value::Object(context)
+    }
+}
+
+fn optional_string(value: &Value, keyword: &str) -> Result<Option<String>> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(s) => Ok(Some(s.clone())),
+        other => Err(anyhow!("{} must be a string or null: {}", keyword, other)),
+    }
+}
+
+/// Resolve a relative reference against `base` (fragment, absolute-path and path-relative forms)
+fn resolve_relative(base: &str, reference: &str) -> String {
+    if reference.starts_with('#') {
+        let base = base.split('#').next().unwrap_or(base);
+        return format!("{}{}", base, reference);
+    }
+    if let Some(path) = reference.strip_prefix('/') {
+        let authority_end = base.find("://")
+            .map(|scheme| scheme + 3 + base[scheme + 3..].find('/').unwrap_or(base.len() - scheme - 3))
+            .unwrap_or(0);
+        return format!("{}/{}", &base[..authority_end], path);
+    }
+    let directory = base.rfind('/').map(|slash| &base[..=slash]).unwrap_or(base);
+    format!("{}{}", directory, reference)
+}
@@ -1,41 +1,525 @@
 //! JSON-LD serialization and deserialization utilities
 
+use crate::context::{JsonLdContext, TermDefinition};
 use crate::model::{JsonLdDocument, Triple, CyberEvent};
-use serde_json;
+use crate::prefix::PrefixMap;
+use crate::term::{BlankNodeScope, RdfTerm};
+use serde_json::{self, Map, Value};
+use std::collections::{HashMap, HashSet};
 use anyhow::{Result, anyhow};
 
+/// Namespace of the cyber event vocabulary (`@vocab` of exported documents)
+pub const SECURITY_VOCAB: &str = "https://w3id.org/security#";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+
 /// Convert JSON-LD document to triples
+///
+/// Blank nodes (`_:` identifiers and nested node objects without `@id`) are
+/// relabelled into a fresh [`BlankNodeScope`], so importing two documents that
+/// both use `_:b0` yields two distinct nodes. Value objects (`@value` with
+/// `@type` or `@language`) become typed or language-tagged literals.
+///
+/// `@context` is processed with [`JsonLdContext`]: property names, `@type`
+/// values and datatypes are expanded with its terms, `@vocab` and prefixes,
+/// and `@id` values with its prefixes and `@base`. Node-level contexts apply
+/// to that node and its children. Term coercion (`@type: @id`, datatypes,
+/// `@language`) applies to string values; JSON numbers and booleans become
+/// `xsd:integer` / `xsd:double` / `xsd:boolean` literals and `@type` becomes
+/// `rdf:type` triples. A document without `@graph` is read as a single node.
 pub fn jsonld_to_triples(doc: &JsonLdDocument) -> Result<Vec<Triple>> {
+    jsonld_to_triples_with_context(doc, &JsonLdContext::default())
+}
+
+/// [`jsonld_to_triples`] with the document's `@context` processed on top of `base`
+///
+/// `base` には事前に読み込んだリモートコンテキスト ([`JsonLdContext::with_document`]) や、
+/// 文書が前提とする既定の項を登録しておける
+pub fn jsonld_to_triples_with_context(doc: &JsonLdDocument, base: &JsonLdContext) -> Result<Vec<Triple>> {
+    let context = base.process(&doc.context)?;
+    let mut scope = BlankNodeScope::new();
     let mut triples = Vec::new();
 
-    if let Some(graph) = &doc.graph {
-        for node in graph {
-            if let Some(node_obj) = node.as_object() {
-                if let Some(subject) = node_obj.get("@id") {
-                    let subject_str = subject.as_str()
-                        .ok_or_else(|| anyhow!("@id must be a string"))?;
-
-                    for (key, value) in node_obj {
-                        if key != "@id" && key != "@type" {
-                            if let Some(obj_str) = value.as_str() {
-                                triples.push(Triple {
-                                    subject: subject_str.to_string(),
-                                    predicate: key.clone(),
-                                    object: obj_str.to_string(),
-                                });
-                            }
-                        }
-                    }
-                }
+    match &doc.graph {
+        Some(graph) => {
+            for node in graph.iter().filter_map(Value::as_object) {
+                node_to_triples(node, &context, &mut scope, &mut triples)?;
             }
         }
+        // `@graph` のない文書はトップレベル自体が 1 つのノード
+        None if !doc.data.is_empty() => {
+            let node: Map<String, Value> = doc.data.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+            node_to_triples(&node, &context, &mut scope, &mut triples)?;
+        }
+        None => {}
     }
 
     Ok(triples)
 }
 
+/// Prefix definitions of a JSON-LD `@context`
+///
+/// JSON-LD 1.1 と同じく、値が `/` `#` `:` で終わる項だけを接頭辞とみなす
+pub fn context_prefixes(context: &Value) -> PrefixMap {
+    let mut prefixes = PrefixMap::empty();
+    if let Some(entries) = context.as_object() {
+        for (term, value) in entries {
+            if let Some(namespace) = value.as_str() {
+                if !term.starts_with('@') && !term.contains(':') && namespace.ends_with(['/', '#', ':']) {
+                    prefixes.insert(term, namespace);
+                }
+            }
+        }
+    }
+    prefixes
+}
+
+/// Emit the triples of one node object and return its (encoded) subject
+fn node_to_triples(
+    node_obj: &Map<String, Value>,
+    context: &JsonLdContext,
+    scope: &mut BlankNodeScope,
+    triples: &mut Vec<Triple>,
+) -> Result<String> {
+    // ノードに埋め込まれた `@context` はそのノード以下にだけ効く
+    let local;
+    let context = match node_obj.get("@context") {
+        Some(local_context) => {
+            local = context.process(local_context)?;
+            &local
+        }
+        None => context,
+    };
+
+    let subject = match node_obj.get("@id") {
+        Some(id) => {
+            let id = id.as_str().ok_or_else(|| anyhow!("@id must be a string"))?;
+            scope.relabel_value(&context.expand_iri(id, false))
+        }
+        None => format!("_:{}", scope.fresh()),
+    };
+
+    for (key, value) in node_obj {
+        if key == "@type" {
+            for class in as_values(value) {
+                let class = class.as_str().ok_or_else(|| anyhow!("@type must be a string"))?;
+                triples.push(Triple {
+                    subject: subject.clone(),
+                    predicate: RDF_TYPE.to_string(),
+                    object: context.expand_iri(class, true),
+                });
+            }
+            continue;
+        }
+        // `@id`・`@context` などのキーワード
+        if key.starts_with('@') {
+            continue;
+        }
+
+        let predicate = context.expand_iri(key, true);
+        let definition = context.term(key);
+        for value in as_values(value) {
+            for object in value_to_objects(value, definition, context, scope, triples)? {
+                triples.push(Triple {
+                    subject: subject.clone(),
+                    predicate: predicate.clone(),
+                    object,
+                });
+            }
+        }
+    }
+
+    Ok(subject)
+}
+
+fn as_values(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        value => vec![value],
+    }
+}
+
+fn value_to_objects(
+    value: &Value,
+    definition: Option<&TermDefinition>,
+    context: &JsonLdContext,
+    scope: &mut BlankNodeScope,
+    triples: &mut Vec<Triple>,
+) -> Result<Vec<String>> {
+    let object = match value {
+        Value::Null => return Ok(Vec::new()),
+        Value::Array(items) => {
+            let mut objects = Vec::new();
+            for item in items {
+                objects.extend(value_to_objects(item, definition, context, scope, triples)?);
+            }
+            return Ok(objects);
+        }
+        Value::String(s) => match definition.and_then(|d| d.type_mapping.as_deref()) {
+            Some("@id") => scope.relabel_value(&context.expand_iri(s, false)),
+            Some("@vocab") => scope.relabel_value(&context.expand_iri(s, true)),
+            Some(datatype) if !datatype.starts_with('@') => RdfTerm::typed_literal(s.as_str(), datatype).encode(),
+            _ => match definition.and_then(|d| d.language.as_deref()).or(context.language()) {
+                Some(language) => RdfTerm::lang_literal(s.as_str(), language).encode(),
+                // 文字列はそのまま格納する。ただし `_:` で始まる文字列はノード参照ではなくリテラル
+                None if s.starts_with("_:") => RdfTerm::literal(s.as_str()).encode(),
+                None => s.clone(),
+            },
+        },
+        Value::Number(_) | Value::Bool(_) => native_literal(value, definition.and_then(TermDefinition::datatype)).encode(),
+        Value::Object(obj) => {
+            // リストと集合は複数値のプロパティとして取り込む (順序は保持しない)
+            if let Some(items) = obj.get("@list").or_else(|| obj.get("@set")) {
+                return value_to_objects(items, definition, context, scope, triples);
+            }
+            match obj.get("@value") {
+                Some(Value::Null) => return Ok(Vec::new()),
+                Some(literal) => {
+                    let term = if let Some(language) = obj.get("@language").and_then(Value::as_str) {
+                        RdfTerm::lang_literal(lexical_form(literal), language)
+                    } else if let Some(datatype) = obj.get("@type").and_then(Value::as_str) {
+                        RdfTerm::typed_literal(lexical_form(literal), context.expand_iri(datatype, true))
+                    } else if let Value::String(s) = literal {
+                        RdfTerm::literal(s.as_str())
+                    } else {
+                        native_literal(literal, None)
+                    };
+                    term.encode()
+                }
+                // ノード参照 (`{"@id": ...}`) またはネストしたノード
+                None => node_to_triples(obj, context, scope, triples)?,
+            }
+        }
+    };
+    Ok(vec![object])
+}
+
+fn lexical_form(literal: &Value) -> String {
+    match literal {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Literal for a JSON number or boolean (`datatype` overrides the JSON-LD default)
+fn native_literal(value: &Value, datatype: Option<&str>) -> RdfTerm {
+    let default = match value {
+        Value::Bool(_) => XSD_BOOLEAN,
+        Value::Number(n) if n.is_f64() => XSD_DOUBLE,
+        _ => XSD_INTEGER,
+    };
+    RdfTerm::typed_literal(value.to_string(), datatype.unwrap_or(default))
+}
+
+/// Convert triples to a JSON-LD document (one node object per subject)
+///
+/// Blank nodes keep their `_:` labels and are referenced as `{"@id": "_:label"}`;
+/// literals that would not round-trip as plain strings become value objects.
+/// The `@context` declares the default prefixes.
+pub fn triples_to_jsonld(triples: &[Triple]) -> JsonLdDocument {
+    triples_to_jsonld_with_prefixes(triples, &PrefixMap::default())
+}
+
+/// [`triples_to_jsonld`] with `prefixes` declared in the `@context` (IRIs stay expanded)
+pub fn triples_to_jsonld_with_prefixes(triples: &[Triple], prefixes: &PrefixMap) -> JsonLdDocument {
+    let mut nodes: Vec<Map<String, Value>> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for triple in triples {
+        let position = *index.entry(triple.subject.as_str()).or_insert_with(|| {
+            let mut node = Map::new();
+            node.insert("@id".to_string(), Value::String(triple.subject.clone()));
+            nodes.push(node);
+            nodes.len() - 1
+        });
+
+        push_value(&mut nodes[position], &triple.predicate, object_to_value(&triple.object));
+    }
+
+    let mut context = prefixes.jsonld_context();
+    context.insert("@vocab".to_string(), Value::String(SECURITY_VOCAB.to_string()));
+
+    JsonLdDocument {
+        context: Value::Object(context),
+        graph: Some(nodes.into_iter().map(Value::Object).collect()),
+        data: HashMap::new(),
+    }
+}
+
+fn object_to_value(object: &str) -> Value {
+    match RdfTerm::parse(object) {
+        RdfTerm::BlankNode(label) => serde_json::json!({ "@id": format!("_:{}", label) }),
+        // 文字列のまま再取り込みしても同じリテラルになる場合だけ素の文字列で出力する
+        RdfTerm::Literal { value, datatype: None, language: None } if RdfTerm::parse(&value) == RdfTerm::literal(value.as_str()) => {
+            Value::String(value)
+        }
+        RdfTerm::Literal { value, datatype, language } => {
+            let mut literal = serde_json::json!({ "@value": value });
+            if let Some(language) = language {
+                literal["@language"] = Value::String(language);
+            } else if let Some(datatype) = datatype {
+                literal["@type"] = Value::String(datatype);
+            }
+            literal
+        }
+        RdfTerm::Iri(_) => Value::String(object.to_string()),
+    }
+}
+
+/// Append `value` under `key`, turning repeated keys into arrays
+fn push_value(node: &mut Map<String, Value>, key: &str, value: Value) {
+    match node.get_mut(key) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None => {
+            node.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// Convert triples to a JSON-LD document compacted with a caller-supplied context
+///
+/// Property names, `@id`s and `@type`s are shortened with the terms, `@vocab`
+/// and prefixes of `context`, and `rdf:type` triples become `@type`. Values
+/// that match a term's coercion (`@type: @id`, a datatype or `@language`) are
+/// written as plain strings, so [`jsonld_to_triples`] reads the result back
+/// into the same triples.
+pub fn triples_to_jsonld_with_context(triples: &[Triple], context: &JsonLdContext) -> JsonLdDocument {
+    let graph = group_by_subject(triples).iter()
+        .map(|(subject, properties)| Value::Object(compact_node(subject, properties, context, &mut |_| None)))
+        .collect();
+
+    JsonLdDocument {
+        context: context.to_value(),
+        graph: Some(graph),
+        data: HashMap::new(),
+    }
+}
+
+/// Frame `triples` into trees shaped by `frame` (JSON-LD 1.1 Framing の基本部分)
+///
+/// - `@type` / `@id` (文字列か配列、`{}` は「何かある」) に一致するノードがトップレベルになる。
+///   どちらもなければ、他のノードから参照されるブランクノードを除く全ノード
+/// - フレームにオブジェクトで書かれたプロパティは、参照先のノードをそのサブフレームで埋め込む。
+///   ブランクノードは常に埋め込み、循環する参照は `@id` のまま残す
+/// - `"@explicit": true` ならフレームに書かれたプロパティ (と `@type`) だけを出力する
+///
+/// 出力はフレームの `@context` で短縮する
+pub fn frame_triples(triples: &[Triple], frame: &Value) -> Result<JsonLdDocument> {
+    let frame = frame.as_object().ok_or_else(|| anyhow!("Frame must be a JSON object"))?;
+    let context_value = frame.get("@context").cloned().unwrap_or_else(|| Value::Object(Map::new()));
+    let context = JsonLdContext::parse(&context_value)?;
+
+    let nodes = group_by_subject(triples);
+    let index: HashMap<&str, &[&Triple]> = nodes.iter().map(|(subject, properties)| (*subject, properties.as_slice())).collect();
+    let filtered = frame.contains_key("@type") || frame.contains_key("@id");
+    let referenced: HashSet<&str> = triples.iter().map(|t| t.object.as_str()).filter(|o| o.starts_with("_:")).collect();
+
+    let mut graph = Vec::new();
+    for (subject, properties) in &nodes {
+        if !filtered && referenced.contains(subject) {
+            continue;
+        }
+        if frame_matches(frame, subject, properties, &context) {
+            let mut path = vec![subject.to_string()];
+            graph.push(Value::Object(frame_node(subject, properties, frame, &context, &index, &mut path)));
+        }
+    }
+
+    Ok(JsonLdDocument {
+        context: context_value,
+        graph: Some(graph),
+        data: HashMap::new(),
+    })
+}
+
+/// Triples grouped by subject, in order of first appearance
+fn group_by_subject(triples: &[Triple]) -> Vec<(&str, Vec<&Triple>)> {
+    let mut nodes: Vec<(&str, Vec<&Triple>)> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for triple in triples {
+        let position = *index.entry(triple.subject.as_str()).or_insert_with(|| {
+            nodes.push((triple.subject.as_str(), Vec::new()));
+            nodes.len() - 1
+        });
+        nodes[position].1.push(triple);
+    }
+    nodes
+}
+
+/// Compact one node; `embed` may replace an object reference with an embedded node
+fn compact_node(
+    subject: &str,
+    properties: &[&Triple],
+    context: &JsonLdContext,
+    embed: &mut dyn FnMut(&Triple) -> Option<Value>,
+) -> Map<String, Value> {
+    let mut node = Map::new();
+    node.insert("@id".to_string(), Value::String(context.compact_iri(subject, false)));
+
+    for triple in properties {
+        let object = RdfTerm::parse(&triple.object);
+        if triple.predicate == RDF_TYPE {
+            if let RdfTerm::Iri(class) = &object {
+                push_value(&mut node, "@type", Value::String(context.compact_iri(class, true)));
+                continue;
+            }
+        }
+        let (key, definition) = context.compact_property(&triple.predicate, |definition| accepts(definition, &object));
+        let value = embed(triple).unwrap_or_else(|| compact_value(&object, definition, context));
+        push_value(&mut node, &key, value);
+    }
+    node
+}
+
+/// Whether `definition`'s coercion lets `object` be written as a plain string
+fn accepts(definition: &TermDefinition, object: &RdfTerm) -> bool {
+    match object {
+        RdfTerm::Iri(_) | RdfTerm::BlankNode(_) => definition.is_reference(),
+        RdfTerm::Literal { datatype: Some(datatype), .. } => definition.datatype() == Some(datatype.as_str()),
+        RdfTerm::Literal { language: Some(language), .. } => {
+            definition.type_mapping.is_none() && definition.language.as_deref() == Some(language.as_str())
+        }
+        RdfTerm::Literal { .. } => definition.type_mapping.is_none() && definition.language.is_none(),
+    }
+}
+
+fn compact_value(object: &RdfTerm, definition: Option<&TermDefinition>, context: &JsonLdContext) -> Value {
+    let coerced = definition.filter(|definition| accepts(definition, object));
+    match object {
+        RdfTerm::Iri(iri) => match coerced.and_then(|d| d.type_mapping.as_deref()) {
+            Some("@vocab") => Value::String(context.compact_iri(iri, true)),
+            Some(_) => Value::String(context.compact_iri(iri, false)),
+            None => serde_json::json!({ "@id": context.compact_iri(iri, false) }),
+        },
+        RdfTerm::BlankNode(label) if coerced.is_some() => Value::String(format!("_:{}", label)),
+        RdfTerm::BlankNode(label) => serde_json::json!({ "@id": format!("_:{}", label) }),
+        RdfTerm::Literal { value, datatype, language } => {
+            let default_language = definition.and_then(|d| d.language.as_deref()).or(context.language());
+            // 項の型指定・既定の言語で同じリテラルに読み戻せる場合だけ素の文字列で出力する
+            let plain = match (datatype, language) {
+                (Some(_), _) => coerced.is_some(),
+                (None, Some(language)) => default_language == Some(language.as_str()),
+                (None, None) => {
+                    default_language.is_none()
+                        && definition.is_none_or(|d| d.type_mapping.is_none())
+                        && RdfTerm::parse(value) == RdfTerm::literal(value.as_str())
+                }
+            };
+            if plain {
+                return Value::String(value.clone());
+            }
+            let mut literal = serde_json::json!({ "@value": value });
+            if let Some(language) = language {
+                literal["@language"] = Value::String(language.clone());
+            } else if let Some(datatype) = datatype {
+                literal["@type"] = Value::String(context.compact_iri(datatype, true));
+            }
+            literal
+        }
+    }
+}
+
+fn frame_matches(frame: &Map<String, Value>, subject: &str, properties: &[&Triple], context: &JsonLdContext) -> bool {
+    let types: Vec<&str> = properties.iter()
+        .filter(|t| t.predicate == RDF_TYPE)
+        .map(|t| t.object.as_str())
+        .collect();
+    let type_matches = match frame_values(frame.get("@type"), context, true) {
+        None => true,
+        Some(wanted) if wanted.is_empty() => !types.is_empty(),
+        Some(wanted) => wanted.iter().any(|class| types.contains(&class.as_str())),
+    };
+    let id_matches = match frame_values(frame.get("@id"), context, false) {
+        None => true,
+        Some(wanted) => wanted.is_empty() || wanted.iter().any(|id| id == subject),
+    };
+    type_matches && id_matches
+}
+
+/// Expanded `@type` / `@id` values of a frame (`Some(empty)` for the `{}` wildcard)
+fn frame_values(value: Option<&Value>, context: &JsonLdContext, vocab: bool) -> Option<Vec<String>> {
+    Some(as_values(value?).into_iter()
+        .filter_map(Value::as_str)
+        .map(|value| context.expand_iri(value, vocab))
+        .collect())
+}
+
+fn frame_node(
+    subject: &str,
+    properties: &[&Triple],
+    frame: &Map<String, Value>,
+    context: &JsonLdContext,
+    index: &HashMap<&str, &[&Triple]>,
+    path: &mut Vec<String>,
+) -> Map<String, Value> {
+    let subframes: HashMap<String, &Value> = frame.iter()
+        .filter(|(key, _)| !key.starts_with('@'))
+        .map(|(key, value)| (context.expand_iri(key, true), value))
+        .collect();
+    let explicit = frame.get("@explicit").and_then(Value::as_bool).unwrap_or(false);
+    let selected: Vec<&Triple> = properties.iter()
+        .copied()
+        .filter(|t| !explicit || t.predicate == RDF_TYPE || subframes.contains_key(t.predicate.as_str()))
+        .collect();
+
+    let empty = Map::new();
+    compact_node(subject, &selected, context, &mut |triple| {
+        let object = triple.object.as_str();
+        let target = index.get(object)?;
+        if path.iter().any(|ancestor| ancestor == object) {
+            return None;
+        }
+        let subframe = match subframes.get(triple.predicate.as_str()) {
+            Some(Value::Object(subframe)) => subframe,
+            Some(Value::Array(items)) => items.iter().find_map(Value::as_object)?,
+            _ if object.starts_with("_:") => &empty,
+            _ => return None,
+        };
+        if !frame_matches(subframe, object, target, context) {
+            return None;
+        }
+        path.push(object.to_string());
+        let embedded = frame_node(object, target, subframe, context, index, path);
+        path.pop();
+        Some(Value::Object(embedded))
+    })
+}
+
+/// Terms of [`security_context`]; each maps to the same name in [`SECURITY_VOCAB`]
+const SECURITY_TERMS: &[&str] = &[
+    "sourceIp", "destIp", "port", "protocol", "timestamp",
+    "processId", "parentProcessId", "commandLine", "user",
+    "filePath", "accessType", "success",
+    "queryName", "queryType", "resolvedIp",
+    "httpMethod", "url", "host", "userAgent", "statusCode",
+    "registryKey", "registryValueName", "registryValueData", "registryOperation",
+    "sender", "recipient", "emailSubject", "replyTo", "attachment",
+];
+
+/// Default context of [`cyber_event_to_jsonld`]: `@vocab` plus one term per event field
+pub fn security_context() -> JsonLdContext {
+    SECURITY_TERMS.iter().fold(JsonLdContext::new().with_vocab(SECURITY_VOCAB), |context, term| {
+        context.with_term(*term, TermDefinition::new(format!("{}{}", SECURITY_VOCAB, term)))
+    })
+}
+
 /// Convert cyber event to JSON-LD
 pub fn cyber_event_to_jsonld(event: &CyberEvent) -> Result<JsonLdDocument> {
+    cyber_event_to_jsonld_with_context(event, &security_context())
+}
+
+/// [`cyber_event_to_jsonld`] with keys and `@type` compacted by a caller-supplied context
+///
+/// イベントの項目とイベント種別は [`SECURITY_VOCAB`] の IRI として扱い、`context` の項・接頭辞・
+/// `@vocab` で短縮する。値 (数値・真偽値・配列) は JSON のまま出力する
+pub fn cyber_event_to_jsonld_with_context(event: &CyberEvent, context: &JsonLdContext) -> Result<JsonLdDocument> {
     let (event_type, data) = match event {
         CyberEvent::NetworkConnection { source_ip, dest_ip, port, protocol, timestamp } => {
             ("NetworkConnection", serde_json::json!({
@@ -72,26 +556,56 @@ pub fn cyber_event_to_jsonld(event: &CyberEvent) -> Result<JsonLdDocument> {
                 "timestamp": timestamp
             }))
         },
+        CyberEvent::DnsQuery { query_name, query_type, source_ip, resolved_ips, timestamp } => {
+            ("DnsQuery", serde_json::json!({
+                "queryName": query_name,
+                "queryType": query_type,
+                "sourceIp": source_ip,
+                "resolvedIp": resolved_ips,
+                "timestamp": timestamp
+            }))
+        },
+        CyberEvent::HttpRequest { method, url, host, user_agent, source_ip, status_code, timestamp } => {
+            ("HttpRequest", serde_json::json!({
+                "httpMethod": method,
+                "url": url,
+                "host": host,
+                "userAgent": user_agent,
+                "sourceIp": source_ip,
+                "statusCode": status_code,
+                "timestamp": timestamp
+            }))
+        },
+        CyberEvent::RegistryModification { key_path, value_name, value_data, operation, process_id, user, timestamp } => {
+            ("RegistryModification", serde_json::json!({
+                "registryKey": key_path,
+                "registryValueName": value_name,
+                "registryValueData": value_data,
+                "registryOperation": operation,
+                "processId": process_id,
+                "user": user,
+                "timestamp": timestamp
+            }))
+        },
+        CyberEvent::EmailReceived { sender, recipient, subject, reply_to, urls, attachments, timestamp } => {
+            ("EmailReceived", serde_json::json!({
+                "sender": sender,
+                "recipient": recipient,
+                "emailSubject": subject,
+                "replyTo": reply_to,
+                "url": urls,
+                "attachment": attachments,
+                "timestamp": timestamp
+            }))
+        },
     };
 
-    let context = serde_json::json!({
-        "@vocab": "https://w3id.org/security#",
-        "sourceIp": "https://w3id.org/security#sourceIp",
-        "destIp": "https://w3id.org/security#destIp",
-        "port": "https://w3id.org/security#port",
-        "protocol": "https://w3id.org/security#protocol",
-        "timestamp": "https://w3id.org/security#timestamp",
-        "processId": "https://w3id.org/security#processId",
-        "parentProcessId": "https://w3id.org/security#parentProcessId",
-        "commandLine": "https://w3id.org/security#commandLine",
-        "user": "https://w3id.org/security#user",
-        "filePath": "https://w3id.org/security#filePath",
-        "accessType": "https://w3id.org/security#accessType",
-        "success": "https://w3id.org/security#success"
-    });
-
-    let mut event_node = data.as_object().unwrap().clone();
-    event_node.insert("@type".to_string(), serde_json::Value::String(event_type.to_string()));
+    let mut event_node = Map::new();
+    for (field, value) in data.as_object().unwrap() {
+        event_node.insert(context.compact_iri(&format!("{}{}", SECURITY_VOCAB, field), true), value.clone());
+    }
+    let event_type = context.compact_iri(&format!("{}{}", SECURITY_VOCAB, event_type), true);
+    event_node.insert("@type".to_string(), Value::String(event_type));
     #[cfg(feature = "uuid")]
     let event_id = format!("_:event_{}", uuid::Uuid::new_v4());
     #[cfg(not(feature = "uuid"))]
@@ -99,8 +613,8 @@ pub fn cyber_event_to_jsonld(event: &CyberEvent) -> Result<JsonLdDocument> {
     event_node.insert("@id".to_string(), serde_json::Value::String(event_id));
 
     Ok(JsonLdDocument {
-        context,
-        graph: Some(vec![serde_json::Value::Object(event_node)]),
+        context: context.to_value(),
+        graph: Some(vec![Value::Object(event_node)]),
         data: std::collections::HashMap::new(),
     })
 }
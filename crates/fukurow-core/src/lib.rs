@@ -4,14 +4,24 @@
 //! サイバーセキュリティイベントの推論に必要なグラフ構造を提供
 
 pub mod model;
+pub mod term;
 pub mod store;
 pub mod query;
 pub mod jsonld;
+pub mod retry;
+pub mod prefix;
+pub mod context;
+pub mod validation;
 
 pub use model::*;
+pub use term::*;
 pub use store::*;
 pub use query::*;
 pub use jsonld::*;
+pub use prefix::*;
+pub use context::*;
+pub use retry::{RetryPolicy, Retryable, SplitMix64};
+pub use validation::{EventValidator, EventValidationMode, ValidationIssue, ValidationOutcome, IssueSeverity};
 
 #[cfg(test)]
 mod tests {
@@ -65,6 +75,26 @@ mod tests {
             assert_eq!(s.as_str(), "test");
         }
 
+        #[test]
+        fn test_interned_strings_share_storage_and_compare_as_str() {
+            let s1 = InternedString::new("http://example.org/shared".to_string());
+            let s2 = InternedString::from("http://example.org/shared");
+            assert!(s1.ptr_eq(&s2));
+            assert_eq!(s1, "http://example.org/shared");
+            assert_eq!("http://example.org/shared".to_string(), s1);
+            assert!(s1.starts_with("http:"));
+
+            let mut index = std::collections::HashMap::new();
+            index.insert(s1.clone(), 1);
+            assert_eq!(index.get("http://example.org/shared"), Some(&1));
+
+            let triple = InternedTriple::new("s", "p", s1);
+            let json = serde_json::to_string(&triple).unwrap();
+            assert_eq!(json, r#"{"subject":"s","predicate":"p","object":"http://example.org/shared"}"#);
+            let plain: Triple = serde_json::from_str(&json).unwrap();
+            assert_eq!(triple, plain);
+        }
+
         #[test]
         fn test_interned_string_display() {
             let s = InternedString::new("test");
@@ -115,6 +145,26 @@ mod tests {
             assert_eq!(triple1, triple2);
             assert_ne!(triple1, triple3);
         }
+
+        #[test]
+        fn test_alert_attack_techniques() {
+            let alert = SecurityAction::Alert {
+                severity: "high".to_string(),
+                message: "Brute force".to_string(),
+                details: serde_json::json!({ "user": "alice" }),
+            };
+            let tagged = alert
+                .with_attack_techniques(&["T1110".to_string()])
+                .with_attack_techniques(&["T1110".to_string(), "T1078".to_string()]);
+            assert_eq!(tagged.attack_techniques(), vec!["T1110", "T1078"]);
+            match &tagged {
+                SecurityAction::Alert { details, .. } => assert_eq!(details["user"], "alice"),
+                other => panic!("unexpected action {:?}", other),
+            }
+
+            let block = SecurityAction::IsolateHost { host_ip: "10.0.0.1".to_string(), reason: "test".to_string() };
+            assert!(block.with_attack_techniques(&["T1110".to_string()]).attack_techniques().is_empty());
+        }
     }
 
     #[cfg(test)]
@@ -325,15 +375,16 @@ mod tests {
             let triples = jsonld_to_triples(&jsonld).unwrap();
             assert_eq!(triples.len(), 2);
 
+            // プロパティ名は @vocab で展開される
             assert!(triples.contains(&Triple {
                 subject: "subject1".to_string(),
-                predicate: "predicate1".to_string(),
+                predicate: "https://example.org/predicate1".to_string(),
                 object: "object1".to_string(),
             }));
 
             assert!(triples.contains(&Triple {
                 subject: "subject1".to_string(),
-                predicate: "predicate2".to_string(),
+                predicate: "https://example.org/predicate2".to_string(),
                 object: "object2".to_string(),
             }));
         }
@@ -350,6 +401,262 @@ mod tests {
             assert_eq!(triples.len(), 0);
         }
 
+        #[test]
+        fn test_jsonld_blank_nodes_are_scoped_per_document() {
+            let jsonld = JsonLdDocument {
+                context: serde_json::json!({}),
+                graph: Some(vec![
+                    serde_json::json!({
+                        "@id": "_:event",
+                        "http://example.org/user": "alice",
+                        "http://example.org/host": { "http://example.org/name": "web-01" }
+                    }),
+                    serde_json::json!({
+                        "@id": "http://example.org/alert",
+                        "http://example.org/about": { "@id": "_:event" }
+                    })
+                ]),
+                data: std::collections::HashMap::new(),
+            };
+
+            let first = jsonld_to_triples(&jsonld).unwrap();
+            assert_eq!(first.len(), 4);
+            let event = first.iter().find(|t| t.predicate == "http://example.org/user").unwrap().subject.clone();
+            assert!(event.starts_with("_:") && event != "_:event");
+            assert!(first.iter().any(|t| t.predicate == "http://example.org/about" && t.object == event));
+
+            // ネストしたノードは新しいブランクノードになる
+            let host = first.iter().find(|t| t.predicate == "http://example.org/host").unwrap();
+            assert!(host.object_term().is_blank_node());
+            assert!(first.iter().any(|t| t.subject == host.object && t.object == "web-01"));
+
+            let second = jsonld_to_triples(&jsonld).unwrap();
+            assert!(second.iter().all(|t| t.subject != event && t.object != event));
+        }
+
+        #[test]
+        fn test_jsonld_value_objects_and_round_trip() {
+            let jsonld = JsonLdDocument {
+                context: serde_json::json!({}),
+                graph: Some(vec![serde_json::json!({
+                    "@id": "http://example.org/e1",
+                    "http://example.org/port": { "@value": 443, "@type": "http://www.w3.org/2001/XMLSchema#integer" },
+                    "http://example.org/label": [{ "@value": "Anmeldung", "@language": "de" }, "login"]
+                })]),
+                data: std::collections::HashMap::new(),
+            };
+
+            let triples = jsonld_to_triples(&jsonld).unwrap();
+            assert_eq!(triples.len(), 3);
+            assert!(triples.iter().any(|t| t.object_term() == RdfTerm::typed_literal("443", "http://www.w3.org/2001/XMLSchema#integer")));
+            assert!(triples.iter().any(|t| t.object_term() == RdfTerm::lang_literal("Anmeldung", "de")));
+
+            let exported = triples_to_jsonld(&triples);
+            assert_eq!(exported.graph.as_ref().unwrap().len(), 1);
+            let mut reimported = jsonld_to_triples(&exported).unwrap();
+            let mut original = triples.clone();
+            reimported.sort_by(|a, b| a.object.cmp(&b.object));
+            original.sort_by(|a, b| a.object.cmp(&b.object));
+            assert_eq!(reimported, original);
+        }
+
+        #[test]
+        fn test_jsonld_context_prefixes_are_expanded() {
+            let jsonld = JsonLdDocument {
+                context: serde_json::json!({"ex": "http://example.org/", "name": "http://xmlns.com/foaf/0.1/name"}),
+                graph: Some(vec![serde_json::json!({
+                    "@id": "ex:h1",
+                    "ex:connectsTo": { "@id": "ex:h2" },
+                    "ex:port": { "@value": "443", "@type": "xsd:integer" }
+                })]),
+                data: std::collections::HashMap::new(),
+            };
+
+            let prefixes = context_prefixes(&jsonld.context);
+            assert_eq!(prefixes.get("ex"), Some("http://example.org/"));
+            assert_eq!(prefixes.get("name"), None);
+
+            let triples = jsonld_to_triples(&jsonld).unwrap();
+            assert!(triples.contains(&Triple {
+                subject: "http://example.org/h1".to_string(),
+                predicate: "http://example.org/connectsTo".to_string(),
+                object: "http://example.org/h2".to_string(),
+            }));
+            // xsd は @context で宣言されていないので展開しない
+            assert!(triples.iter().any(|t| t.object_term() == RdfTerm::typed_literal("443", "xsd:integer")));
+
+            let exported = triples_to_jsonld_with_prefixes(&triples, &PrefixMap::default().with_prefix("ex", "http://example.org/"));
+            assert_eq!(exported.context["ex"], "http://example.org/");
+            assert_eq!(exported.context["owl"], OWL_NAMESPACE);
+        }
+
+        #[test]
+        fn test_jsonld_context_terms_and_coercion() {
+            let jsonld = parse_jsonld(r#"{
+                "@context": {
+                    "@vocab": "http://schema.org/",
+                    "@base": "http://example.org/hosts/",
+                    "ex": "http://example.org/",
+                    "peer": { "@id": "ex:connectsTo", "@type": "@id" },
+                    "seen": { "@id": "ex:lastSeen", "@type": "xsd:dateTime" },
+                    "xsd": "http://www.w3.org/2001/XMLSchema#",
+                    "label": { "@id": "http://www.w3.org/2000/01/rdf-schema#label", "@language": "ja" }
+                },
+                "@id": "h1",
+                "@type": "Server",
+                "name": "web-01",
+                "peer": "ex:h2",
+                "seen": "2024-01-01T00:00:00Z",
+                "label": "ウェブ",
+                "ex:port": 443,
+                "ex:trusted": false
+            }"#).unwrap();
+
+            let triples = jsonld_to_triples(&jsonld).unwrap();
+            let subject = "http://example.org/hosts/h1";
+            let object = |predicate: &str| triples.iter()
+                .find(|t| t.subject == subject && t.predicate == predicate)
+                .map(|t| t.object_term())
+                .unwrap();
+
+            assert_eq!(object("http://www.w3.org/1999/02/22-rdf-syntax-ns#type"), RdfTerm::iri("http://schema.org/Server"));
+            assert_eq!(object("http://schema.org/name"), RdfTerm::literal("web-01"));
+            assert_eq!(object("http://example.org/connectsTo"), RdfTerm::iri("http://example.org/h2"));
+            assert_eq!(object("http://example.org/lastSeen"), RdfTerm::typed_literal("2024-01-01T00:00:00Z", "http://www.w3.org/2001/XMLSchema#dateTime"));
+            assert_eq!(object("http://www.w3.org/2000/01/rdf-schema#label"), RdfTerm::lang_literal("ウェブ", "ja"));
+            assert_eq!(object("http://example.org/port"), RdfTerm::typed_literal("443", "http://www.w3.org/2001/XMLSchema#integer"));
+            assert_eq!(object("http://example.org/trusted"), RdfTerm::typed_literal("false", "http://www.w3.org/2001/XMLSchema#boolean"));
+        }
+
+        #[test]
+        fn test_jsonld_remote_and_node_contexts() {
+            let jsonld = JsonLdDocument {
+                context: serde_json::json!(["https://example.org/context.jsonld", { "note": "http://example.org/note" }]),
+                graph: Some(vec![serde_json::json!({
+                    "@id": "ex:a1",
+                    "host": { "@context": { "@vocab": "http://example.org/host#" }, "name": "web-01" }
+                })]),
+                data: std::collections::HashMap::new(),
+            };
+            assert!(jsonld_to_triples(&jsonld).is_err());
+
+            let base = JsonLdContext::new().with_document(
+                "https://example.org/context.jsonld",
+                serde_json::json!({ "@context": { "ex": "http://example.org/", "host": "ex:host" } }),
+            );
+            let triples = jsonld_to_triples_with_context(&jsonld, &base).unwrap();
+            let host = triples.iter().find(|t| t.subject == "http://example.org/a1" && t.predicate == "http://example.org/host").unwrap();
+            assert!(triples.iter().any(|t| t.subject == host.object && t.predicate == "http://example.org/host#name"));
+
+            let context = JsonLdContext::parse(&serde_json::json!([{ "ex": "http://example.org/" }, null])).unwrap();
+            assert_eq!(context.term("ex"), None);
+        }
+
+        #[test]
+        fn test_triples_to_jsonld_with_context_round_trip() {
+            let context = JsonLdContext::parse(&serde_json::json!({
+                "@vocab": "http://example.org/",
+                "xsd": "http://www.w3.org/2001/XMLSchema#",
+                "peer": { "@id": "http://example.org/connectsTo", "@type": "@id" },
+                "port": { "@id": "http://example.org/port", "@type": "xsd:integer" }
+            })).unwrap();
+            let triple = |p: &str, o: &str| Triple {
+                subject: "http://example.org/h1".to_string(),
+                predicate: p.to_string(),
+                object: o.to_string(),
+            };
+            let triples = vec![
+                triple("http://www.w3.org/1999/02/22-rdf-syntax-ns#type", "http://example.org/Server"),
+                triple("http://example.org/connectsTo", "http://example.org/h2"),
+                triple("http://example.org/port", &RdfTerm::typed_literal("443", "http://www.w3.org/2001/XMLSchema#integer").encode()),
+                triple("http://example.org/name", "web-01"),
+            ];
+
+            let exported = triples_to_jsonld_with_context(&triples, &context);
+            let node = &exported.graph.as_ref().unwrap()[0];
+            assert_eq!(node["@type"], "Server");
+            assert_eq!(node["peer"], "http://example.org/h2");
+            assert_eq!(node["port"], "443");
+            assert_eq!(node["name"], "web-01");
+            assert_eq!(exported.context["peer"]["@type"], "@id");
+
+            let mut reimported = jsonld_to_triples(&exported).unwrap();
+            let mut original = triples.clone();
+            reimported.sort_by(|a, b| a.predicate.cmp(&b.predicate));
+            original.sort_by(|a, b| a.predicate.cmp(&b.predicate));
+            assert_eq!(reimported, original);
+        }
+
+        #[test]
+        fn test_frame_triples_embeds_and_filters() {
+            let triple = |s: &str, p: &str, o: &str| Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() };
+            let rdf_type = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+            let triples = vec![
+                triple("http://example.org/alert1", rdf_type, "http://example.org/Alert"),
+                triple("http://example.org/alert1", "http://example.org/about", "http://example.org/h1"),
+                triple("http://example.org/alert1", "http://example.org/severity", "high"),
+                triple("http://example.org/alert1", "http://example.org/evidence", "_:e1"),
+                triple("_:e1", "http://example.org/note", "port scan"),
+                triple("http://example.org/h1", rdf_type, "http://example.org/Host"),
+                triple("http://example.org/h1", "http://example.org/name", "web-01"),
+                triple("http://example.org/h1", "http://example.org/alert", "http://example.org/alert1"),
+            ];
+
+            let framed = frame_triples(&triples, &serde_json::json!({
+                "@context": { "@vocab": "http://example.org/", "ex": "http://example.org/" },
+                "@type": "Alert",
+                "about": { "@type": "Host", "@explicit": true, "name": {} }
+            })).unwrap();
+            assert_eq!(framed.context["@vocab"], "http://example.org/");
+            let graph = framed.graph.as_ref().unwrap();
+            assert_eq!(graph.len(), 1);
+            let alert = &graph[0];
+            assert_eq!(alert["@id"], "ex:alert1");
+            assert_eq!(alert["severity"], "high");
+            // ブランクノードは常に埋め込む
+            assert_eq!(alert["evidence"]["note"], "port scan");
+            // @explicit なので name と @type だけ、循環する alert は出力しない
+            assert_eq!(alert["about"]["name"], "web-01");
+            assert_eq!(alert["about"]["@type"], "Host");
+            assert!(alert["about"].get("alert").is_none());
+
+            // 型が一致しないサブフレームは参照のまま
+            let framed = frame_triples(&triples, &serde_json::json!({
+                "@context": { "@vocab": "http://example.org/" },
+                "@type": "Alert",
+                "about": { "@type": "Alert" }
+            })).unwrap();
+            assert_eq!(framed.graph.as_ref().unwrap()[0]["about"], serde_json::json!({ "@id": "http://example.org/h1" }));
+
+            // フィルタなしではブランクノードだけがトップレベルから外れる
+            let framed = frame_triples(&triples, &serde_json::json!({})).unwrap();
+            assert_eq!(framed.graph.as_ref().unwrap().len(), 2);
+        }
+
+        #[test]
+        fn test_cyber_event_to_jsonld_with_custom_context() {
+            let event = CyberEvent::UserLogin {
+                user: "charlie".to_string(),
+                source_ip: "203.0.113.1".to_string(),
+                success: false,
+                timestamp: 1640995200,
+            };
+            let context = JsonLdContext::new()
+                .with_term("sec", TermDefinition::new(SECURITY_VOCAB))
+                .with_term("login_user", TermDefinition::new(format!("{}user", SECURITY_VOCAB)));
+
+            let jsonld = cyber_event_to_jsonld_with_context(&event, &context).unwrap();
+            let node = &jsonld.graph.as_ref().unwrap()[0];
+            assert_eq!(node["@type"], "sec:UserLogin");
+            assert_eq!(node["login_user"], "charlie");
+            assert_eq!(node["sec:sourceIp"], "203.0.113.1");
+            assert_eq!(jsonld.context["login_user"], "https://w3id.org/security#user");
+
+            let triples = jsonld_to_triples(&jsonld).unwrap();
+            assert!(triples.iter().any(|t| t.predicate == "https://w3id.org/security#user" && t.object == "charlie"));
+            assert!(triples.iter().any(|t| t.object == "https://w3id.org/security#UserLogin"));
+        }
+
         #[test]
         fn test_cyber_event_to_jsonld_network_connection() {
             let event = CyberEvent::NetworkConnection {
@@ -436,6 +743,78 @@ mod tests {
             assert_eq!(node.get("success").unwrap(), true);
         }
 
+        #[test]
+        fn test_cyber_event_to_jsonld_dns_and_http() {
+            let dns = CyberEvent::DnsQuery {
+                query_name: "xkqjzvbrt.example".to_string(),
+                query_type: "A".to_string(),
+                source_ip: "10.0.0.5".to_string(),
+                resolved_ips: vec!["203.0.113.7".to_string(), "203.0.113.8".to_string()],
+                timestamp: 1640995200,
+            };
+            let jsonld = cyber_event_to_jsonld(&dns).unwrap();
+            let node = &jsonld.graph.as_ref().unwrap()[0];
+            assert_eq!(node.get("@type").unwrap(), "DnsQuery");
+            assert_eq!(node.get("queryName").unwrap(), "xkqjzvbrt.example");
+            assert_eq!(node.get("resolvedIp").unwrap(), &serde_json::json!(["203.0.113.7", "203.0.113.8"]));
+
+            let http = CyberEvent::HttpRequest {
+                method: "GET".to_string(),
+                url: "http://203.0.113.7/payload.bin".to_string(),
+                host: "203.0.113.7".to_string(),
+                user_agent: "curl/8.0".to_string(),
+                source_ip: "10.0.0.5".to_string(),
+                status_code: Some(200),
+                timestamp: 1640995201,
+            };
+            let jsonld = cyber_event_to_jsonld(&http).unwrap();
+            let node = &jsonld.graph.as_ref().unwrap()[0];
+            assert_eq!(node.get("@type").unwrap(), "HttpRequest");
+            assert_eq!(node.get("userAgent").unwrap(), "curl/8.0");
+            assert_eq!(node.get("statusCode").unwrap(), 200);
+            assert!(jsonld.context.get("userAgent").is_some());
+        }
+
+        #[test]
+        fn test_cyber_event_to_jsonld_registry_and_email() {
+            let registry = CyberEvent::RegistryModification {
+                key_path: r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run".to_string(),
+                value_name: Some("updater".to_string()),
+                value_data: Some(r"C:\Users\Public\updater.exe".to_string()),
+                operation: "set".to_string(),
+                process_id: 4242,
+                user: "alice".to_string(),
+                timestamp: 1640995200,
+            };
+            let jsonld = cyber_event_to_jsonld(&registry).unwrap();
+            let node = &jsonld.graph.as_ref().unwrap()[0];
+            assert_eq!(node.get("@type").unwrap(), "RegistryModification");
+            assert_eq!(node.get("registryValueName").unwrap(), "updater");
+            assert_eq!(node.get("registryOperation").unwrap(), "set");
+
+            let email = CyberEvent::EmailReceived {
+                sender: "it-support@examp1e.com".to_string(),
+                recipient: "bob@example.com".to_string(),
+                subject: "Urgent: verify your account".to_string(),
+                reply_to: None,
+                urls: vec!["http://examp1e.com/login".to_string()],
+                attachments: vec![],
+                timestamp: 1640995200,
+            };
+            let jsonld = cyber_event_to_jsonld(&email).unwrap();
+            let node = &jsonld.graph.as_ref().unwrap()[0];
+            assert_eq!(node.get("@type").unwrap(), "EmailReceived");
+            assert_eq!(node.get("emailSubject").unwrap(), "Urgent: verify your account");
+            assert_eq!(node.get("url").unwrap(), &serde_json::json!(["http://examp1e.com/login"]));
+
+            // 省略可能な項目は既定値で読み込める
+            let decoded: CyberEvent = serde_json::from_value(serde_json::json!({
+                "type": "EmailReceived",
+                "data": { "sender": "a@example.com", "recipient": "b@example.com", "subject": "hi", "timestamp": 1 }
+            })).unwrap();
+            assert!(matches!(decoded, CyberEvent::EmailReceived { reply_to: None, ref urls, .. } if urls.is_empty()));
+        }
+
         #[test]
         fn test_parse_jsonld() {
             let json_str = r#"{
@@ -738,4 +1117,43 @@ mod tests {
             assert_eq!(jsonld.graph.as_ref().unwrap().len(), 1);
         }
     }
+
+    #[cfg(test)]
+    mod retry_tests {
+        use super::*;
+        use std::time::Duration;
+
+        #[test]
+        fn test_backoff_without_jitter_is_exponential() {
+            let policy = RetryPolicy::new(5)
+                .with_initial_backoff_ms(100)
+                .with_max_backoff_ms(500)
+                .with_jitter(false);
+
+            let delays: Vec<Duration> = policy.backoff().collect();
+            assert_eq!(delays, vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(400),
+                Duration::from_millis(500),
+            ]);
+        }
+
+        #[test]
+        fn test_backoff_with_jitter_stays_in_bounds() {
+            let policy = RetryPolicy::new(20).with_initial_backoff_ms(50).with_max_backoff_ms(2_000);
+            let delays: Vec<Duration> = policy.backoff_with_seed(7).collect();
+
+            assert_eq!(delays.len(), 19);
+            assert!(delays.iter().all(|d| *d >= Duration::from_millis(50) && *d <= Duration::from_millis(2_000)));
+            let again: Vec<Duration> = policy.backoff_with_seed(7).collect();
+            assert_eq!(delays, again);
+        }
+
+        #[test]
+        fn test_no_retry_policy() {
+            assert_eq!(RetryPolicy::no_retry().backoff().count(), 0);
+            assert_eq!(RetryPolicy::new(0).max_attempts, 1);
+        }
+    }
 }
@@ -1,33 +1,38 @@
 //! Graph data models for JSON-LD reasoning
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 // Sophia API imports removed - using simple string-based representation
-use std::collections::HashMap;
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use lazy_static::lazy_static;
 
 /// Global string interning pool for memory optimization
+///
+/// 文字列そのものをキーにする (content-addressable)。同じ内容の文字列は 1 つの割り当てを共有する
 lazy_static! {
-    static ref STRING_POOL: Arc<RwLock<HashMap<String, Arc<String>>>> = Arc::new(RwLock::new(HashMap::new()));
+    static ref STRING_POOL: RwLock<HashSet<Arc<str>>> = RwLock::new(HashSet::new());
 }
 
 /// Interned string that reuses memory for identical strings
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct InternedString(Arc<String>);
+///
+/// `str` として比較・ハッシュするため、`HashMap<InternedString, _>` は `&str` で引ける
+#[derive(Clone, Eq)]
+pub struct InternedString(Arc<str>);
 
 impl InternedString {
     /// Create a new interned string, reusing existing instances when possible
-    pub fn new<S: Into<String>>(s: S) -> Self {
-        let string = s.into();
-        let mut pool = STRING_POOL.write().unwrap();
-
-        if let Some(interned) = pool.get(&string) {
-            InternedString(Arc::clone(interned))
-        } else {
-            let interned = Arc::new(string.clone());
-            pool.insert(string, Arc::clone(&interned));
-            InternedString(interned)
+    pub fn new<S: AsRef<str> + Into<String>>(s: S) -> Self {
+        if let Some(interned) = STRING_POOL.read().unwrap_or_else(|poisoned| poisoned.into_inner()).get(s.as_ref()) {
+            return InternedString(Arc::clone(interned));
         }
+        let mut pool = STRING_POOL.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(interned) = pool.get(s.as_ref()) {
+            return InternedString(Arc::clone(interned));
+        }
+        let interned: Arc<str> = Arc::from(s.into());
+        pool.insert(Arc::clone(&interned));
+        InternedString(interned)
     }
 
     /// Get the string slice
@@ -44,6 +49,117 @@ impl InternedString {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// Whether both share one allocation
+    pub fn ptr_eq(&self, other: &InternedString) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Drop pooled strings no longer referenced outside the pool; returns the number dropped
+    ///
+    /// 削除したトリプルの文字列はプールに残るため、大量削除の後に呼ぶ
+    pub fn release_unused() -> usize {
+        let mut pool = STRING_POOL.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let before = pool.len();
+        pool.retain(|interned| Arc::strong_count(interned) > 1);
+        before - pool.len()
+    }
+
+    /// Whether `s` is currently held by the pool
+    pub fn is_interned(s: &str) -> bool {
+        STRING_POOL.read().unwrap_or_else(|poisoned| poisoned.into_inner()).contains(s)
+    }
+
+    /// Number of distinct strings in the pool
+    pub fn pool_size() -> usize {
+        STRING_POOL.read().unwrap_or_else(|poisoned| poisoned.into_inner()).len()
+    }
+}
+
+impl Default for InternedString {
+    fn default() -> Self {
+        InternedString::new("")
+    }
+}
+
+impl PartialEq for InternedString {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl std::hash::Hash for InternedString {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for InternedString {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InternedString {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialEq<str> for InternedString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for InternedString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for InternedString {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl PartialEq<&String> for InternedString {
+    fn eq(&self, other: &&String) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl PartialEq<InternedString> for str {
+    fn eq(&self, other: &InternedString) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<InternedString> for &str {
+    fn eq(&self, other: &InternedString) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<InternedString> for String {
+    fn eq(&self, other: &InternedString) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl std::ops::Deref for InternedString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for InternedString {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
 }
 
 impl From<String> for InternedString {
@@ -60,13 +176,25 @@ impl From<&str> for InternedString {
 
 impl From<&String> for InternedString {
     fn from(s: &String) -> Self {
-        InternedString::new(s.clone())
+        InternedString::new(s.as_str())
+    }
+}
+
+impl From<InternedString> for String {
+    fn from(s: InternedString) -> Self {
+        s.as_str().to_string()
     }
 }
 
 impl std::fmt::Display for InternedString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        f.write_str(&self.0)
+    }
+}
+
+impl std::fmt::Debug for InternedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&*self.0, f)
     }
 }
 
@@ -76,6 +204,18 @@ impl AsRef<str> for InternedString {
     }
 }
 
+impl Serialize for InternedString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for InternedString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(InternedString::new)
+    }
+}
+
 /// RDF Triple representation
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Triple {
@@ -85,7 +225,9 @@ pub struct Triple {
 }
 
 /// Memory-optimized RDF Triple using interned strings
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// シリアライズ形式は [`Triple`] と同じ
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct InternedTriple {
     pub subject: InternedString,
     pub predicate: InternedString,
@@ -125,6 +267,42 @@ impl InternedTriple {
     }
 }
 
+impl From<Triple> for InternedTriple {
+    fn from(triple: Triple) -> Self {
+        InternedTriple::new(triple.subject, triple.predicate, triple.object)
+    }
+}
+
+impl From<&Triple> for InternedTriple {
+    fn from(triple: &Triple) -> Self {
+        InternedTriple::from_triple(triple)
+    }
+}
+
+impl From<InternedTriple> for Triple {
+    fn from(triple: InternedTriple) -> Self {
+        triple.to_triple()
+    }
+}
+
+impl From<&InternedTriple> for Triple {
+    fn from(triple: &InternedTriple) -> Self {
+        triple.to_triple()
+    }
+}
+
+impl PartialEq<Triple> for InternedTriple {
+    fn eq(&self, other: &Triple) -> bool {
+        self.subject == other.subject && self.predicate == other.predicate && self.object == other.object
+    }
+}
+
+impl PartialEq<InternedTriple> for Triple {
+    fn eq(&self, other: &InternedTriple) -> bool {
+        other == self
+    }
+}
+
 /// JSON-LD Document with context
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonLdDocument {
@@ -174,8 +352,54 @@ pub enum CyberEvent {
         success: bool,
         timestamp: i64,
     },
+    DnsQuery {
+        query_name: String,
+        /// Record type (`A`, `AAAA`, `TXT`, ...)
+        query_type: String,
+        source_ip: String,
+        #[serde(default)]
+        resolved_ips: Vec<String>,
+        timestamp: i64,
+    },
+    HttpRequest {
+        method: String,
+        url: String,
+        host: String,
+        user_agent: String,
+        source_ip: String,
+        status_code: Option<u16>,
+        timestamp: i64,
+    },
+    RegistryModification {
+        /// Full key path (e.g. `HKLM\Software\Microsoft\Windows\CurrentVersion\Run`)
+        key_path: String,
+        value_name: Option<String>,
+        value_data: Option<String>,
+        /// `set`, `create`, `delete`, ...
+        operation: String,
+        process_id: u32,
+        user: String,
+        timestamp: i64,
+    },
+    EmailReceived {
+        sender: String,
+        recipient: String,
+        subject: String,
+        reply_to: Option<String>,
+        #[serde(default)]
+        urls: Vec<String>,
+        /// Attachment file names
+        #[serde(default)]
+        attachments: Vec<String>,
+        timestamp: i64,
+    },
 }
 
+/// Key of the ATT&CK annotation in `SecurityAction::Alert` details
+///
+/// `{"mitre_attack": {"techniques": ["T1110"], ...}}` の形で格納し、SIEM 連携はここから技術 ID を読む
+pub const ATTACK_DETAILS_KEY: &str = "mitre_attack";
+
 /// Security actions that can be proposed by the reasoner
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "action_type", content = "parameters")]
@@ -187,6 +411,74 @@ pub enum SecurityAction {
     Alert { severity: String, message: String, details: serde_json::Value },
 }
 
+impl SecurityAction {
+    /// Attach the correlation IDs of the events that led to this action
+    pub fn correlated(self, correlation_ids: Vec<String>) -> CorrelatedAction {
+        CorrelatedAction { action: self, correlation_ids }
+    }
+
+    /// Add ATT&CK technique IDs to an alert (other actions are returned unchanged)
+    ///
+    /// 既に付いている技術 ID とは重複させず、詳細がオブジェクトでない場合は `original_details` に退避する
+    pub fn with_attack_techniques(mut self, techniques: &[String]) -> Self {
+        if techniques.is_empty() {
+            return self;
+        }
+        if let SecurityAction::Alert { details, .. } = &mut self {
+            if !details.is_object() {
+                let original = std::mem::take(details);
+                *details = if original.is_null() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::json!({ "original_details": original })
+                };
+            }
+            let annotation = details.as_object_mut().unwrap()
+                .entry(ATTACK_DETAILS_KEY)
+                .or_insert_with(|| serde_json::json!({}));
+            if !annotation.is_object() {
+                *annotation = serde_json::json!({});
+            }
+            let ids = annotation.as_object_mut().unwrap()
+                .entry("techniques")
+                .or_insert_with(|| serde_json::json!([]));
+            if !ids.is_array() {
+                *ids = serde_json::json!([]);
+            }
+            let ids = ids.as_array_mut().unwrap();
+            for technique in techniques {
+                if !ids.iter().any(|id| id.as_str() == Some(technique)) {
+                    ids.push(serde_json::Value::String(technique.clone()));
+                }
+            }
+        }
+        self
+    }
+
+    /// ATT&CK technique IDs an alert is tagged with
+    pub fn attack_techniques(&self) -> Vec<String> {
+        match self {
+            SecurityAction::Alert { details, .. } => details.get(ATTACK_DETAILS_KEY)
+                .and_then(|annotation| annotation.get("techniques"))
+                .and_then(|ids| ids.as_array())
+                .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Security action traced back to its originating events
+///
+/// アクション本体はそのままのJSON形状でシリアライズし、`correlation_ids` を横に並べる
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrelatedAction {
+    #[serde(flatten)]
+    pub action: SecurityAction,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correlation_ids: Vec<String>,
+}
+
 /// Inference rule for pattern matching
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InferenceRule {
@@ -0,0 +1,217 @@
+//! IRI prefix management
+//!
+//! ルール DSL・SPARQL・JSON-LD・CLI が同じ接頭辞の対応表を使って
+//! CURIE (`rdf:type`) と完全な IRI を相互に変換する
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const RDF_NAMESPACE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+pub const RDFS_NAMESPACE: &str = "http://www.w3.org/2000/01/rdf-schema#";
+pub const OWL_NAMESPACE: &str = "http://www.w3.org/2002/07/owl#";
+pub const SH_NAMESPACE: &str = "http://www.w3.org/ns/shacl#";
+pub const XSD_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// Prefixes every [`PrefixMap::default`] starts with
+pub const DEFAULT_PREFIXES: &[(&str, &str)] = &[
+    ("rdf", RDF_NAMESPACE),
+    ("rdfs", RDFS_NAMESPACE),
+    ("owl", OWL_NAMESPACE),
+    ("sh", SH_NAMESPACE),
+    ("xsd", XSD_NAMESPACE),
+];
+
+/// Namespace of a default prefix
+pub fn default_namespace(prefix: &str) -> Option<&'static str> {
+    DEFAULT_PREFIXES.iter().find(|(p, _)| *p == prefix).map(|(_, namespace)| *namespace)
+}
+
+/// CURIE expansion errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PrefixError {
+    #[error("Unknown prefix: {0}:")]
+    UnknownPrefix(String),
+    #[error("Not a CURIE: {0}")]
+    NotACurie(String),
+}
+
+/// Prefix → namespace IRI table
+///
+/// `Default` holds the [`DEFAULT_PREFIXES`]; user-defined prefixes are added
+/// with [`insert`](Self::insert) and override the defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PrefixMap {
+    prefixes: BTreeMap<String, String>,
+}
+
+impl Default for PrefixMap {
+    fn default() -> Self {
+        let prefixes = DEFAULT_PREFIXES.iter()
+            .map(|(prefix, namespace)| (prefix.to_string(), namespace.to_string()))
+            .collect();
+        Self { prefixes }
+    }
+}
+
+impl PrefixMap {
+    /// Map without the default prefixes
+    pub fn empty() -> Self {
+        Self { prefixes: BTreeMap::new() }
+    }
+
+    /// Declare `prefix` (`ex` or `ex:`) for `namespace` (`http://...` or `<http://...>`)
+    pub fn insert(&mut self, prefix: &str, namespace: &str) {
+        let namespace = namespace.trim_start_matches('<').trim_end_matches('>');
+        self.prefixes.insert(prefix.trim_end_matches(':').to_string(), namespace.to_string());
+    }
+
+    pub fn with_prefix(mut self, prefix: &str, namespace: &str) -> Self {
+        self.insert(prefix, namespace);
+        self
+    }
+
+    pub fn remove(&mut self, prefix: &str) -> Option<String> {
+        self.prefixes.remove(prefix.trim_end_matches(':'))
+    }
+
+    /// Add every prefix of `other` (declarations in `other` win)
+    pub fn extend(&mut self, other: &PrefixMap) {
+        for (prefix, namespace) in other.iter() {
+            self.prefixes.insert(prefix.to_string(), namespace.to_string());
+        }
+    }
+
+    pub fn get(&self, prefix: &str) -> Option<&str> {
+        self.prefixes.get(prefix).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.prefixes.iter().map(|(prefix, namespace)| (prefix.as_str(), namespace.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    /// Expand `prefix:local` to a full IRI
+    pub fn expand(&self, curie: &str) -> Result<String, PrefixError> {
+        let (prefix, local) = split_curie(curie).ok_or_else(|| PrefixError::NotACurie(curie.to_string()))?;
+        let namespace = self.get(prefix).ok_or_else(|| PrefixError::UnknownPrefix(prefix.to_string()))?;
+        Ok(format!("{}{}", namespace, local))
+    }
+
+    /// Expand `term` when it is a CURIE with a known prefix; any other term is returned unchanged
+    ///
+    /// 変数 (`?x`)・リテラル・ブランクノード・完全な IRI (`http://...`, `urn:...`) はそのまま返す
+    pub fn expand_term(&self, term: &str) -> String {
+        self.expand(term).unwrap_or_else(|_| term.to_string())
+    }
+
+    /// Shorten `iri` with the longest matching namespace
+    ///
+    /// The local part must be a valid SPARQL local name so the CURIE can be typed back.
+    pub fn compact(&self, iri: &str) -> Option<String> {
+        self.iter()
+            .filter(|(_, namespace)| !namespace.is_empty() && iri.starts_with(namespace))
+            .filter(|(_, namespace)| is_local_name(&iri[namespace.len()..]))
+            .max_by_key(|(_, namespace)| namespace.len())
+            .map(|(prefix, namespace)| format!("{}:{}", prefix, &iri[namespace.len()..]))
+    }
+
+    /// Compact form for display: the CURIE when one exists, `<iri>` otherwise
+    pub fn display(&self, iri: &str) -> String {
+        self.compact(iri).unwrap_or_else(|| format!("<{}>", iri))
+    }
+
+    /// `PREFIX p: <namespace>` lines for prepending to a SPARQL query
+    pub fn sparql_prologue(&self) -> String {
+        self.iter().map(|(prefix, namespace)| format!("PREFIX {}: <{}>\n", prefix, namespace)).collect()
+    }
+
+    /// JSON-LD `@context` entries (`{"rdf": "http://...#", ...}`)
+    pub fn jsonld_context(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.iter()
+            .filter(|(prefix, _)| !prefix.is_empty())
+            .map(|(prefix, namespace)| (prefix.to_string(), serde_json::Value::String(namespace.to_string())))
+            .collect()
+    }
+}
+
+impl<'a> FromIterator<(&'a str, &'a str)> for PrefixMap {
+    fn from_iter<I: IntoIterator<Item = (&'a str, &'a str)>>(iter: I) -> Self {
+        let mut map = PrefixMap::empty();
+        for (prefix, namespace) in iter {
+            map.insert(prefix, namespace);
+        }
+        map
+    }
+}
+
+/// `(prefix, local)` of a CURIE; `None` for variables, literals, blank nodes and IRIs
+fn split_curie(term: &str) -> Option<(&str, &str)> {
+    if term.starts_with(['?', '$', '"', '<']) || term.starts_with("_:") {
+        return None;
+    }
+    let (prefix, local) = term.split_once(':')?;
+    let valid_prefix = prefix.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    // `http://...` はスキーム付きの IRI
+    (valid_prefix && !local.starts_with("//") && !local.contains(char::is_whitespace)).then_some((prefix, local))
+}
+
+fn is_local_name(local: &str) -> bool {
+    !local.is_empty()
+        && !local.ends_with('.')
+        && local.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_and_compact() {
+        let prefixes = PrefixMap::default().with_prefix("ex:", "<http://example.org/>");
+        assert_eq!(prefixes.expand("rdf:type").unwrap(), format!("{}type", RDF_NAMESPACE));
+        assert_eq!(prefixes.expand("sh:NodeShape").unwrap(), format!("{}NodeShape", SH_NAMESPACE));
+        assert_eq!(prefixes.expand("nope:x"), Err(PrefixError::UnknownPrefix("nope".to_string())));
+        assert_eq!(prefixes.expand("?host"), Err(PrefixError::NotACurie("?host".to_string())));
+
+        // 展開できない項はそのまま
+        for term in ["?host", "http://example.org/h1", "\"a:b\"", "_:b0", "nope:x", "192.168.1.1"] {
+            assert_eq!(prefixes.expand_term(term), term);
+        }
+        assert_eq!(prefixes.expand_term("ex:h1"), "http://example.org/h1");
+
+        assert_eq!(prefixes.compact("http://example.org/h1").as_deref(), Some("ex:h1"));
+        assert_eq!(prefixes.compact("http://example.org/a/b"), None);
+        assert_eq!(prefixes.display("http://other.org/x"), "<http://other.org/x>");
+    }
+
+    #[test]
+    fn test_longest_namespace_wins_and_user_prefixes_override() {
+        let mut prefixes = PrefixMap::default()
+            .with_prefix("ex", "http://example.org/")
+            .with_prefix("exv", "http://example.org/vocab#");
+        assert_eq!(prefixes.display("http://example.org/vocab#Host"), "exv:Host");
+
+        prefixes.insert("rdf", "http://example.org/not-rdf#");
+        assert_eq!(prefixes.expand("rdf:type").unwrap(), "http://example.org/not-rdf#type");
+        assert_eq!(default_namespace("rdf"), Some(RDF_NAMESPACE));
+    }
+
+    #[test]
+    fn test_sparql_prologue_and_jsonld_context() {
+        let prefixes: PrefixMap = [("ex", "http://example.org/")].into_iter().collect();
+        assert_eq!(prefixes.sparql_prologue(), "PREFIX ex: <http://example.org/>\n");
+        assert_eq!(prefixes.jsonld_context()["ex"], "http://example.org/");
+
+        let json = serde_json::to_value(&prefixes).unwrap();
+        assert_eq!(json, serde_json::json!({ "ex": "http://example.org/" }));
+        assert_eq!(serde_json::from_value::<PrefixMap>(json).unwrap(), prefixes);
+    }
+}
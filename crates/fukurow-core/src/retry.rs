@@ -0,0 +1,219 @@
+//! Retry / backoff utilities shared by network clients
+//!
+//! SIEM クライアント・ストリーミングプロデューサー等で共通利用する
+//! 指数バックオフ（decorrelated jitter 付き）とリトライ判定フック
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+/// Retry policy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Maximum attempts including the first one (1 = no retry)
+    pub max_attempts: u32,
+    /// Base delay in milliseconds
+    pub initial_backoff_ms: u64,
+    /// Upper bound for a single delay in milliseconds
+    pub max_backoff_ms: u64,
+    /// Growth factor between consecutive delays
+    pub backoff_multiplier: f64,
+    /// Apply decorrelated jitter (otherwise plain exponential backoff)
+    #[serde(default = "default_jitter")]
+    pub jitter: bool,
+}
+
+fn default_jitter() -> bool {
+    true
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 10_000,
+            backoff_multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..Default::default()
+        }
+    }
+
+    /// Policy that never retries
+    pub fn no_retry() -> Self {
+        Self::new(1)
+    }
+
+    pub fn with_initial_backoff_ms(mut self, ms: u64) -> Self {
+        self.initial_backoff_ms = ms;
+        self
+    }
+
+    pub fn with_max_backoff_ms(mut self, ms: u64) -> Self {
+        self.max_backoff_ms = ms;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.backoff_multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delays to wait before each retry (yields `max_attempts - 1` items)
+    pub fn backoff(&self) -> Backoff {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        self.backoff_with_seed(seed)
+    }
+
+    /// Deterministic backoff sequence (for tests and reproducible runs)
+    pub fn backoff_with_seed(&self, seed: u64) -> Backoff {
+        Backoff {
+            policy: self.clone(),
+            previous_ms: self.initial_backoff_ms,
+            retries_left: self.max_attempts.saturating_sub(1),
+            attempt: 0,
+            rng: SplitMix64::new(seed),
+        }
+    }
+}
+
+/// Backoff delay iterator
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: RetryPolicy,
+    previous_ms: u64,
+    retries_left: u32,
+    attempt: u32,
+    rng: SplitMix64,
+}
+
+/// Small deterministic PRNG (SplitMix64) for jitter and noise
+///
+/// 暗号用途には使わない。同じシードからは同じ系列になる
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        if self.retries_left == 0 {
+            return None;
+        }
+        self.retries_left -= 1;
+
+        let base = self.policy.initial_backoff_ms as f64;
+        let cap = self.policy.max_backoff_ms as f64;
+        let multiplier = self.policy.backoff_multiplier.max(1.0);
+
+        let delay_ms = if self.policy.jitter {
+            // decorrelated jitter: random_between(base, previous * multiplier)
+            let upper = (self.previous_ms as f64 * multiplier).max(base);
+            base + (upper - base) * self.rng.next_f64()
+        } else {
+            base * multiplier.powi(self.attempt as i32)
+        }
+        .min(cap);
+
+        self.attempt += 1;
+        self.previous_ms = delay_ms as u64;
+        Some(Duration::from_millis(delay_ms as u64))
+    }
+}
+
+/// Classification hook for errors that may succeed on retry
+pub trait Retryable {
+    fn is_retryable(&self) -> bool;
+}
+
+/// Run `operation` with retries
+///
+/// `should_retry` で一時的なエラーかどうかを判定し、`sleep` には実行環境の
+/// タイマー（例: `tokio::time::sleep`）を渡す
+pub async fn retry_async<T, E, Op, Fut, Classify, Sleep, SleepFut>(
+    policy: &RetryPolicy,
+    mut operation: Op,
+    should_retry: Classify,
+    sleep: Sleep,
+) -> Result<T, E>
+where
+    Op: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    Classify: Fn(&E) -> bool,
+    Sleep: Fn(Duration) -> SleepFut,
+    SleepFut: Future<Output = ()>,
+{
+    let mut backoff = policy.backoff();
+    let mut attempt = 1;
+
+    loop {
+        match operation(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(error) => {
+                if !should_retry(&error) {
+                    return Err(error);
+                }
+                match backoff.next() {
+                    Some(delay) => {
+                        sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(error),
+                }
+            }
+        }
+    }
+}
+
+/// [`retry_async`] for error types implementing [`Retryable`]
+pub async fn retry_retryable<T, E, Op, Fut, Sleep, SleepFut>(
+    policy: &RetryPolicy,
+    operation: Op,
+    sleep: Sleep,
+) -> Result<T, E>
+where
+    E: Retryable,
+    Op: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    Sleep: Fn(Duration) -> SleepFut,
+    SleepFut: Future<Output = ()>,
+{
+    retry_async(policy, operation, |e: &E| e.is_retryable(), sleep).await
+}
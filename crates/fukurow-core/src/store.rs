@@ -1,6 +1,7 @@
 //! Graph storage and manipulation
 
 use crate::model::{Triple, NamedGraph, JsonLdDocument};
+use crate::jsonld::triples_to_jsonld;
 use std::collections::HashMap;
 use anyhow::Result;
 use smallvec::SmallVec;
@@ -180,37 +181,11 @@ impl GraphStore {
 
     /// Convert store to JSON-LD document
     pub fn to_jsonld(&self) -> Result<JsonLdDocument> {
-        let mut graph = Vec::new();
-
-        // Add default graph triples
-        for triple in &self.default_graph.triples {
-            let node = serde_json::json!({
-                "@id": triple.subject,
-                triple.predicate.clone(): triple.object
-            });
-            graph.push(node);
-        }
-
-        // Add named graph triples
-        for named_graph in self.graphs.values() {
-            for triple in &named_graph.triples {
-                let node = serde_json::json!({
-                    "@id": triple.subject,
-                    triple.predicate.clone(): triple.object
-                });
-                graph.push(node);
-            }
-        }
-
-        Ok(JsonLdDocument {
-            context: serde_json::json!({
-                "@vocab": "https://w3id.org/security#",
-                "rdf": "http://www.w3.org/1999/02/22-rdf-syntax-ns#",
-                "rdfs": "http://www.w3.org/2000/01/rdf-schema#"
-            }),
-            graph: Some(graph),
-            data: HashMap::new(),
-        })
+        let triples: Vec<Triple> = self.default_graph.triples.iter()
+            .chain(self.graphs.values().flat_map(|graph| graph.triples.iter()))
+            .cloned()
+            .collect();
+        Ok(triples_to_jsonld(&triples))
     }
 
     /// Clear all graphs
@@ -0,0 +1,472 @@
+//! RDF term model
+//!
+//! ストアは `Triple` の各要素を文字列で保持する。このモジュールはその文字列表現と
+//! RDF 項 (IRI / ブランクノード / リテラル) を相互に変換する。
+//!
+//! String encoding (close to N-Triples, but IRIs are stored without brackets):
+//! - `_:label` is a blank node
+//! - `"value"`, `"value"@lang` and `"value"^^<datatype>` are literals
+//! - `<iri>` is an IRI
+//! - any other string is an IRI when it starts with a URI scheme (`http:`, `urn:`, `event:` ...)
+//!   and a plain literal otherwise, which keeps existing raw values such as `192.168.1.1` readable
+
+use crate::model::{InternedTriple, Triple};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `xsd:string`, the implicit datatype of plain literals
+pub const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+
+/// RDF term
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RdfTerm {
+    Iri(String),
+    /// Blank node label without the `_:` prefix
+    BlankNode(String),
+    /// Literal; `datatype` is `None` for plain (`xsd:string`) and language-tagged literals
+    Literal {
+        value: String,
+        datatype: Option<String>,
+        language: Option<String>,
+    },
+}
+
+impl RdfTerm {
+    pub fn iri(iri: impl Into<String>) -> Self {
+        RdfTerm::Iri(iri.into())
+    }
+
+    pub fn blank_node(label: impl Into<String>) -> Self {
+        RdfTerm::BlankNode(label.into())
+    }
+
+    pub fn literal(value: impl Into<String>) -> Self {
+        RdfTerm::Literal { value: value.into(), datatype: None, language: None }
+    }
+
+    /// Typed literal (`xsd:string` is normalized to a plain literal)
+    pub fn typed_literal(value: impl Into<String>, datatype: impl Into<String>) -> Self {
+        let datatype = datatype.into();
+        RdfTerm::Literal {
+            value: value.into(),
+            datatype: (datatype != XSD_STRING).then_some(datatype),
+            language: None,
+        }
+    }
+
+    /// Language-tagged literal (tags are compared case-insensitively, so they are lowercased)
+    pub fn lang_literal(value: impl Into<String>, language: &str) -> Self {
+        RdfTerm::Literal { value: value.into(), datatype: None, language: Some(language.to_ascii_lowercase()) }
+    }
+
+    /// Parse a stored object value
+    pub fn parse(value: &str) -> Self {
+        if let Some(label) = value.strip_prefix("_:") {
+            return RdfTerm::BlankNode(label.to_string());
+        }
+        if value.starts_with('"') {
+            return parse_quoted_literal(value).unwrap_or_else(|| RdfTerm::literal(value));
+        }
+        if let Some(iri) = value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) {
+            return RdfTerm::Iri(iri.to_string());
+        }
+        if has_scheme(value) {
+            RdfTerm::Iri(value.to_string())
+        } else {
+            RdfTerm::literal(value)
+        }
+    }
+
+    /// Parse a stored subject or predicate (never a literal)
+    pub fn parse_node(value: &str) -> Self {
+        if let Some(label) = value.strip_prefix("_:") {
+            return RdfTerm::BlankNode(label.to_string());
+        }
+        let iri = value.strip_prefix('<').and_then(|v| v.strip_suffix('>')).unwrap_or(value);
+        RdfTerm::Iri(iri.to_string())
+    }
+
+    /// Encode for storage in a `Triple`; `RdfTerm::parse(&t.encode()) == t` for every term
+    ///
+    /// IRIs and plain literals are stored raw whenever that round-trips, so
+    /// terms built from existing data keep their original strings.
+    pub fn encode(&self) -> String {
+        match self {
+            RdfTerm::Iri(iri) => {
+                if has_scheme(iri) {
+                    iri.clone()
+                } else {
+                    format!("<{}>", iri)
+                }
+            }
+            RdfTerm::BlankNode(label) => format!("_:{}", label),
+            RdfTerm::Literal { value, datatype: None, language: None } => {
+                if RdfTerm::parse(value) == *self {
+                    value.clone()
+                } else {
+                    quote(value)
+                }
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn is_iri(&self) -> bool {
+        matches!(self, RdfTerm::Iri(_))
+    }
+
+    pub fn is_blank_node(&self) -> bool {
+        matches!(self, RdfTerm::BlankNode(_))
+    }
+
+    pub fn is_literal(&self) -> bool {
+        matches!(self, RdfTerm::Literal { .. })
+    }
+
+    pub fn as_iri(&self) -> Option<&str> {
+        match self {
+            RdfTerm::Iri(iri) => Some(iri),
+            _ => None,
+        }
+    }
+
+    pub fn blank_node_label(&self) -> Option<&str> {
+        match self {
+            RdfTerm::BlankNode(label) => Some(label),
+            _ => None,
+        }
+    }
+
+    /// IRI, blank node label or literal lexical form
+    pub fn value(&self) -> &str {
+        match self {
+            RdfTerm::Iri(value) | RdfTerm::BlankNode(value) | RdfTerm::Literal { value, .. } => value,
+        }
+    }
+
+    /// Datatype IRI of a literal (`xsd:string` for plain literals)
+    pub fn datatype(&self) -> Option<&str> {
+        match self {
+            RdfTerm::Literal { datatype: Some(datatype), .. } => Some(datatype),
+            RdfTerm::Literal { language: None, .. } => Some(XSD_STRING),
+            RdfTerm::Literal { .. } => Some("http://www.w3.org/1999/02/22-rdf-syntax-ns#langString"),
+            _ => None,
+        }
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        match self {
+            RdfTerm::Literal { language, .. } => language.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// N-Triples syntax
+impl std::fmt::Display for RdfTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RdfTerm::Iri(iri) => write!(f, "<{}>", iri),
+            RdfTerm::BlankNode(label) => write!(f, "_:{}", label),
+            RdfTerm::Literal { value, datatype, language } => {
+                write!(f, "{}", quote(value))?;
+                if let Some(language) = language {
+                    write!(f, "@{}", language)
+                } else if let Some(datatype) = datatype {
+                    write!(f, "^^<{}>", datatype)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+impl Triple {
+    /// Build a triple from terms
+    pub fn from_terms(subject: &RdfTerm, predicate: &RdfTerm, object: &RdfTerm) -> Self {
+        Triple {
+            subject: subject.encode(),
+            predicate: predicate.encode(),
+            object: object.encode(),
+        }
+    }
+
+    pub fn subject_term(&self) -> RdfTerm {
+        RdfTerm::parse_node(&self.subject)
+    }
+
+    pub fn predicate_term(&self) -> RdfTerm {
+        RdfTerm::parse_node(&self.predicate)
+    }
+
+    pub fn object_term(&self) -> RdfTerm {
+        RdfTerm::parse(&self.object)
+    }
+
+    /// Whether the subject or object is a blank node
+    pub fn has_blank_node(&self) -> bool {
+        self.subject.starts_with("_:") || self.object.starts_with("_:")
+    }
+}
+
+impl InternedTriple {
+    pub fn subject_term(&self) -> RdfTerm {
+        RdfTerm::parse_node(&self.subject)
+    }
+
+    pub fn predicate_term(&self) -> RdfTerm {
+        RdfTerm::parse_node(&self.predicate)
+    }
+
+    pub fn object_term(&self) -> RdfTerm {
+        RdfTerm::parse(&self.object)
+    }
+
+    /// Whether the subject or object is a blank node
+    pub fn has_blank_node(&self) -> bool {
+        self.subject.starts_with("_:") || self.object.starts_with("_:")
+    }
+}
+
+static NEXT_SCOPE: AtomicU64 = AtomicU64::new(1);
+
+static PROCESS_PREFIX: OnceLock<String> = OnceLock::new();
+
+/// Random prefix shared by the scopes of this process
+///
+/// スコープ番号はプロセスごとに 1 から数え直すため、前回のプロセスが永続化したラベルと
+/// 衝突しないよう起動ごとに異なる接頭辞を付ける
+fn process_prefix() -> &'static str {
+    PROCESS_PREFIX.get_or_init(|| {
+        let mut hasher = RandomState::new().build_hasher();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        hasher.write_u128(now.as_nanos());
+        hasher.write_u32(std::process::id());
+        format!("{:016x}", hasher.finish())
+    })
+}
+
+/// Blank node label scope of one document
+///
+/// ブランクノードのラベルは文書内でのみ意味を持つ。別々に取り込んだ文書の `_:b0`
+/// 同士が同じノードとして結合されないよう、スコープごとに一意なラベル
+/// (`b{process}_{scope}_{n}`) へ付け替える。`process` は起動ごとの乱数で、
+/// 再起動後に作ったラベルが永続化済みのストアのラベルと重ならない。
+#[derive(Debug)]
+pub struct BlankNodeScope {
+    id: u64,
+    labels: HashMap<String, String>,
+    generated: u64,
+}
+
+impl BlankNodeScope {
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_SCOPE.fetch_add(1, Ordering::Relaxed),
+            labels: HashMap::new(),
+            generated: 0,
+        }
+    }
+
+    /// Scoped label for a document label (stable within this scope)
+    pub fn relabel(&mut self, label: &str) -> String {
+        if let Some(scoped) = self.labels.get(label) {
+            return scoped.clone();
+        }
+        let scoped = self.fresh();
+        self.labels.insert(label.to_string(), scoped.clone());
+        scoped
+    }
+
+    /// New label for an anonymous node
+    pub fn fresh(&mut self) -> String {
+        self.generated += 1;
+        format!("b{}_{}_{}", process_prefix(), self.id, self.generated)
+    }
+
+    /// Relabel an encoded value if it is a blank node, otherwise return it unchanged
+    pub fn relabel_value(&mut self, value: &str) -> String {
+        match value.strip_prefix("_:") {
+            Some(label) => format!("_:{}", self.relabel(label)),
+            None => value.to_string(),
+        }
+    }
+
+    pub fn relabel_triple(&mut self, triple: Triple) -> Triple {
+        if !triple.has_blank_node() {
+            return triple;
+        }
+        Triple {
+            subject: self.relabel_value(&triple.subject),
+            predicate: triple.predicate,
+            object: self.relabel_value(&triple.object),
+        }
+    }
+}
+
+impl Default for BlankNodeScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `[A-Za-z][A-Za-z0-9+.-]+:` で始まり空白を含まない文字列を IRI とみなす
+/// (1 文字のスキームは `C:\...` のようなパスと区別できないため除外)
+fn has_scheme(value: &str) -> bool {
+    let scheme = match value.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None => return false,
+    };
+    let mut chars = scheme.chars();
+    scheme.len() >= 2
+        && chars.next().map(|c| c.is_ascii_alphabetic()).unwrap_or(false)
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+        && !value.contains(|c: char| c.is_whitespace() || c == '\\' || c == '"')
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `"value"`, `"value"@lang`, `"value"^^<dt>` (or `^^dt`); `None` if malformed
+fn parse_quoted_literal(encoded: &str) -> Option<RdfTerm> {
+    let mut value = String::new();
+    let mut chars = encoded.char_indices().skip(1);
+    let mut end = None;
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => {
+                end = Some(index);
+                break;
+            }
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                escaped => value.push(escaped),
+            },
+            c => value.push(c),
+        }
+    }
+
+    let suffix = &encoded[end? + 1..];
+    if suffix.is_empty() {
+        Some(RdfTerm::literal(value))
+    } else if let Some(language) = suffix.strip_prefix('@') {
+        (!language.is_empty()).then(|| RdfTerm::lang_literal(value, language))
+    } else if let Some(datatype) = suffix.strip_prefix("^^") {
+        let datatype = datatype.strip_prefix('<').and_then(|d| d.strip_suffix('>')).unwrap_or(datatype);
+        (!datatype.is_empty()).then(|| RdfTerm::typed_literal(value, datatype))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_encoded_values() {
+        assert_eq!(RdfTerm::parse("_:b0"), RdfTerm::blank_node("b0"));
+        assert_eq!(RdfTerm::parse("http://example.org/a"), RdfTerm::iri("http://example.org/a"));
+        assert_eq!(RdfTerm::parse("<relative>"), RdfTerm::iri("relative"));
+        assert_eq!(RdfTerm::parse("192.168.1.1"), RdfTerm::literal("192.168.1.1"));
+        assert_eq!(RdfTerm::parse("C:\\Windows\\cmd.exe"), RdfTerm::literal("C:\\Windows\\cmd.exe"));
+        assert_eq!(RdfTerm::parse("\"Alice\""), RdfTerm::literal("Alice"));
+        assert_eq!(RdfTerm::parse("\"chat\"@FR"), RdfTerm::lang_literal("chat", "fr"));
+        assert_eq!(
+            RdfTerm::parse("\"42\"^^<http://www.w3.org/2001/XMLSchema#integer>"),
+            RdfTerm::typed_literal("42", "http://www.w3.org/2001/XMLSchema#integer")
+        );
+        assert_eq!(RdfTerm::parse(&format!("\"x\"^^<{}>", XSD_STRING)), RdfTerm::literal("x"));
+        assert_eq!(RdfTerm::parse("\"a \\\"b\\\"\""), RdfTerm::literal("a \"b\""));
+        // 閉じていない引用符はそのままの値として扱う
+        assert_eq!(RdfTerm::parse("\"open"), RdfTerm::literal("\"open"));
+        assert_eq!(RdfTerm::parse_node("subject1"), RdfTerm::iri("subject1"));
+    }
+
+    #[test]
+    fn test_encode_round_trips() {
+        let terms = vec![
+            RdfTerm::iri("http://example.org/a"),
+            RdfTerm::iri("relative"),
+            RdfTerm::blank_node("n1"),
+            RdfTerm::literal("alice"),
+            RdfTerm::literal("http://looks-like-an-iri"),
+            RdfTerm::literal("_:not-a-node"),
+            RdfTerm::literal("line\nbreak"),
+            RdfTerm::lang_literal("hello", "en"),
+            RdfTerm::typed_literal("true", "http://www.w3.org/2001/XMLSchema#boolean"),
+        ];
+        for term in terms {
+            assert_eq!(RdfTerm::parse(&term.encode()), term, "{}", term.encode());
+        }
+        assert_eq!(RdfTerm::literal("alice").encode(), "alice");
+        assert_eq!(RdfTerm::literal("_:x").encode(), "\"_:x\"");
+        assert_eq!(RdfTerm::lang_literal("hi", "en").to_string(), "\"hi\"@en");
+    }
+
+    #[test]
+    fn test_triple_terms() {
+        let triple = Triple::from_terms(
+            &RdfTerm::blank_node("e1"),
+            &RdfTerm::iri("http://example.org/user"),
+            &RdfTerm::literal("alice"),
+        );
+        assert_eq!(triple.subject, "_:e1");
+        assert!(triple.has_blank_node());
+        assert!(triple.subject_term().is_blank_node());
+        assert_eq!(triple.object_term().datatype(), Some(XSD_STRING));
+    }
+
+    #[test]
+    fn test_blank_node_scopes_do_not_collide() {
+        let mut first = BlankNodeScope::new();
+        let mut second = BlankNodeScope::new();
+
+        let a = first.relabel_value("_:b0");
+        assert_eq!(first.relabel_value("_:b0"), a);
+        assert_ne!(first.relabel_value("_:b1"), a);
+        assert_ne!(second.relabel_value("_:b0"), a);
+        assert_eq!(first.relabel_value("http://example.org/a"), "http://example.org/a");
+
+        let triple = Triple {
+            subject: "_:b0".to_string(),
+            predicate: "http://example.org/p".to_string(),
+            object: "literal".to_string(),
+        };
+        assert_eq!(first.relabel_triple(triple).subject, a);
+    }
+
+    #[test]
+    fn test_blank_node_labels_carry_a_process_prefix() {
+        // 再起動後の `b{scope}_{n}` が永続化済みのラベルと重ならないよう、起動ごとの接頭辞を含む
+        let label = BlankNodeScope::new().fresh();
+        let prefix = process_prefix();
+        assert_eq!(prefix.len(), 16);
+        assert!(prefix.chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(label.starts_with(&format!("b{}_", prefix)));
+        assert!(label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        assert_eq!(RdfTerm::parse(&format!("_:{}", label)), RdfTerm::blank_node(&label));
+    }
+}
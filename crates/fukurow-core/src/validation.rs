@@ -0,0 +1,281 @@
+//! Event schema validation
+//!
+//! センサーから届いた CyberEvent をトリプルに変換する前に検査する。
+//! 形式の壊れたペイロード (IP アドレスでない文字列、ポート 0、ミリ秒の時刻など) が
+//! そのままグラフに入るのを防ぐ
+
+use crate::model::CyberEvent;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What to do with events that fail validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventValidationMode {
+    /// Reject events with any issue
+    #[default]
+    Strict,
+    /// Reject events with errors; ingest events that only have warnings
+    Lenient,
+    /// Store events with any issue in the quarantine graph instead of ingesting them
+    Quarantine,
+}
+
+impl EventValidationMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "strict" => Some(Self::Strict),
+            "lenient" => Some(Self::Lenient),
+            "quarantine" => Some(Self::Quarantine),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    /// The field is unusable (not an IP address, empty, ...)
+    Error,
+    /// The value is suspicious but usable (old timestamp, unusual status code, ...)
+    Warning,
+}
+
+/// Problem found in one field of an event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub field: String,
+    pub message: String,
+    pub severity: IssueSeverity,
+}
+
+impl ValidationIssue {
+    fn error(field: &str, message: String) -> Self {
+        Self { field: field.to_string(), message, severity: IssueSeverity::Error }
+    }
+
+    fn warning(field: &str, message: String) -> Self {
+        Self { field: field.to_string(), message, severity: IssueSeverity::Warning }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Decision for one event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationOutcome {
+    /// Ingest the event (warnings are only reported)
+    Accept { warnings: Vec<ValidationIssue> },
+    Reject(Vec<ValidationIssue>),
+    Quarantine(Vec<ValidationIssue>),
+}
+
+impl ValidationOutcome {
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, ValidationOutcome::Accept { .. })
+    }
+}
+
+/// Joins issues into a single message (`field: message; field: message`)
+pub fn describe_issues(issues: &[ValidationIssue]) -> String {
+    issues.iter().map(|issue| issue.to_string()).collect::<Vec<_>>().join("; ")
+}
+
+/// Field checks applied to events at ingestion
+///
+/// タイムスタンプは Unix 秒。未来方向のずれが `max_clock_skew_secs` を超えるもの (ミリ秒で送られた時刻など) は
+/// エラー、`max_age_secs` より古いものは警告とする
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventValidator {
+    pub mode: EventValidationMode,
+    /// How far in the future a timestamp may be (seconds)
+    pub max_clock_skew_secs: i64,
+    /// How old a timestamp may be before it is flagged (seconds, unlimited when unset)
+    pub max_age_secs: Option<i64>,
+}
+
+impl Default for EventValidator {
+    fn default() -> Self {
+        Self {
+            mode: EventValidationMode::Strict,
+            max_clock_skew_secs: 300,
+            max_age_secs: None,
+        }
+    }
+}
+
+impl EventValidator {
+    pub fn new(mode: EventValidationMode) -> Self {
+        Self { mode, ..Self::default() }
+    }
+
+    pub fn with_max_clock_skew(mut self, seconds: i64) -> Self {
+        self.max_clock_skew_secs = seconds;
+        self
+    }
+
+    pub fn with_max_age(mut self, seconds: i64) -> Self {
+        self.max_age_secs = Some(seconds);
+        self
+    }
+
+    /// Check `event` against the current time and decide per the mode
+    pub fn validate(&self, event: &CyberEvent) -> ValidationOutcome {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        self.decide(self.check(event, now))
+    }
+
+    /// Decision for already collected issues
+    pub fn decide(&self, issues: Vec<ValidationIssue>) -> ValidationOutcome {
+        let has_error = issues.iter().any(|issue| issue.severity == IssueSeverity::Error);
+        match self.mode {
+            _ if issues.is_empty() => ValidationOutcome::Accept { warnings: issues },
+            EventValidationMode::Strict => ValidationOutcome::Reject(issues),
+            EventValidationMode::Lenient if has_error => ValidationOutcome::Reject(issues),
+            EventValidationMode::Lenient => ValidationOutcome::Accept { warnings: issues },
+            EventValidationMode::Quarantine => ValidationOutcome::Quarantine(issues),
+        }
+    }
+
+    /// Every issue of `event`, with `now` as the current Unix time
+    pub fn check(&self, event: &CyberEvent, now: i64) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let require_ip = |issues: &mut Vec<ValidationIssue>, field: &str, value: &str| {
+            if value.parse::<IpAddr>().is_err() {
+                issues.push(ValidationIssue::error(field, format!("not an IP address: {:?}", value)));
+            }
+        };
+        let require_text = |issues: &mut Vec<ValidationIssue>, field: &str, value: &str| {
+            if value.trim().is_empty() {
+                issues.push(ValidationIssue::error(field, "must not be empty".to_string()));
+            }
+        };
+
+        let timestamp = match event {
+            CyberEvent::NetworkConnection { source_ip, dest_ip, port, protocol, timestamp } => {
+                require_ip(&mut issues, "source_ip", source_ip);
+                require_ip(&mut issues, "dest_ip", dest_ip);
+                if *port == 0 {
+                    issues.push(ValidationIssue::error("port", "must be between 1 and 65535".to_string()));
+                }
+                require_text(&mut issues, "protocol", protocol);
+                *timestamp
+            }
+            CyberEvent::ProcessExecution { command_line, user, timestamp, .. } => {
+                require_text(&mut issues, "command_line", command_line);
+                require_text(&mut issues, "user", user);
+                *timestamp
+            }
+            CyberEvent::FileAccess { file_path, access_type, user, timestamp, .. } => {
+                require_text(&mut issues, "file_path", file_path);
+                require_text(&mut issues, "access_type", access_type);
+                require_text(&mut issues, "user", user);
+                *timestamp
+            }
+            CyberEvent::UserLogin { user, source_ip, timestamp, .. } => {
+                require_text(&mut issues, "user", user);
+                require_ip(&mut issues, "source_ip", source_ip);
+                *timestamp
+            }
+            CyberEvent::DnsQuery { query_name, query_type, source_ip, resolved_ips, timestamp } => {
+                require_text(&mut issues, "query_name", query_name);
+                require_text(&mut issues, "query_type", query_type);
+                require_ip(&mut issues, "source_ip", source_ip);
+                for ip in resolved_ips {
+                    require_ip(&mut issues, "resolved_ips", ip);
+                }
+                *timestamp
+            }
+            CyberEvent::HttpRequest { method, url, host, source_ip, status_code, timestamp, .. } => {
+                require_text(&mut issues, "method", method);
+                require_text(&mut issues, "url", url);
+                require_text(&mut issues, "host", host);
+                require_ip(&mut issues, "source_ip", source_ip);
+                if let Some(status) = status_code.filter(|status| !(100..=599).contains(status)) {
+                    issues.push(ValidationIssue::warning("status_code", format!("not an HTTP status: {}", status)));
+                }
+                *timestamp
+            }
+            CyberEvent::RegistryModification { key_path, operation, user, timestamp, .. } => {
+                require_text(&mut issues, "key_path", key_path);
+                require_text(&mut issues, "operation", operation);
+                require_text(&mut issues, "user", user);
+                *timestamp
+            }
+            CyberEvent::EmailReceived { sender, recipient, timestamp, .. } => {
+                require_text(&mut issues, "sender", sender);
+                require_text(&mut issues, "recipient", recipient);
+                *timestamp
+            }
+        };
+
+        if timestamp <= 0 {
+            issues.push(ValidationIssue::error("timestamp", format!("must be positive: {}", timestamp)));
+        } else if timestamp > now.saturating_add(self.max_clock_skew_secs) {
+            issues.push(ValidationIssue::error("timestamp", format!("{} is in the future (Unix seconds expected)", timestamp)));
+        } else if let Some(max_age) = self.max_age_secs.filter(|&max_age| timestamp < now.saturating_sub(max_age)) {
+            issues.push(ValidationIssue::warning("timestamp", format!("older than {} seconds: {}", max_age, timestamp)));
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(dest_ip: &str, port: u16, timestamp: i64) -> CyberEvent {
+        CyberEvent::NetworkConnection {
+            source_ip: "10.0.0.5".to_string(),
+            dest_ip: dest_ip.to_string(),
+            port,
+            protocol: "tcp".to_string(),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_checks_ip_port_and_timestamp() {
+        let validator = EventValidator::default().with_max_age(3600);
+        let now = 1_700_000_000;
+        assert!(validator.check(&connection("203.0.113.9", 443, now - 10), now).is_empty());
+
+        let issues = validator.check(&connection("not-an-ip", 0, now * 1000), now);
+        let fields: Vec<&str> = issues.iter().map(|issue| issue.field.as_str()).collect();
+        assert_eq!(fields, vec!["dest_ip", "port", "timestamp"]);
+        assert!(issues.iter().all(|issue| issue.severity == IssueSeverity::Error));
+
+        let stale = validator.check(&connection("203.0.113.9", 443, now - 7200), now);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].severity, IssueSeverity::Warning);
+    }
+
+    #[test]
+    fn test_modes_decide_differently() {
+        let warning = vec![ValidationIssue::warning("timestamp", "old".to_string())];
+        let error = vec![ValidationIssue::error("dest_ip", "bad".to_string())];
+
+        let strict = EventValidator::new(EventValidationMode::Strict);
+        assert!(matches!(strict.decide(warning.clone()), ValidationOutcome::Reject(_)));
+        assert!(strict.decide(Vec::new()).is_accepted());
+
+        let lenient = EventValidator::new(EventValidationMode::Lenient);
+        assert_eq!(lenient.decide(warning.clone()), ValidationOutcome::Accept { warnings: warning.clone() });
+        assert!(matches!(lenient.decide(error.clone()), ValidationOutcome::Reject(_)));
+
+        let quarantine = EventValidator::new(EventValidationMode::Quarantine);
+        assert!(matches!(quarantine.decide(error), ValidationOutcome::Quarantine(_)));
+        assert_eq!(EventValidationMode::parse("Quarantine"), Some(EventValidationMode::Quarantine));
+        assert_eq!(describe_issues(&warning), "timestamp: old");
+    }
+}
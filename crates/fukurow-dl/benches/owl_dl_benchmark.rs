@@ -1,5 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use fukurow_dl::{OwlDlReasoner, OwlDlOntology};
+use fukurow_dl::{OwlDlReasoner, OwlDlOntology, ClassExpression, TableauOptimizations};
+use fukurow_dl::model::{Axiom as DlAxiom, PropertyExpression};
 use fukurow_lite::{Class, Property, Individual, OwlIri, Axiom as LiteAxiom};
 use fukurow_store::store::RdfStore;
 use fukurow_store::provenance::{Provenance, GraphId};
@@ -120,5 +121,59 @@ fn benchmark_dl_to_lite_conversion(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, benchmark_dl_consistency_check, benchmark_dl_loading, benchmark_dl_to_lite_conversion);
+/// Medium-size TBox: a class hierarchy with definitions, disjointness, existentials and GCIs
+fn create_classification_ontology(size: usize) -> OwlDlOntology {
+    let class = |name: String| ClassExpression::Named(OwlIri::new(format!("http://example.org/{}", name)));
+    let property = |name: &str| PropertyExpression::ObjectProperty(OwlIri::new(format!("http://example.org/{}", name)));
+
+    let mut ontology = OwlDlOntology::new();
+    ontology.add_axiom(DlAxiom::ObjectPropertyDomain(property("runs"), class("Host".to_string())));
+    for i in 0..size {
+        let category = i % 10;
+        ontology.add_axiom(DlAxiom::SubClassOf(class(format!("Class{}", i)), class(format!("Category{}", category))));
+        if i >= 10 {
+            ontology.add_axiom(DlAxiom::SubClassOf(class(format!("Class{}", i)), class(format!("Class{}", i - 10))));
+        }
+        // 定義クラス: Defined{i} ≡ Host ⊓ ∃runs.Class{i}
+        ontology.add_axiom(DlAxiom::EquivalentClasses(vec![
+            class(format!("Defined{}", i)),
+            ClassExpression::IntersectionOf(vec![
+                class("Host".to_string()),
+                ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(class(format!("Class{}", i))) },
+            ]),
+        ]));
+        // 一般包含公理: Server ⊓ Defined{i} ⊑ Alert{category}
+        if i % 5 == 0 {
+            ontology.add_axiom(DlAxiom::SubClassOf(
+                ClassExpression::IntersectionOf(vec![class("Server".to_string()), class(format!("Defined{}", i))]),
+                class(format!("Alert{}", category)),
+            ));
+        }
+    }
+    for category in 0..10 {
+        ontology.add_axiom(DlAxiom::DisjointClasses(vec![class(format!("Category{}", category)), class("Host".to_string())]));
+    }
+    ontology.add_axiom(DlAxiom::SubClassOf(class("Server".to_string()), class("Host".to_string())));
+    ontology
+}
+
+fn benchmark_dl_classification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("owl_dl_classification");
+    group.sample_size(10);
+
+    for size in [20, 50] {
+        let ontology = create_classification_ontology(size);
+        for (name, optimizations) in [("optimized", TableauOptimizations::all()), ("unoptimized", TableauOptimizations::none())] {
+            group.bench_function(format!("{}_{}_classes", name, size), |b| {
+                b.iter(|| {
+                    let mut reasoner = OwlDlReasoner::new().with_tableau_optimizations(optimizations);
+                    let _hierarchy = reasoner.classify_ontology(black_box(&ontology)).unwrap();
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_dl_consistency_check, benchmark_dl_loading, benchmark_dl_to_lite_conversion, benchmark_dl_classification);
 criterion_main!(benches);
@@ -5,6 +5,7 @@
 //! - 個体レベルの推論 (individual reasoning)
 //! - 複雑なクラスコンストラクタ (intersectionOf, unionOf, etc.)
 //! - 計算量分析と最適化
+//! - 不整合の説明 (極小の不整合な公理集合)
 
 pub mod model;
 pub mod tableau;
@@ -12,11 +13,12 @@ pub mod reasoner;
 pub mod loader;
 
 pub use model::{ClassExpression, OwlDlOntology};
+pub use tableau::TableauOptimizations;
 pub use reasoner::OwlDlReasoner;
 pub use loader::OwlDlOntologyLoader;
 
 // Re-export OWL Lite types for compatibility
-pub use fukurow_lite::{Ontology as OwlLiteOntology, Class, Property, Individual, Axiom as OwlLiteAxiom, model::OwlIri};
+pub use fukurow_lite::{Ontology as OwlLiteOntology, Class, Property, Individual, Axiom as OwlLiteAxiom, model::OwlIri, ConsistencyReport};
 
 // Error types
 use thiserror::Error;
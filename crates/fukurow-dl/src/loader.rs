@@ -85,69 +85,69 @@ impl DefaultOwlDlOntologyLoader {
 
             // intersectionOf
             if triple.predicate == owl_intersection_of {
-                intersection_classes.insert(triple.subject.clone(), triple.object.clone());
+                intersection_classes.insert(triple.subject.to_string(), triple.object.to_string());
             }
 
             // unionOf
             else if triple.predicate == owl_union_of {
-                union_classes.insert(triple.subject.clone(), triple.object.clone());
+                union_classes.insert(triple.subject.to_string(), triple.object.to_string());
             }
 
             // complementOf
             else if triple.predicate == owl_complement_of {
-                complement_classes.insert(triple.subject.clone(), triple.object.clone());
+                complement_classes.insert(triple.subject.to_string(), triple.object.to_string());
             }
 
             // oneOf
             else if triple.predicate == owl_one_of {
-                enumeration_classes.insert(triple.subject.clone(), triple.object.clone());
+                enumeration_classes.insert(triple.subject.to_string(), triple.object.to_string());
             }
 
             // someValuesFrom
             else if triple.predicate == owl_some_values_from {
-                restriction_classes.entry(triple.subject.clone())
+                restriction_classes.entry(triple.subject.to_string())
                     .or_insert_with(HashMap::new)
-                    .insert("someValuesFrom".to_string(), triple.object.clone());
+                    .insert("someValuesFrom".to_string(), triple.object.to_string());
             }
 
             // allValuesFrom
             else if triple.predicate == owl_all_values_from {
-                restriction_classes.entry(triple.subject.clone())
+                restriction_classes.entry(triple.subject.to_string())
                     .or_insert_with(HashMap::new)
-                    .insert("allValuesFrom".to_string(), triple.object.clone());
+                    .insert("allValuesFrom".to_string(), triple.object.to_string());
             }
 
             // hasValue
             else if triple.predicate == owl_has_value {
-                restriction_classes.entry(triple.subject.clone())
+                restriction_classes.entry(triple.subject.to_string())
                     .or_insert_with(HashMap::new)
-                    .insert("hasValue".to_string(), triple.object.clone());
+                    .insert("hasValue".to_string(), triple.object.to_string());
             }
 
             // minCardinality
             else if triple.predicate == owl_min_cardinality {
-                restriction_classes.entry(triple.subject.clone())
+                restriction_classes.entry(triple.subject.to_string())
                     .or_insert_with(HashMap::new)
-                    .insert("minCardinality".to_string(), triple.object.clone());
+                    .insert("minCardinality".to_string(), triple.object.to_string());
             }
 
             // maxCardinality
             else if triple.predicate == owl_max_cardinality {
-                restriction_classes.entry(triple.subject.clone())
+                restriction_classes.entry(triple.subject.to_string())
                     .or_insert_with(HashMap::new)
-                    .insert("maxCardinality".to_string(), triple.object.clone());
+                    .insert("maxCardinality".to_string(), triple.object.to_string());
             }
 
             // exactCardinality
             else if triple.predicate == owl_exact_cardinality {
-                restriction_classes.entry(triple.subject.clone())
+                restriction_classes.entry(triple.subject.to_string())
                     .or_insert_with(HashMap::new)
-                    .insert("exactCardinality".to_string(), triple.object.clone());
+                    .insert("exactCardinality".to_string(), triple.object.to_string());
             }
 
             // inverseOf
             else if triple.predicate == owl_inverse_of {
-                inverse_properties.insert(triple.subject.clone(), triple.object.clone());
+                inverse_properties.insert(triple.subject.to_string(), triple.object.to_string());
             }
         }
 
@@ -291,17 +291,17 @@ impl DefaultOwlDlOntologyLoader {
                     continue;
                 } else {
                     // This is a class assertion - handle named classes only for now
-                    let class = fukurow_lite::Class::Named(OwlIri::new(triple.object.clone()));
-                    let individual = fukurow_lite::Individual(OwlIri::new(triple.subject.clone()));
+                    let class = fukurow_lite::Class::Named(OwlIri::new(triple.object.to_string()));
+                    let individual = fukurow_lite::Individual(OwlIri::new(triple.subject.to_string()));
                     let axiom = Axiom::OwlLite(fukurow_lite::Axiom::ClassAssertion(class, individual));
                     ontology.add_axiom(axiom);
                 }
             } else {
                 // Object property assertions (assume object is another individual)
                 // Note: This is a simplified assumption - in full OWL DL we need to handle data properties too
-                let subject_individual = fukurow_lite::Individual(OwlIri::new(triple.subject.clone()));
-                let object_individual = fukurow_lite::Individual(OwlIri::new(triple.object.clone()));
-                let property_expr = PropertyExpression::ObjectProperty(OwlIri::new(triple.predicate.clone()));
+                let subject_individual = fukurow_lite::Individual(OwlIri::new(triple.subject.to_string()));
+                let object_individual = fukurow_lite::Individual(OwlIri::new(triple.object.to_string()));
+                let property_expr = PropertyExpression::ObjectProperty(OwlIri::new(triple.predicate.to_string()));
                 let axiom = Axiom::ObjectPropertyAssertion(property_expr, subject_individual, object_individual);
                 ontology.add_axiom(axiom);
             }
@@ -325,7 +325,7 @@ impl DefaultOwlDlOntologyLoader {
             for stored_triple in store.all_triples().values().flatten() {
                 let triple = &stored_triple.triple;
                 if triple.subject == current && triple.predicate == rdf_first {
-                    result.push(triple.object.clone());
+                    result.push(triple.object.to_string());
                     found_first = true;
                     break;
                 }
@@ -343,7 +343,7 @@ impl DefaultOwlDlOntologyLoader {
                     if triple.object == rdf_nil {
                         return Some(result); // End of list
                     }
-                    current = triple.object.clone();
+                    current = triple.object.to_string();
                     found_rest = true;
                     break;
                 }
@@ -370,7 +370,7 @@ impl DefaultOwlDlOntologyLoader {
         for stored_triple in store.all_triples().values().flatten() {
             let triple = &stored_triple.triple;
             if triple.subject == restriction_iri && triple.predicate == owl_on_property {
-                return Ok(PropertyExpression::ObjectProperty(OwlIri::new(triple.object.clone())));
+                return Ok(PropertyExpression::ObjectProperty(OwlIri::new(triple.object.to_string())));
             }
         }
 
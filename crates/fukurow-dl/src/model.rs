@@ -68,6 +68,104 @@ pub enum ClassExpression {
     },
 }
 
+impl ClassExpression {
+    /// Negation normal form (¬ only in front of named classes and nominals)
+    pub fn to_nnf(&self) -> ClassExpression {
+        match self {
+            ClassExpression::ComplementOf(inner) => inner.complement(),
+            ClassExpression::IntersectionOf(classes) => {
+                ClassExpression::IntersectionOf(classes.iter().map(|c| c.to_nnf()).collect())
+            }
+            ClassExpression::UnionOf(classes) => {
+                ClassExpression::UnionOf(classes.iter().map(|c| c.to_nnf()).collect())
+            }
+            ClassExpression::SomeValuesFrom { property, class } => ClassExpression::SomeValuesFrom {
+                property: property.clone(),
+                class: Box::new(class.to_nnf()),
+            },
+            ClassExpression::AllValuesFrom { property, class } => ClassExpression::AllValuesFrom {
+                property: property.clone(),
+                class: Box::new(class.to_nnf()),
+            },
+            ClassExpression::MinCardinality { cardinality: 0, .. } => ClassExpression::Thing,
+            ClassExpression::MinCardinality { cardinality, property, class } => ClassExpression::MinCardinality {
+                cardinality: *cardinality,
+                property: property.clone(),
+                class: class.as_ref().map(|c| Box::new(c.to_nnf())),
+            },
+            ClassExpression::MaxCardinality { cardinality, property, class } => ClassExpression::MaxCardinality {
+                cardinality: *cardinality,
+                property: property.clone(),
+                class: class.as_ref().map(|c| Box::new(c.to_nnf())),
+            },
+            ClassExpression::ExactCardinality { cardinality, property, class } => ClassExpression::IntersectionOf(vec![
+                ClassExpression::MinCardinality { cardinality: *cardinality, property: property.clone(), class: class.clone() }.to_nnf(),
+                ClassExpression::MaxCardinality { cardinality: *cardinality, property: property.clone(), class: class.clone() }.to_nnf(),
+            ]),
+            other => other.clone(),
+        }
+    }
+
+    /// NNF of ¬self
+    pub fn complement(&self) -> ClassExpression {
+        match self {
+            ClassExpression::Thing => ClassExpression::Nothing,
+            ClassExpression::Nothing => ClassExpression::Thing,
+            ClassExpression::ComplementOf(inner) => inner.to_nnf(),
+            ClassExpression::IntersectionOf(classes) => {
+                ClassExpression::UnionOf(classes.iter().map(|c| c.complement()).collect())
+            }
+            ClassExpression::UnionOf(classes) => {
+                ClassExpression::IntersectionOf(classes.iter().map(|c| c.complement()).collect())
+            }
+            ClassExpression::SomeValuesFrom { property, class } => ClassExpression::AllValuesFrom {
+                property: property.clone(),
+                class: Box::new(class.complement()),
+            },
+            ClassExpression::AllValuesFrom { property, class } => ClassExpression::SomeValuesFrom {
+                property: property.clone(),
+                class: Box::new(class.complement()),
+            },
+            ClassExpression::MinCardinality { cardinality: 0, .. } => ClassExpression::Nothing,
+            ClassExpression::MinCardinality { cardinality, property, class } => ClassExpression::MaxCardinality {
+                cardinality: cardinality - 1,
+                property: property.clone(),
+                class: class.as_ref().map(|c| Box::new(c.to_nnf())),
+            },
+            ClassExpression::MaxCardinality { cardinality, property, class } => ClassExpression::MinCardinality {
+                cardinality: cardinality + 1,
+                property: property.clone(),
+                class: class.as_ref().map(|c| Box::new(c.to_nnf())),
+            },
+            ClassExpression::ExactCardinality { cardinality, property, class } => ClassExpression::UnionOf(vec![
+                ClassExpression::MinCardinality { cardinality: *cardinality, property: property.clone(), class: class.clone() }.complement(),
+                ClassExpression::MaxCardinality { cardinality: *cardinality, property: property.clone(), class: class.clone() }.complement(),
+            ]),
+            named_or_nominal => ClassExpression::ComplementOf(Box::new(named_or_nominal.clone())),
+        }
+    }
+
+    /// Whether the expression uses nominals (owl:oneOf / owl:hasValue)
+    pub fn contains_nominal(&self) -> bool {
+        match self {
+            ClassExpression::OneOf(_) | ClassExpression::HasValue { .. } => true,
+            ClassExpression::IntersectionOf(classes) | ClassExpression::UnionOf(classes) => {
+                classes.iter().any(|c| c.contains_nominal())
+            }
+            ClassExpression::ComplementOf(inner) => inner.contains_nominal(),
+            ClassExpression::SomeValuesFrom { class, .. } | ClassExpression::AllValuesFrom { class, .. } => {
+                class.contains_nominal()
+            }
+            ClassExpression::MinCardinality { class, .. }
+            | ClassExpression::MaxCardinality { class, .. }
+            | ClassExpression::ExactCardinality { class, .. } => {
+                class.as_ref().map(|c| c.contains_nominal()).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+}
+
 /// OWL DL Property Expression
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PropertyExpression {
@@ -81,6 +179,16 @@ pub enum PropertyExpression {
     InverseOf(Box<PropertyExpression>),
 }
 
+impl PropertyExpression {
+    /// Inverse property (R⁻⁻ = R)
+    pub fn inverse(&self) -> PropertyExpression {
+        match self {
+            PropertyExpression::InverseOf(inner) => (**inner).clone(),
+            other => PropertyExpression::InverseOf(Box::new(other.clone())),
+        }
+    }
+}
+
 /// OWL DL Axiom (extends OWL Lite)
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Axiom {
@@ -259,6 +367,12 @@ impl OwlDlOntology {
                         let pe = Self::owl_lite_property_to_expression(p.clone());
                         self.add_property_expression(&pe);
                     }
+                    fukurow_lite::Axiom::InverseObjectProperties(p1, p2) => {
+                        let pe1 = Self::owl_lite_property_to_expression(p1.clone());
+                        let pe2 = Self::owl_lite_property_to_expression(p2.clone());
+                        self.add_property_expression(&pe1);
+                        self.add_property_expression(&pe2);
+                    }
                     fukurow_lite::Axiom::SameIndividual(individuals) => {
                         self.individuals.extend(individuals.iter().cloned());
                     }
@@ -354,7 +468,7 @@ impl OwlDlOntology {
         self.axioms.push(axiom);
     }
 
-    fn owl_lite_class_to_expression(class: OwlLiteClass) -> ClassExpression {
+    pub(crate) fn owl_lite_class_to_expression(class: OwlLiteClass) -> ClassExpression {
         match class {
             OwlLiteClass::Named(iri) => ClassExpression::Named(iri),
             OwlLiteClass::Thing => ClassExpression::Thing,
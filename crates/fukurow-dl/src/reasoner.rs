@@ -2,16 +2,27 @@
 
 use crate::model::{OwlDlOntology, ClassExpression, PropertyExpression, Axiom};
 use crate::loader::OwlDlOntologyLoader;
-use crate::tableau::DlTableauReasoner;
+use crate::tableau::{DlTableauReasoner, TBoxIndex, TableauOptimizations};
 use crate::OwlDlError;
 use fukurow_store::store::RdfStore;
-use fukurow_lite::{OwlLiteReasoner, Ontology as OwlLiteOntology, model::OwlIri};
+use fukurow_lite::{ConsistencyReport, OwlLiteReasoner, Ontology as OwlLiteOntology, model::OwlIri};
 use std::collections::{HashMap, HashSet};
 
+/// Rolled-up property assertions deeper than this are cut to ∃R.⊤
+const MAX_ROLL_UP_DEPTH: usize = 4;
+
 /// OWL DL reasoner
 pub struct OwlDlReasoner {
     lite_reasoner: OwlLiteReasoner,
     dl_tableau: DlTableauReasoner,
+    realization: Option<Realization>,
+}
+
+/// Realization of one ontology, reused while its axioms and individuals are unchanged
+struct Realization {
+    axioms: Vec<Axiom>,
+    individuals: HashSet<fukurow_lite::Individual>,
+    types: HashMap<fukurow_lite::Individual, HashSet<ClassExpression>>,
 }
 
 impl OwlDlReasoner {
@@ -19,9 +30,16 @@ impl OwlDlReasoner {
         Self {
             lite_reasoner: OwlLiteReasoner::new(),
             dl_tableau: DlTableauReasoner::new(),
+            realization: None,
         }
     }
 
+    /// Use a chosen set of tableau optimizations for satisfiability and subsumption
+    pub fn with_tableau_optimizations(mut self, optimizations: TableauOptimizations) -> Self {
+        self.dl_tableau = self.dl_tableau.with_optimizations(optimizations);
+        self
+    }
+
     /// Load OWL DL ontology from RDF store
     pub fn load_ontology(&self, store: &RdfStore) -> Result<OwlDlOntology, OwlDlError> {
         // First try to load as OWL Lite
@@ -53,21 +71,67 @@ impl OwlDlReasoner {
         self.dl_tableau.is_consistent(ontology)
     }
 
+    /// Check consistency and, if inconsistent, find a minimal set of axioms causing it
+    ///
+    /// 公理の部分集合ごとに、同じ最適化設定の新しいタブローで整合性を判定する
+    pub fn consistency_report(&self, ontology: &OwlDlOntology) -> Result<ConsistencyReport<Axiom>, OwlDlError> {
+        let optimizations = self.dl_tableau.optimizations();
+        ConsistencyReport::compute(&ontology.axioms, |axioms| {
+            let mut subset = OwlDlOntology::new();
+            for axiom in axioms {
+                subset.add_axiom(axiom.clone());
+            }
+            DlTableauReasoner::new().with_optimizations(optimizations).is_consistent(&subset)
+        })
+    }
+
     /// Check if class expression C1 is subsumed by class expression C2 (C1 ⊑ C2)
-    pub fn is_subsumed_by(&mut self, _ontology: &OwlDlOntology, _subclass: &ClassExpression, _superclass: &ClassExpression) -> Result<bool, OwlDlError> {
-        // Subsumption reasoning for complex class expressions is very complex
-        // This would require model construction or other advanced techniques
-        Err(OwlDlError::UnsupportedFeature("Complex class expression subsumption not yet implemented".to_string()))
+    ///
+    /// C1 ⊓ ¬C2 の充足不能性をテーブローで判定する（名目 (nominal) は未対応）
+    pub fn is_subsumed_by(&mut self, ontology: &OwlDlOntology, subclass: &ClassExpression, superclass: &ClassExpression) -> Result<bool, OwlDlError> {
+        self.dl_tableau.is_subsumed_by(ontology, subclass, superclass)
     }
 
-    /// Get all subclasses of a class expression
-    pub fn get_subclasses(&mut self, _ontology: &OwlDlOntology, _class: &ClassExpression) -> Result<HashSet<ClassExpression>, OwlDlError> {
-        Err(OwlDlError::UnsupportedFeature("Subclass reasoning for complex expressions not yet implemented".to_string()))
+    /// Get all named subclasses of a class expression (including itself)
+    pub fn get_subclasses(&mut self, ontology: &OwlDlOntology, class: &ClassExpression) -> Result<HashSet<ClassExpression>, OwlDlError> {
+        let tbox = self.dl_tableau.tbox(ontology)?;
+        let mut subclasses = HashSet::new();
+
+        for candidate in Self::named_classes(ontology) {
+            if candidate != *class && self.dl_tableau.is_subsumed_in(&tbox, &candidate, class)? {
+                subclasses.insert(candidate);
+            }
+        }
+
+        subclasses.insert(class.clone());
+        Ok(subclasses)
+    }
+
+    /// Get all named superclasses of a class expression (including itself)
+    pub fn get_superclasses(&mut self, ontology: &OwlDlOntology, class: &ClassExpression) -> Result<HashSet<ClassExpression>, OwlDlError> {
+        let tbox = self.dl_tableau.tbox(ontology)?;
+        let mut superclasses = HashSet::new();
+
+        for candidate in Self::named_classes(ontology) {
+            if candidate != *class && self.dl_tableau.is_subsumed_in(&tbox, class, &candidate)? {
+                superclasses.insert(candidate);
+            }
+        }
+
+        superclasses.insert(class.clone());
+        Ok(superclasses)
     }
 
-    /// Get all superclasses of a class expression
-    pub fn get_superclasses(&mut self, _ontology: &OwlDlOntology, _class: &ClassExpression) -> Result<HashSet<ClassExpression>, OwlDlError> {
-        Err(OwlDlError::UnsupportedFeature("Superclass reasoning for complex expressions not yet implemented".to_string()))
+    /// Named classes of the ontology, sorted by IRI
+    fn named_classes(ontology: &OwlDlOntology) -> Vec<ClassExpression> {
+        let mut classes: Vec<OwlIri> = ontology.classes.iter()
+            .filter_map(|class| match class {
+                fukurow_lite::Class::Named(iri) => Some(iri.clone()),
+                _ => None,
+            })
+            .collect();
+        classes.sort_by(|a, b| a.0.cmp(&b.0));
+        classes.into_iter().map(ClassExpression::Named).collect()
     }
 
     /// Check if individual is instance of class expression
@@ -324,16 +388,193 @@ impl OwlDlReasoner {
         Ok(min_result && max_result)
     }
 
-    /// Classify ontology (compute class hierarchy for complex expressions)
-    pub fn classify_ontology(&mut self, _ontology: &OwlDlOntology) -> Result<HashMap<ClassExpression, HashSet<ClassExpression>>, OwlDlError> {
-        // Full classification of OWL DL ontologies is undecidable in general
-        // We can only provide partial results for tractable cases
-        Err(OwlDlError::UnsupportedFeature("Full OWL DL classification not yet implemented".to_string()))
+    /// Classify ontology (compute the subsumption hierarchy of named classes)
+    ///
+    /// Maps each named class to its strict named superclasses, like
+    /// `OwlLiteReasoner::classify_ontology`. Unsatisfiable classes are subsumed by
+    /// every class. Ontologies with nominals are rejected with `UnsupportedFeature`.
+    pub fn classify_ontology(&mut self, ontology: &OwlDlOntology) -> Result<HashMap<ClassExpression, HashSet<ClassExpression>>, OwlDlError> {
+        let tbox = self.dl_tableau.tbox(ontology)?;
+        let classes = Self::named_classes(ontology);
+
+        let mut unsatisfiable = HashSet::new();
+        for class in &classes {
+            if !self.dl_tableau.is_satisfiable_in(&tbox, class)? {
+                unsatisfiable.insert(class.clone());
+            }
+        }
+
+        let mut hierarchy = HashMap::new();
+        for sub in &classes {
+            let mut superclasses = HashSet::new();
+            for sup in &classes {
+                if sub == sup {
+                    continue;
+                }
+                // 充足可能なクラスは充足不能なクラスに包含されない
+                let subsumed = if unsatisfiable.contains(sub) {
+                    true
+                } else if unsatisfiable.contains(sup) {
+                    false
+                } else {
+                    self.dl_tableau.is_subsumed_in(&tbox, sub, sup)?
+                };
+                if subsumed {
+                    superclasses.insert(sup.clone());
+                }
+            }
+            hierarchy.insert(sub.clone(), superclasses);
+        }
+
+        Ok(hierarchy)
+    }
+
+    /// Realize ontology: the most specific types of every individual
+    ///
+    /// 型の候補は名前付きクラスと、オントロジーに現れる開世界で判定できる制約クラス
+    /// (∃・≥n・hasValue とその連言・選言)。構文的なインスタンス判定に加え、個体の表明を
+    /// ロールアップした概念 (表明されたクラス ⊓ ∃R.後続の概念) が候補に包含されるかをテーブローで判定し、
+    /// より特殊な型に包含される型を除く (同値な型は両方残る)。型のない個体は owl:Thing のみ。
+    /// 結果は公理と個体が変わるまでキャッシュする。TBox に名目があれば `UnsupportedFeature`
+    pub fn realize_ontology(&mut self, ontology: &OwlDlOntology) -> Result<HashMap<fukurow_lite::Individual, HashSet<ClassExpression>>, OwlDlError> {
+        Ok(self.realization(ontology)?.types.clone())
     }
 
-    /// Realize ontology (compute individual types for complex expressions)
-    pub fn realize_ontology(&mut self, _ontology: &OwlDlOntology) -> Result<HashMap<fukurow_lite::Individual, HashSet<ClassExpression>>, OwlDlError> {
-        Err(OwlDlError::UnsupportedFeature("Ontology realization for OWL DL not yet implemented".to_string()))
+    /// Most specific types of an individual, from the cached realization of `ontology`
+    pub fn get_types(&mut self, ontology: &OwlDlOntology, individual: &fukurow_lite::Individual) -> Result<HashSet<ClassExpression>, OwlDlError> {
+        self.realization(ontology)?.types.get(individual).cloned()
+            .ok_or_else(|| OwlDlError::ReasoningError(format!("Unknown individual: {}", individual.0.0)))
+    }
+
+    fn realization(&mut self, ontology: &OwlDlOntology) -> Result<&Realization, OwlDlError> {
+        let current = self.realization.as_ref()
+            .is_some_and(|cached| cached.axioms == ontology.axioms && cached.individuals == ontology.individuals);
+        if !current {
+            let types = self.compute_realization(ontology)?;
+            self.realization = Some(Realization {
+                axioms: ontology.axioms.clone(),
+                individuals: ontology.individuals.clone(),
+                types,
+            });
+        }
+        Ok(self.realization.as_ref().expect("realization was just computed"))
+    }
+
+    fn compute_realization(&mut self, ontology: &OwlDlOntology) -> Result<HashMap<fukurow_lite::Individual, HashSet<ClassExpression>>, OwlDlError> {
+        let tbox = self.dl_tableau.tbox(ontology)?;
+        let mut candidates = Self::named_classes(ontology);
+        candidates.extend(ontology.class_expressions.iter().filter(|expr| Self::is_type_candidate(expr)).cloned());
+
+        let mut realization = HashMap::new();
+        for individual in &ontology.individuals {
+            let rolled_up = Self::roll_up(ontology, individual, &mut Vec::new());
+            if !self.dl_tableau.is_satisfiable_in(&tbox, &rolled_up)? {
+                return Err(OwlDlError::ConsistencyError(format!("Individual {} has contradictory types", individual.0.0)));
+            }
+
+            let mut types = Vec::new();
+            for candidate in &candidates {
+                let entailed = self.is_instance_of(ontology, individual, candidate)?
+                    || (!candidate.contains_nominal() && self.dl_tableau.is_subsumed_in(&tbox, &rolled_up, candidate)?);
+                if entailed {
+                    types.push(candidate.clone());
+                }
+            }
+            realization.insert(individual.clone(), self.most_specific(&tbox, &types)?);
+        }
+
+        Ok(realization)
+    }
+
+    /// Restriction classes reported as types; ¬, ∀ and ≤n are only decidable under a closed world
+    fn is_type_candidate(expr: &ClassExpression) -> bool {
+        fn open_world(expr: &ClassExpression) -> bool {
+            match expr {
+                ClassExpression::Named(_) | ClassExpression::Thing | ClassExpression::HasValue { .. } => true,
+                ClassExpression::IntersectionOf(classes) | ClassExpression::UnionOf(classes) => classes.iter().all(open_world),
+                ClassExpression::SomeValuesFrom { class, .. } => open_world(class),
+                ClassExpression::MinCardinality { class: Some(class), .. } => open_world(class),
+                ClassExpression::MinCardinality { class: None, .. } => true,
+                _ => false,
+            }
+        }
+        !matches!(expr, ClassExpression::Named(_) | ClassExpression::Thing | ClassExpression::Nothing) && open_world(expr)
+    }
+
+    /// Concept entailed by an individual's assertions
+    ///
+    /// 表明されたクラス (名目を含むものは除く) と、各プロパティ表明 a R b について ∃R.(b の概念)。
+    /// 経路上の個体に戻る表明と深すぎる表明は ∃R.⊤ に打ち切る (健全だが不完全)
+    fn roll_up(ontology: &OwlDlOntology, individual: &fukurow_lite::Individual, path: &mut Vec<fukurow_lite::Individual>) -> ClassExpression {
+        let mut conjuncts: Vec<ClassExpression> = ontology.axioms.iter()
+            .filter_map(|axiom| match axiom {
+                Axiom::ClassAssertion(class, asserted) if asserted == individual => Some(class.clone()),
+                Axiom::OwlLite(fukurow_lite::Axiom::ClassAssertion(class, asserted)) if asserted == individual => {
+                    Some(OwlDlOntology::owl_lite_class_to_expression(class.clone()))
+                }
+                _ => None,
+            })
+            .filter(|class| !class.contains_nominal())
+            .collect();
+
+        path.push(individual.clone());
+        for (property, object) in Self::outgoing_assertions(ontology, individual) {
+            let filler = if path.len() >= MAX_ROLL_UP_DEPTH || path.contains(&object) {
+                ClassExpression::Thing
+            } else {
+                Self::roll_up(ontology, &object, path)
+            };
+            conjuncts.push(ClassExpression::SomeValuesFrom { property, class: Box::new(filler) });
+        }
+        path.pop();
+
+        match conjuncts.len() {
+            0 => ClassExpression::Thing,
+            1 => conjuncts.remove(0),
+            _ => ClassExpression::IntersectionOf(conjuncts),
+        }
+    }
+
+    /// Object property assertions with the individual as subject
+    fn outgoing_assertions(ontology: &OwlDlOntology, individual: &fukurow_lite::Individual) -> Vec<(PropertyExpression, fukurow_lite::Individual)> {
+        ontology.axioms.iter()
+            .filter_map(|axiom| match axiom {
+                Axiom::ObjectPropertyAssertion(property, subject, object) if subject == individual => {
+                    Some((property.clone(), object.clone()))
+                }
+                Axiom::OwlLite(fukurow_lite::Axiom::ObjectPropertyAssertion(fukurow_lite::Property::Object(iri), subject, object))
+                    if subject == individual =>
+                {
+                    Some((PropertyExpression::ObjectProperty(iri.clone()), object.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Types not strictly subsumed by another type of the set
+    fn most_specific(&self, tbox: &TBoxIndex, types: &[ClassExpression]) -> Result<HashSet<ClassExpression>, OwlDlError> {
+        // 名目を含む型はテーブローで比較できないので、除きも除かせもしない
+        let subsumed = |sub: &ClassExpression, sup: &ClassExpression| -> Result<bool, OwlDlError> {
+            if sub.contains_nominal() || sup.contains_nominal() {
+                return Ok(false);
+            }
+            self.dl_tableau.is_subsumed_in(tbox, sub, sup)
+        };
+
+        let mut most_specific = HashSet::new();
+        'types: for class in types {
+            for other in types {
+                if other != class && subsumed(other, class)? && !subsumed(class, other)? {
+                    continue 'types;
+                }
+            }
+            most_specific.insert(class.clone());
+        }
+        if most_specific.is_empty() {
+            most_specific.insert(ClassExpression::Thing);
+        }
+        Ok(most_specific)
     }
 
     /// Get inferred axioms (closure of the ontology)
@@ -1020,4 +1261,251 @@ mod tests {
         let is_instance = reasoner.is_instance_of(&ontology, &george, &multi_child_parent_class).unwrap();
         assert!(is_instance, "George should be an instance of ≥2 hasChild");
     }
+
+    fn named(name: &str) -> ClassExpression {
+        ClassExpression::Named(OwlIri::new(format!("http://example.org/{}", name)))
+    }
+
+    fn property(name: &str) -> PropertyExpression {
+        PropertyExpression::ObjectProperty(OwlIri::new(format!("http://example.org/{}", name)))
+    }
+
+    fn tbox(axioms: Vec<Axiom>) -> OwlDlOntology {
+        let mut ontology = OwlDlOntology::new();
+        for axiom in axioms {
+            ontology.add_axiom(axiom);
+        }
+        ontology
+    }
+
+    #[test]
+    fn test_subsumption_with_complex_expressions() {
+        let ontology = tbox(vec![
+            Axiom::SubClassOf(named("Malware"), named("Threat")),
+            Axiom::SubClassOf(named("Phishing"), named("Threat")),
+            Axiom::EquivalentClasses(vec![
+                named("CompromisedHost"),
+                ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(named("Malware")) },
+            ]),
+        ]);
+        let mut reasoner = OwlDlReasoner::new();
+
+        // 連言・選言
+        let both = ClassExpression::IntersectionOf(vec![named("Malware"), named("Phishing")]);
+        assert!(reasoner.is_subsumed_by(&ontology, &both, &named("Malware")).unwrap());
+        let either = ClassExpression::UnionOf(vec![named("Malware"), named("Phishing")]);
+        assert!(reasoner.is_subsumed_by(&ontology, &either, &named("Threat")).unwrap());
+        assert!(!reasoner.is_subsumed_by(&ontology, &named("Threat"), &named("Malware")).unwrap());
+
+        // ∃runs.Malware ⊑ ∃runs.Threat、および定義クラスの認識
+        let runs_threat = ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(named("Threat")) };
+        assert!(reasoner.is_subsumed_by(&ontology, &named("CompromisedHost"), &runs_threat).unwrap());
+        let runs_malware_and_more = ClassExpression::IntersectionOf(vec![
+            ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(named("Malware")) },
+            named("Server"),
+        ]);
+        assert!(reasoner.is_subsumed_by(&ontology, &runs_malware_and_more, &named("CompromisedHost")).unwrap());
+
+        // ∀ と ∃ の相互作用
+        let only_benign = ClassExpression::AllValuesFrom {
+            property: property("runs"),
+            class: Box::new(ClassExpression::ComplementOf(Box::new(named("Threat")))),
+        };
+        let contradiction = ClassExpression::IntersectionOf(vec![named("CompromisedHost"), only_benign]);
+        assert!(reasoner.is_subsumed_by(&ontology, &contradiction, &ClassExpression::Nothing).unwrap());
+    }
+
+    #[test]
+    fn test_subsumption_with_properties_and_cardinality() {
+        let ontology = tbox(vec![
+            Axiom::SubPropertyOf(property("exfiltratesTo"), property("connectsTo")),
+            Axiom::ObjectPropertyDomain(property("connectsTo"), named("Host")),
+        ]);
+        let mut reasoner = OwlDlReasoner::new();
+
+        let exfiltrates = ClassExpression::SomeValuesFrom {
+            property: property("exfiltratesTo"),
+            class: Box::new(ClassExpression::Thing),
+        };
+        assert!(reasoner.is_subsumed_by(&ontology, &exfiltrates, &named("Host")).unwrap());
+
+        let at_least_two = ClassExpression::MinCardinality { cardinality: 2, property: property("connectsTo"), class: None };
+        let at_least_one = ClassExpression::MinCardinality { cardinality: 1, property: property("connectsTo"), class: None };
+        let at_most_one = ClassExpression::MaxCardinality { cardinality: 1, property: property("connectsTo"), class: None };
+        assert!(reasoner.is_subsumed_by(&ontology, &at_least_two, &at_least_one).unwrap());
+        assert!(!reasoner.is_subsumed_by(&ontology, &at_least_one, &at_least_two).unwrap());
+
+        let tableau = DlTableauReasoner::new();
+        let impossible = ClassExpression::IntersectionOf(vec![at_least_two, at_most_one.clone()]);
+        assert!(!tableau.is_satisfiable(&ontology, &impossible).unwrap());
+
+        // ≤1 の下では 2 つの ∃ 後続が統合される
+        let merged = ClassExpression::IntersectionOf(vec![
+            at_most_one,
+            ClassExpression::SomeValuesFrom { property: property("connectsTo"), class: Box::new(named("C2")) },
+            ClassExpression::SomeValuesFrom { property: property("connectsTo"), class: Box::new(named("Proxy")) },
+        ]);
+        let via_proxy = ClassExpression::SomeValuesFrom {
+            property: property("connectsTo"),
+            class: Box::new(ClassExpression::IntersectionOf(vec![named("C2"), named("Proxy")])),
+        };
+        assert!(reasoner.is_subsumed_by(&ontology, &merged, &via_proxy).unwrap());
+    }
+
+    #[test]
+    fn test_classify_ontology() {
+        let ontology = tbox(vec![
+            Axiom::OwlLite(fukurow_lite::Axiom::SubClassOf(
+                fukurow_lite::Class::Named(OwlIri::new("http://example.org/Ransomware".to_string())),
+                fukurow_lite::Class::Named(OwlIri::new("http://example.org/Malware".to_string())),
+            )),
+            Axiom::EquivalentClasses(vec![
+                named("Malware"),
+                ClassExpression::IntersectionOf(vec![named("Software"), named("Malicious")]),
+            ]),
+            Axiom::SubClassOf(named("Trojan"), ClassExpression::IntersectionOf(vec![named("Software"), named("Malicious")])),
+            Axiom::DisjointClasses(vec![named("Malicious"), named("Benign")]),
+            Axiom::SubClassOf(named("Impossible"), ClassExpression::IntersectionOf(vec![named("Malicious"), named("Benign")])),
+        ]);
+        let mut reasoner = OwlDlReasoner::new();
+
+        let hierarchy = reasoner.classify_ontology(&ontology).unwrap();
+        assert!(hierarchy[&named("Ransomware")].contains(&named("Software")));
+        assert!(hierarchy[&named("Trojan")].contains(&named("Malware")));
+        assert!(!hierarchy[&named("Software")].contains(&named("Malware")));
+        assert!(hierarchy[&named("Impossible")].contains(&named("Ransomware")));
+        assert!(!hierarchy[&named("Benign")].contains(&named("Malicious")));
+
+        let subclasses = reasoner.get_subclasses(&ontology, &named("Malware")).unwrap();
+        assert!(subclasses.contains(&named("Trojan")) && subclasses.contains(&named("Ransomware")));
+        let superclasses = reasoner.get_superclasses(&ontology, &named("Ransomware")).unwrap();
+        assert!(superclasses.contains(&named("Malicious")));
+    }
+
+    #[test]
+    fn test_subsumption_rejects_nominals() {
+        let ontology = tbox(vec![Axiom::SubClassOf(
+            named("Admin"),
+            ClassExpression::OneOf(vec![Individual(OwlIri::new("http://example.org/root".to_string()))]),
+        )]);
+        let mut reasoner = OwlDlReasoner::new();
+
+        let result = reasoner.is_subsumed_by(&ontology, &named("Admin"), &named("User"));
+        assert!(matches!(result, Err(OwlDlError::UnsupportedFeature(_))));
+    }
+
+    #[test]
+    fn test_tableau_optimizations_do_not_change_results() {
+        let ontology = tbox(vec![
+            // 定義公理 (遅延展開)・一般包含公理 (吸収)・定義域 (ロール吸収)
+            Axiom::EquivalentClasses(vec![
+                named("InfectedHost"),
+                ClassExpression::IntersectionOf(vec![
+                    named("Host"),
+                    ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(named("Malware")) },
+                ]),
+            ]),
+            Axiom::EquivalentClasses(vec![
+                named("Malware"),
+                ClassExpression::UnionOf(vec![named("Worm"), named("Ransomware")]),
+            ]),
+            Axiom::SubClassOf(
+                ClassExpression::IntersectionOf(vec![named("Server"), named("InfectedHost")]),
+                named("Incident"),
+            ),
+            Axiom::SubClassOf(
+                ClassExpression::UnionOf(vec![named("Worm"), named("Ransomware")]),
+                named("Software"),
+            ),
+            Axiom::DisjointClasses(vec![named("Software"), named("Host")]),
+            Axiom::SubClassOf(named("Server"), named("Host")),
+            Axiom::ObjectPropertyDomain(property("runs"), named("Host")),
+            Axiom::SubClassOf(named("Appliance"), ClassExpression::IntersectionOf(vec![named("Worm"), named("Host")])),
+        ]);
+
+        let mut optimized = OwlDlReasoner::new();
+        let mut plain = OwlDlReasoner::new().with_tableau_optimizations(TableauOptimizations::none());
+        assert_eq!(optimized.classify_ontology(&ontology).unwrap(), plain.classify_ontology(&ontology).unwrap());
+
+        let runs_worm = ClassExpression::IntersectionOf(vec![
+            named("Server"),
+            ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(named("Worm")) },
+        ]);
+        for reasoner in [&mut optimized, &mut plain] {
+            assert!(reasoner.is_subsumed_by(&ontology, &runs_worm, &named("Incident")).unwrap());
+            assert!(!reasoner.is_subsumed_by(&ontology, &named("Host"), &named("InfectedHost")).unwrap());
+            let runner = ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(ClassExpression::Thing) };
+            assert!(reasoner.is_subsumed_by(&ontology, &runner, &named("Host")).unwrap());
+            assert!(reasoner.is_subsumed_by(&ontology, &named("Appliance"), &ClassExpression::Nothing).unwrap());
+        }
+
+        // 吸収後に全ノードへ追加される公理は残らない
+        let tableau = DlTableauReasoner::new();
+        let index = tableau.tbox(&ontology).unwrap();
+        assert_eq!(index.global_axiom_count(), 0);
+        assert_eq!(index.definitional_count(), 2);
+        let unoptimized = DlTableauReasoner::new().with_optimizations(TableauOptimizations::none()).tbox(&ontology).unwrap();
+        assert!(unoptimized.global_axiom_count() > 0);
+        assert_eq!(unoptimized.definitional_count(), 0);
+    }
+
+    #[test]
+    fn test_consistency_report_justifies_same_and_different_individuals() {
+        let host = |name: &str| Individual(OwlIri::new(format!("http://example.org/{}", name)));
+        let same = |a: &str, b: &str| Axiom::SameIndividual(vec![host(a), host(b)]);
+        let ontology = tbox(vec![
+            Axiom::SubClassOf(named("Server"), named("Host")),
+            same("h1", "h2"),
+            Axiom::ClassAssertion(named("Server"), host("h1")),
+            same("h2", "h3"),
+            Axiom::ObjectPropertyAssertion(property("connectsTo"), host("h3"), host("h4")),
+            Axiom::DifferentIndividuals(vec![host("h1"), host("h3")]),
+        ]);
+
+        let reasoner = OwlDlReasoner::new();
+        let report = reasoner.consistency_report(&ontology).unwrap();
+        assert!(!report.consistent);
+        assert_eq!(report.justification, vec![
+            same("h1", "h2"),
+            same("h2", "h3"),
+            Axiom::DifferentIndividuals(vec![host("h1"), host("h3")]),
+        ]);
+
+        let consistent = tbox(ontology.axioms[..5].to_vec());
+        let report = reasoner.consistency_report(&consistent).unwrap();
+        assert!(report.consistent && report.justification.is_empty());
+    }
+
+    #[test]
+    fn test_realize_ontology_keeps_most_specific_types() {
+        let host = |name: &str| Individual(OwlIri::new(format!("http://example.org/{}", name)));
+        let runs_malware = ClassExpression::SomeValuesFrom { property: property("runs"), class: Box::new(named("Malware")) };
+        let mut ontology = tbox(vec![
+            Axiom::SubClassOf(named("Ransomware"), named("Malware")),
+            Axiom::EquivalentClasses(vec![named("CompromisedHost"), runs_malware.clone()]),
+            Axiom::SubClassOf(named("CompromisedHost"), named("Host")),
+            Axiom::SubClassOf(named("Server"), named("Host")),
+            Axiom::ClassAssertion(named("Server"), host("h1")),
+            Axiom::ObjectPropertyAssertion(property("runs"), host("h1"), host("lockbit")),
+            Axiom::ClassAssertion(named("Ransomware"), host("lockbit")),
+            Axiom::ClassAssertion(named("Server"), host("h2")),
+            Axiom::ObjectPropertyAssertion(property("connectsTo"), host("h2"), host("c2")),
+        ]);
+        let mut reasoner = OwlDlReasoner::new();
+
+        // ∃runs.Malware は Ransomware の上位クラスを経由してテーブローで導かれる
+        let realization = reasoner.realize_ontology(&ontology).unwrap();
+        assert_eq!(realization[&host("h1")], HashSet::from([named("Server"), named("CompromisedHost"), runs_malware.clone()]));
+        assert_eq!(realization[&host("lockbit")], HashSet::from([named("Ransomware")]));
+        assert_eq!(realization[&host("c2")], HashSet::from([ClassExpression::Thing]));
+        assert_eq!(reasoner.get_types(&ontology, &host("h2")).unwrap(), HashSet::from([named("Server")]));
+        assert!(matches!(reasoner.get_types(&ontology, &host("unknown")), Err(OwlDlError::ReasoningError(_))));
+
+        // 公理が変わればキャッシュを作り直す
+        ontology.add_axiom(Axiom::ObjectPropertyAssertion(property("runs"), host("h2"), host("lockbit")));
+        assert!(reasoner.get_types(&ontology, &host("h2")).unwrap().contains(&named("CompromisedHost")));
+
+        ontology.add_axiom(Axiom::DisjointClasses(vec![named("Server"), named("CompromisedHost")]));
+        assert!(matches!(reasoner.realize_ontology(&ontology), Err(OwlDlError::ConsistencyError(_))));
+    }
 }
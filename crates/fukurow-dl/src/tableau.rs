@@ -5,7 +5,8 @@ use fukurow_lite::Individual;
 use crate::OwlDlError;
 use fukurow_lite::tableau::{CompletionGraph, TableauReasoner};
 use fukurow_lite::model::{OwlIri, Class, Property};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
 
 /// Extended completion graph for OWL DL (includes individual reasoning)
 #[derive(Debug)]
@@ -63,11 +64,11 @@ impl DlCompletionGraph {
             .unwrap_or(false)
     }
 
-    /// Add property assertion between individuals
-    pub fn add_property_assertion(&mut self, from: &Individual, property: PropertyExpression, to: &Individual) {
+    /// Add property assertion between individuals (`true` when it is new)
+    pub fn add_property_assertion(&mut self, from: &Individual, property: PropertyExpression, to: &Individual) -> bool {
         self.property_assertions.entry((from.clone(), property))
             .or_insert_with(HashSet::new)
-            .insert(to.clone());
+            .insert(to.clone())
     }
 
     /// Get property successors
@@ -142,12 +143,14 @@ impl DlCompletionGraph {
 /// OWL DL Tableau reasoner
 pub struct DlTableauReasoner {
     graph: DlCompletionGraph,
+    optimizations: TableauOptimizations,
 }
 
 impl DlTableauReasoner {
     pub fn new() -> Self {
         Self {
             graph: DlCompletionGraph::new(),
+            optimizations: TableauOptimizations::default(),
         }
     }
 
@@ -354,36 +357,26 @@ impl DlTableauReasoner {
     fn apply_individual_rules(&mut self) -> Result<bool, OwlDlError> {
         let mut changed = false;
 
-        // Apply same individual rules (merge labels)
+        // Apply same individual rules: every member gets the labels and property
+        // assertions of the whole class, so the result does not depend on set order
         let same_individuals = self.graph.same_individuals.clone();
         for eq_class in &same_individuals {
-            let individuals: Vec<_> = eq_class.iter().collect();
-
-            for i in 1..individuals.len() {
-                let source = individuals[0];
-                let target = individuals[i];
-
-                // Collect labels to merge first
-                let source_labels = self.graph.individual_labels.get(source).cloned().unwrap_or_default();
-                let source_property_assertions: Vec<_> = self.graph.property_assertions.iter()
-                    .filter(|((from, _), _)| from == source)
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
-
-                // Merge labels from source to target
-                for label in &source_labels {
-                    if !self.graph.has_label(target, label) {
-                        self.graph.add_label(target, label.clone());
-                        changed = true;
-                    }
+            let labels: HashSet<ClassExpression> = eq_class.iter()
+                .filter_map(|member| self.graph.individual_labels.get(member))
+                .flatten()
+                .cloned()
+                .collect();
+            let assertions: Vec<(PropertyExpression, Individual)> = self.graph.property_assertions.iter()
+                .filter(|((from, _), _)| eq_class.contains(from))
+                .flat_map(|((_, property), successors)| successors.iter().map(move |successor| (property.clone(), successor.clone())))
+                .collect();
+
+            for member in eq_class {
+                for label in &labels {
+                    changed |= self.graph.add_label(member, label.clone());
                 }
-
-                // Merge property assertions
-                for ((_, prop), successors) in &source_property_assertions {
-                    for successor in successors {
-                        self.graph.add_property_assertion(target, prop.clone(), successor);
-                        changed = true;
-                    }
+                for (property, successor) in &assertions {
+                    changed |= self.graph.add_property_assertion(member, property.clone(), successor);
                 }
             }
         }
@@ -475,3 +468,861 @@ impl DlTableauReasoner {
         }
     }
 }
+
+/// Upper bound on completion graph nodes for a single satisfiability test
+const MAX_SAT_NODES: usize = 10_000;
+
+/// Optimizations of the satisfiability tableau (all enabled by default)
+///
+/// 無効にしても結果は変わらない。ベンチマークや不具合の切り分けに使う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableauOptimizations {
+    /// Also unfold ¬A for definitional axioms A ≡ C instead of adding C ⊑ A to every node
+    pub definitional_unfolding: bool,
+    /// Rewrite general axioms into axioms on a named class or a property domain
+    pub absorption: bool,
+    /// Skip the remaining alternatives of a branch that did not cause the clash
+    pub backjumping: bool,
+    /// Remember satisfiability per class expression for the lifetime of the TBox index
+    pub caching: bool,
+}
+
+impl TableauOptimizations {
+    pub fn all() -> Self {
+        Self { definitional_unfolding: true, absorption: true, backjumping: true, caching: true }
+    }
+
+    pub fn none() -> Self {
+        Self { definitional_unfolding: false, absorption: false, backjumping: false, caching: false }
+    }
+}
+
+impl Default for TableauOptimizations {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Satisfiability results per class expression (NNF)
+#[derive(Debug, Default)]
+struct SatCache(Mutex<HashMap<ClassExpression, bool>>);
+
+impl SatCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<ClassExpression, bool>> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Clone for SatCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.lock().clone()))
+    }
+}
+
+/// TBox preprocessed for concept satisfiability tests
+///
+/// 左辺が名前付きクラスの包含公理は遅延展開 (lazy unfolding) する。定義公理 A ≡ C は、
+/// A を左辺に持つ公理がほかになく循環もなければ ¬A → ¬C も遅延展開し、C ⊑ A を全ノードに追加しない。
+/// それ以外の一般包含公理は、左辺の選言を分けたうえで、連言に名前付きクラスがあればそのクラスへ、
+/// ∃R.⊤ ならプロパティの定義域へ吸収 (absorption) し、吸収できないものだけを NNF(¬C ⊔ D) として
+/// 全ノードに追加する
+#[derive(Debug, Clone, Default)]
+pub struct TBoxIndex {
+    /// A ⊑ C (unfolded when A is added to a node)
+    unfoldable: HashMap<OwlIri, Vec<ClassExpression>>,
+    /// ¬A ⊑ ¬C for definitional A ≡ C (unfolded when ¬A is added to a node)
+    negative_unfoldable: HashMap<OwlIri, ClassExpression>,
+    /// ∃R.⊤ ⊑ C (added to nodes with an R-neighbour)
+    domains: Vec<(PropertyExpression, ClassExpression)>,
+    /// General axioms, added to every node
+    global: Vec<ClassExpression>,
+    /// Reflexive-transitive super-property closure
+    super_properties: HashMap<PropertyExpression, HashSet<PropertyExpression>>,
+    optimizations: TableauOptimizations,
+    cache: SatCache,
+}
+
+impl TBoxIndex {
+    /// Build the index from the ontology's class and property axioms
+    ///
+    /// Nominals (owl:oneOf / owl:hasValue) are not supported.
+    pub fn from_ontology(ontology: &OwlDlOntology) -> Result<Self, OwlDlError> {
+        Self::from_ontology_with(ontology, TableauOptimizations::default())
+    }
+
+    /// Build the index with a chosen set of optimizations
+    pub fn from_ontology_with(ontology: &OwlDlOntology, optimizations: TableauOptimizations) -> Result<Self, OwlDlError> {
+        let mut tbox = TBoxIndex { optimizations, ..TBoxIndex::default() };
+        let mut inclusions = Vec::new();
+        let mut definitions: Vec<(OwlIri, ClassExpression)> = Vec::new();
+        let mut property_inclusions = Vec::new();
+
+        let lite_class = DlTableauReasoner::owl_lite_class_to_expression;
+        let lite_property = DlTableauReasoner::owl_lite_property_to_expression;
+
+        for axiom in &ontology.axioms {
+            match axiom {
+                Axiom::OwlLite(lite) => match lite {
+                    fukurow_lite::Axiom::SubClassOf(sub, sup) => {
+                        inclusions.push((lite_class(sub.clone()), lite_class(sup.clone())));
+                    }
+                    fukurow_lite::Axiom::EquivalentClasses(classes) => {
+                        let classes: Vec<_> = classes.iter().cloned().map(lite_class).collect();
+                        Self::add_equivalent(&classes, &mut definitions, &mut inclusions);
+                    }
+                    fukurow_lite::Axiom::DisjointClasses(classes) => {
+                        let classes: Vec<_> = classes.iter().cloned().map(lite_class).collect();
+                        Self::add_disjoint(&classes, &mut inclusions);
+                    }
+                    fukurow_lite::Axiom::SubPropertyOf(sub, sup) => {
+                        property_inclusions.push((lite_property(sub.clone()), lite_property(sup.clone())));
+                    }
+                    fukurow_lite::Axiom::EquivalentProperties(properties) => {
+                        let properties: Vec<_> = properties.iter().cloned().map(lite_property).collect();
+                        property_inclusions.extend(Self::equivalent_pairs(&properties));
+                    }
+                    fukurow_lite::Axiom::ObjectPropertyDomain(property, class) => {
+                        inclusions.push(Self::domain(lite_property(property.clone()), lite_class(class.clone())));
+                    }
+                    fukurow_lite::Axiom::ObjectPropertyRange(property, class) => {
+                        inclusions.push(Self::range(lite_property(property.clone()), lite_class(class.clone())));
+                    }
+                    _ => {}
+                },
+                Axiom::SubClassOf(sub, sup) => inclusions.push((sub.clone(), sup.clone())),
+                Axiom::EquivalentClasses(classes) => Self::add_equivalent(classes, &mut definitions, &mut inclusions),
+                Axiom::DisjointClasses(classes) => Self::add_disjoint(classes, &mut inclusions),
+                Axiom::SubPropertyOf(sub, sup) => property_inclusions.push((sub.clone(), sup.clone())),
+                Axiom::EquivalentProperties(properties) => {
+                    property_inclusions.extend(Self::equivalent_pairs(properties));
+                }
+                Axiom::ObjectPropertyDomain(property, class) => inclusions.push(Self::domain(property.clone(), class.clone())),
+                Axiom::ObjectPropertyRange(property, class) => inclusions.push(Self::range(property.clone(), class.clone())),
+                _ => {}
+            }
+        }
+
+        let has_nominal = inclusions.iter().any(|(sub, sup)| sub.contains_nominal() || sup.contains_nominal())
+            || definitions.iter().any(|(_, class)| class.contains_nominal());
+        if has_nominal {
+            return Err(OwlDlError::UnsupportedFeature("Nominals in TBox axioms are not supported".to_string()));
+        }
+
+        let definitional = if optimizations.definitional_unfolding {
+            Self::definitional(&definitions, &inclusions)
+        } else {
+            HashSet::new()
+        };
+        for (name, class) in definitions {
+            if definitional.contains(&name) {
+                tbox.negative_unfoldable.insert(name.clone(), class.complement());
+                tbox.unfoldable.entry(name).or_default().push(class.to_nnf());
+            } else {
+                inclusions.push((ClassExpression::Named(name.clone()), class.clone()));
+                inclusions.push((class, ClassExpression::Named(name)));
+            }
+        }
+        for (sub, sup) in inclusions {
+            tbox.add_subclass(sub, sup, &definitional);
+        }
+
+        tbox.close_properties(property_inclusions);
+        Ok(tbox)
+    }
+
+    fn equivalent_pairs<T: Clone>(items: &[T]) -> Vec<(T, T)> {
+        let mut pairs = Vec::new();
+        for (i, a) in items.iter().enumerate() {
+            for (j, b) in items.iter().enumerate() {
+                if i != j {
+                    pairs.push((a.clone(), b.clone()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// A ≡ C becomes a definition candidate; anything else becomes pairwise inclusions
+    fn add_equivalent(
+        classes: &[ClassExpression],
+        definitions: &mut Vec<(OwlIri, ClassExpression)>,
+        inclusions: &mut Vec<(ClassExpression, ClassExpression)>,
+    ) {
+        if let [first, second] = classes {
+            let candidate = match (first, second) {
+                (ClassExpression::Named(name), class) | (class, ClassExpression::Named(name)) => Some((name, class)),
+                _ => None,
+            };
+            if let Some((name, class)) = candidate {
+                definitions.push((name.clone(), class.clone()));
+                return;
+            }
+        }
+        inclusions.extend(Self::equivalent_pairs(classes));
+    }
+
+    fn add_disjoint(classes: &[ClassExpression], inclusions: &mut Vec<(ClassExpression, ClassExpression)>) {
+        for (i, a) in classes.iter().enumerate() {
+            for b in &classes[i + 1..] {
+                inclusions.push((a.clone(), ClassExpression::ComplementOf(Box::new(b.clone()))));
+            }
+        }
+    }
+
+    /// ∃R.⊤ ⊑ C
+    fn domain(property: PropertyExpression, class: ClassExpression) -> (ClassExpression, ClassExpression) {
+        (ClassExpression::SomeValuesFrom { property, class: Box::new(ClassExpression::Thing) }, class)
+    }
+
+    /// ⊤ ⊑ ∀R.C
+    fn range(property: PropertyExpression, class: ClassExpression) -> (ClassExpression, ClassExpression) {
+        (ClassExpression::Thing, ClassExpression::AllValuesFrom { property, class: Box::new(class) })
+    }
+
+    /// Names whose only axiom is a single acyclic definition A ≡ C
+    fn definitional(definitions: &[(OwlIri, ClassExpression)], inclusions: &[(ClassExpression, ClassExpression)]) -> HashSet<OwlIri> {
+        let mut counts: HashMap<&OwlIri, usize> = HashMap::new();
+        for (name, _) in definitions {
+            *counts.entry(name).or_insert(0) += 1;
+        }
+        for (sub, _) in inclusions {
+            if let ClassExpression::Named(name) = sub {
+                *counts.entry(name).or_insert(0) += 1;
+            }
+        }
+        let candidates: HashMap<&OwlIri, &ClassExpression> = definitions.iter()
+            .filter(|(name, _)| counts[name] == 1)
+            .map(|(name, class)| (name, class))
+            .collect();
+
+        // 定義をたどって自分自身に戻る名前は除く
+        candidates.keys()
+            .filter(|&&name| {
+                let mut seen = HashSet::new();
+                let mut stack = vec![name];
+                while let Some(current) = stack.pop() {
+                    let mut used = HashSet::new();
+                    if let Some(class) = candidates.get(current) {
+                        named_classes_in(class, &mut used);
+                    }
+                    for next in used {
+                        if next == name {
+                            return false;
+                        }
+                        if let Some((key, _)) = candidates.get_key_value(next) {
+                            if seen.insert(*key) {
+                                stack.push(*key);
+                            }
+                        }
+                    }
+                }
+                true
+            })
+            .map(|&name| name.clone())
+            .collect()
+    }
+
+    fn add_subclass(&mut self, sub: ClassExpression, sup: ClassExpression, definitional: &HashSet<OwlIri>) {
+        match sub.to_nnf() {
+            ClassExpression::Named(iri) => self.unfoldable.entry(iri).or_default().push(sup.to_nnf()),
+            ClassExpression::Thing => self.global.push(sup.to_nnf()),
+            ClassExpression::SomeValuesFrom { property, class } if self.optimizations.absorption && *class == ClassExpression::Thing => {
+                self.domains.push((property, sup.to_nnf()));
+            }
+            ClassExpression::UnionOf(disjuncts) if self.optimizations.absorption => {
+                // C1 ⊔ C2 ⊑ D は C1 ⊑ D と C2 ⊑ D に分ける
+                for disjunct in disjuncts {
+                    self.add_subclass(disjunct, sup.clone(), definitional);
+                }
+            }
+            ClassExpression::IntersectionOf(conjuncts) if self.optimizations.absorption => {
+                let mut conjuncts = flatten_intersection(conjuncts);
+                let absorber = conjuncts.iter().position(|c| matches!(c, ClassExpression::Named(iri) if !definitional.contains(iri)));
+                match absorber {
+                    Some(index) => {
+                        // A ⊓ C ⊑ D を A ⊑ ¬C ⊔ D に書き換える
+                        let ClassExpression::Named(iri) = conjuncts.remove(index) else { unreachable!() };
+                        let mut disjuncts: Vec<ClassExpression> = conjuncts.iter().map(|c| c.complement()).collect();
+                        disjuncts.push(sup.to_nnf());
+                        let absorbed = if disjuncts.len() == 1 { disjuncts.remove(0) } else { ClassExpression::UnionOf(disjuncts) };
+                        self.unfoldable.entry(iri).or_default().push(absorbed);
+                    }
+                    None => self.global.push(ClassExpression::UnionOf(vec![
+                        ClassExpression::IntersectionOf(conjuncts).complement(),
+                        sup.to_nnf(),
+                    ])),
+                }
+            }
+            sub => self.global.push(ClassExpression::UnionOf(vec![sub.complement(), sup.to_nnf()])),
+        }
+    }
+
+    fn close_properties(&mut self, inclusions: Vec<(PropertyExpression, PropertyExpression)>) {
+        // R ⊑ S ならば R⁻ ⊑ S⁻
+        let mut direct: HashMap<PropertyExpression, HashSet<PropertyExpression>> = HashMap::new();
+        for (sub, sup) in inclusions {
+            direct.entry(sub.inverse()).or_default().insert(sup.inverse());
+            direct.entry(sub).or_default().insert(sup);
+        }
+
+        for property in direct.keys() {
+            let mut closure = HashSet::new();
+            let mut queue = VecDeque::from([property.clone()]);
+            while let Some(current) = queue.pop_front() {
+                if closure.insert(current.clone()) {
+                    queue.extend(direct.get(&current).into_iter().flatten().cloned());
+                }
+            }
+            self.super_properties.insert(property.clone(), closure);
+        }
+    }
+
+    /// R ⊑* S
+    pub fn is_sub_property(&self, sub: &PropertyExpression, sup: &PropertyExpression) -> bool {
+        sub == sup || self.super_properties.get(sub).map(|supers| supers.contains(sup)).unwrap_or(false)
+    }
+
+    pub fn optimizations(&self) -> TableauOptimizations {
+        self.optimizations
+    }
+
+    /// Number of axioms added to every node (the fewer, the faster)
+    pub fn global_axiom_count(&self) -> usize {
+        self.global.len()
+    }
+
+    /// Number of class names whose negation is unfolded from their definition
+    pub fn definitional_count(&self) -> usize {
+        self.negative_unfoldable.len()
+    }
+
+    fn cached(&self, concept: &ClassExpression) -> Option<bool> {
+        if !self.optimizations.caching {
+            return None;
+        }
+        self.cache.lock().get(concept).copied()
+    }
+
+    fn remember(&self, concept: ClassExpression, satisfiable: bool) {
+        if self.optimizations.caching {
+            self.cache.lock().insert(concept, satisfiable);
+        }
+    }
+
+    /// Expressions already shown to be unsatisfiable
+    fn known_unsatisfiable(&self) -> HashSet<ClassExpression> {
+        if !self.optimizations.caching {
+            return HashSet::new();
+        }
+        self.cache.lock().iter()
+            .filter(|(_, &satisfiable)| !satisfiable)
+            .map(|(concept, _)| concept.clone())
+            .collect()
+    }
+}
+
+/// Named classes occurring in `class`
+fn named_classes_in<'a>(class: &'a ClassExpression, names: &mut HashSet<&'a OwlIri>) {
+    match class {
+        ClassExpression::Named(iri) => {
+            names.insert(iri);
+        }
+        ClassExpression::IntersectionOf(classes) | ClassExpression::UnionOf(classes) => {
+            classes.iter().for_each(|c| named_classes_in(c, names));
+        }
+        ClassExpression::ComplementOf(inner) => named_classes_in(inner, names),
+        ClassExpression::SomeValuesFrom { class, .. } | ClassExpression::AllValuesFrom { class, .. } => {
+            named_classes_in(class, names)
+        }
+        ClassExpression::MinCardinality { class, .. }
+        | ClassExpression::MaxCardinality { class, .. }
+        | ClassExpression::ExactCardinality { class, .. } => {
+            if let Some(class) = class {
+                named_classes_in(class, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn flatten_intersection(conjuncts: Vec<ClassExpression>) -> Vec<ClassExpression> {
+    conjuncts.into_iter()
+        .flat_map(|c| match c {
+            ClassExpression::IntersectionOf(inner) => flatten_intersection(inner),
+            other => vec![other],
+        })
+        .collect()
+}
+
+/// Branch points a label or edge depends on (for dependency-directed backjumping)
+type DepSet = BTreeSet<usize>;
+
+fn union(a: &DepSet, b: &DepSet) -> DepSet {
+    a.union(b).copied().collect()
+}
+
+/// Node of the concept satisfiability completion tree
+#[derive(Debug, Clone)]
+struct SatNode {
+    labels: HashMap<ClassExpression, DepSet>,
+    parent: Option<usize>,
+    /// Properties on the edge parent → node
+    properties: HashMap<PropertyExpression, DepSet>,
+    /// Nodes this node must not be merged with
+    distinct: HashSet<usize>,
+    pruned: bool,
+}
+
+/// Nondeterministic choice
+enum SatBranch {
+    Label(usize, ClassExpression),
+    Merge { from: usize, into: usize },
+}
+
+/// Alternatives of a nondeterministic rule and the dependencies of its premises
+struct SatChoice {
+    branches: Vec<SatBranch>,
+    deps: DepSet,
+}
+
+enum SatOutcome {
+    Satisfiable,
+    /// Clash depending on these branch points
+    Clash(DepSet),
+}
+
+/// Per-test state shared by the tableau rules
+struct SatContext<'a> {
+    tbox: &'a TBoxIndex,
+    /// Labels that clash on their own (from the cache)
+    unsatisfiable: HashSet<ClassExpression>,
+}
+
+/// Completion tree for concept satisfiability (ALCHIQ without nominals)
+#[derive(Debug, Clone, Default)]
+struct SatGraph {
+    nodes: Vec<SatNode>,
+    /// Branch points on the path to this graph
+    level: usize,
+}
+
+impl SatGraph {
+    fn add_node(&mut self, parent: Option<usize>, property: Option<PropertyExpression>, label: ClassExpression, deps: DepSet, tbox: &TBoxIndex) -> Result<usize, OwlDlError> {
+        if self.nodes.len() >= MAX_SAT_NODES {
+            return Err(OwlDlError::ReasoningError(format!("Completion graph exceeded {} nodes", MAX_SAT_NODES)));
+        }
+        let mut labels: HashMap<ClassExpression, DepSet> = tbox.global.iter().map(|c| (c.clone(), deps.clone())).collect();
+        labels.insert(label, deps.clone());
+        self.nodes.push(SatNode {
+            labels,
+            parent,
+            properties: property.into_iter().map(|p| (p, deps.clone())).collect(),
+            distinct: HashSet::new(),
+            pruned: false,
+        });
+        Ok(self.nodes.len() - 1)
+    }
+
+    fn active(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(move |&i| !self.nodes[i].pruned)
+    }
+
+    /// `property`-neighbours of `node` (children, and the parent via inverse edges) with the edge's dependencies
+    fn neighbours(&self, node: usize, property: &PropertyExpression, tbox: &TBoxIndex) -> Vec<(usize, DepSet)> {
+        let matching = |properties: &HashMap<PropertyExpression, DepSet>, inverse: bool| {
+            properties.iter()
+                .filter(|(p, _)| {
+                    let p = if inverse { p.inverse() } else { (*p).clone() };
+                    tbox.is_sub_property(&p, property)
+                })
+                .map(|(_, deps)| deps.clone())
+                .reduce(|a, b| union(&a, &b))
+        };
+        let mut neighbours: Vec<(usize, DepSet)> = self.active()
+            .filter(|&child| self.nodes[child].parent == Some(node))
+            .filter_map(|child| matching(&self.nodes[child].properties, false).map(|deps| (child, deps)))
+            .collect();
+        if let Some(parent) = self.nodes[node].parent {
+            if let Some(deps) = matching(&self.nodes[node].properties, true) {
+                neighbours.push((parent, deps));
+            }
+        }
+        neighbours
+    }
+
+    /// Properties of every edge at `node`, seen from `node`
+    fn edges(&self, node: usize) -> Vec<(PropertyExpression, DepSet)> {
+        let mut edges: Vec<(PropertyExpression, DepSet)> = self.nodes[node].properties.iter()
+            .map(|(p, deps)| (p.inverse(), deps.clone()))
+            .collect();
+        for child in self.active().filter(|&child| self.nodes[child].parent == Some(node)) {
+            edges.extend(self.nodes[child].properties.iter().map(|(p, deps)| (p.clone(), deps.clone())));
+        }
+        edges
+    }
+
+    fn has(&self, node: usize, class: &Option<Box<ClassExpression>>) -> bool {
+        class.as_ref().map(|c| self.nodes[node].labels.contains_key(c.as_ref())).unwrap_or(true)
+    }
+
+    fn label_deps(&self, node: usize, class: &Option<Box<ClassExpression>>) -> DepSet {
+        class.as_ref()
+            .and_then(|c| self.nodes[node].labels.get(c.as_ref()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn add_label(&mut self, node: usize, class: ClassExpression, deps: DepSet) -> bool {
+        match self.nodes[node].labels.entry(class) {
+            std::collections::hash_map::Entry::Occupied(_) => false,
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(deps);
+                true
+            }
+        }
+    }
+
+    fn same_labels(&self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.nodes[a].labels, &self.nodes[b].labels);
+        a.len() == b.len() && a.keys().all(|label| b.contains_key(label))
+    }
+
+    /// Equality blocking: a node is blocked when an ancestor has the same label set
+    fn is_blocked(&self, node: usize) -> bool {
+        let mut ancestor = self.nodes[node].parent;
+        while let Some(current) = ancestor {
+            if self.same_labels(current, node) || self.is_blocked(current) {
+                return true;
+            }
+            ancestor = self.nodes[current].parent;
+        }
+        false
+    }
+
+    /// Largest set of pairwise distinct nodes among `candidates` (greedy)
+    fn distinct_count(&self, candidates: &[usize]) -> usize {
+        let mut chosen: Vec<usize> = Vec::new();
+        for &candidate in candidates {
+            if chosen.iter().all(|c| self.nodes[candidate].distinct.contains(c)) {
+                chosen.push(candidate);
+            }
+        }
+        chosen.len()
+    }
+
+    /// Dependencies of a clash at `node`, if any
+    fn clash(&self, node: usize, context: &SatContext) -> Option<DepSet> {
+        let labels = &self.nodes[node].labels;
+        if let Some(deps) = labels.get(&ClassExpression::Nothing) {
+            return Some(deps.clone());
+        }
+        labels.iter().find_map(|(label, deps)| {
+            if context.unsatisfiable.contains(label) {
+                return Some(deps.clone());
+            }
+            match label {
+                ClassExpression::ComplementOf(inner) => labels.get(inner.as_ref()).map(|other| union(deps, other)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Merge `from` into a sibling or into its parent's predecessor
+    fn merge(&mut self, from: usize, into: usize, deps: &DepSet) {
+        let source = self.nodes[from].clone();
+        for (label, label_deps) in source.labels {
+            self.nodes[into].labels.entry(label).or_insert_with(|| union(&label_deps, deps));
+        }
+        match source.parent {
+            Some(parent) if self.nodes[parent].parent == Some(into) => {
+                // 先行ノードへの統合: 辺 into → parent に逆プロパティを追加
+                for (property, edge_deps) in &source.properties {
+                    self.nodes[parent].properties.entry(property.inverse()).or_insert_with(|| union(edge_deps, deps));
+                }
+            }
+            _ => {
+                for (property, edge_deps) in &source.properties {
+                    self.nodes[into].properties.entry(property.clone()).or_insert_with(|| union(edge_deps, deps));
+                }
+            }
+        }
+        self.nodes[into].distinct.extend(source.distinct.iter().copied());
+        for node in &mut self.nodes {
+            if node.parent == Some(from) {
+                node.parent = Some(into);
+            }
+            if node.distinct.remove(&from) {
+                node.distinct.insert(into);
+            }
+        }
+        self.nodes[from].pruned = true;
+    }
+}
+
+impl DlTableauReasoner {
+    /// Use a chosen set of tableau optimizations (all are enabled by default)
+    pub fn with_optimizations(mut self, optimizations: TableauOptimizations) -> Self {
+        self.optimizations = optimizations;
+        self
+    }
+
+    pub fn optimizations(&self) -> TableauOptimizations {
+        self.optimizations
+    }
+
+    /// TBox index of `ontology` built with this reasoner's optimizations
+    ///
+    /// 充足可能性のキャッシュはインデックスに付くため、同じインデックスを使い回すほど効く
+    pub fn tbox(&self, ontology: &OwlDlOntology) -> Result<TBoxIndex, OwlDlError> {
+        TBoxIndex::from_ontology_with(ontology, self.optimizations)
+    }
+
+    /// Check whether `concept` is satisfiable with respect to the ontology's TBox
+    pub fn is_satisfiable(&self, ontology: &OwlDlOntology, concept: &ClassExpression) -> Result<bool, OwlDlError> {
+        let tbox = self.tbox(ontology)?;
+        self.is_satisfiable_in(&tbox, concept)
+    }
+
+    /// C1 ⊑ C2 iff C1 ⊓ ¬C2 is unsatisfiable
+    pub fn is_subsumed_by(&self, ontology: &OwlDlOntology, subclass: &ClassExpression, superclass: &ClassExpression) -> Result<bool, OwlDlError> {
+        let tbox = self.tbox(ontology)?;
+        self.is_subsumed_in(&tbox, subclass, superclass)
+    }
+
+    /// Satisfiability against a prebuilt TBox index
+    pub fn is_satisfiable_in(&self, tbox: &TBoxIndex, concept: &ClassExpression) -> Result<bool, OwlDlError> {
+        if concept.contains_nominal() {
+            return Err(OwlDlError::UnsupportedFeature("Nominals are not supported in satisfiability tests".to_string()));
+        }
+        let concept = concept.to_nnf();
+        if let Some(satisfiable) = tbox.cached(&concept) {
+            return Ok(satisfiable);
+        }
+
+        let context = SatContext { tbox, unsatisfiable: tbox.known_unsatisfiable() };
+        let mut graph = SatGraph::default();
+        graph.add_node(None, None, concept.clone(), DepSet::new(), tbox)?;
+        let satisfiable = matches!(Self::solve(graph, &context)?, SatOutcome::Satisfiable);
+        tbox.remember(concept, satisfiable);
+        Ok(satisfiable)
+    }
+
+    /// Subsumption against a prebuilt TBox index
+    pub fn is_subsumed_in(&self, tbox: &TBoxIndex, subclass: &ClassExpression, superclass: &ClassExpression) -> Result<bool, OwlDlError> {
+        let test = ClassExpression::IntersectionOf(vec![
+            subclass.clone(),
+            ClassExpression::ComplementOf(Box::new(superclass.clone())),
+        ]);
+        Ok(!self.is_satisfiable_in(tbox, &test)?)
+    }
+
+    fn solve(mut graph: SatGraph, context: &SatContext) -> Result<SatOutcome, OwlDlError> {
+        loop {
+            if let Err(deps) = Self::saturate(&mut graph, context) {
+                return Ok(SatOutcome::Clash(deps));
+            }
+
+            if let Some(choice) = Self::next_branches(&graph, context) {
+                // 分岐がなければ (≤ 制約を満たせない) 矛盾
+                let level = graph.level;
+                let mut branch_deps = choice.deps.clone();
+                branch_deps.insert(level);
+                let mut clash = choice.deps;
+                for branch in choice.branches {
+                    let mut candidate = graph.clone();
+                    candidate.level = level + 1;
+                    match branch {
+                        SatBranch::Label(node, class) => {
+                            candidate.add_label(node, class, branch_deps.clone());
+                        }
+                        SatBranch::Merge { from, into } => candidate.merge(from, into, &branch_deps),
+                    }
+                    match Self::solve(candidate, context)? {
+                        SatOutcome::Satisfiable => return Ok(SatOutcome::Satisfiable),
+                        // この分岐点に依存しない矛盾は、残りの選択肢を試しても解消しない
+                        SatOutcome::Clash(deps) if context.tbox.optimizations.backjumping && !deps.contains(&level) => {
+                            return Ok(SatOutcome::Clash(deps));
+                        }
+                        SatOutcome::Clash(deps) => clash.extend(deps.into_iter().filter(|&l| l != level)),
+                    }
+                }
+                return Ok(SatOutcome::Clash(clash));
+            }
+
+            if !Self::generate(&mut graph, context.tbox)? {
+                // 全規則が適用済みで矛盾なし
+                return Ok(SatOutcome::Satisfiable);
+            }
+        }
+    }
+
+    /// Apply deterministic rules (⊓, unfolding, ∀, domains) to saturation; the clash's dependencies on failure
+    fn saturate(graph: &mut SatGraph, context: &SatContext) -> Result<(), DepSet> {
+        let tbox = context.tbox;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let nodes: Vec<usize> = graph.active().collect();
+            for node in nodes {
+                if let Some(deps) = graph.clash(node, context) {
+                    return Err(deps);
+                }
+                let labels: Vec<(ClassExpression, DepSet)> = graph.nodes[node].labels.iter()
+                    .map(|(label, deps)| (label.clone(), deps.clone()))
+                    .collect();
+                for (label, deps) in labels {
+                    match label {
+                        ClassExpression::IntersectionOf(classes) => {
+                            for class in classes {
+                                changed |= graph.add_label(node, class, deps.clone());
+                            }
+                        }
+                        ClassExpression::Named(iri) => {
+                            for class in tbox.unfoldable.get(&iri).into_iter().flatten() {
+                                changed |= graph.add_label(node, class.clone(), deps.clone());
+                            }
+                        }
+                        ClassExpression::ComplementOf(inner) => {
+                            if let ClassExpression::Named(iri) = inner.as_ref() {
+                                if let Some(class) = tbox.negative_unfoldable.get(iri) {
+                                    changed |= graph.add_label(node, class.clone(), deps);
+                                }
+                            }
+                        }
+                        ClassExpression::AllValuesFrom { property, class } => {
+                            for (neighbour, edge_deps) in graph.neighbours(node, &property, tbox) {
+                                changed |= graph.add_label(neighbour, (*class).clone(), union(&deps, &edge_deps));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if !tbox.domains.is_empty() {
+                    for (property, edge_deps) in graph.edges(node) {
+                        for (domain_property, class) in &tbox.domains {
+                            if tbox.is_sub_property(&property, domain_property) {
+                                changed |= graph.add_label(node, class.clone(), edge_deps.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        match graph.active().find_map(|node| graph.clash(node, context)) {
+            Some(deps) => Err(deps),
+            None => Ok(()),
+        }
+    }
+
+    /// Next nondeterministic rule application (⊔, choose, ≤)
+    fn next_branches(graph: &SatGraph, context: &SatContext) -> Option<SatChoice> {
+        let tbox = context.tbox;
+        for node in graph.active() {
+            for (label, deps) in &graph.nodes[node].labels {
+                match label {
+                    ClassExpression::UnionOf(classes)
+                        if !classes.iter().any(|c| graph.nodes[node].labels.contains_key(c)) => {
+                        return Some(SatChoice {
+                            branches: classes.iter().map(|c| SatBranch::Label(node, c.clone())).collect(),
+                            deps: deps.clone(),
+                        });
+                    }
+                    ClassExpression::MaxCardinality { cardinality, property, class } => {
+                        let neighbours = graph.neighbours(node, property, tbox);
+
+                        // choose-rule: 各近傍は C か ¬C のどちらかに決める
+                        if let Some(class) = class {
+                            let complement = class.complement();
+                            for (neighbour, edge_deps) in &neighbours {
+                                let labels = &graph.nodes[*neighbour].labels;
+                                if !labels.contains_key(class.as_ref()) && !labels.contains_key(&complement) {
+                                    return Some(SatChoice {
+                                        branches: vec![
+                                            SatBranch::Label(*neighbour, (**class).clone()),
+                                            SatBranch::Label(*neighbour, complement),
+                                        ],
+                                        deps: union(deps, edge_deps),
+                                    });
+                                }
+                            }
+                        }
+
+                        // ≤-rule: 上限を超えた近傍を統合する (子ノードを兄弟または先行ノードへ)
+                        let matching: Vec<(usize, DepSet)> = neighbours.into_iter().filter(|(n, _)| graph.has(*n, class)).collect();
+                        if matching.len() > *cardinality as usize {
+                            let mut merges = Vec::new();
+                            let mut choice_deps = deps.clone();
+                            for (i, (from, edge_deps)) in matching.iter().enumerate() {
+                                choice_deps = union(&union(&choice_deps, edge_deps), &graph.label_deps(*from, class));
+                                if graph.nodes[*from].parent != Some(node) {
+                                    continue;
+                                }
+                                for (j, (into, _)) in matching.iter().enumerate() {
+                                    let is_target = j < i || graph.nodes[*into].parent != Some(node);
+                                    if into != from && is_target && !graph.nodes[*from].distinct.contains(into) {
+                                        merges.push(SatBranch::Merge { from: *from, into: *into });
+                                    }
+                                }
+                            }
+                            if merges.is_empty() {
+                                // 区別された後続の由来は追跡しないので、これまでの全分岐点に依存するとみなす
+                                choice_deps = (0..graph.level).collect();
+                            }
+                            return Some(SatChoice { branches: merges, deps: choice_deps });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// Apply one generating rule (∃, ≥) on an unblocked node; false when none applies
+    fn generate(graph: &mut SatGraph, tbox: &TBoxIndex) -> Result<bool, OwlDlError> {
+        let nodes: Vec<usize> = graph.active().collect();
+        for node in nodes {
+            if graph.is_blocked(node) {
+                continue;
+            }
+            let labels: Vec<(ClassExpression, DepSet)> = graph.nodes[node].labels.iter()
+                .map(|(label, deps)| (label.clone(), deps.clone()))
+                .collect();
+            for (label, deps) in labels {
+                match label {
+                    ClassExpression::SomeValuesFrom { property, class } => {
+                        let satisfied = graph.neighbours(node, &property, tbox).into_iter()
+                            .any(|(n, _)| graph.nodes[n].labels.contains_key(class.as_ref()));
+                        if !satisfied {
+                            graph.add_node(Some(node), Some(property), *class, deps, tbox)?;
+                            return Ok(true);
+                        }
+                    }
+                    ClassExpression::MinCardinality { cardinality, property, class } => {
+                        let matching: Vec<usize> = graph.neighbours(node, &property, tbox).into_iter()
+                            .map(|(n, _)| n)
+                            .filter(|&n| graph.has(n, &class))
+                            .collect();
+                        if graph.distinct_count(&matching) < cardinality as usize {
+                            let label = class.map(|c| *c).unwrap_or(ClassExpression::Thing);
+                            let mut created = Vec::new();
+                            for _ in 0..cardinality {
+                                created.push(graph.add_node(Some(node), Some(property.clone()), label.clone(), deps.clone(), tbox)?);
+                            }
+                            for &a in &created {
+                                for &b in &created {
+                                    if a != b {
+                                        graph.nodes[a].distinct.insert(b);
+                                    }
+                                }
+                            }
+                            return Ok(true);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,331 @@
+//! Detector backtesting
+//!
+//! ラベル付きの過去イベント (JSONL) を検知器に再生し、しきい値ごとに precision / recall / F1 と
+//! アラート件数を求める。デプロイ前にしきい値を選ぶための材料にする
+//!
+//! 1 行 1 点: `{"timestamp": 1700000000, "value": 42.0, "label": "login_failures", "anomaly": true}`
+//! - `label` ごとに独立した検知器を作り、時刻順に再生する
+//! - `anomaly` を省略した点は正常として扱う
+//! - 検知器がまだスコアを返さない点 (ウォームアップ中) はアラートなしとして数える
+
+use super::{AnomalyDetectorTrait, TimeSeriesPoint};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+use std::path::Path;
+
+/// Backtest errors
+#[derive(Debug, thiserror::Error)]
+pub enum BacktestError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid record on line {line}: {message}")]
+    InvalidRecord { line: usize, message: String },
+
+    #[error("No thresholds to evaluate")]
+    NoThresholds,
+}
+
+/// Historical point with its ground-truth label
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledPoint {
+    #[serde(flatten)]
+    pub point: TimeSeriesPoint,
+    /// Whether an analyst confirmed this point as anomalous
+    #[serde(default)]
+    pub anomaly: bool,
+}
+
+impl LabeledPoint {
+    pub fn new(point: TimeSeriesPoint, anomaly: bool) -> Self {
+        Self { point, anomaly }
+    }
+}
+
+/// Read labeled points from JSONL (blank lines are skipped)
+pub fn read_labeled_points(reader: impl BufRead) -> Result<Vec<LabeledPoint>, BacktestError> {
+    let mut points = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let point = serde_json::from_str(&line)
+            .map_err(|e| BacktestError::InvalidRecord { line: index + 1, message: e.to_string() })?;
+        points.push(point);
+    }
+    Ok(points)
+}
+
+/// Read labeled points from a JSONL file
+pub fn load_labeled_points(path: impl AsRef<Path>) -> Result<Vec<LabeledPoint>, BacktestError> {
+    let file = std::fs::File::open(path)?;
+    read_labeled_points(std::io::BufReader::new(file))
+}
+
+/// Detector quality and alert volume at one threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdMetrics {
+    pub threshold: f64,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub true_negatives: usize,
+    /// Points the detector did not score (warm-up), counted as not alerted
+    pub unscored: usize,
+    /// 0.0 when nothing was alerted
+    pub precision: f64,
+    /// 0.0 when the history has no labeled anomalies
+    pub recall: f64,
+    pub f1: f64,
+    pub alerts: usize,
+    /// Alerts per day of replayed history
+    pub alerts_per_day: f64,
+    pub alerts_by_label: BTreeMap<String, usize>,
+}
+
+impl ThresholdMetrics {
+    fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            true_positives: 0,
+            false_positives: 0,
+            false_negatives: 0,
+            true_negatives: 0,
+            unscored: 0,
+            precision: 0.0,
+            recall: 0.0,
+            f1: 0.0,
+            alerts: 0,
+            alerts_per_day: 0.0,
+            alerts_by_label: BTreeMap::new(),
+        }
+    }
+
+    fn record(&mut self, label: &str, alerted: bool, anomaly: bool) {
+        match (alerted, anomaly) {
+            (true, true) => self.true_positives += 1,
+            (true, false) => self.false_positives += 1,
+            (false, true) => self.false_negatives += 1,
+            (false, false) => self.true_negatives += 1,
+        }
+        if alerted {
+            self.alerts += 1;
+            *self.alerts_by_label.entry(label.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    fn finish(&mut self, span_secs: u64) {
+        let ratio = |numerator: usize, denominator: usize| {
+            if denominator == 0 { 0.0 } else { numerator as f64 / denominator as f64 }
+        };
+        self.precision = ratio(self.true_positives, self.true_positives + self.false_positives);
+        self.recall = ratio(self.true_positives, self.true_positives + self.false_negatives);
+        self.f1 = if self.precision + self.recall == 0.0 {
+            0.0
+        } else {
+            2.0 * self.precision * self.recall / (self.precision + self.recall)
+        };
+        self.alerts_per_day = self.alerts as f64 * 86_400.0 / span_secs.max(1) as f64;
+    }
+}
+
+/// Metrics of every evaluated threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub detector: String,
+    pub points: usize,
+    pub labeled_anomalies: usize,
+    /// Time between the first and last replayed point
+    pub span_secs: u64,
+    /// In ascending threshold order
+    pub thresholds: Vec<ThresholdMetrics>,
+}
+
+impl BacktestReport {
+    /// Threshold with the best F1 (ties go to the one raising fewer alerts)
+    pub fn best_f1(&self) -> Option<&ThresholdMetrics> {
+        self.thresholds.iter().max_by(|a, b| {
+            a.f1.partial_cmp(&b.f1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.alerts.cmp(&a.alerts))
+        })
+    }
+
+    /// Threshold with the best recall whose alert volume fits the budget
+    ///
+    /// 運用チームが捌ける件数を上限に、取りこぼしが最も少ないしきい値を選ぶ (同率なら F1 の高い方)
+    pub fn best_within_budget(&self, max_alerts_per_day: f64) -> Option<&ThresholdMetrics> {
+        self.thresholds.iter()
+            .filter(|metrics| metrics.alerts_per_day <= max_alerts_per_day)
+            .max_by(|a, b| {
+                a.recall.partial_cmp(&b.recall)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.f1.partial_cmp(&b.f1).unwrap_or(std::cmp::Ordering::Equal))
+            })
+    }
+
+    /// Plain-text table, one row per threshold
+    pub fn to_table(&self) -> String {
+        let mut table = format!(
+            "detector: {} ({} points, {} labeled anomalies)\n{:>10} {:>9} {:>7} {:>6} {:>7} {:>11}\n",
+            self.detector, self.points, self.labeled_anomalies,
+            "threshold", "precision", "recall", "f1", "alerts", "alerts/day",
+        );
+        for metrics in &self.thresholds {
+            table.push_str(&format!(
+                "{:>10.3} {:>9.3} {:>7.3} {:>6.3} {:>7} {:>11.2}\n",
+                metrics.threshold, metrics.precision, metrics.recall, metrics.f1,
+                metrics.alerts, metrics.alerts_per_day,
+            ));
+        }
+        table
+    }
+}
+
+type DetectorFactory = Box<dyn Fn(f64) -> Box<dyn AnomalyDetectorTrait>>;
+
+/// Replays labeled history through a detector at several thresholds
+///
+/// しきい値ごとに `factory` で新しい検知器を作るので、しきい値間で学習状態は共有されない
+pub struct Backtester {
+    detector: String,
+    factory: DetectorFactory,
+    thresholds: Vec<f64>,
+}
+
+impl Backtester {
+    /// `factory` builds a fresh detector for a threshold
+    pub fn new<F>(detector: &str, factory: F) -> Self
+    where
+        F: Fn(f64) -> Box<dyn AnomalyDetectorTrait> + 'static,
+    {
+        Self {
+            detector: detector.to_string(),
+            factory: Box::new(factory),
+            thresholds: Vec::new(),
+        }
+    }
+
+    pub fn with_thresholds(mut self, thresholds: impl IntoIterator<Item = f64>) -> Self {
+        self.thresholds.extend(thresholds);
+        self
+    }
+
+    /// Thresholds from `start` to `end` (inclusive) every `step`
+    pub fn with_threshold_range(mut self, start: f64, end: f64, step: f64) -> Self {
+        if step > 0.0 && end >= start {
+            let steps = ((end - start) / step + 1e-9).floor() as u64;
+            for i in 0..=steps {
+                self.thresholds.push(start + i as f64 * step);
+            }
+        }
+        self
+    }
+
+    pub fn run(&self, history: &[LabeledPoint]) -> Result<BacktestReport, BacktestError> {
+        let mut thresholds: Vec<f64> = self.thresholds.iter().copied().filter(|t| t.is_finite()).collect();
+        thresholds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        thresholds.dedup();
+        if thresholds.is_empty() {
+            return Err(BacktestError::NoThresholds);
+        }
+
+        let mut replay: Vec<&LabeledPoint> = history.iter().collect();
+        replay.sort_by_key(|labeled| labeled.point.timestamp);
+        let span_secs = match (replay.first(), replay.last()) {
+            (Some(first), Some(last)) => last.point.timestamp - first.point.timestamp,
+            _ => 0,
+        };
+
+        let metrics = thresholds.into_iter()
+            .map(|threshold| {
+                let mut metrics = ThresholdMetrics::new(threshold);
+                let mut detectors: HashMap<&str, Box<dyn AnomalyDetectorTrait>> = HashMap::new();
+                for labeled in &replay {
+                    let detector = detectors.entry(labeled.point.label.as_str())
+                        .or_insert_with(|| (self.factory)(threshold));
+                    let result = detector.add_point(labeled.point.clone());
+                    if result.is_none() {
+                        metrics.unscored += 1;
+                    }
+                    let alerted = result.is_some_and(|result| result.is_anomaly);
+                    metrics.record(&labeled.point.label, alerted, labeled.anomaly);
+                }
+                metrics.finish(span_secs);
+                metrics
+            })
+            .collect();
+
+        Ok(BacktestReport {
+            detector: self.detector.clone(),
+            points: replay.len(),
+            labeled_anomalies: replay.iter().filter(|labeled| labeled.anomaly).count(),
+            span_secs,
+            thresholds: metrics,
+        })
+    }
+}
+
+impl std::fmt::Debug for Backtester {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Backtester")
+            .field("detector", &self.detector)
+            .field("thresholds", &self.thresholds)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly_detection::StatisticalDetector;
+
+    /// 1 時間ごとの件数。25 時間目と 50 時間目が確認済みの異常
+    fn history() -> Vec<LabeledPoint> {
+        (0..60u64).map(|hour| {
+            let anomaly = hour == 25 || hour == 50;
+            let value = if anomaly { 100.0 } else { 10.0 + (hour % 5) as f64 };
+            LabeledPoint::new(TimeSeriesPoint { timestamp: hour * 3600, value, label: "login_failures".to_string() }, anomaly)
+        }).collect()
+    }
+
+    #[test]
+    fn test_backtest_reports_metrics_per_threshold() {
+        let report = Backtester::new("z_score", |threshold| Box::new(StatisticalDetector::new(20, threshold)))
+            .with_thresholds([100.0, 1.0, 3.0])
+            .run(&history())
+            .unwrap();
+
+        assert_eq!(report.points, 60);
+        assert_eq!(report.labeled_anomalies, 2);
+        let thresholds: Vec<f64> = report.thresholds.iter().map(|m| m.threshold).collect();
+        assert_eq!(thresholds, vec![1.0, 3.0, 100.0]);
+
+        // 低すぎるしきい値は誤検知が多く、高すぎるしきい値は何も検知しない
+        let (low, mid, high) = (&report.thresholds[0], &report.thresholds[1], &report.thresholds[2]);
+        assert_eq!(low.recall, 1.0);
+        assert!(low.false_positives > 0);
+        assert_eq!((mid.true_positives, mid.false_positives, mid.f1), (2, 0, 1.0));
+        assert_eq!((high.alerts, high.false_negatives, high.f1), (0, 2, 0.0));
+        assert_eq!(mid.unscored, 9);
+        assert_eq!(mid.alerts_by_label.get("login_failures"), Some(&2));
+
+        assert_eq!(report.best_f1().unwrap().threshold, 3.0);
+        assert_eq!(report.best_within_budget(1.0).unwrap().threshold, 3.0);
+        assert_eq!(report.to_table().lines().count(), 5);
+    }
+
+    #[test]
+    fn test_read_labeled_points_from_jsonl() {
+        let jsonl = "{\"timestamp\": 1, \"value\": 3.0, \"label\": \"dns\"}\n\n{\"timestamp\": 2, \"value\": 90.0, \"label\": \"dns\", \"anomaly\": true}\n";
+        let points = read_labeled_points(jsonl.as_bytes()).unwrap();
+        assert_eq!(points.len(), 2);
+        assert!(!points[0].anomaly && points[1].anomaly);
+
+        let err = read_labeled_points("{\"timestamp\": 1}\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, BacktestError::InvalidRecord { line: 1, .. }));
+        assert!(matches!(Backtester::new("z_score", |t| Box::new(StatisticalDetector::new(20, t))).run(&points), Err(BacktestError::NoThresholds)));
+    }
+}
@@ -0,0 +1,760 @@
+//! # ML-Based Anomaly Detection
+//!
+//! 時系列分析と統計的手法によるセキュリティイベント異常検知
+//! シャノン情報論に基づく効率的な異常検知アルゴリズム
+//! ラベル付き履歴による検知器のバックテスト ([`backtest`])
+
+pub mod backtest;
+
+pub use backtest::*;
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+/// 時系列データポイント
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSeriesPoint {
+    pub timestamp: u64,
+    pub value: f64,
+    pub label: String,
+}
+
+/// 異常検知結果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyResult {
+    pub timestamp: u64,
+    pub value: f64,
+    pub label: String,
+    pub score: f64,
+    pub threshold: f64,
+    pub is_anomaly: bool,
+    pub method: String,
+}
+
+/// 統計的異常検知器
+#[derive(Debug, Clone)]
+pub struct StatisticalDetector {
+    window_size: usize,
+    z_threshold: f64,
+    data: VecDeque<f64>,
+    timestamps: VecDeque<u64>,
+}
+
+impl StatisticalDetector {
+    pub fn new(window_size: usize, z_threshold: f64) -> Self {
+        Self {
+            window_size,
+            z_threshold,
+            data: VecDeque::with_capacity(window_size),
+            timestamps: VecDeque::with_capacity(window_size),
+        }
+    }
+
+    pub fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        // データを追加
+        self.data.push_back(point.value);
+        self.timestamps.push_back(point.timestamp);
+
+        // ウィンドウサイズを超えたら古いデータを削除
+        if self.data.len() > self.window_size {
+            self.data.pop_front();
+            self.timestamps.pop_front();
+        }
+
+        // 十分なデータがない場合は異常検知しない
+        if self.data.len() < 10 {
+            return None;
+        }
+
+        self.detect_anomaly(&point)
+    }
+
+    fn detect_anomaly(&self, point: &TimeSeriesPoint) -> Option<AnomalyResult> {
+        // 平均と標準偏差を計算
+        let (mean, std_dev) = self.calculate_stats()?;
+
+        if std_dev == 0.0 {
+            return None;
+        }
+
+        // Z-score計算
+        let z_score = (point.value - mean).abs() / std_dev;
+
+        let is_anomaly = z_score > self.z_threshold;
+        let threshold = mean + self.z_threshold * std_dev;
+
+        Some(AnomalyResult {
+            timestamp: point.timestamp,
+            value: point.value,
+            label: point.label.clone(),
+            score: z_score,
+            threshold,
+            is_anomaly,
+            method: "z_score".to_string(),
+        })
+    }
+
+    fn calculate_stats(&self) -> Option<(f64, f64)> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let n = self.data.len() as f64;
+        let mean = self.data.iter().sum::<f64>() / n;
+        let variance = self.data.iter()
+            .map(|x| (x - mean).powi(2))
+            .sum::<f64>() / n;
+        let std_dev = variance.sqrt();
+
+        Some((mean, std_dev))
+    }
+}
+
+/// IQR（四分位範囲）ベース異常検知器
+#[derive(Debug, Clone)]
+pub struct IQRDetector {
+    window_size: usize,
+    multiplier: f64,
+    data: VecDeque<f64>,
+}
+
+impl IQRDetector {
+    pub fn new(window_size: usize, multiplier: f64) -> Self {
+        Self {
+            window_size,
+            multiplier,
+            data: VecDeque::with_capacity(window_size),
+        }
+    }
+
+    pub fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.data.push_back(point.value);
+
+        if self.data.len() > self.window_size {
+            self.data.pop_front();
+        }
+
+        if self.data.len() < 10 {
+            return None;
+        }
+
+        self.detect_anomaly(&point)
+    }
+
+    fn detect_anomaly(&self, point: &TimeSeriesPoint) -> Option<AnomalyResult> {
+        let mut sorted_data: Vec<f64> = self.data.iter().cloned().collect();
+        sorted_data.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let q1_idx = (sorted_data.len() as f64 * 0.25) as usize;
+        let q3_idx = (sorted_data.len() as f64 * 0.75) as usize;
+
+        let q1 = sorted_data[q1_idx];
+        let q3 = sorted_data[q3_idx];
+        let iqr = q3 - q1;
+
+        let lower_bound = q1 - self.multiplier * iqr;
+        let upper_bound = q3 + self.multiplier * iqr;
+
+        let is_anomaly = point.value < lower_bound || point.value > upper_bound;
+        let threshold = if point.value > q3 { upper_bound } else { lower_bound };
+
+        Some(AnomalyResult {
+            timestamp: point.timestamp,
+            value: point.value,
+            label: point.label.clone(),
+            score: ((point.value - (q1 + q3) / 2.0) / iqr).abs(),
+            threshold,
+            is_anomaly,
+            method: "iqr".to_string(),
+        })
+    }
+}
+
+/// 指数移動平均ベースのトレンド検知器
+#[derive(Debug, Clone)]
+pub struct TrendDetector {
+    alpha: f64,
+    threshold: f64,
+    ema: Option<f64>,
+    trend_score: f64,
+    window_size: usize,
+    recent_values: VecDeque<f64>,
+}
+
+impl TrendDetector {
+    pub fn new(alpha: f64, threshold: f64, window_size: usize) -> Self {
+        Self {
+            alpha,
+            threshold,
+            ema: None,
+            trend_score: 0.0,
+            window_size,
+            recent_values: VecDeque::with_capacity(window_size),
+        }
+    }
+
+    pub fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.recent_values.push_back(point.value);
+        if self.recent_values.len() > self.window_size {
+            self.recent_values.pop_front();
+        }
+
+        // EMA更新
+        self.ema = Some(match self.ema {
+            None => point.value,
+            Some(ema) => self.alpha * point.value + (1.0 - self.alpha) * ema,
+        });
+
+        // トレンドスコア計算（最近の値とEMAの差）
+        if let Some(ema) = self.ema {
+            let recent_avg = self.recent_values.iter().sum::<f64>() / self.recent_values.len() as f64;
+            self.trend_score = (recent_avg - ema).abs() / ema.abs().max(1.0);
+        }
+
+        let is_anomaly = self.trend_score > self.threshold;
+
+        Some(AnomalyResult {
+            timestamp: point.timestamp,
+            value: point.value,
+            label: point.label.clone(),
+            score: self.trend_score,
+            threshold: self.threshold,
+            is_anomaly,
+            method: "trend".to_string(),
+        })
+    }
+}
+
+/// アンサンブル統合戦略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnsembleStrategy {
+    /// 正規化スコアの重み付き平均
+    WeightedAverage,
+    /// 正規化スコアの最大値
+    Max,
+    /// 重み付き多数決（異常と判定した検知器の重み比率）
+    MajorityVote,
+}
+
+impl EnsembleStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnsembleStrategy::WeightedAverage => "weighted_average",
+            EnsembleStrategy::Max => "max",
+            EnsembleStrategy::MajorityVote => "majority_vote",
+        }
+    }
+}
+
+/// 検知器ごとの寄与度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelContribution {
+    pub model: String,
+    pub method: String,
+    /// 検知器の生スコア
+    pub raw_score: f64,
+    /// 0.0〜1.0 に正規化したスコア
+    pub normalized_score: f64,
+    pub weight: f64,
+    /// アンサンブルスコアへの寄与分（全検知器の合計がアンサンブルスコアになる）
+    pub contribution: f64,
+    pub is_anomaly: bool,
+}
+
+/// アンサンブル異常検知結果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnsembleResult {
+    pub timestamp: u64,
+    pub value: f64,
+    pub label: String,
+    pub score: f64,
+    pub threshold: f64,
+    pub is_anomaly: bool,
+    pub strategy: EnsembleStrategy,
+    pub contributions: Vec<ModelContribution>,
+}
+
+impl EnsembleResult {
+    /// 寄与度の大きい順に検知器を返す
+    pub fn top_contributors(&self) -> Vec<&ModelContribution> {
+        let mut sorted: Vec<&ModelContribution> = self.contributions.iter().collect();
+        sorted.sort_by(|a, b| b.contribution.partial_cmp(&a.contribution).unwrap_or(std::cmp::Ordering::Equal));
+        sorted
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl From<&ModelContribution> for fukurow_streaming::AnomalyContribution {
+    fn from(contribution: &ModelContribution) -> Self {
+        Self {
+            model: contribution.model.clone(),
+            score: contribution.raw_score,
+            weight: contribution.weight,
+            contribution: contribution.contribution,
+            is_anomaly: contribution.is_anomaly,
+        }
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl EnsembleResult {
+    /// `AnomalyDetected` streaming event with the per-model contributions (metric = label)
+    pub fn to_streaming_event(&self) -> fukurow_streaming::StreamingEvent {
+        fukurow_streaming::StreamingEvent::AnomalyDetected {
+            score: self.score,
+            threshold: self.threshold,
+            metric: self.label.clone(),
+            timestamp: chrono::DateTime::from_timestamp(self.timestamp as i64, 0).unwrap_or_else(chrono::Utc::now),
+            strategy: Some(self.strategy.as_str().to_string()),
+            contributions: self.contributions.iter().map(Into::into).collect(),
+            entity: None,
+            window: None,
+        }
+    }
+}
+
+/// 統合異常検知器マネージャー
+#[derive(Debug)]
+pub struct AnomalyDetectorManager {
+    detectors: std::collections::HashMap<String, Box<dyn AnomalyDetectorTrait>>,
+    weights: std::collections::HashMap<String, f64>,
+    ensemble_threshold: f64,
+    strategy: EnsembleStrategy,
+}
+
+impl AnomalyDetectorManager {
+    pub fn new(ensemble_threshold: f64) -> Self {
+        Self {
+            detectors: std::collections::HashMap::new(),
+            weights: std::collections::HashMap::new(),
+            ensemble_threshold,
+            strategy: EnsembleStrategy::MajorityVote,
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: EnsembleStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn set_strategy(&mut self, strategy: EnsembleStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn add_detector(&mut self, name: String, detector: Box<dyn AnomalyDetectorTrait>) {
+        self.detectors.insert(name, detector);
+    }
+
+    /// 重み付きで検知器を追加
+    pub fn add_weighted_detector(&mut self, name: String, detector: Box<dyn AnomalyDetectorTrait>, weight: f64) {
+        self.weights.insert(name.clone(), weight.max(0.0));
+        self.detectors.insert(name, detector);
+    }
+
+    pub fn set_weight(&mut self, name: &str, weight: f64) {
+        self.weights.insert(name.to_string(), weight.max(0.0));
+    }
+
+    fn weight_of(&self, name: &str) -> f64 {
+        self.weights.get(name).copied().unwrap_or(1.0)
+    }
+
+    /// スコアを 0.0〜1.0 に正規化（検知器ごとにスケールが異なるため）
+    fn normalize_score(score: f64) -> f64 {
+        let score = score.abs();
+        if score.is_finite() { score / (1.0 + score) } else { 1.0 }
+    }
+
+    /// アンサンブルスコアと検知器ごとの寄与度を算出
+    pub fn detect_ensemble(&mut self, point: TimeSeriesPoint) -> Option<EnsembleResult> {
+        let mut names: Vec<String> = self.detectors.keys().cloned().collect();
+        names.sort();
+
+        let mut outputs = Vec::new();
+        for name in names {
+            let weight = self.weight_of(&name);
+            if let Some(detector) = self.detectors.get_mut(&name) {
+                if let Some(result) = detector.add_point(point.clone()) {
+                    outputs.push((name, weight, result));
+                }
+            }
+        }
+
+        if outputs.is_empty() {
+            return None;
+        }
+
+        let total_weight: f64 = outputs.iter().map(|(_, w, _)| *w).sum();
+        let max_idx = outputs.iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                Self::normalize_score(a.2.score)
+                    .partial_cmp(&Self::normalize_score(b.2.score))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(i, _)| i);
+
+        let contributions: Vec<ModelContribution> = outputs.into_iter()
+            .enumerate()
+            .map(|(i, (model, weight, result))| {
+                let normalized_score = Self::normalize_score(result.score);
+                let contribution = match self.strategy {
+                    _ if total_weight == 0.0 => 0.0,
+                    EnsembleStrategy::WeightedAverage => weight * normalized_score / total_weight,
+                    EnsembleStrategy::Max => if Some(i) == max_idx { normalized_score } else { 0.0 },
+                    EnsembleStrategy::MajorityVote => if result.is_anomaly { weight / total_weight } else { 0.0 },
+                };
+                ModelContribution {
+                    model,
+                    method: result.method,
+                    raw_score: result.score,
+                    normalized_score,
+                    weight,
+                    contribution,
+                    is_anomaly: result.is_anomaly,
+                }
+            })
+            .collect();
+
+        let score: f64 = contributions.iter().map(|c| c.contribution).sum();
+
+        Some(EnsembleResult {
+            timestamp: point.timestamp,
+            value: point.value,
+            label: point.label,
+            score,
+            threshold: self.ensemble_threshold,
+            is_anomaly: score >= self.ensemble_threshold,
+            strategy: self.strategy,
+            contributions,
+        })
+    }
+
+    pub fn detect_anomalies(&mut self, point: TimeSeriesPoint) -> Vec<AnomalyResult> {
+        let mut results = Vec::new();
+        let mut anomaly_count = 0;
+
+        // 各検知器で異常検知
+        for detector in self.detectors.values_mut() {
+            if let Some(result) = detector.add_point(point.clone()) {
+                results.push(result.clone());
+                if result.is_anomaly {
+                    anomaly_count += 1;
+                }
+            }
+        }
+
+        // アンサンブル判定：複数の検知器が異常と判定した場合
+        if anomaly_count as f64 >= self.ensemble_threshold * self.detectors.len() as f64 {
+            for result in &mut results {
+                result.is_anomaly = true;
+                result.method = format!("ensemble_{}", result.method);
+            }
+        }
+
+        results
+    }
+}
+
+/// 異常検知器トレイト
+pub trait AnomalyDetectorTrait: std::fmt::Debug + Send + Sync {
+    fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult>;
+}
+
+// トレイト実装
+impl AnomalyDetectorTrait for StatisticalDetector {
+    fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.add_point(point)
+    }
+}
+
+impl AnomalyDetectorTrait for IQRDetector {
+    fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.add_point(point)
+    }
+}
+
+impl AnomalyDetectorTrait for TrendDetector {
+    fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.add_point(point)
+    }
+}
+
+/// セキュリティイベント異常検知器
+#[derive(Debug)]
+pub struct SecurityAnomalyDetector {
+    manager: AnomalyDetectorManager,
+    event_counts: std::collections::HashMap<String, u64>,
+    /// 異常と判定したアンサンブル結果の送信先
+    #[cfg(feature = "streaming")]
+    event_sender: Option<fukurow_streaming::processor::EventSender>,
+}
+
+impl SecurityAnomalyDetector {
+    pub fn new() -> Self {
+        let mut manager = AnomalyDetectorManager::new(0.6); // 60%以上の検知器が異常と判定
+
+        // 統計的検知器（Z-score）
+        manager.add_detector(
+            "statistical".to_string(),
+            Box::new(StatisticalDetector::new(100, 3.0))
+        );
+
+        // IQR検知器
+        manager.add_detector(
+            "iqr".to_string(),
+            Box::new(IQRDetector::new(100, 1.5))
+        );
+
+        // トレンド検知器
+        manager.add_detector(
+            "trend".to_string(),
+            Box::new(TrendDetector::new(0.1, 0.3, 50))
+        );
+
+        Self {
+            manager,
+            event_counts: std::collections::HashMap::new(),
+            #[cfg(feature = "streaming")]
+            event_sender: None,
+        }
+    }
+
+    /// Emit anomalous ensemble results as `AnomalyDetected` events to `sender`
+    #[cfg(feature = "streaming")]
+    pub fn with_event_sender(mut self, sender: fukurow_streaming::processor::EventSender) -> Self {
+        self.event_sender = Some(sender);
+        self
+    }
+
+    pub fn analyze_event(&mut self, event_type: &str, count: u64) -> Vec<AnomalyResult> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let point = TimeSeriesPoint {
+            timestamp,
+            value: count as f64,
+            label: event_type.to_string(),
+        };
+
+        // イベントカウント更新
+        *self.event_counts.entry(event_type.to_string()).or_insert(0) = count;
+
+        self.manager.detect_anomalies(point)
+    }
+
+    /// アンサンブル結果（検知器ごとの寄与度付き）で分析
+    ///
+    /// 送信先が設定されていれば、異常と判定した結果を `AnomalyDetected` イベントとして送る
+    pub fn analyze_event_ensemble(&mut self, event_type: &str, count: u64) -> Option<EnsembleResult> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let point = TimeSeriesPoint {
+            timestamp,
+            value: count as f64,
+            label: event_type.to_string(),
+        };
+
+        *self.event_counts.entry(event_type.to_string()).or_insert(0) = count;
+
+        let result = self.manager.detect_ensemble(point)?;
+        #[cfg(feature = "streaming")]
+        if let (true, Some(sender)) = (result.is_anomaly, &self.event_sender) {
+            let _ = sender.send(result.to_streaming_event());
+        }
+        Some(result)
+    }
+
+    pub fn get_event_statistics(&self) -> std::collections::HashMap<String, u64> {
+        self.event_counts.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statistical_detector_normal() {
+        let mut detector = StatisticalDetector::new(10, 2.0);
+
+        // 正常データを追加
+        for i in 0..15 {
+            let point = TimeSeriesPoint {
+                timestamp: i as u64,
+                value: 10.0 + (i as f64 * 0.1),
+                label: "test".to_string(),
+            };
+            let result = detector.add_point(point);
+            if i >= 10 {
+                assert!(result.is_some());
+                assert!(!result.unwrap().is_anomaly);
+            }
+        }
+    }
+
+    #[test]
+    fn test_statistical_detector_anomaly() {
+        let mut detector = StatisticalDetector::new(10, 2.0);
+
+        // 正常データを追加
+        for i in 0..10 {
+            let point = TimeSeriesPoint {
+                timestamp: i as u64,
+                value: 10.0,
+                label: "test".to_string(),
+            };
+            detector.add_point(point);
+        }
+
+        // 異常データを追加
+        let anomaly_point = TimeSeriesPoint {
+            timestamp: 10,
+            value: 100.0, // 明らかに異常な値
+            label: "test".to_string(),
+        };
+
+        let result = detector.add_point(anomaly_point).unwrap();
+        assert!(result.is_anomaly);
+        assert_eq!(result.method, "z_score");
+    }
+
+    #[test]
+    fn test_security_anomaly_detector() {
+        let mut detector = SecurityAnomalyDetector::new();
+
+        // 正常なイベントを追加
+        for i in 0..10 {
+            let anomalies = detector.analyze_event("login_attempts", 5 + i);
+            assert!(anomalies.is_empty() || anomalies.iter().all(|a| !a.is_anomaly));
+        }
+
+        // 異常なイベントを追加
+        let anomalies = detector.analyze_event("login_attempts", 1000); // 明らかに異常
+        assert!(!anomalies.is_empty());
+
+        // アンサンブル判定で異常と判定されるはず
+        let has_anomaly = anomalies.iter().any(|a| a.is_anomaly);
+        assert!(has_anomaly, "Ensemble detection should catch anomaly");
+    }
+
+    fn ensemble_manager(strategy: EnsembleStrategy) -> AnomalyDetectorManager {
+        let mut manager = AnomalyDetectorManager::new(0.5).with_strategy(strategy);
+        manager.add_weighted_detector("statistical".to_string(), Box::new(StatisticalDetector::new(20, 3.0)), 2.0);
+        manager.add_weighted_detector("iqr".to_string(), Box::new(IQRDetector::new(20, 1.5)), 1.0);
+        manager.add_weighted_detector("trend".to_string(), Box::new(TrendDetector::new(0.1, 0.3, 10)), 1.0);
+
+        for i in 0..15 {
+            manager.detect_ensemble(TimeSeriesPoint {
+                timestamp: i,
+                value: 10.0 + (i % 3) as f64,
+                label: "test".to_string(),
+            });
+        }
+        manager
+    }
+
+    #[test]
+    fn test_ensemble_contributions_sum_to_score() {
+        for strategy in [EnsembleStrategy::WeightedAverage, EnsembleStrategy::Max, EnsembleStrategy::MajorityVote] {
+            let mut manager = ensemble_manager(strategy);
+            let result = manager.detect_ensemble(TimeSeriesPoint {
+                timestamp: 100,
+                value: 500.0,
+                label: "test".to_string(),
+            }).unwrap();
+
+            assert_eq!(result.strategy, strategy);
+            assert_eq!(result.contributions.len(), 3);
+            let sum: f64 = result.contributions.iter().map(|c| c.contribution).sum();
+            assert!((sum - result.score).abs() < 1e-9);
+            assert!(result.is_anomaly, "{:?} should flag the spike", strategy);
+        }
+    }
+
+    #[test]
+    fn test_ensemble_majority_vote_weights() {
+        let mut manager = ensemble_manager(EnsembleStrategy::MajorityVote);
+        let result = manager.detect_ensemble(TimeSeriesPoint {
+            timestamp: 100,
+            value: 500.0,
+            label: "test".to_string(),
+        }).unwrap();
+
+        let statistical = result.contributions.iter().find(|c| c.model == "statistical").unwrap();
+        assert!(statistical.is_anomaly);
+        assert_eq!(statistical.weight, 2.0);
+        assert!((statistical.contribution - 0.5).abs() < 1e-9);
+        assert_eq!(result.top_contributors()[0].model, "statistical");
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_anomalous_ensemble_results_are_streamed() {
+        use fukurow_streaming::{EventStreamProcessor, ShutdownSignal, StreamError, StreamProcessor, StreamingConfig, StreamingEvent};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Collector {
+            events: Arc<Mutex<Vec<StreamingEvent>>>,
+        }
+
+        #[async_trait::async_trait]
+        impl StreamProcessor for Collector {
+            async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+                self.events.lock().unwrap().push(event);
+                Ok(())
+            }
+
+            async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+                self.events.lock().unwrap().extend(events);
+                Ok(())
+            }
+
+            fn name(&self) -> &'static str {
+                "collector"
+            }
+
+            async fn health_check(&self) -> Result<(), StreamError> {
+                Ok(())
+            }
+        }
+
+        let collector = Collector::default();
+        let shutdown = ShutdownSignal::new();
+        let processor = EventStreamProcessor::new(collector.clone(), StreamingConfig::default())
+            .with_shutdown(shutdown.clone());
+        let mut detector = SecurityAnomalyDetector::new().with_event_sender(processor.event_sender());
+        let task = processor.start_processing().await.unwrap();
+
+        // 正常な値では送らず、異常と判定した結果だけを寄与度付きで送る
+        for i in 0..10 {
+            detector.analyze_event_ensemble("login_attempts", 5 + i % 3);
+        }
+        let result = detector.analyze_event_ensemble("login_attempts", 1000).unwrap();
+        assert!(result.is_anomaly);
+
+        shutdown.trigger();
+        task.await.unwrap();
+        let events = collector.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            StreamingEvent::AnomalyDetected { score, metric, strategy, contributions, .. } => {
+                assert_eq!(*score, result.score);
+                assert_eq!(metric, "login_attempts");
+                assert_eq!(strategy.as_deref(), Some("majority_vote"));
+                assert_eq!(contributions.len(), result.contributions.len());
+                let statistical = contributions.iter().find(|c| c.model == "statistical").unwrap();
+                assert!(statistical.is_anomaly);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
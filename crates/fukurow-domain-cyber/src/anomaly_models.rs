@@ -0,0 +1,798 @@
+//! # Trainable Anomaly Models
+//!
+//! 固定しきい値ではなく、ストア内の過去イベントからベースラインを学習する異常検知モデル。
+//! EWMA・季節性 Z スコア・Isolation Forest 風の 3 種を [`AnomalyModel`] として差し替え可能にし、
+//! 学習済みの状態は JSON で保存・復元できるので再起動後もベースラインを使い続けられる
+
+use crate::anomaly_detection::{AnomalyDetectorTrait, AnomalyResult, TimeSeriesPoint};
+use crate::patterns::{EventFilter, EventRecord};
+use fukurow_rules::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Anomaly model errors
+#[derive(Debug, thiserror::Error)]
+pub enum AnomalyModelError {
+    #[error("Not enough history to train {model}: {actual} points, need {required}")]
+    InsufficientData { model: String, required: usize, actual: usize },
+
+    #[error("Invalid parameter for {model}: {message}")]
+    InvalidParameter { model: String, message: String },
+
+    #[error("Unknown model: {0}")]
+    UnknownModel(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Pluggable anomaly model with a learned baseline
+///
+/// `train` でベースラインを作り直し、`observe` で 1 点ずつ更新する。
+/// `score` はベースラインを変更しないため、判定前の点で自分自身を学習してしまうことはない
+pub trait AnomalyModel: std::fmt::Debug + Send + Sync {
+    /// Model kind, used as the `method` of results
+    fn kind(&self) -> &'static str;
+
+    /// Replace the baseline with one learned from `history`
+    fn train(&mut self, history: &[TimeSeriesPoint]) -> Result<(), AnomalyModelError>;
+
+    /// Score a point against the baseline (`None` until enough data has been seen)
+    fn score(&self, point: &TimeSeriesPoint) -> Option<AnomalyResult>;
+
+    /// Fold a point into the baseline
+    fn observe(&mut self, point: &TimeSeriesPoint);
+
+    fn is_trained(&self) -> bool;
+
+    /// Serializable state for persistence
+    fn snapshot(&self) -> ModelSnapshot;
+
+    /// Score a point, then learn from it
+    fn detect(&mut self, point: &TimeSeriesPoint) -> Option<AnomalyResult> {
+        let result = self.score(point);
+        self.observe(point);
+        result
+    }
+}
+
+/// Persisted model state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum ModelSnapshot {
+    Ewma(EwmaModel),
+    SeasonalZScore(SeasonalZScoreModel),
+    IsolationForest(IsolationForestModel),
+}
+
+impl ModelSnapshot {
+    pub fn into_model(self) -> Box<dyn AnomalyModel> {
+        match self {
+            ModelSnapshot::Ewma(model) => Box::new(model),
+            ModelSnapshot::SeasonalZScore(model) => Box::new(model),
+            ModelSnapshot::IsolationForest(model) => Box::new(model),
+        }
+    }
+}
+
+fn result(point: &TimeSeriesPoint, score: f64, threshold: f64, is_anomaly: bool, method: &str) -> AnomalyResult {
+    AnomalyResult {
+        timestamp: point.timestamp,
+        value: point.value,
+        label: point.label.clone(),
+        score,
+        threshold,
+        is_anomaly,
+        method: method.to_string(),
+    }
+}
+
+/// Exponentially weighted moving mean and variance
+///
+/// 直近の値ほど重く扱うので、緩やかなトレンドには追従しつつ急な変化を検出する
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EwmaModel {
+    /// Smoothing factor in (0, 1]
+    pub alpha: f64,
+    /// Deviations (in standard deviations) above which a point is anomalous
+    pub threshold: f64,
+    /// Points required before scoring
+    pub warmup: u64,
+    /// Floor for the standard deviation, so a flat baseline still scores spikes
+    pub min_std_dev: f64,
+    mean: f64,
+    variance: f64,
+    count: u64,
+}
+
+impl EwmaModel {
+    pub fn new(alpha: f64, threshold: f64) -> Self {
+        Self {
+            alpha,
+            threshold,
+            warmup: 10,
+            min_std_dev: 1.0,
+            mean: 0.0,
+            variance: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn with_warmup(mut self, warmup: u64) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    pub fn with_min_std_dev(mut self, min_std_dev: f64) -> Self {
+        self.min_std_dev = min_std_dev;
+        self
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt().max(self.min_std_dev)
+    }
+}
+
+impl Default for EwmaModel {
+    fn default() -> Self {
+        Self::new(0.1, 3.0)
+    }
+}
+
+impl AnomalyModel for EwmaModel {
+    fn kind(&self) -> &'static str {
+        "ewma"
+    }
+
+    fn train(&mut self, history: &[TimeSeriesPoint]) -> Result<(), AnomalyModelError> {
+        if !(self.alpha > 0.0 && self.alpha <= 1.0) {
+            return Err(AnomalyModelError::InvalidParameter {
+                model: self.kind().to_string(),
+                message: format!("alpha must be in (0, 1], got {}", self.alpha),
+            });
+        }
+        if (history.len() as u64) < self.warmup {
+            return Err(AnomalyModelError::InsufficientData {
+                model: self.kind().to_string(),
+                required: self.warmup as usize,
+                actual: history.len(),
+            });
+        }
+
+        self.mean = 0.0;
+        self.variance = 0.0;
+        self.count = 0;
+        for point in history {
+            self.observe(point);
+        }
+        Ok(())
+    }
+
+    fn score(&self, point: &TimeSeriesPoint) -> Option<AnomalyResult> {
+        if !self.is_trained() {
+            return None;
+        }
+        let std_dev = self.std_dev();
+        let score = (point.value - self.mean).abs() / std_dev;
+        Some(result(point, score, self.mean + self.threshold * std_dev, score > self.threshold, self.kind()))
+    }
+
+    fn observe(&mut self, point: &TimeSeriesPoint) {
+        if self.count == 0 {
+            self.mean = point.value;
+            self.variance = 0.0;
+        } else {
+            let diff = point.value - self.mean;
+            let increment = self.alpha * diff;
+            self.mean += increment;
+            self.variance = (1.0 - self.alpha) * (self.variance + diff * increment);
+        }
+        self.count += 1;
+    }
+
+    fn is_trained(&self) -> bool {
+        self.count >= self.warmup.max(1)
+    }
+
+    fn snapshot(&self) -> ModelSnapshot {
+        ModelSnapshot::Ewma(self.clone())
+    }
+}
+
+/// Welford running mean/variance
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunningStats {
+    pub count: u64,
+    pub mean: f64,
+    m2: f64,
+}
+
+impl RunningStats {
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            (self.m2 / (self.count - 1) as f64).sqrt()
+        }
+    }
+}
+
+/// Z-score against the baseline of the same slot of a period
+///
+/// 既定では 1 日を 24 スロットに分け、時間帯ごとの平常値と比較する。
+/// 夜間のバッチ処理のような周期的な増加を異常として扱わない
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeasonalZScoreModel {
+    /// Length of one season in seconds
+    pub period_secs: u64,
+    /// Number of slots per season
+    pub slots: usize,
+    pub threshold: f64,
+    /// Samples a slot needs before it is scored
+    pub min_samples: u64,
+    /// Floor for the standard deviation of a slot
+    pub min_std_dev: f64,
+    stats: Vec<RunningStats>,
+}
+
+impl SeasonalZScoreModel {
+    pub fn new(period_secs: u64, slots: usize, threshold: f64) -> Self {
+        Self {
+            period_secs,
+            slots,
+            threshold,
+            min_samples: 3,
+            min_std_dev: 1.0,
+            stats: vec![RunningStats::default(); slots],
+        }
+    }
+
+    /// Hour-of-day seasonality
+    pub fn hourly() -> Self {
+        Self::new(86_400, 24, 3.0)
+    }
+
+    pub fn with_min_samples(mut self, min_samples: u64) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    pub fn with_min_std_dev(mut self, min_std_dev: f64) -> Self {
+        self.min_std_dev = min_std_dev;
+        self
+    }
+
+    pub fn slot_of(&self, timestamp: u64) -> usize {
+        if self.period_secs == 0 || self.slots == 0 {
+            return 0;
+        }
+        let offset = timestamp % self.period_secs;
+        ((offset as u128 * self.slots as u128) / self.period_secs as u128) as usize
+    }
+
+    pub fn slot_stats(&self, slot: usize) -> Option<&RunningStats> {
+        self.stats.get(slot)
+    }
+}
+
+impl Default for SeasonalZScoreModel {
+    fn default() -> Self {
+        Self::hourly()
+    }
+}
+
+impl AnomalyModel for SeasonalZScoreModel {
+    fn kind(&self) -> &'static str {
+        "seasonal_z_score"
+    }
+
+    fn train(&mut self, history: &[TimeSeriesPoint]) -> Result<(), AnomalyModelError> {
+        if self.period_secs == 0 || self.slots == 0 {
+            return Err(AnomalyModelError::InvalidParameter {
+                model: self.kind().to_string(),
+                message: "period_secs and slots must be positive".to_string(),
+            });
+        }
+        if history.is_empty() {
+            return Err(AnomalyModelError::InsufficientData { model: self.kind().to_string(), required: 1, actual: 0 });
+        }
+
+        self.stats = vec![RunningStats::default(); self.slots];
+        for point in history {
+            self.observe(point);
+        }
+        Ok(())
+    }
+
+    fn score(&self, point: &TimeSeriesPoint) -> Option<AnomalyResult> {
+        let stats = self.stats.get(self.slot_of(point.timestamp))?;
+        if stats.count < self.min_samples.max(1) {
+            return None;
+        }
+        let std_dev = stats.std_dev().max(self.min_std_dev);
+        let score = (point.value - stats.mean).abs() / std_dev;
+        Some(result(point, score, stats.mean + self.threshold * std_dev, score > self.threshold, self.kind()))
+    }
+
+    fn observe(&mut self, point: &TimeSeriesPoint) {
+        let slot = self.slot_of(point.timestamp);
+        if let Some(stats) = self.stats.get_mut(slot) {
+            stats.push(point.value);
+        }
+    }
+
+    fn is_trained(&self) -> bool {
+        self.stats.iter().any(|stats| stats.count >= self.min_samples.max(1))
+    }
+
+    fn snapshot(&self) -> ModelSnapshot {
+        ModelSnapshot::SeasonalZScore(self.clone())
+    }
+}
+
+/// Node of an isolation tree
+///
+/// 学習時にそのノードへ到達した値の範囲を持ち、範囲外の値はそのノードで分離されたとみなす
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IsolationNode {
+    pub size: usize,
+    pub min: f64,
+    pub max: f64,
+    pub split: Option<Box<IsolationSplit>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IsolationSplit {
+    pub threshold: f64,
+    pub left: IsolationNode,
+    pub right: IsolationNode,
+}
+
+impl IsolationNode {
+    fn build(values: &mut [f64], depth: usize, max_depth: usize, rng: &mut SplitMix64) -> Self {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mut node = Self { size: values.len(), min, max, split: None };
+        if values.len() <= 1 || depth >= max_depth || min >= max {
+            return node;
+        }
+
+        // (min, max] から選ぶので左右どちらも空にならない
+        let threshold = max - rng.next_f64() * (max - min);
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let pivot = values.partition_point(|value| *value < threshold);
+        let (left, right) = values.split_at_mut(pivot);
+        node.split = Some(Box::new(IsolationSplit {
+            threshold,
+            left: Self::build(left, depth + 1, max_depth, rng),
+            right: Self::build(right, depth + 1, max_depth, rng),
+        }));
+        node
+    }
+
+    fn path_length(&self, value: f64, depth: usize) -> f64 {
+        if value < self.min || value > self.max {
+            return depth as f64 + 1.0;
+        }
+        match &self.split {
+            Some(split) if value < split.threshold => split.left.path_length(value, depth + 1),
+            Some(split) => split.right.path_length(value, depth + 1),
+            None => depth as f64 + average_path_length(self.size),
+        }
+    }
+}
+
+/// Average path length of an unsuccessful BST search over `n` items
+fn average_path_length(n: usize) -> f64 {
+    const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+    match n {
+        0 | 1 => 0.0,
+        2 => 1.0,
+        n => {
+            let n = n as f64;
+            2.0 * ((n - 1.0).ln() + EULER_GAMMA) - 2.0 * (n - 1.0) / n
+        }
+    }
+}
+
+/// Deterministic generator so a trained forest is reproducible from its seed
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Isolation-forest style scorer over single values
+///
+/// 少ない分割で孤立する値ほど異常とみなす。スコアは 0〜1 で、0.5 付近が平常。
+/// 分布の形を仮定しないので、多峰性の値 (時間帯で変わる通信量など) にも使える。
+/// 学習はバッチのみで、`observe` ではベースラインを更新しない
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IsolationForestModel {
+    pub trees: usize,
+    /// Values sampled for each tree
+    pub sample_size: usize,
+    /// Score (0.0-1.0) above which a point is anomalous
+    pub threshold: f64,
+    pub seed: u64,
+    forest: Vec<IsolationNode>,
+    /// Sample size actually used (normalizes path lengths)
+    trained_sample_size: usize,
+}
+
+impl IsolationForestModel {
+    pub fn new(trees: usize, sample_size: usize, threshold: f64) -> Self {
+        Self {
+            trees,
+            sample_size,
+            threshold,
+            seed: 0x5EED_F0C0_1234_5678,
+            forest: Vec::new(),
+            trained_sample_size: 0,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Anomaly score in [0, 1] (`None` before training)
+    pub fn anomaly_score(&self, value: f64) -> Option<f64> {
+        if self.forest.is_empty() {
+            return None;
+        }
+        let mean_path = self.forest.iter().map(|tree| tree.path_length(value, 0)).sum::<f64>() / self.forest.len() as f64;
+        Some(2f64.powf(-mean_path / average_path_length(self.trained_sample_size)))
+    }
+}
+
+impl Default for IsolationForestModel {
+    fn default() -> Self {
+        Self::new(100, 256, 0.65)
+    }
+}
+
+impl AnomalyModel for IsolationForestModel {
+    fn kind(&self) -> &'static str {
+        "isolation_forest"
+    }
+
+    fn train(&mut self, history: &[TimeSeriesPoint]) -> Result<(), AnomalyModelError> {
+        if self.trees == 0 || self.sample_size < 2 {
+            return Err(AnomalyModelError::InvalidParameter {
+                model: self.kind().to_string(),
+                message: "trees must be positive and sample_size at least 2".to_string(),
+            });
+        }
+        let values: Vec<f64> = history.iter().map(|point| point.value).filter(|value| value.is_finite()).collect();
+        if values.len() < 2 {
+            return Err(AnomalyModelError::InsufficientData { model: self.kind().to_string(), required: 2, actual: values.len() });
+        }
+        let sample_size = self.sample_size.min(values.len());
+        let max_depth = (sample_size as f64).log2().ceil() as usize;
+        let mut rng = SplitMix64(self.seed);
+
+        self.forest = (0..self.trees).map(|_| {
+            // 部分的な Fisher-Yates で重複なしに抽出する
+            let mut pool = values.clone();
+            for i in 0..sample_size {
+                let j = i + (rng.next_u64() % (pool.len() - i) as u64) as usize;
+                pool.swap(i, j);
+            }
+            let sample = &mut pool[..sample_size];
+            IsolationNode::build(sample, 0, max_depth, &mut rng)
+        }).collect();
+        self.trained_sample_size = sample_size;
+        Ok(())
+    }
+
+    fn score(&self, point: &TimeSeriesPoint) -> Option<AnomalyResult> {
+        let score = self.anomaly_score(point.value)?;
+        Some(result(point, score, self.threshold, score > self.threshold, self.kind()))
+    }
+
+    fn observe(&mut self, _point: &TimeSeriesPoint) {}
+
+    fn is_trained(&self) -> bool {
+        !self.forest.is_empty()
+    }
+
+    fn snapshot(&self) -> ModelSnapshot {
+        ModelSnapshot::IsolationForest(self.clone())
+    }
+}
+
+// アンサンブル (AnomalyDetectorManager) にもそのまま組み込めるようにする
+impl AnomalyDetectorTrait for EwmaModel {
+    fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.detect(&point)
+    }
+}
+
+impl AnomalyDetectorTrait for SeasonalZScoreModel {
+    fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.detect(&point)
+    }
+}
+
+impl AnomalyDetectorTrait for IsolationForestModel {
+    fn add_point(&mut self, point: TimeSeriesPoint) -> Option<AnomalyResult> {
+        self.detect(&point)
+    }
+}
+
+/// How historical events in the store become a training series
+///
+/// フィルタに一致するイベントを `bucket_secs` ごとに数える。
+/// イベントのない区間も 0 件として含めるので、平常時の静けさも学習される
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineQuery {
+    pub label: String,
+    pub bucket_secs: u64,
+    pub filter: EventFilter,
+}
+
+impl BaselineQuery {
+    pub fn new(label: &str, bucket_secs: u64) -> Self {
+        Self {
+            label: label.to_string(),
+            bucket_secs,
+            filter: EventFilter::new(),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Event counts per bucket from the store's `CyberEvent`s
+    pub fn series(&self, store: &RdfStore) -> Vec<TimeSeriesPoint> {
+        self.series_from_events(&EventRecord::from_store(store))
+    }
+
+    pub fn series_from_events(&self, events: &[EventRecord]) -> Vec<TimeSeriesPoint> {
+        let bucket_secs = self.bucket_secs.max(1) as i64;
+        let mut counts: BTreeMap<i64, f64> = BTreeMap::new();
+        for event in events.iter().filter(|event| self.filter.matches(event)) {
+            *counts.entry(event.timestamp.div_euclid(bucket_secs)).or_insert(0.0) += 1.0;
+        }
+
+        let (first, last) = match (counts.keys().next(), counts.keys().next_back()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Vec::new(),
+        };
+        (first..=last)
+            .map(|bucket| TimeSeriesPoint {
+                timestamp: (bucket * bucket_secs).max(0) as u64,
+                value: counts.get(&bucket).copied().unwrap_or(0.0),
+                label: self.label.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Persisted form of a registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RegistrySnapshot {
+    version: u32,
+    models: BTreeMap<String, ModelSnapshot>,
+}
+
+const REGISTRY_FORMAT_VERSION: u32 = 1;
+
+/// Named, trained models with JSON persistence
+#[derive(Debug, Default)]
+pub struct AnomalyModelRegistry {
+    models: BTreeMap<String, Box<dyn AnomalyModel>>,
+}
+
+impl AnomalyModelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_model(mut self, name: &str, model: Box<dyn AnomalyModel>) -> Self {
+        self.register(name, model);
+        self
+    }
+
+    /// Add a model, replacing one of the same name
+    pub fn register(&mut self, name: &str, model: Box<dyn AnomalyModel>) {
+        self.models.insert(name.to_string(), model);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn AnomalyModel> {
+        self.models.get(name).map(|model| model.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.models.keys().map(String::as_str).collect()
+    }
+
+    fn model_mut(&mut self, name: &str) -> Result<&mut (dyn AnomalyModel + 'static), AnomalyModelError> {
+        self.models.get_mut(name)
+            .map(|model| model.as_mut())
+            .ok_or_else(|| AnomalyModelError::UnknownModel(name.to_string()))
+    }
+
+    pub fn train(&mut self, name: &str, history: &[TimeSeriesPoint]) -> Result<(), AnomalyModelError> {
+        self.model_mut(name)?.train(history)
+    }
+
+    /// Train a model on the store's history; returns the number of training points
+    pub fn train_from_store(&mut self, name: &str, query: &BaselineQuery, store: &RdfStore) -> Result<usize, AnomalyModelError> {
+        let series = query.series(store);
+        self.model_mut(name)?.train(&series)?;
+        Ok(series.len())
+    }
+
+    /// Score a point with a model and fold it into its baseline
+    pub fn detect(&mut self, name: &str, point: &TimeSeriesPoint) -> Result<Option<AnomalyResult>, AnomalyModelError> {
+        Ok(self.model_mut(name)?.detect(point))
+    }
+
+    pub fn to_json(&self) -> Result<String, AnomalyModelError> {
+        let snapshot = RegistrySnapshot {
+            version: REGISTRY_FORMAT_VERSION,
+            models: self.models.iter().map(|(name, model)| (name.clone(), model.snapshot())).collect(),
+        };
+        Ok(serde_json::to_string_pretty(&snapshot)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, AnomalyModelError> {
+        let snapshot: RegistrySnapshot = serde_json::from_str(json)?;
+        Ok(Self {
+            models: snapshot.models.into_iter().map(|(name, model)| (name, model.into_model())).collect(),
+        })
+    }
+
+    /// Write every model's baseline to `path` (via a temporary file, so a crash keeps the old file)
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AnomalyModelError> {
+        let path = path.as_ref();
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, self.to_json()?)?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AnomalyModelError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::test_support::record;
+
+    fn point(timestamp: u64, value: f64) -> TimeSeriesPoint {
+        TimeSeriesPoint { timestamp, value, label: "logins".to_string() }
+    }
+
+    fn steady_history() -> Vec<TimeSeriesPoint> {
+        (0..200).map(|i| point(i * 60, 10.0 + (i % 5) as f64)).collect()
+    }
+
+    #[test]
+    fn test_models_flag_spikes_after_training() {
+        let mut models: Vec<Box<dyn AnomalyModel>> = vec![
+            Box::new(EwmaModel::default()),
+            Box::new(SeasonalZScoreModel::new(3_600, 60, 3.0)),
+            Box::new(IsolationForestModel::default()),
+        ];
+        for model in &mut models {
+            assert!(model.score(&point(0, 12.0)).is_none(), "{} scored before training", model.kind());
+            model.train(&steady_history()).unwrap();
+            assert!(model.is_trained());
+
+            let normal = model.score(&point(200 * 60, 12.0)).unwrap();
+            assert!(!normal.is_anomaly, "{} flagged a normal value: {:?}", model.kind(), normal);
+            let spike = model.score(&point(200 * 60, 500.0)).unwrap();
+            assert!(spike.is_anomaly, "{} missed a spike: {:?}", model.kind(), spike);
+            assert_eq!(spike.method, model.kind());
+        }
+    }
+
+    #[test]
+    fn test_seasonal_model_uses_slot_baseline() {
+        // 毎時 0 分台だけ 100 件、それ以外は 5 件前後
+        let history: Vec<TimeSeriesPoint> = (0..24 * 12)
+            .map(|i| {
+                let timestamp = i * 300;
+                point(timestamp, if timestamp % 3_600 < 300 { 100.0 + (i % 3) as f64 } else { 5.0 + (i % 2) as f64 })
+            })
+            .collect();
+        let mut model = SeasonalZScoreModel::new(3_600, 12, 3.0);
+        model.train(&history).unwrap();
+
+        assert!(!model.score(&point(86_400, 101.0)).unwrap().is_anomaly);
+        assert!(model.score(&point(86_400 + 600, 101.0)).unwrap().is_anomaly);
+    }
+
+    #[test]
+    fn test_training_errors() {
+        let mut ewma = EwmaModel::default();
+        assert!(matches!(ewma.train(&steady_history()[..3]), Err(AnomalyModelError::InsufficientData { .. })));
+        let mut invalid = EwmaModel::new(1.5, 3.0);
+        assert!(matches!(invalid.train(&steady_history()), Err(AnomalyModelError::InvalidParameter { .. })));
+
+        let mut registry = AnomalyModelRegistry::new();
+        assert!(matches!(registry.train("missing", &steady_history()), Err(AnomalyModelError::UnknownModel(_))));
+    }
+
+    #[test]
+    fn test_baseline_series_fills_empty_buckets() {
+        let events = vec![
+            record(0, &[("user", "alice"), ("success", "false")]),
+            record(30, &[("user", "alice"), ("success", "false")]),
+            record(200, &[("user", "alice"), ("success", "false")]),
+            record(210, &[("user", "alice"), ("success", "true")]),
+        ];
+        let query = BaselineQuery::new("failed_logins", 60)
+            .with_filter(EventFilter::new().where_field("success", "false"));
+
+        let series = query.series_from_events(&events);
+        let values: Vec<f64> = series.iter().map(|point| point.value).collect();
+        assert_eq!(values, vec![2.0, 0.0, 0.0, 1.0]);
+        assert_eq!(series[3].timestamp, 180);
+        assert!(series.iter().all(|point| point.label == "failed_logins"));
+    }
+
+    #[test]
+    fn test_registry_persistence_round_trip() {
+        let mut registry = AnomalyModelRegistry::new()
+            .with_model("ewma", Box::new(EwmaModel::default()))
+            .with_model("seasonal", Box::new(SeasonalZScoreModel::new(3_600, 60, 3.0)))
+            .with_model("forest", Box::new(IsolationForestModel::new(20, 64, 0.65)));
+        for name in ["ewma", "seasonal", "forest"] {
+            registry.train(name, &steady_history()).unwrap();
+        }
+
+        let path = std::env::temp_dir().join(format!("fukurow-anomaly-models-{}.json", uuid::Uuid::new_v4()));
+        registry.save(&path).unwrap();
+        let restored = AnomalyModelRegistry::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.names(), vec!["ewma", "forest", "seasonal"]);
+        for name in restored.names() {
+            let original = registry.get(name).unwrap();
+            let loaded = restored.get(name).unwrap();
+            assert_eq!(loaded.kind(), original.kind());
+            assert!(loaded.is_trained());
+            for value in [12.0, 500.0] {
+                let (before, after) = (original.score(&point(200 * 60, value)).unwrap(), loaded.score(&point(200 * 60, value)).unwrap());
+                assert!((before.score - after.score).abs() < 1e-9, "{} score changed after reload", name);
+                assert_eq!(before.is_anomaly, after.is_anomaly);
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! MITRE ATT&CK technique annotation
+//!
+//! 同梱の ATT&CK タクソノミー ([`ATTACK_TAXONOMY`]) から戦術・技術を読み、
+//! ルールが宣言した技術 ID (`Rule::attack_techniques`) をアラートに付与する。
+//! 有効なルールが 1 つ以上ある技術を数え、SOC 向けのカバレッジを算出する
+
+use fukurow_core::model::{SecurityAction, ATTACK_DETAILS_KEY};
+use fukurow_store::bootstrap::{BootstrapConfig, Bootstrapper, ATTACK_TAXONOMY};
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Namespace of the ATT&CK classes and properties
+pub const ATTACK_NAMESPACE: &str = "http://example.org/attack/";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+
+/// ATT&CK tactic (`TA0006` Credential Access)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tactic {
+    pub id: String,
+    pub name: String,
+    pub iri: String,
+}
+
+/// ATT&CK technique (`T1110` Brute Force)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Technique {
+    pub id: String,
+    pub name: String,
+    pub iri: String,
+    /// Tactic IDs the technique belongs to
+    pub tactics: Vec<String>,
+}
+
+/// Technique IDs that do not exist in the taxonomy
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown ATT&CK techniques: {}", .0.join(", "))]
+pub struct UnknownTechniques(pub Vec<String>);
+
+/// Tactics and techniques of the ATT&CK ontology
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttackTaxonomy {
+    /// Bundle version the taxonomy was read from
+    pub version: Option<String>,
+    tactics: BTreeMap<String, Tactic>,
+    techniques: BTreeMap<String, Technique>,
+}
+
+impl AttackTaxonomy {
+    /// Taxonomy of the ATT&CK bundle shipped with the store
+    pub fn bundled() -> Self {
+        let mut store = RdfStore::new();
+        let config = BootstrapConfig::new()
+            .with_skip("cyber")
+            .with_skip("rdfs-owl")
+            .with_skip("shapes");
+        // 同梱データのパースはストアのテストで保証されている
+        Bootstrapper::new(config).run(&mut store).expect("bundled ATT&CK taxonomy parses");
+        Self::from_store(&store)
+    }
+
+    /// Taxonomy of the `attack:Tactic` / `attack:Technique` instances in `store`
+    ///
+    /// 起動時にブートストラップしたストアや、新しいリリースを読み込んだストアからそのまま作れる
+    pub fn from_store(store: &RdfStore) -> Self {
+        let tactics = instances(store, "Tactic")
+            .map(|(iri, id)| (id.clone(), Tactic { name: label(store, &iri).unwrap_or_else(|| id.clone()), id, iri }))
+            .collect();
+        let techniques = instances(store, "Technique")
+            .map(|(iri, id)| {
+                let mut tactics: Vec<String> = store.find_triples(Some(&iri), Some(&format!("{}tactic", ATTACK_NAMESPACE)), None)
+                    .into_iter()
+                    .filter_map(|stored| external_id(store, &stored.triple.object))
+                    .collect();
+                tactics.sort();
+                tactics.dedup();
+                (id.clone(), Technique { name: label(store, &iri).unwrap_or_else(|| id.clone()), id, iri, tactics })
+            })
+            .collect();
+
+        Self {
+            version: Bootstrapper::installed_version(store, &ATTACK_TAXONOMY),
+            tactics,
+            techniques,
+        }
+    }
+
+    /// Technique by ID; sub-techniques (`T1110.003`) fall back to their parent
+    pub fn technique(&self, id: &str) -> Option<&Technique> {
+        let id = id.trim().to_ascii_uppercase();
+        self.techniques.get(&id)
+            .or_else(|| id.split_once('.').and_then(|(parent, _)| self.techniques.get(parent)))
+    }
+
+    pub fn tactic(&self, id: &str) -> Option<&Tactic> {
+        self.tactics.get(&id.trim().to_ascii_uppercase())
+    }
+
+    pub fn techniques(&self) -> impl Iterator<Item = &Technique> {
+        self.techniques.values()
+    }
+
+    pub fn tactics(&self) -> impl Iterator<Item = &Tactic> {
+        self.tactics.values()
+    }
+
+    /// Check that every ID names a known technique (for validating rule mappings)
+    pub fn validate(&self, ids: &[String]) -> Result<(), UnknownTechniques> {
+        let unknown: Vec<String> = ids.iter().filter(|id| self.technique(id).is_none()).cloned().collect();
+        if unknown.is_empty() { Ok(()) } else { Err(UnknownTechniques(unknown)) }
+    }
+
+    /// Tag an alert with `techniques` and add their names and tactics
+    ///
+    /// 既に付いている技術 ID (ルールが付けたもの) もまとめて名前と戦術を解決する
+    pub fn annotate(&self, action: SecurityAction, techniques: &[String]) -> SecurityAction {
+        let mut action = action.with_attack_techniques(techniques);
+        let ids = action.attack_techniques();
+        let annotation = match &mut action {
+            SecurityAction::Alert { details, .. } => details.get_mut(ATTACK_DETAILS_KEY).and_then(|a| a.as_object_mut()),
+            _ => None,
+        };
+        if let Some(annotation) = annotation {
+            let known: Vec<&Technique> = ids.iter().filter_map(|id| self.technique(id)).collect();
+            let names: BTreeMap<&str, &str> = known.iter().map(|t| (t.id.as_str(), t.name.as_str())).collect();
+            let tactics: BTreeSet<&str> = known.iter().flat_map(|t| t.tactics.iter().map(String::as_str)).collect();
+            annotation.insert("names".to_string(), serde_json::json!(names));
+            annotation.insert("tactics".to_string(), serde_json::json!(tactics));
+            if let Some(version) = &self.version {
+                annotation.insert("version".to_string(), serde_json::json!(version));
+            }
+        }
+        action
+    }
+
+    /// Coverage of the taxonomy by rule → technique mappings
+    ///
+    /// `mappings` は `RuleRegistry::attack_technique_mappings` の形 (有効なルールだけを渡す)
+    pub fn coverage(&self, mappings: &BTreeMap<String, Vec<String>>) -> AttackCoverage {
+        let mut rules_by_technique: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        let mut unknown: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (rule, techniques) in mappings {
+            for id in techniques {
+                match self.technique(id) {
+                    Some(technique) => {
+                        let rules = rules_by_technique.entry(technique.id.as_str()).or_default();
+                        if !rules.contains(rule) {
+                            rules.push(rule.clone());
+                        }
+                    }
+                    None => unknown.entry(rule.clone()).or_default().push(id.clone()),
+                }
+            }
+        }
+
+        let techniques: Vec<TechniqueCoverage> = self.techniques.values()
+            .map(|technique| TechniqueCoverage {
+                id: technique.id.clone(),
+                name: technique.name.clone(),
+                tactics: technique.tactics.clone(),
+                rules: rules_by_technique.get(technique.id.as_str()).cloned().unwrap_or_default(),
+            })
+            .collect();
+        let tactics = self.tactics.values()
+            .map(|tactic| {
+                let in_tactic: Vec<&TechniqueCoverage> = techniques.iter().filter(|t| t.tactics.contains(&tactic.id)).collect();
+                TacticCoverage {
+                    id: tactic.id.clone(),
+                    name: tactic.name.clone(),
+                    covered: in_tactic.iter().filter(|t| t.is_covered()).count(),
+                    total: in_tactic.len(),
+                }
+            })
+            .collect();
+
+        AttackCoverage {
+            version: self.version.clone(),
+            covered: techniques.iter().filter(|t| t.is_covered()).count(),
+            total: techniques.len(),
+            techniques,
+            tactics,
+            unknown,
+        }
+    }
+}
+
+/// Rules detecting one technique
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TechniqueCoverage {
+    pub id: String,
+    pub name: String,
+    pub tactics: Vec<String>,
+    /// Active rules mapped to the technique (or one of its sub-techniques)
+    pub rules: Vec<String>,
+}
+
+impl TechniqueCoverage {
+    pub fn is_covered(&self) -> bool {
+        !self.rules.is_empty()
+    }
+}
+
+/// Covered techniques of one tactic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TacticCoverage {
+    pub id: String,
+    pub name: String,
+    pub covered: usize,
+    pub total: usize,
+}
+
+/// Which techniques have at least one active rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttackCoverage {
+    pub version: Option<String>,
+    pub covered: usize,
+    pub total: usize,
+    pub techniques: Vec<TechniqueCoverage>,
+    pub tactics: Vec<TacticCoverage>,
+    /// Rule → technique IDs missing from the taxonomy
+    pub unknown: BTreeMap<String, Vec<String>>,
+}
+
+/// `(iri, external ID)` of every instance of `attack:<class>`
+fn instances<'a>(store: &'a RdfStore, class: &str) -> impl Iterator<Item = (String, String)> + 'a {
+    store.find_triples(None, Some(RDF_TYPE), Some(&format!("{}{}", ATTACK_NAMESPACE, class)))
+        .into_iter()
+        .filter_map(move |stored| {
+            let iri = stored.triple.subject.to_string();
+            external_id(store, &iri).map(|id| (iri, id))
+        })
+}
+
+fn external_id(store: &RdfStore, iri: &str) -> Option<String> {
+    store.find_triples(Some(iri), Some(&format!("{}externalId", ATTACK_NAMESPACE)), None)
+        .first()
+        .map(|stored| stored.triple.object_term().value().to_string())
+}
+
+fn label(store: &RdfStore, iri: &str) -> Option<String> {
+    store.find_triples(Some(iri), Some(RDFS_LABEL), None)
+        .first()
+        .map(|stored| stored.triple.object_term().value().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_taxonomy() {
+        let taxonomy = AttackTaxonomy::bundled();
+        assert_eq!(taxonomy.version.as_deref(), Some("14.1"));
+
+        let brute_force = taxonomy.technique("T1110").unwrap();
+        assert_eq!(brute_force.name, "Brute Force");
+        assert_eq!(brute_force.tactics, vec!["TA0006"]);
+        assert_eq!(taxonomy.technique("t1110.003").unwrap().id, "T1110");
+        assert_eq!(taxonomy.tactic("TA0006").unwrap().name, "Credential Access");
+
+        assert!(taxonomy.validate(&["T1078".to_string()]).is_ok());
+        assert_eq!(taxonomy.validate(&["T9999".to_string()]), Err(UnknownTechniques(vec!["T9999".to_string()])));
+    }
+
+    #[test]
+    fn test_annotate_alert() {
+        let taxonomy = AttackTaxonomy::bundled();
+        let alert = SecurityAction::Alert {
+            severity: "high".to_string(),
+            message: "Brute force".to_string(),
+            details: serde_json::json!({ "user": "alice" }),
+        };
+
+        let annotated = taxonomy.annotate(alert, &["T1110".to_string(), "T1078".to_string()]);
+        let SecurityAction::Alert { details, .. } = annotated else { panic!("expected an alert") };
+        let attack = &details[ATTACK_DETAILS_KEY];
+        assert_eq!(attack["techniques"], serde_json::json!(["T1110", "T1078"]));
+        assert_eq!(attack["names"]["T1110"], "Brute Force");
+        assert_eq!(attack["tactics"], serde_json::json!(["TA0001", "TA0003", "TA0004", "TA0005", "TA0006"]));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let taxonomy = AttackTaxonomy::bundled();
+        let mappings = BTreeMap::from([
+            ("ssh_brute_force".to_string(), vec!["T1110.001".to_string()]),
+            ("password_spray".to_string(), vec!["T1110".to_string(), "T9999".to_string()]),
+        ]);
+
+        let coverage = taxonomy.coverage(&mappings);
+        assert_eq!(coverage.covered, 1);
+        assert_eq!(coverage.total, taxonomy.techniques().count());
+        let brute_force = coverage.techniques.iter().find(|t| t.id == "T1110").unwrap();
+        assert_eq!(brute_force.rules, vec!["password_spray", "ssh_brute_force"]);
+        let credential_access = coverage.tactics.iter().find(|t| t.id == "TA0006").unwrap();
+        assert_eq!(credential_access.covered, 1);
+        assert_eq!(coverage.unknown["password_spray"], vec!["T9999"]);
+    }
+}
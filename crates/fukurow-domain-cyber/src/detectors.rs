@@ -228,6 +228,11 @@ impl Rule for MaliciousIpRule {
         "malicious_ip_detection"
     }
 
+    fn attack_techniques(&self) -> Vec<String> {
+        // 既知の悪性 IP への通信は C2 チャネルとして扱う
+        vec!["T1071".to_string()]
+    }
+
     fn description(&self) -> &'static str {
         "Detect connections to known malicious IPs"
     }
@@ -282,3 +287,120 @@ impl Rule for MaliciousIpRule {
         true
     }
 }
+
+/// Rule that alerts when a known sensor has gone quiet
+pub struct StaleSensorRule {
+    /// Silence threshold in milliseconds
+    stale_after_ms: u64,
+}
+
+impl StaleSensorRule {
+    pub fn new(stale_after_ms: u64) -> Self {
+        Self { stale_after_ms }
+    }
+
+    /// Alerts for sensors silent longer than the threshold as of `now` (ms)
+    pub fn evaluate(&self, store: &RdfStore, now: u64) -> Vec<SecurityAction> {
+        store.sensor_registry()
+            .stale(now, self.stale_after_ms)
+            .into_iter()
+            .map(|health| SecurityAction::Alert {
+                severity: if health.silent_for_ms > self.stale_after_ms.saturating_mul(4) { "high" } else { "medium" }.to_string(),
+                message: format!("Sensor {} has been silent for {}s", health.record.source, health.silent_for_ms / 1000),
+                details: serde_json::json!({
+                    "sensor": health.record.source,
+                    "last_seen": health.record.last_seen,
+                    "silent_for_ms": health.silent_for_ms,
+                    "threshold_ms": self.stale_after_ms,
+                    "detection_method": "heartbeat"
+                }),
+            })
+            .collect()
+    }
+}
+
+impl Default for StaleSensorRule {
+    fn default() -> Self {
+        // 既定では 15 分間観測がないセンサーを停止とみなす
+        Self::new(15 * 60 * 1000)
+    }
+}
+
+#[async_trait]
+impl Rule for StaleSensorRule {
+    fn name(&self) -> &'static str {
+        "stale_sensor_detection"
+    }
+
+    fn description(&self) -> &'static str {
+        "Detect sensors that stopped reporting"
+    }
+
+    fn priority(&self) -> i32 {
+        5
+    }
+
+    async fn apply(&self, store: &RdfStore) -> Result<RuleResult, RuleError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        Ok(RuleResult {
+            triples_to_add: vec![],
+            triples_to_remove: vec![],
+            actions: self.evaluate(store, now),
+            violations: vec![],
+            metadata: std::collections::HashMap::new(),
+        })
+    }
+
+    fn should_apply(&self, store: &RdfStore) -> bool {
+        !store.sensor_registry().is_empty()
+    }
+}
+
+/// Built-in cyber security rules with default settings
+///
+/// イベント検知 ([`crate::event_detection_rules`]) と停止したセンサーの検知をまとめて登録できる
+pub fn cyber_rules() -> Vec<Box<dyn Rule>> {
+    let mut rules = crate::event_detectors::event_detection_rules();
+    rules.push(Box::new(StaleSensorRule::default()));
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_store::provenance::{GraphId, Provenance};
+
+    #[test]
+    fn test_stale_sensor_rule() {
+        let mut store = RdfStore::new();
+        let triple = Triple { subject: "e1".to_string(), predicate: "p".to_string(), object: "o".to_string() };
+        store.insert_at(triple.clone(), GraphId::Default, Provenance::Sensor { source: "edr-001".to_string(), confidence: None }, 0);
+        store.insert_at(triple, GraphId::Default, Provenance::Sensor { source: "edr-002".to_string(), confidence: None }, 50_000);
+
+        let rule = StaleSensorRule::new(60_000);
+        let actions = rule.evaluate(&store, 100_000);
+        assert_eq!(actions.len(), 1);
+        match &actions[0] {
+            SecurityAction::Alert { severity, details, .. } => {
+                assert_eq!(severity, "medium");
+                assert_eq!(details["sensor"], "edr-001");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+
+        assert_eq!(rule.evaluate(&store, 1_000_000).len(), 2);
+        assert!(rule.should_apply(&store));
+    }
+
+    #[test]
+    fn test_cyber_rules_include_stale_sensor_detection() {
+        let rules = cyber_rules();
+        let names: Vec<&str> = rules.iter().map(|rule| rule.name()).collect();
+        assert!(names.contains(&"stale_sensor_detection"));
+        assert_eq!(names.len(), crate::event_detection_rules().len() + 1);
+    }
+}
@@ -0,0 +1,724 @@
+//! Detections for DNS, HTTP, registry and email events
+//!
+//! DNS クエリ・HTTP リクエスト・レジストリ変更・メール受信イベントに対する
+//! 固定ヒューリスティックを `DetectionPattern` として実装する。
+//! いずれも `compile()` で単独の `Rule` になり、`event_detection_rules` でまとめて登録できる
+//! (停止したセンサーの検知も含めた組み込みルールは `cyber_rules`)
+
+use crate::patterns::{Detection, DetectionPattern, EventRecord, PatternError};
+use fukurow_rules::Rule;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+/// Brands most often imitated by phishing and typosquatting domains
+const DEFAULT_PROTECTED_DOMAINS: &[&str] = &[
+    "microsoft.com",
+    "office.com",
+    "google.com",
+    "apple.com",
+    "amazon.com",
+    "paypal.com",
+];
+
+/// Look for DGA-style and lookalike domains in DNS queries
+///
+/// 登録ドメインは末尾 2 ラベルとして扱う (`co.jp` のような公開サフィックスは考慮しない)
+#[derive(Debug, Clone)]
+pub struct SuspiciousDomainPattern {
+    protected_domains: Vec<String>,
+    allowed_domains: Vec<String>,
+    min_dga_length: usize,
+    min_entropy: f64,
+    max_edit_distance: usize,
+}
+
+impl SuspiciousDomainPattern {
+    pub fn new() -> Self {
+        Self {
+            protected_domains: DEFAULT_PROTECTED_DOMAINS.iter().map(|d| d.to_string()).collect(),
+            allowed_domains: Vec::new(),
+            min_dga_length: 12,
+            min_entropy: 3.5,
+            max_edit_distance: 1,
+        }
+    }
+
+    /// Add a domain whose lookalikes should be reported
+    pub fn with_protected_domain(mut self, domain: &str) -> Self {
+        self.protected_domains.push(domain.to_ascii_lowercase());
+        self
+    }
+
+    /// Never report this domain or its subdomains
+    pub fn with_allowed_domain(mut self, domain: &str) -> Self {
+        self.allowed_domains.push(domain.to_ascii_lowercase());
+        self
+    }
+
+    /// Shortest registrable label considered for DGA detection
+    pub fn with_min_dga_length(mut self, min_dga_length: usize) -> Self {
+        self.min_dga_length = min_dga_length;
+        self
+    }
+
+    /// Shannon entropy (bits per character) above which a label looks random
+    pub fn with_min_entropy(mut self, min_entropy: f64) -> Self {
+        self.min_entropy = min_entropy;
+        self
+    }
+
+    pub fn with_max_edit_distance(mut self, max_edit_distance: usize) -> Self {
+        self.max_edit_distance = max_edit_distance;
+        self
+    }
+
+    /// Reason the domain is suspicious, if any
+    pub fn classify(&self, domain: &str) -> Option<(&'static str, serde_json::Value)> {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        if self.allowed_domains.iter().any(|allowed| is_same_or_subdomain(&domain, allowed)) {
+            return None;
+        }
+
+        if let Some(target) = lookalike_target(&domain, &self.protected_domains, self.max_edit_distance) {
+            return Some(("lookalike", serde_json::json!({ "imitates": target })));
+        }
+
+        let label = registrable_label(&domain);
+        if label.chars().count() < self.min_dga_length {
+            return None;
+        }
+        let entropy = shannon_entropy(label);
+        let consonant_run = longest_consonant_run(label);
+        let digit_ratio = label.chars().filter(|c| c.is_ascii_digit()).count() as f64 / label.len() as f64;
+        if entropy >= self.min_entropy || consonant_run >= 6 || digit_ratio >= 0.3 {
+            return Some(("dga", serde_json::json!({
+                "entropy": entropy,
+                "consonant_run": consonant_run,
+                "digit_ratio": digit_ratio,
+            })));
+        }
+        None
+    }
+}
+
+impl Default for SuspiciousDomainPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DetectionPattern for SuspiciousDomainPattern {
+    fn name(&self) -> &'static str {
+        "suspicious_domain"
+    }
+
+    fn description(&self) -> &'static str {
+        "DNS lookup of an algorithmically generated or lookalike domain"
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        vec!["T1071".to_string()]
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        if self.min_dga_length == 0 {
+            return Err(PatternError::invalid(self.name(), "min_dga_length must be at least 1"));
+        }
+        if self.min_entropy.is_nan() || self.min_entropy <= 0.0 {
+            return Err(PatternError::invalid(self.name(), "min_entropy must be positive"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        for event in events {
+            let query = match event.field("queryName") {
+                Some(query) => query,
+                None => continue,
+            };
+            if let Some((kind, mut details)) = self.classify(query) {
+                details["kind"] = serde_json::json!(kind);
+                details["domain"] = serde_json::json!(query);
+                detections.push(Detection {
+                    pattern: self.name().to_string(),
+                    severity: if kind == "lookalike" { "high" } else { "medium" }.to_string(),
+                    message: format!("Suspicious {} domain queried: {}", kind, query),
+                    group: event.field("sourceIP").unwrap_or_default().to_string(),
+                    events: vec![event.id.clone()],
+                    timestamp: event.timestamp,
+                    details,
+                });
+            }
+        }
+        detections
+    }
+}
+
+/// Look for scripted clients, scanners and missing user agents in HTTP requests
+#[derive(Debug, Clone)]
+pub struct SuspiciousUserAgentPattern {
+    /// Lower-case substring → severity
+    signatures: Vec<(String, String)>,
+    flag_empty: bool,
+}
+
+impl SuspiciousUserAgentPattern {
+    pub fn new() -> Self {
+        let mut pattern = Self { signatures: Vec::new(), flag_empty: true };
+        for tool in ["curl/", "wget/", "python-requests", "python-urllib", "go-http-client", "powershell", "libwww-perl"] {
+            pattern = pattern.with_signature(tool, "low");
+        }
+        for scanner in ["sqlmap", "nikto", "nmap", "masscan", "zgrab", "nuclei", "dirbuster", "gobuster"] {
+            pattern = pattern.with_signature(scanner, "high");
+        }
+        pattern
+    }
+
+    /// Report user agents containing `substring` (case-insensitive)
+    pub fn with_signature(mut self, substring: &str, severity: &str) -> Self {
+        self.signatures.push((substring.to_ascii_lowercase(), severity.to_string()));
+        self
+    }
+
+    /// Whether requests without a user agent are reported
+    pub fn with_flag_empty(mut self, flag_empty: bool) -> Self {
+        self.flag_empty = flag_empty;
+        self
+    }
+}
+
+impl Default for SuspiciousUserAgentPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DetectionPattern for SuspiciousUserAgentPattern {
+    fn name(&self) -> &'static str {
+        "suspicious_user_agent"
+    }
+
+    fn description(&self) -> &'static str {
+        "HTTP request from a scripting tool, scanner or without a user agent"
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        vec!["T1595".to_string()]
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        if self.signatures.iter().any(|(signature, _)| signature.is_empty()) {
+            return Err(PatternError::invalid(self.name(), "user agent signatures must not be empty"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        for event in events.iter().filter(|event| event.field("httpMethod").is_some()) {
+            let user_agent = event.field("userAgent").unwrap_or_default();
+            let normalized = user_agent.trim().to_ascii_lowercase();
+
+            let matched = if normalized.is_empty() || normalized == "-" {
+                if self.flag_empty { Some(("<empty>", "medium")) } else { None }
+            } else {
+                self.signatures.iter()
+                    .find(|(signature, _)| normalized.contains(signature.as_str()))
+                    .map(|(signature, severity)| (signature.as_str(), severity.as_str()))
+            };
+
+            if let Some((signature, severity)) = matched {
+                detections.push(Detection {
+                    pattern: self.name().to_string(),
+                    severity: severity.to_string(),
+                    message: format!("Suspicious user agent from {}: {}", event.field("sourceIP").unwrap_or("unknown"), user_agent),
+                    group: event.field("sourceIP").unwrap_or_default().to_string(),
+                    events: vec![event.id.clone()],
+                    timestamp: event.timestamp,
+                    details: serde_json::json!({
+                        "user_agent": user_agent,
+                        "signature": signature,
+                        "url": event.field("url"),
+                        "host": event.field("host"),
+                    }),
+                });
+            }
+        }
+        detections
+    }
+}
+
+/// Look for writes to registry keys that start programs automatically
+#[derive(Debug, Clone)]
+pub struct RegistryAutorunPattern {
+    /// Lower-case key path fragments (backslash separated)
+    autorun_keys: Vec<String>,
+}
+
+impl RegistryAutorunPattern {
+    pub fn new() -> Self {
+        let mut pattern = Self { autorun_keys: Vec::new() };
+        for key in [
+            r"\software\microsoft\windows\currentversion\run",
+            r"\software\microsoft\windows\currentversion\runonce",
+            r"\software\microsoft\windows\currentversion\runservices",
+            r"\software\microsoft\windows\currentversion\policies\explorer\run",
+            r"\software\wow6432node\microsoft\windows\currentversion\run",
+            r"\software\microsoft\windows nt\currentversion\winlogon",
+            r"\software\microsoft\windows nt\currentversion\image file execution options",
+            r"\software\microsoft\windows\currentversion\explorer\shell folders",
+            r"\software\microsoft\windows\currentversion\explorer\user shell folders",
+            r"\system\currentcontrolset\services",
+        ] {
+            pattern = pattern.with_autorun_key(key);
+        }
+        pattern
+    }
+
+    /// Treat keys below `key_path` as autorun locations
+    pub fn with_autorun_key(mut self, key_path: &str) -> Self {
+        self.autorun_keys.push(normalize_key(key_path));
+        self
+    }
+
+    /// Autorun location the key belongs to
+    pub fn autorun_location(&self, key_path: &str) -> Option<&str> {
+        let key = normalize_key(key_path);
+        self.autorun_keys.iter()
+            .filter(|autorun| key.ends_with(autorun.as_str()) || key.contains(&format!("{}\\", autorun)))
+            .max_by_key(|autorun| autorun.len())
+            .map(String::as_str)
+    }
+}
+
+impl Default for RegistryAutorunPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DetectionPattern for RegistryAutorunPattern {
+    fn name(&self) -> &'static str {
+        "registry_autorun"
+    }
+
+    fn description(&self) -> &'static str {
+        "Registry change to an autorun or persistence location"
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        vec!["T1547".to_string()]
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        if self.autorun_keys.is_empty() {
+            return Err(PatternError::invalid(self.name(), "at least one autorun key is required"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        for event in events {
+            let key_path = match event.field("registryKey") {
+                Some(key_path) => key_path,
+                None => continue,
+            };
+            // 読み取りや削除は永続化にならないため対象外
+            let operation = event.field("registryOperation").unwrap_or_default().to_ascii_lowercase();
+            if matches!(operation.as_str(), "read" | "query" | "open" | "delete") {
+                continue;
+            }
+            let location = match self.autorun_location(key_path) {
+                Some(location) => location,
+                None => continue,
+            };
+            // Winlogon は Shell / Userinit の書き換えのみが自動起動に関わる
+            if location.ends_with(r"\winlogon") {
+                let value_name = event.field("registryValueName").unwrap_or_default().to_ascii_lowercase();
+                if !matches!(value_name.as_str(), "shell" | "userinit") {
+                    continue;
+                }
+            }
+
+            detections.push(Detection {
+                pattern: self.name().to_string(),
+                severity: "high".to_string(),
+                message: format!("Autorun registry key modified by {}: {}", event.field("user").unwrap_or("unknown"), key_path),
+                group: event.field("user").unwrap_or_default().to_string(),
+                events: vec![event.id.clone()],
+                timestamp: event.timestamp,
+                details: serde_json::json!({
+                    "key_path": key_path,
+                    "location": location,
+                    "operation": operation,
+                    "value_name": event.field("registryValueName"),
+                    "value_data": event.field("registryValueData"),
+                    "process_id": event.field("processId"),
+                }),
+            });
+        }
+        detections
+    }
+}
+
+/// Score received email against common phishing indicators
+///
+/// 指標 (Reply-To 不一致・危険な添付・二重拡張子・IP 直書き URL・なりすましドメイン・
+/// 緊急性を煽る件名) が `min_indicators` 個以上そろったメールを検知する
+#[derive(Debug, Clone)]
+pub struct PhishingPattern {
+    protected_domains: Vec<String>,
+    risky_extensions: Vec<String>,
+    urgent_keywords: Vec<String>,
+    min_indicators: usize,
+}
+
+impl PhishingPattern {
+    pub fn new() -> Self {
+        Self {
+            protected_domains: DEFAULT_PROTECTED_DOMAINS.iter().map(|d| d.to_string()).collect(),
+            risky_extensions: ["exe", "scr", "js", "jse", "vbs", "vbe", "hta", "bat", "cmd", "ps1", "lnk", "iso", "img", "docm", "xlsm", "one"]
+                .iter().map(|ext| ext.to_string()).collect(),
+            urgent_keywords: ["urgent", "immediately", "verify your account", "password expire", "suspended", "unusual sign-in", "invoice", "payment overdue"]
+                .iter().map(|keyword| keyword.to_string()).collect(),
+            min_indicators: 2,
+        }
+    }
+
+    pub fn with_protected_domain(mut self, domain: &str) -> Self {
+        self.protected_domains.push(domain.to_ascii_lowercase());
+        self
+    }
+
+    pub fn with_risky_extension(mut self, extension: &str) -> Self {
+        self.risky_extensions.push(extension.trim_start_matches('.').to_ascii_lowercase());
+        self
+    }
+
+    pub fn with_urgent_keyword(mut self, keyword: &str) -> Self {
+        self.urgent_keywords.push(keyword.to_ascii_lowercase());
+        self
+    }
+
+    pub fn with_min_indicators(mut self, min_indicators: usize) -> Self {
+        self.min_indicators = min_indicators;
+        self
+    }
+
+    /// Indicators present in one email event
+    pub fn indicators(&self, event: &EventRecord) -> Vec<(&'static str, String)> {
+        let mut indicators = Vec::new();
+
+        let sender_domain = event.field("sender").and_then(email_domain);
+        if let (Some(sender), Some(reply_to)) = (sender_domain.as_deref(), event.field("replyTo").and_then(email_domain)) {
+            if registrable_domain(sender) != registrable_domain(&reply_to) {
+                indicators.push(("reply_to_mismatch", reply_to));
+            }
+        }
+        if let Some(sender) = sender_domain.as_deref() {
+            if let Some(target) = lookalike_target(sender, &self.protected_domains, 1) {
+                indicators.push(("lookalike_sender", format!("{} imitates {}", sender, target)));
+            }
+        }
+
+        for attachment in event.values("attachment") {
+            let lower = attachment.to_ascii_lowercase();
+            let extensions: Vec<&str> = lower.rsplit('.').take_while(|part| part.len() <= 4).collect();
+            if let Some(extension) = extensions.first() {
+                if self.risky_extensions.iter().any(|risky| risky == extension) {
+                    indicators.push(("risky_attachment", attachment.to_string()));
+                }
+            }
+            // "invoice.pdf.exe" のように偽装用の拡張子を挟んだもの
+            if extensions.len() >= 2 && lower.matches('.').count() >= 2 {
+                indicators.push(("double_extension", attachment.to_string()));
+            }
+        }
+
+        for url in event.values("url") {
+            let host = match url_host(url) {
+                Some(host) => host,
+                None => continue,
+            };
+            if host.parse::<IpAddr>().is_ok() {
+                indicators.push(("ip_url", url.to_string()));
+            } else if lookalike_target(&host, &self.protected_domains, 1).is_some() {
+                indicators.push(("lookalike_url", url.to_string()));
+            }
+        }
+
+        let subject = event.field("emailSubject").unwrap_or_default().to_ascii_lowercase();
+        if let Some(keyword) = self.urgent_keywords.iter().find(|keyword| subject.contains(keyword.as_str())) {
+            indicators.push(("urgent_subject", keyword.clone()));
+        }
+
+        indicators
+    }
+}
+
+impl Default for PhishingPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DetectionPattern for PhishingPattern {
+    fn name(&self) -> &'static str {
+        "phishing_indicators"
+    }
+
+    fn description(&self) -> &'static str {
+        "Received email carrying several phishing indicators"
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        vec!["T1566".to_string()]
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        if self.min_indicators == 0 {
+            return Err(PatternError::invalid(self.name(), "min_indicators must be at least 1"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        for event in events.iter().filter(|event| event.field("recipient").is_some() && event.field("sender").is_some()) {
+            let indicators = self.indicators(event);
+            if indicators.len() < self.min_indicators {
+                continue;
+            }
+            let mut by_kind: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for (kind, evidence) in &indicators {
+                by_kind.entry(*kind).or_default().push(evidence.as_str());
+            }
+            detections.push(Detection {
+                pattern: self.name().to_string(),
+                severity: if indicators.len() >= self.min_indicators + 2 { "high" } else { "medium" }.to_string(),
+                message: format!(
+                    "Possible phishing email from {} to {}",
+                    event.field("sender").unwrap_or_default(),
+                    event.field("recipient").unwrap_or_default()
+                ),
+                group: event.field("recipient").unwrap_or_default().to_string(),
+                events: vec![event.id.clone()],
+                timestamp: event.timestamp,
+                details: serde_json::json!({
+                    "sender": event.field("sender"),
+                    "subject": event.field("emailSubject"),
+                    "indicator_count": indicators.len(),
+                    "indicators": by_kind,
+                }),
+            });
+        }
+        detections
+    }
+}
+
+/// Rules for the DNS, HTTP, registry and email detections with default settings
+pub fn event_detection_rules() -> Vec<Box<dyn Rule>> {
+    let compiled = [
+        SuspiciousDomainPattern::new().compile(),
+        SuspiciousUserAgentPattern::new().compile(),
+        RegistryAutorunPattern::new().compile(),
+        PhishingPattern::new().compile(),
+    ];
+    compiled.into_iter()
+        .map(|rule| rule.expect("default event detection patterns are valid"))
+        .collect()
+}
+
+fn normalize_key(key_path: &str) -> String {
+    let key = key_path.replace('/', "\\").to_ascii_lowercase();
+    let key = key.trim_end_matches('\\');
+    if key.starts_with('\\') { key.to_string() } else { format!("\\{}", key) }
+}
+
+fn email_domain(address: &str) -> Option<String> {
+    let address = address.trim().trim_end_matches('>');
+    address.rsplit_once('@').map(|(_, domain)| domain.to_ascii_lowercase()).filter(|domain| !domain.is_empty())
+}
+
+/// Host part of a URL (no scheme, credentials or port)
+fn url_host(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit_once('@').map(|(_, host)| host).unwrap_or(authority);
+    let host = if let Some(bracketed) = host.strip_prefix('[') {
+        bracketed.split(']').next()?
+    } else {
+        host.split(':').next()?
+    };
+    if host.is_empty() { None } else { Some(host.to_ascii_lowercase()) }
+}
+
+fn is_same_or_subdomain(domain: &str, parent: &str) -> bool {
+    domain == parent || domain.ends_with(&format!(".{}", parent))
+}
+
+/// Last two labels of the domain
+fn registrable_domain(domain: &str) -> &str {
+    match domain.rmatch_indices('.').nth(1) {
+        Some((index, _)) => &domain[index + 1..],
+        None => domain,
+    }
+}
+
+/// Label just left of the top-level domain
+fn registrable_label(domain: &str) -> &str {
+    registrable_domain(domain).split('.').next().unwrap_or(domain)
+}
+
+/// Protected domain imitated by `domain` (homoglyph substitution or a small edit distance)
+fn lookalike_target<'a>(domain: &str, protected: &'a [String], max_edit_distance: usize) -> Option<&'a str> {
+    let label = registrable_label(domain);
+    let normalized = normalize_homoglyphs(label);
+    protected.iter()
+        .filter(|target| !is_same_or_subdomain(domain, target))
+        .find(|target| {
+            let target_label = registrable_label(target);
+            // 短いラベルは偶然の一致が多いため編集距離では判定しない
+            normalized == target_label
+                || (target_label.len() >= 5 && edit_distance(label, target_label) <= max_edit_distance)
+                || (target_label.len() >= 5 && label.contains(target_label) && label != target_label)
+        })
+        .map(String::as_str)
+}
+
+fn normalize_homoglyphs(label: &str) -> String {
+    let replaced: String = label.chars().map(|c| match c {
+        '0' => 'o',
+        '1' | '!' | '|' => 'l',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        c => c,
+    }).collect();
+    replaced.replace("rn", "m").replace("vv", "w")
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn shannon_entropy(label: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in label.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let total = label.chars().count() as f64;
+    counts.values().map(|&count| {
+        let p = count as f64 / total;
+        -p * p.log2()
+    }).sum()
+}
+
+fn longest_consonant_run(label: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in label.chars() {
+        if c.is_ascii_alphabetic() && !matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y') {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::test_support::record;
+    use fukurow_rules::CyberEvent;
+
+    #[test]
+    fn test_suspicious_domains() {
+        let pattern = SuspiciousDomainPattern::new().with_allowed_domain("cdn-example.net");
+
+        assert_eq!(pattern.classify("xj9qk2vbz7tplmw.com").map(|(kind, _)| kind), Some("dga"));
+        assert_eq!(pattern.classify("paypa1.com").map(|(kind, _)| kind), Some("lookalike"));
+        assert_eq!(pattern.classify("login.rnicrosoft.com").map(|(kind, _)| kind), Some("lookalike"));
+        assert_eq!(pattern.classify("www.microsoft.com"), None);
+        assert_eq!(pattern.classify("microsoftonline.example.org").map(|(kind, _)| kind), None);
+        assert_eq!(pattern.classify("a8f3k2x9q7zzlp.cdn-example.net"), None);
+
+        let events = vec![record(1, &[("queryName", "paypa1.com"), ("sourceIP", "10.0.0.5")])];
+        let detections = pattern.detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].severity, "high");
+        assert_eq!(detections[0].details["imitates"], "paypal.com");
+    }
+
+    #[test]
+    fn test_user_agents_and_autorun_keys() {
+        let user_agents = SuspiciousUserAgentPattern::new();
+        let events = vec![
+            record(1, &[("httpMethod", "GET"), ("userAgent", "sqlmap/1.7"), ("sourceIP", "203.0.113.9")]),
+            record(2, &[("httpMethod", "GET"), ("userAgent", "Mozilla/5.0 (Windows NT 10.0)"), ("sourceIP", "10.0.0.2")]),
+            record(3, &[("httpMethod", "POST"), ("userAgent", ""), ("sourceIP", "10.0.0.3")]),
+        ];
+        let detections = user_agents.detect(&events);
+        assert_eq!(detections.iter().map(|d| d.severity.as_str()).collect::<Vec<_>>(), vec!["high", "medium"]);
+
+        let autorun = RegistryAutorunPattern::new();
+        let events = vec![
+            record(1, &[("registryKey", r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run"), ("registryOperation", "set"), ("user", "alice")]),
+            record(2, &[("registryKey", r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon"), ("registryValueName", "Shell"), ("registryOperation", "set"), ("user", "bob")]),
+            record(3, &[("registryKey", r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon"), ("registryValueName", "LastUsedUsername"), ("registryOperation", "set"), ("user", "bob")]),
+            record(4, &[("registryKey", r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run"), ("registryOperation", "read"), ("user", "carol")]),
+            record(5, &[("registryKey", r"HKCU\Software\Vendor\Settings"), ("registryOperation", "set"), ("user", "dave")]),
+        ];
+        let groups: Vec<String> = autorun.detect(&events).into_iter().map(|d| d.group).collect();
+        assert_eq!(groups, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn test_phishing_email_from_event() {
+        let event = CyberEvent::EmailReceived {
+            sender: "it-support@rnicrosoft.com".to_string(),
+            recipient: "alice@example.com".to_string(),
+            subject: "URGENT: verify your account".to_string(),
+            reply_to: Some("helpdesk@mailbox.example.net".to_string()),
+            urls: vec!["https://example.com/docs".to_string(), "http://198.51.100.7/login".to_string()],
+            attachments: vec!["notes.txt".to_string(), "invoice.pdf.exe".to_string()],
+            timestamp: 1_700_000_000,
+        };
+        let pattern = PhishingPattern::new();
+        let record = EventRecord::from_event(&event);
+        let kinds: Vec<&str> = pattern.indicators(&record).into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, vec!["reply_to_mismatch", "lookalike_sender", "risky_attachment", "double_extension", "ip_url", "urgent_subject"]);
+
+        let detections = pattern.detect(&[record]);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].severity, "high");
+        assert_eq!(detections[0].group, "alice@example.com");
+
+        let benign = CyberEvent::EmailReceived {
+            sender: "bob@example.com".to_string(),
+            recipient: "alice@example.com".to_string(),
+            subject: "Lunch".to_string(),
+            reply_to: None,
+            urls: vec!["https://example.com/menu".to_string()],
+            attachments: vec!["menu.pdf".to_string()],
+            timestamp: 1_700_000_100,
+        };
+        assert!(pattern.detect(&[EventRecord::from_event(&benign)]).is_empty());
+        assert_eq!(event_detection_rules().len(), 4);
+    }
+}
@@ -0,0 +1,829 @@
+//! Threat intelligence feed ingestion
+//!
+//! 設定したフィード (MISP API、CSV/TXT の URL リスト、TAXII 2.1) から定期的に
+//! インジケーターを取得し、値で重複排除して [`ThreatProcessor`] とストアに反映する。
+//! 各インジケーターはフィードごとの最終観測時刻を持ち、すべてのフィードで
+//! TTL を過ぎたものは失効させる
+
+use crate::stix::{to_threat_indicators, StixBundle, StixObject, STIX_NS};
+use crate::threat_intelligence::{IndicatorType, ThreatIndicator, ThreatProcessor};
+use async_trait::async_trait;
+use fukurow_core::model::Triple;
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Named graph holding feed indicators
+pub const THREAT_FEED_GRAPH: &str = "urn:fukurow:graph:threat-feeds";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Upper bound on TAXII pages fetched in one run
+const MAX_TAXII_PAGES: usize = 100;
+
+/// Feed errors
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum FeedError {
+    #[error("Fetch failed: {0}")]
+    Fetch(String),
+
+    #[error("Feed {feed}: {message}")]
+    Parse { feed: String, message: String },
+
+    #[error("Unknown feed: {0}")]
+    UnknownFeed(String),
+}
+
+/// Wire format of a feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeedFormat {
+    /// MISP `attributes/restSearch` (JSON)
+    Misp {
+        /// Only attributes flagged for detection (`to_ids`)
+        #[serde(default = "default_true")]
+        to_ids_only: bool,
+    },
+    /// One indicator per line; `#` starts a comment
+    PlainList {
+        /// Type of every entry (inferred per value when `None`)
+        #[serde(default)]
+        indicator_type: Option<IndicatorType>,
+    },
+    /// Delimited columns
+    Csv {
+        value_column: usize,
+        /// Column naming the indicator type (`ip`, `domain`, `sha256`, ...)
+        #[serde(default)]
+        type_column: Option<usize>,
+        #[serde(default = "default_delimiter")]
+        delimiter: char,
+        #[serde(default)]
+        has_header: bool,
+    },
+    /// TAXII 2.1 collection of STIX objects
+    Taxii { collection: String },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+/// Configuration of one feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedConfig {
+    /// Unique feed name (recorded in indicator sources)
+    pub name: String,
+    /// Endpoint (MISP / TAXII API root or list URL)
+    pub url: String,
+    pub format: FeedFormat,
+    /// Seconds between pulls
+    #[serde(default = "default_interval")]
+    pub interval_secs: u64,
+    /// Seconds an indicator stays valid after this feed last listed it
+    #[serde(default = "default_ttl")]
+    pub ttl_secs: u64,
+    /// MISP auth key or TAXII bearer token
+    #[serde(default, skip_serializing)]
+    pub api_key: Option<String>,
+    /// Threat type assigned when the feed does not provide one
+    #[serde(default = "default_threat_type")]
+    pub threat_type: String,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+}
+
+fn default_interval() -> u64 {
+    3600
+}
+
+fn default_ttl() -> u64 {
+    7 * 24 * 3600
+}
+
+fn default_threat_type() -> String {
+    "unknown".to_string()
+}
+
+fn default_severity() -> String {
+    "medium".to_string()
+}
+
+impl FeedConfig {
+    pub fn new(name: impl Into<String>, url: impl Into<String>, format: FeedFormat) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            format,
+            interval_secs: default_interval(),
+            ttl_secs: default_ttl(),
+            api_key: None,
+            threat_type: default_threat_type(),
+            severity: default_severity(),
+        }
+    }
+
+    pub fn with_interval_secs(mut self, interval_secs: u64) -> Self {
+        self.interval_secs = interval_secs;
+        self
+    }
+
+    pub fn with_ttl_secs(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_threat_type(mut self, threat_type: impl Into<String>) -> Self {
+        self.threat_type = threat_type.into();
+        self
+    }
+
+    pub fn with_severity(mut self, severity: impl Into<String>) -> Self {
+        self.severity = severity.into();
+        self
+    }
+
+    /// HTTP request for the first page; `since` (Unix seconds) limits MISP/TAXII to newer entries
+    pub fn request(&self, since: Option<i64>) -> FeedRequest {
+        let mut request = FeedRequest::get(self.url.clone());
+        match &self.format {
+            FeedFormat::Misp { to_ids_only } => {
+                let mut body = serde_json::json!({ "returnFormat": "json", "to_ids": *to_ids_only });
+                if let Some(since) = since {
+                    body["timestamp"] = serde_json::json!(since);
+                }
+                request.method = FeedMethod::Post;
+                request.url = format!("{}/attributes/restSearch", self.url.trim_end_matches('/'));
+                request.body = Some(body.to_string());
+                request.headers.push(("Accept".to_string(), "application/json".to_string()));
+                request.headers.push(("Content-Type".to_string(), "application/json".to_string()));
+                if let Some(key) = &self.api_key {
+                    request.headers.push(("Authorization".to_string(), key.clone()));
+                }
+            }
+            FeedFormat::Taxii { collection } => {
+                request.url = format!("{}/collections/{}/objects/", self.url.trim_end_matches('/'), collection);
+                if let Some(since) = since.and_then(|s| chrono::DateTime::from_timestamp(s, 0)) {
+                    request.url.push_str(&format!("?added_after={}", since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)));
+                }
+                request.headers.push(("Accept".to_string(), "application/taxii+json;version=2.1".to_string()));
+                if let Some(key) = &self.api_key {
+                    request.headers.push(("Authorization".to_string(), format!("Bearer {}", key)));
+                }
+            }
+            FeedFormat::PlainList { .. } | FeedFormat::Csv { .. } => {
+                if let Some(key) = &self.api_key {
+                    request.headers.push(("Authorization".to_string(), format!("Bearer {}", key)));
+                }
+            }
+        }
+        request
+    }
+
+    /// Parse a response body into indicators; also returns the next TAXII page cursor
+    pub fn parse(&self, body: &str, now: i64) -> Result<(Vec<ThreatIndicator>, Option<String>), FeedError> {
+        let error = |message: String| FeedError::Parse { feed: self.name.clone(), message };
+        let mut next = None;
+        let entries: Vec<(IndicatorType, String, Option<i64>, Vec<String>)> = match &self.format {
+            FeedFormat::PlainList { indicator_type } => body.lines()
+                .map(|line| line.split('#').next().unwrap_or_default().trim())
+                .filter(|line| !line.is_empty())
+                .filter_map(|value| {
+                    let kind = indicator_type.clone().or_else(|| infer_indicator_type(value))?;
+                    Some((kind, value.to_string(), None, Vec::new()))
+                })
+                .collect(),
+            FeedFormat::Csv { value_column, type_column, delimiter, has_header } => body.lines()
+                .skip(usize::from(*has_header))
+                .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                .filter_map(|line| {
+                    let cells: Vec<&str> = line.split(*delimiter).map(|cell| cell.trim().trim_matches('"')).collect();
+                    let value = cells.get(*value_column).filter(|v| !v.is_empty())?;
+                    let kind = match type_column {
+                        Some(column) => cells.get(*column).and_then(|t| indicator_type_from_name(t)),
+                        None => infer_indicator_type(value),
+                    }?;
+                    Some((kind, value.to_string(), None, Vec::new()))
+                })
+                .collect(),
+            FeedFormat::Misp { .. } => {
+                let json: serde_json::Value = serde_json::from_str(body).map_err(|e| error(e.to_string()))?;
+                let attributes = json.pointer("/response/Attribute")
+                    .and_then(|a| a.as_array())
+                    .ok_or_else(|| error("missing response.Attribute".to_string()))?;
+                attributes.iter().filter_map(|attribute| {
+                    let kind = indicator_type_from_name(attribute.get("type")?.as_str()?)?;
+                    let value = attribute.get("value")?.as_str()?;
+                    // `ip-dst|port` などの複合属性は先頭の値だけを使う
+                    let value = value.split('|').next().unwrap_or(value).to_string();
+                    let seen = attribute.get("timestamp").and_then(|t| match t {
+                        serde_json::Value::String(s) => s.parse::<i64>().ok(),
+                        other => other.as_i64(),
+                    });
+                    let tags = attribute.get("Tag").and_then(|t| t.as_array())
+                        .map(|tags| tags.iter().filter_map(|t| t.get("name")?.as_str().map(String::from)).collect())
+                        .unwrap_or_default();
+                    Some((kind, value, seen, tags))
+                }).collect()
+            }
+            FeedFormat::Taxii { .. } => {
+                #[derive(Deserialize)]
+                struct Envelope {
+                    #[serde(default)]
+                    objects: Vec<StixObject>,
+                    #[serde(default)]
+                    more: bool,
+                    next: Option<String>,
+                }
+                let envelope: Envelope = serde_json::from_str(body).map_err(|e| error(e.to_string()))?;
+                if envelope.more {
+                    next = envelope.next;
+                }
+                let indicators = to_threat_indicators(&StixBundle::new(envelope.objects));
+                return Ok((indicators.into_iter().map(|stix| self.normalize(stix.indicator_type, &stix.value, Some(stix.first_seen), stix.tags, Some(stix.threat_type), now)).collect(), next));
+            }
+        };
+
+        let indicators = entries.into_iter()
+            .map(|(kind, value, seen, tags)| self.normalize(kind, &value, seen, tags, None, now))
+            .collect();
+        Ok((indicators, next))
+    }
+
+    fn normalize(&self, indicator_type: IndicatorType, value: &str, seen: Option<i64>, tags: Vec<String>, threat_type: Option<String>, now: i64) -> ThreatIndicator {
+        let value = normalize_value(&indicator_type, value);
+        ThreatIndicator {
+            id: indicator_id(&indicator_type, &value),
+            indicator_type,
+            value,
+            threat_type: threat_type.filter(|t| t != "unknown").unwrap_or_else(|| self.threat_type.clone()),
+            severity: self.severity.clone(),
+            sources: vec![self.name.clone()],
+            first_seen: seen.filter(|s| *s > 0).unwrap_or(now),
+            last_seen: now,
+            tags,
+        }
+    }
+}
+
+/// HTTP method of a feed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedMethod {
+    Get,
+    Post,
+}
+
+/// Transport-independent feed request
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedRequest {
+    pub method: FeedMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl FeedRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self { method: FeedMethod::Get, url: url.into(), headers: Vec::new(), body: None }
+    }
+}
+
+/// Transport used by the scheduler to pull feeds
+#[async_trait]
+pub trait FeedFetcher: Send + Sync {
+    /// Perform the request and return the response body
+    async fn fetch(&self, request: &FeedRequest) -> Result<String, FeedError>;
+}
+
+/// Guess the indicator type of a bare value
+pub fn infer_indicator_type(value: &str) -> Option<IndicatorType> {
+    if value.contains("://") {
+        Some(IndicatorType::Url)
+    } else if value.parse::<std::net::IpAddr>().is_ok() {
+        Some(IndicatorType::IpAddress)
+    } else if matches!(value.len(), 32 | 40 | 64) && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(IndicatorType::FileHash)
+    } else if value.contains('@') {
+        Some(IndicatorType::Email)
+    } else if value.contains('.') && !value.contains(char::is_whitespace) && !value.contains('/') {
+        Some(IndicatorType::Domain)
+    } else {
+        None
+    }
+}
+
+/// Map MISP attribute types and CSV type labels to indicator types
+pub fn indicator_type_from_name(name: &str) -> Option<IndicatorType> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "ip" | "ipv4" | "ipv6" | "ip-src" | "ip-dst" | "ip-src|port" | "ip-dst|port" | "ipv4-addr" | "ipv6-addr" => Some(IndicatorType::IpAddress),
+        "domain" | "hostname" | "domain|ip" | "domain-name" => Some(IndicatorType::Domain),
+        "url" | "uri" | "link" => Some(IndicatorType::Url),
+        "md5" | "sha1" | "sha256" | "sha512" | "hash" | "filename|md5" | "filename|sha256" => Some(IndicatorType::FileHash),
+        "email" | "email-src" | "email-dst" | "email-addr" => Some(IndicatorType::Email),
+        "user-agent" | "useragent" => Some(IndicatorType::UserAgent),
+        _ => None,
+    }
+}
+
+/// Canonical form used for de-duplication (case-insensitive types are lowercased)
+pub fn normalize_value(indicator_type: &IndicatorType, value: &str) -> String {
+    let value = value.trim();
+    match indicator_type {
+        IndicatorType::Domain => value.trim_end_matches('.').to_ascii_lowercase(),
+        IndicatorType::FileHash | IndicatorType::Email => value.to_ascii_lowercase(),
+        _ => value.to_string(),
+    }
+}
+
+/// Stable ID of an indicator value
+pub fn indicator_id(indicator_type: &IndicatorType, value: &str) -> String {
+    let key = format!("{:?}:{}", indicator_type, value);
+    format!("feed--{}", uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, key.as_bytes()))
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "critical" => 4,
+        "high" => 3,
+        "medium" => 2,
+        "low" => 1,
+        _ => 0,
+    }
+}
+
+/// Indicator with the last time each feed listed it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedIndicator {
+    pub indicator: ThreatIndicator,
+    /// Feed name -> last time the feed listed the indicator (Unix seconds)
+    pub seen_by: BTreeMap<String, i64>,
+}
+
+/// Scheduling state of one feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedStatus {
+    pub config: FeedConfig,
+    /// Last successful pull (Unix seconds)
+    pub last_success: Option<i64>,
+    /// Last attempted pull (Unix seconds)
+    pub last_attempt: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_count: usize,
+}
+
+/// Changes made by one scheduler run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeedRunReport {
+    /// Feed name -> number of indicators pulled, or the error
+    pub feeds: BTreeMap<String, Result<usize, String>>,
+    /// New or changed indicators
+    pub upserted: Vec<ThreatIndicator>,
+    /// Indicators whose TTL expired on every feed
+    pub expired: Vec<ThreatIndicator>,
+}
+
+impl FeedRunReport {
+    pub fn changed(&self) -> bool {
+        !self.upserted.is_empty() || !self.expired.is_empty()
+    }
+}
+
+/// Pulls due feeds, de-duplicates their indicators and ages them out
+#[derive(Debug, Clone, Default)]
+pub struct FeedScheduler {
+    feeds: Vec<FeedStatus>,
+    indicators: HashMap<String, TrackedIndicator>,
+}
+
+impl FeedScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a feed, replacing a feed of the same name
+    pub fn with_feed(mut self, config: FeedConfig) -> Self {
+        self.add_feed(config);
+        self
+    }
+
+    pub fn add_feed(&mut self, config: FeedConfig) {
+        self.feeds.retain(|status| status.config.name != config.name);
+        self.feeds.push(FeedStatus { config, last_success: None, last_attempt: None, last_error: None, last_count: 0 });
+    }
+
+    pub fn feeds(&self) -> &[FeedStatus] {
+        &self.feeds
+    }
+
+    /// Current de-duplicated indicators
+    pub fn indicators(&self) -> impl Iterator<Item = &ThreatIndicator> {
+        self.indicators.values().map(|tracked| &tracked.indicator)
+    }
+
+    pub fn tracked(&self, indicator_id: &str) -> Option<&TrackedIndicator> {
+        self.indicators.get(indicator_id)
+    }
+
+    /// Names of feeds whose interval has elapsed
+    pub fn due_feeds(&self, now: i64) -> Vec<String> {
+        self.feeds.iter()
+            .filter(|status| status.last_attempt.is_none_or(|last| now - last >= status.config.interval_secs as i64))
+            .map(|status| status.config.name.clone())
+            .collect()
+    }
+
+    /// Earliest time a feed becomes due (Unix seconds)
+    pub fn next_due_at(&self) -> Option<i64> {
+        self.feeds.iter()
+            .map(|status| status.last_attempt.map_or(i64::MIN, |last| last + status.config.interval_secs as i64))
+            .min()
+    }
+
+    /// Pull every due feed, merge the results and expire stale indicators
+    ///
+    /// 失敗したフィードは次の間隔まで再試行しない。既存のインジケーターは
+    /// TTL が切れるまで保持されるため、一時的な障害で検知が消えることはない
+    pub async fn run_due(&mut self, fetcher: &dyn FeedFetcher, now: i64) -> FeedRunReport {
+        let mut report = FeedRunReport::default();
+        let mut upserted = BTreeSet::new();
+
+        for name in self.due_feeds(now) {
+            let result = self.pull(fetcher, &name, now).await;
+            let status = self.feeds.iter_mut().find(|status| status.config.name == name).expect("due feed exists");
+            status.last_attempt = Some(now);
+            match result {
+                Ok(indicators) => {
+                    status.last_success = Some(now);
+                    status.last_error = None;
+                    status.last_count = indicators.len();
+                    report.feeds.insert(name.clone(), Ok(indicators.len()));
+                    upserted.extend(self.ingest(&name, indicators, now));
+                }
+                Err(e) => {
+                    status.last_error = Some(e.to_string());
+                    report.feeds.insert(name.clone(), Err(e.to_string()));
+                }
+            }
+        }
+
+        report.expired = self.expire(now);
+        report.upserted = upserted.into_iter()
+            .filter_map(|id| self.indicators.get(&id).map(|tracked| tracked.indicator.clone()))
+            .collect();
+        report
+    }
+
+    async fn pull(&self, fetcher: &dyn FeedFetcher, name: &str, now: i64) -> Result<Vec<ThreatIndicator>, FeedError> {
+        let status = self.feeds.iter()
+            .find(|status| status.config.name == name)
+            .ok_or_else(|| FeedError::UnknownFeed(name.to_string()))?;
+        let config = &status.config;
+        let since = status.last_success;
+
+        let mut request = config.request(since);
+        let mut indicators = Vec::new();
+        for _ in 0..MAX_TAXII_PAGES {
+            let body = fetcher.fetch(&request).await?;
+            let (page, next) = config.parse(&body, now)?;
+            indicators.extend(page);
+            match next {
+                Some(cursor) => {
+                    let base = config.request(since);
+                    let separator = if base.url.contains('?') { '&' } else { '?' };
+                    request.url = format!("{}{}next={}", base.url, separator, cursor);
+                }
+                None => break,
+            }
+        }
+        Ok(indicators)
+    }
+
+    /// Merge indicators listed by `feed`; returns the IDs that changed
+    pub fn ingest(&mut self, feed: &str, indicators: Vec<ThreatIndicator>, now: i64) -> Vec<String> {
+        let mut changed = Vec::new();
+        for incoming in indicators {
+            let id = incoming.id.clone();
+            match self.indicators.get_mut(&id) {
+                Some(tracked) => {
+                    let before = tracked.indicator.clone();
+                    let existing = &mut tracked.indicator;
+                    existing.first_seen = existing.first_seen.min(incoming.first_seen);
+                    existing.last_seen = existing.last_seen.max(incoming.last_seen);
+                    if severity_rank(&incoming.severity) > severity_rank(&existing.severity) {
+                        existing.severity = incoming.severity;
+                    }
+                    if existing.threat_type == "unknown" {
+                        existing.threat_type = incoming.threat_type;
+                    }
+                    for source in incoming.sources {
+                        if !existing.sources.contains(&source) {
+                            existing.sources.push(source);
+                        }
+                    }
+                    for tag in incoming.tags {
+                        if !existing.tags.contains(&tag) {
+                            existing.tags.push(tag);
+                        }
+                    }
+                    tracked.seen_by.insert(feed.to_string(), now);
+                    if tracked.indicator != before {
+                        changed.push(id);
+                    }
+                }
+                None => {
+                    self.indicators.insert(id.clone(), TrackedIndicator {
+                        indicator: incoming,
+                        seen_by: BTreeMap::from([(feed.to_string(), now)]),
+                    });
+                    changed.push(id);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Drop feed sightings older than the feed's TTL and remove indicators no feed still lists
+    pub fn expire(&mut self, now: i64) -> Vec<ThreatIndicator> {
+        let ttls: HashMap<&str, i64> = self.feeds.iter()
+            .map(|status| (status.config.name.as_str(), status.config.ttl_secs as i64))
+            .collect();
+
+        let mut expired = Vec::new();
+        self.indicators.retain(|_, tracked| {
+            // 設定から外れたフィードの観測も失効扱いにする
+            tracked.seen_by.retain(|feed, seen| ttls.get(feed.as_str()).is_some_and(|ttl| now - *seen < *ttl));
+            if tracked.seen_by.is_empty() {
+                expired.push(tracked.indicator.clone());
+                return false;
+            }
+            tracked.indicator.sources.retain(|source| tracked.seen_by.contains_key(source));
+            true
+        });
+        expired.sort_by(|a, b| a.id.cmp(&b.id));
+        expired
+    }
+
+    /// Rewrite [`THREAT_FEED_GRAPH`] from the current indicators
+    pub fn write_to_store(&self, store: &mut RdfStore) -> usize {
+        let graph_id = GraphId::Named(THREAT_FEED_GRAPH.to_string());
+        store.clear_graph(&graph_id);
+
+        let imported_at = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let mut ids: Vec<&String> = self.indicators.keys().collect();
+        ids.sort();
+        let mut count = 0;
+        for id in ids {
+            let tracked = &self.indicators[id];
+            let triples = indicator_triples(&tracked.indicator);
+            count += triples.len();
+            let source = tracked.seen_by.keys().next().cloned().unwrap_or_default();
+            store.insert_batch(triples, graph_id.clone(), Provenance::Imported {
+                source_uri: format!("feed:{}", source),
+                imported_at,
+            });
+        }
+        count
+    }
+}
+
+/// Triples describing a feed indicator (STIX vocabulary, so STIX-based rules match it)
+pub fn indicator_triples(indicator: &ThreatIndicator) -> Vec<Triple> {
+    let subject = format!("urn:fukurow:indicator:{}", indicator.id);
+    let term = |local: &str| format!("{}{}", STIX_NS, local);
+    let mut triples = vec![
+        Triple { subject: subject.clone(), predicate: RDF_TYPE.to_string(), object: term("Indicator") },
+        Triple { subject: subject.clone(), predicate: term("observableValue"), object: indicator.value.clone() },
+        Triple { subject: subject.clone(), predicate: term("indicatorType"), object: indicator.threat_type.clone() },
+        Triple { subject: subject.clone(), predicate: term("x_fukurow_observable_type"), object: format!("{:?}", indicator.indicator_type) },
+        Triple { subject: subject.clone(), predicate: term("x_fukurow_severity"), object: indicator.severity.clone() },
+        Triple { subject: subject.clone(), predicate: term("x_fukurow_last_seen"), object: indicator.last_seen.to_string() },
+    ];
+    for source in &indicator.sources {
+        triples.push(Triple { subject: subject.clone(), predicate: term("x_fukurow_feed"), object: source.clone() });
+    }
+    triples
+}
+
+impl ThreatProcessor {
+    /// Apply the changes of a feed run to the threat feed
+    pub fn apply_feed_report(&mut self, report: &FeedRunReport) {
+        for indicator in &report.expired {
+            self.feed_mut().remove_indicator(&indicator.id);
+        }
+        for indicator in &report.upserted {
+            self.feed_mut().add_indicator(indicator.clone());
+        }
+    }
+}
+
+/// reqwest-based fetcher
+#[cfg(feature = "feeds-http")]
+#[derive(Debug, Clone, Default)]
+pub struct HttpFeedFetcher {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "feeds-http")]
+impl HttpFeedFetcher {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "feeds-http")]
+#[async_trait]
+impl FeedFetcher for HttpFeedFetcher {
+    async fn fetch(&self, request: &FeedRequest) -> Result<String, FeedError> {
+        let mut builder = match request.method {
+            FeedMethod::Get => self.client.get(&request.url),
+            FeedMethod::Post => self.client.post(&request.url),
+        };
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+        let response = builder.send().await.map_err(|e| FeedError::Fetch(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(FeedError::Fetch(format!("{} returned {}", request.url, status)));
+        }
+        response.text().await.map_err(|e| FeedError::Fetch(e.to_string()))
+    }
+}
+
+#[cfg(feature = "feeds-http")]
+impl FeedScheduler {
+    /// Pull feeds forever, applying each run to the store and the threat processor
+    pub async fn run(
+        mut self,
+        fetcher: HttpFeedFetcher,
+        store: std::sync::Arc<tokio::sync::RwLock<RdfStore>>,
+        processor: std::sync::Arc<tokio::sync::RwLock<ThreatProcessor>>,
+    ) {
+        loop {
+            let now = chrono::Utc::now().timestamp();
+            let report = self.run_due(&fetcher, now).await;
+            if report.changed() {
+                self.write_to_store(&mut *store.write().await);
+                processor.write().await.apply_feed_report(&report);
+            }
+
+            let wait = self.next_due_at().map_or(60, |due| (due - chrono::Utc::now().timestamp()).clamp(1, 3600));
+            tokio::time::sleep(std::time::Duration::from_secs(wait as u64)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct StaticFetcher {
+        responses: HashMap<String, Result<String, FeedError>>,
+        requests: Mutex<Vec<FeedRequest>>,
+    }
+
+    impl StaticFetcher {
+        fn new(responses: &[(&str, Result<&str, FeedError>)]) -> Self {
+            Self {
+                responses: responses.iter().map(|(url, body)| (url.to_string(), body.clone().map(String::from))).collect(),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl FeedFetcher for StaticFetcher {
+        async fn fetch(&self, request: &FeedRequest) -> Result<String, FeedError> {
+            self.requests.lock().unwrap().push(request.clone());
+            self.responses.get(&request.url).cloned().unwrap_or_else(|| Err(FeedError::Fetch(format!("404 {}", request.url))))
+        }
+    }
+
+    fn plain(name: &str, url: &str) -> FeedConfig {
+        FeedConfig::new(name, url, FeedFormat::PlainList { indicator_type: None })
+    }
+
+    #[test]
+    fn test_plain_list_and_csv_parsing() {
+        let (indicators, _) = plain("list", "https://example.com/bad.txt")
+            .parse("# blocklist\n198.51.100.7\nEvil.Example.COM.\nhttps://evil.example/login # phishing\nnot-an-indicator\n", 100)
+            .unwrap();
+        let values: Vec<(IndicatorType, &str)> = indicators.iter().map(|i| (i.indicator_type.clone(), i.value.as_str())).collect();
+        assert_eq!(values, vec![
+            (IndicatorType::IpAddress, "198.51.100.7"),
+            (IndicatorType::Domain, "evil.example.com"),
+            (IndicatorType::Url, "https://evil.example/login"),
+        ]);
+
+        let csv = FeedConfig::new("csv", "https://example.com/iocs.csv", FeedFormat::Csv { value_column: 1, type_column: Some(0), delimiter: ',', has_header: true });
+        let (indicators, _) = csv.parse("type,value\nsha256,\"ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789\"\nregistry,HKLM\\Run\n", 100).unwrap();
+        assert_eq!(indicators.len(), 1);
+        assert_eq!(indicators[0].indicator_type, IndicatorType::FileHash);
+        assert_eq!(indicators[0].value, "abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789");
+    }
+
+    #[test]
+    fn test_misp_request_and_parsing() {
+        let misp = FeedConfig::new("misp", "https://misp.example/", FeedFormat::Misp { to_ids_only: true }).with_api_key("secret");
+        let request = misp.request(Some(1_700_000_000));
+        assert_eq!(request.method, FeedMethod::Post);
+        assert_eq!(request.url, "https://misp.example/attributes/restSearch");
+        assert!(request.headers.contains(&("Authorization".to_string(), "secret".to_string())));
+        assert!(request.body.unwrap().contains("\"timestamp\":1700000000"));
+
+        let body = r#"{"response":{"Attribute":[
+            {"type":"ip-dst|port","value":"203.0.113.9|4444","timestamp":"1690000000","Tag":[{"name":"tlp:amber"}]},
+            {"type":"comment","value":"ignored"}
+        ]}}"#;
+        let (indicators, next) = misp.parse(body, 1_700_000_100).unwrap();
+        assert!(next.is_none());
+        assert_eq!(indicators.len(), 1);
+        assert_eq!(indicators[0].value, "203.0.113.9");
+        assert_eq!(indicators[0].first_seen, 1_690_000_000);
+        assert_eq!(indicators[0].tags, vec!["tlp:amber".to_string()]);
+        assert!(matches!(misp.parse("{}", 0), Err(FeedError::Parse { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_taxii_pagination() {
+        let taxii = FeedConfig::new("taxii", "https://taxii.example/api1", FeedFormat::Taxii { collection: "c1".to_string() });
+        let page = |ip: &str, more: bool| format!(
+            r#"{{"more":{},"next":"p2","objects":[{{"type":"indicator","id":"indicator--{}","created":"2024-01-01T00:00:00Z","modified":"2024-01-01T00:00:00Z","indicator_types":["malicious-activity"],"pattern":"[ipv4-addr:value = '{}']","pattern_type":"stix","valid_from":"2024-01-01T00:00:00Z"}}]}}"#,
+            more, ip, ip
+        );
+        let (first, second) = (page("192.0.2.1", true), page("192.0.2.2", false));
+        let fetcher = StaticFetcher::new(&[
+            ("https://taxii.example/api1/collections/c1/objects/", Ok(first.as_str())),
+            ("https://taxii.example/api1/collections/c1/objects/?next=p2", Ok(second.as_str())),
+        ]);
+
+        let mut scheduler = FeedScheduler::new().with_feed(taxii);
+        let report = scheduler.run_due(&fetcher, 1_000).await;
+        assert_eq!(report.feeds["taxii"], Ok(2));
+        assert!(report.upserted.iter().all(|i| i.threat_type == "malicious-activity"));
+    }
+
+    #[tokio::test]
+    async fn test_dedup_scheduling_and_ttl() {
+        let fetcher = StaticFetcher::new(&[
+            ("https://a.example/list", Ok("198.51.100.7\nevil.example\n")),
+            ("https://b.example/list", Ok("EVIL.example\n")),
+        ]);
+        let mut scheduler = FeedScheduler::new()
+            .with_feed(plain("a", "https://a.example/list").with_interval_secs(60).with_ttl_secs(100))
+            .with_feed(plain("b", "https://b.example/list").with_interval_secs(600).with_ttl_secs(1000).with_severity("high"))
+            .with_feed(plain("down", "https://down.example/list"));
+
+        let report = scheduler.run_due(&fetcher, 0).await;
+        assert!(report.feeds["down"].is_err());
+        assert_eq!(scheduler.indicators().count(), 2);
+        let domain_id = indicator_id(&IndicatorType::Domain, "evil.example");
+        let domain = scheduler.tracked(&domain_id).unwrap();
+        assert_eq!(domain.indicator.sources, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(domain.indicator.severity, "high");
+
+        assert!(scheduler.due_feeds(30).is_empty());
+        assert_eq!(scheduler.due_feeds(60), vec!["a".to_string()]);
+        assert_eq!(scheduler.next_due_at(), Some(60));
+
+        // フィード a が止まると、a だけが挙げていた IP は TTL 後に失効する
+        scheduler.feeds.retain(|status| status.config.name != "a");
+        let expired = scheduler.expire(150);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].value, "198.51.100.7");
+        assert_eq!(scheduler.tracked(&domain_id).unwrap().indicator.sources, vec!["b".to_string()]);
+
+        let mut processor = ThreatProcessor::new();
+        processor.apply_feed_report(&FeedRunReport { expired, upserted: scheduler.indicators().cloned().collect(), ..Default::default() });
+        assert!(processor.feed().is_threat("evil.example", IndicatorType::Domain).is_some());
+        assert!(processor.feed().is_threat("198.51.100.7", IndicatorType::IpAddress).is_none());
+    }
+
+    #[test]
+    fn test_write_to_store() {
+        let mut scheduler = FeedScheduler::new().with_feed(plain("a", "https://a.example/list"));
+        let (indicators, _) = scheduler.feeds[0].config.parse("198.51.100.7\n", 10).unwrap();
+        scheduler.ingest("a", indicators, 10);
+
+        let mut store = RdfStore::new();
+        assert_eq!(scheduler.write_to_store(&mut store), 7);
+        assert_eq!(scheduler.write_to_store(&mut store), 7);
+        let found = store.find_triples(None, Some(&format!("{}observableValue", STIX_NS)), Some("198.51.100.7"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].graph_id, GraphId::Named(THREAT_FEED_GRAPH.to_string()));
+    }
+}
@@ -0,0 +1,350 @@
+//! GeoIP and ASN enrichment
+//!
+//! イベントの IP アドレス (送信元・宛先) を GeoIP データベースで引き、国・ASN・組織の
+//! トリプルをイベントに付ける。「許可リストにない国からの対話的ログイン」のような条件を
+//! ルールで書けるようにするためのもの。データベースは [`GeoIpDatabase`] で差し替えられ、
+//! オフライン用の [`CidrGeoIpDatabase`] と MaxMind GeoLite2 (`maxmind` feature) を用意する。
+//! `pipeline` feature で取り込みパイプラインのステージ ([`GEOIP_STAGE`]) として使える
+
+use crate::patterns::{GeoPoint, GeoResolver};
+use fukurow_core::model::Triple;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Named graph holding enrichment triples
+pub const GEOIP_GRAPH: &str = "urn:fukurow:graph:geoip";
+
+/// Name of the enrichment stage in the ingestion pipeline
+pub const GEOIP_STAGE: &str = "geoip";
+
+const EVENT_NS: &str = "http://example.org/";
+
+/// GeoIP errors
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum GeoIpError {
+    #[error("Invalid network: {0}")]
+    InvalidNetwork(String),
+
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+/// What a database knows about an address
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeoIpRecord {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+    /// Organization announcing the address
+    pub organization: Option<String>,
+    pub location: Option<GeoPoint>,
+}
+
+/// Address lookup backend
+pub trait GeoIpDatabase: Send + Sync {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoIpRecord>;
+}
+
+/// [`GeoResolver`] for [`crate::ImpossibleTravelPattern`] backed by `database`
+pub fn geo_resolver(database: Arc<dyn GeoIpDatabase>) -> GeoResolver {
+    Arc::new(move |value: &str| value.parse().ok().and_then(|ip| database.lookup(ip)).and_then(|record| record.location))
+}
+
+/// In-memory database of CIDR networks (longest prefix wins)
+///
+/// CSV (`network,country,asn,organization`、空欄可、`#` で始まる行は無視) から読み込める
+#[derive(Debug, Clone, Default)]
+pub struct CidrGeoIpDatabase {
+    /// (IPv6?, prefix length) -> masked network -> record
+    networks: HashMap<(bool, u8), HashMap<u128, GeoIpRecord>>,
+    /// Prefix lengths present, longest first
+    prefixes: BTreeSet<Reverse<(u8, bool)>>,
+}
+
+impl CidrGeoIpDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `network` (`10.0.0.0/8`, `2001:db8::/32` or a single address)
+    pub fn insert(&mut self, network: &str, record: GeoIpRecord) -> Result<(), GeoIpError> {
+        let invalid = || GeoIpError::InvalidNetwork(network.to_string());
+        let (address, prefix) = match network.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix.parse::<u8>().map_err(|_| invalid())?)),
+            None => (network, None),
+        };
+        let ip: IpAddr = address.trim().parse().map_err(|_| invalid())?;
+        let (v6, bits) = address_bits(ip);
+        let prefix = prefix.unwrap_or(bits);
+        if prefix > bits {
+            return Err(invalid());
+        }
+        self.networks.entry((v6, prefix)).or_default().insert(mask(ip, prefix), record);
+        self.prefixes.insert(Reverse((prefix, v6)));
+        Ok(())
+    }
+
+    pub fn with_network(mut self, network: &str, record: GeoIpRecord) -> Result<Self, GeoIpError> {
+        self.insert(network, record)?;
+        Ok(self)
+    }
+
+    /// Load `network,country,asn,organization` lines (a header line is skipped)
+    pub fn from_csv(text: &str) -> Result<Self, GeoIpError> {
+        let mut database = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || (index == 0 && line.starts_with("network")) {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(4, ',').map(str::trim).collect();
+            let field = |i: usize| fields.get(i).copied().filter(|value| !value.is_empty());
+            let asn = field(2)
+                .map(|asn| asn.trim_start_matches("AS").parse::<u32>())
+                .transpose()
+                .map_err(|e| GeoIpError::Parse { line: index + 1, message: format!("invalid ASN: {}", e) })?;
+            let record = GeoIpRecord {
+                country: field(1).map(str::to_uppercase),
+                asn,
+                organization: field(3).map(|organization| organization.trim_matches('"').to_string()),
+                location: None,
+            };
+            database.insert(fields[0], record)
+                .map_err(|e| GeoIpError::Parse { line: index + 1, message: e.to_string() })?;
+        }
+        Ok(database)
+    }
+
+    pub fn len(&self) -> usize {
+        self.networks.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl GeoIpDatabase for CidrGeoIpDatabase {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoIpRecord> {
+        let (v6, _) = address_bits(ip);
+        self.prefixes.iter()
+            .filter(|Reverse((_, network_v6))| *network_v6 == v6)
+            .find_map(|Reverse((prefix, _))| self.networks.get(&(v6, *prefix))?.get(&mask(ip, *prefix)))
+            .cloned()
+    }
+}
+
+fn address_bits(ip: IpAddr) -> (bool, u8) {
+    match ip {
+        IpAddr::V4(_) => (false, 32),
+        IpAddr::V6(_) => (true, 128),
+    }
+}
+
+fn mask(ip: IpAddr, prefix: u8) -> u128 {
+    let (value, bits) = match ip {
+        IpAddr::V4(ip) => (u32::from(ip) as u128, 32),
+        IpAddr::V6(ip) => (u128::from(ip), 128),
+    };
+    match prefix {
+        0 => 0,
+        prefix => value & (u128::MAX << (bits - prefix as u32)) & (u128::MAX >> (128 - bits)),
+    }
+}
+
+/// MaxMind GeoLite2 / GeoIP2 databases (`.mmdb`)
+///
+/// City (または Country) と ASN のデータベースを別々に開く。どちらか一方だけでもよい
+#[cfg(feature = "maxmind")]
+pub struct MaxMindDatabase {
+    city: Option<maxminddb::Reader<Vec<u8>>>,
+    asn: Option<maxminddb::Reader<Vec<u8>>>,
+}
+
+#[cfg(feature = "maxmind")]
+impl MaxMindDatabase {
+    pub fn open(city_path: Option<&std::path::Path>, asn_path: Option<&std::path::Path>) -> Result<Self, GeoIpError> {
+        let open = |path: &std::path::Path| {
+            maxminddb::Reader::open_readfile(path).map_err(|e| GeoIpError::Database(format!("{}: {}", path.display(), e)))
+        };
+        Ok(Self {
+            city: city_path.map(open).transpose()?,
+            asn: asn_path.map(open).transpose()?,
+        })
+    }
+}
+
+#[cfg(feature = "maxmind")]
+impl GeoIpDatabase for MaxMindDatabase {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoIpRecord> {
+        let mut record = GeoIpRecord::default();
+        if let Some(city) = self.city.as_ref().and_then(|reader| reader.lookup::<maxminddb::geoip2::City>(ip).ok()) {
+            record.country = city.country.and_then(|country| country.iso_code).map(str::to_string);
+            record.location = city.location
+                .and_then(|location| Some(GeoPoint::new(location.latitude?, location.longitude?)));
+        }
+        if let Some(asn) = self.asn.as_ref().and_then(|reader| reader.lookup::<maxminddb::geoip2::Asn>(ip).ok()) {
+            record.asn = asn.autonomous_system_number;
+            record.organization = asn.autonomous_system_organization.map(str::to_string);
+        }
+        (record != GeoIpRecord::default()).then_some(record)
+    }
+}
+
+/// IP-valued predicate and the prefix of the predicates derived from it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpField {
+    pub predicate: String,
+    /// `source` gives `sourceCountry`, `sourceASN` and `sourceOrganization`
+    pub prefix: String,
+}
+
+impl IpField {
+    pub fn new(predicate: &str, prefix: &str) -> Self {
+        Self { predicate: predicate.to_string(), prefix: prefix.to_string() }
+    }
+}
+
+/// Adds country, ASN and organization triples for the IP addresses of events
+///
+/// 既定では `sourceIP` と `destIP` を対象にする (NetworkConnection の両端、UserLogin などの送信元)。
+/// 引けなかったアドレス (プライベートアドレスなど) には何も付けない
+#[derive(Clone)]
+pub struct GeoIpEnricher {
+    database: Arc<dyn GeoIpDatabase>,
+    fields: Vec<IpField>,
+    graph: String,
+}
+
+impl GeoIpEnricher {
+    pub fn new(database: Arc<dyn GeoIpDatabase>) -> Self {
+        Self {
+            database,
+            fields: vec![
+                IpField::new(&format!("{}sourceIP", EVENT_NS), "source"),
+                IpField::new(&format!("{}destIP", EVENT_NS), "dest"),
+            ],
+            graph: GEOIP_GRAPH.to_string(),
+        }
+    }
+
+    pub fn with_fields(mut self, fields: Vec<IpField>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Named graph receiving the enrichment triples (default [`GEOIP_GRAPH`])
+    pub fn with_graph(mut self, graph: &str) -> Self {
+        self.graph = graph.to_string();
+        self
+    }
+
+    pub fn graph(&self) -> &str {
+        &self.graph
+    }
+
+    /// Enrichment triples for the IP-valued triples among `triples`
+    pub fn enrich(&self, triples: &[Triple]) -> Vec<Triple> {
+        let mut enriched = Vec::new();
+        for triple in triples {
+            let Some(field) = self.fields.iter().find(|field| field.predicate == triple.predicate) else { continue };
+            let Some(record) = triple.object.parse().ok().and_then(|ip| self.database.lookup(ip)) else { continue };
+            let mut add = |name: &str, value: String| enriched.push(Triple {
+                subject: triple.subject.clone(),
+                predicate: format!("{}{}{}", EVENT_NS, field.prefix, name),
+                object: value,
+            });
+            if let Some(country) = record.country {
+                add("Country", country);
+            }
+            if let Some(asn) = record.asn {
+                add("ASN", asn.to_string());
+            }
+            if let Some(organization) = record.organization {
+                add("Organization", organization);
+            }
+        }
+        enriched
+    }
+}
+
+impl std::fmt::Debug for GeoIpEnricher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoIpEnricher").field("fields", &self.fields).field("graph", &self.graph).finish()
+    }
+}
+
+#[cfg(feature = "pipeline")]
+#[async_trait::async_trait]
+impl fukurow_engine::PipelineStage for GeoIpEnricher {
+    fn name(&self) -> &str {
+        GEOIP_STAGE
+    }
+
+    async fn process(&self, store: &mut fukurow_store::store::RdfStore, batch: &mut fukurow_engine::EventBatch) -> Result<(), fukurow_engine::StageError> {
+        let provenance = fukurow_store::provenance::Provenance::Imported {
+            source_uri: GEOIP_STAGE.to_string(),
+            imported_at: chrono::Utc::now().timestamp_millis() as u64,
+        };
+        for item in &batch.items {
+            let triples = self.enrich(&item.triples);
+            if !triples.is_empty() {
+                store.insert_batch(triples, fukurow_store::provenance::GraphId::Named(self.graph.clone()), provenance.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> CidrGeoIpDatabase {
+        CidrGeoIpDatabase::from_csv(
+            "network,country,asn,organization\n\
+             203.0.113.0/24,jp,AS64500,Example Tokyo\n\
+             203.0.113.128/25,,64501,\"Example, Osaka\"\n\
+             2001:db8::/32,DE,,\n",
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_longest_prefix_lookup() {
+        let database = database();
+        assert_eq!(database.len(), 3);
+
+        let tokyo = database.lookup("203.0.113.10".parse().unwrap()).unwrap();
+        assert_eq!((tokyo.country.as_deref(), tokyo.asn), (Some("JP"), Some(64500)));
+        let osaka = database.lookup("203.0.113.200".parse().unwrap()).unwrap();
+        assert_eq!((osaka.asn, osaka.organization.as_deref()), (Some(64501), Some("Example, Osaka")));
+        assert_eq!(database.lookup("2001:db8::1".parse().unwrap()).unwrap().country.as_deref(), Some("DE"));
+        assert!(database.lookup("10.0.0.1".parse().unwrap()).is_none());
+
+        assert!(matches!(CidrGeoIpDatabase::new().insert("10.0.0.0/33", GeoIpRecord::default()), Err(GeoIpError::InvalidNetwork(_))));
+        assert!(matches!(CidrGeoIpDatabase::from_csv("bogus,JP,,"), Err(GeoIpError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_enriches_source_and_destination() {
+        let enricher = GeoIpEnricher::new(Arc::new(database()));
+        let field = |name: &str, value: &str| Triple {
+            subject: "event:1".to_string(),
+            predicate: format!("{}{}", EVENT_NS, name),
+            object: value.to_string(),
+        };
+        let triples = vec![field("sourceIP", "203.0.113.10"), field("destIP", "10.0.0.5"), field("port", "443")];
+
+        let enriched = enricher.enrich(&triples);
+        let mut values: Vec<(&str, &str)> = enriched.iter()
+            .map(|triple| (triple.predicate.trim_start_matches(EVENT_NS), triple.object.as_str()))
+            .collect();
+        values.sort();
+        assert_eq!(values, vec![("sourceASN", "64500"), ("sourceCountry", "JP"), ("sourceOrganization", "Example Tokyo")]);
+    }
+}
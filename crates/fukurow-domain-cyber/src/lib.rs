@@ -3,13 +3,38 @@
 //! サイバーセキュリティ特化の推論ルール実装
 //! 悪性IP接続、ラテラルムーブ、特権アカウントの危険使用などの検知
 //! MLベース異常検知による時系列分析セキュリティイベント検知
+//! ラベル付きの過去イベントによる異常検知器のバックテストとしきい値選定
+//! 過去イベントから学習・永続化できる異常検知モデル
+//! STIX 2.1 による脅威インテリジェンスの取り込み・書き出し
+//! MISP / TAXII / URL リストからの脅威フィードの定期取り込み
+//! DNS・HTTP・レジストリ・メールイベントの検知 (DGA / なりすましドメイン、不審な UA、自動起動キー、フィッシング)
+//! アラートのリスクスコア算出 (確信度・資産重要度・脅威インテリジェンス) と抑制ウィンドウ
+//! MITRE ATT&CK の技術 ID によるアラートの注釈とルールのカバレッジ
+//! Sigma ルールの取り込み (SPARQL ルールへの変換と未対応構文の報告)
+//! GeoIP / ASN によるイベントの IP アドレスの国・組織情報の付与
 
 pub mod detectors;
 pub mod patterns;
 pub mod threat_intelligence;
 pub mod anomaly_detection;
+pub mod anomaly_models;
+pub mod stix;
+pub mod feeds;
+pub mod event_detectors;
+pub mod scoring;
+pub mod attack;
+pub mod sigma;
+pub mod geoip;
 
 pub use detectors::*;
 pub use patterns::*;
 pub use threat_intelligence::*;
 pub use anomaly_detection::*;
+pub use anomaly_models::*;
+pub use stix::*;
+pub use feeds::*;
+pub use event_detectors::*;
+pub use scoring::*;
+pub use attack::*;
+pub use sigma::*;
+pub use geoip::*;
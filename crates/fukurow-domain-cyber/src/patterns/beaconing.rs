@@ -0,0 +1,185 @@
+//! Beaconing periodicity
+//!
+//! C2 通信に典型的な、一定間隔で繰り返される接続を検知する。
+//! 接続間隔の変動係数 (標準偏差 / 平均) が小さいほど機械的な周期とみなす
+
+use super::{group_events, Detection, DetectionPattern, EventFilter, EventRecord, PatternError};
+
+/// Fires when a group's inter-event intervals are nearly constant
+///
+/// Jitter is the coefficient of variation of the intervals; `0.0` is a perfect
+/// metronome. Groups whose mean interval falls outside
+/// `[min_interval_secs, max_interval_secs]` are ignored, which excludes bursts
+/// (mean near zero) and once-a-day jobs.
+#[derive(Debug, Clone)]
+pub struct BeaconingPattern {
+    name: &'static str,
+    description: &'static str,
+    filter: EventFilter,
+    group_by: Vec<String>,
+    min_events: usize,
+    max_jitter: f64,
+    min_interval_secs: f64,
+    max_interval_secs: f64,
+    severity: String,
+}
+
+impl BeaconingPattern {
+    /// Defaults to connections grouped by `sourceIP` and `destIP`
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            description: "Periodic connections characteristic of command-and-control beaconing",
+            filter: EventFilter::new(),
+            group_by: vec!["sourceIP".to_string(), "destIP".to_string()],
+            min_events: 6,
+            max_jitter: 0.1,
+            min_interval_secs: 10.0,
+            max_interval_secs: 6.0 * 3600.0,
+            severity: "high".to_string(),
+        }
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn where_field(mut self, field: &str, value: &str) -> Self {
+        self.filter = self.filter.where_field(field, value);
+        self
+    }
+
+    /// Replace the grouping fields
+    pub fn group_by(mut self, fields: &[&str]) -> Self {
+        self.group_by = fields.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    pub fn with_min_events(mut self, min_events: usize) -> Self {
+        self.min_events = min_events;
+        self
+    }
+
+    pub fn with_max_jitter(mut self, max_jitter: f64) -> Self {
+        self.max_jitter = max_jitter;
+        self
+    }
+
+    pub fn with_interval_range_secs(mut self, min: f64, max: f64) -> Self {
+        self.min_interval_secs = min;
+        self.max_interval_secs = max;
+        self
+    }
+
+    pub fn with_severity(mut self, severity: &str) -> Self {
+        self.severity = severity.to_string();
+        self
+    }
+}
+
+impl DetectionPattern for BeaconingPattern {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        // 間隔が 2 つ以上ないと揺らぎを測れない
+        if self.min_events < 3 {
+            return Err(PatternError::invalid(self.name, "min_events must be at least 3"));
+        }
+        if !(self.max_jitter.is_finite() && self.max_jitter >= 0.0) {
+            return Err(PatternError::invalid(self.name, "max_jitter must not be negative"));
+        }
+        if !(self.min_interval_secs > 0.0 && self.min_interval_secs <= self.max_interval_secs) {
+            return Err(PatternError::invalid(self.name, "interval range must be positive and ordered"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        for (group, bucket) in group_events(events, &self.filter, &self.group_by) {
+            if bucket.len() < self.min_events.max(3) {
+                continue;
+            }
+
+            let intervals: Vec<f64> = bucket.windows(2)
+                .map(|pair| (pair[1].timestamp - pair[0].timestamp) as f64)
+                .collect();
+            let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+            if mean < self.min_interval_secs || mean > self.max_interval_secs {
+                continue;
+            }
+            let variance = intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+            let jitter = variance.sqrt() / mean;
+            if jitter > self.max_jitter {
+                continue;
+            }
+
+            let last = bucket[bucket.len() - 1];
+            detections.push(Detection {
+                pattern: self.name.to_string(),
+                severity: self.severity.clone(),
+                message: format!("Periodic activity every ~{:.0}s for {} ({} events)", mean, group, bucket.len()),
+                group: group.clone(),
+                events: bucket.iter().map(|e| e.id.clone()).collect(),
+                timestamp: last.timestamp,
+                details: serde_json::json!({
+                    "count": bucket.len(),
+                    "mean_interval_secs": mean,
+                    "jitter": jitter,
+                    "max_jitter": self.max_jitter,
+                    "first_seen": bucket[0].timestamp,
+                    "last_seen": last.timestamp,
+                }),
+            });
+        }
+        detections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::test_support::record;
+
+    fn connections(timestamps: &[i64], dest: &str) -> Vec<EventRecord> {
+        timestamps.iter().map(|ts| record(*ts, &[("sourceIP", "10.0.0.5"), ("destIP", dest)])).collect()
+    }
+
+    #[test]
+    fn test_regular_interval_with_small_jitter() {
+        let events = connections(&[0, 60, 121, 180, 241, 300, 362], "203.0.113.50");
+        let detections = BeaconingPattern::new("beacon").detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].group, "10.0.0.5|203.0.113.50");
+        assert!((detections[0].details["mean_interval_secs"].as_f64().unwrap() - 60.33).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_irregular_bursty_and_short_series_are_ignored() {
+        let pattern = BeaconingPattern::new("beacon");
+        assert!(pattern.detect(&connections(&[0, 5, 300, 320, 900, 2000, 2010], "a")).is_empty());
+        // 同時刻の連続は平均間隔 0 となり除外
+        assert!(pattern.detect(&connections(&[100; 8], "b")).is_empty());
+        assert!(pattern.detect(&connections(&[0, 60, 120, 180, 240], "c")).is_empty());
+        assert_eq!(pattern.clone().with_min_events(5).detect(&connections(&[0, 60, 120, 180, 240], "c")).len(), 1);
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(BeaconingPattern::new("beacon").with_min_events(2).validate().is_err());
+        assert!(BeaconingPattern::new("beacon").with_interval_range_secs(60.0, 30.0).validate().is_err());
+        assert!(BeaconingPattern::new("beacon").validate().is_ok());
+    }
+}
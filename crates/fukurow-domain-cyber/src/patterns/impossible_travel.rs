@@ -0,0 +1,268 @@
+//! Impossible travel
+//!
+//! 同一エンティティの連続するイベントの位置が、経過時間内に移動できない
+//! ほど離れている場合に検知する (例: 東京からのログインの 30 分後にロンドン)
+
+use super::{group_events, Detection, DetectionPattern, EventFilter, EventRecord, PatternError};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Latitude/longitude in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Great-circle distance (haversine)
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.lon - self.lon).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+    }
+}
+
+/// Resolves a location field value (usually an IP) to coordinates
+pub type GeoResolver = Arc<dyn Fn(&str) -> Option<GeoPoint> + Send + Sync>;
+
+/// Fires when consecutive events of an entity imply a speed above `max_speed_kmh`
+///
+/// Events whose location cannot be resolved are ignored. Hops shorter than
+/// `min_distance_km` are never reported, which absorbs geolocation inaccuracy;
+/// distant events with the same timestamp are always reported.
+#[derive(Clone)]
+pub struct ImpossibleTravelPattern {
+    name: &'static str,
+    description: &'static str,
+    filter: EventFilter,
+    entity_field: String,
+    location_field: String,
+    locations: HashMap<String, GeoPoint>,
+    resolver: Option<GeoResolver>,
+    max_speed_kmh: f64,
+    min_distance_km: f64,
+    severity: String,
+}
+
+impl ImpossibleTravelPattern {
+    /// Defaults to `user` entities located by `sourceIP`
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            description: "Consecutive activity from locations too far apart to travel between",
+            filter: EventFilter::new(),
+            entity_field: "user".to_string(),
+            location_field: "sourceIP".to_string(),
+            locations: HashMap::new(),
+            resolver: None,
+            // 旅客機の巡航速度程度
+            max_speed_kmh: 900.0,
+            min_distance_km: 100.0,
+            severity: "high".to_string(),
+        }
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn where_field(mut self, field: &str, value: &str) -> Self {
+        self.filter = self.filter.where_field(field, value);
+        self
+    }
+
+    pub fn entity_field(mut self, field: &str) -> Self {
+        self.entity_field = field.to_string();
+        self
+    }
+
+    pub fn location_field(mut self, field: &str) -> Self {
+        self.location_field = field.to_string();
+        self
+    }
+
+    /// Static location for one field value (takes precedence over the resolver)
+    pub fn with_location(mut self, value: &str, lat: f64, lon: f64) -> Self {
+        self.locations.insert(value.to_string(), GeoPoint::new(lat, lon));
+        self
+    }
+
+    pub fn with_resolver(mut self, resolver: impl Fn(&str) -> Option<GeoPoint> + Send + Sync + 'static) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    pub fn with_max_speed_kmh(mut self, max_speed_kmh: f64) -> Self {
+        self.max_speed_kmh = max_speed_kmh;
+        self
+    }
+
+    pub fn with_min_distance_km(mut self, min_distance_km: f64) -> Self {
+        self.min_distance_km = min_distance_km;
+        self
+    }
+
+    pub fn with_severity(mut self, severity: &str) -> Self {
+        self.severity = severity.to_string();
+        self
+    }
+
+    fn locate(&self, value: &str) -> Option<GeoPoint> {
+        self.locations.get(value).copied()
+            .or_else(|| self.resolver.as_ref().and_then(|resolve| resolve(value)))
+    }
+}
+
+impl DetectionPattern for ImpossibleTravelPattern {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        if !(self.max_speed_kmh.is_finite() && self.max_speed_kmh > 0.0) {
+            return Err(PatternError::invalid(self.name, "max_speed_kmh must be a positive number"));
+        }
+        if !(self.min_distance_km.is_finite() && self.min_distance_km >= 0.0) {
+            return Err(PatternError::invalid(self.name, "min_distance_km must not be negative"));
+        }
+        if self.locations.is_empty() && self.resolver.is_none() {
+            return Err(PatternError::invalid(self.name, "no locations or resolver configured"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let filter = self.filter.clone().require_field(&self.location_field);
+
+        let mut detections = Vec::new();
+        for (entity, bucket) in group_events(events, &filter, std::slice::from_ref(&self.entity_field)) {
+            let located = bucket.into_iter().filter_map(|event| {
+                let value = event.field(&self.location_field)?;
+                self.locate(value).map(|point| (event, value, point))
+            });
+
+            let mut previous: Option<(&EventRecord, &str, GeoPoint)> = None;
+            for current in located {
+                let (from, from_value, from_point) = match previous.replace(current) {
+                    Some(previous) => previous,
+                    None => continue,
+                };
+                let (to, to_value, to_point) = current;
+
+                let distance_km = from_point.distance_km(&to_point);
+                if distance_km < self.min_distance_km {
+                    continue;
+                }
+                let elapsed_secs = to.timestamp - from.timestamp;
+                let speed_kmh = (elapsed_secs > 0).then(|| distance_km / (elapsed_secs as f64 / 3600.0));
+                if speed_kmh.map(|speed| speed <= self.max_speed_kmh).unwrap_or(false) {
+                    continue;
+                }
+
+                detections.push(Detection {
+                    pattern: self.name.to_string(),
+                    severity: self.severity.clone(),
+                    message: format!("Impossible travel for {}: {} -> {} ({:.0} km in {}s)", entity, from_value, to_value, distance_km, elapsed_secs),
+                    group: entity.clone(),
+                    events: vec![from.id.clone(), to.id.clone()],
+                    timestamp: to.timestamp,
+                    details: serde_json::json!({
+                        "from": from_value,
+                        "to": to_value,
+                        "distance_km": distance_km,
+                        "elapsed_secs": elapsed_secs,
+                        // 同時刻のイベントは速度が無限大になるため null
+                        "speed_kmh": speed_kmh,
+                        "max_speed_kmh": self.max_speed_kmh,
+                    }),
+                });
+            }
+        }
+        detections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::test_support::record;
+
+    const TOKYO: &str = "198.51.100.1";
+    const OSAKA: &str = "198.51.100.2";
+    const LONDON: &str = "203.0.113.1";
+
+    fn pattern() -> ImpossibleTravelPattern {
+        ImpossibleTravelPattern::new("impossible_travel")
+            .with_location(TOKYO, 35.68, 139.69)
+            .with_location(OSAKA, 34.69, 135.50)
+            .with_location(LONDON, 51.51, -0.13)
+    }
+
+    fn login(ts: i64, user: &str, ip: &str) -> EventRecord {
+        record(ts, &[("user", user), ("sourceIP", ip)])
+    }
+
+    #[test]
+    fn test_distance() {
+        let tokyo = GeoPoint::new(35.68, 139.69);
+        let london = GeoPoint::new(51.51, -0.13);
+        assert!((tokyo.distance_km(&london) - 9560.0).abs() < 50.0);
+        assert_eq!(tokyo.distance_km(&tokyo), 0.0);
+    }
+
+    #[test]
+    fn test_plausible_and_impossible_hops() {
+        // 東京→大阪 (約 400km) を 2 時間: 移動可能
+        let events = vec![login(0, "alice", TOKYO), login(7200, "alice", OSAKA)];
+        assert!(pattern().detect(&events).is_empty());
+
+        let events = vec![login(0, "alice", TOKYO), login(3600, "alice", LONDON), login(3700, "bob", TOKYO)];
+        let detections = pattern().detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].group, "alice");
+        assert_eq!(detections[0].details["to"], LONDON);
+    }
+
+    #[test]
+    fn test_simultaneous_and_unlocatable_events() {
+        let events = vec![login(100, "alice", TOKYO), login(100, "alice", LONDON)];
+        let detections = pattern().detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert!(detections[0].details["speed_kmh"].is_null());
+
+        // 位置不明のイベントは飛ばし、前後の既知の位置同士を比較する
+        let events = vec![login(0, "alice", TOKYO), login(10, "alice", "192.0.2.1"), login(20, "alice", TOKYO)];
+        assert!(pattern().detect(&events).is_empty());
+    }
+
+    #[test]
+    fn test_resolver_and_validation() {
+        let resolved = ImpossibleTravelPattern::new("travel")
+            .with_resolver(|ip| if ip.starts_with("10.") { Some(GeoPoint::new(0.0, 0.0)) } else { Some(GeoPoint::new(0.0, 90.0)) });
+        let events = vec![login(0, "alice", "10.0.0.1"), login(60, "alice", "172.16.0.1")];
+        assert_eq!(resolved.detect(&events).len(), 1);
+
+        assert!(ImpossibleTravelPattern::new("travel").validate().is_err());
+        assert!(pattern().with_max_speed_kmh(0.0).validate().is_err());
+        assert!(pattern().validate().is_ok());
+    }
+}
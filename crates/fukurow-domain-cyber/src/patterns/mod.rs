@@ -0,0 +1,731 @@
+//! Attack pattern definitions and matching
+//!
+//! Reusable detection primitives
+//! (しきい値・希少値・移動不可能な移動・ビーコン周期性) をビルダーとして提供し、
+//! 組み合わせて `Rule` にコンパイルすることで新しい検知を組み立てる
+
+pub mod threshold;
+pub mod rare_value;
+pub mod impossible_travel;
+pub mod beaconing;
+
+pub use threshold::*;
+pub use rare_value::*;
+pub use impossible_travel::*;
+pub use beaconing::*;
+
+use async_trait::async_trait;
+use fukurow_rules::{SecurityAction, CyberEvent, Rule, RuleResult, RuleError, RdfStore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const CYBER_EVENT: &str = "http://example.org/CyberEvent";
+
+/// Separator between values of a field asserted by several triples
+pub const MULTI_VALUE_SEPARATOR: &str = "\n";
+
+/// Common attack patterns
+#[derive(Debug, Clone)]
+pub struct AttackPattern {
+    pub name: String,
+    pub description: String,
+    pub indicators: Vec<String>,
+    pub severity: String,
+    pub actions: Vec<SecurityAction>,
+}
+
+/// Pattern-based detector
+pub struct PatternDetector {
+    patterns: Vec<AttackPattern>,
+}
+
+impl PatternDetector {
+    pub fn new() -> Self {
+        let mut detector = Self {
+            patterns: Vec::new(),
+        };
+
+        detector.initialize_patterns();
+        detector
+    }
+
+    fn initialize_patterns(&mut self) {
+        // Ransomware pattern
+        self.patterns.push(AttackPattern {
+            name: "ransomware_execution".to_string(),
+            description: "Detect ransomware execution patterns".to_string(),
+            indicators: vec![
+                "encrypt".to_string(),
+                "bitcoin".to_string(),
+                "ransom".to_string(),
+                ".encrypted".to_string(),
+            ],
+            severity: "critical".to_string(),
+            actions: vec![
+                SecurityAction::IsolateHost {
+                    host_ip: "?host_ip".to_string(),
+                    reason: "Ransomware execution detected".to_string(),
+                },
+                SecurityAction::TerminateProcess {
+                    process_id: 0, // Will be filled by detection logic
+                    reason: "Ransomware process termination".to_string(),
+                },
+                SecurityAction::Alert {
+                    severity: "critical".to_string(),
+                    message: "Ransomware attack detected".to_string(),
+                    details: serde_json::json!({
+                        "pattern": "ransomware_execution",
+                        "indicators": ["encrypt", "bitcoin", "ransom"]
+                    }),
+                },
+            ],
+        });
+
+        // Data exfiltration pattern
+        self.patterns.push(AttackPattern {
+            name: "data_exfiltration".to_string(),
+            description: "Detect large data transfers to external IPs".to_string(),
+            indicators: vec![
+                "large_file_transfer".to_string(),
+                "external_ip".to_string(),
+                "unusual_traffic".to_string(),
+            ],
+            severity: "high".to_string(),
+            actions: vec![
+                SecurityAction::BlockConnection {
+                    source_ip: "?source_ip".to_string(),
+                    dest_ip: "?dest_ip".to_string(),
+                    reason: "Potential data exfiltration detected".to_string(),
+                },
+                SecurityAction::Alert {
+                    severity: "high".to_string(),
+                    message: "Data exfiltration attempt".to_string(),
+                    details: serde_json::json!({
+                        "pattern": "data_exfiltration",
+                        "traffic_type": "large_transfer"
+                    }),
+                },
+            ],
+        });
+
+        // Brute force attack pattern
+        self.patterns.push(AttackPattern {
+            name: "brute_force_login".to_string(),
+            description: "Detect brute force login attempts".to_string(),
+            indicators: vec![
+                "multiple_failed_logins".to_string(),
+                "same_user".to_string(),
+                "rapid_attempts".to_string(),
+            ],
+            severity: "medium".to_string(),
+            actions: vec![
+                SecurityAction::BlockConnection {
+                    source_ip: "?source_ip".to_string(),
+                    dest_ip: "?dest_ip".to_string(),
+                    reason: "Brute force login attempts detected".to_string(),
+                },
+                SecurityAction::Alert {
+                    severity: "medium".to_string(),
+                    message: "Brute force attack detected".to_string(),
+                    details: serde_json::json!({
+                        "pattern": "brute_force_login",
+                        "attempt_count": "?count"
+                    }),
+                },
+            ],
+        });
+    }
+
+    /// Match events against attack patterns
+    pub fn match_patterns(&self, events: &[CyberEvent]) -> Vec<SecurityAction> {
+        let mut actions = Vec::new();
+
+        for pattern in &self.patterns {
+            if self.pattern_matches_events(pattern, events) {
+                actions.extend(pattern.actions.clone());
+            }
+        }
+
+        actions
+    }
+
+    fn pattern_matches_events(&self, pattern: &AttackPattern, events: &[CyberEvent]) -> bool {
+        // Simple pattern matching - in real implementation, this would be more sophisticated
+        for event in events {
+            match event {
+                CyberEvent::ProcessExecution { command_line, .. } => {
+                    if pattern.indicators.iter().any(|indicator| command_line.contains(indicator)) {
+                        return true;
+                    }
+                }
+                CyberEvent::NetworkConnection { .. } => {
+                    // Network pattern matching would go here
+                }
+                CyberEvent::FileAccess { .. } => {
+                    // File access pattern matching would go here
+                }
+                CyberEvent::UserLogin { .. } => {
+                    // Login pattern matching would go here
+                }
+                CyberEvent::DnsQuery { query_name, .. } => {
+                    if pattern.indicators.iter().any(|indicator| query_name.contains(indicator)) {
+                        return true;
+                    }
+                }
+                CyberEvent::HttpRequest { url, user_agent, .. } => {
+                    if pattern.indicators.iter().any(|indicator| url.contains(indicator) || user_agent.contains(indicator)) {
+                        return true;
+                    }
+                }
+                CyberEvent::RegistryModification { key_path, .. } => {
+                    if pattern.indicators.iter().any(|indicator| key_path.contains(indicator)) {
+                        return true;
+                    }
+                }
+                CyberEvent::EmailReceived { subject, urls, .. } => {
+                    if pattern.indicators.iter().any(|indicator| subject.contains(indicator) || urls.iter().any(|url| url.contains(indicator))) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Get all available patterns
+    pub fn get_patterns(&self) -> &[AttackPattern] {
+        &self.patterns
+    }
+}
+
+impl Default for PatternDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Behavioral anomaly detector
+pub struct AnomalyDetector {
+    baseline_metrics: HashMap<String, f64>,
+}
+
+impl AnomalyDetector {
+    pub fn new() -> Self {
+        Self {
+            baseline_metrics: HashMap::new(),
+        }
+    }
+
+    /// Update baseline metrics
+    pub fn update_baseline(&mut self, metric_name: String, value: f64) {
+        self.baseline_metrics.insert(metric_name, value);
+    }
+
+    /// Detect anomalies in metrics
+    pub fn detect_anomaly(&self, metric_name: &str, current_value: f64, threshold: f64) -> Option<SecurityAction> {
+        if let Some(baseline) = self.baseline_metrics.get(metric_name) {
+            let deviation = (current_value - baseline).abs() / baseline;
+
+            if deviation > threshold {
+                return Some(SecurityAction::Alert {
+                    severity: "medium".to_string(),
+                    message: format!("Anomaly detected in {}", metric_name),
+                    details: serde_json::json!({
+                        "metric": metric_name,
+                        "baseline": baseline,
+                        "current": current_value,
+                        "deviation_percent": deviation * 100.0
+                    }),
+                });
+            }
+        }
+        None
+    }
+}
+
+impl Default for AnomalyDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pattern configuration errors
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum PatternError {
+    #[error("Invalid parameter for pattern {pattern}: {message}")]
+    InvalidParameter { pattern: String, message: String },
+
+    #[error("Rule {0} has no patterns")]
+    EmptyRule(String),
+}
+
+impl PatternError {
+    pub(crate) fn invalid(pattern: &str, message: impl Into<String>) -> Self {
+        PatternError::InvalidParameter { pattern: pattern.to_string(), message: message.into() }
+    }
+}
+
+/// Flattened view of one event, keyed by predicate local name
+/// (`sourceIP`, `destIP`, `user`, `commandLine`, ...)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Event subject in the store
+    pub id: String,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    pub fields: HashMap<String, String>,
+}
+
+impl EventRecord {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+
+    /// Every value of a multi-valued field (`url`, `attachment`, `resolvedIP`)
+    pub fn values(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.field(name).into_iter().flat_map(|value| value.split(MULTI_VALUE_SEPARATOR))
+    }
+
+    /// Same field names as the triples produced by event ingestion
+    pub fn from_event(event: &CyberEvent) -> Self {
+        let (timestamp, fields): (i64, Vec<(&str, String)>) = match event {
+            CyberEvent::NetworkConnection { source_ip, dest_ip, port, protocol, timestamp } => (*timestamp, vec![
+                ("sourceIP", source_ip.clone()),
+                ("destIP", dest_ip.clone()),
+                ("port", port.to_string()),
+                ("protocol", protocol.clone()),
+            ]),
+            CyberEvent::ProcessExecution { process_id, parent_process_id, command_line, user, timestamp } => {
+                let mut fields = vec![
+                    ("processId", process_id.to_string()),
+                    ("commandLine", command_line.clone()),
+                    ("user", user.clone()),
+                ];
+                if let Some(parent) = parent_process_id {
+                    fields.push(("parentProcessId", parent.to_string()));
+                }
+                (*timestamp, fields)
+            }
+            CyberEvent::FileAccess { file_path, access_type, user, process_id, timestamp } => (*timestamp, vec![
+                ("filePath", file_path.clone()),
+                ("accessType", access_type.clone()),
+                ("user", user.clone()),
+                ("processId", process_id.to_string()),
+            ]),
+            CyberEvent::UserLogin { user, source_ip, success, timestamp } => (*timestamp, vec![
+                ("user", user.clone()),
+                ("sourceIP", source_ip.clone()),
+                ("success", success.to_string()),
+            ]),
+            // 複数値の項目 (解決先 IP・URL・添付) は MULTI_VALUE_SEPARATOR で連結する
+            CyberEvent::DnsQuery { query_name, query_type, source_ip, resolved_ips, timestamp } => {
+                let mut fields = vec![
+                    ("queryName", query_name.clone()),
+                    ("queryType", query_type.clone()),
+                    ("sourceIP", source_ip.clone()),
+                ];
+                if !resolved_ips.is_empty() {
+                    fields.push(("resolvedIP", resolved_ips.join(MULTI_VALUE_SEPARATOR)));
+                }
+                (*timestamp, fields)
+            }
+            CyberEvent::HttpRequest { method, url, host, user_agent, source_ip, status_code, timestamp } => {
+                let mut fields = vec![
+                    ("httpMethod", method.clone()),
+                    ("url", url.clone()),
+                    ("host", host.clone()),
+                    ("userAgent", user_agent.clone()),
+                    ("sourceIP", source_ip.clone()),
+                ];
+                fields.extend(status_code.map(|code| ("statusCode", code.to_string())));
+                (*timestamp, fields)
+            }
+            CyberEvent::RegistryModification { key_path, value_name, value_data, operation, process_id, user, timestamp } => {
+                let mut fields = vec![
+                    ("registryKey", key_path.clone()),
+                    ("registryOperation", operation.clone()),
+                    ("processId", process_id.to_string()),
+                    ("user", user.clone()),
+                ];
+                fields.extend(value_name.clone().map(|name| ("registryValueName", name)));
+                fields.extend(value_data.clone().map(|data| ("registryValueData", data)));
+                (*timestamp, fields)
+            }
+            CyberEvent::EmailReceived { sender, recipient, subject, reply_to, urls, attachments, timestamp } => {
+                let mut fields = vec![
+                    ("sender", sender.clone()),
+                    ("recipient", recipient.clone()),
+                    ("emailSubject", subject.clone()),
+                ];
+                fields.extend(reply_to.clone().map(|address| ("replyTo", address)));
+                if !urls.is_empty() {
+                    fields.push(("url", urls.join(MULTI_VALUE_SEPARATOR)));
+                }
+                if !attachments.is_empty() {
+                    fields.push(("attachment", attachments.join(MULTI_VALUE_SEPARATOR)));
+                }
+                (*timestamp, fields)
+            }
+        };
+
+        Self {
+            id: format!("event:{}", timestamp),
+            timestamp,
+            fields: fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+        }
+    }
+
+    /// All `CyberEvent` subjects in the store, ordered by timestamp
+    pub fn from_store(store: &RdfStore) -> Vec<Self> {
+        let subjects: BTreeSet<&str> = store.find_triples(None, Some(RDF_TYPE), Some(CYBER_EVENT))
+            .into_iter()
+            .map(|stored| stored.triple.subject.as_str())
+            .collect();
+
+        let mut records: Vec<Self> = subjects.into_iter()
+            .filter_map(|subject| {
+                let mut fields: HashMap<String, String> = HashMap::new();
+                for stored in store.find_triples(Some(subject), None, None) {
+                    if stored.triple.predicate == RDF_TYPE {
+                        continue;
+                    }
+                    let name = local_name(&stored.triple.predicate).to_string();
+                    match fields.get_mut(&name) {
+                        Some(value) => {
+                            value.push_str(MULTI_VALUE_SEPARATOR);
+                            value.push_str(&stored.triple.object);
+                        }
+                        None => {
+                            fields.insert(name, stored.triple.object.to_string());
+                        }
+                    }
+                }
+                let timestamp = fields.get("timestamp")?.parse().ok()?;
+                Some(Self { id: subject.to_string(), timestamp, fields })
+            })
+            .collect();
+        records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        records
+    }
+}
+
+fn local_name(iri: &str) -> &str {
+    iri.rsplit(['#', '/']).next().filter(|name| !name.is_empty()).unwrap_or(iri)
+}
+
+/// Field constraints applied before a pattern looks at events
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventFilter {
+    /// `(field, None)` requires presence, `(field, Some(v))` requires equality
+    conditions: Vec<(String, Option<String>)>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn where_field(mut self, field: &str, value: &str) -> Self {
+        self.conditions.push((field.to_string(), Some(value.to_string())));
+        self
+    }
+
+    pub fn require_field(mut self, field: &str) -> Self {
+        self.conditions.push((field.to_string(), None));
+        self
+    }
+
+    pub fn matches(&self, event: &EventRecord) -> bool {
+        self.conditions.iter().all(|(field, expected)| match (event.field(field), expected) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+    }
+}
+
+/// Filter events and bucket them by the values of `fields`
+///
+/// 時刻順に並べたバケットを返す (グループ化フィールドを欠くイベントは除外)
+pub(crate) fn group_events<'a>(
+    events: &'a [EventRecord],
+    filter: &EventFilter,
+    fields: &[String],
+) -> BTreeMap<String, Vec<&'a EventRecord>> {
+    let mut groups: BTreeMap<String, Vec<&EventRecord>> = BTreeMap::new();
+    for event in events.iter().filter(|event| filter.matches(event)) {
+        let key: Option<Vec<&str>> = fields.iter().map(|field| event.field(field)).collect();
+        if let Some(key) = key {
+            groups.entry(key.join("|")).or_default().push(event);
+        }
+    }
+    for bucket in groups.values_mut() {
+        bucket.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+    }
+    groups
+}
+
+/// Finding produced by a pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Detection {
+    pub pattern: String,
+    pub severity: String,
+    pub message: String,
+    /// Group key the finding belongs to (`user`, `sourceIP|destIP`, ...)
+    pub group: String,
+    /// Subjects of the contributing events
+    pub events: Vec<String>,
+    /// Timestamp of the event that completed the finding
+    pub timestamp: i64,
+    pub details: serde_json::Value,
+}
+
+impl Detection {
+    pub fn to_action(&self) -> SecurityAction {
+        let mut details = self.details.clone();
+        if let Some(map) = details.as_object_mut() {
+            map.insert("pattern".to_string(), serde_json::json!(self.pattern));
+            map.insert("group".to_string(), serde_json::json!(self.group));
+            map.insert("events".to_string(), serde_json::json!(self.events));
+        }
+        SecurityAction::Alert {
+            severity: self.severity.clone(),
+            message: self.message.clone(),
+            details,
+        }
+    }
+}
+
+/// Parameterized detection primitive
+pub trait DetectionPattern: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    /// MITRE ATT&CK technique IDs this pattern detects
+    fn attack_techniques(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Check parameters before the pattern is compiled
+    fn validate(&self) -> Result<(), PatternError>;
+
+    /// Run the pattern over time-ordered events
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection>;
+
+    /// Compile this pattern alone into a rule
+    fn compile(self) -> Result<Box<dyn Rule>, PatternError>
+    where
+        Self: Sized + 'static,
+    {
+        PatternRule::new(self.name(), self.description()).with_pattern(self).compile()
+    }
+}
+
+/// How the patterns of a `PatternRule` are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatternMatchMode {
+    /// Any pattern firing produces its detections
+    Any,
+    /// Only groups flagged by every pattern are reported
+    All,
+}
+
+/// Rule assembled from one or more patterns
+pub struct PatternRule {
+    name: &'static str,
+    description: &'static str,
+    priority: i32,
+    mode: PatternMatchMode,
+    patterns: Vec<Box<dyn DetectionPattern>>,
+    techniques: Vec<String>,
+}
+
+impl PatternRule {
+    pub fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            description,
+            priority: 0,
+            mode: PatternMatchMode::Any,
+            patterns: Vec::new(),
+            techniques: Vec::new(),
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: impl DetectionPattern + 'static) -> Self {
+        self.patterns.push(Box::new(pattern));
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: PatternMatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// ATT&CK technique IDs the detections are tagged with
+    pub fn with_technique(mut self, technique: impl Into<String>) -> Self {
+        self.techniques.push(technique.into());
+        self
+    }
+
+    /// Validate every pattern and produce the rule
+    pub fn compile(self) -> Result<Box<dyn Rule>, PatternError> {
+        if self.patterns.is_empty() {
+            return Err(PatternError::EmptyRule(self.name.to_string()));
+        }
+        for pattern in &self.patterns {
+            pattern.validate()?;
+        }
+        Ok(Box::new(self))
+    }
+
+    /// Detections for the given events according to the match mode
+    pub fn evaluate(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let per_pattern: Vec<Vec<Detection>> = self.patterns.iter().map(|p| p.detect(events)).collect();
+        match self.mode {
+            PatternMatchMode::Any => per_pattern.into_iter().flatten().collect(),
+            PatternMatchMode::All => {
+                // 全パターンで検知されたグループのみ残す
+                let mut common: Option<BTreeSet<String>> = None;
+                for detections in &per_pattern {
+                    let groups: BTreeSet<String> = detections.iter().map(|d| d.group.clone()).collect();
+                    common = Some(match common {
+                        Some(common) => common.intersection(&groups).cloned().collect(),
+                        None => groups,
+                    });
+                }
+                let common = common.unwrap_or_default();
+                per_pattern.into_iter().flatten().filter(|d| common.contains(&d.group)).collect()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Rule for PatternRule {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        let mut techniques = self.techniques.clone();
+        for technique in self.patterns.iter().flat_map(|pattern| pattern.attack_techniques()) {
+            if !techniques.contains(&technique) {
+                techniques.push(technique);
+            }
+        }
+        techniques
+    }
+
+    async fn apply(&self, store: &RdfStore) -> Result<RuleResult, RuleError> {
+        let detections = self.evaluate(&EventRecord::from_store(store));
+        let mut metadata = HashMap::new();
+        metadata.insert("detections".to_string(), serde_json::json!(detections.len()));
+
+        Ok(RuleResult {
+            triples_to_add: vec![],
+            triples_to_remove: vec![],
+            actions: detections.iter().map(Detection::to_action).collect(),
+            violations: vec![],
+            metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::EventRecord;
+
+    /// Event with the given fields at `timestamp`
+    pub fn record(timestamp: i64, fields: &[(&str, &str)]) -> EventRecord {
+        EventRecord {
+            id: format!("event:{}:{}", timestamp, fields.iter().map(|(_, v)| *v).collect::<Vec<_>>().join(",")),
+            timestamp,
+            fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+
+    #[test]
+    fn test_event_records_from_store_match_from_event() {
+        let event = CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            success: false,
+            timestamp: 1_700_000_000,
+        };
+        let mut store = RdfStore::new();
+        let subject = "event:1700000000";
+        for (predicate, object) in [
+            (RDF_TYPE, CYBER_EVENT),
+            ("http://example.org/user", "alice"),
+            ("http://example.org/sourceIP", "10.0.0.1"),
+            ("http://example.org/success", "false"),
+            ("http://example.org/timestamp", "1700000000"),
+        ] {
+            let triple = Triple { subject: subject.to_string(), predicate: predicate.to_string(), object: object.to_string() };
+            store.insert(triple, GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        }
+
+        let records = EventRecord::from_store(&store);
+        assert_eq!(records.len(), 1);
+        let mut expected = EventRecord::from_event(&event);
+        expected.fields.insert("timestamp".to_string(), "1700000000".to_string());
+        assert_eq!(records[0], expected);
+    }
+
+    #[test]
+    fn test_all_mode_requires_every_pattern() {
+        use test_support::record;
+
+        let mut events: Vec<EventRecord> = (0..5)
+            .map(|i| record(i * 10, &[("user", "alice"), ("success", "false"), ("sourceIP", "10.0.0.1")]))
+            .collect();
+        events.extend((0..5).map(|i| record(i * 10, &[("user", "bob"), ("success", "false"), ("sourceIP", "10.0.0.2")])));
+        events.push(record(100, &[("user", "alice"), ("success", "true"), ("sourceIP", "10.0.0.1")]));
+
+        let failures = ThresholdPattern::new("failed_logins")
+            .where_field("success", "false")
+            .group_by("user")
+            .with_threshold(5)
+            .over_window_secs(60);
+        let success = ThresholdPattern::new("login_success")
+            .where_field("success", "true")
+            .group_by("user")
+            .with_threshold(1);
+
+        let rule = PatternRule::new("brute_force_success", "Successful login after repeated failures")
+            .with_pattern(failures)
+            .with_pattern(success)
+            .with_mode(PatternMatchMode::All);
+        let detections = rule.evaluate(&events);
+        assert_eq!(detections.len(), 2);
+        assert!(detections.iter().all(|d| d.group == "alice"));
+
+        assert!(matches!(
+            PatternRule::new("empty", "no patterns").compile(),
+            Err(PatternError::EmptyRule(_))
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+//! Rare-value detection
+//!
+//! エンティティごとの履歴の中でほとんど現れない値を検知する。
+//! 例: ユーザーが普段使わない送信元 IP、ホストで初めて実行されたコマンド
+
+use super::threshold::display_group;
+use super::{group_events, Detection, DetectionPattern, EventFilter, EventRecord, PatternError};
+use std::collections::HashMap;
+
+/// Fires for values of `field` seen at most `max_occurrences` times in a group's history
+///
+/// Groups with fewer than `min_history` events are skipped: without a baseline every
+/// value is rare. Without `per` fields the whole event population is one group.
+#[derive(Debug, Clone)]
+pub struct RareValuePattern {
+    name: &'static str,
+    description: &'static str,
+    filter: EventFilter,
+    field: String,
+    per: Vec<String>,
+    max_occurrences: usize,
+    min_history: usize,
+    severity: String,
+}
+
+impl RareValuePattern {
+    pub fn new(name: &'static str, field: &str) -> Self {
+        Self {
+            name,
+            description: "Value rarely seen in the entity's history",
+            filter: EventFilter::new(),
+            field: field.to_string(),
+            per: Vec::new(),
+            max_occurrences: 1,
+            min_history: 20,
+            severity: "low".to_string(),
+        }
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn where_field(mut self, field: &str, value: &str) -> Self {
+        self.filter = self.filter.where_field(field, value);
+        self
+    }
+
+    /// Build a separate baseline per value of `field` (repeatable)
+    pub fn per(mut self, field: &str) -> Self {
+        self.per.push(field.to_string());
+        self
+    }
+
+    pub fn with_max_occurrences(mut self, max_occurrences: usize) -> Self {
+        self.max_occurrences = max_occurrences;
+        self
+    }
+
+    pub fn with_min_history(mut self, min_history: usize) -> Self {
+        self.min_history = min_history;
+        self
+    }
+
+    pub fn with_severity(mut self, severity: &str) -> Self {
+        self.severity = severity.to_string();
+        self
+    }
+}
+
+impl DetectionPattern for RareValuePattern {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        if self.max_occurrences == 0 {
+            return Err(PatternError::invalid(self.name, "max_occurrences must be at least 1"));
+        }
+        if self.min_history <= self.max_occurrences {
+            return Err(PatternError::invalid(self.name, "min_history must exceed max_occurrences"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let filter = self.filter.clone().require_field(&self.field);
+
+        let mut detections = Vec::new();
+        for (group, bucket) in group_events(events, &filter, &self.per) {
+            if bucket.len() < self.min_history {
+                continue;
+            }
+
+            // 値ごとの出現イベント (初出順)
+            let mut order: Vec<&str> = Vec::new();
+            let mut occurrences: HashMap<&str, Vec<&EventRecord>> = HashMap::new();
+            for event in bucket.iter().copied() {
+                let value = event.field(&self.field).unwrap_or_default();
+                let seen = occurrences.entry(value).or_default();
+                if seen.is_empty() {
+                    order.push(value);
+                }
+                seen.push(event);
+            }
+
+            for value in order {
+                let seen = &occurrences[value];
+                if seen.len() > self.max_occurrences {
+                    continue;
+                }
+                let last = seen[seen.len() - 1];
+                detections.push(Detection {
+                    pattern: self.name.to_string(),
+                    severity: self.severity.clone(),
+                    message: format!("Rare {} {} for {}", self.field, value, display_group(&group)),
+                    group: group.clone(),
+                    events: seen.iter().map(|e| e.id.clone()).collect(),
+                    timestamp: last.timestamp,
+                    details: serde_json::json!({
+                        "field": self.field,
+                        "value": value,
+                        "occurrences": seen.len(),
+                        "history": bucket.len(),
+                        "distinct_values": occurrences.len(),
+                    }),
+                });
+            }
+        }
+        detections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::test_support::record;
+
+    fn logins(user: &str, ip: &str, count: i64, offset: i64) -> Vec<EventRecord> {
+        (0..count).map(|i| record(offset + i, &[("user", user), ("sourceIP", ip)])).collect()
+    }
+
+    fn pattern() -> RareValuePattern {
+        RareValuePattern::new("rare_login_source", "sourceIP").per("user").with_min_history(10)
+    }
+
+    #[test]
+    fn test_rare_value_against_baseline() {
+        let mut events = logins("alice", "10.0.0.1", 12, 0);
+        events.extend(logins("alice", "203.0.113.7", 1, 100));
+        events.extend(logins("bob", "203.0.113.7", 12, 0));
+
+        let detections = pattern().detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].group, "alice");
+        assert_eq!(detections[0].details["value"], "203.0.113.7");
+        assert_eq!(detections[0].details["history"], 13);
+    }
+
+    #[test]
+    fn test_short_history_is_not_judged() {
+        let mut events = logins("carol", "10.0.0.1", 5, 0);
+        events.extend(logins("carol", "198.51.100.1", 1, 10));
+        assert!(pattern().detect(&events).is_empty());
+    }
+
+    #[test]
+    fn test_occurrence_limit_and_validation() {
+        let mut events = logins("alice", "10.0.0.1", 20, 0);
+        events.extend(logins("alice", "198.51.100.1", 2, 50));
+        assert!(pattern().detect(&events).is_empty());
+
+        let detections = pattern().with_max_occurrences(2).detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].events.len(), 2);
+
+        assert!(pattern().with_max_occurrences(0).validate().is_err());
+        assert!(pattern().with_min_history(1).validate().is_err());
+    }
+}
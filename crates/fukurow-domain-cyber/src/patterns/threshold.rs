@@ -0,0 +1,241 @@
+//! Threshold over a sliding time window
+//!
+//! 「N 秒以内に M 件以上」型の検知。例: 同一ユーザーのログイン失敗が
+//! 5 分間に 10 回、送信元 IP ごとの接続先ポート数 (distinct) が 1 分間に 100 以上
+
+use super::{group_events, Detection, DetectionPattern, EventFilter, EventRecord, PatternError};
+use std::collections::HashMap;
+
+/// Fires when a group accumulates `threshold` events (or distinct values) within `window_secs`
+///
+/// The window is inclusive: events exactly `window_secs` apart fall in the same window.
+/// After firing, counting restarts with the next event so that one sustained burst
+/// yields one detection per `threshold` events rather than one per event.
+#[derive(Debug, Clone)]
+pub struct ThresholdPattern {
+    name: &'static str,
+    description: &'static str,
+    filter: EventFilter,
+    group_by: Vec<String>,
+    distinct_field: Option<String>,
+    threshold: usize,
+    window_secs: u64,
+    severity: String,
+}
+
+impl ThresholdPattern {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            description: "Event count exceeds a threshold within a time window",
+            filter: EventFilter::new(),
+            group_by: Vec::new(),
+            distinct_field: None,
+            threshold: 5,
+            window_secs: 300,
+            severity: "medium".to_string(),
+        }
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn where_field(mut self, field: &str, value: &str) -> Self {
+        self.filter = self.filter.where_field(field, value);
+        self
+    }
+
+    /// Count per value of `field` (repeatable for compound keys)
+    pub fn group_by(mut self, field: &str) -> Self {
+        self.group_by.push(field.to_string());
+        self
+    }
+
+    /// Count distinct values of `field` instead of events
+    pub fn count_distinct(mut self, field: &str) -> Self {
+        self.distinct_field = Some(field.to_string());
+        self
+    }
+
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn over_window_secs(mut self, window_secs: u64) -> Self {
+        self.window_secs = window_secs;
+        self
+    }
+
+    pub fn with_severity(mut self, severity: &str) -> Self {
+        self.severity = severity.to_string();
+        self
+    }
+}
+
+impl DetectionPattern for ThresholdPattern {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn validate(&self) -> Result<(), PatternError> {
+        if self.threshold == 0 {
+            return Err(PatternError::invalid(self.name, "threshold must be at least 1"));
+        }
+        if self.window_secs > i64::MAX as u64 {
+            return Err(PatternError::invalid(self.name, "window is too large"));
+        }
+        Ok(())
+    }
+
+    fn detect(&self, events: &[EventRecord]) -> Vec<Detection> {
+        let mut filter = self.filter.clone();
+        if let Some(field) = &self.distinct_field {
+            filter = filter.require_field(field);
+        }
+        let window = self.window_secs.min(i64::MAX as u64) as i64;
+
+        let mut detections = Vec::new();
+        for (group, bucket) in group_events(events, &filter, &self.group_by) {
+            let mut start = 0;
+            let mut distinct: HashMap<&str, usize> = HashMap::new();
+
+            for end in 0..bucket.len() {
+                if let Some(field) = &self.distinct_field {
+                    *distinct.entry(bucket[end].field(field).unwrap_or_default()).or_insert(0) += 1;
+                }
+                // ウィンドウ外に出た古いイベントを落とす
+                while bucket[end].timestamp.saturating_sub(bucket[start].timestamp) > window {
+                    if let Some(field) = &self.distinct_field {
+                        let value = bucket[start].field(field).unwrap_or_default();
+                        if let Some(count) = distinct.get_mut(value) {
+                            *count -= 1;
+                            if *count == 0 {
+                                distinct.remove(value);
+                            }
+                        }
+                    }
+                    start += 1;
+                }
+
+                let count = if self.distinct_field.is_some() { distinct.len() } else { end - start + 1 };
+                if count < self.threshold {
+                    continue;
+                }
+
+                let window_events = &bucket[start..=end];
+                let mut details = serde_json::json!({
+                    "count": count,
+                    "threshold": self.threshold,
+                    "window_secs": self.window_secs,
+                    "first_seen": window_events[0].timestamp,
+                    "last_seen": bucket[end].timestamp,
+                });
+                if let Some(field) = &self.distinct_field {
+                    let mut values: Vec<&str> = distinct.keys().copied().collect();
+                    values.sort_unstable();
+                    details["distinct_field"] = serde_json::json!(field);
+                    details["values"] = serde_json::json!(values);
+                }
+                detections.push(Detection {
+                    pattern: self.name.to_string(),
+                    severity: self.severity.clone(),
+                    message: format!("{} reached {} within {}s for {}", self.name, count, self.window_secs, display_group(&group)),
+                    group: group.clone(),
+                    events: window_events.iter().map(|e| e.id.clone()).collect(),
+                    timestamp: bucket[end].timestamp,
+                    details,
+                });
+
+                start = end + 1;
+                distinct.clear();
+            }
+        }
+        detections
+    }
+}
+
+pub(crate) fn display_group(group: &str) -> &str {
+    if group.is_empty() { "all events" } else { group }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::test_support::record;
+
+    fn failures(timestamps: &[i64], user: &str) -> Vec<EventRecord> {
+        timestamps.iter().map(|ts| record(*ts, &[("user", user), ("success", "false")])).collect()
+    }
+
+    fn pattern() -> ThresholdPattern {
+        ThresholdPattern::new("failed_logins")
+            .where_field("success", "false")
+            .group_by("user")
+            .with_threshold(3)
+            .over_window_secs(60)
+    }
+
+    #[test]
+    fn test_window_boundary_is_inclusive() {
+        assert_eq!(pattern().detect(&failures(&[0, 30, 60], "alice")).len(), 1);
+        assert!(pattern().detect(&failures(&[0, 30, 61], "alice")).is_empty());
+    }
+
+    #[test]
+    fn test_burst_restarts_counting_after_firing() {
+        let detections = pattern().detect(&failures(&[0, 1, 2, 3, 4, 5, 6], "alice"));
+        assert_eq!(detections.len(), 2);
+        assert_eq!(detections[0].events.len(), 3);
+        assert_eq!(detections[1].timestamp, 5);
+    }
+
+    #[test]
+    fn test_groups_and_filters_are_independent() {
+        let mut events = failures(&[0, 10], "alice");
+        events.extend(failures(&[20], "bob"));
+        events.push(record(30, &[("user", "alice"), ("success", "true")]));
+        events.push(record(40, &[("success", "false")]));
+        assert!(pattern().detect(&events).is_empty());
+
+        events.extend(failures(&[50], "alice"));
+        let detections = pattern().detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].group, "alice");
+    }
+
+    #[test]
+    fn test_distinct_counting() {
+        let scan = ThresholdPattern::new("port_scan")
+            .group_by("sourceIP")
+            .count_distinct("port")
+            .with_threshold(3)
+            .over_window_secs(10);
+        let events: Vec<EventRecord> = [("22", 0), ("22", 1), ("80", 2), ("22", 15), ("443", 16), ("8080", 17)]
+            .iter()
+            .map(|(port, ts)| record(*ts, &[("sourceIP", "10.0.0.9"), ("port", port)]))
+            .collect();
+
+        let detections = scan.detect(&events);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].timestamp, 17);
+        assert_eq!(detections[0].details["values"], serde_json::json!(["22", "443", "8080"]));
+    }
+
+    #[test]
+    fn test_zero_threshold_is_rejected() {
+        assert!(pattern().with_threshold(0).validate().is_err());
+        assert!(pattern().compile().is_ok());
+    }
+}
@@ -0,0 +1,477 @@
+//! Alert severity scoring and suppression
+//!
+//! ルールが固定文字列で付けた severity を、数値のリスクスコアから付け直す。
+//! - スコア = ルールの確信度・資産の重要度 (グラフから参照)・脅威インテリジェンス一致の重み付き平均 (0〜100)
+//! - 同じアラートは抑制ウィンドウの間は再送せず、ウィンドウ明けに抑制件数を付けて 1 回だけ出す
+//! - 深刻度が上がった場合はウィンドウ内でも出す
+
+use crate::threat_intelligence::{IndicatorType, ThreatFeed};
+use fukurow_core::model::SecurityAction;
+use fukurow_core::term::RdfTerm;
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Predicate giving an asset's criticality (a number in 0..=1, or low / medium / high / critical)
+pub const CRITICALITY_PREDICATE: &str = "http://example.org/criticality";
+
+/// Alert severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// Parse a severity label; unknown labels are `None`
+    pub fn parse(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "info" | "informational" => Some(Severity::Info),
+            "low" => Some(Severity::Low),
+            "medium" | "moderate" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+
+    /// Confidence assumed for a rule that only states a severity
+    fn prior_confidence(&self) -> f64 {
+        match self {
+            Severity::Info => 0.2,
+            Severity::Low => 0.4,
+            Severity::Medium => 0.6,
+            Severity::High => 0.8,
+            Severity::Critical => 0.95,
+        }
+    }
+}
+
+/// Weights and severity bands of [`AlertScorer`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringConfig {
+    pub confidence_weight: f64,
+    pub criticality_weight: f64,
+    pub intel_weight: f64,
+    /// Criticality of assets without a criticality triple
+    pub default_criticality: f64,
+    /// Lowest score of medium, high and critical
+    pub medium_threshold: f64,
+    pub high_threshold: f64,
+    pub critical_threshold: f64,
+    /// Lowest score of low; anything below is info
+    pub low_threshold: f64,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            confidence_weight: 0.4,
+            criticality_weight: 0.3,
+            intel_weight: 0.3,
+            default_criticality: 0.3,
+            low_threshold: 20.0,
+            medium_threshold: 40.0,
+            high_threshold: 65.0,
+            critical_threshold: 85.0,
+        }
+    }
+}
+
+impl ScoringConfig {
+    pub fn severity_for(&self, score: f64) -> Severity {
+        if score >= self.critical_threshold {
+            Severity::Critical
+        } else if score >= self.high_threshold {
+            Severity::High
+        } else if score >= self.medium_threshold {
+            Severity::Medium
+        } else if score >= self.low_threshold {
+            Severity::Low
+        } else {
+            Severity::Info
+        }
+    }
+}
+
+/// Risk score of an alert and the factors behind it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskScore {
+    /// 0..=100
+    pub score: f64,
+    pub severity: Severity,
+    pub confidence: f64,
+    pub criticality: f64,
+    /// Most critical asset referenced by the alert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Alert values found in the threat feed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub intel_matches: Vec<String>,
+}
+
+/// Computes risk scores of alerts
+pub struct AlertScorer<'a> {
+    config: ScoringConfig,
+    feed: Option<&'a ThreatFeed>,
+}
+
+impl<'a> AlertScorer<'a> {
+    pub fn new(config: ScoringConfig) -> Self {
+        Self { config, feed: None }
+    }
+
+    /// Count alert values present in `feed` as threat intel matches
+    pub fn with_threat_feed(mut self, feed: &'a ThreatFeed) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
+    pub fn config(&self) -> &ScoringConfig {
+        &self.config
+    }
+
+    /// Score an alert against `store`; other actions are `None`
+    ///
+    /// 確信度は details の `confidence` (0〜1)、なければルールが付けた severity から見積もる。
+    /// 資産は details の文字列値そのもの、またはその値を目的語に持つノード (IP を持つホストなど)
+    pub fn score(&self, action: &SecurityAction, store: &RdfStore) -> Option<RiskScore> {
+        let SecurityAction::Alert { severity, details, .. } = action else {
+            return None;
+        };
+
+        let confidence = details.get("confidence").and_then(|value| value.as_f64())
+            .or_else(|| Severity::parse(severity).map(|severity| severity.prior_confidence()))
+            .unwrap_or(0.5)
+            .clamp(0.0, 1.0);
+
+        let values = detail_values(details);
+        let (asset, criticality) = values.iter()
+            .filter_map(|value| asset_criticality(store, value))
+            .fold(None, |best: Option<(String, f64)>, candidate| match best {
+                Some(best) if best.1 >= candidate.1 => Some(best),
+                _ => Some(candidate),
+            })
+            .map_or((None, self.config.default_criticality), |(asset, criticality)| (Some(asset), criticality));
+
+        let (intel_matches, intel) = self.intel_matches(&values);
+
+        let config = &self.config;
+        let total_weight = config.confidence_weight + config.criticality_weight + config.intel_weight;
+        let weighted = config.confidence_weight * confidence + config.criticality_weight * criticality + config.intel_weight * intel;
+        let score = if total_weight > 0.0 { (100.0 * weighted / total_weight).clamp(0.0, 100.0) } else { 0.0 };
+        let score = (score * 10.0).round() / 10.0;
+
+        Some(RiskScore { score, severity: config.severity_for(score), confidence, criticality, asset, intel_matches })
+    }
+
+    /// Replace an alert's severity with its scored one and add the score to its details
+    pub fn apply(&self, action: SecurityAction, store: &RdfStore) -> SecurityAction {
+        let Some(risk) = self.score(&action, store) else {
+            return action;
+        };
+        match action {
+            SecurityAction::Alert { message, mut details, .. } => {
+                if let serde_json::Value::Object(map) = &mut details {
+                    map.insert("risk_score".to_string(), serde_json::json!(risk.score));
+                    map.insert("risk".to_string(), serde_json::to_value(&risk).unwrap_or_default());
+                }
+                SecurityAction::Alert { severity: risk.severity.as_str().to_string(), message, details }
+            }
+            other => other,
+        }
+    }
+
+    /// Matched values and the intel factor (severity of the worst matching indicator)
+    fn intel_matches(&self, values: &BTreeSet<String>) -> (Vec<String>, f64) {
+        let Some(feed) = self.feed else {
+            return (Vec::new(), 0.0);
+        };
+        let mut matches = Vec::new();
+        let mut intel: f64 = 0.0;
+        for value in values {
+            let indicator = [IndicatorType::IpAddress, IndicatorType::Domain, IndicatorType::Url, IndicatorType::FileHash, IndicatorType::Email]
+                .into_iter()
+                .find_map(|indicator_type| feed.is_threat(value, indicator_type));
+            if let Some(indicator) = indicator {
+                matches.push(value.clone());
+                let factor = Severity::parse(&indicator.severity).map_or(0.7, |severity| severity.prior_confidence());
+                intel = intel.max(factor);
+            }
+        }
+        (matches, intel)
+    }
+}
+
+impl Default for AlertScorer<'_> {
+    fn default() -> Self {
+        Self::new(ScoringConfig::default())
+    }
+}
+
+/// String values of an alert's details (nested objects and arrays included)
+fn detail_values(details: &serde_json::Value) -> BTreeSet<String> {
+    fn collect(value: &serde_json::Value, values: &mut BTreeSet<String>) {
+        match value {
+            serde_json::Value::String(text) if !text.is_empty() && !text.starts_with('?') => {
+                values.insert(text.clone());
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|item| collect(item, values)),
+            serde_json::Value::Object(map) => map.values().for_each(|item| collect(item, values)),
+            _ => {}
+        }
+    }
+    let mut values = BTreeSet::new();
+    collect(details, &mut values);
+    values
+}
+
+/// Highest criticality of `value` or of a node having `value` as an object
+fn asset_criticality(store: &RdfStore, value: &str) -> Option<(String, f64)> {
+    let mut nodes = vec![value.to_string()];
+    nodes.extend(store.find_triples(None, None, Some(value)).into_iter().map(|stored| stored.triple.subject.to_string()));
+
+    nodes.into_iter()
+        .filter_map(|node| {
+            let criticality = store.find_triples(Some(&node), Some(CRITICALITY_PREDICATE), None).into_iter()
+                .filter_map(|stored| parse_criticality(RdfTerm::parse(&stored.triple.object).value()))
+                .fold(None, |max: Option<f64>, c| Some(max.map_or(c, |max| max.max(c))))?;
+            Some((node, criticality))
+        })
+        .fold(None, |best: Option<(String, f64)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+}
+
+fn parse_criticality(value: &str) -> Option<f64> {
+    if let Ok(number) = value.trim().parse::<f64>() {
+        // 0〜10 の尺度も受け付ける
+        let number = if number > 1.0 { number / 10.0 } else { number };
+        return Some(number.clamp(0.0, 1.0));
+    }
+    Severity::parse(value).map(|severity| match severity {
+        Severity::Info => 0.1,
+        Severity::Low => 0.25,
+        Severity::Medium => 0.5,
+        Severity::High => 0.75,
+        Severity::Critical => 1.0,
+    })
+}
+
+/// Suppression window bookkeeping of one alert
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuppressionEntry {
+    pub last_emitted_ms: i64,
+    pub severity: Severity,
+    /// Occurrences suppressed since the last emission
+    pub suppressed: u64,
+}
+
+/// Drops repeats of the same alert within a window
+///
+/// 同じアラートかどうかは severity と details の `risk`/`risk_score`/`suppressed_count` を除いた
+/// メッセージと details で判定する
+#[derive(Debug, Clone)]
+pub struct AlertSuppressor {
+    window_ms: i64,
+    entries: HashMap<String, SuppressionEntry>,
+}
+
+impl AlertSuppressor {
+    pub fn new(window_ms: i64) -> Self {
+        Self { window_ms, entries: HashMap::new() }
+    }
+
+    pub fn window_ms(&self) -> i64 {
+        self.window_ms
+    }
+
+    /// Alerts to emit at `now_ms`; non-alert actions always pass
+    pub fn filter(&mut self, actions: Vec<SecurityAction>, now_ms: i64) -> Vec<SecurityAction> {
+        actions.into_iter().filter_map(|action| self.admit(action, now_ms)).collect()
+    }
+
+    /// Emit `action` unless a copy was emitted within the window at the same or higher severity
+    ///
+    /// ウィンドウ明けに出すアラートには、その間に抑制した件数を `suppressed_count` として付ける
+    pub fn admit(&mut self, action: SecurityAction, now_ms: i64) -> Option<SecurityAction> {
+        let Some(key) = alert_fingerprint(&action) else {
+            return Some(action);
+        };
+        let severity = match &action {
+            SecurityAction::Alert { severity, .. } => Severity::parse(severity).unwrap_or(Severity::Medium),
+            _ => Severity::Medium,
+        };
+
+        let suppressed = match self.entries.get_mut(&key) {
+            Some(entry) if now_ms - entry.last_emitted_ms < self.window_ms && severity <= entry.severity => {
+                entry.suppressed += 1;
+                return None;
+            }
+            Some(entry) => std::mem::take(&mut entry.suppressed),
+            None => 0,
+        };
+        self.entries.insert(key, SuppressionEntry { last_emitted_ms: now_ms, severity, suppressed: 0 });
+
+        match action {
+            SecurityAction::Alert { severity, message, mut details } if suppressed > 0 => {
+                if let serde_json::Value::Object(map) = &mut details {
+                    map.insert("suppressed_count".to_string(), serde_json::json!(suppressed));
+                }
+                Some(SecurityAction::Alert { severity, message, details })
+            }
+            action => Some(action),
+        }
+    }
+
+    /// Forget alerts whose window ended before `now_ms` with nothing suppressed
+    pub fn prune(&mut self, now_ms: i64) {
+        let window_ms = self.window_ms;
+        self.entries.retain(|_, entry| entry.suppressed > 0 || now_ms - entry.last_emitted_ms < window_ms);
+    }
+
+    pub fn entries(&self) -> &HashMap<String, SuppressionEntry> {
+        &self.entries
+    }
+}
+
+/// Identity of an alert for suppression; `None` for other actions
+pub fn alert_fingerprint(action: &SecurityAction) -> Option<String> {
+    let SecurityAction::Alert { message, details, .. } = action else {
+        return None;
+    };
+    let mut details = details.clone();
+    if let serde_json::Value::Object(map) = &mut details {
+        for volatile in ["risk", "risk_score", "suppressed_count"] {
+            map.remove(volatile);
+        }
+    }
+    // serde_json のオブジェクトはキー順に並ぶので、文字列化した結果で比較できる
+    Some(format!("{}\u{1f}{}", message, details))
+}
+
+/// Scoring followed by suppression, applied to each reasoning cycle's actions
+pub struct AlertTriage<'a> {
+    pub scorer: AlertScorer<'a>,
+    pub suppressor: AlertSuppressor,
+}
+
+impl<'a> AlertTriage<'a> {
+    pub fn new(scorer: AlertScorer<'a>, suppressor: AlertSuppressor) -> Self {
+        Self { scorer, suppressor }
+    }
+
+    pub fn process(&mut self, actions: Vec<SecurityAction>, store: &RdfStore, now_ms: i64) -> Vec<SecurityAction> {
+        let scored = actions.into_iter().map(|action| self.scorer.apply(action, store)).collect();
+        self.suppressor.filter(scored, now_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threat_intelligence::ThreatIndicator;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+
+    fn alert(severity: &str, dest_ip: &str) -> SecurityAction {
+        SecurityAction::Alert {
+            severity: severity.to_string(),
+            message: "Connection to malicious IP detected".to_string(),
+            details: serde_json::json!({ "destination_ip": dest_ip, "connection_id": "conn:1" }),
+        }
+    }
+
+    #[test]
+    fn test_score_uses_criticality_and_intel() {
+        let mut store = RdfStore::new();
+        let provenance = Provenance::Sensor { source: "cmdb".to_string(), confidence: None };
+        store.insert(Triple {
+            subject: "host:db01".to_string(),
+            predicate: "http://example.org/ipAddress".to_string(),
+            object: "10.0.0.5".to_string(),
+        }, GraphId::Default, provenance.clone());
+        store.insert(Triple {
+            subject: "host:db01".to_string(),
+            predicate: CRITICALITY_PREDICATE.to_string(),
+            object: "\"critical\"".to_string(),
+        }, GraphId::Default, provenance);
+
+        let scorer = AlertScorer::default();
+        let plain = scorer.score(&alert("high", "10.9.9.9"), &store).unwrap();
+        let critical_asset = scorer.score(&alert("high", "10.0.0.5"), &store).unwrap();
+        assert_eq!(critical_asset.asset.as_deref(), Some("host:db01"));
+        assert_eq!(critical_asset.criticality, 1.0);
+        assert!(critical_asset.score > plain.score);
+
+        let mut feed = ThreatFeed::new();
+        feed.add_indicator(ThreatIndicator {
+            id: "ind-1".to_string(),
+            indicator_type: IndicatorType::IpAddress,
+            value: "10.0.0.5".to_string(),
+            threat_type: "c2".to_string(),
+            severity: "critical".to_string(),
+            sources: vec![],
+            first_seen: 0,
+            last_seen: 0,
+            tags: vec![],
+        });
+        let with_intel = AlertScorer::default().with_threat_feed(&feed);
+        let risk = with_intel.score(&alert("high", "10.0.0.5"), &store).unwrap();
+        assert_eq!(risk.intel_matches, vec!["10.0.0.5".to_string()]);
+        // 0.4*0.8 + 0.3*1.0 + 0.3*0.95 = 0.905
+        assert_eq!(risk.score, 90.5);
+        assert_eq!(risk.severity, Severity::Critical);
+
+        match with_intel.apply(alert("low", "10.0.0.5"), &store) {
+            SecurityAction::Alert { severity, details, .. } => {
+                assert_eq!(severity, "high");
+                assert!(details["risk_score"].as_f64().is_some());
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        assert!(scorer.score(&SecurityAction::IsolateHost { host_ip: "10.0.0.5".to_string(), reason: "test".to_string() }, &store).is_none());
+    }
+
+    #[test]
+    fn test_suppression_window() {
+        let mut suppressor = AlertSuppressor::new(60_000);
+        assert_eq!(suppressor.filter(vec![alert("medium", "10.0.0.5")], 0).len(), 1);
+        assert!(suppressor.filter(vec![alert("medium", "10.0.0.5")], 10_000).is_empty());
+        assert!(suppressor.filter(vec![alert("low", "10.0.0.5")], 20_000).is_empty());
+        // 別のアラートは抑制しない
+        assert_eq!(suppressor.filter(vec![alert("medium", "10.0.0.6")], 20_000).len(), 1);
+
+        // 深刻度が上がればウィンドウ内でも出す
+        let escalated = suppressor.filter(vec![alert("high", "10.0.0.5")], 30_000);
+        match &escalated[..] {
+            [SecurityAction::Alert { details, .. }] => assert_eq!(details["suppressed_count"], 2),
+            other => panic!("unexpected actions: {:?}", other),
+        }
+
+        assert!(suppressor.filter(vec![alert("high", "10.0.0.5")], 60_000).is_empty());
+        let after_window = suppressor.filter(vec![alert("high", "10.0.0.5")], 91_000);
+        match &after_window[..] {
+            [SecurityAction::Alert { details, .. }] => assert_eq!(details["suppressed_count"], 1),
+            other => panic!("unexpected actions: {:?}", other),
+        }
+
+        suppressor.prune(200_000);
+        assert!(suppressor.entries().is_empty());
+    }
+}
@@ -0,0 +1,886 @@
+//! Sigma rule import
+//!
+//! Sigma の YAML ルールを読み、logsource と検知条件を CyberEvent のトリプル
+//! (`http://example.org/<項目>`) に対応付けて [`SparqlRuleDefinition`] (CONSTRUCT) に変換する。
+//! 一致したイベントには [`SIGMA_MATCH_PREDICATE`] でルールの IRI を付け、レベルに応じたアラートを出す。
+//!
+//! SPARQL ルールは UNION を書けないため、条件は選言標準形に展開し、選言肢ごとに 1 つのルールを作る。
+//! 対応付けられない構文 (未知の項目・修飾子、集計条件など) は黙って落とさず
+//! [`UnsupportedConstruct`] として報告し、その場合はルールを出力しない
+
+use fukurow_core::prefix::PrefixMap;
+use fukurow_rules::{RuleError, SparqlRule, SparqlRuleDefinition};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+/// Namespace of the CyberEvent field predicates
+pub const EVENT_NAMESPACE: &str = "http://example.org/";
+/// Namespace of imported Sigma rules
+pub const SIGMA_NAMESPACE: &str = "http://example.org/sigma/";
+/// Predicate linking a matching event to the Sigma rule it matched
+pub const SIGMA_MATCH_PREDICATE: &str = "http://example.org/sigma/matches";
+
+/// Upper bound on the rules generated from one Sigma rule
+const MAX_BRANCHES: usize = 32;
+
+/// Sigma import errors (malformed rules; unsupported constructs are reported in [`SigmaConversion`])
+#[derive(Debug, thiserror::Error)]
+pub enum SigmaError {
+    #[error("Invalid Sigma YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("Invalid Sigma rule: {0}")]
+    Invalid(String),
+}
+
+/// `logsource` section
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigmaLogsource {
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub product: Option<String>,
+    #[serde(default)]
+    pub service: Option<String>,
+}
+
+/// A parsed Sigma rule
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SigmaRule {
+    pub title: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub logsource: SigmaLogsource,
+    /// Named selections plus `condition`
+    pub detection: Mapping,
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub falsepositives: Vec<String>,
+}
+
+impl SigmaRule {
+    pub fn parse(yaml: &str) -> Result<Self, SigmaError> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// ATT&CK technique IDs from the `attack.tNNNN[.NNN]` tags
+    pub fn attack_techniques(&self) -> Vec<String> {
+        self.tags.iter()
+            .filter_map(|tag| tag.strip_prefix("attack."))
+            .filter(|id| id.len() > 1 && (id.starts_with('t') || id.starts_with('T')) && id[1..].starts_with(|c: char| c.is_ascii_digit()))
+            .map(|id| id.to_ascii_uppercase())
+            .collect()
+    }
+
+    /// Alert severity for the Sigma `level`
+    pub fn severity(&self) -> &'static str {
+        match self.level.as_deref() {
+            Some("informational") => "info",
+            Some("low") => "low",
+            Some("high") => "high",
+            Some("critical") => "critical",
+            _ => "medium",
+        }
+    }
+}
+
+/// A Sigma construct the importer cannot translate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsupportedConstruct {
+    /// Where in the rule (`logsource`, `detection.selection`, `detection.condition`, ...)
+    pub location: String,
+    pub construct: String,
+    pub reason: String,
+}
+
+/// Result of converting one Sigma rule
+#[derive(Debug, Clone, Serialize)]
+pub struct SigmaConversion {
+    pub title: String,
+    pub id: Option<String>,
+    /// IRI the generated rules attach to matching events
+    pub rule_iri: String,
+    /// One rule per disjunct of the condition; empty when anything was unsupported
+    pub rules: Vec<SparqlRuleDefinition>,
+    pub unsupported: Vec<UnsupportedConstruct>,
+}
+
+impl SigmaConversion {
+    /// Whether the whole rule was translated
+    pub fn is_complete(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+
+    /// Build the generated rules
+    pub fn sparql_rules(&self) -> Result<Vec<SparqlRule>, RuleError> {
+        self.rules.iter().map(SparqlRule::from_definition).collect()
+    }
+}
+
+/// How one logsource maps onto CyberEvent triples
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogsourceMapping {
+    /// Event field every event of this source has (distinguishes the CyberEvent variant)
+    pub discriminator: String,
+    /// Sigma field (lower case) → event field
+    pub fields: BTreeMap<String, String>,
+}
+
+impl LogsourceMapping {
+    pub fn new(discriminator: &str, fields: &[(&str, &str)]) -> Self {
+        Self {
+            discriminator: discriminator.to_string(),
+            fields: fields.iter().map(|(sigma, event)| (sigma.to_ascii_lowercase(), event.to_string())).collect(),
+        }
+    }
+}
+
+/// Sigma → SPARQL rule converter
+///
+/// 既定の対応表は CyberEvent の各バリアントに相当する logsource を持つ。
+/// 組織固有の項目名は [`Self::with_field`] で追加する
+#[derive(Debug, Clone)]
+pub struct SigmaConverter {
+    /// logsource category (or service) → mapping
+    logsources: BTreeMap<String, LogsourceMapping>,
+}
+
+impl Default for SigmaConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SigmaConverter {
+    pub fn new() -> Self {
+        let process = LogsourceMapping::new("commandLine", &[
+            ("CommandLine", "commandLine"), ("User", "user"), ("ProcessId", "processId"), ("ParentProcessId", "parentProcessId"),
+        ]);
+        let network = LogsourceMapping::new("destIP", &[
+            ("DestinationIp", "destIP"), ("dst_ip", "destIP"), ("SourceIp", "sourceIP"), ("src_ip", "sourceIP"),
+            ("DestinationPort", "port"), ("dst_port", "port"), ("Protocol", "protocol"),
+        ]);
+        let file = LogsourceMapping::new("filePath", &[
+            ("TargetFilename", "filePath"), ("User", "user"), ("ProcessId", "processId"),
+        ]);
+        let dns = LogsourceMapping::new("queryName", &[
+            ("QueryName", "queryName"), ("query", "queryName"), ("record_type", "queryType"), ("QueryType", "queryType"),
+            ("src_ip", "sourceIP"), ("SourceIp", "sourceIP"), ("answer", "resolvedIP"), ("QueryResults", "resolvedIP"),
+        ]);
+        let proxy = LogsourceMapping::new("userAgent", &[
+            ("c-uri", "url"), ("cs-host", "host"), ("c-useragent", "userAgent"), ("cs-method", "httpMethod"),
+            ("sc-status", "statusCode"), ("c-ip", "sourceIP"), ("src_ip", "sourceIP"),
+        ]);
+        let registry = LogsourceMapping::new("registryKey", &[
+            ("TargetObject", "registryKey"), ("Details", "registryValueData"), ("EventType", "registryOperation"),
+            ("User", "user"), ("ProcessId", "processId"),
+        ]);
+        let authentication = LogsourceMapping::new("success", &[
+            ("User", "user"), ("TargetUserName", "user"), ("IpAddress", "sourceIP"), ("SourceIp", "sourceIP"), ("src_ip", "sourceIP"),
+        ]);
+
+        let mut logsources = BTreeMap::new();
+        logsources.insert("process_creation".to_string(), process);
+        for category in ["network_connection", "firewall"] {
+            logsources.insert(category.to_string(), network.clone());
+        }
+        for category in ["file_event", "file_access", "file_change", "file_delete"] {
+            logsources.insert(category.to_string(), file.clone());
+        }
+        for category in ["dns_query", "dns"] {
+            logsources.insert(category.to_string(), dns.clone());
+        }
+        for category in ["proxy", "webserver"] {
+            logsources.insert(category.to_string(), proxy.clone());
+        }
+        for category in ["registry_event", "registry_set", "registry_add", "registry_delete"] {
+            logsources.insert(category.to_string(), registry.clone());
+        }
+        logsources.insert("authentication".to_string(), authentication);
+        Self { logsources }
+    }
+
+    /// Map a logsource (category or service) onto events carrying `mapping`
+    pub fn with_logsource(mut self, logsource: &str, mapping: LogsourceMapping) -> Self {
+        self.logsources.insert(logsource.to_string(), mapping);
+        self
+    }
+
+    /// Map Sigma field `sigma_field` of `logsource` onto event field `event_field`
+    pub fn with_field(mut self, logsource: &str, sigma_field: &str, event_field: &str) -> Self {
+        if let Some(mapping) = self.logsources.get_mut(logsource) {
+            mapping.fields.insert(sigma_field.to_ascii_lowercase(), event_field.to_string());
+        }
+        self
+    }
+
+    pub fn convert_yaml(&self, yaml: &str) -> Result<SigmaConversion, SigmaError> {
+        self.convert(&SigmaRule::parse(yaml)?)
+    }
+
+    /// Convert `rule`, collecting every unsupported construct
+    pub fn convert(&self, rule: &SigmaRule) -> Result<SigmaConversion, SigmaError> {
+        let mut unsupported = Vec::new();
+        let source = rule.logsource.category.as_ref().or(rule.logsource.service.as_ref());
+        let mapping = source.and_then(|source| self.logsources.get(source));
+        if mapping.is_none() {
+            unsupported.push(UnsupportedConstruct {
+                location: "logsource".to_string(),
+                construct: format!("{:?}", rule.logsource),
+                reason: "no CyberEvent mapping for this logsource".to_string(),
+            });
+        }
+
+        let mut selections = BTreeMap::new();
+        let mut condition = None;
+        for (key, value) in &rule.detection {
+            let key = key.as_str().ok_or_else(|| SigmaError::Invalid("detection keys must be strings".to_string()))?;
+            match key {
+                "condition" => condition = Some(value),
+                "timeframe" => unsupported.push(UnsupportedConstruct {
+                    location: "detection.timeframe".to_string(),
+                    construct: yaml_text(value),
+                    reason: "time-windowed correlation is not supported".to_string(),
+                }),
+                name => {
+                    let clauses = mapping
+                        .map(|mapping| selection_clauses(name, value, mapping, &mut unsupported))
+                        .unwrap_or_default();
+                    selections.insert(name.to_string(), clauses);
+                }
+            }
+        }
+
+        let condition = condition.ok_or_else(|| SigmaError::Invalid("detection has no condition".to_string()))?;
+        let conditions: Vec<&str> = match condition {
+            Value::String(condition) => vec![condition.as_str()],
+            Value::Sequence(conditions) => conditions.iter()
+                .map(|condition| condition.as_str().ok_or_else(|| SigmaError::Invalid("conditions must be strings".to_string())))
+                .collect::<Result<_, _>>()?,
+            _ => return Err(SigmaError::Invalid("condition must be a string or a list".to_string())),
+        };
+        // 複数の条件は OR
+        let mut expressions = Vec::new();
+        for text in conditions {
+            match parse_condition(text, &selections) {
+                Ok(expression) => expressions.push(expression),
+                Err(ConditionError::Unsupported(construct, reason)) => unsupported.push(UnsupportedConstruct {
+                    location: "detection.condition".to_string(),
+                    construct,
+                    reason,
+                }),
+                Err(ConditionError::Invalid(message)) => return Err(SigmaError::Invalid(message)),
+            }
+        }
+
+        let branches = if unsupported.is_empty() {
+            let branches = dnf(&Condition::Or(expressions), false, &selections);
+            if branches.len() > MAX_BRANCHES {
+                unsupported.push(UnsupportedConstruct {
+                    location: "detection.condition".to_string(),
+                    construct: yaml_text(condition),
+                    reason: format!("condition expands to {} rules (at most {})", branches.len(), MAX_BRANCHES),
+                });
+            }
+            branches
+        } else {
+            Vec::new()
+        };
+
+        let slug = slug(&rule.title);
+        let rule_iri = format!("{}{}", SIGMA_NAMESPACE, rule.id.clone().unwrap_or_else(|| slug.clone()));
+        let rules = match (mapping, unsupported.is_empty()) {
+            (Some(mapping), true) => branches.iter().enumerate()
+                .map(|(index, branch)| SparqlRuleDefinition {
+                    name: if branches.len() == 1 { format!("sigma_{}", slug) } else { format!("sigma_{}_{}", slug, index + 1) },
+                    description: rule.description.clone().unwrap_or_else(|| rule.title.clone()),
+                    query: branch_query(branch, mapping, &rule_iri),
+                    priority: 0,
+                    severity: Some(rule.severity().to_string()),
+                    message: Some(format!("Sigma rule matched: {}", rule.title)),
+                    techniques: rule.attack_techniques(),
+                    prefixes: PrefixMap::empty(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(SigmaConversion { title: rule.title.clone(), id: rule.id.clone(), rule_iri, rules, unsupported })
+    }
+}
+
+/// One test on an event field
+#[derive(Debug, Clone, PartialEq)]
+enum FieldTest {
+    /// Some value of the field (any field when `None`) matches the regular expression
+    Regex { field: Option<String>, pattern: String, flags: String },
+    /// The field is present / absent
+    Exists { field: String, present: bool },
+    /// Numeric comparison (`>`, `>=`, `<`, `<=`)
+    Compare { field: String, operator: &'static str, value: String },
+}
+
+/// Conjunction of field tests (one Sigma selection map)
+type Clause = Vec<FieldTest>;
+
+/// Clauses of a selection; the selection matches when any clause does
+fn selection_clauses(name: &str, value: &Value, mapping: &LogsourceMapping, unsupported: &mut Vec<UnsupportedConstruct>) -> Vec<Clause> {
+    let location = format!("detection.{}", name);
+    match value {
+        Value::Mapping(fields) => map_clause(&location, fields, mapping, unsupported).into_iter().collect(),
+        Value::Sequence(items) if items.iter().all(|item| item.is_mapping()) => items.iter()
+            .filter_map(|item| item.as_mapping())
+            .filter_map(|fields| map_clause(&location, fields, mapping, unsupported))
+            .collect(),
+        // キーワード検索: イベントのいずれかの項目が部分一致する
+        Value::Sequence(items) => {
+            let mut alternatives = Vec::new();
+            for item in items {
+                match scalar_text(item) {
+                    Some(keyword) => alternatives.push(wildcard_regex(&keyword, Match::Contains)),
+                    None => unsupported.push(UnsupportedConstruct {
+                        location: location.clone(),
+                        construct: yaml_text(item),
+                        reason: "keyword lists may only contain strings or numbers".to_string(),
+                    }),
+                }
+            }
+            vec![vec![FieldTest::Regex { field: None, pattern: alternatives.join("|"), flags: "i".to_string() }]]
+        }
+        other => {
+            unsupported.push(UnsupportedConstruct {
+                location,
+                construct: yaml_text(other),
+                reason: "selections must be a map, a list of maps or a keyword list".to_string(),
+            });
+            Vec::new()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Match {
+    Equals,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+/// Field tests of a selection map (`None` when any part is unsupported)
+fn map_clause(location: &str, fields: &Mapping, mapping: &LogsourceMapping, unsupported: &mut Vec<UnsupportedConstruct>) -> Option<Clause> {
+    let before = unsupported.len();
+    let mut clause = Vec::new();
+    for (key, value) in fields {
+        let Some(key) = key.as_str() else {
+            unsupported.push(UnsupportedConstruct {
+                location: location.to_string(),
+                construct: yaml_text(key),
+                reason: "field names must be strings".to_string(),
+            });
+            continue;
+        };
+        let mut unsupported_here = |reason: String| unsupported.push(UnsupportedConstruct {
+            location: format!("{}.{}", location, key),
+            construct: key.to_string(),
+            reason,
+        });
+
+        let mut parts = key.split('|');
+        let sigma_field = parts.next().unwrap_or_default();
+        let Some(field) = mapping.fields.get(&sigma_field.to_ascii_lowercase()) else {
+            unsupported_here(format!("field {} has no CyberEvent mapping", sigma_field));
+            continue;
+        };
+
+        let (mut kind, mut all, mut cased, mut regex, mut exists) = (Match::Equals, false, false, false, false);
+        let mut compare = None;
+        let mut regex_flags = String::new();
+        for modifier in parts {
+            match modifier {
+                "contains" => kind = Match::Contains,
+                "startswith" => kind = Match::StartsWith,
+                "endswith" => kind = Match::EndsWith,
+                "all" => all = true,
+                "cased" => cased = true,
+                "re" => regex = true,
+                "i" | "m" | "s" if regex => regex_flags.push_str(modifier),
+                "exists" => exists = true,
+                "gt" => compare = Some(">"),
+                "gte" => compare = Some(">="),
+                "lt" => compare = Some("<"),
+                "lte" => compare = Some("<="),
+                other => unsupported_here(format!("modifier {} is not supported", other)),
+            }
+        }
+
+        let values: Vec<&Value> = match value {
+            Value::Sequence(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        if exists {
+            match value.as_bool() {
+                Some(present) => clause.push(FieldTest::Exists { field: field.clone(), present }),
+                None => unsupported_here("exists expects true or false".to_string()),
+            }
+            continue;
+        }
+        if let Some(operator) = compare {
+            match values.as_slice() {
+                [value] if value.is_number() => clause.push(FieldTest::Compare {
+                    field: field.clone(),
+                    operator,
+                    value: yaml_text(value),
+                }),
+                _ => unsupported_here("numeric comparisons expect a single number".to_string()),
+            }
+            continue;
+        }
+        if values.iter().any(|value| value.is_null()) {
+            if values.len() == 1 {
+                clause.push(FieldTest::Exists { field: field.clone(), present: false });
+            } else {
+                unsupported_here("null mixed with other values".to_string());
+            }
+            continue;
+        }
+
+        let mut patterns = Vec::new();
+        for value in values {
+            match scalar_text(value) {
+                Some(text) if regex => match embeddable_regex(&text) {
+                    Ok(()) => patterns.push(text),
+                    Err(reason) => unsupported_here(reason),
+                },
+                Some(text) => patterns.push(wildcard_regex(&text, kind)),
+                None => unsupported_here(format!("value {} is not a string or number", yaml_text(value))),
+            }
+        }
+        // Sigma の値は既定で大文字小文字を区別しない (`re` は区別する)
+        let flags = match (regex, cased) {
+            (true, _) => regex_flags,
+            (false, true) => String::new(),
+            (false, false) => "i".to_string(),
+        };
+        if all {
+            clause.extend(patterns.into_iter().map(|pattern| FieldTest::Regex { field: Some(field.clone()), pattern, flags: flags.clone() }));
+        } else if !patterns.is_empty() {
+            let pattern = if patterns.len() == 1 { patterns.remove(0) } else { format!("(?:{})", patterns.join(")|(?:")) };
+            clause.push(FieldTest::Regex { field: Some(field.clone()), pattern, flags });
+        }
+    }
+    (unsupported.len() == before).then_some(clause)
+}
+
+/// Regular expression for a Sigma string (`*` / `?` wildcards, `\` escapes)
+///
+/// 英数字以外は `\xHH` で書き、SPARQL ルールの構文解析が区切りとみなす `{` `}` や ` . ` を含めない
+fn wildcard_regex(value: &str, kind: Match) -> String {
+    let mut pattern = String::new();
+    if matches!(kind, Match::Equals | Match::StartsWith) {
+        pattern.push('^');
+    }
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '\\' => match chars.as_str().chars().next() {
+                Some(next @ ('*' | '?' | '\\')) => {
+                    chars.next();
+                    push_literal(&mut pattern, next);
+                }
+                _ => push_literal(&mut pattern, c),
+            },
+            c => push_literal(&mut pattern, c),
+        }
+    }
+    if matches!(kind, Match::Equals | Match::EndsWith) {
+        pattern.push('$');
+    }
+    pattern
+}
+
+fn push_literal(pattern: &mut String, c: char) {
+    if c.is_alphanumeric() || c == '_' || !c.is_ascii() {
+        pattern.push(c);
+    } else {
+        pattern.push_str(&format!("\\x{:02X}", c as u32));
+    }
+}
+
+/// `re` values are copied verbatim; reject what the SPARQL rule parser would split on
+fn embeddable_regex(pattern: &str) -> Result<(), String> {
+    if pattern.contains(['{', '}']) || pattern.contains(" . ") || pattern.contains(['\n', '\r']) {
+        return Err(format!("regular expression {:?} uses braces, \" . \" or line breaks", pattern));
+    }
+    regex::Regex::new(pattern).map(|_| ()).map_err(|e| format!("invalid regular expression: {}", e))
+}
+
+/// Sigma condition expression
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Selection(String),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+#[derive(Debug)]
+enum ConditionError {
+    /// (construct, reason)
+    Unsupported(String, String),
+    Invalid(String),
+}
+
+fn parse_condition(text: &str, selections: &BTreeMap<String, Vec<Clause>>) -> Result<Condition, ConditionError> {
+    if let Some((_, aggregation)) = text.split_once('|') {
+        return Err(ConditionError::Unsupported(aggregation.trim().to_string(), "aggregation conditions are not supported".to_string()));
+    }
+    let spaced = text.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let mut parser = ConditionParser { tokens, position: 0, selections };
+    let condition = parser.or()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(condition),
+        Some(token) => Err(ConditionError::Invalid(format!("unexpected {:?} in condition {:?}", token, text))),
+    }
+}
+
+/// Recursive-descent parser (`not` > `and` > `or`)
+struct ConditionParser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+    selections: &'a BTreeMap<String, Vec<Clause>>,
+}
+
+impl<'a> ConditionParser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, ConditionError> {
+        let token = self.peek().ok_or_else(|| ConditionError::Invalid("condition ends unexpectedly".to_string()))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<Condition, ConditionError> {
+        let mut terms = vec![self.and()?];
+        while self.peek().is_some_and(|token| token.eq_ignore_ascii_case("or")) {
+            self.position += 1;
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Condition::Or(terms) })
+    }
+
+    fn and(&mut self) -> Result<Condition, ConditionError> {
+        let mut terms = vec![self.not()?];
+        while self.peek().is_some_and(|token| token.eq_ignore_ascii_case("and")) {
+            self.position += 1;
+            terms.push(self.not()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Condition::And(terms) })
+    }
+
+    fn not(&mut self) -> Result<Condition, ConditionError> {
+        if self.peek().is_some_and(|token| token.eq_ignore_ascii_case("not")) {
+            self.position += 1;
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Condition, ConditionError> {
+        let token = self.next()?;
+        if token == "(" {
+            let condition = self.or()?;
+            return match self.next()? {
+                ")" => Ok(condition),
+                other => Err(ConditionError::Invalid(format!("expected ) but found {:?}", other))),
+            };
+        }
+        if token.eq_ignore_ascii_case("near") {
+            return Err(ConditionError::Unsupported(token.to_string(), "near correlation is not supported".to_string()));
+        }
+        if self.peek().is_some_and(|next| next.eq_ignore_ascii_case("of")) {
+            self.position += 1;
+            let pattern = self.next()?;
+            let names = self.matching(pattern);
+            if names.is_empty() {
+                return Err(ConditionError::Invalid(format!("no selection matches {:?}", pattern)));
+            }
+            let terms = names.into_iter().map(Condition::Selection).collect();
+            return match token {
+                "1" | "any" => Ok(Condition::Or(terms)),
+                "all" => Ok(Condition::And(terms)),
+                other => Err(ConditionError::Unsupported(format!("{} of {}", other, pattern), "only 1 of / all of are supported".to_string())),
+            };
+        }
+        if self.selections.contains_key(token) {
+            Ok(Condition::Selection(token.to_string()))
+        } else {
+            Err(ConditionError::Invalid(format!("unknown selection {:?}", token)))
+        }
+    }
+
+    /// Selection names matching `them` or a `prefix*` pattern
+    fn matching(&self, pattern: &str) -> Vec<String> {
+        self.selections.keys()
+            .filter(|name| match pattern {
+                // `them` は `_` で始まる選択を含まない
+                "them" => !name.starts_with('_'),
+                pattern => match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name.as_str() == pattern,
+                },
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Clause that must (not) match
+#[derive(Debug, Clone, PartialEq)]
+struct Literal {
+    clause: Clause,
+    negated: bool,
+}
+
+/// Disjunctive normal form of `condition` (negated when `negate`): each branch is a conjunction
+fn dnf(condition: &Condition, negate: bool, selections: &BTreeMap<String, Vec<Clause>>) -> Vec<Vec<Literal>> {
+    match (condition, negate) {
+        (Condition::Selection(name), false) => selections[name].iter()
+            .map(|clause| vec![Literal { clause: clause.clone(), negated: false }])
+            .collect(),
+        // ¬(c1 ∨ c2) = ¬c1 ∧ ¬c2
+        (Condition::Selection(name), true) => vec![selections[name].iter()
+            .map(|clause| Literal { clause: clause.clone(), negated: true })
+            .collect()],
+        (Condition::Not(inner), negate) => dnf(inner, !negate, selections),
+        (Condition::And(terms), false) | (Condition::Or(terms), true) => terms.iter()
+            .map(|term| dnf(term, negate, selections))
+            .fold(vec![Vec::new()], |branches, term| {
+                branches.iter()
+                    .flat_map(|branch| term.iter().map(move |conjunct| branch.iter().chain(conjunct).cloned().collect::<Vec<_>>()))
+                    .collect::<Vec<_>>()
+            }),
+        (Condition::Or(terms), false) | (Condition::And(terms), true) => terms.iter()
+            .flat_map(|term| dnf(term, negate, selections))
+            .collect(),
+    }
+}
+
+/// CONSTRUCT query for one conjunction
+fn branch_query(branch: &[Literal], mapping: &LogsourceMapping, rule_iri: &str) -> String {
+    let mut lines = vec![
+        "CONSTRUCT {".to_string(),
+        format!("    ?event <{}> <{}> .", SIGMA_MATCH_PREDICATE, rule_iri),
+        "}".to_string(),
+        "WHERE {".to_string(),
+        format!("    ?event <{}{}> ?sigma_source .", EVENT_NAMESPACE, mapping.discriminator),
+    ];
+    let mut variables = 0;
+    for literal in branch {
+        if literal.negated {
+            lines.push("    FILTER NOT EXISTS {".to_string());
+            clause_lines(&literal.clause, "        ", &mut variables, &mut lines);
+            lines.push("    }".to_string());
+        } else {
+            clause_lines(&literal.clause, "    ", &mut variables, &mut lines);
+        }
+    }
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+fn clause_lines(clause: &Clause, indent: &str, variables: &mut usize, lines: &mut Vec<String>) {
+    for test in clause {
+        *variables += 1;
+        let value = format!("?v{}", variables);
+        match test {
+            FieldTest::Regex { field, pattern, flags } => {
+                let predicate = match field {
+                    Some(field) => format!("<{}{}>", EVENT_NAMESPACE, field),
+                    None => format!("?p{}", variables),
+                };
+                lines.push(format!("{}?event {} {} .", indent, predicate, value));
+                lines.push(format!("{}FILTER (REGEX(STR({}), \"{}\", \"{}\"))", indent, value, sparql_escape(pattern), flags));
+            }
+            FieldTest::Exists { field, present: true } => {
+                lines.push(format!("{}?event <{}{}> {} .", indent, EVENT_NAMESPACE, field, value));
+            }
+            FieldTest::Exists { field, present: false } => {
+                lines.push(format!("{}FILTER NOT EXISTS {{", indent));
+                lines.push(format!("{}    ?event <{}{}> {} .", indent, EVENT_NAMESPACE, field, value));
+                lines.push(format!("{}}}", indent));
+            }
+            FieldTest::Compare { field, operator, value: number } => {
+                lines.push(format!("{}?event <{}{}> {} .", indent, EVENT_NAMESPACE, field, value));
+                lines.push(format!("{}FILTER (xsd:decimal(STR({})) {} {})", indent, value, operator, number));
+            }
+        }
+    }
+}
+
+fn sparql_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+fn yaml_text(value: &Value) -> String {
+    scalar_text(value).unwrap_or_else(|| serde_yaml::to_string(value).unwrap_or_default().trim().to_string())
+}
+
+/// Rule name fragment from a title (`Suspicious PowerShell` → `suspicious_powershell`)
+fn slug(title: &str) -> String {
+    let slug: Vec<String> = title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect();
+    if slug.is_empty() { "rule".to_string() } else { slug.join("_") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::{SecurityAction, Triple};
+    use fukurow_rules::RuleRegistry;
+    use fukurow_store::provenance::{GraphId, Provenance};
+    use fukurow_store::store::RdfStore;
+
+    const ENCODED_POWERSHELL: &str = r#"
+title: Encoded PowerShell Command
+id: 5b1c0d4e-0000-4000-8000-000000000001
+status: experimental
+logsource:
+    category: process_creation
+    product: windows
+detection:
+    selection:
+        CommandLine|contains|all:
+            - 'powershell'
+            - ' -enc '
+    filter:
+        User: 'NT AUTHORITY\SYSTEM'
+    condition: selection and not filter
+level: high
+tags:
+    - attack.execution
+    - attack.t1059.001
+"#;
+
+    fn event(store: &mut RdfStore, subject: &str, fields: &[(&str, &str)]) {
+        let sensor = Provenance::Sensor { source: "edr".to_string(), confidence: None };
+        for (field, value) in fields {
+            let triple = Triple { subject: subject.to_string(), predicate: format!("{}{}", EVENT_NAMESPACE, field), object: value.to_string() };
+            store.insert(triple, GraphId::Default, sensor.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_converted_rule_matches_events() {
+        let conversion = SigmaConverter::new().convert_yaml(ENCODED_POWERSHELL).unwrap();
+        assert!(conversion.is_complete(), "{:?}", conversion.unsupported);
+        assert_eq!(conversion.rules.len(), 1);
+        let definition = &conversion.rules[0];
+        assert_eq!(definition.name, "sigma_encoded_powershell_command");
+        assert_eq!(definition.severity.as_deref(), Some("high"));
+        assert_eq!(definition.techniques, vec!["T1059.001"]);
+
+        let mut store = RdfStore::new();
+        event(&mut store, "http://example.org/event/1", &[("commandLine", "PowerShell.exe -nop -enc SQBFAFgA"), ("user", "alice")]);
+        event(&mut store, "http://example.org/event/2", &[("commandLine", "powershell.exe -enc SQBFAFgA"), ("user", "NT AUTHORITY\\SYSTEM")]);
+        event(&mut store, "http://example.org/event/3", &[("commandLine", "powershell.exe -File build.ps1"), ("user", "bob")]);
+
+        let mut registry = RuleRegistry::new();
+        for rule in conversion.sparql_rules().unwrap() {
+            registry.register_rule(Box::new(rule));
+        }
+        let results = registry.apply_all_rules(&store).await.unwrap();
+        let matched: Vec<&str> = results.iter()
+            .flat_map(|result| &result.triples_to_add)
+            .map(|triple| triple.subject.as_str())
+            .collect();
+        assert_eq!(matched, vec!["http://example.org/event/1"]);
+        assert_eq!(results[0].triples_to_add[0].object, conversion.rule_iri);
+        assert!(matches!(&results[0].actions[0], SecurityAction::Alert { severity, .. } if severity == "high"));
+    }
+
+    #[test]
+    fn test_conditions_expand_to_one_rule_per_branch() {
+        let yaml = r#"
+title: Suspicious DNS
+logsource:
+    category: dns_query
+detection:
+    selection_tunnel:
+        QueryName|endswith: '.tunnel.example'
+    selection_txt:
+        record_type: TXT
+        QueryName|startswith: ['a*b', 'c?d']
+    filter:
+        answer: null
+    condition: 1 of selection_* and not filter
+"#;
+        let conversion = SigmaConverter::new().convert_yaml(yaml).unwrap();
+        assert!(conversion.is_complete(), "{:?}", conversion.unsupported);
+        assert_eq!(conversion.rules.iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>(), vec!["sigma_suspicious_dns_1", "sigma_suspicious_dns_2"]);
+        assert_eq!(conversion.rules[0].severity.as_deref(), Some("medium"));
+        let query = &conversion.rules[1].query;
+        assert!(query.contains(r#"REGEX(STR(?v1), "^TXT$", "i")"#), "{}", query);
+        assert!(query.contains(r#""(?:^a.*b)|(?:^c.d)""#), "{}", query);
+        assert!(conversion.rules[0].query.contains(r#""\\x2Etunnel\\x2Eexample$""#));
+        // `answer: null` の否定は「値が存在する」
+        assert!(conversion.rules[0].query.contains("FILTER NOT EXISTS {\n        FILTER NOT EXISTS {"));
+        assert!(conversion.sparql_rules().is_ok());
+    }
+
+    #[test]
+    fn test_unsupported_constructs_are_reported() {
+        let yaml = r#"
+title: Many Failed Logons
+logsource:
+    category: process_creation
+detection:
+    selection:
+        Image|endswith: '\net.exe'
+        CommandLine|base64offset|contains: 'user'
+    condition: selection | count() by User > 5
+"#;
+        let conversion = SigmaConverter::new().convert_yaml(yaml).unwrap();
+        assert!(conversion.rules.is_empty());
+        let constructs: Vec<(&str, &str)> = conversion.unsupported.iter()
+            .map(|item| (item.location.as_str(), item.construct.as_str()))
+            .collect();
+        assert_eq!(constructs, vec![
+            ("detection.selection.Image|endswith", "Image|endswith"),
+            ("detection.selection.CommandLine|base64offset|contains", "CommandLine|base64offset|contains"),
+            ("detection.condition", "count() by User > 5"),
+        ]);
+
+        // 項目の対応付けを追加すれば変換できる
+        let converter = SigmaConverter::new().with_field("process_creation", "Image", "commandLine");
+        let yaml = yaml.replace("CommandLine|base64offset|contains", "CommandLine|contains").replace(" | count() by User > 5", "");
+        assert!(converter.convert_yaml(&yaml).unwrap().is_complete());
+
+        assert!(matches!(SigmaConverter::new().convert_yaml("title: x\ndetection:\n  sel: {a: b}\n"), Err(SigmaError::Invalid(_))));
+    }
+}
@@ -0,0 +1,565 @@
+//! STIX 2.1 import/export
+//!
+//! STIX バンドル（indicator / malware / attack-pattern / relationship）を
+//! Imported provenance 付きトリプルとして RdfStore に取り込み、
+//! SecurityAction（検知結果）を STIX sighting として書き出す
+
+use crate::threat_intelligence::{IndicatorType, ThreatIndicator};
+use fukurow_core::model::{SecurityAction, Triple};
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+
+/// STIX vocabulary namespace used for predicates and classes
+pub const STIX_NS: &str = "http://docs.oasis-open.org/cti/ns/stix#";
+/// Default named graph for imported STIX content
+pub const STIX_GRAPH: &str = "stix";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// STIX errors
+#[derive(Debug, thiserror::Error)]
+pub enum StixError {
+    #[error("Invalid STIX bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// STIX bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StixBundle {
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    pub id: String,
+    #[serde(default)]
+    pub objects: Vec<StixObject>,
+}
+
+impl StixBundle {
+    pub fn new(objects: Vec<StixObject>) -> Self {
+        Self {
+            bundle_type: "bundle".to_string(),
+            id: format!("bundle--{}", uuid::Uuid::new_v4()),
+            objects,
+        }
+    }
+
+    /// Parse and validate a bundle from JSON
+    pub fn from_json(json: &str) -> Result<Self, StixError> {
+        let bundle: StixBundle = serde_json::from_str(json)?;
+        if bundle.bundle_type != "bundle" {
+            return Err(StixError::InvalidBundle(format!("unexpected type '{}'", bundle.bundle_type)));
+        }
+        Ok(bundle)
+    }
+
+    pub fn to_json(&self) -> Result<String, StixError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Supported STIX objects (unsupported types are kept as `Unsupported`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum StixObject {
+    Indicator(StixIndicator),
+    Malware(StixMalware),
+    AttackPattern(StixAttackPattern),
+    Relationship(StixRelationship),
+    Sighting(StixSighting),
+    #[serde(other)]
+    Unsupported,
+}
+
+impl StixObject {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            StixObject::Indicator(o) => Some(&o.id),
+            StixObject::Malware(o) => Some(&o.id),
+            StixObject::AttackPattern(o) => Some(&o.id),
+            StixObject::Relationship(o) => Some(&o.id),
+            StixObject::Sighting(o) => Some(&o.id),
+            StixObject::Unsupported => None,
+        }
+    }
+}
+
+fn default_spec_version() -> String {
+    "2.1".to_string()
+}
+
+/// STIX indicator SDO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StixIndicator {
+    pub id: String,
+    #[serde(default = "default_spec_version")]
+    pub spec_version: String,
+    pub created: String,
+    pub modified: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indicator_types: Vec<String>,
+    pub pattern: String,
+    pub pattern_type: String,
+    pub valid_from: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<u8>,
+}
+
+/// STIX malware SDO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StixMalware {
+    pub id: String,
+    #[serde(default = "default_spec_version")]
+    pub spec_version: String,
+    pub created: String,
+    pub modified: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub is_family: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub malware_types: Vec<String>,
+}
+
+/// External reference (e.g. MITRE ATT&CK technique ID)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExternalReference {
+    pub source_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// STIX attack-pattern SDO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StixAttackPattern {
+    pub id: String,
+    #[serde(default = "default_spec_version")]
+    pub spec_version: String,
+    pub created: String,
+    pub modified: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_references: Vec<ExternalReference>,
+}
+
+/// STIX relationship SRO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StixRelationship {
+    pub id: String,
+    #[serde(default = "default_spec_version")]
+    pub spec_version: String,
+    pub created: String,
+    pub modified: String,
+    pub relationship_type: String,
+    pub source_ref: String,
+    pub target_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// STIX sighting SRO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StixSighting {
+    pub id: String,
+    #[serde(default = "default_spec_version")]
+    pub spec_version: String,
+    pub created: String,
+    pub modified: String,
+    pub sighting_of_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub where_sighted_refs: Vec<String>,
+    /// Fukurow action details (custom property)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x_fukurow_action: Option<serde_json::Value>,
+}
+
+/// Summary of a bundle import
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StixImportReport {
+    pub imported_objects: usize,
+    pub triples_added: usize,
+    /// Objects of unsupported types
+    pub skipped_objects: usize,
+}
+
+/// IRI for a STIX object ID
+pub fn stix_iri(id: &str) -> String {
+    format!("urn:stix:{}", id)
+}
+
+fn stix_term(local: &str) -> String {
+    format!("{}{}", STIX_NS, local)
+}
+
+/// Convert a STIX object into triples
+pub fn object_to_triples(object: &StixObject) -> Vec<Triple> {
+    let mut triples = Vec::new();
+    let Some(id) = object.id() else {
+        return triples;
+    };
+    let subject = stix_iri(id);
+    let mut direct = None;
+    let mut add = |predicate: String, object: String| {
+        triples.push(Triple { subject: subject.clone(), predicate, object });
+    };
+
+    match object {
+        StixObject::Indicator(indicator) => {
+            add(RDF_TYPE.to_string(), stix_term("Indicator"));
+            add(stix_term("pattern"), indicator.pattern.clone());
+            add(stix_term("patternType"), indicator.pattern_type.clone());
+            add(stix_term("validFrom"), indicator.valid_from.clone());
+            for indicator_type in &indicator.indicator_types {
+                add(stix_term("indicatorType"), indicator_type.clone());
+            }
+            for (_, value) in parse_pattern_observables(&indicator.pattern) {
+                add(stix_term("observableValue"), value);
+            }
+            if let Some(name) = &indicator.name {
+                add(stix_term("name"), name.clone());
+            }
+            if let Some(confidence) = indicator.confidence {
+                add(stix_term("confidence"), confidence.to_string());
+            }
+        }
+        StixObject::Malware(malware) => {
+            add(RDF_TYPE.to_string(), stix_term("Malware"));
+            add(stix_term("isFamily"), malware.is_family.to_string());
+            for malware_type in &malware.malware_types {
+                add(stix_term("malwareType"), malware_type.clone());
+            }
+            if let Some(name) = &malware.name {
+                add(stix_term("name"), name.clone());
+            }
+        }
+        StixObject::AttackPattern(pattern) => {
+            add(RDF_TYPE.to_string(), stix_term("AttackPattern"));
+            add(stix_term("name"), pattern.name.clone());
+            for reference in &pattern.external_references {
+                if let Some(external_id) = &reference.external_id {
+                    add(stix_term("externalId"), format!("{}:{}", reference.source_name, external_id));
+                }
+            }
+        }
+        StixObject::Relationship(relationship) => {
+            // リレーションシップは主語/目的語間の直接のトリプルとしても表現する
+            add(RDF_TYPE.to_string(), stix_term("Relationship"));
+            add(stix_term("relationshipType"), relationship.relationship_type.clone());
+            add(stix_term("sourceRef"), stix_iri(&relationship.source_ref));
+            add(stix_term("targetRef"), stix_iri(&relationship.target_ref));
+            direct = Some(Triple {
+                subject: stix_iri(&relationship.source_ref),
+                predicate: stix_term(&relationship.relationship_type),
+                object: stix_iri(&relationship.target_ref),
+            });
+        }
+        StixObject::Sighting(sighting) => {
+            add(RDF_TYPE.to_string(), stix_term("Sighting"));
+            add(stix_term("sightingOf"), stix_iri(&sighting.sighting_of_ref));
+            if let Some(count) = sighting.count {
+                add(stix_term("count"), count.to_string());
+            }
+        }
+        StixObject::Unsupported => {}
+    }
+
+    triples.extend(direct);
+    triples
+}
+
+/// Import a STIX bundle into the store with `Imported` provenance
+pub fn import_bundle(bundle: &StixBundle, store: &mut RdfStore, source_uri: &str) -> StixImportReport {
+    let mut report = StixImportReport::default();
+    let imported_at = chrono::Utc::now().timestamp_millis().max(0) as u64;
+    let graph_id = GraphId::Named(STIX_GRAPH.to_string());
+
+    for object in &bundle.objects {
+        let triples = object_to_triples(object);
+        if triples.is_empty() {
+            report.skipped_objects += 1;
+            continue;
+        }
+        report.imported_objects += 1;
+        report.triples_added += triples.len();
+        store.insert_batch(triples, graph_id.clone(), Provenance::Imported {
+            source_uri: source_uri.to_string(),
+            imported_at,
+        });
+    }
+
+    report
+}
+
+/// Extract `(object-path, value)` comparisons from a simple STIX pattern
+///
+/// 例: `[ipv4-addr:value = '198.51.100.1'] OR [domain-name:value = 'evil.example']`
+pub fn parse_pattern_observables(pattern: &str) -> Vec<(String, String)> {
+    let mut observables = Vec::new();
+    for comparison in pattern.split(['[', ']']).flat_map(|part| part.split(" OR ")).flat_map(|part| part.split(" AND ")) {
+        let Some((path, value)) = comparison.split_once('=') else {
+            continue;
+        };
+        let path = path.trim();
+        let value = value.trim().trim_matches('\'');
+        if !path.is_empty() && !value.is_empty() && path.contains(':') {
+            observables.push((path.to_string(), value.to_string()));
+        }
+    }
+    observables
+}
+
+/// Convert STIX indicators into threat-feed indicators
+pub fn to_threat_indicators(bundle: &StixBundle) -> Vec<ThreatIndicator> {
+    let mut indicators = Vec::new();
+    for object in &bundle.objects {
+        let StixObject::Indicator(indicator) = object else {
+            continue;
+        };
+        let seen = chrono::DateTime::parse_from_rfc3339(&indicator.valid_from)
+            .map(|t| t.timestamp())
+            .unwrap_or(0);
+
+        for (index, (path, value)) in parse_pattern_observables(&indicator.pattern).into_iter().enumerate() {
+            let indicator_type = match path.as_str() {
+                "ipv4-addr:value" | "ipv6-addr:value" => IndicatorType::IpAddress,
+                "domain-name:value" => IndicatorType::Domain,
+                "url:value" => IndicatorType::Url,
+                "email-addr:value" => IndicatorType::Email,
+                p if p.starts_with("file:hashes") => IndicatorType::FileHash,
+                _ => continue,
+            };
+            indicators.push(ThreatIndicator {
+                id: format!("{}#{}", indicator.id, index),
+                indicator_type,
+                value,
+                threat_type: indicator.indicator_types.first().cloned().unwrap_or_else(|| "unknown".to_string()),
+                severity: match indicator.confidence {
+                    Some(c) if c >= 85 => "high",
+                    Some(c) if c >= 50 => "medium",
+                    _ => "low",
+                }.to_string(),
+                sources: vec!["stix".to_string()],
+                first_seen: seen,
+                last_seen: seen,
+                tags: indicator.indicator_types.clone(),
+            });
+        }
+    }
+    indicators
+}
+
+fn stix_pattern_for(action: &SecurityAction) -> (String, String) {
+    match action {
+        SecurityAction::IsolateHost { host_ip, reason } => {
+            (format!("[ipv4-addr:value = '{}']", host_ip), reason.clone())
+        }
+        SecurityAction::BlockConnection { source_ip, dest_ip, reason } => (
+            format!("[network-traffic:src_ref.value = '{}' AND network-traffic:dst_ref.value = '{}']", source_ip, dest_ip),
+            reason.clone(),
+        ),
+        SecurityAction::TerminateProcess { process_id, reason } => {
+            (format!("[process:pid = {}]", process_id), reason.clone())
+        }
+        SecurityAction::RevokePrivileges { user, reason, .. } => {
+            (format!("[user-account:user_id = '{}']", user), reason.clone())
+        }
+        SecurityAction::Alert { message, details, .. } => {
+            let pattern = ["destination_ip", "source_ip", "host_ip"]
+                .iter()
+                .find_map(|key| details.get(*key).and_then(|v| v.as_str()))
+                .map(|ip| format!("[ipv4-addr:value = '{}']", ip))
+                .unwrap_or_else(|| format!("[x-fukurow-alert:message = '{}']", message.replace('\'', "\\'")));
+            (pattern, message.clone())
+        }
+    }
+}
+
+/// Export detections as a STIX bundle of indicators and sightings
+///
+/// `Alert` の details に `stix_indicator_id` があれば既存の indicator を参照する
+pub fn export_sightings(actions: &[SecurityAction], identity_ref: Option<&str>) -> StixBundle {
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let mut objects = Vec::new();
+
+    for action in actions {
+        let existing = match action {
+            SecurityAction::Alert { details, .. } => details.get("stix_indicator_id").and_then(|v| v.as_str()).map(String::from),
+            _ => None,
+        };
+
+        let indicator_ref = match existing {
+            Some(id) => id,
+            None => {
+                let (pattern, description) = stix_pattern_for(action);
+                let id = format!("indicator--{}", uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, pattern.as_bytes()));
+                objects.push(StixObject::Indicator(StixIndicator {
+                    id: id.clone(),
+                    spec_version: default_spec_version(),
+                    created: now.clone(),
+                    modified: now.clone(),
+                    name: Some(description.clone()),
+                    description: Some(description),
+                    indicator_types: vec!["malicious-activity".to_string()],
+                    pattern,
+                    pattern_type: "stix".to_string(),
+                    valid_from: now.clone(),
+                    confidence: None,
+                }));
+                id
+            }
+        };
+
+        objects.push(StixObject::Sighting(StixSighting {
+            id: format!("sighting--{}", uuid::Uuid::new_v4()),
+            spec_version: default_spec_version(),
+            created: now.clone(),
+            modified: now.clone(),
+            sighting_of_ref: indicator_ref,
+            count: Some(1),
+            first_seen: Some(now.clone()),
+            last_seen: Some(now.clone()),
+            description: None,
+            where_sighted_refs: identity_ref.map(|r| vec![r.to_string()]).unwrap_or_default(),
+            x_fukurow_action: serde_json::to_value(action).ok(),
+        }));
+    }
+
+    StixBundle::new(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &str = r#"{
+        "type": "bundle",
+        "id": "bundle--5d0092c5-5f74-4287-9642-33f4c354e56d",
+        "objects": [
+            {
+                "type": "indicator",
+                "spec_version": "2.1",
+                "id": "indicator--8e2e2d2b-17d4-4cbf-938f-98ee46b3cd3f",
+                "created": "2024-01-01T00:00:00.000Z",
+                "modified": "2024-01-01T00:00:00.000Z",
+                "name": "C2 server",
+                "indicator_types": ["malicious-activity"],
+                "pattern": "[ipv4-addr:value = '198.51.100.7'] OR [domain-name:value = 'c2.example']",
+                "pattern_type": "stix",
+                "valid_from": "2024-01-01T00:00:00Z",
+                "confidence": 90
+            },
+            {
+                "type": "malware",
+                "spec_version": "2.1",
+                "id": "malware--31b940d4-6f7f-459a-80ea-9c1f17b5891b",
+                "created": "2024-01-01T00:00:00.000Z",
+                "modified": "2024-01-01T00:00:00.000Z",
+                "name": "EvilRAT",
+                "is_family": true,
+                "malware_types": ["remote-access-trojan"]
+            },
+            {
+                "type": "attack-pattern",
+                "spec_version": "2.1",
+                "id": "attack-pattern--7e33a43e-e34b-40ec-89da-36c9bb2cacd5",
+                "created": "2024-01-01T00:00:00.000Z",
+                "modified": "2024-01-01T00:00:00.000Z",
+                "name": "Application Layer Protocol",
+                "external_references": [{"source_name": "mitre-attack", "external_id": "T1071"}]
+            },
+            {
+                "type": "relationship",
+                "spec_version": "2.1",
+                "id": "relationship--44298a74-ba52-4f0c-87a3-1824e67d7fad",
+                "created": "2024-01-01T00:00:00.000Z",
+                "modified": "2024-01-01T00:00:00.000Z",
+                "relationship_type": "indicates",
+                "source_ref": "indicator--8e2e2d2b-17d4-4cbf-938f-98ee46b3cd3f",
+                "target_ref": "malware--31b940d4-6f7f-459a-80ea-9c1f17b5891b"
+            },
+            {
+                "type": "identity",
+                "spec_version": "2.1",
+                "id": "identity--f431f809-377b-45e0-aa1c-6a4751cae5ff",
+                "name": "ACME"
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_import_bundle() {
+        let bundle = StixBundle::from_json(BUNDLE).unwrap();
+        let mut store = RdfStore::new();
+        let report = import_bundle(&bundle, &mut store, "https://feeds.example/stix");
+
+        assert_eq!(report.imported_objects, 4);
+        assert_eq!(report.skipped_objects, 1);
+
+        let indicator = stix_iri("indicator--8e2e2d2b-17d4-4cbf-938f-98ee46b3cd3f");
+        let indicates = store.find_triples(Some(&indicator), Some(&stix_term("indicates")), None);
+        assert_eq!(indicates.len(), 1);
+        assert_eq!(indicates[0].triple.object, stix_iri("malware--31b940d4-6f7f-459a-80ea-9c1f17b5891b"));
+        assert!(matches!(indicates[0].provenance, Provenance::Imported { .. }));
+
+        let technique = store.find_triples(None, Some(&stix_term("externalId")), Some("mitre-attack:T1071"));
+        assert_eq!(technique.len(), 1);
+    }
+
+    #[test]
+    fn test_to_threat_indicators() {
+        let bundle = StixBundle::from_json(BUNDLE).unwrap();
+        let indicators = to_threat_indicators(&bundle);
+
+        assert_eq!(indicators.len(), 2);
+        assert_eq!(indicators[0].indicator_type, IndicatorType::IpAddress);
+        assert_eq!(indicators[0].value, "198.51.100.7");
+        assert_eq!(indicators[0].severity, "high");
+        assert_eq!(indicators[1].indicator_type, IndicatorType::Domain);
+    }
+
+    #[test]
+    fn test_export_sightings_roundtrip() {
+        let actions = vec![
+            SecurityAction::IsolateHost { host_ip: "10.0.0.5".to_string(), reason: "Beaconing".to_string() },
+            SecurityAction::Alert {
+                severity: "high".to_string(),
+                message: "Known C2".to_string(),
+                details: serde_json::json!({"stix_indicator_id": "indicator--8e2e2d2b-17d4-4cbf-938f-98ee46b3cd3f"}),
+            },
+        ];
+        let bundle = export_sightings(&actions, Some("identity--f431f809-377b-45e0-aa1c-6a4751cae5ff"));
+
+        // IsolateHost → indicator + sighting, Alert → sighting only
+        assert_eq!(bundle.objects.len(), 3);
+        let sightings: Vec<_> = bundle.objects.iter()
+            .filter_map(|o| match o { StixObject::Sighting(s) => Some(s), _ => None })
+            .collect();
+        assert_eq!(sightings.len(), 2);
+        assert_eq!(sightings[1].sighting_of_ref, "indicator--8e2e2d2b-17d4-4cbf-938f-98ee46b3cd3f");
+
+        let json = bundle.to_json().unwrap();
+        assert!(json.contains(r#""type": "sighting""#));
+        let parsed = StixBundle::from_json(&json).unwrap();
+        assert_eq!(parsed.objects.len(), 3);
+    }
+}
@@ -13,7 +13,7 @@ pub struct ThreatSource {
 }
 
 /// Threat indicator
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ThreatIndicator {
     pub id: String,
     pub indicator_type: IndicatorType,
@@ -27,7 +27,7 @@ pub struct ThreatIndicator {
 }
 
 /// Type of threat indicator
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum IndicatorType {
     IpAddress,
     Domain,
@@ -56,6 +56,11 @@ impl ThreatFeed {
         self.indicators.insert(indicator.id.clone(), indicator);
     }
 
+    /// Remove a threat indicator by ID
+    pub fn remove_indicator(&mut self, id: &str) -> Option<ThreatIndicator> {
+        self.indicators.remove(id)
+    }
+
     /// Check if value is a known threat
     pub fn is_threat(&self, value: &str, indicator_type: IndicatorType) -> Option<&ThreatIndicator> {
         for indicator in self.indicators.values() {
@@ -150,6 +155,10 @@ impl ThreatProcessor {
     pub fn feed(&self) -> &ThreatFeed {
         &self.feed
     }
+
+    pub fn feed_mut(&mut self) -> &mut ThreatFeed {
+        &mut self.feed
+    }
 }
 
 impl ThreatProcessor {
@@ -0,0 +1,212 @@
+//! # Event Deduplication
+//!
+//! 同じ EDR イベントが Kafka と REST API の両方から届くと推論結果が重複するため、
+//! エンジンの入口で時間窓付きの重複排除を行う。
+//! - キー: 送信元が付けたイベント ID、無ければイベント内容のハッシュ
+//! - 相関 ID: 最初に受理したときに割り当て、重複したイベントにも同じ ID を返す
+
+use fukurow_core::model::CyberEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Predicate linking an event node to its correlation ID
+pub const CORRELATION_ID_PREDICATE: &str = "http://example.org/correlationId";
+/// Default time window in which a repeated event counts as a duplicate
+pub const DEFAULT_DEDUP_WINDOW_SECS: u64 = 300;
+/// Default upper bound of remembered events
+pub const DEFAULT_DEDUP_CAPACITY: usize = 100_000;
+
+/// Deduplication settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupConfig {
+    /// How long an event is remembered; `Duration::ZERO` disables deduplication
+    pub window: Duration,
+    /// Maximum remembered events (the oldest are forgotten first)
+    pub max_entries: usize,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(DEFAULT_DEDUP_WINDOW_SECS),
+            max_entries: DEFAULT_DEDUP_CAPACITY,
+        }
+    }
+}
+
+impl DedupConfig {
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Configuration that accepts every event
+    pub fn disabled() -> Self {
+        Self::default().with_window(Duration::ZERO)
+    }
+}
+
+/// Outcome of submitting an event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventReceipt {
+    /// Correlation ID of the event (the original one for duplicates)
+    pub correlation_id: String,
+    /// The event was seen within the window and was not ingested again
+    pub duplicate: bool,
+    /// The event failed validation and was stored in the quarantine graph instead
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub quarantined: bool,
+}
+
+/// Deduplication key of an event
+///
+/// 送信元のイベント ID があればそれを優先する。ID が無い場合は JSON 表現のハッシュを使うため、
+/// フィールドが 1 つでも異なるイベントは別物として扱われる
+pub fn dedup_key(event: &CyberEvent, event_id: Option<&str>) -> String {
+    match event_id {
+        Some(id) => format!("id:{}", id),
+        None => {
+            let mut hasher = DefaultHasher::new();
+            serde_json::to_string(event).unwrap_or_default().hash(&mut hasher);
+            format!("hash:{:016x}", hasher.finish())
+        }
+    }
+}
+
+/// Time-windowed record of recently seen events
+#[derive(Debug)]
+pub struct EventDeduplicator {
+    config: DedupConfig,
+    seen: HashMap<String, (Instant, String)>,
+    /// Keys in first-seen order, for expiry and capacity eviction
+    order: VecDeque<(Instant, String)>,
+    duplicates: u64,
+}
+
+impl EventDeduplicator {
+    pub fn new(config: DedupConfig) -> Self {
+        Self { config, seen: HashMap::new(), order: VecDeque::new(), duplicates: 0 }
+    }
+
+    pub fn config(&self) -> &DedupConfig {
+        &self.config
+    }
+
+    /// Record `key` seen at `now`
+    ///
+    /// 新しいキーには `correlation_id` で相関 ID を割り当てる。窓内の重複なら最初の相関 ID を返す
+    pub fn observe(&mut self, key: &str, now: Instant, correlation_id: impl FnOnce() -> String) -> EventReceipt {
+        if self.config.window.is_zero() {
+            return EventReceipt { correlation_id: correlation_id(), duplicate: false, quarantined: false };
+        }
+
+        self.expire(now);
+        if let Some((_, id)) = self.seen.get(key) {
+            self.duplicates += 1;
+            return EventReceipt { correlation_id: id.clone(), duplicate: true, quarantined: false };
+        }
+
+        while self.seen.len() >= self.config.max_entries.max(1) {
+            match self.order.pop_front() {
+                Some((_, oldest)) => {
+                    self.seen.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        let id = correlation_id();
+        self.seen.insert(key.to_string(), (now, id.clone()));
+        self.order.push_back((now, key.to_string()));
+        EventReceipt { correlation_id: id, duplicate: false, quarantined: false }
+    }
+
+    /// Forget `key`, e.g. when ingesting the event failed and the sender may retry
+    pub fn forget(&mut self, key: &str) {
+        if self.seen.remove(key).is_some() {
+            self.order.retain(|(_, seen)| seen != key);
+        }
+    }
+
+    /// Number of duplicates rejected so far
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Number of events currently remembered
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some((seen_at, _)) = self.order.front() {
+            if now.saturating_duration_since(*seen_at) < self.config.window {
+                break;
+            }
+            if let Some((_, key)) = self.order.pop_front() {
+                self.seen.remove(&key);
+            }
+        }
+    }
+}
+
+impl Default for EventDeduplicator {
+    fn default() -> Self {
+        Self::new(DedupConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates_within_window_share_correlation_id() {
+        let mut dedup = EventDeduplicator::new(DedupConfig::default().with_window(Duration::from_secs(10)).with_max_entries(2));
+        let start = Instant::now();
+
+        let first = dedup.observe("a", start, || "corr-a".to_string());
+        assert_eq!(first, EventReceipt { correlation_id: "corr-a".to_string(), duplicate: false, quarantined: false });
+        let again = dedup.observe("a", start + Duration::from_secs(5), || "unused".to_string());
+        assert_eq!(again, EventReceipt { correlation_id: "corr-a".to_string(), duplicate: true, quarantined: false });
+        assert_eq!(dedup.duplicates(), 1);
+
+        // 窓を過ぎたら新しいイベントとして受理する
+        let later = dedup.observe("a", start + Duration::from_secs(11), || "corr-a2".to_string());
+        assert!(!later.duplicate);
+
+        // 上限を超えると最も古いキーから忘れる
+        dedup.observe("b", start + Duration::from_secs(12), || "corr-b".to_string());
+        dedup.observe("c", start + Duration::from_secs(13), || "corr-c".to_string());
+        assert_eq!(dedup.len(), 2);
+        assert!(!dedup.observe("a", start + Duration::from_secs(14), || "corr-a3".to_string()).duplicate);
+    }
+
+    #[test]
+    fn test_dedup_key_prefers_event_id() {
+        let event = CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.5".to_string(),
+            success: false,
+            timestamp: 1_700_000_000,
+        };
+        assert_eq!(dedup_key(&event, Some("edr-42")), "id:edr-42");
+        assert_eq!(dedup_key(&event, None), dedup_key(&event.clone(), None));
+    }
+}
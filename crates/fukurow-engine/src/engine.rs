@@ -1,29 +1,49 @@
 //! Inference engine for security event reasoning
 
-use fukurow_core::model::{CyberEvent, SecurityAction, InferenceRule};
-use fukurow_store::{store::RdfStore, Triple};
+use fukurow_core::model::{CyberEvent, SecurityAction, CorrelatedAction, InferenceRule};
+use fukurow_store::{store::RdfStore, SharedStore, Triple};
 use fukurow_rules::{RuleRegistry, Rule};
-use super::orchestration::{ReasoningEngine, ProcessingOptions};
-use std::sync::Arc;
+use super::orchestration::{ReasoningEngine, ProcessingOptions, ReasoningProfile, StageObserver};
+use super::dedup::{dedup_key, DedupConfig, EventDeduplicator, EventReceipt};
+use super::quarantine::QuarantinedEvent;
+use fukurow_core::validation::ValidationIssue;
+use super::ontology::{OntologyError, OntologyRegistry, OntologySwap, OntologyValidation, OntologyVersion};
+use super::owl::OwlConsistencyReport;
+use super::stage::{EventBatch, IngestItem, PipelineStage, StageError, StagePipeline};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::sync::RwLock;
-use tracing::{info, warn};
+use fukurow_observability::tracing::{attributes, spans};
+use tracing::{debug, info, warn, Instrument};
 
 /// Compatibility layer for legacy ReasonerEngine API
 /// Delegates to the new ReasoningEngine
 pub struct ReasonerEngine {
-    rdf_store: Arc<RwLock<RdfStore>>,
+    /// Writable store plus the lock-free replica served to queries
+    rdf_store: SharedStore,
     reasoning_engine: ReasoningEngine,
+    /// Deduplication window shared with batch ingestion
+    dedup: Arc<Mutex<EventDeduplicator>>,
+    /// Correlation IDs of events accepted since the last reasoning run
+    pending_correlations: Arc<Mutex<Vec<String>>>,
+    ontologies: Mutex<OntologyRegistry>,
+    /// Ingestion stages run on every accepted event
+    stages: StagePipeline,
 }
 
 impl ReasonerEngine {
     /// Convert a CyberEvent to a vector of Triples
-    fn cyber_event_to_triples(event: &CyberEvent) -> Vec<fukurow_store::Triple> {
+    pub(crate) fn cyber_event_to_triples(event: &CyberEvent) -> Vec<fukurow_store::Triple> {
         let mut triples = Vec::new();
         let (subject, timestamp) = match event {
             CyberEvent::NetworkConnection { timestamp, .. } => (format!("event:{}", timestamp), *timestamp),
             CyberEvent::ProcessExecution { timestamp, .. } => (format!("event:{}", timestamp), *timestamp),
             CyberEvent::FileAccess { timestamp, .. } => (format!("event:{}", timestamp), *timestamp),
             CyberEvent::UserLogin { timestamp, .. } => (format!("event:{}", timestamp), *timestamp),
+            CyberEvent::DnsQuery { timestamp, .. }
+            | CyberEvent::HttpRequest { timestamp, .. }
+            | CyberEvent::RegistryModification { timestamp, .. }
+            | CyberEvent::EmailReceived { timestamp, .. } => (format!("event:{}", timestamp), *timestamp),
         };
 
         // Add type triple
@@ -140,51 +160,348 @@ impl ReasonerEngine {
                     object: timestamp.to_string(),
                 });
             }
+            // 複数値の項目 (解決先 IP・URL・添付) は同じ述語のトリプルを値の数だけ作る
+            CyberEvent::DnsQuery { query_name, query_type, source_ip, resolved_ips, timestamp } => {
+                triples.push(event_field(&subject, "queryName", query_name));
+                triples.push(event_field(&subject, "queryType", query_type));
+                triples.push(event_field(&subject, "sourceIP", source_ip));
+                triples.extend(resolved_ips.iter().map(|ip| event_field(&subject, "resolvedIP", ip)));
+                triples.push(event_field(&subject, "timestamp", timestamp));
+            }
+            CyberEvent::HttpRequest { method, url, host, user_agent, source_ip, status_code, timestamp } => {
+                triples.push(event_field(&subject, "httpMethod", method));
+                triples.push(event_field(&subject, "url", url));
+                triples.push(event_field(&subject, "host", host));
+                triples.push(event_field(&subject, "userAgent", user_agent));
+                triples.push(event_field(&subject, "sourceIP", source_ip));
+                triples.extend(status_code.map(|code| event_field(&subject, "statusCode", code)));
+                triples.push(event_field(&subject, "timestamp", timestamp));
+            }
+            CyberEvent::RegistryModification { key_path, value_name, value_data, operation, process_id, user, timestamp } => {
+                triples.push(event_field(&subject, "registryKey", key_path));
+                triples.extend(value_name.as_ref().map(|name| event_field(&subject, "registryValueName", name)));
+                triples.extend(value_data.as_ref().map(|data| event_field(&subject, "registryValueData", data)));
+                triples.push(event_field(&subject, "registryOperation", operation));
+                triples.push(event_field(&subject, "processId", process_id));
+                triples.push(event_field(&subject, "user", user));
+                triples.push(event_field(&subject, "timestamp", timestamp));
+            }
+            CyberEvent::EmailReceived { sender, recipient, subject: email_subject, reply_to, urls, attachments, timestamp } => {
+                triples.push(event_field(&subject, "sender", sender));
+                triples.push(event_field(&subject, "recipient", recipient));
+                triples.push(event_field(&subject, "emailSubject", email_subject));
+                triples.extend(reply_to.as_ref().map(|address| event_field(&subject, "replyTo", address)));
+                triples.extend(urls.iter().map(|url| event_field(&subject, "url", url)));
+                triples.extend(attachments.iter().map(|name| event_field(&subject, "attachment", name)));
+                triples.push(event_field(&subject, "timestamp", timestamp));
+            }
         }
 
         triples
     }
     /// Create new reasoning engine
     pub fn new() -> Self {
-        let rdf_store = Arc::new(RwLock::new(RdfStore::new()));
-        let reasoning_engine = ReasoningEngine::new();
+        Self::with_processing_options(ProcessingOptions::default())
+    }
+
+    /// Create a reasoning engine with custom stage order and flags
+    pub fn with_processing_options(options: ProcessingOptions) -> Self {
+        Self::with_store(RdfStore::new(), options)
+    }
+
+    /// Create a reasoning engine over an existing (e.g. pre-loaded) store
+    pub fn with_store(store: RdfStore, options: ProcessingOptions) -> Self {
+        let rdf_store = SharedStore::new(store);
+        let reasoning_engine = ReasoningEngine::with_options(options);
 
         Self {
             rdf_store,
             reasoning_engine,
+            dedup: Arc::default(),
+            pending_correlations: Arc::default(),
+            ontologies: Mutex::new(OntologyRegistry::new()),
+            stages: StagePipeline::default(),
         }
     }
 
+    /// Let queries use a replica up to `max_staleness` old instead of rebuilding it after every write
+    ///
+    /// 既定は [`fukurow_store::DEFAULT_MAX_STALENESS`]。0 にするとクエリは完了した書き込みをすべて反映する
+    pub fn with_query_staleness(mut self, max_staleness: std::time::Duration) -> Self {
+        self.rdf_store = self.rdf_store.clone().with_max_staleness(max_staleness);
+        self
+    }
+
+    /// Register a rule applied in the rules stage of every reasoning run
+    pub fn with_rule(mut self, rule: Box<dyn Rule>) -> Self {
+        self.reasoning_engine.register_rule(rule);
+        self
+    }
+
+    /// Run `stage` on ingested events after the built-in stages (before reasoning)
+    pub fn with_stage(mut self, stage: Box<dyn PipelineStage>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Replace the ingestion stages, e.g. to place a stage before [`crate::stage::ASSERT_STAGE`]
+    pub fn with_stages(mut self, stages: StagePipeline) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Ingestion stages in execution order
+    pub fn stages(&self) -> &StagePipeline {
+        &self.stages
+    }
+
+    /// Replace the deduplication window and capacity
+    pub fn with_dedup(self, config: DedupConfig) -> Self {
+        *self.dedup.lock().unwrap() = EventDeduplicator::new(config);
+        self
+    }
+
     /// Add a cyber security event for reasoning
     pub async fn add_event(&self, event: CyberEvent) -> Result<(), ReasonerError> {
-        info!("Adding cyber event: {:?}", event);
+        self.add_event_from(event, "reasoner-engine").await
+    }
+
+    /// Add a cyber security event reported by a specific sensor
+    pub async fn add_event_from(&self, event: CyberEvent, source: &str) -> Result<(), ReasonerError> {
+        self.add_event_as(event, source, None).await
+    }
 
-        // Convert event to triples directly
-        let triples = Self::cyber_event_to_triples(&event);
+    /// Add a cyber security event, recording `actor` on the resulting audit entries
+    pub async fn add_event_as(&self, event: CyberEvent, source: &str, actor: Option<&str>) -> Result<(), ReasonerError> {
+        self.submit_event(event, source, None, actor).await.map(|_| ())
+    }
+
+    /// Add an event unless it was already seen within the deduplication window
+    ///
+    /// `event_id` は送信元が付けたイベント ID で、重複判定のキーと相関 ID に使う。
+    /// 無い場合は内容のハッシュで判定し、相関 ID を新たに採番する
+    pub async fn submit_event(&self, event: CyberEvent, source: &str, event_id: Option<&str>, actor: Option<&str>) -> Result<EventReceipt, ReasonerError> {
+        let key = dedup_key(&event, event_id);
+        let receipt = self.dedup.lock().unwrap().observe(&key, Instant::now(), || {
+            event_id.map(str::to_string).unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+        });
+        if receipt.duplicate {
+            debug!("Skipping duplicate event from {} (correlation {})", source, receipt.correlation_id);
+            return Ok(receipt);
+        }
+
+        info!("Adding cyber event from {}: {:?}", source, event);
+
+        let item = IngestItem::event(event, source).with_correlation_id(&receipt.correlation_id);
+        let mut batch = EventBatch::new(fukurow_store::provenance::GraphId::Named(crate::replay::EVENTS_GRAPH.to_string()), vec![item]);
 
         let mut store = self.rdf_store.write().await;
-        for triple in triples {
-            store.insert(triple, fukurow_store::provenance::GraphId::Named("events".to_string()),
-                         fukurow_store::provenance::Provenance::Sensor {
-                             source: "reasoner-engine".to_string(),
-                             confidence: None,
-                         });
+        let previous_actor = store.actor().map(str::to_string);
+        if actor.is_some() {
+            store.set_actor(actor.map(str::to_string));
+        }
+        let staged = self.stages.run(&mut store, &mut batch).await;
+        store.set_actor(previous_actor);
+        if let Err(e) = staged {
+            // 失敗したイベントは再送を重複として捨てないよう忘れる
+            self.dedup.lock().unwrap().forget(&key);
+            return Err(e.into());
         }
+        self.pending_correlations.lock().unwrap().push(receipt.correlation_id.clone());
 
-        Ok(())
+        Ok(receipt)
+    }
+
+    /// Events accepted since the last reasoning pass
+    pub fn pending_event_count(&self) -> usize {
+        self.pending_correlations.lock().unwrap().len()
+    }
+
+    /// Number of duplicate events skipped so far
+    pub fn duplicate_count(&self) -> u64 {
+        self.dedup.lock().unwrap().duplicates()
+    }
+
+    /// Store an event that failed validation in the quarantine graph instead of ingesting it
+    ///
+    /// 重複排除と推論の対象にはならない。受領の相関 ID には隔離レコードの ID を返す
+    pub async fn quarantine_event(&self, event: CyberEvent, source: &str, issues: &[ValidationIssue], actor: Option<&str>) -> Result<EventReceipt, ReasonerError> {
+        let record = QuarantinedEvent::new(event, source, issues);
+        warn!("Quarantining event {} from {}: {}", record.id, source, record.issues.join("; "));
+
+        let mut store = self.rdf_store.write().await;
+        let previous_actor = store.actor().map(str::to_string);
+        if actor.is_some() {
+            store.set_actor(actor.map(str::to_string));
+        }
+        store.insert_batch(record.to_triples(), QuarantinedEvent::graph_id(),
+                           fukurow_store::provenance::Provenance::Sensor {
+                               source: source.to_string(),
+                               confidence: None,
+                           });
+        store.set_actor(previous_actor);
+
+        Ok(EventReceipt { correlation_id: record.id, duplicate: false, quarantined: true })
+    }
+
+    /// Events held in the quarantine graph, oldest first
+    pub async fn quarantined_events(&self) -> Vec<QuarantinedEvent> {
+        QuarantinedEvent::all(&*self.fresh_query_view().await)
+    }
+
+    /// Rules applied by this engine (for inspecting ATT&CK mappings and the like)
+    pub fn rule_registry(&self) -> &fukurow_rules::RuleRegistry {
+        self.reasoning_engine.rule_registry()
+    }
+
+    /// Apply one rule to the current store, plus `event` if given, without committing anything
+    ///
+    /// レプリカのコピーに対して実行するため、稼働中のストアへの書き込みは起きない
+    pub async fn dry_run_rule(&self, name: &str, event: Option<&CyberEvent>) -> Result<fukurow_rules::RuleDryRun, ReasonerError> {
+        let view = self.fresh_query_view().await;
+        let result = match event {
+            None => self.rule_registry().dry_run(name, &view).await,
+            Some(event) => {
+                let mut scratch = view.snapshot().to_store();
+                scratch.insert_batch(
+                    Self::cyber_event_to_triples(event),
+                    fukurow_store::provenance::GraphId::Named(crate::replay::EVENTS_GRAPH.to_string()),
+                    fukurow_store::provenance::Provenance::Sensor { source: "dry-run".to_string(), confidence: None },
+                );
+                self.rule_registry().dry_run(name, &scratch).await
+            }
+        };
+        result.map_err(|e| ReasonerError::RuleError(e.to_string()))
+    }
+
+    /// Stage a new version of an ontology
+    pub fn register_ontology(&self, iri: &str, version: &str, triples: Vec<Triple>) -> Result<OntologyVersion, ReasonerError> {
+        Ok(self.ontologies.lock().unwrap().register(iri, version, triples)?.clone())
+    }
+
+    /// Check a staged version against the data currently in the store
+    pub async fn validate_ontology(&self, iri: &str, version: &str) -> Result<OntologyValidation, ReasonerError> {
+        let store = self.rdf_store.read().await;
+        Ok(self.ontologies.lock().unwrap().validate(iri, version, &store)?)
+    }
+
+    /// Check the store's consistency under `profile`, with a justification when inconsistent
+    ///
+    /// クエリ用レプリカを検査するため推論や投入は待たせない。公理数に応じて時間がかかる
+    pub fn check_consistency(&self, profile: ReasoningProfile) -> Result<OwlConsistencyReport, ReasonerError> {
+        crate::owl::owl_consistency_report(&self.query_view(), profile)
+            .map_err(|e| ReasonerError::ReasoningError(e.to_string()))
+    }
+
+    /// Switch the active version of an ontology
+    ///
+    /// 書き込みロックを保持したまま再検査・置き換え・旧版の推論の撤回を行う。
+    /// 新しい版からの推論は次回の `reason` で導かれる
+    pub async fn activate_ontology(&self, iri: &str, version: &str) -> Result<OntologySwap, ReasonerError> {
+        let mut store = self.rdf_store.write().await;
+        let swap = self.ontologies.lock().unwrap().activate(iri, version, &mut store)?;
+        info!(
+            "Activated ontology {} version {} (previous {:?}, {} inferences retracted)",
+            swap.iri, swap.version, swap.previous_version, swap.inferences_retracted
+        );
+        Ok(swap)
+    }
+
+    /// Registered ontology versions
+    pub fn ontology_versions(&self) -> Vec<OntologyVersion> {
+        self.ontologies.lock().unwrap().versions().cloned().collect()
+    }
+
+    /// Start a batch writer for high-volume ingestion into this engine's store
+    ///
+    /// `add_event` はイベントごとに書き込みロックを取得するため、大量投入時はこちらを使う。
+    /// 重複排除の窓と推論待ちの相関 ID はこのエンジンと共有する
+    pub fn start_batch_ingestion(&self, config: crate::ingest::IngestConfig) -> crate::ingest::BatchIngestor {
+        let correlation = crate::ingest::EventCorrelation {
+            dedup: Arc::clone(&self.dedup),
+            pending: Arc::clone(&self.pending_correlations),
+        };
+        crate::ingest::BatchIngestor::spawn_correlated(Arc::clone(self.rdf_store.primary()), config, self.stages.clone(), correlation)
     }
 
     /// Execute reasoning and return proposed security actions
-    /// No side effects - only returns action proposals
+    /// RDFS and rule inferences are written back (to the `rdfs` and `rules` inferred graphs); actions are proposals
     pub async fn reason(&self) -> Result<Vec<SecurityAction>, ReasonerError> {
-        info!("Starting reasoning process");
+        Ok(self.reason_correlated().await?.into_iter().map(|correlated| correlated.action).collect())
+    }
 
-        let store = self.rdf_store.read().await;
-        let result = self.reasoning_engine.process(&store).await
-            .map_err(|e| ReasonerError::ReasoningError(e.to_string()))?;
+    /// Execute reasoning and return actions with the correlation IDs of their originating events
+    ///
+    /// ルールが根拠のイベントを特定できなかったアクションには、前回の推論以降に受理した
+    /// 全イベントの相関 ID を付ける
+    pub async fn reason_correlated(&self) -> Result<Vec<CorrelatedAction>, ReasonerError> {
+        self.reason_correlated_with(None, None).await
+    }
+
+    /// Execute reasoning with the reasoners of `profile`
+    ///
+    /// タイムアウトはプロファイルごとの値 ([`ProcessingOptions::timeout_for`]) を使う
+    pub async fn reason_with_profile(&self, profile: ReasoningProfile) -> Result<Vec<CorrelatedAction>, ReasonerError> {
+        self.reason_correlated_with(Some(profile), None).await
+    }
+
+    /// [`Self::reason_correlated`] (or [`Self::reason_with_profile`]) reporting progress after each stage
+    pub async fn reason_with_progress(&self, profile: Option<ReasoningProfile>, observer: StageObserver<'_>) -> Result<Vec<CorrelatedAction>, ReasonerError> {
+        self.reason_correlated_with(profile, Some(observer)).await
+    }
+
+    async fn reason_correlated_with(&self, profile: Option<ReasoningProfile>, observer: Option<StageObserver<'_>>) -> Result<Vec<CorrelatedAction>, ReasonerError> {
+        let span = tracing::info_span!(
+            spans::REASONING_EXECUTE,
+            reasoning.profile = profile.map_or("default", |profile| profile.as_str()),
+            action.count = tracing::field::Empty,
+            error.type = tracing::field::Empty
+        );
+        let result = self.reason_correlated_inner(profile, observer).instrument(span.clone()).await;
+        match &result {
+            Ok(actions) => span.record(attributes::ACTION_COUNT, actions.len()),
+            Err(e) => span.record(attributes::ERROR_TYPE, tracing::field::display(e)),
+        };
+        result
+    }
+
+    async fn reason_correlated_inner(&self, profile: Option<ReasoningProfile>, observer: Option<StageObserver<'_>>) -> Result<Vec<CorrelatedAction>, ReasonerError> {
+        info!("Starting reasoning process (profile: {})", profile.map_or("default", |profile| profile.as_str()));
+
+        let mut store = self.rdf_store.write().await;
+        let result = match observer {
+            Some(observer) => self.reasoning_engine.process_with_progress(&mut store, profile, observer).await,
+            None => match profile {
+                Some(profile) => self.reasoning_engine.process_with_profile(&mut store, profile).await,
+                None => self.reasoning_engine.process_and_materialize(&mut store).await,
+            },
+        }.map_err(|e| ReasonerError::ReasoningError(e.to_string()))?;
+        let pending = std::mem::take(&mut *self.pending_correlations.lock().unwrap());
 
         info!("Reasoning complete, proposed {} actions", result.actions.len());
-        Ok(result.actions)
+        Ok(result.correlated_actions().into_iter()
+            .map(|mut correlated| {
+                if correlated.correlation_ids.is_empty() {
+                    correlated.correlation_ids = pending.clone();
+                }
+                correlated
+            })
+            .collect())
+    }
+
+    /// Replay events from this engine's audit trail with the current rules
+    ///
+    /// 推論は作業用ストアで行うため、稼働中のストアは読み取りロックしか取らない
+    pub async fn replay(&self, request: &crate::replay::ReplayRequest) -> Result<crate::replay::ReplayRun, ReasonerError> {
+        let store = self.rdf_store.read().await;
+        crate::replay::replay_events(&self.reasoning_engine, &store, request).await
+            .map_err(|e| ReasonerError::ReasoningError(e.to_string()))
+    }
+
+    /// Execute reasoning within a tenant's isolated pool
+    pub async fn reason_for_tenant(&self, scheduler: &crate::tenancy::TenantScheduler, tenant: &str) -> Result<Vec<SecurityAction>, ReasonerError> {
+        scheduler.run(tenant, self.reason()).await
+            .map_err(|e| ReasonerError::CapacityError(e.to_string()))?
     }
 
     /// Process an external RDF store and return reasoning results
@@ -194,14 +511,38 @@ impl ReasonerEngine {
     }
 
     /// Get current graph store (read-only access)
+    ///
+    /// ロックを取るため、長いクエリには [`Self::query_view`] を使う
     pub async fn get_graph_store(&self) -> Arc<RwLock<RdfStore>> {
-        Arc::clone(&self.rdf_store)
+        Arc::clone(self.rdf_store.primary())
+    }
+
+    /// Read-only replica for queries; never blocks ingestion or reasoning and is never blocked by them
+    ///
+    /// 推論の実行中は直前の状態を返す。監査ログとセンサー情報は含まない
+    /// (一貫性の保証は [`fukurow_store::shared`] を参照)
+    pub fn query_view(&self) -> Arc<RdfStore> {
+        self.rdf_store.view()
+    }
+
+    /// Replica including every write completed before the call (waits for a running write)
+    pub async fn fresh_query_view(&self) -> Arc<RdfStore> {
+        self.rdf_store.fresh_view().await
+    }
+
+    /// Start evicting expired triples from this engine's store in the background
+    ///
+    /// 返されたハンドルを破棄するとタスクは止まる (Tokio ランタイムが必要)
+    pub fn spawn_retention(&self, enforcer: fukurow_store::RetentionEnforcer, interval: std::time::Duration) -> fukurow_store::RetentionTask {
+        enforcer.spawn(Arc::clone(self.rdf_store.primary()), interval)
     }
 
     /// Clear all events and reset reasoning state
     pub async fn reset(&mut self) -> Result<(), ReasonerError> {
         let mut store = self.rdf_store.write().await;
         store.clear_all();
+        self.dedup.lock().unwrap().clear();
+        self.pending_correlations.lock().unwrap().clear();
         Ok(())
     }
 
@@ -212,6 +553,15 @@ impl ReasonerEngine {
     }
 }
 
+/// Event property triple using the ingestion vocabulary (`http://example.org/<name>`)
+pub(crate) fn event_field(subject: &str, name: &str, value: impl ToString) -> fukurow_store::Triple {
+    fukurow_store::Triple {
+        subject: subject.to_string(),
+        predicate: format!("http://example.org/{}", name),
+        object: value.to_string(),
+    }
+}
+
 /// Reasoning engine errors
 #[derive(Debug, thiserror::Error)]
 pub enum ReasonerError {
@@ -226,4 +576,13 @@ pub enum ReasonerError {
 
     #[error("Store operation error: {0}")]
     StoreError(String),
+
+    #[error("Reasoning capacity error: {0}")]
+    CapacityError(String),
+
+    #[error("Ontology error: {0}")]
+    OntologyError(#[from] OntologyError),
+
+    #[error("Pipeline stage error: {0}")]
+    StageError(#[from] StageError),
 }
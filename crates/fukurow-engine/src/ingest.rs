@@ -0,0 +1,404 @@
+//! # Batched Event Ingestion
+//!
+//! Asynchronous insert path for event floods.
+//! イベントをロックフリーなチャネルに積み、専用のライタータスクが
+//! ストアの書き込みロックを1バッチにつき1回だけ取得して取り込みステージをまとめて適用する。
+//! イベントは `ReasonerEngine::submit_event` と同じ重複排除と相関 ID の付与を通る
+
+use crate::dedup::{dedup_key, EventDeduplicator};
+use crate::engine::ReasonerError;
+use crate::stage::{EventBatch, IngestItem, StagePipeline};
+use fukurow_core::model::CyberEvent;
+use fukurow_store::provenance::GraphId;
+use fukurow_store::{store::RdfStore, Triple};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, RwLock};
+use tracing::{debug, warn};
+
+/// Batch writer configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestConfig {
+    /// Maximum queued items before `submit` waits (backpressure)
+    pub queue_capacity: usize,
+    /// Maximum items applied under a single write lock
+    pub max_batch_size: usize,
+    /// How long the writer waits for more items once a batch has started (milliseconds)
+    pub max_batch_delay_ms: u64,
+    /// Named graph receiving event triples
+    pub graph: String,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 10_000,
+            max_batch_size: 512,
+            max_batch_delay_ms: 5,
+            graph: "events".to_string(),
+        }
+    }
+}
+
+/// Completion notification for one submitted item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestReceipt {
+    /// Triples inserted for this item
+    pub triples: usize,
+    /// Sequence number of the batch that applied the item
+    pub batch_id: u64,
+    /// Number of items in that batch
+    pub batch_size: usize,
+    /// Correlation ID recorded on the event (the original one for duplicates; none for raw triples)
+    pub correlation_id: Option<String>,
+    /// The event was seen within the deduplication window and was not written again
+    pub duplicate: bool,
+}
+
+/// Writer statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestStats {
+    pub submitted: u64,
+    pub applied: u64,
+    pub triples: u64,
+    pub batches: u64,
+    pub largest_batch: usize,
+}
+
+#[derive(Debug, Default)]
+struct IngestCounters {
+    submitted: AtomicU64,
+    applied: AtomicU64,
+    triples: AtomicU64,
+    batches: AtomicU64,
+    largest_batch: AtomicUsize,
+}
+
+/// Receipt of an item, or why the stages failed its batch
+type IngestAck = oneshot::Sender<Result<IngestReceipt, String>>;
+
+/// Deduplication window and correlation IDs awaiting reasoning, shared with the owning engine
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EventCorrelation {
+    pub(crate) dedup: Arc<Mutex<EventDeduplicator>>,
+    pub(crate) pending: Arc<Mutex<Vec<String>>>,
+}
+
+enum IngestCommand {
+    Insert {
+        item: Box<IngestItem>,
+        /// Deduplication key of an event item (forgotten again if its batch fails)
+        dedup_key: Option<String>,
+        ack: Option<IngestAck>,
+    },
+    Flush(oneshot::Sender<()>),
+}
+
+/// Pending completion of a submitted item
+#[derive(Debug)]
+pub struct IngestTicket {
+    receiver: oneshot::Receiver<Result<IngestReceipt, String>>,
+}
+
+impl IngestTicket {
+    /// Wait until the item has been written to the store
+    pub async fn wait(self) -> Result<IngestReceipt, ReasonerError> {
+        self.receiver.await
+            .map_err(|_| ReasonerError::StoreError("Batch writer stopped before applying the item".to_string()))?
+            .map_err(ReasonerError::StoreError)
+    }
+}
+
+/// Handle to the batch writer task
+///
+/// Cloning the handle shares the same writer; the task exits once every handle is dropped.
+#[derive(Debug, Clone)]
+pub struct BatchIngestor {
+    sender: mpsc::Sender<IngestCommand>,
+    counters: Arc<IngestCounters>,
+    correlation: EventCorrelation,
+}
+
+impl BatchIngestor {
+    /// Spawn the writer task for `store` with the built-in stages (requires a Tokio runtime)
+    pub fn spawn(store: Arc<RwLock<RdfStore>>, config: IngestConfig) -> Self {
+        Self::spawn_with_stages(store, config, StagePipeline::default())
+    }
+
+    /// Spawn the writer task running `stages` over each batch, with its own deduplication window
+    pub fn spawn_with_stages(store: Arc<RwLock<RdfStore>>, config: IngestConfig, stages: StagePipeline) -> Self {
+        Self::spawn_correlated(store, config, stages, EventCorrelation::default())
+    }
+
+    /// Spawn the writer task sharing an engine's deduplication window and pending correlations
+    pub(crate) fn spawn_correlated(store: Arc<RwLock<RdfStore>>, config: IngestConfig, stages: StagePipeline, correlation: EventCorrelation) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        let counters = Arc::new(IngestCounters::default());
+        tokio::spawn(Self::run_writer(store, config, stages, receiver, Arc::clone(&counters), correlation.clone()));
+        Self { sender, counters, correlation }
+    }
+
+    /// Queue an event for insertion with sensor provenance
+    pub async fn submit(&self, event: &CyberEvent, source: &str) -> Result<IngestTicket, ReasonerError> {
+        self.submit_event(event, source, None).await
+    }
+
+    /// Queue an event unless it was already seen within the deduplication window
+    ///
+    /// `event_id` の扱いは `ReasonerEngine::submit_event` と同じ。重複はキューに積まず、
+    /// 元の相関 ID を持つ受領をすぐに返すチケットになる
+    pub async fn submit_event(&self, event: &CyberEvent, source: &str, event_id: Option<&str>) -> Result<IngestTicket, ReasonerError> {
+        let (ack, receiver) = oneshot::channel();
+        match self.correlate(event, source, event_id) {
+            Ok((item, key)) => self.send(IngestCommand::Insert { item: Box::new(item), dedup_key: Some(key), ack: Some(ack) }).await?,
+            Err(duplicate) => {
+                let _ = ack.send(Ok(duplicate));
+            }
+        }
+        Ok(IngestTicket { receiver })
+    }
+
+    /// Queue already converted triples
+    pub async fn submit_triples(&self, triples: Vec<Triple>, source: &str) -> Result<IngestTicket, ReasonerError> {
+        self.submit_item(IngestItem::triples(triples, source)).await
+    }
+
+    /// Queue an event without waiting for (or tracking) its completion
+    pub async fn enqueue(&self, event: &CyberEvent, source: &str) -> Result<(), ReasonerError> {
+        match self.correlate(event, source, None) {
+            Ok((item, key)) => self.send(IngestCommand::Insert { item: Box::new(item), dedup_key: Some(key), ack: None }).await,
+            Err(_) => Ok(()),
+        }
+    }
+
+    async fn submit_item(&self, item: IngestItem) -> Result<IngestTicket, ReasonerError> {
+        let (ack, receiver) = oneshot::channel();
+        self.send(IngestCommand::Insert { item: Box::new(item), dedup_key: None, ack: Some(ack) }).await?;
+        Ok(IngestTicket { receiver })
+    }
+
+    /// Observe `event` in the deduplication window: the item to write, or the receipt of a duplicate
+    fn correlate(&self, event: &CyberEvent, source: &str, event_id: Option<&str>) -> Result<(IngestItem, String), IngestReceipt> {
+        let key = dedup_key(event, event_id);
+        let receipt = self.correlation.dedup.lock().unwrap().observe(&key, Instant::now(), || {
+            event_id.map(str::to_string).unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+        });
+        if receipt.duplicate {
+            debug!("Skipping duplicate event from {} (correlation {})", source, receipt.correlation_id);
+            return Err(IngestReceipt {
+                triples: 0,
+                batch_id: 0,
+                batch_size: 0,
+                correlation_id: Some(receipt.correlation_id),
+                duplicate: true,
+            });
+        }
+        Ok((IngestItem::event(event.clone(), source).with_correlation_id(&receipt.correlation_id), key))
+    }
+
+    /// Queue an event and wait until it is in the store
+    pub async fn ingest(&self, event: &CyberEvent, source: &str) -> Result<IngestReceipt, ReasonerError> {
+        self.submit(event, source).await?.wait().await
+    }
+
+    /// Wait until everything queued before this call has been applied
+    pub async fn flush(&self) -> Result<(), ReasonerError> {
+        let (done, receiver) = oneshot::channel();
+        self.send(IngestCommand::Flush(done)).await?;
+        receiver.await
+            .map_err(|_| ReasonerError::StoreError("Batch writer stopped during flush".to_string()))
+    }
+
+    pub fn stats(&self) -> IngestStats {
+        IngestStats {
+            submitted: self.counters.submitted.load(Ordering::Relaxed),
+            applied: self.counters.applied.load(Ordering::Relaxed),
+            triples: self.counters.triples.load(Ordering::Relaxed),
+            batches: self.counters.batches.load(Ordering::Relaxed),
+            largest_batch: self.counters.largest_batch.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn send(&self, command: IngestCommand) -> Result<(), ReasonerError> {
+        if matches!(command, IngestCommand::Insert { .. }) {
+            self.counters.submitted.fetch_add(1, Ordering::Relaxed);
+        }
+        self.sender.send(command).await
+            .map_err(|_| ReasonerError::StoreError("Batch writer is not running".to_string()))
+    }
+
+    async fn run_writer(
+        store: Arc<RwLock<RdfStore>>,
+        config: IngestConfig,
+        stages: StagePipeline,
+        mut receiver: mpsc::Receiver<IngestCommand>,
+        counters: Arc<IngestCounters>,
+        correlation: EventCorrelation,
+    ) {
+        let max_batch_size = config.max_batch_size.max(1);
+        let delay = Duration::from_millis(config.max_batch_delay_ms);
+        let graph_id = GraphId::Named(config.graph.clone());
+
+        while let Some(first) = receiver.recv().await {
+            let mut batch = vec![first];
+
+            // 最初の要素の後、上限件数か待機時間に達するまで追加の要素を集める
+            while batch.len() < max_batch_size {
+                match receiver.try_recv() {
+                    Ok(command) => batch.push(command),
+                    Err(_) if delay.is_zero() => break,
+                    Err(_) => match tokio::time::timeout(delay, receiver.recv()).await {
+                        Ok(Some(command)) => batch.push(command),
+                        _ => break,
+                    },
+                }
+                if matches!(batch.last(), Some(IngestCommand::Flush(_))) {
+                    break;
+                }
+            }
+
+            let batch_id = counters.batches.fetch_add(1, Ordering::Relaxed) + 1;
+            let batch_size = batch.iter().filter(|c| matches!(c, IngestCommand::Insert { .. })).count();
+            counters.largest_batch.fetch_max(batch_size, Ordering::Relaxed);
+
+            let mut items = Vec::with_capacity(batch_size);
+            let mut acks = Vec::with_capacity(batch_size);
+            let mut dedup_keys = Vec::new();
+            let mut flushes = Vec::new();
+            for command in batch {
+                match command {
+                    IngestCommand::Insert { item, dedup_key, ack } => {
+                        items.push(*item);
+                        acks.push(ack);
+                        dedup_keys.extend(dedup_key);
+                    }
+                    IngestCommand::Flush(done) => flushes.push(done),
+                }
+            }
+
+            let mut events = EventBatch::new(graph_id.clone(), items);
+            let staged = match events.items.is_empty() {
+                true => Ok(()),
+                false => stages.run(&mut *store.write().await, &mut events).await,
+            };
+
+            // 通知はロック解放後に送る
+            match staged {
+                Ok(()) => {
+                    debug!("Applied ingestion batch {} ({} items)", batch_id, batch_size);
+                    correlation.pending.lock().unwrap()
+                        .extend(events.items.iter().filter_map(|item| item.correlation_id.clone()));
+                    for (item, ack) in events.items.iter().zip(acks) {
+                        let triples = item.triples.len();
+                        counters.applied.fetch_add(1, Ordering::Relaxed);
+                        counters.triples.fetch_add(triples as u64, Ordering::Relaxed);
+                        if let Some(ack) = ack {
+                            let receipt = IngestReceipt {
+                                triples,
+                                batch_id,
+                                batch_size,
+                                correlation_id: item.correlation_id.clone(),
+                                duplicate: false,
+                            };
+                            let _ = ack.send(Ok(receipt));
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Ingestion batch {} failed: {}", batch_id, e);
+                    // 失敗したイベントは再送を重複として捨てないよう忘れる
+                    let mut dedup = correlation.dedup.lock().unwrap();
+                    for key in &dedup_keys {
+                        dedup.forget(key);
+                    }
+                    drop(dedup);
+                    for ack in acks.into_iter().flatten() {
+                        let _ = ack.send(Err(e.to_string()));
+                    }
+                }
+            }
+            for done in flushes {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ReasonerEngine;
+
+    fn login(i: u64) -> CyberEvent {
+        CyberEvent::UserLogin {
+            user: format!("user{}", i),
+            source_ip: "10.0.0.1".to_string(),
+            success: true,
+            timestamp: 1_700_000_000 + i as i64,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flood_is_applied_in_batches() {
+        let engine = ReasonerEngine::new();
+        let ingestor = engine.start_batch_ingestion(IngestConfig { max_batch_size: 64, ..Default::default() });
+
+        let mut tickets = Vec::new();
+        for i in 0..200 {
+            tickets.push(ingestor.submit(&login(i), "edr-1").await.unwrap());
+        }
+        for ticket in tickets {
+            let receipt = ticket.wait().await.unwrap();
+            // イベントの 5 トリプル + correlationId
+            assert_eq!(receipt.triples, 6);
+            assert!(receipt.batch_size <= 64);
+        }
+
+        let stats = ingestor.stats();
+        assert_eq!(stats.applied, 200);
+        assert_eq!(stats.triples, 1200);
+        assert!(stats.batches < 200, "events should be coalesced: {:?}", stats);
+
+        let store = engine.get_graph_store().await;
+        let store = store.read().await;
+        assert_eq!(store.get_graph(&GraphId::Named("events".to_string())).len(), 1200);
+        assert_eq!(store.sensor_registry().get("edr-1").unwrap().observation_count, 1200);
+    }
+
+    #[tokio::test]
+    async fn test_flush_waits_for_queued_items() {
+        let engine = ReasonerEngine::new();
+        let ingestor = engine.start_batch_ingestion(IngestConfig::default());
+
+        for i in 0..10 {
+            ingestor.enqueue(&login(i), "edr-2").await.unwrap();
+        }
+        ingestor.flush().await.unwrap();
+
+        let store = engine.get_graph_store().await;
+        assert_eq!(store.read().await.find_triples(None, Some("http://example.org/user"), None).len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_batch_shares_engine_deduplication() {
+        let engine = ReasonerEngine::new();
+        let first = engine.submit_event(login(1), "edr-1", Some("evt-1"), None).await.unwrap();
+        let ingestor = engine.start_batch_ingestion(IngestConfig::default());
+
+        // 単発投入で見たイベントはバッチでも重複になる
+        let duplicate = ingestor.submit_event(&login(1), "edr-1", Some("evt-1")).await.unwrap().wait().await.unwrap();
+        assert!(duplicate.duplicate);
+        assert_eq!(duplicate.correlation_id.as_deref(), Some(first.correlation_id.as_str()));
+
+        let fresh = ingestor.submit_event(&login(2), "edr-1", Some("evt-2")).await.unwrap().wait().await.unwrap();
+        assert!(!fresh.duplicate);
+        assert_eq!(fresh.correlation_id.as_deref(), Some("evt-2"));
+        assert!(engine.submit_event(login(2), "edr-1", Some("evt-2"), None).await.unwrap().duplicate);
+
+        assert_eq!(engine.pending_event_count(), 2);
+        let store = engine.get_graph_store().await;
+        assert_eq!(store.read().await.find_triples(None, Some("http://example.org/correlationId"), Some("evt-2")).len(), 1);
+    }
+}
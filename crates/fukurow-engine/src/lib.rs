@@ -7,11 +7,31 @@ pub mod engine;
 pub mod orchestration;
 pub mod pipeline;
 pub mod scaling;
+pub mod tenancy;
+pub mod ingest;
+pub mod dedup;
+pub mod quarantine;
+pub mod replay;
+pub mod owl;
+pub mod ontology;
+pub mod schedule;
+pub mod stage;
+pub mod workers;
 
 pub use engine::*;
 pub use orchestration::*;
 pub use pipeline::*;
 pub use scaling::*;
+pub use tenancy::*;
+pub use ingest::*;
+pub use dedup::*;
+pub use quarantine::*;
+pub use replay::*;
+pub use owl::*;
+pub use ontology::*;
+pub use schedule::*;
+pub use stage::*;
+pub use workers::*;
 
 #[cfg(test)]
 mod tests {
@@ -51,6 +71,54 @@ mod tests {
         assert!(!triples.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_query_view_does_not_wait_for_reasoning_lock() {
+        let reasoner = ReasonerEngine::new();
+        reasoner.add_event(CyberEvent::NetworkConnection {
+            source_ip: "192.168.1.10".to_string(),
+            dest_ip: "10.0.0.50".to_string(),
+            port: 443,
+            protocol: "tcp".to_string(),
+            timestamp: 1640995200,
+        }).await.unwrap();
+        let before = reasoner.fresh_query_view().await.statistics().total_triples;
+        assert!(before > 0);
+
+        // 書き込みロック中 (推論中と同じ) でもクエリは直前の状態を読める
+        let store = reasoner.get_graph_store().await;
+        let mut writer = store.write().await;
+        writer.clear_all();
+        assert_eq!(reasoner.query_view().statistics().total_triples, before);
+        drop(writer);
+        assert_eq!(reasoner.fresh_query_view().await.statistics().total_triples, 0);
+    }
+
+    #[test]
+    fn test_email_event_triples_repeat_multi_valued_fields() {
+        let event = CyberEvent::EmailReceived {
+            sender: "it@examp1e.com".to_string(),
+            recipient: "bob@example.com".to_string(),
+            subject: "Password expires today".to_string(),
+            reply_to: None,
+            urls: vec!["http://198.51.100.7/a".to_string(), "http://198.51.100.7/b".to_string()],
+            attachments: vec!["invoice.pdf.exe".to_string()],
+            timestamp: 1640995200,
+        };
+
+        let triples = ReasonerEngine::cyber_event_to_triples(&event);
+        let objects = |name: &str| -> Vec<String> {
+            triples.iter()
+                .filter(|t| t.predicate == format!("http://example.org/{}", name))
+                .map(|t| t.object.clone())
+                .collect()
+        };
+        assert!(triples.iter().all(|t| t.subject == "event:1640995200"));
+        assert_eq!(objects("url").len(), 2);
+        assert_eq!(objects("attachment"), vec!["invoice.pdf.exe"]);
+        assert_eq!(objects("emailSubject"), vec!["Password expires today"]);
+        assert!(objects("replyTo").is_empty());
+    }
+
     #[tokio::test]
     async fn test_reasoning_engine_creation() {
         let engine = ReasoningEngine::new();
@@ -98,6 +166,7 @@ mod tests {
                     details: serde_json::json!({"test": true}),
                 }
             ],
+            action_correlations: vec![],
             violations: vec![],
             stats: ProcessingStats {
                 rules_applied: 1,
@@ -148,4 +217,255 @@ mod tests {
         let internal_err = EngineError::InternalError("test error".to_string());
         assert!(internal_err.to_string().contains("test error"));
     }
+
+    fn subclass_store() -> RdfStore {
+        let mut store = RdfStore::new();
+        store.insert(Triple {
+            subject: "http://example.org/CyberEvent".to_string(),
+            predicate: "http://www.w3.org/2000/01/rdf-schema#subClassOf".to_string(),
+            object: "http://example.org/Event".to_string(),
+        }, fukurow_store::provenance::GraphId::Named("ontology".to_string()), fukurow_store::provenance::Provenance::Imported {
+            source_uri: "test".to_string(),
+            imported_at: 0,
+        });
+        store
+    }
+
+    #[tokio::test]
+    async fn test_rdfs_stage_materializes_into_inferred_graph() {
+        let reasoner = ReasonerEngine::new();
+        let store = reasoner.get_graph_store().await;
+        *store.write().await = subclass_store();
+        reasoner.add_event(CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            success: true,
+            timestamp: 1700000000,
+        }).await.unwrap();
+
+        reasoner.reason().await.unwrap();
+        reasoner.reason().await.unwrap();
+
+        let graph_store = store.read().await;
+        let inferred = graph_store.find_triples(Some("event:1700000000"), None, Some("http://example.org/Event"));
+        // 再実行しても推論グラフは作り直されるので重複しない
+        assert_eq!(inferred.len(), 1);
+        assert_eq!(inferred[0].graph_id, fukurow_store::provenance::GraphId::Inferred(RDFS_INFERRED_GRAPH.to_string()));
+        assert!(matches!(&inferred[0].provenance, fukurow_store::provenance::Provenance::Inferred { reasoning_level, .. } if reasoning_level == "rdfs"));
+    }
+
+    #[tokio::test]
+    async fn test_stage_order_is_configurable() {
+        let mut store = subclass_store();
+        store.insert(Triple {
+            subject: "event:1".to_string(),
+            predicate: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+            object: "http://example.org/CyberEvent".to_string(),
+        }, fukurow_store::provenance::GraphId::Default, fukurow_store::provenance::Provenance::Sensor {
+            source: "test".to_string(),
+            confidence: None,
+        });
+
+        let without_rdfs = ReasoningEngine::with_options(ProcessingOptions::default().with_stages(vec![ReasoningStage::Rules, ReasoningStage::Validation]));
+        let result = without_rdfs.process_and_materialize(&mut store).await.unwrap();
+        assert!(result.inferred_triples.is_empty());
+
+        let rdfs_last = ReasoningEngine::with_options(ProcessingOptions::default().with_stages(vec![ReasoningStage::Rules, ReasoningStage::Rdfs]));
+        let result = rdfs_last.process_and_materialize(&mut store).await.unwrap();
+        assert_eq!(result.inferred_triples.len(), 1);
+        assert_eq!(store.get_graph(&fukurow_store::provenance::GraphId::Inferred(RDFS_INFERRED_GRAPH.to_string())).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_events_are_ingested_once() {
+        let reasoner = ReasonerEngine::new();
+        let event = CyberEvent::NetworkConnection {
+            source_ip: "192.168.1.10".to_string(),
+            dest_ip: "10.0.0.50".to_string(),
+            port: 443,
+            protocol: "tcp".to_string(),
+            timestamp: 1640995200,
+        };
+
+        // Kafka 経由と REST 経由で同じイベント ID が届く
+        let first = reasoner.submit_event(event.clone(), "kafka", Some("edr-7"), None).await.unwrap();
+        let second = reasoner.submit_event(event.clone(), "api", Some("edr-7"), None).await.unwrap();
+        assert_eq!(first, EventReceipt { correlation_id: "edr-7".to_string(), duplicate: false, quarantined: false });
+        assert_eq!(second, EventReceipt { correlation_id: "edr-7".to_string(), duplicate: true, quarantined: false });
+        assert_eq!(reasoner.duplicate_count(), 1);
+
+        let store = reasoner.get_graph_store().await;
+        let store_read = store.read().await;
+        let ids = store_read.find_triples(Some("event:1640995200"), Some(CORRELATION_ID_PREDICATE), None);
+        assert_eq!(ids.len(), 1);
+        assert_eq!(ids[0].triple.object, "edr-7");
+        // 重複は別のセンサーからの来歴としても記録されない
+        assert!(store_read.find_triples(None, None, None).iter().all(|stored| matches!(
+            &stored.provenance,
+            fukurow_store::provenance::Provenance::Sensor { source, .. } if source == "kafka"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_failed_events_are_not_remembered_as_duplicates() {
+        use async_trait::async_trait;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        /// Fails until `healthy` is set, like an enrichment backend that is down
+        struct FlakyStage(Arc<AtomicBool>);
+
+        #[async_trait]
+        impl PipelineStage for FlakyStage {
+            fn name(&self) -> &str {
+                "flaky"
+            }
+
+            async fn process(&self, _store: &mut RdfStore, _batch: &mut EventBatch) -> Result<(), StageError> {
+                if self.0.load(Ordering::SeqCst) { Ok(()) } else { Err(StageError::failed("flaky", "backend unavailable")) }
+            }
+        }
+
+        let healthy = Arc::new(AtomicBool::new(false));
+        let reasoner = ReasonerEngine::new().with_stage(Box::new(FlakyStage(healthy.clone())));
+        let event = CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            success: true,
+            timestamp: 1700000000,
+        };
+
+        assert!(reasoner.submit_event(event.clone(), "api", Some("edr-9"), None).await.is_err());
+        assert_eq!(reasoner.pending_event_count(), 0);
+
+        // 窓内の再送は重複扱いされずに取り込まれる
+        healthy.store(true, Ordering::SeqCst);
+        let retry = reasoner.submit_event(event, "api", Some("edr-9"), None).await.unwrap();
+        assert!(!retry.duplicate);
+        assert_eq!(reasoner.pending_event_count(), 1);
+        assert_eq!(reasoner.duplicate_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_can_be_disabled() {
+        let reasoner = ReasonerEngine::new().with_dedup(DedupConfig::disabled());
+        let event = CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            success: true,
+            timestamp: 1700000000,
+        };
+
+        let first = reasoner.submit_event(event.clone(), "kafka", None, None).await.unwrap();
+        let second = reasoner.submit_event(event, "api", None, None).await.unwrap();
+        assert!(!second.duplicate);
+        assert_ne!(first.correlation_id, second.correlation_id);
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_profile_selects_reasoners() {
+        use fukurow_store::provenance::{GraphId, Provenance};
+
+        let profile: ReasoningProfile = serde_json::from_str("\"owl-lite\"").unwrap();
+        assert_eq!(profile, ReasoningProfile::OwlLite);
+        assert_eq!("OWL-DL".parse::<ReasoningProfile>().unwrap(), ReasoningProfile::OwlDl);
+        let options = ProcessingOptions::default().with_profile_timeout(ReasoningProfile::OwlLite, 750).with_profile(profile);
+        assert_eq!(options.stages, vec![ReasoningStage::Rdfs, ReasoningStage::OwlLite, ReasoningStage::Rules, ReasoningStage::Validation]);
+        assert_eq!(options.timeout_ms, Some(750));
+
+        const CONNECTS: &str = "http://example.org/connectsTo";
+        let mut store = RdfStore::new();
+        let sensor = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        for (s, p, o) in [
+            (CONNECTS, "http://www.w3.org/1999/02/22-rdf-syntax-ns#type", "http://www.w3.org/2002/07/owl#ObjectProperty"),
+            (CONNECTS, "http://www.w3.org/1999/02/22-rdf-syntax-ns#type", "http://www.w3.org/2002/07/owl#TransitiveProperty"),
+            ("http://example.org/h1", CONNECTS, "http://example.org/h2"),
+            ("http://example.org/h2", CONNECTS, "http://example.org/h3"),
+        ] {
+            store.insert(Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() }, GraphId::Default, sensor.clone());
+        }
+
+        let engine = ReasoningEngine::new();
+        engine.process_with_profile(&mut store, ReasoningProfile::Rdfs).await.unwrap();
+        assert!(store.find_triples(Some("http://example.org/h1"), Some(CONNECTS), Some("http://example.org/h3")).is_empty());
+
+        let result = engine.process_with_profile(&mut store, ReasoningProfile::OwlLite).await.unwrap();
+        assert!(result.inferred_triples.iter().any(|t| t.subject == "http://example.org/h1" && t.object == "http://example.org/h3"));
+        let entailed = store.find_triples(Some("http://example.org/h1"), Some(CONNECTS), Some("http://example.org/h3"));
+        assert_eq!(entailed.len(), 1);
+        assert_eq!(entailed[0].graph_id, GraphId::Inferred(OWL_LITE_INFERRED_GRAPH.to_string()));
+        assert!(matches!(&entailed[0].provenance, Provenance::Inferred { reasoning_level, .. } if reasoning_level == "owl-lite"));
+
+        // 弱いプロファイルで再実行すると OWL の推論は消える
+        engine.process_with_profile(&mut store, ReasoningProfile::Rdfs).await.unwrap();
+        assert!(store.find_triples(Some("http://example.org/h1"), Some(CONNECTS), Some("http://example.org/h3")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rdfs_inferences_carry_evidence_confidence() {
+        use fukurow_store::confidence::ConfidenceCombination;
+        use fukurow_store::provenance::{GraphId, Provenance};
+
+        let mut store = subclass_store();
+        let typed = Triple {
+            subject: "event:1".to_string(),
+            predicate: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+            object: "http://example.org/CyberEvent".to_string(),
+        };
+        store.insert(typed.clone(), GraphId::Sensor("edr".to_string()), Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.6) });
+
+        let options = ProcessingOptions::default()
+            .with_stages(vec![ReasoningStage::Rdfs])
+            .with_confidence_combination(ConfidenceCombination::Product);
+        ReasoningEngine::with_options(options).process_and_materialize(&mut store).await.unwrap();
+
+        let inferred = store.find_triples(Some("event:1"), None, Some("http://example.org/Event"));
+        match &inferred[0].provenance {
+            Provenance::Inferred { evidence, confidence, .. } => {
+                assert_eq!(*confidence, Some(0.6));
+                assert!(evidence.contains(&fukurow_store::retention::evidence_key(&typed)));
+            }
+            other => panic!("unexpected provenance: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rdfs_rematerialization_writes_only_changes() {
+        use fukurow_store::provenance::{GraphId, Provenance};
+
+        let mut store = subclass_store();
+        store.insert(Triple {
+            subject: "event:1".to_string(),
+            predicate: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+            object: "http://example.org/CyberEvent".to_string(),
+        }, GraphId::Sensor("edr".to_string()), Provenance::Sensor { source: "edr".to_string(), confidence: None });
+        let engine = ReasoningEngine::with_options(ProcessingOptions::default().with_stages(vec![ReasoningStage::Rdfs]));
+
+        engine.process_and_materialize(&mut store).await.unwrap();
+        let audited = store.audit_trail().len();
+        let result = engine.process_and_materialize(&mut store).await.unwrap();
+        // 閉包が変わらなければ何も書き込まない
+        assert_eq!(store.audit_trail().len(), audited);
+        assert!(!result.inferred_triples.is_empty());
+        assert_eq!(store.find_triples(Some("event:1"), None, Some("http://example.org/Event")).len(), 1);
+
+        // 根拠を失った推論だけが消える
+        store.clear_graph(&GraphId::Named("ontology".to_string()));
+        engine.process_and_materialize(&mut store).await.unwrap();
+        assert!(store.find_triples(Some("event:1"), None, Some("http://example.org/Event")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_with_progress_reports_each_stage() {
+        let seen = std::sync::Mutex::new(Vec::new());
+        let observer = |progress: &StageProgress| seen.lock().unwrap().push((progress.stage, progress.completed_stages, progress.total_stages));
+
+        let mut store = RdfStore::new();
+        ReasoningEngine::new().process_with_progress(&mut store, Some(ReasoningProfile::Rdfs), &observer).await.unwrap();
+        assert_eq!(seen.into_inner().unwrap(), vec![
+            (ReasoningStage::Rdfs, 1, 3),
+            (ReasoningStage::Rules, 2, 3),
+            (ReasoningStage::Validation, 3, 3),
+        ]);
+    }
 }
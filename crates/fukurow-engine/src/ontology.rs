@@ -0,0 +1,349 @@
+//! Ontology versions and hot swap
+//!
+//! セキュリティオントロジーの新しい版を再起動なしで入れ替える。
+//! 1. [`OntologyRegistry::register`] で版を登録する (staged)
+//! 2. [`OntologyRegistry::validate`] で現在のデータと合わせた整合性を OWL DL で検査する
+//! 3. [`OntologyRegistry::activate`] で有効化する
+//!
+//! オントロジーはその IRI を名前とするグラフに格納し、有効な版はブートストラップと同じ
+//! メタグラフに `owl:versionInfo` で記録する。有効化では旧版のグラフを置き換え、
+//! 旧版から導かれた推論 (すべての `GraphId::Inferred` グラフ) を撤回する。
+//! 推論は実行のたびに作り直すため、次の推論で新しい版から導き直される
+
+use chrono::{DateTime, Utc};
+use fukurow_core::model::Triple;
+use fukurow_core::term::RdfTerm;
+use fukurow_dl::OwlDlReasoner;
+use fukurow_store::bootstrap::BOOTSTRAP_META_GRAPH;
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const OWL_VERSION_INFO: &str = "http://www.w3.org/2002/07/owl#versionInfo";
+
+/// Lifecycle state of an ontology version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OntologyStatus {
+    /// Registered, not active
+    Staged,
+    /// Failed validation; cannot be activated
+    Rejected,
+    Active,
+    /// Replaced by another version (can be activated again to roll back)
+    Retired,
+}
+
+/// Consistency check of a version against the current data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OntologyValidation {
+    pub consistent: bool,
+    pub classes: usize,
+    pub properties: usize,
+    pub axioms: usize,
+    pub errors: Vec<String>,
+    /// Minimal set of axioms that is inconsistent (empty when consistent)
+    #[serde(default)]
+    pub justification: Vec<fukurow_dl::model::Axiom>,
+    pub validated_at: DateTime<Utc>,
+}
+
+/// One registered version of an ontology
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OntologyVersion {
+    pub iri: String,
+    pub version: String,
+    pub status: OntologyStatus,
+    pub triple_count: usize,
+    pub registered_at: DateTime<Utc>,
+    pub activated_at: Option<DateTime<Utc>>,
+    pub validation: Option<OntologyValidation>,
+    #[serde(skip)]
+    triples: Vec<Triple>,
+}
+
+impl OntologyVersion {
+    pub fn triples(&self) -> &[Triple] {
+        &self.triples
+    }
+
+    /// Named graph the ontology is stored in
+    pub fn graph_id(&self) -> GraphId {
+        GraphId::Named(self.iri.clone())
+    }
+}
+
+/// Result of activating a version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OntologySwap {
+    pub iri: String,
+    pub version: String,
+    /// Version that was active before, if any
+    pub previous_version: Option<String>,
+    pub triples_removed: usize,
+    pub triples_added: usize,
+    /// Materialized inferences retracted with the old version
+    pub inferences_retracted: usize,
+}
+
+/// Ontology registry errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OntologyError {
+    #[error("Ontology {iri} version {version} is already registered")]
+    AlreadyRegistered { iri: String, version: String },
+
+    #[error("Unknown ontology version: {iri} {version}")]
+    UnknownVersion { iri: String, version: String },
+
+    #[error("Ontology {iri} version {version} has no triples")]
+    Empty { iri: String, version: String },
+
+    #[error("Ontology {iri} version {version} is inconsistent with the current data: {}", .errors.join("; "))]
+    Inconsistent { iri: String, version: String, errors: Vec<String> },
+
+    #[error("Ontology {iri} version {version} is already active")]
+    AlreadyActive { iri: String, version: String },
+}
+
+/// Registered ontology versions by IRI and version
+#[derive(Debug, Clone, Default)]
+pub struct OntologyRegistry {
+    versions: BTreeMap<String, BTreeMap<String, OntologyVersion>>,
+}
+
+impl OntologyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage a new version
+    pub fn register(&mut self, iri: &str, version: &str, triples: Vec<Triple>) -> Result<&OntologyVersion, OntologyError> {
+        if triples.is_empty() {
+            return Err(OntologyError::Empty { iri: iri.to_string(), version: version.to_string() });
+        }
+        let versions = self.versions.entry(iri.to_string()).or_default();
+        if versions.contains_key(version) {
+            return Err(OntologyError::AlreadyRegistered { iri: iri.to_string(), version: version.to_string() });
+        }
+        let entry = OntologyVersion {
+            iri: iri.to_string(),
+            version: version.to_string(),
+            status: OntologyStatus::Staged,
+            triple_count: triples.len(),
+            registered_at: Utc::now(),
+            activated_at: None,
+            validation: None,
+            triples,
+        };
+        Ok(versions.entry(version.to_string()).or_insert(entry))
+    }
+
+    pub fn get(&self, iri: &str, version: &str) -> Option<&OntologyVersion> {
+        self.versions.get(iri)?.get(version)
+    }
+
+    /// Every version, by IRI then version
+    pub fn versions(&self) -> impl Iterator<Item = &OntologyVersion> {
+        self.versions.values().flat_map(|versions| versions.values())
+    }
+
+    pub fn active(&self, iri: &str) -> Option<&OntologyVersion> {
+        self.versions.get(iri)?.values().find(|v| v.status == OntologyStatus::Active)
+    }
+
+    /// Check a version together with the data in `store`
+    ///
+    /// 同じ IRI の現在の版と推論済みグラフを除いたストアに新しい版を加え、OWL DL で整合性を検査する。
+    /// 不整合な版は `Rejected` になり有効化できない
+    pub fn validate(&mut self, iri: &str, version: &str, store: &RdfStore) -> Result<OntologyValidation, OntologyError> {
+        let entry = self.entry_mut(iri, version)?;
+
+        let mut candidate = RdfStore::new();
+        for (graph_id, triples) in store.all_triples() {
+            if matches!(graph_id, GraphId::Inferred(_)) || *graph_id == entry.graph_id() {
+                continue;
+            }
+            for stored in triples {
+                candidate.insert(stored.triple.clone(), graph_id.clone(), stored.provenance.clone());
+            }
+        }
+        candidate.insert_batch(entry.triples.clone(), entry.graph_id(), import_provenance(entry));
+
+        let reasoner = OwlDlReasoner::new();
+        let validation = match reasoner.load_ontology(&candidate) {
+            Ok(ontology) => {
+                let (consistent, errors, justification) = match reasoner.consistency_report(&ontology) {
+                    Ok(report) if report.consistent => (true, Vec::new(), Vec::new()),
+                    Ok(report) => (false, vec!["ontology and data have no model".to_string()], report.justification),
+                    Err(e) => (false, vec![e.to_string()], Vec::new()),
+                };
+                OntologyValidation {
+                    consistent,
+                    classes: ontology.classes.len(),
+                    properties: ontology.properties.len(),
+                    axioms: ontology.axioms.len(),
+                    errors,
+                    justification,
+                    validated_at: Utc::now(),
+                }
+            }
+            Err(e) => OntologyValidation {
+                consistent: false,
+                classes: 0,
+                properties: 0,
+                axioms: 0,
+                errors: vec![e.to_string()],
+                justification: Vec::new(),
+                validated_at: Utc::now(),
+            },
+        };
+
+        if entry.status == OntologyStatus::Staged || entry.status == OntologyStatus::Rejected {
+            entry.status = if validation.consistent { OntologyStatus::Staged } else { OntologyStatus::Rejected };
+        }
+        entry.validation = Some(validation.clone());
+        Ok(validation)
+    }
+
+    /// Make a version the active one in `store`
+    ///
+    /// 呼び出し側が書き込みロックを保持している間に検査と置き換えを行うため、読み手が
+    /// 旧版と新版の混ざった状態を見ることはない。検査に失敗した場合ストアは変更しない
+    pub fn activate(&mut self, iri: &str, version: &str, store: &mut RdfStore) -> Result<OntologySwap, OntologyError> {
+        let previous_version = self.active(iri).map(|v| v.version.clone());
+        if previous_version.as_deref() == Some(version) {
+            return Err(OntologyError::AlreadyActive { iri: iri.to_string(), version: version.to_string() });
+        }
+
+        // 検査後にデータが変わっていることがあるため、有効化の直前にもう一度検査する
+        let validation = self.validate(iri, version, store)?;
+        if !validation.consistent {
+            return Err(OntologyError::Inconsistent { iri: iri.to_string(), version: version.to_string(), errors: validation.errors });
+        }
+
+        let entry = self.entry_mut(iri, version)?;
+        let graph_id = entry.graph_id();
+        let triples_removed = store.get_graph(&graph_id).len();
+        store.clear_graph(&graph_id);
+        let triples_added = store.insert_document(entry.triples.clone(), graph_id, import_provenance(entry)).len();
+
+        let meta = GraphId::Named(BOOTSTRAP_META_GRAPH.to_string());
+        let markers: Vec<Triple> = store.find_triples(Some(iri), Some(OWL_VERSION_INFO), None)
+            .into_iter()
+            .filter(|stored| stored.graph_id == meta)
+            .map(|stored| stored.triple.to_triple())
+            .collect();
+        for marker in &markers {
+            store.remove_triple(marker, &meta);
+        }
+        store.insert(version_marker(iri, version), meta, import_provenance(entry));
+
+        // 旧版から導かれた推論を撤回する (どの推論がどの公理に由来するかは記録していないため全件)
+        let inferred: Vec<GraphId> = store.graph_ids().into_iter()
+            .filter(|graph_id| matches!(graph_id, GraphId::Inferred(_)))
+            .cloned()
+            .collect();
+        let mut inferences_retracted = 0;
+        for graph_id in inferred {
+            inferences_retracted += store.get_graph(&graph_id).len();
+            store.clear_graph(&graph_id);
+        }
+
+        entry.status = OntologyStatus::Active;
+        entry.activated_at = Some(Utc::now());
+        if let Some(previous) = &previous_version {
+            if let Ok(previous) = self.entry_mut(iri, previous) {
+                previous.status = OntologyStatus::Retired;
+            }
+        }
+
+        Ok(OntologySwap {
+            iri: iri.to_string(),
+            version: version.to_string(),
+            previous_version,
+            triples_removed,
+            triples_added,
+            inferences_retracted,
+        })
+    }
+
+    /// Version recorded as active in `store` (survives restarts through snapshots and the WAL)
+    pub fn installed_version(store: &RdfStore, iri: &str) -> Option<String> {
+        let meta = GraphId::Named(BOOTSTRAP_META_GRAPH.to_string());
+        store.find_triples(Some(iri), Some(OWL_VERSION_INFO), None)
+            .into_iter()
+            .find(|stored| stored.graph_id == meta)
+            .map(|stored| stored.triple.object_term().value().to_string())
+    }
+
+    fn entry_mut(&mut self, iri: &str, version: &str) -> Result<&mut OntologyVersion, OntologyError> {
+        self.versions.get_mut(iri)
+            .and_then(|versions| versions.get_mut(version))
+            .ok_or_else(|| OntologyError::UnknownVersion { iri: iri.to_string(), version: version.to_string() })
+    }
+}
+
+fn import_provenance(entry: &OntologyVersion) -> Provenance {
+    Provenance::Imported {
+        source_uri: format!("ontology:{}@{}", entry.iri, entry.version),
+        imported_at: entry.registered_at.timestamp_millis() as u64,
+    }
+}
+
+fn version_marker(iri: &str, version: &str) -> Triple {
+    Triple::from_terms(&RdfTerm::iri(iri), &RdfTerm::iri(OWL_VERSION_INFO), &RdfTerm::literal(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONTOLOGY: &str = "http://example.org/security";
+    const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+    const RDFS_SUBCLASS_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subClassOf";
+
+    fn triple(subject: &str, predicate: &str, object: &str) -> Triple {
+        Triple { subject: subject.to_string(), predicate: predicate.to_string(), object: object.to_string() }
+    }
+
+    fn version(superclass: &str) -> Vec<Triple> {
+        vec![
+            triple("http://example.org/Ransomware", RDF_TYPE, "http://www.w3.org/2002/07/owl#Class"),
+            triple("http://example.org/Ransomware", RDFS_SUBCLASS_OF, superclass),
+        ]
+    }
+
+    #[test]
+    fn test_register_validate_and_swap() {
+        let mut store = RdfStore::new();
+        store.insert(triple("http://example.org/h1", RDF_TYPE, "http://example.org/Host"), GraphId::Default, Provenance::Sensor { source: "edr".to_string(), confidence: None });
+        store.insert(triple("http://example.org/x", RDF_TYPE, "http://example.org/Malware"), GraphId::Inferred("rdfs".to_string()), Provenance::Sensor { source: "test".to_string(), confidence: None });
+
+        let mut registry = OntologyRegistry::new();
+        registry.register(ONTOLOGY, "1.0", version("http://example.org/Malware")).unwrap();
+        registry.register(ONTOLOGY, "1.1", version("http://example.org/Threat")).unwrap();
+        assert_eq!(
+            registry.register(ONTOLOGY, "1.1", version("x")).unwrap_err(),
+            OntologyError::AlreadyRegistered { iri: ONTOLOGY.to_string(), version: "1.1".to_string() },
+        );
+
+        let first = registry.activate(ONTOLOGY, "1.0", &mut store).unwrap();
+        assert_eq!(first.previous_version, None);
+        assert_eq!(first.inferences_retracted, 1);
+        assert_eq!(OntologyRegistry::installed_version(&store, ONTOLOGY).as_deref(), Some("1.0"));
+
+        let swap = registry.activate(ONTOLOGY, "1.1", &mut store).unwrap();
+        assert_eq!(swap.previous_version.as_deref(), Some("1.0"));
+        assert_eq!((swap.triples_removed, swap.triples_added), (2, 2));
+        assert_eq!(OntologyRegistry::installed_version(&store, ONTOLOGY).as_deref(), Some("1.1"));
+        assert_eq!(store.find_triples(Some("http://example.org/Ransomware"), Some(RDFS_SUBCLASS_OF), None)[0].triple.object, "http://example.org/Threat");
+        assert_eq!(registry.get(ONTOLOGY, "1.0").unwrap().status, OntologyStatus::Retired);
+        assert_eq!(registry.active(ONTOLOGY).unwrap().version, "1.1");
+
+        // 旧版に戻せる
+        registry.activate(ONTOLOGY, "1.0", &mut store).unwrap();
+        assert!(matches!(registry.activate(ONTOLOGY, "1.0", &mut store), Err(OntologyError::AlreadyActive { .. })));
+        assert!(matches!(registry.activate(ONTOLOGY, "9.9", &mut store), Err(OntologyError::UnknownVersion { .. })));
+    }
+}
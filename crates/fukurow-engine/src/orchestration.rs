@@ -1,12 +1,20 @@
 //! Reasoning engine orchestration
 
 use async_trait::async_trait;
-use fukurow_core::model::{Triple, SecurityAction};
+use fukurow_core::model::{Triple, SecurityAction, CorrelatedAction};
+use fukurow_core::term::RdfTerm;
+use fukurow_store::confidence::{propagate_confidence, ConfidenceCombination};
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::retention::evidence_key;
 use fukurow_store::store::RdfStore;
 use fukurow_rules::{Rule, RuleResult, RuleRegistry};
 use fukurow_rdfs::{RdfsReasoner, RdfsConfig};
+use crate::owl::{OWL_DL_INFERRED_GRAPH, OWL_LITE_INFERRED_GRAPH};
+use crate::workers::WorkerPool;
+use fukurow_observability::tracing::{attributes, spans};
+use tracing::Instrument;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Engine result containing all outputs
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,12 +23,26 @@ pub struct EngineResult {
     pub inferred_triples: Vec<Triple>,
     /// Security actions to execute
     pub actions: Vec<SecurityAction>,
+    /// Correlation IDs of the events behind each action (index-aligned with `actions`)
+    #[serde(default)]
+    pub action_correlations: Vec<Vec<String>>,
     /// Validation violations found
     pub violations: Vec<fukurow_rules::ValidationViolation>,
     /// Processing statistics
     pub stats: ProcessingStats,
 }
 
+impl EngineResult {
+    /// Actions paired with the correlation IDs of their originating events
+    pub fn correlated_actions(&self) -> Vec<CorrelatedAction> {
+        self.actions.iter().enumerate()
+            .map(|(index, action)| {
+                action.clone().correlated(self.action_correlations.get(index).cloned().unwrap_or_default())
+            })
+            .collect()
+    }
+}
+
 /// Processing statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessingStats {
@@ -36,14 +58,144 @@ pub struct ReasoningEngine {
     processing_options: ProcessingOptions,
 }
 
+/// Name of the `GraphId::Inferred` graph holding materialized RDFS inferences
+pub const RDFS_INFERRED_GRAPH: &str = "rdfs";
+
+/// Name of the `GraphId::Inferred` graph holding materialized rule inferences
+pub const RULES_INFERRED_GRAPH: &str = "rules";
+
+/// Progress reported after each stage of a run
+#[derive(Debug, Clone, Serialize)]
+pub struct StageProgress {
+    /// Stage that just finished
+    pub stage: ReasoningStage,
+    /// Stages finished so far (including skipped ones)
+    pub completed_stages: usize,
+    pub total_stages: usize,
+    pub inferred_triples: usize,
+    /// Actions proposed by the stages finished so far
+    pub actions: Vec<SecurityAction>,
+}
+
+/// Callback receiving [`StageProgress`]
+pub type StageObserver<'a> = &'a (dyn Fn(&StageProgress) + Send + Sync);
+
+/// A step of [`ReasoningEngine::process`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningStage {
+    /// RDFS closure (subclass/subproperty hierarchy, domain/range typing)
+    Rdfs,
+    /// Registered inference rules
+    Rules,
+    /// Registered validation rules
+    Validation,
+    /// OWL Lite entailments (class hierarchy, inherited types, property characteristics)
+    OwlLite,
+    /// OWL DL classification of named classes with the tableau reasoner
+    OwlDl,
+}
+
+/// How much inference a reasoning run performs
+///
+/// 推論器の組み合わせを選ぶ。どのプロファイルでもルールと検証は実行する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReasoningProfile {
+    /// No reasoner; rules see asserted triples only
+    None,
+    /// RDFS closure
+    Rdfs,
+    /// RDFS and OWL Lite
+    OwlLite,
+    /// RDFS, OWL Lite and OWL DL classification
+    OwlDl,
+}
+
+impl ReasoningProfile {
+    pub const ALL: [ReasoningProfile; 4] = [Self::None, Self::Rdfs, Self::OwlLite, Self::OwlDl];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Rdfs => "rdfs",
+            Self::OwlLite => "owl-lite",
+            Self::OwlDl => "owl-dl",
+        }
+    }
+
+    /// Stages run under this profile, in order
+    pub fn stages(&self) -> Vec<ReasoningStage> {
+        let reasoners: &[ReasoningStage] = match self {
+            Self::None => &[],
+            Self::Rdfs => &[ReasoningStage::Rdfs],
+            Self::OwlLite => &[ReasoningStage::Rdfs, ReasoningStage::OwlLite],
+            Self::OwlDl => &[ReasoningStage::Rdfs, ReasoningStage::OwlLite, ReasoningStage::OwlDl],
+        };
+        reasoners.iter().copied().chain([ReasoningStage::Rules, ReasoningStage::Validation]).collect()
+    }
+
+    /// Timeout used unless overridden with [`ProcessingOptions::with_profile_timeout`]
+    pub fn default_timeout_ms(&self) -> u64 {
+        match self {
+            Self::None => 2_000,
+            Self::Rdfs => 5_000,
+            Self::OwlLite => 15_000,
+            Self::OwlDl => 60_000,
+        }
+    }
+}
+
+impl std::fmt::Display for ReasoningProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ReasoningProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter()
+            .find(|profile| profile.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown reasoning profile: {} (expected none, rdfs, owl-lite or owl-dl)", s))
+    }
+}
+
+/// Store passed to the stages; only a writable store receives RDFS inferences
+enum StoreAccess<'a> {
+    Read(&'a RdfStore),
+    Write(&'a mut RdfStore),
+}
+
+impl StoreAccess<'_> {
+    fn store(&self) -> &RdfStore {
+        match self {
+            StoreAccess::Read(store) => store,
+            StoreAccess::Write(store) => store,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProcessingOptions {
+    /// Stages in execution order; each is still gated by its `enable_*` flag
+    pub stages: Vec<ReasoningStage>,
     pub max_iterations: usize,
     pub enable_validation: bool,
     pub enable_inference: bool,
     pub enable_rdfs_inference: bool,
+    /// Checked after each stage
     pub timeout_ms: Option<u64>,
     pub rdfs_config: RdfsConfig,
+    /// Profile the stages were selected from; recorded as the `reasoning_level` of inferences
+    pub profile: Option<ReasoningProfile>,
+    /// Per-profile timeouts overriding [`ReasoningProfile::default_timeout_ms`]
+    pub profile_timeouts: HashMap<ReasoningProfile, u64>,
+    /// How evidence confidences are combined into the confidence of RDFS and rule inferences
+    pub confidence_combination: ConfidenceCombination,
+    /// Workers evaluating independent rules in parallel (`None` = one per available core, 1 = sequential)
+    pub max_workers: Option<usize>,
 }
 
 impl ReasoningEngine {
@@ -67,45 +219,94 @@ impl ReasoningEngine {
     }
 
     /// Process a knowledge graph through all reasoning steps
+    ///
+    /// RDFS inferences are returned but not written; see [`Self::process_and_materialize`]
     pub async fn process(&self, store: &RdfStore) -> Result<EngineResult, EngineError> {
+        self.run_stages(StoreAccess::Read(store), &self.processing_options, None).await
+    }
+
+    /// Process a knowledge graph, inserting RDFS and rule inferences before the later stages run
+    ///
+    /// 依存し合うルールは新しいトリプルが出なくなるまで (最大 `max_iterations` 回) 繰り返す。
+    /// 推論結果は `GraphId::Inferred("rdfs")` と `GraphId::Inferred("rules")` に `Provenance::Inferred` 付きで格納する。
+    /// 実行のたびにこれらのグラフを作り直すため、元の事実が消えた推論は残らない
+    pub async fn process_and_materialize(&self, store: &mut RdfStore) -> Result<EngineResult, EngineError> {
+        self.run_stages(StoreAccess::Write(store), &self.processing_options, None).await
+    }
+
+    /// [`Self::process_and_materialize`] with the stages and timeout of `profile`
+    ///
+    /// OWL 推論のグラフはプロファイルに含まれない場合も消すため、ストアには直前の実行で
+    /// 選んだプロファイルの推論だけが残る
+    pub async fn process_with_profile(&self, store: &mut RdfStore, profile: ReasoningProfile) -> Result<EngineResult, EngineError> {
+        self.materialize_observed(store, Some(profile), None).await
+    }
+
+    /// Materializing run (with the stages of `profile`, if any) that reports progress after each stage
+    ///
+    /// 長時間の推論をジョブとして実行するときに、途中経過と提案済みのアクションを見せるために使う
+    pub async fn process_with_progress(&self, store: &mut RdfStore, profile: Option<ReasoningProfile>, observer: StageObserver<'_>) -> Result<EngineResult, EngineError> {
+        self.materialize_observed(store, profile, Some(observer)).await
+    }
+
+    async fn materialize_observed(&self, store: &mut RdfStore, profile: Option<ReasoningProfile>, observer: Option<StageObserver<'_>>) -> Result<EngineResult, EngineError> {
+        let Some(profile) = profile else {
+            return self.run_stages(StoreAccess::Write(store), &self.processing_options, observer).await;
+        };
+        let options = self.processing_options.clone().with_profile(profile);
+        for (stage, graph) in [(ReasoningStage::OwlLite, OWL_LITE_INFERRED_GRAPH), (ReasoningStage::OwlDl, OWL_DL_INFERRED_GRAPH)] {
+            if !options.stages.contains(&stage) {
+                store.clear_graph(&GraphId::Inferred(graph.to_string()));
+            }
+        }
+        if profile == ReasoningProfile::None {
+            store.clear_graph(&GraphId::Inferred(RDFS_INFERRED_GRAPH.to_string()));
+        }
+        self.run_stages(StoreAccess::Write(store), &options, observer).await
+    }
+
+    async fn run_stages(&self, mut access: StoreAccess<'_>, options: &ProcessingOptions, observer: Option<StageObserver<'_>>) -> Result<EngineResult, EngineError> {
         let start_time = std::time::Instant::now();
 
         let mut result = EngineResult {
             inferred_triples: Vec::new(),
             actions: Vec::new(),
+            action_correlations: Vec::new(),
             violations: Vec::new(),
             stats: ProcessingStats {
                 rules_applied: 0,
-                triples_processed: store.statistics().total_triples,
+                triples_processed: access.store().statistics().total_triples,
                 execution_time_ms: 0,
                 memory_used_kb: None,
             },
         };
 
-        // RDFS inference (first step)
-        if self.processing_options.enable_rdfs_inference {
-            let mut rdfs_reasoner = RdfsReasoner::new();
-            let rdfs_triples = rdfs_reasoner.compute_closure(store)?;
-            result.inferred_triples.extend(rdfs_triples);
-            result.stats.rules_applied += 1; // Count RDFS as one "rule"
-        }
-
-        // Apply all rules
-        if self.processing_options.enable_inference {
-            let rule_results = self.rule_registry.apply_all_rules(store).await?;
-
-            for rule_result in rule_results {
-                result.inferred_triples.extend(rule_result.triples_to_add);
-                result.actions.extend(rule_result.actions);
-                result.violations.extend(rule_result.violations);
-                result.stats.rules_applied += 1;
+        for (index, stage) in options.stages.iter().enumerate() {
+            let span = tracing::info_span!(
+                spans::REASONING_STAGE,
+                reasoning.stage = ?stage,
+                triple.count = tracing::field::Empty,
+                action.count = tracing::field::Empty
+            );
+            self.run_stage(*stage, &mut access, options, &mut result).instrument(span.clone()).await?;
+            span.record(attributes::TRIPLE_COUNT, result.inferred_triples.len());
+            span.record(attributes::ACTION_COUNT, result.actions.len());
+
+            if let Some(observer) = observer {
+                observer(&StageProgress {
+                    stage: *stage,
+                    completed_stages: index + 1,
+                    total_stages: options.stages.len(),
+                    inferred_triples: result.inferred_triples.len(),
+                    actions: result.actions.clone(),
+                });
             }
-        }
 
-        // Run validation if enabled
-        if self.processing_options.enable_validation {
-            let violations = self.rule_registry.validate_all(store).await?;
-            result.violations.extend(violations);
+            if let Some(timeout_ms) = options.timeout_ms {
+                if start_time.elapsed().as_millis() as u64 > timeout_ms {
+                    return Err(EngineError::TimeoutError(timeout_ms));
+                }
+            }
         }
 
         result.stats.execution_time_ms = start_time.elapsed().as_millis() as u64;
@@ -113,6 +314,58 @@ impl ReasoningEngine {
         Ok(result)
     }
 
+    /// Run one stage, adding its inferences, actions and violations to `result`
+    async fn run_stage(&self, stage: ReasoningStage, access: &mut StoreAccess<'_>, options: &ProcessingOptions, result: &mut EngineResult) -> Result<(), EngineError> {
+        match stage {
+            ReasoningStage::Rdfs if options.enable_rdfs_inference => {
+                let rdfs_triples = match &mut *access {
+                    StoreAccess::Read(store) => rdfs_closure(store)?,
+                    StoreAccess::Write(store) => materialize_rdfs(store, options)?,
+                };
+                result.inferred_triples.extend(rdfs_triples);
+                result.stats.rules_applied += 1; // Count RDFS as one "rule"
+            }
+            ReasoningStage::Rules if options.enable_inference => {
+                let workers = WorkerPool::new(options.max_workers);
+                let (rule_results, inferred) = match &mut *access {
+                    StoreAccess::Read(store) => {
+                        let rule_results = self.rule_registry.apply_all_rules_with(store, &workers).await?;
+                        let inferred = rule_results.iter().flat_map(|r| r.triples_to_add.iter().cloned()).collect();
+                        (rule_results, inferred)
+                    }
+                    StoreAccess::Write(store) => materialize_rules(&self.rule_registry, store, options, &workers).await?,
+                };
+                result.inferred_triples.extend(inferred);
+
+                for mut rule_result in rule_results {
+                    if rule_result.correlation_ids().is_empty() {
+                        let ids = derived_correlation_ids(access.store(), &rule_result);
+                        rule_result.set_correlation_ids(ids);
+                    }
+                    let ids = rule_result.correlation_ids();
+                    result.action_correlations.extend(rule_result.actions.iter().map(|_| ids.clone()));
+                    result.actions.extend(rule_result.actions);
+                    result.violations.extend(rule_result.violations);
+                    result.stats.rules_applied += 1;
+                }
+            }
+            ReasoningStage::Validation if options.enable_validation => {
+                let violations = self.rule_registry.validate_all(access.store()).await?;
+                result.violations.extend(violations);
+            }
+            ReasoningStage::OwlLite | ReasoningStage::OwlDl => {
+                let owl_triples = match &mut *access {
+                    StoreAccess::Read(store) => owl_closure(stage, store)?,
+                    StoreAccess::Write(store) => materialize_owl(stage, store, options)?,
+                };
+                result.inferred_triples.extend(owl_triples);
+                result.stats.rules_applied += 1;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     /// Get rule registry for inspection
     pub fn rule_registry(&self) -> &RuleRegistry {
         &self.rule_registry
@@ -139,6 +392,9 @@ pub enum EngineError {
     #[error("Maximum iterations ({0}) exceeded")]
     IterationLimitError(usize),
 
+    #[error("OWL reasoning failed: {0}")]
+    OwlError(String),
+
     #[error("Internal engine error: {0}")]
     InternalError(String),
 }
@@ -146,14 +402,181 @@ pub enum EngineError {
 impl Default for ProcessingOptions {
     fn default() -> Self {
         Self {
+            stages: vec![ReasoningStage::Rdfs, ReasoningStage::Rules, ReasoningStage::Validation],
             max_iterations: 10,
             enable_validation: true,
             enable_inference: true,
             enable_rdfs_inference: true, // RDFS推論をデフォルトで有効化
             timeout_ms: Some(5000), // 5 seconds
             rdfs_config: RdfsConfig::default(),
+            profile: None,
+            profile_timeouts: HashMap::new(),
+            confidence_combination: ConfidenceCombination::default(),
+            max_workers: None,
+        }
+    }
+}
+
+impl ProcessingOptions {
+    /// Run only `stages`, in the given order
+    pub fn with_stages(mut self, stages: Vec<ReasoningStage>) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Run the stages of `profile` with its timeout
+    pub fn with_profile(mut self, profile: ReasoningProfile) -> Self {
+        self.stages = profile.stages();
+        self.enable_rdfs_inference = profile != ReasoningProfile::None;
+        self.timeout_ms = Some(self.timeout_for(profile));
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Override the timeout of `profile`
+    pub fn with_profile_timeout(mut self, profile: ReasoningProfile, timeout_ms: u64) -> Self {
+        self.profile_timeouts.insert(profile, timeout_ms);
+        if self.profile == Some(profile) {
+            self.timeout_ms = Some(timeout_ms);
+        }
+        self
+    }
+
+    pub fn with_confidence_combination(mut self, combination: ConfidenceCombination) -> Self {
+        self.confidence_combination = combination;
+        self
+    }
+
+    /// Limit the reasoning worker pool to `max_workers` workers
+    pub fn with_max_workers(mut self, max_workers: usize) -> Self {
+        self.max_workers = Some(max_workers);
+        self
+    }
+
+    pub fn timeout_for(&self, profile: ReasoningProfile) -> u64 {
+        self.profile_timeouts.get(&profile).copied().unwrap_or_else(|| profile.default_timeout_ms())
+    }
+
+    /// `reasoning_level` recorded on inferences: the profile name, or `default` without a profile
+    fn reasoning_level<'a>(&self, default: &'a str) -> &'a str {
+        self.profile.map_or(default, |profile| profile.as_str())
+    }
+}
+
+/// RDFS closure of `store`, without triples the store already holds
+///
+/// リテラルを主語とする推論 (rdfs:range による値の型付け) は RDF として表現できないので除く
+fn rdfs_closure(store: &RdfStore) -> Result<Vec<Triple>, EngineError> {
+    rdfs_closure_with(&mut RdfsReasoner::new(), store, None)
+}
+
+/// [`rdfs_closure`] keeping the justifications of the inferences in `rdfs_reasoner`
+///
+/// `target` のグラフにだけあるトリプル (前回書き込んだ推論) は、ストアが保持しているものとみなさない
+fn rdfs_closure_with(rdfs_reasoner: &mut RdfsReasoner, store: &RdfStore, target: Option<&GraphId>) -> Result<Vec<Triple>, EngineError> {
+    let closure = rdfs_reasoner.compute_closure(store)?;
+    Ok(closure.into_iter()
+        .filter(|triple| !RdfTerm::parse(&triple.subject).is_literal())
+        .filter(|triple| store.find_triples(Some(&triple.subject), Some(&triple.predicate), Some(&triple.object)).iter()
+            .all(|stored| Some(&stored.graph_id) == target))
+        .collect())
+}
+
+/// Correlation IDs of the event nodes a rule result refers to
+///
+/// ルールが相関 ID を設定しなかった場合、追加トリプルと違反トリプルの主語・目的語のうち
+/// 相関 ID を持つイベントノードを根拠とみなす
+fn derived_correlation_ids(store: &RdfStore, rule_result: &RuleResult) -> Vec<String> {
+    let triples = rule_result.triples_to_add.iter()
+        .chain(rule_result.violations.iter().filter_map(|violation| violation.triple.as_ref()));
+    let mut ids = Vec::new();
+    for triple in triples {
+        for node in [&triple.subject, &triple.object] {
+            for stored in store.find_triples(Some(node), Some(crate::dedup::CORRELATION_ID_PREDICATE), None) {
+                let id = stored.triple.object.to_string();
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
         }
     }
+    ids
+}
+
+/// Recompute the RDFS closure into the `rdfs` inferred graph
+///
+/// 各推論には根拠となるトリプルと、その確信度から計算した確信度を付ける。
+/// 閉包は前回の推論を除いたストアから計算し、グラフとの差分 (消えた推論と新しい推論、
+/// 根拠や確信度が変わった推論) だけを書き込むため、変化のない推論で WAL や監査ログを埋めない
+fn materialize_rdfs(store: &mut RdfStore, options: &ProcessingOptions) -> Result<Vec<Triple>, EngineError> {
+    let graph_id = GraphId::Inferred(RDFS_INFERRED_GRAPH.to_string());
+
+    let mut rdfs_reasoner = RdfsReasoner::new().excluding_graph(graph_id.clone());
+    let inferred = rdfs_closure_with(&mut rdfs_reasoner, store, Some(&graph_id))?;
+    let justifications = rdfs_reasoner.get_evidence();
+    let confidences = propagate_confidence(store, justifications, options.confidence_combination);
+    let wanted: HashMap<&Triple, Provenance> = inferred.iter()
+        .map(|triple| (triple, Provenance::Inferred {
+            rule: "rdfs-closure".to_string(),
+            reasoning_level: options.reasoning_level("rdfs").to_string(),
+            evidence: justifications.get(triple).into_iter().flatten().map(evidence_key).collect(),
+            confidence: confidences.get(triple).copied(),
+        }))
+        .collect();
+
+    let mut unchanged = HashSet::new();
+    store.remove_where(&graph_id, |_, stored| {
+        let triple = stored.triple.to_triple();
+        let keep = wanted.get(&triple) == Some(&stored.provenance);
+        !(keep && unchanged.insert(triple))
+    }, "rdfs-closure");
+    for triple in &inferred {
+        if !unchanged.contains(triple) {
+            store.insert(triple.clone(), graph_id.clone(), wanted[triple].clone());
+        }
+    }
+    Ok(inferred)
+}
+
+fn owl_closure(stage: ReasoningStage, store: &RdfStore) -> Result<Vec<Triple>, EngineError> {
+    match stage {
+        ReasoningStage::OwlDl => crate::owl::owl_dl_closure(store),
+        _ => crate::owl::owl_lite_closure(store),
+    }
+}
+
+/// Recompute the inferences of an OWL stage into its inferred graph
+fn materialize_owl(stage: ReasoningStage, store: &mut RdfStore, options: &ProcessingOptions) -> Result<Vec<Triple>, EngineError> {
+    let (graph, rule, level) = match stage {
+        ReasoningStage::OwlDl => (OWL_DL_INFERRED_GRAPH, "owl-dl-classification", "owl-dl"),
+        _ => (OWL_LITE_INFERRED_GRAPH, "owl-lite-closure", "owl-lite"),
+    };
+    let graph_id = GraphId::Inferred(graph.to_string());
+    store.clear_graph(&graph_id);
+
+    let inferred = owl_closure(stage, store)?;
+    store.insert_batch(inferred.clone(), graph_id, Provenance::Inferred {
+        rule: rule.to_string(),
+        reasoning_level: options.reasoning_level(level).to_string(),
+        evidence: Vec::new(),
+        confidence: None,
+    });
+    Ok(inferred)
+}
+
+/// Recompute rule inferences into the `rules` inferred graph, iterating dependent rules to fixpoint
+///
+/// RDFS と同様に実行のたびにグラフを作り直す
+async fn materialize_rules(registry: &RuleRegistry, store: &mut RdfStore, options: &ProcessingOptions, workers: &WorkerPool) -> Result<(Vec<RuleResult>, Vec<Triple>), EngineError> {
+    let graph_id = GraphId::Inferred(RULES_INFERRED_GRAPH.to_string());
+    store.clear_graph(&graph_id);
+
+    let reasoning_level = options.reasoning_level("rules");
+    match registry.apply_to_fixpoint_with(store, &graph_id, options.max_iterations, reasoning_level, options.confidence_combination, workers).await {
+        Ok(run) => Ok((run.results, run.inferred)),
+        Err(fukurow_rules::RuleError::IterationLimit { iterations, .. }) => Err(EngineError::IterationLimitError(iterations)),
+        Err(e) => Err(e.into()),
+    }
 }
 
 impl Default for ReasoningEngine {
@@ -0,0 +1,105 @@
+//! OWL stages of the engine
+//!
+//! プロファイル `owl-lite` / `owl-dl` で実行する OWL 推論。
+//! - OWL Lite: クラス階層、上位クラスへの型付け、推移・対称・逆プロパティによる表明
+//! - OWL DL: テーブローによる名前付きクラスの分類と、その階層にもとづく型付け
+//! - 整合性検査: 不整合なら原因となる極小の公理集合 (justification) を添える
+
+use crate::orchestration::{EngineError, ReasoningProfile};
+use fukurow_core::model::Triple;
+use fukurow_dl::{ClassExpression, ConsistencyReport, OwlDlReasoner};
+use fukurow_lite::model::{Axiom, Class, Property};
+use fukurow_lite::OwlLiteReasoner;
+use fukurow_store::store::RdfStore;
+use std::collections::BTreeSet;
+
+/// Name of the `GraphId::Inferred` graph holding materialized OWL Lite inferences
+pub const OWL_LITE_INFERRED_GRAPH: &str = fukurow_lite::reasoner::OWL_LITE_INFERRED_GRAPH;
+
+/// Name of the `GraphId::Inferred` graph holding materialized OWL DL inferences
+pub const OWL_DL_INFERRED_GRAPH: &str = "owl-dl";
+
+/// Consistency report over OWL DL axioms (OWL Lite axioms are wrapped in `Axiom::OwlLite`)
+pub type OwlConsistencyReport = ConsistencyReport<fukurow_dl::model::Axiom>;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDFS_SUBCLASS_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subClassOf";
+
+/// OWL Lite entailments of `store`, without triples the store already holds
+pub(crate) fn owl_lite_closure(store: &RdfStore) -> Result<Vec<Triple>, EngineError> {
+    let mut reasoner = OwlLiteReasoner::new();
+    let ontology = reasoner.load_ontology(store).map_err(|e| EngineError::OwlError(e.to_string()))?;
+    let axioms = reasoner.get_inferred_axioms(&ontology).map_err(|e| EngineError::OwlError(e.to_string()))?;
+
+    let triples = axioms.into_iter().filter_map(|axiom| match axiom {
+        Axiom::SubClassOf(Class::Named(sub), Class::Named(sup)) => Some(triple(sub.0, RDFS_SUBCLASS_OF, sup.0)),
+        Axiom::ClassAssertion(Class::Named(class), individual) => Some(triple(individual.0.0, RDF_TYPE, class.0)),
+        Axiom::ObjectPropertyAssertion(Property::Object(property) | Property::Data(property), subject, object) => {
+            Some(triple(subject.0.0, &property.0, object.0.0))
+        }
+        _ => None,
+    });
+    Ok(new_triples(store, triples))
+}
+
+/// OWL DL classification of `store`, without triples the store already holds
+///
+/// 名前付きクラスどうしの包含関係と、表明された型から導かれる上位クラスの型を返す
+pub(crate) fn owl_dl_closure(store: &RdfStore) -> Result<Vec<Triple>, EngineError> {
+    let mut reasoner = OwlDlReasoner::new();
+    let ontology = reasoner.load_ontology(store).map_err(|e| EngineError::OwlError(e.to_string()))?;
+    let hierarchy = reasoner.classify_ontology(&ontology).map_err(|e| EngineError::OwlError(e.to_string()))?;
+
+    let mut triples = Vec::new();
+    for (sub, superclasses) in &hierarchy {
+        let ClassExpression::Named(sub) = sub else { continue };
+        for sup in superclasses {
+            if let ClassExpression::Named(sup) = sup {
+                triples.push(triple(sub.0.clone(), RDFS_SUBCLASS_OF, sup.0.clone()));
+            }
+        }
+        for stored in store.find_triples(None, Some(RDF_TYPE), Some(&sub.0)) {
+            for sup in superclasses {
+                if let ClassExpression::Named(sup) = sup {
+                    triples.push(triple(stored.triple.subject.to_string(), RDF_TYPE, sup.0.clone()));
+                }
+            }
+        }
+    }
+    Ok(new_triples(store, triples))
+}
+
+/// Check the consistency of `store`, explaining an inconsistency with a minimal set of axioms
+///
+/// `owl-dl` はテーブローで、それ以外のプロファイルは OWL Lite (関数的プロパティの検査を含む) で判定する
+pub fn owl_consistency_report(store: &RdfStore, profile: ReasoningProfile) -> Result<OwlConsistencyReport, EngineError> {
+    if profile == ReasoningProfile::OwlDl {
+        let reasoner = OwlDlReasoner::new();
+        let ontology = reasoner.load_ontology(store).map_err(|e| EngineError::OwlError(e.to_string()))?;
+        return reasoner.consistency_report(&ontology).map_err(|e| EngineError::OwlError(e.to_string()));
+    }
+
+    let reasoner = OwlLiteReasoner::new();
+    let ontology = reasoner.load_ontology(store).map_err(|e| EngineError::OwlError(e.to_string()))?;
+    let report = reasoner.consistency_report(&ontology).map_err(|e| EngineError::OwlError(e.to_string()))?;
+    Ok(ConsistencyReport {
+        consistent: report.consistent,
+        justification: report.justification.into_iter().map(fukurow_dl::model::Axiom::OwlLite).collect(),
+        axioms: report.axioms,
+        checks: report.checks,
+    })
+}
+
+fn triple(subject: String, predicate: &str, object: String) -> Triple {
+    Triple { subject, predicate: predicate.to_string(), object }
+}
+
+/// Distinct triples not yet in `store`, in a stable order
+fn new_triples(store: &RdfStore, triples: impl IntoIterator<Item = Triple>) -> Vec<Triple> {
+    let unique: BTreeSet<(String, String, String)> = triples.into_iter()
+        .filter(|t| t.subject != t.object || t.predicate != RDFS_SUBCLASS_OF)
+        .filter(|t| store.find_triples(Some(&t.subject), Some(&t.predicate), Some(&t.object)).is_empty())
+        .map(|t| (t.subject, t.predicate, t.object))
+        .collect();
+    unique.into_iter().map(|(subject, predicate, object)| Triple { subject, predicate, object }).collect()
+}
@@ -1,14 +1,14 @@
 //! Processing pipelines for complex reasoning workflows
 
-use super::orchestration::{ReasoningEngine, EngineResult, ProcessingOptions, EngineError};
+use super::orchestration::{ReasoningEngine, EngineResult, ProcessingOptions, ReasoningStage};
 use fukurow_store::store::RdfStore;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
-/// Processing pipeline stage
+/// Processing pipeline stage (declarative; see [`crate::stage::PipelineStage`] for ingestion stages)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PipelineStage {
+pub struct PipelineStageConfig {
     pub name: String,
     pub description: String,
     pub engine: PipelineEngine,
@@ -37,7 +37,7 @@ pub enum PipelineCondition {
 
 /// Processing pipeline
 pub struct ProcessingPipeline {
-    stages: HashMap<String, PipelineStage>,
+    stages: HashMap<String, PipelineStageConfig>,
     current_stage: Option<String>,
     execution_history: Vec<PipelineExecution>,
 }
@@ -62,7 +62,7 @@ impl ProcessingPipeline {
     }
 
     /// Add a processing stage
-    pub fn add_stage(&mut self, stage: PipelineStage) {
+    pub fn add_stage(&mut self, stage: PipelineStageConfig) {
         self.stages.insert(stage.name.clone(), stage);
     }
 
@@ -118,7 +118,7 @@ impl ProcessingPipeline {
         })
     }
 
-    async fn execute_stage(&self, stage: &PipelineStage, store: &RdfStore) -> PipelineExecution {
+    async fn execute_stage(&self, stage: &PipelineStageConfig, store: &RdfStore) -> PipelineExecution {
         let started_at = Utc::now();
         let mut execution = PipelineExecution {
             stage_name: stage.name.clone(),
@@ -132,16 +132,13 @@ impl ProcessingPipeline {
         // Create engine based on configuration
         let engine_result: Result<ReasoningEngine, PipelineError> = match &stage.engine {
             PipelineEngine::Rdfs => {
-                // TODO: Create RDFS engine
-                Err(PipelineError::EngineNotImplemented("RDFS".to_string()))
+                Ok(ReasoningEngine::with_options(ProcessingOptions::default().with_stages(vec![ReasoningStage::Rdfs])))
             }
             PipelineEngine::OwlLite => {
-                // TODO: Create OWL Lite engine
-                Err(PipelineError::EngineNotImplemented("OWL Lite".to_string()))
+                Ok(ReasoningEngine::with_options(ProcessingOptions::default().with_stages(vec![ReasoningStage::OwlLite])))
             }
             PipelineEngine::OwlDl => {
-                // TODO: Create OWL DL engine
-                Err(PipelineError::EngineNotImplemented("OWL DL".to_string()))
+                Ok(ReasoningEngine::with_options(ProcessingOptions::default().with_stages(vec![ReasoningStage::OwlDl])))
             }
             PipelineEngine::Custom(name) => {
                 Err(PipelineError::EngineNotImplemented(format!("Custom: {}", name)))
@@ -206,7 +203,7 @@ impl ProcessingPipeline {
     }
 
     /// Get available stages
-    pub fn stages(&self) -> &HashMap<String, PipelineStage> {
+    pub fn stages(&self) -> &HashMap<String, PipelineStageConfig> {
         &self.stages
     }
 }
@@ -254,7 +251,7 @@ impl PipelineBuilder {
 
     /// Add an RDFS reasoning stage
     pub fn add_rdfs_stage(mut self, name: &str, description: &str) -> Self {
-        let stage = PipelineStage {
+        let stage = PipelineStageConfig {
             name: name.to_string(),
             description: description.to_string(),
             engine: PipelineEngine::Rdfs,
@@ -268,7 +265,7 @@ impl PipelineBuilder {
 
     /// Add an OWL Lite reasoning stage
     pub fn add_owl_lite_stage(mut self, name: &str, description: &str) -> Self {
-        let stage = PipelineStage {
+        let stage = PipelineStageConfig {
             name: name.to_string(),
             description: description.to_string(),
             engine: PipelineEngine::OwlLite,
@@ -282,7 +279,7 @@ impl PipelineBuilder {
 
     /// Add an OWL DL reasoning stage
     pub fn add_owl_dl_stage(mut self, name: &str, description: &str) -> Self {
-        let stage = PipelineStage {
+        let stage = PipelineStageConfig {
             name: name.to_string(),
             description: description.to_string(),
             engine: PipelineEngine::OwlDl,
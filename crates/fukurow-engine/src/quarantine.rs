@@ -0,0 +1,127 @@
+//! # Event Quarantine
+//!
+//! 検証に通らなかったイベントは推論に使わず、元の JSON と理由を隔離グラフに残す。
+//! イベント用の述語は使わないため、ルールや RDFS/OWL 推論の対象にはならない
+
+use fukurow_core::model::CyberEvent;
+use fukurow_core::validation::ValidationIssue;
+use fukurow_store::provenance::GraphId;
+use fukurow_store::store::RdfStore;
+use fukurow_store::{InternedTriple, Triple};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Named graph quarantined events are stored in
+pub const QUARANTINE_GRAPH: &str = "quarantine";
+/// Prefix of quarantine record subjects
+pub const QUARANTINE_PREFIX: &str = "urn:fukurow:quarantine:";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const QUARANTINED_EVENT: &str = "http://example.org/QuarantinedEvent";
+const PAYLOAD: &str = "http://example.org/quarantinePayload";
+const SOURCE: &str = "http://example.org/quarantineSource";
+const ISSUE: &str = "http://example.org/quarantineIssue";
+const QUARANTINED_AT: &str = "http://example.org/quarantinedAt";
+
+/// Event held in the quarantine graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedEvent {
+    pub id: String,
+    pub event: CyberEvent,
+    /// Sensor that reported the event
+    pub source: String,
+    /// Validation issues, as `field: message`
+    pub issues: Vec<String>,
+    /// Unix seconds
+    pub quarantined_at: i64,
+}
+
+impl QuarantinedEvent {
+    pub fn new(event: CyberEvent, source: &str, issues: &[ValidationIssue]) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            event,
+            source: source.to_string(),
+            issues: issues.iter().map(|issue| issue.to_string()).collect(),
+            quarantined_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn graph_id() -> GraphId {
+        GraphId::Named(QUARANTINE_GRAPH.to_string())
+    }
+
+    /// Triples recording this event in the quarantine graph
+    pub fn to_triples(&self) -> Vec<Triple> {
+        let subject = format!("{}{}", QUARANTINE_PREFIX, self.id);
+        let triple = |predicate: &str, object: String| Triple {
+            subject: subject.clone(),
+            predicate: predicate.to_string(),
+            object,
+        };
+        let payload = serde_json::to_string(&self.event).unwrap_or_default();
+
+        let mut triples = vec![
+            triple(RDF_TYPE, QUARANTINED_EVENT.to_string()),
+            triple(PAYLOAD, payload),
+            triple(SOURCE, self.source.clone()),
+            triple(QUARANTINED_AT, self.quarantined_at.to_string()),
+        ];
+        triples.extend(self.issues.iter().map(|issue| triple(ISSUE, issue.clone())));
+        triples
+    }
+
+    /// Every quarantined event in `store`, oldest first
+    ///
+    /// ペイロードを読めないレコードは読み飛ばす
+    pub fn all(store: &RdfStore) -> Vec<QuarantinedEvent> {
+        let mut records: BTreeMap<&str, Vec<&InternedTriple>> = BTreeMap::new();
+        for stored in store.get_graph(&Self::graph_id()) {
+            records.entry(stored.triple.subject.as_str()).or_default().push(&stored.triple);
+        }
+
+        let mut events: Vec<QuarantinedEvent> = records.into_iter()
+            .filter_map(|(subject, triples)| {
+                let value = |predicate: &str| triples.iter().find(|t| t.predicate == predicate).map(|t| t.object.as_str());
+                Some(QuarantinedEvent {
+                    id: subject.strip_prefix(QUARANTINE_PREFIX)?.to_string(),
+                    event: serde_json::from_str(value(PAYLOAD)?).ok()?,
+                    source: value(SOURCE).unwrap_or_default().to_string(),
+                    issues: triples.iter().filter(|t| t.predicate == ISSUE).map(|t| t.object.to_string()).collect(),
+                    quarantined_at: value(QUARANTINED_AT).and_then(|v| v.parse().ok()).unwrap_or(0),
+                })
+            })
+            .collect();
+        events.sort_by_key(|event| event.quarantined_at);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::validation::EventValidator;
+    use fukurow_store::provenance::Provenance;
+
+    #[test]
+    fn test_quarantined_event_round_trips_through_store() {
+        let event = CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "not-an-ip".to_string(),
+            success: false,
+            timestamp: 1640995200,
+        };
+        let issues = EventValidator::default().check(&event, 1640995260);
+        let record = QuarantinedEvent::new(event, "vpn", &issues);
+
+        let mut store = RdfStore::new();
+        store.insert_batch(record.to_triples(), QuarantinedEvent::graph_id(), Provenance::Sensor { source: "vpn".to_string(), confidence: None });
+        let stored = QuarantinedEvent::all(&store);
+        assert_eq!(stored.len(), 1);
+        assert_eq!((&stored[0].id, &stored[0].source, stored[0].quarantined_at), (&record.id, &record.source, record.quarantined_at));
+        assert!(matches!(&stored[0].event, CyberEvent::UserLogin { user, .. } if user == "alice"));
+        assert_eq!(stored[0].issues, vec!["source_ip: not an IP address: \"not-an-ip\"".to_string()]);
+        // 隔離レコードはイベントのグラフには入らない
+        assert!(store.get_graph(&GraphId::Named(crate::replay::EVENTS_GRAPH.to_string())).is_empty());
+    }
+}
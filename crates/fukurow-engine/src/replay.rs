@@ -0,0 +1,313 @@
+//! Event replay from the audit trail
+//!
+//! 監査ログに残ったイベントの挿入を時刻順に読み直し、現在のルールで推論をやり直して
+//! 当時の実行で提案されたアクションとの差分を報告する
+//! (「先週のイベントに今日のルールを当てたら何を判断したか」をフォレンジック調査で再現する)。
+//! 再実行は作業用のストアで行い、推論結果は `GraphId::Inferred("replay:<id>")` にまとめるので
+//! 稼働中のストアの推論グラフには触れない
+
+use crate::orchestration::{EngineError, ReasoningEngine, RDFS_INFERRED_GRAPH, RULES_INFERRED_GRAPH};
+use fukurow_core::model::{SecurityAction, Triple};
+use fukurow_store::audit::{AuditQuery, AuditSinkError};
+use fukurow_store::provenance::{AuditOperation, GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+
+/// Named graph events are ingested into
+pub const EVENTS_GRAPH: &str = "events";
+
+/// Prefix of the `GraphId::Inferred` graph a replay writes its inferences to
+pub const REPLAY_GRAPH_PREFIX: &str = "replay:";
+
+/// What to replay and what to compare against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayRequest {
+    /// Identifier of the replay (names the inferred graph)
+    pub replay_id: String,
+    /// Inclusive lower bound on the ingestion time (Unix timestamp in milliseconds)
+    pub from: Option<u64>,
+    /// Inclusive upper bound on the ingestion time (Unix timestamp in milliseconds)
+    pub to: Option<u64>,
+    /// Actions proposed by the original run over the same events
+    pub original_actions: Vec<SecurityAction>,
+}
+
+impl ReplayRequest {
+    pub fn new(original_actions: Vec<SecurityAction>) -> Self {
+        Self {
+            replay_id: uuid::Uuid::new_v4().to_string(),
+            from: None,
+            to: None,
+            original_actions,
+        }
+    }
+
+    pub fn with_id(mut self, replay_id: impl Into<String>) -> Self {
+        self.replay_id = replay_id.into();
+        self
+    }
+
+    /// Replay only events ingested within `[from, to]`
+    pub fn with_window(mut self, from: Option<u64>, to: Option<u64>) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+
+    /// Inferred graph the replay writes to
+    pub fn graph_id(&self) -> GraphId {
+        GraphId::Inferred(format!("{}{}", REPLAY_GRAPH_PREFIX, self.replay_id))
+    }
+}
+
+/// Event triple read back from the audit trail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayedTriple {
+    /// When the triple was originally ingested (Unix timestamp in milliseconds)
+    pub timestamp: u64,
+    pub triple: Triple,
+    pub provenance: Provenance,
+}
+
+/// Actions of the original run compared with the replay
+///
+/// 同じアクションが複数回提案された場合は回数も比較する
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionDiff {
+    /// Proposed by both runs
+    pub unchanged: Vec<SecurityAction>,
+    /// Proposed by the original run only (today's rules no longer fire)
+    pub only_original: Vec<SecurityAction>,
+    /// Proposed by the replay only (today's rules fire where the original did not)
+    pub only_replay: Vec<SecurityAction>,
+}
+
+impl ActionDiff {
+    pub fn compute(original: &[SecurityAction], replayed: &[SecurityAction]) -> Self {
+        // SecurityAction は PartialEq を持たないため JSON 表現で比較する
+        let key = |action: &SecurityAction| serde_json::to_string(action).unwrap_or_default();
+        let mut remaining: Vec<(String, &SecurityAction)> = original.iter().map(|action| (key(action), action)).collect();
+
+        let mut diff = Self::default();
+        for action in replayed {
+            let action_key = key(action);
+            match remaining.iter().position(|(k, _)| *k == action_key) {
+                Some(position) => {
+                    remaining.remove(position);
+                    diff.unchanged.push(action.clone());
+                }
+                None => diff.only_replay.push(action.clone()),
+            }
+        }
+        diff.only_original = remaining.into_iter().map(|(_, action)| action.clone()).collect();
+        diff
+    }
+
+    /// Both runs proposed the same actions
+    pub fn is_empty(&self) -> bool {
+        self.only_original.is_empty() && self.only_replay.is_empty()
+    }
+}
+
+/// Summary of a replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub replay_id: String,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    /// Distinct event nodes replayed
+    pub events_replayed: usize,
+    /// Event triples replayed
+    pub triples_replayed: usize,
+    /// Triples inferred by the replay
+    pub inferred_triples: usize,
+    /// Validation violations found by the replay
+    pub violations: Vec<fukurow_rules::ValidationViolation>,
+    pub actions: ActionDiff,
+}
+
+/// Replay result with the scratch store it ran against
+#[derive(Debug)]
+pub struct ReplayRun {
+    pub report: ReplayReport,
+    /// Background knowledge, replayed events and the `replay:<id>` inferred graph
+    pub store: RdfStore,
+}
+
+impl ReplayRun {
+    /// Copy the replay's inferred graph into `target` for side-by-side queries
+    pub fn materialize_into(&self, target: &mut RdfStore) -> usize {
+        let graph_id = GraphId::Inferred(format!("{}{}", REPLAY_GRAPH_PREFIX, self.report.replay_id));
+        target.clear_graph(&graph_id);
+        let inferred = self.store.get_graph(&graph_id);
+        for stored in &inferred {
+            target.insert_at(stored.triple.clone(), graph_id.clone(), stored.provenance.clone(), stored.asserted_at);
+        }
+        inferred.len()
+    }
+}
+
+/// Replay errors
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Audit history unavailable: {0}")]
+    Audit(#[from] AuditSinkError),
+
+    #[error("Replay reasoning failed: {0}")]
+    Engine(#[from] EngineError),
+}
+
+/// Event triples ingested within `[from, to]`, oldest first
+///
+/// 監査シンクがあればそこから読む。イベントグラフへの挿入のみを対象とし、
+/// 保持期間による削除は再生に影響させない
+pub fn events_from_audit(store: &RdfStore, from: Option<u64>, to: Option<u64>) -> Result<Vec<ReplayedTriple>, ReplayError> {
+    let query = AuditQuery {
+        from,
+        to,
+        operation: Some("insert".to_string()),
+        graph: Some(GraphId::Named(EVENTS_GRAPH.to_string()).to_string()),
+        ..AuditQuery::default()
+    };
+
+    // 新しい順に返るので反転し、同時刻のものは記録順を保ったまま並べる
+    let mut events: Vec<ReplayedTriple> = store.query_audit(&query)?
+        .into_iter()
+        .rev()
+        .filter_map(|entry| match entry.operation {
+            AuditOperation::Insert { triple, provenance, .. } => Some(ReplayedTriple { timestamp: entry.timestamp, triple, provenance }),
+            _ => None,
+        })
+        .collect();
+    events.sort_by_key(|event| event.timestamp);
+    Ok(events)
+}
+
+/// Re-run `engine` over the events of `source` selected by `request`
+///
+/// 作業用ストアには `source` の推論グラフとイベントグラフ以外 (オントロジーや資産情報など) を
+/// 背景知識としてコピーし、そこへイベントを元の時刻順に挿入してから推論する
+pub async fn replay_events(engine: &ReasoningEngine, source: &RdfStore, request: &ReplayRequest) -> Result<ReplayRun, ReplayError> {
+    let events = events_from_audit(source, request.from, request.to)?;
+    let events_graph = GraphId::Named(EVENTS_GRAPH.to_string());
+
+    let mut store = RdfStore::with_audit_limit(0);
+    for (graph_id, triples) in source.all_triples() {
+        if *graph_id == events_graph || matches!(graph_id, GraphId::Inferred(_)) {
+            continue;
+        }
+        for stored in triples {
+            store.insert_at(stored.triple.clone(), graph_id.clone(), stored.provenance.clone(), stored.asserted_at);
+        }
+    }
+
+    let mut subjects = std::collections::HashSet::new();
+    for event in &events {
+        subjects.insert(event.triple.subject.clone());
+        store.insert_at(event.triple.clone(), events_graph.clone(), event.provenance.clone(), event.timestamp);
+    }
+
+    let result = engine.process_and_materialize(&mut store).await?;
+
+    // RDFS・ルールの推論グラフを replay:<id> にまとめる
+    let replay_graph = request.graph_id();
+    for name in [RDFS_INFERRED_GRAPH, RULES_INFERRED_GRAPH] {
+        let graph_id = GraphId::Inferred(name.to_string());
+        let inferred: Vec<_> = store.get_graph(&graph_id).into_iter().cloned().collect();
+        store.clear_graph(&graph_id);
+        for stored in inferred {
+            store.insert_at(stored.triple, replay_graph.clone(), stored.provenance, stored.asserted_at);
+        }
+    }
+
+    let report = ReplayReport {
+        replay_id: request.replay_id.clone(),
+        from: request.from,
+        to: request.to,
+        events_replayed: subjects.len(),
+        triples_replayed: events.len(),
+        inferred_triples: store.get_graph(&replay_graph).len(),
+        violations: result.violations,
+        actions: ActionDiff::compute(&request.original_actions, &result.actions),
+    };
+    Ok(ReplayRun { report, store })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use fukurow_core::model::CyberEvent;
+    use fukurow_rules::{Rule, RuleError, RuleResult};
+    use std::collections::HashMap;
+
+    /// 4444 番ポートへの接続ごとに宛先ホストの隔離を提案する
+    struct SuspiciousPort;
+
+    #[async_trait]
+    impl Rule for SuspiciousPort {
+        fn name(&self) -> &'static str {
+            "suspicious-port"
+        }
+
+        fn description(&self) -> &'static str {
+            "Connections to port 4444"
+        }
+
+        async fn apply(&self, store: &RdfStore) -> Result<RuleResult, RuleError> {
+            let actions = store.find_triples(None, Some("http://example.org/port"), Some("4444")).into_iter()
+                .flat_map(|stored| store.find_triples(Some(&stored.triple.subject), Some("http://example.org/destIP"), None))
+                .map(|dest| SecurityAction::IsolateHost { host_ip: dest.triple.object.to_string(), reason: "port 4444".to_string() })
+                .collect();
+            Ok(RuleResult {
+                triples_to_add: Vec::new(),
+                triples_to_remove: Vec::new(),
+                actions,
+                violations: Vec::new(),
+                metadata: HashMap::new(),
+            })
+        }
+    }
+
+    fn connection(dest_ip: &str, port: u16, timestamp: i64) -> CyberEvent {
+        CyberEvent::NetworkConnection {
+            source_ip: "192.168.1.10".to_string(),
+            dest_ip: dest_ip.to_string(),
+            port,
+            protocol: "tcp".to_string(),
+            timestamp,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_diffs_actions_against_original_run() {
+        let mut source = RdfStore::new();
+        let sensor = Provenance::Sensor { source: "netflow".to_string(), confidence: None };
+        for event in [connection("10.0.0.5", 4444, 1), connection("10.0.0.6", 443, 2)] {
+            for triple in crate::ReasonerEngine::cyber_event_to_triples(&event) {
+                source.insert(triple, GraphId::Named(EVENTS_GRAPH.to_string()), sensor.clone());
+            }
+        }
+        // イベントを削除しても監査ログから再生できる
+        source.clear_graph(&GraphId::Named(EVENTS_GRAPH.to_string()));
+
+        let mut engine = ReasoningEngine::new();
+        engine.register_rule(Box::new(SuspiciousPort));
+
+        // 当時は別のホストを隔離していた
+        let original = vec![SecurityAction::IsolateHost { host_ip: "10.0.0.6".to_string(), reason: "port 4444".to_string() }];
+        let request = ReplayRequest::new(original).with_id("incident-42");
+        let run = replay_events(&engine, &source, &request).await.unwrap();
+
+        assert_eq!(run.report.events_replayed, 2);
+        assert!(run.report.actions.unchanged.is_empty());
+        assert!(matches!(run.report.actions.only_replay.as_slice(), [SecurityAction::IsolateHost { host_ip, .. }] if host_ip == "10.0.0.5"));
+        assert!(matches!(run.report.actions.only_original.as_slice(), [SecurityAction::IsolateHost { host_ip, .. }] if host_ip == "10.0.0.6"));
+        assert!(!run.report.actions.is_empty());
+
+        // 稼働中のストアには replay グラフ以外を書き込まない
+        assert!(run.store.get_graph(&GraphId::Inferred(RULES_INFERRED_GRAPH.to_string())).is_empty());
+        run.materialize_into(&mut source);
+        assert!(source.graph_ids().iter().all(|graph_id| **graph_id != GraphId::Named(EVENTS_GRAPH.to_string())));
+    }
+}
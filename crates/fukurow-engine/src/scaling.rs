@@ -375,6 +375,22 @@ impl LoadBalancer for HashBasedBalancer {
                         "login".hash(&mut hasher);
                         timestamp.hash(&mut hasher);
                     }
+                    fukurow_core::model::CyberEvent::DnsQuery { timestamp, .. } => {
+                        "dns".hash(&mut hasher);
+                        timestamp.hash(&mut hasher);
+                    }
+                    fukurow_core::model::CyberEvent::HttpRequest { timestamp, .. } => {
+                        "http".hash(&mut hasher);
+                        timestamp.hash(&mut hasher);
+                    }
+                    fukurow_core::model::CyberEvent::RegistryModification { timestamp, .. } => {
+                        "registry".hash(&mut hasher);
+                        timestamp.hash(&mut hasher);
+                    }
+                    fukurow_core::model::CyberEvent::EmailReceived { timestamp, .. } => {
+                        "email".hash(&mut hasher);
+                        timestamp.hash(&mut hasher);
+                    }
                 }
                 hasher.finish()
             }
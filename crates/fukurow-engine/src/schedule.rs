@@ -0,0 +1,490 @@
+//! Scheduled reasoning
+//!
+//! cron 形式 (`分 時 日 月 曜日`、先頭に秒を付けた 6 フィールドも可) の設定に従って推論パスを
+//! 定期実行し、結果を `ReasoningResult` のストリーミングイベントとして送る。時刻は UTC。
+//! 前回のパスがまだ実行中の tick は待たずに読み飛ばす (スキップとして数える)。
+//! incremental モードは前回の推論以降にイベントを受理したときだけ実行する
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use fukurow_store::TenantId;
+use fukurow_streaming::StreamingEvent;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::engine::ReasonerEngine;
+use crate::orchestration::ReasoningProfile;
+
+/// Years searched for the next matching time (expressions such as `0 0 30 2 *` never match)
+const SEARCH_YEARS: i32 = 5;
+
+/// Errors in schedule configuration
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("Invalid cron expression '{expression}': {reason}")]
+    InvalidCron { expression: String, reason: String },
+}
+
+/// Set of allowed values of one cron field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field(u64);
+
+impl Field {
+    fn contains(self, value: u32) -> bool {
+        self.0 & (1 << value) != 0
+    }
+
+    /// Parse `*`, `5`, `1-5`, `*/15`, `10-40/10` and comma-separated lists of them
+    fn parse(text: &str, min: u32, max: u32) -> Result<Self, String> {
+        let mut bits = 0u64;
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step.parse().map_err(|_| format!("invalid step '{}'", step))?;
+                    if step == 0 {
+                        return Err("step must be positive".to_string());
+                    }
+                    (range, Some(step))
+                }
+                None => (part, None),
+            };
+            let value = |text: &str| -> Result<u32, String> {
+                let value: u32 = text.parse().map_err(|_| format!("invalid value '{}'", text))?;
+                if value < min || value > max {
+                    return Err(format!("{} is outside {}-{}", value, min, max));
+                }
+                Ok(value)
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((start, end)) => (value(start)?, value(end)?),
+                    // `5/10` は 5 から最大値まで
+                    None if step.is_some() => (value(range)?, max),
+                    None => (value(range)?, value(range)?),
+                },
+            };
+            if start > end {
+                return Err(format!("empty range '{}'", range));
+            }
+            for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(Self(bits))
+    }
+}
+
+/// Parsed cron expression
+///
+/// 日と曜日の両方を指定した場合は cron と同じくどちらかに一致すれば実行する
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    expression: String,
+    seconds: Field,
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    /// 0 = Sunday (7 is accepted as Sunday too)
+    weekdays: Field,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// First matching time strictly after `after` (`None` when nothing matches within a few years)
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.with_nanosecond(0)? + Duration::seconds(1);
+        let limit = after.year() + SEARCH_YEARS;
+        while time.year() <= limit {
+            if !self.months.contains(time.month()) {
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.day_matches(time) {
+                time = Utc.from_utc_datetime(&time.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?);
+            } else if !self.hours.contains(time.hour()) {
+                time = time.with_minute(0)?.with_second(0)? + Duration::hours(1);
+            } else if !self.minutes.contains(time.minute()) {
+                time = time.with_second(0)? + Duration::minutes(1);
+            } else if !self.seconds.contains(time.second()) {
+                time += Duration::seconds(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, time: DateTime<Utc>) -> bool {
+        let day = self.days.contains(time.day());
+        let weekday = self.weekdays.contains(time.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = ScheduleError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| ScheduleError::InvalidCron { expression: expression.to_string(), reason };
+        let expanded = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let (seconds, rest) = match fields.len() {
+            5 => ("0", &fields[..]),
+            6 => (fields[0], &fields[1..]),
+            n => return Err(invalid(format!("expected 5 or 6 fields, found {}", n))),
+        };
+        let field = |text: &str, min: u32, max: u32, name: &str| {
+            Field::parse(text, min, max).map_err(|reason| invalid(format!("{} field: {}", name, reason)))
+        };
+        let mut weekdays = field(rest[4], 0, 7, "day-of-week")?;
+        if weekdays.contains(7) {
+            weekdays = Field((weekdays.0 | 1) & !(1 << 7));
+        }
+        Ok(Self {
+            expression: expression.trim().to_string(),
+            seconds: field(seconds, 0, 59, "second")?,
+            minutes: field(rest[0], 0, 59, "minute")?,
+            hours: field(rest[1], 0, 23, "hour")?,
+            days: field(rest[2], 1, 31, "day-of-month")?,
+            months: field(rest[3], 1, 12, "month")?,
+            weekdays,
+            days_restricted: rest[2] != "*",
+            weekdays_restricted: rest[4] != "*",
+        })
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = ScheduleError;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        expression.parse()
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.expression
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// What a scheduled pass reasons over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PassMode {
+    /// Reason over the whole store on every tick
+    #[default]
+    Full,
+    /// Reason only when events were accepted since the previous pass
+    Incremental,
+}
+
+/// A reasoning pass run on a cron schedule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReasoningSchedule {
+    pub name: String,
+    pub cron: CronSchedule,
+    /// Reasoning profile of the pass (the engine's stages when unset)
+    #[serde(default)]
+    pub profile: Option<ReasoningProfile>,
+    #[serde(default)]
+    pub mode: PassMode,
+    /// Tenant whose engine runs the pass
+    #[serde(default)]
+    pub tenant: TenantId,
+}
+
+impl ReasoningSchedule {
+    pub fn new(name: impl Into<String>, cron: CronSchedule) -> Self {
+        Self { name: name.into(), cron, profile: None, mode: PassMode::default(), tenant: TenantId::default() }
+    }
+
+    pub fn with_profile(mut self, profile: ReasoningProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    pub fn with_mode(mut self, mode: PassMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: TenantId) -> Self {
+        self.tenant = tenant;
+        self
+    }
+}
+
+/// Outcome of a scheduled tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassOutcome {
+    Completed { actions: usize, execution_time_ms: u64 },
+    /// The previous pass was still executing
+    SkippedRunning,
+    /// Incremental pass with no new events
+    SkippedIdle,
+    Failed(String),
+}
+
+/// Counters of a scheduler's passes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchedulerStats {
+    pub completed: u64,
+    pub skipped_running: u64,
+    pub skipped_idle: u64,
+    pub failed: u64,
+    pub last_completed: Option<DateTime<Utc>>,
+}
+
+/// Receives the `ReasoningResult` event of every completed pass
+pub type ResultSink = Arc<dyn Fn(StreamingEvent) + Send + Sync>;
+
+/// Runs scheduled reasoning passes on one engine, one pass at a time
+pub struct ReasoningScheduler {
+    engine: Arc<ReasonerEngine>,
+    sink: ResultSink,
+    running: AtomicBool,
+    stats: Mutex<SchedulerStats>,
+}
+
+/// Clears the running flag, also when the pass is aborted
+struct RunningGuard<'a>(&'a AtomicBool);
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl ReasoningScheduler {
+    pub fn new(engine: Arc<ReasonerEngine>, sink: impl Fn(StreamingEvent) + Send + Sync + 'static) -> Self {
+        Self { engine, sink: Arc::new(sink), running: AtomicBool::new(false), stats: Mutex::new(SchedulerStats::default()) }
+    }
+
+    /// Whether a pass is executing
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Run `schedule`'s pass now, unless a pass is still executing
+    pub async fn trigger(&self, schedule: &ReasoningSchedule) -> PassOutcome {
+        if self.running.swap(true, Ordering::AcqRel) {
+            warn!("Skipping scheduled reasoning {}: the previous pass is still running", schedule.name);
+            self.stats.lock().unwrap().skipped_running += 1;
+            return PassOutcome::SkippedRunning;
+        }
+        let _running = RunningGuard(&self.running);
+
+        let event_count = self.engine.pending_event_count();
+        if schedule.mode == PassMode::Incremental && event_count == 0 {
+            self.stats.lock().unwrap().skipped_idle += 1;
+            return PassOutcome::SkippedIdle;
+        }
+
+        let started = Instant::now();
+        let result = match schedule.profile {
+            Some(profile) => self.engine.reason_with_profile(profile).await,
+            None => self.engine.reason_correlated().await,
+        };
+        let execution_time_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(correlated) => {
+                let mut correlation_ids: Vec<String> = correlated.iter().flat_map(|c| c.correlation_ids.iter().cloned()).collect();
+                correlation_ids.sort();
+                correlation_ids.dedup();
+                let actions: Vec<_> = correlated.into_iter().map(|c| c.action).collect();
+                let outcome = PassOutcome::Completed { actions: actions.len(), execution_time_ms };
+                info!("Scheduled reasoning {} proposed {} actions in {}ms", schedule.name, actions.len(), execution_time_ms);
+                (self.sink)(StreamingEvent::ReasoningResult {
+                    actions,
+                    execution_time_ms,
+                    event_count,
+                    timestamp: Utc::now(),
+                    correlation_ids,
+                });
+                let mut stats = self.stats.lock().unwrap();
+                stats.completed += 1;
+                stats.last_completed = Some(Utc::now());
+                outcome
+            }
+            Err(e) => {
+                warn!("Scheduled reasoning {} failed: {}", schedule.name, e);
+                self.stats.lock().unwrap().failed += 1;
+                PassOutcome::Failed(e.to_string())
+            }
+        }
+    }
+
+    /// Start a timer task per schedule
+    ///
+    /// パスは tick ごとに別タスクで実行するため、長いパスの間も tick は進み、重なった分はスキップになる。
+    /// 返されたハンドルを破棄するとタイマーは止まる (実行中のパスは最後まで走る)
+    pub fn spawn(self: Arc<Self>, schedules: Vec<ReasoningSchedule>) -> SchedulerTask {
+        let tasks = schedules.into_iter()
+            .map(|schedule| {
+                let scheduler = Arc::clone(&self);
+                tokio::spawn(async move { scheduler.run_schedule(schedule).await })
+            })
+            .collect();
+        SchedulerTask { scheduler: self, tasks }
+    }
+
+    async fn run_schedule(self: Arc<Self>, schedule: ReasoningSchedule) {
+        let mut last = Utc::now();
+        loop {
+            // 時計が少し遅れて起きても同じ時刻で二度実行しない
+            let now = Utc::now().max(last);
+            let Some(next) = schedule.cron.next_after(now) else {
+                warn!("Reasoning schedule {} ({}) never fires; stopping it", schedule.name, schedule.cron);
+                break;
+            };
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+            last = next;
+            let scheduler = Arc::clone(&self);
+            let schedule = schedule.clone();
+            tokio::spawn(async move { scheduler.trigger(&schedule).await });
+        }
+    }
+}
+
+/// Handle of running schedules; dropping it stops them
+pub struct SchedulerTask {
+    scheduler: Arc<ReasoningScheduler>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SchedulerTask {
+    pub fn scheduler(&self) -> &Arc<ReasoningScheduler> {
+        &self.scheduler
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        self.scheduler.stats()
+    }
+}
+
+impl Drop for SchedulerTask {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::CyberEvent;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(expression: &str, after: &str) -> Option<DateTime<Utc>> {
+        expression.parse::<CronSchedule>().unwrap().next_after(at(after))
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        assert_eq!(next("*/15 * * * *", "2024-03-01T10:07:30Z"), Some(at("2024-03-01T10:15:00Z")));
+        assert_eq!(next("*/15 * * * *", "2024-03-01T10:15:00Z"), Some(at("2024-03-01T10:30:00Z")));
+        // 2024-03-02 は土曜日
+        assert_eq!(next("0 9 * * 1-5", "2024-03-02T08:00:00Z"), Some(at("2024-03-04T09:00:00Z")));
+        assert_eq!(next("30 * * * * *", "2024-12-31T23:59:45Z"), Some(at("2025-01-01T00:00:30Z")));
+        assert_eq!(next("@daily", "2024-02-28T12:00:00Z"), Some(at("2024-02-29T00:00:00Z")));
+        // 日と曜日の両方を指定すると OR (13 日か金曜日)
+        assert_eq!(next("0 0 13 * 5", "2024-03-02T00:00:00Z"), Some(at("2024-03-08T00:00:00Z")));
+        assert_eq!(next("0 0 * * 7", "2024-03-02T00:00:00Z"), Some(at("2024-03-03T00:00:00Z")));
+        assert_eq!(next("0 0 30 2 *", "2024-01-01T00:00:00Z"), None);
+
+        for invalid in ["61 * * * *", "* * *", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(matches!(invalid.parse::<CronSchedule>(), Err(ScheduleError::InvalidCron { .. })), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_schedule_deserializes_from_config() {
+        let schedule: ReasoningSchedule = serde_json::from_str(
+            r#"{"name": "nightly", "cron": "0 2 * * *", "profile": "owl-lite", "mode": "incremental"}"#
+        ).unwrap();
+        assert_eq!(schedule.cron.expression(), "0 2 * * *");
+        assert_eq!(schedule.profile, Some(ReasoningProfile::OwlLite));
+        assert_eq!(schedule.mode, PassMode::Incremental);
+        assert!(schedule.tenant.is_default());
+        assert!(serde_json::from_str::<ReasoningSchedule>(r#"{"name": "bad", "cron": "* *"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trigger_skips_idle_and_overlapping_passes() {
+        let engine = Arc::new(ReasonerEngine::new());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let scheduler = Arc::new(ReasoningScheduler::new(Arc::clone(&engine), move |event| sink.lock().unwrap().push(event)));
+        let incremental = ReasoningSchedule::new("incremental", "* * * * *".parse().unwrap()).with_mode(PassMode::Incremental);
+
+        assert_eq!(scheduler.trigger(&incremental).await, PassOutcome::SkippedIdle);
+        engine.add_event(CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            success: true,
+            timestamp: 1700000000,
+        }).await.unwrap();
+        assert!(matches!(scheduler.trigger(&incremental).await, PassOutcome::Completed { .. }));
+        assert!(matches!(events.lock().unwrap().as_slice(), [StreamingEvent::ReasoningResult { event_count: 1, .. }]));
+
+        // 書き込みロックを握って実行中のパスを止めておく
+        let store = engine.get_graph_store().await;
+        let writer = store.write().await;
+        let full = ReasoningSchedule::new("full", "* * * * *".parse().unwrap());
+        let pass = tokio::spawn({
+            let scheduler = Arc::clone(&scheduler);
+            let full = full.clone();
+            async move { scheduler.trigger(&full).await }
+        });
+        while !scheduler.is_running() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(scheduler.trigger(&full).await, PassOutcome::SkippedRunning);
+        drop(writer);
+        assert!(matches!(pass.await.unwrap(), PassOutcome::Completed { .. }));
+        assert!(!scheduler.is_running());
+
+        let stats = scheduler.stats();
+        assert_eq!((stats.completed, stats.skipped_running, stats.skipped_idle, stats.failed), (2, 1, 1, 0));
+        assert_eq!(events.lock().unwrap().len(), 2);
+    }
+}
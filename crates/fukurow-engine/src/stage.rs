@@ -0,0 +1,282 @@
+//! Ingestion pipeline stages
+//!
+//! イベントをストアに取り込む処理をステージの列として組み立てる。組み込みの
+//! [`EventTriplesStage`] (イベント → トリプル) と [`AssertStage`] (ストアへの書き込み) の前後に
+//! 独自のステージ (GeoIP タグ付けなど) を差し込める。ステージは書き込みロックを保持したまま
+//! 登録順に実行されるため、推論とルールは常にすべてのステージを通った結果を見る
+
+use async_trait::async_trait;
+use fukurow_core::model::CyberEvent;
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::{store::RdfStore, Triple};
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::engine::{event_field, ReasonerEngine};
+
+/// Name of [`EventTriplesStage`]
+pub const EVENT_TRIPLES_STAGE: &str = "event-triples";
+
+/// Name of [`AssertStage`]
+pub const ASSERT_STAGE: &str = "assert";
+
+/// One incoming item of an [`EventBatch`]
+#[derive(Debug, Clone)]
+pub struct IngestItem {
+    /// Event being ingested (`None` for already converted triples)
+    pub event: Option<CyberEvent>,
+    /// Sensor that reported the item
+    pub source: String,
+    pub correlation_id: Option<String>,
+    /// Triples written for the item by [`AssertStage`]
+    pub triples: Vec<Triple>,
+}
+
+impl IngestItem {
+    pub fn event(event: CyberEvent, source: &str) -> Self {
+        Self { event: Some(event), source: source.to_string(), correlation_id: None, triples: Vec::new() }
+    }
+
+    pub fn triples(triples: Vec<Triple>, source: &str) -> Self {
+        Self { event: None, source: source.to_string(), correlation_id: None, triples }
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: &str) -> Self {
+        self.correlation_id = Some(correlation_id.to_string());
+        self
+    }
+}
+
+/// Items ingested under one store write lock
+///
+/// ステージは項目の内容を書き換えてよいが、追加・削除はしない (受付通知は位置で対応付ける)。
+/// 取り込みたくない項目は `triples` を空にする
+#[derive(Debug, Clone)]
+pub struct EventBatch {
+    /// Graph receiving the items' triples
+    pub graph: GraphId,
+    pub items: Vec<IngestItem>,
+}
+
+impl EventBatch {
+    pub fn new(graph: GraphId, items: Vec<IngestItem>) -> Self {
+        Self { graph, items }
+    }
+}
+
+/// Pipeline stage errors
+#[derive(Debug, Error)]
+pub enum StageError {
+    #[error("Stage {stage} failed: {message}")]
+    Failed { stage: String, message: String },
+
+    #[error("Unknown pipeline stage: {0}")]
+    UnknownStage(String),
+}
+
+impl StageError {
+    pub fn failed(stage: &str, message: impl ToString) -> Self {
+        Self::Failed { stage: stage.to_string(), message: message.to_string() }
+    }
+}
+
+/// A step between receiving events and reasoning over them
+#[async_trait]
+pub trait PipelineStage: Send + Sync {
+    /// Unique name, used to position other stages relative to this one
+    fn name(&self) -> &str;
+
+    /// Process `batch`; an error stops the pipeline and fails the whole batch
+    ///
+    /// 前のステージが書き込んだ内容は `store` から読める。失敗しても書き込み済みの
+    /// トリプルは取り消さない
+    async fn process(&self, store: &mut RdfStore, batch: &mut EventBatch) -> Result<(), StageError>;
+}
+
+/// Built-in stage converting each item's event into triples
+///
+/// 相関 ID があれば `correlationId` のトリプルも加える
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventTriplesStage;
+
+#[async_trait]
+impl PipelineStage for EventTriplesStage {
+    fn name(&self) -> &str {
+        EVENT_TRIPLES_STAGE
+    }
+
+    async fn process(&self, _store: &mut RdfStore, batch: &mut EventBatch) -> Result<(), StageError> {
+        for item in &mut batch.items {
+            let Some(event) = &item.event else { continue };
+            let mut triples = ReasonerEngine::cyber_event_to_triples(event);
+            if let (Some(subject), Some(correlation_id)) = (triples.first().map(|triple| triple.subject.clone()), &item.correlation_id) {
+                triples.push(event_field(&subject, "correlationId", correlation_id));
+            }
+            item.triples.extend(triples);
+        }
+        Ok(())
+    }
+}
+
+/// Built-in stage writing each item's triples to the batch graph with sensor provenance
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AssertStage;
+
+#[async_trait]
+impl PipelineStage for AssertStage {
+    fn name(&self) -> &str {
+        ASSERT_STAGE
+    }
+
+    async fn process(&self, store: &mut RdfStore, batch: &mut EventBatch) -> Result<(), StageError> {
+        for item in &batch.items {
+            store.insert_batch(item.triples.clone(), batch.graph.clone(), Provenance::Sensor {
+                source: item.source.clone(),
+                confidence: None,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Ordered ingestion stages (the built-in stages by default)
+///
+/// 名前が同じステージを登録すると、元の位置で置き換える
+#[derive(Clone)]
+pub struct StagePipeline {
+    stages: Vec<Arc<dyn PipelineStage>>,
+}
+
+impl Default for StagePipeline {
+    fn default() -> Self {
+        Self { stages: vec![Arc::new(EventTriplesStage), Arc::new(AssertStage)] }
+    }
+}
+
+impl fmt::Debug for StagePipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StagePipeline").field("stages", &self.names()).finish()
+    }
+}
+
+impl StagePipeline {
+    /// Pipeline without any stage (not even the built-in ones)
+    pub fn empty() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    /// Stage names in execution order
+    pub fn names(&self) -> Vec<&str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+
+    /// Run `stage` last
+    pub fn push(&mut self, stage: Box<dyn PipelineStage>) {
+        let stage: Arc<dyn PipelineStage> = Arc::from(stage);
+        match self.position(stage.name()) {
+            Some(index) => self.stages[index] = stage,
+            None => self.stages.push(stage),
+        }
+    }
+
+    /// Run `stage` right before the stage named `anchor`
+    pub fn insert_before(&mut self, anchor: &str, stage: Box<dyn PipelineStage>) -> Result<(), StageError> {
+        self.insert_at(anchor, 0, stage)
+    }
+
+    /// Run `stage` right after the stage named `anchor`
+    pub fn insert_after(&mut self, anchor: &str, stage: Box<dyn PipelineStage>) -> Result<(), StageError> {
+        self.insert_at(anchor, 1, stage)
+    }
+
+    /// Remove the stage named `name`; returns whether it was registered
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.stages.len();
+        self.stages.retain(|stage| stage.name() != name);
+        self.stages.len() != before
+    }
+
+    /// Run every stage over `batch` in order
+    pub async fn run(&self, store: &mut RdfStore, batch: &mut EventBatch) -> Result<(), StageError> {
+        for stage in &self.stages {
+            stage.process(store, batch).await?;
+        }
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.stages.iter().position(|stage| stage.name() == name)
+    }
+
+    fn insert_at(&mut self, anchor: &str, offset: usize, stage: Box<dyn PipelineStage>) -> Result<(), StageError> {
+        let anchor_index = self.position(anchor).ok_or_else(|| StageError::UnknownStage(anchor.to_string()))?;
+        let stage: Arc<dyn PipelineStage> = Arc::from(stage);
+        if stage.name() == anchor {
+            self.stages[anchor_index] = stage;
+            return Ok(());
+        }
+        if let Some(existing) = self.position(stage.name()) {
+            self.stages.remove(existing);
+        }
+        // 同名ステージを取り除くと anchor の位置がずれるため求め直す
+        if let Some(index) = self.position(anchor) {
+            self.stages.insert(index + offset, stage);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tags every event subject with the country of its source IP
+    struct GeoIpStage;
+
+    #[async_trait]
+    impl PipelineStage for GeoIpStage {
+        fn name(&self) -> &str {
+            "geoip"
+        }
+
+        async fn process(&self, store: &mut RdfStore, batch: &mut EventBatch) -> Result<(), StageError> {
+            for item in &batch.items {
+                let Some(subject) = item.triples.first().map(|triple| triple.subject.clone()) else { continue };
+                let enrichment = Triple {
+                    subject,
+                    predicate: "http://example.org/country".to_string(),
+                    object: "JP".to_string(),
+                };
+                store.insert(enrichment, GraphId::Named("enrichment".to_string()), Provenance::Sensor { source: "geoip".to_string(), confidence: None });
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_stage_runs_in_registration_order() {
+        let mut pipeline = StagePipeline::default();
+        pipeline.insert_after(EVENT_TRIPLES_STAGE, Box::new(GeoIpStage)).unwrap();
+        assert_eq!(pipeline.names(), vec![EVENT_TRIPLES_STAGE, "geoip", ASSERT_STAGE]);
+        assert!(matches!(pipeline.insert_before("missing", Box::new(GeoIpStage)), Err(StageError::UnknownStage(_))));
+
+        let event = CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            success: true,
+            timestamp: 1_700_000_000,
+        };
+        let mut store = RdfStore::new();
+        let mut batch = EventBatch::new(GraphId::Named("events".to_string()), vec![IngestItem::event(event, "edr-1").with_correlation_id("c-1")]);
+        pipeline.run(&mut store, &mut batch).await.unwrap();
+
+        assert_eq!(store.find_triples(None, Some("http://example.org/country"), Some("JP")).len(), 1);
+        assert_eq!(store.find_triples(None, Some("http://example.org/correlationId"), Some("c-1")).len(), 1);
+        assert_eq!(store.get_graph(&GraphId::Named("events".to_string())).len(), batch.items[0].triples.len());
+
+        assert!(pipeline.remove("geoip"));
+        pipeline.push(Box::new(GeoIpStage));
+        assert_eq!(pipeline.names(), vec![EVENT_TRIPLES_STAGE, ASSERT_STAGE, "geoip"]);
+    }
+}
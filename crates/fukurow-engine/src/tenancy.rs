@@ -0,0 +1,502 @@
+//! # Tenant Isolation
+//!
+//! Per-tenant (or per-profile) worker pools with queue quotas.
+//! 各テナントの同時実行数・待ち行列長に上限を設け、全体の推論容量は
+//! FIFO の公平なセマフォで配分することで、バースト的なテナントによる独占を防ぐ
+//!
+//! [`TenantEngines`] はテナントごとに独立したストアと推論エンジンを持たせ、
+//! データ・クエリ・推論がテナントの境界を越えないようにする
+
+use crate::engine::ReasonerEngine;
+use crate::orchestration::ProcessingOptions;
+use fukurow_store::{store::RdfStore, TenantId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+/// Pool limits for a single tenant/profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantPoolConfig {
+    /// Maximum reasoning tasks running concurrently for this tenant
+    pub max_concurrency: usize,
+    /// Maximum tasks waiting for a slot (beyond this, submissions are rejected)
+    pub max_queue: usize,
+}
+
+impl Default for TenantPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 2,
+            max_queue: 32,
+        }
+    }
+}
+
+/// Tenant isolation configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantIsolationConfig {
+    /// Total reasoning capacity shared by all tenants
+    pub global_concurrency: usize,
+    /// Limits applied to tenants without an explicit entry
+    #[serde(default)]
+    pub default_pool: TenantPoolConfig,
+    /// Per-tenant overrides
+    #[serde(default)]
+    pub tenants: HashMap<String, TenantPoolConfig>,
+    /// Wait time after which a task is counted as starved (milliseconds)
+    pub starvation_threshold_ms: u64,
+}
+
+impl Default for TenantIsolationConfig {
+    fn default() -> Self {
+        Self {
+            global_concurrency: 8,
+            default_pool: TenantPoolConfig::default(),
+            tenants: HashMap::new(),
+            starvation_threshold_ms: 5_000,
+        }
+    }
+}
+
+impl TenantIsolationConfig {
+    pub fn with_tenant(mut self, tenant: impl Into<String>, pool: TenantPoolConfig) -> Self {
+        self.tenants.insert(tenant.into(), pool);
+        self
+    }
+
+    pub fn pool_for(&self, tenant: &str) -> &TenantPoolConfig {
+        self.tenants.get(tenant).unwrap_or(&self.default_pool)
+    }
+}
+
+/// Tenant scheduling errors
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum TenantError {
+    #[error("Queue quota exceeded for tenant {tenant} (limit {limit})")]
+    QueueFull { tenant: String, limit: usize },
+
+    #[error("Scheduler closed")]
+    Closed,
+}
+
+/// Per-tenant scheduling metrics
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TenantMetrics {
+    pub tenant: String,
+    pub running: usize,
+    pub queued: usize,
+    pub completed: u64,
+    pub rejected: u64,
+    /// Tasks that waited longer than the starvation threshold
+    pub starved: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: u64,
+}
+
+#[derive(Debug)]
+struct TenantPool {
+    slots: Arc<Semaphore>,
+    config: TenantPoolConfig,
+    running: AtomicUsize,
+    queued: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+    starved: AtomicU64,
+    total_wait_ms: AtomicU64,
+    max_wait_ms: AtomicU64,
+}
+
+impl TenantPool {
+    fn new(config: TenantPoolConfig) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(config.max_concurrency.max(1))),
+            config,
+            running: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            completed: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            starved: AtomicU64::new(0),
+            total_wait_ms: AtomicU64::new(0),
+            max_wait_ms: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Permit for one running task; releases the tenant and global slots on drop
+#[derive(Debug)]
+pub struct TenantPermit {
+    pool: Arc<TenantPool>,
+    _tenant_slot: OwnedSemaphorePermit,
+    _global_slot: OwnedSemaphorePermit,
+}
+
+impl Drop for TenantPermit {
+    fn drop(&mut self) {
+        self.pool.running.fetch_sub(1, Ordering::Relaxed);
+        self.pool.completed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts a task as queued until dropped
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Fair scheduler that isolates reasoning capacity per tenant
+#[derive(Debug)]
+pub struct TenantScheduler {
+    config: TenantIsolationConfig,
+    global: Arc<Semaphore>,
+    pools: RwLock<HashMap<String, Arc<TenantPool>>>,
+}
+
+impl TenantScheduler {
+    pub fn new(config: TenantIsolationConfig) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(config.global_concurrency.max(1))),
+            config,
+            pools: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &TenantIsolationConfig {
+        &self.config
+    }
+
+    fn pool(&self, tenant: &str) -> Arc<TenantPool> {
+        if let Some(pool) = self.pools.read().unwrap_or_else(|e| e.into_inner()).get(tenant) {
+            return Arc::clone(pool);
+        }
+        let mut pools = self.pools.write().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(pools.entry(tenant.to_string())
+            .or_insert_with(|| Arc::new(TenantPool::new(self.config.pool_for(tenant).clone()))))
+    }
+
+    /// Wait for a slot for `tenant`, or fail immediately when its queue quota is exhausted
+    pub async fn acquire(&self, tenant: &str) -> Result<TenantPermit, TenantError> {
+        let pool = self.pool(tenant);
+
+        let queued = pool.queued.fetch_add(1, Ordering::AcqRel);
+        let busy = pool.running.load(Ordering::Acquire) >= pool.config.max_concurrency;
+        if busy && queued >= pool.config.max_queue {
+            pool.queued.fetch_sub(1, Ordering::AcqRel);
+            pool.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(TenantError::QueueFull { tenant: tenant.to_string(), limit: pool.config.max_queue });
+        }
+
+        let enqueued_at = Instant::now();
+        // 待機中に future が破棄されても待ち行列の数が戻るようにする
+        let queued = QueuedGuard(&pool.queued);
+        // テナント枠を先に確保し、その後グローバル枠を FIFO で待つ
+        let slots = async {
+            let tenant_slot = Arc::clone(&pool.slots).acquire_owned().await.ok()?;
+            let global_slot = Arc::clone(&self.global).acquire_owned().await.ok()?;
+            Some((tenant_slot, global_slot))
+        }.await;
+        drop(queued);
+        let (tenant_slot, global_slot) = slots.ok_or(TenantError::Closed)?;
+
+        let waited_ms = enqueued_at.elapsed().as_millis() as u64;
+        pool.total_wait_ms.fetch_add(waited_ms, Ordering::Relaxed);
+        pool.max_wait_ms.fetch_max(waited_ms, Ordering::Relaxed);
+        if waited_ms > self.config.starvation_threshold_ms {
+            pool.starved.fetch_add(1, Ordering::Relaxed);
+            warn!("Tenant {} waited {}ms for reasoning capacity", tenant, waited_ms);
+        }
+        pool.running.fetch_add(1, Ordering::AcqRel);
+
+        Ok(TenantPermit {
+            pool,
+            _tenant_slot: tenant_slot,
+            _global_slot: global_slot,
+        })
+    }
+
+    /// Run `task` within the tenant's pool
+    pub async fn run<F, T>(&self, tenant: &str, task: F) -> Result<T, TenantError>
+    where
+        F: Future<Output = T>,
+    {
+        let _permit = self.acquire(tenant).await?;
+        Ok(task.await)
+    }
+
+    /// Metrics for every tenant seen so far, sorted by tenant
+    pub fn metrics(&self) -> Vec<TenantMetrics> {
+        let pools = self.pools.read().unwrap_or_else(|e| e.into_inner());
+        let mut metrics: Vec<TenantMetrics> = pools.iter()
+            .map(|(tenant, pool)| {
+                let completed = pool.completed.load(Ordering::Relaxed);
+                let running = pool.running.load(Ordering::Relaxed);
+                let started = completed + running as u64;
+                TenantMetrics {
+                    tenant: tenant.clone(),
+                    running,
+                    queued: pool.queued.load(Ordering::Relaxed),
+                    completed,
+                    rejected: pool.rejected.load(Ordering::Relaxed),
+                    starved: pool.starved.load(Ordering::Relaxed),
+                    avg_wait_ms: if started == 0 {
+                        0.0
+                    } else {
+                        pool.total_wait_ms.load(Ordering::Relaxed) as f64 / started as f64
+                    },
+                    max_wait_ms: pool.max_wait_ms.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        metrics.sort_by(|a, b| a.tenant.cmp(&b.tenant));
+        metrics
+    }
+
+    /// Global slots currently available
+    pub fn available_capacity(&self) -> usize {
+        self.global.available_permits()
+    }
+}
+
+/// Hook run on the store of every newly created tenant engine (e.g. ontology bootstrap)
+pub type TenantInitializer = Arc<dyn Fn(&TenantId, &mut RdfStore) + Send + Sync>;
+
+/// Builds the rules registered on every newly created tenant engine
+pub type TenantRules = Arc<dyn Fn() -> Vec<Box<dyn fukurow_rules::Rule>> + Send + Sync>;
+
+/// Reasoner engines keyed by tenant
+///
+/// エンジンは最初に参照されたときに作られ、初期化フックで語彙などを読み込んでから
+/// 登録される。テナント間でストアを共有することはない
+pub struct TenantEngines {
+    options: ProcessingOptions,
+    engines: RwLock<HashMap<TenantId, Arc<ReasonerEngine>>>,
+    initializer: RwLock<Option<TenantInitializer>>,
+    rules: RwLock<Option<TenantRules>>,
+}
+
+impl Default for TenantEngines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for TenantEngines {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantEngines")
+            .field("tenants", &self.tenants())
+            .finish()
+    }
+}
+
+impl TenantEngines {
+    pub fn new() -> Self {
+        Self::with_processing_options(ProcessingOptions::default())
+    }
+
+    /// Options used for engines created on demand
+    pub fn with_processing_options(options: ProcessingOptions) -> Self {
+        Self {
+            options,
+            engines: RwLock::new(HashMap::new()),
+            initializer: RwLock::new(None),
+            rules: RwLock::new(None),
+        }
+    }
+
+    /// Set the hook run on stores of engines created from now on
+    pub fn set_initializer(&self, initializer: impl Fn(&TenantId, &mut RdfStore) + Send + Sync + 'static) {
+        *self.initializer.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(initializer));
+    }
+
+    /// Set the rules registered on engines created from now on
+    ///
+    /// ルールはエンジンごとに状態を持ちうるため、テナントごとに `rules` を呼んで作り直す
+    pub fn set_rules(&self, rules: impl Fn() -> Vec<Box<dyn fukurow_rules::Rule>> + Send + Sync + 'static) {
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(rules));
+    }
+
+    /// Register a pre-built engine for `tenant`, replacing any existing one
+    pub fn insert(&self, tenant: TenantId, engine: ReasonerEngine) -> Arc<ReasonerEngine> {
+        let engine = Arc::new(engine);
+        self.engines.write().unwrap_or_else(|e| e.into_inner()).insert(tenant, Arc::clone(&engine));
+        engine
+    }
+
+    /// Engine of `tenant`, creating and initializing it on first use
+    ///
+    /// 初期化フック (語彙の読み込みなど) はロックの外で実行し、他のテナントの参照を待たせない。
+    /// 読み取りだけの経路では [`TenantEngines::get`] を使い、テナントを作らないこと
+    pub fn engine(&self, tenant: &TenantId) -> Arc<ReasonerEngine> {
+        if let Some(engine) = self.get(tenant) {
+            return engine;
+        }
+
+        let mut store = RdfStore::new();
+        let initializer = self.initializer.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(initializer) = initializer {
+            initializer(tenant, &mut store);
+        }
+        let mut engine = ReasonerEngine::with_store(store, self.options.clone());
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner()).clone();
+        for rule in rules.map(|rules| rules()).unwrap_or_default() {
+            engine = engine.with_rule(rule);
+        }
+        let engine = Arc::new(engine);
+        // 初期化の間に別スレッドが作っていればそちらを使う
+        let mut engines = self.engines.write().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(engines.entry(tenant.clone()).or_insert(engine))
+    }
+
+    /// Engine of `tenant` if it exists
+    pub fn get(&self, tenant: &TenantId) -> Option<Arc<ReasonerEngine>> {
+        self.engines.read().unwrap_or_else(|e| e.into_inner()).get(tenant).cloned()
+    }
+
+    /// Drop the tenant's engine (and with it the tenant's store)
+    pub fn remove(&self, tenant: &TenantId) -> Option<Arc<ReasonerEngine>> {
+        self.engines.write().unwrap_or_else(|e| e.into_inner()).remove(tenant)
+    }
+
+    /// Tenants with an engine, sorted
+    pub fn tenants(&self) -> Vec<TenantId> {
+        let mut tenants: Vec<TenantId> = self.engines.read().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect();
+        tenants.sort();
+        tenants
+    }
+
+    pub fn len(&self) -> usize {
+        self.engines.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config() -> TenantIsolationConfig {
+        TenantIsolationConfig {
+            global_concurrency: 3,
+            default_pool: TenantPoolConfig { max_concurrency: 1, max_queue: 4 },
+            tenants: HashMap::new(),
+            starvation_threshold_ms: 10_000,
+        }
+        .with_tenant("bursty", TenantPoolConfig { max_concurrency: 2, max_queue: 1 })
+    }
+
+    #[test]
+    fn test_config_deserialization_defaults() {
+        let config: TenantIsolationConfig = serde_json::from_str(
+            r#"{"global_concurrency": 4, "starvation_threshold_ms": 1000, "tenants": {"acme": {"max_concurrency": 3, "max_queue": 10}}}"#,
+        ).unwrap();
+
+        assert_eq!(config.pool_for("acme").max_concurrency, 3);
+        assert_eq!(config.pool_for("other"), &TenantPoolConfig::default());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_engines_isolate_stores() {
+        let engines = TenantEngines::new();
+        let initialized = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&initialized);
+        engines.set_initializer(move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let acme = TenantId::new("acme").unwrap();
+        let globex = TenantId::new("globex").unwrap();
+        engines.engine(&acme).add_event(fukurow_core::model::CyberEvent::NetworkConnection {
+            source_ip: "192.168.1.10".to_string(),
+            dest_ip: "10.0.0.50".to_string(),
+            port: 443,
+            protocol: "tcp".to_string(),
+            timestamp: 1640995200,
+        }).await.unwrap();
+
+        let acme_store = engines.engine(&acme).get_graph_store().await;
+        let globex_store = engines.engine(&globex).get_graph_store().await;
+        assert!(!acme_store.read().await.find_triples(None, None, None).is_empty());
+        assert!(globex_store.read().await.find_triples(None, None, None).is_empty());
+
+        assert_eq!(initialized.load(Ordering::SeqCst), 2);
+        assert_eq!(engines.tenants(), vec![acme, globex]);
+    }
+
+    #[tokio::test]
+    async fn test_initializer_runs_outside_the_engine_lock() {
+        let engines = Arc::new(TenantEngines::new());
+        let seen = Arc::new(AtomicUsize::new(usize::MAX));
+        let (registry, counter) = (Arc::clone(&engines), Arc::clone(&seen));
+        // 初期化中も他のテナントのエンジンを参照できる
+        engines.set_initializer(move |_, _| counter.store(registry.len(), Ordering::SeqCst));
+
+        let acme = TenantId::new("acme").unwrap();
+        assert!(engines.get(&acme).is_none());
+        engines.engine(&acme);
+        assert_eq!(seen.load(Ordering::SeqCst), 0);
+        assert!(engines.get(&acme).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_cannot_monopolize_capacity() {
+        let scheduler = TenantScheduler::new(config());
+
+        let first = scheduler.acquire("bursty").await.unwrap();
+        let second = scheduler.acquire("bursty").await.unwrap();
+
+        // bursty の同時実行上限 (2) に達しても他テナントは即座に実行できる
+        let quiet = tokio::time::timeout(Duration::from_millis(100), scheduler.acquire("quiet")).await;
+        assert!(quiet.expect("quiet tenant should not wait").is_ok());
+
+        // 上限到達中の待ち行列は 1 件まで
+        let scheduler = Arc::new(scheduler);
+        let waiting = {
+            let scheduler = Arc::clone(&scheduler);
+            tokio::spawn(async move { scheduler.run("bursty", async { 42 }).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            scheduler.acquire("bursty").await.unwrap_err(),
+            TenantError::QueueFull { tenant: "bursty".to_string(), limit: 1 },
+        );
+
+        drop(first);
+        assert_eq!(waiting.await.unwrap().unwrap(), 42);
+        drop(second);
+
+        let metrics = scheduler.metrics();
+        let bursty = metrics.iter().find(|m| m.tenant == "bursty").unwrap();
+        assert_eq!(bursty.rejected, 1);
+        assert_eq!(bursty.completed, 3);
+        assert_eq!(bursty.running, 0);
+        assert!(bursty.max_wait_ms >= 10);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_wait_leaves_the_queue() {
+        let scheduler = TenantScheduler::new(config());
+        let running = scheduler.acquire("acme").await.unwrap();
+
+        // 待機中にタイムアウトで破棄された取得は待ち行列に残らない
+        for _ in 0..8 {
+            assert!(tokio::time::timeout(Duration::from_millis(5), scheduler.acquire("acme")).await.is_err());
+        }
+        let acme = |scheduler: &TenantScheduler| scheduler.metrics().into_iter().find(|m| m.tenant == "acme").unwrap();
+        assert_eq!(acme(&scheduler).queued, 0);
+        assert_eq!(acme(&scheduler).rejected, 0);
+
+        drop(running);
+        assert!(scheduler.acquire("acme").await.is_ok());
+    }
+}
@@ -0,0 +1,174 @@
+//! Reasoning worker pool
+//!
+//! 互いに依存しないルールと、分割可能なルールのパーティション (DSL のポリシーなど) を rayon の
+//! スレッドプールで並列に評価する。ワーカー数は既定で利用可能なコア数、
+//! [`crate::ProcessingOptions::with_max_workers`] で上書きできる。結果はタスクの順に集めるため、
+//! ワーカー数によらず推論結果は逐次実行と同じになる
+
+use async_trait::async_trait;
+use fukurow_rules::{RuleExecutor, RuleOutcome, RuleTask};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Rayon pools shared by every engine, one per worker count
+fn shared_pool(workers: usize) -> Option<Arc<ThreadPool>> {
+    static POOLS: OnceLock<Mutex<HashMap<usize, Arc<ThreadPool>>>> = OnceLock::new();
+    let mut pools = POOLS.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(pool) = pools.get(&workers) {
+        return Some(pool.clone());
+    }
+    match ThreadPoolBuilder::new().num_threads(workers).thread_name(|index| format!("fukurow-reasoner-{}", index)).build() {
+        Ok(pool) => {
+            let pool = Arc::new(pool);
+            pools.insert(workers, pool.clone());
+            Some(pool)
+        }
+        Err(e) => {
+            tracing::warn!(workers, error = %e, "Failed to start reasoning worker pool; evaluating rules sequentially");
+            None
+        }
+    }
+}
+
+/// Worker pool evaluating independent rule applications in parallel
+///
+/// ルールは Tokio ランタイムの外 (ワーカースレッド上) で実行される。ランタイムのハンドルには
+/// 入っているので `tokio::spawn` は使えるが、タイマーなどランタイムの駆動を待つ処理は避けること
+#[derive(Debug, Clone)]
+pub struct WorkerPool {
+    workers: usize,
+}
+
+impl WorkerPool {
+    /// Pool with `max_workers` workers, or one per available core
+    pub fn new(max_workers: Option<usize>) -> Self {
+        let workers = max_workers.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |cores| cores.get()));
+        Self { workers: workers.max(1) }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Apply `f` to every item on the pool, keeping the items' order
+    pub fn map_ordered<T, R, F>(&self, items: Vec<T>, f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Send + Sync,
+    {
+        match (self.workers > 1 && items.len() > 1).then(|| shared_pool(self.workers)).flatten() {
+            Some(pool) => pool.install(|| items.into_par_iter().map(f).collect()),
+            None => items.into_iter().map(f).collect(),
+        }
+    }
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+#[async_trait]
+impl RuleExecutor for WorkerPool {
+    fn concurrency(&self) -> usize {
+        self.workers
+    }
+
+    async fn run<'a>(&self, tasks: Vec<RuleTask<'a>>) -> Vec<RuleOutcome> {
+        if self.workers <= 1 || tasks.len() <= 1 {
+            let mut outcomes = Vec::with_capacity(tasks.len());
+            for task in tasks {
+                outcomes.push(task.await);
+            }
+            return outcomes;
+        }
+
+        let handle = Handle::try_current().ok();
+        let evaluate = || {
+            self.map_ordered(tasks, |task| {
+                let _runtime = handle.as_ref().map(Handle::enter);
+                futures::executor::block_on(task)
+            })
+        };
+        // マルチスレッドのランタイムでは他のタスクを止めないよう、待つ間このワーカーを手放す
+        match handle.as_ref().map(Handle::runtime_flavor) {
+            Some(RuntimeFlavor::MultiThread) => tokio::task::block_in_place(evaluate),
+            _ => evaluate(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProcessingOptions, ReasoningEngine, ReasoningStage};
+    use fukurow_core::model::Triple;
+    use fukurow_rules::DslRule;
+    use fukurow_store::provenance::{GraphId, Provenance};
+    use fukurow_store::store::RdfStore;
+
+    fn policy(name: &str, rules: &[(&str, &str, &str)]) -> DslRule {
+        let rules: Vec<serde_json::Value> = rules.iter().enumerate().map(|(index, (predicate, object, derived))| serde_json::json!({
+            "id": format!("{}-{}", name, index), "name": name, "description": "", "severity": "High", "metadata": {},
+            "conditions": [{"type": "TripleExists", "config": {"subject": "?host", "predicate": predicate, "object": object}}],
+            "actions": [
+                {"type": "AddTriple", "config": {"subject": format!("ex:{}", derived), "predicate": format!("ex:{}", name), "object": derived}},
+                {"type": "SecurityAction", "config": {"action_type": "Alert", "message": format!("{} matched {}", name, object), "details": {}}}
+            ]
+        })).collect();
+        // ポリシーごとに 1 パーティションになるよう、ルールを別々のポリシーに分ける
+        rules.into_iter().enumerate().fold(DslRule::new(), |rule, (index, policy_rule)| {
+            let policy = serde_json::json!({
+                "name": format!("{}-{}", name, index), "description": "", "version": "1.0.0", "priority": 0, "metadata": {},
+                "rules": [policy_rule]
+            });
+            rule.with_json_policy(&policy.to_string()).unwrap()
+        })
+    }
+
+    async fn reason(workers: usize) -> (Vec<Triple>, serde_json::Value) {
+        let mut store = RdfStore::new();
+        for (host, predicate, object) in [("h1", "ex:alert", "level0"), ("h2", "ex:alert", "level1"), ("h3", "ex:alert", "level3"), ("h4", "ex:login", "failed")] {
+            let triple = Triple { subject: format!("ex:{}", host), predicate: predicate.to_string(), object: object.to_string() };
+            store.insert(triple, GraphId::Sensor("edr".to_string()), Provenance::Sensor { source: "edr".to_string(), confidence: None });
+        }
+
+        let options = ProcessingOptions::default().with_stages(vec![ReasoningStage::Rules]).with_max_workers(workers);
+        let mut engine = ReasoningEngine::with_options(options);
+        let levels: Vec<(&str, &str, &str)> = vec![
+            ("ex:alert", "level0", "p0"), ("ex:alert", "level1", "p1"), ("ex:alert", "level2", "p2"),
+            ("ex:alert", "level3", "p3"), ("ex:alert", "level4", "p4"), ("ex:alert", "level5", "p5"),
+        ];
+        engine.register_rule(Box::new(policy("alerts", &levels)));
+        engine.register_rule(Box::new(policy("logins", &[("ex:login", "failed", "brute")])));
+        // alerts の結果を読むので、並列実行でも alerts の後に実行される
+        engine.register_rule(Box::new(policy("escalate", &[("ex:alerts", "p0", "escalated"), ("ex:logins", "brute", "locked")])));
+
+        let result = engine.process_and_materialize(&mut store).await.unwrap();
+        (result.inferred_triples, serde_json::to_value(&result.actions).unwrap())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_parallel_reasoning_matches_sequential() {
+        let sequential = reason(1).await;
+        assert!(sequential.0.iter().any(|triple| triple.object == "escalated"));
+        assert!(sequential.0.iter().any(|triple| triple.object == "locked"));
+        for _ in 0..5 {
+            assert_eq!(reason(4).await, sequential);
+        }
+    }
+
+    #[test]
+    fn test_map_ordered_keeps_item_order() {
+        let pool = WorkerPool::new(Some(4));
+        assert_eq!(pool.workers(), 4);
+        let squares = pool.map_ordered((0..100u64).collect(), |n| n * n);
+        assert_eq!(squares, (0..100u64).map(|n| n * n).collect::<Vec<_>>());
+        assert_eq!(WorkerPool::new(Some(0)).workers(), 1);
+    }
+}
@@ -85,6 +85,7 @@ async fn test_engine_result_creation() {
                 details: serde_json::json!({"test": true}),
             }
         ],
+        action_correlations: vec![],
         violations: vec![],
         stats: ProcessingStats {
             rules_applied: 1,
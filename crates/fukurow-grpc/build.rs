@@ -0,0 +1,8 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // システムに protoc が無くてもビルドできるよう、同梱のバイナリを使う (PROTOC を指定すればそちらを優先)
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_build::compile_protos("proto/fukurow.proto")?;
+    Ok(())
+}
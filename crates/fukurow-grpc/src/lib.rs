@@ -0,0 +1,18 @@
+//! # Fukurow gRPC API
+//!
+//! REST API と同じ操作 (イベント投入・推論・SPARQL・推論結果の購読) を gRPC で提供する。
+//! 状態は [`fukurow_api::AppState`] を共有するため、Axum サーバーと同じテナント・ストア・
+//! 認証設定で動き、どちらから実行した推論結果も両方の購読者に配信される。
+//! `Replicate` と [`Standby`] でテナントのストアをホットスタンバイに複製する
+
+pub mod proto {
+    tonic::include_proto!("fukurow.v1");
+}
+
+pub mod service;
+pub mod server;
+pub mod replication;
+
+pub use service::*;
+pub use server::*;
+pub use replication::*;
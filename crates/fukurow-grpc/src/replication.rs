@@ -0,0 +1,127 @@
+//! Hot standby over gRPC
+//!
+//! [`Standby`] はリーダーの `Replicate` ストリームを購読し、受け取った変更をローカルのストアに適用する。
+//! 接続が切れたり欠番を検出したりしたら再接続し、必要ならスナップショットから取り直す。
+//! [`Standby::promote`] でアクティブに切り替えると購読を止め、自分の変更ログを公開できる状態になる
+
+use crate::proto::{self, fukurow_service_client::FukurowServiceClient};
+use fukurow_store::{RdfStore, ReplicationError, ReplicationFollower, ReplicationMessage, ReplicationRole, ReplicationStatus};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::Request;
+use tracing::{info, warn};
+
+/// Wait before reconnecting to the leader
+pub const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Follower instance replicating a leader's tenant store
+pub struct Standby {
+    endpoint: String,
+    store: Arc<RwLock<RdfStore>>,
+    follower: Mutex<ReplicationFollower>,
+    api_key: Option<String>,
+    tenant: Option<String>,
+    reconnect_interval: Duration,
+}
+
+impl Standby {
+    /// Follow the leader at `endpoint` (e.g. `http://leader:50051`) into `store`
+    pub fn new(endpoint: impl Into<String>, store: Arc<RwLock<RdfStore>>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            store,
+            follower: Mutex::new(ReplicationFollower::new()),
+            api_key: None,
+            tenant: None,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+        }
+    }
+
+    /// Admin API key of the leader
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Tenant to replicate (the key's tenant when unset)
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn with_reconnect_interval(mut self, interval: Duration) -> Self {
+        self.reconnect_interval = interval;
+        self
+    }
+
+    /// Replication position and lag
+    pub fn status(&self) -> ReplicationStatus {
+        self.follower().status()
+    }
+
+    /// Switch this instance to active; returns the new epoch
+    ///
+    /// 以後リーダーからのメッセージは適用しない。`backlog` は自分のフォロワー用に残す変更の数
+    pub async fn promote(&self, backlog: usize) -> u64 {
+        let mut store = self.store.write().await;
+        let epoch = self.follower().promote(&mut store, backlog);
+        info!("Promoted standby of {} to leader (epoch {})", self.endpoint, epoch);
+        epoch
+    }
+
+    /// Follow the leader until promoted
+    ///
+    /// 切断・欠番では再接続を繰り返す。より新しいエポックのインスタンスから古いリーダーに
+    /// つないだ場合 ([`ReplicationError::StaleEpoch`]) は設定の誤りとしてエラーを返す
+    pub async fn run(&self) -> Result<(), ReplicationError> {
+        while self.follower().role() == ReplicationRole::Follower {
+            match self.follow_once().await {
+                Err(ReplicationError::NotFollower) => break,
+                Err(e @ ReplicationError::StaleEpoch { .. }) => return Err(e),
+                Err(e) => {
+                    warn!("Replication from {} interrupted: {}", self.endpoint, e);
+                    tokio::time::sleep(self.reconnect_interval).await;
+                }
+                Ok(()) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Start [`Self::run`] in the background
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<Result<(), ReplicationError>> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn follow_once(&self) -> Result<(), ReplicationError> {
+        let transport = |e: &dyn std::fmt::Display| ReplicationError::Transport(e.to_string());
+        let mut client = FukurowServiceClient::connect(self.endpoint.clone()).await.map_err(|e| transport(&e))?;
+
+        let cursor = self.follower().cursor();
+        let mut request = Request::new(proto::ReplicateRequest {
+            epoch: cursor.map(|cursor| cursor.epoch),
+            sequence: cursor.map(|cursor| cursor.sequence),
+        });
+        for (key, value) in [("x-api-key", &self.api_key), ("x-tenant-id", &self.tenant)] {
+            if let Some(value) = value {
+                let value: MetadataValue<Ascii> = value.parse().map_err(|e| transport(&e))?;
+                request.metadata_mut().insert(key, value);
+            }
+        }
+
+        let mut frames = client.replicate(request).await.map_err(|e| transport(&e))?.into_inner();
+        while let Some(frame) = frames.message().await.map_err(|e| transport(&e))? {
+            let message: ReplicationMessage = serde_json::from_str(&frame.message_json)
+                .map_err(|e| ReplicationError::Transport(format!("Invalid replication frame: {}", e)))?;
+            let mut store = self.store.write().await;
+            self.follower().apply(&mut store, message)?;
+        }
+        Err(ReplicationError::Transport("Leader closed the replication stream".to_string()))
+    }
+
+    fn follower(&self) -> MutexGuard<'_, ReplicationFollower> {
+        self.follower.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
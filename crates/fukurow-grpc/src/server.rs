@@ -0,0 +1,53 @@
+//! gRPC server
+//!
+//! REST サーバーの [`ReasonerServer::app_state`](fukurow_api::ReasonerServer::app_state) を渡して起動すると、
+//! 同じエンジンに対して両方のプロトコルで応答する
+
+use crate::proto::fukurow_service_server::FukurowServiceServer;
+use crate::service::FukurowGrpcService;
+use fukurow_api::AppState;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::info;
+
+/// Default gRPC port (the REST API defaults to 3000)
+pub const DEFAULT_GRPC_PORT: u16 = 50051;
+
+/// gRPC server sharing the REST server's state
+pub struct GrpcServer {
+    addr: SocketAddr,
+    service: FukurowGrpcService,
+}
+
+impl GrpcServer {
+    pub fn new(state: AppState, addr: SocketAddr) -> Self {
+        Self { addr, service: FukurowGrpcService::new(Arc::new(state)) }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Service for mounting on another tonic router
+    pub fn service(&self) -> FukurowServiceServer<FukurowGrpcService> {
+        FukurowServiceServer::new(self.service.clone())
+    }
+
+    /// Serve until the process exits
+    pub async fn serve(self) -> Result<(), tonic::transport::Error> {
+        info!("Starting gRPC server on {}", self.addr);
+        tonic::transport::Server::builder()
+            .add_service(self.service())
+            .serve(self.addr)
+            .await
+    }
+
+    /// Serve until `shutdown_signal` completes
+    pub async fn run_with_shutdown(self, shutdown_signal: impl std::future::Future<Output = ()> + Send + 'static) -> Result<(), tonic::transport::Error> {
+        info!("Starting gRPC server on {} with graceful shutdown", self.addr);
+        tonic::transport::Server::builder()
+            .add_service(self.service())
+            .serve_with_shutdown(self.addr, shutdown_signal)
+            .await
+    }
+}
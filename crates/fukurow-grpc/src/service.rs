@@ -0,0 +1,361 @@
+//! gRPC service implementation
+//!
+//! 認証とロール検査は REST と同じ [`AuthConfig`](fukurow_api::AuthConfig) で行い、
+//! メタデータ (`x-api-key` / `authorization` / `x-tenant-id`) をヘッダとして扱う
+
+use crate::proto::{self, fukurow_service_server::FukurowService};
+use fukurow_api::{ApiError, AppState, AuthError, Principal, Role};
+use fukurow_core::model::{CyberEvent, SecurityAction};
+use fukurow_engine::ReasoningProfile;
+use fukurow_sparql::SparqlParser;
+use fukurow_store::{RdfStore, ReplicationCursor, ReplicationLog, ReplicationMessage, DEFAULT_REPLICATION_BACKLOG};
+use fukurow_streaming::StreamingEvent;
+use futures::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock};
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status};
+
+/// Source recorded for events submitted without one
+pub const DEFAULT_SOURCE: &str = "grpc";
+/// How often an idle replication stream checks for new mutations
+pub const DEFAULT_REPLICATION_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// `FukurowService` backed by the REST server's state
+#[derive(Clone)]
+pub struct FukurowGrpcService {
+    state: Arc<AppState>,
+    replication_poll_interval: Duration,
+    replication_backlog: usize,
+}
+
+impl FukurowGrpcService {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self {
+            state,
+            replication_poll_interval: DEFAULT_REPLICATION_POLL_INTERVAL,
+            replication_backlog: DEFAULT_REPLICATION_BACKLOG,
+        }
+    }
+
+    /// Interval between heartbeats of an idle replication stream
+    pub fn with_replication_poll_interval(mut self, interval: Duration) -> Self {
+        self.replication_poll_interval = interval;
+        self
+    }
+
+    /// Mutations kept for followers that reconnect (older followers receive a snapshot)
+    pub fn with_replication_backlog(mut self, backlog: usize) -> Self {
+        self.replication_backlog = backlog;
+        self
+    }
+
+    /// Authenticate the call and check that its role permits `required`
+    fn authorize(&self, metadata: &MetadataMap, required: Role) -> Result<Principal, AuthError> {
+        let principal = self.state.auth.authenticate(&metadata.clone().into_headers())?;
+        if !principal.role.permits(required) {
+            return Err(AuthError::Forbidden { required, actual: principal.role });
+        }
+        Ok(principal)
+    }
+}
+
+/// Status of a read-only call for a tenant without an engine (never created by reads)
+fn unknown_tenant(principal: &Principal) -> Status {
+    Status::not_found(ApiError::UnknownTenant(principal.tenant.clone()).to_string())
+}
+
+/// gRPC status of an authentication failure (mirrors the REST status codes)
+pub fn auth_status(error: AuthError) -> Status {
+    match error {
+        AuthError::Forbidden { .. } | AuthError::TenantMismatch { .. } => Status::permission_denied(error.to_string()),
+        AuthError::InvalidTenant => Status::invalid_argument(error.to_string()),
+        _ => Status::unauthenticated(error.to_string()),
+    }
+}
+
+/// Protobuf form of an action: its tag and its parameters as JSON
+pub fn to_proto_action(action: &SecurityAction, correlation_ids: Vec<String>) -> proto::Action {
+    let value = serde_json::to_value(action).unwrap_or_default();
+    proto::Action {
+        action_type: value["action_type"].as_str().unwrap_or_default().to_string(),
+        parameters_json: value["parameters"].to_string(),
+        correlation_ids,
+    }
+}
+
+#[tonic::async_trait]
+impl FukurowService for FukurowGrpcService {
+    async fn submit_event(&self, request: Request<proto::SubmitEventRequest>) -> Result<Response<proto::EventReceipt>, Status> {
+        let principal = self.authorize(request.metadata(), Role::Ingest).map_err(auth_status)?;
+        let request = request.into_inner();
+        let event: CyberEvent = serde_json::from_str(&request.event_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid event: {}", e)))?;
+        let source = if request.source.is_empty() { DEFAULT_SOURCE.to_string() } else { request.source };
+
+        let receipt = self.state.ingest_event(&principal, &event, &source, request.event_id.as_deref()).await
+            .map_err(|e| match e {
+                ApiError::InvalidRequest(message) => Status::invalid_argument(format!("Invalid event: {}", message)),
+                e @ ApiError::UnknownTenant(_) => Status::not_found(e.to_string()),
+                e => Status::internal(format!("Failed to submit event: {}", e)),
+            })?;
+        Ok(Response::new(proto::EventReceipt { correlation_id: receipt.correlation_id, duplicate: receipt.duplicate }))
+    }
+
+    async fn reason(&self, request: Request<proto::ReasonRequest>) -> Result<Response<proto::ReasonResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Role::Ingest).map_err(auth_status)?;
+        let profile = match request.get_ref().profile.as_str() {
+            "" => None,
+            profile => Some(profile.parse::<ReasoningProfile>().map_err(Status::invalid_argument)?),
+        };
+
+        let start = Instant::now();
+        let reasoner = self.state.reasoner_for(&principal).map_err(|e| Status::not_found(e.to_string()))?;
+        let correlated = match profile {
+            Some(profile) => reasoner.reason_with_profile(profile).await,
+            None => reasoner.reason_correlated().await,
+        }.map_err(|e| Status::internal(format!("Reasoning failed: {}", e)))?;
+        let execution_time_ms = start.elapsed().as_millis() as u64;
+
+        let mut all_ids: Vec<String> = correlated.iter().flat_map(|c| c.correlation_ids.iter().cloned()).collect();
+        all_ids.sort();
+        all_ids.dedup();
+        // REST の購読者にも同じ結果を配信する
+        self.state.push_hub.publish_to(&principal.tenant, StreamingEvent::ReasoningResult {
+            actions: correlated.iter().map(|c| c.action.clone()).collect(),
+            execution_time_ms,
+            event_count: 0,
+            timestamp: chrono::Utc::now(),
+            correlation_ids: all_ids,
+        });
+
+        let actions = correlated.into_iter().map(|c| to_proto_action(&c.action, c.correlation_ids)).collect();
+        Ok(Response::new(proto::ReasonResponse { actions, execution_time_ms }))
+    }
+
+    async fn query(&self, request: Request<proto::SparqlRequest>) -> Result<Response<proto::SparqlResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Role::ReadOnly).map_err(auth_status)?;
+        let query = request.into_inner().query;
+        let parsed = fukurow_sparql::parser::DefaultSparqlParser.parse(&query)
+            .map_err(|e| Status::invalid_argument(format!("Invalid query: {}", e)))?;
+
+        let graph_store = self.state.existing_reasoner(&principal).ok_or_else(|| unknown_tenant(&principal))?.query_view();
+        let result = fukurow_sparql::execute_query_with_limits(&query, &graph_store, self.state.query_limits)
+            .map(|limited| limited.result)
+            .map_err(|e| match e {
+                fukurow_sparql::SparqlError::LimitExceeded(_) => Status::resource_exhausted(format!("Query failed: {}", e)),
+                _ => Status::internal(format!("Query failed: {}", e)),
+            })?;
+
+        let result = match result {
+            fukurow_sparql::QueryResult::Select { variables, bindings } => {
+                let mut rows: Vec<_> = bindings.iter().map(fukurow_sparql::diff::bindings_to_row).collect();
+                // REST と同じく、ORDER BY がなければ結果が安定するよう並べ替える
+                if parsed.solution_modifier.order.is_none() {
+                    rows.sort();
+                }
+                proto::sparql_response::Result::Select(proto::SelectResult {
+                    variables: variables.into_iter().map(|v| v.0).collect(),
+                    rows: rows.into_iter().map(|row| proto::Row { values: row.into_iter().collect() }).collect(),
+                })
+            }
+            fukurow_sparql::QueryResult::Construct { triples } | fukurow_sparql::QueryResult::Describe { triples } => {
+                proto::sparql_response::Result::Graph(proto::GraphResult {
+                    triples: triples.into_iter()
+                        .map(|t| proto::Triple { subject: t.subject, predicate: t.predicate, object: t.object })
+                        .collect(),
+                })
+            }
+            fukurow_sparql::QueryResult::Ask { result } => proto::sparql_response::Result::Boolean(result),
+        };
+        Ok(Response::new(proto::SparqlResponse { result: Some(result) }))
+    }
+
+    type StreamResultsStream = Pin<Box<dyn Stream<Item = Result<proto::ReasoningResult, Status>> + Send + 'static>>;
+
+    async fn stream_results(&self, request: Request<proto::StreamResultsRequest>) -> Result<Response<Self::StreamResultsStream>, Status> {
+        let principal = self.authorize(request.metadata(), Role::ReadOnly).map_err(auth_status)?;
+        let skip_empty = request.get_ref().skip_empty;
+        let receiver = self.state.push_hub.subscribe_to(&principal.tenant);
+
+        let stream = futures::stream::unfold(receiver, move |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(StreamingEvent::ReasoningResult { actions, execution_time_ms, timestamp, correlation_ids, .. }) => {
+                        if skip_empty && actions.is_empty() {
+                            continue;
+                        }
+                        let result = proto::ReasoningResult {
+                            actions: actions.iter().map(|action| to_proto_action(action, Vec::new())).collect(),
+                            execution_time_ms,
+                            timestamp: timestamp.to_rfc3339(),
+                            correlation_ids,
+                        };
+                        return Some((Ok(result), receiver));
+                    }
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("gRPC result stream subscriber lagged, skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    type ReplicateStream = Pin<Box<dyn Stream<Item = Result<proto::ReplicationFrame, Status>> + Send + 'static>>;
+
+    /// 最初の呼び出しでテナントのストアに変更ログを付ける。エポックは起動時刻 (秒) とし、
+    /// 再起動したリーダーの連番を以前のものと取り違えないようにする
+    async fn replicate(&self, request: Request<proto::ReplicateRequest>) -> Result<Response<Self::ReplicateStream>, Status> {
+        let principal = self.authorize(request.metadata(), Role::Admin).map_err(auth_status)?;
+        let request = request.into_inner();
+        let cursor = match (request.epoch, request.sequence) {
+            (Some(epoch), Some(sequence)) => Some(ReplicationCursor { epoch, sequence }),
+            _ => None,
+        };
+
+        let store = self.state.existing_reasoner(&principal).ok_or_else(|| unknown_tenant(&principal))?.get_graph_store().await;
+        {
+            let mut graph_store = store.write().await;
+            if graph_store.replication().is_none() {
+                let epoch = chrono::Utc::now().timestamp().max(1) as u64;
+                graph_store.attach_replication(ReplicationLog::new(self.replication_backlog).with_epoch(epoch));
+            }
+        }
+
+        let poll_interval = self.replication_poll_interval;
+        let stream = futures::stream::unfold((store, cursor, false), move |(store, cursor, idle): (Arc<RwLock<RdfStore>>, Option<ReplicationCursor>, bool)| async move {
+            if idle {
+                tokio::time::sleep(poll_interval).await;
+            }
+            let message = {
+                let graph_store = store.read().await;
+                // 変更ログが外されたらストリームを終える
+                graph_store.replication()?.catch_up(&graph_store, cursor)
+            };
+            let idle = matches!(message, ReplicationMessage::Heartbeat { .. });
+            let cursor = message.cursor().or(cursor);
+            let frame = serde_json::to_string(&message)
+                .map(|message_json| proto::ReplicationFrame { message_json })
+                .map_err(|e| Status::internal(format!("Failed to encode replication message: {}", e)));
+            Some((frame, (store, cursor, idle)))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_api::{AuthConfig, ReasonerServer, ServerConfig};
+    use futures::StreamExt;
+
+    fn state(config: ServerConfig) -> Arc<AppState> {
+        let monitoring = Arc::new(fukurow_observability::DefaultHealthMonitor::new());
+        Arc::new(ReasonerServer::with_config(config, monitoring).app_state())
+    }
+
+    const EVENT: &str = r#"{"type": "NetworkConnection", "data": {"source_ip": "192.168.1.10", "dest_ip": "10.0.0.50", "port": 443, "protocol": "tcp", "timestamp": 1700000000}}"#;
+
+    #[tokio::test]
+    async fn test_submit_reason_query_and_stream() {
+        let service = FukurowGrpcService::new(state(ServerConfig::default()));
+
+        let receipt = service.submit_event(Request::new(proto::SubmitEventRequest {
+            event_json: EVENT.to_string(),
+            source: String::new(),
+            event_id: Some("evt-1".to_string()),
+        })).await.unwrap().into_inner();
+        assert!(!receipt.duplicate);
+
+        let invalid = service.submit_event(Request::new(proto::SubmitEventRequest {
+            event_json: "{}".to_string(),
+            source: String::new(),
+            event_id: None,
+        })).await.unwrap_err();
+        assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+
+        let mut stream = service.stream_results(Request::new(proto::StreamResultsRequest { skip_empty: false }))
+            .await.unwrap().into_inner();
+        service.reason(Request::new(proto::ReasonRequest { profile: "rdfs".to_string() })).await.unwrap();
+        let streamed = stream.next().await.unwrap().unwrap();
+        assert!(!streamed.timestamp.is_empty());
+
+        let bad_profile = service.reason(Request::new(proto::ReasonRequest { profile: "owl-full".to_string() })).await.unwrap_err();
+        assert_eq!(bad_profile.code(), tonic::Code::InvalidArgument);
+
+        let response = service.query(Request::new(proto::SparqlRequest {
+            query: "ASK { ?event ?p \"10.0.0.50\" }".to_string(),
+        })).await.unwrap().into_inner();
+        assert!(matches!(response.result, Some(proto::sparql_response::Result::Boolean(_))));
+    }
+
+    #[tokio::test]
+    async fn test_metadata_is_authenticated_like_rest_headers() {
+        let auth = AuthConfig::new().with_api_key("reader-key", Principal::new("dashboard", Role::ReadOnly));
+        let service = FukurowGrpcService::new(state(ServerConfig { auth, ..ServerConfig::default() }));
+
+        let missing = service.reason(Request::new(proto::ReasonRequest::default())).await.unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::Unauthenticated);
+
+        let mut request = Request::new(proto::ReasonRequest::default());
+        request.metadata_mut().insert("x-api-key", "reader-key".parse().unwrap());
+        let forbidden = service.reason(request).await.unwrap_err();
+        assert_eq!(forbidden.code(), tonic::Code::PermissionDenied);
+
+        let mut request = Request::new(proto::SparqlRequest { query: "ASK { ?s ?p ?o }".to_string() });
+        request.metadata_mut().insert("x-api-key", "reader-key".parse().unwrap());
+        assert!(service.query(request).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replicate_streams_snapshot_then_mutations() {
+        let service = FukurowGrpcService::new(state(ServerConfig::default()))
+            .with_replication_poll_interval(Duration::from_millis(10));
+        let submit = |event_id: &str| proto::SubmitEventRequest {
+            event_json: EVENT.to_string(),
+            source: "edr".to_string(),
+            event_id: Some(event_id.to_string()),
+        };
+        service.submit_event(Request::new(submit("evt-1"))).await.unwrap();
+
+        let mut frames = service.replicate(Request::new(proto::ReplicateRequest::default())).await.unwrap().into_inner();
+        let mut replica = RdfStore::new();
+        let mut follower = fukurow_store::ReplicationFollower::new();
+        async fn next(frames: &mut <FukurowGrpcService as FukurowService>::ReplicateStream) -> ReplicationMessage {
+            serde_json::from_str(&frames.next().await.unwrap().unwrap().message_json).unwrap()
+        }
+
+        let snapshot = next(&mut frames).await;
+        assert!(matches!(snapshot, ReplicationMessage::Snapshot { sequence: 0, .. }));
+        follower.apply(&mut replica, snapshot).unwrap();
+        assert!(replica.statistics().total_triples > 0);
+        assert!(matches!(next(&mut frames).await, ReplicationMessage::Heartbeat { .. }));
+
+        service.submit_event(Request::new(submit("evt-2"))).await.unwrap();
+        loop {
+            let message = next(&mut frames).await;
+            follower.apply(&mut replica, message).unwrap();
+            if follower.status().lag_records == 0 && follower.status().records_applied > 0 {
+                break;
+            }
+        }
+        let leader = service.state.existing_reasoner(&Principal::anonymous()).unwrap().get_graph_store().await;
+        assert_eq!(replica.statistics().total_triples, leader.read().await.statistics().total_triples);
+    }
+
+    #[test]
+    fn test_action_conversion() {
+        let action = SecurityAction::IsolateHost { host_ip: "10.0.0.5".to_string(), reason: "beaconing".to_string() };
+        let converted = to_proto_action(&action, vec!["corr-1".to_string()]);
+        assert_eq!(converted.action_type, "IsolateHost");
+        let parameters: serde_json::Value = serde_json::from_str(&converted.parameters_json).unwrap();
+        assert_eq!(parameters["host_ip"], "10.0.0.5");
+        assert_eq!(converted.correlation_ids, vec!["corr-1".to_string()]);
+    }
+}
@@ -0,0 +1,412 @@
+//! owl:imports の解決
+//!
+//! ストア内の `owl:imports` が指すオントロジーをリゾルバ (ローカルのファイルカタログ、
+//! または `http-imports` フィーチャの HTTP 取得) で取得し、オントロジー IRI と同名の
+//! 名前付きグラフへ `Provenance::Imported` 付きで読み込む。取り込んだ文書の `owl:imports` も
+//! 再帰的に解決する。循環した import は検出してレポートに記録し、同じオントロジーは 1 度だけ読む
+
+use crate::loader::{DefaultOntologyLoader, OntologyLoader};
+use crate::model::Ontology;
+use crate::OwlError;
+use fukurow_core::model::Triple;
+use fukurow_store::dataset::{read_dataset, DatasetFormat};
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub const OWL_IMPORTS: &str = "http://www.w3.org/2002/07/owl#imports";
+
+/// Document fetched for an imported ontology IRI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedImport {
+    /// Where the document was read from (file path or URL), recorded as its provenance
+    pub location: String,
+    pub format: DatasetFormat,
+    pub content: String,
+}
+
+/// Maps an imported ontology IRI to its document
+pub trait ImportResolver {
+    fn resolve(&self, iri: &str) -> Result<ResolvedImport, OwlError>;
+}
+
+/// Serialization of an imported document from its media type or file extension
+///
+/// Turtle と N-Triples はそれぞれ TriG と N-Quads のサブセットとして読む。判別できなければ Turtle
+pub fn import_format(location: &str, media_type: Option<&str>) -> DatasetFormat {
+    let media_type = media_type.and_then(|m| m.split(';').next()).map(|m| m.trim().to_ascii_lowercase());
+    match media_type.as_deref() {
+        Some("application/n-triples") | Some("application/n-quads") => return DatasetFormat::NQuads,
+        Some("text/turtle") | Some("application/trig") => return DatasetFormat::TriG,
+        _ => {}
+    }
+    let path = location.split(['?', '#']).next().unwrap_or(location);
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("nt") | Some("nq") => DatasetFormat::NQuads,
+        _ => DatasetFormat::TriG,
+    }
+}
+
+/// Local catalog of ontology documents (IRI → file)
+///
+/// カタログファイルは `{"http://example.org/core": "core.ttl"}` 形式の JSON で、
+/// 相対パスはカタログファイルのディレクトリを基準にする
+#[derive(Default)]
+pub struct FileCatalogResolver {
+    entries: HashMap<String, PathBuf>,
+    fallback: Option<Box<dyn ImportResolver + Send + Sync>>,
+}
+
+impl FileCatalogResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a JSON catalog file
+    pub fn from_catalog_file(path: impl AsRef<Path>) -> Result<Self, OwlError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| OwlError::LoaderError(format!("cannot read catalog {}: {}", path.display(), e)))?;
+        let entries: BTreeMap<String, PathBuf> = serde_json::from_str(&text)
+            .map_err(|e| OwlError::LoaderError(format!("invalid catalog {}: {}", path.display(), e)))?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        Ok(entries.into_iter().fold(Self::new(), |catalog, (iri, file)| catalog.with_entry(iri, base.join(file))))
+    }
+
+    pub fn with_entry(mut self, iri: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.entries.insert(iri.into(), path.into());
+        self
+    }
+
+    /// Resolver for IRIs missing from the catalog (e.g. [`HttpImportResolver`])
+    pub fn with_fallback(mut self, fallback: impl ImportResolver + Send + Sync + 'static) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+}
+
+impl ImportResolver for FileCatalogResolver {
+    fn resolve(&self, iri: &str) -> Result<ResolvedImport, OwlError> {
+        let path = match self.entries.get(iri) {
+            Some(path) => path,
+            None => match &self.fallback {
+                Some(fallback) => return fallback.resolve(iri),
+                None => return Err(OwlError::ImportError { iri: iri.to_string(), message: "not in the catalog".to_string() }),
+            },
+        };
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| OwlError::ImportError { iri: iri.to_string(), message: format!("{}: {}", path.display(), e) })?;
+        let location = path.display().to_string();
+        Ok(ResolvedImport { format: import_format(&location, None), location, content })
+    }
+}
+
+/// Fetches imports over HTTP(S), caching documents in memory and optionally on disk
+///
+/// ブロッキングクライアントを使うため、非同期ランタイム上では `spawn_blocking` 内で呼ぶ
+#[cfg(feature = "http-imports")]
+pub struct HttpImportResolver {
+    client: reqwest::blocking::Client,
+    cache: std::sync::Mutex<HashMap<String, ResolvedImport>>,
+    cache_dir: Option<PathBuf>,
+}
+
+#[cfg(feature = "http-imports")]
+impl HttpImportResolver {
+    pub fn new(client: reqwest::blocking::Client) -> Self {
+        Self { client, cache: std::sync::Mutex::new(HashMap::new()), cache_dir: None }
+    }
+
+    /// Keep fetched documents in `dir` and reuse them across runs
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    fn cache_path(&self, iri: &str, format: DatasetFormat) -> Option<PathBuf> {
+        let name: String = iri.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect();
+        let extension = match format {
+            DatasetFormat::NQuads => "nq",
+            DatasetFormat::TriG => "ttl",
+        };
+        self.cache_dir.as_ref().map(|dir| dir.join(format!("{}.{}", name, extension)))
+    }
+
+    fn read_cached(&self, iri: &str) -> Option<ResolvedImport> {
+        if let Some(hit) = self.cache.lock().ok()?.get(iri) {
+            return Some(hit.clone());
+        }
+        [DatasetFormat::TriG, DatasetFormat::NQuads].into_iter().find_map(|format| {
+            let path = self.cache_path(iri, format)?;
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some(ResolvedImport { location: iri.to_string(), format, content })
+        })
+    }
+
+    fn fetch(&self, iri: &str) -> Result<ResolvedImport, OwlError> {
+        let error = |message: String| OwlError::ImportError { iri: iri.to_string(), message };
+        let response = self.client.get(iri)
+            .header(reqwest::header::ACCEPT, "text/turtle, application/trig;q=0.9, application/n-triples;q=0.8, application/n-quads;q=0.8")
+            .send()
+            .map_err(|e| error(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(error(format!("{} returned {}", iri, status)));
+        }
+        let media_type = response.headers().get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let location = response.url().to_string();
+        let content = response.text().map_err(|e| error(e.to_string()))?;
+        Ok(ResolvedImport { format: import_format(&location, media_type.as_deref()), location, content })
+    }
+}
+
+#[cfg(feature = "http-imports")]
+impl ImportResolver for HttpImportResolver {
+    fn resolve(&self, iri: &str) -> Result<ResolvedImport, OwlError> {
+        if !(iri.starts_with("http://") || iri.starts_with("https://")) {
+            return Err(OwlError::ImportError { iri: iri.to_string(), message: "not an HTTP(S) IRI".to_string() });
+        }
+        if let Some(cached) = self.read_cached(iri) {
+            return Ok(cached);
+        }
+        let resolved = self.fetch(iri)?;
+        if let Some(path) = self.cache_path(iri, resolved.format) {
+            // ディスクキャッシュへの書き込み失敗は取り込み自体を失敗させない
+            let _ = std::fs::create_dir_all(path.parent().unwrap_or_else(|| Path::new(".")))
+                .and_then(|_| std::fs::write(&path, &resolved.content));
+        }
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(iri.to_string(), resolved.clone());
+        }
+        Ok(resolved)
+    }
+}
+
+/// Ontology document loaded for an `owl:imports`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedOntology {
+    pub iri: String,
+    /// Ontology whose `owl:imports` pulled this one in
+    pub imported_by: String,
+    pub location: String,
+    pub triples: usize,
+}
+
+/// Outcome of [`DefaultOntologyLoader::resolve_imports`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: Vec<ImportedOntology>,
+    /// Imports whose graph was already in the store
+    pub already_loaded: Vec<String>,
+    /// Import chains that lead back to an ontology being imported, e.g. `[a, b, a]`
+    pub cycles: Vec<Vec<String>>,
+}
+
+/// Ontology IRIs imported by `triples`, as `(importing ontology, imported ontology)`
+fn imports_in<'a>(triples: impl IntoIterator<Item = &'a Triple>) -> Vec<(String, String)> {
+    triples.into_iter()
+        .filter(|triple| triple.predicate_term().as_iri() == Some(OWL_IMPORTS))
+        .filter_map(|triple| {
+            let imported = triple.object_term().as_iri()?.to_string();
+            Some((triple.subject_term().value().to_string(), imported))
+        })
+        .collect()
+}
+
+/// State of one [`DefaultOntologyLoader::resolve_imports`] run
+struct ImportRun {
+    /// Import chain being resolved, from a root ontology
+    path: Vec<String>,
+    visited: HashSet<String>,
+    imported_at: u64,
+    report: ImportReport,
+}
+
+impl DefaultOntologyLoader {
+    /// Resolve every `owl:imports` in the store, recursively, into per-ontology named graphs
+    ///
+    /// 取り込み済みのグラフは読み直さず、その中の `owl:imports` だけをたどる。
+    /// 解決に失敗した import はエラーにし、それまでに読み込んだグラフは残す
+    pub fn resolve_imports(&self, store: &mut RdfStore, resolver: &dyn ImportResolver) -> Result<ImportReport, OwlError> {
+        // 取り込みで作ったグラフ (グラフ名 = オントロジー IRI) 以外にある import が起点
+        let mut roots: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (graph_id, triples) in store.all_triples() {
+            for (importer, iri) in imports_in(&triples.iter().map(|stored| stored.triple.to_triple()).collect::<Vec<_>>()) {
+                if !matches!(graph_id, GraphId::Named(name) if *name == importer) {
+                    roots.entry(importer).or_default().push(iri);
+                }
+            }
+        }
+
+        let mut run = ImportRun {
+            path: Vec::new(),
+            // 起点のオントロジーはストアにあるので取得しない
+            visited: roots.keys().cloned().collect(),
+            imported_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            report: ImportReport::default(),
+        };
+        for (importer, iris) in roots {
+            run.path = vec![importer.clone()];
+            for iri in iris {
+                self.import(store, resolver, &importer, &iri, &mut run)?;
+            }
+        }
+        Ok(run.report)
+    }
+
+    /// [`resolve_imports`](Self::resolve_imports), then load the store including the imported graphs
+    pub fn load_with_imports(&self, store: &mut RdfStore, resolver: &dyn ImportResolver) -> Result<(Ontology, ImportReport), OwlError> {
+        let report = self.resolve_imports(store, resolver)?;
+        Ok((self.load_from_store(store)?, report))
+    }
+
+    fn import(&self, store: &mut RdfStore, resolver: &dyn ImportResolver, importer: &str, iri: &str, run: &mut ImportRun) -> Result<(), OwlError> {
+        if let Some(start) = run.path.iter().position(|ontology| ontology == iri) {
+            let mut cycle = run.path[start..].to_vec();
+            cycle.push(iri.to_string());
+            run.report.cycles.push(cycle);
+            return Ok(());
+        }
+        if !run.visited.insert(iri.to_string()) {
+            return Ok(());
+        }
+
+        let graph_id = GraphId::Named(iri.to_string());
+        let triples: Vec<Triple> = if store.get_graph(&graph_id).is_empty() {
+            let resolved = resolver.resolve(iri)?;
+            let parsed = read_dataset(resolved.content.as_bytes(), resolved.format, &resolved.location)
+                .map_err(|e| OwlError::ImportError { iri: iri.to_string(), message: e.to_string() })?;
+            let provenance = Provenance::Imported { source_uri: resolved.location.clone(), imported_at: run.imported_at };
+            let inserted = store.insert_document(parsed.into_iter().map(|stored| stored.triple.into()).collect(), graph_id, provenance);
+            run.report.imported.push(ImportedOntology {
+                iri: iri.to_string(),
+                imported_by: importer.to_string(),
+                location: resolved.location,
+                triples: inserted.len(),
+            });
+            inserted
+        } else {
+            run.report.already_loaded.push(iri.to_string());
+            store.get_graph(&graph_id).into_iter().map(|stored| stored.triple.to_triple()).collect()
+        };
+
+        run.path.push(iri.to_string());
+        for (_, nested) in imports_in(&triples) {
+            self.import(store, resolver, iri, &nested, run)?;
+        }
+        run.path.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Class, OwlIri};
+
+    const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+    const OWL_CLASS: &str = "http://www.w3.org/2002/07/owl#Class";
+
+    /// In-memory documents keyed by IRI, counting resolutions
+    struct StaticResolver {
+        documents: HashMap<String, String>,
+        calls: std::cell::RefCell<Vec<String>>,
+    }
+
+    impl StaticResolver {
+        fn new(documents: &[(&str, &str)]) -> Self {
+            Self {
+                documents: documents.iter().map(|(iri, doc)| (iri.to_string(), doc.to_string())).collect(),
+                calls: std::cell::RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl ImportResolver for StaticResolver {
+        fn resolve(&self, iri: &str) -> Result<ResolvedImport, OwlError> {
+            self.calls.borrow_mut().push(iri.to_string());
+            let content = self.documents.get(iri)
+                .ok_or_else(|| OwlError::ImportError { iri: iri.to_string(), message: "unknown".to_string() })?;
+            Ok(ResolvedImport { location: format!("{}.nt", iri), format: DatasetFormat::NQuads, content: content.clone() })
+        }
+    }
+
+    fn root_store(imports: &str) -> RdfStore {
+        let mut store = RdfStore::new();
+        store.insert(Triple {
+            subject: "http://example.org/app".to_string(),
+            predicate: OWL_IMPORTS.to_string(),
+            object: imports.to_string(),
+        }, GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        store
+    }
+
+    #[test]
+    fn test_recursive_imports_with_cycle() {
+        let resolver = StaticResolver::new(&[
+            ("http://example.org/security", concat!(
+                "<http://example.org/security> <http://www.w3.org/2002/07/owl#imports> <http://example.org/core> .\n",
+                "<http://example.org/Malware> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#Class> .\n",
+            )),
+            ("http://example.org/core", concat!(
+                "<http://example.org/core> <http://www.w3.org/2002/07/owl#imports> <http://example.org/security> .\n",
+                "<http://example.org/core> <http://www.w3.org/2002/07/owl#imports> <http://example.org/app> .\n",
+                "<http://example.org/Asset> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#Class> .\n",
+            )),
+        ]);
+        let mut store = root_store("http://example.org/security");
+
+        let (ontology, report) = DefaultOntologyLoader.load_with_imports(&mut store, &resolver).unwrap();
+        let imported: Vec<(&str, &str)> = report.imported.iter().map(|i| (i.iri.as_str(), i.imported_by.as_str())).collect();
+        assert_eq!(imported, vec![
+            ("http://example.org/security", "http://example.org/app"),
+            ("http://example.org/core", "http://example.org/security"),
+        ]);
+        let cycles: Vec<String> = report.cycles.iter().map(|cycle| cycle.join(" -> ")).collect();
+        assert_eq!(cycles, vec![
+            "http://example.org/security -> http://example.org/core -> http://example.org/security",
+            "http://example.org/app -> http://example.org/security -> http://example.org/core -> http://example.org/app",
+        ]);
+        assert_eq!(resolver.calls.borrow().len(), 2);
+
+        assert!(ontology.classes.contains(&Class::Named(OwlIri::new("http://example.org/Asset".to_string()))));
+        let core = store.get_graph(&GraphId::Named("http://example.org/core".to_string()));
+        assert_eq!(core.len(), 3);
+        assert!(core.iter().all(|stored| matches!(&stored.provenance,
+            Provenance::Imported { source_uri, .. } if source_uri == "http://example.org/core.nt")));
+
+        // 2 回目は読み込み済みのグラフを使い、再取得しない
+        let report = DefaultOntologyLoader.resolve_imports(&mut store, &resolver).unwrap();
+        assert!(report.imported.is_empty());
+        assert_eq!(report.already_loaded, vec!["http://example.org/security".to_string(), "http://example.org/core".to_string()]);
+        assert_eq!(resolver.calls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_file_catalog_and_unresolvable_import() {
+        let dir = std::env::temp_dir().join(format!("fukurow-imports-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("core.ttl"), format!("<http://example.org/Asset> <{}> <{}> .\n", RDF_TYPE, OWL_CLASS)).unwrap();
+        std::fs::write(dir.join("catalog.json"), r#"{"http://example.org/core": "core.ttl"}"#).unwrap();
+
+        let catalog = FileCatalogResolver::from_catalog_file(dir.join("catalog.json")).unwrap();
+        let resolved = catalog.resolve("http://example.org/core").unwrap();
+        assert_eq!(resolved.format, DatasetFormat::TriG);
+        let mut store = root_store("http://example.org/core");
+        let report = DefaultOntologyLoader.resolve_imports(&mut store, &catalog).unwrap();
+        assert_eq!(report.imported[0].triples, 1);
+
+        let mut store = root_store("http://example.org/missing");
+        assert!(matches!(DefaultOntologyLoader.resolve_imports(&mut store, &catalog), Err(OwlError::ImportError { .. })));
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(import_format("http://example.org/core", Some("application/n-triples; charset=utf-8")), DatasetFormat::NQuads);
+        assert_eq!(import_format("core.nt?v=2", None), DatasetFormat::NQuads);
+    }
+}
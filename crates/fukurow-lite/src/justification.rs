@@ -0,0 +1,80 @@
+//! Consistency explanations (justifications)
+//!
+//! 不整合なオントロジーから、それだけで不整合になる極小の公理集合を求める。
+//! 推論器を整合性判定のブラックボックスとして使い、公理を窓単位で取り除いて縮める
+//! (窓は半分ずつ小さくし、最後に 1 件ずつ確認するため結果は極小になる)
+
+use serde::{Deserialize, Serialize};
+
+/// Result of a consistency check with the axioms responsible for an inconsistency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyReport<A> {
+    pub consistent: bool,
+    /// Minimal inconsistent subset of the axioms (empty when consistent)
+    ///
+    /// 取り除くとどれも整合になる。不整合の原因が複数あるときはそのうちの 1 つ
+    pub justification: Vec<A>,
+    /// Axioms of the checked ontology
+    pub axioms: usize,
+    /// Consistency checks run, including the initial one
+    pub checks: usize,
+}
+
+impl<A: Clone> ConsistencyReport<A> {
+    /// Check `axioms` with `is_consistent` and shrink them to a justification if inconsistent
+    pub fn compute<E>(axioms: &[A], mut is_consistent: impl FnMut(&[A]) -> Result<bool, E>) -> Result<Self, E> {
+        if is_consistent(axioms)? {
+            return Ok(Self { consistent: true, justification: Vec::new(), axioms: axioms.len(), checks: 1 });
+        }
+        let (justification, checks) = shrink(axioms.to_vec(), &mut is_consistent)?;
+        Ok(Self { consistent: false, justification, axioms: axioms.len(), checks: checks + 1 })
+    }
+}
+
+/// Remove axioms from an inconsistent set while it stays inconsistent
+fn shrink<A: Clone, E>(mut kept: Vec<A>, is_consistent: &mut impl FnMut(&[A]) -> Result<bool, E>) -> Result<(Vec<A>, usize), E> {
+    let mut checks = 0;
+    let mut window = (kept.len() / 2).max(1);
+    loop {
+        let mut start = 0;
+        while start < kept.len() {
+            let end = (start + window).min(kept.len());
+            let candidate: Vec<A> = kept[..start].iter().chain(&kept[end..]).cloned().collect();
+            checks += 1;
+            if is_consistent(&candidate)? {
+                // 窓の中に必要な公理がある
+                start = end;
+            } else {
+                kept = candidate;
+            }
+        }
+        if window == 1 {
+            return Ok((kept, checks));
+        }
+        window /= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Inconsistent when it contains both `n` and `-n`
+    fn contradicts(numbers: &[i32]) -> Result<bool, ()> {
+        Ok(!numbers.iter().any(|n| numbers.contains(&-n)))
+    }
+
+    #[test]
+    fn test_shrinks_to_a_minimal_justification() {
+        let axioms: Vec<i32> = (1..=40).chain([-17]).collect();
+        let report = ConsistencyReport::compute(&axioms, contradicts).unwrap();
+        assert!(!report.consistent);
+        assert_eq!(report.justification, vec![17, -17]);
+        assert_eq!(report.axioms, 41);
+        assert!(report.checks < axioms.len());
+
+        let report = ConsistencyReport::compute(&[1, 2, 3], contradicts).unwrap();
+        assert!(report.consistent && report.justification.is_empty());
+        assert_eq!(report.checks, 1);
+    }
+}
@@ -5,15 +5,25 @@
 //! - 整合性検証
 //! - クラス階層推論
 //! - インスタンス検証
+//! - owl:imports の再帰的な解決 (ファイルカタログ / HTTP)
+//! - 不整合の原因となる極小の公理集合 (justification) の抽出
 
 pub mod model;
 pub mod tableau;
 pub mod reasoner;
 pub mod loader;
+pub mod properties;
+pub mod imports;
+pub mod justification;
 
 pub use model::{Ontology, Class, Property, Individual, Axiom};
 pub use reasoner::OwlLiteReasoner;
 pub use loader::OntologyLoader;
+pub use properties::{FunctionalViolation, PropertyCharacteristics};
+pub use imports::{ImportResolver, ResolvedImport, FileCatalogResolver, ImportReport, ImportedOntology};
+pub use justification::ConsistencyReport;
+#[cfg(feature = "http-imports")]
+pub use imports::HttpImportResolver;
 
 // Re-export store types for WASM integration
 pub use fukurow_store::store::RdfStore;
@@ -36,6 +46,9 @@ pub enum OwlError {
 
     #[error("Unsupported feature: {0}")]
     UnsupportedFeature(String),
+
+    #[error("Import error: {iri}: {message}")]
+    ImportError { iri: String, message: String },
 }
 
 #[cfg(test)]
@@ -35,80 +35,98 @@ impl OntologyLoader for DefaultOntologyLoader {
         let owl_inverse_functional_property = "http://www.w3.org/2002/07/owl#InverseFunctionalProperty";
         let owl_transitive_property = "http://www.w3.org/2002/07/owl#TransitiveProperty";
         let owl_symmetric_property = "http://www.w3.org/2002/07/owl#SymmetricProperty";
+        let owl_inverse_of = "http://www.w3.org/2002/07/owl#inverseOf";
 
-        // Process all triples to extract OWL axioms
-        for stored_triple in store.all_triples().values().flatten() {
-            let triple = &stored_triple.triple;
+        // ストアの走査順は不定なので、宣言 → プロパティ特性 → その他の公理の順に3回走査する
+        // (特性やアサーションの解釈に、どこで宣言されたかに依らずプロパティの種類が必要なため)
+        let triples: Vec<_> = store.all_triples().values().flatten().map(|stored| &stored.triple).collect();
 
-            // rdf:type declarations
+        // Pass 1: declarations
+        for triple in &triples {
+            if triple.predicate != rdf_type {
+                continue;
+            }
+            match triple.object.as_str() {
+                x if x == owl_class => {
+                    ontology.classes.insert(Class::Named(OwlIri::new(triple.subject.to_string())));
+                }
+                x if x == owl_object_property => {
+                    ontology.properties.insert(Property::Object(OwlIri::new(triple.subject.to_string())));
+                }
+                x if x == owl_datatype_property => {
+                    ontology.properties.insert(Property::Data(OwlIri::new(triple.subject.to_string())));
+                }
+                x if x == owl_named_individual => {
+                    ontology.individuals.insert(Individual(OwlIri::new(triple.subject.to_string())));
+                }
+                _ => {}
+            }
+        }
+
+        // Pass 2: property characteristics (推移・対称・逆・逆関数はオブジェクトプロパティを含意する)
+        for triple in &triples {
+            if triple.predicate == owl_inverse_of {
+                let p1 = self.object_property(&ontology, &triple.subject);
+                let p2 = self.object_property(&ontology, &triple.object);
+                ontology.add_axiom(Axiom::InverseObjectProperties(p1, p2));
+                continue;
+            }
+            if triple.predicate != rdf_type {
+                continue;
+            }
+            match triple.object.as_str() {
+                x if x == owl_functional_property => {
+                    // データプロパティとして宣言されていなければオブジェクトプロパティとみなす
+                    let prop = self.find_property_by_iri(&ontology, &triple.subject)
+                        .unwrap_or_else(|| Property::Object(OwlIri::new(triple.subject.to_string())));
+                    ontology.add_axiom(Axiom::FunctionalProperty(prop));
+                }
+                x if x == owl_inverse_functional_property => {
+                    let prop = self.object_property(&ontology, &triple.subject);
+                    ontology.add_axiom(Axiom::InverseFunctionalProperty(prop));
+                }
+                x if x == owl_transitive_property => {
+                    let prop = self.object_property(&ontology, &triple.subject);
+                    ontology.add_axiom(Axiom::TransitiveProperty(prop));
+                }
+                x if x == owl_symmetric_property => {
+                    let prop = self.object_property(&ontology, &triple.subject);
+                    ontology.add_axiom(Axiom::SymmetricProperty(prop));
+                }
+                _ => {}
+            }
+        }
+
+        // Pass 3: class axioms and assertions
+        for triple in &triples {
+            // rdf:type (宣言と特性は処理済み。OWL 語彙以外のクラスへの型付けはクラスアサーション)
             if triple.predicate == rdf_type {
-                match triple.object.as_str() {
-                    x if x == owl_class => {
-                        let class = Class::Named(OwlIri::new(triple.subject.clone()));
-                        ontology.classes.insert(class);
-                    }
-                    x if x == owl_object_property => {
-                        let prop = Property::Object(OwlIri::new(triple.subject.clone()));
-                        ontology.properties.insert(prop);
-                    }
-                    x if x == owl_datatype_property => {
-                        let prop = Property::Data(OwlIri::new(triple.subject.clone()));
-                        ontology.properties.insert(prop);
-                    }
-                    x if x == owl_named_individual => {
-                        let individual = Individual(OwlIri::new(triple.subject.clone()));
-                        ontology.individuals.insert(individual);
-                    }
-                    x if x == owl_functional_property => {
-                        if let Some(prop) = self.find_property_by_iri(&ontology, &triple.subject) {
-                            ontology.add_axiom(Axiom::FunctionalProperty(prop));
-                        }
-                    }
-                    x if x == owl_inverse_functional_property => {
-                        if let Some(prop) = self.find_property_by_iri(&ontology, &triple.subject) {
-                            ontology.add_axiom(Axiom::InverseFunctionalProperty(prop));
-                        }
-                    }
-                    x if x == owl_transitive_property => {
-                        if let Some(prop) = self.find_property_by_iri(&ontology, &triple.subject) {
-                            ontology.add_axiom(Axiom::TransitiveProperty(prop));
-                        }
-                    }
-                    x if x == owl_symmetric_property => {
-                        if let Some(prop) = self.find_property_by_iri(&ontology, &triple.subject) {
-                            ontology.add_axiom(Axiom::SymmetricProperty(prop));
-                        }
-                    }
-                    _ => {
-                        // Check if object is a class IRI (not a built-in OWL class) - Class assertion
-                        if !triple.object.starts_with("http://www.w3.org/2002/07/owl#") {
-                            let class = Class::Named(OwlIri::new(triple.object.clone()));
-                            let individual = Individual(OwlIri::new(triple.subject.clone()));
-                            ontology.add_axiom(Axiom::ClassAssertion(class, individual));
-                        }
-                    }
+                if !triple.object.starts_with("http://www.w3.org/2002/07/owl#") {
+                    let class = Class::Named(OwlIri::new(triple.object.to_string()));
+                    let individual = Individual(OwlIri::new(triple.subject.to_string()));
+                    ontology.add_axiom(Axiom::ClassAssertion(class, individual));
                 }
             }
 
             // rdfs:subClassOf
             else if triple.predicate == rdfs_subclass_of {
-                let c1 = Class::Named(OwlIri::new(triple.subject.clone()));
-                let c2 = Class::Named(OwlIri::new(triple.object.clone()));
+                let c1 = Class::Named(OwlIri::new(triple.subject.to_string()));
+                let c2 = Class::Named(OwlIri::new(triple.object.to_string()));
                 ontology.add_axiom(Axiom::SubClassOf(c1, c2));
             }
 
             // owl:subClassOf
             else if triple.predicate == owl_subclass_of {
-                let c1 = Class::Named(OwlIri::new(triple.subject.clone()));
-                let c2 = Class::Named(OwlIri::new(triple.object.clone()));
+                let c1 = Class::Named(OwlIri::new(triple.subject.to_string()));
+                let c2 = Class::Named(OwlIri::new(triple.object.to_string()));
                 ontology.add_axiom(Axiom::SubClassOf(c1, c2));
             }
 
             // owl:equivalentClass
             else if triple.predicate == owl_equivalent_class {
                 // For simplicity, treat as SubClassOf in both directions
-                let c1 = Class::Named(OwlIri::new(triple.subject.clone()));
-                let c2 = Class::Named(OwlIri::new(triple.object.clone()));
+                let c1 = Class::Named(OwlIri::new(triple.subject.to_string()));
+                let c2 = Class::Named(OwlIri::new(triple.object.to_string()));
                 ontology.add_axiom(Axiom::SubClassOf(c1.clone(), c2.clone()));
                 ontology.add_axiom(Axiom::SubClassOf(c2, c1));
             }
@@ -117,7 +135,7 @@ impl OntologyLoader for DefaultOntologyLoader {
             else if triple.predicate == rdfs_domain {
                 if let Some(prop) = self.find_property_by_iri(&ontology, &triple.subject) {
                     if let Property::Object(_) = prop {
-                        let class = Class::Named(OwlIri::new(triple.object.clone()));
+                        let class = Class::Named(OwlIri::new(triple.object.to_string()));
                         ontology.add_axiom(Axiom::ObjectPropertyDomain(prop, class));
                     }
                 }
@@ -127,7 +145,7 @@ impl OntologyLoader for DefaultOntologyLoader {
             else if triple.predicate == rdfs_range {
                 if let Some(prop) = self.find_property_by_iri(&ontology, &triple.subject) {
                     if let Property::Object(_) = prop {
-                        let class = Class::Named(OwlIri::new(triple.object.clone()));
+                        let class = Class::Named(OwlIri::new(triple.object.to_string()));
                         ontology.add_axiom(Axiom::ObjectPropertyRange(prop, class));
                     }
                 }
@@ -139,8 +157,8 @@ impl OntologyLoader for DefaultOntologyLoader {
                 // Check if predicate is an object property
                 if let Some(prop) = self.find_property_by_iri(&ontology, &triple.predicate) {
                     if let Property::Object(_) = prop {
-                        let i1 = Individual(OwlIri::new(triple.subject.clone()));
-                        let i2 = Individual(OwlIri::new(triple.object.clone()));
+                        let i1 = Individual(OwlIri::new(triple.subject.to_string()));
+                        let i2 = Individual(OwlIri::new(triple.object.to_string()));
                         ontology.add_axiom(Axiom::ObjectPropertyAssertion(prop, i1, i2));
                     }
                 }
@@ -152,6 +170,14 @@ impl OntologyLoader for DefaultOntologyLoader {
 }
 
 impl DefaultOntologyLoader {
+    /// Declared property, or an object property when the IRI was not declared
+    fn object_property(&self, ontology: &Ontology, iri: &str) -> Property {
+        match self.find_property_by_iri(ontology, iri) {
+            Some(prop @ Property::Object(_)) => prop,
+            _ => Property::Object(OwlIri::new(iri.to_string())),
+        }
+    }
+
     fn find_property_by_iri(&self, ontology: &Ontology, iri: &str) -> Option<Property> {
         for prop in &ontology.properties {
             match prop {
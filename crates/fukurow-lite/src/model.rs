@@ -83,6 +83,9 @@ pub enum Axiom {
     /// SymmetricProperty(P)
     SymmetricProperty(Property),
 
+    /// InverseObjectProperties(P1 P2)
+    InverseObjectProperties(Property, Property),
+
     /// SameIndividual(i1 ... in)
     SameIndividual(Vec<Individual>),
 
@@ -173,6 +176,10 @@ impl Ontology {
             Axiom::SymmetricProperty(p) => {
                 self.properties.insert(p.clone());
             }
+            Axiom::InverseObjectProperties(p1, p2) => {
+                self.properties.insert(p1.clone());
+                self.properties.insert(p2.clone());
+            }
             Axiom::SameIndividual(individuals) => {
                 self.individuals.extend(individuals.iter().cloned());
             }
@@ -0,0 +1,265 @@
+//! プロパティ特性による推論
+//!
+//! オブジェクトプロパティアサーションに対して以下を不動点まで適用する:
+//! - owl:SymmetricProperty: P(a, b) → P(b, a)
+//! - owl:inverseOf: P(a, b) → Q(b, a) (Q が P の逆)
+//! - owl:TransitiveProperty: P(a, b) ∧ P(b, c) → P(a, c)
+//!
+//! owl:FunctionalProperty / owl:InverseFunctionalProperty は推論結果を含めて検査し、
+//! 同一と宣言されていない複数の値を持つ個体を矛盾として報告する (一意名仮定)
+
+use crate::model::{Axiom, Individual, Ontology, Property};
+use std::collections::{HashMap, HashSet};
+
+/// Object property assertion `P(subject, object)`
+pub type PropertyAssertion = (Property, Individual, Individual);
+
+/// Property axioms of an ontology, indexed by property
+#[derive(Debug, Clone, Default)]
+pub struct PropertyCharacteristics {
+    pub transitive: HashSet<Property>,
+    pub symmetric: HashSet<Property>,
+    pub functional: HashSet<Property>,
+    pub inverse_functional: HashSet<Property>,
+    /// Inverses of each property (both directions are recorded)
+    pub inverses: HashMap<Property, HashSet<Property>>,
+}
+
+impl PropertyCharacteristics {
+    pub fn from_ontology(ontology: &Ontology) -> Self {
+        let mut characteristics = Self::default();
+        for axiom in &ontology.axioms {
+            match axiom {
+                Axiom::TransitiveProperty(p) => {
+                    characteristics.transitive.insert(p.clone());
+                }
+                Axiom::SymmetricProperty(p) => {
+                    characteristics.symmetric.insert(p.clone());
+                }
+                Axiom::FunctionalProperty(p) => {
+                    characteristics.functional.insert(p.clone());
+                }
+                Axiom::InverseFunctionalProperty(p) => {
+                    characteristics.inverse_functional.insert(p.clone());
+                }
+                Axiom::InverseObjectProperties(p1, p2) => {
+                    characteristics.inverses.entry(p1.clone()).or_default().insert(p2.clone());
+                    characteristics.inverses.entry(p2.clone()).or_default().insert(p1.clone());
+                }
+                _ => {}
+            }
+        }
+        characteristics
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitive.is_empty() && self.symmetric.is_empty() && self.inverses.is_empty()
+            && self.functional.is_empty() && self.inverse_functional.is_empty()
+    }
+}
+
+/// Individual with more than one distinct value for a functional property
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionalViolation {
+    pub property: Property,
+    /// Subject (or object, for inverse functional properties) with conflicting values
+    pub individual: Individual,
+    /// Conflicting values, sorted
+    pub values: Vec<Individual>,
+    /// Violation of owl:InverseFunctionalProperty
+    pub inverse: bool,
+}
+
+impl std::fmt::Display for FunctionalViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.inverse { "inverse functional" } else { "functional" };
+        let property = match &self.property {
+            Property::Object(iri) | Property::Data(iri) => iri,
+        };
+        let values: Vec<&str> = self.values.iter().map(|value| value.0.as_str()).collect();
+        write!(f, "{} property {} has multiple values for {}: {}", kind, property, self.individual.0, values.join(", "))
+    }
+}
+
+/// Asserted object property assertions closed under the property characteristics
+pub fn property_closure(ontology: &Ontology) -> HashSet<PropertyAssertion> {
+    let characteristics = PropertyCharacteristics::from_ontology(ontology);
+    let mut closure = AssertionIndex::default();
+    let mut queue = Vec::new();
+    for axiom in &ontology.axioms {
+        if let Axiom::ObjectPropertyAssertion(p, a, b) = axiom {
+            let assertion = (p.clone(), a.clone(), b.clone());
+            if closure.insert(&assertion) {
+                queue.push(assertion);
+            }
+        }
+    }
+
+    while let Some((p, a, b)) = queue.pop() {
+        let mut derived = Vec::new();
+        if characteristics.symmetric.contains(&p) {
+            derived.push((p.clone(), b.clone(), a.clone()));
+        }
+        if let Some(inverses) = characteristics.inverses.get(&p) {
+            derived.extend(inverses.iter().map(|q| (q.clone(), b.clone(), a.clone())));
+        }
+        if characteristics.transitive.contains(&p) {
+            // a → b → c と z → a → b の両方向に連鎖をつなぐ
+            derived.extend(closure.objects(&p, &b).map(|c| (p.clone(), a.clone(), c)));
+            derived.extend(closure.subjects(&p, &a).map(|z| (p.clone(), z, b.clone())));
+        }
+
+        for assertion in derived {
+            if closure.insert(&assertion) {
+                queue.push(assertion);
+            }
+        }
+    }
+
+    closure.assertions
+}
+
+/// Property assertions entailed by the characteristics but not asserted
+pub fn entailed_property_assertions(ontology: &Ontology) -> Vec<PropertyAssertion> {
+    let asserted: HashSet<PropertyAssertion> = ontology.axioms.iter()
+        .filter_map(|axiom| match axiom {
+            Axiom::ObjectPropertyAssertion(p, a, b) => Some((p.clone(), a.clone(), b.clone())),
+            _ => None,
+        })
+        .collect();
+    let mut entailed: Vec<PropertyAssertion> = property_closure(ontology).into_iter()
+        .filter(|assertion| !asserted.contains(assertion))
+        .collect();
+    entailed.sort_by(|x, y| assertion_key(x).cmp(&assertion_key(y)));
+    entailed
+}
+
+/// Functional and inverse functional property violations, including entailed assertions
+pub fn functional_violations(ontology: &Ontology) -> Vec<FunctionalViolation> {
+    let characteristics = PropertyCharacteristics::from_ontology(ontology);
+    if characteristics.functional.is_empty() && characteristics.inverse_functional.is_empty() {
+        return Vec::new();
+    }
+    let same_as: Vec<&Vec<Individual>> = ontology.axioms.iter()
+        .filter_map(|axiom| match axiom {
+            Axiom::SameIndividual(individuals) => Some(individuals),
+            _ => None,
+        })
+        .collect();
+
+    let mut values: HashMap<(Property, Individual, bool), HashSet<Individual>> = HashMap::new();
+    for (p, a, b) in property_closure(ontology) {
+        if characteristics.functional.contains(&p) {
+            values.entry((p.clone(), a.clone(), false)).or_default().insert(b.clone());
+        }
+        if characteristics.inverse_functional.contains(&p) {
+            values.entry((p, b, true)).or_default().insert(a);
+        }
+    }
+
+    let mut violations: Vec<FunctionalViolation> = values.into_iter()
+        .filter(|(_, values)| values.len() > 1 && !same_as.iter().any(|group| values.iter().all(|value| group.contains(value))))
+        .map(|((property, individual, inverse), values)| {
+            let mut values: Vec<Individual> = values.into_iter().collect();
+            values.sort_by(|x, y| x.0.cmp(&y.0));
+            FunctionalViolation { property, individual, values, inverse }
+        })
+        .collect();
+    violations.sort_by(|x, y| (property_iri(&x.property), &x.individual.0).cmp(&(property_iri(&y.property), &y.individual.0)));
+    violations
+}
+
+fn property_iri(property: &Property) -> &str {
+    match property {
+        Property::Object(iri) | Property::Data(iri) => iri.as_str(),
+    }
+}
+
+fn assertion_key(assertion: &PropertyAssertion) -> (&str, &str, &str) {
+    (property_iri(&assertion.0), assertion.1.0.as_str(), assertion.2.0.as_str())
+}
+
+/// Assertions indexed by (property, subject) and (property, object)
+#[derive(Default)]
+struct AssertionIndex {
+    assertions: HashSet<PropertyAssertion>,
+    by_subject: HashMap<(Property, Individual), HashSet<Individual>>,
+    by_object: HashMap<(Property, Individual), HashSet<Individual>>,
+}
+
+impl AssertionIndex {
+    fn insert(&mut self, assertion: &PropertyAssertion) -> bool {
+        if !self.assertions.insert(assertion.clone()) {
+            return false;
+        }
+        let (p, a, b) = assertion;
+        self.by_subject.entry((p.clone(), a.clone())).or_default().insert(b.clone());
+        self.by_object.entry((p.clone(), b.clone())).or_default().insert(a.clone());
+        true
+    }
+
+    /// Objects `c` with `P(subject, c)` (collected, since the index grows while iterating)
+    fn objects(&self, p: &Property, subject: &Individual) -> std::vec::IntoIter<Individual> {
+        self.by_subject.get(&(p.clone(), subject.clone()))
+            .map(|objects| objects.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+    }
+
+    fn subjects(&self, p: &Property, object: &Individual) -> std::vec::IntoIter<Individual> {
+        self.by_object.get(&(p.clone(), object.clone()))
+            .map(|subjects| subjects.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::OwlIri;
+
+    fn prop(name: &str) -> Property {
+        Property::Object(OwlIri::new(format!("http://example.org/{}", name)))
+    }
+
+    fn ind(name: &str) -> Individual {
+        Individual(OwlIri::new(format!("http://example.org/{}", name)))
+    }
+
+    #[test]
+    fn test_closure_combines_characteristics() {
+        let mut ontology = Ontology::new();
+        ontology.add_axiom(Axiom::TransitiveProperty(prop("connectsTo")));
+        ontology.add_axiom(Axiom::SymmetricProperty(prop("peerOf")));
+        ontology.add_axiom(Axiom::InverseObjectProperties(prop("connectsTo"), prop("reachedFrom")));
+        ontology.add_axiom(Axiom::ObjectPropertyAssertion(prop("connectsTo"), ind("h1"), ind("h2")));
+        ontology.add_axiom(Axiom::ObjectPropertyAssertion(prop("connectsTo"), ind("h2"), ind("h3")));
+        ontology.add_axiom(Axiom::ObjectPropertyAssertion(prop("peerOf"), ind("h1"), ind("h4")));
+
+        let entailed = entailed_property_assertions(&ontology);
+        assert!(entailed.contains(&(prop("connectsTo"), ind("h1"), ind("h3"))));
+        assert!(entailed.contains(&(prop("reachedFrom"), ind("h3"), ind("h1"))));
+        assert!(entailed.contains(&(prop("peerOf"), ind("h4"), ind("h1"))));
+        // connectsTo 3 件の逆 + 推移の 1 件 + 対称の 1 件
+        assert_eq!(entailed.len(), 5);
+    }
+
+    #[test]
+    fn test_functional_violations() {
+        let mut ontology = Ontology::new();
+        ontology.add_axiom(Axiom::FunctionalProperty(prop("assignedTo")));
+        ontology.add_axiom(Axiom::InverseObjectProperties(prop("owns"), prop("assignedTo")));
+        ontology.add_axiom(Axiom::ObjectPropertyAssertion(prop("assignedTo"), ind("laptop"), ind("alice")));
+        // 逆プロパティ経由で laptop に 2 人目の値が付く
+        ontology.add_axiom(Axiom::ObjectPropertyAssertion(prop("owns"), ind("bob"), ind("laptop")));
+
+        let violations = functional_violations(&ontology);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].individual, ind("laptop"));
+        assert_eq!(violations[0].values, vec![ind("alice"), ind("bob")]);
+
+        ontology.add_axiom(Axiom::SameIndividual(vec![ind("alice"), ind("bob")]));
+        assert!(functional_violations(&ontology).is_empty());
+    }
+}
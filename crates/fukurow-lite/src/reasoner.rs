@@ -3,10 +3,17 @@
 use crate::model::{Ontology, Class, Property, Individual, Axiom, OwlIri};
 use crate::loader::{OntologyLoader, DefaultOntologyLoader};
 use crate::tableau::TableauReasoner;
+use crate::justification::ConsistencyReport;
+use crate::properties::{self, FunctionalViolation};
 use crate::OwlError;
+use fukurow_core::model::Triple;
+use fukurow_store::provenance::{GraphId, Provenance};
 use fukurow_store::store::RdfStore;
 use std::collections::{HashMap, HashSet};
 
+/// Name of the `GraphId::Inferred` graph holding materialized OWL Lite property assertions
+pub const OWL_LITE_INFERRED_GRAPH: &str = "owl-lite";
+
 /// OWL Lite reasoner
 pub struct OwlLiteReasoner {
     loader: DefaultOntologyLoader,
@@ -27,8 +34,64 @@ impl OwlLiteReasoner {
     }
 
     /// Check if ontology is consistent
+    ///
+    /// タブロー検査に加えて、関数的プロパティに複数の値を持つ個体があれば矛盾とする
     pub fn is_consistent(&mut self, ontology: &Ontology) -> Result<bool, OwlError> {
-        self.tableau.is_consistent(ontology)
+        Ok(self.tableau.is_consistent(ontology)? && properties::functional_violations(ontology).is_empty())
+    }
+
+    /// Check consistency and, if inconsistent, find a minimal set of axioms causing it
+    ///
+    /// 公理の部分集合ごとに新しいタブローで [`is_consistent`](Self::is_consistent) と同じ検査を行う
+    pub fn consistency_report(&self, ontology: &Ontology) -> Result<ConsistencyReport<Axiom>, OwlError> {
+        ConsistencyReport::compute(&ontology.axioms, |axioms| {
+            let mut subset = Ontology::new();
+            for axiom in axioms {
+                subset.add_axiom(axiom.clone());
+            }
+            Ok(TableauReasoner::new().is_consistent(&subset)? && properties::functional_violations(&subset).is_empty())
+        })
+    }
+
+    /// Functional / inverse functional property violations (including entailed assertions)
+    pub fn functional_violations(&self, ontology: &Ontology) -> Vec<FunctionalViolation> {
+        properties::functional_violations(ontology)
+    }
+
+    /// Fail with `ConsistencyError` describing the first functional property violation
+    pub fn check_functional_properties(&self, ontology: &Ontology) -> Result<(), OwlError> {
+        match properties::functional_violations(ontology).first() {
+            Some(violation) => Err(OwlError::ConsistencyError(violation.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the property assertions entailed by transitive/symmetric/inverse properties
+    ///
+    /// 推論結果は `GraphId::Inferred("owl-lite")` に `Provenance::Inferred` 付きで格納し、
+    /// 実行のたびにこのグラフを作り直す。挿入したトリプル数を返す
+    pub fn materialize_property_assertions(&self, ontology: &Ontology, store: &mut RdfStore) -> usize {
+        let graph_id = GraphId::Inferred(OWL_LITE_INFERRED_GRAPH.to_string());
+        store.clear_graph(&graph_id);
+
+        let triples: Vec<Triple> = properties::entailed_property_assertions(ontology).into_iter()
+            .map(|(property, subject, object)| Triple {
+                subject: subject.0.0,
+                predicate: match property {
+                    Property::Object(iri) | Property::Data(iri) => iri.0,
+                },
+                object: object.0.0,
+            })
+            .filter(|triple| store.find_triples(Some(&triple.subject), Some(&triple.predicate), Some(&triple.object)).is_empty())
+            .collect();
+        let count = triples.len();
+        store.insert_batch(triples, graph_id, Provenance::Inferred {
+            rule: "owl-property-characteristics".to_string(),
+            reasoning_level: "owl-lite".to_string(),
+            evidence: Vec::new(),
+            confidence: None,
+        });
+        count
     }
 
     /// Compute class subsumption hierarchy
@@ -127,6 +190,10 @@ impl OwlLiteReasoner {
             }
         }
 
+        // Property assertions entailed by transitive/symmetric/inverse properties
+        inferred.extend(properties::entailed_property_assertions(ontology).into_iter()
+            .map(|(property, subject, object)| Axiom::ObjectPropertyAssertion(property, subject, object)));
+
         // TODO: Add other inferred axioms (property hierarchies, etc.)
 
         Ok(inferred)
@@ -244,4 +311,75 @@ mod tests {
         // Person should be subsumed by Animal
         assert!(hierarchy.get(&person).unwrap().contains(&animal));
     }
+
+    #[test]
+    fn test_property_characteristics_are_loaded_and_materialized() {
+        let t = |s: &str, p: &str, o: &str| Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() };
+        let rdf_type = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+        let part_of = "http://example.org/partOf";
+        let owner = "http://example.org/owner";
+
+        let mut store = RdfStore::new();
+        // 特性の宣言がアサーションより後に走査されても読み込めること (ObjectProperty の宣言は省略)
+        for triple in [
+            t("http://example.org/host1", part_of, "http://example.org/subnetA"),
+            t("http://example.org/subnetA", part_of, "http://example.org/siteTokyo"),
+            t(part_of, rdf_type, "http://www.w3.org/2002/07/owl#TransitiveProperty"),
+            t(owner, rdf_type, "http://www.w3.org/2002/07/owl#FunctionalProperty"),
+            t(owner, rdf_type, "http://www.w3.org/2002/07/owl#ObjectProperty"),
+            t("http://example.org/host1", owner, "http://example.org/alice"),
+        ] {
+            store.insert(triple, GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        }
+
+        let mut reasoner = OwlLiteReasoner::new();
+        let ontology = reasoner.load_ontology(&store).unwrap();
+        assert!(ontology.axioms.contains(&Axiom::TransitiveProperty(Property::Object(OwlIri::new(part_of.to_string())))));
+        assert!(reasoner.is_consistent(&ontology).unwrap());
+
+        assert_eq!(reasoner.materialize_property_assertions(&ontology, &mut store), 1);
+        let inferred = store.find_triples(Some("http://example.org/host1"), Some(part_of), Some("http://example.org/siteTokyo"));
+        assert_eq!(inferred.len(), 1);
+        assert_eq!(inferred[0].graph_id, GraphId::Inferred(OWL_LITE_INFERRED_GRAPH.to_string()));
+
+        // 関数的プロパティに 2 つ目の値が付くと矛盾
+        store.insert(t("http://example.org/host1", owner, "http://example.org/bob"), GraphId::Default,
+                     Provenance::Sensor { source: "test".to_string(), confidence: None });
+        let ontology = reasoner.load_ontology(&store).unwrap();
+        assert!(!reasoner.is_consistent(&ontology).unwrap());
+        assert!(matches!(reasoner.check_functional_properties(&ontology), Err(OwlError::ConsistencyError(_))));
+    }
+
+    #[test]
+    fn test_consistency_report_justifies_functional_violation() {
+        let iri = |name: &str| OwlIri::new(format!("http://example.org/{}", name));
+        let individual = |name: &str| Individual(iri(name));
+        let owner = Property::Object(iri("owner"));
+
+        let mut ontology = Ontology::new();
+        for axiom in [
+            Axiom::SubClassOf(Class::Named(iri("Server")), Class::Named(iri("Host"))),
+            Axiom::ObjectPropertyAssertion(owner.clone(), individual("host1"), individual("alice")),
+            Axiom::ClassAssertion(Class::Named(iri("Server")), individual("host1")),
+            Axiom::FunctionalProperty(owner.clone()),
+            Axiom::ObjectPropertyAssertion(owner.clone(), individual("host2"), individual("alice")),
+            Axiom::ObjectPropertyAssertion(owner.clone(), individual("host1"), individual("bob")),
+        ] {
+            ontology.add_axiom(axiom);
+        }
+
+        let reasoner = OwlLiteReasoner::new();
+        let report = reasoner.consistency_report(&ontology).unwrap();
+        assert!(!report.consistent);
+        assert_eq!(report.axioms, 6);
+        assert_eq!(report.justification, vec![
+            Axiom::ObjectPropertyAssertion(owner.clone(), individual("host1"), individual("alice")),
+            Axiom::FunctionalProperty(owner.clone()),
+            Axiom::ObjectPropertyAssertion(owner, individual("host1"), individual("bob")),
+        ]);
+
+        ontology.axioms.pop();
+        assert!(reasoner.consistency_report(&ontology).unwrap().consistent);
+    }
 }
+
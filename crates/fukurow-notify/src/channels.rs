@@ -0,0 +1,443 @@
+//! Notification channels
+//!
+//! HTTP 系のチャネルはタイムアウトとリトライ ([`RetryPolicy`]) を共通で持ち、
+//! 429 / 5xx / 接続エラーのみ再送する
+
+use crate::{Notification, NotificationChannel, NotifyError, NotifyResult, RenderedMessage, Severity};
+use async_trait::async_trait;
+use fukurow_core::retry::{retry_retryable, RetryPolicy};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use reqwest::Client;
+use std::time::Duration;
+
+/// PagerDuty Events API v2 endpoint
+pub const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Default timeout of one HTTP delivery
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 10;
+
+/// POST `body` as JSON with retries
+async fn post_json(
+    client: &Client,
+    url: &str,
+    headers: &[(String, String)],
+    body: &serde_json::Value,
+    timeout: Duration,
+    retry: &RetryPolicy,
+) -> NotifyResult<()> {
+    retry_retryable(retry, |_attempt| async move {
+        let mut request = client.post(url).timeout(timeout).json(body);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let message = response.text().await.unwrap_or_default();
+            return Err(NotifyError::ApiError { status, message });
+        }
+        Ok(())
+    }, tokio::time::sleep).await
+}
+
+/// Generic HTTP webhook receiving the notification as JSON
+pub struct WebhookChannel {
+    client: Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl WebhookChannel {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECONDS),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Extra header sent with every request (e.g. `Authorization`)
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout = Duration::from_secs(seconds);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Request body: the notification plus the rendered `subject` and `body`
+    pub fn payload(notification: &Notification, message: &RenderedMessage) -> serde_json::Value {
+        let mut payload = serde_json::to_value(notification).unwrap_or_default();
+        payload["subject"] = serde_json::json!(message.subject);
+        payload["body"] = serde_json::json!(message.body);
+        payload
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn kind(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn send(&self, notification: &Notification, message: &RenderedMessage) -> NotifyResult<()> {
+        let payload = Self::payload(notification, message);
+        post_json(&self.client, &self.url, &self.headers, &payload, self.timeout, &self.retry).await
+    }
+}
+
+/// Slack incoming webhook
+pub struct SlackChannel {
+    client: Client,
+    webhook_url: String,
+    retry: RetryPolicy,
+}
+
+impl SlackChannel {
+    pub fn new(webhook_url: &str) -> Self {
+        Self { client: Client::new(), webhook_url: webhook_url.to_string(), retry: RetryPolicy::default() }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn payload(notification: &Notification, message: &RenderedMessage) -> serde_json::Value {
+        serde_json::json!({
+            "text": format!("{} {}", severity_emoji(notification.severity), message.subject),
+            "blocks": [
+                { "type": "header", "text": { "type": "plain_text", "text": truncate(&message.subject, 150) } },
+                { "type": "section", "text": { "type": "mrkdwn", "text": truncate(&message.body, 3000) } },
+            ],
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SlackChannel {
+    fn kind(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn send(&self, notification: &Notification, message: &RenderedMessage) -> NotifyResult<()> {
+        let timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECONDS);
+        post_json(&self.client, &self.webhook_url, &[], &Self::payload(notification, message), timeout, &self.retry).await
+    }
+}
+
+/// Microsoft Teams incoming webhook (MessageCard)
+pub struct TeamsChannel {
+    client: Client,
+    webhook_url: String,
+    retry: RetryPolicy,
+}
+
+impl TeamsChannel {
+    pub fn new(webhook_url: &str) -> Self {
+        Self { client: Client::new(), webhook_url: webhook_url.to_string(), retry: RetryPolicy::default() }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn payload(notification: &Notification, message: &RenderedMessage) -> serde_json::Value {
+        serde_json::json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": message.subject,
+            "themeColor": severity_color(notification.severity),
+            "title": message.subject,
+            "text": message.body,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for TeamsChannel {
+    fn kind(&self) -> &'static str {
+        "teams"
+    }
+
+    async fn send(&self, notification: &Notification, message: &RenderedMessage) -> NotifyResult<()> {
+        let timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECONDS);
+        post_json(&self.client, &self.webhook_url, &[], &Self::payload(notification, message), timeout, &self.retry).await
+    }
+}
+
+/// PagerDuty Events API v2 (`trigger` events)
+pub struct PagerDutyChannel {
+    client: Client,
+    routing_key: String,
+    endpoint: String,
+    retry: RetryPolicy,
+}
+
+impl PagerDutyChannel {
+    pub fn new(routing_key: &str) -> Self {
+        Self {
+            client: Client::new(),
+            routing_key: routing_key.to_string(),
+            endpoint: PAGERDUTY_EVENTS_URL.to_string(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Override the events endpoint (EU service region, tests)
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Event body; the notification ID is the dedup key so retries do not open duplicate incidents
+    pub fn payload(&self, notification: &Notification, message: &RenderedMessage) -> serde_json::Value {
+        let severity = match notification.severity {
+            Severity::Critical => "critical",
+            Severity::High => "error",
+            Severity::Medium | Severity::Low => "warning",
+            Severity::Info => "info",
+        };
+        serde_json::json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": notification.id,
+            "payload": {
+                // summary は 1024 文字まで
+                "summary": truncate(&message.subject, 1024),
+                "severity": severity,
+                "source": "fukurow",
+                "timestamp": notification.timestamp.to_rfc3339(),
+                "component": notification.rule,
+                "custom_details": {
+                    "body": message.body,
+                    "details": notification.details,
+                    "mitre_attack": notification.techniques,
+                },
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for PagerDutyChannel {
+    fn kind(&self) -> &'static str {
+        "pagerduty"
+    }
+
+    async fn send(&self, notification: &Notification, message: &RenderedMessage) -> NotifyResult<()> {
+        let timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECONDS);
+        post_json(&self.client, &self.endpoint, &[], &self.payload(notification, message), timeout, &self.retry).await
+    }
+}
+
+/// Transport security of the SMTP connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmtpSecurity {
+    /// Implicit TLS (port 465)
+    Tls,
+    /// STARTTLS upgrade (port 587)
+    #[default]
+    StartTls,
+    /// Plain text (local relays only)
+    None,
+}
+
+/// SMTP relay settings
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub timeout_seconds: u64,
+}
+
+impl SmtpConfig {
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port: None,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            timeout_seconds: DEFAULT_TIMEOUT_SECONDS,
+        }
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn with_security(mut self, security: SmtpSecurity) -> Self {
+        self.security = security;
+        self
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.username = Some(username.to_string());
+        self.password = Some(password.to_string());
+        self
+    }
+}
+
+/// Email over SMTP (plain-text body)
+pub struct EmailChannel {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailChannel {
+    pub fn new(config: &SmtpConfig, from: &str, to: &[&str]) -> NotifyResult<Self> {
+        let mut builder = match config.security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)),
+        }.map_err(|e| NotifyError::SmtpError(e.to_string()))?;
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        builder = builder.timeout(Some(Duration::from_secs(config.timeout_seconds)));
+
+        if to.is_empty() {
+            return Err(NotifyError::ConfigError("Email channel needs at least one recipient".to_string()));
+        }
+        let mailbox = |address: &str| address.parse::<Mailbox>()
+            .map_err(|e| NotifyError::ConfigError(format!("Invalid email address {}: {}", address, e)));
+        Ok(Self {
+            transport: builder.build(),
+            from: mailbox(from)?,
+            to: to.iter().map(|address| mailbox(address)).collect::<NotifyResult<_>>()?,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn kind(&self) -> &'static str {
+        "email"
+    }
+
+    async fn send(&self, _notification: &Notification, message: &RenderedMessage) -> NotifyResult<()> {
+        let mut builder = Message::builder().from(self.from.clone());
+        for recipient in &self.to {
+            builder = builder.to(recipient.clone());
+        }
+        let email = builder
+            .subject(message.subject.replace(['\r', '\n'], " "))
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body.clone())
+            .map_err(|e| NotifyError::SmtpError(e.to_string()))?;
+        self.transport.send(email).await.map_err(|e| NotifyError::SmtpError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+fn severity_emoji(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => ":rotating_light:",
+        Severity::High => ":red_circle:",
+        Severity::Medium => ":large_orange_circle:",
+        Severity::Low | Severity::Info => ":large_blue_circle:",
+    }
+}
+
+fn severity_color(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "8B0000",
+        Severity::High => "D32F2F",
+        Severity::Medium => "F57C00",
+        Severity::Low | Severity::Info => "1976D2",
+    }
+}
+
+/// First `max` characters of `text`
+fn truncate(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => text[..index].to_string(),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageTemplate;
+
+    fn sample() -> (Notification, RenderedMessage) {
+        let notification = Notification::new(Severity::Critical, "Ransomware detected")
+            .with_rule("ransomware_extension")
+            .with_details(serde_json::json!({ "host": "10.0.0.5" }));
+        let message = MessageTemplate::default().render(&notification);
+        (notification, message)
+    }
+
+    #[test]
+    fn test_pagerduty_payload() {
+        let (notification, message) = sample();
+        let payload = PagerDutyChannel::new("routing-key").payload(&notification, &message);
+        assert_eq!(payload["routing_key"], "routing-key");
+        assert_eq!(payload["event_action"], "trigger");
+        assert_eq!(payload["dedup_key"], notification.id);
+        assert_eq!(payload["payload"]["severity"], "critical");
+        assert_eq!(payload["payload"]["summary"], "[critical] Ransomware detected");
+        assert_eq!(payload["payload"]["component"], "ransomware_extension");
+    }
+
+    #[test]
+    fn test_chat_and_webhook_payloads() {
+        let (notification, message) = sample();
+        let slack = SlackChannel::payload(&notification, &message);
+        assert!(slack["text"].as_str().unwrap().starts_with(":rotating_light:"));
+        assert_eq!(slack["blocks"][0]["text"]["text"], "[critical] Ransomware detected");
+
+        let teams = TeamsChannel::payload(&notification, &message);
+        assert_eq!(teams["@type"], "MessageCard");
+        assert_eq!(teams["themeColor"], "8B0000");
+
+        let webhook = WebhookChannel::payload(&notification, &message);
+        assert_eq!(webhook["severity"], "critical");
+        assert_eq!(webhook["details"]["host"], "10.0.0.5");
+        assert_eq!(webhook["subject"], "[critical] Ransomware detected");
+    }
+
+    #[test]
+    fn test_email_channel_validates_addresses() {
+        let config = SmtpConfig::new("localhost").with_security(SmtpSecurity::None).with_port(2525);
+        assert!(EmailChannel::new(&config, "fukurow@example.com", &["soc@example.com"]).is_ok());
+        assert!(EmailChannel::new(&config, "not an address", &["soc@example.com"]).is_err());
+        assert!(EmailChannel::new(&config, "fukurow@example.com", &[]).is_err());
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        assert_eq!(truncate("検知アラート", 2), "検知");
+        assert_eq!(truncate("short", 10), "short");
+    }
+}
@@ -0,0 +1,230 @@
+//! アラート通知モジュール
+//!
+//! 推論結果のアラートを重大度・ルール名で振り分け、各チャネルへ通知する:
+//! - メール (SMTP)
+//! - Slack / Microsoft Teams の Incoming Webhook
+//! - PagerDuty Events API v2
+//! - 任意の HTTP Webhook
+//!
+//! チャネルごとにレート制限 ([`rate_limit`]) と本文のテンプレート ([`template`]) を設定でき、
+//! 振り分け規則は [`routing`]、配信は [`Notifier`] が行う
+
+pub mod channels;
+pub mod notifier;
+pub mod rate_limit;
+pub mod routing;
+pub mod template;
+
+pub use channels::{EmailChannel, PagerDutyChannel, SlackChannel, SmtpConfig, SmtpSecurity, TeamsChannel, WebhookChannel};
+pub use notifier::{DispatchReport, Notifier};
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use routing::{AlertRouter, RoutingRule};
+pub use template::{MessageTemplate, RenderedMessage};
+
+use chrono::{DateTime, Utc};
+use fukurow_core::model::SecurityAction;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Alert severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = NotifyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "info" | "informational" => Ok(Severity::Info),
+            "low" => Ok(Severity::Low),
+            "medium" | "moderate" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            other => Err(NotifyError::ConfigError(format!("Unknown severity: {}", other))),
+        }
+    }
+}
+
+/// Alert to be delivered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub severity: Severity,
+    /// Name of the rule that raised the alert
+    pub rule: Option<String>,
+    pub title: String,
+    pub details: serde_json::Value,
+    /// MITRE ATT&CK technique IDs of the alert
+    pub techniques: Vec<String>,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, title: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            severity,
+            rule: None,
+            title: title.to_string(),
+            details: serde_json::Value::Object(serde_json::Map::new()),
+            techniques: Vec::new(),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: &str) -> Self {
+        self.rule = Some(rule.to_string());
+        self
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// Notification for a proposed security action
+    ///
+    /// アラートの重大度が解釈できない場合は medium とする。ルール名は詳細の `rule` から読む。
+    /// 隔離などの対処アクションも承認待ちとして通知する
+    pub fn from_action(action: &SecurityAction) -> Self {
+        let (severity, title, details) = match action {
+            SecurityAction::Alert { severity, message, details } => {
+                (severity.parse().unwrap_or(Severity::Medium), message.clone(), details.clone())
+            }
+            SecurityAction::IsolateHost { host_ip, reason } => (
+                Severity::Critical,
+                format!("Host isolation proposed: {} - {}", host_ip, reason),
+                serde_json::json!({ "host_ip": host_ip, "reason": reason }),
+            ),
+            SecurityAction::BlockConnection { source_ip, dest_ip, reason } => (
+                Severity::High,
+                format!("Connection block proposed: {} -> {} - {}", source_ip, dest_ip, reason),
+                serde_json::json!({ "source_ip": source_ip, "dest_ip": dest_ip, "reason": reason }),
+            ),
+            SecurityAction::TerminateProcess { process_id, reason } => (
+                Severity::High,
+                format!("Process termination proposed: {} - {}", process_id, reason),
+                serde_json::json!({ "process_id": process_id, "reason": reason }),
+            ),
+            SecurityAction::RevokePrivileges { user, privilege, reason } => (
+                Severity::High,
+                format!("Privilege revocation proposed: {} ({}) - {}", user, privilege, reason),
+                serde_json::json!({ "user": user, "privilege": privilege, "reason": reason }),
+            ),
+        };
+
+        let mut notification = Notification::new(severity, &title).with_details(details);
+        notification.rule = notification.details.get("rule").and_then(|rule| rule.as_str()).map(str::to_string);
+        notification.techniques = action.attack_techniques();
+        notification
+    }
+}
+
+/// Result type for notification operations
+pub type NotifyResult<T> = Result<T, NotifyError>;
+
+/// Notification errors
+#[derive(thiserror::Error, Debug)]
+pub enum NotifyError {
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("SMTP error: {0}")]
+    SmtpError(String),
+
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
+    #[error("Template error: {0}")]
+    TemplateError(String),
+
+    #[error("API error: {status} - {message}")]
+    ApiError { status: u16, message: String },
+
+    #[error("Unknown channel: {0}")]
+    UnknownChannel(String),
+}
+
+impl fukurow_core::retry::Retryable for NotifyError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            NotifyError::HttpError(e) => {
+                e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| s.as_u16() == 429 || s.is_server_error())
+            }
+            NotifyError::ApiError { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// Destination of notifications
+#[async_trait::async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Kind of the channel (`slack`, `pagerduty`, ...)
+    fn kind(&self) -> &'static str;
+
+    /// Deliver one rendered notification
+    async fn send(&self, notification: &Notification, message: &RenderedMessage) -> NotifyResult<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_order_and_parsing() {
+        assert!(Severity::Critical > Severity::High);
+        assert!(Severity::Low > Severity::Info);
+        assert_eq!("HIGH".parse::<Severity>().unwrap(), Severity::High);
+        assert_eq!("informational".parse::<Severity>().unwrap(), Severity::Info);
+        assert!("urgent".parse::<Severity>().is_err());
+    }
+
+    #[test]
+    fn test_notification_from_action() {
+        let alert = SecurityAction::Alert {
+            severity: "critical".to_string(),
+            message: "Ransomware detected".to_string(),
+            details: serde_json::json!({ "rule": "ransomware_extension", "host": "10.0.0.5" }),
+        }.with_attack_techniques(&["T1486".to_string()]);
+
+        let notification = Notification::from_action(&alert);
+        assert_eq!(notification.severity, Severity::Critical);
+        assert_eq!(notification.rule.as_deref(), Some("ransomware_extension"));
+        assert_eq!(notification.techniques, vec!["T1486"]);
+
+        let isolate = SecurityAction::IsolateHost { host_ip: "10.0.0.5".to_string(), reason: "C2 beacon".to_string() };
+        let notification = Notification::from_action(&isolate);
+        assert_eq!(notification.severity, Severity::Critical);
+        assert_eq!(notification.details["host_ip"], "10.0.0.5");
+
+        let unknown = SecurityAction::Alert { severity: "p1".to_string(), message: "x".to_string(), details: serde_json::Value::Null };
+        assert_eq!(Notification::from_action(&unknown).severity, Severity::Medium);
+    }
+}
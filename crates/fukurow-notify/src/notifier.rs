@@ -0,0 +1,184 @@
+//! Alert dispatch
+//!
+//! 振り分け規則で選んだチャネルごとにレート制限を確認し、テンプレートで本文を作って送る。
+//! 1 つのチャネルの失敗は他のチャネルへの配信を止めない
+
+use crate::{
+    AlertRouter, MessageTemplate, Notification, NotificationChannel, NotifyError, NotifyResult, RateLimit, RateLimiter,
+};
+use fukurow_core::model::SecurityAction;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Outcome of dispatching one notification
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DispatchReport {
+    pub notification_id: String,
+    pub delivered: Vec<String>,
+    /// Channels skipped because their rate limit was reached
+    pub rate_limited: Vec<String>,
+    /// Channel name and error of failed deliveries
+    pub failed: Vec<(String, String)>,
+}
+
+impl DispatchReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Routes alerts to named channels
+pub struct Notifier {
+    router: AlertRouter,
+    channels: HashMap<String, Box<dyn NotificationChannel>>,
+    templates: HashMap<String, MessageTemplate>,
+    limiters: HashMap<String, RateLimiter>,
+    default_template: MessageTemplate,
+}
+
+impl Notifier {
+    pub fn new(router: AlertRouter) -> Self {
+        Self {
+            router,
+            channels: HashMap::new(),
+            templates: HashMap::new(),
+            limiters: HashMap::new(),
+            default_template: MessageTemplate::default(),
+        }
+    }
+
+    pub fn with_channel<C: NotificationChannel + 'static>(mut self, name: &str, channel: C) -> Self {
+        self.channels.insert(name.to_string(), Box::new(channel));
+        self
+    }
+
+    /// Template of one channel (others use the default template)
+    pub fn with_template(mut self, channel: &str, template: MessageTemplate) -> Self {
+        self.templates.insert(channel.to_string(), template);
+        self
+    }
+
+    pub fn with_default_template(mut self, template: MessageTemplate) -> Self {
+        self.default_template = template;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, channel: &str, limit: RateLimit) -> Self {
+        self.limiters.insert(channel.to_string(), RateLimiter::new(limit));
+        self
+    }
+
+    pub fn router(&self) -> &AlertRouter {
+        &self.router
+    }
+
+    /// Check that every channel named by the routes is registered
+    pub fn validate(&self) -> NotifyResult<()> {
+        match self.router.referenced_channels().into_iter().find(|name| !self.channels.contains_key(*name)) {
+            Some(name) => Err(NotifyError::UnknownChannel(name.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Notifications refused by the rate limit of `channel` so far
+    pub fn suppressed(&self, channel: &str) -> u64 {
+        self.limiters.get(channel).map_or(0, RateLimiter::suppressed)
+    }
+
+    /// Deliver `notification` to every channel its routes select
+    pub async fn notify(&self, notification: &Notification) -> DispatchReport {
+        let mut report = DispatchReport { notification_id: notification.id.clone(), ..Default::default() };
+        for name in self.router.channels_for(notification) {
+            let Some(channel) = self.channels.get(&name) else {
+                report.failed.push((name.clone(), NotifyError::UnknownChannel(name).to_string()));
+                continue;
+            };
+            if self.limiters.get(&name).is_some_and(|limiter| !limiter.try_acquire()) {
+                report.rate_limited.push(name);
+                continue;
+            }
+
+            let message = self.templates.get(&name).unwrap_or(&self.default_template).render(notification);
+            match channel.send(notification, &message).await {
+                Ok(()) => report.delivered.push(name),
+                Err(e) => report.failed.push((name, e.to_string())),
+            }
+        }
+        report
+    }
+
+    /// Notify every proposed action (alerts and response actions)
+    pub async fn notify_actions(&self, actions: &[SecurityAction]) -> Vec<DispatchReport> {
+        let mut reports = Vec::with_capacity(actions.len());
+        for action in actions {
+            reports.push(self.notify(&Notification::from_action(action)).await);
+        }
+        reports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RenderedMessage, RoutingRule, Severity};
+    use std::sync::{Arc, Mutex};
+
+    /// Channel recording the rendered messages it was asked to send
+    #[derive(Clone, Default)]
+    struct RecordingChannel {
+        sent: Arc<Mutex<Vec<RenderedMessage>>>,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl NotificationChannel for RecordingChannel {
+        fn kind(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn send(&self, _notification: &Notification, message: &RenderedMessage) -> NotifyResult<()> {
+            if self.fail {
+                return Err(NotifyError::ApiError { status: 500, message: "down".to_string() });
+            }
+            self.sent.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_templates_and_rate_limits() {
+        let pager = RecordingChannel::default();
+        let chat = RecordingChannel::default();
+        let router = AlertRouter::new()
+            .with_route(RoutingRule::new("page", &["pager"]).with_min_severity(Severity::Critical))
+            .with_route(RoutingRule::new("chat", &["chat", "broken"]).with_min_severity(Severity::High));
+        let notifier = Notifier::new(router)
+            .with_channel("pager", pager.clone())
+            .with_channel("chat", chat.clone())
+            .with_channel("broken", RecordingChannel { fail: true, ..Default::default() })
+            .with_template("pager", MessageTemplate::new("PAGE {{rule}}", "{{title}}").unwrap())
+            .with_rate_limit("pager", RateLimit::per_minute(1));
+        assert!(notifier.validate().is_ok());
+
+        let critical = Notification::new(Severity::Critical, "Ransomware detected").with_rule("ransomware_extension");
+        let report = notifier.notify(&critical).await;
+        assert_eq!(report.delivered, vec!["pager", "chat"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(pager.sent.lock().unwrap()[0].subject, "PAGE ransomware_extension");
+        assert!(chat.sent.lock().unwrap()[0].subject.starts_with("[critical]"));
+
+        // ページャーは 1 分に 1 件まで
+        let report = notifier.notify(&critical).await;
+        assert_eq!(report.rate_limited, vec!["pager"]);
+        assert_eq!(notifier.suppressed("pager"), 1);
+
+        let low = Notification::new(Severity::Low, "Port scan");
+        assert!(notifier.notify(&low).await.delivered.is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_channels() {
+        let notifier = Notifier::new(AlertRouter::new().with_default_channels(&["missing"]));
+        assert!(matches!(notifier.validate(), Err(NotifyError::UnknownChannel(name)) if name == "missing"));
+    }
+}
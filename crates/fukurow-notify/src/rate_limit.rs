@@ -0,0 +1,95 @@
+//! Per-channel rate limiting
+//!
+//! 直近のウィンドウ内の送信数を数えるスライディングウィンドウ方式。
+//! 上限を超えた通知は送らずに抑制数として数える (アラートの嵐でページャーを埋めないため)
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// At most `max_notifications` per `window_seconds`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub max_notifications: u32,
+    pub window_seconds: u64,
+}
+
+impl RateLimit {
+    pub fn new(max_notifications: u32, window: Duration) -> Self {
+        Self { max_notifications, window_seconds: window.as_secs().max(1) }
+    }
+
+    pub fn per_minute(max_notifications: u32) -> Self {
+        Self::new(max_notifications, Duration::from_secs(60))
+    }
+
+    pub fn per_hour(max_notifications: u32) -> Self {
+        Self::new(max_notifications, Duration::from_secs(3600))
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_seconds)
+    }
+}
+
+/// Sliding-window limiter of one channel
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    sent: Mutex<VecDeque<Instant>>,
+    suppressed: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self { limit, sent: Mutex::new(VecDeque::new()), suppressed: AtomicU64::new(0) }
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Take a slot for one notification; `false` when the window is full
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    pub(crate) fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut sent = self.sent.lock().unwrap();
+        while sent.front().is_some_and(|at| now.duration_since(*at) >= self.limit.window()) {
+            sent.pop_front();
+        }
+        if sent.len() >= self.limit.max_notifications as usize {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        sent.push_back(now);
+        true
+    }
+
+    /// Number of notifications refused so far
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_window() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_secs(10)));
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at(start));
+        assert!(limiter.try_acquire_at(start + Duration::from_secs(1)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_secs(2)));
+        // 最初の送信がウィンドウから外れると 1 件空く
+        assert!(limiter.try_acquire_at(start + Duration::from_secs(10)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_secs(10)));
+        assert_eq!(limiter.suppressed(), 2);
+    }
+}
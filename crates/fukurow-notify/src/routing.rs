@@ -0,0 +1,188 @@
+//! Alert routing rules
+//!
+//! 規則は上から順に評価し、一致したすべての規則のチャネルへ送る。`stop` が立った規則に
+//! 一致したらそれ以降は評価しない。どの規則にも一致しなければ既定のチャネルへ送る。
+//!
+//! 例: critical は PagerDuty と Slack、high 以上は Slack、ランサムウェア系ルールは常にメール
+
+use crate::{Notification, Severity};
+use serde::{Deserialize, Serialize};
+
+/// One routing rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingRule {
+    pub name: String,
+    /// Lowest severity routed (inclusive)
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+    /// Highest severity routed (inclusive)
+    #[serde(default)]
+    pub max_severity: Option<Severity>,
+    /// Rule name patterns (`*` matches any characters); empty matches every alert
+    #[serde(default)]
+    pub rules: Vec<String>,
+    /// Names of the channels to notify
+    pub channels: Vec<String>,
+    /// Skip the remaining routes when this one matches
+    #[serde(default)]
+    pub stop: bool,
+}
+
+impl RoutingRule {
+    pub fn new(name: &str, channels: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            min_severity: None,
+            max_severity: None,
+            rules: Vec::new(),
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+            stop: false,
+        }
+    }
+
+    pub fn with_min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    pub fn with_max_severity(mut self, severity: Severity) -> Self {
+        self.max_severity = Some(severity);
+        self
+    }
+
+    pub fn with_rule(mut self, pattern: &str) -> Self {
+        self.rules.push(pattern.to_string());
+        self
+    }
+
+    pub fn with_stop(mut self, stop: bool) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub fn matches(&self, notification: &Notification) -> bool {
+        if self.min_severity.is_some_and(|min| notification.severity < min) {
+            return false;
+        }
+        if self.max_severity.is_some_and(|max| notification.severity > max) {
+            return false;
+        }
+        if self.rules.is_empty() {
+            return true;
+        }
+        // ルール名の分からないアラートはルール名で絞る規則には一致しない
+        notification.rule.as_deref()
+            .is_some_and(|rule| self.rules.iter().any(|pattern| glob_match(pattern, rule)))
+    }
+}
+
+/// Ordered routing rules with a fallback
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlertRouter {
+    #[serde(default)]
+    pub routes: Vec<RoutingRule>,
+    /// Channels for alerts no route matches
+    #[serde(default)]
+    pub default_channels: Vec<String>,
+}
+
+impl AlertRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_route(mut self, route: RoutingRule) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    pub fn with_default_channels(mut self, channels: &[&str]) -> Self {
+        self.default_channels = channels.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Channels to notify for `notification`, without duplicates, in route order
+    pub fn channels_for(&self, notification: &Notification) -> Vec<String> {
+        let mut channels: Vec<String> = Vec::new();
+        let mut matched = false;
+        for route in &self.routes {
+            if !route.matches(notification) {
+                continue;
+            }
+            matched = true;
+            for channel in &route.channels {
+                if !channels.contains(channel) {
+                    channels.push(channel.clone());
+                }
+            }
+            if route.stop {
+                break;
+            }
+        }
+        if matched { channels } else { self.default_channels.clone() }
+    }
+
+    /// Every channel name referenced by a route or the fallback
+    pub fn referenced_channels(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.routes.iter()
+            .flat_map(|route| route.channels.iter())
+            .chain(self.default_channels.iter())
+            .map(String::as_str)
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// `*` wildcard match
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty() };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(severity: Severity, rule: Option<&str>) -> Notification {
+        let notification = Notification::new(severity, "test");
+        match rule {
+            Some(rule) => notification.with_rule(rule),
+            None => notification,
+        }
+    }
+
+    #[test]
+    fn test_routes_by_severity_and_rule() {
+        let router = AlertRouter::new()
+            .with_route(RoutingRule::new("ransomware", &["email"]).with_rule("ransomware_*"))
+            .with_route(RoutingRule::new("page", &["pagerduty", "slack"]).with_min_severity(Severity::Critical).with_stop(true))
+            .with_route(RoutingRule::new("chat", &["slack"]).with_min_severity(Severity::High))
+            .with_default_channels(&["webhook"]);
+
+        assert_eq!(router.channels_for(&alert(Severity::Critical, Some("ransomware_extension"))), vec!["email", "pagerduty", "slack"]);
+        assert_eq!(router.channels_for(&alert(Severity::High, None)), vec!["slack"]);
+        assert_eq!(router.channels_for(&alert(Severity::Low, Some("port_scan"))), vec!["webhook"]);
+        assert_eq!(router.referenced_channels(), vec!["email", "pagerduty", "slack", "webhook"]);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("ransomware_*", "ransomware_extension"));
+        assert!(glob_match("*brute*", "ssh_brute_force"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+        assert!(!glob_match("a*a", "a"));
+    }
+}
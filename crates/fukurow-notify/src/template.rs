@@ -0,0 +1,166 @@
+//! Alert body templates
+//!
+//! `{{ severity }}` のようなプレースホルダーを通知の値で置き換える。使える名前は
+//! `id`, `timestamp`, `severity`, `rule`, `title`, `techniques`, `details` と
+//! 詳細の中の値を指す `details.<key>.<key>` (値がなければ空文字列)
+
+use crate::{Notification, NotifyError, NotifyResult};
+use serde::{Deserialize, Serialize};
+
+const FIELDS: &[&str] = &["id", "timestamp", "severity", "rule", "title", "techniques", "details"];
+
+/// Subject and body templates of a channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawTemplate", into = "RawTemplate")]
+pub struct MessageTemplate {
+    subject: String,
+    body: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawTemplate {
+    subject: String,
+    body: String,
+}
+
+impl TryFrom<RawTemplate> for MessageTemplate {
+    type Error = NotifyError;
+
+    fn try_from(raw: RawTemplate) -> Result<Self, Self::Error> {
+        MessageTemplate::new(&raw.subject, &raw.body)
+    }
+}
+
+impl From<MessageTemplate> for RawTemplate {
+    fn from(template: MessageTemplate) -> Self {
+        RawTemplate { subject: template.subject, body: template.body }
+    }
+}
+
+impl Default for MessageTemplate {
+    fn default() -> Self {
+        Self {
+            subject: "[{{severity}}] {{title}}".to_string(),
+            body: "{{title}}\nSeverity: {{severity}}\nRule: {{rule}}\nATT&CK: {{techniques}}\nTime: {{timestamp}}\n\n{{details}}".to_string(),
+        }
+    }
+}
+
+/// Template output for one notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedMessage {
+    pub subject: String,
+    pub body: String,
+}
+
+impl MessageTemplate {
+    /// Template with the given subject and body; unknown placeholders are rejected
+    pub fn new(subject: &str, body: &str) -> NotifyResult<Self> {
+        for text in [subject, body] {
+            for placeholder in placeholders(text)? {
+                let root = placeholder.split('.').next().unwrap_or_default();
+                let valid = FIELDS.contains(&root) && (root == "details" || !placeholder.contains('.'));
+                if !valid {
+                    return Err(NotifyError::TemplateError(format!("Unknown placeholder: {{{{{}}}}}", placeholder)));
+                }
+            }
+        }
+        Ok(Self { subject: subject.to_string(), body: body.to_string() })
+    }
+
+    pub fn render(&self, notification: &Notification) -> RenderedMessage {
+        RenderedMessage {
+            subject: render(&self.subject, notification),
+            body: render(&self.body, notification),
+        }
+    }
+}
+
+/// Placeholder names in `text`, trimmed
+fn placeholders(text: &str) -> NotifyResult<Vec<&str>> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after.find("}}")
+            .ok_or_else(|| NotifyError::TemplateError("Unterminated placeholder".to_string()))?;
+        names.push(after[..end].trim());
+        rest = &after[end + 2..];
+    }
+    Ok(names)
+}
+
+fn render(text: &str, notification: &Notification) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                output.push_str(&value(notification, after[..end].trim()));
+                rest = &after[end + 2..];
+            }
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn value(notification: &Notification, name: &str) -> String {
+    match name {
+        "id" => notification.id.clone(),
+        "timestamp" => notification.timestamp.to_rfc3339(),
+        "severity" => notification.severity.to_string(),
+        "rule" => notification.rule.clone().unwrap_or_default(),
+        "title" => notification.title.clone(),
+        "techniques" => notification.techniques.join(", "),
+        "details" => serde_json::to_string_pretty(&notification.details).unwrap_or_default(),
+        path => {
+            let pointer: String = path.split('.').skip(1).map(|key| format!("/{}", key)).collect();
+            match notification.details.pointer(&pointer) {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(serde_json::Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Severity;
+
+    #[test]
+    fn test_render_placeholders() {
+        let notification = Notification::new(Severity::High, "Brute force")
+            .with_rule("ssh_brute_force")
+            .with_details(serde_json::json!({ "user": "alice", "source": { "ip": "203.0.113.7" }, "attempts": 42 }));
+
+        let template = MessageTemplate::new(
+            "[{{ severity }}] {{title}}",
+            "{{rule}}: {{details.user}} from {{details.source.ip}} ({{details.attempts}} attempts){{details.missing}}",
+        ).unwrap();
+        let message = template.render(&notification);
+        assert_eq!(message.subject, "[high] Brute force");
+        assert_eq!(message.body, "ssh_brute_force: alice from 203.0.113.7 (42 attempts)");
+
+        let default = MessageTemplate::default().render(&notification);
+        assert!(default.body.contains("\"user\": \"alice\""));
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        assert!(MessageTemplate::new("{{host}}", "").is_err());
+        assert!(MessageTemplate::new("{{title.x}}", "").is_err());
+        assert!(MessageTemplate::new("", "{{title").is_err());
+
+        let json = serde_json::json!({ "subject": "{{nope}}", "body": "" });
+        assert!(serde_json::from_value::<MessageTemplate>(json).is_err());
+    }
+}
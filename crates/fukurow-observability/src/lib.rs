@@ -1,4 +1,10 @@
-//! Observability (health/metrics) abstractions and Axum routes
+//! Observability (health/metrics/tracing) abstractions and Axum routes
+//!
+//! トレースの出力先 (ログ / OTLP) の設定は [`telemetry`] を参照
+
+pub mod telemetry;
+
+pub use telemetry::{TelemetryConfig, TelemetryError, TelemetryGuard};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -134,7 +140,10 @@ pub mod tracing {
         pub const API_REQUEST: &str = "api.request";
         pub const REASONING_EXECUTE: &str = "reasoning.execute";
         pub const STORE_QUERY: &str = "store.query";
+        pub const REASONING_STAGE: &str = "reasoning.stage";
         pub const STREAM_SEND: &str = "stream.send";
+        pub const STREAM_CONSUME: &str = "stream.consume";
+        pub const SIEM_SEND: &str = "siem.send";
         pub const OPERATOR_RECONCILE: &str = "operator.reconcile";
         pub const HEALTH_CHECK: &str = "health.check";
     }
@@ -153,9 +162,28 @@ pub mod tracing {
         pub const STATUS_CODE: &str = "status.code";
         pub const ERROR_TYPE: &str = "error.type";
         pub const DURATION_MS: &str = "duration.ms";
+        pub const TENANT_ID: &str = "tenant.id";
+        pub const REASONING_PROFILE: &str = "reasoning.profile";
+        pub const REASONING_STAGE: &str = "reasoning.stage";
+        pub const ACTION_COUNT: &str = "action.count";
+        pub const TRIPLE_COUNT: &str = "triple.count";
+        pub const QUERY_KIND: &str = "query.kind";
+        pub const RESULT_COUNT: &str = "result.count";
+        pub const STREAM_TYPE: &str = "stream.type";
+        pub const STREAM_TOPIC: &str = "stream.topic";
+        pub const EVENT_COUNT: &str = "event.count";
+        pub const SIEM_NAME: &str = "siem.name";
+    }
+
+    /// HTTP headers carrying the request ID and the W3C trace context
+    pub mod headers {
+        pub const REQUEST_ID: &str = "x-request-id";
+        pub const TRACEPARENT: &str = "traceparent";
+        pub const TRACESTATE: &str = "tracestate";
     }
 }
 
+#[cfg(feature = "routes")]
 pub mod routes {
     use super::*;
     use axum::{
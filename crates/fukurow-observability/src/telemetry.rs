@@ -0,0 +1,244 @@
+//! Trace output and export
+//!
+//! `tracing` のスパンとログを標準出力へ書き出し、`otlp` フィーチャー有効時は OTLP (gRPC) で
+//! Jaeger / Tempo などへトレースを送る。プロセスをまたぐ親子関係は W3C Trace Context
+//! (`traceparent` / `tracestate` ヘッダー) でつなぐ
+//!
+//! 環境変数 (`from_env`):
+//! - `OTEL_SERVICE_NAME` - サービス名 (既定: `fukurow`)
+//! - `OTEL_EXPORTER_OTLP_ENDPOINT` - OTLP gRPC エンドポイント (例: `http://tempo:4317`)。未設定なら送らない
+//! - `OTEL_TRACES_SAMPLER_ARG` - ルートトレースのサンプリング率 (0.0 - 1.0)
+//! - `RUST_LOG` - ログとスパンのフィルター
+
+use std::collections::HashMap;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Tracing subscriber settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryConfig {
+    pub service_name: String,
+    /// `RUST_LOG` style filter
+    pub log_filter: String,
+    /// OTLP gRPC endpoint; `None` disables export
+    pub otlp_endpoint: Option<String>,
+    /// Fraction of root traces sampled (child spans follow their parent's decision)
+    pub sample_ratio: f64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            service_name: "fukurow".to_string(),
+            log_filter: "info".to_string(),
+            otlp_endpoint: None,
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+impl TelemetryConfig {
+    pub fn new(service_name: &str) -> Self {
+        Self { service_name: service_name.to_string(), ..Default::default() }
+    }
+
+    /// Settings from the standard OpenTelemetry environment variables
+    pub fn from_env(service_name: &str) -> Result<Self, TelemetryError> {
+        Self::from_vars(service_name, |name| std::env::var(name).ok())
+    }
+
+    fn from_vars(service_name: &str, var: impl Fn(&str) -> Option<String>) -> Result<Self, TelemetryError> {
+        let mut config = Self::new(service_name);
+        if let Some(name) = var("OTEL_SERVICE_NAME").filter(|name| !name.is_empty()) {
+            config.service_name = name;
+        }
+        if let Some(filter) = var("RUST_LOG").filter(|filter| !filter.is_empty()) {
+            config.log_filter = filter;
+        }
+        config.otlp_endpoint = var("OTEL_EXPORTER_OTLP_ENDPOINT").filter(|endpoint| !endpoint.is_empty());
+        if let Some(ratio) = var("OTEL_TRACES_SAMPLER_ARG") {
+            config.sample_ratio = ratio.trim().parse()
+                .map_err(|_| TelemetryError::InvalidConfig(format!("OTEL_TRACES_SAMPLER_ARG is not a number: {}", ratio)))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn with_log_filter(mut self, filter: &str) -> Self {
+        self.log_filter = filter.to_string();
+        self
+    }
+
+    pub fn with_otlp_endpoint(mut self, endpoint: &str) -> Self {
+        self.otlp_endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    pub fn with_sample_ratio(mut self, ratio: f64) -> Self {
+        self.sample_ratio = ratio;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), TelemetryError> {
+        if !(0.0..=1.0).contains(&self.sample_ratio) {
+            return Err(TelemetryError::InvalidConfig(format!("sample ratio must be between 0 and 1: {}", self.sample_ratio)));
+        }
+        if self.otlp_endpoint.is_some() && !cfg!(feature = "otlp") {
+            return Err(TelemetryError::ExporterUnavailable);
+        }
+        Ok(())
+    }
+}
+
+/// Telemetry setup errors
+#[derive(Debug, thiserror::Error)]
+pub enum TelemetryError {
+    #[error("Invalid telemetry configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("OTLP endpoint configured but fukurow-observability was built without the `otlp` feature")]
+    ExporterUnavailable,
+
+    #[error("Failed to start the OTLP exporter: {0}")]
+    Exporter(String),
+
+    #[error("A global tracing subscriber is already installed: {0}")]
+    AlreadyInitialized(String),
+}
+
+/// Keeps the exporter running; flushes pending spans when dropped
+#[must_use = "dropping the guard stops trace export"]
+pub struct TelemetryGuard {
+    exporting: bool,
+}
+
+impl TelemetryGuard {
+    /// Whether spans are sent to an OTLP collector
+    pub fn is_exporting(&self) -> bool {
+        self.exporting
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if self.exporting {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// Install the global subscriber (log output, plus OTLP export when configured)
+///
+/// OTLP のバッチ送信に Tokio ランタイムを使うため、ランタイム内で呼ぶこと
+pub fn init(config: &TelemetryConfig) -> Result<TelemetryGuard, TelemetryError> {
+    config.validate()?;
+    let filter = EnvFilter::try_new(&config.log_filter)
+        .map_err(|e| TelemetryError::InvalidConfig(format!("log filter {}: {}", config.log_filter, e)))?;
+    let exporter = otlp_layer(config)?;
+    let exporting = exporter.is_some();
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(exporter)
+        .try_init()
+        .map_err(|e| TelemetryError::AlreadyInitialized(e.to_string()))?;
+    Ok(TelemetryGuard { exporting })
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_layer<S>(
+    config: &TelemetryConfig,
+) -> Result<Option<tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>>, TelemetryError>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::Sampler;
+
+    let Some(endpoint) = &config.otlp_endpoint else { return Ok(None) };
+    opentelemetry::global::set_text_map_propagator(opentelemetry_sdk::propagation::TraceContextPropagator::new());
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint.clone()))
+        .with_trace_config(
+            opentelemetry_sdk::trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio))))
+                .with_resource(opentelemetry_sdk::Resource::new([opentelemetry::KeyValue::new(
+                    crate::tracing::attributes::SERVICE_NAME,
+                    config.service_name.clone(),
+                )])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .map_err(|e| TelemetryError::Exporter(e.to_string()))?;
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+#[cfg(not(feature = "otlp"))]
+fn otlp_layer(_config: &TelemetryConfig) -> Result<Option<tracing_subscriber::layer::Identity>, TelemetryError> {
+    Ok(None)
+}
+
+/// Continue the trace of an incoming request (`traceparent` / `tracestate` headers) in `span`
+///
+/// ヘッダー名は小文字で渡す。エクスポートが無効なら何もしない
+pub fn set_remote_parent(span: &tracing::Span, headers: &HashMap<String, String>) {
+    #[cfg(feature = "otlp")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        let parent = opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(headers));
+        span.set_parent(parent);
+    }
+    #[cfg(not(feature = "otlp"))]
+    let _ = (span, headers);
+}
+
+/// W3C trace context headers of `span` for an outgoing request (empty when export is disabled)
+pub fn trace_headers(span: &tracing::Span) -> HashMap<String, String> {
+    #[cfg(feature = "otlp")]
+    let headers = {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        let context = span.context();
+        let mut headers = HashMap::new();
+        opentelemetry::global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut headers));
+        headers
+    };
+    #[cfg(not(feature = "otlp"))]
+    let headers = {
+        let _ = span;
+        HashMap::new()
+    };
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_environment_variables() {
+        let vars: HashMap<&str, &str> = [
+            ("OTEL_SERVICE_NAME", "fukurow-api"),
+            ("OTEL_TRACES_SAMPLER_ARG", "0.25"),
+            ("RUST_LOG", "fukurow=debug"),
+        ].into_iter().collect();
+        let config = TelemetryConfig::from_vars("fukurow", |name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(config.service_name, "fukurow-api");
+        assert_eq!(config.sample_ratio, 0.25);
+        assert_eq!(config.log_filter, "fukurow=debug");
+        assert_eq!(config.otlp_endpoint, None);
+
+        let invalid = TelemetryConfig::from_vars("fukurow", |name| (name == "OTEL_TRACES_SAMPLER_ARG").then(|| "2".to_string()));
+        assert!(matches!(invalid, Err(TelemetryError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_otlp_endpoint_requires_feature() {
+        let config = TelemetryConfig::new("fukurow").with_otlp_endpoint("http://tempo:4317");
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "otlp"));
+        assert!(trace_headers(&tracing::Span::none()).is_empty());
+    }
+}
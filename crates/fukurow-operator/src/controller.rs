@@ -2,9 +2,10 @@
 //!
 //! Controller logic for managing FukurowCluster resources
 
-use crate::crds::{FukurowCluster, FukurowClusterStatus, ClusterPhase, ClusterCondition};
+use crate::crds::{FukurowCluster, FukurowClusterStatus, FukurowRuleSet, ClusterPhase, ClusterCondition};
 use crate::reconciler::FukurowReconciler;
-use futures::{StreamExt, TryStreamExt};
+use crate::ruleset::RuleSetReconciler;
+use futures::{FutureExt, StreamExt, TryStreamExt};
 use kube::api::{Api, ResourceExt};
 use kube::runtime::controller::{Action, Controller};
 use kube::runtime::{watcher, WatchStreamExt};
@@ -17,12 +18,19 @@ use tracing::{error, info, warn};
 pub struct FukurowController {
     client: Client,
     reconciler: Arc<FukurowReconciler>,
+    rule_set_reconciler: Option<Arc<RuleSetReconciler>>,
 }
 
 impl FukurowController {
     /// Create a new controller instance
     pub fn new(client: Client, reconciler: Arc<FukurowReconciler>) -> Self {
-        Self { client, reconciler }
+        Self { client, reconciler, rule_set_reconciler: None }
+    }
+
+    /// Also reconcile FukurowRuleSet resources
+    pub fn with_rule_sets(mut self, reconciler: Arc<RuleSetReconciler>) -> Self {
+        self.rule_set_reconciler = Some(reconciler);
+        self
     }
 
     /// Run the controller
@@ -58,28 +66,59 @@ impl FukurowController {
             )
             .for_each(|_| futures::future::ready(()));
 
+        // Rule set distribution runs next to the cluster controller
+        let rule_sets = match self.rule_set_reconciler.clone() {
+            Some(rule_set_reconciler) => {
+                let api: Api<FukurowRuleSet> = Api::all(self.client.clone());
+                Controller::new(api, watcher::Config::default())
+                    .run(
+                        move |rule_set, _ctx| {
+                            let reconciler = Arc::clone(&rule_set_reconciler);
+                            async move {
+                                match reconciler.reconcile(rule_set).await {
+                                    Ok(action) => action,
+                                    Err(e) => {
+                                        error!("Rule set reconciliation failed: {}", e);
+                                        Action::requeue(Duration::from_secs(30))
+                                    }
+                                }
+                            }
+                        },
+                        |_rule_set, _err, _ctx| {
+                            warn!("Rule set reconciliation error: {}", _err);
+                            Action::requeue(Duration::from_secs(30))
+                        },
+                        watcher::Config::default(),
+                    )
+                    .for_each(|_| futures::future::ready(()))
+                    .boxed()
+            }
+            None => futures::future::ready(()).boxed(),
+        };
+
         info!("Controller started successfully");
-        controller.await;
+        futures::future::join(controller, rule_sets).await;
         Ok(())
     }
 
     /// Install the CRD if it doesn't exist
     async fn install_crd(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let crd = FukurowCluster::crd();
         let crds: Api<k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition> =
             Api::all(self.client.clone());
 
-        match crds.get(&crd.metadata.name).await {
-            Ok(_) => {
-                info!("CRD {} already exists", crd.metadata.name);
-            }
-            Err(kube::Error::Api(e)) if e.code == 404 => {
-                info!("Installing CRD {}", crd.metadata.name);
-                crds.create(&Default::default(), &crd).await?;
-                info!("CRD {} installed successfully", crd.metadata.name);
-            }
-            Err(e) => {
-                return Err(Box::new(e));
+        for crd in [FukurowCluster::crd(), FukurowRuleSet::crd()] {
+            match crds.get(&crd.metadata.name).await {
+                Ok(_) => {
+                    info!("CRD {} already exists", crd.metadata.name);
+                }
+                Err(kube::Error::Api(e)) if e.code == 404 => {
+                    info!("Installing CRD {}", crd.metadata.name);
+                    crds.create(&Default::default(), &crd).await?;
+                    info!("CRD {} installed successfully", crd.metadata.name);
+                }
+                Err(e) => {
+                    return Err(Box::new(e));
+                }
             }
         }
 
@@ -281,4 +320,12 @@ mod tests {
         assert_eq!(crd.spec.names.kind, "FukurowCluster");
         assert_eq!(crd.spec.names.plural, "fukurowclusters");
     }
+
+    #[test]
+    fn test_rule_set_crd_generation() {
+        let crd = FukurowRuleSet::crd();
+        assert_eq!(crd.metadata.name, "fukurowrulesets.fukurow.io");
+        assert_eq!(crd.spec.names.kind, "FukurowRuleSet");
+        assert_eq!(crd.spec.names.plural, "fukurowrulesets");
+    }
 }
@@ -5,7 +5,7 @@
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// FukurowCluster CRD - Main cluster resource
 #[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -559,6 +559,211 @@ pub struct ClusterCondition {
     pub message: String,
 }
 
+/// FukurowRuleSet CRD - Rule DSL files and ontologies distributed to a cluster
+///
+/// ルール・オントロジーを ConfigMap (または既存のボリューム) として `clusterRef` の Pod にマウントし、
+/// 各 Pod の管理 API でホットリロードさせる。どのレプリカがどのリビジョンを読み込んだかを status に報告する
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema, Default)]
+#[kube(
+    group = "fukurow.io",
+    version = "v1",
+    kind = "FukurowRuleSet",
+    plural = "fukurowrulesets",
+    derive = "Default",
+    namespaced
+)]
+#[kube(status = "FukurowRuleSetStatus")]
+#[serde(rename_all = "camelCase")]
+pub struct FukurowRuleSetSpec {
+    /// Name of the FukurowCluster in the same namespace
+    pub cluster_ref: String,
+
+    /// Revision label; defaults to a hash of the rule set contents
+    pub revision: Option<String>,
+
+    /// Rule DSL files
+    #[serde(default)]
+    pub rules: Vec<RuleSource>,
+
+    /// Ontology files (Turtle, JSON-LD, ...)
+    #[serde(default)]
+    pub ontologies: Vec<RuleSource>,
+
+    /// How the files reach the pods
+    #[serde(default)]
+    pub delivery: DeliverySpec,
+
+    /// Hot reload through the admin API
+    #[serde(default)]
+    pub reload: ReloadSpec,
+}
+
+/// One file of a rule set
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleSource {
+    /// File name inside the mounted directory
+    pub name: String,
+
+    /// Inline file contents
+    pub inline: Option<String>,
+
+    /// Key of an existing ConfigMap holding the contents
+    pub config_map_key_ref: Option<ConfigMapKeyRef>,
+}
+
+/// Reference to a key of a ConfigMap
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigMapKeyRef {
+    pub name: String,
+    pub key: String,
+}
+
+/// Reference to a key of a Secret
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretKeyRef {
+    pub name: String,
+    pub key: String,
+}
+
+/// Rule set delivery configuration
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliverySpec {
+    /// Delivery mode
+    #[serde(default)]
+    pub mode: DeliveryMode,
+
+    /// Existing volume claim holding the files (`volume` mode only)
+    pub claim_name: Option<String>,
+
+    /// Directory inside the claim (`volume` mode only)
+    pub sub_path: Option<String>,
+
+    /// Mount path inside the pods (defaults to `/app/rulesets/<name>`)
+    pub mount_path: Option<String>,
+}
+
+/// Delivery mode
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, Default, PartialEq)]
+pub enum DeliveryMode {
+    /// The operator renders the files into a ConfigMap
+    #[default]
+    #[serde(rename = "configMap")]
+    ConfigMap,
+
+    /// The files are already on a persistent volume; `revision` must be set
+    #[serde(rename = "volume")]
+    Volume,
+}
+
+/// Hot reload configuration
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadSpec {
+    /// Ask every replica to reload after the files change
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Admin API path of the reload endpoint
+    #[serde(default = "default_reload_path")]
+    pub path: String,
+
+    /// Secret holding an admin API key (sent as `X-API-Key`)
+    pub api_key_secret_ref: Option<SecretKeyRef>,
+
+    /// Per-replica request timeout in seconds
+    #[serde(default = "default_reload_timeout")]
+    pub timeout_seconds: u32,
+}
+
+impl Default for ReloadSpec {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: default_reload_path(),
+            api_key_secret_ref: None,
+            timeout_seconds: default_reload_timeout(),
+        }
+    }
+}
+
+fn default_reload_path() -> String {
+    "/admin/reload".to_string()
+}
+
+fn default_reload_timeout() -> u32 {
+    10
+}
+
+/// Rule set rollout status
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FukurowRuleSetStatus {
+    /// Current phase
+    pub phase: RuleSetPhase,
+
+    /// Revision being rolled out
+    pub revision: Option<String>,
+
+    /// Number of replicas of the target cluster
+    pub replicas: u32,
+
+    /// Replicas that loaded `revision`
+    pub updated_replicas: u32,
+
+    /// Revision → number of replicas that loaded it
+    #[serde(default)]
+    pub replica_revisions: BTreeMap<String, u32>,
+
+    /// Per-replica reload results
+    #[serde(default)]
+    pub pods: Vec<ReplicaRuleSetStatus>,
+
+    /// Conditions
+    #[serde(default)]
+    pub conditions: Vec<ClusterCondition>,
+
+    /// Generation of the spec this status describes
+    pub observed_generation: Option<i64>,
+
+    /// Last update timestamp
+    pub last_update: Option<String>,
+}
+
+/// Reload result of one replica
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaRuleSetStatus {
+    /// Pod name
+    pub pod: String,
+
+    /// Revision the replica reports as loaded
+    pub revision: Option<String>,
+
+    /// Reload error, if any
+    pub error: Option<String>,
+}
+
+/// Rule set phase
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, Default, PartialEq)]
+pub enum RuleSetPhase {
+    #[default]
+    #[serde(rename = "Pending")]
+    Pending,
+
+    #[serde(rename = "RollingOut")]
+    RollingOut,
+
+    #[serde(rename = "Ready")]
+    Ready,
+
+    #[serde(rename = "Failed")]
+    Failed,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -617,6 +822,20 @@ mod tests {
         assert_eq!(deserialized.config.server.port, 3000);
     }
 
+    #[test]
+    fn test_rule_set_spec_defaults() {
+        let spec: FukurowRuleSetSpec = serde_json::from_value(serde_json::json!({
+            "clusterRef": "prod",
+            "rules": [{ "name": "lateral-movement.yaml", "inline": "policies: []" }],
+        })).unwrap();
+
+        assert_eq!(spec.cluster_ref, "prod");
+        assert_eq!(spec.delivery.mode, DeliveryMode::ConfigMap);
+        assert!(spec.reload.enabled);
+        assert_eq!(spec.reload.path, "/admin/reload");
+        assert!(spec.ontologies.is_empty());
+    }
+
     #[test]
     fn test_cluster_status_default() {
         let status = FukurowClusterStatus::default();
@@ -1,17 +1,20 @@
 //! # Fukurow Kubernetes Operator
 //!
 //! Kubernetes operator for deploying and managing Fukurow reasoning engine clusters.
-//! Provides automated scaling, monitoring, and lifecycle management.
+//! Provides automated scaling, monitoring, and lifecycle management, and distributes
+//! rule sets and ontologies (FukurowRuleSet) to running clusters with hot reload.
 
 pub mod crds;
 pub mod controller;
 pub mod manager;
 pub mod reconciler;
+pub mod ruleset;
 
 pub use crds::*;
 pub use controller::*;
 pub use manager::*;
 pub use reconciler::*;
+pub use ruleset::*;
 
 /// Operator configuration
 #[derive(Debug, Clone)]
@@ -2,7 +2,7 @@
 //!
 //! Main manager for the Fukurow Kubernetes operator
 
-use crate::{Controller, FukurowReconciler, OperatorConfig, StatusUpdater};
+use crate::{Controller, FukurowReconciler, OperatorConfig, RuleSetReconciler, StatusUpdater};
 use kube::Client;
 use std::sync::Arc;
 use tokio::signal;
@@ -14,6 +14,7 @@ pub struct OperatorManager {
     config: OperatorConfig,
     client: Client,
     reconciler: Arc<FukurowReconciler>,
+    rule_set_reconciler: Arc<RuleSetReconciler>,
     status_updater: StatusUpdater,
     shutdown_tx: broadcast::Sender<()>,
 }
@@ -23,6 +24,7 @@ impl OperatorManager {
     pub async fn new(config: OperatorConfig) -> Result<Self, Box<dyn std::error::Error>> {
         let client = Client::try_default().await?;
         let reconciler = Arc::new(FukurowReconciler::new(client.clone(), config.clone()));
+        let rule_set_reconciler = Arc::new(RuleSetReconciler::new(client.clone(), config.clone()));
         let status_updater = StatusUpdater::new(client.clone());
         let (shutdown_tx, _) = broadcast::channel(1);
 
@@ -30,6 +32,7 @@ impl OperatorManager {
             config,
             client,
             reconciler,
+            rule_set_reconciler,
             status_updater,
             shutdown_tx,
         })
@@ -44,7 +47,8 @@ impl OperatorManager {
         let controller = Controller::new(
             self.client.clone(),
             Arc::clone(&self.reconciler),
-        );
+        )
+        .with_rule_sets(Arc::clone(&self.rule_set_reconciler));
 
         // Start health check server
         let health_handle = self.start_health_server();
@@ -2,7 +2,8 @@
 //!
 //! Reconciliation logic for FukurowCluster resources
 
-use crate::crds::{FukurowCluster, FukurowClusterStatus, ClusterPhase, ClusterCondition};
+use crate::crds::{FukurowCluster, FukurowClusterStatus, FukurowRuleSet, ClusterPhase, ClusterCondition};
+use crate::ruleset::rule_set_volume;
 use crate::OperatorConfig;
 use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec, DeploymentStatus};
 use k8s_openapi::api::core::v1::{
@@ -156,7 +157,18 @@ impl FukurowReconciler {
             &cluster.metadata.namespace.as_ref().unwrap_or(&"default".to_string()),
         );
 
-        let deployment = self.create_deployment(cluster);
+        // Rule sets targeting this cluster keep their volumes across replacements
+        let rule_set_api: Api<FukurowRuleSet> = Api::namespaced(
+            self.client.clone(),
+            &cluster.metadata.namespace.as_ref().unwrap_or(&"default".to_string()),
+        );
+        let rule_sets = rule_set_api.list(&ListParams::default()).await?
+            .items
+            .into_iter()
+            .filter(|rule_set| Some(&rule_set.spec.cluster_ref) == cluster.metadata.name.as_ref())
+            .collect::<Vec<_>>();
+
+        let deployment = self.create_deployment(cluster, &rule_sets);
         let deploy_name = cluster.metadata.name.clone();
 
         self.apply_resource(deploy_api, &deploy_name, deployment).await?;
@@ -241,7 +253,7 @@ impl FukurowReconciler {
     }
 
     /// Create Deployment
-    fn create_deployment(&self, cluster: &FukurowCluster) -> Deployment {
+    fn create_deployment(&self, cluster: &FukurowCluster, rule_sets: &[FukurowRuleSet]) -> Deployment {
         let labels = self.cluster_labels(cluster);
         let image = format!(
             "{}/{}:{}",
@@ -258,6 +270,8 @@ impl FukurowReconciler {
             })
             .collect::<Vec<_>>();
 
+        let rule_set_volumes = rule_sets.iter().map(rule_set_volume).collect::<Vec<_>>();
+
         let container = Container {
             name: "fukurow".to_string(),
             image: Some(image),
@@ -273,24 +287,24 @@ impl FukurowReconciler {
             }]),
             env: Some(env_vars),
             resources: Some(self.create_resource_requirements(&cluster.spec.resources)),
-            volume_mounts: Some(vec![VolumeMount {
+            volume_mounts: Some(std::iter::once(VolumeMount {
                 name: "config".to_string(),
                 mount_path: "/app/config".to_string(),
                 ..Default::default()
-            }]),
+            }).chain(rule_set_volumes.iter().map(|(_, mount)| mount.clone())).collect()),
             ..Default::default()
         };
 
         let pod_spec = PodSpec {
             containers: vec![container],
-            volumes: Some(vec![Volume {
+            volumes: Some(std::iter::once(Volume {
                 name: "config".to_string(),
                 config_map: Some(k8s_openapi::api::core::v1::ConfigMapVolumeSource {
                     name: Some(format!("{}-config", cluster.metadata.name)),
                     ..Default::default()
                 }),
                 ..Default::default()
-            }]),
+            }).chain(rule_set_volumes.into_iter().map(|(volume, _)| volume)).collect()),
             ..Default::default()
         };
 
@@ -0,0 +1,566 @@
+//! # Rule Set Reconciler
+//!
+//! Distribution of FukurowRuleSet resources to the pods of a FukurowCluster
+//!
+//! ルール DSL とオントロジーを `<ruleset>-ruleset` ConfigMap に書き出し (または既存のボリュームを使い)、
+//! クラスタの Deployment にマウントする。ファイルの更新は Pod を再起動せずに反映させるため、
+//! 各レプリカの管理 API に `POST {reload.path}` を送り、応答のリビジョンを status に集計する。
+//! kubelet が ConfigMap の変更をボリュームに同期するまでは古いリビジョンが返るので、
+//! 全レプリカが揃うまで短い間隔で再キューする
+
+use crate::crds::{
+    ClusterCondition, DeliveryMode, FukurowRuleSet, FukurowRuleSetStatus, ReplicaRuleSetStatus,
+    RuleSetPhase, RuleSource,
+};
+use crate::OperatorConfig;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{
+    ConfigMap, ConfigMapVolumeSource, KeyToPath, PersistentVolumeClaimVolumeSource, Pod, Secret,
+    Volume, VolumeMount,
+};
+use kube::api::{Api, ListParams, Patch, PatchParams, PostParams};
+use kube::runtime::controller::Action;
+use kube::Client;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+/// Annotation carrying the revision of a rendered rule set ConfigMap
+pub const REVISION_ANNOTATION: &str = "fukurow.io/ruleset-revision";
+
+/// File holding the revision inside the mounted directory
+///
+/// リロードした Pod はこのファイルを読んで読み込んだリビジョンを応答する
+pub const REVISION_FILE: &str = "REVISION";
+
+/// Directory rule sets are mounted under unless `delivery.mountPath` is set
+pub const DEFAULT_RULESET_MOUNT_ROOT: &str = "/app/rulesets";
+
+/// Requeue interval while replicas are still on an older revision
+const ROLLOUT_REQUEUE: Duration = Duration::from_secs(15);
+
+/// Reconciler for FukurowRuleSet resources
+pub struct RuleSetReconciler {
+    client: Client,
+    config: OperatorConfig,
+    http: reqwest::Client,
+}
+
+impl RuleSetReconciler {
+    pub fn new(client: Client, config: OperatorConfig) -> Self {
+        Self { client, config, http: reqwest::Client::new() }
+    }
+
+    /// Main reconciliation logic
+    pub async fn reconcile(&self, rule_set: Arc<FukurowRuleSet>) -> Result<Action, Box<dyn std::error::Error>> {
+        let namespace = rule_set.metadata.namespace.clone().unwrap_or_else(|| self.config.namespace.clone());
+        let name = rule_set.metadata.name.clone().unwrap_or_default();
+        info!("Reconciling FukurowRuleSet {}/{}", namespace, name);
+
+        // Resolve file contents and revision
+        let files = match self.resolve_files(&namespace, &rule_set).await {
+            Ok(files) => files,
+            Err(e) => {
+                self.update_status(&rule_set, failed_status(&rule_set, &e.to_string())).await?;
+                return Ok(Action::requeue(Duration::from_secs(60)));
+            }
+        };
+        let revision = match (&rule_set.spec.revision, &rule_set.spec.delivery.mode) {
+            (Some(revision), _) => revision.clone(),
+            (None, DeliveryMode::ConfigMap) => content_revision(&files),
+            (None, DeliveryMode::Volume) => {
+                let message = "spec.revision is required for volume delivery";
+                self.update_status(&rule_set, failed_status(&rule_set, message)).await?;
+                return Ok(Action::await_change());
+            }
+        };
+
+        // Render the ConfigMap
+        if rule_set.spec.delivery.mode == DeliveryMode::ConfigMap {
+            self.reconcile_config_map(&namespace, &rule_set, &files, &revision).await?;
+        }
+
+        // Mount into the cluster's pods
+        self.mount_into_cluster(&namespace, &rule_set).await?;
+
+        // Hot reload and collect the loaded revisions
+        let pods = self.reload_replicas(&namespace, &rule_set, &revision).await?;
+        let status = rollout_status(&rule_set, &revision, pods);
+        let complete = status.phase == RuleSetPhase::Ready;
+        self.update_status(&rule_set, status).await?;
+
+        if complete {
+            Ok(Action::requeue(Duration::from_secs(300))) // 5 minutes
+        } else {
+            Ok(Action::requeue(ROLLOUT_REQUEUE))
+        }
+    }
+
+    /// File name → contents of every rule and ontology file
+    async fn resolve_files(&self, namespace: &str, rule_set: &FukurowRuleSet) -> Result<RuleSetFiles, Box<dyn std::error::Error>> {
+        let cm_api: Api<ConfigMap> = Api::namespaced(self.client.clone(), namespace);
+        let mut files = RuleSetFiles::default();
+
+        for (sources, target) in [(&rule_set.spec.rules, &mut files.rules), (&rule_set.spec.ontologies, &mut files.ontologies)] {
+            for source in sources {
+                let contents = match (&source.inline, &source.config_map_key_ref) {
+                    (Some(inline), _) => inline.clone(),
+                    (None, Some(key_ref)) => cm_api.get(&key_ref.name).await?
+                        .data
+                        .and_then(|mut data| data.remove(&key_ref.key))
+                        .ok_or_else(|| format!("ConfigMap {} has no key {}", key_ref.name, key_ref.key))?,
+                    (None, None) if rule_set.spec.delivery.mode == DeliveryMode::Volume => continue,
+                    (None, None) => return Err(format!("{} has neither inline contents nor a configMapKeyRef", source.name).into()),
+                };
+                target.insert(source.name.clone(), contents);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Render the rule set into `<name>-ruleset`
+    async fn reconcile_config_map(
+        &self,
+        namespace: &str,
+        rule_set: &FukurowRuleSet,
+        files: &RuleSetFiles,
+        revision: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cm_api: Api<ConfigMap> = Api::namespaced(self.client.clone(), namespace);
+        let cm_name = config_map_name(rule_set);
+
+        let config_map = ConfigMap {
+            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
+                name: Some(cm_name.clone()),
+                namespace: Some(namespace.to_string()),
+                labels: Some(rule_set_labels(rule_set).into_iter().collect()),
+                annotations: Some(BTreeMap::from([(REVISION_ANNOTATION.to_string(), revision.to_string())])),
+                owner_references: Some(vec![k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference {
+                    api_version: "fukurow.io/v1".to_string(),
+                    kind: "FukurowRuleSet".to_string(),
+                    name: rule_set.metadata.name.clone().unwrap_or_default(),
+                    uid: rule_set.metadata.uid.clone().unwrap_or_default(),
+                    controller: Some(true),
+                    block_owner_deletion: Some(true),
+                }]),
+                ..Default::default()
+            },
+            data: Some(config_map_data(files, revision)),
+            ..Default::default()
+        };
+
+        match cm_api.get(&cm_name).await {
+            Ok(_) => {
+                cm_api.replace(&cm_name, &PostParams::default(), &config_map).await?;
+                info!("Updated ConfigMap {} (revision {})", cm_name, revision);
+            }
+            Err(kube::Error::Api(e)) if e.code == 404 => {
+                cm_api.create(&PostParams::default(), &config_map).await?;
+                info!("Created ConfigMap {} (revision {})", cm_name, revision);
+            }
+            Err(e) => return Err(Box::new(e)),
+        }
+        Ok(())
+    }
+
+    /// Add the rule set volume to the cluster Deployment
+    ///
+    /// strategic merge patch は volumes / volumeMounts を名前でマージするので、
+    /// 既にマウント済みなら Pod テンプレートは変わらず再起動も起きない
+    async fn mount_into_cluster(&self, namespace: &str, rule_set: &FukurowRuleSet) -> Result<(), Box<dyn std::error::Error>> {
+        let deploy_api: Api<Deployment> = Api::namespaced(self.client.clone(), namespace);
+        let (volume, mount) = rule_set_volume(rule_set);
+
+        let patch = json!({
+            "spec": {
+                "template": {
+                    "spec": {
+                        "volumes": [volume],
+                        "containers": [{
+                            "name": "fukurow",
+                            "volumeMounts": [mount],
+                        }],
+                    }
+                }
+            }
+        });
+
+        deploy_api.patch(
+            &rule_set.spec.cluster_ref,
+            &PatchParams::default(),
+            &Patch::Strategic(patch),
+        ).await?;
+        Ok(())
+    }
+
+    /// Ask every running replica to reload and return what each one loaded
+    async fn reload_replicas(
+        &self,
+        namespace: &str,
+        rule_set: &FukurowRuleSet,
+        revision: &str,
+    ) -> Result<Vec<ReplicaRuleSetStatus>, Box<dyn std::error::Error>> {
+        let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+        let selector = format!("app.kubernetes.io/instance={}", rule_set.spec.cluster_ref);
+        let pods = pod_api.list(&ListParams::default().labels(&selector)).await?;
+
+        let cluster_api: Api<crate::crds::FukurowCluster> = Api::namespaced(self.client.clone(), namespace);
+        let port = cluster_api.get(&rule_set.spec.cluster_ref).await?.spec.config.server.port;
+        let api_key = self.reload_api_key(namespace, rule_set).await?;
+        let mount_path = mount_path(rule_set);
+
+        let mut statuses = Vec::new();
+        for pod in pods.items {
+            let pod_name = pod.metadata.name.clone().unwrap_or_default();
+            let running = pod.status.as_ref().and_then(|s| s.phase.as_deref()) == Some("Running");
+            let Some(ip) = pod.status.as_ref().and_then(|s| s.pod_ip.clone()).filter(|_| running) else {
+                statuses.push(ReplicaRuleSetStatus { pod: pod_name, revision: None, error: Some("pod is not running".to_string()) });
+                continue;
+            };
+            if !rule_set.spec.reload.enabled {
+                statuses.push(ReplicaRuleSetStatus { pod: pod_name, revision: None, error: None });
+                continue;
+            }
+
+            let url = format!("http://{}:{}{}", ip, port, rule_set.spec.reload.path);
+            let body = json!({
+                "ruleSet": rule_set.metadata.name,
+                "revision": revision,
+                "rulesDir": format!("{}/rules", mount_path),
+                "ontologiesDir": format!("{}/ontologies", mount_path),
+            });
+            let mut request = self.http.post(&url)
+                .timeout(Duration::from_secs(rule_set.spec.reload.timeout_seconds as u64))
+                .json(&body);
+            if let Some(key) = &api_key {
+                request = request.header("x-api-key", key);
+            }
+
+            let status = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    let loaded = response.json::<serde_json::Value>().await.ok()
+                        .and_then(|body| reported_revision(&body));
+                    ReplicaRuleSetStatus { pod: pod_name, revision: loaded, error: None }
+                }
+                Ok(response) => ReplicaRuleSetStatus {
+                    pod: pod_name,
+                    revision: None,
+                    error: Some(format!("reload returned {}", response.status())),
+                },
+                Err(e) => {
+                    warn!("Reload of {} failed: {}", pod_name, e);
+                    ReplicaRuleSetStatus { pod: pod_name, revision: None, error: Some(e.to_string()) }
+                }
+            };
+            statuses.push(status);
+        }
+
+        Ok(statuses)
+    }
+
+    async fn reload_api_key(&self, namespace: &str, rule_set: &FukurowRuleSet) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(key_ref) = &rule_set.spec.reload.api_key_secret_ref else {
+            return Ok(None);
+        };
+        let secret_api: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+        let key = secret_api.get(&key_ref.name).await?
+            .data
+            .and_then(|mut data| data.remove(&key_ref.key))
+            .ok_or_else(|| format!("Secret {} has no key {}", key_ref.name, key_ref.key))?;
+        Ok(Some(String::from_utf8(key.0)?))
+    }
+
+    /// Update rule set status
+    async fn update_status(&self, rule_set: &FukurowRuleSet, status: FukurowRuleSetStatus) -> Result<(), Box<dyn std::error::Error>> {
+        let api: Api<FukurowRuleSet> = Api::namespaced(
+            self.client.clone(),
+            rule_set.metadata.namespace.as_ref().unwrap_or(&self.config.namespace),
+        );
+
+        let mut patch = rule_set.clone();
+        patch.status = Some(status);
+
+        api.replace_status(
+            rule_set.metadata.name.as_deref().unwrap_or_default(),
+            &Default::default(),
+            serde_json::to_vec(&patch)?,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Resolved contents of a rule set (file name → contents)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleSetFiles {
+    pub rules: BTreeMap<String, String>,
+    pub ontologies: BTreeMap<String, String>,
+}
+
+/// Revision derived from the file names and contents (FNV-1a, stable across operator versions)
+pub fn content_revision(files: &RuleSetFiles) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        // 区切りを入れて "ab" + "c" と "a" + "bc" を区別する
+        hash ^= 0xff;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    };
+    for (kind, entries) in [("rules", &files.rules), ("ontologies", &files.ontologies)] {
+        for (name, contents) in entries {
+            feed(kind.as_bytes());
+            feed(name.as_bytes());
+            feed(contents.as_bytes());
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// ConfigMap keys of a rendered rule set
+///
+/// ConfigMap のキーには `/` を使えないため `rules.<file>` / `ontologies.<file>` とし、
+/// ボリュームの items で `rules/<file>` / `ontologies/<file>` に配置する
+pub fn config_map_data(files: &RuleSetFiles, revision: &str) -> BTreeMap<String, String> {
+    let mut data = BTreeMap::from([(REVISION_FILE.to_string(), revision.to_string())]);
+    for (name, contents) in &files.rules {
+        data.insert(format!("rules.{}", name), contents.clone());
+    }
+    for (name, contents) in &files.ontologies {
+        data.insert(format!("ontologies.{}", name), contents.clone());
+    }
+    data
+}
+
+/// Volume and mount of a rule set in the cluster pods
+pub fn rule_set_volume(rule_set: &FukurowRuleSet) -> (Volume, VolumeMount) {
+    let volume_name = format!("ruleset-{}", rule_set.metadata.name.as_deref().unwrap_or_default());
+    let delivery = &rule_set.spec.delivery;
+
+    let volume = match delivery.mode {
+        DeliveryMode::ConfigMap => {
+            let items = [("rules", &rule_set.spec.rules), ("ontologies", &rule_set.spec.ontologies)]
+                .into_iter()
+                .flat_map(|(dir, sources)| sources.iter().map(move |source: &RuleSource| KeyToPath {
+                    key: format!("{}.{}", dir, source.name),
+                    path: format!("{}/{}", dir, source.name),
+                    ..Default::default()
+                }))
+                .chain(std::iter::once(KeyToPath {
+                    key: REVISION_FILE.to_string(),
+                    path: REVISION_FILE.to_string(),
+                    ..Default::default()
+                }))
+                .collect();
+            Volume {
+                name: volume_name.clone(),
+                config_map: Some(ConfigMapVolumeSource {
+                    name: Some(config_map_name(rule_set)),
+                    items: Some(items),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        }
+        DeliveryMode::Volume => Volume {
+            name: volume_name.clone(),
+            persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+                claim_name: delivery.claim_name.clone().unwrap_or_default(),
+                read_only: Some(true),
+            }),
+            ..Default::default()
+        },
+    };
+
+    let mount = VolumeMount {
+        name: volume_name,
+        mount_path: mount_path(rule_set),
+        sub_path: delivery.sub_path.clone().filter(|_| delivery.mode == DeliveryMode::Volume),
+        read_only: Some(true),
+        ..Default::default()
+    };
+
+    (volume, mount)
+}
+
+/// Status after a reload round
+///
+/// `revision` を読み込んだレプリカ数が全レプリカ数に達したら Ready
+pub fn rollout_status(rule_set: &FukurowRuleSet, revision: &str, pods: Vec<ReplicaRuleSetStatus>) -> FukurowRuleSetStatus {
+    let mut replica_revisions = BTreeMap::new();
+    for revision in pods.iter().filter_map(|pod| pod.revision.as_ref()) {
+        *replica_revisions.entry(revision.clone()).or_insert(0) += 1;
+    }
+    let replicas = pods.len() as u32;
+    let updated_replicas = replica_revisions.get(revision).copied().unwrap_or(0);
+    let failures = pods.iter().filter(|pod| pod.error.is_some()).count();
+
+    let (phase, reason, message) = if !rule_set.spec.reload.enabled {
+        (RuleSetPhase::Ready, "ReloadDisabled", format!("Revision {} mounted; reload is disabled", revision))
+    } else if replicas > 0 && updated_replicas == replicas {
+        (RuleSetPhase::Ready, "RolloutComplete", format!("{}/{} replicas loaded revision {}", updated_replicas, replicas, revision))
+    } else {
+        (RuleSetPhase::RollingOut, "RolloutInProgress", format!(
+            "{}/{} replicas loaded revision {} ({} reload failures)",
+            updated_replicas, replicas, revision, failures,
+        ))
+    };
+
+    let mut status = rule_set.status.clone().unwrap_or_default();
+    set_condition(&mut status.conditions, phase == RuleSetPhase::Ready, reason, message);
+    FukurowRuleSetStatus {
+        phase,
+        revision: Some(revision.to_string()),
+        replicas,
+        updated_replicas,
+        replica_revisions,
+        pods,
+        conditions: status.conditions,
+        observed_generation: rule_set.metadata.generation,
+        last_update: Some(chrono::Utc::now().to_rfc3339()),
+    }
+}
+
+fn failed_status(rule_set: &FukurowRuleSet, message: &str) -> FukurowRuleSetStatus {
+    let mut status = rule_set.status.clone().unwrap_or_default();
+    status.phase = RuleSetPhase::Failed;
+    status.observed_generation = rule_set.metadata.generation;
+    status.last_update = Some(chrono::Utc::now().to_rfc3339());
+    set_condition(&mut status.conditions, false, "InvalidSpec", message.to_string());
+    status
+}
+
+fn set_condition(conditions: &mut Vec<ClusterCondition>, ready: bool, reason: &str, message: String) {
+    let condition = ClusterCondition {
+        type_: "Ready".to_string(),
+        status: if ready { "True" } else { "False" }.to_string(),
+        last_transition_time: chrono::Utc::now().to_rfc3339(),
+        reason: reason.to_string(),
+        message,
+    };
+    match conditions.iter_mut().find(|c| c.type_ == condition.type_) {
+        // 状態が変わらないときは遷移時刻を保つ
+        Some(existing) if existing.status == condition.status => {
+            existing.reason = condition.reason;
+            existing.message = condition.message;
+        }
+        Some(existing) => *existing = condition,
+        None => conditions.push(condition),
+    }
+}
+
+/// Revision in a reload response (`{"revision": ...}` or `{"data": {"revision": ...}}`)
+fn reported_revision(body: &serde_json::Value) -> Option<String> {
+    body.get("revision")
+        .or_else(|| body.get("data").and_then(|data| data.get("revision")))
+        .and_then(|revision| revision.as_str())
+        .map(str::to_string)
+}
+
+fn config_map_name(rule_set: &FukurowRuleSet) -> String {
+    format!("{}-ruleset", rule_set.metadata.name.as_deref().unwrap_or_default())
+}
+
+fn mount_path(rule_set: &FukurowRuleSet) -> String {
+    rule_set.spec.delivery.mount_path.clone().unwrap_or_else(|| {
+        format!("{}/{}", DEFAULT_RULESET_MOUNT_ROOT, rule_set.metadata.name.as_deref().unwrap_or_default())
+    })
+}
+
+fn rule_set_labels(rule_set: &FukurowRuleSet) -> HashMap<String, String> {
+    HashMap::from([
+        ("app.kubernetes.io/name".to_string(), "fukurow".to_string()),
+        ("app.kubernetes.io/instance".to_string(), rule_set.spec.cluster_ref.clone()),
+        ("app.kubernetes.io/component".to_string(), "ruleset".to_string()),
+        ("fukurow.io/ruleset".to_string(), rule_set.metadata.name.clone().unwrap_or_default()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crds::FukurowRuleSetSpec;
+
+    fn rule_set() -> FukurowRuleSet {
+        FukurowRuleSet {
+            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
+                name: Some("detections".to_string()),
+                namespace: Some("default".to_string()),
+                generation: Some(2),
+                ..Default::default()
+            },
+            spec: FukurowRuleSetSpec {
+                cluster_ref: "prod".to_string(),
+                rules: vec![RuleSource { name: "lateral.yaml".to_string(), inline: Some("policies: []".to_string()), config_map_key_ref: None }],
+                ontologies: vec![RuleSource { name: "cyber.ttl".to_string(), inline: Some("".to_string()), config_map_key_ref: None }],
+                ..Default::default()
+            },
+            status: None,
+        }
+    }
+
+    fn replica(pod: &str, revision: Option<&str>) -> ReplicaRuleSetStatus {
+        ReplicaRuleSetStatus { pod: pod.to_string(), revision: revision.map(str::to_string), error: None }
+    }
+
+    #[test]
+    fn test_content_revision_is_stable() {
+        let mut files = RuleSetFiles::default();
+        files.rules.insert("a.yaml".to_string(), "bc".to_string());
+        let revision = content_revision(&files);
+        assert_eq!(revision, content_revision(&files.clone()));
+        assert_eq!(revision.len(), 16);
+
+        let mut moved = RuleSetFiles::default();
+        moved.rules.insert("a.yamlb".to_string(), "c".to_string());
+        assert_ne!(content_revision(&moved), revision);
+    }
+
+    #[test]
+    fn test_config_map_volume_layout() {
+        let rule_set = rule_set();
+        let mut files = RuleSetFiles::default();
+        files.rules.insert("lateral.yaml".to_string(), "policies: []".to_string());
+        let data = config_map_data(&files, "r1");
+        assert_eq!(data.get(REVISION_FILE).map(String::as_str), Some("r1"));
+        assert!(data.contains_key("rules.lateral.yaml"));
+
+        let (volume, mount) = rule_set_volume(&rule_set);
+        let items = volume.config_map.unwrap().items.unwrap();
+        let paths: Vec<_> = items.iter().map(|item| item.path.as_str()).collect();
+        assert_eq!(paths, vec!["rules/lateral.yaml", "ontologies/cyber.ttl", REVISION_FILE]);
+        assert_eq!(mount.mount_path, "/app/rulesets/detections");
+        assert_eq!(mount.name, volume.name);
+    }
+
+    #[test]
+    fn test_rollout_status_counts_revisions() {
+        let rule_set = rule_set();
+        let status = rollout_status(&rule_set, "r2", vec![
+            replica("prod-0", Some("r2")),
+            replica("prod-1", Some("r1")),
+            ReplicaRuleSetStatus { pod: "prod-2".to_string(), revision: None, error: Some("timeout".to_string()) },
+        ]);
+        assert_eq!(status.phase, RuleSetPhase::RollingOut);
+        assert_eq!((status.replicas, status.updated_replicas), (3, 1));
+        assert_eq!(status.replica_revisions, BTreeMap::from([("r1".to_string(), 1), ("r2".to_string(), 1)]));
+        assert_eq!(status.observed_generation, Some(2));
+
+        let done = rollout_status(&rule_set, "r2", vec![replica("prod-0", Some("r2")), replica("prod-1", Some("r2"))]);
+        assert_eq!(done.phase, RuleSetPhase::Ready);
+        assert_eq!(done.conditions[0].status, "True");
+    }
+
+    #[test]
+    fn test_reported_revision() {
+        assert_eq!(reported_revision(&json!({ "revision": "r1" })).as_deref(), Some("r1"));
+        assert_eq!(reported_revision(&json!({ "success": true, "data": { "revision": "r2" } })).as_deref(), Some("r2"));
+        assert_eq!(reported_revision(&json!({ "success": true })), None);
+    }
+}
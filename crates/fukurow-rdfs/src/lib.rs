@@ -7,7 +7,8 @@
 //! - rdf:type の推論
 
 use fukurow_core::model::Triple;
-use fukurow_store::store::RdfStore;
+use fukurow_store::provenance::GraphId;
+use fukurow_store::store::{RdfStore, StoredTriple};
 use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
@@ -63,6 +64,10 @@ pub struct RdfsReasoner {
     range_constraints: HashMap<Iri, Iri>,
     /// 推論されたトリプルのキャッシュ
     inferred_triples: HashSet<Triple>,
+    /// 推論されたトリプルごとの根拠 (確信度の伝播に使う)
+    evidence: HashMap<Triple, Vec<Triple>>,
+    /// 入力として読まないグラフ (前回の推論結果を保持するグラフ)
+    excluded_graph: Option<GraphId>,
 }
 
 impl RdfsReasoner {
@@ -74,9 +79,20 @@ impl RdfsReasoner {
             domain_constraints: HashMap::new(),
             range_constraints: HashMap::new(),
             inferred_triples: HashSet::new(),
+            evidence: HashMap::new(),
+            excluded_graph: None,
         }
     }
 
+    /// Ignore the triples of `graph_id` when computing the closure
+    ///
+    /// 推論結果を書き戻すグラフを除外すれば、前回の推論が根拠を失った後も残り続けることはない
+    pub fn excluding_graph(mut self, graph_id: GraphId) -> Self {
+        self.excluded_graph = Some(graph_id);
+        self
+    }
+
+
     /// ストアから RDFS 知識を読み込んで推論を実行
     pub fn compute_closure(&mut self, store: &RdfStore) -> Result<Vec<Triple>, RdfsError> {
         self.load_knowledge(store);
@@ -88,39 +104,40 @@ impl RdfsReasoner {
 
     /// RDFS 知識をストアから読み込み
     fn load_knowledge(&mut self, store: &RdfStore) {
-        for stored_triple_vec in store.all_triples().values() {
+        let excluded = self.excluded_graph.clone();
+        for stored_triple_vec in source_graphs(store, excluded.as_ref()) {
             for stored_triple in stored_triple_vec {
                 let triple = &stored_triple.triple;
 
                 // rdfs:subClassOf 関係を読み込み
                 if triple.predicate == vocabulary::rdfs_subclass_of().as_str() {
                     self.class_hierarchy
-                        .entry(Iri::new(triple.subject.clone()))
+                        .entry(Iri::new(triple.subject.to_string()))
                         .or_insert_with(HashSet::new)
-                        .insert(Iri::new(triple.object.clone()));
+                        .insert(Iri::new(triple.object.to_string()));
                 }
 
                 // rdfs:subPropertyOf 関係を読み込み
                 if triple.predicate == vocabulary::rdfs_subproperty_of().as_str() {
                     self.property_hierarchy
-                        .entry(Iri::new(triple.subject.clone()))
+                        .entry(Iri::new(triple.subject.to_string()))
                         .or_insert_with(HashSet::new)
-                        .insert(Iri::new(triple.object.clone()));
+                        .insert(Iri::new(triple.object.to_string()));
                 }
 
                 // rdfs:domain 制約を読み込み
                 if triple.predicate == vocabulary::rdfs_domain().as_str() {
                     self.domain_constraints.insert(
-                        Iri::new(triple.subject.clone()),
-                        Iri::new(triple.object.clone()),
+                        Iri::new(triple.subject.to_string()),
+                        Iri::new(triple.object.to_string()),
                     );
                 }
 
                 // rdfs:range 制約を読み込み
                 if triple.predicate == vocabulary::rdfs_range().as_str() {
                     self.range_constraints.insert(
-                        Iri::new(triple.subject.clone()),
-                        Iri::new(triple.object.clone()),
+                        Iri::new(triple.subject.to_string()),
+                        Iri::new(triple.object.to_string()),
                     );
                 }
             }
@@ -138,29 +155,48 @@ impl RdfsReasoner {
         Self::compute_hierarchy_closure(&property_hierarchy_input, &mut self.property_hierarchy);
 
         // 推論されたトリプルを生成
-        for (child, parents) in &self.class_hierarchy {
-            for parent in parents {
-                if child != parent {  // 自己参照は除く
-                    self.inferred_triples.insert(Triple {
-                        subject: child.0.clone(),
-                        predicate: vocabulary::rdfs_subclass_of().as_str().to_string(),
-                        object: parent.0.clone(),
-                    });
-                }
-            }
+        let mut derived = Self::hierarchy_triples(&class_hierarchy_input, &self.class_hierarchy, vocabulary::RDFS_SUBCLASS_OF);
+        derived.extend(Self::hierarchy_triples(&property_hierarchy_input, &self.property_hierarchy, vocabulary::RDFS_SUBPROPERTY_OF));
+        for (triple, justification) in derived {
+            self.record(triple, justification);
         }
+    }
 
-        for (child, parents) in &self.property_hierarchy {
+    /// 閉包の各辺と、その根拠 (直接の辺と、経由先からの辺)
+    fn hierarchy_triples(direct: &HashMap<Iri, HashSet<Iri>>, closure: &HashMap<Iri, HashSet<Iri>>, predicate: &str) -> Vec<(Triple, Vec<Triple>)> {
+        let edge = |child: &Iri, parent: &Iri| Triple {
+            subject: child.0.clone(),
+            predicate: predicate.to_string(),
+            object: parent.0.clone(),
+        };
+
+        let mut triples = Vec::new();
+        for (child, parents) in closure {
             for parent in parents {
-                if child != parent {  // 自己参照は除く
-                    self.inferred_triples.insert(Triple {
-                        subject: child.0.clone(),
-                        predicate: vocabulary::rdfs_subproperty_of().as_str().to_string(),
-                        object: parent.0.clone(),
-                    });
+                if child == parent {  // 自己参照は除く
+                    continue;
                 }
+                let direct_parents = direct.get(child);
+                let justification = if direct_parents.is_some_and(|d| d.contains(parent)) {
+                    Vec::new()
+                } else {
+                    direct_parents.into_iter().flatten()
+                        .find(|via| closure.get(*via).is_some_and(|p| p.contains(parent)))
+                        .map(|via| vec![edge(child, via), edge(via, parent)])
+                        .unwrap_or_default()
+                };
+                triples.push((edge(child, parent), justification));
             }
         }
+        triples
+    }
+
+    /// 推論結果を根拠とともに記録する (最初に見つかった根拠を残す)
+    fn record(&mut self, triple: Triple, justification: Vec<Triple>) {
+        if !justification.is_empty() {
+            self.evidence.entry(triple.clone()).or_insert(justification);
+        }
+        self.inferred_triples.insert(triple);
     }
 
     /// 階層関係の推移的閉包を計算
@@ -190,18 +226,26 @@ impl RdfsReasoner {
 
     /// 型推論と制約に基づく推論を実行
     fn infer_types_and_constraints(&mut self, store: &RdfStore) {
+        let excluded = self.excluded_graph.clone();
+        let axiom = |property: &Iri, predicate: &str, class: &Iri| Triple {
+            subject: property.0.clone(),
+            predicate: predicate.to_string(),
+            object: class.0.clone(),
+        };
+        let mut constraint_inferences = Vec::new();
+
         // ドメイン制約に基づく rdf:type 推論
         for (property, class) in &self.domain_constraints {
             // このプロパティを使用している全ての主語に対して型を推論
-            for stored_triple_vec in store.all_triples().values() {
+            for stored_triple_vec in source_graphs(store, excluded.as_ref()) {
                 for stored_triple in stored_triple_vec {
                     let triple = &stored_triple.triple;
                     if triple.predicate == property.as_str() {
-                        self.inferred_triples.insert(Triple {
-                            subject: triple.subject.clone(),
+                        constraint_inferences.push((Triple {
+                            subject: triple.subject.to_string(),
                             predicate: vocabulary::rdf_type().as_str().to_string(),
                             object: class.0.clone(),
-                        });
+                        }, vec![triple.to_triple(), axiom(property, vocabulary::RDFS_DOMAIN, class)]));
                     }
                 }
             }
@@ -210,36 +254,40 @@ impl RdfsReasoner {
         // レンジ制約に基づく rdf:type 推論
         for (property, class) in &self.range_constraints {
             // このプロパティを使用している全ての目的語に対して型を推論
-            for stored_triple_vec in store.all_triples().values() {
+            for stored_triple_vec in source_graphs(store, excluded.as_ref()) {
                 for stored_triple in stored_triple_vec {
                     let triple = &stored_triple.triple;
                     if triple.predicate == property.as_str() {
-                        self.inferred_triples.insert(Triple {
-                            subject: triple.object.clone(),
+                        constraint_inferences.push((Triple {
+                            subject: triple.object.to_string(),
                             predicate: vocabulary::rdf_type().as_str().to_string(),
                             object: class.0.clone(),
-                        });
+                        }, vec![triple.to_triple(), axiom(property, vocabulary::RDFS_RANGE, class)]));
                     }
                 }
             }
         }
 
+        for (triple, justification) in constraint_inferences {
+            self.record(triple, justification);
+        }
+
         // クラス階層に基づく rdf:type 推論
         // もし x rdf:type A であり A rdfs:subClassOf B なら x rdf:type B
         let mut type_inferences = Vec::new();
-        for stored_triple_vec in store.all_triples().values() {
+        for stored_triple_vec in source_graphs(store, excluded.as_ref()) {
             for stored_triple in stored_triple_vec {
                 let triple = &stored_triple.triple;
                 if triple.predicate == vocabulary::rdf_type().as_str() {
-                    let subject_iri = Iri::new(triple.subject.clone());
-                    let class_iri = Iri::new(triple.object.clone());
+                    let subject_iri = Iri::new(triple.subject.to_string());
+                    let class_iri = Iri::new(triple.object.to_string());
                     if let Some(superclasses) = self.class_hierarchy.get(&class_iri) {
                         for superclass in superclasses {
-                            type_inferences.push(Triple {
+                            type_inferences.push((Triple {
                                 subject: subject_iri.0.clone(),
                                 predicate: vocabulary::rdf_type().as_str().to_string(),
                                 object: superclass.0.clone(),
-                            });
+                            }, vec![triple.to_triple(), axiom(&class_iri, vocabulary::RDFS_SUBCLASS_OF, superclass)]));
                         }
                     }
                 }
@@ -247,8 +295,8 @@ impl RdfsReasoner {
         }
 
         // 型推論結果を追加
-        for triple in type_inferences {
-            self.inferred_triples.insert(triple);
+        for (triple, justification) in type_inferences {
+            self.record(triple, justification);
         }
     }
 
@@ -257,6 +305,11 @@ impl RdfsReasoner {
         &self.inferred_triples
     }
 
+    /// 推論されたトリプルの根拠を取得 (直接の階層関係など、根拠のないものは含まない)
+    pub fn get_evidence(&self) -> &HashMap<Triple, Vec<Triple>> {
+        &self.evidence
+    }
+
     /// クラス階層を取得
     pub fn get_class_hierarchy(&self) -> &HashMap<Iri, HashSet<Iri>> {
         &self.class_hierarchy
@@ -302,6 +355,13 @@ pub enum RdfsError {
     StoreError(String),
 }
 
+/// 推論の入力とするグラフのトリプル (`excluded` 以外)
+fn source_graphs<'a>(store: &'a RdfStore, excluded: Option<&'a GraphId>) -> impl Iterator<Item = &'a Vec<StoredTriple>> {
+    store.all_triples().iter()
+        .filter(move |(graph_id, _)| excluded != Some(*graph_id))
+        .map(|(_, triples)| triples)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -460,6 +520,38 @@ mod tests {
             .contains(&Iri::new("http://example.org/hasRelated".to_string())));
     }
 
+    #[test]
+    fn test_inferences_record_evidence() {
+        use fukurow_store::provenance::{GraphId, Provenance};
+
+        let triple = |s: &str, p: &str, o: &str| Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() };
+        let mut store = RdfStore::new();
+        let provenance = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        for t in [
+            triple("ex:connectsTo", vocabulary::RDFS_DOMAIN, "ex:Host"),
+            triple("ex:Host", vocabulary::RDFS_SUBCLASS_OF, "ex:Asset"),
+            triple("ex:Asset", vocabulary::RDFS_SUBCLASS_OF, "ex:Thing"),
+            triple("ex:h1", "ex:connectsTo", "ex:h2"),
+        ] {
+            store.insert(t, GraphId::Default, provenance.clone());
+        }
+
+        let mut reasoner = RdfsReasoner::new();
+        reasoner.compute_closure(&store).unwrap();
+        let evidence = reasoner.get_evidence();
+
+        assert_eq!(evidence[&triple("ex:h1", vocabulary::RDF_TYPE, "ex:Host")], vec![
+            triple("ex:h1", "ex:connectsTo", "ex:h2"),
+            triple("ex:connectsTo", vocabulary::RDFS_DOMAIN, "ex:Host"),
+        ]);
+        assert_eq!(evidence[&triple("ex:Host", vocabulary::RDFS_SUBCLASS_OF, "ex:Thing")], vec![
+            triple("ex:Host", vocabulary::RDFS_SUBCLASS_OF, "ex:Asset"),
+            triple("ex:Asset", vocabulary::RDFS_SUBCLASS_OF, "ex:Thing"),
+        ]);
+        // 直接の階層関係は根拠を持たない
+        assert!(!evidence.contains_key(&triple("ex:Host", vocabulary::RDFS_SUBCLASS_OF, "ex:Asset")));
+    }
+
     #[test]
     fn test_multiple_superclasses() {
         let mut reasoner = RdfsReasoner::new();
@@ -0,0 +1,329 @@
+//! # Rule Dependencies
+//!
+//! ルールが参照・生成する述語 ([`Rule::consumes`] / [`Rule::produces`]) から依存グラフを作り、
+//! 生成側のルールが参照側より先に実行されるよう層 (stratum) に分ける。
+//! - 互いに依存するルール (循環) は強連結成分ごとに 1 つの層にまとめ、不動点まで繰り返す
+//! - 層の中、および依存関係のない層どうしは priority の高い順 (同じなら登録順) に実行する
+
+use crate::traits::Rule;
+use std::collections::BTreeSet;
+
+/// Rules executed together, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStratum {
+    /// Indices of the rules in registration order, sorted for execution
+    pub rules: Vec<usize>,
+    /// The rules depend on each other (or a rule on itself) and are repeated until nothing new is derived
+    pub recursive: bool,
+}
+
+/// Producer → consumer graph of registered rules
+#[derive(Debug, Clone)]
+pub struct RuleDependencyGraph {
+    names: Vec<String>,
+    /// `edges[producer]` holds the rules consuming a predicate the producer adds
+    edges: Vec<BTreeSet<usize>>,
+    strata: Vec<RuleStratum>,
+}
+
+impl RuleDependencyGraph {
+    pub fn build(rules: &[Box<dyn Rule>]) -> Self {
+        let names: Vec<String> = rules.iter().map(|rule| rule.name().to_string()).collect();
+        let priorities: Vec<i32> = rules.iter().map(|rule| rule.priority()).collect();
+        let produces: Vec<BTreeSet<String>> = rules.iter().map(|rule| rule.produces().into_iter().collect()).collect();
+        let consumes: Vec<BTreeSet<String>> = rules.iter().map(|rule| rule.consumes().into_iter().collect()).collect();
+
+        let edges: Vec<BTreeSet<usize>> = produces.iter()
+            .map(|produced| (0..rules.len()).filter(|&consumer| !produced.is_disjoint(&consumes[consumer])).collect())
+            .collect();
+
+        let strata = stratify(&edges, &priorities);
+        Self { names, edges, strata }
+    }
+
+    /// Strata in execution order
+    pub fn strata(&self) -> &[RuleStratum] {
+        &self.strata
+    }
+
+    /// Rule indices in execution order
+    pub fn execution_order(&self) -> Vec<usize> {
+        self.strata.iter().flat_map(|stratum| stratum.rules.iter().copied()).collect()
+    }
+
+    /// Names of the rules consuming what `rule` produces
+    pub fn dependents(&self, rule: usize) -> Vec<&str> {
+        self.edges.get(rule).map(|dependents| dependents.iter().map(|&i| self.names[i].as_str()).collect()).unwrap_or_default()
+    }
+
+    /// Whether `consumer` reads a predicate `producer` adds
+    pub fn feeds(&self, producer: usize, consumer: usize) -> bool {
+        self.edges.get(producer).is_some_and(|dependents| dependents.contains(&consumer))
+    }
+
+    /// Rules that depend on each other, one group per cycle
+    pub fn cycles(&self) -> Vec<Vec<&str>> {
+        self.strata.iter()
+            .filter(|stratum| stratum.recursive)
+            .map(|stratum| self.names_of(stratum))
+            .collect()
+    }
+
+    pub fn names_of(&self, stratum: &RuleStratum) -> Vec<&str> {
+        stratum.rules.iter().map(|&i| self.names[i].as_str()).collect()
+    }
+}
+
+/// Topologically ordered strongly connected components
+///
+/// 実行可能な層が複数あるときは、最も priority の高いルールを含む層を先にする
+fn stratify(edges: &[BTreeSet<usize>], priorities: &[i32]) -> Vec<RuleStratum> {
+    let components = strongly_connected(edges);
+    let mut component_of = vec![0; edges.len()];
+    for (c, members) in components.iter().enumerate() {
+        for &rule in members {
+            component_of[rule] = c;
+        }
+    }
+
+    let mut successors = vec![BTreeSet::new(); components.len()];
+    let mut in_degree = vec![0usize; components.len()];
+    for (producer, consumers) in edges.iter().enumerate() {
+        for &consumer in consumers {
+            let (from, to) = (component_of[producer], component_of[consumer]);
+            if from != to && successors[from].insert(to) {
+                in_degree[to] += 1;
+            }
+        }
+    }
+
+    let rank = |c: usize| {
+        let members = &components[c];
+        let priority = members.iter().map(|&i| priorities[i]).max().unwrap_or_default();
+        let first = members.iter().copied().min().unwrap_or_default();
+        (std::cmp::Reverse(priority), first)
+    };
+
+    let mut ready: Vec<usize> = (0..components.len()).filter(|&c| in_degree[c] == 0).collect();
+    let mut strata = Vec::with_capacity(components.len());
+    while let Some(position) = (0..ready.len()).min_by_key(|&i| rank(ready[i])) {
+        let c = ready.swap_remove(position);
+        for &next in &successors[c] {
+            in_degree[next] -= 1;
+            if in_degree[next] == 0 {
+                ready.push(next);
+            }
+        }
+
+        let mut rules = components[c].clone();
+        rules.sort_by_key(|&i| (std::cmp::Reverse(priorities[i]), i));
+        let recursive = rules.len() > 1 || edges[rules[0]].contains(&rules[0]);
+        strata.push(RuleStratum { rules, recursive });
+    }
+    strata
+}
+
+/// Tarjan's strongly connected components
+fn strongly_connected(edges: &[BTreeSet<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        edges: &'a [BTreeSet<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, v: usize) {
+            self.index[v] = Some(self.next);
+            self.low[v] = self.next;
+            self.next += 1;
+            self.stack.push(v);
+            self.on_stack[v] = true;
+
+            let edges = self.edges;
+            for &w in &edges[v] {
+                match self.index[w] {
+                    None => {
+                        self.visit(w);
+                        self.low[v] = self.low[v].min(self.low[w]);
+                    }
+                    Some(index) if self.on_stack[w] => self.low[v] = self.low[v].min(index),
+                    Some(_) => {}
+                }
+            }
+
+            if Some(self.low[v]) == self.index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = self.stack.pop() {
+                    self.on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let n = edges.len();
+    let mut tarjan = Tarjan {
+        edges,
+        index: vec![None; n],
+        low: vec![0; n],
+        on_stack: vec![false; n],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for v in 0..n {
+        if tarjan.index[v].is_none() {
+            tarjan.visit(v);
+        }
+    }
+    tarjan.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{RuleError, RuleRegistry, RuleResult};
+    use async_trait::async_trait;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+    use fukurow_store::store::RdfStore;
+    use std::collections::HashMap;
+
+    const CONNECTS: &str = "http://example.org/connectsTo";
+    const REACHES: &str = "http://example.org/reaches";
+    const FLAGGED: &str = "http://example.org/flagged";
+
+    fn triple(s: &str, p: &str, o: &str) -> Triple {
+        Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() }
+    }
+
+    fn result(triples_to_add: Vec<Triple>) -> RuleResult {
+        RuleResult {
+            triples_to_add,
+            triples_to_remove: Vec::new(),
+            actions: Vec::new(),
+            violations: Vec::new(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// connectsTo を reaches にコピーし、reaches を推移的に閉じる
+    struct Reachability;
+
+    #[async_trait]
+    impl Rule for Reachability {
+        fn name(&self) -> &'static str {
+            "reachability"
+        }
+
+        fn description(&self) -> &'static str {
+            "Transitive reachability"
+        }
+
+        fn consumes(&self) -> Vec<String> {
+            vec![CONNECTS.to_string(), REACHES.to_string()]
+        }
+
+        fn produces(&self) -> Vec<String> {
+            vec![REACHES.to_string()]
+        }
+
+        async fn apply(&self, store: &RdfStore) -> Result<RuleResult, RuleError> {
+            let mut derived: Vec<Triple> = store.find_triples(None, Some(CONNECTS), None).into_iter()
+                .map(|stored| triple(&stored.triple.subject, REACHES, &stored.triple.object))
+                .collect();
+            for first in store.find_triples(None, Some(REACHES), None) {
+                for second in store.find_triples(Some(&first.triple.object), Some(REACHES), None) {
+                    derived.push(triple(&first.triple.subject, REACHES, &second.triple.object));
+                }
+            }
+            Ok(result(derived))
+        }
+    }
+
+    /// internet から到達できるホストに印を付ける (priority は高いが reaches に依存する)
+    struct Exposure;
+
+    #[async_trait]
+    impl Rule for Exposure {
+        fn name(&self) -> &'static str {
+            "exposure"
+        }
+
+        fn description(&self) -> &'static str {
+            "Hosts reachable from the internet"
+        }
+
+        fn priority(&self) -> i32 {
+            100
+        }
+
+        fn consumes(&self) -> Vec<String> {
+            vec![REACHES.to_string()]
+        }
+
+        fn produces(&self) -> Vec<String> {
+            vec![FLAGGED.to_string()]
+        }
+
+        async fn apply(&self, store: &RdfStore) -> Result<RuleResult, RuleError> {
+            Ok(result(store.find_triples(Some("internet"), Some(REACHES), None).into_iter()
+                .map(|stored| triple(&stored.triple.object, FLAGGED, "true"))
+                .collect()))
+        }
+    }
+
+    #[test]
+    fn test_strata_follow_dependencies_then_priority() {
+        let rules: Vec<Box<dyn Rule>> = vec![Box::new(Exposure), Box::new(Reachability)];
+        let graph = RuleDependencyGraph::build(&rules);
+
+        // priority が高くても、reaches を生成するルールの後に実行する
+        assert_eq!(graph.execution_order(), vec![1, 0]);
+        assert_eq!(graph.strata(), &[
+            RuleStratum { rules: vec![1], recursive: true },
+            RuleStratum { rules: vec![0], recursive: false },
+        ]);
+        assert_eq!(graph.cycles(), vec![vec!["reachability"]]);
+        assert_eq!(graph.dependents(1), vec!["exposure", "reachability"]);
+    }
+
+    #[tokio::test]
+    async fn test_apply_to_fixpoint_iterates_recursive_strata() {
+        let mut registry = RuleRegistry::new();
+        registry.register_rule(Box::new(Exposure));
+        registry.register_rule(Box::new(Reachability));
+
+        let mut store = RdfStore::new();
+        let sensor = Provenance::Sensor { source: "netflow".to_string(), confidence: None };
+        for (from, to) in [("internet", "dmz"), ("dmz", "app"), ("app", "db")] {
+            store.insert(triple(from, CONNECTS, to), GraphId::Default, sensor.clone());
+        }
+
+        let graph_id = GraphId::Inferred("rules".to_string());
+        let run = registry.apply_to_fixpoint(&mut store, &graph_id, 10).await.unwrap();
+
+        for host in ["dmz", "app", "db"] {
+            assert!(run.inferred.contains(&triple(host, FLAGGED, "true")), "{} not flagged", host);
+        }
+        // 3 本の reaches + 推移の 3 本 + 印 3 本
+        assert_eq!(run.inferred.len(), 9);
+        assert_eq!(run.results.len(), 2);
+
+        // 不動点に届かない場合はエラー
+        let mut fresh = RdfStore::new();
+        for (from, to) in [("internet", "dmz"), ("dmz", "app"), ("app", "db")] {
+            fresh.insert(triple(from, CONNECTS, to), GraphId::Default, sensor.clone());
+        }
+        let error = registry.apply_to_fixpoint(&mut fresh, &graph_id, 1).await.unwrap_err();
+        assert!(matches!(error, RuleError::IterationLimit { iterations: 1, .. }));
+    }
+}
@@ -8,7 +8,8 @@ use std::collections::HashMap;
 use async_trait::async_trait;
 use crate::{Rule, RuleResult, RuleError, ValidationViolation, ViolationLevel};
 use fukurow_core::model::{Triple, SecurityAction};
-use fukurow_store::store::RdfStore;
+use fukurow_core::prefix::PrefixMap;
+use fukurow_store::store::{RdfStore, StoredTriple};
 use chrono::{Utc};
 
 /// DSLベースのセキュリティポリシー定義
@@ -20,6 +21,9 @@ pub struct SecurityPolicy {
     pub priority: i32,
     pub rules: Vec<PolicyRule>,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// ルール中の CURIE (`ex:Host`) を展開する接頭辞 (エンジンの接頭辞より優先)
+    #[serde(default = "PrefixMap::empty")]
+    pub prefixes: PrefixMap,
 }
 
 /// 個別のポリシールール定義
@@ -40,6 +44,10 @@ pub struct PolicyRule {
 
     /// 追加のメタデータ
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// 検知対象の MITRE ATT&CK 技術 ID (`T1110`)。このルールのアラートに付与する
+    #[serde(default)]
+    pub techniques: Vec<String>,
 }
 
 /// ルール適用条件
@@ -51,6 +59,9 @@ pub enum Condition {
         subject: String,
         predicate: String,
         object: String,
+        /// 指定すると、確信度がこの値以上のトリプルだけを対象にする (確信度のないセンサー値・推論は除く)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_confidence: Option<f64>,
     },
 
     /// トリプルの不存在条件
@@ -180,6 +191,7 @@ pub enum Severity {
 pub struct DslRuleEngine {
     policies: Vec<SecurityPolicy>,
     variables: HashMap<String, serde_json::Value>,
+    prefixes: PrefixMap,
 }
 
 impl DslRuleEngine {
@@ -187,11 +199,32 @@ impl DslRuleEngine {
         Self {
             policies: Vec::new(),
             variables: HashMap::new(),
+            prefixes: PrefixMap::default(),
         }
     }
 
+    /// Prefixes for CURIE expansion in policies added afterwards (defaults: rdf, rdfs, owl, sh, xsd)
+    pub fn with_prefixes(mut self, prefixes: PrefixMap) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
     /// ポリシーを追加
-    pub fn add_policy(&mut self, policy: SecurityPolicy) {
+    ///
+    /// 条件とトリプル操作の CURIE は追加時に完全な IRI へ展開する
+    pub fn add_policy(&mut self, mut policy: SecurityPolicy) {
+        let mut prefixes = self.prefixes.clone();
+        prefixes.extend(&policy.prefixes);
+        for rule in &mut policy.rules {
+            for condition in &mut rule.conditions {
+                expand_condition(condition, &prefixes);
+            }
+            for action in &mut rule.actions {
+                if let PolicyAction::AddTriple { subject, predicate, object } | PolicyAction::RemoveTriple { subject, predicate, object } = action {
+                    expand_terms([subject, predicate, object], &prefixes);
+                }
+            }
+        }
         self.policies.push(policy);
     }
 
@@ -225,18 +258,27 @@ impl DslRuleEngine {
         let mut triples_to_remove = Vec::new();
         let mut actions = Vec::new();
         let mut violations = Vec::new();
+        let mut evidence = Vec::new();
 
         for rule in &policy.rules {
             if self.evaluate_conditions(&rule.conditions, store).await? {
+                // 条件に一致したトリプルを追加するトリプルの根拠とする
+                let mut matched = Vec::new();
+                for condition in &rule.conditions {
+                    condition_evidence(condition, store, &mut matched);
+                }
+
                 // 条件が満たされた場合、アクションを実行
                 for action in &rule.actions {
                     match action {
                         PolicyAction::AddTriple { subject, predicate, object } => {
-                            triples_to_add.push(Triple {
+                            let triple = Triple {
                                 subject: subject.clone(),
                                 predicate: predicate.clone(),
                                 object: object.clone(),
-                            });
+                            };
+                            evidence.push((triple.clone(), matched.clone()));
+                            triples_to_add.push(triple);
                         }
                         PolicyAction::RemoveTriple { subject, predicate, object } => {
                             triples_to_remove.push(Triple {
@@ -298,7 +340,7 @@ impl DslRuleEngine {
                                 },
                             };
 
-                            actions.push(security_action);
+                            actions.push(security_action.with_attack_techniques(&rule.techniques));
                         }
                         PolicyAction::ReportViolation { level, message, context } => {
                             violations.push(ValidationViolation {
@@ -319,13 +361,19 @@ impl DslRuleEngine {
         metadata.insert("policy_version".to_string(), serde_json::json!(policy.version));
         metadata.insert("execution_time".to_string(), serde_json::json!(Utc::now().timestamp()));
 
-        Ok(RuleResult {
+        let mut result = RuleResult {
             triples_to_add,
             triples_to_remove,
             actions,
             violations,
             metadata,
-        })
+        };
+        for (triple, matched) in evidence {
+            if !matched.is_empty() {
+                result.set_evidence(&triple, matched);
+            }
+        }
+        Ok(result)
     }
 
     /// 条件を評価
@@ -341,13 +389,9 @@ impl DslRuleEngine {
     /// 個別の条件を評価
     async fn evaluate_condition(&self, condition: &Condition, store: &RdfStore) -> Result<bool, RuleError> {
         match condition {
-            Condition::TripleExists { subject, predicate, object } => {
+            Condition::TripleExists { subject, predicate, object, min_confidence } => {
                 // トリプルが存在するかチェック
-                let subject_opt = if subject.starts_with('?') { None } else { Some(subject.as_str()) };
-                let predicate_opt = Some(predicate.as_str());
-                let object_opt = if object.starts_with('?') { None } else { Some(object.as_str()) };
-
-                let results = store.find_triples(subject_opt, predicate_opt, object_opt);
+                let results = matching_triples(store, subject, predicate, object, *min_confidence);
                 Ok(!results.is_empty())
             }
 
@@ -357,6 +401,7 @@ impl DslRuleEngine {
                         subject: subject.clone(),
                         predicate: predicate.clone(),
                         object: object.clone(),
+                        min_confidence: None,
                     },
                     store
                 )).await?;
@@ -454,6 +499,12 @@ impl DslRule {
         }
     }
 
+    /// Prefixes for policies added afterwards (see [`DslRuleEngine::with_prefixes`])
+    pub fn with_prefixes(mut self, prefixes: PrefixMap) -> Self {
+        self.engine = self.engine.with_prefixes(prefixes);
+        self
+    }
+
     pub fn with_policy(mut self, policy: SecurityPolicy) -> Self {
         self.engine.add_policy(policy);
         self
@@ -495,19 +546,164 @@ impl Rule for DslRule {
         };
 
         for result in results {
-            combined.triples_to_add.extend(result.triples_to_add);
-            combined.triples_to_remove.extend(result.triples_to_remove);
-            combined.actions.extend(result.actions);
-            combined.violations.extend(result.violations);
-            combined.metadata.extend(result.metadata);
+            combined.merge(result);
         }
 
         Ok(combined)
     }
 
+    /// One partition per policy
+    fn partitions(&self, _store: &RdfStore) -> usize {
+        self.engine.policies.len()
+    }
+
+    async fn apply_partition(&self, store: &RdfStore, partition: usize) -> Result<RuleResult, RuleError> {
+        let policy = self.engine.policies.get(partition).ok_or_else(|| RuleError::ExecutionError {
+            message: format!("DSL rule has no policy #{}", partition),
+        })?;
+        self.engine.execute_policy(policy, store).await
+    }
+
     fn should_apply(&self, _store: &RdfStore) -> bool {
         !self.engine.policies.is_empty()
     }
+
+    fn consumes(&self) -> Vec<String> {
+        let mut predicates = Vec::new();
+        for rule in self.engine.policies.iter().flat_map(|policy| &policy.rules) {
+            for condition in &rule.conditions {
+                condition_predicates(condition, &mut predicates);
+            }
+        }
+        predicates
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        let mut techniques: Vec<String> = Vec::new();
+        for technique in self.engine.policies.iter().flat_map(|policy| &policy.rules).flat_map(|rule| &rule.techniques) {
+            if !techniques.contains(technique) {
+                techniques.push(technique.clone());
+            }
+        }
+        techniques
+    }
+
+    fn produces(&self) -> Vec<String> {
+        let mut predicates = Vec::new();
+        for action in self.engine.policies.iter().flat_map(|policy| &policy.rules).flat_map(|rule| &rule.actions) {
+            if let PolicyAction::AddTriple { predicate, .. } = action {
+                push_predicate(predicate, &mut predicates);
+            }
+        }
+        predicates
+    }
+}
+
+/// Stored triples matching a triple condition (`?` terms match anything)
+fn matching_triples<'a>(store: &'a RdfStore, subject: &str, predicate: &str, object: &str, min_confidence: Option<f64>) -> Vec<&'a StoredTriple> {
+    let subject_opt = if subject.starts_with('?') { None } else { Some(subject) };
+    let object_opt = if object.starts_with('?') { None } else { Some(object) };
+    store.find_triples(subject_opt, Some(predicate), object_opt)
+        .into_iter()
+        .filter(|stored| min_confidence.is_none_or(|min| stored.provenance.meets_confidence(min)))
+        .collect()
+}
+
+/// Triples matched by the positive triple conditions of a satisfied condition
+///
+/// 変数を束縛しないため、条件ごとに最も確信度の高い一致を根拠とする
+fn condition_evidence(condition: &Condition, store: &RdfStore, evidence: &mut Vec<Triple>) {
+    match condition {
+        Condition::TripleExists { subject, predicate, object, min_confidence } => {
+            let best = matching_triples(store, subject, predicate, object, *min_confidence)
+                .into_iter()
+                .max_by(|a, b| a.provenance.confidence().unwrap_or(0.0).total_cmp(&b.provenance.confidence().unwrap_or(0.0)));
+            if let Some(stored) = best.filter(|stored| !evidence.iter().any(|triple| *triple == stored.triple)) {
+                evidence.push(stored.triple.to_triple());
+            }
+        }
+        Condition::And(conditions) => {
+            for condition in conditions {
+                condition_evidence(condition, store, evidence);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn expand_terms<'a>(terms: impl IntoIterator<Item = &'a mut String>, prefixes: &PrefixMap) {
+    for term in terms {
+        *term = prefixes.expand_term(term);
+    }
+}
+
+fn expand_condition(condition: &mut Condition, prefixes: &PrefixMap) {
+    match condition {
+        Condition::TripleExists { subject, predicate, object, .. } | Condition::TripleNotExists { subject, predicate, object } => {
+            expand_terms([subject, predicate, object], prefixes);
+        }
+        Condition::NumericComparison { left, right, .. } => {
+            expand_expression(left, prefixes);
+            expand_expression(right, prefixes);
+        }
+        Condition::And(conditions) | Condition::Or(conditions) => {
+            for condition in conditions {
+                expand_condition(condition, prefixes);
+            }
+        }
+        Condition::Not(condition) => expand_condition(condition, prefixes),
+        Condition::VariableBinding { .. } => {}
+    }
+}
+
+fn expand_expression(expression: &mut ValueExpression, prefixes: &PrefixMap) {
+    match expression {
+        ValueExpression::TripleValue { subject, predicate, .. } => expand_terms([subject, predicate], prefixes),
+        ValueExpression::FunctionCall { arguments, .. } => {
+            for argument in arguments {
+                expand_expression(argument, prefixes);
+            }
+        }
+        ValueExpression::Constant(_) | ValueExpression::Variable(_) => {}
+    }
+}
+
+/// Constant predicates referenced by a condition (変数の述語は依存関係に使えないので除く)
+fn condition_predicates(condition: &Condition, predicates: &mut Vec<String>) {
+    match condition {
+        Condition::TripleExists { predicate, .. } | Condition::TripleNotExists { predicate, .. } => {
+            push_predicate(predicate, predicates);
+        }
+        Condition::NumericComparison { left, right, .. } => {
+            expression_predicates(left, predicates);
+            expression_predicates(right, predicates);
+        }
+        Condition::And(conditions) | Condition::Or(conditions) => {
+            for condition in conditions {
+                condition_predicates(condition, predicates);
+            }
+        }
+        Condition::Not(condition) => condition_predicates(condition, predicates),
+        Condition::VariableBinding { .. } => {}
+    }
+}
+
+fn expression_predicates(expression: &ValueExpression, predicates: &mut Vec<String>) {
+    match expression {
+        ValueExpression::TripleValue { predicate, .. } => push_predicate(predicate, predicates),
+        ValueExpression::FunctionCall { arguments, .. } => {
+            for argument in arguments {
+                expression_predicates(argument, predicates);
+            }
+        }
+        ValueExpression::Constant(_) | ValueExpression::Variable(_) => {}
+    }
+}
+
+fn push_predicate(predicate: &str, predicates: &mut Vec<String>) {
+    if !predicate.starts_with('?') && !predicates.iter().any(|p| p == predicate) {
+        predicates.push(predicate.to_string());
+    }
 }
 
 #[cfg(test)]
@@ -598,6 +794,7 @@ mod tests {
                         subject: "?user".to_string(),
                         predicate: "failed_login_count".to_string(),
                         object: "?count".to_string(),
+                        min_confidence: None,
                     },
                 ],
                 actions: vec![
@@ -609,8 +806,10 @@ mod tests {
                 ],
                 severity: Severity::Medium,
                 metadata: HashMap::new(),
+                techniques: vec!["T1110".to_string()],
             }],
             metadata: HashMap::new(),
+            prefixes: PrefixMap::empty(),
         };
 
         let mut dsl_rule = DslRule::new().with_policy(policy);
@@ -619,6 +818,68 @@ mod tests {
         let result = dsl_rule.apply(&store).await.unwrap();
         assert_eq!(result.actions.len(), 1);
         assert!(result.triples_to_add.is_empty());
+        assert_eq!(result.actions[0].attack_techniques(), vec!["T1110"]);
+        assert_eq!(dsl_rule.attack_techniques(), vec!["T1110"]);
+    }
+
+    #[tokio::test]
+    async fn test_policy_curies_are_expanded() {
+        let mut store = RdfStore::new();
+        store.insert(Triple {
+            subject: "http://example.org/h1".to_string(),
+            predicate: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+            object: "http://example.org/CompromisedHost".to_string(),
+        }, fukurow_store::GraphId::Default, fukurow_store::Provenance::Sensor { source: "test".to_string(), confidence: None });
+
+        let policy_json = r#"{
+            "name": "curie_policy", "description": "", "version": "1.0.0", "priority": 0, "metadata": {},
+            "prefixes": {"ex": "http://example.org/"},
+            "rules": [{
+                "id": "isolate", "name": "Isolate", "description": "", "severity": "High", "metadata": {},
+                "conditions": [{"type": "TripleExists", "config": {"subject": "?host", "predicate": "rdf:type", "object": "ex:CompromisedHost"}}],
+                "actions": [{"type": "AddTriple", "config": {"subject": "ex:h1", "predicate": "ex:status", "object": "isolated"}}]
+            }]
+        }"#;
+        let rule = DslRule::new().with_json_policy(policy_json).unwrap();
+
+        let result = rule.apply(&store).await.unwrap();
+        assert_eq!(result.triples_to_add, vec![Triple {
+            subject: "http://example.org/h1".to_string(),
+            predicate: "http://example.org/status".to_string(),
+            object: "isolated".to_string(),
+        }]);
+        assert_eq!(rule.consumes(), vec!["http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_min_confidence_filters_and_scores_inferences() {
+        use fukurow_store::{ConfidenceCombination, GraphId, Provenance};
+
+        let alert = |host: &str| Triple { subject: host.to_string(), predicate: "ex:hasAlert".to_string(), object: "ex:C2".to_string() };
+        let mut store = RdfStore::new();
+        store.insert(alert("ex:h1"), GraphId::Sensor("ids".to_string()), Provenance::Sensor { source: "ids".to_string(), confidence: Some(0.4) });
+
+        let policy_json = r#"{
+            "name": "confident_c2", "description": "", "version": "1.0.0", "priority": 0, "metadata": {},
+            "rules": [{
+                "id": "compromised", "name": "Compromised", "description": "", "severity": "High", "metadata": {},
+                "conditions": [{"type": "TripleExists", "config": {"subject": "?host", "predicate": "ex:hasAlert", "object": "ex:C2", "min_confidence": 0.7}}],
+                "actions": [{"type": "AddTriple", "config": {"subject": "ex:h1", "predicate": "ex:status", "object": "compromised"}}]
+            }]
+        }"#;
+        let rule = DslRule::new().with_json_policy(policy_json).unwrap();
+        assert!(rule.apply(&store).await.unwrap().triples_to_add.is_empty());
+
+        store.insert(alert("ex:h1"), GraphId::Sensor("edr".to_string()), Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.9) });
+        let result = rule.apply(&store).await.unwrap();
+        assert_eq!(result.evidence_for(&result.triples_to_add[0]), vec![alert("ex:h1")]);
+
+        let mut registry = crate::RuleRegistry::new();
+        registry.register_rule(Box::new(rule));
+        let graph_id = GraphId::Inferred("rules".to_string());
+        registry.apply_to_fixpoint_at_level(&mut store, &graph_id, 5, "rules", ConfidenceCombination::Min).await.unwrap();
+        let inferred = store.find_triples(Some("ex:h1"), Some("ex:status"), None);
+        assert_eq!(inferred[0].provenance.confidence(), Some(0.9));
     }
 
     #[test]
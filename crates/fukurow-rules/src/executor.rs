@@ -0,0 +1,121 @@
+//! # Rule Executors
+//!
+//! 互いに依存しないルール (および分割可能なルールの各パーティション) の適用をまとめて実行する。
+//! 結果は常にタスクの順に返すため、並列に実行しても推論結果は逐次実行と同じ順序になる。
+//! 並列の実装 (ワーカープール) は fukurow-engine が提供する
+
+use crate::traits::{RuleError, RuleResult};
+use async_trait::async_trait;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Instant;
+
+/// Outcome of one rule (or rule partition) application
+#[derive(Debug)]
+pub struct RuleOutcome {
+    pub result: Result<RuleResult, RuleError>,
+    pub duration_us: u64,
+}
+
+impl RuleOutcome {
+    /// Time `application` and wrap its result
+    pub async fn measure(application: impl Future<Output = Result<RuleResult, RuleError>>) -> Self {
+        let started = Instant::now();
+        let result = application.await;
+        Self { result, duration_us: started.elapsed().as_micros() as u64 }
+    }
+}
+
+/// Pending rule application; tasks of one batch only read the store
+pub type RuleTask<'a> = Pin<Box<dyn Future<Output = RuleOutcome> + Send + 'a>>;
+
+/// Runs batches of independent rule applications
+#[async_trait]
+pub trait RuleExecutor: Send + Sync {
+    /// Tasks that may run at the same time (1 = sequential)
+    fn concurrency(&self) -> usize;
+
+    /// Run `tasks` to completion and return their outcomes in task order
+    async fn run<'a>(&self, tasks: Vec<RuleTask<'a>>) -> Vec<RuleOutcome>;
+}
+
+/// Runs tasks one after another on the calling task
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SequentialExecutor;
+
+#[async_trait]
+impl RuleExecutor for SequentialExecutor {
+    fn concurrency(&self) -> usize {
+        1
+    }
+
+    async fn run<'a>(&self, tasks: Vec<RuleTask<'a>>) -> Vec<RuleOutcome> {
+        let mut outcomes = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            outcomes.push(task.await);
+        }
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Rule;
+    use crate::{DslRule, RuleRegistry};
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+    use fukurow_store::store::RdfStore;
+
+    /// Completes tasks last-to-first, as a parallel executor might
+    struct ReversedExecutor;
+
+    #[async_trait]
+    impl RuleExecutor for ReversedExecutor {
+        fn concurrency(&self) -> usize {
+            4
+        }
+
+        async fn run<'a>(&self, tasks: Vec<RuleTask<'a>>) -> Vec<RuleOutcome> {
+            let mut outcomes = Vec::with_capacity(tasks.len());
+            for task in tasks.into_iter().rev() {
+                outcomes.push(task.await);
+            }
+            outcomes.reverse();
+            outcomes
+        }
+    }
+
+    fn hosts_policy(index: usize) -> String {
+        serde_json::json!({
+            "name": format!("policy-{}", index), "description": "", "version": "1.0.0", "priority": 0, "metadata": {},
+            "rules": [{
+                "id": format!("rule-{}", index), "name": "Flag", "description": "", "severity": "High", "metadata": {},
+                "conditions": [{"type": "TripleExists", "config": {"subject": "?host", "predicate": "ex:alert", "object": format!("a{}", index % 2)}}],
+                "actions": [
+                    {"type": "AddTriple", "config": {"subject": format!("ex:h{}", index), "predicate": "ex:flagged", "object": "yes"}},
+                    {"type": "SecurityAction", "config": {"action_type": "Alert", "message": format!("policy {}", index), "details": {}}}
+                ]
+            }]
+        }).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_rules_merge_in_task_order() {
+        let mut store = RdfStore::new();
+        store.insert(Triple { subject: "ex:h0".to_string(), predicate: "ex:alert".to_string(), object: "a0".to_string() },
+            GraphId::Sensor("ids".to_string()), Provenance::Sensor { source: "ids".to_string(), confidence: None });
+        let rule = (0..4).fold(DslRule::new(), |rule, index| rule.with_json_policy(&hosts_policy(index)).unwrap());
+        assert_eq!(rule.partitions(&store), 4);
+        let mut registry = RuleRegistry::new();
+        registry.register_rule(Box::new(rule));
+
+        let sequential = registry.apply_all_rules(&store).await.unwrap();
+        let reversed = registry.apply_all_rules_with(&store, &ReversedExecutor).await.unwrap();
+        assert_eq!(reversed[0].triples_to_add, sequential[0].triples_to_add);
+        assert_eq!(serde_json::to_value(&reversed[0].actions).unwrap(), serde_json::to_value(&sequential[0].actions).unwrap());
+        assert_eq!(reversed[0].triples_to_add.len(), 2);
+        // パーティションに分けても 1 回の適用として数える
+        assert_eq!(registry.rule("dsl_rule").unwrap().stats.applications, 2);
+    }
+}
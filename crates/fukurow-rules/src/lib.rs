@@ -3,12 +3,27 @@
 //! Rule traits and constraint validation (SHACL equivalent)
 //! Domain and policy rules for knowledge validation
 //! Declarative security policy DSL for rule definition
+//! YARA-L 2.0 export of DSL policies
+//! Dependency-ordered rule execution
+//! SPARQL CONSTRUCT/ASK rules
+//! Detection packs (shareable rule bundles)
+//! Rule executors for running independent rules concurrently
 
 pub mod traits;
 pub mod dsl;
+pub mod yaral;
+pub mod dependency;
+pub mod sparql;
+pub mod pack;
+pub mod executor;
 
 pub use traits::*;
 pub use dsl::*;
+pub use yaral::*;
+pub use dependency::*;
+pub use sparql::*;
+pub use pack::*;
+pub use executor::*;
 
 // Re-export types from fukurow-core and fukurow-store for domain crates
 pub use fukurow_core::model::{CyberEvent, SecurityAction, InferenceRule, Triple};
@@ -533,6 +548,34 @@ mod tests {
             let violations = registry.validate_all(&store).await.unwrap();
             assert_eq!(violations.len(), 0);
         }
+
+        #[tokio::test]
+        async fn test_disable_rule_and_dry_run() {
+            let mut registry = RuleRegistry::new();
+            registry.register_rule(Box::new(MockRule::new("rule1", "First rule", 10)));
+            registry.register_rule(Box::new(MockRule::new("rule2", "Second rule", 5).with_should_apply(false)));
+            let store = RdfStore::new();
+
+            let info = registry.set_enabled("rule1", false).unwrap();
+            assert!(!info.enabled);
+            assert!(registry.apply_all_rules(&store).await.unwrap().is_empty());
+            assert!(matches!(registry.set_enabled("missing", false), Err(RuleError::UnknownRule { .. })));
+
+            // 無効なルールも試行でき、統計には数えない
+            let dry_run = registry.dry_run("rule1", &store).await.unwrap();
+            assert!(!dry_run.enabled && dry_run.would_apply);
+            assert_eq!(dry_run.result.triples_to_add.len(), 1);
+            assert!(!registry.dry_run("rule2", &store).await.unwrap().would_apply);
+            assert_eq!(registry.rule("rule1").unwrap().stats, RuleStats::default());
+
+            registry.set_enabled("rule1", true).unwrap();
+            assert_eq!(registry.apply_all_rules(&store).await.unwrap().len(), 1);
+            let rules = registry.rules();
+            assert_eq!(rules.iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>(), vec!["rule1", "rule2"]);
+            assert_eq!((rules[0].stats.applications, rules[0].stats.triples_derived, rules[0].stats.actions), (1, 1, 1));
+            assert!(rules[0].stats.last_applied_at.is_some());
+            assert_eq!(rules[1].stats.applications, 0);
+        }
     }
 
     #[cfg(test)]
@@ -0,0 +1,545 @@
+//! # Detection Packs
+//!
+//! Shareable bundles of detection content, in the spirit of Sigma rule packs.
+//! パックは 1 つのディレクトリで、マニフェスト `pack.json`・ルール (`*.json` の DSL ポリシー、
+//! `*.rq` / `*.sparql` の SPARQL ルール)・オントロジー断片 (Turtle / N-Triples)・テストを持つ。
+//! [`RuleRegistry::register_pack`] はルールを `<パック名>/<ルール名>` の名前空間で登録し、
+//! どのパックのどのバージョンから来たかを [`RuleInfo::pack`] に残す
+//!
+//! ```json
+//! {
+//!   "name": "lateral-movement",
+//!   "version": "1.2.0",
+//!   "description": "Lateral movement detections",
+//!   "rules": ["rules/admin_share.json", "rules/remote_exec.rq"],
+//!   "ontology": ["ontology/lateral.ttl"],
+//!   "tests": ["tests/remote_exec.json"]
+//! }
+//! ```
+
+use crate::{DslRule, Rule, RuleError, RuleInfo, RuleRegistry, RuleResult, SecurityPolicy, SparqlRule};
+use async_trait::async_trait;
+use fukurow_core::model::Triple;
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use fukurow_store::DatasetFormat;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::{Component, Path, PathBuf};
+
+/// Manifest file at the root of a pack
+pub const PACK_MANIFEST: &str = "pack.json";
+/// Separator between the pack name and the rule name in registered rule names
+pub const PACK_NAMESPACE_SEPARATOR: char = '/';
+/// Sensor recorded on the fixture triples of pack tests
+const PACK_TEST_SOURCE: &str = "pack-test";
+const PACK_TEST_MAX_ITERATIONS: usize = 16;
+
+/// Detection pack errors
+#[derive(Debug, thiserror::Error)]
+pub enum PackError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid manifest {path}: {source}")]
+    Manifest {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid pack: {0}")]
+    Invalid(String),
+
+    #[error("Invalid rule {path}: {message}")]
+    Rule { path: PathBuf, message: String },
+
+    #[error("Invalid ontology {path}: {message}")]
+    Ontology { path: PathBuf, message: String },
+
+    #[error("Invalid test {path}: {source}")]
+    Test {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Pack conflicts with the registry: {0}")]
+    Conflict(String),
+
+    #[error("Pack test {test} failed to run: {source}")]
+    TestExecution {
+        test: String,
+        #[source]
+        source: RuleError,
+    },
+}
+
+/// Contents of `pack.json`; file paths are relative to the pack directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackManifest {
+    /// Pack name (`[A-Za-z0-9._-]`), also the namespace of its rules
+    pub name: String,
+    /// `MAJOR.MINOR.PATCH` with an optional `-pre-release` suffix
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub rules: Vec<String>,
+    #[serde(default)]
+    pub ontology: Vec<String>,
+    #[serde(default)]
+    pub tests: Vec<String>,
+}
+
+impl PackManifest {
+    pub fn validate(&self) -> Result<(), PackError> {
+        let valid_name = !self.name.is_empty()
+            && self.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+            && !self.name.starts_with('.');
+        if !valid_name {
+            return Err(PackError::Invalid(format!("pack name {:?} must use only [A-Za-z0-9._-]", self.name)));
+        }
+        if version_numbers(&self.version).is_none() {
+            return Err(PackError::Invalid(format!("version {:?} is not MAJOR.MINOR.PATCH", self.version)));
+        }
+        if self.rules.is_empty() {
+            return Err(PackError::Invalid(format!("pack {} declares no rules", self.name)));
+        }
+        Ok(())
+    }
+
+    pub fn reference(&self) -> PackRef {
+        PackRef { name: self.name.clone(), version: self.version.clone() }
+    }
+}
+
+/// Pack (name and version) a registered rule came from
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PackRef {
+    pub name: String,
+    pub version: String,
+}
+
+/// Compare two pack versions (`None` when either is malformed)
+///
+/// 数値部分を比較し、同じなら pre-release 付きの方を古いとみなす
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let (a_numbers, a_pre) = version_numbers(a)?;
+    let (b_numbers, b_pre) = version_numbers(b)?;
+    Some(a_numbers.cmp(&b_numbers).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(b),
+    }))
+}
+
+fn version_numbers(version: &str) -> Option<([u64; 3], Option<&str>)> {
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) if !pre.is_empty() => (core, Some(pre)),
+        Some(_) => return None,
+        None => (version, None),
+    };
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let numbers = [parts.next()??, parts.next()??, parts.next()??];
+    parts.next().is_none().then_some((numbers, pre))
+}
+
+/// A pack test: fixture triples and what the pack's rules must derive from them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackTest {
+    pub name: String,
+    /// Triples inserted (as sensor data) before the rules run
+    #[serde(default)]
+    pub triples: Vec<Triple>,
+    /// Triples that must be present after reasoning
+    #[serde(default)]
+    pub expect_triples: Vec<Triple>,
+    /// Triples that must not be present after reasoning
+    #[serde(default)]
+    pub expect_absent: Vec<Triple>,
+    /// Minimum number of security actions the rules must propose
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_actions: Option<usize>,
+}
+
+/// Outcome of one pack test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackTestOutcome {
+    pub name: String,
+    pub passed: bool,
+    /// Unmet expectations
+    pub failures: Vec<String>,
+    pub actions: usize,
+    pub inferred: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PackRuleKind {
+    Policy,
+    Sparql,
+}
+
+#[derive(Debug, Clone)]
+struct PackRuleSource {
+    path: PathBuf,
+    kind: PackRuleKind,
+    /// Name within the pack (policy name or SPARQL file stem)
+    name: String,
+    text: String,
+}
+
+/// A loaded (and syntax-checked) detection pack
+#[derive(Debug, Clone)]
+pub struct DetectionPack {
+    manifest: PackManifest,
+    root: PathBuf,
+    rules: Vec<PackRuleSource>,
+    ontology: Vec<(PathBuf, String)>,
+    tests: Vec<(PathBuf, PackTest)>,
+}
+
+impl DetectionPack {
+    /// Load the pack in directory `root`
+    ///
+    /// マニフェスト・全ルール・オントロジー・テストを読み込んで構文を検査する。
+    /// マニフェストのパスがパックの外 (`..` や絶対パス) を指す場合は拒否する
+    pub fn load(root: impl AsRef<Path>) -> Result<Self, PackError> {
+        let root = root.as_ref().to_path_buf();
+        let manifest_path = root.join(PACK_MANIFEST);
+        let manifest: PackManifest = serde_json::from_str(&read(&manifest_path)?)
+            .map_err(|source| PackError::Manifest { path: manifest_path.clone(), source })?;
+        manifest.validate()?;
+
+        let mut rules: Vec<PackRuleSource> = Vec::new();
+        for file in &manifest.rules {
+            let path = resolve(&root, file)?;
+            let source = rule_source(path)?;
+            if rules.iter().any(|rule| rule.name == source.name) {
+                return Err(PackError::Invalid(format!("duplicate rule name {} in pack {}", source.name, manifest.name)));
+            }
+            rules.push(source);
+        }
+
+        let mut ontology = Vec::new();
+        for file in &manifest.ontology {
+            let path = resolve(&root, file)?;
+            let text = read(&path)?;
+            import_ontology(&mut RdfStore::new(), &path, &text)?;
+            ontology.push((path, text));
+        }
+
+        let mut tests = Vec::new();
+        for file in &manifest.tests {
+            let path = resolve(&root, file)?;
+            let test: PackTest = serde_json::from_str(&read(&path)?)
+                .map_err(|source| PackError::Test { path: path.clone(), source })?;
+            tests.push((path, test));
+        }
+
+        let pack = Self { manifest, root, rules, ontology, tests };
+        // ルールを一度組み立てて、クエリやポリシーの誤りを読み込み時に報告する
+        pack.rules()?;
+        Ok(pack)
+    }
+
+    pub fn manifest(&self) -> &PackManifest {
+        &self.manifest
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn reference(&self) -> PackRef {
+        self.manifest.reference()
+    }
+
+    /// Registered (namespaced) names of the pack's rules, in manifest order
+    pub fn rule_names(&self) -> Vec<String> {
+        self.rules.iter().map(|rule| self.namespaced(&rule.name)).collect()
+    }
+
+    pub fn tests(&self) -> impl Iterator<Item = &PackTest> {
+        self.tests.iter().map(|(_, test)| test)
+    }
+
+    /// Fresh instances of the pack's rules, named `<pack>/<rule>`
+    pub fn rules(&self) -> Result<Vec<Box<dyn Rule>>, PackError> {
+        self.rules.iter()
+            .map(|source| {
+                let name = self.namespaced(&source.name);
+                let rule: Box<dyn Rule> = match source.kind {
+                    PackRuleKind::Policy => Box::new(DslRule::new().with_json_policy(&source.text).map_err(|e| PackError::Rule {
+                        path: source.path.clone(),
+                        message: e.to_string(),
+                    })?),
+                    PackRuleKind::Sparql => Box::new(SparqlRule::new(&name, &source.text).map_err(|e| PackError::Rule {
+                        path: source.path.clone(),
+                        message: e.to_string(),
+                    })?),
+                };
+                Ok(Box::new(PackRule { name, inner: rule }) as Box<dyn Rule>)
+            })
+            .collect()
+    }
+
+    /// Import the pack's ontology fragments into `store`; returns the number of triples
+    pub fn load_ontology(&self, store: &mut RdfStore) -> Result<usize, PackError> {
+        let mut count = 0;
+        for (path, text) in &self.ontology {
+            count += import_ontology(store, path, text)?;
+        }
+        Ok(count)
+    }
+
+    /// Run every pack test against a fresh store holding the ontology and the test's fixtures
+    pub async fn run_tests(&self) -> Result<Vec<PackTestOutcome>, PackError> {
+        let mut outcomes = Vec::new();
+        for (_, test) in &self.tests {
+            outcomes.push(self.run_test(test).await?);
+        }
+        Ok(outcomes)
+    }
+
+    async fn run_test(&self, test: &PackTest) -> Result<PackTestOutcome, PackError> {
+        let mut store = RdfStore::new();
+        self.load_ontology(&mut store)?;
+        let sensor = Provenance::Sensor { source: PACK_TEST_SOURCE.to_string(), confidence: None };
+        for triple in &test.triples {
+            store.insert(triple.clone(), GraphId::Default, sensor.clone());
+        }
+
+        let mut registry = RuleRegistry::new();
+        for rule in self.rules()? {
+            registry.register_rule(rule);
+        }
+        let run = registry
+            .apply_to_fixpoint(&mut store, &GraphId::Inferred(PACK_TEST_SOURCE.to_string()), PACK_TEST_MAX_ITERATIONS)
+            .await
+            .map_err(|source| PackError::TestExecution { test: test.name.clone(), source })?;
+        let actions: usize = run.results.iter().map(|result| result.actions.len()).sum();
+
+        let present = |triple: &Triple| {
+            !store.find_triples(Some(triple.subject.as_str()), Some(triple.predicate.as_str()), Some(triple.object.as_str())).is_empty()
+        };
+        let mut failures = Vec::new();
+        for triple in test.expect_triples.iter().filter(|triple| !present(triple)) {
+            failures.push(format!("missing {} {} {}", triple.subject, triple.predicate, triple.object));
+        }
+        for triple in test.expect_absent.iter().filter(|triple| present(triple)) {
+            failures.push(format!("unexpected {} {} {}", triple.subject, triple.predicate, triple.object));
+        }
+        if let Some(expected) = test.expect_actions.filter(|expected| actions < *expected) {
+            failures.push(format!("expected at least {} actions, got {}", expected, actions));
+        }
+
+        Ok(PackTestOutcome {
+            name: test.name.clone(),
+            passed: failures.is_empty(),
+            failures,
+            actions,
+            inferred: run.inferred.len(),
+        })
+    }
+
+    fn namespaced(&self, rule: &str) -> String {
+        format!("{}{}{}", self.manifest.name, PACK_NAMESPACE_SEPARATOR, rule)
+    }
+}
+
+impl RuleInfo {
+    /// Name of the rule within its pack (the full name for rules outside packs)
+    pub fn local_name(&self) -> &str {
+        match &self.pack {
+            Some(pack) => self.name
+                .strip_prefix(pack.name.as_str())
+                .and_then(|rest| rest.strip_prefix(PACK_NAMESPACE_SEPARATOR))
+                .unwrap_or(&self.name),
+            None => &self.name,
+        }
+    }
+}
+
+/// A pack rule under its namespaced name
+struct PackRule {
+    name: String,
+    inner: Box<dyn Rule>,
+}
+
+#[async_trait]
+impl Rule for PackRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn priority(&self) -> i32 {
+        self.inner.priority()
+    }
+
+    async fn apply(&self, store: &RdfStore) -> Result<RuleResult, RuleError> {
+        self.inner.apply(store).await
+    }
+
+    fn should_apply(&self, store: &RdfStore) -> bool {
+        self.inner.should_apply(store)
+    }
+
+    fn consumes(&self) -> Vec<String> {
+        self.inner.consumes()
+    }
+
+    fn produces(&self) -> Vec<String> {
+        self.inner.produces()
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        self.inner.attack_techniques()
+    }
+}
+
+fn read(path: &Path) -> Result<String, PackError> {
+    std::fs::read_to_string(path).map_err(|source| PackError::Io { path: path.to_path_buf(), source })
+}
+
+/// `file` under `root`, rejecting paths that leave the pack
+fn resolve(root: &Path, file: &str) -> Result<PathBuf, PackError> {
+    let relative = Path::new(file);
+    if !relative.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+        return Err(PackError::Invalid(format!("path {} leaves the pack directory", file)));
+    }
+    Ok(root.join(relative))
+}
+
+fn rule_source(path: PathBuf) -> Result<PackRuleSource, PackError> {
+    let text = read(&path)?;
+    let (kind, name) = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => {
+            let policy: SecurityPolicy = serde_json::from_str(&text).map_err(|e| PackError::Rule {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+            (PackRuleKind::Policy, policy.name)
+        }
+        Some("rq" | "sparql") => {
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+            (PackRuleKind::Sparql, stem)
+        }
+        _ => {
+            return Err(PackError::Rule {
+                path,
+                message: "unsupported rule file (expected .json, .rq or .sparql)".to_string(),
+            })
+        }
+    };
+    if name.is_empty() || name.contains(PACK_NAMESPACE_SEPARATOR) {
+        return Err(PackError::Rule { path, message: format!("invalid rule name {:?}", name) });
+    }
+    Ok(PackRuleSource { path, kind, name, text })
+}
+
+/// N-Triples / N-Quads 以外は Turtle (TriG のサブセット) として読む
+fn import_ontology(store: &mut RdfStore, path: &Path, text: &str) -> Result<usize, PackError> {
+    let format = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("nt" | "nq" | "nquads") => DatasetFormat::NQuads,
+        _ => DatasetFormat::TriG,
+    };
+    store.import_dataset(text.as_bytes(), format, &path.display().to_string())
+        .map_err(|e| PackError::Ontology { path: path.to_path_buf(), message: e.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+    const POLICY: &str = r#"{
+        "name": "admin_share",
+        "description": "Access to administrative shares",
+        "version": "1.0.0",
+        "priority": 10,
+        "rules": [],
+        "metadata": {}
+    }"#;
+
+    fn write_pack(dir: &Path, version: &str) {
+        std::fs::create_dir_all(dir.join("rules")).unwrap();
+        std::fs::create_dir_all(dir.join("ontology")).unwrap();
+        std::fs::create_dir_all(dir.join("tests")).unwrap();
+        std::fs::write(dir.join(PACK_MANIFEST), serde_json::json!({
+            "name": "lateral",
+            "version": version,
+            "rules": ["rules/admin_share.json", "rules/remote_exec.rq"],
+            "ontology": ["ontology/lateral.ttl"],
+            "tests": ["tests/remote_exec.json"]
+        }).to_string()).unwrap();
+        std::fs::write(dir.join("rules/admin_share.json"), POLICY).unwrap();
+        std::fs::write(dir.join("rules/remote_exec.rq"), "PREFIX ex: <http://example.org/>\n\
+            CONSTRUCT { ?h a ex:LateralTarget . } WHERE { ?p ex:spawnedBy ?svc .\n?svc a ex:RemoteService .\n?p ex:host ?h . }").unwrap();
+        std::fs::write(dir.join("ontology/lateral.ttl"), "@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+            <http://example.org/PsExec> rdfs:subClassOf <http://example.org/RemoteService> .\n").unwrap();
+        let triple = |s: &str, p: &str, o: &str| serde_json::json!({ "subject": s, "predicate": p, "object": o });
+        std::fs::write(dir.join("tests/remote_exec.json"), serde_json::json!({
+            "name": "remote service child marks the host",
+            "triples": [
+                triple("http://example.org/p1", "http://example.org/spawnedBy", "http://example.org/svc1"),
+                triple("http://example.org/svc1", RDF_TYPE, "http://example.org/RemoteService"),
+                triple("http://example.org/p1", "http://example.org/host", "http://example.org/h1")
+            ],
+            "expect_triples": [triple("http://example.org/h1", RDF_TYPE, "http://example.org/LateralTarget")],
+            "expect_absent": [triple("http://example.org/p1", RDF_TYPE, "http://example.org/LateralTarget")]
+        }).to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_register_and_run_pack() {
+        let dir = std::env::temp_dir().join(format!("fukurow-pack-{}", std::process::id()));
+        write_pack(&dir, "1.2.0");
+
+        let pack = DetectionPack::load(&dir).unwrap();
+        assert_eq!(pack.rule_names(), vec!["lateral/admin_share", "lateral/remote_exec"]);
+        assert_eq!(pack.load_ontology(&mut RdfStore::new()).unwrap(), 1);
+
+        let outcomes = pack.run_tests().await.unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].passed, "{:?}", outcomes[0].failures);
+
+        let mut registry = RuleRegistry::new();
+        let reference = registry.register_pack(&pack).unwrap();
+        assert_eq!(reference, PackRef { name: "lateral".to_string(), version: "1.2.0".to_string() });
+        assert_eq!(registry.packs(), std::slice::from_ref(&reference));
+        let info = registry.rule("lateral/remote_exec").unwrap();
+        assert_eq!(info.pack, Some(reference));
+        assert_eq!(info.local_name(), "remote_exec");
+        assert!(matches!(registry.register_pack(&pack), Err(PackError::Conflict(_))));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rejects_invalid_manifests_and_paths() {
+        let dir = std::env::temp_dir().join(format!("fukurow-pack-invalid-{}", std::process::id()));
+        write_pack(&dir, "1.2");
+        assert!(matches!(DetectionPack::load(&dir), Err(PackError::Invalid(_))));
+
+        std::fs::write(dir.join(PACK_MANIFEST), serde_json::json!({
+            "name": "lateral", "version": "1.0.0", "rules": ["../outside.rq"]
+        }).to_string()).unwrap();
+        assert!(matches!(DetectionPack::load(&dir), Err(PackError::Invalid(message)) if message.contains("leaves")));
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(compare_versions("1.10.0", "1.9.3"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("2.0.0-rc.1", "2.0.0"), Some(Ordering::Less));
+        assert_eq!(compare_versions("2.0", "2.0.0"), None);
+    }
+}
@@ -0,0 +1,403 @@
+//! SPARQL-defined rules
+//!
+//! 検知ルールを Rust で実装せずに SPARQL だけで書く:
+//! - CONSTRUCT: WHERE 句に一致したら、テンプレートのトリプルを推論結果として追加する
+//! - ASK: WHERE 句に一致したらアラートを出す
+//!
+//! 他のルールと同じく [`RuleRegistry`](crate::RuleRegistry) に登録して適用する。
+//! クエリでは既定の接頭辞 (`rdf:`, `owl:`, `cyber:` など) をそのまま使える
+
+use crate::{Rule, RuleError, RuleResult};
+use async_trait::async_trait;
+use fukurow_core::model::SecurityAction;
+use fukurow_core::prefix::PrefixMap;
+use fukurow_sparql::parser::{DefaultSparqlParser, GraphPattern, Term, TriplePattern};
+use fukurow_sparql::{QueryResult, QueryType, SparqlParser, SparqlQuery};
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Query form of a [`SparqlRule`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SparqlRuleKind {
+    /// Adds the constructed triples
+    Construct,
+    /// Raises an alert when the pattern matches
+    Ask,
+}
+
+impl SparqlRuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SparqlRuleKind::Construct => "construct",
+            SparqlRuleKind::Ask => "ask",
+        }
+    }
+}
+
+/// Declarative definition of a SPARQL rule (e.g. loaded from JSON)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparqlRuleDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// CONSTRUCT or ASK query
+    pub query: String,
+    #[serde(default)]
+    pub priority: i32,
+    /// Alert severity; ASK rules default to `medium`, CONSTRUCT rules only alert when it is set
+    #[serde(default)]
+    pub severity: Option<String>,
+    /// Alert message (defaults to the description)
+    #[serde(default)]
+    pub message: Option<String>,
+    /// MITRE ATT&CK technique IDs the rule detects
+    #[serde(default)]
+    pub techniques: Vec<String>,
+    /// Prefixes declared in addition to the default ones
+    #[serde(default = "PrefixMap::empty")]
+    pub prefixes: PrefixMap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SparqlAlert {
+    severity: String,
+    message: String,
+}
+
+/// Rule defined by a SPARQL CONSTRUCT or ASK query
+#[derive(Debug, Clone)]
+pub struct SparqlRule {
+    name: String,
+    description: String,
+    kind: SparqlRuleKind,
+    /// Query with the prefix declarations prepended
+    query: String,
+    priority: i32,
+    alert: Option<SparqlAlert>,
+    techniques: Vec<String>,
+    consumes: Vec<String>,
+    produces: Vec<String>,
+}
+
+impl SparqlRule {
+    /// Rule for `query` using the default prefixes
+    pub fn new(name: &str, query: &str) -> Result<Self, RuleError> {
+        Self::with_prefixes(name, query, &PrefixMap::default())
+    }
+
+    /// Rule for `query` with `prefixes` declared before the query's own PREFIX lines
+    ///
+    /// 登録時に構文を検査し、CONSTRUCT と ASK 以外のクエリは受け付けない
+    pub fn with_prefixes(name: &str, query: &str, prefixes: &PrefixMap) -> Result<Self, RuleError> {
+        let query = format!("{}{}", prefixes.sparql_prologue(), query);
+        let parsed = DefaultSparqlParser.parse(&query).map_err(|e| RuleError::ConfigurationError {
+            message: format!("SPARQL rule {}: {}", name, e),
+        })?;
+
+        let (kind, produces) = match &parsed.query_type {
+            QueryType::Construct(template) => (SparqlRuleKind::Construct, template_predicates(template, &parsed)),
+            QueryType::Ask => (SparqlRuleKind::Ask, Vec::new()),
+            _ => {
+                return Err(RuleError::ConfigurationError {
+                    message: format!("SPARQL rule {} must be a CONSTRUCT or ASK query", name),
+                })
+            }
+        };
+        // 空のテンプレートや WHERE 句は何も生成・照合しないため、誤記として登録時に拒否する
+        if matches!(&parsed.query_type, QueryType::Construct(template) if template.is_empty()) {
+            return Err(RuleError::ConfigurationError {
+                message: format!("SPARQL rule {} has an empty CONSTRUCT template", name),
+            });
+        }
+        if matches!(&parsed.where_clause, GraphPattern::Bgp(triples) if triples.is_empty()) {
+            return Err(RuleError::ConfigurationError {
+                message: format!("SPARQL rule {} has an empty WHERE clause", name),
+            });
+        }
+        let mut consumes = Vec::new();
+        pattern_predicates(&parsed.where_clause, &parsed, &mut consumes);
+
+        let alert = (kind == SparqlRuleKind::Ask).then(|| SparqlAlert {
+            severity: "medium".to_string(),
+            message: format!("SPARQL rule {} matched", name),
+        });
+        Ok(Self {
+            name: name.to_string(),
+            description: String::new(),
+            kind,
+            query,
+            priority: 0,
+            alert,
+            techniques: Vec::new(),
+            consumes,
+            produces,
+        })
+    }
+
+    /// Build a rule from its declarative definition
+    pub fn from_definition(definition: &SparqlRuleDefinition) -> Result<Self, RuleError> {
+        let mut prefixes = PrefixMap::default();
+        prefixes.extend(&definition.prefixes);
+
+        let mut rule = Self::with_prefixes(&definition.name, &definition.query, &prefixes)?
+            .with_description(&definition.description)
+            .with_priority(definition.priority);
+        rule.techniques = definition.techniques.clone();
+        if definition.severity.is_some() || definition.message.is_some() || rule.alert.is_some() {
+            let severity = definition.severity.clone().unwrap_or_else(|| "medium".to_string());
+            let message = definition.message.clone()
+                .or_else(|| (!definition.description.is_empty()).then(|| definition.description.clone()))
+                .unwrap_or_else(|| format!("SPARQL rule {} matched", definition.name));
+            rule = rule.with_alert(&severity, &message);
+        }
+        Ok(rule)
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Alert raised when the rule matches (CONSTRUCT rules: when it constructs any triple)
+    pub fn with_alert(mut self, severity: &str, message: &str) -> Self {
+        self.alert = Some(SparqlAlert { severity: severity.to_string(), message: message.to_string() });
+        self
+    }
+
+    pub fn with_attack_techniques(mut self, techniques: &[&str]) -> Self {
+        self.techniques = techniques.iter().map(|technique| technique.to_string()).collect();
+        self
+    }
+
+    pub fn kind(&self) -> SparqlRuleKind {
+        self.kind
+    }
+
+    /// Query as executed (with the prefix declarations)
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+}
+
+#[async_trait]
+impl Rule for SparqlRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    async fn apply(&self, store: &RdfStore) -> Result<RuleResult, RuleError> {
+        let result = fukurow_sparql::execute_query(&self.query, store).map_err(|e| RuleError::ExecutionError {
+            message: format!("SPARQL rule {}: {}", self.name, e),
+        })?;
+
+        let (triples_to_add, matched) = match result {
+            QueryResult::Construct { triples } => {
+                let matched = !triples.is_empty();
+                (triples, matched)
+            }
+            QueryResult::Ask { result } => (Vec::new(), result),
+            // 登録時に CONSTRUCT / ASK 以外は拒否している
+            QueryResult::Select { .. } | QueryResult::Describe { .. } => (Vec::new(), false),
+        };
+
+        let mut actions = Vec::new();
+        if let (true, Some(alert)) = (matched, &self.alert) {
+            actions.push(SecurityAction::Alert {
+                severity: alert.severity.clone(),
+                message: alert.message.clone(),
+                details: serde_json::json!({
+                    "rule": self.name,
+                    "query_kind": self.kind.as_str(),
+                    "constructed_triples": triples_to_add.len(),
+                }),
+            });
+        }
+
+        Ok(RuleResult {
+            triples_to_add,
+            triples_to_remove: Vec::new(),
+            actions,
+            violations: Vec::new(),
+            metadata: HashMap::new(),
+        })
+    }
+
+    fn consumes(&self) -> Vec<String> {
+        self.consumes.clone()
+    }
+
+    fn produces(&self) -> Vec<String> {
+        self.produces.clone()
+    }
+
+    fn attack_techniques(&self) -> Vec<String> {
+        self.techniques.clone()
+    }
+}
+
+/// IRI of a constant predicate (`None` for variables)
+fn predicate_iri(term: &Term, query: &SparqlQuery) -> Option<String> {
+    match term {
+        Term::Iri(iri) => Some(iri.0.clone()),
+        Term::PrefixedName(prefix, local) => query.prefixes.get(prefix)
+            .map(|namespace| namespace.0.clone())
+            .or_else(|| fukurow_core::prefix::default_namespace(prefix).map(str::to_string))
+            .map(|namespace| format!("{}{}", namespace, local)),
+        _ => None,
+    }
+}
+
+fn template_predicates(template: &[TriplePattern], query: &SparqlQuery) -> Vec<String> {
+    let mut predicates = Vec::new();
+    for pattern in template {
+        if let Some(predicate) = predicate_iri(&pattern.predicate, query) {
+            if !predicates.contains(&predicate) {
+                predicates.push(predicate);
+            }
+        }
+    }
+    predicates
+}
+
+/// Constant predicates read by the WHERE clause (SERVICE patterns read remote data and are skipped)
+fn pattern_predicates(pattern: &GraphPattern, query: &SparqlQuery, predicates: &mut Vec<String>) {
+    match pattern {
+        GraphPattern::Bgp(triples) => {
+            for predicate in template_predicates(triples, query) {
+                if !predicates.contains(&predicate) {
+                    predicates.push(predicate);
+                }
+            }
+        }
+        GraphPattern::Optional(inner) | GraphPattern::Filter(_, inner) | GraphPattern::Graph(_, inner) => {
+            pattern_predicates(inner, query, predicates);
+        }
+        GraphPattern::Union(patterns) => {
+            for inner in patterns {
+                pattern_predicates(inner, query, predicates);
+            }
+        }
+        GraphPattern::Minus(left, right) | GraphPattern::Join(left, right) => {
+            pattern_predicates(left, query, predicates);
+            pattern_predicates(right, query, predicates);
+        }
+        GraphPattern::Service(..) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RuleRegistry;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+
+    const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+    fn store() -> RdfStore {
+        let mut store = RdfStore::new();
+        let sensor = Provenance::Sensor { source: "edr".to_string(), confidence: None };
+        for (s, p, o) in [
+            ("http://example.org/p1", RDF_TYPE, "http://example.org/Process"),
+            ("http://example.org/p1", "http://example.org/writes", "http://example.org/f1.locked"),
+            ("http://example.org/f1.locked", "http://example.org/extension", "\"locked\""),
+        ] {
+            store.insert(Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() }, GraphId::Default, sensor.clone());
+        }
+        store
+    }
+
+    #[tokio::test]
+    async fn test_construct_rule_adds_triples_through_registry() {
+        let rule = SparqlRule::new("ransomware_writer", r#"
+            PREFIX ex: <http://example.org/>
+            CONSTRUCT {
+                ?p a ex:SuspectedRansomware .
+            }
+            WHERE {
+                ?p ex:writes ?f .
+                ?f ex:extension "locked" .
+            }
+        "#).unwrap().with_alert("high", "Process writing encrypted files").with_attack_techniques(&["T1486"]);
+        assert_eq!(rule.kind(), SparqlRuleKind::Construct);
+        assert_eq!(rule.produces(), vec![RDF_TYPE]);
+        assert_eq!(rule.consumes(), vec!["http://example.org/writes", "http://example.org/extension"]);
+
+        let mut registry = RuleRegistry::new();
+        registry.register_rule(Box::new(rule));
+        let results = registry.apply_all_rules(&store()).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].triples_to_add[0].subject, "http://example.org/p1");
+        assert_eq!(results[0].triples_to_add[0].object, "http://example.org/SuspectedRansomware");
+        assert!(matches!(&results[0].actions[0], SecurityAction::Alert { severity, .. } if severity == "high"));
+    }
+
+    #[tokio::test]
+    async fn test_ask_rule_raises_alert_only_when_matched() {
+        let definition: SparqlRuleDefinition = serde_json::from_value(serde_json::json!({
+            "name": "locked_files",
+            "description": "Files with a ransomware extension",
+            "query": "ASK {\n?f ex:extension \"locked\" .\n}",
+            "prefixes": { "ex": "http://example.org/" },
+            "techniques": ["T1486"]
+        })).unwrap();
+        let rule = SparqlRule::from_definition(&definition).unwrap();
+        assert_eq!(rule.kind(), SparqlRuleKind::Ask);
+        assert_eq!(rule.attack_techniques(), vec!["T1486"]);
+
+        let result = rule.apply(&store()).await.unwrap();
+        assert!(result.triples_to_add.is_empty());
+        match &result.actions[..] {
+            [SecurityAction::Alert { severity, message, details }] => {
+                assert_eq!(severity, "medium");
+                assert_eq!(message, "Files with a ransomware extension");
+                assert_eq!(details["rule"], "locked_files");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        assert!(rule.apply(&RdfStore::new()).await.unwrap().actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_single_line_construct_rule() {
+        let rule = SparqlRule::new(
+            "ransomware_writer",
+            "PREFIX ex: <http://example.org/>\nCONSTRUCT { ?p a ex:SuspectedRansomware . } WHERE { ?p ex:writes ?f . ?f ex:extension \"locked\" . }",
+        ).unwrap();
+        assert_eq!(rule.produces(), vec![RDF_TYPE]);
+
+        let result = rule.apply(&store()).await.unwrap();
+        assert_eq!(result.triples_to_add.len(), 1);
+        assert_eq!(result.triples_to_add[0].object, "http://example.org/SuspectedRansomware");
+    }
+
+    #[test]
+    fn test_rejects_empty_template_and_where_clause() {
+        let empty_template = SparqlRule::new("empty_template", "CONSTRUCT { } WHERE { ?s ?p ?o . }");
+        assert!(matches!(empty_template, Err(RuleError::ConfigurationError { message }) if message.contains("CONSTRUCT template")));
+
+        let empty_where = SparqlRule::new("empty_where", "ASK { }");
+        assert!(matches!(empty_where, Err(RuleError::ConfigurationError { message }) if message.contains("WHERE clause")));
+    }
+
+    #[test]
+    fn test_rejects_select_queries() {
+        let select = SparqlRule::new("select", "SELECT ?s\nWHERE {\n?s ?p ?o .\n}");
+        assert!(matches!(select, Err(RuleError::ConfigurationError { .. })));
+    }
+}
@@ -2,9 +2,17 @@
 
 use async_trait::async_trait;
 use fukurow_core::model::{Triple, SecurityAction};
+use crate::dependency::RuleDependencyGraph;
+use crate::executor::{RuleExecutor, RuleOutcome, RuleTask, SequentialExecutor};
+use crate::pack::{DetectionPack, PackError, PackRef};
+use fukurow_store::confidence::{derived_confidence, ConfidenceCombination};
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::retention::evidence_key;
 use fukurow_store::store::RdfStore;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
 
 /// Result of rule application
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +29,69 @@ pub struct RuleResult {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+/// Metadata key holding the correlation IDs of the events a result was derived from
+pub const CORRELATION_IDS_KEY: &str = "correlation_ids";
+/// Metadata key holding the evidence of each added triple (keyed by `"s p o"`)
+pub const EVIDENCE_KEY: &str = "evidence";
+
+impl RuleResult {
+    /// Correlation IDs recorded in the metadata (empty when none were recorded)
+    pub fn correlation_ids(&self) -> Vec<String> {
+        self.metadata.get(CORRELATION_IDS_KEY)
+            .and_then(|value| value.as_array())
+            .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn set_correlation_ids(&mut self, ids: Vec<String>) {
+        self.metadata.insert(CORRELATION_IDS_KEY.to_string(), serde_json::json!(ids));
+    }
+
+    /// Triples `triple` was derived from (empty when the rule recorded none)
+    pub fn evidence_for(&self, triple: &Triple) -> Vec<Triple> {
+        self.metadata.get(EVIDENCE_KEY)
+            .and_then(|evidence| evidence.get(evidence_key(triple)))
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record the triples an added triple was derived from
+    ///
+    /// 根拠の確信度から推論結果の確信度を計算し、根拠が消えたときに推論を取り消すのに使う
+    pub fn set_evidence(&mut self, triple: &Triple, evidence: Vec<Triple>) {
+        let entry = self.metadata.entry(EVIDENCE_KEY.to_string()).or_insert_with(|| serde_json::json!({}));
+        if let Some(map) = entry.as_object_mut() {
+            map.insert(evidence_key(triple), serde_json::json!(evidence));
+        }
+    }
+
+    /// Append `other` (e.g. the result of another partition of the same rule)
+    ///
+    /// 根拠と相関 ID は両方のものを残し、その他のメタデータは `other` の値で上書きする
+    pub fn merge(&mut self, other: RuleResult) {
+        let mut ids = self.correlation_ids();
+        for id in other.correlation_ids() {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        for triple in &other.triples_to_add {
+            let evidence = other.evidence_for(triple);
+            if !evidence.is_empty() {
+                self.set_evidence(triple, evidence);
+            }
+        }
+        self.triples_to_add.extend(other.triples_to_add);
+        self.triples_to_remove.extend(other.triples_to_remove);
+        self.actions.extend(other.actions);
+        self.violations.extend(other.violations);
+        self.metadata.extend(other.metadata.into_iter().filter(|(key, _)| key != EVIDENCE_KEY));
+        if !ids.is_empty() {
+            self.set_correlation_ids(ids);
+        }
+    }
+}
+
 /// Validation violation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationViolation {
@@ -49,10 +120,10 @@ pub enum ViolationLevel {
 #[async_trait]
 pub trait Rule: Send + Sync {
     /// Get the rule name
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     /// Get the rule description
-    fn description(&self) -> &'static str;
+    fn description(&self) -> &str;
 
     /// Get the rule priority (higher = executed first)
     fn priority(&self) -> i32 { 0 }
@@ -62,16 +133,38 @@ pub trait Rule: Send + Sync {
 
     /// Check if this rule should be applied to the given graph
     fn should_apply(&self, store: &RdfStore) -> bool { true }
+
+    /// Predicates this rule reads; rules producing them run first
+    fn consumes(&self) -> Vec<String> { Vec::new() }
+
+    /// Predicates of the triples this rule adds
+    fn produces(&self) -> Vec<String> { Vec::new() }
+
+    /// MITRE ATT&CK technique IDs (`T1110`) this rule detects
+    ///
+    /// レジストリ経由で適用すると、このルールのアラートに技術 ID が付く
+    fn attack_techniques(&self) -> Vec<String> { Vec::new() }
+
+    /// Independent parts the rule can be applied in (e.g. policies or seed bindings)
+    ///
+    /// 2 以上を返すルールは並列実行時にパーティションごとに適用され、結果を順に
+    /// [`RuleResult::merge`] したものが [`Self::apply`] と同じになるよう [`Self::apply_partition`] を実装する
+    fn partitions(&self, _store: &RdfStore) -> usize { 1 }
+
+    /// Apply part `partition` (`0..partitions`) of the rule
+    async fn apply_partition(&self, store: &RdfStore, _partition: usize) -> Result<RuleResult, RuleError> {
+        self.apply(store).await
+    }
 }
 
 /// Validation rule trait (subset of Rule)
 #[async_trait]
 pub trait ValidationRule: Send + Sync {
     /// Get the rule name
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     /// Get the rule description
-    fn description(&self) -> &'static str;
+    fn description(&self) -> &str;
 
     /// Validate a graph and return violations
     async fn validate(&self, store: &RdfStore) -> Result<Vec<ValidationViolation>, RuleError>;
@@ -144,15 +237,80 @@ pub enum RuleError {
     #[error("Validation failed: {message}")]
     ValidationError { message: String },
 
+    #[error("Unknown rule: {name}")]
+    UnknownRule { name: String },
+
+    #[error("Rules {rules:?} still derived new triples after {iterations} iterations")]
+    IterationLimit { rules: Vec<String>, iterations: usize },
+
     #[error("Storage operation failed: {0}")]
     StoreError(#[from] anyhow::Error),
 }
 
+/// Outcome of [`RuleRegistry::apply_to_fixpoint`]
+#[derive(Debug, Clone, Default)]
+pub struct FixpointRun {
+    /// Result of every applied rule, in execution order
+    pub results: Vec<RuleResult>,
+    /// Triples inserted into the store, in derivation order
+    pub inferred: Vec<Triple>,
+    /// Rule passes over all strata
+    pub passes: usize,
+}
+
+/// Execution statistics of one rule (dry runs are not counted)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleStats {
+    pub applications: u64,
+    pub failures: u64,
+    pub triples_derived: u64,
+    pub actions: u64,
+    pub violations: u64,
+    pub total_duration_us: u64,
+    /// Epoch milliseconds of the last application
+    pub last_applied_at: Option<u64>,
+}
+
+/// Registered rule as reported by [`RuleRegistry::rules`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleInfo {
+    pub name: String,
+    pub description: String,
+    pub priority: i32,
+    pub enabled: bool,
+    pub consumes: Vec<String>,
+    pub produces: Vec<String>,
+    pub attack_techniques: Vec<String>,
+    pub stats: RuleStats,
+    /// Detection pack the rule was installed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<PackRef>,
+}
+
+/// Outcome of [`RuleRegistry::dry_run`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleDryRun {
+    pub rule: String,
+    pub enabled: bool,
+    /// Whether `should_apply` holds, i.e. whether a reasoning run would apply the rule
+    pub would_apply: bool,
+    pub result: RuleResult,
+    pub duration_us: u64,
+}
+
 /// Rule registry for managing multiple rules
+///
+/// 無効化したルールは推論で適用しない (依存関係グラフには残る)。
+/// 有効・無効と統計は共有された登録簿からも変更できるよう内部可変にしている
 pub struct RuleRegistry {
     rules: Vec<Box<dyn Rule>>,
     validation_rules: Vec<Box<dyn ValidationRule>>,
     inference_rules: Vec<Box<dyn InferenceRule>>,
+    disabled: RwLock<HashSet<String>>,
+    stats: Mutex<HashMap<String, RuleStats>>,
+    packs: Vec<PackRef>,
+    /// Rule name → pack it came from
+    rule_packs: HashMap<String, PackRef>,
 }
 
 impl RuleRegistry {
@@ -161,6 +319,10 @@ impl RuleRegistry {
             rules: Vec::new(),
             validation_rules: Vec::new(),
             inference_rules: Vec::new(),
+            disabled: RwLock::new(HashSet::new()),
+            stats: Mutex::new(HashMap::new()),
+            packs: Vec::new(),
+            rule_packs: HashMap::new(),
         }
     }
 
@@ -169,6 +331,31 @@ impl RuleRegistry {
         self.rules.push(rule);
     }
 
+    /// Register every rule of a detection pack under the `<pack>/` namespace
+    ///
+    /// 同名のパックが登録済みの場合や、ルール名が既存のルールと衝突する場合は何も登録しない
+    pub fn register_pack(&mut self, pack: &DetectionPack) -> Result<PackRef, PackError> {
+        let reference = pack.reference();
+        if let Some(existing) = self.packs.iter().find(|existing| existing.name == reference.name) {
+            return Err(PackError::Conflict(format!("pack {} {} is already registered", existing.name, existing.version)));
+        }
+        let rules = pack.rules()?;
+        if let Some(rule) = rules.iter().find(|rule| self.find(rule.name()).is_some()) {
+            return Err(PackError::Conflict(format!("rule {} is already registered", rule.name())));
+        }
+        for rule in rules {
+            self.rule_packs.insert(rule.name().to_string(), reference.clone());
+            self.rules.push(rule);
+        }
+        self.packs.push(reference.clone());
+        Ok(reference)
+    }
+
+    /// Detection packs registered through [`Self::register_pack`]
+    pub fn packs(&self) -> &[PackRef] {
+        &self.packs
+    }
+
     /// Register a validation rule
     pub fn register_validation_rule(&mut self, rule: Box<dyn ValidationRule>) {
         self.validation_rules.push(rule);
@@ -179,18 +366,293 @@ impl RuleRegistry {
         self.inference_rules.push(rule);
     }
 
-    /// Apply all rules to a store
+    /// Dependency graph of the registered rules
+    pub fn dependency_graph(&self) -> RuleDependencyGraph {
+        RuleDependencyGraph::build(&self.rules)
+    }
+
+    /// Rule name → ATT&CK technique IDs of every enabled rule that declares any
+    pub fn attack_technique_mappings(&self) -> BTreeMap<String, Vec<String>> {
+        self.rules.iter()
+            .filter(|rule| self.is_enabled(rule.name()))
+            .map(|rule| (rule.name().to_string(), rule.attack_techniques()))
+            .filter(|(_, techniques)| !techniques.is_empty())
+            .collect()
+    }
+
+    /// Registered rules with their state and statistics, in registration order
+    pub fn rules(&self) -> Vec<RuleInfo> {
+        self.rules.iter().map(|rule| self.info(rule.as_ref())).collect()
+    }
+
+    pub fn rule(&self, name: &str) -> Option<RuleInfo> {
+        self.find(name).map(|rule| self.info(rule))
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.read().unwrap_or_else(|poisoned| poisoned.into_inner()).contains(name)
+    }
+
+    /// Enable or disable a rule; takes effect from the next reasoning run
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<RuleInfo, RuleError> {
+        let rule = self.find(name).ok_or_else(|| RuleError::UnknownRule { name: name.to_string() })?;
+        let mut disabled = self.disabled.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if enabled {
+            disabled.remove(name);
+        } else {
+            disabled.insert(name.to_string());
+        }
+        drop(disabled);
+        Ok(self.info(rule))
+    }
+
+    /// Apply a single rule without committing anything, whether or not it is enabled
+    ///
+    /// 結果はストアに書き込まず、統計にも数えない
+    pub async fn dry_run(&self, name: &str, store: &RdfStore) -> Result<RuleDryRun, RuleError> {
+        let rule = self.find(name).ok_or_else(|| RuleError::UnknownRule { name: name.to_string() })?;
+        let started = Instant::now();
+        let result = Self::apply_rule(rule, store).await?;
+        Ok(RuleDryRun {
+            rule: name.to_string(),
+            enabled: self.is_enabled(name),
+            would_apply: rule.should_apply(store),
+            result,
+            duration_us: started.elapsed().as_micros() as u64,
+        })
+    }
+
+    fn find(&self, name: &str) -> Option<&dyn Rule> {
+        self.rules.iter().find(|rule| rule.name() == name).map(|rule| rule.as_ref())
+    }
+
+    fn info(&self, rule: &dyn Rule) -> RuleInfo {
+        RuleInfo {
+            name: rule.name().to_string(),
+            description: rule.description().to_string(),
+            priority: rule.priority(),
+            enabled: self.is_enabled(rule.name()),
+            consumes: rule.consumes(),
+            produces: rule.produces(),
+            attack_techniques: rule.attack_techniques(),
+            stats: self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+                .get(rule.name()).cloned().unwrap_or_default(),
+            pack: self.rule_packs.get(rule.name()).cloned(),
+        }
+    }
+
+    /// Whether a reasoning run applies `rule` to `store` now
+    fn runs(&self, rule: &dyn Rule, store: &RdfStore) -> bool {
+        self.is_enabled(rule.name()) && rule.should_apply(store)
+    }
+
+    async fn apply_rule(rule: &dyn Rule, store: &RdfStore) -> Result<RuleResult, RuleError> {
+        rule.apply(store).await.map(|result| Self::annotate(rule, result))
+    }
+
+    /// Tag the actions of `result` with the rule's ATT&CK techniques
+    fn annotate(rule: &dyn Rule, mut result: RuleResult) -> RuleResult {
+        let techniques = rule.attack_techniques();
+        if !techniques.is_empty() {
+            result.actions = result.actions.into_iter()
+                .map(|action| action.with_attack_techniques(&techniques))
+                .collect();
+        }
+        result
+    }
+
+    /// Apply `rules` to `store` through `executor`, recording their statistics
+    ///
+    /// 並列実行できるときは分割可能なルールをパーティションごとのタスクにする。
+    /// 結果は `rules` の順で、分割したルールも 1 回の適用として数える
+    async fn apply_batch<'a>(&'a self, rules: &[&'a dyn Rule], store: &'a RdfStore, executor: &dyn RuleExecutor) -> Vec<Result<RuleResult, RuleError>> {
+        let parallel = executor.concurrency() > 1;
+        let mut tasks: Vec<RuleTask<'a>> = Vec::new();
+        let mut partitions = Vec::with_capacity(rules.len());
+        for &rule in rules {
+            let count = if parallel { rule.partitions(store).max(1) } else { 1 };
+            partitions.push(count);
+            match count {
+                1 => tasks.push(Box::pin(RuleOutcome::measure(rule.apply(store)))),
+                _ => tasks.extend((0..count).map(move |partition| {
+                    Box::pin(RuleOutcome::measure(rule.apply_partition(store, partition))) as RuleTask<'a>
+                })),
+            }
+        }
+
+        let mut outcomes = executor.run(tasks).await.into_iter();
+        rules.iter().zip(partitions)
+            .map(|(&rule, count)| {
+                let mut duration_us = 0;
+                let result = outcomes.by_ref().take(count)
+                    .map(|outcome| {
+                        duration_us += outcome.duration_us;
+                        outcome.result
+                    })
+                    .reduce(|merged, result| match (merged, result) {
+                        (Ok(mut merged), Ok(result)) => {
+                            merged.merge(result);
+                            Ok(merged)
+                        }
+                        (Err(e), _) | (_, Err(e)) => Err(e),
+                    })
+                    .unwrap_or_else(|| Err(RuleError::ExecutionError { message: format!("Rule {} was not run", rule.name()) }))
+                    .map(|result| Self::annotate(rule, result));
+                self.record(rule, &result, duration_us);
+                result
+            })
+            .collect()
+    }
+
+    /// Add one application of `rule` to its statistics
+    fn record(&self, rule: &dyn Rule, result: &Result<RuleResult, RuleError>, duration_us: u64) {
+        let mut stats = self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = stats.entry(rule.name().to_string()).or_default();
+        entry.applications += 1;
+        entry.total_duration_us += duration_us;
+        entry.last_applied_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|now| now.as_millis() as u64);
+        match result {
+            Ok(result) => {
+                entry.triples_derived += result.triples_to_add.len() as u64;
+                entry.actions += result.actions.len() as u64;
+                entry.violations += result.violations.len() as u64;
+            }
+            Err(_) => entry.failures += 1,
+        }
+    }
+
+    /// Whether `rule` declares what it reads, so it can run alongside rules it does not depend on
+    fn declares_reads(&self, rule: usize) -> bool {
+        !self.rules[rule].consumes().is_empty()
+    }
+
+    /// Insert the triples `result` adds that the store does not hold yet; returns how many were new
+    fn materialize(rule: &dyn Rule, result: &RuleResult, store: &mut RdfStore, graph_id: &GraphId, reasoning_level: &str, combination: ConfidenceCombination, inferred: &mut Vec<Triple>) -> usize {
+        let mut derived = 0;
+        for triple in &result.triples_to_add {
+            if store.find_triples(Some(&triple.subject), Some(&triple.predicate), Some(&triple.object)).is_empty() {
+                let evidence = result.evidence_for(triple);
+                let confidence = derived_confidence(store, &evidence, combination);
+                store.insert(triple.clone(), graph_id.clone(), Provenance::Inferred {
+                    rule: rule.name().to_string(),
+                    reasoning_level: reasoning_level.to_string(),
+                    evidence: evidence.iter().map(evidence_key).collect(),
+                    confidence,
+                });
+                inferred.push(triple.clone());
+                derived += 1;
+            }
+        }
+        derived
+    }
+
+    /// Apply all rules to a store once, in dependency order
+    ///
+    /// ストアは変更しないため、前のルールの結果は後のルールから見えない。
+    /// 連鎖する推論には [`Self::apply_to_fixpoint`] を使う
     pub async fn apply_all_rules(&self, store: &RdfStore) -> Result<Vec<RuleResult>, RuleError> {
-        let mut results = Vec::new();
+        self.apply_all_rules_with(store, &SequentialExecutor).await
+    }
+
+    /// [`Self::apply_all_rules`] running the rules through `executor`
+    ///
+    /// ストアを変更しないので、すべてのルールを同時に実行できる
+    pub async fn apply_all_rules_with(&self, store: &RdfStore, executor: &dyn RuleExecutor) -> Result<Vec<RuleResult>, RuleError> {
+        let rules: Vec<&dyn Rule> = self.dependency_graph().execution_order().into_iter()
+            .map(|index| self.rules[index].as_ref())
+            .filter(|rule| self.runs(*rule, store))
+            .collect();
+        self.apply_batch(&rules, store, executor).await.into_iter().collect()
+    }
+
+    /// Apply rules stratum by stratum, inserting new triples into `graph_id` as they are derived
+    ///
+    /// 循環する層は新しいトリプルが出なくなるまで繰り返し、`max_iterations` 回で収束しなければ
+    /// [`RuleError::IterationLimit`] を返す。`triples_to_remove` は結果に残すだけで適用しない。
+    /// 各ルールの結果は、そのルールの層の最後の反復のもの
+    pub async fn apply_to_fixpoint(&self, store: &mut RdfStore, graph_id: &GraphId, max_iterations: usize) -> Result<FixpointRun, RuleError> {
+        self.apply_to_fixpoint_at_level(store, graph_id, max_iterations, "rules", ConfidenceCombination::default()).await
+    }
+
+    /// [`Self::apply_to_fixpoint`] recording `reasoning_level` in the provenance of derived triples
+    ///
+    /// ルールが根拠 ([`RuleResult::set_evidence`]) を記録した推論には、根拠の確信度を
+    /// `combination` で合成した確信度を付ける
+    pub async fn apply_to_fixpoint_at_level(&self, store: &mut RdfStore, graph_id: &GraphId, max_iterations: usize, reasoning_level: &str, combination: ConfidenceCombination) -> Result<FixpointRun, RuleError> {
+        self.apply_to_fixpoint_with(store, graph_id, max_iterations, reasoning_level, combination, &SequentialExecutor).await
+    }
+
+    /// [`Self::apply_to_fixpoint_at_level`] running independent rules through `executor`
+    ///
+    /// 並列実行できるとき、連続する非循環の層のうち互いに依存しないルールを同じストアの状態に
+    /// 対してまとめて実行し、結果を実行順にストアへ反映する (逐次実行と同じ推論結果になる)。
+    /// `consumes` を宣言しないルールは何を読むか分からないため、単独で実行する
+    pub async fn apply_to_fixpoint_with(&self, store: &mut RdfStore, graph_id: &GraphId, max_iterations: usize, reasoning_level: &str, combination: ConfidenceCombination, executor: &dyn RuleExecutor) -> Result<FixpointRun, RuleError> {
+        let graph = self.dependency_graph();
+        let strata = graph.strata();
+        let mut run = FixpointRun::default();
+
+        let mut next = 0;
+        while let Some(stratum) = strata.get(next) {
+            next += 1;
+            if !stratum.recursive {
+                let mut wave = vec![stratum.rules[0]];
+                if executor.concurrency() > 1 && self.declares_reads(stratum.rules[0]) {
+                    while let Some(candidate) = strata.get(next).filter(|candidate| !candidate.recursive).map(|candidate| candidate.rules[0]) {
+                        if !self.declares_reads(candidate) || wave.iter().any(|&member| graph.feeds(member, candidate)) {
+                            break;
+                        }
+                        wave.push(candidate);
+                        next += 1;
+                    }
+                }
+                run.passes += wave.len();
+                let rules: Vec<&dyn Rule> = wave.iter()
+                    .map(|&index| self.rules[index].as_ref())
+                    .filter(|rule| self.runs(*rule, store))
+                    .collect();
+                let results = self.apply_batch(&rules, store, executor).await;
+                for (rule, result) in rules.into_iter().zip(results) {
+                    let result = result?;
+                    Self::materialize(rule, &result, store, graph_id, reasoning_level, combination, &mut run.inferred);
+                    run.results.push(result);
+                }
+                continue;
+            }
 
-        for rule in &self.rules {
-            if rule.should_apply(store) {
-                let result = rule.apply(store).await?;
-                results.push(result);
+            let mut iterations = 0;
+            loop {
+                iterations += 1;
+                run.passes += 1;
+                let mut derived = 0;
+                let mut results = Vec::new();
+                for &index in &stratum.rules {
+                    let rule = self.rules[index].as_ref();
+                    if !self.runs(rule, store) {
+                        continue;
+                    }
+                    let result = self.apply_batch(&[rule], store, executor).await.remove(0)?;
+                    derived += Self::materialize(rule, &result, store, graph_id, reasoning_level, combination, &mut run.inferred);
+                    results.push(result);
+                }
+
+                if derived == 0 {
+                    run.results.extend(results);
+                    break;
+                }
+                if iterations >= max_iterations.max(1) {
+                    return Err(RuleError::IterationLimit {
+                        rules: graph.names_of(stratum).into_iter().map(str::to_string).collect(),
+                        iterations,
+                    });
+                }
             }
         }
 
-        Ok(results)
+        Ok(run)
     }
 
     /// Run all validation rules
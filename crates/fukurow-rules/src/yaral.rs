@@ -0,0 +1,605 @@
+//! # YARA-L Export
+//!
+//! Translate DSL security policies into Chronicle YARA-L 2.0 detection rules.
+//! トリプルパターンを UDM フィールド条件に写像し、変換できない構文は
+//! レポートに記録する (そのような条件を含むルールは出力しない)
+
+use crate::dsl::{
+    ComparisonOperator, Condition, ExtractType, PolicyAction, PolicyRule, SecurityPolicy, Severity, ValueExpression,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// UDM field targeted by a predicate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UdmField {
+    /// Path below the event variable (`principal.ip`)
+    pub path: String,
+    /// Numeric fields are compared without quoting
+    pub numeric: bool,
+    /// Literal rewrites (`true` → `ALLOW`)
+    pub value_aliases: HashMap<String, String>,
+}
+
+impl UdmField {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_string(), numeric: false, value_aliases: HashMap::new() }
+    }
+
+    pub fn numeric(mut self) -> Self {
+        self.numeric = true;
+        self
+    }
+
+    pub fn with_alias(mut self, value: &str, udm_value: &str) -> Self {
+        self.value_aliases.insert(value.to_string(), udm_value.to_string());
+        self
+    }
+}
+
+/// Exported rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YaraLRule {
+    /// Source DSL rule id
+    pub rule_id: String,
+    /// YARA-L rule identifier
+    pub name: String,
+    pub text: String,
+}
+
+/// Construct that could not be carried over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranslationIssue {
+    pub rule_id: String,
+    /// Short rendering of the offending construct
+    pub construct: String,
+    pub reason: String,
+    /// The rule was dropped because of this construct
+    pub blocking: bool,
+}
+
+/// Result of exporting a policy
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct YaraLExport {
+    pub rules: Vec<YaraLRule>,
+    pub issues: Vec<TranslationIssue>,
+}
+
+impl YaraLExport {
+    /// Every rule translated without losing anything
+    pub fn is_complete(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Ids of rules that were not exported
+    pub fn skipped_rules(&self) -> Vec<&str> {
+        let skipped: BTreeSet<&str> = self.issues.iter()
+            .filter(|issue| issue.blocking)
+            .map(|issue| issue.rule_id.as_str())
+            .collect();
+        skipped.into_iter().collect()
+    }
+
+    /// All rules concatenated into one rules file
+    pub fn to_text(&self) -> String {
+        self.rules.iter().map(|rule| rule.text.as_str()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// DSL → YARA-L 2.0 translator
+#[derive(Debug, Clone)]
+pub struct YaraLExporter {
+    /// Predicate IRI or local name → UDM field
+    fields: HashMap<String, UdmField>,
+    /// Class IRI or local name → `metadata.event_type`
+    event_types: HashMap<String, String>,
+    /// Window of the `match` section for multi-event rules
+    match_window: String,
+    author: String,
+}
+
+impl Default for YaraLExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl YaraLExporter {
+    /// Exporter pre-configured for the vocabulary produced by event ingestion
+    pub fn new() -> Self {
+        let fields = [
+            ("sourceIP", UdmField::new("principal.ip")),
+            ("destIP", UdmField::new("target.ip")),
+            ("port", UdmField::new("target.port").numeric()),
+            ("protocol", UdmField::new("network.ip_protocol")),
+            ("user", UdmField::new("principal.user.userid")),
+            ("commandLine", UdmField::new("target.process.command_line")),
+            ("processId", UdmField::new("target.process.pid")),
+            ("parentProcessId", UdmField::new("principal.process.pid")),
+            ("filePath", UdmField::new("target.file.full_path")),
+            ("timestamp", UdmField::new("metadata.event_timestamp.seconds").numeric()),
+            ("success", UdmField::new("security_result.action").with_alias("true", "ALLOW").with_alias("false", "BLOCK")),
+        ];
+        let event_types = [
+            ("NetworkConnection", "NETWORK_CONNECTION"),
+            ("ProcessExecution", "PROCESS_LAUNCH"),
+            ("FileAccess", "FILE_OPEN"),
+            ("UserLogin", "USER_LOGIN"),
+        ];
+
+        Self {
+            fields: fields.into_iter().map(|(name, field)| (name.to_string(), field)).collect(),
+            event_types: event_types.into_iter().map(|(class, ty)| (class.to_string(), ty.to_string())).collect(),
+            match_window: "5m".to_string(),
+            author: "fukurow".to_string(),
+        }
+    }
+
+    /// Map a predicate (full IRI or local name) to a UDM field
+    pub fn with_field(mut self, predicate: &str, field: UdmField) -> Self {
+        self.fields.insert(predicate.to_string(), field);
+        self
+    }
+
+    /// Map a class (full IRI or local name) to a UDM event type
+    pub fn with_event_type(mut self, class: &str, event_type: &str) -> Self {
+        self.event_types.insert(class.to_string(), event_type.to_string());
+        self
+    }
+
+    pub fn with_match_window(mut self, window: &str) -> Self {
+        self.match_window = window.to_string();
+        self
+    }
+
+    pub fn with_author(mut self, author: &str) -> Self {
+        self.author = author.to_string();
+        self
+    }
+
+    /// Translate every rule of a policy
+    pub fn export_policy(&self, policy: &SecurityPolicy) -> YaraLExport {
+        let mut export = YaraLExport::default();
+        for rule in &policy.rules {
+            let (translated, issues) = self.export_rule(policy, rule);
+            export.rules.extend(translated);
+            export.issues.extend(issues);
+        }
+        export
+    }
+
+    /// Translate a single rule; `None` when a blocking issue was found
+    pub fn export_rule(&self, policy: &SecurityPolicy, rule: &PolicyRule) -> (Option<YaraLRule>, Vec<TranslationIssue>) {
+        let mut ctx = RuleContext::default();
+        let mut issues = Vec::new();
+        let blocking = |construct: String, reason: String| TranslationIssue {
+            rule_id: rule.id.clone(),
+            construct,
+            reason,
+            blocking: true,
+        };
+
+        // トップレベルの条件は暗黙の AND なので1行ずつ出力する
+        for condition in &rule.conditions {
+            match self.condition(condition, &mut ctx) {
+                Ok(line) => ctx.lines.push(line),
+                Err(reason) => issues.push(blocking(describe(condition), reason)),
+            }
+        }
+
+        if ctx.events.is_empty() {
+            issues.push(blocking("conditions".to_string(), "no triple pattern maps to a UDM event".to_string()));
+        }
+
+        // 複数イベントは共有プレースホルダーで結合する
+        let joins: Vec<&String> = ctx.placeholders.iter()
+            .filter(|(_, events)| events.len() > 1)
+            .map(|(placeholder, _)| placeholder)
+            .collect();
+        if ctx.events.len() > 1 && joins.is_empty() {
+            issues.push(blocking(
+                ctx.events.iter().map(|e| format!("${}", e)).collect::<Vec<_>>().join(", "),
+                "multi-event rule has no shared variable to join on".to_string(),
+            ));
+        }
+
+        for action in &rule.actions {
+            if let PolicyAction::AddTriple { subject, predicate, object } | PolicyAction::RemoveTriple { subject, predicate, object } = action {
+                issues.push(TranslationIssue {
+                    rule_id: rule.id.clone(),
+                    construct: format!("{} {} {}", subject, predicate, object),
+                    reason: "graph updates have no YARA-L equivalent and were dropped".to_string(),
+                    blocking: false,
+                });
+            }
+        }
+
+        if issues.iter().any(|issue| issue.blocking) {
+            return (None, issues);
+        }
+
+        let name = rule_identifier(&rule.id);
+        let mut text = format!("rule {} {{\n  meta:\n", name);
+        let mut meta = vec![
+            ("author", self.author.clone()),
+            ("description", if rule.description.is_empty() { rule.name.clone() } else { rule.description.clone() }),
+            ("severity", severity_name(&rule.severity).to_string()),
+            ("fukurow_policy", policy.name.clone()),
+            ("fukurow_version", policy.version.clone()),
+            ("fukurow_rule_id", rule.id.clone()),
+        ];
+        if let Some(message) = rule.actions.iter().find_map(|action| match action {
+            PolicyAction::SecurityAction { message, .. } | PolicyAction::ReportViolation { message, .. } => Some(message.clone()),
+            _ => None,
+        }) {
+            meta.push(("summary", message));
+        }
+        if !rule.techniques.is_empty() {
+            meta.push(("mitre_attack_technique", rule.techniques.join(", ")));
+        }
+        for (key, value) in meta {
+            text.push_str(&format!("    {} = {}\n", key, quote(&value)));
+        }
+
+        text.push_str("\n  events:\n");
+        for line in &ctx.lines {
+            text.push_str(&format!("    {}\n", line));
+        }
+
+        if ctx.events.len() > 1 {
+            let joins: Vec<String> = joins.iter().map(|p| format!("${}", p)).collect();
+            text.push_str(&format!("\n  match:\n    {} over {}\n", joins.join(", "), self.match_window));
+        }
+
+        let events: Vec<String> = ctx.events.iter().map(|e| format!("${}", e)).collect();
+        text.push_str(&format!("\n  condition:\n    {}\n}}\n", events.join(" and ")));
+
+        (Some(YaraLRule { rule_id: rule.id.clone(), name, text }), issues)
+    }
+
+    fn condition(&self, condition: &Condition, ctx: &mut RuleContext) -> Result<String, String> {
+        match condition {
+            Condition::TripleExists { subject, predicate, object, .. } => {
+                let event = event_variable(subject)?;
+                if is_type_predicate(predicate) {
+                    let event_type = lookup(&self.event_types, object)
+                        .ok_or_else(|| format!("class {} has no UDM event type", object))?;
+                    ctx.events.insert(event.clone());
+                    return Ok(format!("${}.metadata.event_type = {}", event, quote(event_type)));
+                }
+                let field = self.field(predicate)?;
+                ctx.events.insert(event.clone());
+                match variable_name(object) {
+                    Some(placeholder) => {
+                        ctx.placeholders.entry(placeholder.clone()).or_default().insert(event.clone());
+                        Ok(format!("${} = ${}.{}", placeholder, event, field.path))
+                    }
+                    None => Ok(format!("${}.{} = {}", event, field.path, field_literal(field, object))),
+                }
+            }
+            Condition::TripleNotExists { subject, predicate, object } => {
+                if variable_name(object).is_some() {
+                    return Err("absence of any value cannot be expressed in YARA-L".to_string());
+                }
+                let event = event_variable(subject)?;
+                let field = self.field(predicate)?;
+                ctx.events.insert(event.clone());
+                Ok(format!("${}.{} != {}", event, field.path, field_literal(field, object)))
+            }
+            Condition::VariableBinding { variable, value } => {
+                Ok(format!("${} = {}", identifier(variable.trim_start_matches('?')), quote(value)))
+            }
+            Condition::NumericComparison { left, operator, right } => {
+                let left = self.value(left, ctx)?;
+                let pattern = |right: &ValueExpression| match right {
+                    ValueExpression::Constant(serde_json::Value::String(s)) => Ok(regex_literal(s)),
+                    _ => Err("Contains requires a constant string operand".to_string()),
+                };
+                match operator {
+                    ComparisonOperator::Contains => Ok(format!("re.regex({}, {})", left, pattern(right)?)),
+                    ComparisonOperator::NotContains => Ok(format!("not re.regex({}, {})", left, pattern(right)?)),
+                    _ => {
+                        let op = match operator {
+                            ComparisonOperator::Equal => "=",
+                            ComparisonOperator::NotEqual => "!=",
+                            ComparisonOperator::GreaterThan => ">",
+                            ComparisonOperator::LessThan => "<",
+                            ComparisonOperator::GreaterThanOrEqual => ">=",
+                            _ => "<=",
+                        };
+                        Ok(format!("{} {} {}", left, op, self.value(right, ctx)?))
+                    }
+                }
+            }
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                let joiner = if matches!(condition, Condition::And(_)) { " and " } else { " or " };
+                let parts = conditions.iter()
+                    .map(|c| self.condition(c, ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match parts.len() {
+                    0 => Err("empty logical group".to_string()),
+                    1 => Ok(parts.into_iter().next().unwrap_or_default()),
+                    _ => Ok(format!("({})", parts.join(joiner))),
+                }
+            }
+            Condition::Not(inner) => Ok(format!("not ({})", self.condition(inner, ctx)?)),
+        }
+    }
+
+    fn value(&self, expr: &ValueExpression, ctx: &mut RuleContext) -> Result<String, String> {
+        match expr {
+            ValueExpression::Constant(value) => match value {
+                serde_json::Value::String(s) => Ok(quote(s)),
+                serde_json::Value::Number(n) => Ok(n.to_string()),
+                serde_json::Value::Bool(b) => Ok(b.to_string()),
+                other => Err(format!("constant {} is not a scalar", other)),
+            },
+            ValueExpression::Variable(name) => Ok(format!("${}", identifier(name.trim_start_matches('?')))),
+            ValueExpression::TripleValue { subject, predicate, extract: ExtractType::Object } => {
+                let event = event_variable(subject)?;
+                let field = self.field(predicate)?;
+                ctx.events.insert(event.clone());
+                Ok(format!("${}.{}", event, field.path))
+            }
+            ValueExpression::TripleValue { extract, .. } => {
+                Err(format!("extracting the {:?} of a triple has no UDM equivalent", extract).to_lowercase())
+            }
+            ValueExpression::FunctionCall { function, .. } => {
+                Err(format!("function {} has no YARA-L equivalent", function))
+            }
+        }
+    }
+
+    fn field(&self, predicate: &str) -> Result<&UdmField, String> {
+        lookup(&self.fields, predicate).ok_or_else(|| format!("predicate {} has no UDM field mapping", predicate))
+    }
+}
+
+#[derive(Debug, Default)]
+struct RuleContext {
+    lines: Vec<String>,
+    /// Event variables (sorted for stable output)
+    events: BTreeSet<String>,
+    /// Placeholder → event variables assigning it
+    placeholders: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Full IRI first, then local name
+fn lookup<'a, T>(map: &'a HashMap<String, T>, iri: &str) -> Option<&'a T> {
+    map.get(iri).or_else(|| map.get(local_name(iri)))
+}
+
+fn local_name(iri: &str) -> &str {
+    iri.rsplit(['#', '/', ':']).next().filter(|name| !name.is_empty()).unwrap_or(iri)
+}
+
+fn is_type_predicate(predicate: &str) -> bool {
+    matches!(predicate, RDF_TYPE | "rdf:type" | "type" | "a")
+}
+
+fn variable_name(term: &str) -> Option<String> {
+    term.strip_prefix('?').map(identifier)
+}
+
+fn event_variable(subject: &str) -> Result<String, String> {
+    variable_name(subject).ok_or_else(|| format!("subject {} is not a variable; UDM events have no identity", subject))
+}
+
+/// Restrict to `[A-Za-z0-9_]`, starting with a letter
+fn identifier(name: &str) -> String {
+    let mut id: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if !id.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        id.insert_str(0, "v_");
+    }
+    id
+}
+
+fn rule_identifier(rule_id: &str) -> String {
+    let id = identifier(rule_id);
+    match id.strip_prefix("v_") {
+        Some(rest) => format!("fukurow_{}", rest),
+        None => id,
+    }
+}
+
+/// Lexical form of an RDF literal (`"443"^^xsd:int` → `443`)
+fn literal_value(term: &str) -> &str {
+    match term.strip_prefix('"').and_then(|rest| rest.rfind('"').map(|end| &rest[..end])) {
+        Some(value) => value,
+        None => term,
+    }
+}
+
+fn field_literal(field: &UdmField, object: &str) -> String {
+    let value = literal_value(object);
+    if let Some(alias) = field.value_aliases.get(value) {
+        return quote(alias);
+    }
+    if field.numeric && value.parse::<f64>().is_ok() {
+        value.to_string()
+    } else {
+        quote(value)
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Substring match as a YARA-L regex literal
+fn regex_literal(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        if c == '`' {
+            escaped.push_str("\\x60");
+        } else {
+            escaped.push(c);
+        }
+    }
+    format!("`{}`", escaped)
+}
+
+fn severity_name(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Low => "LOW",
+        Severity::Medium => "MEDIUM",
+        Severity::High => "HIGH",
+        Severity::Critical => "CRITICAL",
+    }
+}
+
+fn describe(condition: &Condition) -> String {
+    match condition {
+        Condition::TripleExists { subject, predicate, object, .. } => format!("{} {} {}", subject, predicate, object),
+        Condition::TripleNotExists { subject, predicate, object } => format!("NOT EXISTS {} {} {}", subject, predicate, object),
+        Condition::VariableBinding { variable, value } => format!("{} = {}", variable, value),
+        Condition::NumericComparison { operator, .. } => format!("comparison ({:?})", operator),
+        Condition::And(c) => format!("AND ({} conditions)", c.len()),
+        Condition::Or(c) => format!("OR ({} conditions)", c.len()),
+        Condition::Not(_) => "NOT".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(rules: Vec<PolicyRule>) -> SecurityPolicy {
+        SecurityPolicy {
+            name: "soc".to_string(),
+            description: String::new(),
+            version: "1.2.0".to_string(),
+            priority: 0,
+            rules,
+            metadata: HashMap::new(),
+            prefixes: fukurow_core::prefix::PrefixMap::empty(),
+        }
+    }
+
+    fn rule(id: &str, conditions: Vec<Condition>, actions: Vec<PolicyAction>) -> PolicyRule {
+        PolicyRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: "Failed login from blocked network".to_string(),
+            conditions,
+            actions,
+            severity: Severity::High,
+            metadata: HashMap::new(),
+            techniques: Vec::new(),
+        }
+    }
+
+    fn triple(s: &str, p: &str, o: &str) -> Condition {
+        Condition::TripleExists { subject: s.to_string(), predicate: p.to_string(), object: o.to_string(), min_confidence: None }
+    }
+
+    #[test]
+    fn test_single_event_rule() {
+        let rules = vec![rule(
+            "failed-login",
+            vec![
+                triple("?e", RDF_TYPE, "http://example.org/UserLogin"),
+                triple("?e", "http://example.org/success", "false"),
+                triple("?e", "http://example.org/sourceIP", "?ip"),
+                Condition::NumericComparison {
+                    left: ValueExpression::TripleValue {
+                        subject: "?e".to_string(),
+                        predicate: "http://example.org/user".to_string(),
+                        extract: ExtractType::Object,
+                    },
+                    operator: ComparisonOperator::Contains,
+                    right: ValueExpression::Constant(serde_json::json!("admin.")),
+                },
+            ],
+            vec![PolicyAction::SecurityAction {
+                action_type: crate::dsl::SecurityActionType::Alert,
+                message: "Admin login failed".to_string(),
+                details: HashMap::new(),
+            }],
+        )];
+
+        let export = YaraLExporter::new().export_policy(&policy(rules));
+        assert!(export.is_complete(), "{:?}", export.issues);
+        let text = &export.rules[0].text;
+        assert!(text.starts_with("rule failed_login {"));
+        assert!(text.contains("severity = \"HIGH\""));
+        assert!(text.contains("summary = \"Admin login failed\""));
+        assert!(text.contains("$e.metadata.event_type = \"USER_LOGIN\""));
+        assert!(text.contains("$e.security_result.action = \"BLOCK\""));
+        assert!(text.contains("$ip = $e.principal.ip"));
+        assert!(text.contains("re.regex($e.principal.user.userid, `admin\\.`)"));
+        assert!(text.contains("condition:\n    $e\n"));
+        assert!(!text.contains("match:"));
+    }
+
+    #[test]
+    fn test_multi_event_rule_joins_on_shared_placeholder() {
+        let rules = vec![rule(
+            "login-then-exec",
+            vec![
+                triple("?login", "rdf:type", "UserLogin"),
+                triple("?login", "user", "?user"),
+                triple("?proc", "rdf:type", "ProcessExecution"),
+                triple("?proc", "user", "?user"),
+                Condition::Or(vec![
+                    triple("?proc", "port", "4444"),
+                    Condition::TripleNotExists {
+                        subject: "?proc".to_string(),
+                        predicate: "commandLine".to_string(),
+                        object: "whoami".to_string(),
+                    },
+                ]),
+            ],
+            vec![],
+        )];
+
+        let export = YaraLExporter::new().with_match_window("10m").export_policy(&policy(rules));
+        assert!(export.is_complete(), "{:?}", export.issues);
+        let text = &export.rules[0].text;
+        assert!(text.contains("($proc.target.port = 4444 or $proc.target.process.command_line != \"whoami\")"));
+        assert!(text.contains("match:\n    $user over 10m"));
+        assert!(text.contains("condition:\n    $login and $proc"));
+    }
+
+    #[test]
+    fn test_untranslatable_constructs_are_reported() {
+        let rules = vec![
+            rule(
+                "uses-function",
+                vec![
+                    triple("?e", "http://example.org/threatScore", "?score"),
+                    Condition::NumericComparison {
+                        left: ValueExpression::FunctionCall { function: "now".to_string(), arguments: vec![] },
+                        operator: ComparisonOperator::GreaterThan,
+                        right: ValueExpression::Variable("score".to_string()),
+                    },
+                ],
+                vec![],
+            ),
+            rule(
+                "tags-host",
+                vec![triple("?e", "destIP", "10.0.0.5")],
+                vec![PolicyAction::AddTriple {
+                    subject: "?e".to_string(),
+                    predicate: "tag".to_string(),
+                    object: "watched".to_string(),
+                }],
+            ),
+        ];
+
+        let export = YaraLExporter::new().export_policy(&policy(rules));
+        assert_eq!(export.skipped_rules(), vec!["uses-function"]);
+        assert_eq!(export.rules.len(), 1);
+        assert_eq!(export.rules[0].rule_id, "tags-host");
+
+        let reasons: Vec<&str> = export.issues.iter().map(|issue| issue.reason.as_str()).collect();
+        assert!(reasons.iter().any(|r| r.contains("threatScore has no UDM field mapping")));
+        assert!(reasons.iter().any(|r| r.contains("function now")));
+        assert!(export.issues.iter().any(|issue| !issue.blocking && issue.rule_id == "tags-host"));
+    }
+}
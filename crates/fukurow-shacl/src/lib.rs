@@ -10,8 +10,8 @@ pub mod validator;
 pub mod report;
 
 // Re-exports
-pub use loader::{ShaclLoader, ShapesGraph, Shape, PropertyShape, NodeShape};
-pub use validator::{ShaclValidator, ValidationConfig, ValidationMode};
+pub use loader::{ShaclLoader, ShapesGraph, Shape, PropertyShape, PropertyPath, NodeShape};
+pub use validator::{ShaclValidator, ValidationConfig, ValidationMode, evaluate_path};
 pub use report::{ValidationReport, ValidationResult, ViolationLevel};
 
 // Error types
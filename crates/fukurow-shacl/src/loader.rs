@@ -55,7 +55,7 @@ pub enum Target {
 }
 
 /// Property Path
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PropertyPath {
     Predicate(Iri),
     Inverse(Box<PropertyPath>),
@@ -66,6 +66,114 @@ pub enum PropertyPath {
     ZeroOrOne(Box<PropertyPath>),
 }
 
+impl PropertyPath {
+    /// 単一の述語からなるパスならその述語 (`sh:resultPath` に使う)
+    pub fn as_predicate(&self) -> Option<&Iri> {
+        match self {
+            PropertyPath::Predicate(predicate) => Some(predicate),
+            _ => None,
+        }
+    }
+
+    /// 逆向きのパス
+    ///
+    /// `^(a/b)` = `^b/^a` のように、述語レベルまで逆向きを押し下げる
+    pub fn inverted(&self) -> PropertyPath {
+        match self {
+            PropertyPath::Predicate(_) => PropertyPath::Inverse(Box::new(self.clone())),
+            PropertyPath::Inverse(inner) => (**inner).clone(),
+            PropertyPath::Sequence(steps) => PropertyPath::Sequence(steps.iter().rev().map(|step| step.inverted()).collect()),
+            PropertyPath::Alternative(options) => PropertyPath::Alternative(options.iter().map(|option| option.inverted()).collect()),
+            PropertyPath::ZeroOrMore(inner) => PropertyPath::ZeroOrMore(Box::new(inner.inverted())),
+            PropertyPath::OneOrMore(inner) => PropertyPath::OneOrMore(Box::new(inner.inverted())),
+            PropertyPath::ZeroOrOne(inner) => PropertyPath::ZeroOrOne(Box::new(inner.inverted())),
+        }
+    }
+}
+
+const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+const SH_INVERSE_PATH: &str = "http://www.w3.org/ns/shacl#inversePath";
+const SH_ALTERNATIVE_PATH: &str = "http://www.w3.org/ns/shacl#alternativePath";
+const SH_ZERO_OR_MORE_PATH: &str = "http://www.w3.org/ns/shacl#zeroOrMorePath";
+const SH_ONE_OR_MORE_PATH: &str = "http://www.w3.org/ns/shacl#oneOrMorePath";
+const SH_ZERO_OR_ONE_PATH: &str = "http://www.w3.org/ns/shacl#zeroOrOnePath";
+
+/// `sh:path` の値ノードから Property Path を構築する
+///
+/// SHACL 2.3.1 の表現に従う:
+/// - IRI: 述語パス
+/// - RDF リスト: シーケンスパス
+/// - `sh:inversePath` / `sh:alternativePath` (リスト) / `sh:zeroOrMorePath` / `sh:oneOrMorePath` / `sh:zeroOrOnePath`
+pub fn parse_property_path(store: &RdfStore, node: &str) -> Result<PropertyPath, ShaclError> {
+    parse_path_node(store, node, &mut Vec::new())
+}
+
+fn parse_path_node(store: &RdfStore, node: &str, visiting: &mut Vec<String>) -> Result<PropertyPath, ShaclError> {
+    if visiting.iter().any(|visited| visited == node) {
+        return Err(ShaclError::LoaderError(format!("Cyclic property path at {}", node)));
+    }
+    visiting.push(node.to_string());
+
+    let object_of = |predicate: &str| store.find_triples(Some(node), Some(predicate), None)
+        .first()
+        .map(|stored| stored.triple.object.to_string());
+
+    let path = if object_of(RDF_FIRST).is_some() {
+        let steps = parse_path_list(store, node, visiting)?;
+        if steps.len() < 2 {
+            return Err(ShaclError::LoaderError(format!("Sequence path {} must have at least two members", node)));
+        }
+        PropertyPath::Sequence(steps)
+    } else if let Some(inner) = object_of(SH_INVERSE_PATH) {
+        PropertyPath::Inverse(Box::new(parse_path_node(store, &inner, visiting)?))
+    } else if let Some(list) = object_of(SH_ALTERNATIVE_PATH) {
+        let options = parse_path_list(store, &list, visiting)?;
+        if options.len() < 2 {
+            return Err(ShaclError::LoaderError(format!("Alternative path {} must have at least two members", node)));
+        }
+        PropertyPath::Alternative(options)
+    } else if let Some(inner) = object_of(SH_ZERO_OR_MORE_PATH) {
+        PropertyPath::ZeroOrMore(Box::new(parse_path_node(store, &inner, visiting)?))
+    } else if let Some(inner) = object_of(SH_ONE_OR_MORE_PATH) {
+        PropertyPath::OneOrMore(Box::new(parse_path_node(store, &inner, visiting)?))
+    } else if let Some(inner) = object_of(SH_ZERO_OR_ONE_PATH) {
+        PropertyPath::ZeroOrOne(Box::new(parse_path_node(store, &inner, visiting)?))
+    } else {
+        PropertyPath::Predicate(Iri(node.to_string()))
+    };
+
+    visiting.pop();
+    Ok(path)
+}
+
+/// RDF リストの各要素をパスとして読み込む
+fn parse_path_list(store: &RdfStore, head: &str, visiting: &mut Vec<String>) -> Result<Vec<PropertyPath>, ShaclError> {
+    let mut members = Vec::new();
+    let mut cells = Vec::new();
+    let mut current = head.to_string();
+
+    while current != RDF_NIL {
+        if cells.contains(&current) {
+            return Err(ShaclError::LoaderError(format!("Cyclic RDF list at {}", current)));
+        }
+        let first = store.find_triples(Some(current.as_str()), Some(RDF_FIRST), None).first()
+            .map(|stored| stored.triple.object.to_string())
+            .ok_or_else(|| ShaclError::LoaderError(format!("List node {} has no rdf:first", current)))?;
+        let rest = store.find_triples(Some(current.as_str()), Some(RDF_REST), None).first()
+            .map(|stored| stored.triple.object.to_string())
+            .ok_or_else(|| ShaclError::LoaderError(format!("List node {} has no rdf:rest", current)))?;
+
+        // リストのセル自体は訪問中のノードに含めない (要素側で循環を検出する)
+        members.push(parse_path_node(store, &first, visiting)?);
+        cells.push(current);
+        current = rest;
+    }
+
+    Ok(members)
+}
+
 /// Node Constraints
 #[derive(Debug, Clone)]
 pub enum NodeConstraint {
@@ -188,8 +296,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // targetClass 関係から Node Shape を検出
             if triple.predicate == sh_target_class.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let class_iri = Iri(triple.object.clone());
+                let shape_iri = Iri(triple.subject.to_string());
+                let class_iri = Iri(triple.object.to_string());
 
                 let shape = shapes.entry(shape_iri.clone()).or_insert_with(|| Shape::Node(NodeShape {
                     id: shape_iri.clone(),
@@ -205,8 +313,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // property 関係から Property Shape を検出
             if triple.predicate == sh_property.0.as_str() {
-                let parent_shape_iri = Iri(triple.subject.clone());
-                let prop_shape_iri = Iri(triple.object.clone());
+                let parent_shape_iri = Iri(triple.subject.to_string());
+                let prop_shape_iri = Iri(triple.object.to_string());
 
                 let parent_shape_entry = shapes.entry(parent_shape_iri.clone()).or_insert_with(||
                     Shape::Node(NodeShape {
@@ -233,8 +341,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // minCount 制約を検出
             if triple.predicate == sh_min_count.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let count_str = triple.object.clone();
+                let shape_iri = Iri(triple.subject.to_string());
+                let count_str = triple.object.to_string();
 
                 if let Some(count) = count_str.parse::<u64>().ok() {
                     let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
@@ -253,8 +361,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // maxCount 制約を検出
             if triple.predicate == sh_max_count.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let count_str = triple.object.clone();
+                let shape_iri = Iri(triple.subject.to_string());
+                let count_str = triple.object.to_string();
 
                 if let Some(count) = count_str.parse::<u64>().ok() {
                     let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
@@ -273,26 +381,26 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // Property path を検出
             if triple.predicate == sh_path.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let path_iri = Iri(triple.object.clone());
+                let shape_iri = Iri(triple.subject.to_string());
+                let path = parse_property_path(store, &triple.object)?;
 
                 let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
                     Shape::Property(PropertyShape {
                         id: shape_iri.clone(),
-                        path: PropertyPath::Predicate(path_iri.clone()),
+                        path: path.clone(),
                         constraints: vec![],
                     })
                 );
 
                 if let Shape::Property(prop_shape) = shape {
-                    prop_shape.path = PropertyPath::Predicate(path_iri);
+                    prop_shape.path = path;
                 }
             }
 
             // Property class 制約を検出
             if triple.predicate == sh_class.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let class_iri = Iri(triple.object.clone());
+                let shape_iri = Iri(triple.subject.to_string());
+                let class_iri = Iri(triple.object.to_string());
 
                 let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
                     Shape::Property(PropertyShape {
@@ -309,8 +417,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // datatype 制約を検出 (PropertyShape用)
             if triple.predicate == sh_datatype.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let datatype_iri = Iri(triple.object.clone());
+                let shape_iri = Iri(triple.subject.to_string());
+                let datatype_iri = Iri(triple.object.to_string());
 
                 let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
                     Shape::Property(PropertyShape {
@@ -327,8 +435,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // minLength 制約を検出 (PropertyShape用)
             if triple.predicate == sh_min_length.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let length_str = triple.object.clone();
+                let shape_iri = Iri(triple.subject.to_string());
+                let length_str = triple.object.to_string();
 
                 if let Some(length) = length_str.parse::<u64>().ok() {
                     let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
@@ -347,8 +455,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // maxLength 制約を検出 (PropertyShape用)
             if triple.predicate == sh_max_length.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let length_str = triple.object.clone();
+                let shape_iri = Iri(triple.subject.to_string());
+                let length_str = triple.object.to_string();
 
                 if let Some(length) = length_str.parse::<u64>().ok() {
                     let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
@@ -367,8 +475,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // pattern 制約を検出 (PropertyShape用)
             if triple.predicate == sh_pattern.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let pattern_str = triple.object.clone();
+                let shape_iri = Iri(triple.subject.to_string());
+                let pattern_str = triple.object.to_string();
 
                 let shape = shapes.entry(shape_iri.clone()).or_insert_with(||
                     Shape::Property(PropertyShape {
@@ -388,8 +496,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // class 制約を検出
             if triple.predicate == sh_class.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let class_iri = Iri(triple.object.clone());
+                let shape_iri = Iri(triple.subject.to_string());
+                let class_iri = Iri(triple.object.to_string());
 
                 let shape = shapes.entry(shape_iri.clone()).or_insert_with(|| Shape::Node(NodeShape {
                     id: shape_iri.clone(),
@@ -405,8 +513,8 @@ impl ShaclLoader for DefaultShaclLoader {
 
             // hasValue 制約を検出
             if triple.predicate == sh_has_value.0.as_str() {
-                let shape_iri = Iri(triple.subject.clone());
-                let value_str = triple.object.clone();
+                let shape_iri = Iri(triple.subject.to_string());
+                let value_str = triple.object.to_string();
 
                 let shape = shapes.entry(shape_iri.clone()).or_insert_with(|| Shape::Node(NodeShape {
                     id: shape_iri.clone(),
@@ -5,7 +5,7 @@ use crate::report::{ValidationReport, ValidationResult, ViolationLevel};
 use crate::ShaclError;
 use fukurow_store::store::RdfStore;
 use fukurow_sparql::parser::{Iri, Literal, Term};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashSet};
 
 /// Validation Configuration
 #[derive(Debug, Clone, Default)]
@@ -122,7 +122,7 @@ impl DefaultShaclValidator {
 
         // Property constraints を検証
         for constraint in &shape.constraints {
-            let constraint_results = self.validate_property_constraint_for_values(constraint, &values, node, store)?;
+            let constraint_results = self.validate_property_constraint_for_values(constraint, &shape.path, &values, node, store)?;
             results.extend(constraint_results);
         }
 
@@ -154,23 +154,13 @@ impl DefaultShaclValidator {
     }
 
     fn get_property_values_for_node(&self, path: &PropertyPath, node: &str, store: &RdfStore) -> Result<Vec<String>, ShaclError> {
-        match path {
-            PropertyPath::Predicate(predicate) => {
-                let mut values = Vec::new();
-                for stored_triple in store.all_triples().values().flatten() {
-                    let triple = &stored_triple.triple;
-                    if triple.subject == node && triple.predicate == predicate.0 {
-                        values.push(triple.object.clone());
-                    }
-                }
-                Ok(values)
-            }
-            _ => Err(ShaclError::UnsupportedFeature("Complex property paths not yet implemented".to_string())),
-        }
+        let focus = BTreeSet::from([node.to_string()]);
+        Ok(evaluate_path(path, &focus, store).into_iter().collect())
     }
 
-    fn validate_property_constraint_for_values(&self, constraint: &PropertyConstraint, values: &[String], focus_node: &str, store: &RdfStore) -> Result<Vec<ValidationResult>, ShaclError> {
+    fn validate_property_constraint_for_values(&self, constraint: &PropertyConstraint, path: &PropertyPath, values: &[String], focus_node: &str, store: &RdfStore) -> Result<Vec<ValidationResult>, ShaclError> {
         let mut results = Vec::new();
+        let result_path = path.as_predicate().cloned();
 
         match constraint {
             PropertyConstraint::Class(expected_class) => {
@@ -192,7 +182,7 @@ impl DefaultShaclValidator {
                     if !has_class {
                         results.push(ValidationResult {
                             focus_node: Some(Iri(focus_node.to_string())),
-                            result_path: Some(Iri("http://example.org/manager".to_string())), // TODO: get actual path from shape
+                            result_path: result_path.clone(),
                             value: Some(value.clone()),
                             source_constraint_component: Iri("http://www.w3.org/ns/shacl#class".to_string()),
                             source_shape: None, // TODO
@@ -221,7 +211,7 @@ impl DefaultShaclValidator {
                         println!("DEBUG: Adding validation error for datatype constraint");
                         results.push(ValidationResult {
                             focus_node: Some(Iri(focus_node.to_string())),
-                            result_path: Some(Iri("http://example.org/age".to_string())), // TODO: get actual path from shape
+                            result_path: result_path.clone(),
                             value: Some(value.clone()),
                             source_constraint_component: Iri("http://www.w3.org/ns/shacl#datatype".to_string()),
                             source_shape: None,
@@ -237,7 +227,7 @@ impl DefaultShaclValidator {
                 if values.len() < *min_count as usize {
                     results.push(ValidationResult {
                         focus_node: Some(Iri(focus_node.to_string())),
-                        result_path: Some(Iri("http://example.org/manager".to_string())), // TODO: get actual path from shape
+                        result_path: result_path.clone(),
                         value: None,
                         source_constraint_component: Iri("http://www.w3.org/ns/shacl#minCount".to_string()),
                         source_shape: None,
@@ -252,7 +242,7 @@ impl DefaultShaclValidator {
                     if value.len() < *min_length as usize {
                         results.push(ValidationResult {
                             focus_node: Some(Iri(focus_node.to_string())),
-                            result_path: Some(Iri("http://example.org/property".to_string())), // TODO: get actual path
+                            result_path: result_path.clone(),
                             value: Some(value.to_string()),
                             source_constraint_component: Iri("http://www.w3.org/ns/shacl#minLength".to_string()),
                             source_shape: None,
@@ -268,7 +258,7 @@ impl DefaultShaclValidator {
                     if value.len() > *max_length as usize {
                         results.push(ValidationResult {
                             focus_node: Some(Iri(focus_node.to_string())),
-                            result_path: Some(Iri("http://example.org/property".to_string())), // TODO: get actual path
+                            result_path: result_path.clone(),
                             value: Some(value.to_string()),
                             source_constraint_component: Iri("http://www.w3.org/ns/shacl#maxLength".to_string()),
                             source_shape: None,
@@ -285,7 +275,7 @@ impl DefaultShaclValidator {
                         if !regex.is_match(value) {
                             results.push(ValidationResult {
                                 focus_node: Some(Iri(focus_node.to_string())),
-                                result_path: Some(Iri("http://example.org/property".to_string())), // TODO: get actual path
+                                result_path: result_path.clone(),
                                 value: Some(value.to_string()),
                                 source_constraint_component: Iri("http://www.w3.org/ns/shacl#pattern".to_string()),
                                 source_shape: None,
@@ -315,7 +305,7 @@ impl DefaultShaclValidator {
                     for stored_triple in store.all_triples().values().flatten() {
                         let triple = &stored_triple.triple;
                         if triple.predicate == rdf_type.0 && triple.object == class.0 {
-                        nodes.insert(triple.subject.clone());
+                        nodes.insert(triple.subject.to_string());
                         }
                     }
                 }
@@ -326,7 +316,7 @@ impl DefaultShaclValidator {
                     for stored_triple in store.all_triples().values().flatten() {
                         let triple = &stored_triple.triple;
                         if triple.predicate == predicate.0 {
-                        nodes.insert(triple.subject.clone());
+                        nodes.insert(triple.subject.to_string());
                         }
                     }
                 }
@@ -334,7 +324,7 @@ impl DefaultShaclValidator {
                     for stored_triple in store.all_triples().values().flatten() {
                         let triple = &stored_triple.triple;
                         if triple.predicate == predicate.0 {
-                        nodes.insert(triple.object.clone());
+                        nodes.insert(triple.object.to_string());
                         }
                     }
                 }
@@ -474,6 +464,54 @@ impl DefaultShaclValidator {
     fn get_property_values(&self, path: &PropertyPath, store: &RdfStore) -> Result<Vec<String>, ShaclError> {
         // TODO: Property path に従って値を抽出
         // 簡易実装として全トリプルを返す
-        Ok(store.all_triples().values().flatten().map(|t| t.triple.object.clone()).collect())
+        Ok(store.all_triples().values().flatten().map(|t| t.triple.object.to_string()).collect())
+    }
+}
+
+/// `nodes` から `path` をたどって到達するノード (重複なし、安定した順序)
+///
+/// 逆パスは [`PropertyPath::inverted`] で述語レベルまで押し下げて評価し、
+/// `*` / `+` は到達済みノードを記録した幅優先探索で閉包を求めるため循環しても停止する
+pub fn evaluate_path(path: &PropertyPath, nodes: &BTreeSet<String>, store: &RdfStore) -> BTreeSet<String> {
+    match path {
+        PropertyPath::Predicate(predicate) => nodes.iter()
+            .flat_map(|node| store.find_triples(Some(node.as_str()), Some(predicate.0.as_str()), None))
+            .map(|stored| stored.triple.object.to_string())
+            .collect(),
+        PropertyPath::Inverse(inner) => match inner.as_ref() {
+            PropertyPath::Predicate(predicate) => nodes.iter()
+                .flat_map(|node| store.find_triples(None, Some(predicate.0.as_str()), Some(node.as_str())))
+                .map(|stored| stored.triple.subject.to_string())
+                .collect(),
+            inner => evaluate_path(&inner.inverted(), nodes, store),
+        },
+        PropertyPath::Sequence(steps) => steps.iter()
+            .fold(nodes.clone(), |reached, step| evaluate_path(step, &reached, store)),
+        PropertyPath::Alternative(options) => options.iter()
+            .flat_map(|option| evaluate_path(option, nodes, store))
+            .collect(),
+        PropertyPath::ZeroOrMore(inner) => {
+            let mut reached = transitive_closure(inner, nodes, store);
+            reached.extend(nodes.iter().cloned());
+            reached
+        }
+        PropertyPath::OneOrMore(inner) => transitive_closure(inner, nodes, store),
+        PropertyPath::ZeroOrOne(inner) => {
+            let mut reached = evaluate_path(inner, nodes, store);
+            reached.extend(nodes.iter().cloned());
+            reached
+        }
+    }
+}
+
+/// `inner` を 1 回以上たどって到達するノード
+fn transitive_closure(inner: &PropertyPath, nodes: &BTreeSet<String>, store: &RdfStore) -> BTreeSet<String> {
+    let mut reached = BTreeSet::new();
+    let mut frontier = evaluate_path(inner, nodes, store);
+    while !frontier.is_empty() {
+        let new_nodes: BTreeSet<String> = frontier.into_iter().filter(|node| !reached.contains(node)).collect();
+        reached.extend(new_nodes.iter().cloned());
+        frontier = evaluate_path(inner, &new_nodes, store);
     }
+    reached
 }
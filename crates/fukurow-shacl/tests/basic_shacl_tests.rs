@@ -3,7 +3,9 @@
 use fukurow_core::model::Triple;
 use fukurow_store::store::RdfStore;
 use fukurow_store::provenance::{Provenance, GraphId};
-use fukurow_shacl::{ShaclLoader, ShaclValidator, ValidationReport};
+use fukurow_shacl::{evaluate_path, PropertyPath, Shape, ShaclLoader, ShaclValidator};
+use fukurow_sparql::parser::Iri;
+use std::collections::BTreeSet;
 
 fn default_graph_id() -> GraphId {
     GraphId::Named("test".to_string())
@@ -489,3 +491,109 @@ fn test_shacl_multiple_shapes() {
     // Should conform since both shapes are satisfied
     assert!(report.conforms);
 }
+
+fn insert(store: &mut RdfStore, subject: &str, predicate: &str, object: &str) {
+    store.insert(Triple {
+        subject: subject.to_string(),
+        predicate: predicate.to_string(),
+        object: object.to_string(),
+    }, default_graph_id(), sensor_provenance());
+}
+
+#[test]
+fn test_shacl_sequence_and_one_or_more_path() {
+    const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+    const SH: &str = "http://www.w3.org/ns/shacl#";
+    const EX: &str = "http://example.org/";
+    let rdf = |local: &str| format!("{}{}", RDF, local);
+    let sh = |local: &str| format!("{}{}", SH, local);
+    let ex = |local: &str| format!("{}{}", EX, local);
+
+    let mut store = RdfStore::new();
+
+    // 承認済みポリシーの連鎖をたどれるホスト
+    insert(&mut store, &ex("host1"), &rdf("type"), &ex("Host"));
+    insert(&mut store, &ex("host1"), &ex("hasConfig"), &ex("config1"));
+    insert(&mut store, &ex("config1"), &ex("compliesWith"), &ex("baseline"));
+    insert(&mut store, &ex("baseline"), &ex("compliesWith"), &ex("corporatePolicy"));
+    insert(&mut store, &ex("corporatePolicy"), &ex("compliesWith"), &ex("baseline"));
+    insert(&mut store, &ex("baseline"), &rdf("type"), &ex("ApprovedPatchPolicy"));
+    insert(&mut store, &ex("corporatePolicy"), &rdf("type"), &ex("ApprovedPatchPolicy"));
+
+    // 未承認ポリシーに従うホストと、構成を持たないホスト
+    insert(&mut store, &ex("host2"), &rdf("type"), &ex("Host"));
+    insert(&mut store, &ex("host2"), &ex("hasConfig"), &ex("config2"));
+    insert(&mut store, &ex("config2"), &ex("compliesWith"), &ex("legacyPolicy"));
+    insert(&mut store, &ex("host3"), &rdf("type"), &ex("Host"));
+
+    // HostShape: sh:path ( ex:hasConfig [ sh:oneOrMorePath ex:compliesWith ] )
+    insert(&mut store, &ex("HostShape"), &rdf("type"), &sh("NodeShape"));
+    insert(&mut store, &ex("HostShape"), &sh("targetClass"), &ex("Host"));
+    insert(&mut store, &ex("HostShape"), &sh("property"), &ex("PatchPolicyProperty"));
+    insert(&mut store, &ex("PatchPolicyProperty"), &sh("path"), "_:seq0");
+    insert(&mut store, "_:seq0", &rdf("first"), &ex("hasConfig"));
+    insert(&mut store, "_:seq0", &rdf("rest"), "_:seq1");
+    insert(&mut store, "_:seq1", &rdf("first"), "_:plus");
+    insert(&mut store, "_:seq1", &rdf("rest"), &rdf("nil"));
+    insert(&mut store, "_:plus", &sh("oneOrMorePath"), &ex("compliesWith"));
+    insert(&mut store, &ex("PatchPolicyProperty"), &sh("class"), &ex("ApprovedPatchPolicy"));
+    insert(&mut store, &ex("PatchPolicyProperty"), &sh("minCount"), "1");
+
+    let loader = fukurow_shacl::loader::DefaultShaclLoader;
+    let shapes_graph = loader.load_from_store(&store).unwrap();
+    let property = shapes_graph.get_shape(&Iri(ex("PatchPolicyProperty"))).unwrap();
+    match property {
+        Shape::Property(property) => assert_eq!(property.path, PropertyPath::Sequence(vec![
+            PropertyPath::Predicate(Iri(ex("hasConfig"))),
+            PropertyPath::OneOrMore(Box::new(PropertyPath::Predicate(Iri(ex("compliesWith"))))),
+        ])),
+        Shape::Node(_) => panic!("expected a property shape"),
+    }
+
+    let validator = fukurow_shacl::validator::DefaultShaclValidator;
+    let config = fukurow_shacl::validator::ValidationConfig {
+        mode: fukurow_shacl::validator::ValidationMode::Warn,
+        report_jsonld: false,
+    };
+    let report = validator.validate_graph(&shapes_graph, &store, &config).unwrap();
+
+    // 循環する compliesWith も停止し、host1 は違反しない
+    let focus_nodes: Vec<String> = report.results.iter()
+        .filter_map(|result| result.focus_node.as_ref().map(|node| node.0.clone()))
+        .collect();
+    assert!(!focus_nodes.contains(&ex("host1")));
+    assert!(report.results.iter().any(|r| r.focus_node == Some(Iri(ex("host2"))) && r.value == Some(ex("legacyPolicy"))));
+    assert!(report.results.iter().any(|r| r.focus_node == Some(Iri(ex("host3")))
+        && r.source_constraint_component == Iri(sh("minCount"))));
+    assert!(report.results.iter().all(|r| r.result_path.is_none()));
+}
+
+#[test]
+fn test_shacl_inverse_alternative_and_optional_paths() {
+    let mut store = RdfStore::new();
+    insert(&mut store, "http://example.org/alice", "http://example.org/manages", "http://example.org/bob");
+    insert(&mut store, "http://example.org/bob", "http://example.org/manages", "http://example.org/carol");
+    insert(&mut store, "http://example.org/bob", "http://example.org/mentors", "http://example.org/dave");
+
+    let manages = PropertyPath::Predicate(Iri("http://example.org/manages".to_string()));
+    let mentors = PropertyPath::Predicate(Iri("http://example.org/mentors".to_string()));
+    let bob: BTreeSet<String> = BTreeSet::from(["http://example.org/bob".to_string()]);
+    let carol: BTreeSet<String> = BTreeSet::from(["http://example.org/carol".to_string()]);
+
+    let reports = evaluate_path(&PropertyPath::Alternative(vec![manages.clone(), mentors.clone()]), &bob, &store);
+    assert_eq!(reports.into_iter().collect::<Vec<_>>(), vec!["http://example.org/carol", "http://example.org/dave"]);
+
+    let managers = evaluate_path(&PropertyPath::Inverse(Box::new(manages.clone())), &bob, &store);
+    assert_eq!(managers.into_iter().collect::<Vec<_>>(), vec!["http://example.org/alice"]);
+
+    // ^(manages*) は自身と上位の管理者すべて
+    let chain = evaluate_path(&PropertyPath::Inverse(Box::new(PropertyPath::ZeroOrMore(Box::new(manages.clone())))), &carol, &store);
+    assert_eq!(chain.into_iter().collect::<Vec<_>>(), vec!["http://example.org/alice", "http://example.org/bob", "http://example.org/carol"]);
+
+    let optional = evaluate_path(&PropertyPath::ZeroOrOne(Box::new(mentors)), &carol, &store);
+    assert_eq!(optional.into_iter().collect::<Vec<_>>(), vec!["http://example.org/carol"]);
+
+    // ^(manages/manages) = ^manages/^manages
+    let skip_level = evaluate_path(&PropertyPath::Inverse(Box::new(PropertyPath::Sequence(vec![manages.clone(), manages]))), &carol, &store);
+    assert_eq!(skip_level.into_iter().collect::<Vec<_>>(), vec!["http://example.org/alice"]);
+}
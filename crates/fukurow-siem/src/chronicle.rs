@@ -1,16 +1,35 @@
 //! Google Chronicle SIEM統合
-
+//!
+//! - 送信: Ingestion API (`/v2/udmevents:batchCreate`) にイベントを UDM 形式でまとめて送る
+//! - 検索: Search API (`/v1/events:udmSearch`) の UDM 検索結果を `SiemEvent` に戻す
+//!
+//! batchCreate はバッチ内に 1 件でも不正なイベントがあると 400 でバッチ全体を拒否するため、
+//! 400 を受けたバッチは二分割して再送し、不正なイベントだけを失敗として数える
+
+use crate::udm::UdmEvent;
 use crate::{SiemClient, SiemConfig, SiemEvent, SiemResult, SiemError};
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use fukurow_core::model::CyberEvent;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+
+/// Default upper bound of one batchCreate request body (Chronicle accepts up to 1 MB)
+pub const DEFAULT_MAX_BATCH_BYTES: usize = 1024 * 1024;
+/// Default time range searched by `query_events`
+pub const DEFAULT_SEARCH_WINDOW_HOURS: i64 = 24;
 
 /// Google Chronicle client
 pub struct ChronicleClient {
     config: SiemConfig,
     client: Client,
     customer_id: String,
+    /// Search API base URL (defaults to the ingestion endpoint)
+    search_endpoint: Option<String>,
+    max_batch_bytes: usize,
+    search_window: Duration,
 }
 
 impl ChronicleClient {
@@ -20,9 +39,29 @@ impl ChronicleClient {
             client: Client::new(),
             config,
             customer_id: customer_id.to_string(),
+            search_endpoint: None,
+            max_batch_bytes: DEFAULT_MAX_BATCH_BYTES,
+            search_window: Duration::hours(DEFAULT_SEARCH_WINDOW_HOURS),
         }
     }
 
+    /// Use a separate Search API host (e.g. `https://backstory.googleapis.com`)
+    pub fn with_search_endpoint(mut self, endpoint: &str) -> Self {
+        self.search_endpoint = Some(endpoint.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn with_max_batch_bytes(mut self, bytes: usize) -> Self {
+        self.max_batch_bytes = bytes.max(1);
+        self
+    }
+
+    /// Time range ending now that `query_events` searches
+    pub fn with_search_window(mut self, window: Duration) -> Self {
+        self.search_window = window;
+        self
+    }
+
     /// Get authentication headers
     fn get_auth_headers(&self) -> Result<HashMap<String, String>, SiemError> {
         let mut headers = HashMap::new();
@@ -36,122 +75,87 @@ impl ChronicleClient {
 
         Ok(headers)
     }
-}
 
-#[async_trait]
-impl SiemClient for ChronicleClient {
-    async fn send_event(&self, event: SiemEvent) -> SiemResult<()> {
-        let url = format!("{}/v1/projects/{}/locations/global/instances/-/udmEvents:ingest",
-                         self.config.endpoint, self.customer_id);
-
-        // Convert SiemEvent to Chronicle UDM format
-        let udm_event = self.convert_to_udm(event)?;
-
-        let mut request = self.client
-            .post(&url)
-            .json(&udm_event);
+    fn authorized(&self, mut request: reqwest::RequestBuilder) -> SiemResult<reqwest::RequestBuilder> {
+        for (key, value) in self.get_auth_headers()? {
+            request = request.header(&key, &value);
+        }
+        Ok(request)
+    }
 
-        if let Ok(headers) = self.get_auth_headers() {
-            for (key, value) in headers {
-                request = request.header(&key, &value);
+    /// Ingest UDM events, isolating rejected events from the accepted ones
+    pub async fn ingest(&self, events: Vec<UdmEvent>) -> SiemResult<()> {
+        let total = events.len();
+        let mut failure = IngestFailure::default();
+        for batch in split_by_size(events, self.max_batch_bytes) {
+            if let Err(batch_failure) = ingest_isolating(batch, |batch| self.post_batch(batch)).await {
+                failure.merge(batch_failure);
             }
         }
 
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
+        match failure.message {
+            Some(message) => Err(SiemError::BulkError { failed: failure.failed, total, message }),
+            None => Ok(()),
         }
-
-        Ok(())
     }
 
-    async fn send_events(&self, events: Vec<SiemEvent>) -> SiemResult<()> {
-        // Chronicle API supports batch ingestion
-        let url = format!("{}/v1/projects/{}/locations/global/instances/-/udmEvents:batchCreate",
-                         self.config.endpoint, self.customer_id);
-
-        let udm_events: Result<Vec<_>, _> = events.into_iter()
-            .map(|event| self.convert_to_udm(event))
-            .collect();
-
-        let udm_events = udm_events?;
-        let batch_request = ChronicleBatchRequest { events: udm_events };
-
-        let mut request = self.client
-            .post(&url)
-            .json(&batch_request);
+    /// Map sensor events to their UDM types and ingest them
+    pub async fn send_cyber_events(&self, events: &[CyberEvent]) -> SiemResult<()> {
+        self.ingest(events.iter().map(UdmEvent::from_cyber_event).collect()).await
+    }
 
-        if let Ok(headers) = self.get_auth_headers() {
-            for (key, value) in headers {
-                request = request.header(&key, &value);
-            }
+    /// Search UDM events in `[start, end)`
+    pub async fn search(&self, query: &str, start: DateTime<Utc>, end: DateTime<Utc>, limit: Option<usize>) -> SiemResult<Vec<UdmEvent>> {
+        let endpoint = self.search_endpoint.as_deref().unwrap_or(&self.config.endpoint);
+        let url = format!("{}/v1/events:udmSearch", endpoint);
+
+        let mut params = vec![
+            ("query", query.to_string()),
+            ("time_range.start_time", start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            ("time_range.end_time", end.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        ];
+        if let Some(limit) = limit {
+            params.push(("limit", limit.to_string()));
         }
 
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
+        let request = self.authorized(self.client.get(&url).query(&params))?;
+        let response = crate::common::execute_with_retry(&self.config, request).await?;
+        let search: ChronicleSearchResponse = response.json().await?;
 
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
-        }
+        search.events.into_iter()
+            .map(|result| UdmEvent::from_search_json(result.udm).map_err(SiemError::from))
+            .collect()
+    }
 
+    async fn post_batch(&self, events: Vec<UdmEvent>) -> SiemResult<()> {
+        let url = format!("{}/v2/udmevents:batchCreate", self.config.endpoint);
+        let body = ChronicleBatchRequest { customer_id: &self.customer_id, events: &events };
+        let request = self.authorized(self.client.post(&url).json(&body))?;
+        crate::common::execute_with_retry(&self.config, request).await?;
         Ok(())
     }
+}
 
-    async fn query_events(&self, query: &str, limit: Option<usize>) -> SiemResult<Vec<SiemEvent>> {
-        let url = format!("{}/v1/projects/{}/locations/global/instances/-/udmEvents:query",
-                         self.config.endpoint, self.customer_id);
-
-        let query_request = ChronicleQueryRequest {
-            query: query.to_string(),
-            start_time: None, // Could be parsed from query
-            end_time: None,
-            limit: limit.map(|l| l as i32),
-        };
-
-        let mut request = self.client
-            .post(&url)
-            .json(&query_request);
-
-        if let Ok(headers) = self.get_auth_headers() {
-            for (key, value) in headers {
-                request = request.header(&key, &value);
-            }
-        }
-
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
-        }
+#[async_trait]
+impl SiemClient for ChronicleClient {
+    async fn send_event(&self, event: SiemEvent) -> SiemResult<()> {
+        self.send_events(vec![event]).await
+    }
 
-        let query_response: ChronicleQueryResponse = response.json().await?;
-        let events = query_response.events
-            .into_iter()
-            .map(|udm| self.convert_from_udm(udm))
-            .collect::<Result<Vec<_>, _>>()?;
+    async fn send_events(&self, events: Vec<SiemEvent>) -> SiemResult<()> {
+        self.ingest(events.iter().map(UdmEvent::from_siem_event).collect()).await
+    }
 
-        Ok(events)
+    async fn query_events(&self, query: &str, limit: Option<usize>) -> SiemResult<Vec<SiemEvent>> {
+        let end = Utc::now();
+        let events = self.search(query, end - self.search_window, end, limit).await?;
+        Ok(events.iter().map(UdmEvent::to_siem_event).collect())
     }
 
     async fn health_check(&self) -> SiemResult<bool> {
         // Chronicle doesn't have a specific health check endpoint
         // We can try a simple query to check connectivity
-        let test_query = "metadata.event_type = \"NETWORK_CONNECTION\" LIMIT 1";
+        let test_query = "metadata.event_type = \"NETWORK_CONNECTION\"";
 
         match self.query_events(test_query, Some(1)).await {
             Ok(_) => Ok(true),
@@ -162,167 +166,100 @@ impl SiemClient for ChronicleClient {
     }
 }
 
-impl ChronicleClient {
-    /// Convert SiemEvent to Chronicle UDM format
-    fn convert_to_udm(&self, event: SiemEvent) -> SiemResult<ChronicleUdmEvent> {
-        // Create a basic UDM event structure
-        // In practice, this would need more sophisticated mapping
-        let udm_event = ChronicleUdmEvent {
-            metadata: ChronicleMetadata {
-                event_type: "GENERIC_EVENT".to_string(), // Map event.event_type to UDM types
-                event_timestamp: Some(event.timestamp.timestamp_micros()),
-                collected_timestamp: event.timestamp.timestamp_micros(),
-                product_name: "Fukurow".to_string(),
-                product_version: "1.0.0".to_string(),
-                product_event_type: event.event_type.clone(),
-                vendor_name: "Fukurow".to_string(),
-                product_log_id: Some(event.id.clone()),
-            },
-            principal: None,
-            target: None,
-            src: None,
-            network: None,
-            security_result: Some(vec![ChronicleSecurityResult {
-                severity: match event.severity {
-                    crate::SiemSeverity::Low => "INFORMATIONAL".to_string(),
-                    crate::SiemSeverity::Medium => "LOW".to_string(),
-                    crate::SiemSeverity::High => "MEDIUM".to_string(),
-                    crate::SiemSeverity::Critical => "HIGH".to_string(),
-                },
-                action: vec!["ALERT".to_string()],
-                confidence: Some(0.8),
-                summary: event.message.clone(),
-                description: event.raw_data,
-            }]),
-            additional: Some(serde_json::json!({
-                "fukurow_source": event.source,
-                "fukurow_metadata": event.metadata
-            })),
-        };
-
-        Ok(udm_event)
-    }
-
-    /// Convert Chronicle UDM to SiemEvent
-    fn convert_from_udm(&self, udm: ChronicleUdmEvent) -> SiemResult<SiemEvent> {
-        let severity = if let Some(security_results) = &udm.security_result {
-            if let Some(first_result) = security_results.first() {
-                match first_result.severity.as_str() {
-                    "HIGH" | "CRITICAL" => crate::SiemSeverity::High,
-                    "MEDIUM" => crate::SiemSeverity::Medium,
-                    "LOW" => crate::SiemSeverity::Low,
-                    _ => crate::SiemSeverity::Medium,
-                }
-            } else {
-                crate::SiemSeverity::Medium
-            }
-        } else {
-            crate::SiemSeverity::Medium
-        };
-
-        let message = if let Some(security_results) = &udm.security_result {
-            if let Some(first_result) = security_results.first() {
-                first_result.summary.clone()
-            } else {
-                "Chronicle event".to_string()
-            }
-        } else {
-            "Chronicle event".to_string()
-        };
-
-        let additional = udm.additional.clone().unwrap_or(serde_json::Value::Null);
-        let raw_data = Some(serde_json::to_string(&udm).unwrap_or_default());
-
-        Ok(SiemEvent {
-            id: udm.metadata.product_log_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
-            timestamp: udm.metadata.event_timestamp
-                .map(|ts| chrono::DateTime::from_timestamp_micros(ts).unwrap_or_else(|| chrono::Utc::now()))
-                .unwrap_or_else(|| chrono::Utc::now()),
-            event_type: udm.metadata.product_event_type,
-            source: "chronicle".to_string(),
-            severity,
-            message,
-            metadata: additional,
-            raw_data,
-        })
-    }
+/// Events rejected by the ingestion API
+#[derive(Debug, Default)]
+struct IngestFailure {
+    failed: usize,
+    message: Option<String>,
 }
 
-/// Chronicle UDM Event structure (simplified)
-#[derive(Serialize, Deserialize)]
-struct ChronicleUdmEvent {
-    metadata: ChronicleMetadata,
-    principal: Option<ChronicleEntity>,
-    target: Option<ChronicleEntity>,
-    src: Option<ChronicleEntity>,
-    network: Option<ChronicleNetwork>,
-    security_result: Option<Vec<ChronicleSecurityResult>>,
-    additional: Option<serde_json::Value>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct ChronicleMetadata {
-    event_type: String,
-    event_timestamp: Option<i64>,
-    collected_timestamp: i64,
-    product_name: String,
-    product_version: String,
-    product_event_type: String,
-    vendor_name: String,
-    product_log_id: Option<String>,
-}
+impl IngestFailure {
+    fn all(count: usize, message: String) -> Self {
+        Self { failed: count, message: Some(message) }
+    }
 
-#[derive(Serialize, Deserialize)]
-struct ChronicleEntity {
-    hostname: Option<String>,
-    ip: Option<Vec<String>>,
-    user: Option<ChronicleUser>,
+    fn merge(&mut self, other: IngestFailure) {
+        self.failed += other.failed;
+        if self.message.is_none() {
+            self.message = other.message;
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct ChronicleUser {
-    userid: Option<String>,
+/// Group events into batches whose serialized size stays under `max_bytes`
+///
+/// 単体で上限を超えるイベントはそれだけのバッチにし、API 側の拒否に任せる
+fn split_by_size(events: Vec<UdmEvent>, max_bytes: usize) -> Vec<Vec<UdmEvent>> {
+    let mut batches = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = 0;
+    for event in events {
+        let size = serde_json::to_vec(&event).map(|bytes| bytes.len() + 1).unwrap_or(0);
+        if !current.is_empty() && current_bytes + size > max_bytes {
+            batches.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+        current_bytes += size;
+        current.push(event);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
 }
 
-#[derive(Serialize, Deserialize)]
-struct ChronicleNetwork {
-    ip_protocol: Option<i32>,
-    application_protocol: Option<String>,
-}
+/// Send a batch; on 400 split it in half until the rejected events are isolated
+///
+/// 400 以外のエラー (認証・リトライ上限到達など) は分割しても結果が変わらないため、
+/// そのバッチ全体を失敗とする
+async fn ingest_isolating<F, Fut>(batch: Vec<UdmEvent>, send: F) -> Result<(), IngestFailure>
+where
+    F: Fn(Vec<UdmEvent>) -> Fut,
+    Fut: Future<Output = SiemResult<()>>,
+{
+    let mut failure = IngestFailure::default();
+    let mut pending = vec![batch];
+    while let Some(mut batch) = pending.pop() {
+        let count = batch.len();
+        match send(batch.clone()).await {
+            Ok(()) => {}
+            Err(SiemError::ApiError { status: 400, .. }) if count > 1 => {
+                let second = batch.split_off(count / 2);
+                // 先頭側から送るため後半を先に積む
+                pending.push(second);
+                pending.push(batch);
+            }
+            Err(e) => failure.merge(IngestFailure::all(count, e.to_string())),
+        }
+    }
 
-#[derive(Serialize, Deserialize)]
-struct ChronicleSecurityResult {
-    severity: String,
-    action: Vec<String>,
-    confidence: Option<f64>,
-    summary: String,
-    description: Option<String>,
+    if failure.failed == 0 { Ok(()) } else { Err(failure) }
 }
 
 /// Chronicle batch request
 #[derive(Serialize)]
-struct ChronicleBatchRequest {
-    events: Vec<ChronicleUdmEvent>,
+struct ChronicleBatchRequest<'a> {
+    customer_id: &'a str,
+    events: &'a [UdmEvent],
 }
 
-/// Chronicle query request
-#[derive(Serialize)]
-struct ChronicleQueryRequest {
-    query: String,
-    start_time: Option<String>,
-    end_time: Option<String>,
-    limit: Option<i32>,
+/// Chronicle UDM search response
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChronicleSearchResponse {
+    #[serde(default)]
+    events: Vec<ChronicleSearchResult>,
 }
 
-/// Chronicle query response
 #[derive(Deserialize)]
-struct ChronicleQueryResponse {
-    events: Vec<ChronicleUdmEvent>,
+struct ChronicleSearchResult {
+    udm: serde_json::Value,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
 
     #[tokio::test]
     async fn test_chronicle_config() {
@@ -332,4 +269,52 @@ mod tests {
         let client = ChronicleClient::new(config, "test-customer-id");
         assert_eq!(client.customer_id, "test-customer-id");
     }
+
+    fn logged(id: &str) -> UdmEvent {
+        let mut event = UdmEvent::default();
+        event.metadata.event_timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        event.metadata.product_log_id = Some(id.to_string());
+        event
+    }
+
+    #[tokio::test]
+    async fn test_ingest_isolates_rejected_events() {
+        let batch: Vec<UdmEvent> = ["a", "b", "bad", "c", "d"].iter().map(|id| logged(id)).collect();
+        let accepted = Mutex::new(Vec::new());
+
+        let result = ingest_isolating(batch, |events| {
+            let rejected = events.iter().any(|e| e.metadata.product_log_id.as_deref() == Some("bad"));
+            if !rejected {
+                accepted.lock().unwrap().extend(events.iter().filter_map(|e| e.metadata.product_log_id.clone()));
+            }
+            async move {
+                if rejected {
+                    Err(SiemError::ApiError { status: 400, message: "invalid event".to_string() })
+                } else {
+                    Ok(())
+                }
+            }
+        }).await;
+
+        let failure = result.unwrap_err();
+        assert_eq!(failure.failed, 1);
+        assert!(failure.message.unwrap().contains("invalid event"));
+        assert_eq!(*accepted.lock().unwrap(), vec!["a", "b", "c", "d"]);
+
+        // 400 以外はバッチ全体が失敗
+        let result = ingest_isolating(vec![logged("a"), logged("b")], |_| async {
+            Err(SiemError::ApiError { status: 403, message: "denied".to_string() })
+        }).await;
+        assert_eq!(result.unwrap_err().failed, 2);
+    }
+
+    #[test]
+    fn test_split_by_size() {
+        let events: Vec<UdmEvent> = (0..10).map(|i| logged(&i.to_string())).collect();
+        let one = serde_json::to_vec(&events[0]).unwrap().len() + 1;
+
+        let batches = split_by_size(events.clone(), one * 4);
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 4, 2]);
+        assert_eq!(split_by_size(events, 1).len(), 10);
+    }
 }
@@ -1 +1,235 @@
 //! SIEM共通モジュール
+//!
+//! HTTP 送信のリトライと、syslog 向けの CEF / LEEF 形式への変換
+
+use crate::{SiemConfig, SiemError, SiemEvent, SiemResult, SiemSeverity};
+use fukurow_core::retry::retry_retryable;
+
+/// Send a request with the configured timeout and retry policy
+///
+/// 429 / 5xx / タイムアウト / 接続エラーのみリトライし、非成功ステータスは
+/// `SiemError::ApiError` に変換する
+pub(crate) async fn execute_with_retry(config: &SiemConfig, request: reqwest::RequestBuilder) -> SiemResult<reqwest::Response> {
+    let timeout = std::time::Duration::from_secs(config.timeout_seconds);
+    // ストリーミングボディ等で複製できない場合は 1 回のみ送信する
+    let policy = if request.try_clone().is_some() {
+        config.retry.clone()
+    } else {
+        fukurow_core::retry::RetryPolicy::no_retry()
+    };
+
+    retry_retryable(&policy, |_attempt| {
+        let attempt_request = request.try_clone();
+        async move {
+            let attempt_request = attempt_request
+                .ok_or_else(|| SiemError::UnknownError("request could not be cloned".to_string()))?;
+            let response = attempt_request.timeout(timeout).send().await?;
+
+            if !response.status().is_success() {
+                let status = response.status().as_u16();
+                let text = response.text().await.unwrap_or_default();
+                return Err(SiemError::ApiError { status, message: text });
+            }
+
+            Ok(response)
+        }
+    }, tokio::time::sleep).await
+}
+
+/// Product identification in CEF / LEEF headers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub vendor: String,
+    pub product: String,
+    pub version: String,
+}
+
+impl Default for DeviceInfo {
+    fn default() -> Self {
+        Self::new("Fukurow", "Fukurow", env!("CARGO_PKG_VERSION"))
+    }
+}
+
+impl DeviceInfo {
+    pub fn new(vendor: &str, product: &str, version: &str) -> Self {
+        Self { vendor: vendor.to_string(), product: product.to_string(), version: version.to_string() }
+    }
+}
+
+/// Metadata keys copied into CEF / LEEF fields: (metadata key, CEF key, LEEF key)
+///
+/// アラートの詳細 (`host_ip` など) と `CyberEvent` のフィールド名の両方を拾う
+const FIELD_MAPPINGS: &[(&str, &str, &str)] = &[
+    ("source_ip", "src", "src"),
+    ("dest_ip", "dst", "dst"),
+    ("host_ip", "dvc", "identSrc"),
+    ("port", "dpt", "dstPort"),
+    ("protocol", "proto", "proto"),
+    ("user", "suser", "usrName"),
+    ("hostname", "dhost", "identHostName"),
+];
+
+/// CEF severity (0-10)
+pub fn cef_severity(severity: &SiemSeverity) -> u8 {
+    match severity {
+        SiemSeverity::Low => 3,
+        SiemSeverity::Medium => 5,
+        SiemSeverity::High => 8,
+        SiemSeverity::Critical => 10,
+    }
+}
+
+/// Render an event as an ArcSight Common Event Format (CEF:0) record
+///
+/// ヘッダーは `|` と `\` を、拡張フィールドの値は `=` と `\` と改行をエスケープする。
+/// ルール名は `cs1`、ATT&CK テクニックは `cs2` に載せる
+pub fn format_cef(event: &SiemEvent, device: &DeviceInfo) -> String {
+    let mut extension = vec![
+        ("rt", event.timestamp.timestamp_millis().to_string()),
+        ("externalId", event.id.clone()),
+        ("cat", event.event_type.clone()),
+        ("msg", event.message.clone()),
+        ("deviceProcessName", event.source.clone()),
+    ];
+    for (key, cef_key, _) in FIELD_MAPPINGS {
+        if let Some(value) = metadata_value(event, key) {
+            extension.push((cef_key, value));
+        }
+    }
+    if let Some(rule) = metadata_value(event, "rule") {
+        extension.push(("cs1Label", "rule".to_string()));
+        extension.push(("cs1", rule));
+    }
+    if let Some(techniques) = metadata_value(event, fukurow_core::model::ATTACK_DETAILS_KEY) {
+        extension.push(("cs2Label", "mitreAttack".to_string()));
+        extension.push(("cs2", techniques));
+    }
+
+    let extension: Vec<String> = extension.into_iter()
+        .map(|(key, value)| format!("{}={}", key, escape_cef_value(&value)))
+        .collect();
+    format!(
+        "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+        escape_cef_header(&device.vendor),
+        escape_cef_header(&device.product),
+        escape_cef_header(&device.version),
+        escape_cef_header(&signature_id(event)),
+        escape_cef_header(&event.message),
+        cef_severity(&event.severity),
+        extension.join(" "),
+    )
+}
+
+/// Render an event as an IBM QRadar Log Event Extended Format (LEEF:1.0) record
+///
+/// 属性はタブ区切り。値に含まれるタブと改行は空白に置き換え、ヘッダーの `|` はエスケープする
+pub fn format_leef(event: &SiemEvent, device: &DeviceInfo) -> String {
+    let mut attributes = vec![
+        ("devTime", event.timestamp.format("%b %d %Y %H:%M:%S%.3f UTC").to_string()),
+        ("devTimeFormat", "MMM dd yyyy HH:mm:ss.SSS z".to_string()),
+        ("sev", cef_severity(&event.severity).to_string()),
+        ("cat", event.event_type.clone()),
+        ("msg", event.message.clone()),
+        ("externalId", event.id.clone()),
+        ("source", event.source.clone()),
+    ];
+    for (key, _, leef_key) in FIELD_MAPPINGS {
+        if let Some(value) = metadata_value(event, key) {
+            attributes.push((leef_key, value));
+        }
+    }
+    if let Some(rule) = metadata_value(event, "rule") {
+        attributes.push(("rule", rule));
+    }
+    if let Some(techniques) = metadata_value(event, fukurow_core::model::ATTACK_DETAILS_KEY) {
+        attributes.push(("mitreAttack", techniques));
+    }
+
+    let attributes: Vec<String> = attributes.into_iter()
+        .map(|(key, value)| format!("{}={}", key, escape_leef_value(&value)))
+        .collect();
+    format!(
+        "LEEF:1.0|{}|{}|{}|{}|{}",
+        escape_leef_header(&device.vendor),
+        escape_leef_header(&device.product),
+        escape_leef_header(&device.version),
+        escape_leef_header(&signature_id(event)),
+        attributes.join("\t"),
+    )
+}
+
+/// Rule name when known, otherwise the event type
+fn signature_id(event: &SiemEvent) -> String {
+    metadata_value(event, "rule").unwrap_or_else(|| event.event_type.clone())
+}
+
+/// Metadata field as text (arrays joined with `,`; null and objects skipped)
+fn metadata_value(event: &SiemEvent, key: &str) -> Option<String> {
+    match event.metadata.get(key)? {
+        serde_json::Value::String(value) if !value.is_empty() => Some(value.clone()),
+        serde_json::Value::Number(value) => Some(value.to_string()),
+        serde_json::Value::Bool(value) => Some(value.to_string()),
+        serde_json::Value::Array(values) if !values.is_empty() => Some(values.iter()
+            .map(|value| value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string()))
+            .collect::<Vec<_>>()
+            .join(",")),
+        _ => None,
+    }
+}
+
+fn escape_cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn escape_cef_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('=', "\\=").replace('\r', "\\r").replace('\n', "\\n")
+}
+
+fn escape_leef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n', '\t'], " ")
+}
+
+fn escape_leef_value(value: &str) -> String {
+    value.replace(['\r', '\n', '\t'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn event() -> SiemEvent {
+        let mut event = SiemEvent::new("alert", "fukurow", "C2 beacon | to 203.0.113.9\nblocked=yes")
+            .with_severity(SiemSeverity::High)
+            .with_metadata(serde_json::json!({ "rule": "c2_beacon", "source_ip": "10.0.0.5", "port": 4444, "user": "a\\b" }))
+            .with_attack_techniques(&["T1071".to_string(), "T1571".to_string()]);
+        event.id = "evt-1".to_string();
+        event.timestamp = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        event
+    }
+
+    #[test]
+    fn test_cef_escapes_header_and_extension() {
+        let cef = format_cef(&event(), &DeviceInfo::new("Acme|Sec", "Fukurow", "1.0"));
+        assert!(cef.starts_with("CEF:0|Acme\\|Sec|Fukurow|1.0|c2_beacon|C2 beacon \\| to 203.0.113.9 blocked=yes|8|"));
+        assert!(cef.contains("rt=1700000000000 externalId=evt-1 cat=alert"));
+        assert!(cef.contains("msg=C2 beacon | to 203.0.113.9\\nblocked\\=yes"));
+        assert!(cef.contains("src=10.0.0.5 dpt=4444 suser=a\\\\b"));
+        assert!(cef.contains("cs1Label=rule cs1=c2_beacon cs2Label=mitreAttack cs2=T1071,T1571"));
+    }
+
+    #[test]
+    fn test_leef_uses_tab_delimited_attributes() {
+        let leef = format_leef(&event(), &DeviceInfo::default());
+        let (header, attributes) = leef.split_at(leef.find("devTime=").unwrap());
+        assert_eq!(header, format!("LEEF:1.0|Fukurow|Fukurow|{}|c2_beacon|", env!("CARGO_PKG_VERSION")));
+
+        let attributes: Vec<&str> = attributes.split('\t').collect();
+        assert_eq!(attributes[0], "devTime=Nov 14 2023 22:13:20.000 UTC");
+        assert!(attributes.contains(&"sev=8"));
+        assert!(attributes.contains(&"msg=C2 beacon | to 203.0.113.9 blocked=yes"));
+        assert!(attributes.contains(&"src=10.0.0.5"));
+        assert!(attributes.contains(&"dstPort=4444"));
+        assert!(attributes.contains(&"mitreAttack=T1071,T1571"));
+    }
+}
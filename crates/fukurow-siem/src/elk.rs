@@ -1,16 +1,60 @@
 //! ELK Stack (Elasticsearch) SIEM統合
 
-use crate::{SiemClient, SiemConfig, SiemEvent, SiemResult, SiemError};
+use crate::{SiemClient, SiemConfig, SiemError, SiemEvent, SiemResult};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// Elasticsearch `_bulk` settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElkBulkConfig {
+    /// Documents per `_bulk` request
+    pub batch_size: usize,
+    /// Maximum time a document waits in [`ElkBulkIndexer`] before being sent
+    pub flush_interval: Duration,
+    /// Write each event to `{index}-YYYY.MM.DD` (by event timestamp) instead of `{index}`
+    pub daily_rollover: bool,
+}
+
+impl Default for ElkBulkConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            flush_interval: Duration::from_secs(5),
+            daily_rollover: true,
+        }
+    }
+}
+
+impl ElkBulkConfig {
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    pub fn with_daily_rollover(mut self, daily_rollover: bool) -> Self {
+        self.daily_rollover = daily_rollover;
+        self
+    }
+}
 
 /// ELK Stack (Elasticsearch) client
 pub struct ElkClient {
     config: SiemConfig,
     client: Client,
     index_name: String,
+    bulk: ElkBulkConfig,
 }
 
 impl ElkClient {
@@ -20,7 +64,131 @@ impl ElkClient {
             client: Client::new(),
             config,
             index_name: index_name.to_string(),
+            bulk: ElkBulkConfig::default(),
+        }
+    }
+
+    pub fn with_bulk_config(mut self, bulk: ElkBulkConfig) -> Self {
+        self.bulk = bulk;
+        self
+    }
+
+    pub fn bulk_config(&self) -> &ElkBulkConfig {
+        &self.bulk
+    }
+
+    /// Index that an event with `timestamp` is written to
+    pub fn index_for(&self, timestamp: &DateTime<Utc>) -> String {
+        if self.bulk.daily_rollover {
+            format!("{}-{}", self.index_name, timestamp.format("%Y.%m.%d"))
+        } else {
+            self.index_name.clone()
+        }
+    }
+
+    /// Index pattern covering every index this client writes to
+    fn search_target(&self) -> String {
+        if self.bulk.daily_rollover {
+            format!("{}-*", self.index_name)
+        } else {
+            self.index_name.clone()
+        }
+    }
+
+    /// NDJSON body of a `_bulk` request
+    fn bulk_body(&self, events: &[SiemEvent]) -> SiemResult<String> {
+        let mut bulk_body = String::new();
+
+        for event in events {
+            // Action line
+            let action = serde_json::json!({
+                "index": {
+                    "_index": self.index_for(&event.timestamp),
+                    "_id": event.id
+                }
+            });
+            bulk_body.push_str(&serde_json::to_string(&action)?);
+            bulk_body.push('\n');
+
+            // Document line
+            let doc = serde_json::json!({
+                "@timestamp": event.timestamp.to_rfc3339(),
+                "event_type": event.event_type,
+                "source": event.source,
+                "severity": format!("{:?}", event.severity),
+                "message": event.message,
+                "metadata": event.metadata,
+                "raw_data": event.raw_data
+            });
+            bulk_body.push_str(&serde_json::to_string(&doc)?);
+            bulk_body.push('\n');
+        }
+
+        Ok(bulk_body)
+    }
+
+    /// Send one `_bulk` request, resending items rejected with 429 after a backoff
+    ///
+    /// リクエスト全体の 429 は `execute_with_retry` がリトライする。
+    /// 部分的に 429 を返された項目だけを、リトライポリシーのバックオフで再送する
+    async fn send_bulk_batch(&self, mut pending: Vec<SiemEvent>) -> Result<(), BulkFailure> {
+        let url = format!("{}/_bulk", self.config.endpoint);
+        let mut backoff = self.config.retry.backoff();
+        let mut failure = BulkFailure::default();
+
+        while !pending.is_empty() {
+            let body = match self.bulk_body(&pending) {
+                Ok(body) => body,
+                Err(e) => {
+                    failure.merge(BulkFailure::all(pending.len(), e.to_string()));
+                    break;
+                }
+            };
+            let mut request = self.client
+                .post(&url)
+                .header("Content-Type", "application/x-ndjson")
+                .body(body);
+
+            let headers = self.get_auth_headers();
+            for (key, value) in headers {
+                if key.to_lowercase() != "content-type" { // Don't override content-type
+                    request = request.header(&key, &value);
+                }
+            }
+
+            let bulk = match crate::common::execute_with_retry(&self.config, request).await {
+                Ok(response) => response.json::<BulkResponse>().await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            let bulk = match bulk {
+                Ok(bulk) => bulk,
+                Err(message) => {
+                    failure.merge(BulkFailure::all(pending.len(), message));
+                    break;
+                }
+            };
+
+            let outcome = bulk.classify();
+            failure.merge(outcome.failed);
+            let throttled: Vec<SiemEvent> = outcome.throttled.into_iter()
+                .filter_map(|index| pending.get(index).cloned())
+                .collect();
+            if throttled.is_empty() {
+                break;
+            }
+            match backoff.next() {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    pending = throttled;
+                }
+                None => {
+                    failure.merge(BulkFailure::all(throttled.len(), "429 Too Many Requests".to_string()));
+                    break;
+                }
+            }
         }
+
+        if failure.failed == 0 { Ok(()) } else { Err(failure) }
     }
 
     /// Get authentication headers
@@ -44,7 +212,7 @@ impl ElkClient {
 #[async_trait]
 impl SiemClient for ElkClient {
     async fn send_event(&self, event: SiemEvent) -> SiemResult<()> {
-        let url = format!("{}/{}/_doc", self.config.endpoint, self.index_name);
+        let url = format!("{}/{}/_doc", self.config.endpoint, self.index_for(&event.timestamp));
 
         // Convert SiemEvent to Elasticsearch document
         let doc = ElasticsearchDocument {
@@ -65,79 +233,27 @@ impl SiemClient for ElkClient {
             request = request.header(&key, &value);
         }
 
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
-        }
+        crate::common::execute_with_retry(&self.config, request).await?;
 
         Ok(())
     }
 
     async fn send_events(&self, events: Vec<SiemEvent>) -> SiemResult<()> {
-        let url = format!("{}/{}/_bulk", self.config.endpoint, self.index_name);
-
-        // Prepare bulk request body
-        let mut bulk_body = String::new();
-
-        for event in events {
-            // Action line
-            let action = serde_json::json!({
-                "index": {
-                    "_index": self.index_name,
-                    "_id": event.id
-                }
-            });
-            bulk_body.push_str(&serde_json::to_string(&action)?);
-            bulk_body.push('\n');
-
-            // Document line
-            let doc = serde_json::json!({
-                "@timestamp": event.timestamp.to_rfc3339(),
-                "event_type": event.event_type,
-                "source": event.source,
-                "severity": format!("{:?}", event.severity),
-                "message": event.message,
-                "metadata": event.metadata,
-                "raw_data": event.raw_data
-            });
-            bulk_body.push_str(&serde_json::to_string(&doc)?);
-            bulk_body.push('\n');
-        }
-
-        let mut request = self.client
-            .post(&url)
-            .header("Content-Type", "application/x-ndjson")
-            .body(bulk_body);
-
-        let headers = self.get_auth_headers();
-        for (key, value) in headers {
-            if key.to_lowercase() != "content-type" { // Don't override content-type
-                request = request.header(&key, &value);
+        let mut failure = BulkFailure::default();
+        for batch in events.chunks(self.bulk.batch_size.max(1)) {
+            if let Err(batch_failure) = self.send_bulk_batch(batch.to_vec()).await {
+                failure.merge(batch_failure);
             }
         }
 
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
+        match failure.message {
+            Some(message) => Err(SiemError::BulkError { failed: failure.failed, total: events.len(), message }),
+            None => Ok(()),
         }
-
-        Ok(())
     }
 
     async fn query_events(&self, query: &str, limit: Option<usize>) -> SiemResult<Vec<SiemEvent>> {
-        let url = format!("{}/{}/_search", self.config.endpoint, self.index_name);
+        let url = format!("{}/{}/_search", self.config.endpoint, self.search_target());
 
         let search_request = ElasticsearchSearchRequest {
             query: serde_json::from_str(query).unwrap_or_else(|_| {
@@ -163,16 +279,7 @@ impl SiemClient for ElkClient {
             request = request.header(&key, &value);
         }
 
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
-        }
+        let response = crate::common::execute_with_retry(&self.config, request).await?;
 
         let search_response: ElasticsearchSearchResponse = response.json().await?;
         let events = search_response.hits.hits
@@ -264,6 +371,192 @@ struct ElasticsearchHit {
     _source: ElasticsearchDocument,
 }
 
+/// Elasticsearch `_bulk` response
+#[derive(Debug, Deserialize)]
+struct BulkResponse {
+    #[serde(default)]
+    errors: bool,
+    #[serde(default)]
+    items: Vec<HashMap<String, BulkItem>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BulkItem {
+    status: u16,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+/// Per-item result of a `_bulk` response
+#[derive(Debug, Default)]
+struct BulkOutcome {
+    /// Positions of items rejected with 429
+    throttled: Vec<usize>,
+    failed: BulkFailure,
+}
+
+impl BulkResponse {
+    fn classify(&self) -> BulkOutcome {
+        let mut outcome = BulkOutcome::default();
+        if !self.errors {
+            return outcome;
+        }
+        for (index, item) in self.items.iter().enumerate() {
+            // 各要素は {"index": {...}} のように操作名をキーに持つ
+            let result = match item.values().next() {
+                Some(result) => result,
+                None => continue,
+            };
+            match result.status {
+                200..=299 => {}
+                429 => outcome.throttled.push(index),
+                status => {
+                    let reason = result.error.as_ref().map(|e| e.to_string()).unwrap_or_default();
+                    outcome.failed.merge(BulkFailure::all(1, format!("{}: {}", status, reason)));
+                }
+            }
+        }
+        outcome
+    }
+}
+
+/// Items that could not be indexed, with the first error message
+#[derive(Debug, Default)]
+struct BulkFailure {
+    failed: usize,
+    message: Option<String>,
+}
+
+impl BulkFailure {
+    fn all(count: usize, message: String) -> Self {
+        Self { failed: count, message: Some(message) }
+    }
+
+    fn merge(&mut self, other: BulkFailure) {
+        self.failed += other.failed;
+        if self.message.is_none() {
+            self.message = other.message;
+        }
+    }
+}
+
+/// Bulk indexer statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElkBulkStats {
+    pub queued: u64,
+    pub indexed: u64,
+    pub failed: u64,
+    /// `_bulk` batches sent (excluding 429 resends)
+    pub batches: u64,
+}
+
+#[derive(Debug, Default)]
+struct BulkCounters {
+    queued: AtomicU64,
+    indexed: AtomicU64,
+    failed: AtomicU64,
+    batches: AtomicU64,
+}
+
+enum BulkCommand {
+    Event(SiemEvent),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Buffered writer that sends events through `_bulk`
+///
+/// `batch_size` 件たまるか `flush_interval` が経過した時点でまとめて送信する。
+/// ハンドルをすべて破棄するとバッファを送信してからタスクが終了する
+#[derive(Debug, Clone)]
+pub struct ElkBulkIndexer {
+    sender: mpsc::Sender<BulkCommand>,
+    counters: Arc<BulkCounters>,
+}
+
+impl ElkBulkIndexer {
+    /// Spawn the writer task (requires a Tokio runtime)
+    pub fn spawn(client: Arc<ElkClient>) -> Self {
+        // 送信中もバッチ数件分は受け付けられるようにする
+        let (sender, receiver) = mpsc::channel(client.bulk.batch_size.max(1) * 4);
+        let counters = Arc::new(BulkCounters::default());
+        tokio::spawn(Self::run_writer(client, receiver, Arc::clone(&counters)));
+        Self { sender, counters }
+    }
+
+    /// Queue an event (waits while the queue is full)
+    pub async fn push(&self, event: SiemEvent) -> SiemResult<()> {
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        self.sender.send(BulkCommand::Event(event)).await
+            .map_err(|_| SiemError::UnknownError("Bulk indexer is not running".to_string()))
+    }
+
+    /// Send everything queued before this call
+    pub async fn flush(&self) -> SiemResult<()> {
+        let (done, receiver) = oneshot::channel();
+        self.sender.send(BulkCommand::Flush(done)).await
+            .map_err(|_| SiemError::UnknownError("Bulk indexer is not running".to_string()))?;
+        receiver.await
+            .map_err(|_| SiemError::UnknownError("Bulk indexer stopped during flush".to_string()))
+    }
+
+    pub fn stats(&self) -> ElkBulkStats {
+        ElkBulkStats {
+            queued: self.counters.queued.load(Ordering::Relaxed),
+            indexed: self.counters.indexed.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            batches: self.counters.batches.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn run_writer(client: Arc<ElkClient>, mut receiver: mpsc::Receiver<BulkCommand>, counters: Arc<BulkCounters>) {
+        let batch_size = client.bulk.batch_size.max(1);
+        let mut ticker = tokio::time::interval(client.bulk.flush_interval.max(Duration::from_millis(1)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut buffer = Vec::with_capacity(batch_size);
+
+        loop {
+            tokio::select! {
+                command = receiver.recv() => match command {
+                    Some(BulkCommand::Event(event)) => {
+                        buffer.push(event);
+                        if buffer.len() >= batch_size {
+                            Self::send_buffer(&client, &mut buffer, &counters).await;
+                        }
+                    }
+                    Some(BulkCommand::Flush(done)) => {
+                        Self::send_buffer(&client, &mut buffer, &counters).await;
+                        let _ = done.send(());
+                    }
+                    None => {
+                        Self::send_buffer(&client, &mut buffer, &counters).await;
+                        break;
+                    }
+                },
+                _ = ticker.tick() => Self::send_buffer(&client, &mut buffer, &counters).await,
+            }
+        }
+    }
+
+    async fn send_buffer(client: &ElkClient, buffer: &mut Vec<SiemEvent>, counters: &BulkCounters) {
+        if buffer.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(buffer);
+        let total = batch.len() as u64;
+        counters.batches.fetch_add(1, Ordering::Relaxed);
+
+        let failed = match client.send_bulk_batch(batch).await {
+            Ok(()) => 0,
+            Err(failure) => {
+                tracing::warn!(failed = failure.failed, total, error = failure.message.as_deref().unwrap_or_default(), "Failed to index events in Elasticsearch");
+                failure.failed as u64
+            }
+        };
+        counters.failed.fetch_add(failed, Ordering::Relaxed);
+        counters.indexed.fetch_add(total - failed, Ordering::Relaxed);
+    }
+}
+
 /// Elasticsearch health response
 #[derive(Deserialize)]
 struct ElasticsearchHealthResponse {
@@ -291,4 +584,59 @@ mod tests {
         let client = ElkClient::new(config, "security-events");
         assert_eq!(client.index_name, "security-events");
     }
+
+    fn event(id: &str, timestamp: &str) -> SiemEvent {
+        let mut event = SiemEvent::new("detection", "fukurow", "test");
+        event.id = id.to_string();
+        event.timestamp = DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc);
+        event
+    }
+
+    #[test]
+    fn test_daily_index_rollover() {
+        let config = SiemConfig::new("http://localhost:9200");
+        let client = ElkClient::new(config.clone(), "fukurow-events");
+        let timestamp = event("e1", "2024-03-09T23:59:59Z").timestamp;
+        assert_eq!(client.index_for(&timestamp), "fukurow-events-2024.03.09");
+        assert_eq!(client.search_target(), "fukurow-events-*");
+
+        let client = ElkClient::new(config, "fukurow-events")
+            .with_bulk_config(ElkBulkConfig::default().with_daily_rollover(false));
+        assert_eq!(client.index_for(&timestamp), "fukurow-events");
+    }
+
+    #[test]
+    fn test_bulk_body_targets_daily_indices() {
+        let client = ElkClient::new(SiemConfig::new("http://localhost:9200"), "fukurow-events");
+        let body = client.bulk_body(&[event("e1", "2024-03-09T10:00:00Z"), event("e2", "2024-03-10T10:00:00Z")]).unwrap();
+        let lines: Vec<serde_json::Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["index"]["_index"], "fukurow-events-2024.03.09");
+        assert_eq!(lines[0]["index"]["_id"], "e1");
+        assert_eq!(lines[2]["index"]["_index"], "fukurow-events-2024.03.10");
+        assert!(body.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_bulk_response_classification() {
+        let response: BulkResponse = serde_json::from_value(serde_json::json!({
+            "errors": true,
+            "items": [
+                { "index": { "status": 201 } },
+                { "index": { "status": 429, "error": { "type": "es_rejected_execution_exception" } } },
+                { "index": { "status": 400, "error": { "type": "mapper_parsing_exception" } } },
+                { "index": { "status": 429 } }
+            ]
+        })).unwrap();
+
+        let outcome = response.classify();
+        // 429 は再送対象、それ以外のエラーは失敗として数える
+        assert_eq!(outcome.throttled, vec![1, 3]);
+        assert_eq!(outcome.failed.failed, 1);
+        assert!(outcome.failed.message.unwrap().contains("mapper_parsing_exception"));
+
+        let ok: BulkResponse = serde_json::from_value(serde_json::json!({ "errors": false, "items": [] })).unwrap();
+        assert!(ok.classify().throttled.is_empty());
+    }
 }
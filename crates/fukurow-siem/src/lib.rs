@@ -4,21 +4,28 @@
 //! システムとの統合を提供します:
 //! - Splunk (REST API, HEC)
 //! - ELK Stack (Elasticsearch API)
-//! - Chronicle (Google Cloud Security)
+//! - Chronicle (Google Cloud Security, UDM イベントへの変換は [`udm`])
+//! - syslog (UDP / TCP / TLS) で CEF / LEEF を受け付ける SIEM (ArcSight, QRadar など)
 
 pub mod splunk;
 pub mod elk;
 pub mod chronicle;
+pub mod udm;
 pub mod common;
+pub mod syslog;
 
 pub use splunk::SplunkClient;
 pub use elk::ElkClient;
 pub use chronicle::ChronicleClient;
+pub use udm::UdmEvent;
+pub use syslog::{SyslogClient, SyslogConfig, SyslogFormat, SyslogTransport};
+pub use common::{format_cef, format_leef, DeviceInfo};
 
 // Re-export common types
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
+pub use fukurow_core::retry::RetryPolicy;
 
 /// Common SIEM event format
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +91,20 @@ impl SiemEvent {
         self.raw_data = Some(raw_data);
         self
     }
+
+    /// Record MITRE ATT&CK technique IDs under `metadata.mitre_attack`
+    ///
+    /// アラートの `SecurityAction::attack_techniques` をそのまま渡す。空なら何もしない
+    pub fn with_attack_techniques(mut self, techniques: &[String]) -> Self {
+        if techniques.is_empty() {
+            return self;
+        }
+        if !self.metadata.is_object() {
+            self.metadata = serde_json::json!({ "original_metadata": self.metadata });
+        }
+        self.metadata[fukurow_core::model::ATTACK_DETAILS_KEY] = serde_json::json!(techniques);
+        self
+    }
 }
 
 /// SIEM client trait
@@ -110,6 +131,7 @@ pub struct SiemConfig {
     pub username: Option<String>,
     pub password: Option<String>,
     pub timeout_seconds: u64,
+    pub retry: RetryPolicy,
 }
 
 impl SiemConfig {
@@ -120,6 +142,7 @@ impl SiemConfig {
             username: None,
             password: None,
             timeout_seconds: 30,
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -138,6 +161,11 @@ impl SiemConfig {
         self.timeout_seconds = seconds;
         self
     }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 }
 
 /// SIEM operation result type
@@ -167,10 +195,29 @@ pub enum SiemError {
     #[error("Timeout error")]
     TimeoutError,
 
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Bulk indexing failed for {failed} of {total} events: {message}")]
+    BulkError { failed: usize, total: usize, message: String },
+
     #[error("Unknown error: {0}")]
     UnknownError(String),
 }
 
+impl fukurow_core::retry::Retryable for SiemError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            SiemError::HttpError(e) => {
+                e.is_timeout() || e.is_connect() || e.status().map_or(false, |s| s.as_u16() == 429 || s.is_server_error())
+            }
+            SiemError::ApiError { status, .. } => *status == 429 || *status >= 500,
+            SiemError::TimeoutError | SiemError::IoError(_) => true,
+            _ => false,
+        }
+    }
+}
+
 /// SIEM integration manager
 pub struct SiemManager {
     clients: Vec<Box<dyn SiemClient>>,
@@ -260,6 +307,15 @@ mod client_tests {
     mod siem_config_tests {
         use super::*;
 
+        #[test]
+        fn test_siem_event_attack_techniques() {
+            let event = SiemEvent::new("alert", "fukurow", "Brute force")
+                .with_metadata(serde_json::json!({ "rule": "ssh_brute_force" }))
+                .with_attack_techniques(&["T1110".to_string()]);
+            assert_eq!(event.metadata["mitre_attack"], serde_json::json!(["T1110"]));
+            assert_eq!(event.metadata["rule"], "ssh_brute_force");
+        }
+
         #[test]
         fn test_siem_config_creation() {
             let config = SiemConfig::new("https://api.example.com")
@@ -273,6 +329,48 @@ mod client_tests {
             assert_eq!(config.password, Some("pass".to_string()));
             assert_eq!(config.timeout_seconds, 120);
         }
+
+        #[test]
+        fn test_siem_config_retry_policy() {
+            let config = SiemConfig::new("https://api.example.com");
+            assert_eq!(config.retry, RetryPolicy::default());
+
+            let config = config.with_retry(RetryPolicy::new(5).with_jitter(false));
+            assert_eq!(config.retry.max_attempts, 5);
+            assert!(!config.retry.jitter);
+        }
+
+        #[test]
+        fn test_siem_error_retry_classification() {
+            use fukurow_core::retry::Retryable;
+
+            assert!(SiemError::ApiError { status: 503, message: String::new() }.is_retryable());
+            assert!(SiemError::ApiError { status: 429, message: String::new() }.is_retryable());
+            assert!(SiemError::TimeoutError.is_retryable());
+            assert!(!SiemError::ApiError { status: 400, message: String::new() }.is_retryable());
+            assert!(!SiemError::AuthError("denied".to_string()).is_retryable());
+        }
+
+        #[tokio::test]
+        async fn test_retry_stops_on_non_retryable_error() {
+            use std::sync::atomic::{AtomicU32, Ordering};
+
+            let policy = RetryPolicy::new(4).with_initial_backoff_ms(1).with_max_backoff_ms(2);
+            let calls = AtomicU32::new(0);
+            let result: SiemResult<()> = fukurow_core::retry::retry_retryable(&policy, |attempt| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 3 {
+                        Err(SiemError::ApiError { status: 503, message: "busy".to_string() })
+                    } else {
+                        Err(SiemError::AuthError("denied".to_string()))
+                    }
+                }
+            }, tokio::time::sleep).await;
+
+            assert!(matches!(result, Err(SiemError::AuthError(_))));
+            assert_eq!(calls.load(Ordering::SeqCst), 3);
+        }
     }
 
     #[cfg(test)]
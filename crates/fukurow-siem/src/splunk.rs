@@ -1,10 +1,158 @@
 //! Splunk SIEM統合
+//!
+//! HEC (HTTP Event Collector) では複数イベントを改行区切りでまとめて 1 リクエストで送り、
+//! インデクサー確認応答 (indexer acknowledgement) が有効な場合は `/services/collector/ack`
+//! をポーリングして全バッチがインデックスされたことを確認する。
+//! index / sourcetype / source はイベント種別ごとに振り分けられる
 
 use crate::{SiemClient, SiemConfig, SiemEvent, SiemResult, SiemError};
 use async_trait::async_trait;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+/// Default maximum events per HEC request
+pub const DEFAULT_HEC_BATCH_EVENTS: usize = 500;
+/// Default maximum uncompressed size of one HEC request body
+pub const DEFAULT_HEC_BATCH_BYTES: usize = 1024 * 1024;
+
+/// Destination of an event in Splunk (unset fields fall back to the default route)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HecRoute {
+    pub index: Option<String>,
+    pub sourcetype: Option<String>,
+    /// Overrides the event's own `source`
+    pub source: Option<String>,
+}
+
+impl HecRoute {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    pub fn with_sourcetype(mut self, sourcetype: &str) -> Self {
+        self.sourcetype = Some(sourcetype.to_string());
+        self
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Fields of `self`, with unset ones taken from `fallback`
+    fn or(&self, fallback: &HecRoute) -> HecRoute {
+        HecRoute {
+            index: self.index.clone().or_else(|| fallback.index.clone()),
+            sourcetype: self.sourcetype.clone().or_else(|| fallback.sourcetype.clone()),
+            source: self.source.clone().or_else(|| fallback.source.clone()),
+        }
+    }
+}
+
+/// Indexer acknowledgement polling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HecAckConfig {
+    /// Wait between polls of the ack endpoint
+    pub poll_interval: Duration,
+    /// Give up waiting after this long
+    pub timeout: Duration,
+}
+
+impl Default for HecAckConfig {
+    fn default() -> Self {
+        Self { poll_interval: Duration::from_millis(500), timeout: Duration::from_secs(60) }
+    }
+}
+
+/// HEC submission settings
+#[derive(Debug, Clone)]
+pub struct HecConfig {
+    pub max_batch_events: usize,
+    pub max_batch_bytes: usize,
+    /// Send bodies with `Content-Encoding: gzip`
+    pub gzip: bool,
+    /// Poll for indexer acknowledgement (requires ack to be enabled on the token)
+    pub ack: Option<HecAckConfig>,
+    /// Channel identifier sent as `X-Splunk-Request-Channel` (required for acks)
+    pub channel: String,
+    pub host: String,
+    /// Route used for event types without their own route
+    pub default_route: HecRoute,
+    /// Routes by `SiemEvent::event_type`
+    pub routes: HashMap<String, HecRoute>,
+}
+
+impl Default for HecConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_events: DEFAULT_HEC_BATCH_EVENTS,
+            max_batch_bytes: DEFAULT_HEC_BATCH_BYTES,
+            gzip: false,
+            ack: None,
+            channel: uuid::Uuid::new_v4().to_string(),
+            host: "fukurow".to_string(),
+            default_route: HecRoute::new().with_index("main").with_sourcetype("_json"),
+            routes: HashMap::new(),
+        }
+    }
+}
+
+impl HecConfig {
+    pub fn with_batch_limits(mut self, max_events: usize, max_bytes: usize) -> Self {
+        self.max_batch_events = max_events.max(1);
+        self.max_batch_bytes = max_bytes.max(1);
+        self
+    }
+
+    pub fn with_gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    pub fn with_ack(mut self, ack: HecAckConfig) -> Self {
+        self.ack = Some(ack);
+        self
+    }
+
+    pub fn with_channel(mut self, channel: &str) -> Self {
+        self.channel = channel.to_string();
+        self
+    }
+
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    pub fn with_default_route(mut self, route: HecRoute) -> Self {
+        self.default_route = route;
+        self
+    }
+
+    /// Route events of `event_type` (e.g. `alert`) to their own index/sourcetype/source
+    pub fn with_route(mut self, event_type: &str, route: HecRoute) -> Self {
+        self.routes.insert(event_type.to_string(), route);
+        self
+    }
+
+    /// Effective route of an event type
+    pub fn route_for(&self, event_type: &str) -> HecRoute {
+        match self.routes.get(event_type) {
+            Some(route) => route.or(&self.default_route),
+            None => self.default_route.clone(),
+        }
+    }
+}
 
 /// Splunk client supporting both REST API and HEC
 pub struct SplunkClient {
@@ -12,6 +160,7 @@ pub struct SplunkClient {
     client: Client,
     use_hec: bool,
     hec_token: Option<String>,
+    hec: HecConfig,
 }
 
 impl SplunkClient {
@@ -22,6 +171,7 @@ impl SplunkClient {
             config,
             use_hec: false,
             hec_token: None,
+            hec: HecConfig::default(),
         }
     }
 
@@ -32,9 +182,20 @@ impl SplunkClient {
             config,
             use_hec: true,
             hec_token: Some(hec_token.to_string()),
+            hec: HecConfig::default(),
         }
     }
 
+    /// Replace the HEC batching, compression, acknowledgement and routing settings
+    pub fn with_hec_config(mut self, hec: HecConfig) -> Self {
+        self.hec = hec;
+        self
+    }
+
+    pub fn hec_config(&self) -> &HecConfig {
+        &self.hec
+    }
+
     /// Get authentication headers for REST API
     fn get_auth_headers(&self) -> Result<HashMap<String, String>, SiemError> {
         let mut headers = HashMap::new();
@@ -64,6 +225,9 @@ impl SiemClient for SplunkClient {
     }
 
     async fn send_events(&self, events: Vec<SiemEvent>) -> SiemResult<()> {
+        if self.use_hec {
+            return self.send_batch_via_hec(events).await;
+        }
         for event in events {
             self.send_event(event).await?;
         }
@@ -114,31 +278,83 @@ impl SiemClient for SplunkClient {
 impl SplunkClient {
     /// Send event via HEC (HTTP Event Collector)
     async fn send_via_hec(&self, event: SiemEvent) -> SiemResult<()> {
-        let url = format!("{}/services/collector/event", self.config.endpoint);
+        self.send_batch_via_hec(vec![event]).await
+    }
 
-        let hec_event = SplunkHecEvent {
-            event: serde_json::to_string(&event).map_err(|e| SiemError::ParseError(e.to_string()))?,
-            sourcetype: Some("_json".to_string()),
-            source: Some(event.source.clone()),
-            index: Some("main".to_string()),
-            host: Some("fukurow".to_string()),
-        };
+    /// Send events via HEC in batches, then wait for indexer acknowledgement if enabled
+    async fn send_batch_via_hec(&self, events: Vec<SiemEvent>) -> SiemResult<()> {
+        let total = events.len();
+        let hec_events = events.iter().map(|event| SplunkHecEvent::from_event(event, &self.hec)).collect();
+
+        let mut ack_ids = Vec::new();
+        for batch in split_batches(hec_events, self.hec.max_batch_events, self.hec.max_batch_bytes)? {
+            let response = self.post_hec_batch(&batch).await?;
+            if self.hec.ack.is_some() {
+                let ack_id = response.ack_id.ok_or_else(|| SiemError::ConfigError(
+                    "HEC response has no ackId; enable indexer acknowledgement on the token".to_string(),
+                ))?;
+                ack_ids.push((ack_id, batch.len()));
+            }
+        }
 
-        let response = self.client
-            .post(&url)
-            .header("Authorization", format!("Splunk {}", self.hec_token.as_ref().unwrap()))
-            .json(&hec_event)
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
+        match &self.hec.ack {
+            Some(ack) if !ack_ids.is_empty() => self.wait_for_acks(ack_ids, ack, total).await,
+            _ => Ok(()),
+        }
+    }
+
+    fn hec_request(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(url)
+            .header("Authorization", format!("Splunk {}", self.hec_token.as_deref().unwrap_or_default()))
+            .header("X-Splunk-Request-Channel", &self.hec.channel)
+    }
 
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
+    async fn post_hec_batch(&self, batch: &[SplunkHecEvent]) -> SiemResult<HecResponse> {
+        let url = format!("{}/services/collector/event", self.config.endpoint);
+        let body = encode_batch(batch, self.hec.gzip)?;
+
+        let mut request = self.hec_request(&url)
+            .header("Content-Type", "application/json")
+            .body(body);
+        if self.hec.gzip {
+            request = request.header("Content-Encoding", "gzip");
         }
 
-        Ok(())
+        let response = crate::common::execute_with_retry(&self.config, request).await?;
+        let hec_response: HecResponse = response.json().await?;
+        if hec_response.code != 0 {
+            return Err(SiemError::ApiError { status: 400, message: hec_response.text });
+        }
+        Ok(hec_response)
+    }
+
+    /// Poll the ack endpoint until every batch has been indexed or the timeout passes
+    async fn wait_for_acks(&self, mut pending: Vec<(u64, usize)>, ack: &HecAckConfig, total: usize) -> SiemResult<()> {
+        let url = format!("{}/services/collector/ack", self.config.endpoint);
+        let deadline = tokio::time::Instant::now() + ack.timeout;
+
+        loop {
+            let ids: Vec<u64> = pending.iter().map(|(id, _)| *id).collect();
+            let request = self.hec_request(&url)
+                .query(&[("channel", &self.hec.channel)])
+                .json(&HecAckRequest { acks: ids });
+            let response = crate::common::execute_with_retry(&self.config, request).await?;
+            let statuses: HecAckResponse = response.json().await?;
+            pending = unacknowledged(pending, &statuses);
+
+            if pending.is_empty() {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() + ack.poll_interval > deadline {
+                return Err(SiemError::BulkError {
+                    failed: pending.iter().map(|(_, count)| count).sum(),
+                    total,
+                    message: format!("{} HEC batch(es) not acknowledged within {:?}", pending.len(), ack.timeout),
+                });
+            }
+            tokio::time::sleep(ack.poll_interval).await;
+        }
     }
 
     /// Send event via REST API
@@ -156,16 +372,7 @@ impl SplunkClient {
             }
         }
 
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
-        }
+        crate::common::execute_with_retry(&self.config, request).await?;
 
         Ok(())
     }
@@ -192,16 +399,7 @@ impl SplunkClient {
             }
         }
 
-        let response = request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
-        }
+        let response = crate::common::execute_with_retry(&self.config, request).await?;
 
         let search_response: SplunkSearchResponse = response.json().await?;
         let job_sid = search_response.sid;
@@ -219,16 +417,7 @@ impl SplunkClient {
             }
         }
 
-        let results_response = results_request
-            .timeout(std::time::Duration::from_secs(self.config.timeout_seconds))
-            .send()
-            .await?;
-
-        if !results_response.status().is_success() {
-            let status = results_response.status().as_u16();
-            let text = results_response.text().await.unwrap_or_default();
-            return Err(SiemError::ApiError { status, message: text });
-        }
+        let results_response = crate::common::execute_with_retry(&self.config, results_request).await?;
 
         // Parse results (simplified)
         let results: SplunkSearchResults = results_response.json().await?;
@@ -252,13 +441,101 @@ impl SplunkClient {
 }
 
 /// Splunk HEC event format
-#[derive(Serialize)]
+#[derive(Debug, Serialize)]
 struct SplunkHecEvent {
-    event: String,
+    /// Epoch seconds with millisecond precision
+    time: f64,
+    event: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
     sourcetype: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     index: Option<String>,
-    host: Option<String>,
+    host: String,
+}
+
+impl SplunkHecEvent {
+    fn from_event(event: &SiemEvent, hec: &HecConfig) -> Self {
+        let route = hec.route_for(&event.event_type);
+        Self {
+            time: event.timestamp.timestamp_millis() as f64 / 1000.0,
+            event: serde_json::to_value(event).unwrap_or_default(),
+            sourcetype: route.sourcetype,
+            source: route.source.or_else(|| Some(event.source.clone())),
+            index: route.index,
+            host: hec.host.clone(),
+        }
+    }
+}
+
+/// Group serialized events into batches bounded by count and (uncompressed) size
+///
+/// 単体で上限を超えるイベントはそれだけのバッチにし、HEC 側の拒否に任せる
+fn split_batches(events: Vec<SplunkHecEvent>, max_events: usize, max_bytes: usize) -> SiemResult<Vec<Vec<SplunkHecEvent>>> {
+    let mut batches = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = 0;
+    for event in events {
+        let size = serde_json::to_vec(&event)?.len() + 1;
+        if !current.is_empty() && (current.len() >= max_events || current_bytes + size > max_bytes) {
+            batches.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+        current_bytes += size;
+        current.push(event);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    Ok(batches)
+}
+
+/// HEC batch body: newline-separated event objects, optionally gzip-compressed
+fn encode_batch(batch: &[SplunkHecEvent], gzip: bool) -> SiemResult<Vec<u8>> {
+    let mut body = Vec::new();
+    for event in batch {
+        serde_json::to_writer(&mut body, event)?;
+        body.push(b'\n');
+    }
+    if !gzip {
+        return Ok(body);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&body)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| SiemError::UnknownError(format!("gzip compression failed: {}", e)))
+}
+
+/// Batches whose ack is not yet `true`
+fn unacknowledged(pending: Vec<(u64, usize)>, statuses: &HecAckResponse) -> Vec<(u64, usize)> {
+    pending.into_iter()
+        .filter(|(id, _)| !statuses.acks.get(&id.to_string()).copied().unwrap_or(false))
+        .collect()
+}
+
+/// HEC event endpoint response
+#[derive(Debug, Deserialize)]
+struct HecResponse {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    code: i64,
+    #[serde(rename = "ackId")]
+    ack_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct HecAckRequest {
+    acks: Vec<u64>,
+}
+
+/// Ack endpoint response (`{"acks": {"0": true, "1": false}}`)
+#[derive(Debug, Default, Deserialize)]
+struct HecAckResponse {
+    #[serde(default)]
+    acks: HashMap<String, bool>,
 }
 
 /// Splunk search request
@@ -304,4 +581,48 @@ mod tests {
         assert!(client.use_hec);
         assert_eq!(client.hec_token, Some("test-token".to_string()));
     }
+
+    #[test]
+    fn test_routes_fall_back_to_default() {
+        let hec = HecConfig::default()
+            .with_route("alert", HecRoute::new().with_index("security").with_sourcetype("fukurow:alert"))
+            .with_route("audit", HecRoute::new().with_source("fukurow-audit"));
+
+        assert_eq!(hec.route_for("alert"), HecRoute::new().with_index("security").with_sourcetype("fukurow:alert"));
+        assert_eq!(hec.route_for("audit"), HecRoute::new().with_index("main").with_sourcetype("_json").with_source("fukurow-audit"));
+        assert_eq!(hec.route_for("log"), hec.default_route);
+
+        let event = SplunkHecEvent::from_event(&SiemEvent::new("log", "edr", "login"), &hec);
+        assert_eq!(event.source.as_deref(), Some("edr"));
+        assert_eq!(event.index.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn test_batches_are_bounded_and_compressed() {
+        use std::io::Read;
+
+        let hec = HecConfig::default();
+        let events: Vec<SplunkHecEvent> = (0..5)
+            .map(|i| SplunkHecEvent::from_event(&SiemEvent::new("alert", "fukurow", &format!("event {}", i)), &hec))
+            .collect();
+        let one = serde_json::to_vec(&events[0]).unwrap().len() + 1;
+
+        let batches = split_batches(events, 2, usize::MAX).unwrap();
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 2, 1]);
+        let flat: Vec<SplunkHecEvent> = batches.into_iter().flatten().collect();
+        assert_eq!(split_batches(flat, 100, one * 3).unwrap().len(), 2);
+
+        let sample = vec![SplunkHecEvent::from_event(&SiemEvent::new("log", "edr", "login"), &hec)];
+        let plain = encode_batch(&sample, false).unwrap();
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(encode_batch(&sample, true).unwrap().as_slice()).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+        assert!(plain.ends_with(b"\n"));
+    }
+
+    #[test]
+    fn test_unacknowledged_batches() {
+        let statuses: HecAckResponse = serde_json::from_str(r#"{"acks": {"1": true, "2": false}}"#).unwrap();
+        assert_eq!(unacknowledged(vec![(1, 10), (2, 5), (3, 1)], &statuses), vec![(2, 5), (3, 1)]);
+    }
 }
@@ -0,0 +1,351 @@
+//! Syslog 送信 (CEF / LEEF)
+//!
+//! ArcSight や QRadar など syslog でしか受け付けない SIEM 向けに、イベントを CEF または LEEF
+//! ([`crate::common`]) に変換し、RFC 5424 の syslog メッセージとして送る。
+//! TCP / TLS では RFC 6587 の octet counting でフレーミングし、接続は使い回す。
+//! 送信に失敗した接続は破棄し、1 度だけ再接続して送り直す
+
+use crate::common::{format_cef, format_leef, DeviceInfo};
+use crate::{SiemClient, SiemError, SiemEvent, SiemResult, SiemSeverity};
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+
+/// Transport to the syslog receiver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+/// Payload format of the syslog messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogFormat {
+    /// ArcSight Common Event Format
+    Cef,
+    /// QRadar Log Event Extended Format
+    Leef,
+}
+
+impl SyslogFormat {
+    /// MSGID of the syslog header
+    fn msg_id(&self) -> &'static str {
+        match self {
+            SyslogFormat::Cef => "CEF",
+            SyslogFormat::Leef => "LEEF",
+        }
+    }
+}
+
+/// Syslog receiver settings
+#[derive(Debug, Clone)]
+pub struct SyslogConfig {
+    /// `host:port` of the receiver
+    pub address: String,
+    pub transport: SyslogTransport,
+    pub format: SyslogFormat,
+    /// Syslog facility (default 13, log audit)
+    pub facility: u8,
+    /// HOSTNAME of the syslog header (`-` when unset)
+    pub hostname: Option<String>,
+    pub app_name: String,
+    /// Server name verified against the TLS certificate (defaults to the host of `address`)
+    pub tls_domain: Option<String>,
+    pub device: DeviceInfo,
+    pub timeout: Duration,
+}
+
+impl SyslogConfig {
+    pub fn new(address: &str, transport: SyslogTransport, format: SyslogFormat) -> Self {
+        Self {
+            address: address.to_string(),
+            transport,
+            format,
+            facility: 13,
+            hostname: None,
+            app_name: "fukurow".to_string(),
+            tls_domain: None,
+            device: DeviceInfo::default(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn with_facility(mut self, facility: u8) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    pub fn with_hostname(mut self, hostname: &str) -> Self {
+        self.hostname = Some(hostname.to_string());
+        self
+    }
+
+    pub fn with_app_name(mut self, app_name: &str) -> Self {
+        self.app_name = app_name.to_string();
+        self
+    }
+
+    pub fn with_tls_domain(mut self, domain: &str) -> Self {
+        self.tls_domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn with_device(mut self, device: DeviceInfo) -> Self {
+        self.device = device;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn validate(&self) -> SiemResult<()> {
+        if self.facility > 23 {
+            return Err(SiemError::ConfigError(format!("syslog facility must be 0-23: {}", self.facility)));
+        }
+        if self.address.rsplit_once(':').map_or(true, |(host, port)| host.is_empty() || port.parse::<u16>().is_err()) {
+            return Err(SiemError::ConfigError(format!("syslog address must be host:port: {}", self.address)));
+        }
+        Ok(())
+    }
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<tokio_native_tls::TlsStream<TcpStream>>),
+}
+
+/// SIEM client shipping CEF / LEEF records over syslog
+pub struct SyslogClient {
+    config: SyslogConfig,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl SyslogClient {
+    pub fn new(config: SyslogConfig) -> SiemResult<Self> {
+        config.validate()?;
+        Ok(Self { config, connection: Mutex::new(None) })
+    }
+
+    pub fn config(&self) -> &SyslogConfig {
+        &self.config
+    }
+
+    /// CEF / LEEF record of `event`
+    pub fn format_event(&self, event: &SiemEvent) -> String {
+        match self.config.format {
+            SyslogFormat::Cef => format_cef(event, &self.config.device),
+            SyslogFormat::Leef => format_leef(event, &self.config.device),
+        }
+    }
+
+    /// RFC 5424 message carrying the record of `event`
+    pub fn syslog_message(&self, event: &SiemEvent) -> String {
+        let severity = match event.severity {
+            SiemSeverity::Critical => 2,
+            SiemSeverity::High => 3,
+            SiemSeverity::Medium => 4,
+            SiemSeverity::Low => 5,
+        };
+        format!(
+            "<{}>1 {} {} {} - {} - {}",
+            u16::from(self.config.facility) * 8 + severity,
+            event.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            header_field(self.config.hostname.as_deref().unwrap_or("-"), 255),
+            header_field(&self.config.app_name, 48),
+            self.config.format.msg_id(),
+            self.format_event(event),
+        )
+    }
+
+    async fn connect(&self) -> SiemResult<Connection> {
+        let address = self.config.address.as_str();
+        match self.config.transport {
+            SyslogTransport::Udp => {
+                let bind = if address.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" };
+                let socket = UdpSocket::bind(bind).await?;
+                socket.connect(address).await?;
+                Ok(Connection::Udp(socket))
+            }
+            SyslogTransport::Tcp => Ok(Connection::Tcp(TcpStream::connect(address).await?)),
+            SyslogTransport::Tls => {
+                let stream = TcpStream::connect(address).await?;
+                let domain = self.config.tls_domain.clone().unwrap_or_else(|| {
+                    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+                    host.trim_start_matches('[').trim_end_matches(']').to_string()
+                });
+                let connector = tokio_native_tls::native_tls::TlsConnector::new()
+                    .map_err(|e| SiemError::ConfigError(format!("TLS setup failed: {}", e)))?;
+                let stream = tokio_native_tls::TlsConnector::from(connector)
+                    .connect(&domain, stream)
+                    .await
+                    .map_err(|e| SiemError::AuthError(format!("TLS handshake with {} failed: {}", domain, e)))?;
+                Ok(Connection::Tls(Box::new(stream)))
+            }
+        }
+    }
+
+    /// Send messages over the shared connection, reconnecting once on failure
+    ///
+    /// タイムアウト時は書き込めた件数が分からないため、再接続後に残り全件を送り直す (重複しうる)
+    async fn send_messages(&self, messages: &[String]) -> SiemResult<()> {
+        let mut connection = self.connection.lock().await;
+        let mut sent = 0;
+        let mut attempts = 0;
+        loop {
+            let mut open = match connection.take() {
+                Some(open) => open,
+                None => self.with_timeout(self.connect()).await?,
+            };
+            let written = tokio::time::timeout(self.config.timeout, write_messages(&mut open, &messages[sent..])).await
+                .unwrap_or(Err((0, SiemError::TimeoutError)));
+            match written {
+                Ok(()) => {
+                    *connection = Some(open);
+                    return Ok(());
+                }
+                Err((written, e)) => {
+                    sent += written;
+                    attempts += 1;
+                    if attempts == 2 {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn with_timeout<T>(&self, future: impl std::future::Future<Output = SiemResult<T>>) -> SiemResult<T> {
+        tokio::time::timeout(self.config.timeout, future).await
+            .unwrap_or(Err(SiemError::TimeoutError))
+    }
+}
+
+/// Write messages; on failure reports how many were fully written
+async fn write_messages(connection: &mut Connection, messages: &[String]) -> Result<(), (usize, SiemError)> {
+    for (index, message) in messages.iter().enumerate() {
+        let result = match connection {
+            Connection::Udp(socket) => socket.send(message.as_bytes()).await.map(|_| ()),
+            Connection::Tcp(stream) => stream.write_all(&octet_counted(message)).await,
+            Connection::Tls(stream) => stream.write_all(&octet_counted(message)).await,
+        };
+        result.map_err(|e| (index, SiemError::from(e)))?;
+    }
+    let flushed = match connection {
+        Connection::Udp(_) => Ok(()),
+        Connection::Tcp(stream) => stream.flush().await,
+        Connection::Tls(stream) => stream.flush().await,
+    };
+    flushed.map_err(|e| (messages.len(), SiemError::from(e)))
+}
+
+/// RFC 6587 octet-counting frame (`LEN SP MSG`)
+fn octet_counted(message: &str) -> Vec<u8> {
+    format!("{} {}", message.len(), message).into_bytes()
+}
+
+/// Printable-ASCII header field without spaces, truncated to `max` characters
+fn header_field(value: &str, max: usize) -> String {
+    let field: String = value.chars().filter(|c| c.is_ascii_graphic()).take(max).collect();
+    if field.is_empty() { "-".to_string() } else { field }
+}
+
+#[async_trait]
+impl SiemClient for SyslogClient {
+    async fn send_event(&self, event: SiemEvent) -> SiemResult<()> {
+        self.send_messages(&[self.syslog_message(&event)]).await
+    }
+
+    async fn send_events(&self, events: Vec<SiemEvent>) -> SiemResult<()> {
+        let messages: Vec<String> = events.iter().map(|event| self.syslog_message(event)).collect();
+        self.send_messages(&messages).await
+    }
+
+    async fn query_events(&self, _query: &str, _limit: Option<usize>) -> SiemResult<Vec<SiemEvent>> {
+        Err(SiemError::ApiError {
+            status: 400,
+            message: "syslog does not support querying".to_string(),
+        })
+    }
+
+    async fn health_check(&self) -> SiemResult<bool> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            match self.with_timeout(self.connect()).await {
+                Ok(open) => *connection = Some(open),
+                Err(_) => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn event(message: &str) -> SiemEvent {
+        SiemEvent::new("alert", "fukurow", message)
+            .with_severity(SiemSeverity::Critical)
+            .with_metadata(serde_json::json!({ "rule": "ransomware_extension" }))
+    }
+
+    #[test]
+    fn test_syslog_message_header() {
+        let client = SyslogClient::new(
+            SyslogConfig::new("siem.example.com:514", SyslogTransport::Udp, SyslogFormat::Cef)
+                .with_facility(4)
+                .with_hostname("sensor 01"),
+        ).unwrap();
+        let message = client.syslog_message(&event("Ransomware detected"));
+        // facility 4 (auth) * 8 + critical (2)
+        assert!(message.starts_with("<34>1 "));
+        assert!(message.contains(" sensor01 fukurow - CEF - CEF:0|Fukurow|Fukurow|"));
+        assert!(message.contains("|ransomware_extension|Ransomware detected|10|"));
+
+        assert!(SyslogClient::new(SyslogConfig::new("siem.example.com", SyslogTransport::Tcp, SyslogFormat::Leef)).is_err());
+        assert!(SyslogClient::new(SyslogConfig::new("siem:514", SyslogTransport::Tcp, SyslogFormat::Leef).with_facility(24)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_udp_sends_one_datagram_per_event() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = receiver.local_addr().unwrap().to_string();
+        let client = SyslogClient::new(SyslogConfig::new(&address, SyslogTransport::Udp, SyslogFormat::Leef)).unwrap();
+
+        client.send_events(vec![event("first"), event("second")]).await.unwrap();
+        let mut buffer = [0u8; 4096];
+        for expected in ["msg=first", "msg=second"] {
+            let len = receiver.recv(&mut buffer).await.unwrap();
+            let datagram = std::str::from_utf8(&buffer[..len]).unwrap();
+            assert!(datagram.contains(" - LEEF - LEEF:1.0|Fukurow|"));
+            assert!(datagram.contains(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tcp_uses_octet_counting_frames() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let client = SyslogClient::new(SyslogConfig::new(&address, SyslogTransport::Tcp, SyslogFormat::Cef)).unwrap();
+        assert!(client.health_check().await.unwrap());
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        client.send_event(event("Ransomware detected")).await.unwrap();
+        drop(client);
+        let mut received = String::new();
+        stream.read_to_string(&mut received).await.unwrap();
+
+        let (len, message) = received.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), message.len());
+        assert!(message.starts_with("<106>1 "));
+        assert!(message.ends_with("cs1Label=rule cs1=ransomware_extension"));
+    }
+}
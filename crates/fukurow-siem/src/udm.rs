@@ -0,0 +1,541 @@
+//! Google Chronicle Unified Data Model (UDM)
+//!
+//! `CyberEvent` / `SiemEvent` を Chronicle の UDM イベントに変換する。
+//! フィールド名は Ingestion API (v2) の snake_case 表記に合わせ、
+//! Search API が返す camelCase の JSON は [`UdmEvent::from_search_json`] で読み込む
+
+use crate::{SiemEvent, SiemSeverity};
+use chrono::{DateTime, Utc};
+use fukurow_core::model::CyberEvent;
+use serde::{Deserialize, Serialize};
+
+/// Product name reported in `metadata.product_name`
+pub const UDM_PRODUCT_NAME: &str = "Fukurow";
+
+/// One UDM event
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmEvent {
+    pub metadata: UdmMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principal: Option<UdmNoun>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<UdmNoun>,
+    /// Other entities the event refers to (email URLs and attachments)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub about: Vec<UdmNoun>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<UdmNetwork>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security_result: Vec<UdmSecurityResult>,
+    /// Event-type specific extensions (`auth` for USER_LOGIN)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additional: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UdmMetadata {
+    pub event_timestamp: DateTime<Utc>,
+    /// UDM event type (`NETWORK_CONNECTION`, `PROCESS_LAUNCH`, ...)
+    pub event_type: String,
+    pub product_name: String,
+    pub vendor_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_version: Option<String>,
+    /// Fukurow's own event type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_event_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_log_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Default for UdmMetadata {
+    fn default() -> Self {
+        Self {
+            event_timestamp: Utc::now(),
+            event_type: "GENERIC_EVENT".to_string(),
+            product_name: UDM_PRODUCT_NAME.to_string(),
+            vendor_name: UDM_PRODUCT_NAME.to_string(),
+            product_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            product_event_type: None,
+            product_log_id: None,
+            description: None,
+        }
+    }
+}
+
+/// Entity (principal, target, src, about)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmNoun {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ip: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<UdmUser>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<UdmProcess>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<UdmFile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<UdmRegistry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmUser {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub userid: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub email_addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmProcess {
+    /// UDM stores process IDs as strings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_line: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmRegistry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_value_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_value_data: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmNetwork {
+    /// `TCP`, `UDP`, `ICMP`, ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<UdmDns>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<UdmHttp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<UdmEmail>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmDns {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub questions: Vec<UdmDnsRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub answers: Vec<UdmDnsRecord>,
+}
+
+/// DNS question or answer (`type` is the numeric resource record type)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmDnsRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmHttp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_code: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmEmail {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub to: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subject: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UdmSecurityResult {
+    /// `INFORMATIONAL`, `LOW`, `MEDIUM`, `HIGH`, `CRITICAL`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    /// `ALLOW`, `BLOCK`, ...
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub action: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl UdmEvent {
+    /// Map a sensor event to the matching UDM event type
+    pub fn from_cyber_event(event: &CyberEvent) -> Self {
+        let mut udm = UdmEvent::default();
+        let (event_type, timestamp) = match event {
+            CyberEvent::NetworkConnection { source_ip, dest_ip, port, protocol, timestamp } => {
+                udm.principal = Some(UdmNoun { ip: vec![source_ip.clone()], ..UdmNoun::default() });
+                udm.target = Some(UdmNoun { ip: vec![dest_ip.clone()], port: Some(u32::from(*port)), ..UdmNoun::default() });
+                udm.network = Some(UdmNetwork { ip_protocol: Some(ip_protocol(protocol)), ..UdmNetwork::default() });
+                ("NETWORK_CONNECTION", *timestamp)
+            }
+            CyberEvent::ProcessExecution { process_id, parent_process_id, command_line, user, timestamp } => {
+                udm.principal = Some(UdmNoun {
+                    user: Some(user_id(user)),
+                    process: parent_process_id.map(|pid| UdmProcess { pid: Some(pid.to_string()), command_line: None }),
+                    ..UdmNoun::default()
+                });
+                udm.target = Some(UdmNoun {
+                    process: Some(UdmProcess { pid: Some(process_id.to_string()), command_line: Some(command_line.clone()) }),
+                    ..UdmNoun::default()
+                });
+                ("PROCESS_LAUNCH", *timestamp)
+            }
+            CyberEvent::FileAccess { file_path, access_type, user, process_id, timestamp } => {
+                udm.principal = Some(UdmNoun {
+                    user: Some(user_id(user)),
+                    process: Some(UdmProcess { pid: Some(process_id.to_string()), command_line: None }),
+                    ..UdmNoun::default()
+                });
+                udm.target = Some(UdmNoun { file: Some(UdmFile { full_path: Some(file_path.clone()) }), ..UdmNoun::default() });
+                let event_type = match access_type.to_ascii_lowercase().as_str() {
+                    "read" => "FILE_READ",
+                    "write" | "modify" => "FILE_MODIFICATION",
+                    "create" => "FILE_CREATION",
+                    "delete" => "FILE_DELETION",
+                    "open" => "FILE_OPEN",
+                    _ => "FILE_UNCATEGORIZED",
+                };
+                (event_type, *timestamp)
+            }
+            CyberEvent::UserLogin { user, source_ip, success, timestamp } => {
+                udm.principal = Some(UdmNoun { ip: vec![source_ip.clone()], ..UdmNoun::default() });
+                udm.target = Some(UdmNoun { user: Some(user_id(user)), ..UdmNoun::default() });
+                udm.security_result.push(UdmSecurityResult {
+                    action: vec![if *success { "ALLOW" } else { "BLOCK" }.to_string()],
+                    ..UdmSecurityResult::default()
+                });
+                // USER_LOGIN は extensions.auth が必須
+                udm.extensions = Some(serde_json::json!({ "auth": { "type": "AUTHTYPE_UNSPECIFIED" } }));
+                ("USER_LOGIN", *timestamp)
+            }
+            CyberEvent::DnsQuery { query_name, query_type, source_ip, resolved_ips, timestamp } => {
+                udm.principal = Some(UdmNoun { ip: vec![source_ip.clone()], ..UdmNoun::default() });
+                let questions = vec![UdmDnsRecord { name: Some(query_name.clone()), record_type: dns_record_type(query_type), data: None }];
+                let answers = resolved_ips.iter().map(|ip| UdmDnsRecord {
+                    name: Some(query_name.clone()),
+                    record_type: Some(if ip.contains(':') { 28 } else { 1 }),
+                    data: Some(ip.clone()),
+                }).collect();
+                udm.network = Some(UdmNetwork {
+                    application_protocol: Some("DNS".to_string()),
+                    dns: Some(UdmDns { questions, answers }),
+                    ..UdmNetwork::default()
+                });
+                ("NETWORK_DNS", *timestamp)
+            }
+            CyberEvent::HttpRequest { method, url, host, user_agent, source_ip, status_code, timestamp } => {
+                udm.principal = Some(UdmNoun { ip: vec![source_ip.clone()], ..UdmNoun::default() });
+                udm.target = Some(UdmNoun { hostname: Some(host.clone()), url: Some(url.clone()), ..UdmNoun::default() });
+                udm.network = Some(UdmNetwork {
+                    application_protocol: Some(if url.starts_with("https://") { "HTTPS" } else { "HTTP" }.to_string()),
+                    http: Some(UdmHttp {
+                        method: Some(method.clone()),
+                        user_agent: Some(user_agent.clone()).filter(|ua| !ua.is_empty()),
+                        response_code: status_code.map(u32::from),
+                    }),
+                    ..UdmNetwork::default()
+                });
+                ("NETWORK_HTTP", *timestamp)
+            }
+            CyberEvent::RegistryModification { key_path, value_name, value_data, operation, process_id, user, timestamp } => {
+                udm.principal = Some(UdmNoun {
+                    user: Some(user_id(user)),
+                    process: Some(UdmProcess { pid: Some(process_id.to_string()), command_line: None }),
+                    ..UdmNoun::default()
+                });
+                udm.target = Some(UdmNoun {
+                    registry: Some(UdmRegistry {
+                        registry_key: Some(key_path.clone()),
+                        registry_value_name: value_name.clone(),
+                        registry_value_data: value_data.clone(),
+                    }),
+                    ..UdmNoun::default()
+                });
+                let event_type = match operation.to_ascii_lowercase().as_str() {
+                    "create" => "REGISTRY_CREATION",
+                    "delete" => "REGISTRY_DELETION",
+                    _ => "REGISTRY_MODIFICATION",
+                };
+                (event_type, *timestamp)
+            }
+            CyberEvent::EmailReceived { sender, recipient, subject, reply_to, urls, attachments, timestamp } => {
+                udm.principal = Some(UdmNoun { user: Some(email_user(sender)), ..UdmNoun::default() });
+                udm.target = Some(UdmNoun { user: Some(email_user(recipient)), ..UdmNoun::default() });
+                udm.network = Some(UdmNetwork {
+                    application_protocol: Some("SMTP".to_string()),
+                    email: Some(UdmEmail {
+                        from: Some(sender.clone()),
+                        reply_to: reply_to.clone(),
+                        to: vec![recipient.clone()],
+                        subject: vec![subject.clone()],
+                    }),
+                    ..UdmNetwork::default()
+                });
+                udm.about = urls.iter()
+                    .map(|url| UdmNoun { url: Some(url.clone()), ..UdmNoun::default() })
+                    .chain(attachments.iter().map(|name| UdmNoun {
+                        file: Some(UdmFile { full_path: Some(name.clone()) }),
+                        ..UdmNoun::default()
+                    }))
+                    .collect();
+                ("EMAIL_TRANSACTION", *timestamp)
+            }
+        };
+
+        udm.metadata.event_type = event_type.to_string();
+        udm.metadata.event_timestamp = DateTime::from_timestamp(timestamp, 0).unwrap_or_else(Utc::now);
+        udm
+    }
+
+    /// Map a SIEM event; metadata holding a serialized `CyberEvent` keeps its typed mapping
+    ///
+    /// それ以外は GENERIC_EVENT として security_result に重大度とメッセージを載せる
+    pub fn from_siem_event(event: &SiemEvent) -> Self {
+        let mut udm = match serde_json::from_value::<CyberEvent>(event.metadata.clone()) {
+            Ok(cyber_event) => Self::from_cyber_event(&cyber_event),
+            Err(_) => UdmEvent {
+                additional: Some(serde_json::json!({ "fukurow_metadata": event.metadata })),
+                ..UdmEvent::default()
+            },
+        };
+        udm.metadata.event_timestamp = event.timestamp;
+        udm.metadata.product_event_type = Some(event.event_type.clone());
+        udm.metadata.product_log_id = Some(event.id.clone());
+        udm.metadata.description = Some(event.message.clone());
+
+        let severity = match event.severity {
+            SiemSeverity::Low => "LOW",
+            SiemSeverity::Medium => "MEDIUM",
+            SiemSeverity::High => "HIGH",
+            SiemSeverity::Critical => "CRITICAL",
+        };
+        let result = UdmSecurityResult {
+            severity: Some(severity.to_string()),
+            summary: Some(event.message.clone()),
+            description: event.raw_data.clone(),
+            ..UdmSecurityResult::default()
+        };
+        match udm.security_result.first_mut() {
+            Some(existing) => {
+                existing.severity = result.severity;
+                existing.summary = result.summary;
+                existing.description = result.description;
+            }
+            None => udm.security_result.push(result),
+        }
+
+        let mut additional = udm.additional.take().unwrap_or_else(|| serde_json::json!({}));
+        additional["fukurow_source"] = serde_json::json!(event.source);
+        udm.additional = Some(additional);
+        udm
+    }
+
+    /// Back-convert into the common SIEM event
+    pub fn to_siem_event(&self) -> SiemEvent {
+        let result = self.security_result.first();
+        let severity = match result.and_then(|r| r.severity.as_deref()) {
+            Some("CRITICAL") => SiemSeverity::Critical,
+            Some("HIGH") => SiemSeverity::High,
+            Some("LOW") | Some("INFORMATIONAL") => SiemSeverity::Low,
+            _ => SiemSeverity::Medium,
+        };
+        let message = result.and_then(|r| r.summary.clone())
+            .or_else(|| self.metadata.description.clone())
+            .unwrap_or_else(|| format!("Chronicle {} event", self.metadata.event_type));
+        let source = self.additional.as_ref()
+            .and_then(|additional| additional.get("fukurow_source"))
+            .and_then(|source| source.as_str())
+            .unwrap_or("chronicle");
+
+        SiemEvent {
+            id: self.metadata.product_log_id.clone().unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            timestamp: self.metadata.event_timestamp,
+            event_type: self.metadata.product_event_type.clone().unwrap_or_else(|| self.metadata.event_type.clone()),
+            source: source.to_string(),
+            severity,
+            message,
+            metadata: serde_json::to_value(self).unwrap_or(serde_json::Value::Null),
+            raw_data: result.and_then(|r| r.description.clone()),
+        }
+    }
+
+    /// Parse a UDM object returned by the Search API (camelCase keys)
+    pub fn from_search_json(value: serde_json::Value) -> serde_json::Result<Self> {
+        serde_json::from_value(snake_case_keys(value))
+    }
+}
+
+fn user_id(user: &str) -> UdmUser {
+    UdmUser { userid: Some(user.to_string()), email_addresses: Vec::new() }
+}
+
+fn email_user(address: &str) -> UdmUser {
+    UdmUser { userid: None, email_addresses: vec![address.to_string()] }
+}
+
+fn ip_protocol(protocol: &str) -> String {
+    match protocol.to_ascii_uppercase().as_str() {
+        protocol @ ("TCP" | "UDP" | "ICMP" | "ICMP6" | "GRE" | "ESP" | "SCTP") => protocol.to_string(),
+        _ => "UNKNOWN_IP_PROTOCOL".to_string(),
+    }
+}
+
+/// Numeric DNS resource record type
+fn dns_record_type(query_type: &str) -> Option<u32> {
+    match query_type.to_ascii_uppercase().as_str() {
+        "A" => Some(1),
+        "NS" => Some(2),
+        "CNAME" => Some(5),
+        "SOA" => Some(6),
+        "PTR" => Some(12),
+        "MX" => Some(15),
+        "TXT" => Some(16),
+        "AAAA" => Some(28),
+        "SRV" => Some(33),
+        "ANY" => Some(255),
+        other => other.parse().ok(),
+    }
+}
+
+/// Convert object keys from camelCase to snake_case, recursively
+fn snake_case_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map.into_iter()
+            .map(|(key, value)| {
+                let mut snake = String::with_capacity(key.len() + 4);
+                for c in key.chars() {
+                    if c.is_ascii_uppercase() {
+                        snake.push('_');
+                        snake.push(c.to_ascii_lowercase());
+                    } else {
+                        snake.push(c);
+                    }
+                }
+                (snake, snake_case_keys(value))
+            })
+            .collect(),
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(snake_case_keys).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cyber_events_map_to_udm_types() {
+        let login = UdmEvent::from_cyber_event(&CyberEvent::UserLogin {
+            user: "alice".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            success: false,
+            timestamp: 1_700_000_000,
+        });
+        let json = serde_json::to_value(&login).unwrap();
+        assert_eq!(json["metadata"]["event_type"], "USER_LOGIN");
+        assert_eq!(json["metadata"]["event_timestamp"], "2023-11-14T22:13:20Z");
+        assert_eq!(json["target"]["user"]["userid"], "alice");
+        assert_eq!(json["security_result"][0]["action"][0], "BLOCK");
+        assert!(json["extensions"]["auth"].is_object());
+        assert!(json.get("network").is_none());
+
+        let dns = UdmEvent::from_cyber_event(&CyberEvent::DnsQuery {
+            query_name: "example.org".to_string(),
+            query_type: "AAAA".to_string(),
+            source_ip: "10.0.0.5".to_string(),
+            resolved_ips: vec!["2001:db8::1".to_string()],
+            timestamp: 1_700_000_000,
+        });
+        let dns_json = serde_json::to_value(&dns).unwrap();
+        assert_eq!(dns_json["metadata"]["event_type"], "NETWORK_DNS");
+        assert_eq!(dns_json["network"]["dns"]["questions"][0]["type"], 28);
+        assert_eq!(dns_json["network"]["dns"]["answers"][0]["data"], "2001:db8::1");
+
+        let registry = UdmEvent::from_cyber_event(&CyberEvent::RegistryModification {
+            key_path: r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run".to_string(),
+            value_name: Some("updater".to_string()),
+            value_data: None,
+            operation: "delete".to_string(),
+            process_id: 42,
+            user: "bob".to_string(),
+            timestamp: 1_700_000_000,
+        });
+        assert_eq!(registry.metadata.event_type, "REGISTRY_DELETION");
+        assert_eq!(registry.principal.unwrap().process.unwrap().pid.as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn test_siem_event_round_trip_through_search_json() {
+        let cyber_event = CyberEvent::NetworkConnection {
+            source_ip: "10.0.0.1".to_string(),
+            dest_ip: "203.0.113.9".to_string(),
+            port: 4444,
+            protocol: "tcp".to_string(),
+            timestamp: 1_700_000_000,
+        };
+        let event = SiemEvent::new("reverse_shell", "fukurow-api", "Outbound connection to C2")
+            .with_severity(SiemSeverity::Critical)
+            .with_metadata(serde_json::to_value(&cyber_event).unwrap());
+
+        let udm = UdmEvent::from_siem_event(&event);
+        assert_eq!(udm.metadata.event_type, "NETWORK_CONNECTION");
+        assert_eq!(udm.target.as_ref().unwrap().port, Some(4444));
+        assert_eq!(udm.network.as_ref().unwrap().ip_protocol.as_deref(), Some("TCP"));
+
+        // Search API は camelCase で返す
+        let search_json = serde_json::json!({
+            "metadata": {
+                "eventTimestamp": "2023-11-14T22:13:20Z",
+                "eventType": "NETWORK_CONNECTION",
+                "productName": "Fukurow",
+                "vendorName": "Fukurow",
+                "productEventType": "reverse_shell",
+                "productLogId": event.id
+            },
+            "target": { "ip": ["203.0.113.9"], "port": 4444 },
+            "securityResult": [{ "severity": "CRITICAL", "summary": "Outbound connection to C2" }],
+            "additional": { "fukurow_source": "fukurow-api" }
+        });
+        let parsed = UdmEvent::from_search_json(search_json).unwrap();
+        let back = parsed.to_siem_event();
+        assert_eq!(back.id, event.id);
+        assert_eq!(back.event_type, "reverse_shell");
+        assert_eq!(back.source, "fukurow-api");
+        assert!(matches!(back.severity, SiemSeverity::Critical));
+        assert_eq!(back.message, "Outbound connection to C2");
+    }
+}
@@ -0,0 +1,222 @@
+//! Aggregate functions and numeric values
+//!
+//! GROUP BY の各グループについて、集約関数の引数を評価した値の列から結果を計算する。
+//! イベントのフィールドは型なしリテラルで格納されるため、数値として読める型なしリテラルも
+//! 数値として扱う。未束縛の値 (OPTIONAL で欠けたフィールド) は集約の対象から外す
+
+use crate::algebra::Aggregate;
+use crate::parser::{Iri, Literal, Term};
+use std::cmp::Ordering;
+
+pub const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+const INTEGER_TYPES: &[&str] = &[
+    "integer", "int", "long", "short", "byte",
+    "nonNegativeInteger", "positiveInteger", "nonPositiveInteger", "negativeInteger",
+    "unsignedLong", "unsignedInt", "unsignedShort", "unsignedByte",
+];
+const DECIMAL_TYPES: &[&str] = &["decimal", "float", "double"];
+
+/// Numeric value of a literal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numeric {
+    Integer(i64),
+    Decimal(f64),
+}
+
+impl Numeric {
+    /// Numeric value of a term (`None` for IRIs, blank nodes and non-numeric literals)
+    pub fn from_term(term: &Term) -> Option<Self> {
+        let literal = match term {
+            Term::Literal(literal) if literal.language.is_none() => literal,
+            _ => return None,
+        };
+        let value = literal.value.trim();
+        match literal.datatype.as_ref().and_then(|datatype| datatype.0.strip_prefix(XSD)) {
+            Some(local) if INTEGER_TYPES.contains(&local) => value.parse().ok().map(Numeric::Integer),
+            Some(local) if DECIMAL_TYPES.contains(&local) => value.parse().ok().map(Numeric::Decimal),
+            Some(_) => None,
+            None if literal.datatype.is_some() => None,
+            None => value.parse().map(Numeric::Integer).ok()
+                .or_else(|| value.parse::<f64>().ok().filter(|v| v.is_finite()).map(Numeric::Decimal)),
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Numeric::Integer(value) => value as f64,
+            Numeric::Decimal(value) => value,
+        }
+    }
+
+    pub fn to_term(self) -> Term {
+        match self {
+            Numeric::Integer(value) => typed_literal(value.to_string(), "integer"),
+            Numeric::Decimal(value) => typed_literal(value.to_string(), "decimal"),
+        }
+    }
+
+    pub fn checked_add(self, other: Numeric) -> Option<Numeric> {
+        match (self, other) {
+            (Numeric::Integer(a), Numeric::Integer(b)) => a.checked_add(b).map(Numeric::Integer),
+            (a, b) => Some(Numeric::Decimal(a.as_f64() + b.as_f64())),
+        }
+    }
+
+    pub fn checked_sub(self, other: Numeric) -> Option<Numeric> {
+        match (self, other) {
+            (Numeric::Integer(a), Numeric::Integer(b)) => a.checked_sub(b).map(Numeric::Integer),
+            (a, b) => Some(Numeric::Decimal(a.as_f64() - b.as_f64())),
+        }
+    }
+
+    pub fn checked_mul(self, other: Numeric) -> Option<Numeric> {
+        match (self, other) {
+            (Numeric::Integer(a), Numeric::Integer(b)) => a.checked_mul(b).map(Numeric::Integer),
+            (a, b) => Some(Numeric::Decimal(a.as_f64() * b.as_f64())),
+        }
+    }
+
+    /// Division always yields a decimal; division by zero is an error
+    pub fn checked_div(self, other: Numeric) -> Option<Numeric> {
+        let divisor = other.as_f64();
+        if divisor == 0.0 {
+            return None;
+        }
+        Some(Numeric::Decimal(self.as_f64() / divisor))
+    }
+
+    pub fn compare(self, other: Numeric) -> Option<Ordering> {
+        match (self, other) {
+            (Numeric::Integer(a), Numeric::Integer(b)) => Some(a.cmp(&b)),
+            (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+        }
+    }
+}
+
+pub fn typed_literal(value: impl Into<String>, xsd_local: &str) -> Term {
+    Term::Literal(Literal {
+        value: value.into(),
+        datatype: Some(Iri(format!("{}{}", XSD, xsd_local))),
+        language: None,
+    })
+}
+
+pub fn boolean_term(value: bool) -> Term {
+    typed_literal(value.to_string(), "boolean")
+}
+
+/// Lexical form used by STR() and GROUP_CONCAT (`None` for blank nodes)
+pub fn string_value(term: &Term) -> Option<String> {
+    match term {
+        Term::Iri(iri) => Some(iri.0.clone()),
+        Term::Literal(literal) => Some(literal.value.clone()),
+        _ => None,
+    }
+}
+
+/// Order used by comparisons (`None` when the terms are not comparable)
+///
+/// リテラルはデータ型の値空間で比較する ([`crate::datatype::compare_terms`])
+pub fn compare_values(left: &Term, right: &Term) -> Option<Ordering> {
+    crate::datatype::compare_terms(left, right)
+}
+
+/// Total order used by MIN / MAX and ORDER BY: blank nodes < IRIs < literals (numbers numerically)
+pub fn order_terms(left: &Term, right: &Term) -> Ordering {
+    let rank = |term: &Term| match term {
+        Term::BlankNode(_) => 0,
+        Term::Iri(_) | Term::PrefixedName(..) => 1,
+        Term::Literal(_) => 2,
+        Term::Variable(_) => 3,
+    };
+    compare_values(left, right)
+        .unwrap_or_else(|| rank(left).cmp(&rank(right)).then_with(|| string_value(left).cmp(&string_value(right))))
+}
+
+/// Evaluate an aggregate over one group
+///
+/// `values` は各解で引数を評価した値 (`COUNT(*)` では未使用)、`solutions` はグループ内の解の数
+pub fn aggregate(aggregate: &Aggregate, values: Vec<Option<Term>>, solutions: usize) -> Option<Term> {
+    let distinct = match aggregate {
+        Aggregate::Count { distinct, .. }
+        | Aggregate::Sum(_, distinct)
+        | Aggregate::Avg(_, distinct)
+        | Aggregate::Min(_, distinct)
+        | Aggregate::Max(_, distinct)
+        | Aggregate::GroupConcat { distinct, .. } => *distinct,
+        Aggregate::Sample(_) => false,
+    };
+    let mut bound: Vec<Term> = values.into_iter().flatten().collect();
+    if distinct {
+        let mut seen = Vec::with_capacity(bound.len());
+        bound.retain(|value| {
+            if seen.contains(value) {
+                false
+            } else {
+                seen.push(value.clone());
+                true
+            }
+        });
+    }
+
+    match aggregate {
+        Aggregate::Count { expr: None, .. } => Some(Numeric::Integer(solutions as i64).to_term()),
+        Aggregate::Count { expr: Some(_), .. } => Some(Numeric::Integer(bound.len() as i64).to_term()),
+        Aggregate::Sum(..) => sum(&bound).map(Numeric::to_term),
+        Aggregate::Avg(..) => {
+            if bound.is_empty() {
+                return Some(Numeric::Integer(0).to_term());
+            }
+            let total = sum(&bound)?;
+            Some(Numeric::Decimal(total.as_f64() / bound.len() as f64).to_term())
+        }
+        Aggregate::Min(..) => bound.into_iter().min_by(order_terms),
+        Aggregate::Max(..) => bound.into_iter().max_by(order_terms),
+        Aggregate::Sample(_) => bound.into_iter().next(),
+        Aggregate::GroupConcat { separator, .. } => {
+            let parts: Option<Vec<String>> = bound.iter().map(string_value).collect();
+            Some(Term::Literal(Literal {
+                value: parts?.join(separator.as_deref().unwrap_or(" ")),
+                datatype: None,
+                language: None,
+            }))
+        }
+    }
+}
+
+/// Sum of numeric values; a non-numeric value makes the whole sum an error
+fn sum(values: &[Term]) -> Option<Numeric> {
+    values.iter().try_fold(Numeric::Integer(0), |total, value| total.checked_add(Numeric::from_term(value)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Expression, Variable};
+
+    fn plain(value: &str) -> Option<Term> {
+        Some(Term::Literal(Literal { value: value.to_string(), datatype: None, language: None }))
+    }
+
+    #[test]
+    fn test_aggregates_over_values() {
+        let expr = Box::new(Expression::Variable(Variable("bytes".to_string())));
+        let values = vec![plain("10"), plain("2.5"), None, plain("10")];
+
+        assert_eq!(aggregate(&Aggregate::Sum(expr.clone(), false), values.clone(), 4), Some(typed_literal("22.5", "decimal")));
+        assert_eq!(aggregate(&Aggregate::Sum(expr.clone(), true), values.clone(), 4), Some(typed_literal("12.5", "decimal")));
+        assert_eq!(aggregate(&Aggregate::Count { expr: Some(expr.clone()), distinct: false }, values.clone(), 4), Some(typed_literal("3", "integer")));
+        assert_eq!(aggregate(&Aggregate::Count { expr: None, distinct: false }, values.clone(), 4), Some(typed_literal("4", "integer")));
+        assert_eq!(aggregate(&Aggregate::Max(expr.clone(), false), values.clone(), 4), plain("10"));
+        assert_eq!(aggregate(&Aggregate::Min(expr.clone(), false), values.clone(), 4), plain("2.5"));
+        assert_eq!(
+            aggregate(&Aggregate::GroupConcat { expr: expr.clone(), distinct: true, separator: Some(",".to_string()) }, values, 4),
+            plain("10,2.5"),
+        );
+
+        // 数値でない値を含む SUM はエラー (未束縛)
+        assert_eq!(aggregate(&Aggregate::Sum(expr.clone(), false), vec![plain("10"), plain("n/a")], 2), None);
+        assert_eq!(aggregate(&Aggregate::Avg(expr, false), vec![], 0), Some(typed_literal("0", "integer")));
+    }
+}
@@ -9,6 +9,9 @@ pub enum Algebra {
     /// Basic Graph Pattern
     Bgp(Vec<TriplePattern>),
 
+    /// Join
+    Join(Box<Algebra>, Box<Algebra>),
+
     /// Left Join (OPTIONAL)
     LeftJoin {
         left: Box<Algebra>,
@@ -48,7 +51,8 @@ pub enum Algebra {
     Group {
         input: Box<Algebra>,
         keys: Vec<Expression>,
-        aggs: Vec<Aggregate>,
+        /// Aggregates bound to (internal) variables of each group's solution
+        aggs: Vec<(Variable, Aggregate)>,
     },
 
     /// Graph
@@ -76,6 +80,75 @@ pub enum Aggregate {
     Sample(Box<Expression>),
 }
 
+impl Aggregate {
+    /// Expression evaluated for each solution of a group (`None` for `COUNT(*)`)
+    pub fn argument(&self) -> Option<&Expression> {
+        match self {
+            Aggregate::Count { expr, .. } => expr.as_deref(),
+            Aggregate::Sum(expr, _)
+            | Aggregate::Avg(expr, _)
+            | Aggregate::Min(expr, _)
+            | Aggregate::Max(expr, _)
+            | Aggregate::Sample(expr)
+            | Aggregate::GroupConcat { expr, .. } => Some(expr),
+        }
+    }
+}
+
+impl Algebra {
+    /// Direct sub-plans, left to right
+    pub fn children(&self) -> Vec<&Algebra> {
+        match self {
+            Algebra::Bgp(_) | Algebra::Values(_) => Vec::new(),
+            Algebra::Join(left, right) | Algebra::Union(left, right) | Algebra::Minus(left, right) => vec![&**left, &**right],
+            Algebra::LeftJoin { left, right, .. } => vec![&**left, &**right],
+            Algebra::Filter(inner, _)
+            | Algebra::Project(inner, _)
+            | Algebra::Extend(inner, _, _)
+            | Algebra::OrderBy(inner, _)
+            | Algebra::Distinct(inner)
+            | Algebra::Reduced(inner)
+            | Algebra::Graph(_, inner)
+            | Algebra::Service(_, inner, _) => vec![&**inner],
+            Algebra::Slice { input, .. } | Algebra::Group { input, .. } => vec![&**input],
+        }
+    }
+
+    /// Rebuild this node with `f` applied to each direct sub-plan
+    pub fn map_children(self, mut f: impl FnMut(Algebra) -> Algebra) -> Algebra {
+        let mut map = |inner: Box<Algebra>| Box::new(f(*inner));
+        match self {
+            Algebra::Bgp(_) | Algebra::Values(_) => self,
+            Algebra::Join(left, right) => {
+                let left = map(left);
+                Algebra::Join(left, map(right))
+            }
+            Algebra::Union(left, right) => {
+                let left = map(left);
+                Algebra::Union(left, map(right))
+            }
+            Algebra::Minus(left, right) => {
+                let left = map(left);
+                Algebra::Minus(left, map(right))
+            }
+            Algebra::LeftJoin { left, right, expr } => {
+                let left = map(left);
+                Algebra::LeftJoin { left, right: map(right), expr }
+            }
+            Algebra::Filter(inner, expr) => Algebra::Filter(map(inner), expr),
+            Algebra::Project(inner, vars) => Algebra::Project(map(inner), vars),
+            Algebra::Extend(inner, var, expr) => Algebra::Extend(map(inner), var, expr),
+            Algebra::OrderBy(inner, conditions) => Algebra::OrderBy(map(inner), conditions),
+            Algebra::Distinct(inner) => Algebra::Distinct(map(inner)),
+            Algebra::Reduced(inner) => Algebra::Reduced(map(inner)),
+            Algebra::Graph(graph, inner) => Algebra::Graph(graph, map(inner)),
+            Algebra::Service(endpoint, inner, silent) => Algebra::Service(endpoint, map(inner), silent),
+            Algebra::Slice { input, offset, limit } => Algebra::Slice { input: map(input), offset, limit },
+            Algebra::Group { input, keys, aggs } => Algebra::Group { input: map(input), keys, aggs },
+        }
+    }
+}
+
 /// Plan builder trait
 pub trait PlanBuilder {
     fn to_algebra(&self, query: &SparqlQuery) -> Result<Algebra, crate::SparqlError>;
@@ -96,7 +169,17 @@ impl PlanBuilder for DefaultPlanBuilder {
             );
         }
 
-        // Apply solution modifiers
+        // Apply GROUP BY and aggregates (then HAVING and the SELECT expressions)
+        algebra = self.group_and_project_expressions(algebra, query)?;
+
+        // Apply solution modifiers (ORDER BY is applied before the slice)
+        if let Some(order) = &query.solution_modifier.order {
+            algebra = Algebra::OrderBy(
+                Box::new(algebra),
+                order.clone(),
+            );
+        }
+
         if let Some(limit) = query.solution_modifier.limit {
             algebra = Algebra::Slice {
                 input: Box::new(algebra),
@@ -111,15 +194,6 @@ impl PlanBuilder for DefaultPlanBuilder {
             };
         }
 
-        if !query.solution_modifier.order.is_none() {
-            if let Some(order) = &query.solution_modifier.order {
-                algebra = Algebra::OrderBy(
-                    Box::new(algebra),
-                    order.clone(),
-                );
-            }
-        }
-
         if query.solution_modifier.distinct {
             algebra = Algebra::Distinct(Box::new(algebra));
         }
@@ -128,16 +202,6 @@ impl PlanBuilder for DefaultPlanBuilder {
             algebra = Algebra::Reduced(Box::new(algebra));
         }
 
-        // Apply GROUP BY and aggregates
-        if let Some(group_keys) = &query.solution_modifier.group {
-            let aggs = self.extract_aggregates(&query.variables)?;
-            algebra = Algebra::Group {
-                input: Box::new(algebra),
-                keys: group_keys.clone(),
-                aggs,
-            };
-        }
-
         // Projection for SELECT
         match &query.query_type {
             QueryType::Select => {
@@ -159,16 +223,20 @@ impl PlanBuilder for DefaultPlanBuilder {
 }
 
 impl DefaultPlanBuilder {
-    fn graph_pattern_to_algebra(&self, pattern: &GraphPattern) -> Result<Algebra, crate::SparqlError> {
+    pub(crate) fn graph_pattern_to_algebra(&self, pattern: &GraphPattern) -> Result<Algebra, crate::SparqlError> {
         match pattern {
             GraphPattern::Bgp(triples) => Ok(Algebra::Bgp(triples.clone())),
-            GraphPattern::Optional(inner) => {
-                let inner_alg = self.graph_pattern_to_algebra(inner)?;
-                Ok(Algebra::LeftJoin {
-                    left: Box::new(Algebra::Bgp(vec![])), // TODO: 適切なleft plan
-                    right: Box::new(inner_alg),
-                    expr: None,
-                })
+            // グループ先頭の OPTIONAL は空の解 1 つとの左外部結合
+            GraphPattern::Optional(inner) => self.left_join(Algebra::Bgp(vec![]), inner),
+            GraphPattern::Join(left, right) => {
+                let left_alg = self.graph_pattern_to_algebra(left)?;
+                match right.as_ref() {
+                    GraphPattern::Optional(inner) => self.left_join(left_alg, inner),
+                    _ => {
+                        let right_alg = self.graph_pattern_to_algebra(right)?;
+                        Ok(Algebra::Join(Box::new(left_alg), Box::new(right_alg)))
+                    }
+                }
             }
             GraphPattern::Union(patterns) => {
                 if patterns.is_empty() {
@@ -202,9 +270,115 @@ impl DefaultPlanBuilder {
         }
     }
 
-    fn extract_aggregates(&self, variables: &[Variable]) -> Result<Vec<Aggregate>, crate::SparqlError> {
-        // TODO: 変数から集約関数を抽出する実装
-        // 現在はダミー実装
-        Ok(vec![])
+    /// `OPTIONAL { P FILTER(F) }` は LeftJoin(G, P, F): F は左右を結合した解で評価する
+    fn left_join(&self, left: Algebra, optional: &GraphPattern) -> Result<Algebra, crate::SparqlError> {
+        let (right, expr) = match optional {
+            GraphPattern::Filter(expr, inner) => (self.graph_pattern_to_algebra(inner)?, Some(expr.clone())),
+            other => (self.graph_pattern_to_algebra(other)?, None),
+        };
+        Ok(Algebra::LeftJoin { left: Box::new(left), right: Box::new(right), expr })
     }
+
+    /// GROUP BY / 集約 / HAVING / SELECT 式を代数に変換する
+    ///
+    /// 集約呼び出しは内部変数 (`#agg0`, ...) に置き換え、Group がその変数に集約値を束縛する。
+    /// GROUP BY がなくても集約を使っていれば、全体を 1 グループとして扱う
+    fn group_and_project_expressions(&self, mut algebra: Algebra, query: &SparqlQuery) -> Result<Algebra, crate::SparqlError> {
+        let mut aggs = Vec::new();
+        let having: Vec<Expression> = query.solution_modifier.having.iter().flatten()
+            .map(|expr| extract_aggregates(expr, &mut aggs))
+            .collect();
+        let select_expressions: Vec<(Variable, Expression)> = query.select_expressions.iter()
+            .map(|(var, expr)| (var.clone(), extract_aggregates(expr, &mut aggs)))
+            .collect();
+
+        if query.solution_modifier.group.is_some() || !aggs.is_empty() {
+            let mut keys = Vec::new();
+            for condition in query.solution_modifier.group.iter().flatten() {
+                if contains_aggregate(&condition.expr) {
+                    return Err(SparqlError::AlgebraError("Aggregates are not allowed in GROUP BY".to_string()));
+                }
+                match &condition.alias {
+                    Some(alias) => {
+                        algebra = Algebra::Extend(Box::new(algebra), alias.clone(), condition.expr.clone());
+                        keys.push(Expression::Variable(alias.clone()));
+                    }
+                    None => keys.push(condition.expr.clone()),
+                }
+            }
+            algebra = Algebra::Group { input: Box::new(algebra), keys, aggs };
+        } else if !having.is_empty() {
+            return Err(SparqlError::AlgebraError("HAVING requires GROUP BY or an aggregate".to_string()));
+        }
+
+        for expr in having {
+            algebra = Algebra::Filter(Box::new(algebra), expr);
+        }
+        for (var, expr) in select_expressions {
+            algebra = Algebra::Extend(Box::new(algebra), var, expr);
+        }
+        Ok(algebra)
+    }
+}
+
+/// Replace aggregate calls by the variables their values are bound to
+///
+/// 同じ集約 (例: SELECT と HAVING の `COUNT(?dst)`) は 1 つの変数を共有する
+fn extract_aggregates(expr: &Expression, aggs: &mut Vec<(Variable, Aggregate)>) -> Expression {
+    fn binary(
+        left: &Expression,
+        right: &Expression,
+        aggs: &mut Vec<(Variable, Aggregate)>,
+        build: fn(Box<Expression>, Box<Expression>) -> Expression,
+    ) -> Expression {
+        let left = extract_aggregates(left, aggs);
+        build(Box::new(left), Box::new(extract_aggregates(right, aggs)))
+    }
+
+    match expr {
+        Expression::Aggregate(aggregate) => {
+            let existing = aggs.iter().find(|(_, known)| known == aggregate.as_ref()).map(|(var, _)| var.clone());
+            let var = existing.unwrap_or_else(|| {
+                let var = Variable(format!("#agg{}", aggs.len()));
+                aggs.push((var.clone(), aggregate.as_ref().clone()));
+                var
+            });
+            Expression::Variable(var)
+        }
+        Expression::Add(l, r) => binary(l, r, aggs, Expression::Add),
+        Expression::Subtract(l, r) => binary(l, r, aggs, Expression::Subtract),
+        Expression::Multiply(l, r) => binary(l, r, aggs, Expression::Multiply),
+        Expression::Divide(l, r) => binary(l, r, aggs, Expression::Divide),
+        Expression::Equal(l, r) => binary(l, r, aggs, Expression::Equal),
+        Expression::NotEqual(l, r) => binary(l, r, aggs, Expression::NotEqual),
+        Expression::LessThan(l, r) => binary(l, r, aggs, Expression::LessThan),
+        Expression::LessThanOrEqual(l, r) => binary(l, r, aggs, Expression::LessThanOrEqual),
+        Expression::GreaterThan(l, r) => binary(l, r, aggs, Expression::GreaterThan),
+        Expression::GreaterThanOrEqual(l, r) => binary(l, r, aggs, Expression::GreaterThanOrEqual),
+        Expression::And(l, r) => binary(l, r, aggs, Expression::And),
+        Expression::Or(l, r) => binary(l, r, aggs, Expression::Or),
+        Expression::Not(e) => Expression::Not(Box::new(extract_aggregates(e, aggs))),
+        Expression::IsIri(e) => Expression::IsIri(Box::new(extract_aggregates(e, aggs))),
+        Expression::IsLiteral(e) => Expression::IsLiteral(Box::new(extract_aggregates(e, aggs))),
+        Expression::IsBlank(e) => Expression::IsBlank(Box::new(extract_aggregates(e, aggs))),
+        Expression::Str(e) => Expression::Str(Box::new(extract_aggregates(e, aggs))),
+        Expression::Lang(e) => Expression::Lang(Box::new(extract_aggregates(e, aggs))),
+        Expression::Datatype(e) => Expression::Datatype(Box::new(extract_aggregates(e, aggs))),
+        Expression::IriFunc(e) => Expression::IriFunc(Box::new(extract_aggregates(e, aggs))),
+        Expression::Uri(e) => Expression::Uri(Box::new(extract_aggregates(e, aggs))),
+        Expression::Bnode(e) => Expression::Bnode(Box::new(extract_aggregates(e, aggs))),
+        Expression::Cast(iri, e) => Expression::Cast(iri.clone(), Box::new(extract_aggregates(e, aggs))),
+        Expression::Regex(text, pattern, flags) => Expression::Regex(
+            Box::new(extract_aggregates(text, aggs)),
+            Box::new(extract_aggregates(pattern, aggs)),
+            flags.as_ref().map(|flags| Box::new(extract_aggregates(flags, aggs))),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn contains_aggregate(expr: &Expression) -> bool {
+    let mut aggs = Vec::new();
+    extract_aggregates(expr, &mut aggs);
+    !aggs.is_empty()
 }
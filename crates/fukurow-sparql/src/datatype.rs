@@ -0,0 +1,196 @@
+//! Typed literal values
+//!
+//! FILTER の比較はリテラルの字句ではなく値空間で行う。xsd:integer / decimal / double /
+//! boolean / dateTime / date / string を解釈し、XSD のキャスト関数 (`xsd:integer(?x)` など) を提供する。
+//! 型なしリテラルは数値として読めれば数値、そうでなければ文字列として扱う (aggregate と同じ規則)
+
+use crate::aggregate::{self, Numeric, XSD};
+use crate::parser::{Iri, Literal, Term};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use std::cmp::Ordering;
+
+/// Value of a literal in its datatype's value space
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiteralValue {
+    Numeric(Numeric),
+    Boolean(bool),
+    /// `xsd:dateTime`; values without a timezone are read as UTC
+    DateTime(DateTime<Utc>),
+    Date(NaiveDate),
+    /// Plain literal or `xsd:string`
+    String(String),
+    LangString { value: String, language: String },
+    /// Literal of a datatype without a supported value space
+    Other { value: String, datatype: Iri },
+}
+
+impl LiteralValue {
+    /// Value of a literal term (`None` for non-literals and ill-typed literals such as `"abc"^^xsd:integer`)
+    pub fn from_term(term: &Term) -> Option<Self> {
+        let literal = match term {
+            Term::Literal(literal) => literal,
+            _ => return None,
+        };
+        if let Some(language) = &literal.language {
+            return Some(LiteralValue::LangString { value: literal.value.clone(), language: language.to_ascii_lowercase() });
+        }
+        let local = match literal.datatype.as_ref() {
+            None => {
+                return Some(Numeric::from_term(term).map(LiteralValue::Numeric)
+                    .unwrap_or_else(|| LiteralValue::String(literal.value.clone())));
+            }
+            Some(datatype) => match datatype.0.strip_prefix(XSD) {
+                Some(local) => local,
+                None => return Some(LiteralValue::Other { value: literal.value.clone(), datatype: datatype.clone() }),
+            },
+        };
+        match local {
+            "string" => Some(LiteralValue::String(literal.value.clone())),
+            "boolean" => parse_boolean(&literal.value).map(LiteralValue::Boolean),
+            "dateTime" | "dateTimeStamp" => parse_date_time(&literal.value).map(LiteralValue::DateTime),
+            "date" => parse_date(&literal.value).map(LiteralValue::Date),
+            _ => match Numeric::from_term(term) {
+                Some(number) => Some(LiteralValue::Numeric(number)),
+                // 数値型なのに数値として読めないリテラルは不正
+                None if is_numeric_type(local) => None,
+                None => Some(LiteralValue::Other { value: literal.value.clone(), datatype: literal.datatype.clone()? }),
+            },
+        }
+    }
+}
+
+/// Order of two terms in the value space (`None` when they are not comparable, a type error in FILTER)
+pub fn compare_terms(left: &Term, right: &Term) -> Option<Ordering> {
+    if let (Term::Iri(a), Term::Iri(b)) = (left, right) {
+        return Some(a.0.cmp(&b.0));
+    }
+    compare_values(&LiteralValue::from_term(left)?, &LiteralValue::from_term(right)?)
+}
+
+pub fn compare_values(left: &LiteralValue, right: &LiteralValue) -> Option<Ordering> {
+    use LiteralValue::*;
+    match (left, right) {
+        (Numeric(a), Numeric(b)) => a.compare(*b),
+        (Boolean(a), Boolean(b)) => Some(a.cmp(b)),
+        (DateTime(a), DateTime(b)) => Some(a.cmp(b)),
+        (Date(a), Date(b)) => Some(a.cmp(b)),
+        // 型なしで格納されたタイムスタンプは型付きの値と比較するときだけ日時として読む
+        (DateTime(a), String(b)) => parse_date_time(b).map(|b| a.cmp(&b)),
+        (String(a), DateTime(b)) => parse_date_time(a).map(|a| a.cmp(b)),
+        (Date(a), String(b)) => parse_date(b).map(|b| a.cmp(&b)),
+        (String(a), Date(b)) => parse_date(a).map(|a| a.cmp(b)),
+        (String(a), String(b)) => Some(a.cmp(b)),
+        (LangString { value: a, language: la }, LangString { value: b, language: lb }) if la == lb => Some(a.cmp(b)),
+        (Other { value: a, datatype: da }, Other { value: b, datatype: db }) if da == db && a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+/// `xsd:T(term)` cast (`None` when the value cannot be cast, a type error)
+pub fn cast(datatype: &Iri, term: &Term) -> Option<Term> {
+    let target = datatype.0.strip_prefix(XSD)?;
+    if target == "string" {
+        return aggregate::string_value(term).map(|value| Term::Literal(Literal { value, datatype: None, language: None }));
+    }
+    let value = LiteralValue::from_term(term)?;
+    let lexical = match (target, value) {
+        (_, LiteralValue::LangString { .. }) => return None,
+        ("boolean", LiteralValue::Boolean(b)) => b.to_string(),
+        ("boolean", LiteralValue::Numeric(n)) => (n.as_f64() != 0.0 && !n.as_f64().is_nan()).to_string(),
+        ("boolean", LiteralValue::String(s)) => parse_boolean(&s)?.to_string(),
+        ("integer", LiteralValue::Numeric(aggregate::Numeric::Integer(i))) => i.to_string(),
+        ("integer", LiteralValue::Numeric(aggregate::Numeric::Decimal(d))) if d.is_finite() => (d.trunc() as i64).to_string(),
+        ("integer", LiteralValue::Boolean(b)) => (b as i64).to_string(),
+        ("integer", LiteralValue::String(s)) => s.trim().parse::<i64>().ok()?.to_string(),
+        ("decimal" | "double" | "float", LiteralValue::Numeric(n)) => n.as_f64().to_string(),
+        ("decimal" | "double" | "float", LiteralValue::Boolean(b)) => (b as i64).to_string(),
+        ("decimal" | "double" | "float", LiteralValue::String(s)) => s.trim().parse::<f64>().ok()?.to_string(),
+        ("dateTime", LiteralValue::DateTime(dt)) => format_date_time(dt),
+        ("dateTime", LiteralValue::String(s)) => format_date_time(parse_date_time(&s)?),
+        ("dateTime", LiteralValue::Date(date)) => format_date_time(date.and_hms_opt(0, 0, 0)?.and_utc()),
+        ("date", LiteralValue::Date(date)) => date.to_string(),
+        ("date", LiteralValue::DateTime(dt)) => dt.date_naive().to_string(),
+        ("date", LiteralValue::String(s)) => parse_date(&s)?.to_string(),
+        _ => return None,
+    };
+    Some(aggregate::typed_literal(lexical, target))
+}
+
+fn is_numeric_type(local: &str) -> bool {
+    Numeric::from_term(&aggregate::typed_literal("0", local)).is_some()
+}
+
+fn parse_boolean(value: &str) -> Option<bool> {
+    match value.trim() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_date_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value).map(|dt| dt.with_timezone(&Utc)).ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok().map(|dt| dt.and_utc()))
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    // タイムゾーン付きの日付 (`2024-01-01Z`) は日付部分だけを使う
+    let date = value.get(..10).unwrap_or(value);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+fn format_date_time(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(value: &str, local: &str) -> Term {
+        aggregate::typed_literal(value, local)
+    }
+
+    fn plain(value: &str) -> Term {
+        Term::Literal(Literal { value: value.to_string(), datatype: None, language: None })
+    }
+
+    #[test]
+    fn test_value_space_comparison() {
+        // 字句順では "9" > "10" だが値では 9 < 10
+        assert_eq!(compare_terms(&typed("9", "integer"), &typed("10", "integer")), Some(Ordering::Less));
+        assert_eq!(compare_terms(&typed("1.50", "decimal"), &typed("1.5", "double")), Some(Ordering::Equal));
+        assert_eq!(compare_terms(&typed("1", "boolean"), &typed("true", "boolean")), Some(Ordering::Equal));
+        assert_eq!(
+            compare_terms(&typed("2024-01-01T09:00:00+09:00", "dateTime"), &typed("2024-01-01T00:00:00Z", "dateTime")),
+            Some(Ordering::Equal),
+        );
+        assert_eq!(compare_terms(&plain("2024-03-01T00:00:00Z"), &typed("2024-01-01T00:00:00", "dateTime")), Some(Ordering::Greater));
+        assert_eq!(compare_terms(&typed("2024-01-02", "date"), &typed("2024-01-10", "date")), Some(Ordering::Less));
+
+        // 型の異なる値・不正なリテラルは比較できない
+        assert_eq!(compare_terms(&typed("true", "boolean"), &typed("1", "integer")), None);
+        assert_eq!(compare_terms(&typed("abc", "integer"), &typed("1", "integer")), None);
+        assert_eq!(LiteralValue::from_term(&typed("abc", "integer")), None);
+    }
+
+    #[test]
+    fn test_casts() {
+        let integer = Iri(format!("{}integer", XSD));
+        assert_eq!(cast(&integer, &plain(" 42 ")), Some(typed("42", "integer")));
+        assert_eq!(cast(&integer, &typed("3.9", "decimal")), Some(typed("3", "integer")));
+        assert_eq!(cast(&integer, &plain("n/a")), None);
+
+        let boolean = Iri(format!("{}boolean", XSD));
+        assert_eq!(cast(&boolean, &typed("0", "integer")), Some(typed("false", "boolean")));
+
+        let date_time = Iri(format!("{}dateTime", XSD));
+        assert_eq!(cast(&date_time, &plain("2024-01-01T09:00:00+09:00")), Some(typed("2024-01-01T00:00:00Z", "dateTime")));
+
+        let string = Iri(format!("{}string", XSD));
+        assert_eq!(cast(&string, &typed("5", "integer")), Some(plain("5")));
+        assert_eq!(cast(&Iri("http://example.org/custom".to_string()), &plain("5")), None);
+    }
+}
@@ -0,0 +1,146 @@
+//! クエリ結果の差分計算
+//!
+//! 同一クエリを過去のスナップショットと現在のストアで実行し、
+//! 追加・削除された行（またはトリプル）を求める
+
+use crate::evaluator::QueryResult;
+use crate::parser::{Bindings, Term};
+use crate::SparqlError;
+use fukurow_core::model::Triple;
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Result row in a stable, serializable form (variable name -> term)
+pub type ResultRow = BTreeMap<String, String>;
+
+/// Difference between two executions of the same query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QueryDiff {
+    Select {
+        variables: Vec<String>,
+        added: Vec<ResultRow>,
+        removed: Vec<ResultRow>,
+        unchanged: usize,
+    },
+    Graph {
+        added: Vec<Triple>,
+        removed: Vec<Triple>,
+        unchanged: usize,
+    },
+    Ask {
+        before: bool,
+        after: bool,
+    },
+}
+
+impl QueryDiff {
+    /// Whether the results differ
+    pub fn has_changes(&self) -> bool {
+        match self {
+            QueryDiff::Select { added, removed, .. } => !added.is_empty() || !removed.is_empty(),
+            QueryDiff::Graph { added, removed, .. } => !added.is_empty() || !removed.is_empty(),
+            QueryDiff::Ask { before, after } => before != after,
+        }
+    }
+}
+
+/// Render a term the way it appears in result rows
+pub fn format_term(term: &Term) -> String {
+    match term {
+        Term::Iri(iri) => iri.0.clone(),
+        Term::Literal(literal) => match (&literal.language, &literal.datatype) {
+            (Some(lang), _) => format!("\"{}\"@{}", literal.value, lang),
+            (None, Some(datatype)) => format!("\"{}\"^^{}", literal.value, datatype.0),
+            (None, None) => literal.value.clone(),
+        },
+        Term::Variable(variable) => format!("?{}", variable.0),
+        Term::BlankNode(id) => format!("_:{}", id),
+        Term::PrefixedName(prefix, local) => format!("{}:{}", prefix, local),
+    }
+}
+
+/// Convert a binding set into a result row
+pub fn bindings_to_row(bindings: &Bindings) -> ResultRow {
+    bindings.iter().map(|(variable, term)| (variable.0.clone(), format_term(term))).collect()
+}
+
+/// Multiset difference: items in `after` not matched in `before` and vice versa
+fn multiset_diff<T: Ord + Clone>(before: Vec<T>, after: Vec<T>) -> (Vec<T>, Vec<T>, usize) {
+    let mut remaining: BTreeMap<T, usize> = BTreeMap::new();
+    for item in before {
+        *remaining.entry(item).or_insert(0) += 1;
+    }
+
+    let mut added = Vec::new();
+    let mut unchanged = 0;
+    for item in after {
+        match remaining.get_mut(&item) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                unchanged += 1;
+            }
+            _ => added.push(item),
+        }
+    }
+
+    let removed = remaining
+        .into_iter()
+        .flat_map(|(item, count)| std::iter::repeat_n(item, count))
+        .collect();
+    (added, removed, unchanged)
+}
+
+/// Compare two results of the same query
+pub fn diff_results(before: &QueryResult, after: &QueryResult) -> Result<QueryDiff, SparqlError> {
+    match (before, after) {
+        (QueryResult::Select { bindings: old, .. }, QueryResult::Select { variables, bindings: new }) => {
+            let (added, removed, unchanged) = multiset_diff(
+                old.iter().map(bindings_to_row).collect(),
+                new.iter().map(bindings_to_row).collect(),
+            );
+            Ok(QueryDiff::Select {
+                variables: variables.iter().map(|v| v.0.clone()).collect(),
+                added,
+                removed,
+                unchanged,
+            })
+        }
+        (QueryResult::Construct { triples: old } | QueryResult::Describe { triples: old },
+         QueryResult::Construct { triples: new } | QueryResult::Describe { triples: new }) => {
+            let key = |t: &Triple| (t.subject.clone(), t.predicate.clone(), t.object.clone());
+            let old: BTreeSet<_> = old.iter().map(key).collect();
+            let new: BTreeSet<_> = new.iter().map(key).collect();
+            let to_triple = |(subject, predicate, object): &(String, String, String)| Triple {
+                subject: subject.clone(),
+                predicate: predicate.clone(),
+                object: object.clone(),
+            };
+            Ok(QueryDiff::Graph {
+                added: new.difference(&old).map(to_triple).collect(),
+                removed: old.difference(&new).map(to_triple).collect(),
+                unchanged: new.intersection(&old).count(),
+            })
+        }
+        (QueryResult::Ask { result: before }, QueryResult::Ask { result: after }) => {
+            Ok(QueryDiff::Ask { before: *before, after: *after })
+        }
+        _ => Err(SparqlError::EvaluationError("Cannot diff results of different query forms".to_string())),
+    }
+}
+
+/// Execute `query` against both stores and diff the results
+pub fn diff_query(query: &str, before: &RdfStore, after: &RdfStore) -> Result<QueryDiff, SparqlError> {
+    let old = crate::execute_query(query, before)?;
+    let new = crate::execute_query(query, after)?;
+    diff_results(&old, &new)
+}
+
+/// Diff `query` between the store's state at `since` (Unix ms) and now
+///
+/// `since` が保持している監査ログより古い場合は [`SparqlError::HistoryError`] を返す
+pub fn diff_since(query: &str, store: &RdfStore, since: u64) -> Result<QueryDiff, SparqlError> {
+    let before = store.snapshot_at(since).map_err(|e| SparqlError::HistoryError(e.to_string()))?;
+    diff_query(query, &before, store)
+}
@@ -1,13 +1,22 @@
 //! SPARQL 実行エンジン
 
+use crate::aggregate::{self, Numeric};
+use crate::datatype;
 use crate::algebra::Algebra;
-use crate::parser::{Bindings, TriplePattern, Term, Variable, VarOrIri, Expression, OrderCondition};
+use crate::federation::Federation;
+use crate::limits::{LimitKind, LimitedResult, QueryBudget, QueryLimits};
+use crate::parser::{Bindings, GraphPattern, TriplePattern, Term, Variable, VarOrIri, Expression, OrderCondition, Literal, Iri};
 use fukurow_store::store::RdfStore;
 use fukurow_core::model::Triple;
+use fukurow_core::prefix::DEFAULT_PREFIXES;
+use fukurow_core::term::{BlankNodeScope, RdfTerm};
 use std::collections::{HashMap, HashSet};
 use itertools::Itertools;
 use crate::SparqlError;
 
+/// クエリ中のブランクノードを束縛する内部変数の接頭辞 (SPARQL の変数名には現れない)
+const BLANK_NODE_VAR_PREFIX: &str = "_:";
+
 /// クエリ結果
 #[derive(Debug, Clone)]
 pub enum QueryResult {
@@ -45,20 +54,53 @@ impl PrefixResolver {
 /// デフォルト実行エンジン
 pub struct DefaultSparqlEvaluator {
     prefix_resolver: Option<PrefixResolver>,
+    federation: Option<Federation>,
+    budget: QueryBudget,
+    /// SELECT solutions to return as `(offset, limit)`
+    page: Option<(usize, usize)>,
 }
 
 impl DefaultSparqlEvaluator {
     pub fn new() -> Self {
         Self {
             prefix_resolver: None,
+            federation: None,
+            budget: QueryBudget::default(),
+            page: None,
         }
     }
 
     pub fn with_prefixes(prefixes: std::collections::HashMap<String, crate::parser::Iri>) -> Self {
         Self {
             prefix_resolver: Some(PrefixResolver::new(prefixes)),
+            federation: None,
+            budget: QueryBudget::default(),
+            page: None,
         }
     }
+
+    /// Evaluate SERVICE blocks at the endpoints `federation` allows
+    pub fn with_federation(mut self, federation: Federation) -> Self {
+        self.federation = Some(federation);
+        self
+    }
+
+    /// Bound the execution time, intermediate solutions and result size of each evaluation
+    ///
+    /// 時間は [`Self::evaluate_planned`] の呼び出しごと (`evaluate` を直接呼ぶ場合はこの時点) から数える
+    pub fn with_limits(mut self, limits: QueryLimits) -> Self {
+        self.budget = QueryBudget::start(limits);
+        self
+    }
+
+    /// Return only the SELECT solutions `offset..offset + limit` in an order stable across calls
+    ///
+    /// ORDER BY があれば LIMIT と同じく押し下げ、上位 `offset + limit` 件だけを並べる。
+    /// 無ければ結果の変数名順に各値の昇順で並べる
+    pub fn with_page(mut self, offset: usize, limit: usize) -> Self {
+        self.page = Some((offset, limit));
+        self
+    }
 }
 
 impl Default for DefaultSparqlEvaluator {
@@ -67,16 +109,52 @@ impl Default for DefaultSparqlEvaluator {
     }
 }
 
-impl SparqlEvaluator for DefaultSparqlEvaluator {
-    fn evaluate_query(&mut self, query: &crate::parser::SparqlQuery, store: &RdfStore) -> Result<QueryResult, crate::SparqlError> {
-        // Set up prefixes (add default prefixes)
-        let mut prefixes = query.prefixes.clone();
-        // Add default RDF prefix if not present
-        if !prefixes.contains_key("rdf") {
-            prefixes.insert("rdf".to_string(), crate::parser::Iri("http://www.w3.org/1999/02/22-rdf-syntax-ns#".to_string()));
+impl DefaultSparqlEvaluator {
+    /// 構築済みの (最適化済みでもよい) 代数でクエリを評価する
+    ///
+    /// `algebra` は `query` の WHERE 句から作られたものでなければならない。
+    /// ASK / CONSTRUCT の結果整形は [`SparqlEvaluator::evaluate_query`] と同じ。
+    /// 部分結果が打ち切られたかどうかは [`Self::evaluate_planned_limited`] で分かる
+    pub fn evaluate_planned(&mut self, query: &crate::parser::SparqlQuery, algebra: &Algebra, store: &RdfStore) -> Result<QueryResult, crate::SparqlError> {
+        self.evaluate_planned_limited(query, algebra, store).map(|limited| limited.result)
+    }
+
+    /// [`Self::evaluate_planned`] enforcing the evaluator's [`QueryLimits`]
+    ///
+    /// 上限に達した場合、`partial_results` なら打ち切った結果と理由を、そうでなければ
+    /// [`crate::SparqlError::LimitExceeded`] を返す
+    pub fn evaluate_planned_limited(&mut self, query: &crate::parser::SparqlQuery, algebra: &Algebra, store: &RdfStore) -> Result<LimitedResult, crate::SparqlError> {
+        let limits = self.budget.limits();
+        self.budget = QueryBudget::start(limits);
+        let mut result = self.evaluate_planned_inner(query, algebra, store)?;
+
+        if let Some(max) = limits.max_results {
+            let truncated = match &mut result {
+                QueryResult::Select { bindings, .. } if bindings.len() > max => {
+                    bindings.truncate(max);
+                    true
+                }
+                QueryResult::Construct { triples } | QueryResult::Describe { triples } if triples.len() > max => {
+                    triples.truncate(max);
+                    true
+                }
+                _ => false,
+            };
+            if truncated {
+                self.budget.trip(LimitKind::Results);
+            }
+        }
+        match self.budget.exceeded() {
+            Some(kind) if !limits.partial_results => Err(crate::SparqlError::LimitExceeded(kind)),
+            truncated => Ok(LimitedResult { result, truncated }),
         }
-        if !prefixes.contains_key("rdfs") {
-            prefixes.insert("rdfs".to_string(), crate::parser::Iri("http://www.w3.org/2000/01/rdf-schema#".to_string()));
+    }
+
+    fn evaluate_planned_inner(&mut self, query: &crate::parser::SparqlQuery, algebra: &Algebra, store: &RdfStore) -> Result<QueryResult, crate::SparqlError> {
+        // Set up prefixes (undeclared default prefixes: rdf, rdfs, owl, sh, xsd)
+        let mut prefixes = query.prefixes.clone();
+        for (prefix, namespace) in DEFAULT_PREFIXES {
+            prefixes.entry(prefix.to_string()).or_insert_with(|| crate::parser::Iri(namespace.to_string()));
         }
 
         println!("DEBUG: Setting up prefixes: {:?}", prefixes);
@@ -85,10 +163,7 @@ impl SparqlEvaluator for DefaultSparqlEvaluator {
         // ASKクエリの特別処理
         if let crate::parser::QueryType::Ask = query.query_type {
             // ASKクエリはWHERE句を評価して結果が空でないかをチェック
-            use crate::algebra::PlanBuilder;
-            let builder = crate::algebra::DefaultPlanBuilder;
-            let algebra = builder.to_algebra(query)?;
-            let result = self.evaluate(&algebra, store)?;
+            let result = self.evaluate(algebra, store)?;
 
             // ASKは結果が空でない場合にtrue
             match result {
@@ -102,10 +177,7 @@ impl SparqlEvaluator for DefaultSparqlEvaluator {
         // CONSTRUCTクエリの特別処理
         if let crate::parser::QueryType::Construct(templates) = &query.query_type {
             // CONSTRUCTクエリはWHERE句を評価し、テンプレートを使って新しいトリプルを構築
-            use crate::algebra::PlanBuilder;
-            let builder = crate::algebra::DefaultPlanBuilder;
-            let algebra = builder.to_algebra(query)?;
-            let result = self.evaluate(&algebra, store)?;
+            let result = self.evaluate(algebra, store)?;
 
             match result {
                 QueryResult::Select { bindings, .. } => {
@@ -114,19 +186,24 @@ impl SparqlEvaluator for DefaultSparqlEvaluator {
                     // 各バインディングに対してテンプレートをインスタンス化
                     for binding in bindings {
                         println!("DEBUG: Processing binding: {:?}", binding);
+                        // テンプレート中のブランクノードは解ごとに新しいノードになる
+                        let mut scope = BlankNodeScope::new();
                         for template in templates {
                             println!("DEBUG: Processing template: {:?}", template);
-                            let subject = self.instantiate_term(&template.subject, &binding, &query.prefixes);
-                            let predicate = self.instantiate_term(&template.predicate, &binding, &query.prefixes);
-                            let object = self.instantiate_term(&template.object, &binding, &query.prefixes);
+                            let subject = self.instantiate_term(&template.subject, &binding, &mut scope);
+                            let predicate = self.instantiate_term(&template.predicate, &binding, &mut scope);
+                            let object = self.instantiate_term(&template.object, &binding, &mut scope);
 
                             println!("DEBUG: Instantiated: s={:?}, p={:?}, o={:?}", subject, predicate, object);
 
                             if let (Some(s), Some(p), Some(o)) = (subject, predicate, object) {
+                                if s.is_literal() || !p.is_iri() {
+                                    continue;
+                                }
                                 constructed_triples.push(fukurow_core::model::Triple {
-                                    subject: s,
-                                    predicate: p,
-                                    object: o,
+                                    subject: encode_node(&s),
+                                    predicate: encode_node(&p),
+                                    object: o.encode(),
                                 });
                             } else {
                                 println!("DEBUG: Some terms could not be instantiated");
@@ -140,65 +217,130 @@ impl SparqlEvaluator for DefaultSparqlEvaluator {
             }
         }
 
+        if let Some((offset, limit)) = self.page {
+            return self.evaluate_page(query, algebra, store, offset, limit);
+        }
+
         // 他のクエリタイプの処理
-        use crate::algebra::PlanBuilder;
-        let builder = crate::algebra::DefaultPlanBuilder;
-        let algebra = builder.to_algebra(query)?;
-        self.evaluate(&algebra, store)
+        self.evaluate(algebra, store)
     }
 
-    fn evaluate(&self, algebra: &Algebra, store: &RdfStore) -> Result<QueryResult, crate::SparqlError> {
+    fn evaluate_page(&self, query: &crate::parser::SparqlQuery, algebra: &Algebra, store: &RdfStore, offset: usize, limit: usize) -> Result<QueryResult, crate::SparqlError> {
+        if query.solution_modifier.order.is_some() {
+            let page = Algebra::Slice { input: Box::new(algebra.clone()), offset: Some(offset as u64), limit: Some(limit as u64) };
+            return self.evaluate(&page, store);
+        }
+
+        let mut result = self.evaluate(algebra, store)?;
+        if let QueryResult::Select { variables, bindings } = &mut result {
+            let conditions: Vec<OrderCondition> = variables.iter().sorted()
+                .map(|var| OrderCondition::Asc(Expression::Variable(var.clone())))
+                .collect();
+            let ordered = self.order_solutions(std::mem::take(bindings), &conditions, store, Some(offset.saturating_add(limit)))?;
+            *bindings = ordered.into_iter().skip(offset).collect();
+        }
+        Ok(result)
+    }
+}
+
+impl DefaultSparqlEvaluator {
+    /// Evaluate `algebra` producing at least the first `limit` solutions
+    ///
+    /// LIMIT は解の順序・個数を変えない演算 (射影) だけを挟んだ BGP まで押し下げ、
+    /// ストアの走査を必要な件数で打ち切る。ORDER BY は全件を評価したうえで上位
+    /// `limit` 件だけを並べる。それ以外の演算は全件を評価する
+    fn evaluate_limited(&self, algebra: &Algebra, store: &RdfStore, limit: Option<usize>) -> Result<QueryResult, crate::SparqlError> {
         match algebra {
-            Algebra::Bgp(triples) => {
-                let bindings = self.evaluate_bgp(triples, store)?;
-                Ok(QueryResult::Select {
-                    variables: self.extract_variables(triples),
-                    bindings,
-                })
-            }
+            Algebra::Bgp(triples) => Ok(QueryResult::Select {
+                variables: self.extract_variables(triples),
+                bindings: self.evaluate_bgp(triples, store, limit)?,
+            }),
             Algebra::Project(inner, vars) => {
-                let mut result = self.evaluate(inner, store)?;
-                if let QueryResult::Select { bindings, .. } = &mut result {
+                let mut result = self.evaluate_limited(inner, store, limit)?;
+                if let QueryResult::Select { variables, bindings } = &mut result {
                     // 投影変数のみ保持
+                    variables.retain(|var| vars.contains(var));
                     for binding in bindings {
-                        let keys: Vec<_> = binding.keys().cloned().collect();
-                        for key in keys {
-                            if !vars.contains(&key) {
-                                binding.remove(&key);
-                            }
-                        }
+                        binding.retain(|key, _| vars.contains(key));
                     }
                 }
                 Ok(result)
             }
-            Algebra::Filter(inner, expr) => {
+            Algebra::OrderBy(inner, order_conditions) => {
                 let mut result = self.evaluate(inner, store)?;
                 if let QueryResult::Select { bindings, .. } = &mut result {
-                    bindings.retain(|binding| self.evaluate_expression(expr, binding));
+                    *bindings = self.order_solutions(std::mem::take(bindings), order_conditions, store, limit)?;
                 }
                 Ok(result)
             }
-            Algebra::Slice { input, offset, limit } => {
-                let mut result = self.evaluate(input, store)?;
+            _ => self.evaluate(algebra, store),
+        }
+    }
+
+    /// Stable-sort `bindings` by `order_conditions`, keeping only the first `limit` when given
+    ///
+    /// 条件ごとの値を先に評価し、`limit` があれば上位の解を選び出してからその分だけ並べる。
+    /// 同順位は評価順で並ぶ
+    fn order_solutions(&self, bindings: Vec<Bindings>, order_conditions: &[OrderCondition], store: &RdfStore, limit: Option<usize>) -> Result<Vec<Bindings>, crate::SparqlError> {
+        let mut keyed = Vec::with_capacity(bindings.len());
+        for (position, binding) in bindings.into_iter().enumerate() {
+            let keys = order_conditions.iter()
+                .map(|condition| self.order_key(condition, &binding, store))
+                .collect::<Result<Vec<_>, _>>()?;
+            keyed.push((keys, position, binding));
+        }
+        let compare = |(left, left_position, _): &(Vec<Option<Term>>, usize, Bindings), (right, right_position, _): &(Vec<Option<Term>>, usize, Bindings)| {
+            order_conditions.iter().zip(left.iter().zip(right))
+                .map(|(condition, (left, right))| compare_order_keys(condition, left, right))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| left_position.cmp(right_position))
+        };
+        if let Some(limit) = limit.filter(|limit| *limit < keyed.len()) {
+            if limit == 0 {
+                return Ok(Vec::new());
+            }
+            keyed.select_nth_unstable_by(limit - 1, compare);
+            keyed.truncate(limit);
+        }
+        keyed.sort_unstable_by(compare);
+        Ok(keyed.into_iter().map(|(_, _, binding)| binding).collect())
+    }
+}
+
+impl SparqlEvaluator for DefaultSparqlEvaluator {
+    fn evaluate_query(&mut self, query: &crate::parser::SparqlQuery, store: &RdfStore) -> Result<QueryResult, crate::SparqlError> {
+        use crate::algebra::PlanBuilder;
+        let algebra = crate::algebra::DefaultPlanBuilder.to_algebra(query)?;
+        self.evaluate_planned(query, &algebra, store)
+    }
+
+    fn evaluate(&self, algebra: &Algebra, store: &RdfStore) -> Result<QueryResult, crate::SparqlError> {
+        self.budget.check()?;
+        match algebra {
+            Algebra::Bgp(_) | Algebra::Project(..) | Algebra::OrderBy(..) => self.evaluate_limited(algebra, store, None),
+            Algebra::Filter(inner, expr) => {
+                let mut result = self.evaluate(inner, store)?;
                 if let QueryResult::Select { bindings, .. } = &mut result {
-                    let start = offset.unwrap_or(0) as usize;
-                    let end = start + limit.unwrap_or(bindings.len() as u64) as usize;
-                    *bindings = bindings[start..end.min(bindings.len())].to_vec();
+                    let mut kept = Vec::with_capacity(bindings.len());
+                    for binding in bindings.drain(..) {
+                        if self.budget.exhausted() {
+                            break;
+                        }
+                        if self.evaluate_expression(expr, &binding, store)? {
+                            kept.push(binding);
+                        }
+                    }
+                    *bindings = kept;
                 }
                 Ok(result)
             }
-            Algebra::OrderBy(inner, order_conditions) => {
-                let mut result = self.evaluate(inner, store)?;
+            Algebra::Slice { input, offset, limit } => {
+                let start = offset.unwrap_or(0) as usize;
+                let needed = limit.map(|limit| start.saturating_add(limit as usize));
+                let mut result = self.evaluate_limited(input, store, needed)?;
                 if let QueryResult::Select { bindings, .. } = &mut result {
-                    bindings.sort_by(|a, b| {
-                        for condition in order_conditions {
-                            let cmp = self.compare_bindings(a, b, condition);
-                            if cmp != std::cmp::Ordering::Equal {
-                                return cmp;
-                            }
-                        }
-                        std::cmp::Ordering::Equal
-                    });
+                    let end = needed.unwrap_or(bindings.len()).min(bindings.len());
+                    *bindings = bindings[start.min(end)..end].to_vec();
                 }
                 Ok(result)
             }
@@ -228,68 +370,100 @@ impl SparqlEvaluator for DefaultSparqlEvaluator {
                     _ => Err(SparqlError::EvaluationError("UNION only supported for SELECT results".to_string())),
                 }
             }
+            // 右側の SERVICE には左側の解を渡し、エンドポイント側で絞り込ませる
+            Algebra::Join(left, right) if matches!(right.as_ref(), Algebra::Service(..)) => {
+                let Algebra::Service(endpoint, inner, silent) = right.as_ref() else { unreachable!() };
+                match self.evaluate(left, store)? {
+                    QueryResult::Select { variables: left_vars, bindings: left_bindings } => {
+                        if left_bindings.is_empty() {
+                            return Ok(QueryResult::Select { variables: left_vars, bindings: left_bindings });
+                        }
+                        let (service_vars, service_bindings) = self.evaluate_service(endpoint, inner, *silent, &left_bindings)?;
+                        Ok(QueryResult::Select {
+                            variables: union_variables(left_vars, service_vars),
+                            bindings: self.join_bindings(left_bindings, service_bindings),
+                        })
+                    }
+                    _ => Err(SparqlError::EvaluationError("JOIN only supported for SELECT results".to_string())),
+                }
+            }
+            Algebra::Service(endpoint, inner, silent) => {
+                let (variables, bindings) = self.evaluate_service(endpoint, inner, *silent, &[])?;
+                Ok(QueryResult::Select { variables, bindings })
+            }
+            Algebra::Join(left, right) => {
+                match (self.evaluate(left, store)?, self.evaluate(right, store)?) {
+                    (QueryResult::Select { variables: left_vars, bindings: left_bindings },
+                     QueryResult::Select { variables: right_vars, bindings: right_bindings }) => {
+                        Ok(QueryResult::Select {
+                            variables: union_variables(left_vars, right_vars),
+                            bindings: self.join_bindings(left_bindings, right_bindings),
+                        })
+                    }
+                    _ => Err(SparqlError::EvaluationError("JOIN only supported for SELECT results".to_string())),
+                }
+            }
             Algebra::LeftJoin { left, right, expr } => {
-                let left_result = self.evaluate(left, store)?;
-                let right_result = self.evaluate(right, store)?;
-
-                match (left_result, right_result) {
-                    (QueryResult::Select { variables: left_vars, bindings: mut left_bindings },
+                match (self.evaluate(left, store)?, self.evaluate(right, store)?) {
+                    (QueryResult::Select { variables: left_vars, bindings: left_bindings },
                      QueryResult::Select { variables: right_vars, bindings: right_bindings }) => {
-                        // For LEFT JOIN (OPTIONAL), extend left bindings with matching right bindings
-                        for left_binding in &mut left_bindings {
+                        // 互換な右側の解すべてで拡張し、条件を満たす拡張が 1 つもなければ左側の解をそのまま残す
+                        let mut bindings = Vec::new();
+                        for left_binding in left_bindings {
+                            if self.budget.exhausted() {
+                                break;
+                            }
                             let mut extended = false;
                             for right_binding in &right_bindings {
-                                // Check if bindings are compatible (same values for common variables)
-                                let compatible = left_vars.iter().all(|var| {
-                                    if right_vars.contains(var) {
-                                        left_binding.get(var) == right_binding.get(var)
-                                    } else {
-                                        true
-                                    }
-                                });
-
-                                if compatible {
-                                    // Extend left binding with right binding
-                                    for (var, value) in right_binding {
-                                        left_binding.insert(var.clone(), value.clone());
-                                    }
+                                if !self.bindings_compatible(&left_binding, right_binding) {
+                                    continue;
+                                }
+                                let merged = self.merge_bindings(&left_binding, right_binding);
+                                let accepted = match expr {
+                                    Some(expr) => self.evaluate_expression(expr, &merged, store)?,
+                                    None => true,
+                                };
+                                if accepted {
+                                    self.budget.charge(1);
+                                    bindings.push(merged);
                                     extended = true;
-                                    break; // For now, take first match
                                 }
                             }
-
                             if !extended {
-                                // No match found, add unbound variables from right
-                                for var in &right_vars {
-                                    if !left_binding.contains_key(var) {
-                                        // Leave unbound (not in binding)
-                                    }
-                                }
-                            }
-                        }
-
-                        // Combine variables
-                        let mut all_vars = left_vars;
-                        for var in right_vars {
-                            if !all_vars.contains(&var) {
-                                all_vars.push(var);
+                                bindings.push(left_binding);
                             }
                         }
 
                         Ok(QueryResult::Select {
-                            variables: all_vars,
-                            bindings: left_bindings,
+                            variables: union_variables(left_vars, right_vars),
+                            bindings,
                         })
                     }
                     _ => Err(SparqlError::EvaluationError("LEFT JOIN only supported for SELECT results".to_string())),
                 }
             }
+            Algebra::Minus(left, right) => {
+                match (self.evaluate(left, store)?, self.evaluate(right, store)?) {
+                    (QueryResult::Select { variables, bindings: mut left_bindings },
+                     QueryResult::Select { bindings: right_bindings, .. }) => {
+                        // 共有する変数がない解同士は互換でも取り除かない (MINUS の定義)
+                        left_bindings.retain(|left_binding| {
+                            !right_bindings.iter().any(|right_binding| {
+                                left_binding.keys().any(|var| right_binding.contains_key(var))
+                                    && self.bindings_compatible(left_binding, right_binding)
+                            })
+                        });
+                        Ok(QueryResult::Select { variables, bindings: left_bindings })
+                    }
+                    _ => Err(SparqlError::EvaluationError("MINUS only supported for SELECT results".to_string())),
+                }
+            }
             Algebra::Distinct(inner) => {
                 let mut result = self.evaluate(inner, store)?;
                 if let QueryResult::Select { bindings, .. } = &mut result {
                     let mut seen = Vec::new();
                     bindings.retain(|binding| {
-                        if seen.contains(binding) {
+                        if self.budget.exhausted() || seen.contains(binding) {
                             false
                         } else {
                             seen.push(binding.clone());
@@ -303,6 +477,112 @@ impl SparqlEvaluator for DefaultSparqlEvaluator {
                 // REDUCED は DISTINCT と同様に扱う（実装簡略化）
                 self.evaluate(&Algebra::Distinct(inner.clone()), store)
             }
+            Algebra::Graph(VarOrIri::Iri(iri), inner) => {
+                self.evaluate(inner, &crate::provenance::graph_view(store, &iri.0))
+            }
+            Algebra::Graph(VarOrIri::Var(var), inner) => {
+                // 名前付きグラフと各トリプルの出所 (仮想グラフ) ごとに評価し、?g にその IRI を束縛する
+                let mut variables = vec![var.clone()];
+                let mut bindings = Vec::new();
+                for name in crate::provenance::graph_names(store) {
+                    if self.budget.exhausted() {
+                        break;
+                    }
+                    let graph_term = Term::Iri(crate::parser::Iri(name.clone()));
+                    match self.evaluate(inner, &crate::provenance::graph_view(store, &name))? {
+                        QueryResult::Select { variables: inner_vars, bindings: inner_bindings } => {
+                            variables = union_variables(variables, inner_vars);
+                            for mut binding in inner_bindings {
+                                match binding.get(var) {
+                                    Some(bound) if *bound != graph_term => continue,
+                                    _ => {
+                                        self.budget.charge(1);
+                                        binding.insert(var.clone(), graph_term.clone());
+                                        bindings.push(binding);
+                                    }
+                                }
+                            }
+                        }
+                        _ => return Err(SparqlError::EvaluationError("GRAPH only supported for SELECT results".to_string())),
+                    }
+                }
+                Ok(QueryResult::Select { variables, bindings })
+            }
+            Algebra::Extend(inner, var, expr) => {
+                let mut result = self.evaluate(inner, store)?;
+                if let QueryResult::Select { variables, bindings } = &mut result {
+                    // 評価エラー (未束縛・型不一致) の解は変数を束縛せずに残す
+                    for binding in bindings.iter_mut() {
+                        if let Some(value) = self.expression_value(expr, binding, store)? {
+                            binding.insert(var.clone(), value);
+                        }
+                    }
+                    if !variables.contains(var) {
+                        variables.push(var.clone());
+                    }
+                }
+                Ok(result)
+            }
+            Algebra::Group { input, keys, aggs } => {
+                let bindings = match self.evaluate(input, store)? {
+                    QueryResult::Select { bindings, .. } => bindings,
+                    _ => return Err(SparqlError::EvaluationError("GROUP BY only supported for SELECT results".to_string())),
+                };
+
+                // キーの値ごとに解をまとめる (グループの順序は最初に現れた順)
+                let mut index: HashMap<Vec<Option<Term>>, usize> = HashMap::new();
+                let mut groups: Vec<(Vec<Option<Term>>, Vec<Bindings>)> = Vec::new();
+                for binding in bindings {
+                    if self.budget.exhausted() {
+                        break;
+                    }
+                    let key = keys.iter()
+                        .map(|key| self.expression_value(key, &binding, store))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match index.get(&key) {
+                        Some(&position) => groups[position].1.push(binding),
+                        None => {
+                            index.insert(key.clone(), groups.len());
+                            groups.push((key, vec![binding]));
+                        }
+                    }
+                }
+                // GROUP BY のない集約は解が 0 件でも 1 グループ (COUNT(*) = 0)
+                if groups.is_empty() && keys.is_empty() {
+                    groups.push((Vec::new(), Vec::new()));
+                }
+
+                let mut variables: Vec<Variable> = keys.iter()
+                    .filter_map(|key| match key {
+                        Expression::Variable(var) => Some(var.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                variables.extend(aggs.iter().map(|(var, _)| var.clone()));
+
+                let mut grouped = Vec::with_capacity(groups.len());
+                for (key, members) in groups {
+                    let mut binding = Bindings::new();
+                    for (key_expr, value) in keys.iter().zip(key) {
+                        if let (Expression::Variable(var), Some(value)) = (key_expr, value) {
+                            binding.insert(var.clone(), value);
+                        }
+                    }
+                    for (var, agg) in aggs {
+                        let values = match agg.argument() {
+                            Some(argument) => members.iter()
+                                .map(|member| self.expression_value(argument, member, store))
+                                .collect::<Result<Vec<_>, _>>()?,
+                            None => Vec::new(),
+                        };
+                        if let Some(value) = aggregate::aggregate(agg, values, members.len()) {
+                            binding.insert(var.clone(), value);
+                        }
+                    }
+                    grouped.push(binding);
+                }
+                Ok(QueryResult::Select { variables, bindings: grouped })
+            }
             // TODO: 他の代数演算子の実装
             _ => Err(SparqlError::UnsupportedFeature("Algebra operator not implemented".to_string())),
         }
@@ -310,32 +590,76 @@ impl SparqlEvaluator for DefaultSparqlEvaluator {
 }
 
 impl DefaultSparqlEvaluator {
-    fn evaluate_bgp(&self, triples: &[TriplePattern], store: &RdfStore) -> Result<Vec<Bindings>, crate::SparqlError> {
+    /// Solutions of a SERVICE block
+    ///
+    /// SILENT の場合、失敗した呼び出し (許可リスト外・タイムアウト・不正な応答) は空の解 1 つになる
+    fn evaluate_service(&self, endpoint: &VarOrIri, inner: &Algebra, silent: bool, bound: &[Bindings]) -> Result<(Vec<Variable>, Vec<Bindings>), crate::SparqlError> {
+        let no_prefixes = HashMap::new();
+        let prefixes = self.prefix_resolver.as_ref().map_or(&no_prefixes, |resolver| &resolver.prefixes);
+        let outcome = match (&self.federation, endpoint) {
+            (None, _) => Err(SparqlError::UnsupportedFeature("SERVICE requires a federation configuration".to_string())),
+            (Some(_), VarOrIri::Var(var)) => Err(SparqlError::UnsupportedFeature(format!("SERVICE with a variable endpoint (?{})", var.0))),
+            (Some(federation), VarOrIri::Iri(iri)) => federation.call(&iri.0, inner, bound, prefixes),
+        };
+        match outcome {
+            Ok(results) => Ok((results.variables, results.bindings)),
+            Err(_) if silent => Ok((Vec::new(), vec![Bindings::new()])),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn evaluate_bgp(&self, triples: &[TriplePattern], store: &RdfStore, limit: Option<usize>) -> Result<Vec<Bindings>, crate::SparqlError> {
+        let limit = limit.unwrap_or(usize::MAX);
         if triples.is_empty() {
-            return Ok(vec![HashMap::new()]);
+            return Ok(std::iter::once(HashMap::new()).take(limit).collect());
         }
 
-        // 最初のトリプルを評価
-        let mut results = self.evaluate_triple_pattern(&triples[0], store)?;
-        println!("DEBUG: evaluate_bgp initial results: {:?}", results);
+        // 2 つ目以降のパターンは先に評価し、最初のパターンの解をストアから 1 件ずつ取り出して結合する。
+        // 結合順は左から順に結合した場合と同じで、必要な件数が揃ったら走査を止める
+        let rest: Vec<Vec<Bindings>> = triples[1..].iter()
+            .map(|triple| self.triple_pattern_matches(triple, store).collect())
+            .collect();
+
+        let mut results = Vec::new();
+        for binding in self.triple_pattern_matches(&triples[0], store) {
+            if results.len() >= limit || self.budget.exhausted() {
+                break;
+            }
+            self.extend_binding(binding, &rest, &mut results, limit);
+        }
 
-        // 残りのトリプルを結合
-        for triple in &triples[1..] {
-            let next_results = self.evaluate_triple_pattern(triple, store)?;
-            println!("DEBUG: evaluate_bgp next_results: {:?}", next_results);
-            results = self.join_bindings(results, next_results);
-            println!("DEBUG: evaluate_bgp after join: {:?}", results);
+        // ブランクノードの内部変数は BGP の外には見せない
+        for binding in &mut results {
+            binding.retain(|var, _| !var.0.starts_with(BLANK_NODE_VAR_PREFIX));
         }
 
-        println!("DEBUG: evaluate_bgp final results: {:?}", results);
         Ok(results)
     }
 
-    fn evaluate_triple_pattern(&self, pattern: &TriplePattern, store: &RdfStore) -> Result<Vec<Bindings>, crate::SparqlError> {
-        let mut results = Vec::new();
+    /// Join `binding` with every compatible combination of `rest`, stopping at `limit` results
+    fn extend_binding(&self, binding: Bindings, rest: &[Vec<Bindings>], results: &mut Vec<Bindings>, limit: usize) {
+        let Some((next, rest)) = rest.split_first() else {
+            self.budget.charge(1);
+            results.push(binding);
+            return;
+        };
+        for right in next {
+            if results.len() >= limit || self.budget.exhausted() {
+                return;
+            }
+            if self.bindings_compatible(&binding, right) {
+                let mut joined = binding.clone();
+                joined.extend(right.clone());
+                self.extend_binding(joined, rest, results, limit);
+            }
+        }
+    }
 
-        // ストアから全てのトリプルを検索
-        for stored_triple in store.all_triples().values().flatten() {
+    /// Solutions of one triple pattern, read lazily from the store
+    ///
+    /// 上限に達したら走査をやめる
+    fn triple_pattern_matches<'a>(&'a self, pattern: &'a TriplePattern, store: &'a RdfStore) -> impl Iterator<Item = Bindings> + 'a {
+        store.find_triples_iter(None, None, None).take_while(move |_| !self.budget.exhausted()).filter_map(move |stored_triple| {
             let triple = &stored_triple.triple;
 
             // パターンマッチング
@@ -343,65 +667,56 @@ impl DefaultSparqlEvaluator {
             let predicate_match = self.term_matches(&pattern.predicate, &triple.predicate);
             let object_match = self.term_matches(&pattern.object, &triple.object);
 
-            println!("DEBUG: Matching triple: s={:?}, p={:?}, o={:?}", triple.subject, triple.predicate, triple.object);
-            println!("DEBUG: Pattern: s={:?}, p={:?}, o={:?}", pattern.subject, pattern.predicate, pattern.object);
-            println!("DEBUG: Matches: s={}, p={}, o={}", subject_match, predicate_match, object_match);
-
-            if subject_match && predicate_match && object_match {
 
-                let mut binding = HashMap::new();
+            if !(subject_match && predicate_match && object_match) {
+                return None;
+            }
 
-                // 変数を束縛
-                println!("DEBUG: Before binding: {:?}", binding);
-                self.bind_term(&pattern.subject, &triple.subject, &mut binding);
-                self.bind_term(&pattern.predicate, &triple.predicate, &mut binding);
-                self.bind_term(&pattern.object, &triple.object, &mut binding);
-                println!("DEBUG: After binding: {:?}", binding);
+            // 変数を束縛 (同じ変数が複数回現れる場合は同じ値でなければならない)
+            let mut binding = HashMap::new();
+            let consistent = self.bind_term(&pattern.subject, || triple.subject_term(), &mut binding)
+                && self.bind_term(&pattern.predicate, || triple.predicate_term(), &mut binding)
+                && self.bind_term(&pattern.object, || triple.object_term(), &mut binding);
 
-                results.push(binding);
+            if consistent {
+                self.budget.charge(1);
             }
-        }
-
-        Ok(results)
+            consistent.then_some(binding)
+        })
     }
 
     fn term_matches(&self, pattern: &Term, term: &str) -> bool {
         match pattern {
             Term::Variable(_) => true, // 変数は常にマッチ
             Term::Iri(pattern_iri) => {
-                &pattern_iri.0 == term
+                pattern_iri.0 == term || RdfTerm::parse(term).as_iri() == Some(pattern_iri.0.as_str())
             }
             Term::Literal(pattern_lit) => {
-                &pattern_lit.value == term
+                // データ型・言語タグを含めて比較する。素の文字列のリテラルは格納値との完全一致も許す
+                let plain = pattern_lit.datatype.is_none() && pattern_lit.language.is_none();
+                (plain && pattern_lit.value == term)
+                    || pattern.to_rdf_term().map(|literal| RdfTerm::parse(term) == literal).unwrap_or(false)
             }
-            Term::BlankNode(_) => true, // TODO: ブランクノード比較
+            // クエリ中のブランクノードは変数として扱う (束縛は bind_term)
+            Term::BlankNode(_) => true,
             Term::PrefixedName(prefix, local) => {
-                println!("DEBUG: Resolving prefixed name: {}:{}", prefix, local);
                 if let Some(resolver) = &self.prefix_resolver {
                     if let Some(resolved) = resolver.resolve(prefix, local) {
-                        println!("DEBUG: Resolved to: {}", resolved);
                         resolved == term
                     } else {
-                        println!("DEBUG: Resolver failed to resolve");
                         false
                     }
                 } else {
                     // フォールバック: 簡易実装
-                    println!("DEBUG: Using fallback resolution");
                     if prefix == "ex" {
                         let resolved = format!("http://example.org/{}", local);
-                        println!("DEBUG: ex:{} -> {}", local, resolved);
                         resolved == term
                     } else if prefix == "rdf" && local == "type" {
-                        let result = term == "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
-                        println!("DEBUG: rdf:type check: {} == {} -> {}", term, "http://www.w3.org/1999/02/22-rdf-syntax-ns#type", result);
-                        result
+                        term == "http://www.w3.org/1999/02/22-rdf-syntax-ns#type"
                     } else if prefix == "foaf" {
                         let resolved = format!("http://xmlns.com/foaf/0.1/{}", local);
-                        println!("DEBUG: foaf:{} -> {}", local, resolved);
                         resolved == term
                     } else {
-                        println!("DEBUG: Unknown prefix: {}", prefix);
                         false
                     }
                 }
@@ -409,45 +724,40 @@ impl DefaultSparqlEvaluator {
         }
     }
 
-    fn bind_term(&self, pattern: &Term, term: &str, binding: &mut Bindings) {
-        if let Term::Variable(var) = pattern {
-            // String を crate::parser::Term に変換
-            // TODO: IRI, Literal, BlankNode の区別を実装
-            let term_value = Term::Iri(crate::parser::Iri(term.to_string()));
-            binding.insert(var.clone(), term_value);
+    /// Bind a variable (or query blank node) to the stored term; `false` on a conflicting binding
+    fn bind_term(&self, pattern: &Term, term: impl FnOnce() -> RdfTerm, binding: &mut Bindings) -> bool {
+        let var = match pattern {
+            Term::Variable(var) => var.clone(),
+            Term::BlankNode(label) => Variable(format!("{}{}", BLANK_NODE_VAR_PREFIX, label)),
+            _ => return true,
+        };
+        let term_value = Term::from_rdf_term(term());
+        match binding.get(&var) {
+            Some(bound) => *bound == term_value,
+            None => {
+                binding.insert(var, term_value);
+                true
+            }
         }
     }
 
-    fn resolve_prefixed_name(&self, prefix: &str, local: &str, prefixes: &std::collections::HashMap<String, crate::parser::Iri>) -> Option<String> {
-        prefixes.get(prefix).map(|iri| format!("{}{}", iri.0, local))
-    }
-
-    fn instantiate_term(&self, term: &Term, binding: &Bindings, prefixes: &std::collections::HashMap<String, crate::parser::Iri>) -> Option<String> {
+    fn instantiate_term(&self, term: &Term, binding: &Bindings, scope: &mut BlankNodeScope) -> Option<RdfTerm> {
         match term {
-            Term::Variable(var) => {
-                // バインディングから値を取得
-                binding.get(var).and_then(|bound_term| {
-                    match bound_term {
-                        Term::Iri(iri) => Some(iri.0.clone()),
-                        Term::Literal(lit) => Some(lit.value.clone()),
-                        _ => None,
-                    }
-                })
-            }
-            Term::Iri(iri) => Some(iri.0.clone()),
-            Term::Literal(lit) => Some(lit.value.clone()),
+            // バインディングから値を取得
+            Term::Variable(var) => binding.get(var).and_then(Term::to_rdf_term),
+            Term::Iri(_) | Term::Literal(_) => term.to_rdf_term(),
             Term::PrefixedName(prefix, local) => {
                 println!("DEBUG: instantiate_term resolving {}:{}", prefix, local);
                 if let Some(resolver) = &self.prefix_resolver {
                     let result = resolver.resolve(prefix, local);
                     println!("DEBUG: instantiate_term resolved to {:?}", result);
-                    result
+                    result.map(RdfTerm::Iri)
                 } else {
                     println!("DEBUG: instantiate_term no resolver");
                     None
                 }
             }
-            Term::BlankNode(_) => None, // Blank nodes not supported in CONSTRUCT for now
+            Term::BlankNode(label) => Some(RdfTerm::BlankNode(scope.relabel(label))),
         }
     }
 
@@ -456,8 +766,12 @@ impl DefaultSparqlEvaluator {
         let mut results = Vec::new();
 
         for left_binding in &left {
+            if self.budget.exhausted() {
+                break;
+            }
             for right_binding in &right {
                 if self.bindings_compatible(left_binding, right_binding) {
+                    self.budget.charge(1);
                     let mut joined = left_binding.clone();
                     joined.extend(right_binding.clone());
                     println!("DEBUG: joined: {:?}", joined);
@@ -487,49 +801,134 @@ impl DefaultSparqlEvaluator {
         merged
     }
 
-    fn evaluate_expression(&self, expr: &Expression, binding: &Bindings) -> bool {
-        match expr {
+    fn evaluate_expression(&self, expr: &Expression, binding: &Bindings, store: &RdfStore) -> Result<bool, crate::SparqlError> {
+        Ok(match expr {
             Expression::Variable(var) => binding.contains_key(var),
             Expression::Bound(var) => binding.contains_key(var),
-            Expression::Not(inner) => !self.evaluate_expression(inner, binding),
+            Expression::Not(inner) => !self.evaluate_expression(inner, binding, store)?,
             Expression::And(left, right) => {
-                self.evaluate_expression(left, binding) && self.evaluate_expression(right, binding)
+                self.evaluate_expression(left, binding, store)? && self.evaluate_expression(right, binding, store)?
             }
             Expression::Or(left, right) => {
-                self.evaluate_expression(left, binding) || self.evaluate_expression(right, binding)
-            }
-            Expression::Equal(left, right) => {
-                self.compare_expressions(left, right, binding) == std::cmp::Ordering::Equal
+                self.evaluate_expression(left, binding, store)? || self.evaluate_expression(right, binding, store)?
             }
-            // TODO: 他の式評価の実装
-            _ => true, // デフォルトでtrue
-        }
+            Expression::Exists(pattern) => self.pattern_exists(pattern, binding, store)?,
+            Expression::NotExists(pattern) => !self.pattern_exists(pattern, binding, store)?,
+            // 値の有効ブール値 (評価エラーは false)
+            _ => self.expression_value(expr, binding, store)?
+                .as_ref()
+                .and_then(effective_boolean_value)
+                .unwrap_or(false),
+        })
     }
 
-    fn compare_expressions(&self, left: &Expression, right: &Expression, binding: &Bindings) -> std::cmp::Ordering {
-        // 簡易的な比較（TODO: 完全実装）
-        match (left, right) {
-            (Expression::Variable(var1), Expression::Variable(var2)) => {
-                match (binding.get(var1), binding.get(var2)) {
-                    (Some(term1), Some(term2)) => self.compare_terms(term1, term2),
-                    _ => std::cmp::Ordering::Equal,
+    /// Value of an expression for one solution (`None` for unbound variables and type errors)
+    fn expression_value(&self, expr: &Expression, binding: &Bindings, store: &RdfStore) -> Result<Option<Term>, crate::SparqlError> {
+        let value = |inner: &Expression| self.expression_value(inner, binding, store);
+        let numeric = |left: &Expression, right: &Expression| -> Result<Option<(Numeric, Numeric)>, crate::SparqlError> {
+            Ok(match (value(left)?, value(right)?) {
+                (Some(l), Some(r)) => Numeric::from_term(&l).zip(Numeric::from_term(&r)),
+                _ => None,
+            })
+        };
+        let compare = |left: &Expression, right: &Expression, accept: fn(std::cmp::Ordering) -> bool| -> Result<Option<Term>, crate::SparqlError> {
+            Ok(match (value(left)?, value(right)?) {
+                (Some(l), Some(r)) => aggregate::compare_values(&l, &r).map(|ordering| aggregate::boolean_term(accept(ordering))),
+                _ => None,
+            })
+        };
+
+        Ok(match expr {
+            Expression::Variable(var) => binding.get(var).cloned(),
+            Expression::Iri(iri) => Some(Term::Iri(iri.clone())),
+            Expression::Literal(literal) => Some(Term::Literal(literal.clone())),
+            Expression::Add(l, r) => numeric(l, r)?.and_then(|(a, b)| a.checked_add(b)).map(Numeric::to_term),
+            Expression::Subtract(l, r) => numeric(l, r)?.and_then(|(a, b)| a.checked_sub(b)).map(Numeric::to_term),
+            Expression::Multiply(l, r) => numeric(l, r)?.and_then(|(a, b)| a.checked_mul(b)).map(Numeric::to_term),
+            Expression::Divide(l, r) => numeric(l, r)?.and_then(|(a, b)| a.checked_div(b)).map(Numeric::to_term),
+            // 比較できない項同士の等価性は項の同一性で判定する
+            Expression::Equal(l, r) | Expression::NotEqual(l, r) => match (value(l)?, value(r)?) {
+                (Some(a), Some(b)) => {
+                    let equal = match aggregate::compare_values(&a, &b) {
+                        Some(ordering) => ordering == std::cmp::Ordering::Equal,
+                        None => a == b,
+                    };
+                    Some(aggregate::boolean_term(equal == matches!(expr, Expression::Equal(..))))
                 }
+                _ => None,
+            },
+            Expression::LessThan(l, r) => compare(l, r, std::cmp::Ordering::is_lt)?,
+            Expression::LessThanOrEqual(l, r) => compare(l, r, std::cmp::Ordering::is_le)?,
+            Expression::GreaterThan(l, r) => compare(l, r, std::cmp::Ordering::is_gt)?,
+            Expression::GreaterThanOrEqual(l, r) => compare(l, r, std::cmp::Ordering::is_ge)?,
+            Expression::And(..) | Expression::Or(..) | Expression::Not(..) | Expression::Bound(..)
+            | Expression::Exists(..) | Expression::NotExists(..) => {
+                Some(aggregate::boolean_term(self.evaluate_expression(expr, binding, store)?))
             }
-            _ => std::cmp::Ordering::Equal,
-        }
+            Expression::IsIri(inner) => value(inner)?.map(|term| aggregate::boolean_term(matches!(term, Term::Iri(_)))),
+            Expression::IsLiteral(inner) => value(inner)?.map(|term| aggregate::boolean_term(matches!(term, Term::Literal(_)))),
+            Expression::IsBlank(inner) => value(inner)?.map(|term| aggregate::boolean_term(matches!(term, Term::BlankNode(_)))),
+            Expression::Str(inner) => value(inner)?.as_ref().and_then(aggregate::string_value).map(plain_literal),
+            Expression::Lang(inner) => match value(inner)? {
+                Some(Term::Literal(literal)) => Some(plain_literal(literal.language.unwrap_or_default())),
+                _ => None,
+            },
+            Expression::Datatype(inner) => match value(inner)? {
+                Some(Term::Literal(literal)) => Some(Term::Iri(match (literal.datatype, literal.language) {
+                    (Some(datatype), _) => datatype,
+                    (None, Some(_)) => Iri("http://www.w3.org/1999/02/22-rdf-syntax-ns#langString".to_string()),
+                    (None, None) => Iri(format!("{}string", aggregate::XSD)),
+                })),
+                _ => None,
+            },
+            Expression::IriFunc(inner) | Expression::Uri(inner) => match value(inner)? {
+                Some(Term::Iri(iri)) => Some(Term::Iri(iri)),
+                Some(Term::Literal(literal)) if literal.datatype.is_none() && literal.language.is_none() => Some(Term::Iri(Iri(literal.value))),
+                _ => None,
+            },
+            Expression::Bnode(inner) => value(inner)?.as_ref().and_then(aggregate::string_value).map(Term::BlankNode),
+            Expression::Cast(datatype, inner) => value(inner)?.and_then(|term| datatype::cast(datatype, &term)),
+            Expression::Regex(text, pattern, flags) => {
+                let text = value(text)?.as_ref().and_then(aggregate::string_value);
+                let pattern = value(pattern)?.as_ref().and_then(aggregate::string_value);
+                let flags = match flags {
+                    Some(flags) => value(flags)?.as_ref().and_then(aggregate::string_value),
+                    None => Some(String::new()),
+                };
+                match (text, pattern, flags) {
+                    (Some(text), Some(pattern), Some(flags)) => regex::RegexBuilder::new(&pattern)
+                        .case_insensitive(flags.contains('i'))
+                        .multi_line(flags.contains('m'))
+                        .dot_matches_new_line(flags.contains('s'))
+                        .build()
+                        .ok()
+                        .map(|regex| aggregate::boolean_term(regex.is_match(&text))),
+                    _ => None,
+                }
+            }
+            Expression::Aggregate(_) => {
+                return Err(SparqlError::EvaluationError("Aggregate used outside of a SELECT expression or HAVING".to_string()));
+            }
+        })
     }
 
-    fn compare_terms(&self, left: &Term, right: &Term) -> std::cmp::Ordering {
-        match (left, right) {
-            (Term::Iri(iri1), Term::Iri(iri2)) => iri1.to_string().cmp(&iri2.to_string()),
-            (Term::Literal(lit1), Term::Literal(lit2)) => lit1.value.cmp(&lit2.value),
-            _ => std::cmp::Ordering::Equal,
+    /// Whether `pattern` has a solution once the current solution's bindings are substituted
+    fn pattern_exists(&self, pattern: &GraphPattern, binding: &Bindings, store: &RdfStore) -> Result<bool, crate::SparqlError> {
+        let algebra = crate::algebra::DefaultPlanBuilder.graph_pattern_to_algebra(&substitute_pattern(pattern, binding))?;
+        match self.evaluate(&algebra, store)? {
+            // 置換しなかった変数 (ブランクノードの値) は互換性で外側の解と突き合わせる
+            QueryResult::Select { bindings, .. } => {
+                Ok(bindings.iter().any(|inner| self.bindings_compatible(binding, inner)))
+            }
+            _ => Err(SparqlError::EvaluationError("EXISTS only supported for graph patterns".to_string())),
         }
     }
 
-    fn compare_bindings(&self, left: &Bindings, right: &Bindings, condition: &OrderCondition) -> std::cmp::Ordering {
-        // TODO: OrderCondition の評価実装
-        std::cmp::Ordering::Equal
+    /// Value an ORDER BY condition sorts one solution by
+    fn order_key(&self, condition: &OrderCondition, binding: &Bindings, store: &RdfStore) -> Result<Option<Term>, crate::SparqlError> {
+        match condition {
+            OrderCondition::Asc(expr) | OrderCondition::Desc(expr) => self.expression_value(expr, binding, store),
+        }
     }
 
     fn extract_variables(&self, triples: &[TriplePattern]) -> Vec<Variable> {
@@ -550,3 +949,80 @@ impl DefaultSparqlEvaluator {
         }
     }
 }
+
+/// Compare two ORDER BY values: unbound first, then the MIN / MAX term order
+fn compare_order_keys(condition: &OrderCondition, left: &Option<Term>, right: &Option<Term>) -> std::cmp::Ordering {
+    let ordering = match (left, right) {
+        (Some(left), Some(right)) => aggregate::order_terms(left, right),
+        _ => left.is_some().cmp(&right.is_some()),
+    };
+    match condition {
+        OrderCondition::Asc(_) => ordering,
+        OrderCondition::Desc(_) => ordering.reverse(),
+    }
+}
+
+fn plain_literal(value: String) -> Term {
+    Term::Literal(Literal { value, datatype: None, language: None })
+}
+
+/// Effective boolean value of a term (`None` when it has none, e.g. IRIs)
+fn effective_boolean_value(term: &Term) -> Option<bool> {
+    let literal = match term {
+        Term::Literal(literal) => literal,
+        _ => return None,
+    };
+    if literal.datatype.as_ref().and_then(|datatype| datatype.0.strip_prefix(aggregate::XSD)) == Some("boolean") {
+        return Some(literal.value == "true" || literal.value == "1");
+    }
+    match Numeric::from_term(term) {
+        Some(number) if literal.datatype.is_some() => Some(number.as_f64() != 0.0),
+        _ => Some(!literal.value.is_empty()),
+    }
+}
+
+fn union_variables(mut left: Vec<Variable>, right: Vec<Variable>) -> Vec<Variable> {
+    for var in right {
+        if !left.contains(&var) {
+            left.push(var);
+        }
+    }
+    left
+}
+
+/// Replace variables bound in `binding` with their values (used for EXISTS)
+///
+/// クエリ中のブランクノードは変数として扱われるため、ブランクノードの値は置換しない
+pub(crate) fn substitute_pattern(pattern: &GraphPattern, binding: &Bindings) -> GraphPattern {
+    let substitute_term = |term: &Term| match term {
+        Term::Variable(var) => match binding.get(var) {
+            Some(value) if !matches!(value, Term::BlankNode(_)) => value.clone(),
+            _ => term.clone(),
+        },
+        _ => term.clone(),
+    };
+    let substitute = |inner: &GraphPattern| Box::new(substitute_pattern(inner, binding));
+
+    match pattern {
+        GraphPattern::Bgp(triples) => GraphPattern::Bgp(triples.iter().map(|triple| TriplePattern {
+            subject: substitute_term(&triple.subject),
+            predicate: substitute_term(&triple.predicate),
+            object: substitute_term(&triple.object),
+        }).collect()),
+        GraphPattern::Optional(inner) => GraphPattern::Optional(substitute(inner)),
+        GraphPattern::Union(patterns) => GraphPattern::Union(patterns.iter().map(|p| substitute_pattern(p, binding)).collect()),
+        GraphPattern::Filter(expr, inner) => GraphPattern::Filter(expr.clone(), substitute(inner)),
+        GraphPattern::Graph(graph, inner) => GraphPattern::Graph(graph.clone(), substitute(inner)),
+        GraphPattern::Minus(left, right) => GraphPattern::Minus(substitute(left), substitute(right)),
+        GraphPattern::Join(left, right) => GraphPattern::Join(substitute(left), substitute(right)),
+        GraphPattern::Service(endpoint, inner, silent) => GraphPattern::Service(endpoint.clone(), substitute(inner), *silent),
+    }
+}
+
+/// 主語・述語位置の IRI はストアの慣習どおり括弧なしで格納する
+fn encode_node(term: &RdfTerm) -> String {
+    match term {
+        RdfTerm::Iri(iri) => iri.clone(),
+        other => other.encode(),
+    }
+}
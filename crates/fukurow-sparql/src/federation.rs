@@ -0,0 +1,448 @@
+//! SERVICE 句によるフェデレーション
+//!
+//! `SERVICE <endpoint> { ... }` の内側を SPARQL プロトコルで外部エンドポイントに問い合わせ、
+//! 返ってきた解をローカルの解と結合する。問い合わせ先は許可リストに載せたエンドポイントに限り、
+//! 呼び出しごとにタイムアウトと行数の上限を掛ける
+
+use crate::algebra::{Algebra, DefaultPlanBuilder};
+use crate::explain::algebra_variables;
+use crate::parser::{Bindings, Expression, Iri, Literal, Term, TriplePattern, VarOrIri, Variable};
+use crate::SparqlError;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Media type of SPARQL 1.1 Query Results JSON
+pub const SPARQL_RESULTS_JSON: &str = "application/sparql-results+json";
+
+/// Bounds applied to one SERVICE call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServiceLimits {
+    pub timeout: Duration,
+    /// Solutions kept from the endpoint (also sent as the query's LIMIT)
+    pub max_rows: usize,
+}
+
+impl Default for ServiceLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_rows: 10_000,
+        }
+    }
+}
+
+impl ServiceLimits {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+}
+
+/// Solutions returned by a remote endpoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServiceResults {
+    pub variables: Vec<Variable>,
+    pub bindings: Vec<Bindings>,
+}
+
+impl ServiceResults {
+    /// Read a SPARQL Query Results JSON document (`None` when it is not one)
+    pub fn from_json(document: &serde_json::Value) -> Option<Self> {
+        let variables = document.get("head")?.get("vars").map_or(Some(Vec::new()), |vars| {
+            vars.as_array()?.iter().map(|var| var.as_str().map(|name| Variable(name.to_string()))).collect()
+        })?;
+        let rows = document.get("results")?.get("bindings")?.as_array()?;
+
+        let mut bindings = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut binding = Bindings::new();
+            for (name, value) in row.as_object()? {
+                binding.insert(Variable(name.clone()), result_term(value)?);
+            }
+            bindings.push(binding);
+        }
+        Some(Self { variables, bindings })
+    }
+}
+
+fn result_term(value: &serde_json::Value) -> Option<Term> {
+    let text = value.get("value")?.as_str()?.to_string();
+    let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    match value.get("type")?.as_str()? {
+        "uri" => Some(Term::Iri(Iri(text))),
+        "bnode" => Some(Term::BlankNode(text)),
+        // "typed-literal" は SPARQL 1.0 時代の実装が返す
+        "literal" | "typed-literal" => Some(Term::Literal(Literal {
+            value: text,
+            datatype: field("datatype").map(Iri),
+            language: field("xml:lang"),
+        })),
+        _ => None,
+    }
+}
+
+/// Sends SELECT queries to SPARQL endpoints
+pub trait ServiceClient: Send + Sync {
+    fn select(&self, endpoint: &str, query: &str, limits: &ServiceLimits) -> Result<ServiceResults, SparqlError>;
+}
+
+/// SPARQL 1.1 Protocol client over HTTP(S)
+///
+/// ブロッキングクライアントを使うため、非同期ランタイム上では `spawn_blocking` 内で評価する
+#[cfg(feature = "federation")]
+pub struct HttpServiceClient {
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "federation")]
+impl HttpServiceClient {
+    pub fn new(client: reqwest::blocking::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "federation")]
+impl Default for HttpServiceClient {
+    fn default() -> Self {
+        Self::new(reqwest::blocking::Client::new())
+    }
+}
+
+#[cfg(feature = "federation")]
+impl ServiceClient for HttpServiceClient {
+    fn select(&self, endpoint: &str, query: &str, limits: &ServiceLimits) -> Result<ServiceResults, SparqlError> {
+        let error = |message: String| SparqlError::ServiceError { endpoint: endpoint.to_string(), message };
+        let response = self.client.post(endpoint)
+            .header(reqwest::header::CONTENT_TYPE, "application/sparql-query")
+            .header(reqwest::header::ACCEPT, SPARQL_RESULTS_JSON)
+            .timeout(limits.timeout)
+            .body(query.to_string())
+            .send()
+            .map_err(|e| error(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(error(format!("endpoint returned {}", status)));
+        }
+        let document: serde_json::Value = response.json().map_err(|e| error(e.to_string()))?;
+        ServiceResults::from_json(&document).ok_or_else(|| error("response is not SPARQL JSON results".to_string()))
+    }
+}
+
+/// Endpoints SERVICE blocks may call, with their limits
+///
+/// 許可リストにないエンドポイントへの SERVICE はエラー (SILENT なら空の解 1 つ) になる
+#[derive(Clone)]
+pub struct Federation {
+    client: Arc<dyn ServiceClient>,
+    endpoints: HashMap<String, Option<ServiceLimits>>,
+    default_limits: ServiceLimits,
+    max_bound_values: usize,
+}
+
+impl Federation {
+    pub fn new(client: impl ServiceClient + 'static) -> Self {
+        Self {
+            client: Arc::new(client),
+            endpoints: HashMap::new(),
+            default_limits: ServiceLimits::default(),
+            max_bound_values: 100,
+        }
+    }
+
+    /// Allow `endpoint` with the default limits
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoints.insert(endpoint.into(), None);
+        self
+    }
+
+    /// Allow `endpoint` with its own limits
+    pub fn with_endpoint_limits(mut self, endpoint: impl Into<String>, limits: ServiceLimits) -> Self {
+        self.endpoints.insert(endpoint.into(), Some(limits));
+        self
+    }
+
+    pub fn with_default_limits(mut self, limits: ServiceLimits) -> Self {
+        self.default_limits = limits;
+        self
+    }
+
+    /// Largest number of local solutions sent along as a VALUES block
+    ///
+    /// これを超えると VALUES を付けずに問い合わせ、結合はローカルで行う
+    pub fn with_max_bound_values(mut self, max_bound_values: usize) -> Self {
+        self.max_bound_values = max_bound_values;
+        self
+    }
+
+    pub fn is_allowed(&self, endpoint: &str) -> bool {
+        self.endpoints.contains_key(endpoint)
+    }
+
+    /// Limits for `endpoint` (`None` when it is not allowed)
+    pub fn limits(&self, endpoint: &str) -> Option<ServiceLimits> {
+        self.endpoints.get(endpoint).map(|limits| limits.unwrap_or(self.default_limits))
+    }
+
+    /// Evaluate `pattern` at `endpoint`
+    ///
+    /// `bound` はすでに求まったローカルの解。共有する変数の値を VALUES として送り、
+    /// エンドポイント側で絞り込ませる (結合自体は呼び出し側で行う)
+    pub fn call(
+        &self,
+        endpoint: &str,
+        pattern: &Algebra,
+        bound: &[Bindings],
+        prefixes: &HashMap<String, Iri>,
+    ) -> Result<ServiceResults, SparqlError> {
+        let limits = self.limits(endpoint).ok_or_else(|| SparqlError::ServiceError {
+            endpoint: endpoint.to_string(),
+            message: "endpoint is not in the federation allow-list".to_string(),
+        })?;
+        let values = if bound.len() <= self.max_bound_values { bound } else { &[] };
+        let query = service_query(pattern, values, limits.max_rows, prefixes)?;
+
+        let mut results = self.client.select(endpoint, &query, &limits)?;
+        results.bindings.truncate(limits.max_rows);
+        Ok(results)
+    }
+}
+
+impl std::fmt::Debug for Federation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Federation")
+            .field("endpoints", &self.endpoints)
+            .field("default_limits", &self.default_limits)
+            .field("max_bound_values", &self.max_bound_values)
+            .finish()
+    }
+}
+
+/// SELECT query sent for a SERVICE block
+///
+/// `bound` のうちパターンと共有する変数を VALUES にする。ブランクノードの値はエンドポイント側で
+/// 意味を持たないため UNDEF として送る
+pub fn service_query(
+    pattern: &Algebra,
+    bound: &[Bindings],
+    max_rows: usize,
+    prefixes: &HashMap<String, Iri>,
+) -> Result<String, SparqlError> {
+    let writer = PatternWriter { prefixes };
+    let mut body = writer.group(pattern)?;
+
+    let mut pattern_vars = BTreeSet::new();
+    algebra_variables(pattern, &mut pattern_vars);
+    let shared: Vec<&String> = pattern_vars.iter()
+        .filter(|var| bound.iter().any(|binding| binding.contains_key(&Variable(var.to_string()))))
+        .collect();
+    if !shared.is_empty() {
+        let mut rows = BTreeSet::new();
+        for binding in bound {
+            let row: Vec<String> = shared.iter()
+                .map(|var| match binding.get(&Variable(var.to_string())) {
+                    Some(Term::BlankNode(_)) | None => "UNDEF".to_string(),
+                    Some(term) => writer.term(term).unwrap_or_else(|_| "UNDEF".to_string()),
+                })
+                .collect();
+            rows.insert(format!("({})", row.join(" ")));
+        }
+        let header: Vec<String> = shared.iter().map(|var| format!("?{}", var)).collect();
+        let rows: Vec<String> = rows.into_iter().collect();
+        body = format!("VALUES ({}) {{ {} }} {}", header.join(" "), rows.join(" "), body);
+    }
+    Ok(format!("SELECT * WHERE {{ {} }} LIMIT {}", body, max_rows))
+}
+
+/// Writes algebra back as the contents of a group graph pattern
+struct PatternWriter<'a> {
+    prefixes: &'a HashMap<String, Iri>,
+}
+
+impl PatternWriter<'_> {
+    fn group(&self, algebra: &Algebra) -> Result<String, SparqlError> {
+        Ok(match algebra {
+            Algebra::Bgp(triples) => triples.iter()
+                .map(|triple| self.triple(triple))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" "),
+            Algebra::Join(left, right) => format!("{{ {} }} {{ {} }}", self.group(left)?, self.group(right)?),
+            Algebra::LeftJoin { left, right, expr } => {
+                let filter = match expr {
+                    Some(expr) => format!(" FILTER({})", self.expression(expr)?),
+                    None => String::new(),
+                };
+                format!("{{ {} }} OPTIONAL {{ {}{} }}", self.group(left)?, self.group(right)?, filter)
+            }
+            Algebra::Union(left, right) => format!("{{ {} }} UNION {{ {} }}", self.group(left)?, self.group(right)?),
+            Algebra::Minus(left, right) => format!("{{ {} }} MINUS {{ {} }}", self.group(left)?, self.group(right)?),
+            Algebra::Filter(inner, expr) => format!("{{ {} }} FILTER({})", self.group(inner)?, self.expression(expr)?),
+            Algebra::Graph(graph, inner) => format!("GRAPH {} {{ {} }}", self.var_or_iri(graph), self.group(inner)?),
+            Algebra::Service(endpoint, inner, silent) => format!(
+                "SERVICE {}{} {{ {} }}",
+                if *silent { "SILENT " } else { "" },
+                self.var_or_iri(endpoint),
+                self.group(inner)?,
+            ),
+            _ => return Err(SparqlError::UnsupportedFeature("only graph patterns can be sent to a SERVICE endpoint".to_string())),
+        })
+    }
+
+    fn triple(&self, triple: &TriplePattern) -> Result<String, SparqlError> {
+        Ok(format!("{} {} {} .", self.term(&triple.subject)?, self.term(&triple.predicate)?, self.term(&triple.object)?))
+    }
+
+    fn term(&self, term: &Term) -> Result<String, SparqlError> {
+        match term {
+            Term::Variable(var) => Ok(format!("?{}", var.0)),
+            Term::PrefixedName(prefix, local) => {
+                let namespace = self.prefixes.get(prefix.as_str())
+                    .map(|iri| iri.0.clone())
+                    .or_else(|| fukurow_core::prefix::default_namespace(prefix).map(str::to_string))
+                    .ok_or_else(|| SparqlError::EvaluationError(format!("Undeclared prefix: {}", prefix)))?;
+                Ok(format!("<{}{}>", namespace, local))
+            }
+            // 変数・接頭辞付き名前以外は具体的な RDF 項
+            other => Ok(other.to_rdf_term().map(|term| term.to_string()).unwrap_or_default()),
+        }
+    }
+
+    fn var_or_iri(&self, name: &VarOrIri) -> String {
+        match name {
+            VarOrIri::Var(var) => format!("?{}", var.0),
+            VarOrIri::Iri(iri) => format!("<{}>", iri.0),
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> Result<String, SparqlError> {
+        let binary = |op: &str, left: &Expression, right: &Expression| -> Result<String, SparqlError> {
+            Ok(format!("({} {} {})", self.expression(left)?, op, self.expression(right)?))
+        };
+        let call = |name: &str, arg: &Expression| -> Result<String, SparqlError> {
+            Ok(format!("{}({})", name, self.expression(arg)?))
+        };
+        match expr {
+            Expression::Variable(var) => Ok(format!("?{}", var.0)),
+            Expression::Iri(iri) => Ok(format!("<{}>", iri.0)),
+            Expression::Literal(literal) => self.term(&Term::Literal(literal.clone())),
+            Expression::Add(left, right) => binary("+", left, right),
+            Expression::Subtract(left, right) => binary("-", left, right),
+            Expression::Multiply(left, right) => binary("*", left, right),
+            Expression::Divide(left, right) => binary("/", left, right),
+            Expression::Equal(left, right) => binary("=", left, right),
+            Expression::NotEqual(left, right) => binary("!=", left, right),
+            Expression::LessThan(left, right) => binary("<", left, right),
+            Expression::LessThanOrEqual(left, right) => binary("<=", left, right),
+            Expression::GreaterThan(left, right) => binary(">", left, right),
+            Expression::GreaterThanOrEqual(left, right) => binary(">=", left, right),
+            Expression::And(left, right) => binary("&&", left, right),
+            Expression::Or(left, right) => binary("||", left, right),
+            Expression::Not(inner) => Ok(format!("!({})", self.expression(inner)?)),
+            Expression::Bound(var) => Ok(format!("BOUND(?{})", var.0)),
+            Expression::IsIri(arg) => call("isIRI", arg),
+            Expression::IsLiteral(arg) => call("isLiteral", arg),
+            Expression::IsBlank(arg) => call("isBlank", arg),
+            Expression::Str(arg) => call("STR", arg),
+            Expression::Lang(arg) => call("LANG", arg),
+            Expression::Datatype(arg) => call("DATATYPE", arg),
+            Expression::IriFunc(arg) => call("IRI", arg),
+            Expression::Uri(arg) => call("URI", arg),
+            Expression::Bnode(arg) => call("BNODE", arg),
+            Expression::Regex(text, pattern, flags) => Ok(match flags {
+                Some(flags) => format!("REGEX({}, {}, {})", self.expression(text)?, self.expression(pattern)?, self.expression(flags)?),
+                None => format!("REGEX({}, {})", self.expression(text)?, self.expression(pattern)?),
+            }),
+            Expression::Exists(pattern) | Expression::NotExists(pattern) => {
+                let keyword = if matches!(expr, Expression::Exists(_)) { "EXISTS" } else { "NOT EXISTS" };
+                let algebra = DefaultPlanBuilder.graph_pattern_to_algebra(pattern)?;
+                Ok(format!("{} {{ {} }}", keyword, self.group(&algebra)?))
+            }
+            Expression::Cast(datatype, arg) => Ok(format!("<{}>({})", datatype.0, self.expression(arg)?)),
+            Expression::Aggregate(_) => Err(SparqlError::UnsupportedFeature("aggregate inside SERVICE".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records queries and answers with fixed solutions
+    struct FixedEndpoint {
+        results: ServiceResults,
+        queries: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl ServiceClient for FixedEndpoint {
+        fn select(&self, _endpoint: &str, query: &str, _limits: &ServiceLimits) -> Result<ServiceResults, SparqlError> {
+            self.queries.lock().unwrap().push(query.to_string());
+            Ok(self.results.clone())
+        }
+    }
+
+    fn var(name: &str) -> Term {
+        Term::Variable(Variable(name.to_string()))
+    }
+
+    fn iri(value: &str) -> Term {
+        Term::Iri(Iri(value.to_string()))
+    }
+
+    #[test]
+    fn test_results_json_and_service_query() {
+        let document = serde_json::json!({
+            "head": { "vars": ["host", "owner"] },
+            "results": { "bindings": [
+                { "host": { "type": "uri", "value": "http://example.org/h1" },
+                  "owner": { "type": "literal", "value": "ops", "xml:lang": "en" } },
+                { "host": { "type": "bnode", "value": "b0" } }
+            ] }
+        });
+        let results = ServiceResults::from_json(&document).unwrap();
+        assert_eq!(results.variables, vec![Variable("host".to_string()), Variable("owner".to_string())]);
+        assert_eq!(results.bindings[0].get(&Variable("host".to_string())), Some(&iri("http://example.org/h1")));
+        assert_eq!(results.bindings[1].get(&Variable("host".to_string())), Some(&Term::BlankNode("b0".to_string())));
+        assert!(ServiceResults::from_json(&serde_json::json!({ "boolean": true })).is_none());
+
+        let pattern = Algebra::Bgp(vec![TriplePattern {
+            subject: var("host"),
+            predicate: iri("http://example.org/owner"),
+            object: var("owner"),
+        }]);
+        let bound: Vec<Bindings> = ["h1", "h1", "h2"].iter()
+            .map(|host| Bindings::from([(Variable("host".to_string()), iri(&format!("http://example.org/{}", host)))]))
+            .collect();
+        let query = service_query(&pattern, &bound, 50, &HashMap::new()).unwrap();
+        assert_eq!(
+            query,
+            "SELECT * WHERE { VALUES (?host) { (<http://example.org/h1>) (<http://example.org/h2>) } \
+             ?host <http://example.org/owner> ?owner . } LIMIT 50"
+        );
+    }
+
+    #[test]
+    fn test_federation_allow_list_and_row_limit() {
+        let queries = Arc::new(Mutex::new(Vec::new()));
+        let rows = (0..5).map(|i| Bindings::from([(Variable("x".to_string()), iri(&format!("http://example.org/{}", i)))])).collect();
+        let federation = Federation::new(FixedEndpoint { results: ServiceResults { variables: vec![], bindings: rows }, queries: queries.clone() })
+            .with_endpoint("http://cmdb.example.org/sparql")
+            .with_endpoint_limits("http://assets.example.org/sparql", ServiceLimits::default().with_max_rows(2));
+        let pattern = Algebra::Bgp(vec![TriplePattern { subject: var("x"), predicate: var("p"), object: var("o") }]);
+
+        assert!(matches!(
+            federation.call("http://evil.example.org/sparql", &pattern, &[], &HashMap::new()),
+            Err(SparqlError::ServiceError { .. })
+        ));
+        assert!(queries.lock().unwrap().is_empty());
+
+        assert_eq!(federation.call("http://cmdb.example.org/sparql", &pattern, &[], &HashMap::new()).unwrap().bindings.len(), 5);
+        assert_eq!(federation.call("http://assets.example.org/sparql", &pattern, &[], &HashMap::new()).unwrap().bindings.len(), 2);
+        assert!(queries.lock().unwrap()[1].ends_with("LIMIT 2"));
+    }
+}
@@ -5,11 +5,28 @@
 //! - 論理代数変換 (Algebra)
 //! - クエリ最適化 (Optimizer)
 //! - 実行エンジン (Evaluator)
+//! - プリペアドクエリとプランキャッシュ (Prepared)
+//! - 出所 (センサー・推論ルール・インポート元) ごとの仮想グラフ (Provenance)
+//! - 集約 (GROUP BY / HAVING と COUNT・SUM・AVG・MIN・MAX・SAMPLE・GROUP_CONCAT)
+//! - 型付きリテラルの値空間での比較と XSD キャスト (Datatype)
+//! - 実行計画の説明 (Explain)
+//! - SERVICE 句による外部 SPARQL エンドポイントへのフェデレーション (Federation)
+//! - CONSTRUCT の結果を名前付きグラフに保持するマテリアライズドビュー (Materialize)
+//! - 実行時間・中間解・結果件数の上限 (Limits)
 
 pub mod parser;
 pub mod algebra;
 pub mod optimizer;
 pub mod evaluator;
+pub mod diff;
+pub mod prepared;
+pub mod provenance;
+pub mod aggregate;
+pub mod datatype;
+pub mod explain;
+pub mod federation;
+pub mod materialize;
+pub mod limits;
 
 // Re-exports
 pub use parser::{SparqlParser, SparqlQuery, QueryType};
@@ -17,14 +34,86 @@ pub use algebra::{Algebra, PlanBuilder};
 pub use optimizer::{SparqlOptimizer, OptimizationRule};
 pub use evaluator::{SparqlEvaluator, QueryResult};
 pub use parser::Bindings;
+pub use diff::{QueryDiff, diff_query, diff_results, diff_since};
+pub use prepared::{PreparedQuery, QueryCache, CacheStats};
+pub use provenance::{ProvenanceGraph, ProvenanceKind, PROVENANCE_GRAPH_PREFIX};
+pub use explain::{explain_query, ExplainNode, QueryExplanation};
+pub use datatype::LiteralValue;
+pub use federation::{Federation, ServiceClient, ServiceLimits, ServiceResults};
+pub use materialize::{materialize_construct, MaterializationReport, MaterializedView, MATERIALIZED_REASONING_LEVEL};
+pub use limits::{LimitKind, LimitedResult, QueryLimits};
+#[cfg(feature = "federation")]
+pub use federation::HttpServiceClient;
 
 /// クエリ実行の簡易インターフェース
 pub fn execute_query(query: &str, store: &fukurow_store::store::RdfStore) -> Result<QueryResult, SparqlError> {
-    let parser = parser::DefaultSparqlParser;
-    let mut evaluator = evaluator::DefaultSparqlEvaluator::new();
+    execute_with(query, store, evaluator::DefaultSparqlEvaluator::new())
+}
+
+/// Execute `query`, evaluating SERVICE blocks at the endpoints `federation` allows
+pub fn execute_federated_query(
+    query: &str,
+    store: &fukurow_store::store::RdfStore,
+    federation: &Federation,
+) -> Result<QueryResult, SparqlError> {
+    execute_with(query, store, evaluator::DefaultSparqlEvaluator::new().with_federation(federation.clone()))
+}
+
+/// Execute `query` within `limits`
+///
+/// 上限に達すると [`SparqlError::LimitExceeded`] を返す (`partial_results` なら打ち切った結果)
+pub fn execute_query_with_limits(
+    query: &str,
+    store: &fukurow_store::store::RdfStore,
+    limits: QueryLimits,
+) -> Result<LimitedResult, SparqlError> {
+    execute_limited(query, store, evaluator::DefaultSparqlEvaluator::new().with_limits(limits))
+}
+
+/// Execute `query` within `limits`, returning only the SELECT solutions `offset..offset + limit`
+///
+/// 並べ替えと切り出しは評価器で行う ([`evaluator::DefaultSparqlEvaluator::with_page`])。
+/// SELECT 以外の結果は切り出さずに返す
+pub fn execute_query_page(
+    query: &str,
+    store: &fukurow_store::store::RdfStore,
+    limits: QueryLimits,
+    offset: usize,
+    limit: usize,
+) -> Result<LimitedResult, SparqlError> {
+    execute_limited(query, store, evaluator::DefaultSparqlEvaluator::new().with_limits(limits).with_page(offset, limit))
+}
 
+fn execute_with(
+    query: &str,
+    store: &fukurow_store::store::RdfStore,
+    evaluator: evaluator::DefaultSparqlEvaluator,
+) -> Result<QueryResult, SparqlError> {
+    execute_limited(query, store, evaluator).map(|limited| limited.result)
+}
+
+fn execute_limited(
+    query: &str,
+    store: &fukurow_store::store::RdfStore,
+    mut evaluator: evaluator::DefaultSparqlEvaluator,
+) -> Result<LimitedResult, SparqlError> {
+    let parser = parser::DefaultSparqlParser;
     let parsed = parser.parse(query)?;
-    evaluator.evaluate_query(&parsed, store)
+    // ストアの述語統計で BGP の結合順を決める (explain_query と同じ計画)
+    let stats = optimizer::QueryStats::from_store(store, &parsed.prefixes);
+    let algebra = explain::plan_query(&parsed, &stats)?;
+    evaluator.evaluate_planned_limited(&parsed, &algebra, store)
+}
+
+/// Execute `query` with `prefixes` declared before its own PREFIX lines
+///
+/// ルールやダッシュボードで共有する接頭辞を各クエリに書かずに済む。クエリ内の宣言が優先される
+pub fn execute_query_with_prefixes(
+    query: &str,
+    store: &fukurow_store::store::RdfStore,
+    prefixes: &fukurow_core::prefix::PrefixMap,
+) -> Result<QueryResult, SparqlError> {
+    execute_query(&format!("{}{}", prefixes.sparql_prologue(), query), store)
 }
 
 // Error types
@@ -46,6 +135,15 @@ pub enum SparqlError {
 
     #[error("Unsupported feature: {0}")]
     UnsupportedFeature(String),
+
+    #[error("SERVICE {endpoint} failed: {message}")]
+    ServiceError { endpoint: String, message: String },
+
+    #[error("Query limit exceeded: {0}")]
+    LimitExceeded(LimitKind),
+
+    #[error("History error: {0}")]
+    HistoryError(String),
 }
 
 #[cfg(test)]
@@ -237,8 +335,8 @@ mod tests {
                 assert_eq!(variables.len(), 2);
                 assert_eq!(bindings.len(), 1);
                 assert_eq!(bindings[0].get(&parser::Variable("person".to_string())), Some(&parser::Term::Iri(parser::Iri("http://example.org/alice".to_string()))));
-                // The evaluator interprets quoted strings as IRIs in this implementation
-                assert_eq!(bindings[0].get(&parser::Variable("name".to_string())), Some(&parser::Term::Iri(parser::Iri("\"Alice\"".to_string()))));
+                // Quoted values are bound as literals
+                assert_eq!(bindings[0].get(&parser::Variable("name".to_string())), Some(&parser::Term::Literal(parser::Literal { value: "Alice".to_string(), datatype: None, language: None })));
             }
             _ => panic!("Expected Select result"),
         }
@@ -439,6 +537,228 @@ mod tests {
         }
     }
 
+    fn blank_node_store() -> RdfStore {
+        let mut store = RdfStore::new();
+        for (subject, predicate, object) in [
+            ("_:e1", "http://example.org/user", "alice"),
+            ("_:e1", "http://example.org/sourceIP", "10.0.0.5"),
+            ("_:e2", "http://example.org/user", "bob"),
+            ("http://example.org/conn1", "http://example.org/port", "\"443\"^^<http://www.w3.org/2001/XMLSchema#integer>"),
+            ("http://example.org/conn2", "http://example.org/port", "443"),
+        ] {
+            store.insert(Triple {
+                subject: subject.to_string(),
+                predicate: predicate.to_string(),
+                object: object.to_string(),
+            }, default_graph_id(), sensor_provenance());
+        }
+        store
+    }
+
+    fn select_values(query: &str, store: &RdfStore, variable: &str) -> Vec<parser::Term> {
+        match execute_query(query, store).unwrap() {
+            QueryResult::Select { bindings, .. } => {
+                assert!(bindings.iter().all(|b| b.keys().all(|v| !v.0.starts_with("_:"))));
+                bindings.iter().filter_map(|b| b.get(&parser::Variable(variable.to_string())).cloned()).collect()
+            }
+            _ => panic!("Expected Select result"),
+        }
+    }
+
+    #[test]
+    fn test_query_blank_nodes_join_within_bgp() {
+        let store = blank_node_store();
+        let query = r#"
+            PREFIX ex: <http://example.org/>
+            SELECT ?user
+            WHERE {
+                _:event ex:user ?user .
+                _:event ex:sourceIP ?ip .
+            }
+        "#;
+
+        let users = select_values(query, &store, "user");
+        assert_eq!(users, vec![parser::Term::Literal(parser::Literal { value: "alice".to_string(), datatype: None, language: None })]);
+    }
+
+    #[test]
+    fn test_bound_terms_keep_their_kind() {
+        let store = blank_node_store();
+        let query = r#"
+            PREFIX ex: <http://example.org/>
+            SELECT ?event
+            WHERE {
+                ?event ex:user "bob" .
+            }
+        "#;
+        assert_eq!(select_values(query, &store, "event"), vec![parser::Term::BlankNode("e2".to_string())]);
+
+        let typed = r#"
+            PREFIX ex: <http://example.org/>
+            SELECT ?conn
+            WHERE {
+                ?conn ex:port "443"^^xsd:integer .
+            }
+        "#;
+        assert_eq!(select_values(typed, &store, "conn"), vec![parser::Term::Iri(parser::Iri("http://example.org/conn1".to_string()))]);
+
+        let plain = typed.replace("\"443\"^^xsd:integer", "\"443\"");
+        assert_eq!(select_values(&plain, &store, "conn"), vec![parser::Term::Iri(parser::Iri("http://example.org/conn2".to_string()))]);
+    }
+
+    #[test]
+    fn test_construct_template_blank_nodes_are_fresh_per_solution() {
+        let store = blank_node_store();
+        let query = r#"
+            PREFIX ex: <http://example.org/>
+            CONSTRUCT {
+                _:alert ex:about ?event .
+                _:alert ex:severity "high" .
+            }
+            WHERE {
+                ?event ex:user ?user .
+            }
+        "#;
+
+        match execute_query(query, &store).unwrap() {
+            QueryResult::Construct { triples } => {
+                assert_eq!(triples.len(), 4);
+                let alerts: std::collections::HashSet<&str> = triples.iter().map(|t| t.subject.as_str()).collect();
+                assert_eq!(alerts.len(), 2);
+                assert!(alerts.iter().all(|alert| alert.starts_with("_:")));
+
+                let mut about: Vec<&str> = triples.iter()
+                    .filter(|t| t.predicate == "http://example.org/about")
+                    .map(|t| t.object.as_str())
+                    .collect();
+                about.sort_unstable();
+                assert_eq!(about, vec!["_:e1", "_:e2"]);
+                assert!(triples.iter().any(|t| t.predicate == "http://example.org/severity" && t.object == "high"));
+            }
+            _ => panic!("Expected Construct result"),
+        }
+    }
+
+    fn allow_list_store() -> RdfStore {
+        let mut store = RdfStore::new();
+        for (subject, predicate, object) in [
+            ("http://example.org/conn1", "http://example.org/dest", "http://example.org/hostA"),
+            ("http://example.org/conn2", "http://example.org/dest", "http://example.org/hostB"),
+            ("http://example.org/conn3", "http://example.org/dest", "http://example.org/hostC"),
+            ("http://example.org/conn1", "http://example.org/user", "alice"),
+            ("http://example.org/conn1", "http://example.org/user", "bob"),
+            ("http://example.org/allowlist", "http://example.org/contains", "http://example.org/hostA"),
+        ] {
+            store.insert(Triple {
+                subject: subject.to_string(),
+                predicate: predicate.to_string(),
+                object: object.to_string(),
+            }, default_graph_id(), sensor_provenance());
+        }
+        store
+    }
+
+    fn sorted_iris(query: &str, store: &RdfStore, variable: &str) -> Vec<String> {
+        let mut iris: Vec<String> = select_values(query, store, variable).into_iter()
+            .map(|term| match term {
+                parser::Term::Iri(iri) => iri.0,
+                other => panic!("Expected IRI, got {:?}", other),
+            })
+            .collect();
+        iris.sort();
+        iris
+    }
+
+    #[test]
+    fn test_not_exists_and_minus_exclude_allow_listed_hosts() {
+        let store = allow_list_store();
+        let not_exists = r#"
+            PREFIX ex: <http://example.org/>
+            SELECT ?conn
+            WHERE {
+                ?conn ex:dest ?host .
+                FILTER NOT EXISTS {
+                    ?list ex:contains ?host .
+                }
+            }
+        "#;
+        let expected = vec!["http://example.org/conn2".to_string(), "http://example.org/conn3".to_string()];
+        assert_eq!(sorted_iris(not_exists, &store, "conn"), expected);
+
+        let exists = not_exists.replace("FILTER NOT EXISTS", "FILTER EXISTS");
+        assert_eq!(sorted_iris(&exists, &store, "conn"), vec!["http://example.org/conn1".to_string()]);
+
+        let minus = r#"
+            PREFIX ex: <http://example.org/>
+            SELECT ?conn
+            WHERE {
+                ?conn ex:dest ?host .
+                MINUS { ?list ex:contains ?host . }
+            }
+        "#;
+        assert_eq!(sorted_iris(minus, &store, "conn"), expected);
+
+        // 変数を共有しない MINUS は何も取り除かない
+        let disjoint = minus.replace("?list ex:contains ?host", "?list ex:contains ?other");
+        assert_eq!(sorted_iris(&disjoint, &store, "conn").len(), 3);
+
+        // 1 行に書いても文の後ろのキーワードでブロックの種類が決まる
+        let one_line = |block: &str| format!("PREFIX ex: <http://example.org/>\nSELECT ?conn WHERE {{ ?conn ex:dest ?host . {} {{ ?list ex:contains ?host }} }}", block);
+        assert_eq!(sorted_iris(&one_line("FILTER NOT EXISTS"), &store, "conn"), expected);
+        assert_eq!(sorted_iris(&one_line("MINUS"), &store, "conn"), expected);
+        assert_eq!(sorted_iris(&one_line("FILTER EXISTS"), &store, "conn"), vec!["http://example.org/conn1".to_string()]);
+    }
+
+    #[test]
+    fn test_unsupported_where_patterns_are_rejected() {
+        let parser = parser::DefaultSparqlParser;
+        for query in [
+            "SELECT ?s WHERE { ?s ?p ?o . UNION { ?s ?p ?x } }",
+            "SELECT ?s WHERE { ?s ?p ?o . BIND(?o AS ?x) }",
+            "SELECT ?s WHERE { 42 ?p ?o }",
+        ] {
+            assert!(matches!(parser.parse(query), Err(SparqlError::ParseError(_))), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_optional_keeps_unmatched_solutions() {
+        let store = allow_list_store();
+        let query = r#"
+            PREFIX ex: <http://example.org/>
+            SELECT ?conn ?user
+            WHERE {
+                ?conn ex:dest ?host .
+                OPTIONAL {
+                    ?conn ex:user ?user .
+                }
+            }
+        "#;
+
+        let parsed = parser::DefaultSparqlParser.parse(query).unwrap();
+        match &parsed.where_clause {
+            parser::GraphPattern::Join(left, right) => {
+                assert!(matches!(left.as_ref(), parser::GraphPattern::Bgp(triples) if triples.len() == 1));
+                assert!(matches!(right.as_ref(), parser::GraphPattern::Optional(_)));
+            }
+            other => panic!("Expected Join, got {:?}", other),
+        }
+
+        let one_line = "PREFIX ex: <http://example.org/>\nSELECT ?conn ?user WHERE { ?conn ex:dest ?host . OPTIONAL { ?conn ex:user ?user } }";
+        for query in [query, one_line] {
+            match execute_query(query, &store).unwrap() {
+                QueryResult::Select { bindings, .. } => {
+                    // conn1 は利用者ごとに 1 解、conn2・conn3 は ?user 未束縛のまま残る
+                    assert_eq!(bindings.len(), 4);
+                    let user = parser::Variable("user".to_string());
+                    assert_eq!(bindings.iter().filter(|b| b.contains_key(&user)).count(), 2);
+                    assert_eq!(bindings.iter().filter(|b| !b.contains_key(&user)).count(), 2);
+                }
+                _ => panic!("Expected Select result"),
+            }
+        }
+    }
+
     #[test]
     fn test_term_variants() {
         let iri_term = parser::Term::Iri(parser::Iri("http://example.org/test".to_string()));
@@ -450,7 +770,8 @@ mod tests {
         let literal_term = parser::Term::Literal(parser::Literal { value: "\"test\"".to_string(), datatype: None, language: None });
         assert!(matches!(literal_term, parser::Term::Literal(_)));
 
-        // Note: Blank nodes don't exist in this Term enum
+        let blank_term = parser::Term::BlankNode("b0".to_string());
+        assert_eq!(blank_term.to_rdf_term(), Some(fukurow_core::term::RdfTerm::blank_node("b0")));
     }
 
     #[test]
@@ -471,4 +792,391 @@ mod tests {
         // Variable should be created
         assert!(true);
     }
+
+    #[test]
+    fn test_select_result_diff() {
+        let row = |name: &str| {
+            let mut bindings = Bindings::new();
+            bindings.insert(parser::Variable("name".to_string()), parser::Term::Iri(parser::Iri(name.to_string())));
+            bindings
+        };
+        let variables = vec![parser::Variable("name".to_string())];
+        let before = QueryResult::Select { variables: variables.clone(), bindings: vec![row("alice"), row("bob")] };
+        let after = QueryResult::Select { variables, bindings: vec![row("bob"), row("carol")] };
+
+        match diff_results(&before, &after).unwrap() {
+            QueryDiff::Select { added, removed, unchanged, .. } => {
+                assert_eq!(added.len(), 1);
+                assert_eq!(added[0]["name"], "carol");
+                assert_eq!(removed[0]["name"], "alice");
+                assert_eq!(unchanged, 1);
+            }
+            other => panic!("Expected Select diff, got {:?}", other),
+        }
+
+        assert!(diff_results(&QueryResult::Ask { result: true }, &after).is_err());
+    }
+
+    #[test]
+    fn test_diff_since_snapshot() {
+        let mut store = RdfStore::new();
+        store.insert(Triple {
+            subject: "http://example.org/alice".to_string(),
+            predicate: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+            object: "http://example.org/Person".to_string(),
+        }, default_graph_id(), sensor_provenance());
+
+        let since = store.audit_trail().last().unwrap().timestamp;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        store.insert(Triple {
+            subject: "http://example.org/alice".to_string(),
+            predicate: "http://example.org/name".to_string(),
+            object: "\"Alice\"".to_string(),
+        }, default_graph_id(), sensor_provenance());
+
+        let query = r#"
+            PREFIX ex: <http://example.org/>
+            ASK { ?person ex:name ?name . }
+        "#;
+        let diff = diff_since(query, &store, since).unwrap();
+        assert_eq!(diff, QueryDiff::Ask { before: false, after: true });
+        assert!(diff.has_changes());
+
+        // 監査ログの上限で since 時点のエントリが捨てられた後は差分を出さない
+        store.set_audit_limit(1);
+        assert!(matches!(diff_since(query, &store, since), Err(SparqlError::HistoryError(_))));
+    }
+
+    #[test]
+    fn test_graph_by_provenance() {
+        let mut store = RdfStore::new();
+        let connects = |from: &str, to: &str| Triple {
+            subject: format!("http://example.org/{}", from),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: format!("http://example.org/{}", to),
+        };
+        store.insert(connects("h1", "h2"), GraphId::Default, Provenance::Sensor { source: "edr-1".to_string(), confidence: Some(0.9) });
+        store.insert(connects("h3", "h4"), GraphId::Default, Provenance::Sensor { source: "fw-1".to_string(), confidence: Some(0.4) });
+        store.insert(connects("h1", "h4"), GraphId::Inferred("transitive".to_string()), Provenance::Inferred {
+            rule: "transitive".to_string(),
+            reasoning_level: "owl-lite".to_string(),
+            evidence: vec![],
+            confidence: None,
+        });
+
+        let hosts = |graph: &str| -> Vec<Bindings> {
+            let query = format!(
+                "PREFIX ex: <http://example.org/>\nSELECT ?host ?g\nWHERE {{\nGRAPH {} {{ ?host ex:connectsTo ?dest . }}\n}}",
+                graph
+            );
+            match execute_query(&query, &store).unwrap() {
+                QueryResult::Select { bindings, .. } => bindings,
+                other => panic!("Expected Select result, got {:?}", other),
+            }
+        };
+        assert_eq!(hosts("<urn:fukurow:provenance:sensor:edr-1>").len(), 1);
+        assert_eq!(hosts("<urn:fukurow:provenance:sensor>").len(), 2);
+        assert_eq!(hosts("<urn:fukurow:provenance:sensor?min_confidence=0.5>").len(), 1);
+        assert_eq!(hosts("<urn:fukurow:provenance:inferred:transitive>").len(), 1);
+
+        let by_graph = hosts("?g");
+        let mut graphs: Vec<String> = by_graph.iter()
+            .map(|binding| match &binding[&parser::Variable("g".to_string())] {
+                parser::Term::Iri(iri) => iri.0.clone(),
+                other => panic!("Expected graph IRI, got {:?}", other),
+            })
+            .collect();
+        graphs.sort();
+        assert_eq!(graphs, vec![
+            "urn:fukurow:provenance:inferred:transitive",
+            "urn:fukurow:provenance:sensor:edr-1",
+            "urn:fukurow:provenance:sensor:fw-1",
+        ]);
+    }
+
+    #[test]
+    fn test_group_by_having_counts_connections() {
+        let mut store = RdfStore::new();
+        for (from, to) in [("h1", "h2"), ("h1", "h3"), ("h1", "h4"), ("h5", "h2")] {
+            store.insert(Triple {
+                subject: format!("http://example.org/{}", from),
+                predicate: "http://example.org/connectsTo".to_string(),
+                object: format!("http://example.org/{}", to),
+            }, default_graph_id(), sensor_provenance());
+        }
+        let var = |name: &str| parser::Variable(name.to_string());
+
+        // 宛先が複数あるホストと、その宛先数
+        let query = r#"
+            PREFIX ex: <http://example.org/>
+            SELECT ?src (COUNT(DISTINCT ?dst) AS ?n)
+            WHERE {
+                ?src ex:connectsTo ?dst .
+            }
+            GROUP BY ?src
+            HAVING (COUNT(DISTINCT ?dst) > 1)
+        "#;
+        match execute_query(query, &store).unwrap() {
+            QueryResult::Select { variables, bindings } => {
+                assert_eq!(variables, vec![var("src"), var("n")]);
+                assert_eq!(bindings.len(), 1);
+                assert_eq!(bindings[0][&var("src")], parser::Term::Iri(parser::Iri("http://example.org/h1".to_string())));
+                assert_eq!(bindings[0][&var("n")], aggregate::typed_literal("3", "integer"));
+            }
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+
+        // GROUP BY がなければ全体で 1 グループ
+        let total = "PREFIX ex: <http://example.org/>\nSELECT (COUNT(*) AS ?total) (COUNT(*) * 2 AS ?double)\nWHERE {\n?src ex:connectsTo ?dst .\n}";
+        match execute_query(total, &store).unwrap() {
+            QueryResult::Select { bindings, .. } => {
+                assert_eq!(bindings.len(), 1);
+                assert_eq!(bindings[0][&var("total")], aggregate::typed_literal("4", "integer"));
+                assert_eq!(bindings[0][&var("double")], aggregate::typed_literal("8", "integer"));
+            }
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+
+        // WHERE 句と同じ行の GROUP BY / HAVING
+        let one_line = "PREFIX ex: <http://example.org/>\nSELECT ?src (COUNT(?dst) AS ?n) WHERE { ?src ex:connectsTo ?dst } GROUP BY ?src HAVING (COUNT(?dst) > 1) ORDER BY ?src";
+        match execute_query(one_line, &store).unwrap() {
+            QueryResult::Select { bindings, .. } => {
+                assert_eq!(bindings.len(), 1);
+                assert_eq!(bindings[0][&var("n")], aggregate::typed_literal("3", "integer"));
+            }
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+        let grouped = parser::DefaultSparqlParser.parse("SELECT ?src (COUNT(?dst) AS ?n) WHERE { ?src <http://example.org/connectsTo> ?dst } GROUP BY ?src").unwrap();
+        assert_eq!(grouped.solution_modifier.group.map(|g| g.len()), Some(1));
+        assert!(grouped.solution_modifier.having.is_none());
+
+        let invalid = parser::DefaultSparqlParser.parse("SELECT (COUNT(?dst) ?n)\nWHERE {\n}");
+        assert!(matches!(invalid, Err(SparqlError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_filter_compares_typed_literals_by_value() {
+        use fukurow_core::term::RdfTerm;
+        const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+        let mut store = RdfStore::new();
+        let logins = [
+            ("alice", RdfTerm::typed_literal("9", format!("{}integer", XSD)), "2024-01-01T09:00:00+09:00"),
+            ("bob", RdfTerm::typed_literal("10", format!("{}integer", XSD)), "2024-01-02T00:00:00Z"),
+            // センサーから型なしで届いた値
+            ("carol", RdfTerm::literal("100"), "2023-12-31T23:59:59Z"),
+        ];
+        for (user, failures, at) in logins {
+            let subject = format!("http://example.org/{}", user);
+            let mut insert = |predicate: &str, object: RdfTerm| store.insert(Triple {
+                subject: subject.clone(),
+                predicate: format!("http://example.org/{}", predicate),
+                object: object.encode(),
+            }, default_graph_id(), sensor_provenance());
+            insert("failures", failures);
+            insert("at", RdfTerm::typed_literal(at, format!("{}dateTime", XSD)));
+        }
+
+        let users = |query: &str| -> Vec<String> {
+            match execute_query(query, &store).unwrap() {
+                QueryResult::Select { bindings, .. } => {
+                    let mut users: Vec<String> = bindings.iter()
+                        .map(|b| diff::format_term(&b[&parser::Variable("user".to_string())]))
+                        .collect();
+                    users.sort();
+                    users
+                }
+                other => panic!("Expected Select result, got {:?}", other),
+            }
+        };
+
+        // 字句比較なら "9" > "10" になる
+        let numeric = "PREFIX ex: <http://example.org/>\nSELECT ?user\nWHERE {\n?user ex:failures ?n .\nFILTER (?n >= 10)\n}";
+        assert_eq!(users(numeric), vec!["http://example.org/bob", "http://example.org/carol"]);
+
+        // 同じ時刻のタイムゾーン違いは等しい
+        let since = "PREFIX ex: <http://example.org/>\nSELECT ?user\nWHERE {\n?user ex:at ?at .\nFILTER (?at >= \"2024-01-01T00:00:00Z\"^^xsd:dateTime)\n}";
+        assert_eq!(users(since), vec!["http://example.org/alice", "http://example.org/bob"]);
+
+        let typed = "PREFIX ex: <http://example.org/>\nSELECT ?user\nWHERE {\n?user ex:failures ?n .\nFILTER (datatype(?n) = xsd:integer && xsd:integer(str(?n)) < 10)\n}";
+        assert_eq!(users(typed), vec!["http://example.org/alice"]);
+
+        let cast = "PREFIX ex: <http://example.org/>\nSELECT ?user\nWHERE {\n?user ex:failures ?n .\nFILTER (xsd:integer(?n) > 50)\n}";
+        assert_eq!(users(cast), vec!["http://example.org/carol"]);
+    }
+
+    #[test]
+    fn test_shared_and_default_prefixes() {
+        let mut store = RdfStore::new();
+        store.insert(Triple {
+            subject: "http://example.org/Malware".to_string(),
+            predicate: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+            object: "http://www.w3.org/2002/07/owl#Class".to_string(),
+        }, default_graph_id(), sensor_provenance());
+
+        // owl: は宣言しなくても既定の接頭辞で解決される
+        let prefixes = fukurow_core::prefix::PrefixMap::empty().with_prefix("ex", "http://example.org/");
+        let ask = "ASK {\nex:Malware rdf:type owl:Class .\n}";
+        assert!(matches!(execute_query_with_prefixes(ask, &store, &prefixes).unwrap(), QueryResult::Ask { result: true }));
+        assert!(matches!(execute_query(ask, &store).unwrap(), QueryResult::Ask { result: false }));
+
+        // クエリ内の宣言が共有の接頭辞より優先される
+        let shadowed = "PREFIX ex: <http://other.org/>\nASK {\nex:Malware rdf:type owl:Class .\n}";
+        assert!(matches!(execute_query_with_prefixes(shadowed, &store, &prefixes).unwrap(), QueryResult::Ask { result: false }));
+    }
+
+    #[test]
+    fn test_limit_is_pushed_down_without_changing_results() {
+        let mut store = RdfStore::new();
+        for i in 0..20 {
+            let host = format!("http://example.org/host{}", i);
+            store.insert(Triple { subject: host.clone(), predicate: "http://example.org/type".to_string(), object: "http://example.org/Host".to_string() }, default_graph_id(), sensor_provenance());
+            store.insert(Triple { subject: host, predicate: "http://example.org/ip".to_string(), object: format!("10.0.0.{}", i) }, default_graph_id(), sensor_provenance());
+        }
+
+        let hosts = |modifiers: &str| -> Vec<Bindings> {
+            let query = format!("PREFIX ex: <http://example.org/>\nSELECT ?host ?ip\nWHERE {{\n?host ex:type ex:Host .\n?host ex:ip ?ip .\n}}{}", modifiers);
+            match execute_query(&query, &store).unwrap() {
+                QueryResult::Select { bindings, .. } => bindings,
+                other => panic!("Expected Select result, got {:?}", other),
+            }
+        };
+
+        // 押し下げた結果は全件評価してから切り出した結果と同じ
+        let all = hosts("");
+        assert_eq!(all.len(), 20);
+        assert_eq!(hosts("\nLIMIT 3 OFFSET 2"), all[2..5].to_vec());
+        assert!(hosts("\nLIMIT 0").is_empty());
+        assert!(hosts("\nLIMIT 5 OFFSET 100").is_empty());
+
+        // ORDER BY は切り出しより先に適用する
+        let mut by_ip = all.clone();
+        let ip = |binding: &Bindings| match &binding[&parser::Variable("ip".to_string())] {
+            parser::Term::Literal(literal) => literal.value.clone(),
+            other => panic!("Expected literal ip, got {:?}", other),
+        };
+        by_ip.sort_by_key(|binding| std::cmp::Reverse(ip(binding)));
+        assert_eq!(hosts("\nORDER BY DESC(?ip)\nLIMIT 3"), by_ip[..3].to_vec());
+        assert_eq!(hosts("\nORDER BY DESC(?ip) LIMIT 2 OFFSET 1"), by_ip[1..3].to_vec());
+    }
+
+    #[test]
+    fn test_query_page_slices_in_the_evaluator() {
+        let mut store = RdfStore::new();
+        for i in (0..10).rev() {
+            let host = format!("http://example.org/host{}", i);
+            store.insert(Triple { subject: host, predicate: "http://example.org/ip".to_string(), object: format!("10.0.0.{}", i) }, default_graph_id(), sensor_provenance());
+        }
+
+        let page = |modifiers: &str, offset: usize, limit: usize| -> Vec<String> {
+            let query = format!("PREFIX ex: <http://example.org/>\nSELECT ?host ?ip\nWHERE {{\n?host ex:ip ?ip .\n}}{}", modifiers);
+            match execute_query_page(&query, &store, QueryLimits::default(), offset, limit).unwrap().result {
+                QueryResult::Select { bindings, .. } => bindings.iter().map(|binding| match &binding[&parser::Variable("ip".to_string())] {
+                    parser::Term::Literal(literal) => literal.value.clone(),
+                    other => panic!("Expected literal ip, got {:?}", other),
+                }).collect(),
+                other => panic!("Expected Select result, got {:?}", other),
+            }
+        };
+
+        // ORDER BY が無ければ変数名順に各値の昇順で並べ、ページをまたいで重ならない
+        assert_eq!(page("", 0, 3), vec!["10.0.0.0", "10.0.0.1", "10.0.0.2"]);
+        assert_eq!(page("", 3, 3), vec!["10.0.0.3", "10.0.0.4", "10.0.0.5"]);
+        assert_eq!(page("", 9, 3), vec!["10.0.0.9"]);
+
+        // ORDER BY とクエリ自身の LIMIT / OFFSET の内側で切り出す
+        assert_eq!(page("\nORDER BY DESC(?ip)", 1, 2), vec!["10.0.0.8", "10.0.0.7"]);
+        assert_eq!(page("\nORDER BY DESC(?ip) LIMIT 4 OFFSET 2", 2, 5), vec!["10.0.0.5", "10.0.0.4"]);
+    }
+
+    #[test]
+    fn test_service_results_join_local_solutions() {
+        use std::sync::{Arc, Mutex};
+
+        /// CMDB endpoint that knows the owners of host1 and host9
+        struct Cmdb(Arc<Mutex<Vec<String>>>);
+
+        impl ServiceClient for Cmdb {
+            fn select(&self, _endpoint: &str, query: &str, _limits: &ServiceLimits) -> Result<ServiceResults, SparqlError> {
+                self.0.lock().unwrap().push(query.to_string());
+                let owner = |host: &str, owner: &str| Bindings::from([
+                    (parser::Variable("host".to_string()), parser::Term::Iri(parser::Iri(format!("http://example.org/{}", host)))),
+                    (parser::Variable("owner".to_string()), parser::Term::Literal(parser::Literal { value: owner.to_string(), datatype: None, language: None })),
+                ]);
+                Ok(ServiceResults { variables: vec![], bindings: vec![owner("host1", "ops"), owner("host9", "dev")] })
+            }
+        }
+
+        let mut store = RdfStore::new();
+        for i in 1..=2 {
+            store.insert(Triple {
+                subject: format!("http://example.org/host{}", i),
+                predicate: "http://example.org/ip".to_string(),
+                object: format!("10.0.0.{}", i),
+            }, default_graph_id(), sensor_provenance());
+        }
+        let queries = Arc::new(Mutex::new(Vec::new()));
+        let federation = Federation::new(Cmdb(queries.clone())).with_endpoint("http://cmdb.example.org/sparql");
+
+        let query = |endpoint: &str, silent: &str| format!(
+            "PREFIX ex: <http://example.org/>\nSELECT ?host ?owner\nWHERE {{\n?host ex:ip ?ip .\nSERVICE {}<{}> {{\n?host ex:owner ?owner .\n}}\n}}",
+            silent, endpoint,
+        );
+        match execute_federated_query(&query("http://cmdb.example.org/sparql", ""), &store, &federation).unwrap() {
+            QueryResult::Select { bindings, .. } => {
+                assert_eq!(bindings.len(), 1);
+                assert_eq!(diff::format_term(&bindings[0][&parser::Variable("owner".to_string())]), "ops");
+            }
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+        // ローカルの解が VALUES として送られる
+        assert!(queries.lock().unwrap()[0].contains("VALUES (?host) { (<http://example.org/host1>) (<http://example.org/host2>) }"));
+
+        // 許可リスト外のエンドポイントは呼ばない。SILENT ならローカルの解だけが残る
+        let blocked = query("http://evil.example.org/sparql", "");
+        assert!(matches!(execute_federated_query(&blocked, &store, &federation), Err(SparqlError::ServiceError { .. })));
+        match execute_federated_query(&query("http://evil.example.org/sparql", "SILENT "), &store, &federation).unwrap() {
+            QueryResult::Select { bindings, .. } => assert_eq!(bindings.len(), 2),
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+        assert_eq!(queries.lock().unwrap().len(), 1);
+        assert!(matches!(execute_query(&blocked, &store), Err(SparqlError::UnsupportedFeature(_))));
+    }
+
+    #[test]
+    fn test_query_limits_stop_cross_products() {
+        let mut store = RdfStore::new();
+        for i in 0..20 {
+            store.insert(Triple {
+                subject: format!("http://example.org/host{}", i),
+                predicate: "http://example.org/connectsTo".to_string(),
+                object: "http://example.org/gateway".to_string(),
+            }, default_graph_id(), sensor_provenance());
+        }
+        // 400 件の直積
+        let query = "SELECT * WHERE { ?a ?p ?b . ?c ?q ?d }";
+        let full = execute_query_with_limits(query, &store, QueryLimits::default()).unwrap();
+        assert!(full.truncated.is_none());
+        assert!(matches!(full.result, QueryResult::Select { ref bindings, .. } if bindings.len() == 400));
+
+        let bounded = QueryLimits::default().with_max_intermediate_bindings(100);
+        assert!(matches!(
+            execute_query_with_limits(query, &store, bounded),
+            Err(SparqlError::LimitExceeded(LimitKind::IntermediateBindings))
+        ));
+        let partial = execute_query_with_limits(query, &store, bounded.with_partial_results(true)).unwrap();
+        assert_eq!(partial.truncated, Some(LimitKind::IntermediateBindings));
+        assert!(matches!(partial.result, QueryResult::Select { ref bindings, .. } if !bindings.is_empty() && bindings.len() < 400));
+
+        let first = execute_query_with_limits(query, &store, QueryLimits::default().with_max_results(5).with_partial_results(true)).unwrap();
+        assert_eq!(first.truncated, Some(LimitKind::Results));
+        assert!(matches!(first.result, QueryResult::Select { ref bindings, .. } if bindings.len() == 5));
+
+        let expired = QueryLimits::default().with_max_execution_time(std::time::Duration::ZERO);
+        assert!(matches!(
+            execute_query_with_limits(query, &store, expired),
+            Err(SparqlError::LimitExceeded(LimitKind::ExecutionTime))
+        ));
+    }
 }
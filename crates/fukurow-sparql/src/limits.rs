@@ -0,0 +1,175 @@
+//! Query limits
+//!
+//! 評価器の中で協調的に確認する実行時間・中間解・結果件数の上限。上限に達するとストアの走査と
+//! 結合を打ち切り、エラーを返すか (既定)、それまでに得た解を部分結果として返す。
+//! 時間はトリプルの走査と各演算子の入口で確認するため、上限をわずかに超えることがある
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::SparqlError;
+
+/// Bounds applied to one query evaluation (unlimited by default)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryLimits {
+    pub max_execution_time: Option<Duration>,
+    /// Solutions produced by pattern matching and joins, summed over the whole evaluation
+    pub max_intermediate_bindings: Option<usize>,
+    /// Solutions (or constructed triples) in the result
+    pub max_results: Option<usize>,
+    /// Return the solutions found so far instead of [`SparqlError::LimitExceeded`]
+    pub partial_results: bool,
+}
+
+impl QueryLimits {
+    pub fn with_max_execution_time(mut self, max_execution_time: Duration) -> Self {
+        self.max_execution_time = Some(max_execution_time);
+        self
+    }
+
+    pub fn with_max_intermediate_bindings(mut self, max_intermediate_bindings: usize) -> Self {
+        self.max_intermediate_bindings = Some(max_intermediate_bindings);
+        self
+    }
+
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    pub fn with_partial_results(mut self, partial_results: bool) -> Self {
+        self.partial_results = partial_results;
+        self
+    }
+
+    /// Limits no looser than either `self` or `other` (the partial flag comes from `other`)
+    ///
+    /// リクエストごとの上限はサーバー全体の上限を緩められない
+    pub fn tightened_by(self, other: QueryLimits) -> Self {
+        fn min<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+        Self {
+            max_execution_time: min(self.max_execution_time, other.max_execution_time),
+            max_intermediate_bindings: min(self.max_intermediate_bindings, other.max_intermediate_bindings),
+            max_results: min(self.max_results, other.max_results),
+            partial_results: other.partial_results,
+        }
+    }
+}
+
+/// Which limit stopped an evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    ExecutionTime,
+    IntermediateBindings,
+    Results,
+}
+
+impl fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LimitKind::ExecutionTime => "execution time",
+            LimitKind::IntermediateBindings => "intermediate bindings",
+            LimitKind::Results => "results",
+        })
+    }
+}
+
+/// Query result with the limit that truncated it, if any
+#[derive(Debug, Clone)]
+pub struct LimitedResult {
+    pub result: crate::QueryResult,
+    /// Set when `partial_results` allowed returning an incomplete result
+    pub truncated: Option<LimitKind>,
+}
+
+/// Running tally of one evaluation against its limits
+#[derive(Debug, Default)]
+pub(crate) struct QueryBudget {
+    limits: QueryLimits,
+    deadline: Option<Instant>,
+    bindings: AtomicUsize,
+    exceeded: OnceLock<LimitKind>,
+}
+
+impl QueryBudget {
+    /// Start the clock of an evaluation
+    pub(crate) fn start(limits: QueryLimits) -> Self {
+        Self { limits, deadline: limits.max_execution_time.map(|max| Instant::now() + max), ..Self::default() }
+    }
+
+    pub(crate) fn limits(&self) -> QueryLimits {
+        self.limits
+    }
+
+    pub(crate) fn exceeded(&self) -> Option<LimitKind> {
+        self.exceeded.get().copied()
+    }
+
+    /// Record that `kind` was exceeded (the first limit hit is reported)
+    pub(crate) fn trip(&self, kind: LimitKind) {
+        let _ = self.exceeded.set(kind);
+    }
+
+    /// Whether evaluation should stop producing solutions
+    pub(crate) fn exhausted(&self) -> bool {
+        if self.exceeded.get().is_some() {
+            return true;
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                self.trip(LimitKind::ExecutionTime);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Count `count` intermediate solutions
+    pub(crate) fn charge(&self, count: usize) {
+        if let Some(max) = self.limits.max_intermediate_bindings {
+            if self.bindings.fetch_add(count, Ordering::Relaxed) + count > max {
+                self.trip(LimitKind::IntermediateBindings);
+            }
+        }
+    }
+
+    /// Stop evaluating once a limit was hit, unless partial results were requested
+    pub(crate) fn check(&self) -> Result<(), SparqlError> {
+        match self.exhausted() {
+            true if !self.limits.partial_results => Err(SparqlError::LimitExceeded(self.exceeded().unwrap_or(LimitKind::ExecutionTime))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_limits_cannot_loosen_server_limits() {
+        let server = QueryLimits::default().with_max_execution_time(Duration::from_secs(30)).with_max_intermediate_bindings(1000);
+        let request = QueryLimits::default().with_max_execution_time(Duration::from_secs(60)).with_max_results(10).with_partial_results(true);
+        let limits = server.tightened_by(request);
+        assert_eq!(limits.max_execution_time, Some(Duration::from_secs(30)));
+        assert_eq!(limits.max_intermediate_bindings, Some(1000));
+        assert_eq!(limits.max_results, Some(10));
+        assert!(limits.partial_results);
+
+        let budget = QueryBudget::start(QueryLimits::default().with_max_intermediate_bindings(2));
+        budget.charge(2);
+        assert!(!budget.exhausted());
+        budget.charge(1);
+        assert!(budget.exhausted());
+        assert!(matches!(budget.check(), Err(SparqlError::LimitExceeded(LimitKind::IntermediateBindings))));
+    }
+}
@@ -0,0 +1,150 @@
+//! CONSTRUCT materialization
+//!
+//! CONSTRUCT クエリの結果を名前付きグラフに書き込み、導出グラフ (マテリアライズドビュー) として保持する。
+//! 書き込むトリプルの出所は `Provenance::Inferred` で、`rule` にクエリ本文を記録する。
+//! 再計算ではグラフの内容を新しい結果で置き換える (クエリからは置き換え前の内容も見える)
+
+use crate::parser::{DefaultSparqlParser, QueryType};
+use crate::{execute_query, QueryResult, SparqlError, SparqlParser};
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Reasoning level recorded on materialized triples
+pub const MATERIALIZED_REASONING_LEVEL: &str = "sparql-construct";
+
+/// A CONSTRUCT query whose result is kept in a graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaterializedView {
+    pub name: String,
+    /// CONSTRUCT query (with its PREFIX declarations)
+    pub query: String,
+    /// Graph receiving the result (named or inferred graphs only)
+    pub graph: GraphId,
+    /// Refresh period when the view is kept up to date on a schedule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_interval_secs: Option<u64>,
+}
+
+/// Outcome of materializing a view
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaterializationReport {
+    pub graph: GraphId,
+    /// Distinct triples written
+    pub triples: usize,
+    /// Triples the graph held before
+    pub replaced: usize,
+    /// Unix timestamp in milliseconds
+    pub refreshed_at: u64,
+}
+
+impl MaterializedView {
+    /// View of `query` written to `graph`
+    ///
+    /// CONSTRUCT 以外のクエリと、既定グラフ・センサーグラフへの書き込みは受け付けない
+    pub fn new(name: impl Into<String>, query: impl Into<String>, graph: GraphId) -> Result<Self, SparqlError> {
+        let query = query.into();
+        let parsed = DefaultSparqlParser.parse(&query)?;
+        if !matches!(parsed.query_type, QueryType::Construct(_)) {
+            return Err(SparqlError::UnsupportedFeature("only CONSTRUCT queries can be materialized".to_string()));
+        }
+        if matches!(graph, GraphId::Default | GraphId::Sensor(_)) {
+            return Err(SparqlError::UnsupportedFeature(format!("cannot materialize into {:?}; use a named or inferred graph", graph)));
+        }
+        Ok(Self { name: name.into(), query, graph, refresh_interval_secs: None })
+    }
+
+    pub fn with_refresh_interval(mut self, secs: u64) -> Self {
+        self.refresh_interval_secs = Some(secs.max(1));
+        self
+    }
+
+    /// Run the query and replace the view graph with its result
+    ///
+    /// クエリが失敗した場合はグラフを変更しない
+    pub fn refresh(&self, store: &mut RdfStore) -> Result<MaterializationReport, SparqlError> {
+        let triples = match execute_query(&self.query, store)? {
+            QueryResult::Construct { triples } => triples,
+            _ => return Err(SparqlError::UnsupportedFeature("only CONSTRUCT queries can be materialized".to_string())),
+        };
+        let mut seen = HashSet::new();
+        let triples: Vec<_> = triples.into_iter().filter(|triple| seen.insert(triple.clone())).collect();
+
+        let replaced = store.get_graph(&self.graph).len();
+        store.clear_graph(&self.graph);
+        let provenance = Provenance::Inferred {
+            rule: self.query.clone(),
+            reasoning_level: MATERIALIZED_REASONING_LEVEL.to_string(),
+            evidence: Vec::new(),
+            confidence: None,
+        };
+        let count = triples.len();
+        store.insert_batch(triples, self.graph.clone(), provenance);
+
+        Ok(MaterializationReport {
+            graph: self.graph.clone(),
+            triples: count,
+            replaced,
+            refreshed_at: chrono::Utc::now().timestamp_millis().max(0) as u64,
+        })
+    }
+}
+
+/// Materialize `query` into `graph` once
+pub fn materialize_construct(query: &str, store: &mut RdfStore, graph: GraphId) -> Result<MaterializationReport, SparqlError> {
+    MaterializedView::new("construct", query, graph)?.refresh(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::Triple;
+
+    const QUERY: &str = r#"
+        PREFIX ex: <http://example.org/>
+        CONSTRUCT {
+            ?host ex:talksTo ?dest .
+        }
+        WHERE {
+            ?conn ex:source ?host .
+            ?conn ex:dest ?dest .
+        }
+    "#;
+
+    fn insert(store: &mut RdfStore, s: &str, p: &str, o: &str) {
+        let triple = Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() };
+        store.insert(triple, GraphId::Default, Provenance::Sensor { source: "fw".to_string(), confidence: None });
+    }
+
+    #[test]
+    fn test_refresh_replaces_view_graph_with_inferred_triples() {
+        let mut store = RdfStore::new();
+        insert(&mut store, "http://example.org/c1", "http://example.org/source", "http://example.org/h1");
+        insert(&mut store, "http://example.org/c1", "http://example.org/dest", "http://example.org/h2");
+        let graph = GraphId::Named("http://example.org/views/talks-to".to_string());
+        let view = MaterializedView::new("talks_to", QUERY, graph.clone()).unwrap();
+
+        let report = view.refresh(&mut store).unwrap();
+        assert_eq!((report.triples, report.replaced), (1, 0));
+        let stored = store.get_graph(&graph);
+        assert_eq!(stored[0].triple.subject.as_str(), "http://example.org/h1");
+        assert!(matches!(&stored[0].provenance, Provenance::Inferred { rule, reasoning_level, .. }
+            if rule == QUERY && reasoning_level == MATERIALIZED_REASONING_LEVEL));
+
+        insert(&mut store, "http://example.org/c2", "http://example.org/source", "http://example.org/h3");
+        insert(&mut store, "http://example.org/c2", "http://example.org/dest", "http://example.org/h2");
+        let report = view.refresh(&mut store).unwrap();
+        assert_eq!((report.triples, report.replaced), (2, 1));
+        assert_eq!(store.get_graph(&graph).len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_non_construct_queries_and_base_graphs() {
+        let graph = GraphId::Named("http://example.org/views/v".to_string());
+        let select = "PREFIX ex: <http://example.org/>\nSELECT ?s WHERE {\n?s ex:p ?o .\n}";
+        assert!(matches!(MaterializedView::new("v", select, graph), Err(SparqlError::UnsupportedFeature(_))));
+        assert!(MaterializedView::new("v", QUERY, GraphId::Default).is_err());
+        assert!(materialize_construct(QUERY, &mut RdfStore::new(), GraphId::Inferred("views".to_string())).is_ok());
+    }
+}
@@ -1,8 +1,9 @@
 //! SPARQL クエリ最適化
 
 use crate::algebra::Algebra;
-use crate::parser::{Expression, Variable, TriplePattern, Term};
-use std::collections::HashMap;
+use crate::parser::{Expression, TriplePattern, Term, Iri};
+use fukurow_store::store::RdfStore;
+use std::collections::{HashMap, HashSet};
 use crate::SparqlError;
 
 /// 最適化ルール
@@ -39,6 +40,82 @@ pub struct QueryStats {
     pub predicate_selectivities: HashMap<String, f64>,
 }
 
+impl QueryStats {
+    /// Triple count and per-predicate selectivities of a store
+    ///
+    /// `prefixes` はクエリの PREFIX 宣言。接頭辞付き名のままの述語 (`ex:p`) でも引けるよう別名を登録する
+    pub fn from_store(store: &RdfStore, prefixes: &HashMap<String, Iri>) -> Self {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut triple_count = 0;
+        for stored in store.all_triples().values().flatten() {
+            *counts.entry(stored.triple.predicate.to_string()).or_default() += 1;
+            triple_count += 1;
+        }
+
+        let mut predicate_selectivities = HashMap::new();
+        for (predicate, count) in counts {
+            let selectivity = count as f64 / triple_count.max(1) as f64;
+            for (prefix, namespace) in prefixes {
+                if let Some(local) = predicate.strip_prefix(namespace.0.as_str()) {
+                    predicate_selectivities.insert(format!("{}:{}", prefix, local), selectivity);
+                }
+            }
+            predicate_selectivities.insert(predicate, selectivity);
+        }
+
+        Self {
+            triple_count,
+            variable_count: 0,
+            selectivity_estimates: HashMap::new(),
+            predicate_selectivities,
+        }
+    }
+
+    /// Estimated fraction of the store matching `pattern` (1.0 = every triple)
+    pub fn pattern_selectivity(&self, pattern: &TriplePattern) -> f64 {
+        let mut selectivity = 1.0;
+
+        // Subject の選択度
+        if is_constant(&pattern.subject) {
+            selectivity *= 0.1; // IRI指定で10分の1
+        }
+
+        // Predicate の選択度
+        let predicate_key = match &pattern.predicate {
+            Term::Iri(iri) => Some(iri.to_string()),
+            Term::PrefixedName(prefix, local) => Some(format!("{}:{}", prefix, local)),
+            _ => None,
+        };
+        if let Some(key) = predicate_key {
+            selectivity *= self.predicate_selectivities.get(&key).unwrap_or(&0.5);
+        }
+
+        // Object の選択度
+        if let Term::Literal(_) = &pattern.object {
+            selectivity *= 0.2;
+        } else if is_constant(&pattern.object) {
+            selectivity *= 0.1;
+        }
+
+        selectivity
+    }
+}
+
+fn is_constant(term: &Term) -> bool {
+    matches!(term, Term::Iri(_) | Term::PrefixedName(..))
+}
+
+/// Variables (and blank nodes, which act as variables) of a triple pattern
+pub(crate) fn pattern_variables(pattern: &TriplePattern) -> Vec<String> {
+    [&pattern.subject, &pattern.predicate, &pattern.object].into_iter()
+        .filter_map(|term| match term {
+            Term::Variable(var) => Some(var.0.clone()),
+            Term::BlankNode(label) => Some(format!("_:{}", label)),
+            _ => None,
+        })
+        .collect()
+}
+
 /// 最適化器トレイト
 pub trait SparqlOptimizer {
     fn optimize(&self, algebra: Algebra, stats: Option<&QueryStats>) -> Algebra;
@@ -94,14 +171,6 @@ impl DefaultSparqlOptimizer {
             Algebra::Filter(inner, expr) => {
                 // フィルタを下位にプッシュ
                 match *inner {
-                    Algebra::LeftJoin { left, right, expr: join_expr } => {
-                        // OPTIONAL の場合、フィルタを右側にプッシュ
-                        Algebra::LeftJoin {
-                            left,
-                            right: Box::new(Algebra::Filter(right, expr)),
-                            expr: join_expr,
-                        }
-                    }
                     Algebra::Union(left, right) => {
                         // UNION の場合、両側にプッシュ
                         Algebra::Union(
@@ -113,6 +182,7 @@ impl DefaultSparqlOptimizer {
                         // GRAPH の場合、内側にプッシュ
                         Algebra::Graph(graph, Box::new(Algebra::Filter(inner, expr)))
                     }
+                    // OPTIONAL の右側に押し込むと、フィルタで落ちるはずの左側だけの解が残るので押し込まない
                     _ => Algebra::Filter(inner, expr),
                 }
             }
@@ -121,44 +191,41 @@ impl DefaultSparqlOptimizer {
     }
 
     fn reorder_bgp(&self, algebra: Algebra, stats: Option<&QueryStats>) -> Algebra {
+        let Some(stats) = stats else {
+            return algebra;
+        };
         match algebra {
-            Algebra::Bgp(mut triples) => {
-                // BGP 内のトリプルパターンを選択度で並べ替え
-                if let Some(stats) = stats {
-                    triples.sort_by(|a, b| {
-                        let selectivity_a = self.estimate_triple_selectivity(a, stats);
-                        let selectivity_b = self.estimate_triple_selectivity(b, stats);
-                        selectivity_a.partial_cmp(&selectivity_b).unwrap_or(std::cmp::Ordering::Equal)
-                    });
-                }
-                Algebra::Bgp(triples)
-            }
-            _ => algebra,
+            Algebra::Bgp(triples) => Algebra::Bgp(self.order_patterns(triples, stats)),
+            other => other.map_children(|child| self.reorder_bgp(child, Some(stats))),
         }
     }
 
-    fn estimate_triple_selectivity(&self, triple: &TriplePattern, stats: &QueryStats) -> f64 {
-        let mut selectivity = 1.0;
-
-        // Subject の選択度
-        if let Term::Iri(_) = &triple.subject {
-            selectivity *= 0.1; // IRI指定で10分の1
-        }
-
-        // Predicate の選択度
-        if let Term::Iri(iri) = &triple.predicate {
-            let pred_key = iri.to_string();
-            selectivity *= stats.predicate_selectivities.get(&pred_key).unwrap_or(&0.5);
-        }
+    /// Join order of a BGP: most selective pattern first, then patterns sharing a bound variable
+    ///
+    /// 共有変数のないパターンを先に結合すると直積になるため、つながるパターンがある限りそちらを優先する
+    fn order_patterns(&self, triples: Vec<TriplePattern>, stats: &QueryStats) -> Vec<TriplePattern> {
+        let mut remaining: Vec<(usize, f64, TriplePattern)> = triples.into_iter().enumerate()
+            .map(|(position, triple)| (position, stats.pattern_selectivity(&triple), triple))
+            .collect();
+        let mut bound: HashSet<String> = HashSet::new();
+        let mut ordered = Vec::with_capacity(remaining.len());
 
-        // Object の選択度
-        if let Term::Iri(_) = &triple.object {
-            selectivity *= 0.1;
-        } else if let Term::Literal(_) = &triple.object {
-            selectivity *= 0.2;
+        while !remaining.is_empty() {
+            let connected = |triple: &TriplePattern| bound.is_empty() || pattern_variables(triple).iter().any(|var| bound.contains(var));
+            let next = (0..remaining.len())
+                .min_by(|&a, &b| {
+                    let (position_a, selectivity_a, triple_a) = &remaining[a];
+                    let (position_b, selectivity_b, triple_b) = &remaining[b];
+                    connected(triple_b).cmp(&connected(triple_a))
+                        .then(selectivity_a.partial_cmp(selectivity_b).unwrap_or(std::cmp::Ordering::Equal))
+                        .then(position_a.cmp(position_b))
+                })
+                .unwrap_or(0);
+            let (_, _, triple) = remaining.remove(next);
+            bound.extend(pattern_variables(&triple));
+            ordered.push(triple);
         }
-
-        selectivity
+        ordered
     }
 
     fn fold_constants(&self, algebra: Algebra) -> Algebra {
@@ -231,18 +298,9 @@ impl DefaultSparqlOptimizer {
                     Algebra::Bgp(triples)
                 }
             }
-            Algebra::Filter(inner, _) => {
-                let optimized_inner = self.eliminate_empty_patterns(*inner);
-                if let Algebra::Bgp(triples) = &optimized_inner {
-                    if triples.is_empty() {
-                        // 空パターンにフィルタを適用しても空
-                        optimized_inner
-                    } else {
-                        Algebra::Filter(Box::new(optimized_inner), Expression::Variable(Variable("dummy".to_string())))
-                    }
-                } else {
-                    Algebra::Filter(Box::new(optimized_inner), Expression::Variable(Variable("dummy".to_string())))
-                }
+            // 空の BGP は解を 1 つ持つので、フィルタ式は残したまま内側だけを整理する
+            Algebra::Filter(inner, expr) => {
+                Algebra::Filter(Box::new(self.eliminate_empty_patterns(*inner)), expr)
             }
             _ => algebra,
         }
@@ -7,6 +7,8 @@ use winnow::{
     token::take_while,
 };
 use std::collections::HashMap;
+use fukurow_core::prefix::{default_namespace, XSD_NAMESPACE};
+use fukurow_core::term::RdfTerm;
 
 /// SPARQL Parser trait
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -234,6 +236,35 @@ pub enum Term {
     PrefixedName(String, String), // (prefix, local_name)
 }
 
+impl Term {
+    /// Convert a stored RDF term into a query term
+    pub fn from_rdf_term(term: RdfTerm) -> Self {
+        match term {
+            RdfTerm::Iri(iri) => Term::Iri(Iri(iri)),
+            RdfTerm::BlankNode(label) => Term::BlankNode(label),
+            RdfTerm::Literal { value, datatype, language } => Term::Literal(Literal {
+                value,
+                datatype: datatype.map(Iri),
+                language,
+            }),
+        }
+    }
+
+    /// Concrete RDF term (`None` for variables and unresolved prefixed names)
+    pub fn to_rdf_term(&self) -> Option<RdfTerm> {
+        match self {
+            Term::Iri(iri) => Some(RdfTerm::Iri(iri.0.clone())),
+            Term::BlankNode(label) => Some(RdfTerm::BlankNode(label.clone())),
+            Term::Literal(literal) => Some(match (&literal.language, &literal.datatype) {
+                (Some(language), _) => RdfTerm::lang_literal(literal.value.clone(), language),
+                (None, Some(datatype)) => RdfTerm::typed_literal(literal.value.clone(), datatype.0.clone()),
+                (None, None) => RdfTerm::literal(literal.value.clone()),
+            }),
+            Term::Variable(_) | Term::PrefixedName(..) => None,
+        }
+    }
+}
+
 /// Triple Pattern
 #[derive(Debug, Clone, PartialEq)]
 pub struct TriplePattern {
@@ -263,6 +294,8 @@ pub enum GraphPattern {
     Filter(Expression, Box<GraphPattern>),
     Graph(VarOrIri, Box<GraphPattern>),
     Minus(Box<GraphPattern>, Box<GraphPattern>),
+    /// Patterns evaluated in sequence and joined
+    Join(Box<GraphPattern>, Box<GraphPattern>),
     Service(VarOrIri, Box<GraphPattern>, bool), // silent flag
 }
 
@@ -304,6 +337,10 @@ pub enum Expression {
     // Exists
     Exists(Box<GraphPattern>),
     NotExists(Box<GraphPattern>),
+    /// Aggregate call in a SELECT expression or HAVING (replaced by a variable when planned)
+    Aggregate(Box<crate::algebra::Aggregate>),
+    /// XSD constructor function, e.g. `xsd:integer(?port)`
+    Cast(Iri, Box<Expression>),
 }
 
 /// Var or IRI
@@ -320,10 +357,17 @@ pub enum OrderCondition {
     Desc(Expression),
 }
 
+/// GROUP BY condition: `?x`, `STR(?x)` or `(expr AS ?alias)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupCondition {
+    pub expr: Expression,
+    pub alias: Option<Variable>,
+}
+
 /// Solution modifier
 #[derive(Debug, Clone, PartialEq)]
 pub struct SolutionModifier {
-    pub group: Option<Vec<Expression>>,
+    pub group: Option<Vec<GroupCondition>>,
     pub having: Option<Vec<Expression>>,
     pub order: Option<Vec<OrderCondition>>,
     pub limit: Option<u64>,
@@ -337,6 +381,8 @@ pub struct SolutionModifier {
 pub struct SparqlQuery {
     pub query_type: QueryType,
     pub variables: Vec<Variable>,
+    /// `(expr AS ?var)` items of the SELECT clause (their variables are also in `variables`)
+    pub select_expressions: Vec<(Variable, Expression)>,
     pub dataset: Vec<GraphRef>,
     pub where_clause: GraphPattern,
     pub solution_modifier: SolutionModifier,
@@ -357,6 +403,9 @@ pub enum QueryType {
 /// Bindings (variable -> term mapping)
 pub type Bindings = HashMap<Variable, Term>;
 
+/// Projected variables of a SELECT clause and the `(expr AS ?var)` items among them
+type SelectItems = (Vec<Variable>, Vec<(Variable, Expression)>);
+
 /// Graph reference for FROM/FROM NAMED
 #[derive(Debug, Clone, PartialEq)]
 pub enum GraphRef {
@@ -479,6 +528,858 @@ impl DefaultSparqlParser {
     }
 }
 
+/// `"v"`, `"v"@lang`, `"v"^^<dt>`, `"v"^^prefix:local` をリテラルに変換する
+fn literal_token(token: &str, prefixes: &HashMap<String, Iri>) -> Option<Term> {
+    // 接頭辞付きのデータ型は展開してから解釈する (既定の接頭辞は宣言がなくても解決する)
+    let expanded;
+    let token = match token.rsplit_once("^^") {
+        Some((lexical, datatype)) if lexical.ends_with('"') && !datatype.starts_with('<') => {
+            let (prefix, local) = datatype.split_once(':')?;
+            let namespace = match prefixes.get(prefix) {
+                Some(iri) => iri.0.as_str(),
+                None => default_namespace(prefix)?,
+            };
+            expanded = format!("{}^^<{}{}>", lexical, namespace, local);
+            expanded.as_str()
+        }
+        _ => token,
+    };
+    match RdfTerm::parse(token) {
+        // 閉じ引用符がない (空白を含むリテラルが分割された) 場合はそのまま値になるので除外
+        literal @ RdfTerm::Literal { .. } if literal != RdfTerm::literal(token) => Some(Term::from_rdf_term(literal)),
+        _ => None,
+    }
+}
+
+/// `line` が大文字小文字を区別せず `keyword` で始まるか
+fn starts_with_keyword(line: &str, keyword: &str) -> bool {
+    line.get(..keyword.len()).is_some_and(|head| head.eq_ignore_ascii_case(keyword))
+}
+
+/// Recursive-descent parser for expressions in SELECT, GROUP BY and HAVING
+struct ExpressionParser<'a> {
+    input: &'a str,
+    pos: usize,
+    prefixes: &'a HashMap<String, Iri>,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn new(input: &'a str, prefixes: &'a HashMap<String, Iri>) -> Self {
+        Self { input, pos: 0, prefixes }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.input.len() - trimmed.len();
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.rest().is_empty()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let rest = self.rest();
+        rest.get(..keyword.len()).is_some_and(|head| head.eq_ignore_ascii_case(keyword))
+            && !rest[keyword.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Case-insensitive keyword not followed by an identifier character
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matched = self.peek_keyword(keyword);
+        if matched {
+            self.pos += keyword.len();
+        }
+        matched
+    }
+
+    /// Keyword starting the next solution modifier (the clause being read ends there)
+    fn at_modifier(&mut self) -> bool {
+        ["HAVING", "ORDER", "LIMIT", "OFFSET", "VALUES"].iter().any(|keyword| self.peek_keyword(keyword))
+    }
+
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !accept(c)).unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn expression(&mut self) -> Option<Expression> {
+        let mut left = self.conjunction()?;
+        while self.eat("||") {
+            left = Expression::Or(Box::new(left), Box::new(self.conjunction()?));
+        }
+        Some(left)
+    }
+
+    fn conjunction(&mut self) -> Option<Expression> {
+        let mut left = self.relational()?;
+        while self.eat("&&") {
+            left = Expression::And(Box::new(left), Box::new(self.relational()?));
+        }
+        Some(left)
+    }
+
+    fn relational(&mut self) -> Option<Expression> {
+        let left = self.additive()?;
+        // 演算子の位置にある `<` は IRI ではなく比較演算子
+        let operator: fn(Box<Expression>, Box<Expression>) -> Expression = if self.eat("<=") {
+            Expression::LessThanOrEqual
+        } else if self.eat(">=") {
+            Expression::GreaterThanOrEqual
+        } else if self.eat("!=") {
+            Expression::NotEqual
+        } else if self.eat("=") {
+            Expression::Equal
+        } else if self.eat("<") {
+            Expression::LessThan
+        } else if self.eat(">") {
+            Expression::GreaterThan
+        } else {
+            return Some(left);
+        };
+        Some(operator(Box::new(left), Box::new(self.additive()?)))
+    }
+
+    fn additive(&mut self) -> Option<Expression> {
+        let mut left = self.multiplicative()?;
+        loop {
+            if self.eat("+") {
+                left = Expression::Add(Box::new(left), Box::new(self.multiplicative()?));
+            } else if self.eat("-") {
+                left = Expression::Subtract(Box::new(left), Box::new(self.multiplicative()?));
+            } else {
+                return Some(left);
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> Option<Expression> {
+        let mut left = self.unary()?;
+        loop {
+            if self.eat("*") {
+                left = Expression::Multiply(Box::new(left), Box::new(self.unary()?));
+            } else if self.eat("/") {
+                left = Expression::Divide(Box::new(left), Box::new(self.unary()?));
+            } else {
+                return Some(left);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Option<Expression> {
+        if self.eat("!") {
+            Some(Expression::Not(Box::new(self.unary()?)))
+        } else if self.eat("-") {
+            let zero = Expression::Literal(Literal { value: "0".to_string(), datatype: Some(Iri(format!("{}integer", XSD_NAMESPACE))), language: None });
+            Some(Expression::Subtract(Box::new(zero), Box::new(self.unary()?)))
+        } else if self.eat("+") {
+            self.unary()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Option<Expression> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if self.eat("(") {
+            let expr = self.expression()?;
+            return self.eat(")").then_some(expr);
+        }
+        if rest.starts_with('?') || rest.starts_with('$') {
+            return self.variable().map(Expression::Variable);
+        }
+        if rest.starts_with('<') {
+            let iri = self.iri()?;
+            return self.cast_or_iri(iri);
+        }
+        if rest.starts_with('"') || rest.starts_with('\'') {
+            return self.literal().map(Expression::Literal);
+        }
+        if rest.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            return self.number().map(Expression::Literal);
+        }
+
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        if self.rest().starts_with(':') {
+            self.pos += 1;
+            let local = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-');
+            let iri = self.expand(name, local)?;
+            return self.cast_or_iri(iri);
+        }
+        if self.eat("(") {
+            return self.call(name);
+        }
+        match name.to_ascii_lowercase().as_str() {
+            "true" | "false" => Some(Expression::Literal(Literal {
+                value: name.to_ascii_lowercase(),
+                datatype: Some(Iri(format!("{}boolean", XSD_NAMESPACE))),
+                language: None,
+            })),
+            _ => None,
+        }
+    }
+
+    /// `iri(expr)` is a cast (XSD constructor function), a bare IRI otherwise
+    fn cast_or_iri(&mut self, iri: Iri) -> Option<Expression> {
+        if !self.rest().starts_with('(') {
+            return Some(Expression::Iri(iri));
+        }
+        self.pos += 1;
+        let expr = self.expression()?;
+        self.eat(")").then(|| Expression::Cast(iri, Box::new(expr)))
+    }
+
+    /// Function or aggregate call; the opening parenthesis has been read
+    fn call(&mut self, name: &str) -> Option<Expression> {
+        use crate::algebra::Aggregate;
+
+        let upper = name.to_ascii_uppercase();
+        let expr = match upper.as_str() {
+            "COUNT" => {
+                let distinct = self.eat_keyword("DISTINCT");
+                let expr = if self.eat("*") { None } else { Some(Box::new(self.expression()?)) };
+                Expression::Aggregate(Box::new(Aggregate::Count { expr, distinct }))
+            }
+            "SUM" | "AVG" | "MIN" | "MAX" | "SAMPLE" => {
+                let distinct = self.eat_keyword("DISTINCT");
+                let expr = Box::new(self.expression()?);
+                Expression::Aggregate(Box::new(match upper.as_str() {
+                    "SUM" => Aggregate::Sum(expr, distinct),
+                    "AVG" => Aggregate::Avg(expr, distinct),
+                    "MIN" => Aggregate::Min(expr, distinct),
+                    "MAX" => Aggregate::Max(expr, distinct),
+                    _ => Aggregate::Sample(expr),
+                }))
+            }
+            "GROUP_CONCAT" => {
+                let distinct = self.eat_keyword("DISTINCT");
+                let expr = Box::new(self.expression()?);
+                let separator = if self.eat(";") {
+                    if !self.eat_keyword("SEPARATOR") || !self.eat("=") {
+                        return None;
+                    }
+                    self.skip_whitespace();
+                    Some(self.string()?)
+                } else {
+                    None
+                };
+                Expression::Aggregate(Box::new(Aggregate::GroupConcat { expr, distinct, separator }))
+            }
+            "BOUND" => Expression::Bound(self.variable()?),
+            "REGEX" => {
+                let text = Box::new(self.expression()?);
+                if !self.eat(",") {
+                    return None;
+                }
+                let pattern = Box::new(self.expression()?);
+                let flags = if self.eat(",") { Some(Box::new(self.expression()?)) } else { None };
+                Expression::Regex(text, pattern, flags)
+            }
+            _ => {
+                let function: fn(Box<Expression>) -> Expression = match upper.as_str() {
+                    "STR" => Expression::Str,
+                    "LANG" => Expression::Lang,
+                    "DATATYPE" => Expression::Datatype,
+                    "ISIRI" | "ISURI" => Expression::IsIri,
+                    "ISLITERAL" => Expression::IsLiteral,
+                    "ISBLANK" => Expression::IsBlank,
+                    "IRI" => Expression::IriFunc,
+                    "URI" => Expression::Uri,
+                    "BNODE" => Expression::Bnode,
+                    _ => return None,
+                };
+                function(Box::new(self.expression()?))
+            }
+        };
+        self.eat(")").then_some(expr)
+    }
+
+    fn variable(&mut self) -> Option<Variable> {
+        self.skip_whitespace();
+        if !(self.rest().starts_with('?') || self.rest().starts_with('$')) {
+            return None;
+        }
+        self.pos += 1;
+        let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+        (!name.is_empty()).then(|| Variable(name.to_string()))
+    }
+
+    fn iri(&mut self) -> Option<Iri> {
+        let rest = self.rest().strip_prefix('<')?;
+        let end = rest.find('>')?;
+        let iri = &rest[..end];
+        if iri.contains(char::is_whitespace) {
+            return None;
+        }
+        self.pos += end + 2;
+        Some(Iri(iri.to_string()))
+    }
+
+    fn expand(&self, prefix: &str, local: &str) -> Option<Iri> {
+        match self.prefixes.get(prefix) {
+            Some(namespace) => Some(Iri(format!("{}{}", namespace.0, local))),
+            None => default_namespace(prefix).map(|namespace| Iri(format!("{}{}", namespace, local))),
+        }
+    }
+
+    /// Quoted string (single or double quotes) with escapes resolved
+    fn string(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let mut value = String::new();
+        let mut escaped = false;
+        for (index, c) in rest[1..].char_indices() {
+            if escaped {
+                value.push(match c {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    other => other,
+                });
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == quote {
+                self.pos += 1 + index + c.len_utf8();
+                return Some(value);
+            } else {
+                value.push(c);
+            }
+        }
+        None
+    }
+
+    fn literal(&mut self) -> Option<Literal> {
+        let value = self.string()?;
+        if self.rest().starts_with('@') {
+            self.pos += 1;
+            let language = self.take_while(|c| c.is_ascii_alphanumeric() || c == '-');
+            return Some(Literal { value, datatype: None, language: Some(language.to_string()) });
+        }
+        if self.rest().starts_with("^^") {
+            self.pos += 2;
+            let datatype = if self.rest().starts_with('<') {
+                self.iri()?
+            } else {
+                let prefix = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !self.rest().starts_with(':') {
+                    return None;
+                }
+                self.pos += 1;
+                let local = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-');
+                self.expand(prefix, local)?
+            };
+            return Some(Literal { value, datatype: Some(datatype), language: None });
+        }
+        Some(Literal { value, datatype: None, language: None })
+    }
+
+    fn number(&mut self) -> Option<Literal> {
+        let rest = self.rest();
+        let mut end = 0;
+        let mut previous = ' ';
+        for (index, c) in rest.char_indices() {
+            let accepted = c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E'
+                || ((c == '+' || c == '-') && (previous == 'e' || previous == 'E'));
+            if !accepted {
+                break;
+            }
+            end = index + 1;
+            previous = c;
+        }
+        let text = &rest[..end];
+        let datatype = if text.contains(['e', 'E']) {
+            "double"
+        } else if text.contains('.') {
+            "decimal"
+        } else {
+            "integer"
+        };
+        text.parse::<f64>().ok()?;
+        self.pos += end;
+        Some(Literal { value: text.to_string(), datatype: Some(Iri(format!("{}{}", XSD_NAMESPACE, datatype))), language: None })
+    }
+
+    /// Items of a SELECT clause: `?x` and `(expr AS ?y)`, up to `WHERE` or `{`
+    fn select_items(&mut self) -> Option<SelectItems> {
+        let mut variables = Vec::new();
+        let mut expressions = Vec::new();
+        while !self.at_end() && !self.peek_keyword("WHERE") && !self.rest().starts_with('{') {
+            if self.eat("(") {
+                let expr = self.expression()?;
+                if !self.eat_keyword("AS") {
+                    return None;
+                }
+                let variable = self.variable()?;
+                if !self.eat(")") {
+                    return None;
+                }
+                variables.push(variable.clone());
+                expressions.push((variable, expr));
+            } else {
+                variables.push(self.variable()?);
+            }
+        }
+        Some((variables, expressions))
+    }
+
+    /// GROUP BY conditions, then an optional HAVING clause on the same line
+    fn group_conditions(&mut self) -> Option<(Vec<GroupCondition>, Option<Vec<Expression>>)> {
+        let mut conditions = Vec::new();
+        while !self.at_end() && !self.at_modifier() {
+            if self.eat("(") {
+                let expr = self.expression()?;
+                let alias = if self.eat_keyword("AS") { Some(self.variable()?) } else { None };
+                if !self.eat(")") {
+                    return None;
+                }
+                conditions.push(GroupCondition { expr, alias });
+            } else {
+                conditions.push(GroupCondition { expr: self.primary()?, alias: None });
+            }
+        }
+        if conditions.is_empty() {
+            return None;
+        }
+        let having = if self.eat_keyword("HAVING") { Some(self.constraints()?) } else { None };
+        Some((conditions, having))
+    }
+
+    /// HAVING constraints (bracketed expressions or calls)
+    fn constraints(&mut self) -> Option<Vec<Expression>> {
+        let mut constraints = Vec::new();
+        while !self.at_end() && !self.at_modifier() {
+            constraints.push(self.primary()?);
+        }
+        (!constraints.is_empty()).then_some(constraints)
+    }
+
+    /// `GROUP BY` / `HAVING` / `ORDER BY` / `LIMIT` / `OFFSET` clauses up to the end of the input
+    fn solution_modifiers(&mut self, modifier: &mut SolutionModifier) -> Option<()> {
+        while !self.at_end() {
+            if self.eat_keyword("GROUP") {
+                if !self.eat_keyword("BY") {
+                    return None;
+                }
+                let (conditions, having) = self.group_conditions()?;
+                modifier.group = Some(conditions);
+                if let Some(having) = having {
+                    modifier.having.get_or_insert_with(Vec::new).extend(having);
+                }
+            } else if self.eat_keyword("HAVING") {
+                let constraints = self.constraints()?;
+                modifier.having.get_or_insert_with(Vec::new).extend(constraints);
+            } else if self.eat_keyword("ORDER") {
+                if !self.eat_keyword("BY") {
+                    return None;
+                }
+                let mut conditions = Vec::new();
+                while !self.at_end() && !self.at_modifier() {
+                    conditions.push(self.order_condition()?);
+                }
+                if conditions.is_empty() {
+                    return None;
+                }
+                modifier.order = Some(conditions);
+            } else if self.eat_keyword("LIMIT") {
+                modifier.limit = Some(self.unsigned()?);
+            } else if self.eat_keyword("OFFSET") {
+                modifier.offset = Some(self.unsigned()?);
+            } else {
+                return None;
+            }
+        }
+        Some(())
+    }
+
+    /// `ASC(expr)`, `DESC(expr)`, or an ascending variable, call or bracketed expression
+    fn order_condition(&mut self) -> Option<OrderCondition> {
+        if self.eat_keyword("ASC") {
+            return Some(OrderCondition::Asc(self.bracketed()?));
+        }
+        if self.eat_keyword("DESC") {
+            return Some(OrderCondition::Desc(self.bracketed()?));
+        }
+        Some(OrderCondition::Asc(self.primary()?))
+    }
+
+    fn bracketed(&mut self) -> Option<Expression> {
+        if !self.eat("(") {
+            return None;
+        }
+        let expr = self.expression()?;
+        self.eat(")").then_some(expr)
+    }
+
+    fn unsigned(&mut self) -> Option<u64> {
+        self.skip_whitespace();
+        self.take_while(|c| c.is_ascii_digit()).parse().ok()
+    }
+}
+
+/// `FILTER (expr)` / `FILTER fn(...)` statement of a WHERE clause
+fn filter_constraint(statement: &str, prefixes: &HashMap<String, Iri>) -> Option<Expression> {
+    let statement = statement.trim();
+    if !starts_with_keyword(statement, "FILTER") {
+        return None;
+    }
+    let mut parser = ExpressionParser::new(&statement["FILTER".len()..], prefixes);
+    let constraint = parser.primary()?;
+    parser.eat(".");
+    parser.at_end().then_some(constraint)
+}
+
+/// Parse one `s p o` statement of a WHERE clause (`None` for anything else)
+fn where_triple(line: &str, prefixes: &HashMap<String, Iri>) -> Option<TriplePattern> {
+    let line = line.trim();
+    let line = line.strip_suffix('.').unwrap_or(line); // Remove the trailing dot
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    let subject = if parts[0].starts_with('?') {
+        Term::Variable(Variable(parts[0][1..].to_string()))
+    } else if parts[0].starts_with('<') {
+        Term::Iri(Iri(parts[0].trim_matches('<').trim_matches('>').to_string()))
+    } else if let Some(label) = parts[0].strip_prefix("_:") {
+        Term::BlankNode(label.to_string())
+    } else if let Some((prefix, local)) = parts[0].split_once(':').filter(|(_, local)| !local.contains(':')) {
+        Term::PrefixedName(prefix.to_string(), local.to_string())
+    } else {
+        return None; // 主語になれない項 (呼び出し側でエラーにする)
+    };
+
+    let predicate = if parts[1] == "a" {
+        // "a" is shorthand for rdf:type
+        Term::PrefixedName("rdf".to_string(), "type".to_string())
+    } else if let Some(name) = parts[1].strip_prefix('?') {
+        Term::Variable(Variable(name.to_string()))
+    } else if parts[1].starts_with('<') {
+        Term::Iri(Iri(parts[1].trim_matches('<').trim_matches('>').to_string()))
+    } else if parts[1].contains(':') {
+        let colon_parts: Vec<&str> = parts[1].split(':').collect();
+        if colon_parts.len() == 2 {
+            Term::PrefixedName(colon_parts[0].to_string(), colon_parts[1].to_string())
+        } else {
+            return None;
+        }
+    } else {
+        return None;
+    };
+
+    let object = if parts[2].starts_with('?') {
+        Term::Variable(Variable(parts[2][1..].to_string()))
+    } else if parts[2].starts_with('<') {
+        Term::Iri(Iri(parts[2].trim_matches('<').trim_matches('>').to_string()))
+    } else if parts[2].starts_with('"') {
+        // String literal, optionally with a language tag or datatype
+        literal_token(parts[2], prefixes)?
+    } else if let Some(label) = parts[2].strip_prefix("_:") {
+        Term::BlankNode(label.to_string())
+    } else if parts[2].contains(':') {
+        let colon_parts: Vec<&str> = parts[2].split(':').collect();
+        if colon_parts.len() == 2 {
+            Term::PrefixedName(colon_parts[0].to_string(), colon_parts[1].to_string())
+        } else {
+            return None;
+        }
+    } else {
+        return None;
+    };
+
+    Some(TriplePattern {
+        subject,
+        predicate,
+        object,
+    })
+}
+/// Kind of a nested `{ ... }` block in a WHERE clause
+#[derive(Debug, Clone, PartialEq)]
+enum GroupKind {
+    Group,
+    Optional,
+    Minus,
+    Exists,
+    NotExists,
+    Graph(VarOrIri),
+    /// `SERVICE [SILENT] endpoint`
+    Service(VarOrIri, bool),
+}
+
+/// Group graph pattern being built while reading the WHERE clause
+///
+/// 要素は出現順に畳み込む: 連続するトリプルは 1 つの BGP、OPTIONAL は直前までの
+/// パターンとの左外部結合、MINUS は直前までのパターンからの差、
+/// FILTER [NOT] EXISTS はグループ全体に掛かるフィルタ、GRAPH は内側を対象グラフに限定する。
+/// SERVICE は内側を外部エンドポイントで評価し、直前までのパターンと結合する
+#[derive(Debug)]
+struct GroupBuilder {
+    kind: GroupKind,
+    pattern: Option<GraphPattern>,
+    triples: Vec<TriplePattern>,
+    filters: Vec<Expression>,
+}
+
+impl GroupBuilder {
+    fn new(kind: GroupKind) -> Self {
+        Self { kind, pattern: None, triples: Vec::new(), filters: Vec::new() }
+    }
+
+    fn flush_triples(&mut self) {
+        if !self.triples.is_empty() {
+            let bgp = GraphPattern::Bgp(std::mem::take(&mut self.triples));
+            self.pattern = Some(join_patterns(self.pattern.take(), bgp));
+        }
+    }
+
+    fn add_group(&mut self, kind: GroupKind, child: GraphPattern) {
+        match kind {
+            GroupKind::Exists => self.filters.push(Expression::Exists(Box::new(child))),
+            GroupKind::NotExists => self.filters.push(Expression::NotExists(Box::new(child))),
+            GroupKind::Group => {
+                self.flush_triples();
+                self.pattern = Some(join_patterns(self.pattern.take(), child));
+            }
+            GroupKind::Optional => {
+                self.flush_triples();
+                let optional = GraphPattern::Optional(Box::new(child));
+                self.pattern = Some(match self.pattern.take() {
+                    Some(left) => GraphPattern::Join(Box::new(left), Box::new(optional)),
+                    None => optional,
+                });
+            }
+            GroupKind::Minus => {
+                self.flush_triples();
+                let left = self.pattern.take().unwrap_or(GraphPattern::Bgp(vec![]));
+                self.pattern = Some(GraphPattern::Minus(Box::new(left), Box::new(child)));
+            }
+            GroupKind::Graph(graph) => {
+                self.flush_triples();
+                let graph = GraphPattern::Graph(graph, Box::new(child));
+                self.pattern = Some(join_patterns(self.pattern.take(), graph));
+            }
+            GroupKind::Service(endpoint, silent) => {
+                self.flush_triples();
+                let service = GraphPattern::Service(endpoint, Box::new(child), silent);
+                self.pattern = Some(join_patterns(self.pattern.take(), service));
+            }
+        }
+    }
+
+    fn finish(mut self) -> GraphPattern {
+        self.flush_triples();
+        let pattern = self.pattern.unwrap_or(GraphPattern::Bgp(vec![]));
+        let filter = self.filters.into_iter()
+            .reduce(|left, right| Expression::And(Box::new(left), Box::new(right)));
+        match filter {
+            Some(expr) => GraphPattern::Filter(expr, Box::new(pattern)),
+            None => pattern,
+        }
+    }
+}
+
+fn join_patterns(left: Option<GraphPattern>, right: GraphPattern) -> GraphPattern {
+    match (left, right) {
+        (None, right) => right,
+        (Some(GraphPattern::Bgp(mut left)), GraphPattern::Bgp(right)) => {
+            left.extend(right);
+            GraphPattern::Bgp(left)
+        }
+        (Some(left), right) => GraphPattern::Join(Box::new(left), Box::new(right)),
+    }
+}
+
+/// `?var`, `<iri>` or `prefix:local` naming a graph or service, and the text after it
+fn var_or_iri<'a>(text: &'a str, prefixes: &HashMap<String, Iri>) -> Option<(VarOrIri, &'a str)> {
+    let text = text.trim_start();
+    let end = text.find(|c: char| c.is_whitespace() || c == '{').unwrap_or(text.len());
+    let (name, after) = text.split_at(end);
+    let name = if let Some(var) = name.strip_prefix('?') {
+        VarOrIri::Var(Variable(var.to_string()))
+    } else if name.starts_with('<') && name.ends_with('>') {
+        VarOrIri::Iri(Iri(name[1..name.len() - 1].to_string()))
+    } else {
+        let (prefix, local) = name.split_once(':')?;
+        VarOrIri::Iri(Iri(format!("{}{}", prefixes.get(prefix)?.0, local)))
+    };
+    Some((name, after))
+}
+
+/// Keyword opening a nested block, and the text after its `{`
+fn open_group<'a>(text: &'a str, prefixes: &HashMap<String, Iri>) -> Option<(GroupKind, &'a str)> {
+    if let Some(rest) = text.strip_prefix("GRAPH ") {
+        let (graph, after) = var_or_iri(rest, prefixes)?;
+        return after.trim_start().strip_prefix('{').map(|rest| (GroupKind::Graph(graph), rest));
+    }
+    if let Some(rest) = text.strip_prefix("SERVICE ") {
+        let rest = rest.trim_start();
+        let (silent, rest) = match rest.strip_prefix("SILENT ") {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let (endpoint, after) = var_or_iri(rest, prefixes)?;
+        return after.trim_start().strip_prefix('{').map(|rest| (GroupKind::Service(endpoint, silent), rest));
+    }
+
+    let keywords = [
+        ("OPTIONAL", GroupKind::Optional),
+        ("MINUS", GroupKind::Minus),
+        ("FILTER NOT EXISTS", GroupKind::NotExists),
+        ("FILTER EXISTS", GroupKind::Exists),
+        ("", GroupKind::Group),
+    ];
+    keywords.iter().find_map(|(keyword, kind)| {
+        text.strip_prefix(keyword)
+            .and_then(|rest| rest.trim_start().strip_prefix('{'))
+            .map(|rest| (kind.clone(), rest))
+    })
+}
+
+/// Start of the block keyword ending `text` (`OPTIONAL`, `GRAPH ?g`, ...), or `text.len()` without one
+///
+/// `?s ?p ?o . OPTIONAL {` のように同じ行で文に続くキーワードを `{` の前で切り出す
+fn block_keyword_start(text: &str) -> usize {
+    const KEYWORDS: [&str; 6] = ["FILTER NOT EXISTS", "FILTER EXISTS", "OPTIONAL", "MINUS", "GRAPH ", "SERVICE "];
+    KEYWORDS.iter()
+        .filter_map(|keyword| text.rfind(keyword))
+        .filter(|start| text[..*start].chars().next_back().is_none_or(char::is_whitespace))
+        .max()
+        .unwrap_or(text.len())
+}
+
+/// Push the group opened by a `{` onto the group stack
+fn push_group(kind: GroupKind, groups: &mut Vec<GroupBuilder>, state: &mut WhereState) {
+    if *state == WhereState::Pending && kind == GroupKind::Group {
+        *state = WhereState::Open;
+    } else {
+        if let Some(group) = groups.last_mut() {
+            group.flush_triples();
+        }
+        groups.push(GroupBuilder::new(kind));
+    }
+}
+
+/// Progress through the WHERE clause's outer braces
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WhereState {
+    /// `WHERE` seen, `{` not yet
+    Pending,
+    Open,
+    Closed,
+}
+
+/// Solution modifiers following the WHERE clause (`text` may be empty)
+fn trailing_modifiers(text: &str, prefixes: &HashMap<String, Iri>, modifier: &mut SolutionModifier) -> Result<(), SparqlError> {
+    ExpressionParser::new(text, prefixes).solution_modifiers(modifier)
+        .ok_or_else(|| SparqlError::ParseError(format!("Invalid solution modifier: {}", text.trim())))
+}
+
+/// Read one line of a WHERE clause into the group stack (`groups[0]` is the WHERE clause itself)
+///
+/// WHERE 句が閉じた後の残り (同じ行の `LIMIT 10` など) を返す。トリプルでも FILTER でもない文や
+/// 未対応のブロックは黙って読み飛ばさずにエラーにする
+fn parse_where_line<'a>(line: &'a str, prefixes: &HashMap<String, Iri>, groups: &mut Vec<GroupBuilder>, state: &mut WhereState) -> Result<&'a str, SparqlError> {
+    let mut rest = line.trim();
+    while !rest.is_empty() && *state != WhereState::Closed {
+        if let Some(after) = rest.strip_prefix('}') {
+            match groups.pop() {
+                Some(group) if !groups.is_empty() => {
+                    let kind = group.kind.clone();
+                    if let Some(parent) = groups.last_mut() {
+                        parent.add_group(kind, group.finish());
+                    }
+                }
+                Some(root) => {
+                    groups.push(root);
+                    *state = WhereState::Closed;
+                }
+                None => *state = WhereState::Closed,
+            }
+            rest = after.trim_start();
+        } else if let Some((kind, after)) = open_group(rest, prefixes) {
+            push_group(kind, groups, state);
+            rest = after.trim_start();
+        } else {
+            // 次のブロック境界までを `s p o . s p o .` として読む
+            let end = rest.find(['{', '}']).unwrap_or(rest.len());
+            let (text, after) = rest.split_at(end);
+            let keyword = match after.starts_with('{') {
+                true => block_keyword_start(text),
+                false => text.len(),
+            };
+            for statement in text[..keyword].split(" . ") {
+                let statement = statement.trim();
+                if statement.is_empty() || statement == "." {
+                    continue;
+                }
+                let Some(group) = groups.last_mut() else { continue };
+                if let Some(filter) = filter_constraint(statement, prefixes) {
+                    group.filters.push(filter);
+                } else if let Some(triple) = where_triple(statement, prefixes) {
+                    group.triples.push(triple);
+                } else {
+                    return Err(SparqlError::ParseError(format!("Unsupported WHERE pattern: {}", statement)));
+                }
+            }
+            if !after.starts_with('{') {
+                rest = after;
+                continue;
+            }
+            let block = &rest[keyword..];
+            let (kind, inner) = open_group(block, prefixes)
+                .ok_or_else(|| SparqlError::ParseError(format!("Unsupported block: {}", text[keyword..].trim())))?;
+            push_group(kind, groups, state);
+            rest = inner.trim_start();
+        }
+    }
+    Ok(rest)
+}
+
+/// Read one line of a CONSTRUCT template into `triples`
+///
+/// テンプレートがこの行で閉じたら `}` の後ろ (同じ行の WHERE 句など) を返す。
+/// トリプルとして読めない文は黙って読み飛ばさずにエラーにする
+fn construct_template_line<'a>(line: &'a str, prefixes: &HashMap<String, Iri>, triples: &mut Vec<TriplePattern>) -> Result<Option<&'a str>, SparqlError> {
+    let text = line.trim();
+    let text = text.strip_prefix('{').unwrap_or(text);
+    let (template, rest) = match text.find('}') {
+        Some(end) => (&text[..end], Some(text[end + 1..].trim())),
+        None => (text, None),
+    };
+    for statement in template.split(" . ") {
+        let statement = statement.trim();
+        if statement.is_empty() || statement == "." {
+            continue;
+        }
+        let triple = where_triple(statement, prefixes)
+            .ok_or_else(|| SparqlError::ParseError(format!("Unsupported CONSTRUCT template: {}", statement)))?;
+        triples.push(triple);
+    }
+    Ok(rest)
+}
+
 impl SparqlParser for DefaultSparqlParser {
     fn parse(&self, query: &str) -> Result<SparqlQuery, crate::SparqlError> {
         // Simple line-based parsing for now
@@ -487,8 +1388,11 @@ impl SparqlParser for DefaultSparqlParser {
         let mut query_type = QueryType::Select;
         let mut in_where = false;
         let mut in_construct = false;
-        let mut triples = Vec::new();
+        let mut where_state = WhereState::Pending;
+        let mut groups = vec![GroupBuilder::new(GroupKind::Group)];
         let mut construct_triples = Vec::new();
+        let mut select_expressions = Vec::new();
+        let mut modifier = SolutionModifier { group: None, having: None, order: None, limit: None, offset: None, distinct: false, reduced: false };
 
         for line in query.lines() {
             let line = line.trim();
@@ -514,153 +1418,69 @@ impl SparqlParser for DefaultSparqlParser {
                         // TODO: Handle DISTINCT
                         continue;
                     }
-                    if var_part == "*" {
-                        // SELECT * - no specific variables
-                        continue;
+                    let mut parser = ExpressionParser::new(var_part, &prefixes);
+                    // SELECT * は変数を列挙しない
+                    if !parser.eat("*") {
+                        let (vars, expressions) = parser.select_items()
+                            .ok_or_else(|| SparqlError::ParseError(format!("Invalid SELECT clause: {}", line)))?;
+                        variables.extend(vars);
+                        select_expressions.extend(expressions);
                     }
-                    for part in var_part.split_whitespace() {
-                        if part.starts_with('?') {
-                            variables.push(Variable(part[1..].to_string()));
+                    // 同じ行に続く WHERE 句 (`SELECT ?x WHERE { ... }`)
+                    if !parser.at_end() {
+                        if !parser.eat_keyword("WHERE") && !parser.rest().starts_with('{') {
+                            return Err(SparqlError::ParseError(format!("Invalid SELECT clause: {}", line)));
                         }
+                        in_where = true;
+                        let trailing = parse_where_line(parser.rest(), &prefixes, &mut groups, &mut where_state)?;
+                        trailing_modifiers(trailing, &prefixes, &mut modifier)?;
                     }
                 }
-            } else if line.starts_with("ASK") {
+            } else if let Some(rest) = line.strip_prefix("ASK") {
                 // ASK query - no variables needed, just WHERE clause
                 query_type = QueryType::Ask;
                 in_where = true;
-            } else if line.starts_with("CONSTRUCT") {
+                let rest = rest.trim_start();
+                let rest = rest.strip_prefix("WHERE").unwrap_or(rest);
+                let trailing = parse_where_line(rest, &prefixes, &mut groups, &mut where_state)?;
+                trailing_modifiers(trailing, &prefixes, &mut modifier)?;
+            } else if let Some(rest) = line.strip_prefix("WHERE") {
+                in_where = true;
+                in_construct = false; // Switch from construct to where
+                let trailing = parse_where_line(rest, &prefixes, &mut groups, &mut where_state)?;
+                trailing_modifiers(trailing, &prefixes, &mut modifier)?;
+            } else if let Some(template) = line.strip_prefix("CONSTRUCT").or(in_construct.then_some(line)) {
                 // CONSTRUCT query - parse construct template
                 query_type = QueryType::Construct(vec![]);
                 in_construct = true;
-            } else if line.starts_with("WHERE") {
-                in_where = true;
-                in_construct = false; // Switch from construct to where
-            } else if in_construct && line.trim().ends_with('.') {
-                // Parse construct triple template
-                let line = line.trim();
-                let line = &line[..line.len()-1]; // Remove the trailing dot
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 3 {
-                    let subject = if parts[0].starts_with('?') {
-                        Term::Variable(Variable(parts[0][1..].to_string()))
-                    } else if parts[0].starts_with('<') {
-                        Term::Iri(Iri(parts[0].trim_matches('<').trim_matches('>').to_string()))
-                    } else {
-                        continue; // Skip complex patterns for now
-                    };
-
-                    let predicate = if parts[1] == "a" {
-                        // "a" is shorthand for rdf:type
-                        Term::PrefixedName("rdf".to_string(), "type".to_string())
-                    } else if parts[1].starts_with('<') {
-                        Term::Iri(Iri(parts[1].trim_matches('<').trim_matches('>').to_string()))
-                    } else if parts[1].contains(':') {
-                        let colon_parts: Vec<&str> = parts[1].split(':').collect();
-                        if colon_parts.len() == 2 {
-                            Term::PrefixedName(colon_parts[0].to_string(), colon_parts[1].to_string())
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    };
-
-                    let object = if parts[2].starts_with('?') {
-                        Term::Variable(Variable(parts[2][1..].to_string()))
-                    } else if parts[2].starts_with('<') {
-                        Term::Iri(Iri(parts[2].trim_matches('<').trim_matches('>').to_string()))
-                    } else if parts[2].starts_with('"') && parts[2].ends_with('"') {
-                        let value = parts[2].trim_matches('"').to_string();
-                        Term::Literal(Literal {
-                            value,
-                            datatype: None,
-                            language: None,
-                        })
-                    } else if parts[2].contains(':') {
-                        let colon_parts: Vec<&str> = parts[2].split(':').collect();
-                        if colon_parts.len() == 2 {
-                            Term::PrefixedName(colon_parts[0].to_string(), colon_parts[1].to_string())
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    };
-
-                           println!("DEBUG: Adding construct triple: subject={:?}, predicate={:?}, object={:?}", subject, predicate, object);
-                           construct_triples.push(TriplePattern {
-                               subject,
-                               predicate,
-                               object,
-                           });
-                }
-            } else if in_where && line.trim().ends_with('.') {
-                // Parse triple pattern (very simple)
-                let line = line.trim();
-                let line = &line[..line.len()-1]; // Remove the trailing dot
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 3 {
-                    let subject = if parts[0].starts_with('?') {
-                        Term::Variable(Variable(parts[0][1..].to_string()))
-                    } else if parts[0].starts_with('<') {
-                        Term::Iri(Iri(parts[0].trim_matches('<').trim_matches('>').to_string()))
-                    } else {
-                        continue; // Skip complex patterns for now
-                    };
-
-                    let predicate = if parts[1] == "a" {
-                        // "a" is shorthand for rdf:type
-                        Term::PrefixedName("rdf".to_string(), "type".to_string())
-                    } else if parts[1].starts_with('<') {
-                        Term::Iri(Iri(parts[1].trim_matches('<').trim_matches('>').to_string()))
-                    } else if parts[1].contains(':') {
-                        let colon_parts: Vec<&str> = parts[1].split(':').collect();
-                        if colon_parts.len() == 2 {
-                            Term::PrefixedName(colon_parts[0].to_string(), colon_parts[1].to_string())
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    };
-
-                    let object = if parts[2].starts_with('?') {
-                        Term::Variable(Variable(parts[2][1..].to_string()))
-                    } else if parts[2].starts_with('<') {
-                        Term::Iri(Iri(parts[2].trim_matches('<').trim_matches('>').to_string()))
-                    } else if parts[2].starts_with('"') && parts[2].ends_with('"') {
-                        // Simple string literal
-                        let value = parts[2].trim_matches('"').to_string();
-                        Term::Literal(Literal {
-                            value,
-                            datatype: None,
-                            language: None,
-                        })
-                    } else if parts[2].contains(':') {
-                        let colon_parts: Vec<&str> = parts[2].split(':').collect();
-                        if colon_parts.len() == 2 {
-                            Term::PrefixedName(colon_parts[0].to_string(), colon_parts[1].to_string())
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    };
-
-                    triples.push(TriplePattern {
-                        subject,
-                        predicate,
-                        object,
-                    });
+                // テンプレートが閉じたら、同じ行に続く WHERE 句を読む
+                if let Some(rest) = construct_template_line(template, &prefixes, &mut construct_triples)? {
+                    in_construct = false;
+                    if !rest.is_empty() {
+                        in_where = true;
+                        let rest = rest.strip_prefix("WHERE").unwrap_or(rest);
+                        let trailing = parse_where_line(rest, &prefixes, &mut groups, &mut where_state)?;
+                        trailing_modifiers(trailing, &prefixes, &mut modifier)?;
+                    }
                 }
+            } else if ["GROUP BY", "HAVING", "ORDER BY", "LIMIT", "OFFSET"].iter().any(|keyword| starts_with_keyword(line, keyword)) {
+                trailing_modifiers(line, &prefixes, &mut modifier)?;
+            } else if in_where {
+                let trailing = parse_where_line(line, &prefixes, &mut groups, &mut where_state)?;
+                trailing_modifiers(trailing, &prefixes, &mut modifier)?;
             }
         }
 
-        println!("DEBUG: Parsed query type: {:?}", query_type);
-        println!("DEBUG: Parsed variables: {:?}", variables);
-        println!("DEBUG: Parsed triples: {:?}", triples);
-        println!("DEBUG: Parsed construct triples: {:?}", construct_triples);
-        println!("DEBUG: Parsed prefixes: {:?}", prefixes);
+        // 閉じられていないブロックは外側のグループに畳み込む
+        let mut where_clause = GraphPattern::Bgp(vec![]);
+        while let Some(group) = groups.pop() {
+            let kind = group.kind.clone();
+            let pattern = group.finish();
+            match groups.last_mut() {
+                Some(parent) => parent.add_group(kind, pattern),
+                None => where_clause = pattern,
+            }
+        }
 
         // Set construct templates for CONSTRUCT queries
         let final_query_type = match query_type {
@@ -671,17 +1491,10 @@ impl SparqlParser for DefaultSparqlParser {
         Ok(SparqlQuery {
             query_type: final_query_type,
             variables,
+            select_expressions,
             dataset: vec![],
-            where_clause: GraphPattern::Bgp(triples),
-            solution_modifier: SolutionModifier {
-                group: None,
-                having: None,
-                order: None,
-                limit: None,
-                offset: None,
-                distinct: false,
-                reduced: false,
-            },
+            where_clause,
+            solution_modifier: modifier,
             values: None,
             base_iri: None,
             prefixes,
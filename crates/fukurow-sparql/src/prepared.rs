@@ -0,0 +1,489 @@
+//! Prepared queries and the query plan cache
+//!
+//! ダッシュボードのように同じクエリ文字列を繰り返し実行する用途では、毎回の構文解析と
+//! 最適化を省くため [`QueryCache`] がクエリテキストをキーに最適化済みの代数を保持する。
+//! [`PreparedQuery::execute_with`] は変数に定数をバインドしてから評価する
+
+use crate::algebra::{Aggregate, Algebra, DefaultPlanBuilder, PlanBuilder};
+use crate::evaluator::{substitute_pattern, DefaultSparqlEvaluator, QueryResult};
+use crate::optimizer::{DefaultSparqlOptimizer, SparqlOptimizer};
+use crate::parser::{
+    Bindings, DefaultSparqlParser, Expression, Iri, Literal, OrderCondition, QueryType, SparqlParser,
+    SparqlQuery, Term, TriplePattern, VarOrIri,
+};
+use crate::SparqlError;
+use fukurow_core::prefix::default_namespace;
+use fukurow_store::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+
+/// Parsed and optimized query, ready to be executed many times
+#[derive(Debug, Clone)]
+pub struct PreparedQuery {
+    text: String,
+    query: SparqlQuery,
+    algebra: Algebra,
+}
+
+impl PreparedQuery {
+    /// Parse, plan and optimize `text`
+    pub fn prepare(text: &str) -> Result<Self, SparqlError> {
+        let query = DefaultSparqlParser.parse(text)?;
+        let algebra = DefaultSparqlOptimizer::default().optimize(DefaultPlanBuilder.to_algebra(&query)?, None);
+        Ok(Self { text: text.to_string(), query, algebra })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn query(&self) -> &SparqlQuery {
+        &self.query
+    }
+
+    pub fn query_type(&self) -> &QueryType {
+        &self.query.query_type
+    }
+
+    /// Optimized algebra shared by every execution
+    pub fn algebra(&self) -> &Algebra {
+        &self.algebra
+    }
+
+    /// Execute without parameters
+    pub fn execute(&self, store: &RdfStore) -> Result<QueryResult, SparqlError> {
+        DefaultSparqlEvaluator::new().evaluate_planned(&self.query, &self.algebra, store)
+    }
+
+    /// Execute with `params` substituted for the matching variables
+    ///
+    /// パラメータは IRI・リテラル・接頭辞付き名にのみバインドできる。SELECT で投影される
+    /// パラメータは、置換で消えた列として各解に値を補って返す
+    pub fn execute_with(&self, params: &Bindings, store: &RdfStore) -> Result<QueryResult, SparqlError> {
+        if params.is_empty() {
+            return self.execute(store);
+        }
+
+        let params = self.resolve_params(params)?;
+        let mut query = self.query.clone();
+        if let QueryType::Construct(templates) = &mut query.query_type {
+            *templates = templates.iter().map(|template| bind_triple(template, &params)).collect();
+        }
+        let algebra = bind_algebra(&self.algebra, &params);
+
+        let mut result = DefaultSparqlEvaluator::new().evaluate_planned(&query, &algebra, store)?;
+        if let (QueryType::Select, QueryResult::Select { variables, bindings }) = (&self.query.query_type, &mut result) {
+            for (var, value) in &params {
+                if !self.query.variables.is_empty() && !self.query.variables.contains(var) {
+                    continue;
+                }
+                if !variables.contains(var) {
+                    variables.push(var.clone());
+                }
+                for binding in bindings.iter_mut() {
+                    binding.insert(var.clone(), value.clone());
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Check parameter values and expand prefixed names with the query's prefixes
+    fn resolve_params(&self, params: &Bindings) -> Result<Bindings, SparqlError> {
+        params.iter().map(|(var, value)| {
+            let value = match value {
+                Term::Iri(_) | Term::Literal(_) => value.clone(),
+                Term::PrefixedName(prefix, local) => match self.query.prefixes.get(prefix).map(|namespace| namespace.0.as_str()).or_else(|| default_namespace(prefix)) {
+                    Some(namespace) => Term::Iri(Iri(format!("{}{}", namespace, local))),
+                    None => return Err(SparqlError::EvaluationError(format!("unknown prefix '{}' in parameter ?{}", prefix, var.0))),
+                },
+                Term::Variable(_) | Term::BlankNode(_) => {
+                    return Err(SparqlError::EvaluationError(format!("parameter ?{} must be bound to an IRI or literal", var.0)));
+                }
+            };
+            Ok((var.clone(), value))
+        }).collect()
+    }
+}
+
+/// Cache hit statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from the cache (0.0 before the first lookup)
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    query: Arc<PreparedQuery>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+/// LRU cache of prepared queries keyed by query text
+///
+/// 構文解析はロックの外で行うため、キャッシュミスが他のスレッドのヒットを待たせない
+#[derive(Debug)]
+pub struct QueryCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl QueryCache {
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    /// Create a cache holding at most `capacity` queries (0 disables caching)
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, state: Mutex::new(CacheState::default()) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Cached query for `text`, preparing and caching it on a miss
+    ///
+    /// 構文エラーのクエリはキャッシュしない
+    pub fn prepare(&self, text: &str) -> Result<Arc<PreparedQuery>, SparqlError> {
+        {
+            let mut state = self.lock();
+            state.clock += 1;
+            let now = state.clock;
+            if let Some(entry) = state.entries.get_mut(text) {
+                entry.last_used = now;
+                let query = entry.query.clone();
+                state.hits += 1;
+                return Ok(query);
+            }
+            state.misses += 1;
+        }
+
+        let prepared = Arc::new(PreparedQuery::prepare(text)?);
+        if self.capacity == 0 {
+            return Ok(prepared);
+        }
+
+        let mut state = self.lock();
+        state.clock += 1;
+        let now = state.clock;
+        // 並行して同じクエリが準備された場合は先に入ったものを共有する
+        if let Some(entry) = state.entries.get_mut(text) {
+            entry.last_used = now;
+            return Ok(entry.query.clone());
+        }
+        while state.entries.len() >= self.capacity {
+            let oldest = state.entries.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => {
+                    state.entries.remove(&key);
+                    state.evictions += 1;
+                }
+                None => break,
+            }
+        }
+        state.entries.insert(text.to_string(), CacheEntry { query: prepared.clone(), last_used: now });
+        Ok(prepared)
+    }
+
+    /// Prepare (or reuse) `text` and execute it
+    pub fn execute(&self, text: &str, store: &RdfStore) -> Result<QueryResult, SparqlError> {
+        self.prepare(text)?.execute(store)
+    }
+
+    /// Prepare (or reuse) `text` and execute it with `params` bound
+    pub fn execute_with(&self, text: &str, params: &Bindings, store: &RdfStore) -> Result<QueryResult, SparqlError> {
+        self.prepare(text)?.execute_with(params, store)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            hits: state.hits,
+            misses: state.misses,
+            evictions: state.evictions,
+            entries: state.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    /// Drop the cached plan for `text`; returns whether it was cached
+    pub fn invalidate(&self, text: &str) -> bool {
+        self.lock().entries.remove(text).is_some()
+    }
+
+    /// Drop every cached plan (statistics are kept)
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn bind_term(term: &Term, params: &Bindings) -> Term {
+    match term {
+        Term::Variable(var) => params.get(var).cloned().unwrap_or_else(|| term.clone()),
+        _ => term.clone(),
+    }
+}
+
+fn bind_triple(triple: &TriplePattern, params: &Bindings) -> TriplePattern {
+    TriplePattern {
+        subject: bind_term(&triple.subject, params),
+        predicate: bind_term(&triple.predicate, params),
+        object: bind_term(&triple.object, params),
+    }
+}
+
+fn bind_graph_name(graph: &VarOrIri, params: &Bindings) -> VarOrIri {
+    match graph {
+        VarOrIri::Var(var) => match params.get(var) {
+            Some(Term::Iri(iri)) => VarOrIri::Iri(iri.clone()),
+            _ => graph.clone(),
+        },
+        VarOrIri::Iri(_) => graph.clone(),
+    }
+}
+
+fn bind_algebra(algebra: &Algebra, params: &Bindings) -> Algebra {
+    let bind = |inner: &Algebra| Box::new(bind_algebra(inner, params));
+    let bind_expr = |expr: &Expression| bind_expression(expr, params);
+
+    match algebra {
+        Algebra::Bgp(triples) => Algebra::Bgp(triples.iter().map(|triple| bind_triple(triple, params)).collect()),
+        Algebra::Join(left, right) => Algebra::Join(bind(left), bind(right)),
+        Algebra::LeftJoin { left, right, expr } => Algebra::LeftJoin {
+            left: bind(left),
+            right: bind(right),
+            expr: expr.as_ref().map(bind_expr),
+        },
+        Algebra::Union(left, right) => Algebra::Union(bind(left), bind(right)),
+        Algebra::Filter(inner, expr) => Algebra::Filter(bind(inner), bind_expr(expr)),
+        Algebra::Project(inner, vars) => Algebra::Project(bind(inner), vars.clone()),
+        Algebra::Extend(inner, var, expr) => Algebra::Extend(bind(inner), var.clone(), bind_expr(expr)),
+        Algebra::Slice { input, offset, limit } => Algebra::Slice { input: bind(input), offset: *offset, limit: *limit },
+        Algebra::OrderBy(inner, conditions) => Algebra::OrderBy(bind(inner), conditions.iter().map(|condition| match condition {
+            OrderCondition::Asc(expr) => OrderCondition::Asc(bind_expr(expr)),
+            OrderCondition::Desc(expr) => OrderCondition::Desc(bind_expr(expr)),
+        }).collect()),
+        Algebra::Distinct(inner) => Algebra::Distinct(bind(inner)),
+        Algebra::Reduced(inner) => Algebra::Reduced(bind(inner)),
+        Algebra::Group { input, keys, aggs } => Algebra::Group {
+            input: bind(input),
+            keys: keys.iter().map(bind_expr).collect(),
+            aggs: aggs.iter().map(|(var, agg)| (var.clone(), bind_aggregate(agg, params))).collect(),
+        },
+        Algebra::Graph(graph, inner) => Algebra::Graph(bind_graph_name(graph, params), bind(inner)),
+        Algebra::Minus(left, right) => Algebra::Minus(bind(left), bind(right)),
+        Algebra::Service(endpoint, inner, silent) => Algebra::Service(bind_graph_name(endpoint, params), bind(inner), *silent),
+        // パラメータと矛盾する行を落とし、残りの行からはパラメータ列を除く
+        Algebra::Values(rows) => Algebra::Values(rows.iter()
+            .filter(|row| params.iter().all(|(var, value)| row.get(var).is_none_or(|bound| bound == value)))
+            .map(|row| row.iter()
+                .filter(|(var, _)| !params.contains_key(*var))
+                .map(|(var, term)| (var.clone(), term.clone()))
+                .collect())
+            .collect()),
+    }
+}
+
+fn bind_aggregate(aggregate: &Aggregate, params: &Bindings) -> Aggregate {
+    let bind = |expr: &Expression| Box::new(bind_expression(expr, params));
+
+    match aggregate {
+        Aggregate::Count { expr, distinct } => Aggregate::Count { expr: expr.as_deref().map(bind), distinct: *distinct },
+        Aggregate::Sum(expr, distinct) => Aggregate::Sum(bind(expr), *distinct),
+        Aggregate::Avg(expr, distinct) => Aggregate::Avg(bind(expr), *distinct),
+        Aggregate::Min(expr, distinct) => Aggregate::Min(bind(expr), *distinct),
+        Aggregate::Max(expr, distinct) => Aggregate::Max(bind(expr), *distinct),
+        Aggregate::GroupConcat { expr, distinct, separator } => Aggregate::GroupConcat {
+            expr: bind(expr),
+            distinct: *distinct,
+            separator: separator.clone(),
+        },
+        Aggregate::Sample(expr) => Aggregate::Sample(bind(expr)),
+    }
+}
+
+fn bind_expression(expr: &Expression, params: &Bindings) -> Expression {
+    let bind = |inner: &Expression| Box::new(bind_expression(inner, params));
+
+    match expr {
+        Expression::Variable(var) => match params.get(var) {
+            Some(Term::Iri(iri)) => Expression::Iri(iri.clone()),
+            Some(Term::Literal(literal)) => Expression::Literal(literal.clone()),
+            _ => expr.clone(),
+        },
+        Expression::Iri(_) | Expression::Literal(_) => expr.clone(),
+        Expression::Add(left, right) => Expression::Add(bind(left), bind(right)),
+        Expression::Subtract(left, right) => Expression::Subtract(bind(left), bind(right)),
+        Expression::Multiply(left, right) => Expression::Multiply(bind(left), bind(right)),
+        Expression::Divide(left, right) => Expression::Divide(bind(left), bind(right)),
+        Expression::Equal(left, right) => Expression::Equal(bind(left), bind(right)),
+        Expression::NotEqual(left, right) => Expression::NotEqual(bind(left), bind(right)),
+        Expression::LessThan(left, right) => Expression::LessThan(bind(left), bind(right)),
+        Expression::LessThanOrEqual(left, right) => Expression::LessThanOrEqual(bind(left), bind(right)),
+        Expression::GreaterThan(left, right) => Expression::GreaterThan(bind(left), bind(right)),
+        Expression::GreaterThanOrEqual(left, right) => Expression::GreaterThanOrEqual(bind(left), bind(right)),
+        Expression::And(left, right) => Expression::And(bind(left), bind(right)),
+        Expression::Or(left, right) => Expression::Or(bind(left), bind(right)),
+        Expression::Not(inner) => Expression::Not(bind(inner)),
+        // バインド済みのパラメータは常に束縛されている
+        Expression::Bound(var) if params.contains_key(var) => Expression::Literal(Literal {
+            value: "true".to_string(),
+            datatype: Some(Iri(XSD_BOOLEAN.to_string())),
+            language: None,
+        }),
+        Expression::Bound(_) => expr.clone(),
+        Expression::IsIri(inner) => Expression::IsIri(bind(inner)),
+        Expression::IsLiteral(inner) => Expression::IsLiteral(bind(inner)),
+        Expression::IsBlank(inner) => Expression::IsBlank(bind(inner)),
+        Expression::Str(inner) => Expression::Str(bind(inner)),
+        Expression::Lang(inner) => Expression::Lang(bind(inner)),
+        Expression::Datatype(inner) => Expression::Datatype(bind(inner)),
+        Expression::IriFunc(inner) => Expression::IriFunc(bind(inner)),
+        Expression::Uri(inner) => Expression::Uri(bind(inner)),
+        Expression::Bnode(inner) => Expression::Bnode(bind(inner)),
+        Expression::Cast(iri, inner) => Expression::Cast(iri.clone(), bind(inner)),
+        Expression::Regex(text, pattern, flags) => Expression::Regex(bind(text), bind(pattern), flags.as_deref().map(bind)),
+        Expression::Exists(pattern) => Expression::Exists(Box::new(substitute_pattern(pattern, params))),
+        Expression::NotExists(pattern) => Expression::NotExists(Box::new(substitute_pattern(pattern, params))),
+        Expression::Aggregate(aggregate) => Expression::Aggregate(Box::new(bind_aggregate(aggregate, params))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Variable;
+    use fukurow_core::model::Triple;
+    use fukurow_store::provenance::{GraphId, Provenance};
+
+    const EVENTS_BY_USER: &str = r#"
+        PREFIX ex: <http://example.org/>
+        SELECT ?event ?user
+        WHERE {
+            ?event ex:user ?user .
+        }
+    "#;
+
+    fn store() -> RdfStore {
+        let mut store = RdfStore::new();
+        for (subject, user) in [("http://example.org/e1", "alice"), ("http://example.org/e2", "bob")] {
+            store.insert(Triple {
+                subject: subject.to_string(),
+                predicate: "http://example.org/user".to_string(),
+                object: user.to_string(),
+            }, GraphId::Default, Provenance::Sensor { source: "edr".to_string(), confidence: None });
+        }
+        store
+    }
+
+    fn user(name: &str) -> Term {
+        Term::Literal(Literal { value: name.to_string(), datatype: None, language: None })
+    }
+
+    fn params(name: &str, value: Term) -> Bindings {
+        Bindings::from([(Variable(name.to_string()), value)])
+    }
+
+    #[test]
+    fn test_cache_hits_and_lru_eviction() {
+        let cache = QueryCache::new(1);
+        let first = cache.prepare(EVENTS_BY_USER).unwrap();
+        let second = cache.prepare(EVENTS_BY_USER).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let ask = "PREFIX ex: <http://example.org/>\nASK WHERE { ?event ex:user ?user . }";
+        cache.prepare(ask).unwrap();
+        cache.prepare(EVENTS_BY_USER).unwrap();
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions, stats.entries), (1, 3, 2, 1));
+        assert_eq!(stats.hit_ratio(), 0.25);
+        assert!(cache.invalidate(EVENTS_BY_USER));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_execute_with_binds_parameters() {
+        let store = store();
+        let cache = QueryCache::default();
+
+        match cache.execute_with(EVENTS_BY_USER, &params("user", user("bob")), &store).unwrap() {
+            QueryResult::Select { bindings, .. } => {
+                assert_eq!(bindings.len(), 1);
+                assert_eq!(bindings[0].get(&Variable("event".to_string())), Some(&Term::Iri(Iri("http://example.org/e2".to_string()))));
+                assert_eq!(bindings[0].get(&Variable("user".to_string())), Some(&user("bob")));
+            }
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+
+        match cache.execute(EVENTS_BY_USER, &store).unwrap() {
+            QueryResult::Select { bindings, .. } => assert_eq!(bindings.len(), 2),
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[test]
+    fn test_execute_with_prefixed_name_and_invalid_parameter() {
+        let store = store();
+        let prepared = PreparedQuery::prepare(EVENTS_BY_USER).unwrap();
+
+        let event = params("event", Term::PrefixedName("ex".to_string(), "e1".to_string()));
+        match prepared.execute_with(&event, &store).unwrap() {
+            QueryResult::Select { bindings, .. } => {
+                assert_eq!(bindings.len(), 1);
+                assert_eq!(bindings[0].get(&Variable("user".to_string())), Some(&user("alice")));
+            }
+            other => panic!("Expected Select result, got {:?}", other),
+        }
+
+        let blank = params("event", Term::BlankNode("b0".to_string()));
+        assert!(matches!(prepared.execute_with(&blank, &store), Err(SparqlError::EvaluationError(_))));
+    }
+}
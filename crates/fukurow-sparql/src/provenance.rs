@@ -0,0 +1,203 @@
+//! Provenance virtual graphs
+//!
+//! `GRAPH <urn:fukurow:provenance:...> { ... }` でトリプルの出所 (センサー・推論ルール・
+//! インポート元) ごとに絞り込んで問い合わせる。仮想グラフはストア上に実体を持たず、
+//! 評価時に各トリプルの `Provenance` と照合する
+//!
+//! | IRI | 対象 |
+//! |-----|------|
+//! | `urn:fukurow:provenance:sensor` | センサー由来のすべてのトリプル |
+//! | `urn:fukurow:provenance:sensor:edr-1` | センサー `edr-1` のトリプル |
+//! | `urn:fukurow:provenance:inferred:rdfs9` | ルール `rdfs9` が推論したトリプル |
+//! | `urn:fukurow:provenance:imported:<uri>` | `<uri>` から取り込んだトリプル |
+//!
+//! 末尾に `?min_confidence=0.8` を付けると、信頼度がそれ未満 (または未設定) の
+//! センサー観測と推論を除外する。推論の信頼度は根拠から計算したもの (`fukurow_store::confidence`)
+
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::{RdfStore, StoredTriple};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// IRI prefix of every provenance virtual graph
+pub const PROVENANCE_GRAPH_PREFIX: &str = "urn:fukurow:provenance:";
+
+/// Kind of origin a virtual graph selects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProvenanceKind {
+    Sensor,
+    Inferred,
+    Imported,
+}
+
+impl ProvenanceKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ProvenanceKind::Sensor => "sensor",
+            ProvenanceKind::Inferred => "inferred",
+            ProvenanceKind::Imported => "imported",
+        }
+    }
+
+    fn of(provenance: &Provenance) -> (Self, &str) {
+        match provenance {
+            Provenance::Sensor { source, .. } => (ProvenanceKind::Sensor, source),
+            Provenance::Inferred { rule, .. } => (ProvenanceKind::Inferred, rule),
+            Provenance::Imported { source_uri, .. } => (ProvenanceKind::Imported, source_uri),
+        }
+    }
+}
+
+/// Selection of triples by origin, addressed as a graph IRI
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProvenanceGraph {
+    pub kind: ProvenanceKind,
+    /// Sensor source, rule name or import URI (`None` selects every origin of the kind)
+    pub origin: Option<String>,
+    /// Minimum confidence of sensor readings and inferences
+    pub min_confidence: Option<f64>,
+}
+
+impl ProvenanceGraph {
+    pub fn new(kind: ProvenanceKind) -> Self {
+        Self { kind, origin: None, min_confidence: None }
+    }
+
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = Some(min_confidence);
+        self
+    }
+
+    /// Parse a virtual graph IRI; `None` for ordinary graph IRIs
+    pub fn parse(iri: &str) -> Option<Self> {
+        let rest = iri.strip_prefix(PROVENANCE_GRAPH_PREFIX)?;
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+        // 取り込み元 URI 自体が `:` を含むため、種別の後ろはそのまま origin とする
+        let (kind, origin) = match path.split_once(':') {
+            Some((kind, origin)) => (kind, Some(origin)),
+            None => (path, None),
+        };
+        let kind = match kind {
+            "sensor" => ProvenanceKind::Sensor,
+            "inferred" => ProvenanceKind::Inferred,
+            "imported" => ProvenanceKind::Imported,
+            _ => return None,
+        };
+
+        let mut graph = Self::new(kind);
+        if let Some(origin) = origin.filter(|origin| !origin.is_empty()) {
+            graph = graph.with_origin(origin);
+        }
+        for param in query.into_iter().flat_map(|query| query.split('&')) {
+            match param.split_once('=') {
+                Some(("min_confidence", value)) => graph = graph.with_min_confidence(value.parse().ok()?),
+                _ => return None,
+            }
+        }
+        Some(graph)
+    }
+
+    /// Graph IRI for one concrete origin
+    pub fn for_provenance(provenance: &Provenance) -> Self {
+        let (kind, origin) = ProvenanceKind::of(provenance);
+        Self::new(kind).with_origin(origin)
+    }
+
+    pub fn matches(&self, provenance: &Provenance) -> bool {
+        let (kind, origin) = ProvenanceKind::of(provenance);
+        if kind != self.kind || self.origin.as_deref().is_some_and(|expected| expected != origin) {
+            return false;
+        }
+        self.min_confidence.is_none_or(|min| provenance.meets_confidence(min))
+    }
+
+    /// Store holding only the matching triples (graphs and provenance are kept)
+    pub fn select(&self, store: &RdfStore) -> RdfStore {
+        select_triples(store, |stored| self.matches(&stored.provenance))
+    }
+}
+
+impl fmt::Display for ProvenanceGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", PROVENANCE_GRAPH_PREFIX, self.kind.as_str())?;
+        if let Some(origin) = &self.origin {
+            write!(f, ":{}", origin)?;
+        }
+        if let Some(min) = self.min_confidence {
+            write!(f, "?min_confidence={}", min)?;
+        }
+        Ok(())
+    }
+}
+
+/// Triples addressed by a `GRAPH <iri>` clause
+///
+/// 仮想グラフでなければ、同じ名前の名前付きグラフ (`GraphId::Named`) を対象にする
+pub fn graph_view(store: &RdfStore, iri: &str) -> RdfStore {
+    match ProvenanceGraph::parse(iri) {
+        Some(graph) => graph.select(store),
+        None => select_triples(store, |stored| matches!(&stored.graph_id, GraphId::Named(name) if name == iri)),
+    }
+}
+
+/// Graph IRIs a `GRAPH ?g` clause ranges over: named graphs and the origin of every triple
+pub fn graph_names(store: &RdfStore) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for (graph_id, triples) in store.all_triples() {
+        if let GraphId::Named(name) = graph_id {
+            if !triples.is_empty() {
+                names.insert(name.clone());
+            }
+        }
+        for stored in triples {
+            names.insert(ProvenanceGraph::for_provenance(&stored.provenance).to_string());
+        }
+    }
+    names
+}
+
+fn select_triples(store: &RdfStore, keep: impl Fn(&StoredTriple) -> bool) -> RdfStore {
+    let mut view = RdfStore::new();
+    for stored in store.all_triples().values().flatten().filter(|stored| keep(stored)) {
+        view.insert_at(stored.triple.clone(), stored.graph_id.clone(), stored.provenance.clone(), stored.asserted_at);
+    }
+    view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provenance_graph_iris() {
+        let graph = ProvenanceGraph::parse("urn:fukurow:provenance:imported:https://feeds.example.org/c2.csv").unwrap();
+        assert_eq!(graph.kind, ProvenanceKind::Imported);
+        assert_eq!(graph.origin.as_deref(), Some("https://feeds.example.org/c2.csv"));
+
+        let graph = ProvenanceGraph::parse("urn:fukurow:provenance:sensor?min_confidence=0.8").unwrap();
+        assert_eq!(graph, ProvenanceGraph::new(ProvenanceKind::Sensor).with_min_confidence(0.8));
+        assert_eq!(graph.to_string(), "urn:fukurow:provenance:sensor?min_confidence=0.8");
+
+        assert!(graph.matches(&Provenance::Sensor { source: "edr-1".to_string(), confidence: Some(0.9) }));
+        assert!(!graph.matches(&Provenance::Sensor { source: "edr-1".to_string(), confidence: None }));
+        assert!(!graph.matches(&Provenance::Inferred { rule: "r".to_string(), reasoning_level: "rdfs".to_string(), evidence: vec![], confidence: None }));
+
+        let inferred = |confidence| Provenance::Inferred { rule: "r".to_string(), reasoning_level: "rdfs".to_string(), evidence: vec![], confidence };
+        let graph = ProvenanceGraph::parse("urn:fukurow:provenance:inferred?min_confidence=0.8").unwrap();
+        assert!(graph.matches(&inferred(Some(0.85))));
+        assert!(!graph.matches(&inferred(Some(0.6))));
+        assert!(!graph.matches(&inferred(None)));
+
+        assert!(ProvenanceGraph::parse("urn:fukurow:provenance:unknown").is_none());
+        assert!(ProvenanceGraph::parse("urn:fukurow:provenance:sensor?limit=3").is_none());
+        assert!(ProvenanceGraph::parse("http://example.org/graph").is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! Ingestion throughput while long queries run
+//!
+//! 長いクエリを実行するタスクと並行してトリプルを 1 件ずつ書き込み、書き込み側の所要時間を測る:
+//! - `rwlock`: 従来どおりクエリが primary の読み取りロックを保持する
+//! - `replica`: クエリは [`SharedStore::view`] のレプリカを使う
+//!
+//! `cargo bench -p fukurow-store --features tokio --bench concurrency_benchmark`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use fukurow_core::model::Triple;
+use fukurow_store::provenance::{GraphId, Provenance};
+use fukurow_store::store::RdfStore;
+use fukurow_store::SharedStore;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const STORE_SIZE: usize = 20_000;
+const WRITES_PER_ITER: usize = 100;
+const READERS: usize = 2;
+
+fn triple(i: usize) -> Triple {
+    Triple {
+        subject: format!("http://example.org/host_{}", i),
+        predicate: "http://example.org/connectsTo".to_string(),
+        object: format!("http://example.org/host_{}", i % 1000),
+    }
+}
+
+fn provenance() -> Provenance {
+    Provenance::Sensor { source: "benchmark".to_string(), confidence: Some(1.0) }
+}
+
+fn preloaded_store() -> RdfStore {
+    let mut store = RdfStore::new();
+    for i in 0..STORE_SIZE {
+        store.insert(triple(i), GraphId::Default, provenance());
+    }
+    store
+}
+
+/// Stand-in for a long SPARQL query: repeated full scans
+fn long_query(store: &RdfStore) -> usize {
+    (0..10).map(|_| store.find_triples(None, Some("http://example.org/connectsTo"), None).len()).sum()
+}
+
+#[derive(Clone, Copy)]
+enum ReadMode {
+    RwLock,
+    Replica,
+}
+
+fn benchmark_ingestion_during_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ingestion_during_queries");
+    group.sample_size(10);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(READERS + 2)
+        .build()
+        .unwrap();
+
+    for (name, mode) in [("rwlock", ReadMode::RwLock), ("replica", ReadMode::Replica)] {
+        let shared = SharedStore::new(preloaded_store()).with_max_staleness(Duration::from_millis(100));
+        let next = Arc::new(AtomicUsize::new(STORE_SIZE));
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_custom(|iters| runtime.block_on(async {
+                let stop = Arc::new(AtomicBool::new(false));
+                let readers: Vec<_> = (0..READERS).map(|_| {
+                    let (shared, stop) = (shared.clone(), Arc::clone(&stop));
+                    tokio::spawn(async move {
+                        let mut queries = 0usize;
+                        while !stop.load(Ordering::Relaxed) {
+                            match mode {
+                                ReadMode::RwLock => black_box(long_query(&*shared.read().await)),
+                                ReadMode::Replica => black_box(long_query(&shared.view())),
+                            };
+                            queries += 1;
+                            tokio::task::yield_now().await;
+                        }
+                        queries
+                    })
+                }).collect();
+
+                let started = Instant::now();
+                for _ in 0..iters * WRITES_PER_ITER as u64 {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    shared.write().await.insert(triple(i), GraphId::Default, provenance());
+                }
+                let elapsed = started.elapsed();
+
+                stop.store(true, Ordering::Relaxed);
+                for reader in readers {
+                    black_box(reader.await.unwrap());
+                }
+                elapsed
+            }));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_ingestion_during_queries);
+criterion_main!(benches);
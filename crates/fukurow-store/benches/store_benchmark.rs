@@ -176,6 +176,7 @@ fn benchmark_provenance_operations(c: &mut Criterion) {
             rule: "test_rule".to_string(),
             reasoning_level: "rdfs".to_string(),
             evidence: vec!["evidence1".to_string()],
+            confidence: Some(0.9),
         },
         Provenance::Imported {
             source_uri: "http://example.org/data.ttl".to_string(),
@@ -0,0 +1,232 @@
+//! Access statistics and hot-key profiling
+//!
+//! パターン検索を `sample_rate` 回に 1 回だけ記録し、束縛された主語・述語の上位 K 件を
+//! Space-Saving 法 (固定サイズの表で近似) で保持する。読み取り件数と hits はサンプル率を掛けた
+//! 推定値、書き込み件数は全件を数えた正確な値。プロファイラは primary とクエリ用レプリカで共有する
+
+use crate::provenance::GraphId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Default: record one lookup in 16
+pub const DEFAULT_SAMPLE_RATE: u64 = 16;
+
+/// Default number of hot subjects / predicates reported
+pub const DEFAULT_TOP_K: usize = 10;
+
+/// Candidates tracked per reported key (more slots make the top K more accurate)
+const TRACKED_PER_KEY: usize = 4;
+
+/// Estimated load on a term
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotKey {
+    pub term: String,
+    /// Estimated lookups binding the term
+    pub hits: u64,
+}
+
+/// Per-graph access counters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphAccess {
+    pub graph: GraphId,
+    /// Estimated triples read from the graph
+    pub reads: u64,
+    /// Triples inserted into or removed from the graph
+    pub writes: u64,
+}
+
+/// Access statistics of a store (part of [`StoreStatistics`](crate::StoreStatistics))
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccessStatistics {
+    /// Pattern lookups and graph scans, sampled or not
+    pub lookups: u64,
+    pub sampled_lookups: u64,
+    pub sample_rate: u64,
+    /// Graphs ordered by reads plus writes, busiest first
+    pub graphs: Vec<GraphAccess>,
+    pub hot_subjects: Vec<HotKey>,
+    pub hot_predicates: Vec<HotKey>,
+}
+
+/// Space-Saving top-K counter
+#[derive(Debug)]
+struct TopK {
+    capacity: usize,
+    counts: HashMap<String, u64>,
+}
+
+impl TopK {
+    fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), counts: HashMap::new() }
+    }
+
+    fn record(&mut self, key: &str) {
+        if let Some(count) = self.counts.get_mut(key) {
+            *count += 1;
+            return;
+        }
+        let mut count = 1;
+        if self.counts.len() >= self.capacity {
+            // 最小のキーを追い出し、その件数を引き継ぐ (過大評価はしても過小評価はしない)
+            if let Some((evicted, min)) = self.counts.iter().min_by_key(|(_, count)| **count).map(|(key, count)| (key.clone(), *count)) {
+                self.counts.remove(&evicted);
+                count += min;
+            }
+        }
+        self.counts.insert(key.to_string(), count);
+    }
+
+    fn top(&self, k: usize, scale: u64) -> Vec<HotKey> {
+        let mut keys: Vec<HotKey> = self.counts.iter()
+            .map(|(term, count)| HotKey { term: term.clone(), hits: count * scale })
+            .collect();
+        keys.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.term.cmp(&b.term)));
+        keys.truncate(k);
+        keys
+    }
+}
+
+#[derive(Debug, Default)]
+struct GraphCounters {
+    sampled_reads: u64,
+    writes: u64,
+}
+
+#[derive(Debug)]
+struct ProfileState {
+    graphs: HashMap<GraphId, GraphCounters>,
+    subjects: TopK,
+    predicates: TopK,
+}
+
+/// Sampling profiler of store lookups
+#[derive(Debug)]
+pub struct AccessProfiler {
+    sample_rate: u64,
+    top_k: usize,
+    lookups: AtomicU64,
+    sampled: AtomicU64,
+    state: Mutex<ProfileState>,
+}
+
+impl Default for AccessProfiler {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_RATE, DEFAULT_TOP_K)
+    }
+}
+
+impl AccessProfiler {
+    /// Record one lookup in `sample_rate` (every lookup when 1) and report `top_k` hot keys
+    pub fn new(sample_rate: u64, top_k: usize) -> Self {
+        let capacity = top_k.max(1) * TRACKED_PER_KEY;
+        Self {
+            sample_rate: sample_rate.max(1),
+            top_k,
+            lookups: AtomicU64::new(0),
+            sampled: AtomicU64::new(0),
+            state: Mutex::new(ProfileState {
+                graphs: HashMap::new(),
+                subjects: TopK::new(capacity),
+                predicates: TopK::new(capacity),
+            }),
+        }
+    }
+
+    /// Count a lookup; returns whether it is sampled
+    pub(crate) fn sample(&self) -> bool {
+        let sampled = self.lookups.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.sample_rate);
+        if sampled {
+            self.sampled.fetch_add(1, Ordering::Relaxed);
+        }
+        sampled
+    }
+
+    /// Record the bound terms of a sampled lookup
+    pub(crate) fn record_lookup(&self, subject: Option<&str>, predicate: Option<&str>) {
+        if subject.is_none() && predicate.is_none() {
+            return;
+        }
+        let mut state = self.lock();
+        if let Some(subject) = subject {
+            state.subjects.record(subject);
+        }
+        if let Some(predicate) = predicate {
+            state.predicates.record(predicate);
+        }
+    }
+
+    /// Record `count` triples read from `graph` by a sampled lookup
+    pub(crate) fn record_reads(&self, graph: &GraphId, count: u64) {
+        self.lock().graphs.entry(graph.clone()).or_default().sampled_reads += count;
+    }
+
+    /// Record `count` triples written to `graph` (always counted)
+    pub(crate) fn record_writes(&self, graph: &GraphId, count: u64) {
+        if count > 0 {
+            self.lock().graphs.entry(graph.clone()).or_default().writes += count;
+        }
+    }
+
+    pub fn statistics(&self) -> AccessStatistics {
+        let state = self.lock();
+        let mut graphs: Vec<GraphAccess> = state.graphs.iter()
+            .map(|(graph, counters)| GraphAccess {
+                graph: graph.clone(),
+                reads: counters.sampled_reads * self.sample_rate,
+                writes: counters.writes,
+            })
+            .collect();
+        graphs.sort_by(|a, b| (b.reads + b.writes).cmp(&(a.reads + a.writes)).then_with(|| a.graph.to_string().cmp(&b.graph.to_string())));
+        AccessStatistics {
+            lookups: self.lookups.load(Ordering::Relaxed),
+            sampled_lookups: self.sampled.load(Ordering::Relaxed),
+            sample_rate: self.sample_rate,
+            graphs,
+            hot_subjects: state.subjects.top(self.top_k, self.sample_rate),
+            hot_predicates: state.predicates.top(self.top_k, self.sample_rate),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProfileState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_k_keeps_heavy_hitters() {
+        let mut top = TopK::new(3);
+        for _ in 0..10 {
+            top.record("hot");
+        }
+        for key in ["a", "b", "c", "d", "e"] {
+            top.record(key);
+        }
+        let keys = top.top(1, 2);
+        assert_eq!(keys, vec![HotKey { term: "hot".to_string(), hits: 20 }]);
+        assert_eq!(top.counts.len(), 3);
+    }
+
+    #[test]
+    fn test_sampling_scales_estimates() {
+        let profiler = AccessProfiler::new(4, 2);
+        for _ in 0..8 {
+            if profiler.sample() {
+                profiler.record_lookup(Some("s"), None);
+                profiler.record_reads(&GraphId::Default, 3);
+            }
+        }
+        profiler.record_writes(&GraphId::Default, 5);
+
+        let stats = profiler.statistics();
+        assert_eq!((stats.lookups, stats.sampled_lookups), (8, 2));
+        assert_eq!(stats.hot_subjects, vec![HotKey { term: "s".to_string(), hits: 8 }]);
+        assert!(stats.hot_predicates.is_empty());
+        assert_eq!(stats.graphs, vec![GraphAccess { graph: GraphId::Default, reads: 24, writes: 5 }]);
+    }
+}
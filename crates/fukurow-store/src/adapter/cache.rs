@@ -0,0 +1,256 @@
+//! Read-through pattern cache
+//!
+//! DB を使うバックエンド (`SqliteBackend`, `SledBackend`) では
+//! find_triples のたびに DB へ問い合わせが発生する。
+//! パターンごとの検索結果を LRU でキャッシュし、書き込み時には影響する
+//! パターンだけを無効化する
+
+use super::{BackendError, TripleBackend};
+use crate::provenance::GraphId;
+use crate::store::StoredTriple;
+use fukurow_core::model::Triple;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Cached lookup pattern
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PatternKey {
+    pub subject: Option<String>,
+    pub predicate: Option<String>,
+    pub object: Option<String>,
+}
+
+impl PatternKey {
+    pub fn new(subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Self {
+        Self {
+            subject: subject.map(str::to_string),
+            predicate: predicate.map(str::to_string),
+            object: object.map(str::to_string),
+        }
+    }
+
+    /// Whether `triple` belongs to this pattern's result
+    pub fn matches(&self, triple: &Triple) -> bool {
+        fn bound_eq(bound: &Option<String>, value: &str) -> bool {
+            bound.as_deref().map(|b| b == value).unwrap_or(true)
+        }
+        bound_eq(&self.subject, &triple.subject)
+            && bound_eq(&self.predicate, &triple.predicate)
+            && bound_eq(&self.object, &triple.object)
+    }
+}
+
+/// Cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to stay within capacity
+    pub evictions: u64,
+    /// Entries dropped because a write could change their result
+    pub invalidations: u64,
+    /// Current number of cached patterns
+    pub entries: usize,
+}
+
+impl CacheMetrics {
+    /// Fraction of lookups served from the cache (`0.0` before the first lookup)
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// LRU map of pattern results
+///
+/// 最終アクセス時刻 (単調増加のカウンタ) で順序付けし、容量超過時は最も古いものから捨てる
+#[derive(Debug, Default)]
+struct LruCache {
+    entries: HashMap<PatternKey, (Arc<Vec<StoredTriple>>, u64)>,
+    recency: BTreeMap<u64, PatternKey>,
+    clock: u64,
+}
+
+impl LruCache {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get(&mut self, key: &PatternKey) -> Option<Arc<Vec<StoredTriple>>> {
+        let now = self.tick();
+        let (result, last_used) = self.entries.get_mut(key)?;
+        let previous = std::mem::replace(last_used, now);
+        let result = Arc::clone(result);
+        if let Some(key) = self.recency.remove(&previous) {
+            self.recency.insert(now, key);
+        }
+        Some(result)
+    }
+
+    /// Insert and return the number of evicted entries
+    fn put(&mut self, key: PatternKey, result: Arc<Vec<StoredTriple>>, capacity: usize) -> u64 {
+        let now = self.tick();
+        if let Some((_, previous)) = self.entries.insert(key.clone(), (result, now)) {
+            self.recency.remove(&previous);
+        }
+        self.recency.insert(now, key);
+
+        let mut evicted = 0;
+        while self.entries.len() > capacity {
+            let oldest = match self.recency.keys().next().copied() {
+                Some(oldest) => oldest,
+                None => break,
+            };
+            if let Some(key) = self.recency.remove(&oldest) {
+                self.entries.remove(&key);
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    /// Drop entries selected by `stale`; returns how many were dropped
+    fn invalidate(&mut self, mut stale: impl FnMut(&PatternKey, &[StoredTriple]) -> bool) -> u64 {
+        let before = self.entries.len();
+        let recency = &mut self.recency;
+        self.entries.retain(|key, (result, last_used)| {
+            let keep = !stale(key, result.as_slice());
+            if !keep {
+                recency.remove(&*last_used);
+            }
+            keep
+        });
+        (before - self.entries.len()) as u64
+    }
+
+    fn clear(&mut self) -> u64 {
+        let dropped = self.entries.len() as u64;
+        self.entries.clear();
+        self.recency.clear();
+        dropped
+    }
+}
+
+/// Read-through caching wrapper for a [`TripleBackend`]
+///
+/// Lookups are served from an LRU of pattern results; misses go to the backend
+/// and are cached. Writes go straight to the backend and then drop only the
+/// cached patterns the written triple matches, so unrelated hot patterns stay
+/// warm. Writers that bypass this wrapper must call [`CachedBackend::invalidate_all`].
+#[derive(Debug)]
+pub struct CachedBackend<B> {
+    backend: B,
+    capacity: usize,
+    cache: Mutex<LruCache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl<B: TripleBackend> CachedBackend<B> {
+    /// Wrap a backend with the default capacity of 1024 patterns
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            capacity: 1024,
+            cache: Mutex::new(LruCache::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// Maximum number of cached patterns (`0` disables caching)
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_inner(self) -> B {
+        self.backend
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries: self.lock().entries.len(),
+        }
+    }
+
+    /// Drop every cached pattern
+    pub fn invalidate_all(&self) {
+        let dropped = self.lock().clear();
+        self.invalidations.fetch_add(dropped, Ordering::Relaxed);
+    }
+
+    /// Find triples, serving repeated patterns from the cache
+    pub fn find_cached(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Arc<Vec<StoredTriple>>, BackendError> {
+        let key = PatternKey::new(subject, predicate, object);
+        if let Some(result) = self.lock().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(result);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // バックエンドへの問い合わせ中はロックを保持しない
+        let result = Arc::new(self.backend.find_triples(subject, predicate, object)?);
+        if self.capacity > 0 {
+            let evicted = self.lock().put(key, Arc::clone(&result), self.capacity);
+            self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        }
+        Ok(result)
+    }
+
+    fn invalidate_where(&self, stale: impl FnMut(&PatternKey, &[StoredTriple]) -> bool) {
+        let dropped = self.lock().invalidate(stale);
+        self.invalidations.fetch_add(dropped, Ordering::Relaxed);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache> {
+        // キャッシュはいつでも捨てられるので、poison されていても中身を使い続ける
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<B: TripleBackend> TripleBackend for CachedBackend<B> {
+    fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Vec<StoredTriple>, BackendError> {
+        self.find_cached(subject, predicate, object).map(|result| result.as_ref().clone())
+    }
+
+    fn insert(&mut self, stored: StoredTriple) -> Result<(), BackendError> {
+        let triple = stored.triple.to_triple();
+        let result = self.backend.insert(stored);
+        // 失敗しても部分的に書き込まれた可能性があるため常に無効化する
+        self.invalidate_where(|key, _| key.matches(&triple));
+        result
+    }
+
+    fn remove_triple(&mut self, triple: &Triple, graph_id: &GraphId) -> Result<usize, BackendError> {
+        let result = self.backend.remove_triple(triple, graph_id);
+        self.invalidate_where(|key, _| key.matches(triple));
+        result
+    }
+
+    fn clear_graph(&mut self, graph_id: &GraphId) -> Result<(), BackendError> {
+        let result = self.backend.clear_graph(graph_id);
+        // 削除だけなので、そのグラフのトリプルを含む結果だけが変わる
+        self.invalidate_where(|_, triples| triples.iter().any(|stored| &stored.graph_id == graph_id));
+        result
+    }
+}
@@ -0,0 +1,74 @@
+//! Storage backend adapters
+//!
+//! DB に置いたトリプルを `TripleBackend` として抽象化し、`CachedBackend` で読み取りキャッシュを挟めるようにする。
+//! 現在のバックエンドは SQLite (`sqlite` フィーチャ) と sled (`sled` フィーチャ) のみ。
+//! Postgres / Turso のバックエンドはまだ無い (`turso.rs` はビルド対象外で、
+//! `turso` フィーチャと依存関係の追加後に `TripleBackend` として組み込む)
+
+pub mod cache;
+#[cfg(feature = "sled")]
+pub mod sled;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use cache::*;
+#[cfg(feature = "sled")]
+pub use self::sled::SledBackend;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteBackend;
+
+use crate::provenance::GraphId;
+use crate::store::{RdfStore, StoredTriple};
+use fukurow_core::model::Triple;
+
+/// Backend errors
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    #[error("Connection error: {0}")]
+    Connection(String),
+
+    #[error("Query error: {0}")]
+    Query(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Triple storage behind a (possibly remote) connection
+///
+/// Every call may be a round trip to the database, so interactive callers
+/// should wrap DB-backed implementations in a [`CachedBackend`].
+pub trait TripleBackend: Send + Sync {
+    /// Find triples matching a pattern (`None` matches anything)
+    fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Vec<StoredTriple>, BackendError>;
+
+    /// Persist a triple
+    fn insert(&mut self, stored: StoredTriple) -> Result<(), BackendError>;
+
+    /// Remove a triple from a graph; returns the number of removed copies
+    fn remove_triple(&mut self, triple: &Triple, graph_id: &GraphId) -> Result<usize, BackendError>;
+
+    /// Remove every triple of a graph
+    fn clear_graph(&mut self, graph_id: &GraphId) -> Result<(), BackendError>;
+}
+
+/// The in-memory store as a backend (reference implementation and tests)
+impl TripleBackend for RdfStore {
+    fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Vec<StoredTriple>, BackendError> {
+        Ok(RdfStore::find_triples(self, subject, predicate, object).into_iter().cloned().collect())
+    }
+
+    fn insert(&mut self, stored: StoredTriple) -> Result<(), BackendError> {
+        self.insert_at(stored.triple, stored.graph_id, stored.provenance, stored.asserted_at);
+        Ok(())
+    }
+
+    fn remove_triple(&mut self, triple: &Triple, graph_id: &GraphId) -> Result<usize, BackendError> {
+        Ok(RdfStore::remove_triple(self, triple, graph_id))
+    }
+
+    fn clear_graph(&mut self, graph_id: &GraphId) -> Result<(), BackendError> {
+        RdfStore::clear_graph(self, graph_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+//! Sled persistence backend
+//!
+//! グラフごとに sled の木 (tree) を分け、キーは `主語\0述語\0目的語\0連番` とする。
+//! 主語 (と述語) を指定した検索は前方一致の走査で済み、グラフの削除は木を落とすだけで済む。
+//! メタデータの木 `graphs` はグラフ ID の JSON → 木の名前を持つ。
+//!
+//! - 1 件の挿入はメタデータとグラフの木をまたぐトランザクションで書く
+//! - `save_store` は新しい世代の木へ書き込んでからメタデータを一括で差し替えるため、
+//!   途中でクラッシュしても古い内容か新しい内容のどちらかが残る (参照されない木は次回 open 時に消す)
+//! - `iter` / `load_store` は木を順に走査し、全件を一度にメモリへ載せない
+
+use super::{BackendError, TripleBackend};
+use crate::provenance::{GraphId, Provenance};
+use crate::store::{RdfStore, StoredTriple};
+use fukurow_core::model::{InternedString, InternedTriple, Triple};
+use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::Transactional;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::path::Path;
+
+/// Metadata tree: graph ID (JSON) → name of the graph's tree
+const GRAPHS_TREE: &str = "graphs";
+/// Prefix of per-graph tree names (`graph/<generation>/<graph JSON>`)
+const GRAPH_TREE_PREFIX: &str = "graph/";
+const SEPARATOR: u8 = 0;
+
+/// Value stored for each triple (the terms live in the key)
+#[derive(Serialize, Deserialize)]
+struct SledRow {
+    asserted_at: u64,
+    provenance: Provenance,
+}
+
+/// Sled-backed triple storage
+#[derive(Debug, Clone)]
+pub struct SledBackend {
+    db: sled::Db,
+    graphs: sled::Tree,
+}
+
+impl SledBackend {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BackendError> {
+        let db = sled::open(path).map_err(|e| BackendError::Connection(e.to_string()))?;
+        Self::with_db(db)
+    }
+
+    /// Temporary database removed when dropped (tests)
+    pub fn temporary() -> Result<Self, BackendError> {
+        let db = sled::Config::new().temporary(true).open().map_err(|e| BackendError::Connection(e.to_string()))?;
+        Self::with_db(db)
+    }
+
+    fn with_db(db: sled::Db) -> Result<Self, BackendError> {
+        let graphs = db.open_tree(GRAPHS_TREE).map_err(storage_error)?;
+        let backend = Self { db, graphs };
+        backend.drop_orphan_trees()?;
+        Ok(backend)
+    }
+
+    /// Graphs that have persisted triples
+    pub fn graph_ids(&self) -> Result<Vec<GraphId>, BackendError> {
+        Ok(self.graph_trees()?.into_iter().map(|(graph_id, _)| graph_id).collect())
+    }
+
+    /// Flush pending writes to disk
+    pub fn flush(&self) -> Result<(), BackendError> {
+        self.db.flush().map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Replace the persisted contents with every triple of `store`
+    pub fn save_store(&self, store: &RdfStore) -> Result<(), BackendError> {
+        let generation = self.db.generate_id().map_err(storage_error)?;
+        let mut meta = sled::Batch::default();
+        for existing in self.graphs.iter().keys() {
+            meta.remove(existing.map_err(storage_error)?);
+        }
+
+        for (graph_id, triples) in store.all_triples() {
+            if triples.is_empty() {
+                continue;
+            }
+            let graph_json = serde_json::to_string(graph_id)?;
+            let name = format!("{}{}/{}", GRAPH_TREE_PREFIX, generation, graph_json);
+            let tree = self.db.open_tree(&name).map_err(storage_error)?;
+            let mut batch = sled::Batch::default();
+            for stored in triples {
+                let (key, value) = self.encode(stored)?;
+                batch.insert(key, value);
+            }
+            tree.apply_batch(batch).map_err(storage_error)?;
+            meta.insert(graph_json.as_bytes(), name.as_bytes());
+        }
+        // 新しい世代を書き終えてからメタデータを一括で切り替える
+        self.db.flush().map_err(storage_error)?;
+        self.graphs.apply_batch(meta).map_err(storage_error)?;
+        self.db.flush().map_err(storage_error)?;
+        self.drop_orphan_trees()
+    }
+
+    /// Load every persisted triple into a new in-memory store
+    pub fn load_store(&self) -> Result<RdfStore, BackendError> {
+        let mut store = RdfStore::new();
+        for stored in self.iter()? {
+            let stored = stored?;
+            store.insert_at(stored.triple, stored.graph_id, stored.provenance, stored.asserted_at);
+        }
+        Ok(store)
+    }
+
+    /// Stream every persisted triple, one graph tree at a time
+    pub fn iter(&self) -> Result<impl Iterator<Item = Result<StoredTriple, BackendError>> + '_, BackendError> {
+        Ok(self.graph_trees()?.into_iter().flat_map(move |(graph_id, tree)| {
+            tree.iter().map(move |entry| {
+                let (key, value) = entry.map_err(storage_error)?;
+                decode(&graph_id, &key, &value)
+            })
+        }))
+    }
+
+    fn select(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Vec<StoredTriple>, BackendError> {
+        // 主語から順に指定されている項だけをキーの接頭辞にできる
+        let mut prefix = Vec::new();
+        if let Some(subject) = subject {
+            push_term(&mut prefix, subject)?;
+            if let Some(predicate) = predicate {
+                push_term(&mut prefix, predicate)?;
+                if let Some(object) = object {
+                    push_term(&mut prefix, object)?;
+                }
+            }
+        }
+
+        let mut terms: HashMap<Vec<u8>, InternedString> = HashMap::new();
+        let mut triples = Vec::new();
+        for (graph_id, tree) in self.graph_trees()? {
+            for entry in tree.scan_prefix(&prefix) {
+                let (key, value) = entry.map_err(storage_error)?;
+                let [s, p, o] = split_key(&key)?;
+                let matches = |pattern: Option<&str>, term: &[u8]| match pattern {
+                    Some(pattern) => pattern.as_bytes() == term,
+                    None => true,
+                };
+                if !(matches(subject, s) && matches(predicate, p) && matches(object, o)) {
+                    continue;
+                }
+                // 同じ項は一度だけインターンする
+                let mut intern = |term: &[u8]| -> Result<InternedString, BackendError> {
+                    if let Some(interned) = terms.get(term) {
+                        return Ok(interned.clone());
+                    }
+                    let interned = InternedString::new(term_str(term)?);
+                    terms.insert(term.to_vec(), interned.clone());
+                    Ok(interned)
+                };
+                let row: SledRow = serde_json::from_slice(&value)?;
+                triples.push(StoredTriple {
+                    graph_id: graph_id.clone(),
+                    triple: InternedTriple::new(intern(s)?, intern(p)?, intern(o)?),
+                    asserted_at: row.asserted_at,
+                    provenance: row.provenance,
+                });
+            }
+        }
+        Ok(triples)
+    }
+
+    fn graph_trees(&self) -> Result<Vec<(GraphId, sled::Tree)>, BackendError> {
+        let mut trees = Vec::new();
+        for entry in self.graphs.iter() {
+            let (graph_json, name) = entry.map_err(storage_error)?;
+            let graph_id: GraphId = serde_json::from_slice(&graph_json)?;
+            trees.push((graph_id, self.db.open_tree(name).map_err(storage_error)?));
+        }
+        Ok(trees)
+    }
+
+    /// Tree of `graph_id`, creating (but not yet registering) a new one when missing
+    fn tree_for(&self, graph_id: &GraphId) -> Result<(String, sled::Tree), BackendError> {
+        let graph_json = serde_json::to_string(graph_id)?;
+        let name = match self.graphs.get(graph_json.as_bytes()).map_err(storage_error)? {
+            Some(name) => String::from_utf8_lossy(&name).into_owned(),
+            None => {
+                let generation = self.db.generate_id().map_err(storage_error)?;
+                format!("{}{}/{}", GRAPH_TREE_PREFIX, generation, graph_json)
+            }
+        };
+        let tree = self.db.open_tree(&name).map_err(storage_error)?;
+        Ok((graph_json, tree))
+    }
+
+    fn encode(&self, stored: &StoredTriple) -> Result<(Vec<u8>, Vec<u8>), BackendError> {
+        let triple = &stored.triple;
+        let mut key = Vec::with_capacity(triple.subject.len() + triple.predicate.len() + triple.object.len() + 11);
+        push_term(&mut key, &triple.subject)?;
+        push_term(&mut key, &triple.predicate)?;
+        push_term(&mut key, &triple.object)?;
+        // 同じトリプルを複数回アサートしても別のエントリになるよう連番を付ける
+        key.extend_from_slice(&self.db.generate_id().map_err(storage_error)?.to_be_bytes());
+        let value = serde_json::to_vec(&SledRow { asserted_at: stored.asserted_at, provenance: stored.provenance.clone() })?;
+        Ok((key, value))
+    }
+
+    /// Drop graph trees no longer referenced by the metadata (left by an interrupted `save_store`)
+    fn drop_orphan_trees(&self) -> Result<(), BackendError> {
+        let referenced: HashSet<Vec<u8>> = self.graphs.iter()
+            .values()
+            .map(|name| name.map(|name| name.to_vec()).map_err(storage_error))
+            .collect::<Result<_, _>>()?;
+        for name in self.db.tree_names() {
+            if name.starts_with(GRAPH_TREE_PREFIX.as_bytes()) && !referenced.contains(&name[..]) {
+                self.db.drop_tree(&name).map_err(storage_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TripleBackend for SledBackend {
+    fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Vec<StoredTriple>, BackendError> {
+        self.select(subject, predicate, object)
+    }
+
+    fn insert(&mut self, stored: StoredTriple) -> Result<(), BackendError> {
+        let (graph_json, tree) = self.tree_for(&stored.graph_id)?;
+        let (key, value) = self.encode(&stored)?;
+        let name = tree.name();
+        (&self.graphs, &tree)
+            .transaction(|(graphs, tree)| {
+                graphs.insert(graph_json.as_bytes(), name.clone())?;
+                tree.insert(key.as_slice(), value.as_slice())?;
+                Ok::<_, ConflictableTransactionError<Infallible>>(())
+            })
+            .map_err(transaction_error)
+    }
+
+    fn remove_triple(&mut self, triple: &Triple, graph_id: &GraphId) -> Result<usize, BackendError> {
+        let graph_json = serde_json::to_string(graph_id)?;
+        let Some(name) = self.graphs.get(graph_json.as_bytes()).map_err(storage_error)? else {
+            return Ok(0);
+        };
+        let tree = self.db.open_tree(name).map_err(storage_error)?;
+        let mut prefix = Vec::new();
+        push_term(&mut prefix, &triple.subject)?;
+        push_term(&mut prefix, &triple.predicate)?;
+        push_term(&mut prefix, &triple.object)?;
+
+        let keys = tree.scan_prefix(&prefix).keys()
+            .map(|key| key.map_err(storage_error))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        // 最後のトリプルを消したグラフはメタデータからも外す (木はコミット後に落とす)
+        let emptied = tree.len() == keys.len();
+        (&self.graphs, &tree)
+            .transaction(|(graphs, tree)| {
+                for key in &keys {
+                    tree.remove(key)?;
+                }
+                if emptied {
+                    graphs.remove(graph_json.as_bytes())?;
+                }
+                Ok::<_, ConflictableTransactionError<Infallible>>(())
+            })
+            .map_err(transaction_error)?;
+        if emptied {
+            // ここでクラッシュしても次回 open 時に drop_orphan_trees が片付ける
+            self.db.drop_tree(tree.name()).map_err(storage_error)?;
+        }
+        Ok(keys.len())
+    }
+
+    fn clear_graph(&mut self, graph_id: &GraphId) -> Result<(), BackendError> {
+        let graph_json = serde_json::to_string(graph_id)?;
+        // メタデータを先に消せば、木を落とす前にクラッシュしても次回 open 時に片付く
+        if let Some(name) = self.graphs.remove(graph_json.as_bytes()).map_err(storage_error)? {
+            self.db.drop_tree(name).map_err(storage_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Append a term and its separator to a key
+fn push_term(key: &mut Vec<u8>, term: &str) -> Result<(), BackendError> {
+    if term.as_bytes().contains(&SEPARATOR) {
+        return Err(BackendError::Query(format!("term contains a NUL byte: {:?}", term)));
+    }
+    key.extend_from_slice(term.as_bytes());
+    key.push(SEPARATOR);
+    Ok(())
+}
+
+/// Subject, predicate and object of a key (the trailing sequence number is ignored)
+fn split_key(key: &[u8]) -> Result<[&[u8]; 3], BackendError> {
+    let terms = key.len().checked_sub(8).map(|end| &key[..end]).ok_or_else(corrupt_key)?;
+    let mut parts = terms.split(|byte| *byte == SEPARATOR);
+    let mut next = || parts.next().ok_or_else(corrupt_key);
+    Ok([next()?, next()?, next()?])
+}
+
+fn decode(graph_id: &GraphId, key: &[u8], value: &[u8]) -> Result<StoredTriple, BackendError> {
+    let [s, p, o] = split_key(key)?;
+    let row: SledRow = serde_json::from_slice(value)?;
+    Ok(StoredTriple {
+        graph_id: graph_id.clone(),
+        triple: InternedTriple::new(
+            InternedString::new(term_str(s)?),
+            InternedString::new(term_str(p)?),
+            InternedString::new(term_str(o)?),
+        ),
+        asserted_at: row.asserted_at,
+        provenance: row.provenance,
+    })
+}
+
+fn term_str(term: &[u8]) -> Result<&str, BackendError> {
+    std::str::from_utf8(term).map_err(|e| BackendError::Query(format!("invalid UTF-8 in stored term: {}", e)))
+}
+
+fn corrupt_key() -> BackendError {
+    BackendError::Query("corrupt triple key".to_string())
+}
+
+fn storage_error(e: sled::Error) -> BackendError {
+    BackendError::Query(e.to_string())
+}
+
+fn transaction_error(e: TransactionError<Infallible>) -> BackendError {
+    BackendError::Query(e.to_string())
+}
@@ -0,0 +1,238 @@
+//! SQLite persistence backend
+//!
+//! ストア全体の保存・読み込みと、`TripleBackend` としての行単位のアクセスを提供する。
+//! グラフ ID と Provenance は JSON として保存する。
+//! IRI・リテラルは `terms` 辞書に一度だけ保存し、`triples` は辞書の ID で参照する
+
+use super::{BackendError, TripleBackend};
+use crate::provenance::{GraphId, Provenance};
+use crate::store::{RdfStore, StoredTriple};
+use fukurow_core::model::{InternedString, InternedTriple, Triple};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// SQLite-backed triple storage
+#[derive(Debug)]
+pub struct SqliteBackend {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BackendError> {
+        let conn = Connection::open(path).map_err(|e| BackendError::Connection(e.to_string()))?;
+        Self::with_connection(conn)
+    }
+
+    pub fn in_memory() -> Result<Self, BackendError> {
+        let conn = Connection::open_in_memory().map_err(|e| BackendError::Connection(e.to_string()))?;
+        Self::with_connection(conn)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self, BackendError> {
+        let legacy = has_text_columns(&conn)?;
+        let tx = conn.transaction().map_err(query_error)?;
+        if legacy {
+            tx.execute("ALTER TABLE triples RENAME TO triples_legacy", []).map_err(query_error)?;
+            for index in ["idx_triples_sp", "idx_triples_po", "idx_triples_o", "idx_triples_graph"] {
+                tx.execute(&format!("DROP INDEX IF EXISTS {}", index), []).map_err(query_error)?;
+            }
+        }
+        tx.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS terms (
+              id INTEGER PRIMARY KEY,
+              value TEXT NOT NULL UNIQUE
+            );
+            CREATE TABLE IF NOT EXISTS triples (
+              graph_json TEXT NOT NULL,
+              s INTEGER NOT NULL REFERENCES terms(id),
+              p INTEGER NOT NULL REFERENCES terms(id),
+              o INTEGER NOT NULL REFERENCES terms(id),
+              asserted_at INTEGER NOT NULL,
+              provenance_json TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_triples_sp ON triples(s, p);
+            CREATE INDEX IF NOT EXISTS idx_triples_po ON triples(p, o);
+            CREATE INDEX IF NOT EXISTS idx_triples_o ON triples(o);
+            CREATE INDEX IF NOT EXISTS idx_triples_graph ON triples(graph_json);
+        "#,
+        ).map_err(query_error)?;
+        if legacy {
+            // 文字列で保存していた旧形式のテーブルを辞書形式へ移す
+            tx.execute_batch(
+                r#"
+                INSERT OR IGNORE INTO terms(value)
+                  SELECT s FROM triples_legacy UNION SELECT p FROM triples_legacy UNION SELECT o FROM triples_legacy;
+                INSERT INTO triples(graph_json, s, p, o, asserted_at, provenance_json)
+                  SELECT l.graph_json, ts.id, tp.id, tobj.id, l.asserted_at, l.provenance_json
+                  FROM triples_legacy l
+                  JOIN terms ts ON ts.value = l.s
+                  JOIN terms tp ON tp.value = l.p
+                  JOIN terms tobj ON tobj.value = l.o
+                  ORDER BY l.rowid;
+                DROP TABLE triples_legacy;
+            "#,
+            ).map_err(query_error)?;
+        }
+        tx.commit().map_err(query_error)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Number of distinct terms in the persisted dictionary
+    pub fn term_count(&self) -> Result<usize, BackendError> {
+        let conn = self.lock()?;
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM terms", [], |row| row.get(0)).map_err(query_error)?;
+        Ok(count as usize)
+    }
+
+    /// Replace the persisted contents with every triple of `store`
+    pub fn save_store(&self, store: &RdfStore) -> Result<(), BackendError> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(query_error)?;
+        tx.execute("DELETE FROM triples", []).map_err(query_error)?;
+        tx.execute("DELETE FROM terms", []).map_err(query_error)?;
+        let mut dictionary = HashMap::new();
+        for stored in store.all_triples().values().flatten() {
+            insert_row(&tx, stored, &mut dictionary)?;
+        }
+        tx.commit().map_err(query_error)
+    }
+
+    /// Load every persisted triple into a new in-memory store
+    pub fn load_store(&self) -> Result<RdfStore, BackendError> {
+        let mut store = RdfStore::new();
+        for stored in self.select(None, None, None)? {
+            store.insert_at(stored.triple, stored.graph_id, stored.provenance, stored.asserted_at);
+        }
+        Ok(store)
+    }
+
+    fn select(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Vec<StoredTriple>, BackendError> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(
+            "SELECT t.graph_json, t.s, ts.value, t.p, tp.value, t.o, tobj.value, t.asserted_at, t.provenance_json
+             FROM triples t
+             JOIN terms ts ON ts.id = t.s
+             JOIN terms tp ON tp.id = t.p
+             JOIN terms tobj ON tobj.id = t.o
+             WHERE (?1 IS NULL OR ts.value = ?1)
+               AND (?2 IS NULL OR tp.value = ?2)
+               AND (?3 IS NULL OR tobj.value = ?3)
+             ORDER BY t.rowid",
+        ).map_err(query_error)?;
+
+        let rows = stmt.query_map(params![subject, predicate, object], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                [
+                    (row.get::<_, i64>(1)?, row.get::<_, String>(2)?),
+                    (row.get::<_, i64>(3)?, row.get::<_, String>(4)?),
+                    (row.get::<_, i64>(5)?, row.get::<_, String>(6)?),
+                ],
+                row.get::<_, i64>(7)?,
+                row.get::<_, String>(8)?,
+            ))
+        }).map_err(query_error)?;
+
+        // 同じ辞書 ID は一度だけインターンする
+        let mut terms: HashMap<i64, InternedString> = HashMap::new();
+        let mut intern = |(id, value): (i64, String)| terms.entry(id).or_insert_with(|| InternedString::new(value)).clone();
+
+        let mut triples = Vec::new();
+        for row in rows {
+            let (graph_json, [subject, predicate, object], asserted_at, provenance_json) = row.map_err(query_error)?;
+            let graph_id: GraphId = serde_json::from_str(&graph_json)?;
+            let provenance: Provenance = serde_json::from_str(&provenance_json)?;
+            triples.push(StoredTriple {
+                graph_id,
+                triple: InternedTriple::new(intern(subject), intern(predicate), intern(object)),
+                asserted_at: asserted_at as u64,
+                provenance,
+            });
+        }
+        Ok(triples)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, BackendError> {
+        self.conn.lock().map_err(|e| BackendError::Connection(e.to_string()))
+    }
+}
+
+impl TripleBackend for SqliteBackend {
+    fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Result<Vec<StoredTriple>, BackendError> {
+        self.select(subject, predicate, object)
+    }
+
+    fn insert(&mut self, stored: StoredTriple) -> Result<(), BackendError> {
+        let conn = self.lock()?;
+        insert_row(&conn, &stored, &mut HashMap::new())
+    }
+
+    fn remove_triple(&mut self, triple: &Triple, graph_id: &GraphId) -> Result<usize, BackendError> {
+        let conn = self.lock()?;
+        conn.execute(
+            "DELETE FROM triples WHERE graph_json = ?1
+               AND s = (SELECT id FROM terms WHERE value = ?2)
+               AND p = (SELECT id FROM terms WHERE value = ?3)
+               AND o = (SELECT id FROM terms WHERE value = ?4)",
+            params![serde_json::to_string(graph_id)?, triple.subject, triple.predicate, triple.object],
+        ).map_err(query_error)
+    }
+
+    fn clear_graph(&mut self, graph_id: &GraphId) -> Result<(), BackendError> {
+        let conn = self.lock()?;
+        conn.execute("DELETE FROM triples WHERE graph_json = ?1", params![serde_json::to_string(graph_id)?])
+            .map_err(query_error)?;
+        Ok(())
+    }
+}
+
+/// Whether `triples` still uses the legacy layout with inline term strings
+fn has_text_columns(conn: &Connection) -> Result<bool, BackendError> {
+    let column_type: Option<String> = conn
+        .query_row("SELECT type FROM pragma_table_info('triples') WHERE name = 's'", [], |row| row.get(0))
+        .optional()
+        .map_err(query_error)?;
+    Ok(column_type.is_some_and(|t| t.eq_ignore_ascii_case("TEXT")))
+}
+
+/// Dictionary ID of `term`, adding it when missing
+///
+/// `cache` は同じ書き込みの中で引いた ID を覚えておき、辞書への問い合わせを減らす
+fn term_id(conn: &Connection, term: &InternedString, cache: &mut HashMap<InternedString, i64>) -> Result<i64, BackendError> {
+    if let Some(&id) = cache.get(term) {
+        return Ok(id);
+    }
+    conn.execute("INSERT OR IGNORE INTO terms(value) VALUES (?1)", params![term.as_str()]).map_err(query_error)?;
+    let id = conn.query_row("SELECT id FROM terms WHERE value = ?1", params![term.as_str()], |row| row.get(0))
+        .map_err(query_error)?;
+    cache.insert(term.clone(), id);
+    Ok(id)
+}
+
+fn insert_row(conn: &Connection, stored: &StoredTriple, dictionary: &mut HashMap<InternedString, i64>) -> Result<(), BackendError> {
+    let triple = &stored.triple;
+    let (s, p, o) = (
+        term_id(conn, &triple.subject, dictionary)?,
+        term_id(conn, &triple.predicate, dictionary)?,
+        term_id(conn, &triple.object, dictionary)?,
+    );
+    conn.execute(
+        "INSERT INTO triples(graph_json, s, p, o, asserted_at, provenance_json) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            serde_json::to_string(&stored.graph_id)?,
+            s,
+            p,
+            o,
+            stored.asserted_at as i64,
+            serde_json::to_string(&stored.provenance)?,
+        ],
+    ).map_err(query_error)?;
+    Ok(())
+}
+
+fn query_error(e: rusqlite::Error) -> BackendError {
+    BackendError::Query(e.to_string())
+}
@@ -77,9 +77,9 @@ mod inner {
                         libsql::params!(
                             graph_kind,
                             graph_name,
-                            &st.triple.subject,
-                            &st.triple.predicate,
-                            &st.triple.object,
+                            st.triple.subject.as_str(),
+                            st.triple.predicate.as_str(),
+                            st.triple.object.as_str(),
                             st.asserted_at.to_rfc3339(),
                             serde_json::to_string(&st.provenance)?
                         ),
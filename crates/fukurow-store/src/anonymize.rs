@@ -0,0 +1,361 @@
+//! Privacy-preserving export (k-anonymity generalization + differential privacy noise)
+//!
+//! 外部パートナーとの共有向けに、IRI の仮名化・準識別子の一般化（k-匿名性）・
+//! 件数へのラプラスノイズ付与（ε-差分プライバシー）を適用したエクスポートを生成する。
+//! k-匿名性は主語ごとの準識別子の組 (全準識別子の一般化後の値) に対して判定する
+
+use fukurow_core::model::Triple;
+use fukurow_core::retry::SplitMix64;
+use fukurow_core::term::RdfTerm;
+use crate::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Highest generalization level; at this level every value becomes `*`
+pub const MAX_GENERALIZATION_LEVEL: u8 = 4;
+
+/// Export configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnonymizationConfig {
+    /// Minimum number of distinct subjects sharing each generalized quasi-identifier tuple
+    pub k: usize,
+    /// Privacy budget for noisy counts (smaller = more noise)
+    pub epsilon: f64,
+    /// Predicates to export (empty = all predicates)
+    pub predicates: Vec<String>,
+    /// Predicates whose values are quasi-identifiers and must be generalized
+    pub quasi_identifiers: Vec<String>,
+    /// Replace subjects and IRI objects with stable pseudonyms
+    pub pseudonymize_subjects: bool,
+    /// Seed for reproducible noise (random when unset)
+    pub seed: Option<u64>,
+}
+
+impl AnonymizationConfig {
+    pub fn new(k: usize, epsilon: f64) -> Self {
+        Self {
+            k,
+            epsilon,
+            predicates: Vec::new(),
+            quasi_identifiers: Vec::new(),
+            pseudonymize_subjects: true,
+            seed: None,
+        }
+    }
+
+    pub fn with_predicate(mut self, predicate: impl Into<String>) -> Self {
+        self.predicates.push(predicate.into());
+        self
+    }
+
+    pub fn with_quasi_identifier(mut self, predicate: impl Into<String>) -> Self {
+        self.quasi_identifiers.push(predicate.into());
+        self
+    }
+
+    pub fn with_pseudonymized_subjects(mut self, enabled: bool) -> Self {
+        self.pseudonymize_subjects = enabled;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn validate(&self) -> Result<(), AnonymizationError> {
+        if self.k == 0 {
+            return Err(AnonymizationError::InvalidConfig("k must be at least 1".to_string()));
+        }
+        if !(self.epsilon > 0.0 && self.epsilon.is_finite()) {
+            return Err(AnonymizationError::InvalidConfig("epsilon must be a positive finite number".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Anonymization errors
+#[derive(Debug, thiserror::Error)]
+pub enum AnonymizationError {
+    #[error("Invalid anonymization config: {0}")]
+    InvalidConfig(String),
+}
+
+/// How a quasi-identifier predicate was generalized
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GeneralizationRecord {
+    pub predicate: String,
+    /// Generalization level applied (0 = unchanged)
+    pub level: u8,
+    /// Number of distinct original values
+    pub original_values: usize,
+    /// Number of distinct values after generalization
+    pub generalized_values: usize,
+}
+
+/// Report of what was suppressed/generalized during export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnonymizationReport {
+    pub k: usize,
+    pub epsilon: f64,
+    pub exported_triples: usize,
+    pub suppressed_triples: usize,
+    /// Suppressed triple count per predicate
+    pub suppressed_by_predicate: BTreeMap<String, usize>,
+    pub generalizations: Vec<GeneralizationRecord>,
+    pub pseudonymized_subjects: usize,
+    /// IRI objects that are not subjects, replaced with pseudonyms
+    #[serde(default)]
+    pub pseudonymized_iris: usize,
+}
+
+/// Result of a privacy-preserving export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnonymizedExport {
+    pub triples: Vec<Triple>,
+    /// Per-predicate triple counts with Laplace noise applied
+    pub noisy_counts: BTreeMap<String, u64>,
+    pub report: AnonymizationReport,
+}
+
+/// Privacy-preserving exporter
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    config: AnonymizationConfig,
+}
+
+impl Anonymizer {
+    pub fn new(config: AnonymizationConfig) -> Result<Self, AnonymizationError> {
+        config.validate()?;
+        Ok(Self { config })
+    }
+
+    /// Export the store applying generalization, suppression, pseudonymization and noise
+    pub fn export(&self, store: &RdfStore) -> AnonymizedExport {
+        let selected: HashSet<&str> = self.config.predicates.iter().map(|p| p.as_str()).collect();
+
+        let mut triples: Vec<Triple> = store.all_triples()
+            .values()
+            .flatten()
+            .map(|stored| stored.triple.to_triple())
+            .filter(|t| selected.is_empty() || selected.contains(t.predicate.as_str()))
+            .collect();
+        triples.sort_by(|a, b| (&a.subject, &a.predicate, &a.object).cmp(&(&b.subject, &b.predicate, &b.object)));
+
+        let mut report = AnonymizationReport {
+            k: self.config.k,
+            epsilon: self.config.epsilon,
+            ..Default::default()
+        };
+
+        // 準識別子ごとに単独で k-匿名性を満たす最小の一般化レベルから始める
+        let mut levels: HashMap<String, u8> = HashMap::new();
+        let mut quasi_predicates = Vec::new();
+        for predicate in &self.config.quasi_identifiers {
+            let values: Vec<(&str, &str)> = triples.iter()
+                .filter(|t| &t.predicate == predicate)
+                .map(|t| (t.subject.as_str(), t.object.as_str()))
+                .collect();
+            if values.is_empty() || levels.contains_key(predicate) {
+                continue;
+            }
+
+            let level = (0..=MAX_GENERALIZATION_LEVEL)
+                .find(|level| Self::min_group_size(&values, *level) >= self.config.k)
+                .unwrap_or(MAX_GENERALIZATION_LEVEL);
+            levels.insert(predicate.clone(), level);
+            quasi_predicates.push(predicate.clone());
+        }
+
+        // 組として k 未満のクラスが残る間は、1 段の一般化で k 未満の主語が最も減る準識別子を一般化する
+        while self.quasi_identifier_classes(&triples, &quasi_predicates, &levels).below(self.config.k) > 0 {
+            // 同数なら設定で先に挙げた準識別子を選ぶ
+            let next = quasi_predicates.iter()
+                .filter(|predicate| levels[*predicate] < MAX_GENERALIZATION_LEVEL)
+                .min_by_key(|predicate| {
+                    let mut raised = levels.clone();
+                    *raised.get_mut(*predicate).expect("level chosen above") += 1;
+                    self.quasi_identifier_classes(&triples, &quasi_predicates, &raised).below(self.config.k)
+                })
+                .cloned();
+            match next {
+                Some(predicate) => *levels.get_mut(&predicate).expect("level chosen above") += 1,
+                None => break,
+            }
+        }
+
+        for predicate in &quasi_predicates {
+            let level = levels[predicate];
+            report.generalizations.push(GeneralizationRecord {
+                predicate: predicate.clone(),
+                level,
+                original_values: Self::distinct_values(&triples, predicate, 0),
+                generalized_values: Self::distinct_values(&triples, predicate, level),
+            });
+        }
+
+        // 最大まで一般化しても組が k 未満の主語は、準識別子のトリプルを抑制する
+        let classes = self.quasi_identifier_classes(&triples, &quasi_predicates, &levels);
+        let subjects: HashSet<String> = triples.iter().map(|t| t.subject.clone()).collect();
+
+        let mut subject_pseudonyms: HashMap<String, String> = HashMap::new();
+        let mut iri_pseudonyms: HashMap<String, String> = HashMap::new();
+        let mut exported = Vec::new();
+        for triple in triples {
+            let object = match levels.get(&triple.predicate) {
+                Some(level) => {
+                    if classes.class_size(&triple.subject) < self.config.k {
+                        report.suppressed_triples += 1;
+                        *report.suppressed_by_predicate.entry(triple.predicate.clone()).or_insert(0) += 1;
+                        continue;
+                    }
+                    generalize_value(&triple.object, *level)
+                }
+                // 主語として現れる IRI は主語と同じ仮名に、それ以外の IRI は別の仮名に置き換える
+                None if self.config.pseudonymize_subjects && subjects.contains(&triple.object) => {
+                    Self::pseudonym(&mut subject_pseudonyms, triple.object, "subject")
+                }
+                None if self.config.pseudonymize_subjects && is_iri(&triple.object) => {
+                    Self::pseudonym(&mut iri_pseudonyms, triple.object, "iri")
+                }
+                None => triple.object,
+            };
+
+            let subject = if self.config.pseudonymize_subjects {
+                Self::pseudonym(&mut subject_pseudonyms, triple.subject, "subject")
+            } else {
+                triple.subject
+            };
+
+            exported.push(Triple { subject, predicate: triple.predicate, object });
+        }
+
+        report.exported_triples = exported.len();
+        report.pseudonymized_subjects = subject_pseudonyms.len();
+        report.pseudonymized_iris = iri_pseudonyms.len();
+
+        let mut true_counts: BTreeMap<String, u64> = BTreeMap::new();
+        for triple in &exported {
+            *true_counts.entry(triple.predicate.clone()).or_insert(0) += 1;
+        }
+        let mut rng = SplitMix64::new(self.config.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64
+        }));
+        let noisy_counts = true_counts.into_iter()
+            .map(|(predicate, count)| {
+                let noisy = count as f64 + laplace(&mut rng, 1.0 / self.config.epsilon);
+                (predicate, noisy.round().max(0.0) as u64)
+            })
+            .collect();
+
+        AnonymizedExport {
+            triples: exported,
+            noisy_counts,
+            report,
+        }
+    }
+
+    /// Stable pseudonym of `term` (`anon:<kind>-<n>` in order of first use)
+    fn pseudonym(pseudonyms: &mut HashMap<String, String>, term: String, kind: &str) -> String {
+        let next = pseudonyms.len() + 1;
+        pseudonyms.entry(term).or_insert_with(|| format!("anon:{}-{}", kind, next)).clone()
+    }
+
+    /// Generalized quasi-identifier tuple of every subject with at least one quasi-identifier
+    fn quasi_identifier_classes(&self, triples: &[Triple], predicates: &[String], levels: &HashMap<String, u8>) -> QuasiIdentifierClasses {
+        let mut tuples: HashMap<&str, Vec<BTreeSet<String>>> = HashMap::new();
+        for triple in triples {
+            let Some(position) = predicates.iter().position(|p| p == &triple.predicate) else { continue };
+            tuples.entry(triple.subject.as_str())
+                .or_insert_with(|| vec![BTreeSet::new(); predicates.len()])[position]
+                .insert(generalize_value(&triple.object, levels[&triple.predicate]));
+        }
+
+        let mut sizes: HashMap<&Vec<BTreeSet<String>>, usize> = HashMap::new();
+        for tuple in tuples.values() {
+            *sizes.entry(tuple).or_insert(0) += 1;
+        }
+        QuasiIdentifierClasses {
+            class_sizes: tuples.iter().map(|(subject, tuple)| (subject.to_string(), sizes[tuple])).collect(),
+        }
+    }
+
+    fn distinct_values(triples: &[Triple], predicate: &str, level: u8) -> usize {
+        triples.iter()
+            .filter(|t| t.predicate == predicate)
+            .map(|t| generalize_value(&t.object, level))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn min_group_size(values: &[(&str, &str)], level: u8) -> usize {
+        let mut groups: HashMap<String, HashSet<&str>> = HashMap::new();
+        for (subject, value) in values {
+            groups.entry(generalize_value(value, level)).or_default().insert(subject);
+        }
+        groups.values().map(|s| s.len()).min().unwrap_or(0)
+    }
+}
+
+/// Equivalence class size of each subject under its quasi-identifier tuple
+struct QuasiIdentifierClasses {
+    class_sizes: HashMap<String, usize>,
+}
+
+impl QuasiIdentifierClasses {
+    /// Subjects in classes smaller than `k`
+    fn below(&self, k: usize) -> usize {
+        self.class_sizes.values().filter(|size| **size < k).count()
+    }
+
+    fn class_size(&self, subject: &str) -> usize {
+        self.class_sizes.get(subject).copied().unwrap_or(0)
+    }
+}
+
+fn is_iri(term: &str) -> bool {
+    matches!(RdfTerm::parse(term), RdfTerm::Iri(_) | RdfTerm::BlankNode(_))
+}
+
+/// Generalize a literal value to the given level
+///
+/// IPv4 アドレスは /24 → /16 → /8、整数は 10 → 100 → 1000 幅の範囲、
+/// その他の文字列は前方一致の接頭辞に丸める
+pub fn generalize_value(value: &str, level: u8) -> String {
+    if level == 0 {
+        return value.to_string();
+    }
+    if level >= MAX_GENERALIZATION_LEVEL {
+        return "*".to_string();
+    }
+
+    let octets: Vec<u8> = value.split('.').filter_map(|o| o.parse().ok()).collect();
+    if octets.len() == 4 && value.split('.').count() == 4 {
+        let keep = 4 - level as usize;
+        let masked: Vec<String> = octets.iter()
+            .enumerate()
+            .map(|(i, o)| if i < keep { o.to_string() } else { "0".to_string() })
+            .collect();
+        return format!("{}/{}", masked.join("."), keep * 8);
+    }
+
+    if let Ok(number) = value.parse::<i64>() {
+        let width = 10_i64.pow(level as u32);
+        let low = number.div_euclid(width) * width;
+        return format!("{}-{}", low, low + width - 1);
+    }
+
+    let chars: Vec<char> = value.chars().collect();
+    let keep = chars.len() >> level;
+    format!("{}*", chars[..keep].iter().collect::<String>())
+}
+
+/// Laplace(0, scale) sample
+fn laplace(rng: &mut SplitMix64, scale: f64) -> f64 {
+    // (0, 1) の一様乱数から逆関数法で求める
+    let u = ((rng.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64 - 0.5;
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
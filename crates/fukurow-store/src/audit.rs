@@ -0,0 +1,272 @@
+//! Persistent audit sinks and audit queries
+//!
+//! RdfStore 内の監査ログはメモリ上で件数制限されるため、AuditSink を通じて
+//! 永続化し、時間範囲・アクター・操作種別・グラフで検索できるようにする
+
+use crate::provenance::{AuditEntry, AuditOperation, GraphId};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Audit sink errors
+#[derive(Debug, thiserror::Error)]
+pub enum AuditSinkError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Backend error: {0}")]
+    Backend(String),
+}
+
+/// Audit query filter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    /// Inclusive lower bound (Unix timestamp in milliseconds)
+    pub from: Option<u64>,
+    /// Inclusive upper bound (Unix timestamp in milliseconds)
+    pub to: Option<u64>,
+    pub actor: Option<String>,
+    /// Operation kind (`insert`, `delete`, `clear`, `inference`, `query`)
+    pub operation: Option<String>,
+    /// Graph in display form (`default`, `named:events`, ...)
+    pub graph: Option<String>,
+    /// Maximum number of entries (newest first)
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        if self.from.is_some_and(|from| entry.timestamp < from) {
+            return false;
+        }
+        if self.to.is_some_and(|to| entry.timestamp > to) {
+            return false;
+        }
+        if let Some(actor) = &self.actor {
+            if entry.actor.as_deref() != Some(actor.as_str()) {
+                return false;
+            }
+        }
+        if let Some(operation) = &self.operation {
+            if !entry.operation.kind().eq_ignore_ascii_case(operation) {
+                return false;
+            }
+        }
+        if let Some(graph) = &self.graph {
+            match entry.operation.graph_id() {
+                Some(graph_id) if &graph_id.to_string() == graph => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Filter entries (in insertion order) and apply the limit, newest first
+    pub fn apply<'a>(&self, entries: impl DoubleEndedIterator<Item = &'a AuditEntry>) -> Vec<AuditEntry> {
+        entries
+            .rev()
+            .filter(|entry| self.matches(entry))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+impl AuditOperation {
+    /// Operation kind name
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditOperation::Insert { .. } => "insert",
+            AuditOperation::Delete { .. } => "delete",
+            AuditOperation::Clear { .. } => "clear",
+            AuditOperation::Inference { .. } => "inference",
+            AuditOperation::Query { .. } => "query",
+        }
+    }
+
+    /// Graph affected by the operation, if any
+    pub fn graph_id(&self) -> Option<&GraphId> {
+        match self {
+            AuditOperation::Insert { graph_id, .. }
+            | AuditOperation::Delete { graph_id, .. }
+            | AuditOperation::Clear { graph_id, .. } => Some(graph_id),
+            _ => None,
+        }
+    }
+}
+
+/// Destination for audit entries that outlives the in-memory trail
+pub trait AuditSink: std::fmt::Debug + Send + Sync {
+    /// Persist a single entry
+    fn record(&mut self, entry: &AuditEntry) -> Result<(), AuditSinkError>;
+
+    /// Query persisted entries
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditSinkError>;
+}
+
+/// Unbounded in-memory sink (mainly for tests)
+#[derive(Debug, Default)]
+pub struct InMemoryAuditSink {
+    entries: Vec<AuditEntry>,
+}
+
+impl InMemoryAuditSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AuditSink for InMemoryAuditSink {
+    fn record(&mut self, entry: &AuditEntry) -> Result<(), AuditSinkError> {
+        self.entries.push(entry.clone());
+        Ok(())
+    }
+
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditSinkError> {
+        Ok(query.apply(self.entries.iter()))
+    }
+}
+
+/// Append-only JSON Lines file sink
+#[derive(Debug)]
+pub struct JsonlAuditSink {
+    path: PathBuf,
+}
+
+impl JsonlAuditSink {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, AuditSinkError> {
+        let path = path.as_ref().to_path_buf();
+        std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path })
+    }
+
+    fn read_all(&self) -> Result<Vec<AuditEntry>, AuditSinkError> {
+        let file = std::fs::File::open(&self.path)?;
+        let mut entries = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line)?);
+        }
+        Ok(entries)
+    }
+}
+
+impl AuditSink for JsonlAuditSink {
+    fn record(&mut self, entry: &AuditEntry) -> Result<(), AuditSinkError> {
+        let mut file = std::fs::OpenOptions::new().append(true).open(&self.path)?;
+        let line = serde_json::to_string(entry)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditSinkError> {
+        let entries = self.read_all()?;
+        Ok(query.apply(entries.iter()))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use rusqlite::{params, Connection};
+    use std::sync::Mutex;
+
+    /// SQLite-backed audit sink
+    #[derive(Debug)]
+    pub struct SqliteAuditSink {
+        conn: Mutex<Connection>,
+    }
+
+    impl SqliteAuditSink {
+        pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditSinkError> {
+            let conn = Connection::open(path).map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+            Self::with_connection(conn)
+        }
+
+        pub fn in_memory() -> Result<Self, AuditSinkError> {
+            let conn = Connection::open_in_memory().map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+            Self::with_connection(conn)
+        }
+
+        fn with_connection(conn: Connection) -> Result<Self, AuditSinkError> {
+            conn.execute_batch(
+                r#"
+                CREATE TABLE IF NOT EXISTS audit_log (
+                  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                  id TEXT NOT NULL,
+                  timestamp INTEGER NOT NULL,
+                  actor TEXT,
+                  operation TEXT NOT NULL,
+                  graph TEXT,
+                  entry_json TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_audit_ts ON audit_log(timestamp);
+                CREATE INDEX IF NOT EXISTS idx_audit_actor ON audit_log(actor);
+            "#,
+            ).map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+            Ok(Self { conn: Mutex::new(conn) })
+        }
+    }
+
+    impl AuditSink for SqliteAuditSink {
+        fn record(&mut self, entry: &AuditEntry) -> Result<(), AuditSinkError> {
+            let json = serde_json::to_string(entry)?;
+            let conn = self.conn.lock().map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+            conn.execute(
+                "INSERT INTO audit_log(id, timestamp, actor, operation, graph, entry_json) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    entry.id,
+                    entry.timestamp as i64,
+                    entry.actor,
+                    entry.operation.kind(),
+                    entry.operation.graph_id().map(|g| g.to_string()),
+                    json,
+                ],
+            ).map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+            Ok(())
+        }
+
+        fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditSinkError> {
+            let conn = self.conn.lock().map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+            let mut stmt = conn.prepare(
+                "SELECT entry_json FROM audit_log
+                 WHERE (?1 IS NULL OR timestamp >= ?1)
+                   AND (?2 IS NULL OR timestamp <= ?2)
+                   AND (?3 IS NULL OR actor = ?3)
+                   AND (?4 IS NULL OR operation = lower(?4))
+                   AND (?5 IS NULL OR graph = ?5)
+                 ORDER BY seq DESC
+                 LIMIT ?6",
+            ).map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+
+            let limit = query.limit.map_or(-1, |l| l as i64);
+            let rows = stmt.query_map(
+                params![
+                    query.from.map(|v| v as i64),
+                    query.to.map(|v| v as i64),
+                    query.actor,
+                    query.operation,
+                    query.graph,
+                    limit,
+                ],
+                |row| row.get::<_, String>(0),
+            ).map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+
+            let mut entries = Vec::new();
+            for row in rows {
+                let json = row.map_err(|e| AuditSinkError::Backend(e.to_string()))?;
+                entries.push(serde_json::from_str(&json)?);
+            }
+            Ok(entries)
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteAuditSink;
@@ -0,0 +1,315 @@
+//! Cold-start bootstrap of standard ontologies
+//!
+//! 初回起動時のストアは空で、スキーマが無いため検知ルールが誤作動する。
+//! 同梱のオントロジー (サイバーオントロジー、RDFS/OWL 語彙、既定の SHACL シェイプ、
+//! ATT&CK タクソノミー) を既知の名前付きグラフへ読み込む。
+//! 読み込んだバージョンはメタグラフに記録するため、再実行しても同じバージョンは読み直さない
+
+use crate::provenance::{GraphId, Provenance};
+use crate::store::RdfStore;
+use fukurow_core::model::Triple;
+use fukurow_core::term::RdfTerm;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Graph recording which bundle versions are installed
+pub const BOOTSTRAP_META_GRAPH: &str = "urn:fukurow:graph:bootstrap";
+
+const OWL_VERSION_INFO: &str = "http://www.w3.org/2002/07/owl#versionInfo";
+
+/// One bundled release of an ontology, in N-Triples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleRelease {
+    pub version: &'static str,
+    pub ntriples: &'static str,
+}
+
+/// Ontology shipped with the binary and loaded into its own named graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OntologyBundle {
+    pub name: &'static str,
+    /// Named graph IRI the bundle is loaded into
+    pub graph: &'static str,
+    /// Bundled releases, oldest first; the last one is loaded unless pinned
+    pub releases: &'static [BundleRelease],
+}
+
+impl OntologyBundle {
+    pub fn graph_id(&self) -> GraphId {
+        GraphId::Named(self.graph.to_string())
+    }
+
+    pub fn latest(&self) -> Option<&'static BundleRelease> {
+        self.releases.last()
+    }
+
+    pub fn release(&self, version: &str) -> Option<&'static BundleRelease> {
+        self.releases.iter().find(|release| release.version == version)
+    }
+}
+
+/// Cyber event classes and properties (`http://example.org/`)
+pub const CYBER_ONTOLOGY: OntologyBundle = OntologyBundle {
+    name: "cyber",
+    graph: "urn:fukurow:graph:cyber",
+    releases: &[BundleRelease { version: "1.0.0", ntriples: include_str!("../ontologies/cyber-1.0.0.nt") }],
+};
+
+/// RDF, RDFS and OWL core vocabulary
+pub const RDFS_OWL_VOCABULARY: OntologyBundle = OntologyBundle {
+    name: "rdfs-owl",
+    graph: "urn:fukurow:graph:rdfs-owl",
+    releases: &[BundleRelease { version: "1.0.0", ntriples: include_str!("../ontologies/rdfs-owl-1.0.0.nt") }],
+};
+
+/// Default SHACL shapes for cyber events
+pub const DEFAULT_SHAPES: OntologyBundle = OntologyBundle {
+    name: "shapes",
+    graph: "urn:fukurow:graph:shapes",
+    releases: &[BundleRelease { version: "1.0.0", ntriples: include_str!("../ontologies/shapes-1.0.0.nt") }],
+};
+
+/// MITRE ATT&CK tactics and core techniques
+pub const ATTACK_TAXONOMY: OntologyBundle = OntologyBundle {
+    name: "attack",
+    graph: "urn:fukurow:graph:attack",
+    releases: &[BundleRelease { version: "14.1", ntriples: include_str!("../ontologies/attack-14.1.nt") }],
+};
+
+/// Bundles loaded by [`Bootstrapper::new`]
+pub const STANDARD_BUNDLES: [OntologyBundle; 4] = [CYBER_ONTOLOGY, RDFS_OWL_VOCABULARY, DEFAULT_SHAPES, ATTACK_TAXONOMY];
+
+/// Bootstrap settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BootstrapConfig {
+    /// `false` skips every bundle
+    pub enabled: bool,
+    /// Bundle names that are never loaded
+    pub skip: BTreeSet<String>,
+    /// Bundle name -> version to keep instead of the latest bundled release
+    pub pins: BTreeMap<String, String>,
+}
+
+impl Default for BootstrapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            skip: BTreeSet::new(),
+            pins: BTreeMap::new(),
+        }
+    }
+}
+
+impl BootstrapConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn disabled() -> Self {
+        Self { enabled: false, ..Self::default() }
+    }
+
+    pub fn with_skip(mut self, bundle: impl Into<String>) -> Self {
+        self.skip.insert(bundle.into());
+        self
+    }
+
+    pub fn with_pin(mut self, bundle: impl Into<String>, version: impl Into<String>) -> Self {
+        self.pins.insert(bundle.into(), version.into());
+        self
+    }
+}
+
+/// Bootstrap errors
+///
+/// 設定ミスと同梱データの破損はどちらもストアを変更する前に検出する
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BootstrapError {
+    #[error("Unknown ontology bundle: {0}")]
+    UnknownBundle(String),
+    #[error("Bundle {bundle} has no bundled release {version}")]
+    UnknownVersion { bundle: String, version: String },
+    #[error("Bundle {bundle} {version}, line {line}: {message}")]
+    Parse { bundle: String, version: String, line: usize, message: String },
+}
+
+/// What happened to one bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BundleOutcome {
+    /// Loaded into an empty graph
+    Installed { version: String, triples: usize },
+    /// Installed version replaced (newer release or a changed pin)
+    Updated { from: String, to: String, triples: usize },
+    /// The wanted version was already installed
+    UpToDate { version: String },
+    Skipped,
+}
+
+/// Result of a bootstrap run, keyed by bundle name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootstrapReport {
+    pub bundles: BTreeMap<String, BundleOutcome>,
+}
+
+impl BootstrapReport {
+    /// Whether any graph was (re)loaded
+    pub fn changed(&self) -> bool {
+        self.bundles.values().any(|outcome| {
+            matches!(outcome, BundleOutcome::Installed { .. } | BundleOutcome::Updated { .. })
+        })
+    }
+}
+
+/// Loads bundled ontologies into a store
+#[derive(Debug, Clone)]
+pub struct Bootstrapper {
+    config: BootstrapConfig,
+    bundles: Vec<OntologyBundle>,
+}
+
+impl Bootstrapper {
+    /// Bootstrap the [`STANDARD_BUNDLES`]
+    pub fn new(config: BootstrapConfig) -> Self {
+        Self { config, bundles: STANDARD_BUNDLES.to_vec() }
+    }
+
+    /// Add a bundle, replacing a bundle of the same name
+    pub fn with_bundle(mut self, bundle: OntologyBundle) -> Self {
+        self.bundles.retain(|existing| existing.name != bundle.name);
+        self.bundles.push(bundle);
+        self
+    }
+
+    pub fn bundles(&self) -> &[OntologyBundle] {
+        &self.bundles
+    }
+
+    /// Version of `bundle` recorded in the store's meta graph
+    pub fn installed_version(store: &RdfStore, bundle: &OntologyBundle) -> Option<String> {
+        let meta = GraphId::Named(BOOTSTRAP_META_GRAPH.to_string());
+        store.find_triples(Some(bundle.graph), Some(OWL_VERSION_INFO), None)
+            .into_iter()
+            .find(|stored| stored.graph_id == meta)
+            .map(|stored| stored.triple.object_term().value().to_string())
+    }
+
+    /// Load every enabled bundle whose wanted version is not installed yet
+    ///
+    /// 設定の検証と同梱データのパースを先に済ませるので、エラー時はストアを変更しない
+    pub fn run(&self, store: &mut RdfStore) -> Result<BootstrapReport, BootstrapError> {
+        for name in self.config.skip.iter().chain(self.config.pins.keys()) {
+            if !self.bundles.iter().any(|bundle| bundle.name == name) {
+                return Err(BootstrapError::UnknownBundle(name.clone()));
+            }
+        }
+
+        let mut report = BootstrapReport::default();
+        let mut pending = Vec::new();
+        for bundle in &self.bundles {
+            if !self.config.enabled || self.config.skip.contains(bundle.name) {
+                report.bundles.insert(bundle.name.to_string(), BundleOutcome::Skipped);
+                continue;
+            }
+
+            let release = match self.config.pins.get(bundle.name) {
+                Some(version) => bundle.release(version).ok_or_else(|| BootstrapError::UnknownVersion {
+                    bundle: bundle.name.to_string(),
+                    version: version.clone(),
+                })?,
+                None => match bundle.latest() {
+                    Some(release) => release,
+                    None => {
+                        report.bundles.insert(bundle.name.to_string(), BundleOutcome::Skipped);
+                        continue;
+                    }
+                },
+            };
+
+            let installed = Self::installed_version(store, bundle);
+            if installed.as_deref() == Some(release.version) {
+                report.bundles.insert(bundle.name.to_string(), BundleOutcome::UpToDate { version: release.version.to_string() });
+                continue;
+            }
+            pending.push((bundle, release, installed, parse_release(bundle, release)?));
+        }
+
+        let meta = GraphId::Named(BOOTSTRAP_META_GRAPH.to_string());
+        let imported_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        for (bundle, release, installed, triples) in pending {
+            let graph_id = bundle.graph_id();
+            store.clear_graph(&graph_id);
+            let provenance = Provenance::Imported {
+                source_uri: format!("bundle:{}@{}", bundle.name, release.version),
+                imported_at,
+            };
+            let count = store.insert_document(triples, graph_id, provenance.clone()).len();
+
+            if let Some(previous) = &installed {
+                store.remove_triple(&version_marker(bundle, previous), &meta);
+            }
+            store.insert(version_marker(bundle, release.version), meta.clone(), provenance);
+
+            let outcome = match installed {
+                Some(from) => BundleOutcome::Updated { from, to: release.version.to_string(), triples: count },
+                None => BundleOutcome::Installed { version: release.version.to_string(), triples: count },
+            };
+            report.bundles.insert(bundle.name.to_string(), outcome);
+        }
+
+        Ok(report)
+    }
+}
+
+fn version_marker(bundle: &OntologyBundle, version: &str) -> Triple {
+    Triple::from_terms(
+        &RdfTerm::iri(bundle.graph),
+        &RdfTerm::iri(OWL_VERSION_INFO),
+        &RdfTerm::literal(version),
+    )
+}
+
+/// Parse the line-oriented N-Triples of a bundled release
+fn parse_release(bundle: &OntologyBundle, release: &BundleRelease) -> Result<Vec<Triple>, BootstrapError> {
+    let error = |line: usize, message: &str| BootstrapError::Parse {
+        bundle: bundle.name.to_string(),
+        version: release.version.to_string(),
+        line,
+        message: message.to_string(),
+    };
+
+    let mut triples = Vec::new();
+    for (index, line) in release.ntriples.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let statement = line.strip_suffix('.').ok_or_else(|| error(index + 1, "missing terminating '.'"))?.trim_end();
+
+        let (subject, rest) = statement.split_once(char::is_whitespace).ok_or_else(|| error(index + 1, "expected subject"))?;
+        let (predicate, object) = rest.trim_start().split_once(char::is_whitespace).ok_or_else(|| error(index + 1, "expected predicate"))?;
+        let object = object.trim();
+
+        if !(subject.starts_with("_:") || subject.starts_with('<') && subject.ends_with('>')) {
+            return Err(error(index + 1, "subject must be an IRI or blank node"));
+        }
+        if !(predicate.starts_with('<') && predicate.ends_with('>')) {
+            return Err(error(index + 1, "predicate must be an IRI"));
+        }
+        let object_term = RdfTerm::parse(object);
+        // 閉じていない引用符は文字列全体がそのままリテラルになる
+        let malformed_literal = object.starts_with('"') && object_term == RdfTerm::literal(object);
+        let malformed_iri = object.starts_with('<') && !object.ends_with('>');
+        if object.is_empty() || malformed_literal || malformed_iri {
+            return Err(error(index + 1, "malformed object"));
+        }
+
+        triples.push(Triple::from_terms(&RdfTerm::parse_node(subject), &RdfTerm::parse_node(predicate), &object_term));
+    }
+    Ok(triples)
+}
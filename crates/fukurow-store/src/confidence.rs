@@ -0,0 +1,163 @@
+//! # Confidence Propagation
+//!
+//! 推論したトリプルの確信度を根拠 (evidence) の確信度から計算する。
+//! - インポートしたデータは 1.0、確信度のないセンサー値・推論は「不明」とし、不明な根拠を含む推論も不明とする
+//! - 同じトリプルが複数のグラフにあれば最も高い確信度を使う
+//! - 根拠がまだストアにない推論結果 (同じ推論の中で導いたもの) は再帰的に計算する
+
+use crate::store::RdfStore;
+use fukurow_core::model::Triple;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// How the confidences of the evidence of one inference are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidenceCombination {
+    /// Weakest evidence (an inference is as strong as its weakest premise)
+    #[default]
+    Min,
+    /// Product of the evidence confidences (premises are independent)
+    Product,
+    /// `1 - Π(1 - c)`: each premise alone supports the inference
+    NoisyOr,
+}
+
+impl ConfidenceCombination {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().replace('-', "_").as_str() {
+            "min" => Some(Self::Min),
+            "product" => Some(Self::Product),
+            "noisy_or" => Some(Self::NoisyOr),
+            _ => None,
+        }
+    }
+
+    /// Combined confidence, 1.0 without evidence
+    pub fn combine(&self, confidences: impl IntoIterator<Item = f64>) -> f64 {
+        let mut confidences = confidences.into_iter().map(|c| c.clamp(0.0, 1.0)).peekable();
+        if confidences.peek().is_none() {
+            return 1.0;
+        }
+        match self {
+            Self::Min => confidences.fold(1.0, f64::min),
+            Self::Product => confidences.product(),
+            Self::NoisyOr => 1.0 - confidences.map(|c| 1.0 - c).product::<f64>(),
+        }
+    }
+}
+
+/// Highest confidence of `triple` in `store` (`None` when it is not stored or unscored)
+pub fn stored_confidence(store: &RdfStore, triple: &Triple) -> Option<f64> {
+    store.find_triples_iter(Some(&triple.subject), Some(&triple.predicate), Some(&triple.object))
+        .filter_map(|stored| stored.provenance.confidence())
+        .fold(None, |best: Option<f64>, c| Some(best.map_or(c, |best| best.max(c))))
+}
+
+/// Confidence of an inference from stored evidence (`None` without evidence or with unscored evidence)
+pub fn derived_confidence(store: &RdfStore, evidence: &[Triple], combination: ConfidenceCombination) -> Option<f64> {
+    if evidence.is_empty() {
+        return None;
+    }
+    evidence.iter()
+        .map(|triple| stored_confidence(store, triple))
+        .collect::<Option<Vec<f64>>>()
+        .map(|confidences| combination.combine(confidences))
+}
+
+/// Confidence of each pending inference, whose evidence may itself be pending
+///
+/// `justifications` は推論結果ごとの根拠。循環した根拠はストアの値で打ち切る。
+/// 確信度が不明な推論は結果に含めない
+pub fn propagate_confidence(
+    store: &RdfStore,
+    justifications: &HashMap<Triple, Vec<Triple>>,
+    combination: ConfidenceCombination,
+) -> HashMap<Triple, f64> {
+    fn resolve(
+        triple: &Triple,
+        store: &RdfStore,
+        justifications: &HashMap<Triple, Vec<Triple>>,
+        combination: ConfidenceCombination,
+        resolved: &mut HashMap<Triple, Option<f64>>,
+        visiting: &mut HashSet<Triple>,
+    ) -> Option<f64> {
+        if let Some(&confidence) = resolved.get(triple) {
+            return confidence;
+        }
+        let evidence = match justifications.get(triple) {
+            Some(evidence) if !evidence.is_empty() && visiting.insert(triple.clone()) => evidence,
+            _ => return stored_confidence(store, triple),
+        };
+        let confidences: Option<Vec<f64>> = evidence.iter()
+            .map(|premise| resolve(premise, store, justifications, combination, resolved, visiting))
+            .collect();
+        visiting.remove(triple);
+
+        let confidence = confidences.map(|confidences| combination.combine(confidences));
+        resolved.insert(triple.clone(), confidence);
+        confidence
+    }
+
+    let mut resolved = HashMap::new();
+    let mut visiting = HashSet::new();
+    for triple in justifications.keys() {
+        resolve(triple, store, justifications, combination, &mut resolved, &mut visiting);
+    }
+    resolved.into_iter()
+        .filter_map(|(triple, confidence)| Some((triple, confidence?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::{GraphId, Provenance};
+
+    fn triple(s: &str, p: &str, o: &str) -> Triple {
+        Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() }
+    }
+
+    fn sensor(source: &str, confidence: Option<f64>) -> Provenance {
+        Provenance::Sensor { source: source.to_string(), confidence }
+    }
+
+    #[test]
+    fn test_combinations() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(ConfidenceCombination::Min.combine([0.9, 0.6]), 0.6));
+        assert!(close(ConfidenceCombination::Product.combine([0.9, 0.6]), 0.54));
+        assert!(close(ConfidenceCombination::NoisyOr.combine([0.9, 0.6]), 0.96));
+        assert!(close(ConfidenceCombination::Product.combine([]), 1.0));
+        assert_eq!(ConfidenceCombination::parse("noisy-or"), Some(ConfidenceCombination::NoisyOr));
+    }
+
+    #[test]
+    fn test_propagates_through_pending_inferences() {
+        let mut store = RdfStore::new();
+        let alert = triple("host", "hasAlert", "c2");
+        store.insert(alert.clone(), GraphId::Sensor("edr".to_string()), sensor("edr", Some(0.8)));
+        store.insert(alert.clone(), GraphId::Sensor("ids".to_string()), sensor("ids", Some(0.5)));
+        let domain = triple("hasAlert", "domain", "Compromised");
+        store.insert(domain.clone(), GraphId::Default, Provenance::Imported { source_uri: "ontology.ttl".to_string(), imported_at: 0 });
+        let beacon = triple("host", "beacons", "c2");
+        store.insert(beacon.clone(), GraphId::Sensor("proxy".to_string()), sensor("proxy", None));
+
+        // host a Compromised ← (alert, domain); host a Isolated ← (host a Compromised, domain)
+        let compromised = triple("host", "type", "Compromised");
+        let isolated = triple("host", "type", "Isolated");
+        let beaconing = triple("host", "type", "Beaconing");
+        let justifications = HashMap::from([
+            (compromised.clone(), vec![alert.clone(), domain.clone()]),
+            (isolated.clone(), vec![compromised.clone(), domain.clone()]),
+            (beaconing.clone(), vec![beacon, domain]),
+        ]);
+
+        let confidences = propagate_confidence(&store, &justifications, ConfidenceCombination::Product);
+        assert_eq!(stored_confidence(&store, &alert), Some(0.8));
+        assert!((confidences[&compromised] - 0.8).abs() < 1e-9);
+        assert!((confidences[&isolated] - 0.8).abs() < 1e-9);
+        // 確信度のない根拠からの推論は不明のまま
+        assert!(!confidences.contains_key(&beaconing));
+    }
+}
@@ -0,0 +1,747 @@
+//! N-Quads / TriG datasets with provenance
+//!
+//! 名前付きグラフを含むストア全体を N-Quads または TriG で書き出し、失われる情報なく読み戻す。
+//! グラフは [`graph_iri`] の規則で IRI に対応付ける。来歴とアサート時刻は RDF のトリプル自体には
+//! 載らないため、メタデータグラフ [`PROVENANCE_GRAPH`] に RDF 具象化で記録する:
+//!
+//! ```text
+//! <urn:fukurow:statement:0> rdf:subject <h1> ; rdf:predicate <port> ; rdf:object "443" ;
+//!     prov:graph <urn:fukurow:sensor:edr> ; prov:assertedAt "20"^^xsd:integer ;
+//!     prov:kind "sensor" ; prov:source "edr" ; prov:confidence "0.8"^^xsd:double .
+//! ```
+//!
+//! `prov:` は `urn:fukurow:prov:`、既定グラフは `prov:graph` に [`DEFAULT_GRAPH_IRI`] を使う。
+//! 推論の来歴は `prov:rule` / `prov:reasoningLevel` / `prov:evidence` (複数)、取り込みの来歴は
+//! `prov:sourceUri` / `prov:importedAt` で表す。メタデータの無いトリプルは `Provenance::Imported` として読む
+
+use crate::persistence::{graph_id_from_iri, graph_iri, parse_nquad, to_nquad, PersistenceError};
+use crate::provenance::{GraphId, Provenance};
+use crate::store::{RdfStore, StoredTriple};
+use fukurow_core::model::Triple;
+use fukurow_core::term::{BlankNodeScope, RdfTerm};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// Graph holding the provenance of every statement of a dataset dump
+pub const PROVENANCE_GRAPH: &str = "urn:fukurow:provenance";
+/// Namespace of the provenance properties
+pub const PROVENANCE_NS: &str = "urn:fukurow:prov:";
+/// Stands for the default graph in `prov:graph`
+pub const DEFAULT_GRAPH_IRI: &str = "urn:fukurow:default-graph";
+/// Prefix of the statement nodes in the provenance graph
+const STATEMENT_PREFIX: &str = "urn:fukurow:statement:";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDF_SUBJECT: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#subject";
+const RDF_PREDICATE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#predicate";
+const RDF_OBJECT: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#object";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// Dataset serialization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatasetFormat {
+    #[default]
+    NQuads,
+    TriG,
+}
+
+impl DatasetFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DatasetFormat::NQuads => "nq",
+            DatasetFormat::TriG => "trig",
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            DatasetFormat::NQuads => "application/n-quads",
+            DatasetFormat::TriG => "application/trig",
+        }
+    }
+
+    /// Guess the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "nq" | "nquads" => Some(DatasetFormat::NQuads),
+            "trig" => Some(DatasetFormat::TriG),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for DatasetFormat {
+    type Err = PersistenceError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "nq" | "nquads" | "n-quads" | "application/n-quads" => Ok(DatasetFormat::NQuads),
+            "trig" | "application/trig" => Ok(DatasetFormat::TriG),
+            other => Err(PersistenceError::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+/// Write triples with their graphs and, in the provenance graph, their provenance
+///
+/// グラフは表示名順、グラフ内は挿入順で書き出す
+pub fn write_dataset<'a>(triples: impl IntoIterator<Item = &'a StoredTriple>, format: DatasetFormat, writer: &mut impl Write) -> Result<usize, PersistenceError> {
+    let mut graphs: BTreeMap<String, Vec<&StoredTriple>> = BTreeMap::new();
+    for stored in triples {
+        // 既定グラフを先頭にする
+        let key = match &stored.graph_id {
+            GraphId::Default => String::new(),
+            graph_id => graph_id.to_string(),
+        };
+        graphs.entry(key).or_default().push(stored);
+    }
+    let ordered: Vec<&StoredTriple> = graphs.values().flatten().copied().collect();
+    let graph_term = RdfTerm::iri(PROVENANCE_GRAPH);
+
+    match format {
+        DatasetFormat::NQuads => {
+            for stored in &ordered {
+                writeln!(writer, "{}", to_nquad(stored))?;
+            }
+            for (index, stored) in ordered.iter().enumerate() {
+                let node = statement_node(index);
+                for (predicate, object) in provenance_statements(stored) {
+                    writeln!(writer, "{} {} {} {} .", node, predicate, object, graph_term)?;
+                }
+            }
+        }
+        DatasetFormat::TriG => {
+            let mut current: Option<&GraphId> = None;
+            for stored in &ordered {
+                if current != Some(&stored.graph_id) {
+                    if current.is_some_and(|graph_id| *graph_id != GraphId::Default) {
+                        writeln!(writer, "}}")?;
+                    }
+                    if let Some(graph) = graph_iri(&stored.graph_id) {
+                        writeln!(writer, "{} {{", RdfTerm::iri(graph))?;
+                    }
+                    current = Some(&stored.graph_id);
+                }
+                let indent = if stored.graph_id == GraphId::Default { "" } else { "    " };
+                let triple = &stored.triple;
+                writeln!(writer, "{}{} {} {} .", indent, triple.subject_term(), triple.predicate_term(), triple.object_term())?;
+            }
+            if current.is_some_and(|graph_id| *graph_id != GraphId::Default) {
+                writeln!(writer, "}}")?;
+            }
+
+            if !ordered.is_empty() {
+                writeln!(writer, "{} {{", graph_term)?;
+                for (index, stored) in ordered.iter().enumerate() {
+                    let statements: Vec<String> = provenance_statements(stored).into_iter()
+                        .map(|(predicate, object)| format!("{} {}", predicate, object))
+                        .collect();
+                    writeln!(writer, "    {} {} .", statement_node(index), statements.join(" ;\n        "))?;
+                }
+                writeln!(writer, "}}")?;
+            }
+        }
+    }
+    Ok(ordered.len())
+}
+
+/// Read a dataset written by [`write_dataset`] (or any N-Quads / TriG document)
+///
+/// `source` は来歴メタデータの無いトリプルの `Provenance::Imported` に記録する
+pub fn read_dataset(reader: impl Read, format: DatasetFormat, source: &str) -> Result<Vec<StoredTriple>, PersistenceError> {
+    let quads = match format {
+        DatasetFormat::NQuads => {
+            let mut quads = Vec::new();
+            for (index, line) in BufReader::new(reader).lines().enumerate() {
+                let line = line?;
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (triple, graph_id) = parse_nquad(line).map_err(|message| PersistenceError::Parse {
+                    line: index + 1,
+                    message: message.to_string(),
+                })?;
+                quads.push(Quad { triple, graph_id, line: index + 1 });
+            }
+            quads
+        }
+        DatasetFormat::TriG => {
+            let mut text = String::new();
+            BufReader::new(reader).read_to_string(&mut text)?;
+            TrigParser::new(&text)?.parse()?
+        }
+    };
+    assemble(quads, source)
+}
+
+impl RdfStore {
+    /// Dump every graph with provenance as N-Quads or TriG; returns the number of triples
+    pub fn export_dataset(&self, format: DatasetFormat, writer: &mut impl Write) -> Result<usize, PersistenceError> {
+        write_dataset(self.all_triples().values().flatten(), format, writer)
+    }
+
+    /// Load a dataset, keeping graphs, provenance and assertion times
+    ///
+    /// ブランクノードは文書ごとのスコープで付け替えるため、既存のノードとは結合しない
+    pub fn import_dataset(&mut self, reader: impl Read, format: DatasetFormat, source: &str) -> Result<usize, PersistenceError> {
+        let triples = read_dataset(reader, format, source)?;
+        let count = triples.len();
+        let mut scope = BlankNodeScope::new();
+        for stored in triples {
+            self.insert_at(scope.relabel_triple(stored.triple.into()), stored.graph_id, stored.provenance, stored.asserted_at);
+        }
+        Ok(count)
+    }
+}
+
+struct Quad {
+    triple: Triple,
+    graph_id: GraphId,
+    line: usize,
+}
+
+fn statement_node(index: usize) -> RdfTerm {
+    RdfTerm::iri(format!("{}{}", STATEMENT_PREFIX, index))
+}
+
+fn prov(name: &str) -> String {
+    format!("{}{}", PROVENANCE_NS, name)
+}
+
+fn integer(value: u64) -> RdfTerm {
+    RdfTerm::typed_literal(value.to_string(), format!("{}integer", XSD))
+}
+
+/// Predicate/object pairs describing one stored triple
+fn provenance_statements(stored: &StoredTriple) -> Vec<(RdfTerm, RdfTerm)> {
+    let triple = &stored.triple;
+    let graph = graph_iri(&stored.graph_id).unwrap_or_else(|| DEFAULT_GRAPH_IRI.to_string());
+    let mut statements = vec![
+        (RdfTerm::iri(RDF_SUBJECT), triple.subject_term()),
+        (RdfTerm::iri(RDF_PREDICATE), triple.predicate_term()),
+        (RdfTerm::iri(RDF_OBJECT), triple.object_term()),
+        (RdfTerm::iri(prov("graph")), RdfTerm::iri(graph)),
+        (RdfTerm::iri(prov("assertedAt")), integer(stored.asserted_at)),
+    ];
+    let mut property = |name: &str, value: RdfTerm| statements.push((RdfTerm::iri(prov(name)), value));
+    match &stored.provenance {
+        Provenance::Sensor { source, confidence } => {
+            property("kind", RdfTerm::literal("sensor"));
+            property("source", RdfTerm::literal(source.as_str()));
+            if let Some(confidence) = confidence {
+                property("confidence", RdfTerm::typed_literal(confidence.to_string(), format!("{}double", XSD)));
+            }
+        }
+        Provenance::Inferred { rule, reasoning_level, evidence, confidence } => {
+            property("kind", RdfTerm::literal("inferred"));
+            property("rule", RdfTerm::literal(rule.as_str()));
+            property("reasoningLevel", RdfTerm::literal(reasoning_level.as_str()));
+            for item in evidence {
+                property("evidence", RdfTerm::literal(item.as_str()));
+            }
+            if let Some(confidence) = confidence {
+                property("confidence", RdfTerm::typed_literal(confidence.to_string(), format!("{}double", XSD)));
+            }
+        }
+        Provenance::Imported { source_uri, imported_at } => {
+            property("kind", RdfTerm::literal("imported"));
+            property("sourceUri", RdfTerm::literal(source_uri.as_str()));
+            property("importedAt", integer(*imported_at));
+        }
+    }
+    statements
+}
+
+/// Join data quads with the provenance recorded in the metadata graph
+fn assemble(quads: Vec<Quad>, source: &str) -> Result<Vec<StoredTriple>, PersistenceError> {
+    let imported_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let provenance_graph = GraphId::Named(PROVENANCE_GRAPH.to_string());
+
+    let mut statements: HashMap<String, (usize, Vec<(String, RdfTerm)>)> = HashMap::new();
+    let mut data = Vec::new();
+    for quad in quads {
+        if quad.graph_id == provenance_graph {
+            statements.entry(quad.triple.subject.clone())
+                .or_insert_with(|| (quad.line, Vec::new()))
+                .1
+                .push((quad.triple.predicate.clone(), quad.triple.object_term()));
+        } else {
+            data.push(quad);
+        }
+    }
+
+    let mut recorded: HashMap<(GraphId, Triple), (Provenance, u64)> = HashMap::new();
+    for (line, properties) in statements.into_values() {
+        let (key, provenance, asserted_at) = decode_statement(&properties)
+            .map_err(|message| PersistenceError::Parse { line, message })?;
+        recorded.insert(key, (provenance, asserted_at));
+    }
+
+    Ok(data.into_iter()
+        .map(|quad| {
+            let (provenance, asserted_at) = recorded.remove(&(quad.graph_id.clone(), quad.triple.clone()))
+                .unwrap_or_else(|| (Provenance::Imported { source_uri: source.to_string(), imported_at }, imported_at));
+            StoredTriple { triple: quad.triple.into(), graph_id: quad.graph_id, provenance, asserted_at }
+        })
+        .collect())
+}
+
+fn decode_statement(properties: &[(String, RdfTerm)]) -> Result<((GraphId, Triple), Provenance, u64), String> {
+    let value = |predicate: &str| properties.iter().find(|(p, _)| p == predicate).map(|(_, term)| term);
+    let text = |name: &str| value(&prov(name)).map(|term| term.value().to_string());
+    let number = |name: &str| -> Result<Option<u64>, String> {
+        text(name).map(|v| v.parse::<u64>().map_err(|_| format!("prov:{} must be an integer", name))).transpose()
+    };
+    let required = |predicate: &str| value(predicate).ok_or_else(|| format!("statement without <{}>", predicate));
+
+    let triple = Triple::from_terms(required(RDF_SUBJECT)?, required(RDF_PREDICATE)?, required(RDF_OBJECT)?);
+    let graph_id = match value(&prov("graph")).and_then(|term| term.as_iri()) {
+        Some(DEFAULT_GRAPH_IRI) | None => GraphId::Default,
+        Some(iri) => graph_id_from_iri(iri),
+    };
+    let asserted_at = number("assertedAt")?.unwrap_or_default();
+
+    let confidence = text("confidence")
+        .map(|v| v.parse::<f64>().map_err(|_| "prov:confidence must be a number".to_string()))
+        .transpose()?;
+
+    let provenance = match text("kind").as_deref() {
+        Some("sensor") => Provenance::Sensor {
+            source: text("source").unwrap_or_default(),
+            confidence,
+        },
+        Some("inferred") => Provenance::Inferred {
+            rule: text("rule").unwrap_or_default(),
+            reasoning_level: text("reasoningLevel").unwrap_or_default(),
+            evidence: properties.iter()
+                .filter(|(p, _)| *p == prov("evidence"))
+                .map(|(_, term)| term.value().to_string())
+                .collect(),
+            confidence,
+        },
+        Some("imported") => Provenance::Imported {
+            source_uri: text("sourceUri").unwrap_or_default(),
+            imported_at: number("importedAt")?.unwrap_or_default(),
+        },
+        Some(other) => return Err(format!("unknown provenance kind {:?}", other)),
+        None => return Err("statement without prov:kind".to_string()),
+    };
+    Ok(((graph_id, triple), provenance, asserted_at))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Iri(String),
+    PrefixedName(String, String),
+    BlankNode(String),
+    Literal { value: String, language: Option<String> },
+    Number(String),
+    /// Keywords (`a`, `true`, `GRAPH`, `@prefix`, ...)
+    Word(String),
+    /// `.`, `;`, `,`, `{`, `}` and `^` (for `^^`)
+    Punct(char),
+}
+
+/// TriG subset: prefixes, graph blocks, `;` / `,` lists, `a`, and numeric / boolean literals
+///
+/// `[ ]`・コレクション・三重引用符の文字列・相対 IRI は扱わない (エラーにする)
+struct TrigParser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    prefixes: HashMap<String, String>,
+    quads: Vec<Quad>,
+}
+
+impl TrigParser {
+    fn new(text: &str) -> Result<Self, PersistenceError> {
+        Ok(Self { tokens: tokenize(text)?, position: 0, prefixes: HashMap::new(), quads: Vec::new() })
+    }
+
+    fn parse(mut self) -> Result<Vec<Quad>, PersistenceError> {
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Word(word) if word == "@prefix" || word.eq_ignore_ascii_case("prefix") => {
+                    self.position += 1;
+                    let line = self.line();
+                    let prefix = match self.next() {
+                        Some(Token::PrefixedName(prefix, local)) if local.is_empty() => prefix,
+                        _ => return Err(self.error(line, "expected a prefix name")),
+                    };
+                    let iri = match self.next() {
+                        Some(Token::Iri(iri)) => iri,
+                        _ => return Err(self.error(line, "expected a namespace IRI")),
+                    };
+                    self.prefixes.insert(prefix, iri);
+                    if word == "@prefix" {
+                        self.expect('.')?;
+                    }
+                }
+                Token::Word(word) if word.eq_ignore_ascii_case("graph") => {
+                    self.position += 1;
+                    let label = self.term()?;
+                    let graph_id = self.graph_id(&label)?;
+                    self.expect('{')?;
+                    self.block(graph_id)?;
+                }
+                Token::Punct('{') => {
+                    self.position += 1;
+                    self.block(GraphId::Default)?;
+                }
+                _ => {
+                    let subject = self.term()?;
+                    if self.peek() == Some(&Token::Punct('{')) {
+                        self.position += 1;
+                        let graph_id = self.graph_id(&subject)?;
+                        self.block(graph_id)?;
+                    } else {
+                        self.predicate_objects(&subject, &GraphId::Default)?;
+                        self.expect('.')?;
+                    }
+                }
+            }
+        }
+        Ok(self.quads)
+    }
+
+    fn block(&mut self, graph_id: GraphId) -> Result<(), PersistenceError> {
+        loop {
+            if self.peek() == Some(&Token::Punct('}')) {
+                self.position += 1;
+                return Ok(());
+            }
+            let subject = self.term()?;
+            self.predicate_objects(&subject, &graph_id)?;
+            match self.next() {
+                Some(Token::Punct('.')) => {}
+                Some(Token::Punct('}')) => return Ok(()),
+                _ => return Err(self.error(self.line(), "expected '.' or '}'")),
+            }
+        }
+    }
+
+    fn predicate_objects(&mut self, subject: &RdfTerm, graph_id: &GraphId) -> Result<(), PersistenceError> {
+        let line = self.line();
+        if subject.is_literal() {
+            return Err(self.error(line, "subject must be an IRI or blank node"));
+        }
+        loop {
+            let predicate = match self.peek() {
+                Some(Token::Word(word)) if word == "a" => {
+                    self.position += 1;
+                    RdfTerm::iri(RDF_TYPE)
+                }
+                _ => self.term()?,
+            };
+            if !predicate.is_iri() {
+                return Err(self.error(self.line(), "predicate must be an IRI"));
+            }
+            loop {
+                let object = self.term()?;
+                self.quads.push(Quad {
+                    triple: Triple::from_terms(subject, &predicate, &object),
+                    graph_id: graph_id.clone(),
+                    line: self.line(),
+                });
+                if self.peek() != Some(&Token::Punct(',')) {
+                    break;
+                }
+                self.position += 1;
+            }
+            if self.peek() != Some(&Token::Punct(';')) {
+                return Ok(());
+            }
+            while self.peek() == Some(&Token::Punct(';')) {
+                self.position += 1;
+            }
+            if matches!(self.peek(), Some(Token::Punct('.' | '}')) | None) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<RdfTerm, PersistenceError> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Iri(iri)) => Ok(RdfTerm::Iri(iri)),
+            Some(Token::PrefixedName(prefix, local)) => self.resolve(&prefix, &local, line),
+            Some(Token::BlankNode(label)) => Ok(RdfTerm::BlankNode(label)),
+            Some(Token::Literal { value, language }) => {
+                if let Some(language) = language {
+                    return Ok(RdfTerm::lang_literal(value, &language));
+                }
+                if self.peek() != Some(&Token::Punct('^')) {
+                    return Ok(RdfTerm::literal(value));
+                }
+                self.position += 1;
+                match self.term()? {
+                    RdfTerm::Iri(datatype) => Ok(RdfTerm::typed_literal(value, datatype)),
+                    _ => Err(self.error(line, "datatype must be an IRI")),
+                }
+            }
+            Some(Token::Number(number)) => {
+                let datatype = if number.contains(['e', 'E']) {
+                    "double"
+                } else if number.contains('.') {
+                    "decimal"
+                } else {
+                    "integer"
+                };
+                Ok(RdfTerm::typed_literal(number, format!("{}{}", XSD, datatype)))
+            }
+            Some(Token::Word(word)) if word == "true" || word == "false" => Ok(RdfTerm::typed_literal(word, format!("{}boolean", XSD))),
+            Some(other) => Err(self.error(line, &format!("unexpected {:?}", other))),
+            None => Err(self.error(line, "unexpected end of document")),
+        }
+    }
+
+    fn resolve(&self, prefix: &str, local: &str, line: usize) -> Result<RdfTerm, PersistenceError> {
+        self.prefixes.get(prefix)
+            .map(|namespace| RdfTerm::iri(format!("{}{}", namespace, local)))
+            .ok_or_else(|| self.error(line, &format!("undeclared prefix {:?}", prefix)))
+    }
+
+    fn graph_id(&self, label: &RdfTerm) -> Result<GraphId, PersistenceError> {
+        label.as_iri()
+            .map(graph_id_from_iri)
+            .ok_or_else(|| self.error(self.line(), "graph label must be an IRI"))
+    }
+
+    fn expect(&mut self, punct: char) -> Result<(), PersistenceError> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Punct(c)) if c == punct => Ok(()),
+            _ => Err(self.error(line, &format!("expected '{}'", punct))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    /// Line of the current token (the last one at the end of the document)
+    fn line(&self) -> usize {
+        self.tokens.get(self.position.min(self.tokens.len().saturating_sub(1))).map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, line: usize, message: &str) -> PersistenceError {
+        PersistenceError::Parse { line, message: message.to_string() }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, PersistenceError> {
+    let error = |line: usize, message: &str| PersistenceError::Parse { line, message: message.to_string() };
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '<' => {
+                let start = i + 1;
+                let end = (start..chars.len()).find(|&j| chars[j] == '>' || chars[j].is_whitespace())
+                    .filter(|&j| chars[j] == '>')
+                    .ok_or_else(|| error(line, "unterminated IRI"))?;
+                tokens.push((Token::Iri(chars[start..end].iter().collect()), line));
+                i = end + 1;
+            }
+            '"' => {
+                if chars.get(i + 1) == Some(&'"') && chars.get(i + 2) == Some(&'"') {
+                    return Err(error(line, "long strings are not supported"));
+                }
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err(error(line, "unterminated string")),
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = chars.get(i + 1).copied().ok_or_else(|| error(line, "unterminated string"))?;
+                            i += 2;
+                            match escaped {
+                                'n' => value.push('\n'),
+                                'r' => value.push('\r'),
+                                't' => value.push('\t'),
+                                'u' | 'U' => {
+                                    let width = if escaped == 'u' { 4 } else { 8 };
+                                    let hex: String = chars.get(i..i + width).ok_or_else(|| error(line, "truncated escape"))?.iter().collect();
+                                    let decoded = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                                        .ok_or_else(|| error(line, "invalid unicode escape"))?;
+                                    value.push(decoded);
+                                    i += width;
+                                }
+                                other => value.push(other),
+                            }
+                            continue;
+                        }
+                        Some(&other) => value.push(other),
+                    }
+                    i += 1;
+                }
+                i += 1;
+                let mut language = None;
+                if chars.get(i) == Some(&'@') {
+                    let start = i + 1;
+                    i = start;
+                    while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '-') {
+                        i += 1;
+                    }
+                    if i == start {
+                        return Err(error(line, "empty language tag"));
+                    }
+                    language = Some(chars[start..i].iter().collect());
+                }
+                tokens.push((Token::Literal { value, language }, line));
+            }
+            '^' => {
+                if chars.get(i + 1) != Some(&'^') {
+                    return Err(error(line, "expected '^^'"));
+                }
+                tokens.push((Token::Punct('^'), line));
+                i += 2;
+            }
+            '.' | ';' | ',' | '{' | '}' => {
+                tokens.push((Token::Punct(c), line));
+                i += 1;
+            }
+            '[' | ']' | '(' | ')' => return Err(error(line, "anonymous blank nodes and collections are not supported")),
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"<>\"{}[]();,^#".contains(chars[i]) {
+                    i += 1;
+                }
+                // 末尾の '.' は文の終端
+                while i > start + 1 && chars[i - 1] == '.' {
+                    i -= 1;
+                }
+                if i == start {
+                    return Err(error(line, &format!("unexpected character {:?}", chars[i])));
+                }
+                let word: String = chars[start..i].iter().collect();
+                let token = if let Some(label) = word.strip_prefix("_:") {
+                    Token::BlankNode(label.to_string())
+                } else if word.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') {
+                    Token::Number(word)
+                } else if !word.starts_with('@') && word.contains(':') {
+                    let (prefix, local) = word.split_once(':').unwrap_or_default();
+                    Token::PrefixedName(prefix.to_string(), local.to_string())
+                } else {
+                    Token::Word(word)
+                };
+                tokens.push((token, line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_store() -> RdfStore {
+        let mut store = RdfStore::new();
+        store.insert_at(
+            Triple::from_terms(&RdfTerm::blank_node("h"), &RdfTerm::iri("http://example.org/label"), &RdfTerm::lang_literal("web \"front\"", "en")),
+            GraphId::Named("urn:fukurow:graph:assets".to_string()),
+            Provenance::Imported { source_uri: "cmdb.csv".to_string(), imported_at: 5 },
+            10,
+        );
+        store.insert_at(
+            Triple::from_terms(&RdfTerm::blank_node("h"), &RdfTerm::iri("http://example.org/port"), &RdfTerm::typed_literal("443", "http://www.w3.org/2001/XMLSchema#integer")),
+            GraphId::Sensor("edr".to_string()),
+            Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.8) },
+            20,
+        );
+        store.insert_at(
+            Triple { subject: "event:1".to_string(), predicate: RDF_TYPE.to_string(), object: "http://example.org/Event".to_string() },
+            GraphId::Inferred("rdfs".to_string()),
+            Provenance::Inferred { rule: "rdfs9".to_string(), reasoning_level: "rdfs".to_string(), evidence: vec!["a".to_string(), "b".to_string()], confidence: Some(0.72) },
+            30,
+        );
+        store.insert_at(
+            Triple { subject: "http://example.org/h1".to_string(), predicate: "http://example.org/ip".to_string(), object: "10.0.0.1".to_string() },
+            GraphId::Default,
+            Provenance::Sensor { source: "netflow".to_string(), confidence: None },
+            40,
+        );
+        store
+    }
+
+    fn sorted(triples: &[StoredTriple]) -> Vec<(String, String, String, String, u64)> {
+        let mut rows: Vec<_> = triples.iter()
+            .map(|s| (s.graph_id.to_string(), s.triple.predicate.to_string(), s.triple.object.to_string(), format!("{:?}", s.provenance), s.asserted_at))
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_dataset_round_trip_preserves_graphs_and_provenance() {
+        let store = sample_store();
+        let original: Vec<StoredTriple> = store.all_triples().values().flatten().cloned().collect();
+
+        for format in [DatasetFormat::NQuads, DatasetFormat::TriG] {
+            let mut buffer = Vec::new();
+            assert_eq!(store.export_dataset(format, &mut buffer).unwrap(), 4);
+            let restored = read_dataset(buffer.as_slice(), format, "dump").unwrap();
+            assert_eq!(sorted(&restored), sorted(&original), "{:?}", format);
+
+            // 同じ文書内のブランクノードは同じノードのまま
+            let subjects: Vec<&str> = restored.iter().filter(|s| s.triple.subject.starts_with("_:")).map(|s| s.triple.subject.as_str()).collect();
+            assert_eq!(subjects.len(), 2);
+            assert_eq!(subjects[0], subjects[1]);
+        }
+    }
+
+    #[test]
+    fn test_trig_parses_prefixes_and_abbreviations() {
+        let input = r#"
+            @prefix ex: <http://example.org/> .
+            PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+            ex:h1 a ex:Host ; ex:port 443, "8080"^^xsd:integer .
+            GRAPH ex:assets {
+                ex:h1 ex:owner "alice"@EN ;
+                      ex:critical true
+            }
+        "#;
+        let triples = read_dataset(input.as_bytes(), DatasetFormat::TriG, "inline.trig").unwrap();
+        assert_eq!(triples.len(), 5);
+        assert!(triples.iter().all(|s| matches!(&s.provenance, Provenance::Imported { source_uri, .. } if source_uri == "inline.trig")));
+        let integer = format!("{}integer", XSD);
+        let ports: Vec<RdfTerm> = triples.iter().filter(|s| s.triple.predicate == "http://example.org/port").map(|s| s.triple.object_term()).collect();
+        assert_eq!(ports, vec![RdfTerm::typed_literal("443", integer.as_str()), RdfTerm::typed_literal("8080", integer.as_str())]);
+        let owner = triples.iter().find(|s| s.triple.predicate == "http://example.org/owner").unwrap();
+        assert_eq!(owner.graph_id, GraphId::Named("http://example.org/assets".to_string()));
+        assert_eq!(owner.triple.object_term(), RdfTerm::lang_literal("alice", "en"));
+
+        match read_dataset("ex:a ex:b ex:c .".as_bytes(), DatasetFormat::TriG, "bad.trig") {
+            Err(PersistenceError::Parse { line: 1, message }) => assert!(message.contains("undeclared prefix")),
+            other => panic!("expected a parse error, got {:?}", other.map(|t| t.len())),
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Event-sourced store mode (CQRS)
+//!
+//! 追記専用のイベントログを唯一の真実とし、インデックス付き RdfStore や
+//! エンティティ要約などの読み取りモデルはログから再構築可能なプロジェクションとして扱う
+
+use fukurow_core::model::Triple;
+use crate::provenance::{Provenance, GraphId};
+use crate::store::RdfStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Store event (source of truth)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StoreEvent {
+    /// Monotonic sequence number (starts at 1)
+    pub sequence: u64,
+    /// When the event was recorded (Unix timestamp in milliseconds)
+    pub timestamp: u64,
+    /// User/context that produced the event
+    pub actor: Option<String>,
+    /// Event payload
+    pub kind: StoreEventKind,
+}
+
+/// Kinds of store events
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum StoreEventKind {
+    /// Triple asserted into a graph
+    TripleAsserted {
+        triple: Triple,
+        graph_id: GraphId,
+        provenance: Provenance,
+    },
+    /// Triple retracted from a graph
+    TripleRetracted {
+        triple: Triple,
+        graph_id: GraphId,
+    },
+    /// Graph cleared
+    GraphCleared {
+        graph_id: GraphId,
+    },
+    /// All graphs cleared
+    AllCleared,
+}
+
+/// Append-only event log; entries are never truncated or rewritten
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventLog {
+    events: Vec<StoreEvent>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn append(&mut self, kind: StoreEventKind, actor: Option<String>) -> &StoreEvent {
+        let sequence = self.events.last().map_or(1, |e| e.sequence + 1);
+        self.events.push(StoreEvent {
+            sequence,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            actor,
+            kind,
+        });
+        self.events.last().expect("event just appended")
+    }
+
+    /// All events in order
+    pub fn events(&self) -> &[StoreEvent] {
+        &self.events
+    }
+
+    /// Events with a sequence number greater than `sequence`
+    pub fn events_since(&self, sequence: u64) -> &[StoreEvent] {
+        let start = self.events.partition_point(|e| e.sequence <= sequence);
+        &self.events[start..]
+    }
+
+    /// Sequence number of the last event (0 when empty)
+    pub fn last_sequence(&self) -> u64 {
+        self.events.last().map_or(0, |e| e.sequence)
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// Read model built by folding store events
+pub trait Projection: std::fmt::Debug + Send + Sync {
+    /// Unique projection name
+    fn name(&self) -> &str;
+
+    /// Apply a single event
+    fn apply(&mut self, event: &StoreEvent);
+
+    /// Reset to the empty state before a rebuild
+    fn reset(&mut self);
+
+    /// Serializable view of the current state
+    fn snapshot(&self) -> serde_json::Value;
+}
+
+/// Per-entity (subject) summary
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EntitySummary {
+    pub triple_count: usize,
+    pub predicates: BTreeSet<String>,
+    pub graphs: BTreeSet<String>,
+    pub last_updated: u64,
+}
+
+/// Projection that maintains per-subject summaries
+///
+/// イベントごとに対象の主語の要約だけを更新する (ストア全体は走査しない)
+#[derive(Debug, Default)]
+pub struct EntitySummaryProjection {
+    summaries: HashMap<String, EntitySummary>,
+    /// Live triples of each subject by graph, kept so retractions and clears can be accounted for
+    live: HashMap<String, HashMap<GraphId, Vec<Triple>>>,
+    /// Subjects with live triples in each graph
+    graph_subjects: HashMap<GraphId, HashSet<String>>,
+}
+
+impl EntitySummaryProjection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn summary(&self, subject: &str) -> Option<&EntitySummary> {
+        self.summaries.get(subject)
+    }
+
+    pub fn summaries(&self) -> &HashMap<String, EntitySummary> {
+        &self.summaries
+    }
+
+    fn recompute(&mut self, subject: &str, timestamp: u64) {
+        let Some(graphs) = self.live.get(subject) else {
+            self.summaries.remove(subject);
+            return;
+        };
+
+        let mut summary = EntitySummary { last_updated: timestamp, ..Default::default() };
+        for (graph_id, triples) in graphs {
+            summary.triple_count += triples.len();
+            summary.predicates.extend(triples.iter().map(|t| t.predicate.clone()));
+            summary.graphs.insert(graph_id.to_string());
+        }
+        self.summaries.insert(subject.to_string(), summary);
+    }
+
+    /// Drop the (subject, graph) entry once it has no live triples
+    fn prune(&mut self, subject: &str, graph_id: &GraphId) {
+        let Some(graphs) = self.live.get_mut(subject) else { return };
+        if graphs.get(graph_id).is_some_and(|triples| triples.is_empty()) {
+            graphs.remove(graph_id);
+            if let Some(subjects) = self.graph_subjects.get_mut(graph_id) {
+                subjects.remove(subject);
+            }
+        }
+        if graphs.is_empty() {
+            self.live.remove(subject);
+        }
+    }
+}
+
+impl Projection for EntitySummaryProjection {
+    fn name(&self) -> &str {
+        "entity_summary"
+    }
+
+    fn apply(&mut self, event: &StoreEvent) {
+        match &event.kind {
+            StoreEventKind::TripleAsserted { triple, graph_id, .. } => {
+                self.live.entry(triple.subject.clone()).or_default()
+                    .entry(graph_id.clone()).or_default()
+                    .push(triple.clone());
+                self.graph_subjects.entry(graph_id.clone()).or_default().insert(triple.subject.clone());
+                self.recompute(&triple.subject, event.timestamp);
+            }
+            StoreEventKind::TripleRetracted { triple, graph_id } => {
+                if let Some(triples) = self.live.get_mut(&triple.subject).and_then(|graphs| graphs.get_mut(graph_id)) {
+                    triples.retain(|t| t != triple);
+                }
+                self.prune(&triple.subject, graph_id);
+                self.recompute(&triple.subject, event.timestamp);
+            }
+            StoreEventKind::GraphCleared { graph_id } => {
+                for subject in self.graph_subjects.remove(graph_id).unwrap_or_default() {
+                    if let Some(graphs) = self.live.get_mut(&subject) {
+                        graphs.remove(graph_id);
+                        if graphs.is_empty() {
+                            self.live.remove(&subject);
+                        }
+                    }
+                    self.recompute(&subject, event.timestamp);
+                }
+            }
+            StoreEventKind::AllCleared => self.reset(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.summaries.clear();
+        self.live.clear();
+        self.graph_subjects.clear();
+    }
+
+    fn snapshot(&self) -> serde_json::Value {
+        serde_json::to_value(&self.summaries).unwrap_or(serde_json::Value::Null)
+    }
+}
+
+/// Errors raised by the event-sourced store
+#[derive(Debug, thiserror::Error)]
+pub enum EventStoreError {
+    #[error("Projection already registered: {0}")]
+    DuplicateProjection(String),
+
+    #[error("Event log is not in sequence order at {0}")]
+    OutOfOrder(u64),
+}
+
+/// Store whose audit/event log is the source of truth and whose indexes are read models
+#[derive(Debug)]
+pub struct EventSourcedStore {
+    log: EventLog,
+    read_model: RdfStore,
+    projections: Vec<Box<dyn Projection>>,
+}
+
+impl EventSourcedStore {
+    pub fn new() -> Self {
+        Self {
+            log: EventLog::new(),
+            read_model: Self::empty_read_model(),
+            projections: Vec::new(),
+        }
+    }
+
+    /// Restore a store from a previously persisted event log
+    pub fn from_log(log: EventLog) -> Result<Self, EventStoreError> {
+        let mut previous = 0;
+        for event in log.events() {
+            if event.sequence <= previous {
+                return Err(EventStoreError::OutOfOrder(event.sequence));
+            }
+            previous = event.sequence;
+        }
+
+        let mut store = Self {
+            log,
+            read_model: Self::empty_read_model(),
+            projections: Vec::new(),
+        };
+        store.rebuild_read_models();
+        Ok(store)
+    }
+
+    // 読み取りモデル側の監査ログは不要（イベントログが真実）
+    fn empty_read_model() -> RdfStore {
+        RdfStore::with_audit_limit(0)
+    }
+
+    /// Assert a triple; returns the event sequence number
+    pub fn assert_triple(&mut self, triple: Triple, graph_id: GraphId, provenance: Provenance, actor: Option<String>) -> u64 {
+        self.record(StoreEventKind::TripleAsserted { triple, graph_id, provenance }, actor)
+    }
+
+    /// Retract a triple; returns the event sequence number
+    pub fn retract_triple(&mut self, triple: Triple, graph_id: GraphId, actor: Option<String>) -> u64 {
+        self.record(StoreEventKind::TripleRetracted { triple, graph_id }, actor)
+    }
+
+    /// Clear a graph; returns the event sequence number
+    pub fn clear_graph(&mut self, graph_id: GraphId, actor: Option<String>) -> u64 {
+        self.record(StoreEventKind::GraphCleared { graph_id }, actor)
+    }
+
+    /// Clear every graph; returns the event sequence number
+    pub fn clear_all(&mut self, actor: Option<String>) -> u64 {
+        self.record(StoreEventKind::AllCleared, actor)
+    }
+
+    fn record(&mut self, kind: StoreEventKind, actor: Option<String>) -> u64 {
+        let event = self.log.append(kind, actor).clone();
+        Self::apply_to_read_model(&mut self.read_model, &event);
+        for projection in &mut self.projections {
+            projection.apply(&event);
+        }
+        event.sequence
+    }
+
+    fn apply_to_read_model(store: &mut RdfStore, event: &StoreEvent) {
+        match &event.kind {
+            StoreEventKind::TripleAsserted { triple, graph_id, provenance } => {
+                store.insert_at(triple.clone(), graph_id.clone(), provenance.clone(), event.timestamp);
+            }
+            StoreEventKind::TripleRetracted { triple, graph_id } => {
+                store.remove_triple(triple, graph_id);
+            }
+            StoreEventKind::GraphCleared { graph_id } => store.clear_graph(graph_id),
+            StoreEventKind::AllCleared => store.clear_all(),
+        }
+    }
+
+    /// Indexed triple store read model
+    pub fn read_model(&self) -> &RdfStore {
+        &self.read_model
+    }
+
+    /// The append-only event log
+    pub fn log(&self) -> &EventLog {
+        &self.log
+    }
+
+    /// Discard all read models and rebuild them by replaying the full log
+    pub fn rebuild_read_models(&mut self) {
+        self.read_model = Self::empty_read_model();
+        for projection in &mut self.projections {
+            projection.reset();
+        }
+
+        for event in self.log.events() {
+            Self::apply_to_read_model(&mut self.read_model, event);
+            for projection in &mut self.projections {
+                projection.apply(event);
+            }
+        }
+    }
+
+    /// Register a new projection and catch it up with the full history
+    pub fn register_projection(&mut self, mut projection: Box<dyn Projection>) -> Result<(), EventStoreError> {
+        if self.projections.iter().any(|p| p.name() == projection.name()) {
+            return Err(EventStoreError::DuplicateProjection(projection.name().to_string()));
+        }
+
+        projection.reset();
+        for event in self.log.events() {
+            projection.apply(event);
+        }
+        self.projections.push(projection);
+        Ok(())
+    }
+
+    /// Look up a registered projection by name
+    pub fn projection(&self, name: &str) -> Option<&dyn Projection> {
+        self.projections.iter().find(|p| p.name() == name).map(|p| p.as_ref())
+    }
+
+    /// Names of all registered projections
+    pub fn projection_names(&self) -> Vec<&str> {
+        self.projections.iter().map(|p| p.name()).collect()
+    }
+
+    /// Rebuild a read model as of a past sequence number (time travel)
+    pub fn read_model_at(&self, sequence: u64) -> RdfStore {
+        let mut store = Self::empty_read_model();
+        for event in self.log.events().iter().take_while(|e| e.sequence <= sequence) {
+            Self::apply_to_read_model(&mut store, event);
+        }
+        store
+    }
+}
+
+impl Default for EventSourcedStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
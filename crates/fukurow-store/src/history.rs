@@ -0,0 +1,99 @@
+//! Historical snapshots reconstructed from assertion timestamps and the audit log
+//!
+//! 現在のトリプルのうち指定時刻以前に挿入されたものに、指定時刻より後に削除された
+//! トリプルを監査ログから復元して過去時点のストアを再構成する
+
+use crate::audit::{AuditQuery, AuditSinkError};
+use crate::provenance::{AuditOperation, GraphId, Provenance};
+use crate::store::RdfStore;
+use fukurow_core::model::Triple;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+/// Errors reconstructing past states of the store
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    /// The audit entries needed to reconstruct `requested` were already pruned
+    #[error("No audit history at {requested}: the oldest retained entry is {}", .oldest.map_or_else(|| "missing".to_string(), |t| t.to_string()))]
+    BeforeRetention { requested: u64, oldest: Option<u64> },
+
+    #[error(transparent)]
+    Audit(#[from] AuditSinkError),
+}
+
+/// Parse the legacy `"subject predicate object"` form of audit entries
+pub fn parse_audit_triple(triple: &str) -> Option<Triple> {
+    let mut parts = triple.splitn(3, ' ');
+    let subject = parts.next()?;
+    let predicate = parts.next()?;
+    let object = parts.next()?;
+    if subject.is_empty() || predicate.is_empty() {
+        return None;
+    }
+    Some(Triple {
+        subject: subject.to_string(),
+        predicate: predicate.to_string(),
+        object: object.to_string(),
+    })
+}
+
+/// Deserialize an audit triple, accepting the legacy string form written by older versions
+pub(crate) fn deserialize_audit_triple<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Triple, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AuditTriple {
+        Structured(Triple),
+        Legacy(String),
+    }
+
+    match AuditTriple::deserialize(deserializer)? {
+        AuditTriple::Structured(triple) => Ok(triple),
+        AuditTriple::Legacy(text) => parse_audit_triple(&text)
+            .ok_or_else(|| serde::de::Error::custom(format!("malformed audit triple: {}", text))),
+    }
+}
+
+impl RdfStore {
+    /// Reconstruct the store as it was at `timestamp` (Unix milliseconds)
+    ///
+    /// Triples removed with `clear_graph`/`clear_all` cannot be restored because the
+    /// audit log only records their count; deletions via `remove_triple` are restored
+    /// from their original insert. Fails with [`HistoryError::BeforeRetention`] when
+    /// entries were dropped from the audit trail and `timestamp` is older than the
+    /// oldest retained one.
+    pub fn snapshot_at(&self, timestamp: u64) -> Result<RdfStore, HistoryError> {
+        // 新しい順に返るため、以下では rev() で古い順に走査する
+        let history = self.query_audit(&AuditQuery::default())?;
+        // 捨てられたエントリより前の変更は追えないので、近似のスナップショットを返さずに失敗させる
+        let oldest = history.last().map(|entry| entry.timestamp);
+        if self.audit_history_pruned() && oldest.is_none_or(|oldest| timestamp < oldest) {
+            return Err(HistoryError::BeforeRetention { requested: timestamp, oldest });
+        }
+
+        let mut snapshot = RdfStore::with_audit_limit(0);
+
+        for stored in self.all_triples().values().flatten() {
+            if stored.asserted_at <= timestamp {
+                snapshot.insert_at(stored.triple.clone(), stored.graph_id.clone(), stored.provenance.clone(), stored.asserted_at);
+            }
+        }
+
+        // 指定時刻以前の挿入（最新のもの）を記録
+        let mut inserted_before: HashMap<(Triple, GraphId), (Provenance, u64)> = HashMap::new();
+        for entry in history.iter().rev().filter(|e| e.timestamp <= timestamp) {
+            if let AuditOperation::Insert { triple, graph_id, provenance } = &entry.operation {
+                inserted_before.insert((triple.clone(), graph_id.clone()), (provenance.clone(), entry.timestamp));
+            }
+        }
+
+        for entry in history.iter().rev().filter(|e| e.timestamp > timestamp) {
+            if let AuditOperation::Delete { triple, graph_id } = &entry.operation {
+                if let Some((provenance, asserted_at)) = inserted_before.remove(&(triple.clone(), graph_id.clone())) {
+                    snapshot.insert_at(triple.clone(), graph_id.clone(), provenance, asserted_at);
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
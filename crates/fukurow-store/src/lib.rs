@@ -9,12 +9,51 @@ use std::collections::HashMap;
 
 pub mod store;
 pub mod provenance;
+pub mod event_sourced;
+pub mod anonymize;
+pub mod audit;
+pub mod sensors;
+pub mod history;
+pub mod vocabulary;
+pub mod adapter;
+pub mod bootstrap;
+pub mod snapshot;
+pub mod persistence;
+pub mod dataset;
+pub mod tenant;
+pub mod wal;
+pub mod retention;
+pub mod confidence;
+pub mod replication;
+pub mod access_stats;
+#[cfg(feature = "tokio")]
+pub mod shared;
 
 pub use store::*;
 pub use provenance::*;
+pub use event_sourced::*;
+pub use anonymize::*;
+pub use audit::*;
+pub use sensors::*;
+pub use history::*;
+pub use vocabulary::*;
+pub use adapter::*;
+pub use bootstrap::*;
+pub use snapshot::*;
+pub use persistence::*;
+pub use dataset::*;
+pub use tenant::*;
+pub use wal::*;
+pub use retention::*;
+pub use confidence::*;
+pub use replication::*;
+pub use access_stats::*;
+#[cfg(feature = "tokio")]
+pub use shared::*;
 
 // Re-export Triple from fukurow_core for external use
-pub use fukurow_core::model::Triple;
+pub use fukurow_core::model::{InternedString, InternedTriple, Triple};
+pub use fukurow_core::term::{BlankNodeScope, RdfTerm};
 
 #[cfg(test)]
 mod tests {
@@ -52,6 +91,28 @@ mod tests {
         assert_eq!(results[0].provenance, provenance);
     }
 
+    #[test]
+    fn test_statistics_report_interned_terms() {
+        let mut store = RdfStore::new();
+        let predicate = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+        for i in 0..10 {
+            store.insert(Triple {
+                subject: format!("host{}", i),
+                predicate: predicate.to_string(),
+                object: "Host".to_string(),
+            }, GraphId::Default, Provenance::Sensor { source: "s".to_string(), confidence: None });
+        }
+
+        let stats = store.statistics();
+        assert_eq!(stats.distinct_terms, 12);
+        // 述語と目的語は 10 回参照されるが文字列は一つずつ
+        assert_eq!(stats.interning_saved_bytes, 9 * (predicate.len() + "Host".len()));
+
+        let stored = store.find_triples(Some("host0"), None, None)[0].triple.clone();
+        let other = store.find_triples(Some("host1"), None, None)[0].triple.clone();
+        assert!(stored.predicate.ptr_eq(&other.predicate));
+    }
+
     #[test]
     fn test_graph_id_display() {
         assert_eq!(format!("{}", GraphId::Default), "default");
@@ -80,7 +141,7 @@ mod tests {
     #[test]
     fn test_audit_operation_display() {
         let operation = AuditOperation::Insert {
-            triple: "s p o".to_string(),
+            triple: Triple { subject: "s".to_string(), predicate: "p".to_string(), object: "o".to_string() },
             graph_id: GraphId::Default,
             provenance: Provenance::Sensor {
                 source: "test".to_string(),
@@ -90,7 +151,7 @@ mod tests {
 
         match operation {
             AuditOperation::Insert { triple, graph_id, provenance } => {
-                assert_eq!(triple, "s p o");
+                assert_eq!(triple.object, "o");
                 assert_eq!(graph_id, GraphId::Default);
                 match provenance {
                     Provenance::Sensor { source, confidence } => {
@@ -161,6 +222,25 @@ mod tests {
         assert_eq!(store.statistics().graph_count, 0);
     }
 
+    #[test]
+    fn test_clear_releases_pooled_strings() {
+        let mut store = RdfStore::new();
+        let graph_id = GraphId::Named("release_test".to_string());
+        let subject = "urn:test:release-after-clear";
+        store.insert(Triple { subject: subject.to_string(), predicate: "p".to_string(), object: "o".to_string() },
+                     graph_id.clone(), Provenance::Sensor { source: "test".to_string(), confidence: None });
+        assert!(InternedString::is_interned(subject));
+
+        // クリア後はどのトリプルも参照しない文字列がプールに残らない
+        store.clear_graph(&graph_id);
+        assert!(!InternedString::is_interned(subject));
+
+        store.insert(Triple { subject: subject.to_string(), predicate: "p".to_string(), object: "o".to_string() },
+                     GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        store.clear_all();
+        assert!(!InternedString::is_interned(subject));
+    }
+
     #[test]
     fn test_clear_all() {
         let mut store = RdfStore::new();
@@ -217,10 +297,11 @@ mod tests {
             rule: "inference_rule".to_string(),
             reasoning_level: "owl".to_string(),
             evidence: evidence.clone(),
+            confidence: None,
         };
 
         match provenance {
-            Provenance::Inferred { rule, reasoning_level, evidence: ev } => {
+            Provenance::Inferred { rule, reasoning_level, evidence: ev, .. } => {
                 assert_eq!(rule, "inference_rule");
                 assert_eq!(reasoning_level, "owl");
                 assert_eq!(ev, evidence);
@@ -250,13 +331,13 @@ mod tests {
     fn test_audit_operation_variants() {
         // Test all audit operation variants
         let insert_op = AuditOperation::Insert {
-            triple: "s p o".to_string(),
+            triple: Triple { subject: "s".to_string(), predicate: "p".to_string(), object: "o".to_string() },
             graph_id: GraphId::Default,
             provenance: Provenance::Sensor { source: "test".to_string(), confidence: None },
         };
 
         let delete_op = AuditOperation::Delete {
-            triple: "s p o".to_string(),
+            triple: Triple { subject: "s".to_string(), predicate: "p".to_string(), object: "o".to_string() },
             graph_id: GraphId::Named("test".to_string()),
         };
 
@@ -359,6 +440,57 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    #[test]
+    fn test_find_triples_iter_is_lazy() {
+        let mut store = RdfStore::new();
+        for i in 0..100 {
+            store.insert(Triple { subject: format!("s{}", i), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        }
+
+        let mut visited = 0;
+        let first: Vec<&StoredTriple> = store.find_triples_iter(None, Some("p1"), None)
+            .inspect(|_| visited += 1)
+            .take(3)
+            .collect();
+        assert_eq!(first.len(), 3);
+        assert_eq!(visited, 3);
+
+        assert_eq!(store.find_triples_iter(Some("s7"), Some("p1"), Some("o1")).count(), 1);
+        assert_eq!(store.find_triples_iter(Some("s7"), Some("p2"), None).count(), 0);
+        assert_eq!(store.find_triples_iter(None, None, None).count(), store.find_triples(None, None, None).len());
+    }
+
+    #[test]
+    fn test_find_triples_page_follows_keyset() {
+        let mut store = RdfStore::new();
+        for i in (0..5).rev() {
+            let triple = Triple { subject: format!("s{}", i), predicate: "p1".to_string(), object: "o1".to_string() };
+            store.insert(triple.clone(), GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+            // 別グラフの同じトリプルは 1 件として数える
+            store.insert(triple, GraphId::Named("copy".to_string()), Provenance::Sensor { source: "test".to_string(), confidence: None });
+        }
+
+        let subjects = |page: Vec<Triple>| page.into_iter().map(|t| t.subject).collect::<Vec<_>>();
+        assert_eq!(subjects(store.find_triples_page(None, Some("p1"), None, None, 2)), vec!["s0", "s1"]);
+        assert_eq!(subjects(store.find_triples_page(None, Some("p1"), None, Some(("s1", "p1", "o1")), 2)), vec!["s2", "s3"]);
+        assert_eq!(subjects(store.find_triples_page(None, Some("p1"), None, Some(("s3", "p1", "o1")), 2)), vec!["s4"]);
+        assert!(store.find_triples_page(None, Some("p2"), None, None, 2).is_empty());
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn test_find_triples_stream() {
+        use futures::StreamExt;
+
+        let mut store = RdfStore::new();
+        for i in 0..10 {
+            store.insert(Triple { subject: format!("s{}", i), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        }
+
+        let found: Vec<&StoredTriple> = futures::executor::block_on(store.find_triples_stream(None, None, Some("o1")).take(4).collect());
+        assert_eq!(found.len(), 4);
+    }
+
     #[test]
     fn test_store_statistics() {
         let mut store = RdfStore::new();
@@ -414,7 +546,7 @@ mod tests {
         let audit_trail = store.get_audit_trail();
         assert_eq!(audit_trail.len(), 1);
         assert_eq!(audit_trail[0].operation, AuditOperation::Insert {
-            triple: "s p o".to_string(),
+            triple: Triple { subject: "s".to_string(), predicate: "p".to_string(), object: "o".to_string() },
             graph_id: GraphId::Default,
             provenance: Provenance::Sensor { source: "test".to_string(), confidence: None }
         });
@@ -436,4 +568,726 @@ mod tests {
         // Should have the 2 most recent entries
         assert_eq!(store.audit_trail().len(), 2);
     }
+
+    #[test]
+    fn test_remove_triple() {
+        let mut store = RdfStore::new();
+        let triple = Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1".to_string() };
+        store.insert(triple.clone(), GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        store.insert(Triple { subject: "s2".to_string(), predicate: "p1".to_string(), object: "o2".to_string() }, GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+
+        assert_eq!(store.remove_triple(&triple, &GraphId::Default), 1);
+        assert_eq!(store.remove_triple(&triple, &GraphId::Default), 0);
+        assert!(store.find_triples(Some("s1"), None, None).is_empty());
+        assert_eq!(store.find_triples(None, Some("p1"), None).len(), 1);
+        assert!(matches!(store.audit_trail().last().unwrap().operation, AuditOperation::Delete { .. }));
+    }
+
+    #[test]
+    fn test_remove_triple_keeps_indices_of_later_triples() {
+        let mut store = RdfStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        let triple = |s: &str, o: &str| Triple { subject: s.to_string(), predicate: "p".to_string(), object: o.to_string() };
+        for (s, o) in [("s1", "o1"), ("s2", "o2"), ("s1", "o1"), ("s3", "o3")] {
+            store.insert(triple(s, o), GraphId::Default, prov.clone());
+        }
+        store.insert(triple("s1", "o1"), GraphId::Named("other".to_string()), prov);
+
+        // 削除位置より後ろのトリプルも索引から引ける
+        assert_eq!(store.remove_triple(&triple("s1", "o1"), &GraphId::Default), 2);
+        let subjects = |o: &str| store.find_triples(None, None, Some(o)).into_iter().map(|t| t.triple.subject.to_string()).collect::<Vec<_>>();
+        assert_eq!(subjects("o2"), vec!["s2"]);
+        assert_eq!(subjects("o3"), vec!["s3"]);
+        assert_eq!(subjects("o1"), vec!["s1"]);
+        assert_eq!(store.find_triples(None, Some("p"), None).len(), 3);
+        let order: Vec<String> = store.get_graph(&GraphId::Default).iter().map(|t| t.triple.subject.to_string()).collect();
+        assert_eq!(order, vec!["s2", "s3"]);
+    }
+
+    #[test]
+    fn test_event_sourced_store_rebuilds_read_models() {
+        let mut store = EventSourcedStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        let t1 = Triple { subject: "host1".to_string(), predicate: "ip".to_string(), object: "10.0.0.1".to_string() };
+        let t2 = Triple { subject: "host1".to_string(), predicate: "os".to_string(), object: "linux".to_string() };
+
+        store.assert_triple(t1.clone(), GraphId::Default, prov.clone(), None);
+        store.assert_triple(t2.clone(), GraphId::Named("events".to_string()), prov.clone(), Some("analyst".to_string()));
+        let seq = store.retract_triple(t1.clone(), GraphId::Default, None);
+
+        assert_eq!(seq, 3);
+        assert_eq!(store.log().len(), 3);
+        assert_eq!(store.read_model().statistics().total_triples, 1);
+        assert_eq!(store.read_model_at(2).statistics().total_triples, 2);
+
+        // 読み取りモデルはログから完全に再構築できる
+        let restored = EventSourcedStore::from_log(store.log().clone()).unwrap();
+        assert_eq!(restored.read_model().find_triples(Some("host1"), None, None).len(), 1);
+        assert_eq!(restored.log().events_since(1).len(), 2);
+
+        store.rebuild_read_models();
+        assert_eq!(store.read_model().statistics().total_triples, 1);
+        assert_eq!(store.log().len(), 3);
+    }
+
+    #[test]
+    fn test_entity_summary_projection_catches_up() {
+        let mut store = EventSourcedStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        store.assert_triple(Triple { subject: "host1".to_string(), predicate: "ip".to_string(), object: "10.0.0.1".to_string() }, GraphId::Default, prov.clone(), None);
+        store.assert_triple(Triple { subject: "host1".to_string(), predicate: "os".to_string(), object: "linux".to_string() }, GraphId::Default, prov.clone(), None);
+
+        store.register_projection(Box::new(EntitySummaryProjection::new())).unwrap();
+        assert!(store.register_projection(Box::new(EntitySummaryProjection::new())).is_err());
+
+        store.assert_triple(Triple { subject: "host2".to_string(), predicate: "ip".to_string(), object: "10.0.0.2".to_string() }, GraphId::Default, prov, None);
+        store.clear_graph(GraphId::Default, None);
+        store.rebuild_read_models();
+
+        let snapshot = store.projection("entity_summary").unwrap().snapshot();
+        assert_eq!(snapshot, serde_json::json!({}));
+        assert_eq!(store.projection_names(), vec!["entity_summary"]);
+    }
+
+    #[test]
+    fn test_entity_summary_projection_tracks_retractions_per_graph() {
+        let mut projection = EntitySummaryProjection::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        let ip = Triple { subject: "host1".to_string(), predicate: "ip".to_string(), object: "10.0.0.1".to_string() };
+        let os = Triple { subject: "host1".to_string(), predicate: "os".to_string(), object: "linux".to_string() };
+        let inferred = GraphId::Named("inferred".to_string());
+        let event = |sequence, kind| StoreEvent { sequence, timestamp: sequence, actor: None, kind };
+
+        projection.apply(&event(1, StoreEventKind::TripleAsserted { triple: ip.clone(), graph_id: GraphId::Default, provenance: prov.clone() }));
+        projection.apply(&event(2, StoreEventKind::TripleAsserted { triple: os.clone(), graph_id: inferred.clone(), provenance: prov }));
+        let summary = projection.summary("host1").unwrap();
+        assert_eq!((summary.triple_count, summary.graphs.len(), summary.last_updated), (2, 2, 2));
+
+        projection.apply(&event(3, StoreEventKind::TripleRetracted { triple: ip, graph_id: GraphId::Default }));
+        let summary = projection.summary("host1").unwrap();
+        assert_eq!(summary.predicates.iter().collect::<Vec<_>>(), vec!["os"]);
+        assert_eq!(summary.graphs.iter().collect::<Vec<_>>(), vec!["named:inferred"]);
+
+        projection.apply(&event(4, StoreEventKind::GraphCleared { graph_id: inferred }));
+        assert!(projection.summary("host1").is_none());
+    }
+
+    #[test]
+    fn test_event_log_rejects_out_of_order() {
+        let mut store = EventSourcedStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        store.assert_triple(Triple { subject: "s".to_string(), predicate: "p".to_string(), object: "o".to_string() }, GraphId::Default, prov, None);
+
+        let mut events = store.log().events().to_vec();
+        events.push(events[0].clone());
+        let log: EventLog = serde_json::from_value(serde_json::json!({ "events": events })).unwrap();
+        assert!(matches!(EventSourcedStore::from_log(log), Err(EventStoreError::OutOfOrder(1))));
+    }
+
+    #[test]
+    fn test_generalize_value() {
+        assert_eq!(generalize_value("192.168.1.10", 0), "192.168.1.10");
+        assert_eq!(generalize_value("192.168.1.10", 1), "192.168.1.0/24");
+        assert_eq!(generalize_value("192.168.1.10", 2), "192.168.0.0/16");
+        assert_eq!(generalize_value("443", 1), "440-449");
+        assert_eq!(generalize_value("alice", 1), "al*");
+        assert_eq!(generalize_value("anything", MAX_GENERALIZATION_LEVEL), "*");
+    }
+
+    #[test]
+    fn test_anonymized_export_k_anonymity() {
+        let mut store = RdfStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        for (host, ip) in [("host1", "10.0.1.1"), ("host2", "10.0.1.2"), ("host3", "10.0.2.3")] {
+            store.insert(Triple { subject: host.to_string(), predicate: "ip".to_string(), object: ip.to_string() }, GraphId::Default, prov.clone());
+            store.insert(Triple { subject: host.to_string(), predicate: "alerts".to_string(), object: "1".to_string() }, GraphId::Default, prov.clone());
+        }
+        store.insert(Triple { subject: "host1".to_string(), predicate: "owner".to_string(), object: "alice".to_string() }, GraphId::Default, prov);
+
+        let config = AnonymizationConfig::new(3, 1.0)
+            .with_predicate("ip")
+            .with_predicate("alerts")
+            .with_quasi_identifier("ip")
+            .with_seed(42);
+        let export = Anonymizer::new(config.clone()).unwrap().export(&store);
+
+        assert_eq!(export.report.exported_triples, 6);
+        assert_eq!(export.report.suppressed_triples, 0);
+        assert_eq!(export.report.pseudonymized_subjects, 3);
+        assert_eq!(export.report.generalizations[0].level, 2);
+        assert!(export.triples.iter().all(|t| t.subject.starts_with("anon:") && t.predicate != "owner"));
+        assert!(export.triples.iter().filter(|t| t.predicate == "ip").all(|t| t.object == "10.0.0.0/16"));
+
+        // 同じシードなら同じノイズ
+        let again = Anonymizer::new(config).unwrap().export(&store);
+        assert_eq!(export.noisy_counts, again.noisy_counts);
+    }
+
+    #[test]
+    fn test_anonymized_export_suppression_and_config() {
+        let mut store = RdfStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        store.insert(Triple { subject: "host1".to_string(), predicate: "ip".to_string(), object: "10.0.0.1".to_string() }, GraphId::Default, prov);
+
+        let export = Anonymizer::new(AnonymizationConfig::new(2, 0.5).with_quasi_identifier("ip").with_seed(1)).unwrap().export(&store);
+        assert!(export.triples.is_empty());
+        assert_eq!(export.report.suppressed_triples, 1);
+        assert_eq!(export.report.suppressed_by_predicate.get("ip"), Some(&1));
+
+        assert!(Anonymizer::new(AnonymizationConfig::new(0, 1.0)).is_err());
+        assert!(Anonymizer::new(AnonymizationConfig::new(2, 0.0)).is_err());
+    }
+
+    #[test]
+    fn test_anonymized_export_checks_quasi_identifier_combinations() {
+        let mut store = RdfStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        // ip (/24) と port はそれぞれ単独では 2-匿名だが、組み合わせると全員が一意になる
+        for (host, ip, port) in [("host1", "10.0.1.1", "80"), ("host2", "10.0.1.2", "443"), ("host3", "10.0.2.3", "80"), ("host4", "10.0.2.4", "443")] {
+            store.insert(Triple { subject: host.to_string(), predicate: "ip".to_string(), object: ip.to_string() }, GraphId::Default, prov.clone());
+            store.insert(Triple { subject: host.to_string(), predicate: "port".to_string(), object: port.to_string() }, GraphId::Default, prov.clone());
+        }
+
+        let config = AnonymizationConfig::new(2, 1.0).with_quasi_identifier("ip").with_quasi_identifier("port").with_seed(7);
+        let export = Anonymizer::new(config).unwrap().export(&store);
+        assert_eq!(export.report.suppressed_triples, 0);
+        let levels: Vec<u8> = export.report.generalizations.iter().map(|g| g.level).collect();
+        assert_eq!(levels, vec![2, 0]);
+
+        let mut tuples: HashMap<String, Vec<String>> = HashMap::new();
+        for triple in &export.triples {
+            tuples.entry(triple.subject.clone()).or_default().push(triple.object.clone());
+        }
+        let mut classes: HashMap<Vec<String>, usize> = HashMap::new();
+        for tuple in tuples.into_values() {
+            *classes.entry(tuple).or_insert(0) += 1;
+        }
+        assert!(classes.values().all(|size| *size >= 2), "{:?}", classes);
+    }
+
+    #[test]
+    fn test_anonymized_export_pseudonymizes_iri_objects() {
+        let mut store = RdfStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        let triples = [
+            ("http://example.org/host1", "connectsTo", "http://example.org/c2"),
+            ("http://example.org/host2", "peer", "http://example.org/host1"),
+            ("http://example.org/host2", "label", "workstation"),
+        ];
+        for (s, p, o) in triples {
+            store.insert(Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() }, GraphId::Default, prov.clone());
+        }
+
+        let export = Anonymizer::new(AnonymizationConfig::new(1, 1.0).with_seed(3)).unwrap().export(&store);
+        assert!(export.triples.iter().all(|t| !t.subject.contains("example.org") && !t.object.contains("example.org")));
+        let object = |predicate: &str| export.triples.iter().find(|t| t.predicate == predicate).unwrap().object.clone();
+        let host1 = export.triples.iter().find(|t| t.predicate == "connectsTo").unwrap().subject.clone();
+        // 目的語に現れた主語は主語と同じ仮名になる
+        assert_eq!(object("peer"), host1);
+        assert_eq!(object("connectsTo"), "anon:iri-1");
+        assert_eq!(object("label"), "workstation");
+        assert_eq!((export.report.pseudonymized_subjects, export.report.pseudonymized_iris), (2, 1));
+    }
+
+    #[test]
+    fn test_audit_sink_outlives_audit_limit() {
+        let mut store = RdfStore::with_audit_limit(1);
+        store.set_audit_sink(Box::new(InMemoryAuditSink::new()));
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        store.insert(Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Default, prov.clone());
+        store.insert(Triple { subject: "s2".to_string(), predicate: "p2".to_string(), object: "o2".to_string() }, GraphId::Named("events".to_string()), prov);
+        store.clear_graph(&GraphId::Named("events".to_string()));
+
+        assert_eq!(store.audit_trail().len(), 1);
+        assert_eq!(store.query_audit(&AuditQuery::default()).unwrap().len(), 3);
+
+        let inserts = store.query_audit(&AuditQuery { operation: Some("insert".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(inserts.len(), 2);
+
+        let events = store.query_audit(&AuditQuery { graph: Some("named:events".to_string()), limit: Some(1), ..Default::default() }).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].operation.kind(), "clear");
+
+        let none = store.query_audit(&AuditQuery { actor: Some("analyst".to_string()), ..Default::default() }).unwrap();
+        assert!(none.is_empty());
+        assert_eq!(store.audit_sink_failures(), 0);
+    }
+
+    #[test]
+    fn test_jsonl_audit_sink_persists_across_instances() {
+        let path = std::env::temp_dir().join(format!("fukurow-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let mut store = RdfStore::new();
+            store.set_audit_sink(Box::new(JsonlAuditSink::new(&path).unwrap()));
+            store.insert(Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        }
+
+        let sink = JsonlAuditSink::new(&path).unwrap();
+        let entries = sink.query(&AuditQuery { from: Some(0), ..Default::default() }).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].operation.kind(), "insert");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_audit_sink_round_trip() {
+        let path = std::env::temp_dir().join(format!("fukurow-audit-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        {
+            let mut store = RdfStore::new();
+            store.set_audit_sink(Box::new(SqliteAuditSink::open(&path).unwrap()));
+            store.insert(Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Default, prov.clone());
+            store.insert(Triple { subject: "s2".to_string(), predicate: "p2".to_string(), object: "o2".to_string() }, GraphId::Named("events".to_string()), prov);
+            store.clear_graph(&GraphId::Named("events".to_string()));
+            assert_eq!(store.audit_sink_failures(), 0);
+        }
+
+        // 開き直したシンクは書き込んだエントリをそのまま新しい順に返す
+        let sink = SqliteAuditSink::open(&path).unwrap();
+        let entries = sink.query(&AuditQuery::default()).unwrap();
+        assert_eq!(entries.iter().map(|e| e.operation.kind()).collect::<Vec<_>>(), vec!["clear", "insert", "insert"]);
+        match &entries[2].operation {
+            AuditOperation::Insert { graph_id, triple, .. } => {
+                assert_eq!(graph_id, &GraphId::Default);
+                assert_eq!(triple.subject, "s1");
+            }
+            other => panic!("unexpected operation: {:?}", other),
+        }
+
+        let events = sink.query(&AuditQuery { graph: Some("named:events".to_string()), operation: Some("INSERT".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(events.len(), 1);
+        let from = entries[0].timestamp;
+        assert!(sink.query(&AuditQuery { from: Some(from + 1), ..Default::default() }).unwrap().is_empty());
+        assert_eq!(sink.query(&AuditQuery { limit: Some(2), ..Default::default() }).unwrap().len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_sensor_registry_tracks_heartbeats() {
+        let mut store = RdfStore::new();
+        let t = |s: &str| Triple { subject: s.to_string(), predicate: "p".to_string(), object: "o".to_string() };
+        let edr = Provenance::Sensor { source: "edr-001".to_string(), confidence: None };
+        let fw = Provenance::Sensor { source: "fw-001".to_string(), confidence: Some(0.9) };
+        store.insert_at(t("e1"), GraphId::Default, edr.clone(), 1_000);
+        store.insert_at(t("e2"), GraphId::Default, edr, 121_000);
+        store.insert_at(t("f1"), GraphId::Default, fw, 2_000);
+
+        let edr = store.sensor_registry().get("edr-001").unwrap();
+        assert_eq!((edr.first_seen, edr.last_seen, edr.observation_count), (1_000, 121_000, 2));
+        assert!((edr.rate_per_minute() - 1.0).abs() < 1e-9);
+
+        let stale = store.sensor_registry().stale(200_000, 150_000);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].record.source, "fw-001");
+
+        store.clear_all();
+        store.materialize_sensor_registry();
+        let registry_graph = store.get_graph(&GraphId::Named(SENSOR_REGISTRY_GRAPH.to_string()));
+        assert!(registry_graph.iter().any(|t| t.triple.subject == "urn:fukurow:sensor:edr-001"
+            && t.triple.predicate == "https://w3id.org/security#lastSeen"
+            && t.triple.object == "121000"));
+        assert_eq!(store.sensor_registry().len(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_at_restores_deleted_triples() {
+        let mut store = RdfStore::new();
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        let kept = Triple { subject: "s1".to_string(), predicate: "p".to_string(), object: "o1".to_string() };
+        let removed = Triple { subject: "s2".to_string(), predicate: "p".to_string(), object: "some literal".to_string() };
+        store.insert(kept.clone(), GraphId::Default, prov.clone());
+        store.insert(removed.clone(), GraphId::Default, prov.clone());
+
+        let checkpoint = store.audit_trail().last().unwrap().timestamp;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        store.remove_triple(&removed, &GraphId::Default);
+        let late = Triple { subject: "s3".to_string(), predicate: "p".to_string(), object: "o3".to_string() };
+        store.insert(late, GraphId::Default, prov);
+
+        let snapshot = store.snapshot_at(checkpoint).unwrap();
+        let subjects: std::collections::BTreeSet<_> = snapshot.find_triples(None, Some("p"), None)
+            .into_iter()
+            .map(|t| t.triple.subject.to_string())
+            .collect();
+        assert_eq!(subjects, ["s1", "s2"].iter().map(|s| s.to_string()).collect());
+        assert_eq!(snapshot.find_triples(Some("s2"), None, None)[0].triple.object, "some literal");
+
+        assert_eq!(parse_audit_triple("a b c d"), Some(Triple { subject: "a".to_string(), predicate: "b".to_string(), object: "c d".to_string() }));
+        assert_eq!(parse_audit_triple("broken"), None);
+    }
+
+    #[test]
+    fn test_snapshot_before_the_retained_audit_history_fails() {
+        let mut store = RdfStore::with_audit_limit(2);
+        let prov = Provenance::Sensor { source: "test".to_string(), confidence: None };
+        let t = |s: &str| Triple { subject: s.to_string(), predicate: "p".to_string(), object: "o".to_string() };
+        store.insert_at(t("s1"), GraphId::Default, prov.clone(), 1);
+        let first = store.audit_trail()[0].timestamp;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        store.insert(t("s2"), GraphId::Default, prov.clone());
+        store.insert(t("s3"), GraphId::Default, prov);
+
+        // 最初の挿入のエントリは上限で捨てられている
+        let oldest = store.audit_trail()[0].timestamp;
+        assert!(matches!(store.snapshot_at(first), Err(HistoryError::BeforeRetention { requested, oldest: Some(o) }) if requested == first && o == oldest));
+        let snapshot = store.snapshot_at(oldest).unwrap();
+        assert!(["s1", "s2"].iter().all(|s| snapshot.find_triples(Some(s), None, None).len() == 1));
+
+        // 履歴がすべて残っていれば、最初の変更より前の時点も再構成できる
+        let mut complete = RdfStore::new();
+        complete.insert(t("s1"), GraphId::Default, Provenance::Sensor { source: "test".to_string(), confidence: None });
+        assert_eq!(complete.snapshot_at(0).unwrap().find_triples(None, None, None).len(), 0);
+    }
+
+    #[test]
+    fn test_audit_entries_store_structured_triples() {
+        let triple = Triple { subject: "s".to_string(), predicate: "p".to_string(), object: "\"a literal\"".to_string() };
+        let operation = AuditOperation::Delete { triple: triple.clone(), graph_id: GraphId::Default };
+        let json = serde_json::to_value(&operation).unwrap();
+        assert_eq!(json["Delete"]["triple"]["object"], "\"a literal\"");
+        assert_eq!(serde_json::from_value::<AuditOperation>(json).unwrap(), operation);
+
+        // 以前の "subject predicate object" 形式のエントリも読める
+        let legacy = serde_json::json!({ "Delete": { "triple": "s p \"a literal\"", "graph_id": "Default" } });
+        assert_eq!(serde_json::from_value::<AuditOperation>(legacy).unwrap(), operation);
+    }
+
+    #[test]
+    fn test_ontology_terms_and_search() {
+        let mut store = RdfStore::new();
+        let t = |s: &str, p: &str, o: &str| Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() };
+        let rdf_type = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+        let rdfs = "http://www.w3.org/2000/01/rdf-schema#";
+        let owl = "http://www.w3.org/2002/07/owl#";
+        let sec = "https://w3id.org/security#";
+
+        let ontology = vec![
+            t(&format!("{}Host", sec), rdf_type, &format!("{}Class", owl)),
+            t(&format!("{}Host", sec), &format!("{}label", rdfs), "\"Host\"@en"),
+            t(&format!("{}HostName", sec), rdf_type, &format!("{}Class", owl)),
+            t(&format!("{}connectsTo", sec), rdf_type, &format!("{}ObjectProperty", owl)),
+            t(&format!("{}connectsTo", sec), &format!("{}domain", rdfs), &format!("{}Host", sec)),
+            t(&format!("{}connectsTo", sec), &format!("{}comment", rdfs), "Network connection"),
+        ];
+        store.insert_batch(ontology, GraphId::Named("ontology".to_string()), Provenance::Imported { source_uri: "test.ttl".to_string(), imported_at: 0 });
+
+        let data = vec![
+            t("urn:host:1", rdf_type, &format!("{}Host", sec)),
+            t("urn:host:2", rdf_type, &format!("{}Host", sec)),
+            t("urn:host:1", &format!("{}connectsTo", sec), "urn:host:2"),
+            t("urn:host:1", &format!("{}hostname", sec), "web-01"),
+        ];
+        store.insert_batch(data, GraphId::Default, Provenance::Sensor { source: "edr".to_string(), confidence: None });
+
+        let terms = store.ontology_terms();
+        let host = terms.iter().find(|term| term.local_name == "Host").unwrap();
+        assert_eq!(host.kind, TermKind::Class);
+        assert_eq!(host.label.as_deref(), Some("Host"));
+        assert_eq!(host.usage_count, 2);
+
+        let connects = terms.iter().find(|term| term.local_name == "connectsTo").unwrap();
+        assert_eq!(connects.kind, TermKind::ObjectProperty);
+        assert_eq!(connects.domains, vec![format!("{}Host", sec)]);
+        assert_eq!(connects.comment.as_deref(), Some("Network connection"));
+        assert_eq!(connects.usage_count, 1);
+
+        // 宣言のない述語も候補に含まれ、RDF/OWL の語彙は含まれない
+        assert!(terms.iter().any(|term| term.local_name == "hostname" && term.kind == TermKind::Property));
+        assert!(!terms.iter().any(|term| term.iri == rdf_type));
+
+        let results = search_terms(terms.clone(), Some("host"), None);
+        assert_eq!(results[0].local_name, "Host");
+        assert_eq!(results[1].local_name, "hostname");
+        let classes = search_terms(terms.clone(), Some("host"), Some(TermKind::Class));
+        assert_eq!(classes.iter().map(|term| term.local_name.as_str()).collect::<Vec<_>>(), vec!["Host", "HostName"]);
+
+        // 接頭辞付きの入力・部分的な IRI・ラベルのいずれからでも補完できる
+        let prefixes = fukurow_core::prefix::PrefixMap::default().with_prefix("sec", sec);
+        let completions = complete_terms(terms.clone(), "sec:ho", &prefixes, None);
+        assert_eq!(completions.iter().map(|c| c.curie.as_deref().unwrap()).collect::<Vec<_>>(), vec!["sec:Host", "sec:hostname", "sec:HostName"]);
+        assert_eq!((completions[0].usage_count, completions[0].label.as_deref()), (2, Some("Host")));
+        let by_iri = complete_terms(terms.clone(), &format!("<{}conn", sec), &prefixes, Some(TermKind::ObjectProperty));
+        assert_eq!(by_iri.len(), 1);
+        assert_eq!(by_iri[0].iri, format!("{}connectsTo", sec));
+        assert_eq!(complete_terms(terms.clone(), "hostn", &prefixes, None).len(), 2);
+        assert!(complete_terms(terms, "unknown:ho", &prefixes, None).is_empty());
+    }
+
+    #[test]
+    fn test_insert_document_scopes_blank_nodes() {
+        let mut store = RdfStore::new();
+        let document = vec![
+            Triple { subject: "_:b0".to_string(), predicate: "http://example.org/user".to_string(), object: "alice".to_string() },
+            Triple { subject: "http://example.org/alert".to_string(), predicate: "http://example.org/about".to_string(), object: "_:b0".to_string() },
+        ];
+        let provenance = Provenance::Sensor { source: "test-sensor".to_string(), confidence: None };
+
+        let first = store.insert_document(document.clone(), GraphId::Default, provenance.clone());
+        let second = store.insert_document(document, GraphId::Default, provenance);
+        assert_eq!(first[0].subject, first[1].object);
+        assert_ne!(first[0].subject, second[0].subject);
+
+        let users = store.find_terms(None, Some(&RdfTerm::iri("http://example.org/user")), Some(&RdfTerm::literal("alice")));
+        assert_eq!(users.len(), 2);
+        let node = first[0].subject_term();
+        assert_eq!(store.find_terms(Some(&node), None, None).len(), 1);
+    }
+
+    #[test]
+    fn test_find_terms_ignores_encoding_differences() {
+        let mut store = RdfStore::new();
+        let provenance = Provenance::Sensor { source: "test-sensor".to_string(), confidence: None };
+        store.insert(Triple {
+            subject: "http://example.org/alice".to_string(),
+            predicate: "http://example.org/name".to_string(),
+            object: "\"Alice\"".to_string(),
+        }, GraphId::Default, provenance);
+
+        let by_literal = store.find_terms(None, None, Some(&RdfTerm::literal("Alice")));
+        assert_eq!(by_literal.len(), 1);
+        let by_iri = store.find_terms(Some(&RdfTerm::iri("http://example.org/alice")), None, None);
+        assert_eq!(by_iri.len(), 1);
+        assert!(store.find_terms(None, None, Some(&RdfTerm::lang_literal("Alice", "en"))).is_empty());
+    }
+
+    fn cached_store() -> CachedBackend<RdfStore> {
+        let mut store = RdfStore::new();
+        let provenance = Provenance::Sensor { source: "test-sensor".to_string(), confidence: None };
+        store.insert(Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Default, provenance.clone());
+        store.insert(Triple { subject: "s2".to_string(), predicate: "p2".to_string(), object: "o2".to_string() }, GraphId::Named("other".to_string()), provenance);
+        CachedBackend::new(store).with_capacity(2)
+    }
+
+    fn stored(subject: &str, predicate: &str, object: &str) -> StoredTriple {
+        StoredTriple {
+            graph_id: GraphId::Default,
+            triple: Triple { subject: subject.to_string(), predicate: predicate.to_string(), object: object.to_string() }.into(),
+            asserted_at: 0,
+            provenance: Provenance::Sensor { source: "test-sensor".to_string(), confidence: None },
+        }
+    }
+
+    #[test]
+    fn test_cached_backend_hits_and_lru_eviction() {
+        let cache = cached_store();
+        assert_eq!(cache.find_triples(Some("s1"), None, None).unwrap().len(), 1);
+        assert_eq!(cache.find_triples(Some("s1"), None, None).unwrap().len(), 1);
+        cache.find_triples(None, Some("p2"), None).unwrap();
+        // s1 を最近使ったものにしてから 3 つ目を入れると p2 が追い出される
+        cache.find_triples(Some("s1"), None, None).unwrap();
+        cache.find_triples(None, None, Some("o1")).unwrap();
+
+        let metrics = cache.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.evictions, metrics.entries), (2, 3, 1, 2));
+        cache.find_triples(Some("s1"), None, None).unwrap();
+        cache.find_triples(None, Some("p2"), None).unwrap();
+        assert_eq!(cache.metrics().hits, 3);
+        assert!((cache.metrics().hit_ratio() - 3.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cached_backend_invalidates_matching_patterns_on_write() {
+        let mut cache = cached_store().with_capacity(10);
+        cache.find_triples(Some("s1"), None, None).unwrap();
+        cache.find_triples(None, Some("p2"), None).unwrap();
+
+        cache.insert(stored("s1", "p9", "o9")).unwrap();
+        assert_eq!(cache.metrics().invalidations, 1);
+        assert_eq!(cache.find_triples(Some("s1"), None, None).unwrap().len(), 2);
+        // 無関係なパターンはキャッシュに残る
+        let hits = cache.metrics().hits;
+        cache.find_triples(None, Some("p2"), None).unwrap();
+        assert_eq!(cache.metrics().hits, hits + 1);
+
+        let triple = Triple { subject: "s1".to_string(), predicate: "p9".to_string(), object: "o9".to_string() };
+        assert_eq!(cache.remove_triple(&triple, &GraphId::Default).unwrap(), 1);
+        assert_eq!(cache.find_triples(Some("s1"), None, None).unwrap().len(), 1);
+
+        cache.clear_graph(&GraphId::Named("other".to_string())).unwrap();
+        assert!(cache.find_triples(None, Some("p2"), None).unwrap().is_empty());
+
+        cache.invalidate_all();
+        assert_eq!(cache.metrics().entries, 0);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_cached_backend_over_sqlite() {
+        let mut cache = CachedBackend::new(SqliteBackend::in_memory().unwrap());
+        cache.insert(stored("s1", "p1", "o1")).unwrap();
+        assert_eq!(cache.find_triples(Some("s1"), None, None).unwrap().len(), 1);
+        assert_eq!(cache.find_triples(Some("s1"), None, None).unwrap().len(), 1);
+        assert_eq!((cache.metrics().hits, cache.metrics().misses), (1, 1));
+
+        // 書き込みで無効化され、次の検索は DB から読み直す
+        cache.insert(stored("s1", "p2", "o2")).unwrap();
+        assert_eq!(cache.find_triples(Some("s1"), None, None).unwrap().len(), 2);
+        assert_eq!(cache.metrics().misses, 2);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_backend_round_trips_store() {
+        let mut store = RdfStore::new();
+        let provenance = Provenance::Sensor { source: "test-sensor".to_string(), confidence: Some(0.5) };
+        store.insert_at(Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Named("events".to_string()), provenance, 42);
+
+        store.insert(Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1b".to_string() }, GraphId::Named("events".to_string()), Provenance::Sensor { source: "test-sensor".to_string(), confidence: None });
+
+        let mut backend = SqliteBackend::in_memory().unwrap();
+        backend.save_store(&store).unwrap();
+        // s1 と p1 は辞書に一度だけ入る
+        assert_eq!(backend.term_count().unwrap(), 4);
+        let loaded = backend.load_store().unwrap();
+        let found = loaded.find_triples(Some("s1"), None, Some("o1"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].graph_id, GraphId::Named("events".to_string()));
+        assert_eq!(found[0].asserted_at, 42);
+
+        backend.insert(stored("s2", "p1", "o2")).unwrap();
+        assert_eq!(TripleBackend::find_triples(&backend, None, Some("p1"), None).unwrap().len(), 3);
+        backend.clear_graph(&GraphId::Named("events".to_string())).unwrap();
+        assert_eq!(TripleBackend::find_triples(&backend, None, None, None).unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_backend_round_trips_store() {
+        let mut store = RdfStore::new();
+        let provenance = Provenance::Sensor { source: "test-sensor".to_string(), confidence: Some(0.5) };
+        store.insert_at(Triple { subject: "s1".to_string(), predicate: "p1".to_string(), object: "o1".to_string() }, GraphId::Named("events".to_string()), provenance.clone(), 42);
+        store.insert_at(Triple { subject: "s1".to_string(), predicate: "p2".to_string(), object: "o2".to_string() }, GraphId::Sensor("edr".to_string()), provenance, 43);
+
+        let mut backend = SledBackend::temporary().unwrap();
+        backend.save_store(&store).unwrap();
+        assert_eq!(backend.graph_ids().unwrap().len(), 2);
+        let loaded = backend.load_store().unwrap();
+        let found = loaded.find_triples(Some("s1"), None, Some("o1"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].graph_id, GraphId::Named("events".to_string()));
+        assert_eq!(found[0].asserted_at, 42);
+
+        // 保存し直すと古い世代は置き換わる
+        backend.save_store(&store).unwrap();
+        assert_eq!(backend.iter().unwrap().count(), 2);
+
+        backend.insert(stored("s2", "p1", "o2")).unwrap();
+        backend.insert(stored("s2", "p1", "o2")).unwrap();
+        assert_eq!(TripleBackend::find_triples(&backend, None, Some("p1"), None).unwrap().len(), 3);
+        assert_eq!(TripleBackend::find_triples(&backend, Some("s1"), Some("p2"), None).unwrap()[0].graph_id, GraphId::Sensor("edr".to_string()));
+        assert_eq!(backend.remove_triple(&Triple { subject: "s2".to_string(), predicate: "p1".to_string(), object: "o2".to_string() }, &GraphId::Default).unwrap(), 2);
+
+        backend.clear_graph(&GraphId::Named("events".to_string())).unwrap();
+        assert_eq!(TripleBackend::find_triples(&backend, None, None, None).unwrap().len(), 1);
+        assert_eq!(backend.graph_ids().unwrap(), vec![GraphId::Sensor("edr".to_string())]);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_backend_migrates_legacy_text_rows() {
+        let path = std::env::temp_dir().join(format!("fukurow-sqlite-legacy-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE triples (graph_json TEXT NOT NULL, s TEXT NOT NULL, p TEXT NOT NULL, o TEXT NOT NULL,
+                                      asserted_at INTEGER NOT NULL, provenance_json TEXT NOT NULL);
+                CREATE INDEX idx_triples_sp ON triples(s, p);
+            "#,
+            ).unwrap();
+            let provenance = serde_json::to_string(&Provenance::Sensor { source: "legacy".to_string(), confidence: None }).unwrap();
+            for (s, o) in [("s1", "o1"), ("s1", "o2")] {
+                conn.execute(
+                    "INSERT INTO triples VALUES (?1, ?2, 'p1', ?3, 7, ?4)",
+                    rusqlite::params![serde_json::to_string(&GraphId::Default).unwrap(), s, o, provenance],
+                ).unwrap();
+            }
+        }
+
+        let backend = SqliteBackend::open(&path).unwrap();
+        assert_eq!(backend.term_count().unwrap(), 4);
+        let found = TripleBackend::find_triples(&backend, Some("s1"), Some("p1"), None).unwrap();
+        assert_eq!(found.iter().map(|stored| stored.triple.object.as_str()).collect::<Vec<_>>(), vec!["o1", "o2"]);
+        assert_eq!(found[0].asserted_at, 7);
+        drop(backend);
+        // 移行済みのファイルは開き直しても変わらない
+        assert_eq!(SqliteBackend::open(&path).unwrap().load_store().unwrap().find_triples(None, Some("p1"), None).len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bootstrap_loads_standard_bundles_once() {
+        let mut store = RdfStore::new();
+        let bootstrapper = Bootstrapper::new(BootstrapConfig::default());
+
+        let report = bootstrapper.run(&mut store).unwrap();
+        assert_eq!(report.bundles.len(), STANDARD_BUNDLES.len());
+        for bundle in STANDARD_BUNDLES.iter() {
+            match &report.bundles[bundle.name] {
+                BundleOutcome::Installed { triples, .. } => assert_eq!(store.get_graph(&bundle.graph_id()).len(), *triples),
+                other => panic!("{} was not installed: {:?}", bundle.name, other),
+            }
+        }
+        assert_eq!(Bootstrapper::installed_version(&store, &ATTACK_TAXONOMY).as_deref(), Some("14.1"));
+        assert_eq!(store.find_triples(Some("https://attack.mitre.org/techniques/T1110"), Some("http://example.org/attack/tactic"), None).len(), 1);
+        assert_eq!(store.find_triples(Some("http://example.org/shapes/CyberEventShape-timestamp"), Some("http://www.w3.org/ns/shacl#minCount"), Some("1")).len(), 1);
+
+        // 二回目は何も読み直さない
+        let total = store.all_triples().values().map(|g| g.len()).sum::<usize>();
+        let report = bootstrapper.run(&mut store).unwrap();
+        assert!(!report.changed());
+        assert_eq!(store.all_triples().values().map(|g| g.len()).sum::<usize>(), total);
+    }
+
+    #[test]
+    fn test_bootstrap_skip_pin_and_update() {
+        const SITE: OntologyBundle = OntologyBundle {
+            name: "site",
+            graph: "urn:test:site",
+            releases: &[
+                BundleRelease { version: "1", ntriples: "<http://x/a> <http://x/p> \"old\" .\n" },
+                BundleRelease { version: "2", ntriples: "# v2\n<http://x/a> <http://x/p> \"new\"@en .\n<http://x/a> <http://x/q> _:b0 .\n" },
+            ],
+        };
+        let mut store = RdfStore::new();
+
+        let config = BootstrapConfig::new().with_skip("attack").with_skip("cyber").with_skip("shapes").with_skip("rdfs-owl");
+        let pinned = Bootstrapper::new(config.clone().with_pin("site", "1")).with_bundle(SITE);
+        let report = pinned.run(&mut store).unwrap();
+        assert_eq!(report.bundles["attack"], BundleOutcome::Skipped);
+        assert_eq!(report.bundles["site"], BundleOutcome::Installed { version: "1".to_string(), triples: 1 });
+        assert!(store.get_graph(&ATTACK_TAXONOMY.graph_id()).is_empty());
+
+        let report = Bootstrapper::new(config.clone()).with_bundle(SITE).run(&mut store).unwrap();
+        assert_eq!(report.bundles["site"], BundleOutcome::Updated { from: "1".to_string(), to: "2".to_string(), triples: 2 });
+        assert!(store.find_triples(None, None, Some("old")).is_empty());
+        assert_eq!(Bootstrapper::installed_version(&store, &SITE).as_deref(), Some("2"));
+        let meta = GraphId::Named(BOOTSTRAP_META_GRAPH.to_string());
+        assert_eq!(store.get_graph(&meta).len(), 1);
+
+        let unknown = Bootstrapper::new(config.clone().with_pin("site", "3")).with_bundle(SITE).run(&mut store);
+        assert_eq!(unknown, Err(BootstrapError::UnknownVersion { bundle: "site".to_string(), version: "3".to_string() }));
+        assert!(matches!(Bootstrapper::new(config.with_skip("nope")).run(&mut store), Err(BootstrapError::UnknownBundle(_))));
+
+        let report = Bootstrapper::new(BootstrapConfig::disabled()).run(&mut RdfStore::new()).unwrap();
+        assert!(report.bundles.values().all(|outcome| *outcome == BundleOutcome::Skipped));
+    }
+
+    #[test]
+    fn test_audit_entries_record_current_actor() {
+        let mut store = RdfStore::new();
+        let triple = Triple { subject: "s".to_string(), predicate: "p".to_string(), object: "o".to_string() };
+        store.set_actor(Some("alice".to_string()));
+        store.insert(triple.clone(), GraphId::Default, Provenance::Sensor { source: "api".to_string(), confidence: None });
+        store.set_actor(None);
+        store.remove_triple(&triple, &GraphId::Default);
+
+        let actors: Vec<Option<String>> = store.get_audit_trail().iter().map(|entry| entry.actor.clone()).collect();
+        assert_eq!(actors, vec![Some("alice".to_string()), None]);
+    }
 }
@@ -0,0 +1,438 @@
+//! Snapshot export and import
+//!
+//! [`StoreSnapshot`] をファイルへ書き出し、読み戻す。
+//! - N-Quads: 他の RDF ツールでも読める可搬形式。来歴は失われ、読み込み時は `Provenance::Imported` になる
+//! - JSON: グラフ・来歴・アサート時刻をすべて保持するネイティブ形式
+//!
+//! ファイルは一時ファイルへ書いてから rename するため、書き込み途中のダンプが読まれることはない。
+//! どちらの形式もスナップショットに含まれる WAL の連番を保持し、[`PersistenceManager::recover`] は
+//! それより新しい WAL レコードだけを再生する
+
+use crate::provenance::{GraphId, Provenance};
+use crate::snapshot::StoreSnapshot;
+use crate::store::{RdfStore, StoredTriple};
+use crate::tenant::TenantId;
+use crate::wal::WriteAheadLog;
+use fukurow_core::model::Triple;
+use fukurow_core::term::RdfTerm;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Graph IRI prefix for `GraphId::Sensor` in N-Quads dumps
+pub const SENSOR_GRAPH_PREFIX: &str = "urn:fukurow:sensor:";
+/// Graph IRI prefix for `GraphId::Inferred` in N-Quads dumps
+pub const INFERRED_GRAPH_PREFIX: &str = "urn:fukurow:inferred:";
+
+/// Version written into JSON dumps
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+/// Header comment carrying the WAL sequence in N-Quads dumps
+const NQUADS_WAL_SEQUENCE_HEADER: &str = "# fukurow-wal-sequence:";
+
+/// Snapshot file format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotFormat {
+    #[default]
+    NQuads,
+    Json,
+}
+
+impl SnapshotFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            SnapshotFormat::NQuads => "nq",
+            SnapshotFormat::Json => "json",
+        }
+    }
+
+    /// Guess the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "nq" | "nquads" => Some(SnapshotFormat::NQuads),
+            "json" => Some(SnapshotFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for SnapshotFormat {
+    type Err = PersistenceError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "nq" | "nquads" | "n-quads" => Ok(SnapshotFormat::NQuads),
+            "json" => Ok(SnapshotFormat::Json),
+            other => Err(PersistenceError::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+/// Persistence errors
+#[derive(Debug, thiserror::Error)]
+pub enum PersistenceError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Unknown snapshot format: {0}")]
+    UnknownFormat(String),
+
+    #[error("Unsupported snapshot version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+}
+
+/// Metadata of an exported snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub path: PathBuf,
+    pub format: SnapshotFormat,
+    pub triples: usize,
+    /// When the snapshot was taken (Unix timestamp in milliseconds)
+    pub taken_at: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    version: u32,
+    taken_at: u64,
+    #[serde(default)]
+    wal_sequence: u64,
+    triples: Vec<StoredTriple>,
+}
+
+/// Writes snapshots to and reads them from a directory
+#[derive(Debug, Clone)]
+pub struct PersistenceManager {
+    dir: PathBuf,
+    format: SnapshotFormat,
+}
+
+impl PersistenceManager {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), format: SnapshotFormat::default() }
+    }
+
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Manager writing to the tenant's subdirectory (`<dir>/<tenant>`) in the same format
+    pub fn for_tenant(&self, tenant: &TenantId) -> Self {
+        Self { dir: self.dir.join(tenant.as_str()), format: self.format }
+    }
+
+    pub fn format(&self) -> SnapshotFormat {
+        self.format
+    }
+
+    /// Export in the configured format as `snapshot-<taken_at>.<ext>`
+    pub fn export_snapshot(&self, snapshot: &StoreSnapshot) -> Result<SnapshotInfo, PersistenceError> {
+        self.export_snapshot_as(snapshot, self.format)
+    }
+
+    pub fn export_snapshot_as(&self, snapshot: &StoreSnapshot, format: SnapshotFormat) -> Result<SnapshotInfo, PersistenceError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("snapshot-{}.{}", snapshot.taken_at(), format.extension()));
+        let tmp = path.with_extension(format!("{}.tmp", format.extension()));
+
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&tmp)?);
+        write_snapshot(snapshot, format, &mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&tmp, &path)?;
+
+        Ok(SnapshotInfo { path, format, triples: snapshot.len(), taken_at: snapshot.taken_at() })
+    }
+
+    /// Import a dump; the format is taken from the extension, falling back to the configured one
+    pub fn import_snapshot(&self, path: impl AsRef<Path>) -> Result<StoreSnapshot, PersistenceError> {
+        let path = path.as_ref();
+        let format = SnapshotFormat::from_path(path).unwrap_or(self.format);
+        let file = std::fs::File::open(path)?;
+        read_snapshot(BufReader::new(file), format, &path.display().to_string())
+    }
+
+    /// Most recent snapshot file in the directory, if any
+    pub fn latest_snapshot(&self) -> Result<Option<PathBuf>, PersistenceError> {
+        if !self.dir.exists() {
+            return Ok(None);
+        }
+        let mut latest: Option<(u64, PathBuf)> = None;
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if SnapshotFormat::from_path(&path).is_none() {
+                continue;
+            }
+            let taken_at = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix("snapshot-"))
+                .and_then(|ts| ts.parse::<u64>().ok());
+            if let Some(taken_at) = taken_at {
+                if latest.as_ref().is_none_or(|(best, _)| taken_at > *best) {
+                    latest = Some((taken_at, path));
+                }
+            }
+        }
+        Ok(latest.map(|(_, path)| path))
+    }
+
+    /// Save a snapshot of the store, then compact its write-ahead log
+    ///
+    /// コンパクションはスナップショットの rename が完了した後に行うため、
+    /// その間にクラッシュしてもレコードは失われない (再生時に連番で重複を除く)
+    pub fn checkpoint(&self, store: &mut RdfStore) -> Result<SnapshotInfo, PersistenceError> {
+        let snapshot = store.snapshot();
+        let info = self.export_snapshot(&snapshot)?;
+        if let Some(wal) = store.wal_mut() {
+            wal.compact_through(snapshot.wal_sequence())?;
+        }
+        Ok(info)
+    }
+
+    /// Rebuild a store from the latest snapshot plus the newer WAL records, with the WAL attached
+    pub fn recover(&self, wal_path: impl AsRef<Path>) -> Result<RdfStore, PersistenceError> {
+        let snapshot = match self.latest_snapshot()? {
+            Some(path) => self.import_snapshot(path)?,
+            None => StoreSnapshot::default(),
+        };
+        let mut store = snapshot.to_store();
+        let wal = WriteAheadLog::open(wal_path.as_ref())?;
+        wal.replay(&mut store, snapshot.wal_sequence())?;
+        store.attach_wal(wal);
+        Ok(store)
+    }
+}
+
+/// Serialize a snapshot to any writer
+pub fn write_snapshot(snapshot: &StoreSnapshot, format: SnapshotFormat, writer: &mut impl Write) -> Result<(), PersistenceError> {
+    match format {
+        SnapshotFormat::Json => {
+            let file = SnapshotFile {
+                version: SNAPSHOT_FORMAT_VERSION,
+                taken_at: snapshot.taken_at(),
+                wal_sequence: snapshot.wal_sequence(),
+                triples: snapshot.iter().cloned().collect(),
+            };
+            serde_json::to_writer(writer, &file)?;
+        }
+        SnapshotFormat::NQuads => {
+            if snapshot.wal_sequence() > 0 {
+                writeln!(writer, "{} {}", NQUADS_WAL_SEQUENCE_HEADER, snapshot.wal_sequence())?;
+            }
+            for stored in snapshot.iter() {
+                writeln!(writer, "{}", to_nquad(stored))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deserialize a snapshot; `source` is recorded as the import source of N-Quads triples
+pub fn read_snapshot(reader: impl Read, format: SnapshotFormat, source: &str) -> Result<StoreSnapshot, PersistenceError> {
+    match format {
+        SnapshotFormat::Json => {
+            let file: SnapshotFile = serde_json::from_reader(reader)?;
+            if file.version != SNAPSHOT_FORMAT_VERSION {
+                return Err(PersistenceError::UnsupportedVersion(file.version));
+            }
+            Ok(StoreSnapshot::from_triples(file.triples, file.taken_at).with_wal_sequence(file.wal_sequence))
+        }
+        SnapshotFormat::NQuads => {
+            let imported_at = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let provenance = Provenance::Imported { source_uri: source.to_string(), imported_at };
+
+            let mut triples = Vec::new();
+            let mut wal_sequence = 0;
+            for (index, line) in BufReader::new(reader).lines().enumerate() {
+                let line = line?;
+                let line = line.trim();
+                if let Some(sequence) = line.strip_prefix(NQUADS_WAL_SEQUENCE_HEADER) {
+                    wal_sequence = sequence.trim().parse().map_err(|_| PersistenceError::Parse {
+                        line: index + 1,
+                        message: "invalid WAL sequence".to_string(),
+                    })?;
+                    continue;
+                }
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (triple, graph_id) = parse_nquad(line).map_err(|message| PersistenceError::Parse {
+                    line: index + 1,
+                    message: message.to_string(),
+                })?;
+                triples.push(StoredTriple { graph_id, triple: triple.into(), asserted_at: imported_at, provenance: provenance.clone() });
+            }
+            Ok(StoreSnapshot::from_triples(triples, imported_at).with_wal_sequence(wal_sequence))
+        }
+    }
+}
+
+/// IRI naming a graph in N-Quads (`None` for the default graph)
+pub fn graph_iri(graph_id: &GraphId) -> Option<String> {
+    match graph_id {
+        GraphId::Default => None,
+        GraphId::Named(name) => Some(name.clone()),
+        GraphId::Sensor(sensor) => Some(format!("{}{}", SENSOR_GRAPH_PREFIX, sensor)),
+        GraphId::Inferred(rule) => Some(format!("{}{}", INFERRED_GRAPH_PREFIX, rule)),
+    }
+}
+
+/// Inverse of [`graph_iri`]
+pub fn graph_id_from_iri(iri: &str) -> GraphId {
+    if let Some(sensor) = iri.strip_prefix(SENSOR_GRAPH_PREFIX) {
+        GraphId::Sensor(sensor.to_string())
+    } else if let Some(rule) = iri.strip_prefix(INFERRED_GRAPH_PREFIX) {
+        GraphId::Inferred(rule.to_string())
+    } else {
+        GraphId::Named(iri.to_string())
+    }
+}
+
+pub(crate) fn to_nquad(stored: &StoredTriple) -> String {
+    let triple = &stored.triple;
+    let mut quad = format!(
+        "{} {} {}",
+        RdfTerm::parse_node(&triple.subject),
+        RdfTerm::parse_node(&triple.predicate),
+        RdfTerm::parse(&triple.object),
+    );
+    if let Some(graph) = graph_iri(&stored.graph_id) {
+        quad.push(' ');
+        quad.push_str(&RdfTerm::iri(graph).to_string());
+    }
+    quad.push_str(" .");
+    quad
+}
+
+pub(crate) fn parse_nquad(line: &str) -> Result<(Triple, GraphId), &'static str> {
+    let statement = line.strip_suffix('.').ok_or("missing terminating '.'")?.trim_end();
+    let (subject, rest) = statement.split_once(char::is_whitespace).ok_or("expected subject")?;
+    let (predicate, rest) = rest.trim_start().split_once(char::is_whitespace).ok_or("expected predicate")?;
+    let rest = rest.trim();
+
+    if !is_iri_token(subject) && !subject.starts_with("_:") {
+        return Err("subject must be an IRI or blank node");
+    }
+    if !is_iri_token(predicate) {
+        return Err("predicate must be an IRI");
+    }
+
+    // 最後の空白区切りの `<...>` はその前が完結した項のときだけグラフラベルとみなす
+    let (object, graph_id) = match rest.rsplit_once(char::is_whitespace) {
+        Some((object, graph)) if is_iri_token(graph) && is_complete_term(object.trim_end()) => {
+            (object.trim_end(), graph_id_from_iri(&graph[1..graph.len() - 1]))
+        }
+        _ => (rest, GraphId::Default),
+    };
+    if !is_complete_term(object) {
+        return Err("malformed object");
+    }
+
+    let triple = Triple::from_terms(&RdfTerm::parse_node(subject), &RdfTerm::parse_node(predicate), &RdfTerm::parse(object));
+    Ok((triple, graph_id))
+}
+
+fn is_iri_token(token: &str) -> bool {
+    token.len() > 2 && token.starts_with('<') && token.ends_with('>') && !token[1..token.len() - 1].contains(char::is_whitespace)
+}
+
+fn is_complete_term(token: &str) -> bool {
+    if token.starts_with('"') {
+        // 閉じていない引用符は文字列全体がそのままリテラルになる
+        return RdfTerm::parse(token) != RdfTerm::literal(token);
+    }
+    is_iri_token(token) || (token.starts_with("_:") && token.len() > 2 && !token.contains(char::is_whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::RdfStore;
+
+    fn sample_store() -> RdfStore {
+        let mut store = RdfStore::new();
+        let sensor = Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.8) };
+        store.insert_at(
+            Triple::from_terms(&RdfTerm::iri("http://example.org/h1"), &RdfTerm::iri("http://example.org/label"), &RdfTerm::lang_literal("web <frontend> \"a\"", "en")),
+            GraphId::Named("urn:fukurow:graph:events".to_string()),
+            sensor.clone(),
+            10,
+        );
+        store.insert_at(
+            Triple::from_terms(&RdfTerm::blank_node("b0"), &RdfTerm::iri("http://example.org/port"), &RdfTerm::typed_literal("443", "http://www.w3.org/2001/XMLSchema#integer")),
+            GraphId::Sensor("edr".to_string()),
+            sensor.clone(),
+            20,
+        );
+        store.insert_at(
+            Triple::from_terms(&RdfTerm::iri("http://example.org/h1"), &RdfTerm::iri("http://example.org/connectsTo"), &RdfTerm::iri("http://example.org/h2")),
+            GraphId::Default,
+            sensor,
+            30,
+        );
+        store
+    }
+
+    fn sorted(snapshot: &StoreSnapshot) -> Vec<(GraphId, Triple)> {
+        let mut quads: Vec<(GraphId, Triple)> = snapshot.iter().map(|s| (s.graph_id.clone(), s.triple.to_triple())).collect();
+        quads.sort_by_key(|(graph, triple)| (graph.to_string(), triple.subject.clone(), triple.predicate.clone()));
+        quads
+    }
+
+    #[test]
+    fn test_nquads_round_trip() {
+        let snapshot = sample_store().snapshot();
+        let mut buffer = Vec::new();
+        write_snapshot(&snapshot, SnapshotFormat::NQuads, &mut buffer).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.contains("<http://example.org/h1> <http://example.org/connectsTo> <http://example.org/h2> ."));
+        assert!(text.contains("<urn:fukurow:sensor:edr> ."));
+
+        let restored = read_snapshot(buffer.as_slice(), SnapshotFormat::NQuads, "test.nq").unwrap();
+        assert_eq!(sorted(&restored), sorted(&snapshot));
+        assert!(restored.iter().all(|s| matches!(&s.provenance, Provenance::Imported { source_uri, .. } if source_uri == "test.nq")));
+    }
+
+    #[test]
+    fn test_json_export_import_keeps_provenance() {
+        let dir = std::env::temp_dir().join(format!("fukurow-snapshot-{}", std::process::id()));
+        let manager = PersistenceManager::new(&dir).with_format(SnapshotFormat::Json);
+        let snapshot = sample_store().snapshot();
+
+        let info = manager.export_snapshot(&snapshot).unwrap();
+        assert_eq!(info.triples, 3);
+        assert_eq!(manager.latest_snapshot().unwrap(), Some(info.path.clone()));
+
+        let restored = manager.import_snapshot(&info.path).unwrap();
+        assert_eq!(restored.taken_at(), snapshot.taken_at());
+        assert_eq!(sorted(&restored), sorted(&snapshot));
+        let port = restored.find_triples(None, Some("http://example.org/port"), None);
+        assert_eq!(port[0].asserted_at, 20);
+        assert_eq!(port[0].provenance, Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.8) });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_malformed_nquads_report_line() {
+        let input = "<http://a> <http://b> <http://c> .\n<http://a> <http://b> \"unterminated .\n";
+        match read_snapshot(input.as_bytes(), SnapshotFormat::NQuads, "bad.nq") {
+            Err(PersistenceError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected a parse error, got {:?}", other.map(|s| s.len())),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! Provenance and audit trail management
 
+use fukurow_core::model::Triple;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -21,6 +22,9 @@ pub enum Provenance {
         reasoning_level: String,
         /// Supporting evidence (other triples that led to this inference)
         evidence: Vec<String>,
+        /// Confidence derived from the evidence (see [`crate::confidence`])
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        confidence: Option<f64>,
     },
     /// Data loaded from external sources
     Imported {
@@ -31,6 +35,21 @@ pub enum Provenance {
     },
 }
 
+impl Provenance {
+    /// Confidence of the triple: imported data counts as certain, unscored readings and inferences have none
+    pub fn confidence(&self) -> Option<f64> {
+        match self {
+            Provenance::Sensor { confidence, .. } | Provenance::Inferred { confidence, .. } => *confidence,
+            Provenance::Imported { .. } => Some(1.0),
+        }
+    }
+
+    /// Whether the confidence is at least `min` (unscored triples never are)
+    pub fn meets_confidence(&self, min: f64) -> bool {
+        self.confidence().is_some_and(|confidence| confidence >= min)
+    }
+}
+
 /// Graph identifier for organizing triples
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum GraphId {
@@ -65,13 +84,15 @@ pub struct AuditEntry {
 pub enum AuditOperation {
     /// Triple inserted
     Insert {
-        triple: String,
+        #[serde(deserialize_with = "crate::history::deserialize_audit_triple")]
+        triple: Triple,
         graph_id: GraphId,
         provenance: Provenance,
     },
     /// Triple deleted
     Delete {
-        triple: String,
+        #[serde(deserialize_with = "crate::history::deserialize_audit_triple")]
+        triple: Triple,
         graph_id: GraphId,
     },
     /// Graph cleared
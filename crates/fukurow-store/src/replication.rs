@@ -0,0 +1,423 @@
+//! # Replication (hot standby)
+//!
+//! アクティブ/パッシブ構成のため、リーダーのストアへの変更 (挿入・削除・クリア、来歴つき) を
+//! WAL と同じ [`WalOperation`] でフォロワーへ送り、フォロワーは同じ順序で適用する。
+//! - フォロワーの位置が不明、またはバックログから外れた場合は、先にスナップショットを送る
+//! - フォロワーは適用済みの連番とリーダーの連番から遅延 (件数・ミリ秒) を報告する
+//! - [`ReplicationFollower::promote`] でフォロワーをアクティブに切り替える。エポックを上げるため、
+//!   復帰した旧リーダーからのメッセージは拒否される
+//!
+//! 転送は [`ReplicationMessage`] (JSON) を運べれば何でもよい (gRPC の `Replicate` など)
+
+use crate::snapshot::StoreSnapshot;
+use crate::store::{RdfStore, StoredTriple};
+use crate::wal::{WalOperation, WalRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Mutations kept for followers by default
+pub const DEFAULT_REPLICATION_BACKLOG: usize = 10_000;
+
+/// Role of an engine instance in an active/passive pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplicationRole {
+    Leader,
+    Follower,
+}
+
+/// Position of a follower in the leader's change feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationCursor {
+    /// Leadership term the sequence numbers belong to
+    pub epoch: u64,
+    /// Last applied mutation
+    pub sequence: u64,
+}
+
+/// Message from the leader to a follower
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplicationMessage {
+    /// Every triple as of `sequence`; replaces the follower's contents
+    Snapshot { epoch: u64, sequence: u64, triples: Vec<StoredTriple> },
+    /// Mutations in sequence order
+    Records { epoch: u64, records: Vec<WalRecord> },
+    /// Sent while there is nothing to replicate (Unix milliseconds of the leader)
+    Heartbeat { epoch: u64, sequence: u64, timestamp: u64 },
+}
+
+impl ReplicationMessage {
+    pub fn epoch(&self) -> u64 {
+        match self {
+            ReplicationMessage::Snapshot { epoch, .. }
+            | ReplicationMessage::Records { epoch, .. }
+            | ReplicationMessage::Heartbeat { epoch, .. } => *epoch,
+        }
+    }
+
+    /// Cursor of a follower that applied this message (`None` for heartbeats and empty batches)
+    pub fn cursor(&self) -> Option<ReplicationCursor> {
+        match self {
+            ReplicationMessage::Snapshot { epoch, sequence, .. } => Some(ReplicationCursor { epoch: *epoch, sequence: *sequence }),
+            ReplicationMessage::Records { epoch, records } => {
+                records.last().map(|record| ReplicationCursor { epoch: *epoch, sequence: record.sequence })
+            }
+            ReplicationMessage::Heartbeat { .. } => None,
+        }
+    }
+}
+
+/// Replication errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReplicationError {
+    #[error("Missing mutations: expected sequence {expected}, received {received}")]
+    SequenceGap { expected: u64, received: u64 },
+
+    #[error("Message from epoch {received} rejected (current epoch {current})")]
+    StaleEpoch { current: u64, received: u64 },
+
+    #[error("Records received before a snapshot")]
+    SnapshotRequired,
+
+    #[error("Instance was promoted and no longer follows a leader")]
+    NotFollower,
+
+    #[error("Replication transport failed: {0}")]
+    Transport(String),
+}
+
+/// Change feed of a leader store: its recent mutations, numbered in order
+///
+/// [`RdfStore::attach_replication`] で付けると、以降の変更がすべて記録される
+#[derive(Debug, Clone)]
+pub struct ReplicationLog {
+    epoch: u64,
+    last_sequence: u64,
+    backlog: VecDeque<WalRecord>,
+    capacity: usize,
+}
+
+impl Default for ReplicationLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLICATION_BACKLOG)
+    }
+}
+
+impl ReplicationLog {
+    /// Log keeping the last `capacity` mutations
+    pub fn new(capacity: usize) -> Self {
+        Self { epoch: 1, last_sequence: 0, backlog: VecDeque::new(), capacity: capacity.max(1) }
+    }
+
+    pub fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Continue numbering after `sequence` (used when a follower is promoted)
+    pub fn starting_at(mut self, sequence: u64) -> Self {
+        self.last_sequence = sequence;
+        self
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Number of mutations a follower can still catch up on without a snapshot
+    pub fn backlog_len(&self) -> usize {
+        self.backlog.len()
+    }
+
+    pub(crate) fn record(&mut self, operation: WalOperation) {
+        self.last_sequence += 1;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.backlog.push_back(WalRecord { sequence: self.last_sequence, timestamp, operation });
+        while self.backlog.len() > self.capacity {
+            self.backlog.pop_front();
+        }
+    }
+
+    /// Next message for a follower at `cursor` (`None` for a follower without data)
+    ///
+    /// 別のエポックの位置や、バックログより古い位置からはスナップショットで追いつかせる
+    pub fn catch_up(&self, store: &RdfStore, cursor: Option<ReplicationCursor>) -> ReplicationMessage {
+        let oldest = self.backlog.front().map_or(self.last_sequence + 1, |record| record.sequence);
+        match cursor {
+            Some(cursor) if cursor.epoch == self.epoch && cursor.sequence == self.last_sequence => self.heartbeat(),
+            Some(cursor) if cursor.epoch == self.epoch && cursor.sequence + 1 >= oldest && cursor.sequence < self.last_sequence => {
+                ReplicationMessage::Records {
+                    epoch: self.epoch,
+                    records: self.backlog.iter().filter(|record| record.sequence > cursor.sequence).cloned().collect(),
+                }
+            }
+            _ => ReplicationMessage::Snapshot {
+                epoch: self.epoch,
+                sequence: self.last_sequence,
+                triples: store.all_triples().values().flatten().cloned().collect(),
+            },
+        }
+    }
+
+    pub fn heartbeat(&self) -> ReplicationMessage {
+        ReplicationMessage::Heartbeat {
+            epoch: self.epoch,
+            sequence: self.last_sequence,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        }
+    }
+}
+
+/// Replication state of an instance, as reported to operators and metrics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationStatus {
+    pub role: ReplicationRole,
+    pub epoch: u64,
+    /// Last applied mutation (`None` before the first snapshot)
+    pub applied_sequence: Option<u64>,
+    /// Last mutation the leader reported
+    pub leader_sequence: u64,
+    /// Mutations the leader has that are not applied yet
+    pub lag_records: u64,
+    /// Leader time between the last applied mutation and the newest the leader reported
+    pub lag_ms: u64,
+    pub snapshots_received: u64,
+    pub records_applied: u64,
+}
+
+/// Passive side of an active/passive pair: applies the leader's messages to a store
+///
+/// フォロワーのストアにはリーダー以外から書き込まないこと
+#[derive(Debug, Clone)]
+pub struct ReplicationFollower {
+    role: ReplicationRole,
+    epoch: u64,
+    cursor: Option<ReplicationCursor>,
+    leader_sequence: u64,
+    /// Leader clock of the last applied mutation and of the newest leader message
+    applied_at: u64,
+    leader_time: u64,
+    snapshots_received: u64,
+    records_applied: u64,
+}
+
+impl Default for ReplicationFollower {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReplicationFollower {
+    pub fn new() -> Self {
+        Self {
+            role: ReplicationRole::Follower,
+            epoch: 0,
+            cursor: None,
+            leader_sequence: 0,
+            applied_at: 0,
+            leader_time: 0,
+            snapshots_received: 0,
+            records_applied: 0,
+        }
+    }
+
+    pub fn role(&self) -> ReplicationRole {
+        self.role
+    }
+
+    /// Position to resume from (`None` until a snapshot is applied or after a gap)
+    pub fn cursor(&self) -> Option<ReplicationCursor> {
+        self.cursor
+    }
+
+    /// Apply one leader message to `store`
+    ///
+    /// 欠番を検出したら位置を捨て、次の接続でスナップショットから取り直す
+    pub fn apply(&mut self, store: &mut RdfStore, message: ReplicationMessage) -> Result<(), ReplicationError> {
+        if self.role == ReplicationRole::Leader {
+            return Err(ReplicationError::NotFollower);
+        }
+        if message.epoch() < self.epoch {
+            return Err(ReplicationError::StaleEpoch { current: self.epoch, received: message.epoch() });
+        }
+        self.epoch = message.epoch();
+
+        match message {
+            ReplicationMessage::Snapshot { epoch, sequence, triples } => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                StoreSnapshot::from_triples(triples, now).restore_into(store);
+                self.cursor = Some(ReplicationCursor { epoch, sequence });
+                self.leader_sequence = sequence;
+                self.applied_at = self.leader_time.max(now);
+                self.leader_time = self.applied_at;
+                self.snapshots_received += 1;
+            }
+            ReplicationMessage::Records { epoch, records } => {
+                let mut cursor = match self.cursor {
+                    Some(cursor) if cursor.epoch == epoch => cursor,
+                    _ => return Err(ReplicationError::SnapshotRequired),
+                };
+                for record in records {
+                    // 再接続で重複して届いたものは読み飛ばす
+                    if record.sequence <= cursor.sequence {
+                        continue;
+                    }
+                    if record.sequence != cursor.sequence + 1 {
+                        self.cursor = None;
+                        return Err(ReplicationError::SequenceGap { expected: cursor.sequence + 1, received: record.sequence });
+                    }
+                    record.operation.apply(store);
+                    cursor.sequence = record.sequence;
+                    self.cursor = Some(cursor);
+                    self.applied_at = record.timestamp;
+                    self.leader_time = self.leader_time.max(record.timestamp);
+                    self.leader_sequence = self.leader_sequence.max(record.sequence);
+                    self.records_applied += 1;
+                }
+            }
+            ReplicationMessage::Heartbeat { sequence, timestamp, .. } => {
+                self.leader_sequence = self.leader_sequence.max(sequence);
+                self.leader_time = self.leader_time.max(timestamp);
+                if self.cursor.is_some_and(|cursor| cursor.sequence >= sequence) {
+                    self.applied_at = self.leader_time;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> ReplicationStatus {
+        let applied = self.cursor.map(|cursor| cursor.sequence);
+        let lag_records = self.leader_sequence.saturating_sub(applied.unwrap_or(0));
+        ReplicationStatus {
+            role: self.role,
+            epoch: self.epoch,
+            applied_sequence: applied,
+            leader_sequence: self.leader_sequence,
+            lag_records,
+            lag_ms: if lag_records == 0 { 0 } else { self.leader_time.saturating_sub(self.applied_at) },
+            snapshots_received: self.snapshots_received,
+            records_applied: self.records_applied,
+        }
+    }
+
+    /// Switch to active: start a new epoch and record mutations for followers of this instance
+    ///
+    /// 旧リーダーのメッセージは以後 [`ReplicationError::NotFollower`] で拒否する。新しいエポックを返す
+    pub fn promote(&mut self, store: &mut RdfStore, backlog: usize) -> u64 {
+        self.role = ReplicationRole::Leader;
+        self.epoch += 1;
+        let sequence = self.cursor.map_or(0, |cursor| cursor.sequence);
+        store.attach_replication(ReplicationLog::new(backlog).with_epoch(self.epoch).starting_at(sequence));
+        self.epoch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::{GraphId, Provenance};
+    use fukurow_core::model::Triple;
+
+    fn triple(subject: &str) -> Triple {
+        Triple {
+            subject: subject.to_string(),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: "http://example.org/c2".to_string(),
+        }
+    }
+
+    fn sensor() -> Provenance {
+        Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.9) }
+    }
+
+    /// Round trip through JSON, as a transport would
+    fn ship(message: ReplicationMessage) -> ReplicationMessage {
+        serde_json::from_str(&serde_json::to_string(&message).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_follower_catches_up_from_snapshot_and_records() {
+        let mut leader = RdfStore::new();
+        leader.insert(triple("http://example.org/h1"), GraphId::Default, sensor());
+        leader.attach_replication(ReplicationLog::new(2));
+        leader.insert(triple("http://example.org/h2"), GraphId::Sensor("edr".to_string()), sensor());
+
+        let mut replica = RdfStore::new();
+        let mut follower = ReplicationFollower::new();
+        // 位置のないフォロワーにはスナップショットを送る
+        let message = leader.replication().unwrap().catch_up(&leader, follower.cursor());
+        assert!(matches!(message, ReplicationMessage::Snapshot { sequence: 1, .. }));
+        follower.apply(&mut replica, ship(message)).unwrap();
+        assert_eq!(replica.statistics().total_triples, 2);
+
+        leader.remove_triple(&triple("http://example.org/h1"), &GraphId::Default);
+        leader.insert(triple("http://example.org/h3"), GraphId::Default, sensor());
+        follower.apply(&mut replica, leader.replication().unwrap().heartbeat()).unwrap();
+        let status = follower.status();
+        assert_eq!((status.applied_sequence, status.leader_sequence, status.lag_records), (Some(1), 3, 2));
+
+        let message = leader.replication().unwrap().catch_up(&leader, follower.cursor());
+        assert!(matches!(&message, ReplicationMessage::Records { records, .. } if records.len() == 2));
+        follower.apply(&mut replica, ship(message)).unwrap();
+        assert!(replica.find_triples(Some("http://example.org/h1"), None, None).is_empty());
+        assert_eq!(replica.find_triples(Some("http://example.org/h3"), None, None)[0].provenance, sensor());
+        assert_eq!(follower.status().lag_records, 0);
+
+        // バックログ (2 件) より遅れたフォロワーはスナップショットからやり直す
+        for host in ["h4", "h5", "h6"] {
+            leader.insert(triple(&format!("http://example.org/{}", host)), GraphId::Default, sensor());
+        }
+        let message = leader.replication().unwrap().catch_up(&leader, follower.cursor());
+        assert!(matches!(message, ReplicationMessage::Snapshot { sequence: 6, .. }));
+    }
+
+    #[test]
+    fn test_gaps_and_promotion() {
+        let mut leader = RdfStore::new();
+        leader.attach_replication(ReplicationLog::default());
+        let mut replica = RdfStore::new();
+        let mut follower = ReplicationFollower::new();
+        follower.apply(&mut replica, leader.replication().unwrap().catch_up(&leader, None)).unwrap();
+
+        leader.insert(triple("http://example.org/h1"), GraphId::Default, sensor());
+        leader.insert(triple("http://example.org/h2"), GraphId::Default, sensor());
+        let records = match leader.replication().unwrap().catch_up(&leader, follower.cursor()) {
+            ReplicationMessage::Records { records, .. } => records,
+            other => panic!("unexpected message: {:?}", other),
+        };
+        let gap = follower.apply(&mut replica, ReplicationMessage::Records { epoch: 1, records: records[1..].to_vec() });
+        assert_eq!(gap, Err(ReplicationError::SequenceGap { expected: 1, received: 2 }));
+        assert_eq!(follower.cursor(), None);
+
+        follower.apply(&mut replica, leader.replication().unwrap().catch_up(&leader, follower.cursor())).unwrap();
+        assert_eq!(follower.promote(&mut replica, 100), 2);
+        assert_eq!(follower.role(), ReplicationRole::Leader);
+        assert_eq!(follower.apply(&mut replica, leader.replication().unwrap().heartbeat()), Err(ReplicationError::NotFollower));
+
+        // 昇格したインスタンスのフォロワーは旧リーダーのエポックを拒否する
+        replica.insert(triple("http://example.org/h3"), GraphId::Default, sensor());
+        let log = replica.replication().unwrap();
+        assert_eq!((log.epoch(), log.last_sequence()), (2, 3));
+        let mut standby = ReplicationFollower::new();
+        standby.apply(&mut RdfStore::new(), log.catch_up(&replica, None)).unwrap();
+        assert_eq!(
+            standby.apply(&mut RdfStore::new(), leader.replication().unwrap().heartbeat()),
+            Err(ReplicationError::StaleEpoch { current: 2, received: 1 })
+        );
+    }
+}
@@ -0,0 +1,387 @@
+//! # Retention Policies
+//!
+//! センサーデータが無制限に蓄積しないよう、グラフごとに保持期間 (TTL) と最大トリプル数を設定する。
+//! - 期限切れ・上限超過のトリプルは古いものから削除し、監査ログに Clear / Delete を残す
+//! - 削除したトリプルを根拠 (`Provenance::Inferred` の evidence) とする推論結果は連鎖的に取り消せる
+//! - [`EvictionHook`] で下流 (キャッシュ、外部インデックスなど) に削除を通知する
+//!
+//! `tokio` フィーチャーでは [`RetentionEnforcer::spawn`] がバックグラウンドで定期的に適用する
+
+use crate::provenance::{GraphId, Provenance};
+use crate::store::{RdfStore, StoredTriple};
+use fukurow_core::model::Triple;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Audit metadata `reason` of retention deletions
+pub const RETENTION_REASON: &str = "retention";
+/// Audit metadata `reason` of retracted inferences
+pub const RETRACTION_REASON: &str = "evidence-evicted";
+
+/// Limits of one graph; unset limits are not enforced
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Maximum age of a triple, measured from `asserted_at`
+    pub ttl: Option<Duration>,
+    /// Maximum triples kept (the oldest are evicted first)
+    pub max_triples: Option<usize>,
+}
+
+impl RetentionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_max_triples(mut self, max_triples: usize) -> Self {
+        self.max_triples = Some(max_triples);
+        self
+    }
+
+    /// Positions in `graph` to evict at `now` (Unix milliseconds)
+    pub fn select(&self, graph: &[&StoredTriple], now: u64) -> HashSet<usize> {
+        let mut evicted = HashSet::new();
+        if let Some(ttl) = self.ttl {
+            let cutoff = now.saturating_sub(ttl.as_millis() as u64);
+            evicted.extend(graph.iter().enumerate().filter(|(_, stored)| stored.asserted_at < cutoff).map(|(i, _)| i));
+        }
+        if let Some(max) = self.max_triples {
+            let remaining = graph.len() - evicted.len();
+            if remaining > max {
+                // 挿入順が時刻順とは限らない (履歴の再生など) ため assert 時刻で並べる
+                let mut candidates: Vec<usize> = (0..graph.len()).filter(|i| !evicted.contains(i)).collect();
+                candidates.sort_by_key(|&i| (graph[i].asserted_at, i));
+                evicted.extend(candidates.into_iter().take(remaining - max));
+            }
+        }
+        evicted
+    }
+}
+
+/// Retention policies of a store
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicies {
+    /// Graph-specific policies
+    pub graphs: HashMap<GraphId, RetentionPolicy>,
+    /// Policy of `GraphId::Sensor` graphs without their own entry
+    pub sensor_default: Option<RetentionPolicy>,
+    /// Retract inferred triples whose evidence was evicted
+    pub retract_derived: bool,
+}
+
+impl RetentionPolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_policy(mut self, graph_id: GraphId, policy: RetentionPolicy) -> Self {
+        self.graphs.insert(graph_id, policy);
+        self
+    }
+
+    pub fn with_sensor_default(mut self, policy: RetentionPolicy) -> Self {
+        self.sensor_default = Some(policy);
+        self
+    }
+
+    pub fn with_derived_retraction(mut self, enabled: bool) -> Self {
+        self.retract_derived = enabled;
+        self
+    }
+
+    pub fn policy_for(&self, graph_id: &GraphId) -> Option<&RetentionPolicy> {
+        self.graphs.get(graph_id).or(match graph_id {
+            GraphId::Sensor(_) => self.sensor_default.as_ref(),
+            _ => None,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graphs.is_empty() && self.sensor_default.is_none()
+    }
+}
+
+/// Outcome of one retention pass
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvictionReport {
+    /// Triples removed by the policies
+    pub evicted: Vec<StoredTriple>,
+    /// Inferred triples removed because their evidence was evicted
+    pub retracted: Vec<StoredTriple>,
+}
+
+impl EvictionReport {
+    pub fn is_empty(&self) -> bool {
+        self.evicted.is_empty() && self.retracted.is_empty()
+    }
+
+    /// Evicted triples per graph
+    pub fn evicted_by_graph(&self) -> HashMap<GraphId, usize> {
+        let mut counts = HashMap::new();
+        for stored in &self.evicted {
+            *counts.entry(stored.graph_id.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// Receives every non-empty eviction report while the store is still locked
+///
+/// 推論結果の取り消しなど、削除に追従してストアを書き換える処理を差し込む
+pub trait EvictionHook: Send + Sync {
+    fn on_evicted(&self, store: &mut RdfStore, report: &EvictionReport);
+}
+
+impl<F> EvictionHook for F
+where
+    F: Fn(&mut RdfStore, &EvictionReport) + Send + Sync,
+{
+    fn on_evicted(&self, store: &mut RdfStore, report: &EvictionReport) {
+        self(store, report)
+    }
+}
+
+/// Key identifying a triple in `Provenance::Inferred` evidence
+pub fn evidence_key(triple: &Triple) -> String {
+    format!("{} {} {}", triple.subject, triple.predicate, triple.object)
+}
+
+/// Remove inferred triples supported by evicted evidence, transitively
+///
+/// 同じトリプルが別のグラフに残っていれば根拠は失われていないとみなす
+pub fn retract_derived(store: &mut RdfStore, evicted: &[StoredTriple]) -> Vec<StoredTriple> {
+    let mut lost: HashSet<String> = evicted.iter()
+        .filter(|stored| store.find_triples(Some(&stored.triple.subject), Some(&stored.triple.predicate), Some(&stored.triple.object)).is_empty())
+        .map(|stored| evidence_key(&stored.triple.to_triple()))
+        .collect();
+    let mut retracted = Vec::new();
+
+    while !lost.is_empty() {
+        let graphs: Vec<GraphId> = store.graph_ids().into_iter().cloned().collect();
+        let mut removed = Vec::new();
+        for graph_id in graphs {
+            removed.extend(store.remove_where(&graph_id, |_, stored| match &stored.provenance {
+                Provenance::Inferred { evidence, .. } => evidence.iter().any(|key| lost.contains(key)),
+                _ => false,
+            }, RETRACTION_REASON));
+        }
+        // 取り消した推論を根拠とする推論も次の周回で取り消す
+        lost = removed.iter()
+            .filter(|stored| store.find_triples(Some(&stored.triple.subject), Some(&stored.triple.predicate), Some(&stored.triple.object)).is_empty())
+            .map(|stored| evidence_key(&stored.triple.to_triple()))
+            .collect();
+        retracted.extend(removed);
+    }
+    retracted
+}
+
+/// Applies retention policies and notifies hooks
+#[derive(Clone, Default)]
+pub struct RetentionEnforcer {
+    policies: RetentionPolicies,
+    hooks: Vec<Arc<dyn EvictionHook>>,
+}
+
+impl RetentionEnforcer {
+    pub fn new(policies: RetentionPolicies) -> Self {
+        Self { policies, hooks: Vec::new() }
+    }
+
+    pub fn with_hook(mut self, hook: impl EvictionHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    pub fn policies(&self) -> &RetentionPolicies {
+        &self.policies
+    }
+
+    /// Run one retention pass at `now` (Unix milliseconds)
+    pub fn enforce(&self, store: &mut RdfStore, now: u64) -> EvictionReport {
+        let mut report = EvictionReport::default();
+        let graphs: Vec<GraphId> = store.graph_ids().into_iter().cloned().collect();
+        for graph_id in graphs {
+            let policy = match self.policies.policy_for(&graph_id) {
+                Some(policy) => policy,
+                None => continue,
+            };
+            let selected = policy.select(&store.get_graph(&graph_id), now);
+            if !selected.is_empty() {
+                report.evicted.extend(store.remove_where(&graph_id, |position, _| selected.contains(&position), RETENTION_REASON));
+            }
+        }
+
+        if self.policies.retract_derived && !report.evicted.is_empty() {
+            report.retracted = retract_derived(store, &report.evicted);
+        }
+        if !report.is_empty() {
+            for hook in &self.hooks {
+                hook.on_evicted(store, &report);
+            }
+        }
+        report
+    }
+
+    /// Run one retention pass at the current time
+    pub fn enforce_now(&self, store: &mut RdfStore) -> EvictionReport {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.enforce(store, now)
+    }
+}
+
+impl std::fmt::Debug for RetentionEnforcer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetentionEnforcer")
+            .field("policies", &self.policies)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod task {
+    use super::*;
+    use fukurow_core::model::InternedString;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tokio::sync::RwLock;
+    use tokio::task::JoinHandle;
+
+    /// Handle of the background retention task; dropping it stops the task
+    #[derive(Debug)]
+    pub struct RetentionTask {
+        handle: JoinHandle<()>,
+        evicted: Arc<AtomicU64>,
+        retracted: Arc<AtomicU64>,
+    }
+
+    impl RetentionTask {
+        /// Triples evicted since the task started
+        pub fn evicted(&self) -> u64 {
+            self.evicted.load(Ordering::Relaxed)
+        }
+
+        /// Inferred triples retracted since the task started
+        pub fn retracted(&self) -> u64 {
+            self.retracted.load(Ordering::Relaxed)
+        }
+
+        pub fn stop(self) {}
+    }
+
+    impl Drop for RetentionTask {
+        fn drop(&mut self) {
+            self.handle.abort();
+        }
+    }
+
+    impl RetentionEnforcer {
+        /// Enforce the policies every `interval` on a Tokio task
+        pub fn spawn(self, store: Arc<RwLock<RdfStore>>, interval: Duration) -> RetentionTask {
+            let evicted = Arc::new(AtomicU64::new(0));
+            let retracted = Arc::new(AtomicU64::new(0));
+            let counters = (Arc::clone(&evicted), Arc::clone(&retracted));
+            let handle = tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(1)));
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    ticker.tick().await;
+                    let report = {
+                        let mut store = store.write().await;
+                        self.enforce_now(&mut store)
+                    };
+                    counters.0.fetch_add(report.evicted.len() as u64, Ordering::Relaxed);
+                    counters.1.fetch_add(report.retracted.len() as u64, Ordering::Relaxed);
+                    // 退避したトリプルを手放してから、それだけが使っていた文字列をプールから外す
+                    if !report.is_empty() {
+                        drop(report);
+                        InternedString::release_unused();
+                    }
+                }
+            });
+            RetentionTask { handle, evicted, retracted }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub use task::RetentionTask;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::AuditOperation;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn triple(s: &str, p: &str, o: &str) -> Triple {
+        Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() }
+    }
+
+    fn sensor() -> Provenance {
+        Provenance::Sensor { source: "edr-1".to_string(), confidence: None }
+    }
+
+    #[test]
+    fn test_ttl_and_max_triples() {
+        let mut store = RdfStore::new();
+        let events = GraphId::Sensor("edr-1".to_string());
+        for (i, at) in [1_000u64, 2_000, 9_000, 9_500, 9_800].iter().enumerate() {
+            store.insert_at(triple(&format!("event:{}", i), "type", "Event"), events.clone(), sensor(), *at);
+        }
+        store.insert_at(triple("host", "type", "Asset"), GraphId::Default, sensor(), 0);
+
+        let policies = RetentionPolicies::new()
+            .with_sensor_default(RetentionPolicy::new().with_ttl(Duration::from_secs(5)).with_max_triples(2));
+        let report = RetentionEnforcer::new(policies).enforce(&mut store, 10_000);
+
+        // TTL で 2 件、上限で最も古い残り 1 件を削除する。方針の無いグラフは残す
+        assert_eq!(report.evicted_by_graph().get(&events), Some(&3));
+        let remaining: Vec<&str> = store.get_graph(&events).iter().map(|s| s.triple.subject.as_str()).collect();
+        assert_eq!(remaining, vec!["event:3", "event:4"]);
+        assert_eq!(store.get_graph(&GraphId::Default).len(), 1);
+        let deletes = store.get_audit_trail().iter()
+            .filter(|entry| matches!(entry.operation, AuditOperation::Delete { .. }) && entry.metadata.get("reason") == Some(&serde_json::Value::from(RETENTION_REASON)))
+            .count();
+        assert_eq!(deletes, 3);
+    }
+
+    #[test]
+    fn test_evicted_evidence_retracts_inferences() {
+        let mut store = RdfStore::new();
+        let events = GraphId::Sensor("edr-1".to_string());
+        let evidence = triple("event:1", "connectsTo", "198.51.100.7");
+        store.insert_at(evidence.clone(), events.clone(), sensor(), 1_000);
+        let inferred = |evidence: &Triple| Provenance::Inferred {
+            rule: "c2".to_string(),
+            reasoning_level: "rules".to_string(),
+            evidence: vec![evidence_key(evidence)],
+            confidence: None,
+        };
+        let alert = triple("host", "hasAlert", "c2");
+        store.insert_at(alert.clone(), GraphId::Inferred("rules".to_string()), inferred(&evidence), 1_000);
+        store.insert_at(triple("host", "riskLevel", "high"), GraphId::Inferred("rules".to_string()), inferred(&alert), 1_000);
+
+        let notified = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&notified);
+        let enforcer = RetentionEnforcer::new(RetentionPolicies::new()
+            .with_policy(events.clone(), RetentionPolicy::new().with_ttl(Duration::from_secs(1)))
+            .with_derived_retraction(true))
+            .with_hook(move |_: &mut RdfStore, report: &EvictionReport| {
+                counter.fetch_add(report.retracted.len(), Ordering::Relaxed);
+            });
+
+        let report = enforcer.enforce(&mut store, 10_000);
+        assert_eq!(report.evicted.len(), 1);
+        assert_eq!(report.retracted.len(), 2);
+        assert_eq!(notified.load(Ordering::Relaxed), 2);
+        assert_eq!(store.statistics().total_triples, 0);
+        assert!(store.get_audit_trail().iter().any(|entry| matches!(entry.operation, AuditOperation::Clear { .. })));
+    }
+}
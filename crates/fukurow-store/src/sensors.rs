@@ -0,0 +1,144 @@
+//! Sensor heartbeat registry
+//!
+//! `Provenance::Sensor` で挿入されたトリプルの送信元ごとに初回/最終観測時刻と
+//! 観測レートを記録し、一定時間沈黙しているセンサー（停止したエージェント）を検出する
+
+use fukurow_core::model::Triple;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Named graph holding the materialized sensor registry
+pub const SENSOR_REGISTRY_GRAPH: &str = "sensor-registry";
+
+const SEC: &str = "https://w3id.org/security#";
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Heartbeat record for a single sensor source
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SensorRecord {
+    pub source: String,
+    /// First observation (Unix timestamp in milliseconds)
+    pub first_seen: u64,
+    /// Latest observation (Unix timestamp in milliseconds)
+    pub last_seen: u64,
+    /// Number of triples asserted by this sensor
+    pub observation_count: u64,
+}
+
+impl SensorRecord {
+    /// Average observations per minute over the sensor's active lifetime
+    pub fn rate_per_minute(&self) -> f64 {
+        let minutes = (self.last_seen.saturating_sub(self.first_seen) as f64 / 60_000.0).max(1.0);
+        self.observation_count as f64 / minutes
+    }
+
+    /// IRI used for this sensor in the registry graph
+    pub fn iri(&self) -> String {
+        format!("urn:fukurow:sensor:{}", self.source)
+    }
+}
+
+/// Sensor health classification
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorStatus {
+    Healthy,
+    Stale,
+}
+
+/// Health view of a sensor at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SensorHealth {
+    #[serde(flatten)]
+    pub record: SensorRecord,
+    pub status: SensorStatus,
+    /// Milliseconds since the sensor was last seen
+    pub silent_for_ms: u64,
+    pub rate_per_minute: f64,
+}
+
+/// Registry of known sensors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SensorRegistry {
+    sensors: HashMap<String, SensorRecord>,
+}
+
+impl SensorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an observation from `source` at `timestamp` (ms)
+    pub fn observe(&mut self, source: &str, timestamp: u64) {
+        let record = self.sensors.entry(source.to_string()).or_insert_with(|| SensorRecord {
+            source: source.to_string(),
+            first_seen: timestamp,
+            last_seen: timestamp,
+            observation_count: 0,
+        });
+        record.first_seen = record.first_seen.min(timestamp);
+        record.last_seen = record.last_seen.max(timestamp);
+        record.observation_count += 1;
+    }
+
+    pub fn get(&self, source: &str) -> Option<&SensorRecord> {
+        self.sensors.get(source)
+    }
+
+    /// All records, sorted by source
+    pub fn records(&self) -> Vec<&SensorRecord> {
+        let mut records: Vec<_> = self.sensors.values().collect();
+        records.sort_by(|a, b| a.source.cmp(&b.source));
+        records
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    /// Health of every sensor; sensors silent longer than `stale_after_ms` are stale
+    pub fn health(&self, now: u64, stale_after_ms: u64) -> Vec<SensorHealth> {
+        self.records()
+            .into_iter()
+            .map(|record| {
+                let silent_for_ms = now.saturating_sub(record.last_seen);
+                SensorHealth {
+                    record: record.clone(),
+                    status: if silent_for_ms > stale_after_ms { SensorStatus::Stale } else { SensorStatus::Healthy },
+                    silent_for_ms,
+                    rate_per_minute: record.rate_per_minute(),
+                }
+            })
+            .collect()
+    }
+
+    /// Sensors that have been silent longer than `stale_after_ms`
+    pub fn stale(&self, now: u64, stale_after_ms: u64) -> Vec<SensorHealth> {
+        self.health(now, stale_after_ms)
+            .into_iter()
+            .filter(|h| h.status == SensorStatus::Stale)
+            .collect()
+    }
+
+    /// Registry as RDF triples (for the registry graph)
+    pub fn to_triples(&self) -> Vec<Triple> {
+        let mut triples = Vec::new();
+        for record in self.records() {
+            let subject = record.iri();
+            let mut push = |predicate: String, object: String| {
+                triples.push(Triple { subject: subject.clone(), predicate, object });
+            };
+            push(RDF_TYPE.to_string(), format!("{}Sensor", SEC));
+            push(format!("{}sensorSource", SEC), record.source.clone());
+            push(format!("{}firstSeen", SEC), record.first_seen.to_string());
+            push(format!("{}lastSeen", SEC), record.last_seen.to_string());
+            push(format!("{}observationCount", SEC), record.observation_count.to_string());
+            push(format!("{}ratePerMinute", SEC), format!("{:.3}", record.rate_per_minute()));
+        }
+        triples
+    }
+}
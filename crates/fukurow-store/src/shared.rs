@@ -0,0 +1,255 @@
+//! Concurrent read access
+//!
+//! [`SharedStore`] は書き込み用のストア (primary、`RwLock<RdfStore>`) と、クエリ用の
+//! 読み取り専用レプリカ (`Arc<RdfStore>`) を持つ。クエリはレプリカをロックなしで参照するため、
+//! 長い SPARQL クエリの実行中もイベントの投入や推論は待たされず、逆に推論中のクエリも待たされない
+//!
+//! 一貫性の保証:
+//! - レプリカはある時点の primary の完全なコピー (スナップショット一貫性)。
+//!   1 回の書き込みロック内の変更が途中まで見えることはない
+//! - [`SharedStore::view`] は最大 `max_staleness` (既定 [`DEFAULT_MAX_STALENESS`]) 古いレプリカを返す。
+//!   primary が書き込み中で更新できないときや、別のタスクが更新中のときは、それより古いレプリカをそのまま返す
+//! - 自分の書き込みを確実に読むには [`SharedStore::fresh_view`] を使う (実行中の書き込みの完了を待つ)。
+//!   `with_max_staleness(Duration::ZERO)` にすると `view` も完了した書き込みをすべて反映するが、
+//!   書き込みのたびにクエリ側で索引を再構築することになる
+//! - レプリカにはトリプルと索引だけが入る。監査ログ・センサー情報・WAL は primary で参照する
+//!
+//! レプリカの更新はスナップショット ([`RdfStore::snapshot`]) の取得だけをロック内で行い、
+//! 索引の再構築はロックの外で行う。変更がなければ再構築しない
+
+use crate::access_stats::AccessProfiler;
+use crate::snapshot::StoreSnapshot;
+use crate::store::RdfStore;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Default bound on how old a replica returned by [`SharedStore::view`] may be
+///
+/// 投入が続く間もレプリカの再構築はこの間隔に 1 回までになる
+pub const DEFAULT_MAX_STALENESS: Duration = Duration::from_millis(100);
+
+/// Store with a lock-free read replica for queries
+#[derive(Debug, Clone)]
+pub struct SharedStore {
+    primary: Arc<RwLock<RdfStore>>,
+    replica: Arc<Mutex<Replica>>,
+    /// Held while a replica is being rebuilt
+    refresh: Arc<tokio::sync::Mutex<()>>,
+    max_staleness: Duration,
+    /// Profiler of the primary, also fed by queries on replicas
+    access: Arc<AccessProfiler>,
+}
+
+#[derive(Debug, Clone)]
+struct Replica {
+    snapshot: StoreSnapshot,
+    store: Arc<RdfStore>,
+    refreshed_at: Instant,
+}
+
+impl SharedStore {
+    /// Share `store`; [`view`](Self::view) lags writes by at most [`DEFAULT_MAX_STALENESS`]
+    pub fn new(store: RdfStore) -> Self {
+        let snapshot = store.snapshot();
+        let access = Arc::clone(store.access_profiler());
+        let replica = Replica {
+            store: Arc::new(RdfStore::replica_of(&snapshot, Arc::clone(&access))),
+            snapshot,
+            refreshed_at: Instant::now(),
+        };
+        Self {
+            primary: Arc::new(RwLock::new(store)),
+            replica: Arc::new(Mutex::new(replica)),
+            refresh: Arc::new(tokio::sync::Mutex::new(())),
+            max_staleness: DEFAULT_MAX_STALENESS,
+            access,
+        }
+    }
+
+    /// How old a replica [`view`](Self::view) may return before rebuilding it
+    ///
+    /// 0 にすると完了した書き込みをすべて反映するが、大量投入中は変更のたびに索引を再構築する
+    pub fn with_max_staleness(mut self, max_staleness: Duration) -> Self {
+        self.max_staleness = max_staleness;
+        self
+    }
+
+    pub fn max_staleness(&self) -> Duration {
+        self.max_staleness
+    }
+
+    /// The writable store (shared with batch ingestion, retention and the reasoner)
+    pub fn primary(&self) -> &Arc<RwLock<RdfStore>> {
+        &self.primary
+    }
+
+    /// Lock the primary for reading (audit trail, sensors and other non-replicated state)
+    pub async fn read(&self) -> RwLockReadGuard<'_, RdfStore> {
+        self.primary.read().await
+    }
+
+    /// Lock the primary for writing
+    pub async fn write(&self) -> RwLockWriteGuard<'_, RdfStore> {
+        self.primary.write().await
+    }
+
+    /// Read replica for queries; never waits for a lock
+    ///
+    /// レプリカが `max_staleness` より古ければ更新を試みる。primary が書き込み中か、
+    /// 別のタスクが更新中なら現在のレプリカを返す
+    pub fn view(&self) -> Arc<RdfStore> {
+        let current = self.current();
+        if current.refreshed_at.elapsed() < self.max_staleness {
+            return current.store;
+        }
+        let Ok(_refresh) = self.refresh.try_lock() else { return current.store };
+        let snapshot = match self.primary.try_read() {
+            Ok(primary) => primary.snapshot(),
+            Err(_) => return current.store,
+        };
+        self.publish(snapshot)
+    }
+
+    /// Read replica including every write completed before the call
+    pub async fn fresh_view(&self) -> Arc<RdfStore> {
+        let _refresh = self.refresh.lock().await;
+        let snapshot = self.primary.read().await.snapshot();
+        self.publish(snapshot)
+    }
+
+    /// When the current replica was taken
+    pub fn replica_taken_at(&self) -> u64 {
+        self.current().snapshot.taken_at()
+    }
+
+    fn current(&self) -> Replica {
+        self.replica.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Install the replica of `snapshot` (reusing the current one if nothing changed)
+    fn publish(&self, snapshot: StoreSnapshot) -> Arc<RdfStore> {
+        let current = self.current();
+        let store = if snapshot.shares_segments_with(&current.snapshot) {
+            current.store
+        } else {
+            Arc::new(RdfStore::replica_of(&snapshot, Arc::clone(&self.access)))
+        };
+        *self.replica.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Replica {
+            snapshot,
+            store: Arc::clone(&store),
+            refreshed_at: Instant::now(),
+        };
+        store
+    }
+}
+
+impl Default for SharedStore {
+    fn default() -> Self {
+        Self::new(RdfStore::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::{GraphId, Provenance};
+    use fukurow_core::model::Triple;
+
+    fn insert(store: &mut RdfStore, subject: &str) {
+        store.insert(Triple {
+            subject: subject.to_string(),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: "http://example.org/c2".to_string(),
+        }, GraphId::Default, Provenance::Sensor { source: "edr".to_string(), confidence: None });
+    }
+
+    #[tokio::test]
+    async fn test_reads_do_not_wait_for_writers() {
+        let shared = SharedStore::default().with_max_staleness(Duration::ZERO);
+        insert(&mut *shared.write().await, "http://example.org/h1");
+        let view = shared.view();
+        assert_eq!(view.find_triples(Some("http://example.org/h1"), None, None).len(), 1);
+
+        // 書き込み中でも直前のレプリカを返し、書き込みはクエリ中のレプリカに影響しない
+        let mut writer = shared.write().await;
+        insert(&mut writer, "http://example.org/h2");
+        let during = shared.view();
+        assert_eq!(during.statistics().total_triples, 1);
+        drop(writer);
+
+        assert_eq!(shared.view().statistics().total_triples, 2);
+        assert_eq!(during.statistics().total_triples, 1);
+        assert!(shared.read().await.get_audit_trail().len() >= 2);
+        assert!(shared.view().get_audit_trail().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_store_reuses_replica_and_staleness_bound() {
+        let shared = SharedStore::default().with_max_staleness(Duration::from_secs(60));
+        insert(&mut *shared.write().await, "http://example.org/h1");
+
+        // 許容範囲内なら古いレプリカを返し、fresh_view は書き込みを反映する
+        assert_eq!(shared.view().statistics().total_triples, 0);
+        let fresh = shared.fresh_view().await;
+        assert_eq!(fresh.statistics().total_triples, 1);
+        assert!(Arc::ptr_eq(&fresh, &shared.fresh_view().await));
+    }
+
+    #[tokio::test]
+    async fn test_replica_reports_revision_of_its_snapshot() {
+        let shared = SharedStore::default().with_max_staleness(Duration::ZERO);
+        let empty = shared.view();
+        assert_eq!((empty.revision(), empty.modified_at()), (0, None));
+
+        insert(&mut *shared.write().await, "http://example.org/h1");
+        let revision = shared.read().await.revision();
+        assert!(revision > 0);
+        let view = shared.view();
+        assert_eq!(view.revision(), revision);
+        assert!(view.modified_at().is_some());
+        // 変更がなければ同じレプリカ・同じリビジョンを返す
+        assert_eq!(shared.fresh_view().await.revision(), revision);
+
+        shared.write().await.clear_all();
+        assert!(shared.view().revision() > revision);
+    }
+
+    #[tokio::test]
+    async fn test_replica_lookups_count_in_primary_access_statistics() {
+        let mut store = RdfStore::new();
+        store.set_access_profiler(Arc::new(AccessProfiler::new(1, 5)));
+        let shared = SharedStore::new(store);
+        insert(&mut *shared.write().await, "http://example.org/h1");
+        insert(&mut *shared.write().await, "http://example.org/h2");
+
+        let view = shared.fresh_view().await;
+        for _ in 0..3 {
+            assert_eq!(view.find_triples(Some("http://example.org/h1"), None, None).len(), 1);
+        }
+        assert_eq!(view.find_triples(None, Some("http://example.org/connectsTo"), None).len(), 2);
+
+        let access = shared.read().await.statistics().access;
+        assert_eq!(access.lookups, 4);
+        assert_eq!(access.hot_subjects[0].term, "http://example.org/h1");
+        assert_eq!(access.hot_subjects[0].hits, 3);
+        assert_eq!(access.hot_predicates[0].hits, 1);
+        assert_eq!(access.graphs[0].graph, GraphId::Default);
+        assert_eq!((access.graphs[0].reads, access.graphs[0].writes), (5, 2));
+    }
+
+    #[tokio::test]
+    async fn test_default_staleness_batches_replica_rebuilds() {
+        let shared = SharedStore::default();
+        assert_eq!(shared.max_staleness(), DEFAULT_MAX_STALENESS);
+        let before = shared.view();
+
+        // 許容範囲内の書き込みごとにはレプリカを作り直さない
+        for i in 0..10 {
+            insert(&mut *shared.write().await, &format!("http://example.org/h{}", i));
+            assert!(Arc::ptr_eq(&before, &shared.view()));
+        }
+        tokio::time::sleep(DEFAULT_MAX_STALENESS).await;
+        assert_eq!(shared.view().statistics().total_triples, 10);
+    }
+}
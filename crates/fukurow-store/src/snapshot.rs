@@ -0,0 +1,195 @@
+//! Immutable point-in-time views of the store
+//!
+//! [`RdfStore::snapshot`] はグラフごとのセグメントを `Arc` で共有した読み取り専用の
+//! ビューを返す。スナップショットはロックを解放した後も一貫した状態を保ち、
+//! エクスポートや検証をエンジンの書き込みと並行して行える
+
+use crate::provenance::GraphId;
+use crate::store::{RdfStore, StoredTriple};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Consistent, cheaply clonable view of an [`RdfStore`]
+#[derive(Debug, Clone, Default)]
+pub struct StoreSnapshot {
+    graphs: Arc<HashMap<GraphId, Arc<Vec<StoredTriple>>>>,
+    /// When the snapshot was taken (Unix timestamp in milliseconds)
+    taken_at: u64,
+    /// Last write-ahead log record the snapshot includes
+    wal_sequence: u64,
+    /// [`RdfStore::revision`] when the snapshot was taken
+    revision: u64,
+    modified_at: Option<u64>,
+}
+
+impl StoreSnapshot {
+    pub(crate) fn new(graphs: HashMap<GraphId, Arc<Vec<StoredTriple>>>, taken_at: u64) -> Self {
+        Self { graphs: Arc::new(graphs), taken_at, wal_sequence: 0, revision: 0, modified_at: None }
+    }
+
+    pub(crate) fn with_wal_sequence(mut self, wal_sequence: u64) -> Self {
+        self.wal_sequence = wal_sequence;
+        self
+    }
+
+    pub(crate) fn with_revision(mut self, revision: u64, modified_at: Option<u64>) -> Self {
+        self.revision = revision;
+        self.modified_at = modified_at;
+        self
+    }
+
+    pub(crate) fn segments(&self) -> &HashMap<GraphId, Arc<Vec<StoredTriple>>> {
+        &self.graphs
+    }
+
+    /// Whether both snapshots share every graph segment (no graph changed in between)
+    pub fn shares_segments_with(&self, other: &StoreSnapshot) -> bool {
+        Arc::ptr_eq(&self.graphs, &other.graphs)
+            || (self.graphs.len() == other.graphs.len()
+                && self.graphs.iter().all(|(graph_id, segment)| {
+                    other.graphs.get(graph_id).is_some_and(|theirs| Arc::ptr_eq(segment, theirs))
+                }))
+    }
+
+    /// Build a snapshot from loose triples (e.g. an imported dump)
+    pub fn from_triples(triples: impl IntoIterator<Item = StoredTriple>, taken_at: u64) -> Self {
+        let mut graphs: HashMap<GraphId, Vec<StoredTriple>> = HashMap::new();
+        for stored in triples {
+            graphs.entry(stored.graph_id.clone()).or_default().push(stored);
+        }
+        Self::new(graphs.into_iter().map(|(graph_id, graph)| (graph_id, Arc::new(graph))).collect(), taken_at)
+    }
+
+    pub fn taken_at(&self) -> u64 {
+        self.taken_at
+    }
+
+    /// Sequence number of the last write-ahead log record reflected in the snapshot
+    pub fn wal_sequence(&self) -> u64 {
+        self.wal_sequence
+    }
+
+    /// Revision of the store when the snapshot was taken (see [`RdfStore::revision`])
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn modified_at(&self) -> Option<u64> {
+        self.modified_at
+    }
+
+    /// Total number of triples
+    pub fn len(&self) -> usize {
+        self.graphs.values().map(|graph| graph.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graphs.values().all(|graph| graph.is_empty())
+    }
+
+    /// Graph IDs in their display order (`default`, `named:...`, ...)
+    pub fn graph_ids(&self) -> Vec<&GraphId> {
+        let mut ids: Vec<&GraphId> = self.graphs.keys().collect();
+        ids.sort_by_key(|id| id.to_string());
+        ids
+    }
+
+    /// Triples of one graph in insertion order
+    pub fn graph(&self, graph_id: &GraphId) -> &[StoredTriple] {
+        self.graphs.get(graph_id).map(|graph| graph.as_slice()).unwrap_or_default()
+    }
+
+    /// Every triple, graph by graph in [`graph_ids`](Self::graph_ids) order
+    pub fn iter(&self) -> impl Iterator<Item = &StoredTriple> {
+        self.graph_ids().into_iter().flat_map(move |id| self.graph(id).iter())
+    }
+
+    /// Find triples matching a pattern (`None` matches anything); scans every graph
+    pub fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Vec<&StoredTriple> {
+        self.iter().filter(|stored| {
+            subject.is_none_or(|s| stored.triple.subject == s)
+                && predicate.is_none_or(|p| stored.triple.predicate == p)
+                && object.is_none_or(|o| stored.triple.object == o)
+        }).collect()
+    }
+
+    /// Rebuild a live store holding the snapshot's triples and provenance
+    pub fn to_store(&self) -> RdfStore {
+        let mut store = RdfStore::new();
+        self.restore_into(&mut store);
+        store
+    }
+
+    /// Replace the contents of `store` with the snapshot
+    ///
+    /// 復元は通常の削除・挿入として監査ログに記録される
+    pub fn restore_into(&self, store: &mut RdfStore) {
+        store.clear_all();
+        for stored in self.iter() {
+            store.insert_at(stored.triple.clone(), stored.graph_id.clone(), stored.provenance.clone(), stored.asserted_at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::Provenance;
+    use fukurow_core::model::Triple;
+
+    fn triple(subject: &str, object: &str) -> Triple {
+        Triple {
+            subject: subject.to_string(),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: object.to_string(),
+        }
+    }
+
+    fn sensor() -> Provenance {
+        Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.9) }
+    }
+
+    #[test]
+    fn test_snapshot_is_isolated_from_later_writes() {
+        let mut store = RdfStore::new();
+        store.insert(triple("http://example.org/h1", "http://example.org/h2"), GraphId::Default, sensor());
+        let snapshot = store.snapshot();
+        let clone = snapshot.clone();
+
+        store.insert(triple("http://example.org/h2", "http://example.org/h3"), GraphId::Default, sensor());
+        store.clear_graph(&GraphId::Default);
+
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(clone.find_triples(Some("http://example.org/h1"), None, None).len(), 1);
+        assert_eq!(store.snapshot().len(), 0);
+    }
+
+    #[test]
+    fn test_unchanged_graphs_share_segments() {
+        let mut store = RdfStore::new();
+        let events = GraphId::Named("urn:fukurow:graph:events".to_string());
+        store.insert(triple("http://example.org/h1", "http://example.org/h2"), GraphId::Default, sensor());
+        store.insert(triple("http://example.org/h3", "http://example.org/h4"), events.clone(), sensor());
+        let first = store.snapshot();
+
+        store.insert(triple("http://example.org/h5", "http://example.org/h6"), events.clone(), sensor());
+        let second = store.snapshot();
+
+        assert!(Arc::ptr_eq(&first.graphs[&GraphId::Default], &second.graphs[&GraphId::Default]));
+        assert!(!Arc::ptr_eq(&first.graphs[&events], &second.graphs[&events]));
+        assert_eq!(second.graph(&events).len(), 2);
+    }
+
+    #[test]
+    fn test_restore_round_trip_keeps_provenance() {
+        let mut store = RdfStore::new();
+        store.insert_at(triple("http://example.org/h1", "http://example.org/h2"), GraphId::Sensor("edr".to_string()), sensor(), 42);
+        let restored = store.snapshot().to_store();
+
+        let found = restored.find_triples(Some("http://example.org/h1"), None, None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].asserted_at, 42);
+        assert_eq!(found[0].graph_id, GraphId::Sensor("edr".to_string()));
+        assert_eq!(found[0].provenance, sensor());
+    }
+}
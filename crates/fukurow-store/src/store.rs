@@ -1,17 +1,25 @@
 //! RDF Store implementation with provenance
 
-use fukurow_core::model::Triple;
+use fukurow_core::model::{InternedString, InternedTriple, Triple};
+use fukurow_core::term::{BlankNodeScope, RdfTerm};
 use crate::provenance::{Provenance, GraphId, AuditEntry, AuditOperation};
+use crate::audit::{AuditQuery, AuditSink, AuditSinkError};
+use crate::sensors::{SensorRegistry, SENSOR_REGISTRY_GRAPH};
+use crate::snapshot::StoreSnapshot;
+use crate::replication::ReplicationLog;
+use crate::wal::{WalOperation, WriteAheadLog};
+use crate::access_stats::{AccessProfiler, AccessStatistics};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 /// Stored triple with metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StoredTriple {
     /// Graph identifier
     pub graph_id: GraphId,
-    /// The RDF triple
-    pub triple: Triple,
+    /// The RDF triple (terms shared with every other use of the same string)
+    pub triple: InternedTriple,
     /// When this triple was asserted (Unix timestamp in milliseconds)
     pub asserted_at: u64,
     /// Provenance information
@@ -26,13 +34,37 @@ pub struct RdfStore {
     /// Audit trail (limited size for memory efficiency)
     audit_trail: Vec<AuditEntry>,
     /// Subject index for fast lookup
-    subject_index: HashMap<String, HashSet<(GraphId, usize)>>,
+    subject_index: HashMap<InternedString, HashSet<(GraphId, usize)>>,
     /// Predicate index for fast lookup
-    predicate_index: HashMap<String, HashSet<(GraphId, usize)>>,
+    predicate_index: HashMap<InternedString, HashSet<(GraphId, usize)>>,
     /// Object index for fast lookup
-    object_index: HashMap<String, HashSet<(GraphId, usize)>>,
+    object_index: HashMap<InternedString, HashSet<(GraphId, usize)>>,
     /// Maximum audit trail size (for memory management)
     max_audit_entries: usize,
+    /// Whether entries were dropped from the in-memory audit trail to stay within the limit
+    audit_pruned: bool,
+    /// Persistent audit sink (receives every entry, regardless of the in-memory limit)
+    audit_sink: Option<Box<dyn AuditSink>>,
+    /// Number of entries the sink failed to persist
+    audit_sink_failures: usize,
+    /// Write-ahead log receiving every mutation
+    wal: Option<WriteAheadLog>,
+    /// Number of mutations the write-ahead log failed to record
+    wal_failures: usize,
+    /// Change feed for hot standby followers
+    replication: Option<ReplicationLog>,
+    /// Heartbeat registry of sensors seen in `Provenance::Sensor`
+    sensor_registry: SensorRegistry,
+    /// Actor recorded on audit entries while set
+    actor: Option<String>,
+    /// Per-graph segments shared with snapshots; a graph's segment is dropped when it changes
+    snapshot_segments: Mutex<HashMap<GraphId, Arc<Vec<StoredTriple>>>>,
+    /// Sampled lookup profile, shared with the read replicas of this store
+    access: Arc<AccessProfiler>,
+    /// Number of changes to the triples so far (copied to snapshots and replicas)
+    revision: u64,
+    /// When the triples last changed (Unix timestamp in milliseconds)
+    modified_at: Option<u64>,
 }
 
 impl RdfStore {
@@ -50,21 +82,143 @@ impl RdfStore {
             predicate_index: HashMap::new(),
             object_index: HashMap::new(),
             max_audit_entries,
+            audit_pruned: false,
+            audit_sink: None,
+            audit_sink_failures: 0,
+            wal: None,
+            wal_failures: 0,
+            replication: None,
+            sensor_registry: SensorRegistry::new(),
+            actor: None,
+            snapshot_segments: Mutex::new(HashMap::new()),
+            access: Arc::new(AccessProfiler::default()),
+            revision: 0,
+            modified_at: None,
+        }
+    }
+
+    /// Attach a persistent audit sink
+    pub fn set_audit_sink(&mut self, sink: Box<dyn AuditSink>) {
+        self.audit_sink = Some(sink);
+    }
+
+    /// Attach a write-ahead log; replay it into the store before attaching
+    pub fn attach_wal(&mut self, wal: WriteAheadLog) {
+        self.wal = Some(wal);
+    }
+
+    pub fn detach_wal(&mut self) -> Option<WriteAheadLog> {
+        self.wal.take()
+    }
+
+    pub fn wal_mut(&mut self) -> Option<&mut WriteAheadLog> {
+        self.wal.as_mut()
+    }
+
+    /// Sequence number of the last logged mutation (0 without a write-ahead log)
+    pub fn wal_sequence(&self) -> u64 {
+        self.wal.as_ref().map_or(0, |wal| wal.last_sequence())
+    }
+
+    /// Counter bumped by every change to the triples
+    ///
+    /// プロセス内でのみ単調増加する (再起動すると 0 から数え直す)。HTTP の ETag などの
+    /// 変更検知に使い、レプリカは元になったスナップショット時点の値を返す
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// When the triples last changed (Unix timestamp in milliseconds); `None` until the first change
+    pub fn modified_at(&self) -> Option<u64> {
+        self.modified_at
+    }
+
+    /// Number of mutations that could not be written to the write-ahead log
+    pub fn wal_failures(&self) -> usize {
+        self.wal_failures
+    }
+
+    /// Record every later mutation for replication followers
+    pub fn attach_replication(&mut self, log: ReplicationLog) {
+        self.replication = Some(log);
+    }
+
+    pub fn detach_replication(&mut self) -> Option<ReplicationLog> {
+        self.replication.take()
+    }
+
+    pub fn replication(&self) -> Option<&ReplicationLog> {
+        self.replication.as_ref()
+    }
+
+    /// Set the actor recorded on subsequent audit entries (`None` to stop attributing)
+    ///
+    /// 書き込みロックを保持している間だけ設定し、解放前に戻すこと
+    pub fn set_actor(&mut self, actor: Option<String>) {
+        self.actor = actor;
+    }
+
+    pub fn actor(&self) -> Option<&str> {
+        self.actor.as_deref()
+    }
+
+    /// Number of audit entries that could not be persisted to the sink
+    pub fn audit_sink_failures(&self) -> usize {
+        self.audit_sink_failures
+    }
+
+    /// Profile lookups with `profiler` (e.g. another sample rate or top K)
+    pub fn set_access_profiler(&mut self, profiler: Arc<AccessProfiler>) {
+        self.access = profiler;
+    }
+
+    /// Lookup profiler of this store (shared with its read replicas)
+    pub fn access_profiler(&self) -> &Arc<AccessProfiler> {
+        &self.access
+    }
+
+    /// Per-graph read/write counters and hot subjects/predicates
+    pub fn access_statistics(&self) -> AccessStatistics {
+        self.access.statistics()
+    }
+
+    /// Query audit history (from the sink when attached, otherwise the in-memory trail)
+    pub fn query_audit(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditSinkError> {
+        match &self.audit_sink {
+            Some(sink) => sink.query(query),
+            None => Ok(query.apply(self.audit_trail.iter())),
         }
     }
 
     /// Insert a triple with provenance
-    pub fn insert(&mut self, triple: Triple, graph_id: GraphId, provenance: Provenance) {
+    pub fn insert(&mut self, triple: impl Into<InternedTriple>, graph_id: GraphId, provenance: Provenance) {
+        let asserted_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.insert_at(triple, graph_id, provenance, asserted_at);
+    }
+
+    /// Insert a triple with an explicit assertion timestamp (used when replaying history)
+    pub fn insert_at(&mut self, triple: impl Into<InternedTriple>, graph_id: GraphId, provenance: Provenance, asserted_at: u64) {
+        let triple = triple.into();
+        if let Provenance::Sensor { source, .. } = &provenance {
+            self.sensor_registry.observe(source, asserted_at);
+        }
+
         let stored = StoredTriple {
             graph_id: graph_id.clone(),
             triple: triple.clone(),
-            asserted_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis() as u64,
+            asserted_at,
             provenance: provenance.clone(),
         };
 
+        if self.logs_mutations() {
+            self.log_wal(WalOperation::Insert { stored: stored.clone() });
+        }
+
+        self.invalidate_segment(&graph_id);
+        self.access.record_writes(&graph_id, 1);
         let graph = self.triples.entry(graph_id.clone()).or_insert_with(Vec::new);
         let index = graph.len();
         graph.push(stored);
@@ -91,7 +245,7 @@ impl RdfStore {
                 .unwrap_or_default()
                 .as_millis() as u64,
             operation: AuditOperation::Insert {
-                triple: format!("{} {} {}", triple.subject, triple.predicate, triple.object),
+                triple: triple.to_triple(),
                 graph_id,
                 provenance,
             },
@@ -107,70 +261,139 @@ impl RdfStore {
         }
     }
 
-    /// Find triples matching a pattern
-    pub fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Vec<&StoredTriple> {
-        let mut candidates = Vec::new();
+    /// Insert the triples of one document, giving its blank nodes store-unique labels
+    ///
+    /// 文書ごとにブランクノードのスコープを分けるため、別の文書の `_:b0` とは
+    /// 別ノードになる。付け替え後のトリプルを返す
+    pub fn insert_document(&mut self, triples: Vec<Triple>, graph_id: GraphId, provenance: Provenance) -> Vec<Triple> {
+        let mut scope = BlankNodeScope::new();
+        let scoped: Vec<Triple> = triples.into_iter().map(|triple| scope.relabel_triple(triple)).collect();
+        self.insert_batch(scoped.clone(), graph_id, provenance);
+        scoped
+    }
 
-        // Use the most selective index
-        if let Some(subj) = subject {
-            if let Some(indices) = self.subject_index.get(subj) {
-                for (graph_id, idx) in indices {
-                    if let Some(graph) = self.triples.get(graph_id) {
-                        if let Some(stored) = graph.get(*idx) {
-                            candidates.push(stored);
-                        }
-                    }
-                }
-            }
-        } else if let Some(pred) = predicate {
-            if let Some(indices) = self.predicate_index.get(pred) {
-                for (graph_id, idx) in indices {
-                    if let Some(graph) = self.triples.get(graph_id) {
-                        if let Some(stored) = graph.get(*idx) {
-                            candidates.push(stored);
-                        }
-                    }
-                }
+    /// Find triples by RDF term
+    ///
+    /// Compares parsed terms instead of raw strings, so `"Alice"` matches a stored
+    /// `Alice` and `<http://x>` matches `http://x`. This scans every triple.
+    pub fn find_terms(&self, subject: Option<&RdfTerm>, predicate: Option<&RdfTerm>, object: Option<&RdfTerm>) -> Vec<&StoredTriple> {
+        self.triples.values().flatten().filter(|stored| {
+            if let Some(s) = subject {
+                if stored.triple.subject_term() != *s { return false; }
             }
-        } else if let Some(obj) = object {
-            if let Some(indices) = self.object_index.get(obj) {
-                for (graph_id, idx) in indices {
-                    if let Some(graph) = self.triples.get(graph_id) {
-                        if let Some(stored) = graph.get(*idx) {
-                            candidates.push(stored);
-                        }
-                    }
-                }
+            if let Some(p) = predicate {
+                if stored.triple.predicate_term() != *p { return false; }
             }
-        } else {
-            // No pattern - return all triples
-            for graph in self.triples.values() {
-                for stored in graph {
-                    candidates.push(stored);
-                }
+            if let Some(o) = object {
+                if stored.triple.object_term() != *o { return false; }
             }
+            true
+        }).collect()
+    }
+
+    /// Find triples matching a pattern
+    pub fn find_triples(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Vec<&StoredTriple> {
+        self.find_triples_iter(subject, predicate, object).collect()
+    }
+
+    /// Lazily iterate over the triples matching a pattern
+    ///
+    /// 最も選択的なインデックスから候補を 1 件ずつ取り出すため、`take` や `next` で
+    /// 途中で打ち切れば残りの候補は読まない (SPARQL の LIMIT の押し下げに使う)
+    pub fn find_triples_iter<'a, 'p>(&'a self, subject: Option<&'p str>, predicate: Option<&'p str>, object: Option<&'p str>) -> impl Iterator<Item = &'a StoredTriple> + 'p
+    where
+        'a: 'p,
+    {
+        // Use the most selective index
+        let indices = match (subject, predicate, object) {
+            (Some(subj), _, _) => Some(self.subject_index.get(subj)),
+            (None, Some(pred), _) => Some(self.predicate_index.get(pred)),
+            (None, None, Some(obj)) => Some(self.object_index.get(obj)),
+            (None, None, None) => None,
+        };
+        let candidates: Box<dyn Iterator<Item = &'a StoredTriple> + 'a> = match indices {
+            Some(indices) => Box::new(indices.into_iter().flatten().filter_map(move |(graph_id, idx)| {
+                self.triples.get(graph_id).and_then(|graph| graph.get(*idx))
+            })),
+            // No pattern - every triple
+            None => Box::new(self.triples.values().flatten()),
+        };
+
+        let sampled = self.access.sample();
+        if sampled {
+            self.access.record_lookup(subject, predicate);
         }
 
         // Filter candidates by remaining constraints
-        candidates.into_iter().filter(|stored| {
-            if let Some(s) = subject {
-                if stored.triple.subject != s { return false; }
+        candidates.filter(move |stored| {
+            subject.is_none_or(|s| stored.triple.subject == s)
+                && predicate.is_none_or(|p| stored.triple.predicate == p)
+                && object.is_none_or(|o| stored.triple.object == o)
+        })
+        .inspect(move |stored| {
+            if sampled {
+                self.access.record_reads(&stored.graph_id, 1);
             }
-            if let Some(p) = predicate {
-                if stored.triple.predicate != p { return false; }
+        })
+    }
+
+    /// Stream the triples matching a pattern for async pipelines
+    ///
+    /// [`find_triples_iter`](Self::find_triples_iter) を包んだだけのストリームで、
+    /// 消費側が止まれば走査も止まる
+    #[cfg(feature = "stream")]
+    pub fn find_triples_stream<'a, 'p>(&'a self, subject: Option<&'p str>, predicate: Option<&'p str>, object: Option<&'p str>) -> impl futures::Stream<Item = &'a StoredTriple> + 'p
+    where
+        'a: 'p,
+    {
+        futures::stream::iter(self.find_triples_iter(subject, predicate, object))
+    }
+
+    /// Page through the distinct triples matching a pattern in (subject, predicate, object) order
+    ///
+    /// `after` より大きいキーのうち小さい順に `limit` 件だけを保持しながら走査するため、
+    /// 一致した全件を整列しない。複数のグラフにある同じトリプルは 1 件として数える
+    pub fn find_triples_page(
+        &self,
+        subject: Option<&str>,
+        predicate: Option<&str>,
+        object: Option<&str>,
+        after: Option<(&str, &str, &str)>,
+        limit: usize,
+    ) -> Vec<Triple> {
+        let mut page: BTreeSet<(&str, &str, &str)> = BTreeSet::new();
+        if limit == 0 {
+            return Vec::new();
+        }
+        for stored in self.find_triples_iter(subject, predicate, object) {
+            let key = (stored.triple.subject.as_str(), stored.triple.predicate.as_str(), stored.triple.object.as_str());
+            if after.is_some_and(|after| key <= after) || page.contains(&key) {
+                continue;
             }
-            if let Some(o) = object {
-                if stored.triple.object != o { return false; }
+            if page.len() == limit {
+                match page.last() {
+                    Some(largest) if key < *largest => {
+                        page.pop_last();
+                    }
+                    _ => continue,
+                }
             }
-            true
-        }).collect()
+            page.insert(key);
+        }
+        page.into_iter()
+            .map(|(s, p, o)| Triple { subject: s.to_string(), predicate: p.to_string(), object: o.to_string() })
+            .collect()
     }
 
     /// Get all triples in a specific graph
     pub fn get_graph(&self, graph_id: &GraphId) -> Vec<&StoredTriple> {
-        self.triples.get(graph_id)
+        let graph: Vec<&StoredTriple> = self.triples.get(graph_id)
             .map(|graph| graph.iter().collect())
-            .unwrap_or_default()
+            .unwrap_or_default();
+        if self.access.sample() {
+            self.access.record_reads(graph_id, graph.len() as u64);
+        }
+        graph
     }
 
     /// Get all graph IDs
@@ -178,13 +401,137 @@ impl RdfStore {
         self.triples.keys().collect()
     }
 
+    /// Remove a triple from a graph; returns the number of removed copies
+    pub fn remove_triple(&mut self, triple: &Triple, graph_id: &GraphId) -> usize {
+        let first = match self.triples.get(graph_id).and_then(|graph| graph.iter().position(|stored| &stored.triple == triple)) {
+            Some(first) => first,
+            None => return 0,
+        };
+
+        // 挿入順を保つため、最初に消す位置より後ろの索引だけを付け替える
+        self.unindex_from(graph_id, first);
+        let graph = self.triples.get_mut(graph_id).expect("graph holds the removed triple");
+        let before = graph.len();
+        graph.retain(|stored| &stored.triple != triple);
+        let removed = before - graph.len();
+        self.index_from(graph_id, first);
+
+        self.access.record_writes(graph_id, removed as u64);
+        self.log_wal(WalOperation::Delete { triple: triple.clone(), graph_id: graph_id.clone() });
+
+        self.invalidate_segment(graph_id);
+        if self.triples.get(graph_id).is_some_and(|g| g.is_empty()) {
+            self.triples.remove(graph_id);
+        }
+
+        self.add_audit_entry(AuditEntry {
+            id: format!("audit-{}", std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            operation: AuditOperation::Delete {
+                triple: triple.clone(),
+                graph_id: graph_id.clone(),
+            },
+            actor: None,
+            metadata: HashMap::new(),
+        });
+
+        removed
+    }
+
+    /// Remove the triples of a graph selected by `(position, triple)`; returns the removed triples
+    ///
+    /// グラフが空になれば Clear、そうでなければ削除したトリプルごとに Delete を監査ログに記録し、
+    /// `reason` をメタデータに残す。WAL の Delete は同じトリプルの全コピーを消すため、
+    /// 残したコピーは直後に Insert として記録し直す
+    pub fn remove_where(&mut self, graph_id: &GraphId, mut selected: impl FnMut(usize, &StoredTriple) -> bool, reason: &str) -> Vec<StoredTriple> {
+        let graph = match self.triples.get_mut(graph_id) {
+            Some(graph) => graph,
+            None => return Vec::new(),
+        };
+        let mut removed = Vec::new();
+        let mut kept = Vec::with_capacity(graph.len());
+        for (position, stored) in graph.drain(..).enumerate() {
+            if selected(position, &stored) {
+                removed.push(stored);
+            } else {
+                kept.push(stored);
+            }
+        }
+        *graph = kept;
+        if removed.is_empty() {
+            return removed;
+        }
+
+        self.invalidate_segment(graph_id);
+        self.access.record_writes(graph_id, removed.len() as u64);
+        let cleared = self.triples.get(graph_id).is_none_or(|g| g.is_empty());
+        if cleared {
+            self.triples.remove(graph_id);
+            self.log_wal(WalOperation::ClearGraph { graph_id: graph_id.clone() });
+        } else if self.logs_mutations() {
+            let removed_triples: HashSet<&InternedTriple> = removed.iter().map(|stored| &stored.triple).collect();
+            let survivors: Vec<StoredTriple> = self.triples.get(graph_id).into_iter().flatten()
+                .filter(|stored| removed_triples.contains(&stored.triple))
+                .cloned()
+                .collect();
+            let mut logged = HashSet::new();
+            let deletes: Vec<Triple> = removed.iter()
+                .filter(|stored| logged.insert(&stored.triple))
+                .map(|stored| stored.triple.to_triple())
+                .collect();
+            for triple in deletes {
+                self.log_wal(WalOperation::Delete { triple, graph_id: graph_id.clone() });
+            }
+            for stored in survivors {
+                self.log_wal(WalOperation::Insert { stored });
+            }
+        }
+        self.rebuild_indices();
+
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        let metadata: HashMap<String, serde_json::Value> = [("reason".to_string(), serde_json::Value::from(reason))].into_iter().collect();
+        let operations: Vec<AuditOperation> = if cleared {
+            vec![AuditOperation::Clear { graph_id: graph_id.clone(), triple_count: removed.len() }]
+        } else {
+            removed.iter()
+                .map(|stored| AuditOperation::Delete {
+                    triple: stored.triple.to_triple(),
+                    graph_id: graph_id.clone(),
+                })
+                .collect()
+        };
+        for (sequence, operation) in operations.into_iter().enumerate() {
+            self.add_audit_entry(AuditEntry {
+                id: format!("audit-{}-{}", now.as_nanos(), sequence),
+                timestamp: now.as_millis() as u64,
+                operation,
+                actor: None,
+                metadata: metadata.clone(),
+            });
+        }
+
+        removed
+    }
+
     /// Clear a specific graph
     pub fn clear_graph(&mut self, graph_id: &GraphId) {
         if let Some(graph) = self.triples.remove(graph_id) {
             let count = graph.len();
+            drop(graph);
+            self.invalidate_segment(graph_id);
+            self.access.record_writes(graph_id, count as u64);
+            self.log_wal(WalOperation::ClearGraph { graph_id: graph_id.clone() });
 
             // Remove from indices
             self.rebuild_indices();
+            // 削除したトリプルだけが使っていた文字列をプールから外す
+            InternedString::release_unused();
 
         // Audit trail with memory management
         self.add_audit_entry(AuditEntry {
@@ -209,11 +556,18 @@ impl RdfStore {
     /// Clear all graphs
     pub fn clear_all(&mut self) {
         let total_count: usize = self.triples.values().map(|g| g.len()).sum();
+        self.log_wal(WalOperation::ClearAll);
+        for (graph_id, graph) in &self.triples {
+            self.access.record_writes(graph_id, graph.len() as u64);
+        }
 
         self.triples.clear();
+        self.segments().clear();
+        self.touch();
         self.subject_index.clear();
         self.predicate_index.clear();
         self.object_index.clear();
+        InternedString::release_unused();
 
         // Audit trail with memory management
         self.add_audit_entry(AuditEntry {
@@ -234,20 +588,59 @@ impl RdfStore {
         });
     }
 
+    /// Sensor heartbeat registry (survives graph clears)
+    pub fn sensor_registry(&self) -> &SensorRegistry {
+        &self.sensor_registry
+    }
+
+    /// Rewrite the registry graph from the current sensor registry
+    pub fn materialize_sensor_registry(&mut self) {
+        let graph_id = GraphId::Named(SENSOR_REGISTRY_GRAPH.to_string());
+        self.clear_graph(&graph_id);
+
+        let triples = self.sensor_registry.to_triples();
+        let provenance = Provenance::Imported {
+            source_uri: format!("urn:fukurow:{}", SENSOR_REGISTRY_GRAPH),
+            imported_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        self.insert_batch(triples, graph_id, provenance);
+    }
+
     /// Get audit trail
     pub fn audit_trail(&self) -> &[AuditEntry] {
         &self.audit_trail
     }
 
     /// Get statistics
+    ///
+    /// 項の統計は索引のキー (= ストア内の異なる項) から数えるため、トリプル数ではなく項の種類数に比例する
     pub fn statistics(&self) -> StoreStatistics {
         let total_triples: usize = self.triples.values().map(|g| g.len()).sum();
         let graph_count = self.triples.len();
 
+        let mut distinct: HashSet<&str> = HashSet::new();
+        let mut term_bytes = 0;
+        let mut referenced_term_bytes = 0;
+        for index in [&self.subject_index, &self.predicate_index, &self.object_index] {
+            for (term, postings) in index {
+                referenced_term_bytes += term.len() * postings.len();
+                if distinct.insert(term.as_str()) {
+                    term_bytes += term.len();
+                }
+            }
+        }
+
         StoreStatistics {
             total_triples,
             graph_count,
             audit_entries: self.audit_trail.len(),
+            distinct_terms: distinct.len(),
+            term_bytes,
+            interning_saved_bytes: referenced_term_bytes.saturating_sub(term_bytes),
+            access: self.access_statistics(),
         }
     }
 
@@ -256,19 +649,84 @@ impl RdfStore {
         &self.triples
     }
 
+    /// Take a consistent, immutable view of every graph
+    ///
+    /// グラフごとのセグメントを `Arc` で共有するため、前回のスナップショット以降に
+    /// 変更されていないグラフはコピーしない。スナップショットの複製も `Arc` の複製だけで済む
+    pub fn snapshot(&self) -> StoreSnapshot {
+        let mut segments = self.snapshot_segments.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let graphs = self.triples.iter()
+            .map(|(graph_id, graph)| {
+                let segment = segments.entry(graph_id.clone())
+                    .or_insert_with(|| Arc::new(graph.clone()));
+                (graph_id.clone(), Arc::clone(segment))
+            })
+            .collect();
+        let taken_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        StoreSnapshot::new(graphs, taken_at)
+            .with_wal_sequence(self.wal_sequence())
+            .with_revision(self.revision, self.modified_at)
+    }
+
+    /// Read-only copy of a snapshot: triples and indices, without audit trail, WAL or sinks
+    ///
+    /// 監査ログには何も記録しない。セグメントはスナップショットと共有するため、
+    /// 複製したストアの `snapshot()` はコピーを伴わない。`access` には primary の
+    /// プロファイラを渡し、レプリカで実行したクエリも primary の統計に数える
+    pub(crate) fn replica_of(snapshot: &StoreSnapshot, access: Arc<AccessProfiler>) -> Self {
+        let mut store = Self::new();
+        store.access = access;
+        for (graph_id, segment) in snapshot.segments() {
+            store.triples.insert(graph_id.clone(), segment.as_ref().clone());
+        }
+        store.rebuild_indices();
+        *store.segments() = snapshot.segments().clone();
+        store.revision = snapshot.revision();
+        store.modified_at = snapshot.modified_at();
+        store
+    }
+
     /// Get audit trail (for serialization)
     pub fn get_audit_trail(&self) -> &[AuditEntry] {
         &self.audit_trail
     }
 
+    fn logs_mutations(&self) -> bool {
+        self.wal.is_some() || self.replication.is_some()
+    }
+
+    fn log_wal(&mut self, operation: WalOperation) {
+        if let Some(log) = self.replication.as_mut() {
+            log.record(operation.clone());
+        }
+        if let Some(wal) = self.wal.as_mut() {
+            if wal.append(operation).is_err() {
+                self.wal_failures += 1;
+            }
+        }
+    }
+
     /// Add audit entry with memory management
-    fn add_audit_entry(&mut self, entry: AuditEntry) {
+    fn add_audit_entry(&mut self, mut entry: AuditEntry) {
+        if entry.actor.is_none() {
+            entry.actor = self.actor.clone();
+        }
+        if let Some(sink) = self.audit_sink.as_mut() {
+            if sink.record(&entry).is_err() {
+                self.audit_sink_failures += 1;
+            }
+        }
+
         self.audit_trail.push(entry);
 
         // Memory management: remove oldest entries if over limit
         if self.audit_trail.len() > self.max_audit_entries {
             let remove_count = self.audit_trail.len() - self.max_audit_entries;
             self.audit_trail.drain(0..remove_count);
+            self.audit_pruned = true;
         }
     }
 
@@ -280,6 +738,65 @@ impl RdfStore {
         if self.audit_trail.len() > limit {
             let remove_count = self.audit_trail.len() - limit;
             self.audit_trail.drain(0..remove_count);
+            self.audit_pruned = true;
+        }
+    }
+
+    /// Whether the audit history is incomplete (no sink, and old entries were dropped from the in-memory trail)
+    pub fn audit_history_pruned(&self) -> bool {
+        self.audit_sink.is_none() && self.audit_pruned
+    }
+
+    fn segments(&mut self) -> &mut HashMap<GraphId, Arc<Vec<StoredTriple>>> {
+        self.snapshot_segments.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Drop the shared segment of a graph that is about to change
+    fn invalidate_segment(&mut self, graph_id: &GraphId) {
+        self.segments().remove(graph_id);
+        self.touch();
+    }
+
+    /// Record a change to the triples
+    fn touch(&mut self) {
+        self.revision += 1;
+        self.modified_at = Some(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64);
+    }
+
+    /// Remove the index entries of the triples of `graph_id` at `from` and later positions
+    fn unindex_from(&mut self, graph_id: &GraphId, from: usize) {
+        let Some(graph) = self.triples.get(graph_id) else { return };
+        for (position, stored) in graph.iter().enumerate().skip(from) {
+            let entry = (graph_id.clone(), position);
+            for (index, term) in [
+                (&mut self.subject_index, &stored.triple.subject),
+                (&mut self.predicate_index, &stored.triple.predicate),
+                (&mut self.object_index, &stored.triple.object),
+            ] {
+                if let Some(positions) = index.get_mut(term) {
+                    positions.remove(&entry);
+                    if positions.is_empty() {
+                        index.remove(term);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Index the triples of `graph_id` at `from` and later positions
+    fn index_from(&mut self, graph_id: &GraphId, from: usize) {
+        let Some(graph) = self.triples.get(graph_id) else { return };
+        for (position, stored) in graph.iter().enumerate().skip(from) {
+            for (index, term) in [
+                (&mut self.subject_index, &stored.triple.subject),
+                (&mut self.predicate_index, &stored.triple.predicate),
+                (&mut self.object_index, &stored.triple.object),
+            ] {
+                index.entry(term.clone()).or_default().insert((graph_id.clone(), position));
+            }
         }
     }
 
@@ -311,6 +828,18 @@ pub struct StoreStatistics {
     pub total_triples: usize,
     pub graph_count: usize,
     pub audit_entries: usize,
+    /// Distinct subjects, predicates and objects (each string is held once)
+    #[serde(default)]
+    pub distinct_terms: usize,
+    /// Bytes of the distinct term strings
+    #[serde(default)]
+    pub term_bytes: usize,
+    /// String bytes saved by sharing terms instead of copying them into every triple
+    #[serde(default)]
+    pub interning_saved_bytes: usize,
+    /// Sampled lookup load, including queries served by read replicas
+    #[serde(default)]
+    pub access: AccessStatistics,
 }
 
 impl Default for RdfStore {
@@ -0,0 +1,96 @@
+//! Tenant identifiers
+//!
+//! 1 つのデプロイメントで複数の顧客環境をホストする場合、テナントごとに独立した
+//! ストアを持たせて分離する。テナント ID はディレクトリ名やヘッダ値にそのまま
+//! 使えるよう、英数字と `-` `_` `.` のみに制限する
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Validated tenant identifier
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TenantId(String);
+
+/// Invalid tenant identifier
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid tenant id '{0}': expected 1-64 characters of [A-Za-z0-9_.-]")]
+pub struct TenantIdError(pub String);
+
+impl TenantId {
+    /// Tenant used when no tenant is specified (single-tenant deployments)
+    pub const DEFAULT: &'static str = "default";
+    pub const MAX_LEN: usize = 64;
+
+    pub fn new(id: impl Into<String>) -> Result<Self, TenantIdError> {
+        let id = id.into();
+        let valid_chars = id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        // "." / ".." はパスとして解釈されてしまうため拒否する
+        if id.is_empty() || id.len() > Self::MAX_LEN || !valid_chars || id.chars().all(|c| c == '.') {
+            return Err(TenantIdError(id));
+        }
+        Ok(Self(id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.0 == Self::DEFAULT
+    }
+}
+
+impl Default for TenantId {
+    fn default() -> Self {
+        Self(Self::DEFAULT.to_string())
+    }
+}
+
+impl fmt::Display for TenantId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for TenantId {
+    type Err = TenantIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for TenantId {
+    type Error = TenantIdError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<TenantId> for String {
+    fn from(tenant: TenantId) -> Self {
+        tenant.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_id_validation() {
+        assert_eq!(TenantId::new("acme-prod_1.eu").unwrap().as_str(), "acme-prod_1.eu");
+        assert!(TenantId::default().is_default());
+
+        for invalid in ["", "..", "acme/../other", "a b", &"x".repeat(TenantId::MAX_LEN + 1)] {
+            assert_eq!(invalid.parse::<TenantId>(), Err(TenantIdError(invalid.to_string())));
+        }
+
+        let parsed: TenantId = serde_json::from_str("\"acme\"").unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), "\"acme\"");
+        assert!(serde_json::from_str::<TenantId>("\"../etc\"").is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Ontology vocabulary metadata
+//!
+//! ストアに読み込まれたオントロジーからクラス・プロパティの一覧
+//! (ラベル、コメント、定義域/値域、使用回数) を抽出し、
+//! クエリビルダー UI やルール作成 UI の補完候補として提供する
+
+use crate::store::RdfStore;
+use fukurow_core::prefix::PrefixMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDF_PROPERTY: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#Property";
+const RDFS_CLASS: &str = "http://www.w3.org/2000/01/rdf-schema#Class";
+const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+const RDFS_COMMENT: &str = "http://www.w3.org/2000/01/rdf-schema#comment";
+const RDFS_DOMAIN: &str = "http://www.w3.org/2000/01/rdf-schema#domain";
+const RDFS_RANGE: &str = "http://www.w3.org/2000/01/rdf-schema#range";
+const OWL_CLASS: &str = "http://www.w3.org/2002/07/owl#Class";
+const OWL_OBJECT_PROPERTY: &str = "http://www.w3.org/2002/07/owl#ObjectProperty";
+const OWL_DATATYPE_PROPERTY: &str = "http://www.w3.org/2002/07/owl#DatatypeProperty";
+const OWL_ANNOTATION_PROPERTY: &str = "http://www.w3.org/2002/07/owl#AnnotationProperty";
+
+/// Namespaces whose terms are never offered as instance classes
+const META_NAMESPACES: [&str; 3] = [
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#",
+    "http://www.w3.org/2000/01/rdf-schema#",
+    "http://www.w3.org/2002/07/owl#",
+];
+
+/// Kind of vocabulary term
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TermKind {
+    Class,
+    ObjectProperty,
+    DatatypeProperty,
+    AnnotationProperty,
+    /// rdf:Property, or a predicate used without a declaration
+    Property,
+}
+
+impl TermKind {
+    fn from_declaration(rdf_type: &str) -> Option<Self> {
+        match rdf_type {
+            OWL_CLASS | RDFS_CLASS => Some(TermKind::Class),
+            OWL_OBJECT_PROPERTY => Some(TermKind::ObjectProperty),
+            OWL_DATATYPE_PROPERTY => Some(TermKind::DatatypeProperty),
+            OWL_ANNOTATION_PROPERTY => Some(TermKind::AnnotationProperty),
+            RDF_PROPERTY => Some(TermKind::Property),
+            _ => None,
+        }
+    }
+
+    pub fn is_property(&self) -> bool {
+        !matches!(self, TermKind::Class)
+    }
+}
+
+impl std::str::FromStr for TermKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "class" => Ok(TermKind::Class),
+            "object_property" => Ok(TermKind::ObjectProperty),
+            "datatype_property" => Ok(TermKind::DatatypeProperty),
+            "annotation_property" => Ok(TermKind::AnnotationProperty),
+            "property" => Ok(TermKind::Property),
+            other => Err(format!("Unknown term kind: {}", other)),
+        }
+    }
+}
+
+/// Metadata for a single class or property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OntologyTerm {
+    pub iri: String,
+    pub kind: TermKind,
+    /// Fragment or last path segment of the IRI
+    pub local_name: String,
+    pub label: Option<String>,
+    pub comment: Option<String>,
+    /// rdfs:domain (properties only)
+    pub domains: Vec<String>,
+    /// rdfs:range (properties only)
+    pub ranges: Vec<String>,
+    /// Instances (classes) or asserting triples (properties) in the store
+    pub usage_count: usize,
+}
+
+/// Fragment or last path segment of an IRI
+pub fn local_name(iri: &str) -> &str {
+    iri.rsplit(['#', '/', ':']).next().filter(|name| !name.is_empty()).unwrap_or(iri)
+}
+
+/// Lexical form of a literal (`"Host"@en` → `Host`)
+fn literal_value(literal: &str) -> String {
+    match literal.strip_prefix('"').and_then(|rest| rest.rfind('"').map(|end| &rest[..end])) {
+        Some(value) => value.to_string(),
+        None => literal.to_string(),
+    }
+}
+
+#[derive(Default)]
+struct TermBuilder {
+    kinds: BTreeSet<TermKind>,
+    label: Option<String>,
+    comment: Option<String>,
+    domains: BTreeSet<String>,
+    ranges: BTreeSet<String>,
+}
+
+impl RdfStore {
+    /// Classes and properties declared in (or used by) the store, sorted by IRI
+    pub fn ontology_terms(&self) -> Vec<OntologyTerm> {
+        let mut terms: BTreeMap<String, TermBuilder> = BTreeMap::new();
+        let mut instance_counts: HashMap<String, usize> = HashMap::new();
+        let mut predicate_counts: HashMap<String, usize> = HashMap::new();
+
+        for stored in self.all_triples().values().flatten() {
+            let triple = &stored.triple;
+            *predicate_counts.entry(triple.predicate.to_string()).or_insert(0) += 1;
+
+            match triple.predicate.as_str() {
+                RDF_TYPE => match TermKind::from_declaration(&triple.object) {
+                    Some(kind) => {
+                        terms.entry(triple.subject.to_string()).or_default().kinds.insert(kind);
+                    }
+                    None => *instance_counts.entry(triple.object.to_string()).or_insert(0) += 1,
+                },
+                RDFS_LABEL => {
+                    let term = terms.entry(triple.subject.to_string()).or_default();
+                    term.label.get_or_insert_with(|| literal_value(&triple.object));
+                }
+                RDFS_COMMENT => {
+                    let term = terms.entry(triple.subject.to_string()).or_default();
+                    term.comment.get_or_insert_with(|| literal_value(&triple.object));
+                }
+                RDFS_DOMAIN => {
+                    terms.entry(triple.subject.to_string()).or_default().domains.insert(triple.object.to_string());
+                }
+                RDFS_RANGE => {
+                    terms.entry(triple.subject.to_string()).or_default().ranges.insert(triple.object.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        // 宣言のない型・述語も使用されていれば候補に含める
+        let is_meta = |iri: &str| META_NAMESPACES.iter().any(|ns| iri.starts_with(ns));
+        for class in instance_counts.keys().filter(|iri| !is_meta(iri)) {
+            let term = terms.entry(class.clone()).or_default();
+            if term.kinds.is_empty() {
+                term.kinds.insert(TermKind::Class);
+            }
+        }
+        for predicate in predicate_counts.keys().filter(|iri| !is_meta(iri)) {
+            let term = terms.entry(predicate.clone()).or_default();
+            if term.kinds.is_empty() {
+                term.kinds.insert(TermKind::Property);
+            }
+        }
+
+        let mut result = Vec::new();
+        for (iri, builder) in terms {
+            // ドメイン/レンジだけが記述された項目はプロパティとみなす
+            let mut kinds = builder.kinds;
+            if kinds.is_empty() && !(builder.domains.is_empty() && builder.ranges.is_empty()) {
+                kinds.insert(TermKind::Property);
+            }
+
+            for kind in kinds {
+                let usage_count = if kind.is_property() {
+                    predicate_counts.get(&iri).copied().unwrap_or(0)
+                } else {
+                    instance_counts.get(&iri).copied().unwrap_or(0)
+                };
+                result.push(OntologyTerm {
+                    local_name: local_name(&iri).to_string(),
+                    iri: iri.clone(),
+                    kind,
+                    label: builder.label.clone(),
+                    comment: builder.comment.clone(),
+                    domains: if kind.is_property() { builder.domains.iter().cloned().collect() } else { Vec::new() },
+                    ranges: if kind.is_property() { builder.ranges.iter().cloned().collect() } else { Vec::new() },
+                    usage_count,
+                });
+            }
+        }
+        result
+    }
+}
+
+/// Rank terms matching `query` (case-insensitive) for auto-completion
+///
+/// 完全一致 > 前方一致 > 部分一致 の順に並べ、同順位では使用回数の多いものを優先する
+pub fn search_terms(terms: Vec<OntologyTerm>, query: Option<&str>, kind: Option<TermKind>) -> Vec<OntologyTerm> {
+    let query = query.map(|q| q.trim().to_lowercase()).filter(|q| !q.is_empty());
+
+    let mut ranked: Vec<(u8, OntologyTerm)> = terms.into_iter()
+        .filter(|term| kind.map(|k| term.kind == k).unwrap_or(true))
+        .filter_map(|term| {
+            let query = match &query {
+                Some(query) => query,
+                None => return Some((0, term)),
+            };
+            let candidates = [Some(term.local_name.to_lowercase()), term.label.as_ref().map(|l| l.to_lowercase())];
+            let rank = candidates.iter().flatten()
+                .filter_map(|candidate| {
+                    if candidate == query {
+                        Some(0)
+                    } else if candidate.starts_with(query.as_str()) {
+                        Some(1)
+                    } else if candidate.contains(query.as_str()) {
+                        Some(2)
+                    } else {
+                        None
+                    }
+                })
+                .min()
+                .or_else(|| term.iri.to_lowercase().contains(query.as_str()).then_some(3))?;
+            Some((rank, term))
+        })
+        .collect();
+
+    ranked.sort_by(|(rank_a, a), (rank_b, b)| {
+        rank_a.cmp(rank_b)
+            .then(b.usage_count.cmp(&a.usage_count))
+            .then(a.iri.cmp(&b.iri))
+    });
+    ranked.into_iter().map(|(_, term)| term).collect()
+}
+
+/// Auto-completion candidate for rule and query authoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TermCompletion {
+    pub iri: String,
+    /// `prefix:local` form when a known prefix covers the IRI
+    pub curie: Option<String>,
+    pub kind: TermKind,
+    pub label: Option<String>,
+    pub usage_count: usize,
+}
+
+/// Complete a typed prefix, CURIE or partial IRI
+///
+/// `http://...` (`<` 付きも可) は IRI の前方一致、`ex:Ho` のように既知の接頭辞で始まる入力は
+/// 展開した IRI の前方一致 (ローカル名は大文字小文字を区別しない)、それ以外はローカル名か
+/// ラベルの前方一致。ローカル名が入力と一致する候補を先頭に、あとは使用回数の多い順に並べる
+pub fn complete_terms(terms: Vec<OntologyTerm>, input: &str, prefixes: &PrefixMap, kind: Option<TermKind>) -> Vec<TermCompletion> {
+    let input = input.trim().trim_start_matches('<');
+    let is_iri = input.contains("://") || input.starts_with("urn:");
+    let curie = input.split_once(':').and_then(|(prefix, local)| Some((prefixes.get(prefix)?, local.to_lowercase())));
+    let text = input.to_lowercase();
+
+    let mut ranked: Vec<(bool, TermCompletion)> = terms.into_iter()
+        .filter(|term| kind.map(|k| term.kind == k).unwrap_or(true))
+        .filter_map(|term| {
+            let (matches, exact) = match &curie {
+                _ if is_iri => (term.iri.starts_with(input), term.iri == input),
+                Some((namespace, local)) => match term.iri.strip_prefix(namespace) {
+                    Some(rest) => (rest.to_lowercase().starts_with(local.as_str()), rest.to_lowercase() == *local),
+                    None => (false, false),
+                },
+                None => {
+                    let label = term.label.as_deref().map(str::to_lowercase);
+                    let name = term.local_name.to_lowercase();
+                    (name.starts_with(&text) || label.is_some_and(|label| label.starts_with(&text)), name == text)
+                }
+            };
+            matches.then(|| (exact, TermCompletion {
+                curie: prefixes.compact(&term.iri),
+                iri: term.iri,
+                kind: term.kind,
+                label: term.label,
+                usage_count: term.usage_count,
+            }))
+        })
+        .collect();
+
+    ranked.sort_by(|(exact_a, a), (exact_b, b)| {
+        exact_b.cmp(exact_a)
+            .then(b.usage_count.cmp(&a.usage_count))
+            .then(a.iri.cmp(&b.iri))
+            .then(a.kind.cmp(&b.kind))
+    });
+    ranked.into_iter().map(|(_, completion)| completion).collect()
+}
@@ -0,0 +1,284 @@
+//! Write-ahead log
+//!
+//! スナップショット間の変更をクラッシュで失わないよう、RdfStore への挿入・削除・クリアを
+//! 来歴ごと追記専用ファイル (JSON Lines) に記録する。
+//! - 起動時: 最新スナップショットを読み込み、そのスナップショットより新しいレコードだけを再生する
+//! - コンパクション: スナップショットの保存に成功したら、含まれるレコードをログから取り除く
+//!
+//! 書き込み途中でクラッシュした末尾の不完全な行は、開き直した時点で切り捨てる
+
+use crate::persistence::PersistenceError;
+use crate::provenance::GraphId;
+use crate::store::{RdfStore, StoredTriple};
+use fukurow_core::model::Triple;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// One logged mutation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalRecord {
+    /// Monotonic sequence number (starts at 1, survives compaction)
+    pub sequence: u64,
+    /// When the record was written (Unix timestamp in milliseconds)
+    pub timestamp: u64,
+    pub operation: WalOperation,
+}
+
+/// Logged store mutation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WalOperation {
+    /// Triple inserted (with graph, provenance and assertion time)
+    Insert { stored: StoredTriple },
+    Delete { triple: Triple, graph_id: GraphId },
+    ClearGraph { graph_id: GraphId },
+    ClearAll,
+    /// Written by compaction; keeps the sequence number of the records it removed
+    Checkpoint,
+}
+
+impl WalOperation {
+    /// Apply the mutation to a store
+    pub fn apply(&self, store: &mut RdfStore) {
+        match self {
+            WalOperation::Insert { stored } => store.insert_at(
+                stored.triple.clone(),
+                stored.graph_id.clone(),
+                stored.provenance.clone(),
+                stored.asserted_at,
+            ),
+            WalOperation::Delete { triple, graph_id } => {
+                store.remove_triple(triple, graph_id);
+            }
+            WalOperation::ClearGraph { graph_id } => store.clear_graph(graph_id),
+            WalOperation::ClearAll => store.clear_all(),
+            WalOperation::Checkpoint => {}
+        }
+    }
+}
+
+/// Append-only log file of store mutations
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: File,
+    last_sequence: u64,
+    /// fsync after every record
+    sync: bool,
+}
+
+impl WriteAheadLog {
+    /// Open (or create) a log, discarding a torn trailing record
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, PersistenceError> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let (records, valid_len) = if path.exists() { read_records(&path)? } else { (Vec::new(), 0) };
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() > valid_len {
+            file.set_len(valid_len)?;
+        }
+        // 改行の直前で途切れた最後のレコードは有効なので、行を閉じてから追記する
+        if valid_len > 0 && !ends_with_newline(&path, valid_len)? {
+            file.write_all(b"\n")?;
+        }
+
+        let last_sequence = records.iter().map(|record| record.sequence).max().unwrap_or(0);
+        Ok(Self { path, file, last_sequence, sync: false })
+    }
+
+    pub fn with_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sequence number of the last written record (0 when nothing was logged)
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Append a record; returns its sequence number
+    pub fn append(&mut self, operation: WalOperation) -> Result<u64, PersistenceError> {
+        let record = WalRecord { sequence: self.last_sequence + 1, timestamp: now_millis(), operation };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        // 1 回の write で書き、途中で失敗したレコードは次回 open 時に切り捨てられる
+        self.file.write_all(&line)?;
+        if self.sync {
+            self.file.sync_data()?;
+        }
+        self.last_sequence = record.sequence;
+        Ok(record.sequence)
+    }
+
+    /// Every record currently in the log
+    pub fn records(&self) -> Result<Vec<WalRecord>, PersistenceError> {
+        Ok(read_records(&self.path)?.0)
+    }
+
+    /// Apply the records newer than `after` (a snapshot's WAL sequence); returns how many were applied
+    pub fn replay(&self, store: &mut RdfStore, after: u64) -> Result<usize, PersistenceError> {
+        let mut applied = 0;
+        for record in self.records()?.into_iter().filter(|record| record.sequence > after) {
+            record.operation.apply(store);
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Drop the records up to `sequence` once a snapshot holding them has been saved
+    ///
+    /// 一時ファイルへ書いてから rename するため、途中でクラッシュしても元のログが残る。
+    /// 削除したレコードの代わりに Checkpoint を残し、開き直しても連番が巻き戻らないようにする
+    pub fn compact_through(&mut self, sequence: u64) -> Result<usize, PersistenceError> {
+        let records = self.records()?;
+        let before = records.len();
+        let kept: Vec<WalRecord> = records.into_iter().filter(|record| record.sequence > sequence).collect();
+
+        let tmp = self.path.with_extension("compact.tmp");
+        let mut writer = std::io::BufWriter::new(File::create(&tmp)?);
+        let checkpoint = WalRecord { sequence, timestamp: now_millis(), operation: WalOperation::Checkpoint };
+        for record in std::iter::once(&checkpoint).chain(kept.iter()) {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+        std::fs::rename(&tmp, &self.path)?;
+
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(before - kept.len())
+    }
+}
+
+/// Parse records; returns them with the byte length of the valid prefix
+fn read_records(path: &Path) -> Result<(Vec<WalRecord>, u64), PersistenceError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut records = Vec::new();
+    let mut valid_len = 0u64;
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        let complete = line.ends_with('\n');
+        let text = line.trim();
+        if text.is_empty() {
+            valid_len += read as u64;
+            continue;
+        }
+        match serde_json::from_str::<WalRecord>(text) {
+            Ok(record) => {
+                records.push(record);
+                valid_len += read as u64;
+            }
+            // 改行で終わらない末尾の行は書き込み途中のクラッシュとみなす
+            Err(_) if !complete => break,
+            Err(e) => return Err(PersistenceError::Parse { line: line_number, message: e.to_string() }),
+        }
+    }
+    Ok((records, valid_len))
+}
+
+fn ends_with_newline(path: &Path, len: u64) -> Result<bool, PersistenceError> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(len - 1))?;
+    let mut last = [0u8; 1];
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{PersistenceManager, SnapshotFormat};
+    use crate::provenance::Provenance;
+
+    fn triple(subject: &str) -> Triple {
+        Triple {
+            subject: subject.to_string(),
+            predicate: "http://example.org/connectsTo".to_string(),
+            object: "http://example.org/c2".to_string(),
+        }
+    }
+
+    fn sensor() -> Provenance {
+        Provenance::Sensor { source: "edr".to_string(), confidence: Some(0.7) }
+    }
+
+    #[test]
+    fn test_recover_replays_records_after_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("fukurow-wal-{}", std::process::id()));
+        let manager = PersistenceManager::new(dir.join("snapshots")).with_format(SnapshotFormat::Json);
+        let wal_path = dir.join("store.wal");
+
+        let mut store = RdfStore::new();
+        store.attach_wal(WriteAheadLog::open(&wal_path).unwrap());
+        store.insert(triple("http://example.org/h1"), GraphId::Default, sensor());
+        store.insert(triple("http://example.org/h2"), GraphId::Default, sensor());
+        let info = manager.checkpoint(&mut store).unwrap();
+        assert_eq!(info.triples, 2);
+
+        store.insert_at(triple("http://example.org/h3"), GraphId::Sensor("edr".to_string()), sensor(), 77);
+        store.remove_triple(&triple("http://example.org/h1"), &GraphId::Default);
+        // コンパクション後のログは Checkpoint と以降の 2 件だけ
+        let records = store.wal_mut().unwrap().records().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], WalRecord { sequence: 2, timestamp: records[0].timestamp, operation: WalOperation::Checkpoint });
+        assert_eq!(store.wal_failures(), 0);
+        drop(store);
+
+        let recovered = manager.recover(&wal_path).unwrap();
+        assert_eq!(recovered.statistics().total_triples, 2);
+        assert!(recovered.find_triples(Some("http://example.org/h1"), None, None).is_empty());
+        let h3 = recovered.find_triples(Some("http://example.org/h3"), None, None);
+        assert_eq!((h3[0].asserted_at, &h3[0].provenance), (77, &sensor()));
+        assert_eq!(recovered.wal_sequence(), 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_tail_is_discarded() {
+        let path = std::env::temp_dir().join(format!("fukurow-wal-torn-{}.wal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut wal = WriteAheadLog::open(&path).unwrap();
+        wal.append(WalOperation::ClearAll).unwrap();
+        drop(wal);
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"sequence\":2,\"timest").unwrap();
+        drop(file);
+
+        let mut wal = WriteAheadLog::open(&path).unwrap();
+        assert_eq!(wal.last_sequence(), 1);
+        assert_eq!(wal.append(WalOperation::ClearGraph { graph_id: GraphId::Default }).unwrap(), 2);
+        let sequences: Vec<u64> = wal.records().unwrap().iter().map(|record| record.sequence).collect();
+        assert_eq!(sequences, vec![1, 2]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+//! # Anomaly Feedback Stage
+//!
+//! Writes anomaly detection results back into the knowledge graph.
+//! 検知結果をストリームに流すだけだと推論から参照できないため、`AnomalyDetected` を
+//! トリプル (指標・スコア・窓・対象エンティティ) に変換し `Provenance::Inferred` 付きで格納する。
+//! ルールは `?host ex:hasAnomaly ?a` で異常と離散的なセキュリティイベントを相関できる
+//! (例: ホストの異常 + 新しい管理者ログイン ⇒ 重大アラート)
+
+use crate::{StreamingEvent, StreamError, StreamProcessor};
+use async_trait::async_trait;
+use fukurow_core::model::Triple;
+use fukurow_store::{GraphId, Provenance, RdfStore};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Class of anomaly nodes
+pub const ANOMALY_CLASS: &str = "http://example.org/Anomaly";
+pub const ANOMALY_METRIC: &str = "http://example.org/anomalyMetric";
+pub const ANOMALY_SCORE: &str = "http://example.org/anomalyScore";
+pub const ANOMALY_THRESHOLD: &str = "http://example.org/anomalyThreshold";
+pub const ANOMALY_STRATEGY: &str = "http://example.org/anomalyStrategy";
+pub const ANOMALY_DETECTED_AT: &str = "http://example.org/detectedAt";
+pub const ANOMALY_WINDOW_START: &str = "http://example.org/windowStart";
+pub const ANOMALY_WINDOW_END: &str = "http://example.org/windowEnd";
+/// Anomaly → affected entity
+pub const ANOMALY_ENTITY: &str = "http://example.org/affectedEntity";
+/// Affected entity → anomaly (the direction rules join on)
+pub const HAS_ANOMALY: &str = "http://example.org/hasAnomaly";
+/// Default graph anomalies are written to
+pub const DEFAULT_ANOMALY_GRAPH: &str = "anomaly";
+
+/// Triples describing an anomaly event (empty for other events)
+///
+/// ノード IRI は指標・エンティティ・時刻から決めるため、同じ検知結果が再配信されても同じノードになる
+pub fn anomaly_triples(event: &StreamingEvent) -> Vec<Triple> {
+    let StreamingEvent::AnomalyDetected { score, threshold, metric, timestamp, strategy, entity, window, .. } = event else {
+        return Vec::new();
+    };
+
+    let mut hasher = DefaultHasher::new();
+    (metric, entity, timestamp.to_rfc3339()).hash(&mut hasher);
+    let node = format!("urn:fukurow:anomaly:{:016x}", hasher.finish());
+    let triple = |subject: &str, predicate: &str, object: String| Triple {
+        subject: subject.to_string(),
+        predicate: predicate.to_string(),
+        object,
+    };
+
+    let mut triples = vec![
+        triple(&node, RDF_TYPE, ANOMALY_CLASS.to_string()),
+        triple(&node, ANOMALY_METRIC, metric.clone()),
+        triple(&node, ANOMALY_SCORE, score.to_string()),
+        triple(&node, ANOMALY_THRESHOLD, threshold.to_string()),
+        triple(&node, ANOMALY_DETECTED_AT, timestamp.to_rfc3339()),
+    ];
+    if let Some(strategy) = strategy {
+        triples.push(triple(&node, ANOMALY_STRATEGY, strategy.clone()));
+    }
+    if let Some(window) = window {
+        triples.push(triple(&node, ANOMALY_WINDOW_START, window.start.to_rfc3339()));
+        triples.push(triple(&node, ANOMALY_WINDOW_END, window.end.to_rfc3339()));
+    }
+    if let Some(entity) = entity {
+        triples.push(triple(&node, ANOMALY_ENTITY, entity.clone()));
+        triples.push(triple(entity, HAS_ANOMALY, node.clone()));
+    }
+    triples
+}
+
+/// Feedback stage statistics
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct AnomalyFeedbackStats {
+    /// Anomaly events written to the store
+    pub recorded: u64,
+    /// Triples inserted for them
+    pub triples: u64,
+}
+
+/// Streaming stage that records anomalies in the knowledge graph and forwards every event
+pub struct AnomalyFeedbackStage<P: StreamProcessor> {
+    store: Arc<RwLock<RdfStore>>,
+    downstream: P,
+    graph_id: GraphId,
+    recorded: AtomicU64,
+    triples: AtomicU64,
+}
+
+impl<P: StreamProcessor> AnomalyFeedbackStage<P> {
+    /// Record into `store` (e.g. `ReasonerEngine::get_graph_store()`)
+    pub fn new(store: Arc<RwLock<RdfStore>>, downstream: P) -> Self {
+        Self {
+            store,
+            downstream,
+            graph_id: GraphId::Inferred(DEFAULT_ANOMALY_GRAPH.to_string()),
+            recorded: AtomicU64::new(0),
+            triples: AtomicU64::new(0),
+        }
+    }
+
+    /// Graph anomalies are written to
+    pub fn with_graph(mut self, graph_id: GraphId) -> Self {
+        self.graph_id = graph_id;
+        self
+    }
+
+    pub fn stats(&self) -> AnomalyFeedbackStats {
+        AnomalyFeedbackStats {
+            recorded: self.recorded.load(Ordering::Relaxed),
+            triples: self.triples.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Insert the anomalies among `events`; returns the number of triples inserted
+    pub async fn record(&self, events: &[StreamingEvent]) -> usize {
+        let anomalies: Vec<Vec<Triple>> = events.iter()
+            .map(anomaly_triples)
+            .filter(|triples| !triples.is_empty())
+            .collect();
+        if anomalies.is_empty() {
+            return 0;
+        }
+
+        let mut store = self.store.write().await;
+        let mut inserted = 0;
+        for triples in &anomalies {
+            inserted += triples.len();
+            store.insert_batch(triples.clone(), self.graph_id.clone(), Self::provenance());
+        }
+        self.recorded.fetch_add(anomalies.len() as u64, Ordering::Relaxed);
+        self.triples.fetch_add(inserted as u64, Ordering::Relaxed);
+        inserted
+    }
+
+    fn provenance() -> Provenance {
+        Provenance::Inferred {
+            rule: "anomaly-detection".to_string(),
+            reasoning_level: "anomaly".to_string(),
+            evidence: Vec::new(),
+            confidence: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: StreamProcessor> StreamProcessor for AnomalyFeedbackStage<P> {
+    async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        self.record(std::slice::from_ref(&event)).await;
+        self.downstream.process_event(event).await
+    }
+
+    async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        self.record(&events).await;
+        self.downstream.process_batch(events).await
+    }
+
+    async fn process_batch_with_output(&self, events: Vec<StreamingEvent>) -> Result<Vec<StreamingEvent>, StreamError> {
+        self.record(&events).await;
+        self.downstream.process_batch_with_output(events).await
+    }
+
+    fn name(&self) -> &'static str {
+        "anomaly_feedback_stage"
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        self.downstream.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnomalyWindow;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Collector {
+        events: Mutex<Vec<StreamingEvent>>,
+    }
+
+    #[async_trait]
+    impl StreamProcessor for Arc<Collector> {
+        async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            self.events.lock().unwrap().extend(events);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "collector"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    fn anomaly(entity: Option<&str>) -> StreamingEvent {
+        let end = chrono::Utc::now();
+        StreamingEvent::AnomalyDetected {
+            score: 3.5,
+            threshold: 2.0,
+            metric: "admin_logins".to_string(),
+            timestamp: end,
+            strategy: None,
+            contributions: Vec::new(),
+            entity: entity.map(str::to_string),
+            window: Some(AnomalyWindow { start: end - chrono::Duration::minutes(5), end }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_anomalies_and_forwards_all_events() {
+        let store = Arc::new(RwLock::new(RdfStore::new()));
+        let collector = Arc::new(Collector::default());
+        let stage = AnomalyFeedbackStage::new(Arc::clone(&store), Arc::clone(&collector));
+
+        let metrics = StreamingEvent::SystemMetrics {
+            cpu_usage: 1.0,
+            memory_usage: 1.0,
+            active_connections: 1,
+            timestamp: chrono::Utc::now(),
+        };
+        stage.process_batch(vec![anomaly(Some("http://example.org/host1")), metrics]).await.unwrap();
+        assert_eq!(collector.events.lock().unwrap().len(), 2);
+
+        let store = store.read().await;
+        let links = store.find_triples(Some("http://example.org/host1"), Some(HAS_ANOMALY), None);
+        assert_eq!(links.len(), 1);
+        let node = links[0].triple.object.to_string();
+        assert_eq!(store.find_triples(Some(&node), Some(ANOMALY_METRIC), Some("admin_logins")).len(), 1);
+        assert_eq!(store.find_triples(Some(&node), Some(ANOMALY_SCORE), None)[0].triple.object, "3.5");
+        assert_eq!(store.find_triples(Some(&node), Some(ANOMALY_WINDOW_START), None).len(), 1);
+        assert_eq!(links[0].graph_id, GraphId::Inferred(DEFAULT_ANOMALY_GRAPH.to_string()));
+        assert!(matches!(&links[0].provenance, Provenance::Inferred { rule, .. } if rule == "anomaly-detection"));
+
+        let stats = stage.stats();
+        assert_eq!(stats.recorded, 1);
+        assert_eq!(stats.triples, 9);
+    }
+
+    #[test]
+    fn test_anomaly_node_is_stable_across_redelivery() {
+        let event = anomaly(None);
+        let triples = anomaly_triples(&event);
+        assert!(triples.iter().all(|t| t.predicate != HAS_ANOMALY && t.predicate != ANOMALY_ENTITY));
+        assert_eq!(anomaly_triples(&event.clone())[0].subject, triples[0].subject);
+        assert!(anomaly_triples(&StreamingEvent::SystemMetrics {
+            cpu_usage: 1.0,
+            memory_usage: 1.0,
+            active_connections: 1,
+            timestamp: chrono::Utc::now(),
+        }).is_empty());
+    }
+}
@@ -0,0 +1,819 @@
+//! # Payload Codecs
+//!
+//! Serialization of [`StreamingEvent`] payloads: JSON, Avro or Protobuf, optional gzip
+//! compression, and Confluent Schema Registry integration.
+//!
+//! Avro / Protobuf のペイロードは Confluent のワイヤ形式 (マジックバイト 0 + 4 バイトのスキーマ ID)
+//! で書き込み、スキーマはトピックごとのサブジェクトに登録する。イベント本体はスキーマ上の
+//! `payload` フィールドに JSON として載せるため、`StreamingEvent` にバリアントが増えても
+//! スキーマの互換性は保たれる。
+
+use crate::{StreamingEvent, StreamError};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// First byte of a payload in the Confluent wire format
+pub const MAGIC_BYTE: u8 = 0;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Avro schema of the event envelope
+pub const AVRO_EVENT_SCHEMA: &str = r#"{"type":"record","name":"StreamingEvent","namespace":"io.fukurow.streaming","fields":[{"name":"event_type","type":"string"},{"name":"timestamp","type":"long","doc":"Epoch milliseconds"},{"name":"payload","type":"string","doc":"StreamingEvent as JSON"}]}"#;
+
+/// Protobuf schema of the event envelope
+pub const PROTOBUF_EVENT_SCHEMA: &str = r#"syntax = "proto3";
+package fukurow.streaming;
+
+message StreamingEvent {
+  string event_type = 1;
+  // Epoch milliseconds
+  int64 timestamp = 2;
+  // StreamingEvent as JSON
+  string payload = 3;
+}
+"#;
+
+/// Payload serialization format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadFormat {
+    /// Plain JSON (no schema registry)
+    #[default]
+    Json,
+    /// Avro binary in the Confluent wire format (`avro` feature)
+    Avro,
+    /// Protobuf in the Confluent wire format (`protobuf` feature)
+    Protobuf,
+}
+
+impl PayloadFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadFormat::Json => "json",
+            PayloadFormat::Avro => "avro",
+            PayloadFormat::Protobuf => "protobuf",
+        }
+    }
+
+    /// Schema registered for this format (`None` for JSON)
+    pub fn schema(&self) -> Option<SchemaDefinition> {
+        match self {
+            PayloadFormat::Json => None,
+            PayloadFormat::Avro => Some(SchemaDefinition::new(SchemaType::Avro, AVRO_EVENT_SCHEMA)),
+            PayloadFormat::Protobuf => Some(SchemaDefinition::new(SchemaType::Protobuf, PROTOBUF_EVENT_SCHEMA)),
+        }
+    }
+
+    /// Fully qualified record name used by the record name strategies
+    pub fn record_name(&self) -> &'static str {
+        match self {
+            PayloadFormat::Json => "StreamingEvent",
+            PayloadFormat::Avro => "io.fukurow.streaming.StreamingEvent",
+            PayloadFormat::Protobuf => "fukurow.streaming.StreamingEvent",
+        }
+    }
+
+    /// Fail if this build cannot encode the format
+    pub fn ensure_supported(&self) -> Result<(), StreamError> {
+        let supported = match self {
+            PayloadFormat::Json => true,
+            PayloadFormat::Avro => cfg!(feature = "avro"),
+            PayloadFormat::Protobuf => cfg!(feature = "protobuf"),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(StreamError::ConfigError(format!("{} payloads require the `{}` feature", self.as_str(), self.as_str())))
+        }
+    }
+}
+
+/// Compression applied to encoded payloads
+///
+/// Kafka では他のコンシューマとの互換性のため、プロデューサの `compression.type`
+/// ([`KafkaConfig::properties`](crate::config::KafkaConfig)) を使う方がよい。
+/// 復号時は gzip ヘッダで判別するため、圧縮の有無が混在するトピックも読める
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadCompression {
+    #[default]
+    None,
+    Gzip,
+}
+
+/// How the schema registry subject is derived from the topic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubjectNameStrategy {
+    /// `<topic>-value`
+    #[default]
+    TopicName,
+    /// `<record name>` (shared by every topic)
+    RecordName,
+    /// `<topic>-<record name>`
+    TopicRecordName,
+}
+
+/// Per-topic serialization settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerializationConfig {
+    #[serde(default)]
+    pub format: PayloadFormat,
+
+    #[serde(default)]
+    pub compression: PayloadCompression,
+
+    /// Schema registry URL (required for Avro and Protobuf)
+    #[serde(default)]
+    pub registry_url: Option<String>,
+
+    #[serde(default)]
+    pub subject_strategy: SubjectNameStrategy,
+
+    /// Register the schema on startup; otherwise it must already be registered under the subject
+    #[serde(default = "default_true")]
+    pub auto_register: bool,
+
+    /// Refuse to register a schema the registry reports as incompatible with the latest version
+    #[serde(default = "default_true")]
+    pub check_compatibility: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for SerializationConfig {
+    fn default() -> Self {
+        Self {
+            format: PayloadFormat::Json,
+            compression: PayloadCompression::None,
+            registry_url: None,
+            subject_strategy: SubjectNameStrategy::TopicName,
+            auto_register: true,
+            check_compatibility: true,
+        }
+    }
+}
+
+impl SerializationConfig {
+    /// Registry subject of `topic`'s values
+    pub fn subject(&self, topic: &str) -> String {
+        let record = self.format.record_name();
+        match self.subject_strategy {
+            SubjectNameStrategy::TopicName => format!("{}-value", topic),
+            SubjectNameStrategy::RecordName => record.to_string(),
+            SubjectNameStrategy::TopicRecordName => format!("{}-{}", topic, record),
+        }
+    }
+}
+
+#[cfg(feature = "schema-registry")]
+impl SerializationConfig {
+    /// Client for `registry_url`, if set
+    pub fn http_registry(&self) -> Option<Arc<dyn SchemaRegistryClient>> {
+        self.registry_url.as_ref().map(|url| Arc::new(HttpSchemaRegistry::new(url.clone())) as Arc<dyn SchemaRegistryClient>)
+    }
+}
+
+/// Schema type as named by the registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SchemaType {
+    Avro,
+    Protobuf,
+    Json,
+}
+
+/// Schema text and its type
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SchemaDefinition {
+    pub schema_type: SchemaType,
+    pub schema: String,
+}
+
+impl SchemaDefinition {
+    pub fn new(schema_type: SchemaType, schema: impl Into<String>) -> Self {
+        Self { schema_type, schema: schema.into() }
+    }
+}
+
+/// Schema version stored under a subject
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredSchema {
+    pub id: u32,
+    pub version: u32,
+    pub schema: SchemaDefinition,
+}
+
+/// Schema registry operations needed by [`EventCodec`]
+///
+/// Confluent REST API 用の実装は `schema-registry` フィーチャの [`HttpSchemaRegistry`]
+#[async_trait]
+pub trait SchemaRegistryClient: Send + Sync {
+    /// Register `schema` under `subject` and return its global ID (existing ID if already registered)
+    async fn register(&self, subject: &str, schema: &SchemaDefinition) -> Result<u32, StreamError>;
+
+    /// Version of `subject` holding exactly `schema`, if any
+    async fn lookup(&self, subject: &str, schema: &SchemaDefinition) -> Result<Option<RegisteredSchema>, StreamError>;
+
+    /// Whether `schema` may be registered after the latest version of `subject` (true for new subjects)
+    async fn is_compatible(&self, subject: &str, schema: &SchemaDefinition) -> Result<bool, StreamError>;
+
+    /// Schema with the global ID `id`
+    async fn schema_by_id(&self, id: u32) -> Result<SchemaDefinition, StreamError>;
+}
+
+/// In-process schema registry for tests and single-node deployments
+///
+/// 互換性は BACKWARD で判定する: Avro レコードでは追加フィールドに `default` が必要で、
+/// 既存フィールドの型は変えられない。Protobuf / JSON はフィールド番号を検査しないため常に互換とする
+#[derive(Debug, Default)]
+pub struct InMemorySchemaRegistry {
+    state: Mutex<RegistryState>,
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    /// Schemas by global ID - 1
+    schemas: Vec<SchemaDefinition>,
+    /// Schema IDs of each subject's versions
+    subjects: HashMap<String, Vec<u32>>,
+}
+
+impl InMemorySchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schema IDs registered under `subject`, oldest first
+    pub fn versions(&self, subject: &str) -> Vec<u32> {
+        self.state.lock().unwrap().subjects.get(subject).cloned().unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl SchemaRegistryClient for InMemorySchemaRegistry {
+    async fn register(&self, subject: &str, schema: &SchemaDefinition) -> Result<u32, StreamError> {
+        let mut state = self.state.lock().unwrap();
+        let id = match state.schemas.iter().position(|registered| registered == schema) {
+            Some(index) => index as u32 + 1,
+            None => {
+                state.schemas.push(schema.clone());
+                state.schemas.len() as u32
+            }
+        };
+        let versions = state.subjects.entry(subject.to_string()).or_default();
+        if !versions.contains(&id) {
+            versions.push(id);
+        }
+        Ok(id)
+    }
+
+    async fn lookup(&self, subject: &str, schema: &SchemaDefinition) -> Result<Option<RegisteredSchema>, StreamError> {
+        let state = self.state.lock().unwrap();
+        let Some(versions) = state.subjects.get(subject) else { return Ok(None) };
+        Ok(versions.iter().enumerate()
+            .find(|(_, id)| state.schemas[**id as usize - 1] == *schema)
+            .map(|(index, id)| RegisteredSchema { id: *id, version: index as u32 + 1, schema: schema.clone() }))
+    }
+
+    async fn is_compatible(&self, subject: &str, schema: &SchemaDefinition) -> Result<bool, StreamError> {
+        let state = self.state.lock().unwrap();
+        let Some(latest) = state.subjects.get(subject).and_then(|versions| versions.last()) else { return Ok(true) };
+        let latest = &state.schemas[*latest as usize - 1];
+        Ok(match (latest.schema_type, schema.schema_type) {
+            (SchemaType::Avro, SchemaType::Avro) => avro_backward_compatible(&latest.schema, &schema.schema),
+            (old, new) => old == new,
+        })
+    }
+
+    async fn schema_by_id(&self, id: u32) -> Result<SchemaDefinition, StreamError> {
+        let state = self.state.lock().unwrap();
+        id.checked_sub(1)
+            .and_then(|index| state.schemas.get(index as usize))
+            .cloned()
+            .ok_or_else(|| StreamError::SerializationError(format!("unknown schema id {}", id)))
+    }
+}
+
+/// Whether data written with `old` can be read with `new` (Avro record fields only)
+fn avro_backward_compatible(old: &str, new: &str) -> bool {
+    let (Ok(old), Ok(new)) = (serde_json::from_str::<serde_json::Value>(old), serde_json::from_str::<serde_json::Value>(new)) else {
+        return false;
+    };
+    let fields = |schema: &serde_json::Value| -> Option<Vec<serde_json::Value>> {
+        (schema["type"] == "record").then(|| schema["fields"].as_array().cloned().unwrap_or_default())
+    };
+    let (Some(old_fields), Some(new_fields)) = (fields(&old), fields(&new)) else {
+        return old == new;
+    };
+    new_fields.iter().all(|field| {
+        match old_fields.iter().find(|old_field| old_field["name"] == field["name"]) {
+            Some(old_field) => old_field["type"] == field["type"],
+            None => field.get("default").is_some(),
+        }
+    })
+}
+
+/// Confluent Schema Registry REST client
+#[cfg(feature = "schema-registry")]
+pub struct HttpSchemaRegistry {
+    client: reqwest::Client,
+    base_url: String,
+    basic_auth: Option<(String, String)>,
+}
+
+#[cfg(feature = "schema-registry")]
+impl HttpSchemaRegistry {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            basic_auth: None,
+        }
+    }
+
+    pub fn with_basic_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.basic_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path))
+            .header(reqwest::header::CONTENT_TYPE, "application/vnd.schemaregistry.v1+json");
+        match &self.basic_auth {
+            Some((username, password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        }
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<Option<T>, StreamError> {
+        let response = request.send().await.map_err(|e| StreamError::ConnectionError(format!("schema registry: {}", e)))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(StreamError::SerializationError(format!("schema registry returned {}: {}", status, body)));
+        }
+        response.json::<T>().await
+            .map(Some)
+            .map_err(|e| StreamError::SerializationError(format!("schema registry response: {}", e)))
+    }
+}
+
+#[cfg(feature = "schema-registry")]
+#[derive(Serialize)]
+struct RegistrySchemaRequest<'a> {
+    schema: &'a str,
+    #[serde(rename = "schemaType")]
+    schema_type: SchemaType,
+}
+
+#[cfg(feature = "schema-registry")]
+#[derive(Deserialize)]
+struct RegistrySchemaResponse {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    version: u32,
+    schema: String,
+    /// Absent for Avro
+    #[serde(rename = "schemaType", default)]
+    schema_type: Option<SchemaType>,
+}
+
+#[cfg(feature = "schema-registry")]
+fn subject_path(subject: &str) -> String {
+    subject.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(feature = "schema-registry")]
+#[async_trait]
+impl SchemaRegistryClient for HttpSchemaRegistry {
+    async fn register(&self, subject: &str, schema: &SchemaDefinition) -> Result<u32, StreamError> {
+        #[derive(Deserialize)]
+        struct Registered {
+            id: u32,
+        }
+        let body = RegistrySchemaRequest { schema: &schema.schema, schema_type: schema.schema_type };
+        let request = self.request(reqwest::Method::POST, &format!("/subjects/{}/versions", subject_path(subject))).json(&body);
+        self.send::<Registered>(request).await?
+            .map(|registered| registered.id)
+            .ok_or_else(|| StreamError::SerializationError(format!("schema registry rejected subject {}", subject)))
+    }
+
+    async fn lookup(&self, subject: &str, schema: &SchemaDefinition) -> Result<Option<RegisteredSchema>, StreamError> {
+        let body = RegistrySchemaRequest { schema: &schema.schema, schema_type: schema.schema_type };
+        let request = self.request(reqwest::Method::POST, &format!("/subjects/{}", subject_path(subject))).json(&body);
+        Ok(self.send::<RegistrySchemaResponse>(request).await?.map(|found| RegisteredSchema {
+            id: found.id,
+            version: found.version,
+            schema: SchemaDefinition::new(found.schema_type.unwrap_or(SchemaType::Avro), found.schema),
+        }))
+    }
+
+    async fn is_compatible(&self, subject: &str, schema: &SchemaDefinition) -> Result<bool, StreamError> {
+        #[derive(Deserialize)]
+        struct Compatibility {
+            is_compatible: bool,
+        }
+        let body = RegistrySchemaRequest { schema: &schema.schema, schema_type: schema.schema_type };
+        let request = self.request(reqwest::Method::POST, &format!("/compatibility/subjects/{}/versions/latest", subject_path(subject)))
+            .json(&body);
+        // 未登録のサブジェクトは 404 になる
+        Ok(self.send::<Compatibility>(request).await?.map_or(true, |result| result.is_compatible))
+    }
+
+    async fn schema_by_id(&self, id: u32) -> Result<SchemaDefinition, StreamError> {
+        let request = self.request(reqwest::Method::GET, &format!("/schemas/ids/{}", id));
+        self.send::<RegistrySchemaResponse>(request).await?
+            .map(|found| SchemaDefinition::new(found.schema_type.unwrap_or(SchemaType::Avro), found.schema))
+            .ok_or_else(|| StreamError::SerializationError(format!("unknown schema id {}", id)))
+    }
+}
+
+/// Event envelope carried by the Avro and Protobuf schemas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EventEnvelope {
+    event_type: String,
+    timestamp: i64,
+    payload: String,
+}
+
+impl EventEnvelope {
+    fn from_event(event: &StreamingEvent) -> Result<Self, StreamError> {
+        Ok(Self {
+            event_type: event.event_type().to_string(),
+            timestamp: event.timestamp().timestamp_millis(),
+            payload: serde_json::to_string(event).map_err(|e| StreamError::SerializationError(e.to_string()))?,
+        })
+    }
+
+    fn into_event(self) -> Result<StreamingEvent, StreamError> {
+        serde_json::from_str(&self.payload).map_err(|e| StreamError::SerializationError(e.to_string()))
+    }
+}
+
+#[cfg(feature = "protobuf")]
+mod proto {
+    /// Generated-equivalent of `StreamingEvent` in [`PROTOBUF_EVENT_SCHEMA`](super::PROTOBUF_EVENT_SCHEMA)
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StreamingEvent {
+        #[prost(string, tag = "1")]
+        pub event_type: String,
+        #[prost(int64, tag = "2")]
+        pub timestamp: i64,
+        #[prost(string, tag = "3")]
+        pub payload: String,
+    }
+}
+
+/// Encoder/decoder of one topic's payloads
+///
+/// 復号はワイヤ形式を先頭バイトで判別する: gzip ヘッダなら展開し、マジックバイト 0 なら
+/// スキーマ ID の書き込みスキーマで読み、それ以外は素の JSON として読む。
+/// 移行中に JSON と Avro / Protobuf が混在していても読める
+pub struct EventCodec {
+    format: PayloadFormat,
+    compression: PayloadCompression,
+    /// Registry ID of the schema this codec writes
+    schema_id: Option<u32>,
+    registry: Option<Arc<dyn SchemaRegistryClient>>,
+    /// Writer schemas seen while decoding
+    writer_schemas: Mutex<HashMap<u32, SchemaDefinition>>,
+}
+
+impl std::fmt::Debug for EventCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventCodec")
+            .field("format", &self.format)
+            .field("compression", &self.compression)
+            .field("schema_id", &self.schema_id)
+            .finish()
+    }
+}
+
+impl Default for EventCodec {
+    fn default() -> Self {
+        Self::json()
+    }
+}
+
+impl EventCodec {
+    /// Plain JSON codec (the historical wire format)
+    pub fn json() -> Self {
+        Self {
+            format: PayloadFormat::Json,
+            compression: PayloadCompression::None,
+            schema_id: None,
+            registry: None,
+            writer_schemas: Mutex::default(),
+        }
+    }
+
+    pub fn with_compression(mut self, compression: PayloadCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Codec for `topic`, registering (or looking up) its schema in `registry`
+    ///
+    /// `check_compatibility` が有効なら、最新バージョンと互換でないスキーマは登録せずにエラーにする
+    pub async fn connect(topic: &str, config: &SerializationConfig, registry: Option<Arc<dyn SchemaRegistryClient>>) -> Result<Self, StreamError> {
+        config.format.ensure_supported()?;
+        let mut codec = Self::json().with_compression(config.compression);
+        codec.format = config.format;
+        let Some(definition) = config.format.schema() else {
+            codec.registry = registry;
+            return Ok(codec);
+        };
+        let registry = registry.ok_or_else(|| StreamError::ConfigError(format!("{} payloads require a schema registry", config.format.as_str())))?;
+
+        let subject = config.subject(topic);
+        let id = if config.auto_register {
+            if config.check_compatibility && !registry.is_compatible(&subject, &definition).await? {
+                return Err(StreamError::SerializationError(format!(
+                    "schema is incompatible with the latest version of subject {}", subject
+                )));
+            }
+            registry.register(&subject, &definition).await?
+        } else {
+            registry.lookup(&subject, &definition).await?
+                .ok_or_else(|| StreamError::ConfigError(format!("schema is not registered under subject {}", subject)))?
+                .id
+        };
+
+        codec.writer_schemas.lock().unwrap().insert(id, definition);
+        codec.schema_id = Some(id);
+        codec.registry = Some(registry);
+        Ok(codec)
+    }
+
+    /// Codec for a [`StreamConfig`](crate::StreamConfig)'s topic and serialization settings
+    pub async fn for_stream(config: &crate::StreamConfig, registry: Option<Arc<dyn SchemaRegistryClient>>) -> Result<Self, StreamError> {
+        Self::connect(&config.topic, &config.serialization, registry).await
+    }
+
+    pub fn format(&self) -> PayloadFormat {
+        self.format
+    }
+
+    pub fn schema_id(&self) -> Option<u32> {
+        self.schema_id
+    }
+
+    /// Encode `event` in the configured format
+    pub fn encode(&self, event: &StreamingEvent) -> Result<Vec<u8>, StreamError> {
+        let encoded = match self.format {
+            PayloadFormat::Json => serde_json::to_vec(event).map_err(|e| StreamError::SerializationError(e.to_string()))?,
+            PayloadFormat::Avro | PayloadFormat::Protobuf => {
+                let schema_id = self.schema_id
+                    .ok_or_else(|| StreamError::ConfigError("codec has no registered schema".to_string()))?;
+                let mut framed = vec![MAGIC_BYTE];
+                framed.extend_from_slice(&schema_id.to_be_bytes());
+                let envelope = EventEnvelope::from_event(event)?;
+                if self.format == PayloadFormat::Avro {
+                    framed.extend(encode_avro(&envelope)?);
+                } else {
+                    // メッセージインデックス [0] (ファイル内の最初のメッセージ) の省略形
+                    framed.push(0);
+                    framed.extend(encode_protobuf(&envelope)?);
+                }
+                framed
+            }
+        };
+
+        match self.compression {
+            PayloadCompression::None => Ok(encoded),
+            PayloadCompression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&encoded).map_err(|e| StreamError::SerializationError(e.to_string()))?;
+                encoder.finish().map_err(|e| StreamError::SerializationError(e.to_string()))
+            }
+        }
+    }
+
+    /// Decode a payload written by any codec (JSON, Avro or Protobuf, compressed or not)
+    pub async fn decode(&self, payload: &[u8]) -> Result<StreamingEvent, StreamError> {
+        let decompressed;
+        let payload = if payload.starts_with(&GZIP_MAGIC) {
+            let mut buffer = Vec::new();
+            flate2::read::GzDecoder::new(payload).read_to_end(&mut buffer)
+                .map_err(|e| StreamError::SerializationError(format!("gzip: {}", e)))?;
+            decompressed = buffer;
+            &decompressed[..]
+        } else {
+            payload
+        };
+
+        if payload.len() < 5 || payload[0] != MAGIC_BYTE {
+            return serde_json::from_slice(payload).map_err(|e| StreamError::SerializationError(e.to_string()));
+        }
+        let schema_id = u32::from_be_bytes([payload[1], payload[2], payload[3], payload[4]]);
+        let writer = self.writer_schema(schema_id).await?;
+        let body = &payload[5..];
+        match writer.schema_type {
+            SchemaType::Avro => decode_avro(&writer.schema, body)?.into_event(),
+            SchemaType::Protobuf => decode_protobuf(skip_message_indexes(body)?)?.into_event(),
+            SchemaType::Json => serde_json::from_slice(body).map_err(|e| StreamError::SerializationError(e.to_string())),
+        }
+    }
+
+    async fn writer_schema(&self, schema_id: u32) -> Result<SchemaDefinition, StreamError> {
+        let cached = self.writer_schemas.lock().unwrap().get(&schema_id).cloned();
+        if let Some(schema) = cached {
+            return Ok(schema);
+        }
+        let registry = self.registry.as_ref()
+            .ok_or_else(|| StreamError::SerializationError(format!("schema id {} is unknown and no registry is configured", schema_id)))?;
+        let schema = registry.schema_by_id(schema_id).await?;
+        self.writer_schemas.lock().unwrap().insert(schema_id, schema.clone());
+        Ok(schema)
+    }
+}
+
+/// Skip the Confluent message-index array preceding a Protobuf message
+fn skip_message_indexes(body: &[u8]) -> Result<&[u8], StreamError> {
+    let mut position = 0;
+    let mut read_varint = || -> Result<i64, StreamError> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = *body.get(position)
+                .ok_or_else(|| StreamError::SerializationError("truncated message index".to_string()))?;
+            position += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                // zigzag
+                return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+        Err(StreamError::SerializationError("message index varint is too long".to_string()))
+    };
+    let count = read_varint()?;
+    for _ in 0..count.max(0) {
+        read_varint()?;
+    }
+    Ok(&body[position..])
+}
+
+#[cfg(feature = "avro")]
+fn encode_avro(envelope: &EventEnvelope) -> Result<Vec<u8>, StreamError> {
+    let schema = apache_avro::Schema::parse_str(AVRO_EVENT_SCHEMA).map_err(|e| StreamError::SerializationError(e.to_string()))?;
+    let value = apache_avro::to_value(envelope).map_err(|e| StreamError::SerializationError(e.to_string()))?;
+    apache_avro::to_avro_datum(&schema, value).map_err(|e| StreamError::SerializationError(e.to_string()))
+}
+
+#[cfg(not(feature = "avro"))]
+fn encode_avro(_envelope: &EventEnvelope) -> Result<Vec<u8>, StreamError> {
+    PayloadFormat::Avro.ensure_supported().map(|_| Vec::new())
+}
+
+/// Read an Avro datum written with `writer_schema` into the current envelope schema
+#[cfg(feature = "avro")]
+fn decode_avro(writer_schema: &str, body: &[u8]) -> Result<EventEnvelope, StreamError> {
+    let writer = apache_avro::Schema::parse_str(writer_schema).map_err(|e| StreamError::SerializationError(e.to_string()))?;
+    let reader = apache_avro::Schema::parse_str(AVRO_EVENT_SCHEMA).map_err(|e| StreamError::SerializationError(e.to_string()))?;
+    let value = apache_avro::from_avro_datum(&writer, &mut &body[..], Some(&reader))
+        .map_err(|e| StreamError::SerializationError(e.to_string()))?;
+    apache_avro::from_value(&value).map_err(|e| StreamError::SerializationError(e.to_string()))
+}
+
+#[cfg(not(feature = "avro"))]
+fn decode_avro(_writer_schema: &str, _body: &[u8]) -> Result<EventEnvelope, StreamError> {
+    Err(PayloadFormat::Avro.ensure_supported().unwrap_err())
+}
+
+#[cfg(feature = "protobuf")]
+fn encode_protobuf(envelope: &EventEnvelope) -> Result<Vec<u8>, StreamError> {
+    use prost::Message;
+    Ok(proto::StreamingEvent {
+        event_type: envelope.event_type.clone(),
+        timestamp: envelope.timestamp,
+        payload: envelope.payload.clone(),
+    }.encode_to_vec())
+}
+
+#[cfg(not(feature = "protobuf"))]
+fn encode_protobuf(_envelope: &EventEnvelope) -> Result<Vec<u8>, StreamError> {
+    PayloadFormat::Protobuf.ensure_supported().map(|_| Vec::new())
+}
+
+#[cfg(feature = "protobuf")]
+fn decode_protobuf(body: &[u8]) -> Result<EventEnvelope, StreamError> {
+    use prost::Message;
+    let message = proto::StreamingEvent::decode(body).map_err(|e| StreamError::SerializationError(e.to_string()))?;
+    Ok(EventEnvelope { event_type: message.event_type, timestamp: message.timestamp, payload: message.payload })
+}
+
+#[cfg(not(feature = "protobuf"))]
+fn decode_protobuf(_body: &[u8]) -> Result<EventEnvelope, StreamError> {
+    Err(PayloadFormat::Protobuf.ensure_supported().unwrap_err())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> StreamingEvent {
+        StreamingEvent::SystemMetrics {
+            cpu_usage: 12.5,
+            memory_usage: 40.0,
+            active_connections: 3,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_codec_with_gzip_reads_plain_json() {
+        let codec = EventCodec::json().with_compression(PayloadCompression::Gzip);
+        let encoded = codec.encode(&event()).unwrap();
+        assert!(encoded.starts_with(&GZIP_MAGIC));
+        assert_eq!(codec.decode(&encoded).await.unwrap().event_type(), "system_metrics");
+
+        let plain = serde_json::to_vec(&event()).unwrap();
+        assert_eq!(codec.decode(&plain).await.unwrap().event_type(), "system_metrics");
+        assert!(codec.decode(b"not json").await.is_err());
+    }
+
+    #[test]
+    fn test_subject_naming_and_config_defaults() {
+        let mut config: SerializationConfig = serde_json::from_str(r#"{"format": "avro"}"#).unwrap();
+        assert!(config.auto_register && config.check_compatibility);
+        assert_eq!(config.subject("security.events"), "security.events-value");
+        config.subject_strategy = SubjectNameStrategy::RecordName;
+        assert_eq!(config.subject("security.events"), "io.fukurow.streaming.StreamingEvent");
+        config.subject_strategy = SubjectNameStrategy::TopicRecordName;
+        assert_eq!(config.subject("security.events"), "security.events-io.fukurow.streaming.StreamingEvent");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_registry_checks_backward_compatibility() {
+        let registry = InMemorySchemaRegistry::new();
+        let v1 = SchemaDefinition::new(SchemaType::Avro, AVRO_EVENT_SCHEMA);
+        assert!(registry.is_compatible("events-value", &v1).await.unwrap());
+        let id = registry.register("events-value", &v1).await.unwrap();
+        assert_eq!(registry.register("events-value", &v1).await.unwrap(), id);
+        assert_eq!(registry.lookup("events-value", &v1).await.unwrap().unwrap().version, 1);
+
+        let with_default = AVRO_EVENT_SCHEMA.replace(
+            r#"{"name":"payload""#,
+            r#"{"name":"tenant","type":"string","default":"default"},{"name":"payload""#,
+        );
+        let without_default = AVRO_EVENT_SCHEMA.replace(r#"{"name":"payload""#, r#"{"name":"tenant","type":"string"},{"name":"payload""#);
+        let retyped = AVRO_EVENT_SCHEMA.replace(r#""name":"timestamp","type":"long""#, r#""name":"timestamp","type":"string""#);
+        assert!(registry.is_compatible("events-value", &SchemaDefinition::new(SchemaType::Avro, with_default)).await.unwrap());
+        assert!(!registry.is_compatible("events-value", &SchemaDefinition::new(SchemaType::Avro, without_default)).await.unwrap());
+        assert!(!registry.is_compatible("events-value", &SchemaDefinition::new(SchemaType::Avro, retyped)).await.unwrap());
+        assert!(registry.schema_by_id(id + 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_schema_formats_require_registry() {
+        let config = SerializationConfig { format: PayloadFormat::Avro, ..SerializationConfig::default() };
+        assert!(matches!(EventCodec::connect("events", &config, None).await, Err(StreamError::ConfigError(_))));
+    }
+
+    #[cfg(feature = "avro")]
+    #[tokio::test]
+    async fn test_avro_roundtrip_in_confluent_wire_format() {
+        let registry: Arc<dyn SchemaRegistryClient> = Arc::new(InMemorySchemaRegistry::new());
+        let config = SerializationConfig { format: PayloadFormat::Avro, ..SerializationConfig::default() };
+        let producer = EventCodec::connect("events", &config, Some(registry.clone())).await.unwrap();
+        let encoded = producer.encode(&event()).unwrap();
+        assert_eq!(encoded[0], MAGIC_BYTE);
+        assert_eq!(u32::from_be_bytes(encoded[1..5].try_into().unwrap()), producer.schema_id().unwrap());
+
+        // 別プロセスのコンシューマはレジストリから書き込みスキーマを取得する
+        let consumer = EventCodec::connect("events", &SerializationConfig::default(), Some(registry.clone())).await.unwrap();
+        assert_eq!(consumer.decode(&encoded).await.unwrap().event_type(), "system_metrics");
+
+        let incompatible = SchemaDefinition::new(SchemaType::Avro, AVRO_EVENT_SCHEMA.replace(r#""type":"long""#, r#""type":"string""#));
+        registry.register("strict-value", &incompatible).await.unwrap();
+        assert!(matches!(EventCodec::connect("strict", &config, Some(registry)).await, Err(StreamError::SerializationError(_))));
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[tokio::test]
+    async fn test_protobuf_roundtrip_in_confluent_wire_format() {
+        let registry: Arc<dyn SchemaRegistryClient> = Arc::new(InMemorySchemaRegistry::new());
+        let config = SerializationConfig {
+            format: PayloadFormat::Protobuf,
+            compression: PayloadCompression::Gzip,
+            ..SerializationConfig::default()
+        };
+        let codec = EventCodec::connect("events", &config, Some(registry)).await.unwrap();
+        let encoded = codec.encode(&event()).unwrap();
+        assert_eq!(codec.decode(&encoded).await.unwrap().event_type(), "system_metrics");
+    }
+}
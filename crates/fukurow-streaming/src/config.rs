@@ -64,6 +64,76 @@ pub struct KafkaConfig {
 
     /// Additional Kafka properties
     pub properties: HashMap<String, String>,
+
+    /// When consumed offsets are committed
+    #[serde(default)]
+    pub commit_strategy: CommitStrategy,
+
+    /// Producer `transactional.id` (required for [`CommitStrategy::ExactlyOnce`])
+    #[serde(default)]
+    pub transactional_id: Option<String>,
+}
+
+/// Kafka offset commit strategy
+///
+/// 自動コミットは使わず、処理との前後関係を明示的に選ぶ
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitStrategy {
+    /// Commit as soon as a batch is received; a crash during processing loses the batch
+    AtMostOnce,
+    /// Commit after the batch is processed; a crash before the commit redelivers it
+    #[default]
+    AtLeastOnce,
+    /// Produce the results and commit the offsets in one Kafka transaction
+    ExactlyOnce,
+}
+
+impl KafkaConfig {
+    /// Check settings the commit strategy depends on
+    pub fn validate(&self) -> Result<(), crate::StreamError> {
+        if self.consume_topics.is_empty() {
+            return Err(crate::StreamError::ConfigError("no topics to consume".to_string()));
+        }
+        if self.commit_strategy == CommitStrategy::ExactlyOnce {
+            if self.transactional_id.as_deref().is_none_or(str::is_empty) {
+                return Err(crate::StreamError::ConfigError("exactly-once commits require a transactional_id".to_string()));
+            }
+            if self.produce_topic.is_empty() {
+                return Err(crate::StreamError::ConfigError("exactly-once commits require a produce_topic".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// librdkafka consumer properties
+    ///
+    /// `properties` を先に適用し、コミット方式に関わるキーは上書きできないようにする
+    pub fn consumer_properties(&self) -> HashMap<String, String> {
+        let mut props = self.properties.clone();
+        props.insert("bootstrap.servers".to_string(), self.bootstrap_servers.join(","));
+        props.insert("group.id".to_string(), self.group_id.clone());
+        props.insert("enable.auto.commit".to_string(), "false".to_string());
+        props.insert("enable.auto.offset.store".to_string(), "false".to_string());
+        if self.commit_strategy == CommitStrategy::ExactlyOnce {
+            // 中断されたトランザクションの結果を読まない
+            props.insert("isolation.level".to_string(), "read_committed".to_string());
+        }
+        props
+    }
+
+    /// librdkafka producer properties
+    pub fn producer_properties(&self) -> HashMap<String, String> {
+        let mut props = self.properties.clone();
+        props.insert("bootstrap.servers".to_string(), self.bootstrap_servers.join(","));
+        if self.commit_strategy == CommitStrategy::ExactlyOnce {
+            if let Some(id) = &self.transactional_id {
+                props.insert("transactional.id".to_string(), id.clone());
+            }
+            props.insert("enable.idempotence".to_string(), "true".to_string());
+        }
+        props
+    }
 }
 
 /// NATS configuration
@@ -80,6 +150,177 @@ pub struct NATSConfig {
 
     /// Authentication credentials
     pub credentials: Option<String>,
+
+    /// Consume through a JetStream durable consumer instead of a core NATS subscription
+    #[serde(default)]
+    pub jetstream: Option<JetStreamConfig>,
+}
+
+/// Where a JetStream durable consumer starts delivering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum JetStreamDeliverPolicy {
+    /// Every message still stored in the stream
+    #[default]
+    All,
+    /// Only messages published after the consumer is created
+    New,
+    /// The last message of the stream, then new ones
+    Last,
+    /// Replay from a stream sequence number
+    ByStartSequence { start_sequence: u64 },
+    /// Replay messages stored at or after a time
+    ByStartTime { start_time: chrono::DateTime<chrono::Utc> },
+}
+
+/// JetStream stream and durable consumer settings
+///
+/// コアの NATS 購読では fukurow の停止中に届いたメッセージが失われるため、
+/// ストリームに保存し、ACK されるまで再配信される durable コンシューマで読む
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JetStreamConfig {
+    /// Stream name
+    pub stream: String,
+
+    /// Subjects captured by the stream (defaults to the consumed subject)
+    #[serde(default)]
+    pub subjects: Vec<String>,
+
+    /// Durable consumer name; its position survives restarts
+    pub durable_name: String,
+
+    #[serde(default)]
+    pub deliver_policy: JetStreamDeliverPolicy,
+
+    /// How long an unacknowledged message waits before redelivery
+    #[serde(default = "default_ack_wait_ms")]
+    pub ack_wait_ms: u64,
+
+    /// Maximum deliveries of one message (-1 for unlimited)
+    #[serde(default = "default_max_deliver")]
+    pub max_deliver: i64,
+
+    /// Redelivery delay requested when reasoning fails (NAK)
+    #[serde(default = "default_nak_delay_ms")]
+    pub nak_delay_ms: u64,
+
+    /// How long the stream keeps messages (unlimited when unset)
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+
+    /// How long one pull request waits for messages
+    #[serde(default = "default_fetch_expires_ms")]
+    pub fetch_expires_ms: u64,
+}
+
+fn default_ack_wait_ms() -> u64 {
+    30_000
+}
+
+fn default_max_deliver() -> i64 {
+    5
+}
+
+fn default_nak_delay_ms() -> u64 {
+    1_000
+}
+
+fn default_fetch_expires_ms() -> u64 {
+    5_000
+}
+
+impl JetStreamConfig {
+    pub fn new(stream: impl Into<String>, durable_name: impl Into<String>) -> Self {
+        Self {
+            stream: stream.into(),
+            subjects: Vec::new(),
+            durable_name: durable_name.into(),
+            deliver_policy: JetStreamDeliverPolicy::default(),
+            ack_wait_ms: default_ack_wait_ms(),
+            max_deliver: default_max_deliver(),
+            nak_delay_ms: default_nak_delay_ms(),
+            max_age_secs: None,
+            fetch_expires_ms: default_fetch_expires_ms(),
+        }
+    }
+
+    pub fn with_deliver_policy(mut self, deliver_policy: JetStreamDeliverPolicy) -> Self {
+        self.deliver_policy = deliver_policy;
+        self
+    }
+
+    /// Build from a generic [`crate::StreamConfig`]
+    ///
+    /// `group_id` が durable 名になる。`options` で `stream`, `subjects` (カンマ区切り),
+    /// `start_sequence`, `start_time` (RFC 3339), `deliver_policy` (all / new / last),
+    /// `ack_wait_ms`, `max_deliver`, `nak_delay_ms`, `max_age_secs` を指定できる
+    pub fn from_stream_config(config: &crate::StreamConfig) -> Result<Self, crate::StreamError> {
+        let options = &config.options;
+        let durable = config.group_id.clone()
+            .ok_or_else(|| crate::StreamError::ConfigError("JetStream consumers require a group_id (durable name)".to_string()))?;
+        let stream = options.get("stream").cloned()
+            .unwrap_or_else(|| config.topic.split('.').next().unwrap_or_default().to_ascii_uppercase());
+        let mut jetstream = Self::new(stream, durable);
+
+        let number = |key: &str| -> Result<Option<i64>, crate::StreamError> {
+            options.get(key)
+                .map(|value| value.parse::<i64>().map_err(|_| crate::StreamError::ConfigError(format!("{} must be an integer: {}", key, value))))
+                .transpose()
+        };
+        if let Some(subjects) = options.get("subjects") {
+            jetstream.subjects = subjects.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect();
+        }
+        jetstream.deliver_policy = match (options.get("deliver_policy").map(String::as_str), number("start_sequence")?, options.get("start_time")) {
+            (_, Some(sequence), _) => JetStreamDeliverPolicy::ByStartSequence { start_sequence: sequence.max(1) as u64 },
+            (_, None, Some(time)) => JetStreamDeliverPolicy::ByStartTime {
+                start_time: chrono::DateTime::parse_from_rfc3339(time)
+                    .map_err(|e| crate::StreamError::ConfigError(format!("start_time: {}", e)))?
+                    .with_timezone(&chrono::Utc),
+            },
+            (Some("new"), None, None) => JetStreamDeliverPolicy::New,
+            (Some("last"), None, None) => JetStreamDeliverPolicy::Last,
+            (Some("all") | None, None, None) => JetStreamDeliverPolicy::All,
+            (Some(other), None, None) => {
+                return Err(crate::StreamError::ConfigError(format!("unknown deliver_policy: {}", other)));
+            }
+        };
+        if let Some(value) = number("ack_wait_ms")? {
+            jetstream.ack_wait_ms = value.max(0) as u64;
+        }
+        if let Some(value) = number("max_deliver")? {
+            jetstream.max_deliver = value;
+        }
+        if let Some(value) = number("nak_delay_ms")? {
+            jetstream.nak_delay_ms = value.max(0) as u64;
+        }
+        jetstream.max_age_secs = number("max_age_secs")?.map(|value| value.max(0) as u64);
+        jetstream.validate()?;
+        Ok(jetstream)
+    }
+
+    /// Subjects of the stream, falling back to the consumed subject
+    pub fn stream_subjects(&self, subject: &str) -> Vec<String> {
+        if self.subjects.is_empty() { vec![subject.to_string()] } else { self.subjects.clone() }
+    }
+
+    /// Check names and limits before provisioning
+    pub fn validate(&self) -> Result<(), crate::StreamError> {
+        // JetStream の名前にはサブジェクトのトークン区切りやワイルドカードを使えない
+        let invalid_name = |name: &str| name.is_empty() || name.contains(|c: char| c == '.' || c == '*' || c == '>' || c.is_whitespace());
+        if invalid_name(&self.stream) {
+            return Err(crate::StreamError::ConfigError(format!("invalid stream name: {:?}", self.stream)));
+        }
+        if invalid_name(&self.durable_name) {
+            return Err(crate::StreamError::ConfigError(format!("invalid durable name: {:?}", self.durable_name)));
+        }
+        if self.max_deliver == 0 || self.max_deliver < -1 {
+            return Err(crate::StreamError::ConfigError("max_deliver must be positive or -1".to_string()));
+        }
+        if self.ack_wait_ms == 0 || self.fetch_expires_ms == 0 {
+            return Err(crate::StreamError::ConfigError("ack_wait_ms and fetch_expires_ms must be at least 1".to_string()));
+        }
+        Ok(())
+    }
 }
 
 /// Redis configuration
@@ -99,6 +340,50 @@ pub struct RedisConfig {
 
     /// Database number
     pub database: Option<u8>,
+
+    /// Pending entries idle for longer than this are claimed from other consumers (XAUTOCLAIM)
+    #[serde(default = "default_claim_min_idle_ms")]
+    pub claim_min_idle_ms: u64,
+
+    /// Maximum entries claimed per XAUTOCLAIM call
+    #[serde(default = "default_claim_count")]
+    pub claim_count: usize,
+
+    /// How long XREADGROUP blocks waiting for new entries
+    #[serde(default = "default_block_ms")]
+    pub block_ms: u64,
+}
+
+fn default_claim_min_idle_ms() -> u64 {
+    60_000
+}
+
+fn default_claim_count() -> usize {
+    100
+}
+
+fn default_block_ms() -> u64 {
+    5_000
+}
+
+impl RedisConfig {
+    /// Check the consumer group settings
+    pub fn validate(&self) -> Result<(), crate::StreamError> {
+        if self.stream_key.is_empty() {
+            return Err(crate::StreamError::ConfigError("no stream key to consume".to_string()));
+        }
+        if self.consumer_group.is_empty() || self.consumer_name.is_empty() {
+            return Err(crate::StreamError::ConfigError("consumer_group and consumer_name are required".to_string()));
+        }
+        if self.claim_count == 0 {
+            return Err(crate::StreamError::ConfigError("claim_count must be at least 1".to_string()));
+        }
+        // BLOCK 0 は無期限に待つため、シャットダウンできなくなる
+        if self.block_ms == 0 {
+            return Err(crate::StreamError::ConfigError("block_ms must be at least 1".to_string()));
+        }
+        Ok(())
+    }
 }
 
 /// RabbitMQ configuration
@@ -118,6 +403,159 @@ pub struct RabbitMQConfig {
 
     /// Exchange type
     pub exchange_type: String,
+
+    /// Survive broker restarts (exchange, queue and persistent messages)
+    #[serde(default = "default_durable")]
+    pub durable: bool,
+
+    #[serde(default)]
+    pub queue_type: RabbitMQQueueType,
+
+    /// Unacknowledged deliveries the broker sends ahead (basic.qos)
+    #[serde(default = "default_prefetch_count")]
+    pub prefetch_count: u16,
+
+    /// Exchange rejected and expired messages are routed to
+    #[serde(default)]
+    pub dead_letter_exchange: Option<String>,
+
+    /// Routing key for dead-lettered messages (the original key when unset)
+    #[serde(default)]
+    pub dead_letter_routing_key: Option<String>,
+
+    /// Queue bound to the dead-letter exchange, declared along with it
+    #[serde(default)]
+    pub dead_letter_queue: Option<String>,
+
+    /// Deliveries before a quorum queue dead-letters a message (`x-delivery-limit`)
+    #[serde(default)]
+    pub delivery_limit: Option<u32>,
+
+    /// Wait for the broker to confirm each publish
+    #[serde(default = "default_publisher_confirms")]
+    pub publisher_confirms: bool,
+
+    /// Publishes of one message when the broker NACKs it
+    #[serde(default = "default_max_publish_attempts")]
+    pub max_publish_attempts: u32,
+}
+
+/// Queue implementation used for the consumed queue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RabbitMQQueueType {
+    #[default]
+    Classic,
+    /// Replicated Raft queue; must be durable
+    Quorum,
+}
+
+fn default_durable() -> bool {
+    true
+}
+
+fn default_prefetch_count() -> u16 {
+    100
+}
+
+fn default_publisher_confirms() -> bool {
+    true
+}
+
+fn default_max_publish_attempts() -> u32 {
+    3
+}
+
+impl RabbitMQConfig {
+    pub fn new(url: impl Into<String>, exchange: impl Into<String>, queue: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            exchange: exchange.into(),
+            routing_key: "security.events".to_string(),
+            queue: queue.into(),
+            exchange_type: "topic".to_string(),
+            durable: default_durable(),
+            queue_type: RabbitMQQueueType::default(),
+            prefetch_count: default_prefetch_count(),
+            dead_letter_exchange: None,
+            dead_letter_routing_key: None,
+            dead_letter_queue: None,
+            delivery_limit: None,
+            publisher_confirms: default_publisher_confirms(),
+            max_publish_attempts: default_max_publish_attempts(),
+        }
+    }
+
+    pub fn with_routing_key(mut self, routing_key: impl Into<String>) -> Self {
+        self.routing_key = routing_key.into();
+        self
+    }
+
+    /// Use a quorum queue, dead-lettering messages after `delivery_limit` deliveries
+    pub fn with_quorum_queue(mut self, delivery_limit: Option<u32>) -> Self {
+        self.queue_type = RabbitMQQueueType::Quorum;
+        self.durable = true;
+        self.delivery_limit = delivery_limit;
+        self
+    }
+
+    /// Route rejected messages to `exchange`, into `queue` when given
+    pub fn with_dead_letter(mut self, exchange: impl Into<String>, queue: Option<String>) -> Self {
+        self.dead_letter_exchange = Some(exchange.into());
+        self.dead_letter_queue = queue;
+        self
+    }
+
+    pub fn with_prefetch(mut self, prefetch_count: u16) -> Self {
+        self.prefetch_count = prefetch_count;
+        self
+    }
+
+    /// `x-` arguments of the consumed queue's declaration
+    pub fn queue_arguments(&self) -> std::collections::BTreeMap<String, serde_json::Value> {
+        let mut arguments = std::collections::BTreeMap::new();
+        if self.queue_type == RabbitMQQueueType::Quorum {
+            arguments.insert("x-queue-type".to_string(), serde_json::json!("quorum"));
+            if let Some(limit) = self.delivery_limit {
+                arguments.insert("x-delivery-limit".to_string(), serde_json::json!(limit));
+            }
+        }
+        if let Some(exchange) = &self.dead_letter_exchange {
+            arguments.insert("x-dead-letter-exchange".to_string(), serde_json::json!(exchange));
+            if let Some(routing_key) = &self.dead_letter_routing_key {
+                arguments.insert("x-dead-letter-routing-key".to_string(), serde_json::json!(routing_key));
+            }
+        }
+        arguments
+    }
+
+    /// Check the topology before declaring it
+    pub fn validate(&self) -> Result<(), crate::StreamError> {
+        if self.queue.is_empty() || self.exchange.is_empty() {
+            return Err(crate::StreamError::ConfigError("exchange and queue are required".to_string()));
+        }
+        if !matches!(self.exchange_type.as_str(), "direct" | "fanout" | "topic" | "headers") {
+            return Err(crate::StreamError::ConfigError(format!("unknown exchange type: {}", self.exchange_type)));
+        }
+        // クォーラムキューは常に永続で、非永続として宣言するとブローカーが拒否する
+        if self.queue_type == RabbitMQQueueType::Quorum && !self.durable {
+            return Err(crate::StreamError::ConfigError("quorum queues must be durable".to_string()));
+        }
+        if self.delivery_limit.is_some() && self.queue_type != RabbitMQQueueType::Quorum {
+            return Err(crate::StreamError::ConfigError("delivery_limit requires a quorum queue".to_string()));
+        }
+        if self.dead_letter_queue.is_some() && self.dead_letter_exchange.is_none() {
+            return Err(crate::StreamError::ConfigError("dead_letter_queue requires dead_letter_exchange".to_string()));
+        }
+        // prefetch 0 は無制限を意味し、処理待ちのメッセージがメモリに溜まり続ける
+        if self.prefetch_count == 0 {
+            return Err(crate::StreamError::ConfigError("prefetch_count must be at least 1".to_string()));
+        }
+        if self.max_publish_attempts == 0 {
+            return Err(crate::StreamError::ConfigError("max_publish_attempts must be at least 1".to_string()));
+        }
+        Ok(())
+    }
 }
 
 /// Processing configuration
@@ -137,6 +575,21 @@ pub struct ProcessingConfig {
 
     /// Retry configuration
     pub retry: RetryConfig,
+
+    /// Dead-letter destination for events that fail after all retries
+    #[serde(default)]
+    pub dead_letter: Option<DeadLetterConfig>,
+}
+
+/// Dead-letter queue configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeadLetterConfig {
+    /// Publish failed events to an alternate topic/subject/stream
+    Topic { topic: String },
+
+    /// Append failed events to a local JSON Lines spool file
+    Spool { path: String },
 }
 
 /// Retry configuration
@@ -155,6 +608,16 @@ pub struct RetryConfig {
     pub backoff_multiplier: f64,
 }
 
+impl RetryConfig {
+    /// Convert to the shared retry policy (decorrelated jitter enabled)
+    pub fn to_policy(&self) -> fukurow_core::retry::RetryPolicy {
+        fukurow_core::retry::RetryPolicy::new(self.max_attempts)
+            .with_initial_backoff_ms(self.initial_backoff_ms)
+            .with_max_backoff_ms(self.max_backoff_ms)
+            .with_multiplier(self.backoff_multiplier)
+    }
+}
+
 /// Monitoring configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitoringConfig {
@@ -181,6 +644,8 @@ impl Default for StreamingConfig {
                 consume_topics: vec!["security-events".to_string()],
                 produce_topic: "reasoning-results".to_string(),
                 properties: HashMap::new(),
+                commit_strategy: CommitStrategy::default(),
+                transactional_id: None,
             }),
             processing: ProcessingConfig {
                 batch_size: 100,
@@ -193,6 +658,7 @@ impl Default for StreamingConfig {
                     max_backoff_ms: 10000,
                     backoff_multiplier: 2.0,
                 },
+                dead_letter: None,
             },
             monitoring: MonitoringConfig {
                 enable_metrics: true,
@@ -234,6 +700,8 @@ mod tests {
             properties: HashMap::from([
                 ("auto.offset.reset".to_string(), "earliest".to_string()),
             ]),
+            commit_strategy: CommitStrategy::AtLeastOnce,
+            transactional_id: None,
         };
 
         let json = serde_json::to_string(&kafka_config).unwrap();
@@ -243,6 +711,87 @@ mod tests {
         assert_eq!(deserialized.group_id, "test-group");
     }
 
+    #[test]
+    fn test_commit_strategy_properties() {
+        let legacy = r#"{"bootstrap_servers":["kafka:9092"],"group_id":"g","consume_topics":["events"],"produce_topic":"results","properties":{"enable.auto.commit":"true"}}"#;
+        let mut config: KafkaConfig = serde_json::from_str(legacy).unwrap();
+        assert_eq!(config.commit_strategy, CommitStrategy::AtLeastOnce);
+        assert_eq!(config.consumer_properties()["enable.auto.commit"], "false");
+        assert!(!config.consumer_properties().contains_key("isolation.level"));
+        assert!(config.validate().is_ok());
+
+        config.commit_strategy = CommitStrategy::ExactlyOnce;
+        assert!(config.validate().is_err());
+        config.transactional_id = Some("fukurow-reasoner-0".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!(config.consumer_properties()["isolation.level"], "read_committed");
+        assert_eq!(config.producer_properties()["transactional.id"], "fukurow-reasoner-0");
+
+        let json = serde_json::to_string(&CommitStrategy::AtMostOnce).unwrap();
+        assert_eq!(json, r#""at_most_once""#);
+    }
+
+    #[test]
+    fn test_redis_config_defaults() {
+        let json = r#"{"url":"redis://localhost","stream_key":"events","consumer_group":"fukurow","consumer_name":"node-1","database":null}"#;
+        let mut config: RedisConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.claim_min_idle_ms, 60_000);
+        assert_eq!(config.claim_count, 100);
+        assert!(config.validate().is_ok());
+
+        config.block_ms = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_jetstream_config_from_stream_config() {
+        let mut stream = crate::StreamConfig {
+            stream_type: crate::StreamType::NATS,
+            topic: "security.events".to_string(),
+            group_id: Some("fukurow-reasoner".to_string()),
+            partition: None,
+            options: HashMap::from([("start_sequence".to_string(), "42".to_string())]),
+            serialization: crate::SerializationConfig::default(),
+        };
+        let config = JetStreamConfig::from_stream_config(&stream).unwrap();
+        assert_eq!(config.stream, "SECURITY");
+        assert_eq!(config.stream_subjects("security.events"), vec!["security.events".to_string()]);
+        assert_eq!(config.deliver_policy, JetStreamDeliverPolicy::ByStartSequence { start_sequence: 42 });
+        assert_eq!((config.ack_wait_ms, config.max_deliver), (30_000, 5));
+
+        stream.options = HashMap::from([("start_time".to_string(), "2026-10-16T00:00:00Z".to_string())]);
+        let config = JetStreamConfig::from_stream_config(&stream).unwrap();
+        assert!(matches!(config.deliver_policy, JetStreamDeliverPolicy::ByStartTime { .. }));
+
+        stream.group_id = Some("bad.name".to_string());
+        assert!(JetStreamConfig::from_stream_config(&stream).is_err());
+
+        let json = serde_json::to_string(&JetStreamDeliverPolicy::ByStartSequence { start_sequence: 7 }).unwrap();
+        assert_eq!(json, r#"{"policy":"by_start_sequence","start_sequence":7}"#);
+    }
+
+    #[test]
+    fn test_rabbitmq_config_queue_arguments() {
+        let legacy = r#"{"url":"amqp://localhost","exchange":"security","routing_key":"events.#","queue":"fukurow","exchange_type":"topic"}"#;
+        let config: RabbitMQConfig = serde_json::from_str(legacy).unwrap();
+        assert!(config.durable && config.publisher_confirms);
+        assert_eq!((config.queue_type, config.prefetch_count), (RabbitMQQueueType::Classic, 100));
+        assert!(config.queue_arguments().is_empty());
+        assert!(config.validate().is_ok());
+
+        let quorum = config.with_quorum_queue(Some(5)).with_dead_letter("security.dlx", Some("fukurow.dlq".to_string()));
+        let arguments = quorum.queue_arguments();
+        assert_eq!(arguments["x-queue-type"], "quorum");
+        assert_eq!(arguments["x-delivery-limit"], 5);
+        assert_eq!(arguments["x-dead-letter-exchange"], "security.dlx");
+        assert!(quorum.validate().is_ok());
+
+        let mut invalid = quorum.clone();
+        invalid.durable = false;
+        assert!(invalid.validate().is_err());
+        assert!(quorum.with_prefetch(0).validate().is_err());
+    }
+
     #[test]
     fn test_retry_config() {
         let retry = RetryConfig {
@@ -255,4 +804,26 @@ mod tests {
         assert_eq!(retry.max_attempts, 5);
         assert_eq!(retry.backoff_multiplier, 1.5);
     }
+
+    #[test]
+    fn test_retry_config_to_policy() {
+        let retry = StreamingConfig::default().processing.retry;
+        let policy = retry.to_policy();
+
+        assert_eq!(policy.max_attempts, 3);
+        assert_eq!(policy.initial_backoff_ms, 100);
+        assert_eq!(policy.max_backoff_ms, 10000);
+        assert!(policy.jitter);
+        assert_eq!(policy.backoff().count(), 2);
+    }
+
+    #[test]
+    fn test_dead_letter_config_serialization() {
+        let config: DeadLetterConfig = serde_json::from_str(r#"{"type":"spool","path":"/var/spool/fukurow.jsonl"}"#).unwrap();
+        assert_eq!(config, DeadLetterConfig::Spool { path: "/var/spool/fukurow.jsonl".to_string() });
+
+        let json = serde_json::to_string(&DeadLetterConfig::Topic { topic: "events.dlq".to_string() }).unwrap();
+        assert!(json.contains(r#""type":"topic""#));
+        assert!(StreamingConfig::default().processing.dead_letter.is_none());
+    }
 }
@@ -2,10 +2,17 @@
 //!
 //! Stream consumer implementations
 
-use crate::{StreamingEvent, StreamError, StreamConsumer, StreamProducer};
+use crate::codec::EventCodec;
+use crate::config::CommitStrategy;
+use crate::{StreamingEvent, StreamError, StreamConsumer, StreamProcessor, StreamProducer};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::pin::Pin;
+use std::sync::Arc;
 use futures::stream::{Stream, StreamExt};
+use fukurow_observability::tracing::{attributes, spans};
+use tracing::{warn, Instrument};
 
 /// Kafka consumer (stub implementation)
 pub struct KafkaConsumer {
@@ -58,6 +65,11 @@ impl StreamProducer for KafkaProducer {
         Ok(())
     }
 
+    async fn produce_to(&self, _topic: &str, _payload: Vec<u8>) -> Result<(), StreamError> {
+        // TODO: Implement production to an alternate topic
+        Ok(())
+    }
+
     fn name(&self) -> &'static str {
         "kafka_producer"
     }
@@ -67,3 +79,2107 @@ impl StreamProducer for KafkaProducer {
         Ok(())
     }
 }
+
+/// Offset of one topic partition (Kafka convention: the next offset to consume)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PartitionOffset {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+}
+
+/// Raw record fetched from Kafka
+#[derive(Debug, Clone)]
+pub struct KafkaRecord {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub payload: Vec<u8>,
+}
+
+/// Kafka operations needed by [`CommittingConsumer`]
+///
+/// 実ブローカー用の実装は `kafka` フィーチャの [`RdKafkaClient`]
+#[async_trait]
+pub trait KafkaClient: Send + Sync {
+    /// Fetch up to `max` records, waiting for at least one
+    async fn poll_batch(&self, max: usize) -> Result<Vec<KafkaRecord>, StreamError>;
+
+    /// Synchronously commit consumer offsets
+    async fn commit(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError>;
+
+    /// Move the consumer position back so records are fetched again
+    async fn seek(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError>;
+
+    async fn begin_transaction(&self) -> Result<(), StreamError>;
+
+    /// Produce events inside the open transaction
+    async fn produce_in_transaction(&self, events: &[StreamingEvent]) -> Result<(), StreamError>;
+
+    /// Attach consumer offsets to the open transaction and commit it
+    async fn commit_transaction(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError>;
+
+    async fn abort_transaction(&self) -> Result<(), StreamError>;
+
+    /// Wait until produced messages are delivered (called on shutdown)
+    async fn flush(&self) -> Result<(), StreamError> {
+        Ok(())
+    }
+}
+
+/// What happened to one polled batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BatchOutcome {
+    /// Records fetched
+    pub records: usize,
+    /// Records whose payload was not a `StreamingEvent` (skipped but committed)
+    pub undecodable: usize,
+    /// Events lost because processing failed after an at-most-once commit
+    pub dropped: usize,
+    /// Events produced in the transaction (exactly-once)
+    pub produced: usize,
+    pub committed: Vec<PartitionOffset>,
+    /// Batches whose processing failed (rewound, or aborted for exactly-once)
+    pub failed_batches: usize,
+}
+
+impl BatchOutcome {
+    /// Accumulate `other`; `committed` keeps the latest commit
+    fn add(&mut self, other: BatchOutcome) {
+        self.records += other.records;
+        self.undecodable += other.undecodable;
+        self.dropped += other.dropped;
+        self.produced += other.produced;
+        self.failed_batches += other.failed_batches;
+        if !other.committed.is_empty() {
+            self.committed = other.committed;
+        }
+    }
+}
+
+/// Kafka consumer loop that commits offsets according to a [`CommitStrategy`]
+///
+/// 処理に失敗したバッチは at-least-once / exactly-once ではコミットせずに
+/// 先頭オフセットへ巻き戻すため、同じバッチが再配信される
+pub struct CommittingConsumer<C: KafkaClient> {
+    client: C,
+    strategy: CommitStrategy,
+    batch_size: usize,
+    codec: Arc<EventCodec>,
+}
+
+impl<C: KafkaClient> CommittingConsumer<C> {
+    pub fn new(client: C, strategy: CommitStrategy) -> Self {
+        Self { client, strategy, batch_size: 100, codec: Arc::default() }
+    }
+
+    /// Decode payloads with `codec` (plain JSON by default)
+    pub fn with_codec(mut self, codec: Arc<EventCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn strategy(&self) -> CommitStrategy {
+        self.strategy
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Poll one batch, process it and commit according to the strategy
+    pub async fn run_batch<P: StreamProcessor + ?Sized>(&self, processor: &P) -> Result<BatchOutcome, StreamError> {
+        let records = self.client.poll_batch(self.batch_size).await?;
+        self.process_records(records, processor).await
+    }
+
+    /// Run batches until `shutdown` completes, then flush the client
+    ///
+    /// シャットダウンはポーリングの待ち時間にだけ割り込むため、処理中のバッチは最後まで
+    /// 処理してオフセットをコミットしてから戻る (割り込まれたポーリングの分は再配信される)。
+    /// バッチの失敗はログに残して続行する
+    pub async fn run_until<P, F>(&self, processor: &P, shutdown: F) -> BatchOutcome
+    where
+        P: StreamProcessor + ?Sized,
+        F: std::future::Future<Output = ()>,
+    {
+        let mut shutdown = std::pin::pin!(shutdown);
+        let mut total = BatchOutcome::default();
+        loop {
+            let records = tokio::select! {
+                biased;
+                _ = &mut shutdown => break,
+                records = self.client.poll_batch(self.batch_size) => records,
+            };
+            match records {
+                Ok(records) => match self.process_records(records, processor).await {
+                    Ok(outcome) => total.add(outcome),
+                    Err(e) => {
+                        warn!("Kafka consumer batch failed: {}", e);
+                        total.failed_batches += 1;
+                    }
+                },
+                Err(e) => {
+                    warn!("Kafka consumer poll failed: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                }
+            }
+        }
+        if let Err(e) = self.client.flush().await {
+            warn!("Failed to flush Kafka client on shutdown: {}", e);
+        }
+        total
+    }
+
+    async fn process_records<P: StreamProcessor + ?Sized>(&self, records: Vec<KafkaRecord>, processor: &P) -> Result<BatchOutcome, StreamError> {
+        traced_batch("kafka", None, self.consume_records(records, processor), |outcome| outcome.records).await
+    }
+
+    async fn consume_records<P: StreamProcessor + ?Sized>(&self, records: Vec<KafkaRecord>, processor: &P) -> Result<BatchOutcome, StreamError> {
+        let mut outcome = BatchOutcome { records: records.len(), ..BatchOutcome::default() };
+        if records.is_empty() {
+            return Ok(outcome);
+        }
+
+        let (start, next) = batch_offsets(&records);
+        let mut events = Vec::with_capacity(records.len());
+        for record in &records {
+            match self.codec.decode(&record.payload).await {
+                Ok(event) => events.push(event),
+                Err(e) => {
+                    // 再配信しても復号できないため、処理済みとして扱う
+                    warn!("Skipping undecodable record {}/{}@{}: {}", record.topic, record.partition, record.offset, e);
+                    outcome.undecodable += 1;
+                }
+            }
+        }
+
+        match self.strategy {
+            CommitStrategy::AtMostOnce => {
+                self.client.commit(&next).await?;
+                outcome.committed = next;
+                let count = events.len();
+                if let Err(e) = processor.process_batch(events).await {
+                    warn!("Dropping {} events after at-most-once commit: {}", count, e);
+                    outcome.dropped = count;
+                }
+            }
+            CommitStrategy::AtLeastOnce => {
+                if let Err(e) = processor.process_batch(events).await {
+                    self.client.seek(&start).await?;
+                    return Err(e);
+                }
+                self.client.commit(&next).await?;
+                outcome.committed = next;
+            }
+            CommitStrategy::ExactlyOnce => {
+                self.client.begin_transaction().await?;
+                let result = match processor.process_batch_with_output(events).await {
+                    Ok(outputs) => match self.client.produce_in_transaction(&outputs).await {
+                        Ok(()) => self.client.commit_transaction(&next).await.map(|_| outputs.len()),
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(produced) => {
+                        outcome.produced = produced;
+                        outcome.committed = next;
+                    }
+                    Err(e) => {
+                        if let Err(abort_error) = self.client.abort_transaction().await {
+                            warn!("Failed to abort transaction: {}", abort_error);
+                        }
+                        self.client.seek(&start).await?;
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+/// Run one consumer batch inside a `stream.consume` span
+///
+/// バッチ内のイベントの処理 (推論・SIEM 送信など) はこのスパンの子になる
+async fn traced_batch<T>(
+    stream_type: &'static str,
+    source: Option<&str>,
+    batch: impl std::future::Future<Output = Result<T, StreamError>>,
+    events: impl Fn(&T) -> usize,
+) -> Result<T, StreamError> {
+    let span = tracing::info_span!(
+        spans::STREAM_CONSUME,
+        stream.type = stream_type,
+        stream.topic = source,
+        event.count = tracing::field::Empty,
+        error.type = tracing::field::Empty
+    );
+    let result = batch.instrument(span.clone()).await;
+    match &result {
+        Ok(outcome) => span.record(attributes::EVENT_COUNT, events(outcome)),
+        Err(e) => span.record(attributes::ERROR_TYPE, tracing::field::display(e)),
+    };
+    result
+}
+
+/// First offset and next offset to commit for each partition in a batch
+fn batch_offsets(records: &[KafkaRecord]) -> (Vec<PartitionOffset>, Vec<PartitionOffset>) {
+    let mut ranges: BTreeMap<(String, i32), (i64, i64)> = BTreeMap::new();
+    for record in records {
+        let range = ranges.entry((record.topic.clone(), record.partition)).or_insert((record.offset, record.offset));
+        range.0 = range.0.min(record.offset);
+        range.1 = range.1.max(record.offset);
+    }
+    ranges.into_iter()
+        .map(|((topic, partition), (first, last))| {
+            (
+                PartitionOffset { topic: topic.clone(), partition, offset: first },
+                PartitionOffset { topic, partition, offset: last + 1 },
+            )
+        })
+        .unzip()
+}
+
+/// librdkafka-backed client
+#[cfg(feature = "kafka")]
+pub struct RdKafkaClient {
+    consumer: rdkafka::consumer::StreamConsumer,
+    /// Transactional producer (exactly-once only)
+    producer: Option<rdkafka::producer::FutureProducer>,
+    produce_topic: String,
+    timeout: std::time::Duration,
+    codec: Arc<EventCodec>,
+}
+
+#[cfg(feature = "kafka")]
+impl RdKafkaClient {
+    /// Connect, subscribe and (for exactly-once) initialize transactions
+    pub fn new(config: &crate::config::KafkaConfig) -> Result<Self, StreamError> {
+        use rdkafka::consumer::Consumer;
+        use rdkafka::producer::Producer;
+
+        config.validate()?;
+        let timeout = std::time::Duration::from_secs(30);
+        let consumer: rdkafka::consumer::StreamConsumer = client_config(&config.consumer_properties())
+            .create()
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        let topics: Vec<&str> = config.consume_topics.iter().map(String::as_str).collect();
+        consumer.subscribe(&topics).map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+
+        let producer = if config.commit_strategy == CommitStrategy::ExactlyOnce {
+            let producer: rdkafka::producer::FutureProducer = client_config(&config.producer_properties())
+                .create()
+                .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+            producer.init_transactions(timeout).map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+            Some(producer)
+        } else {
+            None
+        };
+
+        Ok(Self { consumer, producer, produce_topic: config.produce_topic.clone(), timeout, codec: Arc::default() })
+    }
+
+    /// Encode transactional output with `codec` (plain JSON by default)
+    pub fn with_codec(mut self, codec: Arc<EventCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    fn producer(&self) -> Result<&rdkafka::producer::FutureProducer, StreamError> {
+        self.producer.as_ref().ok_or_else(|| StreamError::ConfigError("transactions require the exactly-once strategy".to_string()))
+    }
+}
+
+#[cfg(feature = "kafka")]
+fn client_config(properties: &std::collections::HashMap<String, String>) -> rdkafka::ClientConfig {
+    let mut config = rdkafka::ClientConfig::new();
+    for (key, value) in properties {
+        config.set(key, value);
+    }
+    config
+}
+
+#[cfg(feature = "kafka")]
+fn topic_partition_list(offsets: &[PartitionOffset]) -> Result<rdkafka::TopicPartitionList, StreamError> {
+    let mut list = rdkafka::TopicPartitionList::new();
+    for offset in offsets {
+        list.add_partition_offset(&offset.topic, offset.partition, rdkafka::Offset::Offset(offset.offset))
+            .map_err(|e| StreamError::ConfigError(e.to_string()))?;
+    }
+    Ok(list)
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait]
+impl KafkaClient for RdKafkaClient {
+    async fn poll_batch(&self, max: usize) -> Result<Vec<KafkaRecord>, StreamError> {
+        use rdkafka::Message;
+
+        let mut records = Vec::new();
+        while records.len() < max {
+            let message = if records.is_empty() {
+                self.consumer.recv().await
+            } else {
+                // 1件目以降は届いている分だけ取る
+                match tokio::time::timeout(std::time::Duration::from_millis(50), self.consumer.recv()).await {
+                    Ok(message) => message,
+                    Err(_) => break,
+                }
+            };
+            let message = message.map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+            records.push(KafkaRecord {
+                topic: message.topic().to_string(),
+                partition: message.partition(),
+                offset: message.offset(),
+                payload: message.payload().map(<[u8]>::to_vec).unwrap_or_default(),
+            });
+        }
+        Ok(records)
+    }
+
+    async fn commit(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError> {
+        use rdkafka::consumer::{CommitMode, Consumer};
+        self.consumer.commit(&topic_partition_list(offsets)?, CommitMode::Sync)
+            .map_err(|e| StreamError::SendError(e.to_string()))
+    }
+
+    async fn seek(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError> {
+        use rdkafka::consumer::Consumer;
+        for offset in offsets {
+            self.consumer.seek(&offset.topic, offset.partition, rdkafka::Offset::Offset(offset.offset), self.timeout)
+                .map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn begin_transaction(&self) -> Result<(), StreamError> {
+        use rdkafka::producer::Producer;
+        self.producer()?.begin_transaction().map_err(|e| StreamError::SendError(e.to_string()))
+    }
+
+    async fn produce_in_transaction(&self, events: &[StreamingEvent]) -> Result<(), StreamError> {
+        let producer = self.producer()?;
+        for event in events {
+            let payload = self.codec.encode(event)?;
+            let record = rdkafka::producer::FutureRecord::to(&self.produce_topic)
+                .key(event.event_type())
+                .payload(&payload);
+            producer.send(record, self.timeout).await
+                .map_err(|(e, _)| StreamError::SendError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn commit_transaction(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError> {
+        use rdkafka::consumer::Consumer;
+        use rdkafka::producer::Producer;
+
+        let producer = self.producer()?;
+        let group = self.consumer.group_metadata()
+            .ok_or_else(|| StreamError::ConfigError("consumer has no group metadata".to_string()))?;
+        producer.send_offsets_to_transaction(&topic_partition_list(offsets)?, &group, self.timeout)
+            .map_err(|e| StreamError::SendError(e.to_string()))?;
+        producer.commit_transaction(self.timeout).map_err(|e| StreamError::SendError(e.to_string()))
+    }
+
+    async fn abort_transaction(&self) -> Result<(), StreamError> {
+        use rdkafka::producer::Producer;
+        self.producer()?.abort_transaction(self.timeout).map_err(|e| StreamError::SendError(e.to_string()))
+    }
+
+    async fn flush(&self) -> Result<(), StreamError> {
+        use rdkafka::producer::Producer;
+        match &self.producer {
+            Some(producer) => producer.flush(self.timeout).map_err(|e| StreamError::SendError(e.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Field of a Redis stream entry that holds the JSON-encoded `StreamingEvent`
+pub const REDIS_PAYLOAD_FIELD: &str = "event";
+
+/// Entry read from a Redis stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedisEntry {
+    pub id: String,
+    pub payload: Vec<u8>,
+}
+
+/// Reply of one XAUTOCLAIM call
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RedisClaim {
+    /// Cursor for the next call (`0-0` once the pending list has been scanned)
+    pub next_start: String,
+    pub entries: Vec<RedisEntry>,
+    /// Pending IDs whose entries were trimmed from the stream
+    pub deleted: Vec<String>,
+}
+
+/// Redis Streams operations needed by [`RedisGroupConsumer`]
+///
+/// 実サーバー用の実装は `redis` フィーチャの [`RedisStreamsClient`]
+#[async_trait]
+pub trait RedisStreamClient: Send + Sync {
+    /// Create the consumer group (and the stream) if it does not exist
+    async fn create_group(&self, stream: &str, group: &str) -> Result<(), StreamError>;
+
+    /// Read entries never delivered to the group; `block_ms = None` returns immediately
+    async fn read_group(
+        &self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+        block_ms: Option<u64>,
+    ) -> Result<Vec<RedisEntry>, StreamError>;
+
+    /// Take over entries pending for at least `min_idle_ms` on any consumer
+    async fn auto_claim(
+        &self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        min_idle_ms: u64,
+        start: &str,
+        count: usize,
+    ) -> Result<RedisClaim, StreamError>;
+
+    /// Acknowledge entries; returns how many were still pending
+    async fn ack(&self, stream: &str, group: &str, ids: &[String]) -> Result<usize, StreamError>;
+}
+
+/// What happened to one batch of a [`RedisGroupConsumer`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RedisBatchOutcome {
+    /// Stale entries taken over from other consumers
+    pub claimed: usize,
+    /// New entries read from the stream
+    pub read: usize,
+    /// Entries whose payload was not a `StreamingEvent` (skipped but acknowledged)
+    pub undecodable: usize,
+    pub acked: usize,
+}
+
+impl RedisBatchOutcome {
+    fn add(&mut self, other: &RedisBatchOutcome) {
+        self.claimed += other.claimed;
+        self.read += other.read;
+        self.undecodable += other.undecodable;
+        self.acked += other.acked;
+    }
+}
+
+/// Redis Streams consumer-group member
+///
+/// 複数の fukurow インスタンスが同じグループで 1 本のストリームを分担する。
+/// 各バッチではまず XAUTOCLAIM で停止・クラッシュした他コンシューマの古い保留エントリを
+/// 引き取り、残りの枠で新規エントリを読む。処理に成功したエントリだけを ACK するため、
+/// 失敗したエントリは保留のまま残り、`claim_min_idle_ms` 経過後にいずれかのメンバーが再処理する
+pub struct RedisGroupConsumer<C: RedisStreamClient> {
+    client: C,
+    stream: String,
+    group: String,
+    consumer: String,
+    min_idle_ms: u64,
+    claim_count: usize,
+    block_ms: u64,
+    batch_size: usize,
+    /// XAUTOCLAIM cursor carried between batches
+    claim_cursor: std::sync::Mutex<String>,
+    codec: Arc<EventCodec>,
+}
+
+impl<C: RedisStreamClient> RedisGroupConsumer<C> {
+    pub fn new(client: C, config: &crate::config::RedisConfig) -> Result<Self, StreamError> {
+        config.validate()?;
+        Ok(Self {
+            client,
+            stream: config.stream_key.clone(),
+            group: config.consumer_group.clone(),
+            consumer: config.consumer_name.clone(),
+            min_idle_ms: config.claim_min_idle_ms,
+            claim_count: config.claim_count,
+            block_ms: config.block_ms,
+            batch_size: 100,
+            claim_cursor: std::sync::Mutex::new("0-0".to_string()),
+            codec: Arc::default(),
+        })
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Decode payloads with `codec` (plain JSON by default)
+    pub fn with_codec(mut self, codec: Arc<EventCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    pub fn consumer_name(&self) -> &str {
+        &self.consumer
+    }
+
+    /// Create the consumer group; safe to call from every member
+    pub async fn init(&self) -> Result<(), StreamError> {
+        self.client.create_group(&self.stream, &self.group).await
+    }
+
+    /// Claim stale pending entries, read new ones, process them and acknowledge
+    pub async fn run_batch<P: StreamProcessor + ?Sized>(&self, processor: &P) -> Result<RedisBatchOutcome, StreamError> {
+        traced_batch("redis", Some(self.stream.as_str()), self.consume_batch(processor), |outcome| outcome.claimed + outcome.read).await
+    }
+
+    async fn consume_batch<P: StreamProcessor + ?Sized>(&self, processor: &P) -> Result<RedisBatchOutcome, StreamError> {
+        let mut entries = self.claim_stale().await?;
+        let mut outcome = RedisBatchOutcome { claimed: entries.len(), ..RedisBatchOutcome::default() };
+
+        let room = self.batch_size.saturating_sub(entries.len());
+        if room > 0 {
+            // 引き取ったエントリがあるときは待たずにすぐ処理へ進む
+            let block = if entries.is_empty() { Some(self.block_ms) } else { None };
+            let fresh = self.client.read_group(&self.stream, &self.group, &self.consumer, room, block).await?;
+            outcome.read = fresh.len();
+            entries.extend(fresh);
+        }
+        if entries.is_empty() {
+            return Ok(outcome);
+        }
+
+        let mut events = Vec::with_capacity(entries.len());
+        let mut undecodable = Vec::new();
+        let mut decoded = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match self.codec.decode(&entry.payload).await {
+                Ok(event) => {
+                    events.push(event);
+                    decoded.push(entry.id);
+                }
+                Err(e) => {
+                    // 何度引き取っても復号できないため、処理済みとして ACK する
+                    warn!("Skipping undecodable stream entry {}/{}: {}", self.stream, entry.id, e);
+                    undecodable.push(entry.id);
+                }
+            }
+        }
+        outcome.undecodable = undecodable.len();
+        if !undecodable.is_empty() {
+            outcome.acked += self.client.ack(&self.stream, &self.group, &undecodable).await?;
+        }
+
+        if !events.is_empty() {
+            processor.process_batch(events).await?;
+            outcome.acked += self.client.ack(&self.stream, &self.group, &decoded).await?;
+        }
+        Ok(outcome)
+    }
+
+    /// Run batches until `shutdown` completes
+    ///
+    /// シャットダウン要求は各バッチの合間にだけ確認するため、処理中のバッチは最後まで
+    /// 処理して ACK してから戻る (新規読み取りの待ち時間は最大 `block_ms`)。
+    /// バッチの失敗はログに残して続行し、未 ACK のエントリは後で引き取られる
+    pub async fn run_until<P, F>(&self, processor: &P, shutdown: F) -> RedisBatchOutcome
+    where
+        P: StreamProcessor + ?Sized,
+        F: std::future::Future<Output = ()>,
+    {
+        use futures::FutureExt;
+
+        let mut shutdown = Box::pin(shutdown);
+        let mut total = RedisBatchOutcome::default();
+        while shutdown.as_mut().now_or_never().is_none() {
+            match self.run_batch(processor).await {
+                Ok(outcome) => total.add(&outcome),
+                Err(e) => {
+                    warn!("Redis consumer {} batch failed: {}", self.consumer, e);
+                    tokio::time::sleep(std::time::Duration::from_millis(self.block_ms.min(1_000))).await;
+                }
+            }
+        }
+        total
+    }
+
+    async fn claim_stale(&self) -> Result<Vec<RedisEntry>, StreamError> {
+        let start = self.claim_cursor.lock().unwrap().clone();
+        let count = self.claim_count.min(self.batch_size);
+        let claim = self.client
+            .auto_claim(&self.stream, &self.group, &self.consumer, self.min_idle_ms, &start, count)
+            .await?;
+        if !claim.deleted.is_empty() {
+            warn!("{} pending entries of {} were trimmed before they could be claimed", claim.deleted.len(), self.stream);
+        }
+        *self.claim_cursor.lock().unwrap() = claim.next_start;
+        Ok(claim.entries)
+    }
+}
+
+/// redis-rs backed client
+#[cfg(feature = "redis")]
+pub struct RedisStreamsClient {
+    connection: redis::aio::MultiplexedConnection,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStreamsClient {
+    pub async fn connect(config: &crate::config::RedisConfig) -> Result<Self, StreamError> {
+        let mut info = redis::IntoConnectionInfo::into_connection_info(config.url.as_str())
+            .map_err(|e| StreamError::ConfigError(e.to_string()))?;
+        if let Some(database) = config.database {
+            info.redis.db = i64::from(database);
+        }
+        let connection = redis::Client::open(info)
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?
+            .get_multiplexed_tokio_connection()
+            .await
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        Ok(Self { connection })
+    }
+}
+
+/// `[id, [field, value, ...]]` pairs of an XREADGROUP / XAUTOCLAIM reply
+#[cfg(feature = "redis")]
+fn redis_entries(value: &redis::Value) -> Vec<RedisEntry> {
+    let items = match value {
+        redis::Value::Bulk(items) => items,
+        _ => return Vec::new(),
+    };
+    items.iter().filter_map(|item| {
+        let (id, fields) = match item {
+            redis::Value::Bulk(pair) if pair.len() == 2 => (&pair[0], &pair[1]),
+            _ => return None,
+        };
+        let id: String = redis::from_redis_value(id).ok()?;
+        // Redis 6.2 の XAUTOCLAIM は削除済みエントリのフィールドを nil で返す
+        let fields: Vec<Vec<u8>> = redis::from_redis_value(fields).ok()?;
+        let payload = fields.chunks(2)
+            .find(|pair| pair.len() == 2 && pair[0] == REDIS_PAYLOAD_FIELD.as_bytes())
+            .map(|pair| pair[1].clone())
+            .unwrap_or_default();
+        Some(RedisEntry { id, payload })
+    }).collect()
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RedisStreamClient for RedisStreamsClient {
+    async fn create_group(&self, stream: &str, group: &str) -> Result<(), StreamError> {
+        let result: redis::RedisResult<()> = redis::cmd("XGROUP")
+            .arg("CREATE").arg(stream).arg(group).arg("$").arg("MKSTREAM")
+            .query_async(&mut self.connection.clone())
+            .await;
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+            Err(e) => Err(StreamError::ConnectionError(e.to_string())),
+        }
+    }
+
+    async fn read_group(
+        &self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+        block_ms: Option<u64>,
+    ) -> Result<Vec<RedisEntry>, StreamError> {
+        let mut cmd = redis::cmd("XREADGROUP");
+        cmd.arg("GROUP").arg(group).arg(consumer).arg("COUNT").arg(count);
+        if let Some(block_ms) = block_ms {
+            cmd.arg("BLOCK").arg(block_ms);
+        }
+        cmd.arg("STREAMS").arg(stream).arg(">");
+        let reply: redis::Value = cmd.query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+
+        // [[stream, entries]] / タイムアウト時は nil
+        let streams = match reply {
+            redis::Value::Bulk(streams) => streams,
+            _ => return Ok(Vec::new()),
+        };
+        Ok(streams.iter().flat_map(|entry| match entry {
+            redis::Value::Bulk(pair) if pair.len() == 2 => redis_entries(&pair[1]),
+            _ => Vec::new(),
+        }).collect())
+    }
+
+    async fn auto_claim(
+        &self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        min_idle_ms: u64,
+        start: &str,
+        count: usize,
+    ) -> Result<RedisClaim, StreamError> {
+        let reply: redis::Value = redis::cmd("XAUTOCLAIM")
+            .arg(stream).arg(group).arg(consumer).arg(min_idle_ms).arg(start).arg("COUNT").arg(count)
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+
+        let parts = match reply {
+            redis::Value::Bulk(parts) if parts.len() >= 2 => parts,
+            other => return Err(StreamError::ReceiveError(format!("unexpected XAUTOCLAIM reply: {:?}", other))),
+        };
+        let next_start: String = redis::from_redis_value(&parts[0]).map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+        // 3 番目の要素 (削除済み ID) は Redis 7 以降のみ
+        let deleted: Vec<String> = parts.get(2).and_then(|value| redis::from_redis_value(value).ok()).unwrap_or_default();
+        Ok(RedisClaim { next_start, entries: redis_entries(&parts[1]), deleted })
+    }
+
+    async fn ack(&self, stream: &str, group: &str, ids: &[String]) -> Result<usize, StreamError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        redis::cmd("XACK").arg(stream).arg(group).arg(ids)
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| StreamError::SendError(e.to_string()))
+    }
+}
+
+/// Message delivered to a JetStream durable pull consumer
+#[derive(Debug, Clone, PartialEq)]
+pub struct JetStreamMessage {
+    pub subject: String,
+    /// Position in the stream (stable across redeliveries)
+    pub stream_sequence: u64,
+    pub consumer_sequence: u64,
+    /// Number of times this message has been delivered, including this one
+    pub delivered: i64,
+    pub payload: Vec<u8>,
+}
+
+/// Acknowledgement of a JetStream message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JetStreamAck {
+    /// Processed; never redeliver
+    Ack,
+    /// Processing failed; redeliver after the delay (immediately when `None`)
+    Nak { delay: Option<std::time::Duration> },
+    /// Can never be processed; stop redelivering
+    Term,
+}
+
+/// JetStream operations needed by [`JetStreamConsumer`] and [`crate::JetStreamProducer`]
+///
+/// 実サーバー用の実装は `nats` フィーチャの [`NatsJetStreamClient`]
+#[async_trait]
+pub trait JetStreamClient: Send + Sync {
+    /// Create the stream, or update its subjects and retention if it exists
+    async fn ensure_stream(&self, stream: &str, subjects: &[String], max_age: Option<std::time::Duration>) -> Result<(), StreamError>;
+
+    /// Create the durable pull consumer if it does not exist (explicit acks)
+    async fn ensure_consumer(&self, stream: &str, config: &crate::config::JetStreamConfig, filter_subject: &str) -> Result<(), StreamError>;
+
+    /// Delete a durable consumer; deleting a missing consumer succeeds
+    async fn delete_consumer(&self, stream: &str, durable: &str) -> Result<(), StreamError>;
+
+    /// Pull up to `max` messages, waiting at most `expires` for the first
+    async fn fetch(&self, stream: &str, durable: &str, max: usize, expires: std::time::Duration) -> Result<Vec<JetStreamMessage>, StreamError>;
+
+    async fn acknowledge(&self, message: &JetStreamMessage, ack: JetStreamAck) -> Result<(), StreamError>;
+
+    /// Publish and wait for the stream's acknowledgement; returns the stored sequence
+    async fn publish(&self, subject: &str, payload: Vec<u8>) -> Result<u64, StreamError>;
+}
+
+/// What happened to one batch of a [`JetStreamConsumer`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct JetStreamBatchOutcome {
+    pub fetched: usize,
+    /// Messages delivered before (ack timeout or NAK)
+    pub redelivered: usize,
+    pub acked: usize,
+    /// Batches whose processing failed (their messages were NAKed)
+    pub failed_batches: usize,
+    /// Messages whose payload was not a `StreamingEvent` (terminated)
+    pub terminated: usize,
+}
+
+impl JetStreamBatchOutcome {
+    fn add(&mut self, other: &JetStreamBatchOutcome) {
+        self.fetched += other.fetched;
+        self.redelivered += other.redelivered;
+        self.acked += other.acked;
+        self.failed_batches += other.failed_batches;
+        self.terminated += other.terminated;
+    }
+}
+
+/// JetStream durable pull consumer
+///
+/// ストリームと durable コンシューマを設定から用意し、推論に成功したメッセージだけを ACK する。
+/// 失敗したバッチは NAK して `nak_delay_ms` 後に再配信させ、`max_deliver` 回で諦める。
+/// durable の位置はサーバー側に残るため、再起動後は未 ACK のメッセージから再開する
+pub struct JetStreamConsumer<C: JetStreamClient> {
+    client: C,
+    subject: String,
+    config: std::sync::Mutex<crate::config::JetStreamConfig>,
+    batch_size: usize,
+    codec: Arc<EventCodec>,
+}
+
+impl<C: JetStreamClient> JetStreamConsumer<C> {
+    /// Consumer for a NATS connection with `jetstream` settings
+    pub fn new(client: C, config: &crate::config::NATSConfig) -> Result<Self, StreamError> {
+        let jetstream = config.jetstream.clone()
+            .ok_or_else(|| StreamError::ConfigError("NATS connection has no jetstream settings".to_string()))?;
+        Self::with_config(client, &config.subject, jetstream)
+    }
+
+    pub fn with_config(client: C, subject: &str, config: crate::config::JetStreamConfig) -> Result<Self, StreamError> {
+        config.validate()?;
+        Ok(Self { client, subject: subject.to_string(), config: std::sync::Mutex::new(config), batch_size: 100, codec: Arc::default() })
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Decode payloads with `codec` (plain JSON by default)
+    pub fn with_codec(mut self, codec: Arc<EventCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    pub fn config(&self) -> crate::config::JetStreamConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Provision the stream and the durable consumer; safe to call from every instance
+    pub async fn init(&self) -> Result<(), StreamError> {
+        let config = self.config();
+        let max_age = config.max_age_secs.map(std::time::Duration::from_secs);
+        self.client.ensure_stream(&config.stream, &config.stream_subjects(&self.subject), max_age).await?;
+        self.client.ensure_consumer(&config.stream, &config, &self.subject).await
+    }
+
+    /// Restart delivery from a stream sequence or time
+    ///
+    /// durable の配信開始位置は作成後に変えられないため、削除して作り直す。
+    /// 同じ durable を読んでいる他のインスタンスも新しい位置から読むことになる
+    pub async fn replay_from(&self, deliver_policy: crate::config::JetStreamDeliverPolicy) -> Result<(), StreamError> {
+        let config = {
+            let mut config = self.config.lock().unwrap();
+            config.deliver_policy = deliver_policy;
+            config.clone()
+        };
+        self.client.delete_consumer(&config.stream, &config.durable_name).await?;
+        self.client.ensure_consumer(&config.stream, &config, &self.subject).await
+    }
+
+    /// Fetch a batch, process it and acknowledge according to the outcome
+    pub async fn run_batch<P: StreamProcessor + ?Sized>(&self, processor: &P) -> Result<JetStreamBatchOutcome, StreamError> {
+        traced_batch("nats", Some(self.subject.as_str()), self.consume_batch(processor), |outcome| outcome.fetched).await
+    }
+
+    async fn consume_batch<P: StreamProcessor + ?Sized>(&self, processor: &P) -> Result<JetStreamBatchOutcome, StreamError> {
+        let config = self.config();
+        let expires = std::time::Duration::from_millis(config.fetch_expires_ms);
+        let messages = self.client.fetch(&config.stream, &config.durable_name, self.batch_size, expires).await?;
+        let mut outcome = JetStreamBatchOutcome {
+            fetched: messages.len(),
+            redelivered: messages.iter().filter(|message| message.delivered > 1).count(),
+            ..JetStreamBatchOutcome::default()
+        };
+
+        let mut events = Vec::with_capacity(messages.len());
+        let mut decoded = Vec::with_capacity(messages.len());
+        for message in messages {
+            match self.codec.decode(&message.payload).await {
+                Ok(event) => {
+                    events.push(event);
+                    decoded.push(message);
+                }
+                Err(e) => {
+                    // 再配信しても復号できないため TERM で配信を止める
+                    warn!("Terminating undecodable JetStream message {}#{}: {}", config.stream, message.stream_sequence, e);
+                    self.client.acknowledge(&message, JetStreamAck::Term).await?;
+                    outcome.terminated += 1;
+                }
+            }
+        }
+        if events.is_empty() {
+            return Ok(outcome);
+        }
+
+        match processor.process_batch(events).await {
+            Ok(()) => {
+                for message in &decoded {
+                    self.client.acknowledge(message, JetStreamAck::Ack).await?;
+                }
+                outcome.acked = decoded.len();
+                Ok(outcome)
+            }
+            Err(e) => {
+                let delay = Some(std::time::Duration::from_millis(config.nak_delay_ms));
+                for message in &decoded {
+                    self.client.acknowledge(message, JetStreamAck::Nak { delay }).await?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Run batches until `shutdown` completes
+    ///
+    /// 処理中のバッチは ACK / NAK まで済ませてから戻る。バッチの失敗はログに残して続行する
+    pub async fn run_until<P, F>(&self, processor: &P, shutdown: F) -> JetStreamBatchOutcome
+    where
+        P: StreamProcessor + ?Sized,
+        F: std::future::Future<Output = ()>,
+    {
+        use futures::FutureExt;
+
+        let mut shutdown = Box::pin(shutdown);
+        let mut total = JetStreamBatchOutcome::default();
+        while shutdown.as_mut().now_or_never().is_none() {
+            match self.run_batch(processor).await {
+                Ok(outcome) => total.add(&outcome),
+                Err(e) => {
+                    warn!("JetStream consumer {} batch failed: {}", self.config().durable_name, e);
+                    total.failed_batches += 1;
+                    tokio::time::sleep(std::time::Duration::from_millis(self.config().nak_delay_ms.min(1_000))).await;
+                }
+            }
+        }
+        total
+    }
+}
+
+/// async-nats backed JetStream client
+#[cfg(feature = "nats")]
+pub struct NatsJetStreamClient {
+    context: async_nats::jetstream::Context,
+    /// Fetched messages awaiting acknowledgement, by stream sequence
+    in_flight: tokio::sync::Mutex<std::collections::HashMap<u64, async_nats::jetstream::Message>>,
+}
+
+#[cfg(feature = "nats")]
+impl NatsJetStreamClient {
+    /// Connect to the configured servers; `credentials` is a `.creds` file path
+    pub async fn connect(config: &crate::config::NATSConfig) -> Result<Self, StreamError> {
+        let mut options = async_nats::ConnectOptions::new();
+        if let Some(credentials) = &config.credentials {
+            options = options.credentials_file(credentials).await
+                .map_err(|e| StreamError::ConfigError(e.to_string()))?;
+        }
+        let client = options.connect(config.servers.join(",").as_str()).await
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        Ok(Self::new(client))
+    }
+
+    pub fn new(client: async_nats::Client) -> Self {
+        Self { context: async_nats::jetstream::new(client), in_flight: tokio::sync::Mutex::default() }
+    }
+
+    async fn stream(&self, stream: &str) -> Result<async_nats::jetstream::stream::Stream, StreamError> {
+        self.context.get_stream(stream).await.map_err(|e| StreamError::ConnectionError(e.to_string()))
+    }
+}
+
+#[cfg(feature = "nats")]
+fn nats_deliver_policy(policy: crate::config::JetStreamDeliverPolicy) -> Result<async_nats::jetstream::consumer::DeliverPolicy, StreamError> {
+    use crate::config::JetStreamDeliverPolicy;
+    use async_nats::jetstream::consumer::DeliverPolicy;
+
+    Ok(match policy {
+        JetStreamDeliverPolicy::All => DeliverPolicy::All,
+        JetStreamDeliverPolicy::New => DeliverPolicy::New,
+        JetStreamDeliverPolicy::Last => DeliverPolicy::Last,
+        JetStreamDeliverPolicy::ByStartSequence { start_sequence } => DeliverPolicy::ByStartSequence { start_sequence },
+        JetStreamDeliverPolicy::ByStartTime { start_time } => DeliverPolicy::ByStartTime {
+            start_time: time::OffsetDateTime::from_unix_timestamp_nanos(i128::from(start_time.timestamp_nanos_opt().unwrap_or_default()))
+                .map_err(|e| StreamError::ConfigError(e.to_string()))?,
+        },
+    })
+}
+
+#[cfg(feature = "nats")]
+#[async_trait]
+impl JetStreamClient for NatsJetStreamClient {
+    async fn ensure_stream(&self, stream: &str, subjects: &[String], max_age: Option<std::time::Duration>) -> Result<(), StreamError> {
+        let config = async_nats::jetstream::stream::Config {
+            name: stream.to_string(),
+            subjects: subjects.to_vec(),
+            max_age: max_age.unwrap_or_default(),
+            ..Default::default()
+        };
+        match self.context.get_stream(stream).await {
+            Ok(_) => self.context.update_stream(&config).await.map(|_| ()),
+            Err(_) => self.context.create_stream(config).await.map(|_| ()),
+        }
+        .map_err(|e| StreamError::ConnectionError(e.to_string()))
+    }
+
+    async fn ensure_consumer(&self, stream: &str, config: &crate::config::JetStreamConfig, filter_subject: &str) -> Result<(), StreamError> {
+        use async_nats::jetstream::consumer::{pull, AckPolicy};
+
+        let consumer = pull::Config {
+            durable_name: Some(config.durable_name.clone()),
+            deliver_policy: nats_deliver_policy(config.deliver_policy)?,
+            ack_policy: AckPolicy::Explicit,
+            ack_wait: std::time::Duration::from_millis(config.ack_wait_ms),
+            max_deliver: config.max_deliver,
+            filter_subject: filter_subject.to_string(),
+            ..Default::default()
+        };
+        self.stream(stream).await?
+            .get_or_create_consumer(&config.durable_name, consumer)
+            .await
+            .map(|_: async_nats::jetstream::consumer::PullConsumer| ())
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))
+    }
+
+    async fn delete_consumer(&self, stream: &str, durable: &str) -> Result<(), StreamError> {
+        let stream = self.stream(stream).await?;
+        match stream.delete_consumer(durable).await {
+            Ok(_) => Ok(()),
+            // 存在しない durable の削除は成功扱い
+            Err(e) if e.to_string().contains("not found") => Ok(()),
+            Err(e) => Err(StreamError::ConnectionError(e.to_string())),
+        }
+    }
+
+    async fn fetch(&self, stream: &str, durable: &str, max: usize, expires: std::time::Duration) -> Result<Vec<JetStreamMessage>, StreamError> {
+        let consumer: async_nats::jetstream::consumer::PullConsumer = self.stream(stream).await?
+            .get_consumer(durable)
+            .await
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        let mut batch = consumer.fetch().max_messages(max).expires(expires).messages().await
+            .map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+
+        let mut messages = Vec::new();
+        let mut in_flight = self.in_flight.lock().await;
+        while let Some(message) = batch.next().await {
+            let message = message.map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+            let info = message.info().map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+            let fetched = JetStreamMessage {
+                subject: message.subject.to_string(),
+                stream_sequence: info.stream_sequence,
+                consumer_sequence: info.consumer_sequence,
+                delivered: info.delivered,
+                payload: message.payload.to_vec(),
+            };
+            in_flight.insert(fetched.stream_sequence, message);
+            messages.push(fetched);
+        }
+        Ok(messages)
+    }
+
+    async fn acknowledge(&self, message: &JetStreamMessage, ack: JetStreamAck) -> Result<(), StreamError> {
+        use async_nats::jetstream::AckKind;
+
+        let pending = self.in_flight.lock().await.remove(&message.stream_sequence)
+            .ok_or_else(|| StreamError::SendError(format!("message {} is not in flight", message.stream_sequence)))?;
+        let kind = match ack {
+            JetStreamAck::Ack => AckKind::Ack,
+            JetStreamAck::Nak { delay } => AckKind::Nak(delay),
+            JetStreamAck::Term => AckKind::Term,
+        };
+        pending.ack_with(kind).await.map_err(|e| StreamError::SendError(e.to_string()))
+    }
+
+    async fn publish(&self, subject: &str, payload: Vec<u8>) -> Result<u64, StreamError> {
+        let ack = self.context.publish(subject.to_string(), payload.into()).await
+            .map_err(|e| StreamError::SendError(e.to_string()))?
+            .await
+            .map_err(|e| StreamError::SendError(e.to_string()))?;
+        Ok(ack.sequence)
+    }
+}
+
+/// Message delivered to an AMQP 0.9.1 consumer
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmqpDelivery {
+    /// Channel-scoped tag used to acknowledge the delivery
+    pub delivery_tag: u64,
+    pub routing_key: String,
+    /// The broker delivered this message before (requeued or consumer lost)
+    pub redelivered: bool,
+    /// Deliveries counted by a quorum queue (`x-delivery-count`), if any
+    pub delivery_count: Option<u64>,
+    pub payload: Vec<u8>,
+}
+
+/// Acknowledgement of an AMQP delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmqpAck {
+    Ack,
+    /// Reject; requeue for redelivery or dead-letter (drop when no dead-letter exchange)
+    Nack { requeue: bool },
+}
+
+/// Broker answer to a confirmed publish
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishConfirm {
+    /// Stored by the broker (or confirms are disabled)
+    Ack,
+    /// Not stored; the publisher should send it again
+    Nack,
+}
+
+/// AMQP operations needed by [`RabbitMQConsumer`] and [`crate::RabbitMQProducer`]
+///
+/// 実サーバー用の実装は `rabbitmq` フィーチャの [`LapinClient`]
+#[async_trait]
+pub trait AmqpClient: Send + Sync {
+    /// Declare the exchange, the queue (with its `x-` arguments) and the binding, plus the dead-letter exchange and queue
+    async fn declare_topology(&self, config: &crate::config::RabbitMQConfig) -> Result<(), StreamError>;
+
+    /// Limit unacknowledged deliveries on the channel (basic.qos)
+    async fn set_prefetch(&self, count: u16) -> Result<(), StreamError>;
+
+    /// Start consuming the queue (basic.consume, explicit acks)
+    async fn consume(&self, queue: &str) -> Result<(), StreamError>;
+
+    /// Take up to `max` deliveries, waiting at most `timeout` for the first
+    async fn fetch(&self, max: usize, timeout: std::time::Duration) -> Result<Vec<AmqpDelivery>, StreamError>;
+
+    async fn acknowledge(&self, delivery_tag: u64, ack: AmqpAck) -> Result<(), StreamError>;
+
+    /// Publish a persistent message and wait for its confirm when confirms are enabled
+    async fn publish(&self, exchange: &str, routing_key: &str, payload: &[u8]) -> Result<PublishConfirm, StreamError>;
+}
+
+/// What happened to one batch of a [`RabbitMQConsumer`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RabbitMQBatchOutcome {
+    pub fetched: usize,
+    /// Deliveries the broker had delivered before
+    pub redelivered: usize,
+    pub acked: usize,
+    /// Deliveries of failed batches put back on the queue
+    pub requeued: usize,
+    /// Deliveries rejected without requeue (undecodable, or failed again with a dead-letter exchange)
+    pub dead_lettered: usize,
+    /// Batches whose processing failed
+    pub failed_batches: usize,
+}
+
+impl RabbitMQBatchOutcome {
+    fn add(&mut self, other: &RabbitMQBatchOutcome) {
+        self.fetched += other.fetched;
+        self.redelivered += other.redelivered;
+        self.acked += other.acked;
+        self.requeued += other.requeued;
+        self.dead_lettered += other.dead_lettered;
+        self.failed_batches += other.failed_batches;
+    }
+}
+
+/// RabbitMQ queue consumer with explicit acknowledgements
+///
+/// 設定からエクスチェンジ・キュー・デッドレターを宣言し、推論に成功したバッチだけを ACK する。
+/// 失敗したバッチは NACK して再キューする。クォーラムキューは `delivery_limit` でブローカーが
+/// デッドレターに回すが、クラシックキューは配信回数を持たないため、デッドレター先があれば
+/// 再配信でも失敗したメッセージは再キューせずにデッドレターへ送る
+pub struct RabbitMQConsumer<C: AmqpClient> {
+    client: C,
+    config: crate::config::RabbitMQConfig,
+    batch_size: usize,
+    fetch_timeout: std::time::Duration,
+    codec: Arc<EventCodec>,
+}
+
+impl<C: AmqpClient> RabbitMQConsumer<C> {
+    pub fn new(client: C, config: crate::config::RabbitMQConfig) -> Result<Self, StreamError> {
+        config.validate()?;
+        Ok(Self { client, config, batch_size: 100, fetch_timeout: std::time::Duration::from_secs(5), codec: Arc::default() })
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Decode payloads with `codec` (plain JSON by default)
+    pub fn with_codec(mut self, codec: Arc<EventCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn with_fetch_timeout(mut self, fetch_timeout: std::time::Duration) -> Self {
+        self.fetch_timeout = fetch_timeout;
+        self
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    pub fn config(&self) -> &crate::config::RabbitMQConfig {
+        &self.config
+    }
+
+    /// Prefetch actually requested from the broker
+    ///
+    /// バッチ全体を処理してから ACK するため、prefetch がバッチサイズより小さいと
+    /// バッチが埋まらずに毎回タイムアウトまで待つことになる
+    pub fn effective_prefetch(&self) -> u16 {
+        let batch = u16::try_from(self.batch_size).unwrap_or(u16::MAX);
+        self.config.prefetch_count.max(batch)
+    }
+
+    /// Declare the topology, set the prefetch and start consuming
+    pub async fn init(&self) -> Result<(), StreamError> {
+        self.client.declare_topology(&self.config).await?;
+        self.client.set_prefetch(self.effective_prefetch()).await?;
+        self.client.consume(&self.config.queue).await
+    }
+
+    /// Change the prefetch of a running consumer (e.g. when processing falls behind)
+    pub async fn tune_prefetch(&mut self, prefetch_count: u16) -> Result<(), StreamError> {
+        if prefetch_count == 0 {
+            return Err(StreamError::ConfigError("prefetch_count must be at least 1".to_string()));
+        }
+        self.config.prefetch_count = prefetch_count;
+        self.client.set_prefetch(self.effective_prefetch()).await
+    }
+
+    /// Fetch a batch, process it and acknowledge according to the outcome
+    pub async fn run_batch<P: StreamProcessor + ?Sized>(&self, processor: &P) -> Result<RabbitMQBatchOutcome, StreamError> {
+        traced_batch("rabbitmq", Some(self.config.queue.as_str()), self.consume_batch(processor), |outcome| outcome.fetched).await
+    }
+
+    async fn consume_batch<P: StreamProcessor + ?Sized>(&self, processor: &P) -> Result<RabbitMQBatchOutcome, StreamError> {
+        let deliveries = self.client.fetch(self.batch_size, self.fetch_timeout).await?;
+        let mut outcome = RabbitMQBatchOutcome {
+            fetched: deliveries.len(),
+            redelivered: deliveries.iter().filter(|delivery| delivery.redelivered).count(),
+            ..RabbitMQBatchOutcome::default()
+        };
+
+        let mut events = Vec::with_capacity(deliveries.len());
+        let mut decoded = Vec::with_capacity(deliveries.len());
+        for delivery in deliveries {
+            match self.codec.decode(&delivery.payload).await {
+                Ok(event) => {
+                    events.push(event);
+                    decoded.push(delivery);
+                }
+                Err(e) => {
+                    warn!("Rejecting undecodable RabbitMQ delivery {} from {}: {}", delivery.delivery_tag, self.config.queue, e);
+                    self.client.acknowledge(delivery.delivery_tag, AmqpAck::Nack { requeue: false }).await?;
+                    outcome.dead_lettered += 1;
+                }
+            }
+        }
+        if events.is_empty() {
+            return Ok(outcome);
+        }
+
+        match processor.process_batch(events).await {
+            Ok(()) => {
+                for delivery in &decoded {
+                    self.client.acknowledge(delivery.delivery_tag, AmqpAck::Ack).await?;
+                }
+                outcome.acked = decoded.len();
+                Ok(outcome)
+            }
+            Err(e) => {
+                for delivery in &decoded {
+                    let requeue = self.requeue_on_failure(delivery);
+                    self.client.acknowledge(delivery.delivery_tag, AmqpAck::Nack { requeue }).await?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn requeue_on_failure(&self, delivery: &AmqpDelivery) -> bool {
+        match self.config.queue_type {
+            crate::config::RabbitMQQueueType::Quorum => true,
+            crate::config::RabbitMQQueueType::Classic => !(delivery.redelivered && self.config.dead_letter_exchange.is_some()),
+        }
+    }
+
+    /// Run batches until `shutdown` completes
+    ///
+    /// 処理中のバッチは ACK / NACK まで済ませてから戻る。バッチの失敗はログに残して続行する
+    pub async fn run_until<P, F>(&self, processor: &P, shutdown: F) -> RabbitMQBatchOutcome
+    where
+        P: StreamProcessor + ?Sized,
+        F: std::future::Future<Output = ()>,
+    {
+        use futures::FutureExt;
+
+        let mut shutdown = Box::pin(shutdown);
+        let mut total = RabbitMQBatchOutcome::default();
+        while shutdown.as_mut().now_or_never().is_none() {
+            match self.run_batch(processor).await {
+                Ok(outcome) => total.add(&outcome),
+                Err(e) => {
+                    warn!("RabbitMQ consumer on {} batch failed: {}", self.config.queue, e);
+                    total.failed_batches += 1;
+                    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                }
+            }
+        }
+        total
+    }
+}
+
+/// lapin backed AMQP 0.9.1 client
+///
+/// 1 つのチャネルで宣言・消費・発行を行う。パブリッシャー確認は設定で有効なときに confirm.select する
+#[cfg(feature = "rabbitmq")]
+pub struct LapinClient {
+    channel: lapin::Channel,
+    publisher_confirms: bool,
+    consumer: tokio::sync::Mutex<Option<lapin::Consumer>>,
+}
+
+#[cfg(feature = "rabbitmq")]
+impl LapinClient {
+    pub async fn connect(config: &crate::config::RabbitMQConfig) -> Result<Self, StreamError> {
+        let connection = lapin::Connection::connect(&config.url, lapin::ConnectionProperties::default()).await
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        let channel = connection.create_channel().await
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        if config.publisher_confirms {
+            channel.confirm_select(lapin::options::ConfirmSelectOptions::default()).await
+                .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        }
+        Ok(Self { channel, publisher_confirms: config.publisher_confirms, consumer: tokio::sync::Mutex::default() })
+    }
+}
+
+#[cfg(feature = "rabbitmq")]
+fn amqp_field_table(arguments: &BTreeMap<String, serde_json::Value>) -> lapin::types::FieldTable {
+    use lapin::types::AMQPValue;
+
+    let mut table = lapin::types::FieldTable::default();
+    for (key, value) in arguments {
+        let value = match value {
+            serde_json::Value::Bool(flag) => AMQPValue::Boolean(*flag),
+            serde_json::Value::Number(number) if number.is_i64() => AMQPValue::LongLongInt(number.as_i64().unwrap_or_default()),
+            serde_json::Value::String(text) => AMQPValue::LongString(text.as_str().into()),
+            other => AMQPValue::LongString(other.to_string().as_str().into()),
+        };
+        table.insert(key.as_str().into(), value);
+    }
+    table
+}
+
+#[cfg(feature = "rabbitmq")]
+fn amqp_exchange_kind(exchange_type: &str) -> lapin::ExchangeKind {
+    match exchange_type {
+        "direct" => lapin::ExchangeKind::Direct,
+        "fanout" => lapin::ExchangeKind::Fanout,
+        "headers" => lapin::ExchangeKind::Headers,
+        "topic" => lapin::ExchangeKind::Topic,
+        other => lapin::ExchangeKind::Custom(other.to_string()),
+    }
+}
+
+#[cfg(feature = "rabbitmq")]
+#[async_trait]
+impl AmqpClient for LapinClient {
+    async fn declare_topology(&self, config: &crate::config::RabbitMQConfig) -> Result<(), StreamError> {
+        use lapin::options::{ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions};
+        use lapin::types::FieldTable;
+
+        let error = |e: lapin::Error| StreamError::ConfigError(e.to_string());
+        let exchange_options = ExchangeDeclareOptions { durable: config.durable, ..Default::default() };
+        let queue_options = QueueDeclareOptions { durable: config.durable, ..Default::default() };
+
+        // デッドレター先はキューの宣言より先に用意する (fanout なので元のルーティングキーを問わず受ける)
+        if let Some(dead_letter_exchange) = &config.dead_letter_exchange {
+            self.channel.exchange_declare(dead_letter_exchange, lapin::ExchangeKind::Fanout, exchange_options, FieldTable::default()).await.map_err(error)?;
+            if let Some(dead_letter_queue) = &config.dead_letter_queue {
+                self.channel.queue_declare(dead_letter_queue, queue_options, FieldTable::default()).await.map_err(error)?;
+                self.channel.queue_bind(dead_letter_queue, dead_letter_exchange, "", QueueBindOptions::default(), FieldTable::default()).await.map_err(error)?;
+            }
+        }
+
+        self.channel.exchange_declare(&config.exchange, amqp_exchange_kind(&config.exchange_type), exchange_options, FieldTable::default()).await.map_err(error)?;
+        self.channel.queue_declare(&config.queue, queue_options, amqp_field_table(&config.queue_arguments())).await.map_err(error)?;
+        self.channel.queue_bind(&config.queue, &config.exchange, &config.routing_key, QueueBindOptions::default(), FieldTable::default()).await.map_err(error)?;
+        Ok(())
+    }
+
+    async fn set_prefetch(&self, count: u16) -> Result<(), StreamError> {
+        self.channel.basic_qos(count, lapin::options::BasicQosOptions::default()).await
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))
+    }
+
+    async fn consume(&self, queue: &str) -> Result<(), StreamError> {
+        let consumer = self.channel.basic_consume(queue, "fukurow", lapin::options::BasicConsumeOptions::default(), lapin::types::FieldTable::default()).await
+            .map_err(|e| StreamError::ConnectionError(e.to_string()))?;
+        *self.consumer.lock().await = Some(consumer);
+        Ok(())
+    }
+
+    async fn fetch(&self, max: usize, timeout: std::time::Duration) -> Result<Vec<AmqpDelivery>, StreamError> {
+        let mut guard = self.consumer.lock().await;
+        let consumer = guard.as_mut().ok_or_else(|| StreamError::ReceiveError("consume() has not been called".to_string()))?;
+
+        let mut deliveries = Vec::new();
+        // 最初の 1 件だけ timeout まで待ち、以降はすでに届いている分だけを取る
+        let mut wait = timeout;
+        while deliveries.len() < max {
+            let delivery = match tokio::time::timeout(wait, consumer.next()).await {
+                Ok(Some(delivery)) => delivery.map_err(|e| StreamError::ReceiveError(e.to_string()))?,
+                Ok(None) => return Err(StreamError::StreamClosed),
+                Err(_) => break,
+            };
+            let delivery_count = delivery.properties.headers().as_ref()
+                .and_then(|headers| headers.inner().get("x-delivery-count"))
+                .and_then(|value| match value {
+                    lapin::types::AMQPValue::LongLongInt(count) => u64::try_from(*count).ok(),
+                    lapin::types::AMQPValue::LongInt(count) => u64::try_from(*count).ok(),
+                    _ => None,
+                });
+            deliveries.push(AmqpDelivery {
+                delivery_tag: delivery.delivery_tag,
+                routing_key: delivery.routing_key.to_string(),
+                redelivered: delivery.redelivered,
+                delivery_count,
+                payload: delivery.data,
+            });
+            wait = std::time::Duration::from_millis(1);
+        }
+        Ok(deliveries)
+    }
+
+    async fn acknowledge(&self, delivery_tag: u64, ack: AmqpAck) -> Result<(), StreamError> {
+        use lapin::options::{BasicAckOptions, BasicNackOptions};
+
+        match ack {
+            AmqpAck::Ack => self.channel.basic_ack(delivery_tag, BasicAckOptions::default()).await,
+            AmqpAck::Nack { requeue } => self.channel.basic_nack(delivery_tag, BasicNackOptions { multiple: false, requeue }).await,
+        }
+        .map_err(|e| StreamError::SendError(e.to_string()))
+    }
+
+    async fn publish(&self, exchange: &str, routing_key: &str, payload: &[u8]) -> Result<PublishConfirm, StreamError> {
+        let properties = lapin::BasicProperties::default()
+            .with_delivery_mode(2)
+            .with_content_type("application/json".into());
+        let confirm = self.channel.basic_publish(exchange, routing_key, lapin::options::BasicPublishOptions::default(), payload, properties).await
+            .map_err(|e| StreamError::SendError(e.to_string()))?;
+        if !self.publisher_confirms {
+            return Ok(PublishConfirm::Ack);
+        }
+        match confirm.await.map_err(|e| StreamError::SendError(e.to_string()))? {
+            lapin::publisher_confirm::Confirmation::Nack(_) => Ok(PublishConfirm::Nack),
+            _ => Ok(PublishConfirm::Ack),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockClient {
+        records: Mutex<Vec<KafkaRecord>>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl MockClient {
+        fn with_records(offsets: &[(i32, i64)]) -> Self {
+            let event = StreamingEvent::SystemMetrics {
+                cpu_usage: 1.0,
+                memory_usage: 1.0,
+                active_connections: 1,
+                timestamp: chrono::Utc::now(),
+            };
+            let payload = serde_json::to_vec(&event).unwrap();
+            let records = offsets.iter()
+                .map(|&(partition, offset)| KafkaRecord { topic: "events".to_string(), partition, offset, payload: payload.clone() })
+                .collect();
+            Self { records: Mutex::new(records), calls: Mutex::default() }
+        }
+
+        fn log(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    fn describe(offsets: &[PartitionOffset]) -> String {
+        offsets.iter().map(|o| format!("{}/{}@{}", o.topic, o.partition, o.offset)).collect::<Vec<_>>().join(",")
+    }
+
+    #[async_trait]
+    impl KafkaClient for MockClient {
+        async fn poll_batch(&self, max: usize) -> Result<Vec<KafkaRecord>, StreamError> {
+            let mut records = self.records.lock().unwrap();
+            let take = records.len().min(max);
+            Ok(records.drain(..take).collect())
+        }
+
+        async fn commit(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError> {
+            self.log(format!("commit {}", describe(offsets)));
+            Ok(())
+        }
+
+        async fn seek(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError> {
+            self.log(format!("seek {}", describe(offsets)));
+            Ok(())
+        }
+
+        async fn begin_transaction(&self) -> Result<(), StreamError> {
+            self.log("begin".to_string());
+            Ok(())
+        }
+
+        async fn produce_in_transaction(&self, events: &[StreamingEvent]) -> Result<(), StreamError> {
+            self.log(format!("produce {}", events.len()));
+            Ok(())
+        }
+
+        async fn commit_transaction(&self, offsets: &[PartitionOffset]) -> Result<(), StreamError> {
+            self.log(format!("commit_transaction {}", describe(offsets)));
+            Ok(())
+        }
+
+        async fn abort_transaction(&self) -> Result<(), StreamError> {
+            self.log("abort".to_string());
+            Ok(())
+        }
+
+        async fn flush(&self) -> Result<(), StreamError> {
+            self.log("flush".to_string());
+            Ok(())
+        }
+    }
+
+    struct Processor {
+        fail: bool,
+        calls: Mutex<usize>,
+    }
+
+    impl Processor {
+        fn new(fail: bool) -> Self {
+            Self { fail, calls: Mutex::new(0) }
+        }
+    }
+
+    #[async_trait]
+    impl StreamProcessor for Processor {
+        async fn process_event(&self, _event: StreamingEvent) -> Result<(), StreamError> {
+            Ok(())
+        }
+
+        async fn process_batch(&self, _events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            *self.calls.lock().unwrap() += 1;
+            if self.fail {
+                Err(StreamError::ProcessorError("reasoner unavailable".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "test_processor"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_at_least_once_commits_after_processing() {
+        let consumer = CommittingConsumer::new(MockClient::with_records(&[(0, 5), (0, 6), (1, 2)]), CommitStrategy::AtLeastOnce);
+        let outcome = consumer.run_batch(&Processor::new(false)).await.unwrap();
+        assert_eq!(outcome.records, 3);
+        assert_eq!(consumer.client().calls(), vec!["commit events/0@7,events/1@3"]);
+
+        let consumer = CommittingConsumer::new(MockClient::with_records(&[(0, 5), (0, 6)]), CommitStrategy::AtLeastOnce);
+        assert!(consumer.run_batch(&Processor::new(true)).await.is_err());
+        // 失敗したバッチはコミットせず先頭へ戻す
+        assert_eq!(consumer.client().calls(), vec!["seek events/0@5"]);
+    }
+
+    #[tokio::test]
+    async fn test_at_most_once_commits_before_processing() {
+        let consumer = CommittingConsumer::new(MockClient::with_records(&[(0, 1), (0, 2)]), CommitStrategy::AtMostOnce);
+        let processor = Processor::new(true);
+        let outcome = consumer.run_batch(&processor).await.unwrap();
+
+        assert_eq!(outcome.dropped, 2);
+        assert_eq!(*processor.calls.lock().unwrap(), 1);
+        assert_eq!(consumer.client().calls(), vec!["commit events/0@3"]);
+    }
+
+    #[tokio::test]
+    async fn test_exactly_once_uses_transactions() {
+        let consumer = CommittingConsumer::new(MockClient::with_records(&[(2, 10)]), CommitStrategy::ExactlyOnce);
+        consumer.run_batch(&Processor::new(false)).await.unwrap();
+        assert_eq!(consumer.client().calls(), vec!["begin", "produce 0", "commit_transaction events/2@11"]);
+
+        let consumer = CommittingConsumer::new(MockClient::with_records(&[(2, 10)]), CommitStrategy::ExactlyOnce);
+        assert!(consumer.run_batch(&Processor::new(true)).await.is_err());
+        assert_eq!(consumer.client().calls(), vec!["begin", "abort", "seek events/2@10"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_until_commits_batches_and_flushes_on_shutdown() {
+        let consumer = CommittingConsumer::new(MockClient::with_records(&[(0, 1), (0, 2), (0, 3)]), CommitStrategy::AtLeastOnce)
+            .with_batch_size(2);
+        let processor = Processor::new(false);
+
+        // 全レコードを取り出した時点で停止を要求する
+        let total = consumer.run_until(&processor, async {
+            while !consumer.client().records.lock().unwrap().is_empty() {
+                tokio::task::yield_now().await;
+            }
+        }).await;
+
+        assert_eq!((total.records, total.failed_batches), (3, 0));
+        assert_eq!(consumer.client().calls(), vec!["commit events/0@3", "commit events/0@4", "flush"]);
+    }
+
+    /// In-memory stream with one consumer group: `(id, owner, idle_ms)` pending entries
+    #[derive(Default)]
+    struct MockRedis {
+        fresh: Mutex<Vec<RedisEntry>>,
+        pending: Mutex<Vec<(RedisEntry, String, u64)>>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl MockRedis {
+        fn entry(id: &str) -> RedisEntry {
+            let event = StreamingEvent::SystemMetrics {
+                cpu_usage: 1.0,
+                memory_usage: 1.0,
+                active_connections: 1,
+                timestamp: chrono::Utc::now(),
+            };
+            RedisEntry { id: id.to_string(), payload: serde_json::to_vec(&event).unwrap() }
+        }
+
+        fn pending_ids(&self) -> Vec<String> {
+            self.pending.lock().unwrap().iter().map(|(entry, owner, _)| format!("{}@{}", entry.id, owner)).collect()
+        }
+    }
+
+    #[async_trait]
+    impl RedisStreamClient for MockRedis {
+        async fn create_group(&self, stream: &str, group: &str) -> Result<(), StreamError> {
+            self.calls.lock().unwrap().push(format!("create {} {}", stream, group));
+            Ok(())
+        }
+
+        async fn read_group(&self, _stream: &str, _group: &str, consumer: &str, count: usize, block_ms: Option<u64>) -> Result<Vec<RedisEntry>, StreamError> {
+            self.calls.lock().unwrap().push(format!("read {} block={:?}", count, block_ms));
+            let mut fresh = self.fresh.lock().unwrap();
+            let take = fresh.len().min(count);
+            let entries: Vec<RedisEntry> = fresh.drain(..take).collect();
+            self.pending.lock().unwrap().extend(entries.iter().map(|e| (e.clone(), consumer.to_string(), 0)));
+            Ok(entries)
+        }
+
+        async fn auto_claim(&self, _stream: &str, _group: &str, consumer: &str, min_idle_ms: u64, start: &str, count: usize) -> Result<RedisClaim, StreamError> {
+            self.calls.lock().unwrap().push(format!("claim {} from {}", min_idle_ms, start));
+            let mut claimed = Vec::new();
+            for (entry, owner, idle) in self.pending.lock().unwrap().iter_mut() {
+                if *idle >= min_idle_ms && entry.id.as_str() >= start && claimed.len() < count {
+                    *owner = consumer.to_string();
+                    *idle = 0;
+                    claimed.push(entry.clone());
+                }
+            }
+            Ok(RedisClaim { next_start: "0-0".to_string(), entries: claimed, deleted: vec![] })
+        }
+
+        async fn ack(&self, _stream: &str, _group: &str, ids: &[String]) -> Result<usize, StreamError> {
+            let mut pending = self.pending.lock().unwrap();
+            let before = pending.len();
+            pending.retain(|(entry, _, _)| !ids.contains(&entry.id));
+            Ok(before - pending.len())
+        }
+    }
+
+    fn redis_config(consumer: &str) -> crate::config::RedisConfig {
+        crate::config::RedisConfig {
+            url: "redis://localhost".to_string(),
+            stream_key: "events".to_string(),
+            consumer_group: "fukurow".to_string(),
+            consumer_name: consumer.to_string(),
+            database: None,
+            claim_min_idle_ms: 30_000,
+            claim_count: 10,
+            block_ms: 100,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redis_consumer_claims_stale_entries_before_reading() {
+        let client = MockRedis::default();
+        // node-1 が処理途中で落ちたエントリ (十分に古い) と、まだ新しいエントリ
+        client.pending.lock().unwrap().push((MockRedis::entry("1-0"), "node-1".to_string(), 45_000));
+        client.pending.lock().unwrap().push((MockRedis::entry("2-0"), "node-1".to_string(), 1_000));
+        client.fresh.lock().unwrap().push(MockRedis::entry("3-0"));
+
+        let consumer = RedisGroupConsumer::new(client, &redis_config("node-2")).unwrap().with_batch_size(5);
+        let outcome = consumer.run_batch(&Processor::new(false)).await.unwrap();
+
+        assert_eq!(outcome, RedisBatchOutcome { claimed: 1, read: 1, undecodable: 0, acked: 2 });
+        assert_eq!(consumer.client().pending_ids(), vec!["2-0@node-1"]);
+        // 引き取りがあったので新規読み取りはブロックしない
+        assert_eq!(*consumer.client().calls.lock().unwrap(), vec!["claim 30000 from 0-0", "read 4 block=None"]);
+    }
+
+    #[tokio::test]
+    async fn test_redis_consumer_leaves_failed_entries_pending() {
+        let client = MockRedis::default();
+        client.fresh.lock().unwrap().push(MockRedis::entry("1-0"));
+        client.fresh.lock().unwrap().push(RedisEntry { id: "2-0".to_string(), payload: b"not json".to_vec() });
+
+        let consumer = RedisGroupConsumer::new(client, &redis_config("node-1")).unwrap();
+        assert!(consumer.run_batch(&Processor::new(true)).await.is_err());
+        // 復号できないエントリだけ ACK 済み
+        assert_eq!(consumer.client().pending_ids(), vec!["1-0@node-1"]);
+        assert!(consumer.client().calls.lock().unwrap().contains(&"read 100 block=Some(100)".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_redis_consumer_acks_in_flight_batch_on_shutdown() {
+        struct StopAfterBatch {
+            stop: Mutex<Option<tokio::sync::oneshot::Sender<()>>>,
+        }
+
+        #[async_trait]
+        impl StreamProcessor for StopAfterBatch {
+            async fn process_event(&self, _event: StreamingEvent) -> Result<(), StreamError> {
+                Ok(())
+            }
+
+            async fn process_batch(&self, _events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+                // 処理中にシャットダウンを要求する
+                if let Some(stop) = self.stop.lock().unwrap().take() {
+                    let _ = stop.send(());
+                }
+                Ok(())
+            }
+
+            fn name(&self) -> &'static str {
+                "stop_after_batch"
+            }
+
+            async fn health_check(&self) -> Result<(), StreamError> {
+                Ok(())
+            }
+        }
+
+        let client = MockRedis::default();
+        client.fresh.lock().unwrap().extend(["1-0", "2-0", "3-0"].map(MockRedis::entry));
+        let consumer = RedisGroupConsumer::new(client, &redis_config("node-1")).unwrap().with_batch_size(2);
+
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let processor = StopAfterBatch { stop: Mutex::new(Some(stop)) };
+        let total = consumer.run_until(&processor, async {
+            let _ = stopped.await;
+        }).await;
+
+        assert_eq!(total.read, 2);
+        assert_eq!(total.acked, 2);
+        assert!(consumer.client().pending_ids().is_empty());
+        assert_eq!(consumer.client().fresh.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_records_are_committed() {
+        let client = MockClient::default();
+        client.records.lock().unwrap().push(KafkaRecord { topic: "events".to_string(), partition: 0, offset: 0, payload: b"not json".to_vec() });
+        let consumer = CommittingConsumer::new(client, CommitStrategy::AtLeastOnce).with_batch_size(10);
+
+        let outcome = consumer.run_batch(&Processor::new(false)).await.unwrap();
+        assert_eq!(outcome.undecodable, 1);
+        assert_eq!(outcome.committed, vec![PartitionOffset { topic: "events".to_string(), partition: 0, offset: 1 }]);
+    }
+
+    #[derive(Default)]
+    struct MockJetStream {
+        messages: Mutex<Vec<JetStreamMessage>>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl MockJetStream {
+        fn message(sequence: u64, delivered: i64) -> JetStreamMessage {
+            let event = StreamingEvent::SystemMetrics {
+                cpu_usage: 1.0,
+                memory_usage: 1.0,
+                active_connections: 1,
+                timestamp: chrono::Utc::now(),
+            };
+            JetStreamMessage {
+                subject: "events.edr".to_string(),
+                stream_sequence: sequence,
+                consumer_sequence: sequence,
+                delivered,
+                payload: serde_json::to_vec(&event).unwrap(),
+            }
+        }
+
+        fn log(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl JetStreamClient for MockJetStream {
+        async fn ensure_stream(&self, stream: &str, subjects: &[String], max_age: Option<std::time::Duration>) -> Result<(), StreamError> {
+            self.log(format!("stream {} {} max_age={:?}", stream, subjects.join(","), max_age));
+            Ok(())
+        }
+
+        async fn ensure_consumer(&self, stream: &str, config: &crate::config::JetStreamConfig, filter_subject: &str) -> Result<(), StreamError> {
+            self.log(format!("consumer {}/{} {} {:?}", stream, config.durable_name, filter_subject, config.deliver_policy));
+            Ok(())
+        }
+
+        async fn delete_consumer(&self, stream: &str, durable: &str) -> Result<(), StreamError> {
+            self.log(format!("delete {}/{}", stream, durable));
+            Ok(())
+        }
+
+        async fn fetch(&self, _stream: &str, _durable: &str, max: usize, _expires: std::time::Duration) -> Result<Vec<JetStreamMessage>, StreamError> {
+            let mut messages = self.messages.lock().unwrap();
+            let take = messages.len().min(max);
+            Ok(messages.drain(..take).collect())
+        }
+
+        async fn acknowledge(&self, message: &JetStreamMessage, ack: JetStreamAck) -> Result<(), StreamError> {
+            self.log(format!("{:?} {}", ack, message.stream_sequence));
+            Ok(())
+        }
+
+        async fn publish(&self, subject: &str, _payload: Vec<u8>) -> Result<u64, StreamError> {
+            self.log(format!("publish {}", subject));
+            Ok(1)
+        }
+    }
+
+    fn nats_config() -> crate::config::NATSConfig {
+        crate::config::NATSConfig {
+            servers: vec!["nats://localhost:4222".to_string()],
+            subject: "events.edr".to_string(),
+            queue_group: None,
+            credentials: None,
+            jetstream: Some(crate::config::JetStreamConfig::new("EVENTS", "fukurow")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jetstream_consumer_acks_after_processing() {
+        let client = MockJetStream::default();
+        client.messages.lock().unwrap().push(MockJetStream::message(1, 1));
+        client.messages.lock().unwrap().push(MockJetStream::message(2, 3));
+        let mut garbage = MockJetStream::message(3, 1);
+        garbage.payload = b"not json".to_vec();
+        client.messages.lock().unwrap().push(garbage);
+
+        let consumer = JetStreamConsumer::new(client, &nats_config()).unwrap();
+        consumer.init().await.unwrap();
+        let outcome = consumer.run_batch(&Processor::new(false)).await.unwrap();
+
+        assert_eq!(outcome, JetStreamBatchOutcome { fetched: 3, redelivered: 1, acked: 2, failed_batches: 0, terminated: 1 });
+        assert_eq!(consumer.client().calls(), vec![
+            "stream EVENTS events.edr max_age=None",
+            "consumer EVENTS/fukurow events.edr All",
+            "Term 3",
+            "Ack 1",
+            "Ack 2",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_jetstream_consumer_naks_failed_batch() {
+        let client = MockJetStream::default();
+        client.messages.lock().unwrap().push(MockJetStream::message(7, 1));
+
+        let consumer = JetStreamConsumer::new(client, &nats_config()).unwrap();
+        assert!(consumer.run_batch(&Processor::new(true)).await.is_err());
+        // 推論に失敗したメッセージは nak_delay_ms 後に再配信させる
+        assert_eq!(consumer.client().calls(), vec!["Nak { delay: Some(1s) } 7"]);
+    }
+
+    #[tokio::test]
+    async fn test_jetstream_replay_recreates_durable_consumer() {
+        let consumer = JetStreamConsumer::new(MockJetStream::default(), &nats_config()).unwrap();
+        let policy = crate::config::JetStreamDeliverPolicy::ByStartSequence { start_sequence: 42 };
+        consumer.replay_from(policy).await.unwrap();
+
+        assert_eq!(consumer.config().deliver_policy, policy);
+        assert_eq!(consumer.client().calls(), vec![
+            "delete EVENTS/fukurow",
+            "consumer EVENTS/fukurow events.edr ByStartSequence { start_sequence: 42 }",
+        ]);
+
+        let mut core_only = nats_config();
+        core_only.jetstream = None;
+        assert!(JetStreamConsumer::new(MockJetStream::default(), &core_only).is_err());
+    }
+
+    #[derive(Default)]
+    struct MockAmqp {
+        deliveries: Mutex<Vec<AmqpDelivery>>,
+        confirms: Mutex<Vec<PublishConfirm>>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl MockAmqp {
+        fn delivery(delivery_tag: u64, redelivered: bool) -> AmqpDelivery {
+            let event = StreamingEvent::SystemMetrics {
+                cpu_usage: 1.0,
+                memory_usage: 1.0,
+                active_connections: 1,
+                timestamp: chrono::Utc::now(),
+            };
+            AmqpDelivery {
+                delivery_tag,
+                routing_key: "security.events".to_string(),
+                redelivered,
+                delivery_count: None,
+                payload: serde_json::to_vec(&event).unwrap(),
+            }
+        }
+
+        fn log(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl AmqpClient for MockAmqp {
+        async fn declare_topology(&self, config: &crate::config::RabbitMQConfig) -> Result<(), StreamError> {
+            self.log(format!("declare {} -> {} {:?}", config.exchange, config.queue, config.queue_arguments().keys().collect::<Vec<_>>()));
+            Ok(())
+        }
+
+        async fn set_prefetch(&self, count: u16) -> Result<(), StreamError> {
+            self.log(format!("qos {}", count));
+            Ok(())
+        }
+
+        async fn consume(&self, queue: &str) -> Result<(), StreamError> {
+            self.log(format!("consume {}", queue));
+            Ok(())
+        }
+
+        async fn fetch(&self, max: usize, _timeout: std::time::Duration) -> Result<Vec<AmqpDelivery>, StreamError> {
+            let mut deliveries = self.deliveries.lock().unwrap();
+            let take = deliveries.len().min(max);
+            Ok(deliveries.drain(..take).collect())
+        }
+
+        async fn acknowledge(&self, delivery_tag: u64, ack: AmqpAck) -> Result<(), StreamError> {
+            self.log(format!("{:?} {}", ack, delivery_tag));
+            Ok(())
+        }
+
+        async fn publish(&self, exchange: &str, routing_key: &str, _payload: &[u8]) -> Result<PublishConfirm, StreamError> {
+            self.log(format!("publish {} {}", exchange, routing_key));
+            let mut confirms = self.confirms.lock().unwrap();
+            Ok(if confirms.is_empty() { PublishConfirm::Ack } else { confirms.remove(0) })
+        }
+    }
+
+    fn rabbitmq_config() -> crate::config::RabbitMQConfig {
+        crate::config::RabbitMQConfig::new("amqp://localhost", "security", "fukurow")
+            .with_dead_letter("security.dlx", Some("fukurow.dlq".to_string()))
+            .with_prefetch(10)
+    }
+
+    #[tokio::test]
+    async fn test_rabbitmq_consumer_declares_and_acks() {
+        let client = MockAmqp::default();
+        client.deliveries.lock().unwrap().push(MockAmqp::delivery(1, false));
+        let mut garbage = MockAmqp::delivery(2, false);
+        garbage.payload = b"not json".to_vec();
+        client.deliveries.lock().unwrap().push(garbage);
+
+        // prefetch はバッチサイズより小さくしない
+        let consumer = RabbitMQConsumer::new(client, rabbitmq_config()).unwrap().with_batch_size(50);
+        consumer.init().await.unwrap();
+        let outcome = consumer.run_batch(&Processor::new(false)).await.unwrap();
+
+        assert_eq!(outcome, RabbitMQBatchOutcome { fetched: 2, acked: 1, dead_lettered: 1, ..RabbitMQBatchOutcome::default() });
+        assert_eq!(consumer.client().calls(), vec![
+            r#"declare security -> fukurow ["x-dead-letter-exchange"]"#,
+            "qos 50",
+            "consume fukurow",
+            "Nack { requeue: false } 2",
+            "Ack 1",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_rabbitmq_failed_batch_requeues_then_dead_letters() {
+        let client = MockAmqp::default();
+        client.deliveries.lock().unwrap().extend([MockAmqp::delivery(1, false), MockAmqp::delivery(2, true)]);
+
+        let consumer = RabbitMQConsumer::new(client, rabbitmq_config()).unwrap();
+        assert!(consumer.run_batch(&Processor::new(true)).await.is_err());
+        // クラシックキューでは 2 回目の失敗でデッドレターに送る
+        assert_eq!(consumer.client().calls(), vec!["Nack { requeue: true } 1", "Nack { requeue: false } 2"]);
+
+        // クォーラムキューは delivery_limit に任せて常に再キューする
+        let client = MockAmqp::default();
+        client.deliveries.lock().unwrap().push(MockAmqp::delivery(3, true));
+        let consumer = RabbitMQConsumer::new(client, rabbitmq_config().with_quorum_queue(Some(5))).unwrap();
+        assert!(consumer.run_batch(&Processor::new(true)).await.is_err());
+        assert_eq!(consumer.client().calls(), vec!["Nack { requeue: true } 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_rabbitmq_producer_resends_nacked_publishes() {
+        let event = StreamingEvent::SystemMetrics {
+            cpu_usage: 1.0,
+            memory_usage: 1.0,
+            active_connections: 1,
+            timestamp: chrono::Utc::now(),
+        };
+        let client = MockAmqp::default();
+        client.confirms.lock().unwrap().extend([PublishConfirm::Nack, PublishConfirm::Ack]);
+        let producer = crate::RabbitMQProducer::new(client, &rabbitmq_config());
+        producer.produce(event.clone()).await.unwrap();
+        assert_eq!(producer.client().calls().len(), 2);
+
+        let client = MockAmqp::default();
+        client.confirms.lock().unwrap().extend([PublishConfirm::Nack; 3]);
+        let producer = crate::RabbitMQProducer::new(client, &rabbitmq_config());
+        assert!(producer.produce(event).await.is_err());
+        assert_eq!(producer.client().calls(), vec!["publish security security.events"; 3]);
+    }
+}
@@ -0,0 +1,471 @@
+//! # Dead-Letter Queue
+//!
+//! Events that a producer fails to publish after all retries are routed to a
+//! dead-letter queue (alternate topic or local disk spool) for later replay
+//! instead of being dropped.
+
+use crate::{DeadLetterConfig, StreamingEvent, StreamError, StreamProducer};
+use async_trait::async_trait;
+use fukurow_core::retry::{retry_retryable, RetryPolicy};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Failed event together with the reason it was dead-lettered
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetter {
+    pub event: StreamingEvent,
+    pub error: String,
+    /// Name of the producer that failed
+    pub producer: String,
+    /// Number of publish attempts made
+    pub attempts: u32,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Destination for events that could not be published
+#[async_trait]
+pub trait DeadLetterQueue: Send + Sync {
+    /// Store a failed event
+    async fn send(&self, letter: DeadLetter) -> Result<(), StreamError>;
+
+    /// Remove and return all pending dead letters
+    async fn drain(&self) -> Result<Vec<DeadLetter>, StreamError>;
+
+    /// Get queue name
+    fn name(&self) -> &'static str;
+
+    /// Re-publish pending dead letters through `producer`
+    ///
+    /// 再送に失敗したイベントはキューに戻す。戻り値は再送できた件数
+    async fn replay<P: StreamProducer>(&self, producer: &P) -> Result<usize, StreamError>
+    where
+        Self: Sized,
+    {
+        let mut replayed = 0;
+        for letter in self.drain().await? {
+            match producer.produce(letter.event.clone()).await {
+                Ok(()) => replayed += 1,
+                Err(e) => {
+                    self.send(DeadLetter { error: e.to_string(), ..letter }).await?;
+                }
+            }
+        }
+        Ok(replayed)
+    }
+}
+
+#[async_trait]
+impl<D: DeadLetterQueue + ?Sized> DeadLetterQueue for Arc<D> {
+    async fn send(&self, letter: DeadLetter) -> Result<(), StreamError> {
+        (**self).send(letter).await
+    }
+
+    async fn drain(&self) -> Result<Vec<DeadLetter>, StreamError> {
+        (**self).drain().await
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+}
+
+/// In-memory dead-letter queue (mainly for tests)
+#[derive(Debug, Default)]
+pub struct InMemoryDeadLetterQueue {
+    letters: Mutex<Vec<DeadLetter>>,
+}
+
+impl InMemoryDeadLetterQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.letters.lock().map(|l| l.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueue for InMemoryDeadLetterQueue {
+    async fn send(&self, letter: DeadLetter) -> Result<(), StreamError> {
+        self.letters
+            .lock()
+            .map_err(|e| StreamError::SendError(e.to_string()))?
+            .push(letter);
+        Ok(())
+    }
+
+    async fn drain(&self) -> Result<Vec<DeadLetter>, StreamError> {
+        let mut letters = self.letters
+            .lock()
+            .map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+        Ok(std::mem::take(&mut *letters))
+    }
+
+    fn name(&self) -> &'static str {
+        "in_memory_dlq"
+    }
+}
+
+/// Dead-letter queue that publishes failed events to an alternate topic
+///
+/// イベント単体ではなく [`DeadLetter`] 全体 (エラー・producer 名・試行回数) を JSON の封筒として送る
+pub struct TopicDeadLetterQueue<P: StreamProducer> {
+    producer: P,
+    topic: String,
+}
+
+impl<P: StreamProducer> TopicDeadLetterQueue<P> {
+    pub fn new(producer: P, topic: impl Into<String>) -> Self {
+        Self { producer, topic: topic.into() }
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+}
+
+#[async_trait]
+impl<P: StreamProducer> DeadLetterQueue for TopicDeadLetterQueue<P> {
+    async fn send(&self, letter: DeadLetter) -> Result<(), StreamError> {
+        let envelope = serde_json::to_vec(&letter).map_err(|e| StreamError::SendError(e.to_string()))?;
+        self.producer.produce_to(&self.topic, envelope).await
+    }
+
+    async fn drain(&self) -> Result<Vec<DeadLetter>, StreamError> {
+        // トピック上のイベントは通常のコンシューマーで再処理する
+        Ok(Vec::new())
+    }
+
+    fn name(&self) -> &'static str {
+        "topic_dlq"
+    }
+}
+
+/// Dead-letter queue backed by a JSON Lines spool file on local disk
+#[derive(Debug)]
+pub struct DiskSpoolDeadLetterQueue {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl DiskSpoolDeadLetterQueue {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, StreamError> {
+        let path = path.as_ref().to_path_buf();
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| StreamError::ConfigError(format!("Cannot open spool {}: {}", path.display(), e)))?;
+        Ok(Self { path, lock: Mutex::new(()) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read pending dead letters without removing them
+    pub fn peek(&self) -> Result<Vec<DeadLetter>, StreamError> {
+        let _guard = self.lock.lock().map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+        self.read_all()
+    }
+
+    fn read_all(&self) -> Result<Vec<DeadLetter>, StreamError> {
+        let file = std::fs::File::open(&self.path).map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+        let mut letters = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line.map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(letter) => letters.push(letter),
+                Err(e) => warn!("Skipping corrupt dead letter in {}: {}", self.path.display(), e),
+            }
+        }
+        Ok(letters)
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueue for DiskSpoolDeadLetterQueue {
+    async fn send(&self, letter: DeadLetter) -> Result<(), StreamError> {
+        let line = serde_json::to_string(&letter).map_err(|e| StreamError::SendError(e.to_string()))?;
+        let _guard = self.lock.lock().map_err(|e| StreamError::SendError(e.to_string()))?;
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| StreamError::SendError(e.to_string()))?;
+        writeln!(file, "{}", line).map_err(|e| StreamError::SendError(e.to_string()))
+    }
+
+    async fn drain(&self) -> Result<Vec<DeadLetter>, StreamError> {
+        let _guard = self.lock.lock().map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+        let letters = self.read_all()?;
+        std::fs::File::create(&self.path).map_err(|e| StreamError::ReceiveError(e.to_string()))?;
+        Ok(letters)
+    }
+
+    fn name(&self) -> &'static str {
+        "disk_spool_dlq"
+    }
+}
+
+impl DeadLetterConfig {
+    /// Build the configured dead-letter queue
+    ///
+    /// `Topic` は `producer` 経由で設定トピックへ送るため producer が必須
+    pub fn build(&self, producer: Option<Arc<dyn StreamProducer>>) -> Result<Arc<dyn DeadLetterQueue>, StreamError> {
+        match self {
+            DeadLetterConfig::Topic { topic } => {
+                let producer = producer.ok_or_else(|| {
+                    StreamError::ConfigError(format!("dead-letter topic {} needs a producer", topic))
+                })?;
+                Ok(Arc::new(TopicDeadLetterQueue::new(producer, topic.clone())))
+            }
+            DeadLetterConfig::Spool { path } => Ok(Arc::new(DiskSpoolDeadLetterQueue::new(path)?)),
+        }
+    }
+}
+
+/// Dead-letter statistics
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetterStats {
+    pub published: u64,
+    pub dead_lettered: u64,
+}
+
+/// Producer wrapper that retries with backoff and dead-letters events that still fail
+pub struct DeadLetterProducer<P: StreamProducer, D: DeadLetterQueue> {
+    inner: P,
+    dlq: D,
+    policy: RetryPolicy,
+    published: AtomicU64,
+    dead_lettered: AtomicU64,
+}
+
+impl<P: StreamProducer, D: DeadLetterQueue> DeadLetterProducer<P, D> {
+    pub fn new(inner: P, dlq: D, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            dlq,
+            policy,
+            published: AtomicU64::new(0),
+            dead_lettered: AtomicU64::new(0),
+        }
+    }
+
+    pub fn dead_letter_queue(&self) -> &D {
+        &self.dlq
+    }
+
+    pub fn stats(&self) -> DeadLetterStats {
+        DeadLetterStats {
+            published: self.published.load(Ordering::Relaxed),
+            dead_lettered: self.dead_lettered.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn dead_letter(&self, event: StreamingEvent, error: StreamError, attempts: u32) -> Result<(), StreamError> {
+        warn!("Dead-lettering {} after {} attempt(s): {}", event.event_type(), attempts, error);
+        self.dlq.send(DeadLetter {
+            event,
+            error: error.to_string(),
+            producer: self.inner.name().to_string(),
+            attempts,
+            failed_at: chrono::Utc::now(),
+        }).await?;
+        self.dead_lettered.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<P: StreamProducer, D: DeadLetterQueue> StreamProducer for DeadLetterProducer<P, D> {
+    async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        let attempts = AtomicU64::new(0);
+        let result = retry_retryable(&self.policy, |attempt| {
+            attempts.store(attempt as u64, Ordering::Relaxed);
+            self.inner.produce(event.clone())
+        }, tokio::time::sleep).await;
+
+        match result {
+            Ok(()) => {
+                self.published.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(e) => self.dead_letter(event, e, attempts.load(Ordering::Relaxed) as u32).await,
+        }
+    }
+
+    async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        let result = retry_retryable(&self.policy, |_| self.inner.produce_batch(events.clone()), tokio::time::sleep).await;
+        if result.is_ok() {
+            self.published.fetch_add(events.len() as u64, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        // バッチ単位で失敗した場合は個別に再送し、失敗分のみデッドレター化する
+        for event in events {
+            self.produce(event).await?;
+        }
+        Ok(())
+    }
+
+    async fn produce_to(&self, topic: &str, payload: Vec<u8>) -> Result<(), StreamError> {
+        self.inner.produce_to(topic, payload).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Producer that fails the first `failures` calls
+    struct FlakyProducer {
+        failures: usize,
+        calls: AtomicUsize,
+        produced: Mutex<Vec<StreamingEvent>>,
+        /// Payloads sent with `produce_to`, by topic
+        published: Mutex<Vec<(String, Vec<u8>)>>,
+    }
+
+    impl FlakyProducer {
+        fn new(failures: usize) -> Self {
+            Self { failures, calls: AtomicUsize::new(0), produced: Mutex::new(Vec::new()), published: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl StreamProducer for FlakyProducer {
+        async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(StreamError::ConnectionError("broker down".to_string()));
+            }
+            self.produced.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            for event in events {
+                self.produce(event).await?;
+            }
+            Ok(())
+        }
+
+        async fn produce_to(&self, topic: &str, payload: Vec<u8>) -> Result<(), StreamError> {
+            self.published.lock().unwrap().push((topic.to_string(), payload));
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    fn metrics_event() -> StreamingEvent {
+        StreamingEvent::SystemMetrics {
+            cpu_usage: 10.0,
+            memory_usage: 20.0,
+            active_connections: 1,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(max_attempts).with_initial_backoff_ms(1).with_max_backoff_ms(1)
+    }
+
+    #[tokio::test]
+    async fn test_retry_then_success() {
+        let producer = DeadLetterProducer::new(FlakyProducer::new(2), InMemoryDeadLetterQueue::new(), fast_policy(3));
+        producer.produce(metrics_event()).await.unwrap();
+
+        assert_eq!(producer.stats().published, 1);
+        assert!(producer.dead_letter_queue().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_retries_are_dead_lettered_and_replayed() {
+        let producer = DeadLetterProducer::new(FlakyProducer::new(10), InMemoryDeadLetterQueue::new(), fast_policy(2));
+        producer.produce(metrics_event()).await.unwrap();
+
+        let stats = producer.stats();
+        assert_eq!((stats.published, stats.dead_lettered), (0, 1));
+        assert_eq!(producer.dead_letter_queue().len(), 1);
+
+        let healthy = FlakyProducer::new(0);
+        let replayed = producer.dead_letter_queue().replay(&healthy).await.unwrap();
+        assert_eq!(replayed, 1);
+        assert_eq!(healthy.produced.lock().unwrap().len(), 1);
+        assert!(producer.dead_letter_queue().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disk_spool_roundtrip() {
+        let path = std::env::temp_dir().join(format!("fukurow-dlq-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let spool = DiskSpoolDeadLetterQueue::new(&path).unwrap();
+
+        let producer = DeadLetterProducer::new(FlakyProducer::new(10), spool, fast_policy(1));
+        producer.produce_batch(vec![metrics_event(), metrics_event()]).await.unwrap();
+
+        let pending = producer.dead_letter_queue().peek().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].producer, "flaky");
+        assert!(pending[0].error.contains("broker down"));
+
+        let drained = producer.dead_letter_queue().drain().await.unwrap();
+        assert_eq!(drained.len(), 2);
+        assert!(producer.dead_letter_queue().peek().unwrap().is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_topic_dead_letters_carry_their_metadata() {
+        let dlq_producer = Arc::new(FlakyProducer::new(0));
+        let dlq = DeadLetterConfig::Topic { topic: "events.dlq".to_string() }
+            .build(Some(dlq_producer.clone()))
+            .unwrap();
+        let producer = DeadLetterProducer::new(FlakyProducer::new(10), dlq, fast_policy(2));
+        producer.produce(metrics_event()).await.unwrap();
+
+        // 元の宛先ではなく設定トピックへ、封筒として送られる
+        assert!(dlq_producer.produced.lock().unwrap().is_empty());
+        let published = dlq_producer.published.lock().unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].0, "events.dlq");
+        let letter: DeadLetter = serde_json::from_slice(&published[0].1).unwrap();
+        assert_eq!(letter.producer, "flaky");
+        assert_eq!(letter.attempts, 2);
+        assert!(letter.error.contains("broker down"));
+        assert_eq!(letter.event.event_type(), "system_metrics");
+    }
+
+    #[test]
+    fn test_topic_dead_letter_config_requires_a_producer() {
+        let config = DeadLetterConfig::Topic { topic: "events.dlq".to_string() };
+        assert!(matches!(config.build(None), Err(StreamError::ConfigError(_))));
+    }
+}
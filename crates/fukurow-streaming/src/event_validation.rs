@@ -0,0 +1,263 @@
+//! # Event Validation Stage
+//!
+//! Field-level validation of consumed security events (IP format, port range,
+//! timestamp sanity) before they reach the engine.
+//! 拒否したイベントは破棄し、quarantine モードでは違反内容を付けて隔離用プロデューサーへ送る
+
+use crate::{ConformanceStatus, ShapeViolation, StreamingEvent, StreamError, StreamProcessor, StreamProducer};
+use async_trait::async_trait;
+use fukurow_core::validation::{describe_issues, EventValidator, ValidationIssue, ValidationOutcome};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Constraint name attached to quarantined events
+pub const EVENT_SCHEMA_CONSTRAINT: &str = "event-schema";
+
+/// Event validation stage statistics
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct EventValidationStats {
+    pub validated: u64,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub quarantined: u64,
+    pub passed_through: u64,
+}
+
+/// Streaming stage that validates security events per an [`EventValidator`]
+pub struct EventValidationStage<P: StreamProcessor> {
+    validator: EventValidator,
+    downstream: P,
+    quarantine: Option<Arc<dyn StreamProducer>>,
+    validated: AtomicU64,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    quarantined: AtomicU64,
+    passed_through: AtomicU64,
+}
+
+/// Where a screened event goes
+enum Screened {
+    Forward(StreamingEvent),
+    Quarantine(StreamingEvent),
+    Drop,
+}
+
+impl<P: StreamProcessor> EventValidationStage<P> {
+    pub fn new(validator: EventValidator, downstream: P) -> Self {
+        Self {
+            validator,
+            downstream,
+            quarantine: None,
+            validated: AtomicU64::new(0),
+            accepted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            quarantined: AtomicU64::new(0),
+            passed_through: AtomicU64::new(0),
+        }
+    }
+
+    /// Producer receiving quarantined events (without one they are dropped like rejected events)
+    pub fn with_quarantine(mut self, quarantine: impl StreamProducer + 'static) -> Self {
+        self.quarantine = Some(Arc::new(quarantine));
+        self
+    }
+
+    pub fn stats(&self) -> EventValidationStats {
+        EventValidationStats {
+            validated: self.validated.load(Ordering::Relaxed),
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            quarantined: self.quarantined.load(Ordering::Relaxed),
+            passed_through: self.passed_through.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Violations in the form carried by `StreamingEvent::ValidatedEvent`
+    pub fn violations(issues: &[ValidationIssue]) -> Vec<ShapeViolation> {
+        issues.iter()
+            .map(|issue| ShapeViolation {
+                focus_node: None,
+                path: Some(issue.field.clone()),
+                constraint: EVENT_SCHEMA_CONSTRAINT.to_string(),
+                message: Some(issue.message.clone()),
+            })
+            .collect()
+    }
+
+    fn screen(&self, event: StreamingEvent) -> Screened {
+        let outcome = match &event {
+            StreamingEvent::SecurityEvent { event, .. } => self.validator.validate(event),
+            _ => {
+                self.passed_through.fetch_add(1, Ordering::Relaxed);
+                return Screened::Forward(event);
+            }
+        };
+        self.validated.fetch_add(1, Ordering::Relaxed);
+
+        match outcome {
+            ValidationOutcome::Accept { warnings } => {
+                if !warnings.is_empty() {
+                    warn!("Forwarding streamed event with warnings: {}", describe_issues(&warnings));
+                }
+                self.accepted.fetch_add(1, Ordering::Relaxed);
+                Screened::Forward(event)
+            }
+            ValidationOutcome::Quarantine(issues) if self.quarantine.is_some() => {
+                self.quarantined.fetch_add(1, Ordering::Relaxed);
+                Screened::Quarantine(StreamingEvent::ValidatedEvent {
+                    event: Box::new(event),
+                    conformance: ConformanceStatus::Violates,
+                    violations: Self::violations(&issues),
+                    timestamp: chrono::Utc::now(),
+                })
+            }
+            ValidationOutcome::Reject(issues) | ValidationOutcome::Quarantine(issues) => {
+                warn!("Dropping invalid streamed event: {}", describe_issues(&issues));
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                Screened::Drop
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: StreamProcessor> StreamProcessor for EventValidationStage<P> {
+    async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        match (self.screen(event), &self.quarantine) {
+            (Screened::Forward(event), _) => self.downstream.process_event(event).await,
+            (Screened::Quarantine(event), Some(quarantine)) => quarantine.produce(event).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        let mut accepted = Vec::new();
+        let mut quarantined = Vec::new();
+        for event in events {
+            match self.screen(event) {
+                Screened::Forward(event) => accepted.push(event),
+                Screened::Quarantine(event) => quarantined.push(event),
+                Screened::Drop => {}
+            }
+        }
+
+        if let (false, Some(quarantine)) = (quarantined.is_empty(), &self.quarantine) {
+            quarantine.produce_batch(quarantined).await?;
+        }
+        if !accepted.is_empty() {
+            self.downstream.process_batch(accepted).await?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "event_validation_stage"
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        self.downstream.health_check().await?;
+        match &self.quarantine {
+            Some(quarantine) => quarantine.health_check().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::CyberEvent;
+    use fukurow_core::validation::EventValidationMode;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Collector {
+        events: Mutex<Vec<StreamingEvent>>,
+    }
+
+    #[async_trait]
+    impl StreamProcessor for Arc<Collector> {
+        async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            self.events.lock().unwrap().extend(events);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "collector"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl StreamProducer for Arc<Collector> {
+        async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            self.events.lock().unwrap().extend(events);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "quarantine"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    fn connection(dest_ip: &str) -> StreamingEvent {
+        StreamingEvent::SecurityEvent {
+            event: CyberEvent::NetworkConnection {
+                source_ip: "192.168.1.1".to_string(),
+                dest_ip: dest_ip.to_string(),
+                port: 443,
+                protocol: "tcp".to_string(),
+                timestamp: 1640995200,
+            },
+            timestamp: chrono::Utc::now(),
+            source: "sensor1".to_string(),
+            correlation_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_events_are_dropped_or_quarantined() {
+        let downstream = Arc::new(Collector::default());
+        let strict = EventValidationStage::new(EventValidator::default(), downstream.clone());
+        strict.process_batch(vec![connection("10.0.0.1"), connection("10.0.0.300")]).await.unwrap();
+        assert_eq!(downstream.events.lock().unwrap().len(), 1);
+        let stats = strict.stats();
+        assert_eq!((stats.validated, stats.accepted, stats.rejected), (2, 1, 1));
+
+        let downstream = Arc::new(Collector::default());
+        let quarantine = Arc::new(Collector::default());
+        let stage = EventValidationStage::new(EventValidator::new(EventValidationMode::Quarantine), downstream.clone())
+            .with_quarantine(quarantine.clone());
+        stage.process_event(connection("not-an-ip")).await.unwrap();
+
+        assert!(downstream.events.lock().unwrap().is_empty());
+        let quarantined = quarantine.events.lock().unwrap();
+        match &quarantined[..] {
+            [StreamingEvent::ValidatedEvent { conformance, violations, .. }] => {
+                assert_eq!(*conformance, ConformanceStatus::Violates);
+                assert_eq!(violations[0].path.as_deref(), Some("dest_ip"));
+                assert_eq!(violations[0].constraint, EVENT_SCHEMA_CONSTRAINT);
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+        assert_eq!(stage.stats().quarantined, 1);
+    }
+}
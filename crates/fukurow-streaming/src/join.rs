@@ -0,0 +1,565 @@
+//! # Stream-Stream Join
+//!
+//! Windowed join of two event streams on key fields.
+//! 左右のストリームのイベントを結合キー (例: `left.source = right.source`) ごとにバッファし、
+//! 時間窓内に収まる組を `StreamingEvent::JoinedEvent` として出力する。
+//! 例: プロセス実行の後 30 秒以内に同じホストからネットワーク接続があれば 1 件の相関イベントにする
+
+use crate::processor::StreamProcessor;
+use crate::window::{event_field, event_time_ms, TimeSource};
+use crate::{StreamError, StreamingEvent};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Equality conditions between left and right event fields
+///
+/// Field names are resolved with [`event_field`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinCondition {
+    pairs: Vec<(String, String)>,
+}
+
+impl JoinCondition {
+    /// `left.<left_field> = right.<right_field>`
+    pub fn on(left_field: impl Into<String>, right_field: impl Into<String>) -> Self {
+        Self { pairs: vec![(left_field.into(), right_field.into())] }
+    }
+
+    /// Add another equality (all must hold)
+    pub fn and(mut self, left_field: impl Into<String>, right_field: impl Into<String>) -> Self {
+        self.pairs.push((left_field.into(), right_field.into()));
+        self
+    }
+
+    /// `(left field, right field)` pairs in key order
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.pairs
+    }
+
+    fn left_key(&self, event: &StreamingEvent) -> Option<Vec<String>> {
+        self.pairs.iter().map(|(field, _)| event_field(event, field)).collect()
+    }
+
+    fn right_key(&self, event: &StreamingEvent) -> Option<Vec<String>> {
+        self.pairs.iter().map(|(_, field)| event_field(event, field)).collect()
+    }
+}
+
+/// Parse `left.source = right.source AND left.user = right.user`
+///
+/// 各項は `left.<field>` と `right.<field>` の等号で、左右の順序は問わない
+impl FromStr for JoinCondition {
+    type Err = StreamError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| StreamError::ConfigError(format!("invalid join condition '{}': {}", expression, reason));
+
+        let mut clauses = vec![Vec::new()];
+        for token in expression.split_whitespace() {
+            if token.eq_ignore_ascii_case("and") {
+                clauses.push(Vec::new());
+            } else if let Some(clause) = clauses.last_mut() {
+                clause.push(token);
+            }
+        }
+
+        let mut pairs = Vec::new();
+        for clause in clauses {
+            let clause = clause.concat();
+            let (a, b) = clause.split_once('=').ok_or_else(|| invalid("expected 'left.<field> = right.<field>'"))?;
+            let side = |operand: &str| match operand.split_once('.') {
+                Some(("left", field)) if !field.is_empty() => Ok((true, field.to_string())),
+                Some(("right", field)) if !field.is_empty() => Ok((false, field.to_string())),
+                _ => Err(invalid(&format!("operand '{}' must be left.<field> or right.<field>", operand))),
+            };
+            match (side(a)?, side(b)?) {
+                ((true, left), (false, right)) | ((false, right), (true, left)) => pairs.push((left, right)),
+                _ => return Err(invalid("each clause must compare a left field with a right field")),
+            }
+        }
+        Ok(Self { pairs })
+    }
+}
+
+/// Time window of a join, relative to the left event (milliseconds)
+///
+/// A pair matches when `left_time - before_ms <= right_time <= left_time + after_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinWindow {
+    pub before_ms: i64,
+    pub after_ms: i64,
+}
+
+impl JoinWindow {
+    /// Right event within `within` after the left event
+    pub fn following(within: std::time::Duration) -> Self {
+        Self { before_ms: 0, after_ms: within.as_millis() as i64 }
+    }
+
+    /// Right event within `within` of the left event, in either order
+    pub fn around(within: std::time::Duration) -> Self {
+        let ms = within.as_millis() as i64;
+        Self { before_ms: ms, after_ms: ms }
+    }
+
+    fn validate(&self) -> Result<(), StreamError> {
+        if self.before_ms < 0 || self.after_ms < 0 {
+            return Err(StreamError::ConfigError("join window bounds must not be negative".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Join statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinStats {
+    pub left: u64,
+    pub right: u64,
+    /// Events matching neither side or missing a key field
+    pub skipped: u64,
+    /// Events arriving after every possible partner had been evicted
+    pub late: u64,
+    pub joined: u64,
+    /// Buffered events dropped to stay within `max_buffered`
+    pub dropped: u64,
+    pub buffered: usize,
+}
+
+type EventFilter = Box<dyn Fn(&StreamingEvent) -> bool + Send + Sync>;
+
+/// Filter accepting security events of one `CyberEvent` type (e.g. `ProcessExecution`)
+pub fn event_type_is(event_type: &'static str) -> impl Fn(&StreamingEvent) -> bool + Send + Sync + 'static {
+    move |event| event_field(event, "event_type").as_deref() == Some(event_type)
+}
+
+/// Buffered events of one side, indexed by key and by time for eviction
+#[derive(Default)]
+struct JoinSide {
+    events: HashMap<Vec<String>, BTreeMap<(i64, u64), StreamingEvent>>,
+    by_time: BTreeMap<(i64, u64), Vec<String>>,
+}
+
+impl JoinSide {
+    fn len(&self) -> usize {
+        self.by_time.len()
+    }
+
+    fn insert(&mut self, key: Vec<String>, slot: (i64, u64), event: StreamingEvent) {
+        self.events.entry(key.clone()).or_default().insert(slot, event);
+        self.by_time.insert(slot, key);
+    }
+
+    /// Events of `key` with time in `[from_ms, to_ms]`
+    fn matches(&self, key: &[String], from_ms: i64, to_ms: i64) -> impl Iterator<Item = (i64, &StreamingEvent)> {
+        self.events.get(key).into_iter()
+            .flat_map(move |events| events.range((from_ms, 0)..=(to_ms, u64::MAX)))
+            .map(|((time_ms, _), event)| (*time_ms, event))
+    }
+
+    /// Drop the oldest event; returns false when empty
+    fn pop_oldest(&mut self) -> bool {
+        let Some((slot, key)) = self.by_time.pop_first() else { return false };
+        if let Some(events) = self.events.get_mut(&key) {
+            events.remove(&slot);
+            if events.is_empty() {
+                self.events.remove(&key);
+            }
+        }
+        true
+    }
+
+    /// Drop events older than `cutoff_ms`
+    fn evict_before(&mut self, cutoff_ms: i64) {
+        while self.by_time.first_key_value().map(|((time_ms, _), _)| *time_ms < cutoff_ms).unwrap_or(false) {
+            self.pop_oldest();
+        }
+    }
+}
+
+/// Keyed stream-stream join state machine
+///
+/// Each event is matched against the buffered events of the other side and then
+/// buffered itself, so every pair is emitted exactly once, as soon as its second
+/// event arrives. The watermark trails the newest event time by `allowed_lateness_ms`;
+/// buffered events are evicted once no event at or after the watermark can match them.
+pub struct StreamJoinOperator {
+    name: String,
+    left_filter: EventFilter,
+    right_filter: EventFilter,
+    condition: JoinCondition,
+    window: JoinWindow,
+    time_source: TimeSource,
+    allowed_lateness_ms: i64,
+    max_buffered: Option<usize>,
+    watermark_ms: Option<i64>,
+    left: JoinSide,
+    right: JoinSide,
+    sequence: u64,
+    stats: JoinStats,
+}
+
+impl StreamJoinOperator {
+    pub fn new(
+        name: impl Into<String>,
+        left: impl Fn(&StreamingEvent) -> bool + Send + Sync + 'static,
+        right: impl Fn(&StreamingEvent) -> bool + Send + Sync + 'static,
+        condition: JoinCondition,
+        window: JoinWindow,
+    ) -> Result<Self, StreamError> {
+        window.validate()?;
+        if condition.pairs.is_empty() {
+            return Err(StreamError::ConfigError("join condition needs at least one field".to_string()));
+        }
+        Ok(Self {
+            name: name.into(),
+            left_filter: Box::new(left),
+            right_filter: Box::new(right),
+            condition,
+            window,
+            time_source: TimeSource::default(),
+            allowed_lateness_ms: 0,
+            max_buffered: None,
+            watermark_ms: None,
+            left: JoinSide::default(),
+            right: JoinSide::default(),
+            sequence: 0,
+            stats: JoinStats::default(),
+        })
+    }
+
+    pub fn with_time_source(mut self, time_source: TimeSource) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    /// How long events stay buffered for out-of-order partners
+    pub fn with_allowed_lateness_ms(mut self, allowed_lateness_ms: i64) -> Self {
+        self.allowed_lateness_ms = allowed_lateness_ms.max(0);
+        self
+    }
+
+    /// Cap on buffered events per side; the oldest are dropped first
+    pub fn with_max_buffered(mut self, max_buffered: usize) -> Self {
+        self.max_buffered = Some(max_buffered.max(1));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn window(&self) -> &JoinWindow {
+        &self.window
+    }
+
+    pub fn stats(&self) -> JoinStats {
+        JoinStats { buffered: self.left.len() + self.right.len(), ..self.stats }
+    }
+
+    /// Add an event; returns the joined events it completes
+    pub fn push(&mut self, event: &StreamingEvent) -> Vec<StreamingEvent> {
+        let left_key = if (self.left_filter)(event) { self.condition.left_key(event) } else { None };
+        let right_key = if (self.right_filter)(event) { self.condition.right_key(event) } else { None };
+        if left_key.is_none() && right_key.is_none() {
+            self.stats.skipped += 1;
+            return Vec::new();
+        }
+
+        let time_ms = event_time_ms(event, self.time_source);
+        let watermark = self.watermark_ms.unwrap_or(i64::MIN);
+        // 相手側がすべて破棄済みになる時刻のイベントは結合できない
+        let left_key = left_key.filter(|_| time_ms.saturating_add(self.window.after_ms) >= watermark);
+        let right_key = right_key.filter(|_| time_ms.saturating_add(self.window.before_ms) >= watermark);
+        if left_key.is_none() && right_key.is_none() {
+            self.stats.late += 1;
+            return Vec::new();
+        }
+
+        // 両側に該当するイベントが自分自身と組にならないよう、照合してから登録する
+        let mut joined = Vec::new();
+        if let Some(key) = &left_key {
+            let (from, to) = (time_ms.saturating_sub(self.window.before_ms), time_ms.saturating_add(self.window.after_ms));
+            for (right_ms, right) in self.right.matches(key, from, to) {
+                joined.push(self.joined(key, event, time_ms, right, right_ms));
+            }
+        }
+        if let Some(key) = &right_key {
+            let (from, to) = (time_ms.saturating_sub(self.window.after_ms), time_ms.saturating_add(self.window.before_ms));
+            for (left_ms, left) in self.left.matches(key, from, to) {
+                joined.push(self.joined(key, left, left_ms, event, time_ms));
+            }
+        }
+        self.stats.joined += joined.len() as u64;
+
+        self.sequence += 1;
+        let slot = (time_ms, self.sequence);
+        if let Some(key) = left_key {
+            self.stats.left += 1;
+            self.left.insert(key, slot, event.clone());
+        }
+        if let Some(key) = right_key {
+            self.stats.right += 1;
+            self.right.insert(key, slot, event.clone());
+        }
+        if let Some(max) = self.max_buffered {
+            for side in [&mut self.left, &mut self.right] {
+                while side.len() > max && side.pop_oldest() {
+                    self.stats.dropped += 1;
+                }
+            }
+        }
+
+        let candidate = time_ms.saturating_sub(self.allowed_lateness_ms);
+        if candidate > watermark {
+            self.advance_watermark(candidate);
+        }
+        joined
+    }
+
+    /// Move the watermark forward (e.g. on an idle timer) and evict expired events
+    pub fn advance_watermark(&mut self, watermark_ms: i64) {
+        if self.watermark_ms.map(|current| watermark_ms <= current).unwrap_or(false) {
+            return;
+        }
+        self.watermark_ms = Some(watermark_ms);
+        self.left.evict_before(watermark_ms.saturating_sub(self.window.after_ms));
+        self.right.evict_before(watermark_ms.saturating_sub(self.window.before_ms));
+    }
+
+    fn joined(&self, key: &[String], left: &StreamingEvent, left_ms: i64, right: &StreamingEvent, right_ms: i64) -> StreamingEvent {
+        StreamingEvent::JoinedEvent {
+            join: self.name.clone(),
+            key: key.to_vec(),
+            delta_ms: right_ms - left_ms,
+            timestamp: left.timestamp().max(right.timestamp()),
+            left: Box::new(left.clone()),
+            right: Box::new(right.clone()),
+        }
+    }
+}
+
+/// `StreamProcessor` that joins two streams and forwards the joined events downstream
+///
+/// ```ignore
+/// // プロセス実行の後 30 秒以内の、同じホストからのネットワーク接続
+/// let operator = StreamJoinOperator::new(
+///     "exec_then_connect",
+///     event_type_is("ProcessExecution"),
+///     event_type_is("NetworkConnection"),
+///     "left.source = right.source".parse()?,
+///     JoinWindow::following(Duration::from_secs(30)),
+/// )?;
+/// let processor = StreamJoinProcessor::new(operator, reasoning_pipeline);
+/// ```
+pub struct StreamJoinProcessor<P: StreamProcessor> {
+    operator: Mutex<StreamJoinOperator>,
+    downstream: P,
+    passthrough: bool,
+}
+
+impl<P: StreamProcessor> StreamJoinProcessor<P> {
+    pub fn new(operator: StreamJoinOperator, downstream: P) -> Self {
+        Self { operator: Mutex::new(operator), downstream, passthrough: true }
+    }
+
+    /// Also forward the input events (default), or only the joined events
+    pub fn with_passthrough(mut self, passthrough: bool) -> Self {
+        self.passthrough = passthrough;
+        self
+    }
+
+    pub fn stats(&self) -> JoinStats {
+        self.lock().map(|operator| operator.stats()).unwrap_or_default()
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, StreamJoinOperator>, StreamError> {
+        self.operator.lock().map_err(|_| StreamError::ProcessorError("join state poisoned".to_string()))
+    }
+
+    async fn forward(&self, mut events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        match events.len() {
+            0 => Ok(()),
+            1 => self.downstream.process_event(events.remove(0)).await,
+            _ => self.downstream.process_batch(events).await,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: StreamProcessor> StreamProcessor for StreamJoinProcessor<P> {
+    async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        self.process_batch(vec![event]).await
+    }
+
+    async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        let mut output = Vec::with_capacity(events.len());
+        {
+            let mut operator = self.lock()?;
+            for event in events {
+                let joined = operator.push(&event);
+                if self.passthrough {
+                    output.push(event);
+                }
+                output.extend(joined);
+            }
+        }
+        self.forward(output).await
+    }
+
+    fn name(&self) -> &'static str {
+        "stream_join"
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        self.lock().map(|_| ())?;
+        self.downstream.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_core::model::CyberEvent;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn event(host: &str, event: CyberEvent, at_secs: i64) -> StreamingEvent {
+        StreamingEvent::SecurityEvent {
+            event,
+            timestamp: chrono::DateTime::from_timestamp(at_secs, 0).unwrap(),
+            source: host.to_string(),
+            correlation_id: Some(format!("{}-{}", host, at_secs)),
+        }
+    }
+
+    fn exec(host: &str, at_secs: i64) -> StreamingEvent {
+        event(host, CyberEvent::ProcessExecution {
+            process_id: 4242,
+            parent_process_id: Some(1),
+            command_line: "powershell -enc SQBFAFgA".to_string(),
+            user: "alice".to_string(),
+            timestamp: at_secs,
+        }, at_secs)
+    }
+
+    fn connect(host: &str, at_secs: i64) -> StreamingEvent {
+        event(host, CyberEvent::NetworkConnection {
+            source_ip: "10.0.0.5".to_string(),
+            dest_ip: "198.51.100.7".to_string(),
+            port: 443,
+            protocol: "tcp".to_string(),
+            timestamp: at_secs,
+        }, at_secs)
+    }
+
+    fn exec_then_connect() -> StreamJoinOperator {
+        StreamJoinOperator::new(
+            "exec_then_connect",
+            event_type_is("ProcessExecution"),
+            event_type_is("NetworkConnection"),
+            "left.source = right.source".parse().unwrap(),
+            JoinWindow::following(Duration::from_secs(30)),
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_condition_parsing() {
+        let condition: JoinCondition = "left.source = right.source AND right.user=left.user".parse().unwrap();
+        assert_eq!(condition, JoinCondition::on("source", "source").and("user", "user"));
+
+        assert!("left.source = left.source".parse::<JoinCondition>().is_err());
+        assert!("source = right.source".parse::<JoinCondition>().is_err());
+        assert!("left.source right.source".parse::<JoinCondition>().is_err());
+    }
+
+    #[test]
+    fn test_process_then_connection_on_same_host() {
+        let mut operator = exec_then_connect();
+
+        assert!(operator.push(&exec("ws-01", 100)).is_empty());
+        // 別ホストの接続、窓の外の接続は結合しない
+        assert!(operator.push(&connect("ws-02", 110)).is_empty());
+        let joined = operator.push(&connect("ws-01", 120));
+        assert_eq!(joined.len(), 1);
+        match &joined[0] {
+            StreamingEvent::JoinedEvent { join, key, delta_ms, .. } => {
+                assert_eq!(join, "exec_then_connect");
+                assert_eq!(key, &vec!["ws-01".to_string()]);
+                assert_eq!(*delta_ms, 20_000);
+            }
+            other => panic!("expected joined event, got {:?}", other),
+        }
+        assert_eq!(joined[0].correlation_ids(), vec!["ws-01-100".to_string(), "ws-01-120".to_string()]);
+        assert_eq!(event_field(&joined[0], "left.command_line").as_deref(), Some("powershell -enc SQBFAFgA"));
+        assert_eq!(event_field(&joined[0], "right.dest_ip").as_deref(), Some("198.51.100.7"));
+
+        assert!(operator.push(&connect("ws-01", 131)).is_empty());
+        let stats = operator.stats();
+        assert_eq!((stats.left, stats.right, stats.joined), (1, 3, 1));
+    }
+
+    #[test]
+    fn test_out_of_order_and_eviction() {
+        let mut operator = exec_then_connect()
+            .with_time_source(TimeSource::Event)
+            .with_allowed_lateness_ms(10_000);
+
+        // 接続が先に届いても、遅延許容内なら後から来たプロセス実行と結合する
+        assert!(operator.push(&connect("ws-01", 105)).is_empty());
+        assert_eq!(operator.push(&exec("ws-01", 100)).len(), 1);
+
+        // ウォーターマークが進むと古いプロセス実行は破棄され、それより古いイベントは遅延扱い
+        assert!(operator.push(&connect("ws-03", 200)).is_empty());
+        assert_eq!(operator.stats().buffered, 1);
+        assert!(operator.push(&exec("ws-01", 120)).is_empty());
+        assert_eq!(operator.stats().late, 1);
+
+        assert!(StreamJoinOperator::new("bad", |_| true, |_| true, JoinCondition::on("a", "b"),
+            JoinWindow { before_ms: -1, after_ms: 0 }).is_err());
+    }
+
+    #[derive(Default)]
+    struct Collector {
+        events: Mutex<Vec<StreamingEvent>>,
+    }
+
+    #[async_trait]
+    impl StreamProcessor for Arc<Collector> {
+        async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            self.events.lock().unwrap().extend(events);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "collector"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_join_processor_forwards_downstream() {
+        let collector = Arc::new(Collector::default());
+        let processor = StreamJoinProcessor::new(exec_then_connect(), Arc::clone(&collector));
+        processor.process_batch(vec![exec("ws-01", 100), connect("ws-01", 101)]).await.unwrap();
+        let types: Vec<&str> = collector.events.lock().unwrap().iter().map(|e| e.event_type()).collect();
+        assert_eq!(types, vec!["security_event", "security_event", "joined_event"]);
+
+        let collector = Arc::new(Collector::default());
+        let processor = StreamJoinProcessor::new(exec_then_connect(), Arc::clone(&collector)).with_passthrough(false);
+        processor.process_event(exec("ws-01", 100)).await.unwrap();
+        processor.process_event(connect("ws-01", 102)).await.unwrap();
+        assert_eq!(collector.events.lock().unwrap().len(), 1);
+        assert_eq!(processor.stats().joined, 1);
+    }
+}
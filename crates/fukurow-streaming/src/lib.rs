@@ -2,18 +2,46 @@
 //!
 //! Real-time streaming processing for Fukurow reasoning engine.
 //! Supports Kafka, NATS, Redis Streams, and RabbitMQ.
+//! Tumbling/sliding windows with per-window aggregation.
+//! Windowed stream-stream joins correlating events on key fields.
+//! Redis Streams consumer groups that claim stale pending entries (XAUTOCLAIM).
+//! RabbitMQ queues (classic or quorum) with dead-lettering and publisher confirms.
+//! JSON, Avro or Protobuf payloads with Confluent Schema Registry integration.
+//! Event field validation with strict, lenient or quarantine handling.
+//! Anomaly results written back into the knowledge graph for rule correlation.
+//! Coordinated shutdown: consumers finish and commit their batch, processors flush buffered events.
 
 pub mod stream;
 pub mod processor;
 pub mod consumer;
 pub mod producer;
 pub mod config;
+pub mod dlq;
+pub mod window;
+pub mod join;
+pub mod codec;
+pub mod event_validation;
+pub mod shutdown;
+#[cfg(feature = "shacl")]
+pub mod validation;
+#[cfg(feature = "anomaly-feedback")]
+pub mod anomaly_feedback;
 
 pub use stream::{StreamConfig, StreamType, AbstractStream, StreamMessage, StreamError};
 pub use processor::{StreamProcessor, EventStreamProcessor, EventSender, StreamConsumer, StreamProducer};
 pub use consumer::*;
 pub use producer::*;
 pub use config::*;
+pub use dlq::*;
+pub use window::*;
+pub use join::*;
+pub use codec::*;
+pub use event_validation::{EventValidationStage, EventValidationStats, EVENT_SCHEMA_CONSTRAINT};
+pub use shutdown::ShutdownSignal;
+#[cfg(feature = "shacl")]
+pub use validation::{ShaclValidationStage, ValidationStageStats, TripleExtractor, default_event_triples};
+#[cfg(feature = "anomaly-feedback")]
+pub use anomaly_feedback::{AnomalyFeedbackStage, AnomalyFeedbackStats, anomaly_triples};
 
 /// Streaming event types
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -23,6 +51,9 @@ pub enum StreamingEvent {
         event: fukurow_core::model::CyberEvent,
         timestamp: chrono::DateTime<chrono::Utc>,
         source: String,
+        /// Correlation ID assigned when the engine accepted the event
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        correlation_id: Option<String>,
     },
 
     /// Reasoning result
@@ -31,6 +62,9 @@ pub enum StreamingEvent {
         execution_time_ms: u64,
         event_count: usize,
         timestamp: chrono::DateTime<chrono::Utc>,
+        /// Correlation IDs of the events behind the actions
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        correlation_ids: Vec<String>,
     },
 
     /// Anomaly detection result
@@ -39,6 +73,18 @@ pub enum StreamingEvent {
         threshold: f64,
         metric: String,
         timestamp: chrono::DateTime<chrono::Utc>,
+        /// Ensemble strategy used to combine model scores, if any
+        #[serde(default)]
+        strategy: Option<String>,
+        /// Per-model contribution breakdown
+        #[serde(default)]
+        contributions: Vec<AnomalyContribution>,
+        /// Entity the metric was measured on (host, user, IP ...)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        entity: Option<String>,
+        /// Observation window the score was computed over
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        window: Option<AnomalyWindow>,
     },
 
     /// System metrics
@@ -48,6 +94,63 @@ pub enum StreamingEvent {
         active_connections: u32,
         timestamp: chrono::DateTime<chrono::Utc>,
     },
+
+    /// Event tagged with its SHACL conformance status
+    ValidatedEvent {
+        event: Box<StreamingEvent>,
+        conformance: ConformanceStatus,
+        violations: Vec<ShapeViolation>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+
+    /// Pair of events matched by a stream-stream join
+    JoinedEvent {
+        /// Name of the join
+        join: String,
+        /// Join key values, in condition order
+        key: Vec<String>,
+        left: Box<StreamingEvent>,
+        right: Box<StreamingEvent>,
+        /// Right event time minus left event time (milliseconds)
+        delta_ms: i64,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+/// SHACL conformance status of a streamed event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConformanceStatus {
+    Conforms,
+    Violates,
+    /// Validation could not be performed
+    Error,
+}
+
+/// Single shape violation attached to a validated event
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ShapeViolation {
+    pub focus_node: Option<String>,
+    pub path: Option<String>,
+    pub constraint: String,
+    pub message: Option<String>,
+}
+
+/// Contribution of a single anomaly model to an ensemble score
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AnomalyContribution {
+    pub model: String,
+    pub score: f64,
+    pub weight: f64,
+    pub contribution: f64,
+    pub is_anomaly: bool,
+}
+
+/// Time range an anomaly score covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AnomalyWindow {
+    pub start: chrono::DateTime<chrono::Utc>,
+    pub end: chrono::DateTime<chrono::Utc>,
 }
 
 impl StreamingEvent {
@@ -58,6 +161,8 @@ impl StreamingEvent {
             StreamingEvent::ReasoningResult { .. } => "reasoning_result",
             StreamingEvent::AnomalyDetected { .. } => "anomaly_detected",
             StreamingEvent::SystemMetrics { .. } => "system_metrics",
+            StreamingEvent::ValidatedEvent { .. } => "validated_event",
+            StreamingEvent::JoinedEvent { .. } => "joined_event",
         }
     }
 
@@ -68,6 +173,24 @@ impl StreamingEvent {
             StreamingEvent::ReasoningResult { timestamp, .. } => *timestamp,
             StreamingEvent::AnomalyDetected { timestamp, .. } => *timestamp,
             StreamingEvent::SystemMetrics { timestamp, .. } => *timestamp,
+            StreamingEvent::ValidatedEvent { timestamp, .. } => *timestamp,
+            StreamingEvent::JoinedEvent { timestamp, .. } => *timestamp,
+        }
+    }
+
+    /// Correlation IDs linking this event to the originating sensor events
+    pub fn correlation_ids(&self) -> Vec<String> {
+        match self {
+            StreamingEvent::SecurityEvent { correlation_id, .. } => correlation_id.iter().cloned().collect(),
+            StreamingEvent::ReasoningResult { correlation_ids, .. } => correlation_ids.clone(),
+            StreamingEvent::ValidatedEvent { event, .. } => event.correlation_ids(),
+            StreamingEvent::JoinedEvent { left, right, .. } => {
+                let mut ids = left.correlation_ids();
+                let new_ids: Vec<String> = right.correlation_ids().into_iter().filter(|id| !ids.contains(id)).collect();
+                ids.extend(new_ids);
+                ids
+            }
+            StreamingEvent::AnomalyDetected { .. } | StreamingEvent::SystemMetrics { .. } => Vec::new(),
         }
     }
 }
@@ -89,10 +212,12 @@ mod tests {
             },
             timestamp: chrono::Utc::now(),
             source: "sensor1".to_string(),
+            correlation_id: Some("edr-7".to_string()),
         };
 
         assert_eq!(security_event.event_type(), "security_event");
         assert!(security_event.timestamp() <= chrono::Utc::now());
+        assert_eq!(security_event.correlation_ids(), vec!["edr-7".to_string()]);
     }
 
     #[test]
@@ -107,4 +232,45 @@ mod tests {
         assert_eq!(metrics_event.event_type(), "system_metrics");
         assert_eq!(metrics_event.timestamp() <= chrono::Utc::now(), true);
     }
+
+    #[test]
+    fn test_anomaly_event_contributions_roundtrip() {
+        let legacy = r#"{"AnomalyDetected":{"score":2.5,"threshold":2.0,"metric":"login_attempts","timestamp":"2024-01-01T00:00:00Z"}}"#;
+        let event: StreamingEvent = serde_json::from_str(legacy).unwrap();
+        match &event {
+            StreamingEvent::AnomalyDetected { strategy, contributions, entity, window, .. } => {
+                assert!(strategy.is_none());
+                assert!(contributions.is_empty());
+                assert!(entity.is_none() && window.is_none());
+            }
+            _ => panic!("expected anomaly event"),
+        }
+
+        let event = StreamingEvent::AnomalyDetected {
+            score: 0.75,
+            threshold: 0.5,
+            metric: "login_attempts".to_string(),
+            timestamp: chrono::Utc::now(),
+            strategy: Some("majority_vote".to_string()),
+            contributions: vec![AnomalyContribution {
+                model: "statistical".to_string(),
+                score: 3.9,
+                weight: 2.0,
+                contribution: 0.5,
+                is_anomaly: true,
+            }],
+            entity: Some("host-1".to_string()),
+            window: None,
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        let decoded: StreamingEvent = serde_json::from_str(&json).unwrap();
+        match decoded {
+            StreamingEvent::AnomalyDetected { contributions, entity, .. } => {
+                assert_eq!(contributions.len(), 1);
+                assert_eq!(contributions[0].model, "statistical");
+                assert_eq!(entity.as_deref(), Some("host-1"));
+            }
+            _ => panic!("expected anomaly event"),
+        }
+    }
 }
@@ -2,12 +2,15 @@
 //!
 //! Core streaming processor for handling events
 
-use crate::{StreamingEvent, StreamingConfig, StreamError};
+use crate::{DeadLetter, DeadLetterQueue, ShutdownSignal, StreamingEvent, StreamingConfig, StreamError};
 use async_trait::async_trait;
+use fukurow_core::retry::{retry_retryable, RetryPolicy};
 use futures::stream::{Stream, StreamExt};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
 /// Stream processor trait
@@ -19,6 +22,13 @@ pub trait StreamProcessor: Send + Sync {
     /// Process a batch of events
     async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError>;
 
+    /// Process a batch and return events to publish downstream
+    ///
+    /// Exactly-once consumers produce these in the same transaction as the offset commit.
+    async fn process_batch_with_output(&self, events: Vec<StreamingEvent>) -> Result<Vec<StreamingEvent>, StreamError> {
+        self.process_batch(events).await.map(|_| Vec::new())
+    }
+
     /// Get processor name
     fn name(&self) -> &'static str;
 
@@ -32,6 +42,8 @@ pub struct EventStreamProcessor<P: StreamProcessor> {
     config: StreamingConfig,
     event_tx: mpsc::UnboundedSender<StreamingEvent>,
     event_rx: mpsc::UnboundedReceiver<StreamingEvent>,
+    shutdown: ShutdownSignal,
+    dead_letter_producer: Option<Arc<dyn StreamProducer>>,
 }
 
 impl<P: StreamProcessor + 'static> EventStreamProcessor<P> {
@@ -44,24 +56,60 @@ impl<P: StreamProcessor + 'static> EventStreamProcessor<P> {
             config,
             event_tx,
             event_rx,
+            shutdown: ShutdownSignal::new(),
+            dead_letter_producer: None,
         }
     }
 
+    /// Stop once `shutdown` fires, after processing the buffered events
+    pub fn with_shutdown(mut self, shutdown: ShutdownSignal) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Publish dead letters through `producer` when `processing.dead_letter` is a topic
+    pub fn with_dead_letter_producer(mut self, producer: Arc<dyn StreamProducer>) -> Self {
+        self.dead_letter_producer = Some(producer);
+        self
+    }
+
     /// Start processing events
-    pub async fn start_processing(mut self) -> Result<(), StreamError> {
+    ///
+    /// 返されたタスクはシャットダウンシグナルの発火後、チャネルに残ったイベントを
+    /// すべて処理してから終了する (以降の送信は `ChannelClosed` になる)。
+    /// `processing.dead_letter` が設定されていれば、リトライし尽くしたバッチのイベントはそこへ送る
+    pub async fn start_processing(mut self) -> Result<JoinHandle<()>, StreamError> {
         info!("Starting event stream processor: {}", self.processor.name());
 
+        let dead_letters = match &self.config.processing.dead_letter {
+            Some(config) => Some(config.build(self.dead_letter_producer.take())?),
+            None => None,
+        };
         let processor = Arc::clone(&self.processor);
-        let batch_size = self.config.processing.batch_size;
+        let retry_policy = self.config.processing.retry.to_policy();
+        let batch_size = self.config.processing.batch_size.max(1);
         let processing_timeout = std::time::Duration::from_secs(
             self.config.processing.processing_timeout_seconds
         );
+        let shutdown = self.shutdown.clone();
 
-        tokio::spawn(async move {
+        let task = tokio::spawn(async move {
             let mut batch = Vec::with_capacity(batch_size);
             let mut last_process_time = std::time::Instant::now();
 
-            while let Some(event) = self.event_rx.recv().await {
+            loop {
+                let event = tokio::select! {
+                    biased;
+                    _ = shutdown.triggered() => {
+                        self.event_rx.close();
+                        while let Some(event) = self.event_rx.recv().await {
+                            batch.push(event);
+                        }
+                        None
+                    }
+                    event = self.event_rx.recv() => event,
+                };
+                let Some(event) = event else { break };
                 batch.push(event);
 
                 // Process batch if it's full or timeout has passed
@@ -69,23 +117,20 @@ impl<P: StreamProcessor + 'static> EventStreamProcessor<P> {
                     last_process_time.elapsed() >= processing_timeout;
 
                 if should_process {
-                    if let Err(e) = processor.process_batch(batch).await {
-                        error!("Failed to process batch: {}", e);
-                    }
+                    process_batch_or_dead_letter(processor.as_ref(), &retry_policy, dead_letters.as_deref(), batch).await;
                     batch = Vec::with_capacity(batch_size);
                     last_process_time = std::time::Instant::now();
                 }
             }
 
             // Process remaining events
-            if !batch.is_empty() {
-                if let Err(e) = processor.process_batch(batch).await {
-                    error!("Failed to process final batch: {}", e);
-                }
+            for chunk in batch.chunks(batch_size) {
+                process_batch_or_dead_letter(processor.as_ref(), &retry_policy, dead_letters.as_deref(), chunk.to_vec()).await;
             }
+            info!("Event stream processor {} stopped", processor.name());
         });
 
-        Ok(())
+        Ok(task)
     }
 
     /// Send event to processor
@@ -107,8 +152,53 @@ impl<P: StreamProcessor + 'static> EventStreamProcessor<P> {
     }
 }
 
+/// Process a batch, retrying transient failures according to the policy
+///
+/// 失敗した場合はエラーと試行回数を返す
+async fn process_batch_with_retry<P: StreamProcessor + ?Sized>(
+    processor: &P,
+    policy: &RetryPolicy,
+    batch: Vec<StreamingEvent>,
+) -> Result<(), (StreamError, u32)> {
+    let attempts = AtomicU32::new(0);
+    retry_retryable(policy, |attempt| {
+        attempts.store(attempt, Ordering::Relaxed);
+        if attempt > 1 {
+            warn!("Retrying batch of {} events (attempt {})", batch.len(), attempt);
+        }
+        processor.process_batch(batch.clone())
+    }, tokio::time::sleep).await
+        .map_err(|e| (e, attempts.load(Ordering::Relaxed)))
+}
+
+/// Process a batch and dead-letter its events if it still fails after all retries
+async fn process_batch_or_dead_letter<P: StreamProcessor + ?Sized>(
+    processor: &P,
+    policy: &RetryPolicy,
+    dead_letters: Option<&dyn DeadLetterQueue>,
+    batch: Vec<StreamingEvent>,
+) {
+    let Err((e, attempts)) = process_batch_with_retry(processor, policy, batch.clone()).await else { return };
+    error!("Failed to process batch of {} events: {}", batch.len(), e);
+    let Some(dead_letters) = dead_letters else { return };
+
+    let failed_at = chrono::Utc::now();
+    for event in batch {
+        let letter = DeadLetter {
+            event,
+            error: e.to_string(),
+            producer: processor.name().to_string(),
+            attempts,
+            failed_at,
+        };
+        if let Err(e) = dead_letters.send(letter).await {
+            error!("Failed to dead-letter event via {}: {}", dead_letters.name(), e);
+        }
+    }
+}
+
 /// Event sender handle for external components
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct EventSender {
     sender: mpsc::UnboundedSender<StreamingEvent>,
 }
@@ -122,21 +212,33 @@ impl EventSender {
 
     /// Send security event
     pub fn send_security_event(&self, event: fukurow_core::model::CyberEvent, source: String) -> Result<(), StreamError> {
+        self.send_correlated_security_event(event, source, None)
+    }
+
+    /// Send security event carrying the correlation ID assigned at ingestion
+    pub fn send_correlated_security_event(&self, event: fukurow_core::model::CyberEvent, source: String, correlation_id: Option<String>) -> Result<(), StreamError> {
         let streaming_event = StreamingEvent::SecurityEvent {
             event,
             timestamp: chrono::Utc::now(),
             source,
+            correlation_id,
         };
         self.send(streaming_event)
     }
 
     /// Send reasoning result
     pub fn send_reasoning_result(&self, actions: Vec<fukurow_core::model::SecurityAction>, execution_time_ms: u64, event_count: usize) -> Result<(), StreamError> {
+        self.send_correlated_reasoning_result(actions, execution_time_ms, event_count, Vec::new())
+    }
+
+    /// Send reasoning result with the correlation IDs of the events behind it
+    pub fn send_correlated_reasoning_result(&self, actions: Vec<fukurow_core::model::SecurityAction>, execution_time_ms: u64, event_count: usize, correlation_ids: Vec<String>) -> Result<(), StreamError> {
         let streaming_event = StreamingEvent::ReasoningResult {
             actions,
             execution_time_ms,
             event_count,
             timestamp: chrono::Utc::now(),
+            correlation_ids,
         };
         self.send(streaming_event)
     }
@@ -148,6 +250,54 @@ impl EventSender {
             threshold,
             metric,
             timestamp: chrono::Utc::now(),
+            strategy: None,
+            contributions: Vec::new(),
+            entity: None,
+            window: None,
+        };
+        self.send(streaming_event)
+    }
+
+    /// Send anomaly detection result for one entity over an observation window
+    pub fn send_entity_anomaly(
+        &self,
+        score: f64,
+        threshold: f64,
+        metric: String,
+        entity: String,
+        window: Option<crate::AnomalyWindow>,
+    ) -> Result<(), StreamError> {
+        let streaming_event = StreamingEvent::AnomalyDetected {
+            score,
+            threshold,
+            metric,
+            timestamp: chrono::Utc::now(),
+            strategy: None,
+            contributions: Vec::new(),
+            entity: Some(entity),
+            window,
+        };
+        self.send(streaming_event)
+    }
+
+    /// Send ensemble anomaly detection result with per-model contributions
+    pub fn send_ensemble_anomaly(
+        &self,
+        score: f64,
+        threshold: f64,
+        metric: String,
+        strategy: String,
+        contributions: Vec<crate::AnomalyContribution>,
+    ) -> Result<(), StreamError> {
+        let streaming_event = StreamingEvent::AnomalyDetected {
+            score,
+            threshold,
+            metric,
+            timestamp: chrono::Utc::now(),
+            strategy: Some(strategy),
+            contributions,
+            entity: None,
+            window: None,
         };
         self.send(streaming_event)
     }
@@ -186,6 +336,18 @@ pub trait StreamProducer: Send + Sync {
     /// Produce batch of events
     async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError>;
 
+    /// Publish an already encoded payload to `topic` instead of the producer's own destination
+    ///
+    /// トピック DLQ が封筒 ([`crate::DeadLetter`]) を送るのに使う。トピックを指定できない producer はエラーを返す
+    async fn produce_to(&self, topic: &str, _payload: Vec<u8>) -> Result<(), StreamError> {
+        Err(StreamError::ConfigError(format!("{} cannot publish to topic {}", self.name(), topic)))
+    }
+
+    /// Deliver events still buffered by the client (called on shutdown)
+    async fn flush(&self) -> Result<(), StreamError> {
+        Ok(())
+    }
+
     /// Get producer name
     fn name(&self) -> &'static str;
 
@@ -193,6 +355,33 @@ pub trait StreamProducer: Send + Sync {
     async fn health_check(&self) -> Result<(), StreamError>;
 }
 
+#[async_trait]
+impl<P: StreamProducer + ?Sized> StreamProducer for Arc<P> {
+    async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        (**self).produce(event).await
+    }
+
+    async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        (**self).produce_batch(events).await
+    }
+
+    async fn produce_to(&self, topic: &str, payload: Vec<u8>) -> Result<(), StreamError> {
+        (**self).produce_to(topic, payload).await
+    }
+
+    async fn flush(&self) -> Result<(), StreamError> {
+        (**self).flush().await
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        (**self).health_check().await
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -246,6 +435,8 @@ mod tests {
 
         // Send anomaly
         sender.send_anomaly(2.5, 2.0, "login_attempts".to_string()).unwrap();
+        sender.send_ensemble_anomaly(0.75, 0.5, "login_attempts".to_string(), "majority_vote".to_string(), vec![]).unwrap();
+        sender.send_entity_anomaly(3.1, 2.0, "admin_logins".to_string(), "host-1".to_string(), None).unwrap();
 
         // Send metrics
         sender.send_metrics(45.5, 67.8, 150).unwrap();
@@ -254,6 +445,156 @@ mod tests {
         assert!(stream_processor.health_check().await.is_ok());
     }
 
+    #[derive(Default)]
+    struct CountingProcessor {
+        processed: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl StreamProcessor for Arc<CountingProcessor> {
+        async fn process_event(&self, _event: StreamingEvent) -> Result<(), StreamError> {
+            Ok(())
+        }
+
+        async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            self.processed.fetch_add(events.len(), std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "counting_processor"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_processes_buffered_events() {
+        let processor = Arc::new(CountingProcessor::default());
+        let shutdown = ShutdownSignal::new();
+        // バッチが埋まらずタイムアウトもしない設定で、バッファに溜まったままにする
+        let mut config = StreamingConfig::default();
+        config.processing.batch_size = 100;
+        config.processing.processing_timeout_seconds = 3600;
+        let stream_processor = EventStreamProcessor::new(Arc::clone(&processor), config).with_shutdown(shutdown.clone());
+        let sender = stream_processor.event_sender();
+        let task = stream_processor.start_processing().await.unwrap();
+
+        for _ in 0..3 {
+            sender.send_metrics(1.0, 1.0, 1).unwrap();
+        }
+        shutdown.trigger();
+        task.await.unwrap();
+
+        assert_eq!(processor.processed.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(matches!(sender.send_metrics(1.0, 1.0, 1), Err(StreamError::ChannelClosed)));
+    }
+
+    struct FailingProcessor;
+
+    #[async_trait]
+    impl StreamProcessor for FailingProcessor {
+        async fn process_event(&self, _event: StreamingEvent) -> Result<(), StreamError> {
+            Err(StreamError::ConnectionError("store unavailable".to_string()))
+        }
+
+        async fn process_batch(&self, _events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            Err(StreamError::ConnectionError("store unavailable".to_string()))
+        }
+
+        fn name(&self) -> &'static str {
+            "failing_processor"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_batches_go_to_the_configured_dead_letter_queue() {
+        let path = std::env::temp_dir().join(format!("fukurow-processor-dlq-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut config = StreamingConfig::default();
+        config.processing.batch_size = 2;
+        config.processing.retry.max_attempts = 2;
+        config.processing.retry.initial_backoff_ms = 1;
+        config.processing.retry.max_backoff_ms = 1;
+        config.processing.dead_letter = Some(crate::DeadLetterConfig::Spool { path: path.display().to_string() });
+
+        let stream_processor = EventStreamProcessor::new(FailingProcessor, config);
+        let sender = stream_processor.event_sender();
+        let task = stream_processor.start_processing().await.unwrap();
+        sender.send_metrics(1.0, 1.0, 1).unwrap();
+        sender.send_metrics(2.0, 2.0, 2).unwrap();
+        drop(sender);
+        task.await.unwrap();
+
+        let letters = crate::DiskSpoolDeadLetterQueue::new(&path).unwrap().peek().unwrap();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].producer, "failing_processor");
+        assert_eq!(letters[0].attempts, 2);
+        assert!(letters[0].error.contains("store unavailable"));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_topic_dead_letter_config_without_a_producer_is_rejected() {
+        let mut config = StreamingConfig::default();
+        config.processing.dead_letter = Some(crate::DeadLetterConfig::Topic { topic: "events.dlq".to_string() });
+        let stream_processor = EventStreamProcessor::new(FailingProcessor, config);
+        assert!(matches!(stream_processor.start_processing().await, Err(StreamError::ConfigError(_))));
+    }
+
+    struct FlakyProducer {
+        failures_left: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait]
+    impl StreamProducer for FlakyProducer {
+        async fn produce(&self, _event: StreamingEvent) -> Result<(), StreamError> {
+            if self.failures_left.load(std::sync::atomic::Ordering::SeqCst) > 0 {
+                self.failures_left.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                return Err(StreamError::ConnectionError("broker unavailable".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn produce_batch(&self, _events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            Err(StreamError::ConfigError("bad topic".to_string()))
+        }
+
+        fn name(&self) -> &'static str {
+            "flaky_producer"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retrying_producer() {
+        let policy = fukurow_core::retry::RetryPolicy::new(3).with_initial_backoff_ms(1).with_max_backoff_ms(2);
+        let producer = crate::producer::RetryingProducer::new(
+            FlakyProducer { failures_left: std::sync::atomic::AtomicU32::new(2) },
+            policy,
+        );
+        let event = StreamingEvent::SystemMetrics {
+            cpu_usage: 1.0,
+            memory_usage: 1.0,
+            active_connections: 1,
+            timestamp: chrono::Utc::now(),
+        };
+
+        assert!(producer.produce(event.clone()).await.is_ok());
+        assert!(matches!(producer.produce_batch(vec![event]).await, Err(StreamError::ConfigError(_))));
+        assert_eq!(producer.name(), "flaky_producer");
+    }
+
     #[test]
     fn test_stream_error_display() {
         let err = StreamError::ChannelClosed;
@@ -2,8 +2,54 @@
 //!
 //! Stream producer implementations
 
+use crate::codec::EventCodec;
 use crate::{StreamingEvent, StreamError, StreamProducer};
 use async_trait::async_trait;
+use fukurow_core::retry::{retry_retryable, RetryPolicy};
+use std::sync::Arc;
+
+/// Producer wrapper that retries transient failures with the shared backoff policy
+pub struct RetryingProducer<P: StreamProducer> {
+    inner: P,
+    policy: RetryPolicy,
+}
+
+impl<P: StreamProducer> RetryingProducer<P> {
+    pub fn new(inner: P, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<P: StreamProducer> StreamProducer for RetryingProducer<P> {
+    async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        retry_retryable(&self.policy, |_| self.inner.produce(event.clone()), tokio::time::sleep).await
+    }
+
+    async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        retry_retryable(&self.policy, |_| self.inner.produce_batch(events.clone()), tokio::time::sleep).await
+    }
+
+    async fn produce_to(&self, topic: &str, payload: Vec<u8>) -> Result<(), StreamError> {
+        retry_retryable(&self.policy, |_| self.inner.produce_to(topic, payload.clone()), tokio::time::sleep).await
+    }
+
+    async fn flush(&self) -> Result<(), StreamError> {
+        self.inner.flush().await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        self.inner.health_check().await
+    }
+}
 
 /// NATS producer (stub implementation)
 #[cfg(feature = "nats")]
@@ -31,6 +77,11 @@ impl StreamProducer for NATSProducer {
         Ok(())
     }
 
+    async fn produce_to(&self, _topic: &str, _payload: Vec<u8>) -> Result<(), StreamError> {
+        // TODO: Implement production to an alternate subject
+        Ok(())
+    }
+
     fn name(&self) -> &'static str {
         "nats_producer"
     }
@@ -41,6 +92,58 @@ impl StreamProducer for NATSProducer {
     }
 }
 
+/// JetStream producer
+///
+/// publish ごとにストリームの ACK を待つため、成功したイベントはサーバーに永続化されている
+pub struct JetStreamProducer<C: crate::consumer::JetStreamClient> {
+    client: C,
+    subject: String,
+    codec: Arc<EventCodec>,
+}
+
+impl<C: crate::consumer::JetStreamClient> JetStreamProducer<C> {
+    pub fn new(client: C, subject: impl Into<String>) -> Self {
+        Self { client, subject: subject.into(), codec: Arc::default() }
+    }
+
+    /// Encode payloads with `codec` (plain JSON by default)
+    pub fn with_codec(mut self, codec: Arc<EventCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl<C: crate::consumer::JetStreamClient> StreamProducer for JetStreamProducer<C> {
+    async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        let payload = self.codec.encode(&event)?;
+        self.client.publish(&self.subject, payload).await.map(|_| ())
+    }
+
+    async fn produce_to(&self, topic: &str, payload: Vec<u8>) -> Result<(), StreamError> {
+        self.client.publish(topic, payload).await.map(|_| ())
+    }
+
+    async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        for event in events {
+            self.produce(event).await?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "jetstream_producer"
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        Ok(())
+    }
+}
+
 /// Redis producer (stub implementation)
 #[cfg(feature = "redis")]
 pub struct RedisProducer {
@@ -67,6 +170,11 @@ impl StreamProducer for RedisProducer {
         Ok(())
     }
 
+    async fn produce_to(&self, _topic: &str, _payload: Vec<u8>) -> Result<(), StreamError> {
+        // TODO: Implement production to an alternate stream
+        Ok(())
+    }
+
     fn name(&self) -> &'static str {
         "redis_producer"
     }
@@ -77,29 +185,68 @@ impl StreamProducer for RedisProducer {
     }
 }
 
-/// RabbitMQ producer (stub implementation)
-#[cfg(feature = "rabbitmq")]
-pub struct RabbitMQProducer {
-    config: crate::config::ConnectionConfig,
+/// RabbitMQ producer with publisher confirms
+///
+/// ブローカーが NACK したメッセージは `max_publish_attempts` 回まで送り直す。
+/// 確認が有効なら、成功したイベントはブローカーに保存されている
+pub struct RabbitMQProducer<C: crate::consumer::AmqpClient> {
+    client: C,
+    exchange: String,
+    routing_key: String,
+    max_attempts: u32,
+    codec: Arc<EventCodec>,
 }
 
-#[cfg(feature = "rabbitmq")]
-impl RabbitMQProducer {
-    pub fn new(config: crate::config::ConnectionConfig) -> Self {
-        Self { config }
+impl<C: crate::consumer::AmqpClient> RabbitMQProducer<C> {
+    pub fn new(client: C, config: &crate::config::RabbitMQConfig) -> Self {
+        Self {
+            client,
+            exchange: config.exchange.clone(),
+            routing_key: config.routing_key.clone(),
+            max_attempts: config.max_publish_attempts.max(1),
+            codec: Arc::default(),
+        }
+    }
+
+    /// Encode payloads with `codec` (plain JSON by default)
+    pub fn with_codec(mut self, codec: Arc<EventCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    async fn publish_confirmed(&self, routing_key: &str, payload: &[u8]) -> Result<(), StreamError> {
+        for attempt in 1..=self.max_attempts {
+            match self.client.publish(&self.exchange, routing_key, payload).await? {
+                crate::consumer::PublishConfirm::Ack => return Ok(()),
+                crate::consumer::PublishConfirm::Nack => {
+                    tracing::warn!("RabbitMQ NACKed publish to {} (attempt {}/{})", self.exchange, attempt, self.max_attempts);
+                }
+            }
+        }
+        Err(StreamError::SendError(format!("broker rejected the message {} times", self.max_attempts)))
     }
 }
 
-#[cfg(feature = "rabbitmq")]
 #[async_trait]
-impl StreamProducer for RabbitMQProducer {
-    async fn produce(&self, _event: StreamingEvent) -> Result<(), StreamError> {
-        // TODO: Implement actual RabbitMQ production
-        Ok(())
+impl<C: crate::consumer::AmqpClient> StreamProducer for RabbitMQProducer<C> {
+    async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        let payload = self.codec.encode(&event)?;
+        self.publish_confirmed(&self.routing_key, &payload).await
     }
 
-    async fn produce_batch(&self, _events: Vec<StreamingEvent>) -> Result<(), StreamError> {
-        // TODO: Implement batch production
+    /// Publish to the same exchange with `topic` as the routing key
+    async fn produce_to(&self, topic: &str, payload: Vec<u8>) -> Result<(), StreamError> {
+        self.publish_confirmed(topic, &payload).await
+    }
+
+    async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        for event in events {
+            self.produce(event).await?;
+        }
         Ok(())
     }
 
@@ -108,7 +255,6 @@ impl StreamProducer for RabbitMQProducer {
     }
 
     async fn health_check(&self) -> Result<(), StreamError> {
-        // TODO: Implement health check
         Ok(())
     }
 }
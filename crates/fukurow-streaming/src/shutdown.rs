@@ -0,0 +1,64 @@
+//! Coordinated shutdown
+//!
+//! SIGTERM や API の drain 要求で発火するシグナル。コンシューマの `run_until` に
+//! `signal.triggered()` を渡すと処理中のバッチを終えて (オフセットをコミットして) 停止し、
+//! `EventStreamProcessor` はバッファ済みのイベントを処理してから終了する
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Cloneable shutdown trigger shared by the server and streaming components
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self { sender: Arc::new(sender) }
+    }
+
+    /// Fire the signal; returns `false` when it had already fired
+    pub fn trigger(&self) -> bool {
+        self.sender.send_if_modified(|triggered| !std::mem::replace(triggered, true))
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Complete once the signal has fired (immediately if it already has)
+    pub async fn triggered(&self) {
+        let mut receiver = self.sender.subscribe();
+        // 送信側はこの構造体が保持しているため、閉じられることはない
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signal_fires_once_for_every_clone() {
+        let signal = ShutdownSignal::new();
+        let waiter = {
+            let signal = signal.clone();
+            tokio::spawn(async move { signal.triggered().await })
+        };
+
+        assert!(!signal.is_triggered());
+        assert!(signal.trigger());
+        assert!(!signal.clone().trigger());
+        waiter.await.unwrap();
+        signal.triggered().await;
+        assert!(signal.is_triggered());
+    }
+}
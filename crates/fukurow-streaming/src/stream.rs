@@ -15,6 +15,9 @@ pub struct StreamConfig {
     pub group_id: Option<String>,
     pub partition: Option<i32>,
     pub options: std::collections::HashMap<String, String>,
+    /// Payload format, compression and schema registry settings of this topic
+    #[serde(default)]
+    pub serialization: crate::codec::SerializationConfig,
 }
 
 /// Stream type
@@ -69,6 +72,9 @@ pub enum StreamError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+
     #[error("Processor error: {0}")]
     ProcessorError(String),
 
@@ -78,3 +84,15 @@ pub enum StreamError {
     #[error("Stream closed")]
     StreamClosed,
 }
+
+impl fukurow_core::retry::Retryable for StreamError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            StreamError::ProcessingTimeout
+                | StreamError::ConnectionError(_)
+                | StreamError::SendError(_)
+                | StreamError::HealthCheckError(_)
+        )
+    }
+}
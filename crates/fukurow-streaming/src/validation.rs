@@ -0,0 +1,370 @@
+//! # SHACL Validation Stage
+//!
+//! In-flight SHACL validation of event-derived triples.
+//! Conforming events are tagged and forwarded downstream; violating events
+//! are tagged and routed to a quarantine producer before reaching the store.
+
+use crate::{ConformanceStatus, ShapeViolation, StreamingEvent, StreamError, StreamProcessor, StreamProducer};
+use async_trait::async_trait;
+use fukurow_core::model::{CyberEvent, Triple};
+use fukurow_shacl::loader::ShapesGraph;
+use fukurow_shacl::report::ViolationLevel;
+use fukurow_shacl::validator::{DefaultShaclValidator, ShaclValidator, ValidationConfig, ValidationMode};
+use fukurow_store::{GraphId, Provenance, RdfStore};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Converts a cyber event into the triples that will be validated
+pub type TripleExtractor = Arc<dyn Fn(&CyberEvent) -> Vec<Triple> + Send + Sync>;
+
+/// Default extractor: JSON-LD projection with expanded predicates and an `rdf:type` triple
+pub fn default_event_triples(event: &CyberEvent) -> Vec<Triple> {
+    let doc = match fukurow_core::jsonld::cyber_event_to_jsonld(event) {
+        Ok(doc) => doc,
+        Err(_) => return Vec::new(),
+    };
+    let vocab = doc.context.get("@vocab").and_then(|v| v.as_str()).unwrap_or("");
+    let expand = |key: &str| {
+        doc.context.get(key)
+            .and_then(|v| v.as_str())
+            .map(|iri| iri.to_string())
+            .unwrap_or_else(|| format!("{}{}", vocab, key))
+    };
+
+    let mut triples = Vec::new();
+    for node in doc.graph.iter().flatten().filter_map(|n| n.as_object()) {
+        let subject = match node.get("@id").and_then(|v| v.as_str()) {
+            Some(id) => id.to_string(),
+            None => continue,
+        };
+        for (key, value) in node {
+            let object = match (key.as_str(), value) {
+                ("@id", _) => continue,
+                ("@type", serde_json::Value::String(t)) => {
+                    triples.push(Triple { subject: subject.clone(), predicate: RDF_TYPE.to_string(), object: expand(t) });
+                    continue;
+                }
+                (_, serde_json::Value::String(s)) => s.clone(),
+                (_, serde_json::Value::Number(n)) => n.to_string(),
+                (_, serde_json::Value::Bool(b)) => b.to_string(),
+                _ => continue,
+            };
+            triples.push(Triple { subject: subject.clone(), predicate: expand(key), object });
+        }
+    }
+    triples
+}
+
+/// Validation stage statistics
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ValidationStageStats {
+    pub validated: u64,
+    pub conforming: u64,
+    pub quarantined: u64,
+    pub passed_through: u64,
+}
+
+/// Streaming stage that validates security events against SHACL shapes
+pub struct ShaclValidationStage<P: StreamProcessor, Q: StreamProducer> {
+    shapes: ShapesGraph,
+    downstream: P,
+    quarantine: Q,
+    extractor: TripleExtractor,
+    validated: AtomicU64,
+    conforming: AtomicU64,
+    quarantined: AtomicU64,
+    passed_through: AtomicU64,
+}
+
+impl<P: StreamProcessor, Q: StreamProducer> ShaclValidationStage<P, Q> {
+    pub fn new(shapes: ShapesGraph, downstream: P, quarantine: Q) -> Self {
+        Self {
+            shapes,
+            downstream,
+            quarantine,
+            extractor: Arc::new(default_event_triples),
+            validated: AtomicU64::new(0),
+            conforming: AtomicU64::new(0),
+            quarantined: AtomicU64::new(0),
+            passed_through: AtomicU64::new(0),
+        }
+    }
+
+    /// Use a custom event → triples mapping
+    pub fn with_extractor(mut self, extractor: TripleExtractor) -> Self {
+        self.extractor = extractor;
+        self
+    }
+
+    pub fn stats(&self) -> ValidationStageStats {
+        ValidationStageStats {
+            validated: self.validated.load(Ordering::Relaxed),
+            conforming: self.conforming.load(Ordering::Relaxed),
+            quarantined: self.quarantined.load(Ordering::Relaxed),
+            passed_through: self.passed_through.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Validate a single event and wrap it with its conformance status
+    ///
+    /// Non-security events are returned unchanged.
+    pub fn validate(&self, event: StreamingEvent) -> StreamingEvent {
+        let cyber_event = match &event {
+            StreamingEvent::SecurityEvent { event, .. } => event.clone(),
+            _ => {
+                self.passed_through.fetch_add(1, Ordering::Relaxed);
+                return event;
+            }
+        };
+        self.validated.fetch_add(1, Ordering::Relaxed);
+
+        let mut store = RdfStore::with_audit_limit(0);
+        let provenance = Provenance::Sensor {
+            source: "shacl-validation-stage".to_string(),
+            confidence: None,
+        };
+        store.insert_batch((self.extractor)(&cyber_event), GraphId::Default, provenance);
+
+        let config = ValidationConfig {
+            mode: ValidationMode::Warn,
+            report_jsonld: false,
+        };
+        let (conformance, violations) = match DefaultShaclValidator.validate_graph(&self.shapes, &store, &config) {
+            Ok(report) => {
+                let violations: Vec<ShapeViolation> = report.results.iter()
+                    .filter(|r| matches!(r.severity, ViolationLevel::Violation))
+                    .map(|r| ShapeViolation {
+                        focus_node: r.focus_node.as_ref().map(|i| i.0.clone()),
+                        path: r.result_path.as_ref().map(|i| i.0.clone()),
+                        constraint: r.source_constraint_component.0.clone(),
+                        message: r.message.clone(),
+                    })
+                    .collect();
+                let status = if violations.is_empty() { ConformanceStatus::Conforms } else { ConformanceStatus::Violates };
+                (status, violations)
+            }
+            Err(e) => {
+                warn!("SHACL validation failed: {}", e);
+                (ConformanceStatus::Error, vec![ShapeViolation {
+                    focus_node: None,
+                    path: None,
+                    constraint: "validation-error".to_string(),
+                    message: Some(e.to_string()),
+                }])
+            }
+        };
+
+        StreamingEvent::ValidatedEvent {
+            event: Box::new(event),
+            conformance,
+            violations,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    fn should_quarantine(event: &StreamingEvent) -> bool {
+        matches!(
+            event,
+            StreamingEvent::ValidatedEvent { conformance, .. } if *conformance != ConformanceStatus::Conforms
+        )
+    }
+}
+
+#[async_trait]
+impl<P: StreamProcessor, Q: StreamProducer> StreamProcessor for ShaclValidationStage<P, Q> {
+    async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        let tagged = self.validate(event);
+        if Self::should_quarantine(&tagged) {
+            self.quarantined.fetch_add(1, Ordering::Relaxed);
+            self.quarantine.produce(tagged).await
+        } else {
+            if matches!(tagged, StreamingEvent::ValidatedEvent { .. }) {
+                self.conforming.fetch_add(1, Ordering::Relaxed);
+            }
+            self.downstream.process_event(tagged).await
+        }
+    }
+
+    async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        let (rejected, accepted): (Vec<_>, Vec<_>) = events.into_iter()
+            .map(|event| self.validate(event))
+            .partition(Self::should_quarantine);
+
+        let conforming = accepted.iter().filter(|e| matches!(e, StreamingEvent::ValidatedEvent { .. })).count();
+        self.conforming.fetch_add(conforming as u64, Ordering::Relaxed);
+        self.quarantined.fetch_add(rejected.len() as u64, Ordering::Relaxed);
+
+        if !rejected.is_empty() {
+            self.quarantine.produce_batch(rejected).await?;
+        }
+        if !accepted.is_empty() {
+            self.downstream.process_batch(accepted).await?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "shacl_validation_stage"
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        self.downstream.health_check().await?;
+        self.quarantine.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fukurow_shacl::loader::{NodeShape, PropertyConstraint, PropertyPath, PropertyShape, Shape, Target};
+    use fukurow_sparql::parser::Iri;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Collector {
+        events: Mutex<Vec<StreamingEvent>>,
+    }
+
+    #[async_trait]
+    impl StreamProcessor for Arc<Collector> {
+        async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            self.events.lock().unwrap().extend(events);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "collector"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl StreamProducer for Arc<Collector> {
+        async fn produce(&self, event: StreamingEvent) -> Result<(), StreamError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+
+        async fn produce_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+            self.events.lock().unwrap().extend(events);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "quarantine"
+        }
+
+        async fn health_check(&self) -> Result<(), StreamError> {
+            Ok(())
+        }
+    }
+
+    fn shapes_requiring(property: &str) -> ShapesGraph {
+        let node_id = Iri("http://example.org/shapes/NetworkConnectionShape".to_string());
+        let prop_id = Iri("http://example.org/shapes/RequiredProperty".to_string());
+        let mut shapes = HashMap::new();
+        shapes.insert(node_id.clone(), Shape::Node(NodeShape {
+            id: node_id,
+            targets: vec![Target::Class(Iri("https://w3id.org/security#NetworkConnection".to_string()))],
+            constraints: vec![],
+            property_shapes: vec![prop_id.clone()],
+        }));
+        shapes.insert(prop_id.clone(), Shape::Property(PropertyShape {
+            id: prop_id,
+            path: PropertyPath::Predicate(Iri(property.to_string())),
+            constraints: vec![PropertyConstraint::MinCount(1)],
+        }));
+        ShapesGraph { shapes, prefixes: HashMap::new() }
+    }
+
+    fn security_event() -> StreamingEvent {
+        StreamingEvent::SecurityEvent {
+            event: CyberEvent::NetworkConnection {
+                source_ip: "192.168.1.1".to_string(),
+                dest_ip: "10.0.0.1".to_string(),
+                port: 443,
+                protocol: "tcp".to_string(),
+                timestamp: 1640995200,
+            },
+            timestamp: chrono::Utc::now(),
+            source: "sensor1".to_string(),
+            correlation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_default_event_triples() {
+        let triples = match security_event() {
+            StreamingEvent::SecurityEvent { event, .. } => default_event_triples(&event),
+            _ => unreachable!(),
+        };
+        assert!(triples.iter().any(|t| t.predicate == RDF_TYPE && t.object == "https://w3id.org/security#NetworkConnection"));
+        assert!(triples.iter().any(|t| t.predicate == "https://w3id.org/security#port" && t.object == "443"));
+    }
+
+    #[tokio::test]
+    async fn test_conforming_event_forwarded() {
+        let downstream = Arc::new(Collector::default());
+        let quarantine = Arc::new(Collector::default());
+        let stage = ShaclValidationStage::new(
+            shapes_requiring("https://w3id.org/security#destIp"),
+            downstream.clone(),
+            quarantine.clone(),
+        );
+
+        stage.process_event(security_event()).await.unwrap();
+
+        let forwarded = downstream.events.lock().unwrap();
+        assert_eq!(forwarded.len(), 1);
+        assert!(matches!(&forwarded[0], StreamingEvent::ValidatedEvent { conformance: ConformanceStatus::Conforms, .. }));
+        assert!(quarantine.events.lock().unwrap().is_empty());
+        assert_eq!(stage.stats().conforming, 1);
+    }
+
+    #[tokio::test]
+    async fn test_violating_events_quarantined() {
+        let downstream = Arc::new(Collector::default());
+        let quarantine = Arc::new(Collector::default());
+        let stage = ShaclValidationStage::new(
+            shapes_requiring("https://w3id.org/security#hostname"),
+            downstream.clone(),
+            quarantine.clone(),
+        );
+
+        let metrics = StreamingEvent::SystemMetrics {
+            cpu_usage: 1.0,
+            memory_usage: 1.0,
+            active_connections: 1,
+            timestamp: chrono::Utc::now(),
+        };
+        stage.process_batch(vec![security_event(), metrics]).await.unwrap();
+
+        let quarantined = quarantine.events.lock().unwrap();
+        assert_eq!(quarantined.len(), 1);
+        match &quarantined[0] {
+            StreamingEvent::ValidatedEvent { conformance, violations, event, .. } => {
+                assert_eq!(*conformance, ConformanceStatus::Violates);
+                assert!(!violations.is_empty());
+                assert_eq!(event.event_type(), "security_event");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // 非セキュリティイベントはそのまま下流へ
+        assert_eq!(downstream.events.lock().unwrap().len(), 1);
+        let stats = stage.stats();
+        assert_eq!((stats.validated, stats.quarantined, stats.passed_through), (1, 1, 1));
+    }
+}
@@ -0,0 +1,547 @@
+//! # Windowed Stream Processing
+//!
+//! Tumbling and sliding time windows over `StreamingEvent`s.
+//! イベントを任意フィールド (例: source_ip) のキーごとにウィンドウへ割り当て、
+//! ウォーターマークがウィンドウ終端を越えた時点で集約結果を出力する
+
+use crate::processor::StreamProcessor;
+use crate::{StreamError, StreamingEvent};
+use async_trait::async_trait;
+use fukurow_core::model::CyberEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+
+/// Window shape (all durations in milliseconds)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WindowSpec {
+    /// Fixed, non-overlapping windows
+    Tumbling { size_ms: i64 },
+    /// Windows of `size_ms` starting every `slide_ms`; an event belongs to `size_ms / slide_ms` windows
+    Sliding { size_ms: i64, slide_ms: i64 },
+}
+
+impl WindowSpec {
+    pub fn tumbling(size: std::time::Duration) -> Self {
+        WindowSpec::Tumbling { size_ms: size.as_millis() as i64 }
+    }
+
+    pub fn sliding(size: std::time::Duration, slide: std::time::Duration) -> Self {
+        WindowSpec::Sliding { size_ms: size.as_millis() as i64, slide_ms: slide.as_millis() as i64 }
+    }
+
+    pub fn size_ms(&self) -> i64 {
+        match self {
+            WindowSpec::Tumbling { size_ms } | WindowSpec::Sliding { size_ms, .. } => *size_ms,
+        }
+    }
+
+    fn slide_ms(&self) -> i64 {
+        match self {
+            WindowSpec::Tumbling { size_ms } => *size_ms,
+            WindowSpec::Sliding { slide_ms, .. } => *slide_ms,
+        }
+    }
+
+    fn validate(&self) -> Result<(), StreamError> {
+        if self.size_ms() <= 0 || self.slide_ms() <= 0 {
+            return Err(StreamError::ConfigError("window size and slide must be positive".to_string()));
+        }
+        if self.slide_ms() > self.size_ms() {
+            return Err(StreamError::ConfigError("window slide must not exceed the window size".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Windows containing `time_ms`, oldest first
+    pub fn assign(&self, time_ms: i64) -> Vec<TimeWindow> {
+        let (size, slide) = (self.size_ms(), self.slide_ms());
+        let last_start = time_ms.div_euclid(slide) * slide;
+        let mut windows = Vec::new();
+        let mut start = last_start;
+        while start > time_ms - size {
+            windows.push(TimeWindow { start_ms: start, end_ms: start + size });
+            start -= slide;
+        }
+        windows.reverse();
+        windows
+    }
+}
+
+/// Half-open interval `[start_ms, end_ms)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Which timestamp places an event in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeSource {
+    /// `StreamingEvent::timestamp()` (time of arrival in the stream)
+    #[default]
+    Envelope,
+    /// The sensor-reported `CyberEvent` timestamp, falling back to the envelope
+    Event,
+}
+
+/// Per-window aggregation hook
+///
+/// 各ウィンドウはアキュムレータを1つ持ち、ウィンドウが閉じたときに `finish` が呼ばれる
+pub trait WindowAggregator: Send + Sync {
+    type Accumulator: Default + Send;
+    type Output: Send + 'static;
+
+    fn accumulate(&self, acc: &mut Self::Accumulator, event: &StreamingEvent);
+
+    fn finish(&self, acc: Self::Accumulator) -> Self::Output;
+}
+
+/// Number of events in the window
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountAggregator;
+
+impl WindowAggregator for CountAggregator {
+    type Accumulator = u64;
+    type Output = u64;
+
+    fn accumulate(&self, acc: &mut u64, _event: &StreamingEvent) {
+        *acc += 1;
+    }
+
+    fn finish(&self, acc: u64) -> u64 {
+        acc
+    }
+}
+
+/// Number of distinct values of a field in the window
+#[derive(Debug, Clone)]
+pub struct DistinctCountAggregator {
+    field: String,
+}
+
+impl DistinctCountAggregator {
+    pub fn new(field: &str) -> Self {
+        Self { field: field.to_string() }
+    }
+}
+
+impl WindowAggregator for DistinctCountAggregator {
+    type Accumulator = HashSet<String>;
+    type Output = usize;
+
+    fn accumulate(&self, acc: &mut HashSet<String>, event: &StreamingEvent) {
+        if let Some(value) = event_field(event, &self.field) {
+            acc.insert(value);
+        }
+    }
+
+    fn finish(&self, acc: HashSet<String>) -> usize {
+        acc.len()
+    }
+}
+
+/// Closed window with its aggregate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowResult<O> {
+    /// Values of the key fields, in the order they were configured
+    pub key: Vec<String>,
+    pub window: TimeWindow,
+    /// Events assigned to the window
+    pub count: u64,
+    pub output: O,
+}
+
+/// Windowing statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowStats {
+    pub accepted: u64,
+    /// Events rejected by the filter or missing a key field
+    pub skipped: u64,
+    /// Events arriving after all of their windows had closed
+    pub late: u64,
+    pub windows_emitted: u64,
+    pub open_windows: usize,
+}
+
+struct OpenWindow<A> {
+    count: u64,
+    acc: A,
+}
+
+type EventFilter = Box<dyn Fn(&StreamingEvent) -> bool + Send + Sync>;
+
+/// Keyed window state machine
+///
+/// The watermark trails the newest event time by `allowed_lateness_ms`; a window is
+/// emitted once the watermark reaches its end. Events older than the watermark are
+/// still added to windows that are open, and counted as late otherwise.
+pub struct WindowOperator<A: WindowAggregator> {
+    spec: WindowSpec,
+    key_fields: Vec<String>,
+    aggregator: A,
+    filter: Option<EventFilter>,
+    time_source: TimeSource,
+    allowed_lateness_ms: i64,
+    watermark_ms: Option<i64>,
+    open: BTreeMap<(TimeWindow, Vec<String>), OpenWindow<A::Accumulator>>,
+    stats: WindowStats,
+}
+
+impl<A: WindowAggregator> WindowOperator<A> {
+    pub fn new(spec: WindowSpec, key_fields: &[&str], aggregator: A) -> Result<Self, StreamError> {
+        spec.validate()?;
+        Ok(Self {
+            spec,
+            key_fields: key_fields.iter().map(|f| f.to_string()).collect(),
+            aggregator,
+            filter: None,
+            time_source: TimeSource::default(),
+            allowed_lateness_ms: 0,
+            watermark_ms: None,
+            open: BTreeMap::new(),
+            stats: WindowStats::default(),
+        })
+    }
+
+    /// Only window events matching the predicate
+    pub fn with_filter(mut self, filter: impl Fn(&StreamingEvent) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    pub fn with_time_source(mut self, time_source: TimeSource) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    /// How long windows stay open for out-of-order events
+    pub fn with_allowed_lateness_ms(mut self, allowed_lateness_ms: i64) -> Self {
+        self.allowed_lateness_ms = allowed_lateness_ms.max(0);
+        self
+    }
+
+    pub fn spec(&self) -> &WindowSpec {
+        &self.spec
+    }
+
+    pub fn stats(&self) -> WindowStats {
+        WindowStats { open_windows: self.open.len(), ..self.stats }
+    }
+
+    /// Add an event; returns windows closed by the advancing watermark
+    pub fn push(&mut self, event: &StreamingEvent) -> Vec<WindowResult<A::Output>> {
+        if self.filter.as_ref().map(|filter| !filter(event)).unwrap_or(false) {
+            self.stats.skipped += 1;
+            return Vec::new();
+        }
+        let key: Option<Vec<String>> = self.key_fields.iter().map(|field| event_field(event, field)).collect();
+        let key = match key {
+            Some(key) => key,
+            None => {
+                self.stats.skipped += 1;
+                return Vec::new();
+            }
+        };
+
+        let time_ms = event_time_ms(event, self.time_source);
+        let watermark = self.watermark_ms.unwrap_or(i64::MIN);
+        let windows: Vec<TimeWindow> = self.spec.assign(time_ms).into_iter()
+            .filter(|window| window.end_ms > watermark)
+            .collect();
+        if windows.is_empty() {
+            self.stats.late += 1;
+            return Vec::new();
+        }
+
+        self.stats.accepted += 1;
+        for window in windows {
+            let open = self.open.entry((window, key.clone()))
+                .or_insert_with(|| OpenWindow { count: 0, acc: A::Accumulator::default() });
+            open.count += 1;
+            self.aggregator.accumulate(&mut open.acc, event);
+        }
+
+        let candidate = time_ms.saturating_sub(self.allowed_lateness_ms);
+        if candidate > watermark {
+            self.advance_watermark(candidate)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Move the watermark forward (e.g. on an idle timer) and emit closed windows
+    pub fn advance_watermark(&mut self, watermark_ms: i64) -> Vec<WindowResult<A::Output>> {
+        if self.watermark_ms.map(|current| watermark_ms <= current).unwrap_or(false) {
+            return Vec::new();
+        }
+        self.watermark_ms = Some(watermark_ms);
+
+        // 全ウィンドウは同じ長さなので開始時刻順 = 終了時刻順。先頭から閉じる
+        let mut results = Vec::new();
+        while let Some(entry) = self.open.first_entry() {
+            if entry.key().0.end_ms > watermark_ms {
+                break;
+            }
+            let ((window, key), open) = entry.remove_entry();
+            results.push(self.emit(window, key, open));
+        }
+        results
+    }
+
+    /// Emit every open window regardless of the watermark (e.g. on shutdown)
+    pub fn flush(&mut self) -> Vec<WindowResult<A::Output>> {
+        let open = std::mem::take(&mut self.open);
+        open.into_iter().map(|((window, key), open)| self.emit(window, key, open)).collect()
+    }
+
+    fn emit(&mut self, window: TimeWindow, key: Vec<String>, open: OpenWindow<A::Accumulator>) -> WindowResult<A::Output> {
+        self.stats.windows_emitted += 1;
+        WindowResult { key, window, count: open.count, output: self.aggregator.finish(open.acc) }
+    }
+}
+
+type WindowHook<O> = Box<dyn Fn(WindowResult<O>) + Send + Sync>;
+
+/// `StreamProcessor` that feeds a window operator and hands closed windows to a hook
+///
+/// ```ignore
+/// // 5 分間に 10 回を超えるログイン失敗 (送信元 IP ごと)
+/// let operator = WindowOperator::new(WindowSpec::tumbling(Duration::from_secs(300)), &["source_ip"], CountAggregator)?
+///     .with_filter(is_failed_login);
+/// let processor = WindowedProcessor::new("failed_logins", operator, |result| {
+///     if result.output > 10 { alert(result) }
+/// });
+/// ```
+pub struct WindowedProcessor<A: WindowAggregator> {
+    name: &'static str,
+    operator: Mutex<WindowOperator<A>>,
+    on_window: WindowHook<A::Output>,
+}
+
+impl<A: WindowAggregator> WindowedProcessor<A> {
+    pub fn new(
+        name: &'static str,
+        operator: WindowOperator<A>,
+        on_window: impl Fn(WindowResult<A::Output>) + Send + Sync + 'static,
+    ) -> Self {
+        Self { name, operator: Mutex::new(operator), on_window: Box::new(on_window) }
+    }
+
+    pub fn stats(&self) -> WindowStats {
+        self.lock().map(|operator| operator.stats()).unwrap_or_default()
+    }
+
+    /// Emit all open windows through the hook
+    pub fn flush(&self) -> Result<(), StreamError> {
+        let results = self.lock()?.flush();
+        results.into_iter().for_each(|result| (self.on_window)(result));
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, WindowOperator<A>>, StreamError> {
+        self.operator.lock().map_err(|_| StreamError::ProcessorError("window state poisoned".to_string()))
+    }
+}
+
+#[async_trait]
+impl<A: WindowAggregator> StreamProcessor for WindowedProcessor<A> {
+    async fn process_event(&self, event: StreamingEvent) -> Result<(), StreamError> {
+        // フックはロック解放後に呼ぶ
+        let results = self.lock()?.push(&event);
+        results.into_iter().for_each(|result| (self.on_window)(result));
+        Ok(())
+    }
+
+    async fn process_batch(&self, events: Vec<StreamingEvent>) -> Result<(), StreamError> {
+        let results: Vec<_> = {
+            let mut operator = self.lock()?;
+            events.iter().flat_map(|event| operator.push(event)).collect()
+        };
+        results.into_iter().for_each(|result| (self.on_window)(result));
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn health_check(&self) -> Result<(), StreamError> {
+        self.lock().map(|_| ())
+    }
+}
+
+/// Field value used for keys and aggregates
+///
+/// For security events `source` and `event_type` refer to the envelope; any other
+/// name is looked up in the event data (`source_ip`, `user`, `port`, ...).
+/// Joined events expose their members as `left.<field>` and `right.<field>`.
+pub fn event_field(event: &StreamingEvent, field: &str) -> Option<String> {
+    let scalar = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    };
+    if let StreamingEvent::JoinedEvent { left, right, .. } = event {
+        match field.split_once('.') {
+            Some(("left", field)) => return event_field(left, field),
+            Some(("right", field)) => return event_field(right, field),
+            _ => {}
+        }
+    }
+
+    match event {
+        StreamingEvent::SecurityEvent { event, source, .. } => {
+            if field == "source" {
+                return Some(source.clone());
+            }
+            let value = serde_json::to_value(event).ok()?;
+            match field {
+                "event_type" => scalar(value.get("type")?),
+                _ => scalar(value.get("data")?.get(field)?),
+            }
+        }
+        StreamingEvent::ValidatedEvent { event, .. } => event_field(event, field),
+        other => {
+            if field == "event_type" {
+                return Some(other.event_type().to_string());
+            }
+            let value = serde_json::to_value(other).ok()?;
+            scalar(value.as_object()?.values().next()?.get(field)?)
+        }
+    }
+}
+
+pub(crate) fn event_time_ms(event: &StreamingEvent, source: TimeSource) -> i64 {
+    if let (TimeSource::Event, StreamingEvent::SecurityEvent { event, .. }) = (source, event) {
+        let seconds = match event {
+            CyberEvent::NetworkConnection { timestamp, .. }
+            | CyberEvent::ProcessExecution { timestamp, .. }
+            | CyberEvent::FileAccess { timestamp, .. }
+            | CyberEvent::UserLogin { timestamp, .. }
+            | CyberEvent::DnsQuery { timestamp, .. }
+            | CyberEvent::HttpRequest { timestamp, .. }
+            | CyberEvent::RegistryModification { timestamp, .. }
+            | CyberEvent::EmailReceived { timestamp, .. } => *timestamp,
+        };
+        return seconds.saturating_mul(1000);
+    }
+    match event {
+        StreamingEvent::ValidatedEvent { event, .. } => event_time_ms(event, source),
+        StreamingEvent::JoinedEvent { left, right, .. } => event_time_ms(left, source).max(event_time_ms(right, source)),
+        other => other.timestamp().timestamp_millis(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn login(user: &str, ip: &str, success: bool, at_secs: i64) -> StreamingEvent {
+        StreamingEvent::SecurityEvent {
+            event: CyberEvent::UserLogin { user: user.to_string(), source_ip: ip.to_string(), success, timestamp: at_secs },
+            timestamp: chrono::DateTime::from_timestamp(at_secs, 0).unwrap(),
+            source: "auth-gw".to_string(),
+            correlation_id: None,
+        }
+    }
+
+    fn is_failed_login(event: &StreamingEvent) -> bool {
+        event_field(event, "success").as_deref() == Some("false")
+    }
+
+    #[test]
+    fn test_window_assignment() {
+        let tumbling = WindowSpec::tumbling(Duration::from_secs(60));
+        assert_eq!(tumbling.assign(59_999), vec![TimeWindow { start_ms: 0, end_ms: 60_000 }]);
+        assert_eq!(tumbling.assign(60_000), vec![TimeWindow { start_ms: 60_000, end_ms: 120_000 }]);
+        assert_eq!(tumbling.assign(-1), vec![TimeWindow { start_ms: -60_000, end_ms: 0 }]);
+
+        let sliding = WindowSpec::sliding(Duration::from_secs(60), Duration::from_secs(20));
+        let starts: Vec<i64> = sliding.assign(65_000).iter().map(|w| w.start_ms).collect();
+        assert_eq!(starts, vec![20_000, 40_000, 60_000]);
+
+        assert!(WindowSpec::Sliding { size_ms: 10, slide_ms: 20 }.validate().is_err());
+        assert!(WindowSpec::Tumbling { size_ms: 0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_failed_logins_per_source_ip() {
+        let mut operator = WindowOperator::new(WindowSpec::tumbling(Duration::from_secs(300)), &["source_ip"], CountAggregator)
+            .unwrap()
+            .with_filter(is_failed_login);
+
+        let mut results = Vec::new();
+        for i in 0..12 {
+            results.extend(operator.push(&login("alice", "203.0.113.9", false, 10 + i * 20)));
+        }
+        results.extend(operator.push(&login("alice", "203.0.113.9", true, 250)));
+        results.extend(operator.push(&login("bob", "10.0.0.2", false, 100)));
+        assert!(results.is_empty());
+
+        // 次のウィンドウのイベントでウォーターマークが進み、最初のウィンドウが閉じる
+        results.extend(operator.push(&login("carol", "10.0.0.3", false, 301)));
+        assert_eq!(results.len(), 2);
+        let alert: Vec<_> = results.iter().filter(|r| r.output > 10).collect();
+        assert_eq!(alert.len(), 1);
+        assert_eq!(alert[0].key, vec!["203.0.113.9".to_string()]);
+        assert_eq!(alert[0].window, TimeWindow { start_ms: 0, end_ms: 300_000 });
+
+        let stats = operator.stats();
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.open_windows, 1);
+        assert_eq!(operator.flush().len(), 1);
+    }
+
+    #[test]
+    fn test_sliding_windows_and_late_events() {
+        let spec = WindowSpec::sliding(Duration::from_secs(10), Duration::from_secs(5));
+        let mut operator = WindowOperator::new(spec, &["user"], DistinctCountAggregator::new("source_ip"))
+            .unwrap()
+            .with_time_source(TimeSource::Event)
+            .with_allowed_lateness_ms(2_000);
+
+        assert!(operator.push(&login("alice", "10.0.0.1", true, 1)).is_empty());
+        // ウォーターマーク 5s で [-5s, 5s) が閉じる
+        let closed = operator.push(&login("alice", "10.0.0.2", true, 7));
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].window.start_ms, closed[0].output), (-5_000, 1));
+        assert!(operator.push(&login("alice", "10.0.0.3", true, 11)).is_empty());
+        // 遅延許容内: [0s, 10s) はまだ開いている
+        assert!(operator.push(&login("alice", "10.0.0.4", true, 9)).is_empty());
+
+        let closed = operator.push(&login("alice", "10.0.0.1", true, 20));
+        let by_start: Vec<(i64, usize)> = closed.iter().map(|r| (r.window.start_ms, r.output)).collect();
+        assert_eq!(by_start, vec![(0, 3), (5_000, 3)]);
+
+        // 全ウィンドウが閉じた後の到着は遅延として捨てる
+        assert!(operator.push(&login("alice", "10.0.0.9", true, 3)).is_empty());
+        assert_eq!(operator.stats().late, 1);
+    }
+
+    #[tokio::test]
+    async fn test_windowed_processor_hook() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&fired);
+        let operator = WindowOperator::new(WindowSpec::tumbling(Duration::from_secs(60)), &["user"], CountAggregator).unwrap();
+        let processor = WindowedProcessor::new("login_windows", operator, move |result| {
+            sink.lock().unwrap().push((result.key[0].clone(), result.output));
+        });
+
+        processor.process_batch(vec![login("alice", "10.0.0.1", true, 0), login("bob", "10.0.0.2", true, 30)]).await.unwrap();
+        processor.process_event(login("alice", "10.0.0.1", true, 61)).await.unwrap();
+        let mut seen = fired.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen, vec![("alice".to_string(), 1), ("bob".to_string(), 1)]);
+
+        processor.flush().unwrap();
+        assert_eq!(fired.lock().unwrap().len(), 3);
+        assert_eq!(processor.stats().windows_emitted, 3);
+        assert!(processor.health_check().await.is_ok());
+    }
+}
@@ -1,6 +1,7 @@
 //! Headless WebAssembly bindings for Fukurow reasoning engine
 //!
 //! DOM/Node 依存を一切持たない、純計算 API を提供します。
+//! ストアを保持して差分追加・繰り返しクエリを行うセッション API は [`session`] を参照。
 
 use wasm_bindgen::prelude::*;
 use serde::Deserialize;
@@ -12,6 +13,9 @@ use fukurow_shacl::{ShaclLoader, ValidationReport};
 use fukurow_shacl::loader::DefaultShaclLoader;
 use fukurow_shacl::validator::{ShaclValidator, DefaultShaclValidator, ValidationConfig};
 
+pub mod session;
+pub use session::*;
+
 #[derive(Debug, Deserialize)]
 struct ReasonOptions {
     // "lite" | "dl"
@@ -25,11 +29,18 @@ fn default_engine() -> String { "lite".to_string() }
 
 // Simplified JSON-LD processing for WASM
 fn jsonld_to_store(jsonld_str: &str) -> Result<RdfStore, JsValue> {
+    let mut store = RdfStore::new();
+    add_jsonld_to_store(&mut store, jsonld_str)?;
+    Ok(store)
+}
+
+/// Insert the triples of a JSON-LD document into `store`, returning how many were inserted
+fn add_jsonld_to_store(store: &mut RdfStore, jsonld_str: &str) -> Result<usize, JsValue> {
     // For simplicity, parse basic JSON-LD format manually
     let json: serde_json::Value = serde_json::from_str(jsonld_str)
         .map_err(|e| JsValue::from_str(&format!("JSON parse error: {}", e)))?;
 
-    let mut store = RdfStore::new();
+    let mut inserted = 0;
     let graph_id = GraphId::Default;
     let provenance = Provenance::Sensor {
         source: "wasm-input".to_string(),
@@ -54,6 +65,7 @@ fn jsonld_to_store(jsonld_str: &str) -> Result<RdfStore, JsValue> {
                                         object: obj_str.to_string(),
                                     };
                                     store.insert(triple, graph_id.clone(), provenance.clone());
+                                    inserted += 1;
                                 }
                             }
                         }
@@ -63,7 +75,7 @@ fn jsonld_to_store(jsonld_str: &str) -> Result<RdfStore, JsValue> {
         }
     }
 
-    Ok(store)
+    Ok(inserted)
 }
 
 fn store_to_jsonld(store: &RdfStore) -> Result<String, JsValue> {
@@ -74,10 +86,10 @@ fn store_to_jsonld(store: &RdfStore) -> Result<String, JsValue> {
         let triple = &stored_triple.triple;
 
         let mut node = serde_json::Map::new();
-        node.insert("@id".to_string(), serde_json::Value::String(triple.subject.clone()));
+        node.insert("@id".to_string(), serde_json::Value::String(triple.subject.to_string()));
 
         let mut properties = serde_json::Map::new();
-        properties.insert(triple.predicate.clone(), serde_json::Value::String(triple.object.clone()));
+        properties.insert(triple.predicate.to_string(), serde_json::Value::String(triple.object.to_string()));
         node.insert("properties".to_string(), serde_json::Value::Object(properties));
 
         graph.push(serde_json::Value::Object(node));
@@ -92,52 +104,29 @@ fn store_to_jsonld(store: &RdfStore) -> Result<String, JsValue> {
         .map_err(|e| JsValue::from_str(&format!("JSON serialize error: {}", e)))
 }
 
-#[wasm_bindgen]
-pub fn reason_owl(input_jsonld: &str, options_json: &str) -> Result<String, JsValue> {
-    let opts: ReasonOptions = serde_json::from_str(options_json).unwrap_or(ReasonOptions {
-        engine: default_engine(),
-        params: serde_json::json!({}),
-    });
-
-    // Parse JSON-LD to RdfStore
-    let store = jsonld_to_store(input_jsonld)?;
+/// Named graph receiving OWL inferences
+const INFERRED_GRAPH: &str = "owl-reasoning";
 
+/// Triples entailed by the ontology in `store` (subclass hierarchy and property characteristics)
+fn owl_inferences(store: &RdfStore) -> Result<Vec<Triple>, JsValue> {
     // Load ontology from store
     let loader = DefaultOntologyLoader;
-    let ontology = loader.load_from_store(&store)
+    let ontology = loader.load_from_store(store)
         .map_err(|e| JsValue::from_str(&format!("Ontology loading error: {:?}", e)))?;
 
     // Create reasoner and perform inference
     let mut reasoner = OwlLiteReasoner::new();
 
     // Compute class hierarchy (main inference)
-    let hierarchy = reasoner.compute_class_hierarchy(&ontology)
+    reasoner.compute_class_hierarchy(&ontology)
         .map_err(|e| JsValue::from_str(&format!("Reasoning error: {:?}", e)))?;
 
     // Get inferred axioms from hierarchy
     let inferred = reasoner.get_inferred_axioms(&ontology)
         .map_err(|e| JsValue::from_str(&format!("Inference error: {:?}", e)))?;
 
-    // Create result store with original data + inferred axioms
-    let mut result_store = RdfStore::new();
-    // Copy original triples to result store
-    for (graph_id, triples) in store.all_triples() {
-        for stored_triple in triples {
-            result_store.insert(
-                stored_triple.triple.clone(),
-                graph_id.clone(),
-                stored_triple.provenance.clone(),
-            );
-        }
-    }
-
-    let inferred_graph_id = GraphId::Inferred("owl-reasoning".to_string());
-    let inferred_provenance = Provenance::Sensor {
-        source: "fukurow-lite".to_string(),
-        confidence: Some(1.0),
-    };
-
-    // Convert inferred axioms back to triples and add to result store
+    // Convert inferred axioms back to triples
+    let mut triples = Vec::new();
     for axiom in inferred {
         match axiom {
             fukurow_lite::model::Axiom::SubClassOf(subclass, superclass) => {
@@ -150,17 +139,67 @@ pub fn reason_owl(input_jsonld: &str, options_json: &str) -> Result<String, JsVa
                     _ => continue,
                 };
 
-                let triple = Triple {
+                triples.push(Triple {
                     subject,
                     predicate: "http://www.w3.org/2000/01/rdf-schema#subClassOf".to_string(),
                     object,
+                });
+            }
+            // Transitive / symmetric / inverse property entailments
+            fukurow_lite::model::Axiom::ObjectPropertyAssertion(property, subject, object) => {
+                let predicate = match property {
+                    fukurow_lite::model::Property::Object(iri) | fukurow_lite::model::Property::Data(iri) => iri.0,
                 };
-                result_store.insert(triple, inferred_graph_id.clone(), inferred_provenance.clone());
+                triples.push(Triple { subject: subject.0.0, predicate, object: object.0.0 });
             }
             // Add other axiom types as needed
             _ => {} // Skip other axiom types for now
         }
     }
+    Ok(triples)
+}
+
+/// Insert inferred triples into the `owl-reasoning` graph
+fn insert_inferences(store: &mut RdfStore, inferred: Vec<Triple>) {
+    let inferred_graph_id = GraphId::Inferred(INFERRED_GRAPH.to_string());
+    let inferred_provenance = Provenance::Sensor {
+        source: "fukurow-lite".to_string(),
+        confidence: Some(1.0),
+    };
+    for triple in inferred {
+        store.insert(triple, inferred_graph_id.clone(), inferred_provenance.clone());
+    }
+}
+
+#[wasm_bindgen]
+pub fn reason_owl(input_jsonld: &str, options_json: &str) -> Result<String, JsValue> {
+    let opts: ReasonOptions = serde_json::from_str(options_json).unwrap_or(ReasonOptions {
+        engine: default_engine(),
+        params: serde_json::json!({}),
+    });
+    // 推論は OWL Lite のクラス階層のみ (セッションの reason_store と同じ)
+    if opts.engine != "lite" {
+        return Err(JsValue::from_str(&format!("Unsupported reasoning engine: {}", opts.engine)));
+    }
+
+    // Parse JSON-LD to RdfStore
+    let store = jsonld_to_store(input_jsonld)?;
+
+    let inferred = owl_inferences(&store)?;
+
+    // Create result store with original data + inferred axioms
+    let mut result_store = RdfStore::new();
+    // Copy original triples to result store
+    for (graph_id, triples) in store.all_triples() {
+        for stored_triple in triples {
+            result_store.insert(
+                stored_triple.triple.clone(),
+                graph_id.clone(),
+                stored_triple.provenance.clone(),
+            );
+        }
+    }
+    insert_inferences(&mut result_store, inferred);
 
     // Serialize result back to JSON-LD
     store_to_jsonld(&result_store)
@@ -220,8 +259,13 @@ pub fn query_sparql(data_jsonld: &str, sparql: &str) -> Result<String, JsValue>
     let result = fukurow_sparql::execute_query(sparql, &store)
         .map_err(|e| JsValue::from_str(&format!("SPARQL execution error: {:?}", e)))?;
 
-    // Convert result to JSON
-    let json_result = match result {
+    serde_json::to_string(&sparql_result_to_json(result))
+        .map_err(|e| JsValue::from_str(&format!("JSON serialization error: {}", e)))
+}
+
+/// SPARQL 1.1 JSON results (SELECT / ASK) or a simplified JSON-LD graph (CONSTRUCT / DESCRIBE)
+fn sparql_result_to_json(result: SparqlResult) -> serde_json::Value {
+    match result {
         SparqlResult::Select { variables, bindings } => {
             serde_json::json!({
                 "head": {
@@ -239,10 +283,16 @@ pub fn query_sparql(data_jsonld: &str, sparql: &str) -> Result<String, JsValue>
                             })
                         },
                         fukurow_sparql::parser::Term::Literal(lit) => {
-                            serde_json::json!({
+                            let mut literal = serde_json::json!({
                                 "type": "literal",
                                 "value": lit.value.trim_matches('"')
-                            })
+                            });
+                            if let Some(lang) = &lit.language {
+                                literal["xml:lang"] = serde_json::json!(lang);
+                            } else if let Some(datatype) = &lit.datatype {
+                                literal["datatype"] = serde_json::json!(datatype.0);
+                            }
+                            literal
                         },
                         fukurow_sparql::parser::Term::Variable(var) => {
                             serde_json::json!({
@@ -306,8 +356,5 @@ pub fn query_sparql(data_jsonld: &str, sparql: &str) -> Result<String, JsValue>
                 "@graph": graph
             })
         },
-    };
-
-    serde_json::to_string(&json_result)
-        .map_err(|e| JsValue::from_str(&format!("JSON serialization error: {}", e)))
+    }
 }
@@ -0,0 +1,131 @@
+//! Persistent stores addressed by handle
+//!
+//! ステートレス API は呼び出しごとに JSON-LD 全体を読み直すため、ブラウザ側でストアを
+//! 少しずつ組み立てて繰り返しクエリする用途ではセッション API を使う。
+//! ハンドルは `free` を呼ぶまで有効 (WASM はシングルスレッドなのでスレッドローカルに保持する)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+use fukurow_dl::{ClassExpression, OwlDlReasoner};
+use fukurow_lite::{GraphId, Individual, RdfStore};
+use fukurow_lite::model::OwlIri;
+
+#[derive(Default)]
+struct Sessions {
+    stores: HashMap<u32, RdfStore>,
+    /// OWL DL reasoners caching the realization of each store
+    reasoners: HashMap<u32, OwlDlReasoner>,
+    next_handle: u32,
+}
+
+thread_local! {
+    static SESSIONS: RefCell<Sessions> = RefCell::new(Sessions::default());
+}
+
+fn with_store<T>(handle: u32, f: impl FnOnce(&mut RdfStore) -> Result<T, JsValue>) -> Result<T, JsValue> {
+    SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        let store = sessions.stores.get_mut(&handle).ok_or_else(|| unknown_handle(handle))?;
+        f(store)
+    })
+}
+
+fn unknown_handle(handle: u32) -> JsValue {
+    JsValue::from_str(&format!("Unknown store handle: {}", handle))
+}
+
+/// Create an empty store and return its handle
+#[wasm_bindgen]
+pub fn create_store() -> u32 {
+    SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        // 0 は JS 側で未初期化と区別しやすいよう使わない
+        sessions.next_handle = sessions.next_handle.wrapping_add(1).max(1);
+        while sessions.stores.contains_key(&sessions.next_handle) {
+            sessions.next_handle = sessions.next_handle.wrapping_add(1).max(1);
+        }
+        let handle = sessions.next_handle;
+        sessions.stores.insert(handle, RdfStore::new());
+        handle
+    })
+}
+
+/// Add the triples of a JSON-LD document to a store; returns the number of triples read
+#[wasm_bindgen]
+pub fn add_jsonld(handle: u32, data: &str) -> Result<u32, JsValue> {
+    with_store(handle, |store| {
+        let inserted = crate::add_jsonld_to_store(store, data)?;
+        Ok(inserted as u32)
+    })
+}
+
+/// Recompute OWL inferences of a store into its `owl-reasoning` graph
+///
+/// 前回の推論結果を捨ててから計算し直す。戻り値は `{"inferred": n, "triples": m}` の JSON
+#[wasm_bindgen(js_name = reason)]
+pub fn reason_store(handle: u32) -> Result<String, JsValue> {
+    with_store(handle, |store| {
+        store.clear_graph(&GraphId::Inferred(crate::INFERRED_GRAPH.to_string()));
+        let inferred = crate::owl_inferences(store)?;
+        let count = inferred.len();
+        crate::insert_inferences(store, inferred);
+
+        let summary = serde_json::json!({
+            "inferred": count,
+            "triples": store.statistics().total_triples,
+        });
+        Ok(summary.to_string())
+    })
+}
+
+/// Run a SPARQL query against a store (asserted and inferred triples)
+#[wasm_bindgen(js_name = query)]
+pub fn query_store(handle: u32, sparql: &str) -> Result<String, JsValue> {
+    with_store(handle, |store| {
+        let result = fukurow_sparql::execute_query(sparql, store)
+            .map_err(|e| JsValue::from_str(&format!("SPARQL execution error: {:?}", e)))?;
+        serde_json::to_string(&crate::sparql_result_to_json(result))
+            .map_err(|e| JsValue::from_str(&format!("JSON serialization error: {}", e)))
+    })
+}
+
+/// Most specific classes of an individual by OWL DL realization of a store
+///
+/// 実現結果はハンドルごとにキャッシュし、ストアのオントロジーが変わるまで計算し直さない。
+/// 戻り値は JSON 配列で、名前付きクラスは IRI、制約クラスは `ClassExpression` の JSON 表現
+#[wasm_bindgen(js_name = getTypes)]
+pub fn get_types(handle: u32, individual: &str) -> Result<String, JsValue> {
+    SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        let Sessions { stores, reasoners, .. } = &mut *sessions;
+        let store = stores.get(&handle).ok_or_else(|| unknown_handle(handle))?;
+        let reasoner = reasoners.entry(handle).or_insert_with(OwlDlReasoner::new);
+
+        let ontology = reasoner.load_ontology(store)
+            .map_err(|e| JsValue::from_str(&format!("Ontology loading error: {}", e)))?;
+        let types = reasoner.get_types(&ontology, &Individual(OwlIri::new(individual.to_string())))
+            .map_err(|e| JsValue::from_str(&format!("Realization error: {}", e)))?;
+
+        let mut types: Vec<serde_json::Value> = types.into_iter()
+            .map(|class| match class {
+                ClassExpression::Named(iri) => serde_json::Value::String(iri.0),
+                ClassExpression::Thing => serde_json::Value::from("http://www.w3.org/2002/07/owl#Thing"),
+                restriction => serde_json::to_value(restriction).unwrap_or(serde_json::Value::Null),
+            })
+            .collect();
+        types.sort_by_key(|class| class.to_string());
+        serde_json::to_string(&types)
+            .map_err(|e| JsValue::from_str(&format!("JSON serialization error: {}", e)))
+    })
+}
+
+/// Release a store; returns `false` if the handle was unknown
+#[wasm_bindgen(js_name = free)]
+pub fn free_store(handle: u32) -> bool {
+    SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        sessions.reasoners.remove(&handle);
+        sessions.stores.remove(&handle).is_some()
+    })
+}
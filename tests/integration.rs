@@ -256,9 +256,9 @@ async fn test_knowledge_graph_query_integration() {
 
     assert!(person_triples.len() >= 2, "Should find at least 2 person instances");
     // Verify the results contain expected subjects
-    let subjects: Vec<_> = person_triples.iter().map(|t| &t.triple.subject).collect();
-    assert!(subjects.contains(&&"http://example.org/alice".to_string()));
-    assert!(subjects.contains(&&"http://example.org/bob".to_string()));
+    let subjects: Vec<_> = person_triples.iter().map(|t| t.triple.subject.as_str()).collect();
+    assert!(subjects.contains(&"http://example.org/alice"));
+    assert!(subjects.contains(&"http://example.org/bob"));
 }
 
 #[tokio::test]